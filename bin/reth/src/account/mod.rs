@@ -0,0 +1,237 @@
+//! Accounts of a geth-compatible JSON keystore.
+//!
+//! Every account is a file of the keystore directory that holds its key encrypted with a password,
+//! so the keystores of geth and reth can be used interchangeably. The node signs with the keys of
+//! the accounts unlocked with `--unlock`.
+use crate::{dirs::data_dir, util::parse_path};
+use clap::{Args, Parser, Subcommand};
+use eyre::WrapErr;
+use reth_primitives::Address;
+use reth_rpc::key_address;
+use secp256k1::SecretKey;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+/// `reth account` command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    keystore: KeystoreArgs,
+
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+/// `reth account` subcommands
+#[derive(Subcommand, Debug)]
+pub enum Subcommands {
+    /// Creates an account with a new random key
+    New,
+    /// Imports the hex encoded private key of the file as a new account
+    Import {
+        /// The file with the private key
+        path: PathBuf,
+    },
+    /// Lists the addresses of the accounts
+    List,
+}
+
+/// The location of the keystore and the password of its accounts.
+#[derive(Debug, Clone, Default, Args)]
+pub struct KeystoreArgs {
+    /// The directory of the keystore.
+    ///
+    /// Defaults to `keystore` in the OS-specific data directory.
+    #[arg(long, value_name = "PATH", value_parser = parse_path)]
+    pub keystore: Option<PathBuf>,
+
+    /// The file with the password of the accounts.
+    ///
+    /// The first line of the file is the password.
+    #[arg(long, value_name = "FILE")]
+    pub password: Option<PathBuf>,
+}
+
+// === impl KeystoreArgs ===
+
+impl KeystoreArgs {
+    /// Returns the keystore at the configured or default directory.
+    pub fn keystore(&self) -> eyre::Result<Keystore> {
+        let dir = match &self.keystore {
+            Some(dir) => dir.clone(),
+            None => data_dir()
+                .map(|dir| dir.join("keystore"))
+                .ok_or_else(|| eyre::eyre!("Could not determine the keystore path. Set one."))?,
+        };
+        Ok(Keystore::new(dir))
+    }
+
+    /// Reads the password from the password file, fails if no file is set.
+    pub fn password(&self) -> eyre::Result<String> {
+        let path = self.password.as_ref().ok_or_else(|| eyre::eyre!("No password file set"))?;
+        let content = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+        Ok(content.lines().next().unwrap_or_default().to_string())
+    }
+}
+
+impl Command {
+    /// Execute `account` command
+    pub async fn execute(&self) -> eyre::Result<()> {
+        let keystore = self.keystore.keystore()?;
+        match &self.command {
+            Subcommands::New => {
+                let key = SecretKey::new(&mut rand::thread_rng());
+                let address = keystore.import(&key, &self.keystore.password()?)?;
+                info!("Created account {address:?}");
+            }
+            Subcommands::Import { path } => {
+                let content = std::fs::read_to_string(path)
+                    .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+                let content = content.trim();
+                let key = SecretKey::from_str(content.strip_prefix("0x").unwrap_or(content))
+                    .wrap_err("Invalid private key")?;
+                let address = keystore.import(&key, &self.keystore.password()?)?;
+                info!("Imported account {address:?}");
+            }
+            Subcommands::List => {
+                for (address, path) in keystore.accounts()? {
+                    println!("{address:?} {}", path.display());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A directory of geth-compatible JSON key files.
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+// === impl Keystore ===
+
+impl Keystore {
+    /// Creates the keystore of the directory, which is created once an account is added.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the directory of the keystore.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Encrypts the key with the password into a new key file and returns the address of its
+    /// account.
+    ///
+    /// Like geth, the directory is only accessible by its owner on unix, and so is the key file.
+    pub fn import(&self, key: &SecretKey, password: &str) -> eyre::Result<Address> {
+        let address = key_address(key);
+        if self.path_of(address)?.is_some() {
+            eyre::bail!("Account {address:?} already exists")
+        }
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(&self.dir)?;
+
+        // the naming scheme of geth, with the time in seconds
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let name = format!("UTC--{now}--{}", hex::encode(address));
+        eth_keystore::encrypt_key(
+            &self.dir,
+            &mut rand::thread_rng(),
+            key.secret_bytes(),
+            password,
+            Some(&name),
+        )?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = std::fs::Permissions::from_mode(0o600);
+            std::fs::set_permissions(self.dir.join(&name), permissions)?;
+        }
+        Ok(address)
+    }
+
+    /// Returns the addresses and key files of the accounts, ordered by address.
+    ///
+    /// Files that are not key files are skipped.
+    pub fn accounts(&self) -> eyre::Result<Vec<(Address, PathBuf)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new())
+        }
+        let mut accounts = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if let Some(address) = key_file_address(&path) {
+                accounts.push((address, path));
+            }
+        }
+        accounts.sort();
+        Ok(accounts)
+    }
+
+    /// Decrypts the key of the account with the password.
+    pub fn unlock(&self, address: Address, password: &str) -> eyre::Result<SecretKey> {
+        let path = self
+            .path_of(address)?
+            .ok_or_else(|| eyre::eyre!("Account {address:?} not found in the keystore"))?;
+        let key = eth_keystore::decrypt_key(&path, password)
+            .wrap_err_with(|| format!("Could not unlock account {address:?}"))?;
+        Ok(SecretKey::from_slice(&key)?)
+    }
+
+    fn path_of(&self, address: Address) -> eyre::Result<Option<PathBuf>> {
+        let accounts = self.accounts()?;
+        Ok(accounts.into_iter().find(|(account, _)| *account == address).map(|(_, path)| path))
+    }
+}
+
+/// Returns the address of the key file, `None` if the file is not a key file.
+fn key_file_address(path: &Path) -> Option<Address> {
+    let content = std::fs::read(path).ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&content).ok()?;
+    let address = json.get("address")?.as_str()?;
+    Address::from_str(address.strip_prefix("0x").unwrap_or(address)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_and_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Keystore::new(dir.path().join("keystore"));
+        assert!(keystore.accounts().unwrap().is_empty());
+
+        let key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let address = keystore.import(&key, "secret").unwrap();
+        assert_eq!(address, key_address(&key));
+        assert!(keystore.import(&key, "secret").is_err());
+
+        let accounts = keystore.accounts().unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].0, address);
+
+        assert_eq!(keystore.unlock(address, "secret").unwrap(), key);
+        assert!(keystore.unlock(address, "wrong").is_err());
+        assert!(keystore.unlock(Address::zero(), "secret").is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode(keystore.dir()) & 0o777, 0o700);
+            assert_eq!(mode(&accounts[0].1) & 0o777, 0o600);
+        }
+    }
+}
@@ -1,27 +1,30 @@
 //! CLI definition and entrypoint to executable
 
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{Parser, Subcommand};
 use tracing_subscriber::util::SubscriberInitExt;
 
 use crate::{
-    db, node, test_eth_chain,
-    util::reth_tracing::{self, TracingMode},
+    account, config, db, export, import, node, p2p, test_engine, test_eth_chain, txpool,
+    util::reth_tracing::{self, LogArgs},
 };
 
 /// main function that parses cli and runs command
 pub async fn run() -> eyre::Result<()> {
     let opt = Cli::parse();
-    reth_tracing::build_subscriber(if opt.silent {
-        TracingMode::Silent
-    } else {
-        TracingMode::from(opt.verbose)
-    })
-    .init();
+    let (subscriber, filter_handle, _guard) = reth_tracing::build_subscriber(&opt.logs)?;
+    subscriber.init();
 
     match opt.command {
-        Commands::Node(command) => command.execute().await,
+        Commands::Node(command) => command.execute(&opt.logs, filter_handle).await,
         Commands::TestEthChain(command) => command.execute().await,
+        Commands::TestEngine(command) => command.execute().await,
         Commands::Db(command) => command.execute().await,
+        Commands::TxPool(command) => command.execute().await,
+        Commands::Config(command) => command.execute().await,
+        Commands::P2P(command) => command.execute().await,
+        Commands::Import(command) => command.execute().await,
+        Commands::Export(command) => command.execute().await,
+        Commands::Account(command) => command.execute().await,
     }
 }
 
@@ -34,9 +37,30 @@ pub enum Commands {
     /// Runs Ethereum blockchain tests
     #[command(name = "test-chain")]
     TestEthChain(test_eth_chain::Command),
+    /// Replays recorded Engine API calls against the consensus engine
+    #[command(name = "test-engine")]
+    TestEngine(test_engine::Command),
     /// DB Debugging utilities
     #[command(name = "db")]
     Db(db::Command),
+    /// Export and import the transactions of a running node's transaction pool
+    #[command(name = "txpool")]
+    TxPool(txpool::Command),
+    /// Print the effective configuration of the config file and flags
+    #[command(name = "config")]
+    Config(config::Command),
+    /// Fetch a header or body from a single peer to debug connectivity
+    #[command(name = "p2p")]
+    P2P(p2p::Command),
+    /// Import blocks from a file of RLP encoded blocks
+    #[command(name = "import")]
+    Import(import::Command),
+    /// Export canonical blocks to a file of RLP encoded blocks
+    #[command(name = "export")]
+    Export(export::Command),
+    /// Create, import and list the accounts of the keystore
+    #[command(name = "account")]
+    Account(account::Command),
 }
 
 #[derive(Parser)]
@@ -46,11 +70,6 @@ struct Cli {
     #[clap(subcommand)]
     command: Commands,
 
-    /// Use verbose output
-    #[clap(short, long, action = ArgAction::Count, global = true)]
-    verbose: u8,
-
-    /// Silence all output
-    #[clap(long, global = true)]
-    silent: bool,
+    #[clap(flatten)]
+    logs: LogArgs,
 }
@@ -1,5 +1,19 @@
 //! Configuration files.
+//!
+//! The node reads its settings from a TOML file (`reth.toml` in the OS-specific config directory
+//! by default). Every setting that can also be passed as a CLI flag takes the value of the flag if
+//! it is set, see [`ConfigArgs`].
+use crate::dirs::ConfigPath;
+use clap::{Args, Parser, ValueEnum};
+use eyre::WrapErr;
+use reth_db::mdbx::{EnvConfig, SyncMode};
+use reth_executor::threaded_revm_wrap::{
+    StateReaderConfig, DEFAULT_STATE_READERS, DEFAULT_STATE_READ_QUEUE_SIZE,
+};
+use reth_primitives::Address;
+use reth_stages::stages::{execution::DEFAULT_REORG_BUFFER_BLOCKS, prune::ReceiptsPruneMode};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 /// Configuration for the reth node.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -7,6 +21,47 @@ pub struct Config {
     /// Configuration for each stage in the pipeline.
     // TODO(onbjerg): Can we make this easier to maintain when we add/remove stages?
     pub stages: StageConfig,
+    /// Configuration of the peer connection limits.
+    #[serde(default)]
+    pub peers: PeersLimitsConfig,
+    /// Logging configuration.
+    #[serde(default)]
+    pub log: LogConfig,
+    /// Pruning configuration.
+    #[serde(default)]
+    pub prune: PruneConfig,
+    /// RPC configuration.
+    #[serde(default)]
+    pub rpc: RpcConfig,
+    /// Archive mirrors configuration.
+    #[serde(default)]
+    pub mirrors: MirrorsConfig,
+    /// Freezer configuration.
+    #[serde(default)]
+    pub freezer: FreezerConfig,
+    /// Database configuration.
+    #[serde(default)]
+    pub db: DatabaseConfig,
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Reads the configuration from the TOML file at the given path.
+    ///
+    /// Returns the default configuration if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        confy::load_path(path).wrap_err_with(|| format!("Could not load config from {path:?}"))
+    }
+
+    /// Returns the configuration as a TOML document.
+    pub fn to_toml(&self) -> eyre::Result<String> {
+        toml::to_string(self).wrap_err("Could not serialize config")
+    }
 }
 
 /// Configuration for each stage in the pipeline.
@@ -18,6 +73,9 @@ pub struct StageConfig {
     pub bodies: BodiesConfig,
     /// Sender recovery stage configuration.
     pub sender_recovery: SenderRecoveryConfig,
+    /// Execution stage configuration.
+    #[serde(default)]
+    pub execution: ExecutionConfig,
 }
 
 /// Header stage configuration.
@@ -78,3 +136,601 @@ impl Default for SenderRecoveryConfig {
         Self { commit_threshold: 5_000, batch_size: 1000 }
     }
 }
+
+/// Execution stage configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExecutionConfig {
+    /// The number of threads that read the state the blocks are known to touch while the blocks
+    /// before them are executed. Zero reads it on the thread of the stage before the blocks are
+    /// executed.
+    pub state_readers: usize,
+    /// The number of reads that are queued for the state readers before the prefetch waits.
+    pub state_read_queue_size: usize,
+    /// The number of most recent blocks whose execution outcomes are kept in memory, so a reorg
+    /// back to a chain that was canonical before doesn't execute its blocks again.
+    pub reorg_buffer_blocks: usize,
+    /// The maximum number of blocks a reorg may unwind, the node stops instead of unwinding
+    /// deeper. `None` unwinds reorgs of any depth.
+    pub max_reorg_depth: Option<u64>,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            state_readers: DEFAULT_STATE_READERS,
+            state_read_queue_size: DEFAULT_STATE_READ_QUEUE_SIZE,
+            reorg_buffer_blocks: DEFAULT_REORG_BUFFER_BLOCKS,
+            max_reorg_depth: None,
+        }
+    }
+}
+
+// === impl ExecutionConfig ===
+
+impl ExecutionConfig {
+    /// Returns the settings of the state readers, `None` if there are none.
+    pub fn state_readers(&self) -> Option<StateReaderConfig> {
+        (self.state_readers > 0).then_some(StateReaderConfig {
+            workers: self.state_readers,
+            queue_size: self.state_read_queue_size,
+        })
+    }
+}
+
+/// Peer connection limits.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PeersLimitsConfig {
+    /// The maximum number of inbound connections.
+    pub max_inbound: usize,
+    /// The maximum number of outbound connections.
+    pub max_outbound: usize,
+}
+
+impl Default for PeersLimitsConfig {
+    fn default() -> Self {
+        Self { max_inbound: 30, max_outbound: 100 }
+    }
+}
+
+/// Logging configuration.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LogConfig {
+    /// The log filter directives, e.g. `reth=info,net=debug`.
+    ///
+    /// Ignored if `--log.filter` or `RUST_LOG` is set.
+    pub filter: Option<String>,
+}
+
+/// Pruning configuration.
+///
+/// Every setting is the number of most recent blocks to keep the data for; `None` keeps the data
+/// of all blocks.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PruneConfig {
+    /// Receipts and logs.
+    pub receipts: Option<u64>,
+    /// Account and storage changesets and their history indices.
+    pub history: Option<u64>,
+    /// Transaction senders, they're recovered from the signatures if the blocks are executed
+    /// again.
+    pub senders: Option<u64>,
+    /// The contracts whose receipts are kept for all blocks if receipts are pruned, e.g. the
+    /// deposit contract.
+    #[serde(default)]
+    pub receipts_contracts: Vec<Address>,
+}
+
+// === impl PruneConfig ===
+
+impl PruneConfig {
+    /// Returns the receipts to prune, or `None` if all receipts are kept.
+    pub fn receipts_prune_mode(&self) -> Option<ReceiptsPruneMode> {
+        let distance = self.receipts?;
+        Some(ReceiptsPruneMode::new(distance).with_contracts(self.receipts_contracts.clone()))
+    }
+}
+
+/// RPC configuration.
+///
+/// The Engine API is not a selectable namespace, it's only served on the authenticated port.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RpcConfig {
+    /// The RPC namespaces to serve over HTTP.
+    #[serde(alias = "modules")]
+    pub http: Vec<RpcModule>,
+    /// The RPC namespaces to serve over WebSocket.
+    pub ws: Vec<RpcModule>,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self { http: RpcModule::DEFAULT.to_vec(), ws: RpcModule::DEFAULT.to_vec() }
+    }
+}
+
+/// Archive mirrors configuration.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MirrorsConfig {
+    /// The base URLs of HTTP(S) archive mirrors.
+    ///
+    /// Headers and bodies are downloaded from the mirrors if no peer returns them.
+    pub urls: Vec<String>,
+}
+
+/// Freezer configuration.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FreezerConfig {
+    /// The number of most recent blocks that stay in the database.
+    ///
+    /// The headers, bodies and receipts of older blocks are moved to the freezer in the `freezer`
+    /// folder of the database. `None` keeps all blocks in the database.
+    pub distance: Option<u64>,
+}
+
+/// Database configuration.
+///
+/// The defaults suit servers with fast disks. Machines with little memory or disk space should
+/// use a smaller growth step.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// The maximum size of the database in GB.
+    pub max_size_gb: usize,
+    /// The number of MB the database file grows by once it is full.
+    pub growth_step_mb: usize,
+    /// How commits are flushed to disk.
+    pub sync_mode: DbSyncMode,
+    /// The maximum number of concurrent read transactions, the MDBX default if unset.
+    ///
+    /// Every RPC request and running stage holds a read transaction, busy RPC servers need more
+    /// than the default.
+    pub max_readers: Option<u32>,
+    /// Whether the OS reads ahead of the accessed pages.
+    ///
+    /// Only helps if the database fits into memory.
+    pub read_ahead: bool,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        let config = EnvConfig::default();
+        Self {
+            max_size_gb: config.max_size / GB,
+            growth_step_mb: config.growth_step / MB,
+            sync_mode: DbSyncMode::Durable,
+            max_readers: config.max_readers,
+            read_ahead: config.read_ahead,
+        }
+    }
+}
+
+// === impl DatabaseConfig ===
+
+impl DatabaseConfig {
+    /// Returns the settings of the MDBX environment.
+    pub fn env_config(&self) -> EnvConfig {
+        EnvConfig {
+            max_size: self.max_size_gb * GB,
+            growth_step: self.growth_step_mb * MB,
+            sync_mode: self.sync_mode.into(),
+            max_readers: self.max_readers,
+            read_ahead: self.read_ahead,
+        }
+    }
+}
+
+const MB: usize = 1024 * 1024;
+const GB: usize = 1024 * MB;
+
+/// How database commits are flushed to disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DbSyncMode {
+    /// Every commit is flushed to disk, a crash loses no committed data.
+    #[default]
+    Durable,
+    /// The metadata of a commit is not flushed, a crash may undo the last commit.
+    NoMetaSync,
+    /// The OS decides when commits are flushed, a crash undoes the commits since the last flush
+    /// but can't corrupt the database.
+    SafeNoSync,
+}
+
+impl From<DbSyncMode> for SyncMode {
+    fn from(mode: DbSyncMode) -> Self {
+        match mode {
+            DbSyncMode::Durable => SyncMode::Durable,
+            DbSyncMode::NoMetaSync => SyncMode::NoMetaSync,
+            DbSyncMode::SafeNoSync => SyncMode::SafeNoSync,
+        }
+    }
+}
+
+/// An RPC namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcModule {
+    /// `admin_` methods.
+    Admin,
+    /// `debug_` methods.
+    Debug,
+    /// `eth_` methods.
+    Eth,
+    /// `net_` methods.
+    Net,
+    /// `trace_` methods.
+    ///
+    /// Not served by default, every trace replays transactions on top of historical state.
+    Trace,
+    /// `txpool_` methods.
+    Txpool,
+    /// `web3_` methods.
+    Web3,
+}
+
+// === impl RpcModule ===
+
+impl RpcModule {
+    /// The namespaces served if none are selected, `admin` and `debug` have to be enabled
+    /// explicitly.
+    pub const DEFAULT: [RpcModule; 3] = [RpcModule::Eth, RpcModule::Net, RpcModule::Web3];
+}
+
+/// The configuration file and the flags that override its settings.
+#[derive(Debug, Clone, Args)]
+pub struct ConfigArgs {
+    /// The path to the configuration file to use.
+    #[arg(long, value_name = "FILE", default_value_t)]
+    pub config: ConfigPath,
+
+    /// The maximum number of inbound peer connections.
+    #[arg(long = "peers.max-inbound", value_name = "COUNT")]
+    pub max_inbound_peers: Option<usize>,
+
+    /// The maximum number of outbound peer connections.
+    #[arg(long = "peers.max-outbound", value_name = "COUNT")]
+    pub max_outbound_peers: Option<usize>,
+
+    /// The maximum number of headers to request from a peer at a time.
+    #[arg(long = "stages.headers.batch-size", value_name = "COUNT")]
+    pub headers_batch_size: Option<u64>,
+
+    /// The maximum number of bodies to request from a peer at a time.
+    #[arg(long = "stages.bodies.batch-size", value_name = "COUNT")]
+    pub bodies_batch_size: Option<usize>,
+
+    /// The maximum number of transactions to recover senders for concurrently.
+    #[arg(long = "stages.sender-recovery.batch-size", value_name = "COUNT")]
+    pub sender_recovery_batch_size: Option<usize>,
+
+    /// The number of threads that read the state of the blocks before they are executed, zero
+    /// reads it on the thread of the execution stage.
+    #[arg(long = "stages.execution.state-readers", value_name = "COUNT")]
+    pub execution_state_readers: Option<usize>,
+
+    /// The number of most recent blocks whose execution outcomes are kept for reorgs.
+    #[arg(long = "stages.execution.reorg-buffer", value_name = "BLOCKS")]
+    pub execution_reorg_buffer: Option<usize>,
+
+    /// The maximum number of blocks a reorg may unwind.
+    #[arg(long = "stages.execution.max-reorg-depth", value_name = "BLOCKS")]
+    pub execution_max_reorg_depth: Option<u64>,
+
+    /// The flags that override the pruning configuration.
+    #[clap(flatten)]
+    pub prune: PruneArgs,
+
+    /// Comma separated RPC namespaces to serve over HTTP, e.g. `eth,net,web3`.
+    #[arg(long = "http.api", value_name = "MODULES", value_delimiter = ',')]
+    pub http_api: Option<Vec<RpcModule>>,
+
+    /// Comma separated RPC namespaces to serve over WebSocket, e.g. `eth,net,web3`.
+    #[arg(long = "ws.api", value_name = "MODULES", value_delimiter = ',')]
+    pub ws_api: Option<Vec<RpcModule>>,
+
+    /// Comma separated base URLs of HTTP(S) archive mirrors to download headers and bodies from
+    /// if no peer returns them.
+    #[arg(long = "mirrors", value_name = "URLS", value_delimiter = ',')]
+    pub mirrors: Option<Vec<String>>,
+
+    /// The number of most recent blocks to keep in the database, older blocks are moved to the
+    /// freezer.
+    #[arg(long = "freezer.distance", value_name = "BLOCKS")]
+    pub freezer_distance: Option<u64>,
+
+    /// The maximum size of the database in GB.
+    #[arg(long = "db.max-size", value_name = "GB")]
+    pub db_max_size: Option<usize>,
+
+    /// The number of MB the database file grows by once it is full.
+    #[arg(long = "db.growth-step", value_name = "MB")]
+    pub db_growth_step: Option<usize>,
+
+    /// How database commits are flushed to disk.
+    #[arg(long = "db.sync-mode", value_name = "MODE")]
+    pub db_sync_mode: Option<DbSyncMode>,
+
+    /// The maximum number of concurrent database read transactions.
+    #[arg(long = "db.max-readers", value_name = "COUNT")]
+    pub db_max_readers: Option<u32>,
+
+    /// Whether the OS reads ahead of the accessed database pages.
+    #[arg(long = "db.read-ahead", value_name = "BOOL")]
+    pub db_read_ahead: Option<bool>,
+}
+
+// === impl ConfigArgs ===
+
+impl ConfigArgs {
+    /// Loads the configuration file and applies the flags on top of it.
+    pub fn load(&self) -> eyre::Result<Config> {
+        let mut config = Config::load(&self.config)?;
+        self.apply(&mut config);
+        Ok(config)
+    }
+
+    /// Overrides the settings of the config with the flags that are set.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(max_inbound) = self.max_inbound_peers {
+            config.peers.max_inbound = max_inbound;
+        }
+        if let Some(max_outbound) = self.max_outbound_peers {
+            config.peers.max_outbound = max_outbound;
+        }
+        if let Some(batch_size) = self.headers_batch_size {
+            config.stages.headers.downloader_batch_size = batch_size;
+        }
+        if let Some(batch_size) = self.bodies_batch_size {
+            config.stages.bodies.downloader_batch_size = batch_size;
+        }
+        if let Some(batch_size) = self.sender_recovery_batch_size {
+            config.stages.sender_recovery.batch_size = batch_size;
+        }
+        if let Some(state_readers) = self.execution_state_readers {
+            config.stages.execution.state_readers = state_readers;
+        }
+        if let Some(blocks) = self.execution_reorg_buffer {
+            config.stages.execution.reorg_buffer_blocks = blocks;
+        }
+        if let Some(depth) = self.execution_max_reorg_depth {
+            config.stages.execution.max_reorg_depth = Some(depth);
+        }
+        self.prune.apply(&mut config.prune);
+        if let Some(modules) = &self.http_api {
+            config.rpc.http = modules.clone();
+        }
+        if let Some(modules) = &self.ws_api {
+            config.rpc.ws = modules.clone();
+        }
+        if let Some(mirrors) = &self.mirrors {
+            config.mirrors.urls = mirrors.clone();
+        }
+        config.freezer.distance = self.freezer_distance.or(config.freezer.distance);
+        if let Some(max_size) = self.db_max_size {
+            config.db.max_size_gb = max_size;
+        }
+        if let Some(growth_step) = self.db_growth_step {
+            config.db.growth_step_mb = growth_step;
+        }
+        if let Some(sync_mode) = self.db_sync_mode {
+            config.db.sync_mode = sync_mode;
+        }
+        config.db.max_readers = self.db_max_readers.or(config.db.max_readers);
+        if let Some(read_ahead) = self.db_read_ahead {
+            config.db.read_ahead = read_ahead;
+        }
+    }
+}
+
+/// The number of blocks a full node keeps the changesets and history indices for, unless
+/// `--prune.history` is set.
+pub const FULL_NODE_HISTORY: u64 = 128;
+
+/// The flags that override the [`PruneConfig`].
+#[derive(Debug, Clone, Default, Args)]
+pub struct PruneArgs {
+    /// Run as a full node, which only keeps the historical state of the last blocks
+    ///
+    /// The changesets and history indices older than 128 blocks are pruned unless
+    /// `--prune.history` or the config set a different distance. The state of older blocks can't
+    /// be queried anymore.
+    #[arg(long)]
+    pub full: bool,
+    /// Overrides the number of blocks to keep receipts for
+    #[arg(long = "prune.receipts", value_name = "BLOCKS")]
+    pub receipts: Option<u64>,
+    /// Overrides the number of blocks to keep changesets and history indices for
+    #[arg(long = "prune.history", value_name = "BLOCKS")]
+    pub history: Option<u64>,
+    /// Overrides the number of blocks to keep transaction senders for
+    #[arg(long = "prune.senders", value_name = "BLOCKS")]
+    pub senders: Option<u64>,
+    /// Overrides the comma separated contracts whose receipts are never pruned
+    #[arg(long = "prune.receipts.contracts", value_name = "ADDRESSES", value_delimiter = ',')]
+    pub receipts_contracts: Option<Vec<Address>>,
+}
+
+// === impl PruneArgs ===
+
+impl PruneArgs {
+    /// Overrides the settings of the config with the flags that are set.
+    pub fn apply(&self, prune: &mut PruneConfig) {
+        prune.receipts = self.receipts.or(prune.receipts);
+        prune.history = self.history.or(prune.history).or(self.full.then_some(FULL_NODE_HISTORY));
+        prune.senders = self.senders.or(prune.senders);
+        if let Some(contracts) = &self.receipts_contracts {
+            prune.receipts_contracts = contracts.clone();
+        }
+    }
+}
+
+/// `reth config` command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[clap(flatten)]
+    args: ConfigArgs,
+
+    /// Print the default configuration, ignoring the config file and all flags.
+    #[arg(long)]
+    default: bool,
+}
+
+impl Command {
+    /// Execute `config` command
+    pub async fn execute(&self) -> eyre::Result<()> {
+        let config = if self.default { Config::default() } else { self.args.load()? };
+        print!("{}", config.to_toml()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Parser)]
+    struct CommandParser {
+        #[clap(flatten)]
+        args: ConfigArgs,
+    }
+
+    #[test]
+    fn default_config_roundtrip() {
+        let config = Config::default();
+        let decoded: Config = toml::from_str(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(decoded.to_toml().unwrap(), config.to_toml().unwrap());
+        assert_eq!(decoded.rpc.http, vec![RpcModule::Eth, RpcModule::Net, RpcModule::Web3]);
+        assert_eq!(decoded.rpc.ws, decoded.rpc.http);
+    }
+
+    #[test]
+    fn partial_config_file() {
+        let config: Config = toml::from_str(
+            r#"
+            [stages.headers]
+            commit_threshold = 100
+            downloader_batch_size = 10
+            downloader_retries = 1
+
+            [stages.bodies]
+            commit_threshold = 100
+            downloader_batch_size = 10
+            downloader_retries = 1
+            downloader_concurrency = 1
+
+            [stages.sender_recovery]
+            commit_threshold = 100
+            batch_size = 10
+
+            [rpc]
+            modules = ["eth", "debug"]
+            "#,
+        )
+        .unwrap();
+        // `modules` is the old name of the HTTP namespaces
+        assert_eq!(config.rpc.http, vec![RpcModule::Eth, RpcModule::Debug]);
+        assert_eq!(config.rpc.ws, RpcModule::DEFAULT);
+        assert_eq!(config.peers.max_inbound, PeersLimitsConfig::default().max_inbound);
+        assert_eq!(config.prune.receipts, None);
+        assert_eq!(config.prune.receipts_prune_mode(), None);
+        assert_eq!(config.stages.execution.state_readers(), Some(StateReaderConfig::default()));
+        assert_eq!(config.stages.execution.reorg_buffer_blocks, DEFAULT_REORG_BUFFER_BLOCKS);
+        assert_eq!(config.stages.execution.max_reorg_depth, None);
+    }
+
+    #[test]
+    fn flags_take_precedence() {
+        let mut config = Config::default();
+        config.peers.max_inbound = 5;
+        config.prune.history = Some(1000);
+        config.prune.senders = Some(1000);
+
+        let args = CommandParser::parse_from([
+            "reth",
+            "--peers.max-inbound",
+            "10",
+            "--stages.bodies.batch-size",
+            "50",
+            "--stages.execution.state-readers",
+            "0",
+            "--stages.execution.max-reorg-depth",
+            "16",
+            "--prune.senders",
+            "64",
+            "--prune.receipts",
+            "128",
+            "--prune.receipts.contracts",
+            "0x00000000219ab540356cbb839cbe05303d7705fa",
+            "--http.api",
+            "eth,txpool",
+            "--mirrors",
+            "https://a.example,https://b.example",
+            "--freezer.distance",
+            "90000",
+            "--db.sync-mode",
+            "safe-no-sync",
+            "--db.max-readers",
+            "1024",
+        ])
+        .args;
+        args.apply(&mut config);
+
+        assert_eq!(config.peers.max_inbound, 10);
+        assert_eq!(config.peers.max_outbound, PeersLimitsConfig::default().max_outbound);
+        assert_eq!(config.stages.bodies.downloader_batch_size, 50);
+        assert_eq!(config.stages.execution.state_readers(), None);
+        assert_eq!(config.stages.execution.max_reorg_depth, Some(16));
+        assert_eq!(config.stages.execution.reorg_buffer_blocks, DEFAULT_REORG_BUFFER_BLOCKS);
+        assert_eq!(
+            config.stages.headers.downloader_batch_size,
+            HeadersConfig::default().downloader_batch_size
+        );
+        assert_eq!(config.prune.history, Some(1000));
+        assert_eq!(config.prune.senders, Some(64));
+        let deposit_contract: Address =
+            "0x00000000219ab540356cbb839cbe05303d7705fa".parse().unwrap();
+        assert_eq!(
+            config.prune.receipts_prune_mode(),
+            Some(ReceiptsPruneMode::new(128).with_contracts(vec![deposit_contract]))
+        );
+        assert_eq!(config.rpc.http, vec![RpcModule::Eth, RpcModule::Txpool]);
+        assert_eq!(config.rpc.ws, RpcModule::DEFAULT);
+        assert_eq!(config.mirrors.urls, vec!["https://a.example", "https://b.example"]);
+        assert_eq!(config.freezer.distance, Some(90_000));
+        assert_eq!(config.db.sync_mode, DbSyncMode::SafeNoSync);
+        assert_eq!(config.db.max_readers, Some(1024));
+        assert_eq!(config.db.growth_step_mb, DatabaseConfig::default().growth_step_mb);
+    }
+
+    #[test]
+    fn database_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [db]
+            growth_step_mb = 64
+            sync_mode = "no-meta-sync"
+            "#,
+        )
+        .unwrap();
+        let env = config.db.env_config();
+        assert_eq!(env.growth_step, 64 * 1024 * 1024);
+        assert_eq!(env.max_size, EnvConfig::default().max_size);
+        assert!(matches!(env.sync_mode, SyncMode::NoMetaSync));
+        assert!(!env.read_ahead);
+    }
+
+    #[test]
+    fn full_node_prunes_history() {
+        let mut config = Config::default();
+        CommandParser::parse_from(["reth", "--full"]).args.apply(&mut config);
+        assert_eq!(config.prune.history, Some(FULL_NODE_HISTORY));
+
+        let mut config = Config::default();
+        CommandParser::parse_from(["reth", "--full", "--prune.history", "1000"])
+            .args
+            .apply(&mut config);
+        assert_eq!(config.prune.history, Some(1000));
+    }
+}
@@ -0,0 +1,112 @@
+//! Local control endpoint to reconfigure a running node.
+//!
+//! The endpoint is served over IPC (a unix socket or a windows named pipe) and allows changing a
+//! subset of the [`Config`] that is safe to apply at runtime. Every change is persisted back to
+//! the config file, so it survives a restart.
+//!
+//! Supported methods:
+//! - `control_config`: returns the current configuration
+//! - `control_setLogFilter(filter)`: replaces the log filter, e.g. `reth=info,net=debug`
+//! - `control_setPeerLimits(max_inbound, max_outbound)`: updates the peer connection limits
+
+use crate::{config::Config, util::reth_tracing::FilterHandle};
+use eyre::WrapErr;
+use jsonrpsee::{core::Error, server::ServerHandle, RpcModule};
+use reth_network::peers::PeersHandle;
+use std::{path::PathBuf, sync::Mutex};
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+/// The settings that can be changed via the control endpoint.
+#[derive(Debug)]
+pub struct ControlState {
+    /// The current configuration.
+    config: Mutex<Config>,
+    /// Where the configuration is persisted.
+    config_path: PathBuf,
+    /// Handle to replace the log filter.
+    filter: FilterHandle,
+    /// Handle to update the peer limits.
+    peers: PeersHandle,
+}
+
+// === impl ControlState ===
+
+impl ControlState {
+    /// Creates a new instance that persists the `config` at `config_path`.
+    pub fn new(
+        config: Config,
+        config_path: impl Into<PathBuf>,
+        filter: FilterHandle,
+        peers: PeersHandle,
+    ) -> Self {
+        Self { config: Mutex::new(config), config_path: config_path.into(), filter, peers }
+    }
+
+    /// Returns the current configuration.
+    pub fn config(&self) -> Config {
+        self.config.lock().expect("not poisoned").clone()
+    }
+
+    /// Replaces the log filter with the given directives.
+    pub fn set_log_filter(&self, directives: String) -> eyre::Result<()> {
+        let filter = EnvFilter::try_new(&directives)
+            .wrap_err_with(|| format!("Invalid log filter: {directives}"))?;
+        self.filter.reload(filter).wrap_err("Could not replace log filter")?;
+        info!(target: "reth::control", %directives, "Updated log filter");
+        self.update(|config| config.log.filter = Some(directives))
+    }
+
+    /// Updates the maximum number of inbound and outbound peer connections.
+    pub fn set_peer_limits(&self, max_inbound: usize, max_outbound: usize) -> eyre::Result<()> {
+        self.peers.set_connection_limits(max_inbound, max_outbound);
+        info!(target: "reth::control", max_inbound, max_outbound, "Updated peer limits");
+        self.update(|config| {
+            config.peers.max_inbound = max_inbound;
+            config.peers.max_outbound = max_outbound;
+        })
+    }
+
+    /// Applies the change to the configuration and writes it to the config file.
+    fn update(&self, f: impl FnOnce(&mut Config)) -> eyre::Result<()> {
+        let mut config = self.config.lock().expect("not poisoned");
+        f(&mut config);
+        confy::store_path(&self.config_path, &*config)
+            .wrap_err_with(|| format!("Could not write config to {}", self.config_path.display()))
+    }
+
+    /// Returns the [`RpcModule`] with all control methods.
+    pub fn into_rpc(self) -> RpcModule<Self> {
+        let mut module = RpcModule::new(self);
+        module
+            .register_method("control_config", |_, state| Ok(state.config()))
+            .expect("unique method name");
+        module
+            .register_method("control_setLogFilter", |params, state| {
+                let filter = params.one::<String>()?;
+                state.set_log_filter(filter).map_err(|err| Error::Custom(format!("{err:#}")))
+            })
+            .expect("unique method name");
+        module
+            .register_method("control_setPeerLimits", |params, state| {
+                let (max_inbound, max_outbound) = params.parse::<(usize, usize)>()?;
+                state
+                    .set_peer_limits(max_inbound, max_outbound)
+                    .map_err(|err| Error::Custom(format!("{err:#}")))
+            })
+            .expect("unique method name");
+        module
+    }
+}
+
+/// Starts the control endpoint at the given IPC path.
+///
+/// The endpoint is stopped once the returned [`ServerHandle`] is dropped.
+pub async fn start(endpoint: &str, state: ControlState) -> eyre::Result<ServerHandle> {
+    let server = reth_ipc::server::Builder::default()
+        .build(endpoint)
+        .wrap_err_with(|| format!("Could not create control endpoint at {endpoint}"))?;
+    let handle = server.start(state.into_rpc()).await?;
+    info!(target: "reth::control", %endpoint, "Started control endpoint");
+    Ok(handle)
+}
@@ -0,0 +1,300 @@
+//! Dropping the database and clearing tables to redo the stages that write them.
+use crate::{node::insert_genesis_alloc, util::chainspec::Genesis};
+use eyre::WrapErr;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    models::BlockNumHash,
+    table::Table,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::Header;
+use reth_stages::{
+    stages::{
+        bodies::BODIES, execution::EXECUTION, hashing_account::ACCOUNT_HASHING,
+        hashing_storage::STORAGE_HASHING, headers::HEADERS, history_index::HISTORY_INDEX,
+        log_index::LOG_INDEX, merkle::MERKLE, sender_recovery::SENDER_RECOVERY,
+    },
+    StageId,
+};
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+};
+use tracing::info;
+
+/// The stages and the tables they write.
+///
+/// A stage can only be redone from the first block if all of its tables are empty, so they are
+/// cleared together.
+const STAGE_TABLES: &[(StageId, &[&str])] = &[
+    (
+        HEADERS,
+        &[
+            tables::CanonicalHeaders::const_name(),
+            tables::HeaderTD::const_name(),
+            tables::HeaderNumbers::const_name(),
+            tables::Headers::const_name(),
+        ],
+    ),
+    (
+        BODIES,
+        &[
+            tables::BlockBodies::const_name(),
+            tables::BlockOmmers::const_name(),
+            tables::Transactions::const_name(),
+            tables::TxHashNumber::const_name(),
+            tables::BlockTransitionIndex::const_name(),
+            tables::TxTransitionIndex::const_name(),
+        ],
+    ),
+    (SENDER_RECOVERY, &[tables::TxSenders::const_name()]),
+    (
+        EXECUTION,
+        &[
+            tables::PlainAccountState::const_name(),
+            tables::PlainStorageState::const_name(),
+            tables::Bytecodes::const_name(),
+            tables::AccountChangeSet::const_name(),
+            tables::StorageChangeSet::const_name(),
+            tables::Receipts::const_name(),
+        ],
+    ),
+    (ACCOUNT_HASHING, &[tables::HashedAccount::const_name()]),
+    (STORAGE_HASHING, &[tables::HashedStorage::const_name()]),
+    (
+        MERKLE,
+        &[
+            tables::StorageRoots::const_name(),
+            tables::AccountsTrie::const_name(),
+            tables::StoragesTrie::const_name(),
+        ],
+    ),
+    (LOG_INDEX, &[tables::LogAddressIndex::const_name(), tables::LogTopicIndex::const_name()]),
+    (HISTORY_INDEX, &[tables::AccountHistory::const_name(), tables::StorageHistory::const_name()]),
+];
+
+/// Returns the stage that writes the table and all tables it writes, `None` if no stage writes
+/// the table.
+pub(crate) fn stage_of(table: &str) -> Option<(StageId, &'static [&'static str])> {
+    STAGE_TABLES.iter().find(|(_, tables)| tables.contains(&table)).copied()
+}
+
+/// Removes the database at `path` after the user confirmed it, unless `yes` is set.
+pub(crate) fn drop_db(path: &Path, yes: bool) -> eyre::Result<()> {
+    if !path.exists() {
+        info!(target: "reth::cli", "No database at {}", path.display());
+        return Ok(())
+    }
+    if !yes && !confirm(&format!("Remove the database at {}?", path.display()))? {
+        info!(target: "reth::cli", "Database not removed");
+        return Ok(())
+    }
+
+    // Fails if a node is running on the database.
+    drop(super::open_db(path, true)?);
+    std::fs::remove_dir_all(path)
+        .wrap_err_with(|| format!("Could not remove {}", path.display()))?;
+    info!(target: "reth::cli", "Removed the database at {}", path.display());
+    Ok(())
+}
+
+/// Clears the table and resets the checkpoint of the stage that writes it, so the next run of the
+/// node redoes the stage from the first block.
+///
+/// The other tables of the stage are cleared as well. Tables that no stage writes are only
+/// cleared. The checkpoints of the following stages are kept, they continue once the stage caught
+/// up again.
+///
+/// The genesis block is written when the database is created instead of by the stages, so its
+/// rows are kept, and the accounts of the genesis state are written again if the plain state is
+/// cleared. The genesis must be the one of the database.
+pub(crate) fn clear_table(db: &Env<WriteMap>, table: &str, genesis: &Genesis) -> eyre::Result<()> {
+    if !tables::TABLES.iter().any(|(_, name)| *name == table) {
+        eyre::bail!("Unknown table {table}")
+    }
+    if table == tables::Config::NAME {
+        eyre::bail!("{table} stores the versions of the table encodings and can not be cleared")
+    }
+
+    let stage = stage_of(table);
+    let single = [table];
+    let cleared = stage.map_or(&single[..], |(_, stage_tables)| stage_tables);
+    db.update(|tx| {
+        let genesis_rows = GenesisRows::read(tx)?;
+        if let Some(rows) = &genesis_rows {
+            let hash = Header::from(genesis.clone()).hash_slow();
+            if rows.key.hash() != hash {
+                eyre::bail!(
+                    "The genesis block {:?} of the database is not the one of the chain {hash:?}",
+                    rows.key.hash()
+                )
+            }
+        }
+
+        for table in cleared {
+            let table_db = tx.inner.open_db(Some(table)).wrap_err("Could not open db.")?;
+            tx.inner.clear_db(&table_db).wrap_err_with(|| format!("Could not clear {table}"))?;
+            info!(target: "reth::cli", "Cleared table {table}");
+        }
+        if let Some(rows) = genesis_rows {
+            rows.restore(tx, cleared)?;
+        }
+        if cleared.contains(&tables::PlainAccountState::NAME) {
+            insert_genesis_alloc(tx, genesis)?;
+            info!(target: "reth::cli", "Wrote the genesis state again");
+        }
+        if let Some((stage, _)) = stage {
+            stage.save_progress(tx, 0)?;
+            info!(target: "reth::cli", "Reset the checkpoint of stage {stage}");
+        }
+        Ok::<_, eyre::Report>(())
+    })??;
+    Ok(())
+}
+
+/// The rows of the genesis block in the tables of the headers and bodies stages.
+struct GenesisRows {
+    key: BlockNumHash,
+    header: Option<Header>,
+    td: Option<<tables::HeaderTD as Table>::Value>,
+    body: Option<<tables::BlockBodies as Table>::Value>,
+    ommers: Option<<tables::BlockOmmers as Table>::Value>,
+    transition: Option<<tables::BlockTransitionIndex as Table>::Value>,
+}
+
+// === impl GenesisRows ===
+
+impl GenesisRows {
+    /// Reads the rows of the genesis block, `None` if there is no genesis block.
+    fn read<'a>(tx: &impl DbTx<'a>) -> Result<Option<Self>, reth_db::Error> {
+        let Some(hash) = tx.get::<tables::CanonicalHeaders>(0)? else { return Ok(None) };
+        let key = BlockNumHash((0, hash));
+        Ok(Some(Self {
+            key,
+            header: tx.get::<tables::Headers>(key)?,
+            td: tx.get::<tables::HeaderTD>(key)?,
+            body: tx.get::<tables::BlockBodies>(key)?,
+            ommers: tx.get::<tables::BlockOmmers>(key)?,
+            transition: tx.get::<tables::BlockTransitionIndex>(key)?,
+        }))
+    }
+
+    /// Writes the rows of the cleared tables again.
+    fn restore<'a>(self, tx: &impl DbTxMut<'a>, cleared: &[&str]) -> Result<(), reth_db::Error> {
+        let Self { key, header, td, body, ommers, transition } = self;
+        restore::<tables::CanonicalHeaders>(tx, cleared, 0, Some(key.hash()))?;
+        restore::<tables::HeaderNumbers>(tx, cleared, key.hash(), Some(0))?;
+        restore::<tables::Headers>(tx, cleared, key, header)?;
+        restore::<tables::HeaderTD>(tx, cleared, key, td)?;
+        restore::<tables::BlockBodies>(tx, cleared, key, body)?;
+        restore::<tables::BlockOmmers>(tx, cleared, key, ommers)?;
+        restore::<tables::BlockTransitionIndex>(tx, cleared, key, transition)
+    }
+}
+
+/// Writes the value again if the table was cleared.
+fn restore<'a, T: Table>(
+    tx: &impl DbTxMut<'a>,
+    cleared: &[&str],
+    key: T::Key,
+    value: Option<T::Value>,
+) -> Result<(), reth_db::Error> {
+    match value {
+        Some(value) if cleared.contains(&T::NAME) => tx.put::<T>(key, value),
+        _ => Ok(()),
+    }
+}
+
+/// Asks the user to confirm the question on the terminal.
+fn confirm(question: &str) -> eyre::Result<bool> {
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{node::init_genesis, util::chainspec::GenesisAccount};
+    use reth_db::mdbx::test_utils::create_test_rw_db;
+    use reth_primitives::{Account, Address, H256, U256};
+
+    #[test]
+    fn stage_tables_are_tables() {
+        for (stage, stage_tables) in STAGE_TABLES {
+            for table in *stage_tables {
+                assert!(
+                    tables::TABLES.iter().any(|(_, name)| name == table),
+                    "{table} of stage {stage} is not a table"
+                );
+                assert_eq!(stage_of(table).map(|(stage, _)| stage), Some(*stage));
+            }
+        }
+    }
+
+    #[test]
+    fn clears_tables_of_stage() {
+        let db = create_test_rw_db::<WriteMap>();
+        db.update(|tx| {
+            tx.put::<tables::HashedAccount>(H256::zero(), Account::default())?;
+            tx.put::<tables::PlainAccountState>(Address::zero(), Account::default())?;
+            ACCOUNT_HASHING.save_progress(tx, 10)?;
+            EXECUTION.save_progress(tx, 10)
+        })
+        .unwrap()
+        .unwrap();
+
+        let genesis = Genesis::default();
+        clear_table(&db, tables::HashedAccount::NAME, &genesis).unwrap();
+        assert!(clear_table(&db, "Unknown", &genesis).is_err());
+        assert!(clear_table(&db, tables::Config::NAME, &genesis).is_err());
+
+        let tx = db.tx().unwrap();
+        assert_eq!(tx.get::<tables::HashedAccount>(H256::zero()).unwrap(), None);
+        assert!(tx.get::<tables::PlainAccountState>(Address::zero()).unwrap().is_some());
+        assert_eq!(ACCOUNT_HASHING.get_progress(&tx).unwrap(), Some(0));
+        assert_eq!(EXECUTION.get_progress(&tx).unwrap(), Some(10));
+    }
+
+    #[test]
+    fn keeps_genesis() {
+        let db = create_test_rw_db::<WriteMap>();
+        let address = Address::from_low_u64_be(1);
+        let mut genesis = Genesis::default();
+        genesis.alloc.insert(address, GenesisAccount { nonce: None, balance: U256::from(10) });
+        let hash = init_genesis(db.clone(), genesis.clone()).unwrap();
+        let key = BlockNumHash((0, hash));
+        db.update(|tx| {
+            tx.put::<tables::CanonicalHeaders>(1, H256::from_low_u64_be(1))?;
+            tx.put::<tables::PlainAccountState>(address, Account::default())
+        })
+        .unwrap()
+        .unwrap();
+
+        clear_table(&db, tables::BlockBodies::NAME, &genesis).unwrap();
+        clear_table(&db, tables::PlainAccountState::NAME, &genesis).unwrap();
+        clear_table(&db, tables::Headers::NAME, &genesis).unwrap();
+
+        let tx = db.tx().unwrap();
+        assert_eq!(tx.get::<tables::CanonicalHeaders>(0).unwrap(), Some(hash));
+        assert_eq!(tx.get::<tables::CanonicalHeaders>(1).unwrap(), None);
+        assert_eq!(tx.get::<tables::HeaderNumbers>(hash).unwrap(), Some(0));
+        assert!(tx.get::<tables::Headers>(key).unwrap().is_some());
+        assert!(tx.get::<tables::HeaderTD>(key).unwrap().is_some());
+        assert!(tx.get::<tables::BlockBodies>(key).unwrap().is_some());
+        assert_eq!(tx.get::<tables::BlockTransitionIndex>(key).unwrap(), Some(0));
+        assert_eq!(
+            tx.get::<tables::PlainAccountState>(address).unwrap(),
+            Some(Account { nonce: 0, balance: U256::from(10), bytecode_hash: None })
+        );
+        drop(tx);
+
+        // the genesis of another chain is refused
+        genesis.nonce = 1;
+        assert!(clear_table(&db, tables::PlainAccountState::NAME, &genesis).is_err());
+    }
+}
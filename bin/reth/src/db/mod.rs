@@ -1,17 +1,29 @@
 //! Database debugging tool
-use crate::dirs::DbPath;
+use crate::{
+    config::{Config, PruneArgs},
+    dirs::{ConfigPath, DbPath},
+    util::chainspec::{chain_spec_value_parser, ChainSpecification},
+};
 use clap::{Parser, Subcommand};
 use eyre::{Result, WrapErr};
 use reth_db::{
     cursor::{DbCursorRO, Walker},
     database::Database,
+    mdbx::{Env, EnvConfig, EnvKind, WriteMap},
+    migration,
     table::Table,
     tables,
     transaction::DbTx,
 };
 use reth_interfaces::test_utils::generators::random_block_range;
 use reth_provider::insert_canonical_block;
+use std::path::Path;
 use tracing::info;
+use usage::UsageReport;
+
+mod clear;
+mod repair;
+mod usage;
 
 /// `reth db` command
 #[derive(Debug, Parser)]
@@ -37,8 +49,48 @@ const DEFAULT_NUM_ITEMS: &str = "5";
 pub enum Subcommands {
     /// Lists all the tables, their entry count and their size
     Stats,
+    /// Reports the disk usage per segment and the savings of the prune settings
+    Usage(UsageArgs),
     /// Lists the contents of a table
     List(ListArgs),
+    /// Migrates the tables to the encodings of this release
+    Migrate,
+    /// Checks the database file for structural problems and repairs them with a compacting copy
+    ///
+    /// Problems of the freelist and lost pages can be repaired, damaged tables can not. The
+    /// original database is kept next to the repaired one.
+    RepairFreelist {
+        /// Only report the problems and whether they can be repaired
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Removes the whole database
+    Drop {
+        /// Remove the database without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Clears a table and resets the checkpoint of the stage that writes it
+    ///
+    /// The other tables of the stage are cleared as well, so the next run of the node redoes the
+    /// stage from the first block. The genesis block and its state are kept.
+    Clear {
+        /// The table name
+        table: String,
+
+        /// The chain of the database, whose genesis state is written again if the plain state is
+        /// cleared.
+        ///
+        /// Either a built-in chain (mainnet, goerli, sepolia, dev) or the path to a chain
+        /// specification file.
+        #[arg(
+            long,
+            value_name = "CHAIN_OR_PATH",
+            default_value = "mainnet",
+            value_parser = chain_spec_value_parser
+        )]
+        chain: ChainSpecification,
+    },
     /// Seeds the database with random blocks on top of each other
     Seed {
         /// How many blocks to generate
@@ -60,16 +112,40 @@ pub struct ListArgs {
     len: usize,
 }
 
+#[derive(Parser, Debug)]
+/// The arguments for the `reth db usage` command
+pub struct UsageArgs {
+    /// Also list the usage of every table of a segment
+    #[arg(long)]
+    detailed: bool,
+    /// The path to the configuration file with the prune settings.
+    #[arg(long, value_name = "FILE", default_value_t)]
+    config: ConfigPath,
+    #[clap(flatten)]
+    prune: PruneArgs,
+}
+
+// === impl Subcommands ===
+
+impl Subcommands {
+    /// Returns true if the command only reads the database.
+    fn is_read_only(&self) -> bool {
+        matches!(self, Subcommands::Stats | Subcommands::Usage(_) | Subcommands::List(_))
+    }
+}
+
 impl Command {
     /// Execute `db` command
     pub async fn execute(&self) -> eyre::Result<()> {
-        std::fs::create_dir_all(&self.db)?;
+        // The repair replaces the database, so it must not be open.
+        if let Subcommands::RepairFreelist { dry_run } = &self.command {
+            return repair::repair_freelist(self.db.as_ref(), *dry_run)
+        }
+        if let Subcommands::Drop { yes } = &self.command {
+            return clear::drop_db(self.db.as_ref(), *yes)
+        }
 
-        // TODO: Auto-impl for Database trait
-        let db = reth_db::mdbx::Env::<reth_db::mdbx::WriteMap>::open(
-            self.db.as_ref(),
-            reth_db::mdbx::EnvKind::RW,
-        )?;
+        let db = open_db(self.db.as_ref(), !self.command.is_read_only())?;
 
         let mut tool = DbTool::new(&db)?;
 
@@ -105,6 +181,30 @@ impl Command {
                     Ok::<(), eyre::Report>(())
                 })??;
             }
+            Subcommands::Usage(args) => {
+                let mut prune = Config::load(&args.config)?.prune;
+                args.prune.apply(&mut prune);
+
+                UsageReport::collect(&db)?.print(&prune, args.detailed);
+            }
+            Subcommands::Migrate => {
+                let migrated = migration::migrate(&db)?;
+                if migrated.is_empty() {
+                    info!("Database is up to date");
+                }
+                for (table, version) in migrated {
+                    info!(
+                        "Migrated table {table} from version {version} to {}",
+                        migration::current_version(table)
+                    );
+                }
+            }
+            Subcommands::RepairFreelist { .. } | Subcommands::Drop { .. } => {
+                unreachable!("handled above")
+            }
+            Subcommands::Clear { table, chain } => {
+                clear::clear_table(&db, table, &chain.genesis)?;
+            }
             Subcommands::Seed { len } => {
                 tool.seed(*len)?;
             }
@@ -117,6 +217,30 @@ impl Command {
     }
 }
 
+/// Opens the database at `path`.
+///
+/// Commands that only read the database open it read-only, so they can inspect the database of a
+/// running node. Commands that write it open it exclusively, which fails while a node or another
+/// command has the database open instead of writing to it concurrently.
+pub(crate) fn open_db(path: &Path, write: bool) -> eyre::Result<Env<WriteMap>> {
+    if !write {
+        if !path.exists() {
+            eyre::bail!("No database at {}", path.display())
+        }
+        return Env::<WriteMap>::open(path, EnvKind::RO)
+            .wrap_err_with(|| format!("Could not open database at {}", path.display()))
+    }
+
+    std::fs::create_dir_all(path)?;
+    let config = EnvConfig { exclusive: true, ..Default::default() };
+    Env::<WriteMap>::open_with_config(path, EnvKind::RW, config).wrap_err_with(|| {
+        format!(
+            "Could not open database at {} exclusively, is a node running on it?",
+            path.display()
+        )
+    })
+}
+
 /// Wrapper over DB that implements many useful DB queries.
 struct DbTool<'a, DB: Database> {
     pub(crate) db: &'a DB,
@@ -169,6 +293,7 @@ impl<'a, DB: Database> DbTool<'a, DB> {
             BlockOmmers,
             TxHashNumber,
             PlainAccountState,
+            HashedAccount,
             BlockTransitionIndex,
             TxTransitionIndex,
             SyncStage,
@@ -197,3 +322,22 @@ impl<'a, DB: Database> DbTool<'a, DB> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::transaction::DbTxMut;
+
+    #[test]
+    fn opens_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        assert!(open_db(&path, false).is_err());
+
+        open_db(&path, true).unwrap().create_tables().unwrap();
+        let db = open_db(&path, false).unwrap();
+        let progress = db.view(|tx| tx.get::<tables::SyncStage>(b"Headers".to_vec())).unwrap();
+        assert_eq!(progress.unwrap(), None);
+        assert!(db.update(|tx| tx.put::<tables::SyncStage>(b"Headers".to_vec(), 1)).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Structural check of the database file and its repair by a compacting copy.
+use super::usage::human_bytes;
+use eyre::WrapErr;
+use reth_db::mdbx::{CheckReport, Env, EnvKind, WriteMap};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// Checks the database at `path` and, unless `dry_run` is set, repairs it with a compacting copy.
+///
+/// The repaired copy is checked again before it replaces the database. The original database is
+/// kept next to it, so it can be restored if the repair lost data.
+///
+/// The database is opened exclusively until it is replaced, so no node can run on it meanwhile.
+pub(crate) fn repair_freelist(path: &Path, dry_run: bool) -> eyre::Result<()> {
+    if !path.exists() {
+        eyre::bail!("No database at {}", path.display())
+    }
+    let db = super::open_db(path, true)?;
+    let report = db.check().wrap_err("Could not check the database")?;
+    print_report(&report);
+
+    if report.is_healthy() {
+        info!(target: "reth::cli", "No problems found, nothing to repair");
+        return Ok(())
+    }
+    if !report.is_repairable() {
+        eyre::bail!(
+            "Databases are damaged, a compacting copy can not repair them. The database has to be \
+             restored from a backup or synced again."
+        )
+    }
+    if dry_run {
+        info!(target: "reth::cli", "The problems can be repaired with a compacting copy");
+        return Ok(())
+    }
+
+    let repaired = sibling(path, "repaired")?;
+    let backup = sibling(path, "corrupted")?;
+    for dir in [&repaired, &backup] {
+        if dir.exists() {
+            eyre::bail!("{} already exists, remove it to repair the database", dir.display())
+        }
+    }
+
+    info!(target: "reth::cli", to = %repaired.display(), "Copying the database with compaction");
+    fs::create_dir_all(&repaired)?;
+    db.copy(&repaired.join("mdbx.dat"), true).wrap_err("Could not copy the database")?;
+
+    let copy_report = Env::<WriteMap>::open(&repaired, EnvKind::RO)?
+        .check()
+        .wrap_err("Could not check the repaired database")?;
+    if !copy_report.is_healthy() {
+        print_report(&copy_report);
+        eyre::bail!(
+            "The repaired copy at {} still has problems, the database was not replaced",
+            repaired.display()
+        )
+    }
+
+    fs::rename(path, &backup)?;
+    fs::rename(&repaired, path)?;
+    drop(db);
+    info!(
+        target: "reth::cli",
+        backup = %backup.display(),
+        "Database repaired, {} reclaimed. Remove the backup once the node runs fine",
+        human_bytes(
+            report.allocated_pages.saturating_sub(copy_report.allocated_pages) *
+                report.page_size as usize
+        )
+    );
+    Ok(())
+}
+
+/// Prints the findings of the check.
+fn print_report(report: &CheckReport) {
+    let size = |pages: usize| human_bytes(pages * report.page_size as usize);
+    println!("{:<24} {:>14} {:>12}", "Pages", "Count", "Size");
+    for (name, pages) in [
+        ("allocated", report.allocated_pages),
+        ("databases", report.tree_pages),
+        ("garbage collector", report.gc_pages),
+        ("free", report.free_pages),
+    ] {
+        println!("{name:<24} {pages:>14} {:>12}", size(pages));
+    }
+
+    let orphans = report.orphan_pages();
+    if orphans > 0 {
+        warn!(target: "reth::cli", pages = orphans, "Allocated pages are neither used nor free");
+    } else if orphans < 0 {
+        warn!(target: "reth::cli", pages = -orphans, "Pages are used and listed as free");
+    }
+    for error in &report.freelist_errors {
+        warn!(target: "reth::cli", "Invalid freelist: {error}");
+    }
+    for (name, error) in &report.damaged {
+        let name = if name.is_empty() { "<main>" } else { name.as_str() };
+        warn!(target: "reth::cli", table = name, %error, "Table can not be read");
+    }
+}
+
+/// Returns the path next to the database with the given suffix.
+fn sibling(path: &Path, suffix: &str) -> eyre::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| eyre::eyre!("Invalid database path {}", path.display()))?
+        .to_string_lossy();
+    Ok(path.with_file_name(format!("{name}.{suffix}")))
+}
@@ -0,0 +1,295 @@
+//! Disk usage report of the database, attributed to logical segments.
+use crate::config::PruneConfig;
+use eyre::WrapErr;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use std::fmt;
+
+/// The logical segments of the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Segment {
+    Headers,
+    Bodies,
+    Receipts,
+    State,
+    History,
+    Indices,
+    Other,
+}
+
+impl Segment {
+    /// Returns the segment the table belongs to.
+    pub(crate) fn of_table(table: &str) -> Self {
+        match table {
+            "CanonicalHeaders" | "HeaderTD" | "Headers" => Segment::Headers,
+            "BlockBodies" |
+            "BlockOmmers" |
+            "NonCanonicalTransactions" |
+            "Transactions" |
+            "TxSenders" => Segment::Bodies,
+            "Receipts" | "Logs" => Segment::Receipts,
+            "PlainAccountState" |
+            "PlainStorageState" |
+            "HashedAccount" |
+            "HashedStorage" |
+            "StorageRoots" |
+            "AccountsTrie" |
+            "StoragesTrie" |
+            "Bytecodes" => Segment::State,
+            "AccountHistory" | "StorageHistory" | "AccountChangeSet" | "StorageChangeSet" => {
+                Segment::History
+            }
+            "HeaderNumbers" | "TxHashNumber" | "BlockTransitionIndex" | "TxTransitionIndex" => {
+                Segment::Indices
+            }
+            _ => Segment::Other,
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Segment::Headers => "headers",
+            Segment::Bodies => "bodies",
+            Segment::Receipts => "receipts",
+            Segment::State => "state",
+            Segment::History => "history",
+            Segment::Indices => "indices",
+            Segment::Other => "other",
+        };
+        f.write_str(s)
+    }
+}
+
+/// The data that can be pruned, see [PruneConfig].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PruneTarget {
+    Receipts,
+    History,
+    Senders,
+}
+
+impl PruneTarget {
+    const ALL: [PruneTarget; 3] =
+        [PruneTarget::Receipts, PruneTarget::History, PruneTarget::Senders];
+
+    /// The tables whose entries are removed when pruning the target.
+    fn tables(&self) -> &'static [&'static str] {
+        match self {
+            PruneTarget::Receipts => &["Receipts", "Logs"],
+            PruneTarget::History => {
+                &["AccountHistory", "StorageHistory", "AccountChangeSet", "StorageChangeSet"]
+            }
+            PruneTarget::Senders => &["TxSenders"],
+        }
+    }
+
+    /// The number of recent blocks that are kept according to the config.
+    fn distance(&self, config: &PruneConfig) -> Option<u64> {
+        match self {
+            PruneTarget::Receipts => config.receipts,
+            PruneTarget::History => config.history,
+            PruneTarget::Senders => config.senders,
+        }
+    }
+}
+
+impl fmt::Display for PruneTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            PruneTarget::Receipts => "receipts",
+            PruneTarget::History => "history",
+            PruneTarget::Senders => "senders",
+        };
+        f.write_str(s)
+    }
+}
+
+/// The disk usage of a single table.
+#[derive(Debug, Clone)]
+pub(crate) struct TableUsage {
+    pub(crate) table: &'static str,
+    pub(crate) segment: Segment,
+    pub(crate) entries: usize,
+    pub(crate) size: usize,
+}
+
+/// The disk usage of all tables.
+#[derive(Debug, Clone)]
+pub(crate) struct UsageReport {
+    /// The usage of all tables, ordered by segment.
+    pub(crate) tables: Vec<TableUsage>,
+    /// The highest canonical block number.
+    pub(crate) tip: u64,
+}
+
+impl UsageReport {
+    /// Collects the disk usage of all tables.
+    pub(crate) fn collect(db: &Env<WriteMap>) -> eyre::Result<Self> {
+        db.view(|tx| {
+            let mut tables = Vec::with_capacity(tables::TABLES.len());
+            for table in tables::TABLES.iter().map(|(_, name)| *name) {
+                let table_db = tx.inner.open_db(Some(table)).wrap_err("Could not open db.")?;
+                let stats = tx
+                    .inner
+                    .db_stat(&table_db)
+                    .wrap_err(format!("Could not find table: {table}"))?;
+
+                let page_size = stats.page_size() as usize;
+                let num_pages = stats.leaf_pages() + stats.branch_pages() + stats.overflow_pages();
+                tables.push(TableUsage {
+                    table,
+                    segment: Segment::of_table(table),
+                    entries: stats.entries(),
+                    size: page_size * num_pages,
+                });
+            }
+            tables.sort_by_key(|usage| usage.segment);
+
+            let tip = tx.cursor::<tables::CanonicalHeaders>()?.last()?.map(|(number, _)| number);
+            Ok::<_, eyre::Report>(Self { tables, tip: tip.unwrap_or_default() })
+        })?
+    }
+
+    /// Returns the total size and number of entries per segment.
+    pub(crate) fn segments(&self) -> Vec<(Segment, usize, usize)> {
+        let mut segments: Vec<(Segment, usize, usize)> = Vec::new();
+        for usage in &self.tables {
+            match segments.last_mut() {
+                Some((segment, entries, size)) if *segment == usage.segment => {
+                    *entries += usage.entries;
+                    *size += usage.size;
+                }
+                _ => segments.push((usage.segment, usage.entries, usage.size)),
+            }
+        }
+        segments
+    }
+
+    /// Returns the current size and the estimated size saved for every configured prune target.
+    ///
+    /// Pruned data is assumed to be evenly distributed over all blocks, so pruning everything but
+    /// the last `distance` blocks saves the corresponding share of the tables.
+    pub(crate) fn prune_savings(
+        &self,
+        config: &PruneConfig,
+    ) -> Vec<(PruneTarget, u64, usize, usize)> {
+        PruneTarget::ALL
+            .into_iter()
+            .filter_map(|target| {
+                let distance = target.distance(config)?;
+                let size = self
+                    .tables
+                    .iter()
+                    .filter(|usage| target.tables().contains(&usage.table))
+                    .map(|usage| usage.size)
+                    .sum::<usize>();
+                Some((target, distance, size, estimate_savings(size, self.tip, distance)))
+            })
+            .collect()
+    }
+
+    /// Prints the report, including every table if `detailed` is set.
+    pub(crate) fn print(&self, config: &PruneConfig, detailed: bool) {
+        let total = self.tables.iter().map(|usage| usage.size).sum::<usize>();
+        println!("{:<24} {:>14} {:>12}", "Segment", "Entries", "Size");
+        for (segment, entries, size) in self.segments() {
+            println!("{:<24} {entries:>14} {:>12}", segment.to_string(), human_bytes(size));
+            if detailed {
+                for usage in self.tables.iter().filter(|usage| usage.segment == segment) {
+                    println!(
+                        "  {:<22} {:>14} {:>12}",
+                        usage.table,
+                        usage.entries,
+                        human_bytes(usage.size)
+                    );
+                }
+            }
+        }
+        println!("{:<24} {:>14} {:>12}", "total", "", human_bytes(total));
+
+        let savings = self.prune_savings(config);
+        if savings.is_empty() {
+            println!("\nNo prune settings configured.");
+            return
+        }
+        println!("\nEstimated savings of the prune settings at block #{}:", self.tip);
+        for (target, distance, size, saved) in savings {
+            println!(
+                "{:<24} keep last {distance} blocks, saves {} of {}",
+                target.to_string(),
+                human_bytes(saved),
+                human_bytes(size)
+            );
+        }
+    }
+}
+
+/// Estimates how many bytes of `size` are freed by only keeping the last `distance` of `tip`
+/// blocks.
+fn estimate_savings(size: usize, tip: u64, distance: u64) -> usize {
+    if tip == 0 || distance >= tip {
+        return 0
+    }
+    let pruned = tip - distance;
+    (size as u128 * pruned as u128 / tip as u128) as usize
+}
+
+/// Formats the bytes with a binary unit.
+pub(crate) fn human_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_tables_have_segment() {
+        for (_, table) in tables::TABLES.iter() {
+            let segment = Segment::of_table(table);
+            let is_metadata = matches!(*table, "Config" | "SyncStage");
+            assert_eq!(segment == Segment::Other, is_metadata, "{table}");
+        }
+    }
+
+    #[test]
+    fn prune_targets_are_tables() {
+        let names = tables::TABLES.iter().map(|(_, name)| *name).collect::<Vec<_>>();
+        for target in PruneTarget::ALL {
+            assert!(target.tables().iter().all(|table| names.contains(table)), "{target}");
+        }
+    }
+
+    #[test]
+    fn estimates_savings() {
+        assert_eq!(estimate_savings(1000, 100, 10), 900);
+        assert_eq!(estimate_savings(1000, 100, 100), 0);
+        assert_eq!(estimate_savings(1000, 100, 200), 0);
+        assert_eq!(estimate_savings(1000, 0, 0), 0);
+    }
+
+    #[test]
+    fn formats_bytes() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1536), "1.50 KiB");
+        assert_eq!(human_bytes(3 * 1024 * 1024 * 1024), "3.00 GiB");
+    }
+}
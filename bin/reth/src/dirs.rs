@@ -21,6 +21,13 @@ pub fn database_path() -> Option<PathBuf> {
     data_dir().map(|root| root.join("db"))
 }
 
+/// Returns the path to the secret key of the node's p2p identity.
+///
+/// Refer to [dirs_next::data_dir] for cross-platform behavior.
+pub fn p2p_secret_key_path() -> Option<PathBuf> {
+    data_dir().map(|root| root.join("discovery-secret"))
+}
+
 /// Returns the path to the reth configuration directory.
 ///
 /// Refer to [dirs_next::config_dir] for cross-platform behavior.
@@ -28,6 +35,16 @@ pub fn config_dir() -> Option<PathBuf> {
     dirs_next::config_dir().map(|root| root.join("reth"))
 }
 
+/// Returns the default path of the IPC endpoint, `~/.reth/reth.ipc`.
+///
+/// On Windows the endpoint is the `reth.ipc` named pipe.
+pub fn ipc_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return Some(PathBuf::from(r"\\.\pipe\reth.ipc"))
+    }
+    dirs_next::home_dir().map(|home| home.join(".reth").join("reth.ipc"))
+}
+
 /// A wrapper type that either parses a user-given path for the reth database or defaults to an
 /// OS-specific path.
 #[derive(Clone, Debug)]
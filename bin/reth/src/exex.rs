@@ -0,0 +1,242 @@
+//! Execution extensions (ExExs), tasks launched alongside the node that follow the canonical
+//! chain.
+//!
+//! An ExEx receives every change of the canonical chain as an [ExExNotification] that carries the
+//! blocks, their receipts and the changes of the state, which allows embedding indexers in the
+//! node instead of polling its RPC. Reverted blocks are delivered before the blocks that replace
+//! them.
+use futures::{future::BoxFuture, FutureExt};
+use reth_db::database::Database;
+use reth_provider::{CanonicalBlock, ChainNotification, ChainNotifications, ProviderImpl};
+use reth_tasks::TaskExecutor;
+use std::{future::Future, sync::Arc};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, info};
+
+/// A change of the canonical chain delivered to an ExEx.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExExNotification {
+    /// The blocks were appended to the canonical chain.
+    ChainCommitted {
+        /// The new canonical blocks in ascending order.
+        new: Arc<Vec<CanonicalBlock>>,
+    },
+    /// The blocks were removed from the canonical chain, e.g. because of a reorg.
+    ChainReverted {
+        /// The reverted blocks in ascending order.
+        old: Arc<Vec<CanonicalBlock>>,
+    },
+}
+
+/// Errors of the [ExExNotifications].
+#[derive(Debug, thiserror::Error)]
+pub enum ExExError {
+    /// The ExEx fell behind the node and the oldest notifications were dropped.
+    #[error("The ExEx missed {0} chain notifications")]
+    Lagged(u64),
+    /// The blocks of a notification could not be read.
+    #[error(transparent)]
+    Provider(#[from] reth_interfaces::Error),
+}
+
+/// The changes of the canonical chain an ExEx receives.
+pub struct ExExNotifications<DB: Database> {
+    provider: Arc<ProviderImpl<DB>>,
+    notifications: broadcast::Receiver<ChainNotification>,
+}
+
+// === impl ExExNotifications ===
+
+impl<DB: Database> ExExNotifications<DB> {
+    /// Creates a new stream of the notifications that are sent after this call.
+    pub fn new(provider: Arc<ProviderImpl<DB>>, notifications: &ChainNotifications) -> Self {
+        Self { provider, notifications: notifications.subscribe() }
+    }
+
+    /// Returns the next change of the canonical chain, or `None` once the node stopped.
+    ///
+    /// Fails with [ExExError::Lagged] if the ExEx fell behind, the notifications in between are
+    /// lost. Committed blocks that were reverted before they could be read are skipped.
+    pub async fn recv(&mut self) -> Option<Result<ExExNotification, ExExError>> {
+        loop {
+            let notification = match self.notifications.recv().await {
+                Ok(notification) => notification,
+                Err(RecvError::Lagged(missed)) => return Some(Err(ExExError::Lagged(missed))),
+                Err(RecvError::Closed) => return None,
+            };
+            return match notification {
+                ChainNotification::Committed { range } => {
+                    match self.provider.canonical_blocks(range) {
+                        Ok(new) if new.is_empty() => continue,
+                        Ok(new) => {
+                            Some(Ok(ExExNotification::ChainCommitted { new: Arc::new(new) }))
+                        }
+                        Err(err) => Some(Err(err.into())),
+                    }
+                }
+                ChainNotification::Reverted { blocks } => {
+                    Some(Ok(ExExNotification::ChainReverted { old: blocks }))
+                }
+            }
+        }
+    }
+}
+
+impl<DB: Database> std::fmt::Debug for ExExNotifications<DB> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExExNotifications").finish_non_exhaustive()
+    }
+}
+
+/// Everything an ExEx is launched with.
+pub struct ExExContext<DB: Database> {
+    /// The id the ExEx was installed with.
+    pub id: String,
+    /// The provider of the node's chain and state.
+    pub provider: Arc<ProviderImpl<DB>>,
+    /// The changes of the canonical chain since the launch.
+    pub notifications: ExExNotifications<DB>,
+}
+
+impl<DB: Database> std::fmt::Debug for ExExContext<DB> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExExContext").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+/// Launches an ExEx, the returned future runs until the ExEx is done.
+pub type ExExLauncher<DB> =
+    Box<dyn FnOnce(ExExContext<DB>) -> BoxFuture<'static, eyre::Result<()>> + Send>;
+
+/// The ExExs installed on a node.
+pub struct ExExs<DB: Database> {
+    exexs: Vec<(String, ExExLauncher<DB>)>,
+}
+
+// === impl ExExs ===
+
+impl<DB: Database> ExExs<DB> {
+    /// Creates an empty set of ExExs.
+    pub fn new() -> Self {
+        Self { exexs: Vec::new() }
+    }
+
+    /// Installs an ExEx with the given id, it's launched once the node starts.
+    pub fn install<F, Fut>(mut self, id: impl Into<String>, exex: F) -> Self
+    where
+        F: FnOnce(ExExContext<DB>) -> Fut + Send + 'static,
+        Fut: Future<Output = eyre::Result<()>> + Send + 'static,
+    {
+        self.exexs.push((id.into(), Box::new(move |ctx| exex(ctx).boxed())));
+        self
+    }
+
+    /// Returns the number of installed ExExs.
+    pub fn len(&self) -> usize {
+        self.exexs.len()
+    }
+
+    /// Returns true if no ExEx is installed.
+    pub fn is_empty(&self) -> bool {
+        self.exexs.is_empty()
+    }
+
+    /// Spawns all ExExs with the executor, they receive the notifications sent after this call.
+    ///
+    /// An ExEx that fails or panics is logged and doesn't affect the node.
+    pub fn launch(
+        self,
+        provider: Arc<ProviderImpl<DB>>,
+        notifications: &ChainNotifications,
+        executor: &TaskExecutor,
+    ) where
+        DB: 'static,
+    {
+        for (id, launch) in self.exexs {
+            let ctx = ExExContext {
+                id: id.clone(),
+                provider: provider.clone(),
+                notifications: ExExNotifications::new(provider.clone(), notifications),
+            };
+            let exex = launch(ctx);
+            executor.spawn("exex", async move {
+                info!(target: "reth::exex", %id, "ExEx started");
+                match exex.await {
+                    Ok(()) => info!(target: "reth::exex", %id, "ExEx finished"),
+                    Err(err) => error!(target: "reth::exex", %id, ?err, "ExEx failed"),
+                }
+            });
+        }
+    }
+}
+
+impl<DB: Database> Default for ExExs<DB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<DB: Database> std::fmt::Debug for ExExs<DB> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids = self.exexs.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        f.debug_struct("ExExs").field("exexs", &ids).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        mdbx::{test_utils::create_test_rw_db, WriteMap},
+        models::AccountBeforeTx,
+        tables,
+        transaction::DbTxMut,
+    };
+    use reth_interfaces::test_utils::generators::random_block_range;
+    use reth_primitives::Address;
+    use reth_provider::{chain_notifications, insert_canonical_block};
+    use reth_tasks::TaskManager;
+    use tokio::{runtime::Handle, sync::mpsc};
+
+    #[tokio::test]
+    async fn deliver_chain_changes() {
+        let db = create_test_rw_db::<WriteMap>();
+        let blocks = random_block_range(0..3, Default::default(), 0..1);
+        let address = Address::from_low_u64_be(1);
+        db.update(|tx| {
+            for block in &blocks {
+                insert_canonical_block(tx, block, false).unwrap();
+            }
+            // the changes of block 1 are recorded at its only transition
+            tx.put::<tables::AccountChangeSet>(1, AccountBeforeTx { address, info: None }).unwrap();
+        })
+        .unwrap();
+        let provider = Arc::new(ProviderImpl::new(db));
+        let notifications = chain_notifications(8);
+
+        let tasks = TaskManager::new(Handle::current());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        ExExs::new()
+            .install("test", |mut ctx: ExExContext<_>| async move {
+                while let Some(notification) = ctx.notifications.recv().await {
+                    tx.send(notification?)?;
+                }
+                Ok(())
+            })
+            .launch(provider.clone(), &notifications, &tasks.executor());
+
+        notifications.send(ChainNotification::Committed { range: 1..=2 }).unwrap();
+        let reverted = Arc::new(provider.canonical_blocks(2..=2).unwrap());
+        notifications.send(ChainNotification::Reverted { blocks: reverted.clone() }).unwrap();
+
+        let ExExNotification::ChainCommitted { new } = rx.recv().await.unwrap() else {
+            panic!("expected committed blocks")
+        };
+        let hashes = new.iter().map(|block| block.hash).collect::<Vec<_>>();
+        assert_eq!(hashes, vec![blocks[1].hash(), blocks[2].hash()]);
+        assert_eq!(new[0].state.accounts, vec![(address, None)]);
+        assert!(new[1].state.accounts.is_empty());
+
+        assert_eq!(rx.recv().await.unwrap(), ExExNotification::ChainReverted { old: reverted });
+    }
+}
@@ -0,0 +1,162 @@
+//! Exports canonical blocks as a file of RLP encoded blocks.
+//!
+//! The file has the format that `geth export` writes and `reth import` reads.
+use crate::{db::open_db, dirs::DbPath};
+use clap::Parser;
+use eyre::WrapErr;
+use reth_db::{cursor::DbCursorRO, database::Database, tables, transaction::DbTx};
+use reth_primitives::{Block, BlockNumber};
+use reth_rlp::Encodable;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    ops::RangeInclusive,
+    path::PathBuf,
+};
+use tracing::info;
+
+/// The number of blocks after which the progress is logged.
+const LOG_INTERVAL: u64 = 100_000;
+
+/// Export blocks to a file
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The path to the database folder.
+    ///
+    /// Defaults to the OS-specific data directory:
+    ///
+    /// - Linux: `$XDG_DATA_HOME/reth/db` or `$HOME/.local/share/reth/db`
+    /// - Windows: `{FOLDERID_RoamingAppData}/reth/db`
+    /// - macOS: `$HOME/Library/Application Support/reth/db`
+    #[arg(long, value_name = "PATH", verbatim_doc_comment, default_value_t)]
+    db: DbPath,
+
+    /// The first block to export.
+    #[arg(long, value_name = "NUMBER", default_value_t = 0)]
+    from: BlockNumber,
+
+    /// The last block to export, defaults to the last canonical block.
+    #[arg(long, value_name = "NUMBER")]
+    to: Option<BlockNumber>,
+
+    /// The path of the file to write the RLP encoded blocks to.
+    #[arg(value_name = "FILE")]
+    path: PathBuf,
+}
+
+impl Command {
+    /// Execute `export` command
+    pub async fn execute(&self) -> eyre::Result<()> {
+        let db = open_db(self.db.as_ref(), false)?;
+
+        let file = File::create(&self.path)
+            .wrap_err_with(|| format!("Could not create {}", self.path.display()))?;
+        let mut out = BufWriter::new(file);
+
+        let tx = db.tx()?;
+        let last = tx.cursor::<tables::CanonicalHeaders>()?.last()?.map(|(number, _)| number);
+        let to = match (self.to, last) {
+            (Some(to), Some(last)) if to <= last => to,
+            (None, Some(last)) => last,
+            (to, last) => {
+                eyre::bail!("Block {:?} is not in the database, the last block is {last:?}", to)
+            }
+        };
+        if self.from > to {
+            eyre::bail!("The first block {} is after the last block {to}", self.from)
+        }
+
+        info!(target: "reth::cli", from = self.from, to, "Exporting blocks to {}", self.path.display());
+        let exported = export_blocks(&tx, self.from..=to, &mut out)?;
+        out.flush()?;
+        tx.commit()?;
+
+        info!(target: "reth::cli", exported, "Export finished");
+        Ok(())
+    }
+}
+
+/// Writes the RLP encoding of the canonical blocks in the range to `out`, one after another.
+///
+/// Returns the number of exported blocks.
+pub fn export_blocks<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    range: RangeInclusive<BlockNumber>,
+    out: &mut impl Write,
+) -> eyre::Result<u64> {
+    let mut canonical = tx.cursor::<tables::CanonicalHeaders>()?;
+    let mut transactions = tx.cursor::<tables::Transactions>()?;
+    let mut buf = Vec::new();
+    let mut exported = 0;
+
+    for entry in canonical.walk(*range.start())? {
+        let (number, hash) = entry?;
+        if number > *range.end() {
+            break
+        }
+        let key = (number, hash).into();
+        let header = tx
+            .get::<tables::Headers>(key)?
+            .ok_or_else(|| eyre::eyre!("Header of block {number} not found"))?;
+        let indices = tx
+            .get::<tables::BlockBodies>(key)?
+            .ok_or_else(|| eyre::eyre!("Body of block {number} not found"))?;
+        let ommers = tx.get::<tables::BlockOmmers>(key)?.map(|stored| stored.ommers);
+        let body = transactions
+            .walk(indices.start_tx_id)?
+            .take(indices.tx_count as usize)
+            .map(|entry| entry.map(|(_, transaction)| transaction))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        buf.clear();
+        Block { header, body, ommers: ommers.unwrap_or_default() }.encode(&mut buf);
+        out.write_all(&buf)?;
+
+        exported += 1;
+        if exported % LOG_INTERVAL == 0 {
+            info!(target: "reth::cli", number, "Exported {exported} blocks");
+        }
+    }
+
+    if exported != range.end() - range.start() + 1 {
+        eyre::bail!("Only {exported} blocks of {range:?} are in the database")
+    }
+    Ok(exported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::file_client::BlockFileReader;
+    use reth_db::mdbx::{test_utils::create_test_rw_db, WriteMap};
+    use reth_interfaces::test_utils::generators::random_block_range;
+    use reth_provider::insert_canonical_block;
+
+    #[test]
+    fn export_and_read_blocks() {
+        let db = create_test_rw_db::<WriteMap>();
+        let blocks = random_block_range(0..10, Default::default(), 0..4);
+        db.update(|tx| {
+            for block in &blocks {
+                insert_canonical_block(tx, block, false).unwrap();
+            }
+        })
+        .unwrap();
+
+        let mut file = Vec::new();
+        let tx = db.tx().unwrap();
+        assert_eq!(export_blocks(&tx, 2..=7, &mut file).unwrap(), 6);
+
+        let mut reader = BlockFileReader::new(&file[..]);
+        let exported = reader.next_blocks(10).unwrap();
+        assert_eq!(exported.len(), 6);
+        for (exported, block) in exported.iter().zip(&blocks[2..=7]) {
+            assert_eq!(exported.header.hash_slow(), block.hash());
+            assert_eq!(exported.body, block.body);
+            assert_eq!(exported.ommers.len(), block.ommers.len());
+        }
+
+        // blocks that are not in the database
+        assert!(export_blocks(&tx, 8..=12, &mut Vec::new()).is_err());
+    }
+}
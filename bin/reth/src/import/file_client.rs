@@ -0,0 +1,279 @@
+//! Serves blocks read from a file to the downloaders.
+
+use eyre::WrapErr;
+use reth_eth_wire::BlockBody;
+use reth_interfaces::p2p::{
+    bodies::client::BodiesClient,
+    downloader::DownloadClient,
+    error::PeerRequestResult,
+    headers::client::{BlockHeaders, HeadersClient, HeadersRequest, StatusUpdater},
+};
+use reth_primitives::{
+    Block, BlockHashOrNumber, BlockNumber, Header, PeerId, SealedHeader, H256, U256,
+};
+use reth_rlp::{Decodable, DecodeError};
+use std::{collections::HashMap, io::Read};
+
+/// The number of bytes read from the file at once.
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Reads RLP encoded blocks one after another from a file, as written by e.g. `geth export`.
+#[derive(Debug)]
+pub struct BlockFileReader<R> {
+    reader: R,
+    /// Bytes read from the file that were not decoded yet.
+    buf: Vec<u8>,
+    /// Whether the end of the file was reached.
+    eof: bool,
+}
+
+// === impl BlockFileReader ===
+
+impl<R: Read> BlockFileReader<R> {
+    /// Creates a new reader of the blocks in the given file.
+    pub fn new(reader: R) -> Self {
+        Self { reader, buf: Vec::new(), eof: false }
+    }
+
+    /// Reads the next block, returns `None` at the end of the file.
+    pub fn next_block(&mut self) -> eyre::Result<Option<Block>> {
+        loop {
+            if self.buf.is_empty() && self.eof {
+                return Ok(None)
+            }
+
+            let mut buf = &self.buf[..];
+            match Block::decode(&mut buf) {
+                Ok(block) => {
+                    let consumed = self.buf.len() - buf.len();
+                    self.buf.drain(..consumed);
+                    return Ok(Some(block))
+                }
+                Err(DecodeError::InputTooShort) if !self.eof => self.fill_buf()?,
+                Err(err) => return Err(err).wrap_err("Failed to decode block"),
+            }
+        }
+    }
+
+    /// Reads up to `max` blocks.
+    pub fn next_blocks(&mut self, max: usize) -> eyre::Result<Vec<Block>> {
+        let mut blocks = Vec::new();
+        while blocks.len() < max {
+            match self.next_block()? {
+                Some(block) => blocks.push(block),
+                None => break,
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn fill_buf(&mut self) -> eyre::Result<()> {
+        let len = self.buf.len();
+        self.buf.resize(len + READ_BUFFER_SIZE, 0);
+        let read = self.reader.read(&mut self.buf[len..]).wrap_err("Failed to read blocks")?;
+        self.buf.truncate(len + read);
+        self.eof = read == 0;
+        Ok(())
+    }
+}
+
+/// A client that answers the header and body requests of the downloaders with a contiguous range
+/// of blocks, as if there was a peer with exactly these blocks.
+#[derive(Debug, Default)]
+pub struct FileClient {
+    headers: HashMap<BlockNumber, Header>,
+    numbers: HashMap<H256, BlockNumber>,
+    bodies: HashMap<H256, BlockBody>,
+    /// The last block.
+    tip: Option<SealedHeader>,
+    /// The number of transactions of all blocks.
+    transactions: usize,
+}
+
+// === impl FileClient ===
+
+impl FileClient {
+    /// Creates a new client for the given blocks.
+    ///
+    /// Returns an error if the blocks are not ordered from parent to child.
+    pub fn new(blocks: impl IntoIterator<Item = Block>) -> eyre::Result<Self> {
+        let mut client = Self::default();
+        for Block { header, body, ommers } in blocks {
+            let header = header.seal();
+            if let Some(parent) = &client.tip {
+                if header.parent_hash != parent.hash() || header.number != parent.number + 1 {
+                    eyre::bail!(
+                        "Block #{} ({:?}) is not a child of block #{} ({:?})",
+                        header.number,
+                        header.hash(),
+                        parent.number,
+                        parent.hash()
+                    )
+                }
+            }
+
+            client.transactions += body.len();
+            client.numbers.insert(header.hash(), header.number);
+            client.bodies.insert(header.hash(), BlockBody { transactions: body, ommers });
+            client.headers.insert(header.number, header.as_ref().clone());
+            client.tip = Some(header);
+        }
+        Ok(client)
+    }
+
+    /// Returns the last block.
+    pub fn tip(&self) -> Option<&SealedHeader> {
+        self.tip.as_ref()
+    }
+
+    /// Returns the number of blocks.
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Returns `true` if the client has no blocks.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// Returns the number of transactions of all blocks.
+    pub fn transactions(&self) -> usize {
+        self.transactions
+    }
+
+    /// Returns the gas used by all blocks.
+    pub fn gas_used(&self) -> u64 {
+        self.headers.values().map(|header| header.gas_used).sum()
+    }
+
+    fn headers(&self, request: HeadersRequest) -> Vec<Header> {
+        let HeadersRequest { start, limit, direction } = request;
+        let mut number = match start {
+            BlockHashOrNumber::Hash(hash) => match self.numbers.get(&hash) {
+                Some(number) => *number,
+                None => return Vec::new(),
+            },
+            BlockHashOrNumber::Number(number) => number,
+        };
+
+        // the direction maps to the `reverse` flag of the request, like it is sent to peers
+        let reverse = bool::from(direction);
+        let mut headers = Vec::new();
+        while let Some(header) = self.headers.get(&number) {
+            headers.push(header.clone());
+            if headers.len() as u64 >= limit {
+                break
+            }
+            number = match if reverse { number.checked_sub(1) } else { number.checked_add(1) } {
+                Some(number) => number,
+                None => break,
+            };
+        }
+        headers
+    }
+}
+
+impl DownloadClient for FileClient {
+    fn report_bad_message(&self, _peer_id: PeerId) {
+        // the blocks of the file can not be replaced
+    }
+}
+
+#[async_trait::async_trait]
+impl HeadersClient for FileClient {
+    async fn get_headers(&self, request: HeadersRequest) -> PeerRequestResult<BlockHeaders> {
+        Ok((PeerId::default(), BlockHeaders(self.headers(request))).into())
+    }
+}
+
+#[async_trait::async_trait]
+impl BodiesClient for FileClient {
+    async fn get_block_bodies(&self, hashes: Vec<H256>) -> PeerRequestResult<Vec<BlockBody>> {
+        let bodies = hashes.iter().map_while(|hash| self.bodies.get(hash).cloned()).collect();
+        Ok((PeerId::default(), bodies).into())
+    }
+}
+
+/// A [StatusUpdater] for a pipeline without a network, which has no peers to announce the status
+/// to.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopStatusUpdater;
+
+impl StatusUpdater for NoopStatusUpdater {
+    fn update_status(&self, _height: u64, _hash: H256, _total_difficulty: U256) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::HeadersDirection;
+    use reth_rlp::Encodable;
+
+    /// Returns a chain of empty blocks after genesis.
+    fn blocks(len: u64) -> Vec<Block> {
+        let mut parent = Header::default().seal();
+        let mut blocks = Vec::new();
+        for number in 1..=len {
+            let header = Header {
+                parent_hash: parent.hash(),
+                number,
+                gas_used: 21_000,
+                ..Default::default()
+            };
+            parent = header.clone().seal();
+            blocks.push(Block { header, body: Vec::new(), ommers: Vec::new() });
+        }
+        blocks
+    }
+
+    #[test]
+    fn read_blocks_from_file() {
+        let blocks = blocks(10);
+        let mut file = Vec::new();
+        blocks.iter().for_each(|block| block.encode(&mut file));
+
+        let mut reader = BlockFileReader::new(&file[..]);
+        assert_eq!(reader.next_blocks(4).unwrap(), blocks[..4]);
+        assert_eq!(reader.next_blocks(10).unwrap(), blocks[4..]);
+        assert_eq!(reader.next_block().unwrap(), None);
+
+        // a truncated file is an error instead of the end of the blocks
+        let mut reader = BlockFileReader::new(&file[..file.len() - 1]);
+        assert_eq!(reader.next_blocks(9).unwrap(), blocks[..9]);
+        assert!(reader.next_block().is_err());
+    }
+
+    #[tokio::test]
+    async fn serve_blocks() {
+        let blocks = blocks(10);
+        let client = FileClient::new(blocks.clone()).unwrap();
+        assert_eq!(client.len(), 10);
+        assert_eq!(client.gas_used(), 210_000);
+        let tip = client.tip().unwrap().clone();
+        assert_eq!(tip.number, 10);
+
+        // the headers downloader requests the headers from the tip towards the local head
+        let request = HeadersRequest {
+            start: tip.hash().into(),
+            limit: 4,
+            direction: HeadersDirection::Rising,
+        };
+        let headers = client.get_headers(request).await.unwrap().1 .0;
+        assert_eq!(headers.iter().map(|h| h.number).collect::<Vec<_>>(), vec![10, 9, 8, 7]);
+
+        let request =
+            HeadersRequest { start: 8u64.into(), limit: 4, direction: HeadersDirection::Falling };
+        let headers = client.get_headers(request).await.unwrap().1 .0;
+        assert_eq!(headers.iter().map(|h| h.number).collect::<Vec<_>>(), vec![8, 9, 10]);
+
+        let bodies = client.get_block_bodies(vec![tip.hash(), H256::zero()]).await.unwrap().1;
+        assert_eq!(bodies, vec![BlockBody::default()]);
+    }
+
+    #[test]
+    fn reject_gaps() {
+        let mut blocks = blocks(3);
+        blocks.remove(1);
+        assert!(FileClient::new(blocks).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Imports blocks from a file of RLP encoded blocks.
+//!
+//! The blocks are served to the downloaders of the regular pipeline, so they are validated,
+//! executed and indexed exactly like blocks received from peers.
+use crate::{
+    config::{ConfigArgs, ExecutionConfig},
+    dirs::DbPath,
+    node::{init_db, init_freezer, init_genesis},
+    util::chainspec::{chain_spec_value_parser, ChainSpecification},
+};
+use clap::Parser;
+use eyre::WrapErr;
+use reth_consensus::BeaconConsensus;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTx,
+};
+use reth_downloaders::{bodies, headers};
+use reth_executor::SpecUpgrades;
+use reth_interfaces::consensus::ForkchoiceState;
+use reth_stages::{
+    stages::{
+        bodies::BodyStage,
+        execution::ExecutionStage,
+        freeze::FreezeStage,
+        hashing_account::AccountHashingStage,
+        hashing_storage::StorageHashingStage,
+        headers::HeaderStage,
+        history_index::HistoryIndexStage,
+        log_index::LogIndexStage,
+        merkle::MerkleStage,
+        prune::{PruneStage, ReceiptsPruneMode},
+        sender_recovery::SenderRecoveryStage,
+    },
+    stages_metrics::HeaderMetrics,
+    Pipeline,
+};
+use std::{fs::File, path::PathBuf, sync::Arc, time::Instant};
+use tracing::info;
+
+pub mod file_client;
+
+use file_client::{BlockFileReader, FileClient, NoopStatusUpdater};
+
+/// Import blocks from a file
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The path to the database folder.
+    ///
+    /// Defaults to the OS-specific data directory:
+    ///
+    /// - Linux: `$XDG_DATA_HOME/reth/db` or `$HOME/.local/share/reth/db`
+    /// - Windows: `{FOLDERID_RoamingAppData}/reth/db`
+    /// - macOS: `$HOME/Library/Application Support/reth/db`
+    #[arg(long, value_name = "PATH", verbatim_doc_comment, default_value_t)]
+    db: DbPath,
+
+    #[clap(flatten)]
+    config: ConfigArgs,
+
+    /// The chain of the blocks.
+    ///
+    /// Possible values are either a built-in chain or the path to a chain specification file.
+    ///
+    /// Built-in chains:
+    /// - mainnet
+    /// - goerli
+    /// - sepolia
+    #[arg(
+        long,
+        value_name = "CHAIN_OR_PATH",
+        verbatim_doc_comment,
+        default_value = "mainnet",
+        value_parser = chain_spec_value_parser
+    )]
+    chain: ChainSpecification,
+
+    /// The number of blocks that are read from the file and run through the pipeline at once.
+    #[arg(long, value_name = "BLOCKS", default_value_t = 10_000)]
+    batch_size: usize,
+
+    /// The path to the file of RLP encoded blocks, e.g. written by `geth export`.
+    ///
+    /// Blocks that are already in the database are skipped, so an interrupted import can be
+    /// resumed with the same file.
+    #[arg(value_name = "FILE")]
+    path: PathBuf,
+}
+
+impl Command {
+    /// Execute `import` command
+    pub async fn execute(&self) -> eyre::Result<()> {
+        let config = self.config.load()?;
+
+        info!(target: "reth::cli", "Opening database at {}", &self.db);
+        let db = Arc::new(init_db(&self.db, &config.db)?);
+        init_genesis(db.clone(), self.chain.genesis.clone())?;
+        let freezer = init_freezer(&self.db, &config.freezer)?;
+        let consensus = Arc::new(BeaconConsensus::new(self.chain.consensus.clone()));
+        let executor_config = executor_config(&self.chain.consensus);
+        let receipts_pruning = config.prune.receipts_prune_mode();
+
+        let file = File::open(&self.path)
+            .wrap_err_with(|| format!("Could not open {}", self.path.display()))?;
+        let mut reader = BlockFileReader::new(file);
+
+        let mut head = last_block(db.as_ref())?;
+        let started = Instant::now();
+        let (mut blocks, mut transactions, mut gas) = (0, 0, 0);
+        loop {
+            let batch = reader.next_blocks(self.batch_size)?;
+            if batch.is_empty() {
+                break
+            }
+            let client =
+                Arc::new(FileClient::new(batch.into_iter().filter(|block| block.number > head))?);
+            let tip = match client.tip() {
+                Some(tip) => tip.clone(),
+                None => continue,
+            };
+
+            info!(target: "reth::cli", from = head + 1, to = tip.number, "Importing blocks");
+            consensus.notify_fork_choice_state(ForkchoiceState {
+                head_block_hash: tip.hash(),
+                safe_block_hash: tip.hash(),
+                finalized_block_hash: tip.hash(),
+            })?;
+
+            let batch_started = Instant::now();
+            let mut pipeline = Pipeline::<Env<WriteMap>>::new()
+                .push(HeaderStage {
+                    downloader: headers::linear::LinearDownloadBuilder::default()
+                        .batch_size(config.stages.headers.downloader_batch_size)
+                        .build(consensus.clone(), client.clone()),
+                    consensus: consensus.clone(),
+                    client: client.clone(),
+                    network_handle: NoopStatusUpdater,
+                    commit_threshold: config.stages.headers.commit_threshold,
+                    metrics: HeaderMetrics::default(),
+                })
+                .push(BodyStage {
+                    downloader: Arc::new(
+                        bodies::concurrent::ConcurrentDownloader::new(
+                            client.clone(),
+                            consensus.clone(),
+                        )
+                        .with_batch_size(config.stages.bodies.downloader_batch_size),
+                    ),
+                    consensus: consensus.clone(),
+                    commit_threshold: config.stages.bodies.commit_threshold,
+                })
+                .push(SenderRecoveryStage {
+                    batch_size: config.stages.sender_recovery.batch_size,
+                    commit_threshold: config.stages.sender_recovery.commit_threshold,
+                })
+                .push(execution_stage(
+                    executor_config.clone(),
+                    &config.stages.execution,
+                    receipts_pruning.clone(),
+                ))
+                .push(AccountHashingStage::default())
+                .push(StorageHashingStage::default())
+                .push(MerkleStage::default())
+                .push(LogIndexStage::default())
+                .push(HistoryIndexStage::default())
+                .push(PruneStage {
+                    receipts: receipts_pruning.clone(),
+                    senders: config.prune.senders,
+                    history: config.prune.history,
+                    ..Default::default()
+                });
+            if let (Some(freezer), Some(distance)) = (&freezer, config.freezer.distance) {
+                pipeline =
+                    pipeline.push(FreezeStage { distance, ..FreezeStage::new(freezer.clone()) });
+            }
+            pipeline = pipeline.set_max_block(Some(tip.number));
+            pipeline.run(db.clone()).await?;
+
+            let elapsed = batch_started.elapsed().as_secs_f64();
+            info!(
+                target: "reth::cli",
+                blocks = client.len(),
+                transactions = client.transactions(),
+                "Imported blocks up to #{} in {:.1}s ({:.1} blocks/s, {:.1} Mgas/s)",
+                tip.number,
+                elapsed,
+                client.len() as f64 / elapsed,
+                client.gas_used() as f64 / elapsed / 1_000_000.0,
+            );
+            head = tip.number;
+            blocks += client.len();
+            transactions += client.transactions();
+            gas += client.gas_used();
+        }
+
+        let elapsed = started.elapsed().as_secs_f64();
+        info!(
+            target: "reth::cli",
+            blocks,
+            transactions,
+            "Import finished at #{head} in {:.1}s ({:.1} blocks/s, {:.1} Mgas/s)",
+            elapsed,
+            blocks as f64 / elapsed,
+            gas as f64 / elapsed / 1_000_000.0,
+        );
+        Ok(())
+    }
+}
+
+/// Returns the number of the last canonical block in the database.
+fn last_block<DB: Database>(db: &DB) -> eyre::Result<u64> {
+    let tx = db.tx()?;
+    let last = tx.cursor::<tables::CanonicalHeaders>()?.last()?.map(|(number, _)| number);
+    tx.commit()?;
+    Ok(last.unwrap_or_default())
+}
+
+/// Creates the execution stage, which skips the receipts that are pruned right away.
+pub(crate) fn execution_stage(
+    config: reth_executor::Config,
+    stage_config: &ExecutionConfig,
+    receipts_pruning: Option<ReceiptsPruneMode>,
+) -> ExecutionStage {
+    let stage = ExecutionStage::new(config).with_reorg_buffer(stage_config.reorg_buffer_blocks);
+    let stage = match stage_config.max_reorg_depth {
+        Some(depth) => stage.with_max_reorg_depth(depth),
+        None => stage,
+    };
+    let stage = match stage_config.state_readers() {
+        Some(readers) => stage.with_state_readers(readers),
+        None => stage,
+    };
+    match receipts_pruning {
+        Some(mode) => stage.with_receipts_pruning(mode),
+        None => stage,
+    }
+}
+
+/// Derives the configuration of the executor from the fork blocks of the chain.
+pub(crate) fn executor_config(chain: &reth_consensus::Config) -> reth_executor::Config {
+    reth_executor::Config {
+        chain_id: chain.chain_id.into(),
+        spec_upgrades: SpecUpgrades {
+            frontier: 0,
+            homestead: chain.homestead_block,
+            tangerine_whistle: chain.eip_150_block,
+            spurious_dragon: chain.eip_158_block,
+            byzantium: chain.byzantium_block,
+            petersburg: chain.petersburg_block,
+            istanbul: chain.istanbul_block,
+            berlin: chain.berlin_block,
+            london: chain.london_block,
+            paris: chain.paris_block,
+            shanghai: u64::MAX,
+        },
+        hooks: Default::default(),
+    }
+}
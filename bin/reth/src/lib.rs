@@ -6,11 +6,20 @@
 ))]
 //! Rust Ethereum (reth) binary executable.
 
+pub mod account;
 pub mod cli;
 pub mod config;
+pub mod control;
 pub mod db;
 pub mod dirs;
+pub mod exex;
+pub mod export;
+pub mod import;
 pub mod node;
+pub mod p2p;
 pub mod prometheus_exporter;
+pub mod rpc;
+pub mod test_engine;
 pub mod test_eth_chain;
+pub mod txpool;
 pub mod util;
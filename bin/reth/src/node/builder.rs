@@ -0,0 +1,691 @@
+//! Embeddable reth node.
+//!
+//! The [NodeBuilder] wires up the database, the network, the consensus engine, the RPC servers and
+//! the pipeline of a node. Other projects embed a node by building it from their own
+//! configuration, the hooks of the builder swap or extend components, e.g. to push an execution
+//! stage with a custom executor or to serve additional RPC methods.
+use super::{
+    dev::{DevArgs, DevMiner},
+    init_db, init_freezer, init_genesis, SyncMode,
+};
+use crate::{
+    config::Config,
+    exex::{ExExContext, ExExs},
+    prometheus_exporter,
+    rpc::{self, RpcRegistry, RpcServerArgs, RpcServerHandles},
+    util::chainspec::ChainSpecification,
+};
+use futures::{FutureExt, StreamExt};
+use jsonrpsee::Methods;
+use reth_consensus::{
+    engine::{CanonicalHeadersHandle, EthConsensusEngine},
+    BeaconConsensus,
+};
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+    transaction::DbTxMut,
+};
+use reth_downloaders::{bodies, fallback::FallbackClient, headers, mirror::MirrorClient};
+use reth_eth_wire::capability::Capability;
+use reth_network::{
+    config::{mainnet_nodes, rng_secret_key},
+    error::NetworkError,
+    snap_requests::SnapRequestHandler,
+    NatResolver, NetworkConfig, NetworkHandle, NetworkManager, NodeRecord, PeersConfig,
+};
+use reth_primitives::{BlockNumber, TransactionSignedEcRecovered, H256};
+use reth_provider::{
+    chain_notifications, AccountRangeProvider, BlockProvider, ChainNotifications, HeaderProvider,
+    ProviderImpl, ReceiptProvider, StorageRangeProvider, TrieNodeProvider,
+    DEFAULT_CHAIN_NOTIFICATIONS_CAPACITY,
+};
+use reth_rpc::{CallConfig, EngineApi, EthConfig, UnlockedAccounts};
+use reth_stages::{
+    stages::{
+        bodies::BodyStage, freeze::FreezeStage, hashing_account::AccountHashingStage,
+        hashing_storage::StorageHashingStage, headers::HeaderStage,
+        history_index::HistoryIndexStage, log_index::LogIndexStage, merkle::MerkleStage,
+        prune::PruneStage, sender_recovery::SenderRecoveryStage, snap::SnapSyncStage,
+    },
+    stages_metrics::HeaderMetrics,
+    Pipeline,
+};
+use reth_tasks::{shutdown::Shutdown, TaskExecutor, TaskManager};
+use reth_transaction_pool::{EthTransactionValidator, GasPriceOrdering, Pool, PoolConfig};
+use secp256k1::SecretKey;
+use std::{
+    future::Future,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
+use tokio::{runtime::Handle, sync::mpsc::unbounded_channel};
+use tracing::{info, warn};
+
+/// The database of a node.
+pub type NodeDb = Env<WriteMap>;
+
+/// The transaction pool of a node.
+pub type NodePool = Pool<
+    EthTransactionValidator<ProviderImpl<NodeDb>>,
+    GasPriceOrdering<TransactionSignedEcRecovered>,
+>;
+
+/// Customizes the pipeline of a node after the default stages were added.
+type PipelineHook = Box<dyn FnOnce(Pipeline<NodeDb>) -> Pipeline<NodeDb> + Send>;
+
+/// Builds and launches a node.
+pub struct NodeBuilder {
+    db_path: PathBuf,
+    chain: ChainSpecification,
+    config: Config,
+    rpc: RpcServerArgs,
+    metrics: Option<SocketAddr>,
+    trusted_peers: Vec<NodeRecord>,
+    nat: NatResolver,
+    p2p_secret_key: Option<SecretKey>,
+    max_block: Option<BlockNumber>,
+    halt_before: Option<(BlockNumber, usize)>,
+    sync_mode: SyncMode,
+    serve_snap: bool,
+    executor_config: Option<reth_executor::Config>,
+    dev: DevArgs,
+    unlocked_accounts: Vec<SecretKey>,
+    rpc_methods: Methods,
+    pipeline_hooks: Vec<PipelineHook>,
+    exexs: ExExs<NodeDb>,
+    shutdown: Shutdown,
+}
+
+// === impl NodeBuilder ===
+
+impl NodeBuilder {
+    /// Creates a new builder of a node of the chain that stores its database at the given path.
+    ///
+    /// The RPC servers use the defaults of the `reth node` flags.
+    pub fn new(db_path: impl Into<PathBuf>, chain: ChainSpecification, config: Config) -> Self {
+        Self {
+            db_path: db_path.into(),
+            chain,
+            config,
+            rpc: RpcServerArgs::default(),
+            metrics: None,
+            trusted_peers: Vec::new(),
+            nat: NatResolver::default(),
+            p2p_secret_key: None,
+            max_block: None,
+            halt_before: None,
+            sync_mode: SyncMode::default(),
+            serve_snap: false,
+            executor_config: None,
+            dev: DevArgs::default(),
+            unlocked_accounts: Vec::new(),
+            rpc_methods: Methods::new(),
+            pipeline_hooks: Vec::new(),
+            exexs: ExExs::new(),
+            shutdown: Shutdown::default(),
+        }
+    }
+
+    /// Sets the settings of the RPC servers.
+    pub fn rpc(mut self, rpc: RpcServerArgs) -> Self {
+        self.rpc = rpc;
+        self
+    }
+
+    /// Serves the Prometheus metrics at the given address.
+    pub fn metrics(mut self, addr: Option<SocketAddr>) -> Self {
+        self.metrics = addr;
+        self
+    }
+
+    /// Sets the peers that are never disconnected.
+    pub fn trusted_peers(mut self, peers: Vec<NodeRecord>) -> Self {
+        self.trusted_peers = peers;
+        self
+    }
+
+    /// Sets how the external IP advertised to other nodes is resolved.
+    pub fn nat(mut self, nat: NatResolver) -> Self {
+        self.nat = nat;
+        self
+    }
+
+    /// Sets the secret key the node's p2p identity is derived from.
+    ///
+    /// Without a key, the node uses a random identity.
+    pub fn p2p_secret_key(mut self, secret_key: SecretKey) -> Self {
+        self.p2p_secret_key = Some(secret_key);
+        self
+    }
+
+    /// Stops the pipeline once all stages reached the given block.
+    pub fn max_block(mut self, block: Option<BlockNumber>) -> Self {
+        self.max_block = block;
+        self
+    }
+
+    /// Halts the execution before the transaction with the given index of the block and stops
+    /// the pipeline, see
+    /// [ExecutionStage::with_halt_before](reth_stages::stages::execution::ExecutionStage::with_halt_before).
+    ///
+    /// This takes precedence over the [max block](Self::max_block).
+    pub fn halt_before(mut self, halt: Option<(BlockNumber, usize)>) -> Self {
+        self.halt_before = halt;
+        self
+    }
+
+    /// Sets how the state is synced.
+    ///
+    /// In full mode, the execution, hashing, merkle, log index and prune stages are pushed after
+    /// the sender recovery stage, followed by the freeze stage if a freezer distance is
+    /// configured. In snap mode, a [SnapSyncStage] is pushed instead.
+    pub fn sync_mode(mut self, mode: SyncMode) -> Self {
+        self.sync_mode = mode;
+        self
+    }
+
+    /// Serves the state to peers that snap sync.
+    ///
+    /// The proofs are read from the tries the merkle stage stores, see
+    /// [SnapRequestHandler].
+    pub fn serve_snap(mut self, serve: bool) -> Self {
+        self.serve_snap = serve;
+        self
+    }
+
+    /// Sets the configuration of the executor of the execution stage, the RPC calls and the dev
+    /// miner, e.g. to execute blocks with custom hooks.
+    ///
+    /// Without a configuration, it's derived from the fork blocks of the chain.
+    pub fn executor_config(mut self, config: reth_executor::Config) -> Self {
+        self.executor_config = Some(config);
+        self
+    }
+
+    /// Sets the dev mode, which mines the transactions of the pool instead of syncing and
+    /// disables networking.
+    pub fn dev(mut self, dev: DevArgs) -> Self {
+        self.dev = dev;
+        self
+    }
+
+    /// Sets the keys of the accounts that sign the requests of `eth_sign` and
+    /// `eth_signTransaction`.
+    pub fn unlocked_accounts(mut self, keys: Vec<SecretKey>) -> Self {
+        self.unlocked_accounts = keys;
+        self
+    }
+
+    /// Serves the methods on the HTTP, WebSocket and IPC servers along with the configured
+    /// namespaces.
+    ///
+    /// Fails if a method is registered already.
+    pub fn extend_rpc_methods(mut self, methods: impl Into<Methods>) -> eyre::Result<Self> {
+        self.rpc_methods.merge(methods)?;
+        Ok(self)
+    }
+
+    /// Customizes the pipeline once the stages of the sync mode were added, e.g. to push a stage
+    /// that indexes the synced blocks.
+    ///
+    /// Hooks are applied in the order they were added.
+    pub fn on_pipeline<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(Pipeline<NodeDb>) -> Pipeline<NodeDb> + Send + 'static,
+    {
+        self.pipeline_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Stops the node once the shutdown is requested.
+    ///
+    /// The pipeline stops after the progress of the running stage was committed, and the network
+    /// and the consensus engine stop right away.
+    pub fn shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Installs an ExEx that is launched along with the node, see [ExExs::install].
+    pub fn install_exex<F, Fut>(mut self, id: impl Into<String>, exex: F) -> Self
+    where
+        F: FnOnce(ExExContext<NodeDb>) -> Fut + Send + 'static,
+        Fut: Future<Output = eyre::Result<()>> + Send + 'static,
+    {
+        self.exexs = self.exexs.install(id, exex);
+        self
+    }
+
+    /// Opens the database, starts the network, the consensus engine, the RPC servers and the
+    /// ExExs, and returns the node with its pipeline ready to run.
+    pub async fn launch(self) -> eyre::Result<Node> {
+        let Self {
+            db_path,
+            chain,
+            config,
+            rpc,
+            metrics,
+            trusted_peers,
+            nat,
+            p2p_secret_key,
+            max_block,
+            halt_before,
+            sync_mode,
+            serve_snap,
+            executor_config,
+            dev,
+            unlocked_accounts,
+            rpc_methods,
+            pipeline_hooks,
+            exexs,
+            shutdown,
+        } = self;
+
+        let tasks = TaskManager::new(Handle::current());
+        let executor = tasks.executor();
+
+        info!("Opening database at {}", db_path.display());
+        let db = Arc::new(init_db(&db_path, &config.db)?);
+        info!("Database open");
+
+        if let Some(listen_addr) = metrics {
+            info!("Starting metrics endpoint at {}", listen_addr);
+            prometheus_exporter::initialize(listen_addr, &executor)?;
+            prometheus_exporter::describe();
+            prometheus_exporter::spawn_db_metrics(db.clone(), &executor);
+        }
+
+        let chain_id = chain.consensus.chain_id;
+        let executor_config =
+            executor_config.unwrap_or_else(|| crate::import::executor_config(&chain.consensus));
+        let consensus = Arc::new(BeaconConsensus::new(chain.consensus.clone()));
+        let genesis_hash = init_genesis(db.clone(), chain.genesis.clone())?;
+        let freezer = init_freezer(&db_path, &config.freezer)?;
+        let provider = Arc::new(match &freezer {
+            Some(freezer) => ProviderImpl::new(db.clone()).with_freezer(freezer.clone()),
+            None => ProviderImpl::new(db.clone()),
+        });
+
+        let mut validator = EthTransactionValidator::new(provider.clone(), chain_id);
+        if dev.dev {
+            // the dev chain never changes its gas limit
+            validator = validator.with_block_gas_limit(chain.genesis.gas_limit);
+        }
+        let pool = Pool::new(
+            Arc::new(validator),
+            Arc::new(GasPriceOrdering::default()),
+            PoolConfig::default(),
+        );
+
+        let network = if dev.dev {
+            info!("Dev mode, networking is disabled");
+            // the network only serves the local node, no peers are ever connected
+            let peers_config = PeersConfig::default().with_max_inbound(0).with_max_outbound(0);
+            let local = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+            let config = NetworkConfig::builder(provider.clone(), rng_secret_key())
+                .peer_config(peers_config)
+                .listener_addr(local)
+                .discovery_addr(local)
+                .genesis_hash(genesis_hash)
+                .chain_id(chain_id)
+                .executor(executor.clone())
+                .build();
+            start_network(config, pool.clone(), false, false, &executor, shutdown.clone()).await?
+        } else {
+            info!("Connecting to p2p");
+            let peers_config = PeersConfig::default()
+                .with_trusted_nodes(trusted_peers.into_iter().collect())
+                .with_max_inbound(config.peers.max_inbound)
+                .with_max_outbound(config.peers.max_outbound);
+            let secret_key = p2p_secret_key.unwrap_or_else(rng_secret_key);
+            start_network(
+                network_config(
+                    provider.clone(),
+                    secret_key,
+                    chain_id,
+                    genesis_hash,
+                    peers_config,
+                    nat,
+                    executor.clone(),
+                ),
+                pool.clone(),
+                serve_snap,
+                sync_mode == SyncMode::Snap,
+                &executor,
+                shutdown.clone(),
+            )
+            .await?
+        };
+
+        let (engine_tx, engine_rx) = unbounded_channel();
+        let (unwind_tx, unwind_rx) = unbounded_channel();
+        let consensus_engine =
+            EthConsensusEngine::new(chain.consensus.clone(), provider.clone(), engine_rx)
+                .with_pipeline(consensus.clone(), unwind_tx);
+        executor.spawn_critical(
+            "forkchoice recorder",
+            shutdown
+                .clone()
+                .until(record_forkchoice(db.clone(), consensus_engine.canonical_headers()))
+                .map(drop),
+        );
+        executor
+            .spawn_critical("consensus engine", shutdown.clone().until(consensus_engine).map(drop));
+        let chain_notifications = chain_notifications(DEFAULT_CHAIN_NOTIFICATIONS_CAPACITY);
+        if !exexs.is_empty() {
+            info!("Launching {} ExExs", exexs.len());
+            exexs.launch(provider.clone(), &chain_notifications, &executor);
+        }
+        let registry = RpcRegistry::new(
+            provider.clone(),
+            pool.clone(),
+            network.clone(),
+            chain_notifications.clone(),
+            executor.clone(),
+        )
+        .with_eth_config(EthConfig {
+            // calls and the chain id of `eth_chainId` and `net_version` follow the chain
+            call: CallConfig { executor: executor_config.clone(), ..Default::default() },
+            accounts: UnlockedAccounts::new(chain_id, unlocked_accounts),
+            ..rpc.eth_config()?
+        })
+        .with_extra_methods(rpc_methods);
+        let engine = EngineApi::new(engine_tx, chain.consensus.clone());
+        let rpc = rpc::start_servers(&rpc, &config.rpc, &registry, engine, &executor).await?;
+
+        // TODO: Are most of these Arcs unnecessary? For example, fetch client is completely
+        // cloneable on its own
+        let fetch_client = Arc::new(FallbackClient::new(
+            network.fetch_client().await?,
+            (!config.mirrors.urls.is_empty())
+                .then(|| MirrorClient::new(config.mirrors.urls.clone())),
+        ));
+        let pipeline = Pipeline::new()
+            .push(HeaderStage {
+                downloader: headers::linear::LinearDownloadBuilder::default()
+                    .batch_size(config.stages.headers.downloader_batch_size)
+                    .retries(config.stages.headers.downloader_retries)
+                    .build(consensus.clone(), fetch_client.clone()),
+                consensus: consensus.clone(),
+                client: fetch_client.clone(),
+                network_handle: network.clone(),
+                commit_threshold: config.stages.headers.commit_threshold,
+                metrics: HeaderMetrics::default(),
+            })
+            .push(BodyStage {
+                downloader: Arc::new(
+                    bodies::concurrent::ConcurrentDownloader::new(
+                        fetch_client.clone(),
+                        consensus.clone(),
+                    )
+                    .with_batch_size(config.stages.bodies.downloader_batch_size)
+                    .with_retries(config.stages.bodies.downloader_retries)
+                    .with_concurrency(config.stages.bodies.downloader_concurrency),
+                ),
+                consensus: consensus.clone(),
+                commit_threshold: config.stages.bodies.commit_threshold,
+            })
+            .push(SenderRecoveryStage {
+                batch_size: config.stages.sender_recovery.batch_size,
+                commit_threshold: config.stages.sender_recovery.commit_threshold,
+            });
+        let receipts_pruning = config.prune.receipts_prune_mode();
+        let execution = crate::import::execution_stage(
+            executor_config.clone(),
+            &config.stages.execution,
+            receipts_pruning.clone(),
+        );
+        let execution = match halt_before {
+            Some((block, index)) => execution.with_halt_before(block, index),
+            None => execution,
+        };
+        let pipeline = match sync_mode {
+            SyncMode::Full => pipeline
+                .push(execution)
+                .push(AccountHashingStage::default())
+                .push(StorageHashingStage::default())
+                .push(MerkleStage::default())
+                .push(LogIndexStage::default())
+                .push(HistoryIndexStage::default())
+                .push(PruneStage {
+                    receipts: receipts_pruning,
+                    senders: config.prune.senders,
+                    history: config.prune.history,
+                    ..Default::default()
+                }),
+            SyncMode::Snap => pipeline.push(SnapSyncStage::new(Arc::new(network.clone()))),
+        };
+        let pipeline = match (freezer, config.freezer.distance) {
+            (Some(freezer), Some(distance)) if sync_mode == SyncMode::Full => {
+                pipeline.push(FreezeStage { distance, ..FreezeStage::new(freezer) })
+            }
+            _ => pipeline,
+        };
+        let pipeline = pipeline_hooks
+            .into_iter()
+            .fold(pipeline, |pipeline, hook| hook(pipeline))
+            .set_max_block(max_block)
+            .set_halt_block(halt_before.map(|(block, _)| block))
+            .set_unwind_requests(unwind_rx)
+            .set_chain_notifications(chain_notifications.clone())
+            .set_shutdown(shutdown.clone());
+
+        let miner = if dev.dev {
+            let head = provider.chain_info()?.best_hash;
+            let head = provider
+                .header(&head)?
+                .ok_or_else(|| eyre::eyre!("Missing header of the head {head}"))?
+                .seal();
+            Some(DevMiner::new(db.clone(), executor_config, pool.clone(), head, dev.block_time()))
+        } else {
+            None
+        };
+
+        Ok(Node {
+            chain_id,
+            db,
+            provider,
+            consensus,
+            network,
+            pool,
+            chain_notifications,
+            rpc,
+            pipeline,
+            miner,
+            executor,
+            tasks,
+            shutdown,
+        })
+    }
+}
+
+impl std::fmt::Debug for NodeBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeBuilder")
+            .field("db_path", &self.db_path)
+            .field("chain_id", &self.chain.consensus.chain_id)
+            .field("exexs", &self.exexs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A launched node.
+///
+/// The network, the consensus engine and the RPC servers are running, the node syncs once the
+/// pipeline runs, see [Node::run]. In dev mode the node mines the transactions of its pool
+/// instead.
+///
+/// The tasks of the node are spawned with its [TaskExecutor], a panic of a critical task stops
+/// the node with an error.
+pub struct Node {
+    /// The id of the chain.
+    pub chain_id: u64,
+    /// The database.
+    pub db: Arc<NodeDb>,
+    /// The provider of the chain and state.
+    pub provider: Arc<ProviderImpl<NodeDb>>,
+    /// The consensus, which also receives the forkchoice updates the pipeline syncs to.
+    pub consensus: Arc<BeaconConsensus>,
+    /// The handle of the network.
+    pub network: NetworkHandle,
+    /// The transaction pool.
+    pub pool: NodePool,
+    /// The notifications about changes of the canonical chain.
+    pub chain_notifications: ChainNotifications,
+    /// The handles of the RPC servers, which are stopped once they're dropped.
+    pub rpc: RpcServerHandles,
+    /// The pipeline that syncs the chain.
+    pub pipeline: Pipeline<NodeDb>,
+    /// The miner of the dev mode.
+    miner: Option<DevMiner<NodeDb, NodePool>>,
+    /// The executor of the tasks of the node.
+    pub executor: TaskExecutor,
+    /// Notified about panics of the critical tasks.
+    tasks: TaskManager,
+    /// The listener of the shutdown of the node.
+    shutdown: Shutdown,
+}
+
+// === impl Node ===
+
+impl Node {
+    /// Runs the pipeline until it reaches the maximum block, if any, or the shutdown is
+    /// requested.
+    ///
+    /// In dev mode, the pool is mined instead until the node is stopped. The RPC servers are
+    /// stopped and the database is closed once the pipeline is done.
+    ///
+    /// Fails if a critical task of the node panics.
+    pub async fn run(self) -> eyre::Result<()> {
+        let Self { db, mut pipeline, miner, mut tasks, shutdown, .. } = self;
+        let run = async move {
+            if let Some(miner) = miner {
+                info!("Starting dev miner");
+                return shutdown.until(miner.run()).await.unwrap_or(Ok(()))
+            }
+            info!("Starting pipeline");
+            pipeline.run(db).await?;
+            Ok(())
+        };
+        tokio::select! {
+            res = run => res,
+            Some(err) = tasks.next() => Err(err.into()),
+        }
+    }
+}
+
+impl std::fmt::Debug for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Node")
+            .field("chain_id", &self.chain_id)
+            .field("rpc", &self.rpc)
+            .field("pipeline", &self.pipeline)
+            .field("miner", &self.miner)
+            .finish_non_exhaustive()
+    }
+}
+
+// TODO: This should be based on some external config
+fn network_config<DB: Database>(
+    provider: Arc<ProviderImpl<DB>>,
+    secret_key: SecretKey,
+    chain_id: u64,
+    genesis_hash: H256,
+    peers_config: PeersConfig,
+    nat: NatResolver,
+    executor: TaskExecutor,
+) -> NetworkConfig<ProviderImpl<DB>> {
+    NetworkConfig::builder(provider, secret_key)
+        .boot_nodes(mainnet_nodes())
+        .peer_config(peers_config)
+        .genesis_hash(genesis_hash)
+        .chain_id(chain_id)
+        .external_ip_resolver(nat)
+        .executor(executor)
+        .build()
+}
+
+/// Records the finalized and safe blocks of the forkchoice updates in the database, see
+/// [`FINALIZED_BLOCK_KEY`](tables::FINALIZED_BLOCK_KEY), which the RPC reads them from.
+async fn record_forkchoice<DB: Database>(db: Arc<DB>, headers: CanonicalHeadersHandle) {
+    let finalized =
+        headers.subscribe_finalized().map(|header| (tables::FINALIZED_BLOCK_KEY, header.number));
+    let safe = headers.subscribe_safe().map(|header| (tables::SAFE_BLOCK_KEY, header.number));
+    let mut updates = futures::stream::select(finalized, safe);
+    while let Some((key, number)) = updates.next().await {
+        let updated =
+            db.update(|tx| tx.put::<tables::Config>(key.to_vec(), number.to_be_bytes().to_vec()));
+        if let Err(err) = updated.and_then(|updated| updated) {
+            warn!(target: "reth::cli", ?err, number, "Failed to record forkchoice block");
+        }
+    }
+}
+
+/// Starts the networking stack given a [NetworkConfig] and returns a handle to the network.
+///
+/// The transactions of the pool are exchanged with the peers, and the state is served to peers
+/// that snap sync if `serve_snap` is set. `snap/1` is only advertised to peers if the state is
+/// served or if the node snap syncs itself. The tasks of the network are critical tasks of the
+/// node, they stop once the shutdown is requested.
+async fn start_network<C>(
+    mut config: NetworkConfig<C>,
+    pool: NodePool,
+    serve_snap: bool,
+    snap_sync: bool,
+    executor: &TaskExecutor,
+    shutdown: Shutdown,
+) -> Result<NetworkHandle, NetworkError>
+where
+    C: BlockProvider
+        + HeaderProvider
+        + ReceiptProvider
+        + AccountRangeProvider
+        + StorageRangeProvider
+        + TrieNodeProvider
+        + 'static,
+{
+    if serve_snap || snap_sync {
+        config.hello_message.capabilities.push(Capability::new("snap".into(), 1));
+    }
+
+    let client = config.client.clone();
+    let (handle, mut network, txpool, eth) = NetworkManager::builder(config)
+        .await?
+        .transactions(pool)
+        .request_handler(client.clone())
+        .split_with_handle();
+
+    if serve_snap {
+        let (tx, rx) = unbounded_channel();
+        network.set_snap_request_handler(tx);
+        let snap = SnapRequestHandler::new(client, handle.peers_handle().clone(), rx);
+        executor.spawn_critical("snap requests", shutdown.clone().until(snap).map(drop));
+    }
+
+    executor.spawn_critical("network", shutdown.clone().until(network).map(drop));
+    executor.spawn_critical("transactions", shutdown.clone().until(txpool).map(drop));
+    executor.spawn_critical("eth requests", shutdown.until(eth).map(drop));
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::RpcModule;
+
+    #[test]
+    fn extend_rpc_methods() {
+        let mut module = RpcModule::new(());
+        module.register_method("custom_hello", |_, _| Ok("hello")).unwrap();
+
+        let builder = NodeBuilder::new("db", ChainSpecification::default(), Config::default())
+            .extend_rpc_methods(module.clone())
+            .unwrap();
+        assert!(builder.rpc_methods.method("custom_hello").is_some());
+        // methods can't be overridden
+        assert!(builder.extend_rpc_methods(module).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Dev mode of the node.
+//!
+//! A dev node runs a single-node chain from the dev genesis without networking. The [DevMiner]
+//! mines a block whenever a transaction enters the pool, or on a fixed interval, which makes the
+//! node usable for local testing like anvil.
+use clap::Args;
+use reth_consensus::{
+    config::EIP1559_INITIAL_BASE_FEE, verification::calculate_next_block_base_fee,
+};
+use reth_db::database::Database;
+use reth_executor::builder::BlockAttributes;
+use reth_primitives::{Address, IntoRecoveredTransaction, SealedHeader, U256};
+use reth_stages::{
+    sequencer::{SequencedBlock, SequencerDriver},
+    StageError,
+};
+use reth_transaction_pool::{
+    BestTransactions, OnNewBlockEvent, PoolTransaction, StateDiff, TransactionPool,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Parameters of the dev mode.
+#[derive(Debug, Clone, Default, Args)]
+pub struct DevArgs {
+    /// Start a single-node development chain.
+    ///
+    /// The chain starts from the dev genesis, which funds the first five accounts of the
+    /// `test test test test test test test test test test test junk` mnemonic with 10000 ETH
+    /// each. A block is mined as soon as a transaction enters the pool and networking is
+    /// disabled.
+    #[arg(long)]
+    pub dev: bool,
+
+    /// Mine a block every given number of seconds instead of for every transaction.
+    #[arg(long = "dev.block-time", value_name = "SECONDS", requires = "dev")]
+    pub block_time: Option<u64>,
+}
+
+// === impl DevArgs ===
+
+impl DevArgs {
+    /// Returns the interval of mined blocks, `None` if a block is mined for every transaction.
+    pub fn block_time(&self) -> Option<Duration> {
+        self.block_time.map(Duration::from_secs)
+    }
+}
+
+/// Mines the transactions of the pool into blocks on top of the canonical head.
+///
+/// Blocks are produced with the [SequencerDriver], so they are written like synced blocks.
+pub struct DevMiner<DB, Pool> {
+    driver: SequencerDriver<DB>,
+    pool: Pool,
+    block_time: Option<Duration>,
+    head: SealedHeader,
+}
+
+// === impl DevMiner ===
+
+impl<DB, Pool> DevMiner<DB, Pool>
+where
+    DB: Database,
+    Pool: TransactionPool,
+    Pool::Transaction: IntoRecoveredTransaction,
+{
+    /// Creates a new miner of blocks on top of the head, mining for every transaction if no
+    /// block time is set.
+    pub fn new(
+        db: Arc<DB>,
+        config: reth_executor::Config,
+        pool: Pool,
+        head: SealedHeader,
+        block_time: Option<Duration>,
+    ) -> Self {
+        // the blocks are produced directly, not from a feed
+        let (_, feed) = mpsc::channel(1);
+        Self { driver: SequencerDriver::new(db, config, feed), pool, block_time, head }
+    }
+
+    /// Mines blocks until the pool stops notifying about new transactions.
+    pub async fn run(mut self) -> eyre::Result<()> {
+        match self.block_time {
+            Some(block_time) => {
+                let mut interval = tokio::time::interval(block_time);
+                // the first tick completes immediately
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    self.mine()?;
+                }
+            }
+            None => {
+                let mut pending = self.pool.pending_transactions_listener();
+                while pending.recv().await.is_some() {
+                    // all pending transactions are mined at once, later notifications of the
+                    // same batch find an empty pool
+                    if self.pool.best_transactions().next().is_some() {
+                        self.mine()?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Mines a block with the best transactions of the pool that fit into the gas limit of the
+    /// block.
+    ///
+    /// Returns `None` if the block could not be executed, its transactions are removed from the
+    /// pool.
+    pub fn mine(&mut self) -> eyre::Result<Option<SealedHeader>> {
+        let gas_limit = self.head.gas_limit;
+        let base_fee = match self.head.base_fee_per_gas {
+            Some(base_fee) => {
+                calculate_next_block_base_fee(self.head.gas_used, self.head.gas_limit, base_fee)
+            }
+            None => EIP1559_INITIAL_BASE_FEE,
+        };
+
+        let mut best = self.pool.best_transactions();
+        let mut transactions = Vec::new();
+        let mut hashes = Vec::new();
+        let mut cumulative_gas = 0;
+        while let Some(tx) = best.next() {
+            let fits = cumulative_gas + tx.gas_limit() <= gas_limit;
+            let pays_base_fee = tx
+                .transaction
+                .max_fee_per_gas()
+                .unwrap_or_else(|| tx.transaction.effective_gas_price()) >=
+                U256::from(base_fee);
+            if !fits || !pays_base_fee {
+                // dependent transactions of the sender can't be included either
+                best.mark_invalid(&tx);
+                continue
+            }
+            cumulative_gas += tx.gas_limit();
+            hashes.push(*tx.hash());
+            transactions.push(tx.transaction.to_recovered_transaction());
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let attributes = BlockAttributes {
+            timestamp: now.max(self.head.timestamp + 1),
+            beneficiary: Address::zero(),
+            gas_limit,
+            base_fee_per_gas: Some(base_fee),
+            ..Default::default()
+        };
+
+        match self.driver.produce(SequencedBlock { attributes, transactions }) {
+            Ok(header) => {
+                info!(target: "reth::dev", number = header.number, txs = hashes.len(), "Mined block");
+                self.pool.on_new_block(OnNewBlockEvent {
+                    hash: header.hash(),
+                    pending_block_base_fee: U256::from(calculate_next_block_base_fee(
+                        header.gas_used,
+                        header.gas_limit,
+                        base_fee,
+                    )),
+                    state_changes: StateDiff::default(),
+                    mined_transactions: hashes,
+                });
+                self.head = header.clone();
+                Ok(Some(header))
+            }
+            Err(StageError::ExecutionError { block, error }) => {
+                warn!(target: "reth::dev", block, ?error, "Failed to mine block, dropping its transactions");
+                self.pool.remove_invalid(hashes);
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl<DB, Pool> std::fmt::Debug for DevMiner<DB, Pool> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DevMiner")
+            .field("block_time", &self.block_time)
+            .field("head", &self.head.number)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{node::init_genesis, util::chainspec::chain_spec_value_parser};
+    use reth_db::mdbx::{test_utils::create_test_rw_db, WriteMap};
+    use reth_interfaces::test_utils::generators::sign_message;
+    use reth_primitives::{
+        Transaction, TransactionKind, TransactionSigned, TransactionSignedEcRecovered, TxEip1559,
+        H256,
+    };
+    use reth_provider::ProviderImpl;
+    use reth_transaction_pool::{
+        EthTransactionValidator, GasPriceOrdering, Pool, PoolConfig, TransactionOrigin,
+    };
+    use std::str::FromStr;
+
+    /// Signs a transfer from the first funded account of the dev genesis.
+    fn transfer(nonce: u64) -> TransactionSignedEcRecovered {
+        let secret =
+            H256::from_str("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+                .unwrap();
+        let transaction = Transaction::Eip1559(TxEip1559 {
+            chain_id: 1337,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1,
+            to: TransactionKind::Call(Address::repeat_byte(1)),
+            value: 1,
+            ..Default::default()
+        });
+        let signature = sign_message(secret, transaction.signature_hash()).unwrap();
+        TransactionSigned::from_transaction_and_signature(transaction, signature)
+            .into_ecrecovered()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn mine_pool_transactions() {
+        let chain = chain_spec_value_parser("dev").unwrap();
+        let db = create_test_rw_db::<WriteMap>();
+        init_genesis(db.clone(), chain.genesis.clone()).unwrap();
+        let head = reth_primitives::Header::from(chain.genesis.clone()).seal();
+
+        let provider = Arc::new(ProviderImpl::new(db.clone()));
+        let pool = Pool::new(
+            Arc::new(EthTransactionValidator::new(provider, 1337)),
+            Arc::new(GasPriceOrdering::default()),
+            PoolConfig::default(),
+        );
+        let mut miner = DevMiner::new(
+            db,
+            crate::import::executor_config(&chain.consensus),
+            pool.clone(),
+            head,
+            None,
+        );
+
+        pool.add_transaction(TransactionOrigin::Local, transfer(0)).await.unwrap();
+        pool.add_transaction(TransactionOrigin::Local, transfer(1)).await.unwrap();
+
+        let header = miner.mine().unwrap().unwrap();
+        assert_eq!(header.number, 1);
+        assert_eq!(header.gas_used, 42_000);
+        assert_eq!(pool.status().pending, 0);
+
+        // the next block is empty and on top of the mined one
+        let header = miner.mine().unwrap().unwrap();
+        assert_eq!(header.number, 2);
+        assert_eq!(header.gas_used, 0);
+    }
+}
@@ -2,36 +2,43 @@
 //!
 //! Starts the client
 use crate::{
-    config::Config,
-    dirs::{ConfigPath, DbPath},
-    prometheus_exporter,
-    util::chainspec::{chain_spec_value_parser, ChainSpecification, Genesis},
+    account::KeystoreArgs,
+    config::{Config, ConfigArgs, DatabaseConfig, FreezerConfig},
+    control::{self, ControlState},
+    dirs::{self, DbPath},
+    rpc::RpcServerArgs,
+    util::{
+        chainspec::{chain_spec_value_parser, ChainSpecification, Genesis},
+        reth_tracing::{FilterHandle, LogArgs},
+    },
 };
-use clap::{crate_version, Parser};
-use reth_consensus::BeaconConsensus;
+use clap::{crate_version, Parser, ValueEnum};
 use reth_db::{
     cursor::DbCursorRO,
     database::Database,
     mdbx::{Env, WriteMap},
-    tables,
+    migration, tables,
     transaction::{DbTx, DbTxMut},
 };
-use reth_downloaders::{bodies, headers};
 use reth_interfaces::consensus::ForkchoiceState;
-use reth_network::{
-    config::{mainnet_nodes, rng_secret_key},
-    error::NetworkError,
-    NetworkConfig, NetworkHandle, NetworkManager,
+use reth_network::{config::load_or_create_secret_key, NatResolver, NodeRecord};
+use reth_primitives::{Account, Address, BlockNumber, Header, H256};
+use reth_provider::freezer::Freezer;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use reth_primitives::{Account, Header, H256};
-use reth_provider::{db_provider::ProviderImpl, BlockProvider, HeaderProvider};
-use reth_stages::{
-    stages::{bodies::BodyStage, headers::HeaderStage, sender_recovery::SenderRecoveryStage},
-    stages_metrics::HeaderMetrics,
-    stages_metrics_describer,
-};
-use std::{net::SocketAddr, path::Path, sync::Arc};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+use tracing_subscriber::EnvFilter;
+
+mod builder;
+pub mod dev;
+mod shutdown;
+pub mod tip;
+
+pub use builder::{Node, NodeBuilder, NodeDb, NodePool};
+pub use dev::{DevArgs, DevMiner};
 
 /// Start the client
 #[derive(Debug, Parser)]
@@ -46,9 +53,8 @@ pub struct Command {
     #[arg(long, value_name = "PATH", verbatim_doc_comment, default_value_t)]
     db: DbPath,
 
-    /// The path to the configuration file to use.
-    #[arg(long, value_name = "FILE", verbatim_doc_comment, default_value_t)]
-    config: ConfigPath,
+    #[clap(flatten)]
+    config: ConfigArgs,
 
     /// The chain this node is running.
     ///
@@ -58,6 +64,7 @@ pub struct Command {
     /// - mainnet
     /// - goerli
     /// - sepolia
+    /// - dev
     #[arg(
         long,
         value_name = "CHAIN_OR_PATH",
@@ -69,108 +76,307 @@ pub struct Command {
 
     /// Enable Prometheus metrics.
     ///
-    /// The metrics will be served at the given interface and port.
+    /// The metrics of the pipeline, network and database will be served at the given interface
+    /// and port.
     #[clap(long, value_name = "SOCKET")]
     metrics: Option<SocketAddr>,
 
     /// Set the chain tip manually for testing purposes.
     ///
     /// NOTE: This is a temporary flag
-    #[arg(long = "debug.tip")]
+    #[arg(long = "debug.tip", conflicts_with_all = ["tip_rpc_url", "etherscan", "dev"])]
     tip: Option<H256>,
+
+    /// Follow the chain tip of the HTTP JSON-RPC endpoint at the given URL instead of a consensus
+    /// client.
+    ///
+    /// The latest block of the endpoint is polled and used as head, safe and finalized block,
+    /// which allows testing the sync without running a consensus client.
+    #[arg(long = "debug.rpc-url", value_name = "URL", conflicts_with_all = ["etherscan", "dev"])]
+    tip_rpc_url: Option<String>,
+
+    /// Follow the chain tip of Etherscan instead of a consensus client.
+    ///
+    /// The API key is read from the `ETHERSCAN_API_KEY` environment variable. Requests without a
+    /// key are heavily rate limited.
+    #[arg(long = "debug.etherscan", conflicts_with = "dev")]
+    etherscan: bool,
+
+    /// Stop the sync once all stages reached the specified block.
+    ///
+    /// The database is left in the state right after this block, which allows inspecting the
+    /// state right before a known failure.
+    #[arg(long = "debug.terminate-block", value_name = "NUMBER")]
+    terminate_block: Option<BlockNumber>,
+
+    /// Stop the sync right before the transaction with the given index of the block.
+    ///
+    /// Only the transactions of the block before the index are executed, and the database is
+    /// left in the state right before the transaction, which allows inspecting the state a failing
+    /// transaction runs on. The later stages stop at the parent of the block.
+    #[arg(
+        long = "debug.halt-before-tx",
+        value_name = "BLOCK:INDEX",
+        value_parser = halt_position_value_parser,
+        conflicts_with = "terminate_block"
+    )]
+    halt_before_tx: Option<(BlockNumber, usize)>,
+
+    /// Comma separated enode URLs of trusted peers.
+    ///
+    /// Trusted peers are never disconnected and are exempt from the peer slot limits.
+    #[arg(long, value_name = "ENODES", value_delimiter = ',')]
+    trusted_peers: Vec<NodeRecord>,
+
+    /// How to resolve the external IP that is advertised to other nodes.
+    ///
+    /// Possible values:
+    /// - any: the first IP resolved via UPnP or a public IP lookup
+    /// - upnp
+    /// - natpmp
+    /// - publicip
+    /// - extip:<IP>: use the given IP
+    /// - none: advertise the local IP
+    #[arg(long, value_name = "RESOLVER", verbatim_doc_comment, default_value = "any")]
+    nat: NatResolver,
+
+    /// The path to the hex encoded secret key of the node's p2p identity.
+    ///
+    /// A new key is written to the path if the file doesn't exist, so the node keeps its enode
+    /// URL and ENR across restarts. Defaults to `discovery-secret` in the OS-specific data
+    /// directory.
+    #[arg(long = "p2p-secret-key", value_name = "PATH")]
+    p2p_secret_key: Option<PathBuf>,
+
+    /// Serve the control endpoint at the given IPC path.
+    ///
+    /// The control endpoint allows changing the log filter and peer limits of the running node.
+    /// All changes are written back to the configuration file.
+    #[arg(long = "control.ipc", value_name = "PATH")]
+    control_ipc: Option<String>,
+
+    /// How the state is synced.
+    ///
+    /// Possible values:
+    /// - full: execute all blocks
+    /// - snap: download the state of a recent block from peers of the `snap/1` protocol and heal
+    ///   it as the chain progresses, without the plain state and the history
+    #[arg(long = "sync-mode", value_name = "MODE", verbatim_doc_comment, default_value = "full")]
+    sync_mode: SyncMode,
+
+    /// Serve the state to peers that snap sync.
+    ///
+    /// The state is served from the hashed state and the tries of the merkle stage, until the
+    /// merkle stage caught up with new blocks nothing is served.
+    #[arg(long = "serve-snap")]
+    serve_snap: bool,
+
+    #[clap(flatten)]
+    rpc: RpcServerArgs,
+
+    #[clap(flatten)]
+    dev: DevArgs,
+
+    /// Comma separated addresses of the keystore accounts that sign the requests of `eth_sign`
+    /// and `eth_signTransaction`.
+    ///
+    /// The accounts are decrypted with the password of `--password`. Anyone with access to the RPC
+    /// servers can sign with unlocked accounts, so only unlock accounts for development.
+    #[arg(long, value_name = "ADDRESSES", value_delimiter = ',', requires = "password")]
+    unlock: Vec<Address>,
+
+    #[clap(flatten)]
+    keystore: KeystoreArgs,
 }
 
 impl Command {
     /// Execute `node` command
-    // TODO: RPC
-    pub async fn execute(&self) -> eyre::Result<()> {
-        let config: Config = confy::load_path(&self.config).unwrap_or_default();
-        info!("reth {} starting", crate_version!());
+    pub async fn execute(&self, logs: &LogArgs, filter_handle: FilterHandle) -> eyre::Result<()> {
+        self.execute_with(logs, filter_handle, |builder| builder).await
+    }
 
-        info!("Opening database at {}", &self.db);
-        let db = Arc::new(init_db(&self.db)?);
-        info!("Database open");
+    /// Execute `node` command with a node that is customized by the given function, e.g. to
+    /// install ExExs or serve additional RPC methods.
+    pub async fn execute_with<F>(
+        &self,
+        logs: &LogArgs,
+        filter_handle: FilterHandle,
+        customize: F,
+    ) -> eyre::Result<()>
+    where
+        F: FnOnce(NodeBuilder) -> NodeBuilder,
+    {
+        let config = self.config.load()?;
+        info!("reth {} starting", crate_version!());
 
-        if let Some(listen_addr) = self.metrics {
-            info!("Starting metrics endpoint at {}", listen_addr);
-            prometheus_exporter::initialize(listen_addr)?;
-            stages_metrics_describer::describe();
+        if let Some(filter) = config.log.filter.as_ref().filter(|_| !logs.is_filter_set()) {
+            filter_handle.reload(EnvFilter::try_new(filter)?)?;
         }
 
-        let chain_id = self.chain.consensus.chain_id;
-        let consensus = Arc::new(BeaconConsensus::new(self.chain.consensus.clone()));
-        let genesis_hash = init_genesis(db.clone(), self.chain.genesis.clone())?;
-
-        info!("Connecting to p2p");
-        let network = start_network(network_config(db.clone(), chain_id, genesis_hash)).await?;
-
-        // TODO: Are most of these Arcs unnecessary? For example, fetch client is completely
-        // cloneable on its own
-        // TODO: Remove magic numbers
-        let fetch_client = Arc::new(network.fetch_client().await?);
-        let mut pipeline = reth_stages::Pipeline::new()
-            .push(HeaderStage {
-                downloader: headers::linear::LinearDownloadBuilder::default()
-                    .batch_size(config.stages.headers.downloader_batch_size)
-                    .retries(config.stages.headers.downloader_retries)
-                    .build(consensus.clone(), fetch_client.clone()),
-                consensus: consensus.clone(),
-                client: fetch_client.clone(),
-                network_handle: network.clone(),
-                commit_threshold: config.stages.headers.commit_threshold,
-                metrics: HeaderMetrics::default(),
-            })
-            .push(BodyStage {
-                downloader: Arc::new(
-                    bodies::concurrent::ConcurrentDownloader::new(
-                        fetch_client.clone(),
-                        consensus.clone(),
-                    )
-                    .with_batch_size(config.stages.bodies.downloader_batch_size)
-                    .with_retries(config.stages.bodies.downloader_retries)
-                    .with_concurrency(config.stages.bodies.downloader_concurrency),
-                ),
-                consensus: consensus.clone(),
-                commit_threshold: config.stages.bodies.commit_threshold,
-            })
-            .push(SenderRecoveryStage {
-                batch_size: config.stages.sender_recovery.batch_size,
-                commit_threshold: config.stages.sender_recovery.commit_threshold,
-            });
+        let (signal, shutdown) = reth_tasks::shutdown::signal();
+        let _signal_handler = shutdown::spawn(signal);
+        let node = customize(self.node_builder(config)?.shutdown(shutdown)).launch().await?;
+
+        let _control = if let Some(endpoint) = &self.control_ipc {
+            // the flags are not written back to the config file along with the runtime changes
+            let state = ControlState::new(
+                Config::load(&self.config.config)?,
+                self.config.config.as_ref(),
+                filter_handle,
+                node.network.peers_handle().clone(),
+            );
+            Some(control::start(endpoint, state).await?)
+        } else {
+            None
+        };
 
         if let Some(tip) = self.tip {
             debug!("Tip manually set: {}", tip);
-            consensus.notify_fork_choice_state(ForkchoiceState {
+            node.consensus.notify_fork_choice_state(ForkchoiceState {
                 head_block_hash: tip,
                 safe_block_hash: tip,
                 finalized_block_hash: tip,
             })?;
         }
 
-        // Run pipeline
-        info!("Starting pipeline");
-        pipeline.run(db.clone()).await?;
+        let chain_id = node.chain_id;
+        let tip_source = if let Some(url) = &self.tip_rpc_url {
+            Some(tip::TipSource::rpc(url)?)
+        } else if self.etherscan {
+            let api_key = std::env::var("ETHERSCAN_API_KEY").ok();
+            Some(tip::TipSource::etherscan(chain_id, api_key).ok_or_else(|| {
+                eyre::eyre!("Etherscan does not support the chain with id {chain_id}")
+            })?)
+        } else {
+            None
+        };
+        if let Some(source) = tip_source {
+            info!(
+                "Following the chain tip of {}",
+                self.tip_rpc_url.as_deref().unwrap_or("Etherscan")
+            );
+            tip::spawn(source, node.consensus.clone(), tip::DEFAULT_POLL_INTERVAL, &node.executor);
+        }
+
+        node.run().await?;
+
+        if let Some(block) = self.terminate_block {
+            info!("Reached terminate block {}", block);
+        }
+        if let Some((block, index)) = self.halt_before_tx {
+            info!("Halted before transaction {} of block {}", index, block);
+        }
 
         info!("Finishing up");
         Ok(())
     }
+
+    /// Returns the builder of the node configured by the flags and the given configuration.
+    ///
+    /// The dev mode runs the dev chain, regardless of the `--chain` flag.
+    pub fn node_builder(&self, config: Config) -> eyre::Result<NodeBuilder> {
+        let chain = if self.dev.dev { chain_spec_value_parser("dev")? } else { self.chain.clone() };
+        let mut keys = Vec::with_capacity(self.unlock.len());
+        if !self.unlock.is_empty() {
+            let keystore = self.keystore.keystore()?;
+            let password = self.keystore.password()?;
+            for address in &self.unlock {
+                keys.push(keystore.unlock(*address, &password)?);
+            }
+            warn!(
+                "Unlocked {} accounts, anyone with access to the RPC servers can sign with them",
+                keys.len()
+            );
+        }
+        let mut builder = NodeBuilder::new(self.db.as_ref(), chain, config)
+            .rpc(self.rpc.clone())
+            .metrics(self.metrics)
+            .trusted_peers(self.trusted_peers.clone())
+            .nat(self.nat)
+            .max_block(self.terminate_block)
+            .halt_before(self.halt_before_tx)
+            .sync_mode(self.sync_mode)
+            .serve_snap(self.serve_snap)
+            .dev(self.dev.clone())
+            .unlocked_accounts(keys);
+        if !self.dev.dev {
+            let path = p2p_secret_key_path(self.p2p_secret_key.as_deref())?;
+            builder = builder.p2p_secret_key(load_or_create_secret_key(&path)?);
+        }
+        Ok(builder)
+    }
+}
+
+/// How a node syncs the state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SyncMode {
+    /// The state is built by executing all blocks.
+    #[default]
+    Full,
+    /// The state of a recent block is downloaded from peers of the `snap/1` protocol, see
+    /// [SnapSyncStage](reth_stages::stages::snap::SnapSyncStage).
+    Snap,
+}
+
+/// Returns the given path of the p2p secret key or the default one.
+pub(crate) fn p2p_secret_key_path(path: Option<&Path>) -> eyre::Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path.to_path_buf()),
+        None => dirs::p2p_secret_key_path()
+            .ok_or_else(|| eyre::eyre!("Could not determine the p2p secret key path. Set one.")),
+    }
+}
+
+/// Parses the position of a transaction as `<BLOCK>:<INDEX>`.
+fn halt_position_value_parser(value: &str) -> eyre::Result<(BlockNumber, usize)> {
+    let (block, index) = value
+        .split_once(':')
+        .ok_or_else(|| eyre::eyre!("Expected <BLOCK>:<INDEX>, got {value}"))?;
+    Ok((block.parse()?, index.parse()?))
 }
 
 /// Opens up an existing database or creates a new one at the specified path.
-fn init_db<P: AsRef<Path>>(path: P) -> eyre::Result<Env<WriteMap>> {
+pub(crate) fn init_db<P: AsRef<Path>>(
+    path: P,
+    config: &DatabaseConfig,
+) -> eyre::Result<Env<WriteMap>> {
     std::fs::create_dir_all(path.as_ref())?;
-    let db = reth_db::mdbx::Env::<reth_db::mdbx::WriteMap>::open(
+    let db = reth_db::mdbx::Env::<reth_db::mdbx::WriteMap>::open_with_config(
         path.as_ref(),
         reth_db::mdbx::EnvKind::RW,
+        config.env_config(),
     )?;
     db.create_tables()?;
+    match migration::check_version(&db) {
+        Err(err @ migration::MigrationError::Outdated { .. }) => {
+            eyre::bail!("{err} Run `reth db migrate` to upgrade it.")
+        }
+        res => res?,
+    }
 
     Ok(db)
 }
 
+/// Opens the freezer in the `freezer` folder of the database if it is enabled or was used before.
+pub(crate) fn init_freezer(
+    db: impl AsRef<Path>,
+    config: &FreezerConfig,
+) -> eyre::Result<Option<Arc<Freezer>>> {
+    let dir = db.as_ref().join("freezer");
+    if config.distance.is_none() && !dir.exists() {
+        return Ok(None)
+    }
+    let freezer = Freezer::open(&dir)?;
+    info!(target: "reth::cli", blocks = freezer.blocks(), "Freezer open at {}", dir.display());
+    Ok(Some(Arc::new(freezer)))
+}
+
 /// Write the genesis block if it has not already been written
 #[allow(clippy::field_reassign_with_default)]
-fn init_genesis<DB: Database>(db: Arc<DB>, genesis: Genesis) -> Result<H256, reth_db::Error> {
+pub(crate) fn init_genesis<DB: Database>(
+    db: Arc<DB>,
+    genesis: Genesis,
+) -> Result<H256, reth_db::Error> {
     let tx = db.tx_mut()?;
     if let Some((_, hash)) = tx.cursor::<tables::CanonicalHeaders>()?.first()? {
         debug!("Genesis already written, skipping.");
@@ -179,16 +385,7 @@ fn init_genesis<DB: Database>(db: Arc<DB>, genesis: Genesis) -> Result<H256, ret
     debug!("Writing genesis block.");
 
     // Insert account state
-    for (address, account) in &genesis.alloc {
-        tx.put::<tables::PlainAccountState>(
-            *address,
-            Account {
-                nonce: account.nonce.unwrap_or_default(),
-                balance: account.balance,
-                bytecode_hash: None,
-            },
-        )?;
-    }
+    insert_genesis_alloc(&tx, &genesis)?;
 
     // Insert header
     let header: Header = genesis.into();
@@ -204,30 +401,20 @@ fn init_genesis<DB: Database>(db: Arc<DB>, genesis: Genesis) -> Result<H256, ret
     Ok(hash)
 }
 
-// TODO: This should be based on some external config
-fn network_config<DB: Database>(
-    db: Arc<DB>,
-    chain_id: u64,
-    genesis_hash: H256,
-) -> NetworkConfig<ProviderImpl<DB>> {
-    NetworkConfig::builder(Arc::new(ProviderImpl::new(db)), rng_secret_key())
-        .boot_nodes(mainnet_nodes())
-        .genesis_hash(genesis_hash)
-        .chain_id(chain_id)
-        .build()
-}
-
-/// Starts the networking stack given a [NetworkConfig] and returns a handle to the network.
-async fn start_network<C>(config: NetworkConfig<C>) -> Result<NetworkHandle, NetworkError>
-where
-    C: BlockProvider + HeaderProvider + 'static,
-{
-    let client = config.client.clone();
-    let (handle, network, _txpool, eth) =
-        NetworkManager::builder(config).await?.request_handler(client).split_with_handle();
-
-    tokio::task::spawn(network);
-    // TODO: tokio::task::spawn(txpool);
-    tokio::task::spawn(eth);
-    Ok(handle)
+/// Writes the accounts of the genesis state to the plain state.
+pub(crate) fn insert_genesis_alloc<'a, TX: DbTxMut<'a>>(
+    tx: &TX,
+    genesis: &Genesis,
+) -> Result<(), reth_db::Error> {
+    for (address, account) in &genesis.alloc {
+        tx.put::<tables::PlainAccountState>(
+            *address,
+            Account {
+                nonce: account.nonce.unwrap_or_default(),
+                balance: account.balance,
+                bytecode_hash: None,
+            },
+        )?;
+    }
+    Ok(())
 }
@@ -0,0 +1,44 @@
+//! Graceful shutdown on SIGINT and SIGTERM.
+//!
+//! The first signal requests the node to shut down: the pipeline stops once the progress of the
+//! running stage is committed, the network and the consensus engine stop and the database is closed
+//! when the node is dropped. The second signal exits the process right away.
+
+use reth_tasks::shutdown::ShutdownSignal;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Spawns a task that fires the shutdown signal on the first SIGINT or SIGTERM and exits the
+/// process on the second one.
+pub fn spawn(signal: ShutdownSignal) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(err) = wait_for_signal().await {
+            warn!(target: "reth::cli", ?err, "Failed to listen for shutdown signals");
+            return
+        }
+        info!(target: "reth::cli", "Shutting down, press Ctrl-C again to exit immediately");
+        signal.fire();
+
+        if wait_for_signal().await.is_ok() {
+            warn!(target: "reth::cli", "Exiting without committing the current stage");
+            std::process::exit(1);
+        }
+    })
+}
+
+/// Resolves once the process receives SIGINT or SIGTERM.
+async fn wait_for_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            res = tokio::signal::ctrl_c() => res,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
+}
@@ -0,0 +1,212 @@
+//! Follows the chain tip of an external source in place of a consensus client.
+//!
+//! Without a consensus client the pipeline never learns about a tip to sync to. For testing, the
+//! node can instead poll the latest block of another node's RPC or of an Etherscan-like API and
+//! forward it as the [ForkchoiceState]. Failed requests are logged and retried on the next poll.
+
+use eyre::WrapErr;
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::{HttpClient, HttpClientBuilder},
+    rpc_params,
+};
+use reth_consensus::BeaconConsensus;
+use reth_interfaces::consensus::ForkchoiceState;
+use reth_primitives::{H256, U64};
+use reth_tasks::TaskExecutor;
+use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+/// The default interval between two requests for the latest block.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+/// The number and hash of the latest block of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct LatestBlock {
+    /// The block number.
+    pub number: U64,
+    /// The block hash.
+    pub hash: H256,
+}
+
+/// Where the latest block is fetched from.
+#[derive(Debug, Clone)]
+pub enum TipSource {
+    /// The `eth_getBlockByNumber` method of an HTTP JSON-RPC endpoint.
+    Rpc(HttpClient),
+    /// The proxy module of an Etherscan-like API.
+    Etherscan {
+        /// The HTTP client.
+        client: reqwest::Client,
+        /// The URL of the API, e.g. `https://api.etherscan.io/api`.
+        url: String,
+        /// The API key, requests without one are heavily rate limited.
+        api_key: Option<String>,
+    },
+}
+
+// === impl TipSource ===
+
+impl TipSource {
+    /// Follows the HTTP JSON-RPC endpoint at the given URL.
+    pub fn rpc(url: &str) -> eyre::Result<Self> {
+        let client = HttpClientBuilder::default()
+            .build(url)
+            .wrap_err_with(|| format!("Could not connect to {url}"))?;
+        Ok(Self::Rpc(client))
+    }
+
+    /// Follows the Etherscan API of the given chain.
+    ///
+    /// Returns `None` if Etherscan does not support the chain.
+    pub fn etherscan(chain_id: u64, api_key: Option<String>) -> Option<Self> {
+        let url = match chain_id {
+            1 => "https://api.etherscan.io/api",
+            5 => "https://api-goerli.etherscan.io/api",
+            11155111 => "https://api-sepolia.etherscan.io/api",
+            _ => return None,
+        };
+        Some(Self::Etherscan { client: reqwest::Client::new(), url: url.to_string(), api_key })
+    }
+
+    /// Fetches the latest block of the source.
+    pub async fn latest_block(&self) -> eyre::Result<Option<LatestBlock>> {
+        match self {
+            TipSource::Rpc(client) => {
+                Ok(client.request("eth_getBlockByNumber", rpc_params!["latest", false]).await?)
+            }
+            TipSource::Etherscan { client, url, api_key } => {
+                let mut query = vec![
+                    ("module", "proxy"),
+                    ("action", "eth_getBlockByNumber"),
+                    ("tag", "latest"),
+                    ("boolean", "false"),
+                ];
+                if let Some(api_key) = api_key {
+                    query.push(("apikey", api_key.as_str()));
+                }
+                let body = client.get(url).query(&query).send().await?.text().await?;
+                parse_etherscan_response(&body)
+            }
+        }
+    }
+}
+
+/// Extracts the block of an Etherscan proxy response.
+///
+/// Etherscan reports errors with a `result` string instead of an error code, e.g.
+/// `{"status":"0","message":"NOTOK","result":"Invalid API Key"}`.
+fn parse_etherscan_response(body: &str) -> eyre::Result<Option<LatestBlock>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum EtherscanResult {
+        Block(Option<LatestBlock>),
+        Error(String),
+    }
+
+    #[derive(Deserialize)]
+    struct EtherscanResponse {
+        result: EtherscanResult,
+    }
+
+    let response: EtherscanResponse =
+        serde_json::from_str(body).wrap_err("Invalid Etherscan response")?;
+    match response.result {
+        EtherscanResult::Block(block) => Ok(block),
+        EtherscanResult::Error(err) => eyre::bail!("Etherscan request failed: {err}"),
+    }
+}
+
+/// Spawns a task of the executor that polls the latest block of the source and notifies the
+/// consensus of every new one.
+///
+/// The latest block is reported as head, safe and finalized block alike.
+pub fn spawn(
+    source: TipSource,
+    consensus: Arc<BeaconConsensus>,
+    interval: Duration,
+    executor: &TaskExecutor,
+) {
+    executor.spawn("tip follower", async move {
+        let mut interval = tokio::time::interval(interval);
+        let mut current = None;
+        loop {
+            interval.tick().await;
+            let block = match source.latest_block().await {
+                Ok(Some(block)) => block,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!(target: "reth::cli", ?err, "Failed to fetch the latest block");
+                    continue
+                }
+            };
+            if current == Some(block.hash) {
+                continue
+            }
+            current = Some(block.hash);
+
+            info!(target: "reth::cli", number = %block.number, hash = ?block.hash, "New tip");
+            let state = ForkchoiceState {
+                head_block_hash: block.hash,
+                safe_block_hash: block.hash,
+                finalized_block_hash: block.hash,
+            };
+            if let Err(err) = consensus.notify_fork_choice_state(state) {
+                warn!(target: "reth::cli", ?err, "Fork choice channel closed, stopping tip follower");
+                return
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::{server::ServerBuilder, RpcModule};
+
+    const HASH: &str = "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6";
+
+    #[test]
+    fn parse_etherscan() {
+        let body = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"result":{{"number":"0x1","hash":"{HASH}","miner":"0x05a56e2d52c817161883f50c441c3228cfe54d9f"}}}}"#
+        );
+        let block = parse_etherscan_response(&body).unwrap().unwrap();
+        assert_eq!(block.number, U64::from(1));
+        assert_eq!(block.hash, HASH.parse().unwrap());
+
+        let body = r#"{"status":"0","message":"NOTOK","result":"Invalid API Key"}"#;
+        let err = parse_etherscan_response(body).unwrap_err();
+        assert!(err.to_string().contains("Invalid API Key"));
+
+        assert!(TipSource::etherscan(1, None).is_some());
+        assert!(TipSource::etherscan(1337, None).is_none());
+    }
+
+    #[tokio::test]
+    async fn latest_block_of_rpc() {
+        let server = ServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let mut module = RpcModule::new(());
+        module
+            .register_method("eth_getBlockByNumber", |params, _| {
+                let (tag, _full) = params.parse::<(String, bool)>()?;
+                assert_eq!(tag, "latest");
+                Ok(serde_json::json!({ "number": "0x10", "hash": HASH, "gasUsed": "0x0" }))
+            })
+            .unwrap();
+        let _handle = server.start(module).unwrap();
+
+        let source = TipSource::rpc(&format!("http://{addr}")).unwrap();
+        let block = source.latest_block().await.unwrap().unwrap();
+        assert_eq!(block, LatestBlock { number: U64::from(16), hash: HASH.parse().unwrap() });
+
+        let consensus = Arc::new(BeaconConsensus::new(Default::default()));
+        let mut fork_choice = reth_interfaces::consensus::Consensus::fork_choice_state(&*consensus);
+        let task = spawn(source, consensus, Duration::from_millis(10));
+        fork_choice.changed().await.unwrap();
+        assert_eq!(fork_choice.borrow().head_block_hash, block.hash);
+        task.abort();
+    }
+}
@@ -0,0 +1,310 @@
+//! P2P debugging tool
+//!
+//! Connects to a single peer, without a database or the sync pipeline, and requests a header or a
+//! body. The discovery and RLPx handshake are logged, more details are available with e.g.
+//! `--log.filter net=trace,discv4=trace`.
+//!
+//! `reth p2p enr` prints the address of the local node instead, so operators can share it.
+use crate::{
+    node::p2p_secret_key_path,
+    util::chainspec::{chain_spec_value_parser, ChainSpecification},
+};
+use clap::{Parser, Subcommand};
+use eyre::WrapErr;
+use futures::StreamExt;
+use reth_eth_wire::{BlockBody, GetBlockBodies, GetBlockHeaders};
+use reth_network::{
+    config::{load_or_create_secret_key, rng_secret_key},
+    NatResolver, NetworkConfig, NetworkEvent, NetworkHandle, NetworkManager, NodeRecord,
+    PeerRequest, PeersConfig,
+};
+use reth_primitives::{BlockHashOrNumber, Header, HeadersDirection, H256};
+use reth_provider::test_utils::TestApi;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::oneshot;
+use tracing::info;
+
+/// `reth p2p` command
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The chain the peer is expected to be on.
+    ///
+    /// Possible values are either a built-in chain or the path to a chain specification file.
+    ///
+    /// Built-in chains:
+    /// - mainnet
+    /// - goerli
+    /// - sepolia
+    #[arg(
+        long,
+        value_name = "CHAIN_OR_PATH",
+        verbatim_doc_comment,
+        default_value = "mainnet",
+        value_parser = chain_spec_value_parser
+    )]
+    chain: ChainSpecification,
+
+    /// The number of seconds to wait for the session to be established and for the response.
+    #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+    timeout: u64,
+
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Subcommand, Debug)]
+/// `reth p2p` subcommands
+pub enum Subcommands {
+    /// Download a single header from the peer
+    Header {
+        /// The enode URL of the peer
+        enode: NodeRecord,
+        /// The number or hash of the block
+        #[arg(value_parser = block_id_value_parser)]
+        id: BlockHashOrNumber,
+    },
+    /// Download a single body from the peer
+    ///
+    /// The header is downloaded first if the block is given by number.
+    Body {
+        /// The enode URL of the peer
+        enode: NodeRecord,
+        /// The number or hash of the block
+        #[arg(value_parser = block_id_value_parser)]
+        id: BlockHashOrNumber,
+    },
+    /// Print the enode URL, ENR, ports and protocols of the local node
+    ///
+    /// The node is identified by the same secret key as `reth node`.
+    Enr {
+        /// The path to the hex encoded secret key of the node's p2p identity.
+        ///
+        /// A new key is written to the path if the file doesn't exist. Defaults to
+        /// `discovery-secret` in the OS-specific data directory.
+        #[arg(long = "p2p-secret-key", value_name = "PATH")]
+        p2p_secret_key: Option<PathBuf>,
+        /// The port of the discovery service and the listener for incoming connections.
+        #[arg(long, value_name = "PORT", default_value_t = 30303)]
+        port: u16,
+        /// How to resolve the external IP that is advertised to other nodes, see `reth node
+        /// --nat`.
+        #[arg(long, value_name = "RESOLVER", default_value = "any")]
+        nat: NatResolver,
+    },
+}
+
+impl Command {
+    /// Execute `p2p` command
+    pub async fn execute(&self) -> eyre::Result<()> {
+        let (enode, id) = match &self.command {
+            Subcommands::Header { enode, id } | Subcommands::Body { enode, id } => (enode, *id),
+            Subcommands::Enr { p2p_secret_key, port, nat } => {
+                return self.print_enr(p2p_secret_key.as_deref(), *port, *nat).await
+            }
+        };
+        let timeout = Duration::from_secs(self.timeout);
+
+        let network = self.start_network(enode).await?;
+        tokio::time::timeout(timeout, wait_for_session(&network, enode))
+            .await
+            .wrap_err_with(|| format!("Timed out connecting to {}", enode.id))??;
+
+        match &self.command {
+            Subcommands::Header { .. } => {
+                let header = tokio::time::timeout(timeout, get_header(&network, enode, id))
+                    .await
+                    .wrap_err("Timed out waiting for the header")??;
+                println!("{header:#?}");
+            }
+            Subcommands::Body { .. } => {
+                let body = tokio::time::timeout(timeout, async {
+                    let hash = match id {
+                        BlockHashOrNumber::Hash(hash) => hash,
+                        BlockHashOrNumber::Number(_) => {
+                            get_header(&network, enode, id).await?.hash_slow()
+                        }
+                    };
+                    get_body(&network, enode, hash).await
+                })
+                .await
+                .wrap_err("Timed out waiting for the body")??;
+                println!("{body:#?}");
+            }
+            Subcommands::Enr { .. } => unreachable!("handled above"),
+        }
+
+        Ok(())
+    }
+
+    /// Prints the address of the local node, as it is advertised by `reth node`.
+    async fn print_enr(
+        &self,
+        p2p_secret_key: Option<&Path>,
+        port: u16,
+        nat: NatResolver,
+    ) -> eyre::Result<()> {
+        let path = p2p_secret_key_path(p2p_secret_key)?;
+        let secret_key = load_or_create_secret_key(&path)
+            .wrap_err_with(|| format!("Could not load the p2p secret key {}", path.display()))?;
+        let ip = nat.external_addr().await.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let addr = SocketAddr::new(ip, port);
+        let genesis_hash = Header::from(self.chain.genesis.clone()).hash_slow();
+
+        let info = NetworkConfig::builder(Arc::new(TestApi::default()), secret_key)
+            .genesis_hash(genesis_hash)
+            .chain_id(self.chain.consensus.chain_id)
+            .listener_addr(addr)
+            .discovery_addr(addr)
+            .build()
+            .node_info();
+        let protocols = info.capabilities.iter().map(ToString::to_string).collect::<Vec<_>>();
+        println!("enode:     {}", info.record);
+        println!("enr:       {}", info.enr.to_base64());
+        println!("discovery: {}", info.record.udp_port);
+        println!("listener:  {}", info.record.tcp_port);
+        println!("protocols: {}", protocols.join(", "));
+        Ok(())
+    }
+
+    /// Starts a network that only connects to the given peer.
+    ///
+    /// The peer is used as the only boot node for discovery and as a trusted peer, all other slots
+    /// are closed. Requests of the peer are answered with empty responses.
+    async fn start_network(&self, enode: &NodeRecord) -> eyre::Result<NetworkHandle> {
+        let genesis_hash = Header::from(self.chain.genesis.clone()).hash_slow();
+        let unspecified = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0));
+        let peers_config = PeersConfig::default()
+            .with_trusted_nodes([*enode].into_iter().collect())
+            .with_max_inbound(0)
+            .with_max_outbound(0);
+        let client = Arc::new(TestApi::default());
+        let config = NetworkConfig::builder(client.clone(), rng_secret_key())
+            .boot_nodes([*enode])
+            .peer_config(peers_config)
+            .genesis_hash(genesis_hash)
+            .chain_id(self.chain.consensus.chain_id)
+            .listener_addr(unspecified)
+            .discovery_addr(unspecified)
+            .build();
+
+        let (handle, network, _txpool, eth) =
+            NetworkManager::builder(config).await?.request_handler(client).split_with_handle();
+        tokio::task::spawn(network);
+        tokio::task::spawn(eth);
+        info!(target: "reth::cli", peer_id = ?handle.peer_id(), "Started network");
+        Ok(handle)
+    }
+}
+
+/// Waits until the session with the peer is established.
+async fn wait_for_session(network: &NetworkHandle, enode: &NodeRecord) -> eyre::Result<()> {
+    let mut events = network.event_listener();
+    info!(target: "reth::cli", peer = %enode, "Connecting to peer");
+    while let Some(event) = events.next().await {
+        match event {
+            NetworkEvent::SessionEstablished { peer_id, capabilities, status, .. }
+                if peer_id == enode.id =>
+            {
+                info!(target: "reth::cli", ?peer_id, ?capabilities, ?status, "Session established");
+                return Ok(())
+            }
+            NetworkEvent::SessionClosed { peer_id, reason } if peer_id == enode.id => {
+                info!(target: "reth::cli", ?peer_id, ?reason, "Session closed, retrying");
+            }
+            _ => {}
+        }
+    }
+    eyre::bail!("Network stopped")
+}
+
+/// Requests a single header from the peer.
+async fn get_header(
+    network: &NetworkHandle,
+    enode: &NodeRecord,
+    id: BlockHashOrNumber,
+) -> eyre::Result<Header> {
+    let (tx, rx) = oneshot::channel();
+    let request =
+        GetBlockHeaders { start_block: id, limit: 1, skip: 0, direction: HeadersDirection::Rising };
+    info!(target: "reth::cli", ?request, "Requesting header");
+    network.send_request(enode.id, PeerRequest::GetBlockHeaders { request, response: tx });
+
+    let headers = rx.await??.0;
+    let header =
+        headers.into_iter().next().ok_or_else(|| eyre::eyre!("Header not found: {id:?}"))?;
+    match id {
+        BlockHashOrNumber::Hash(hash) if header.hash_slow() != hash => {
+            eyre::bail!("Received header {:?} instead of {hash:?}", header.hash_slow())
+        }
+        BlockHashOrNumber::Number(number) if header.number != number => {
+            eyre::bail!("Received header #{} instead of #{number}", header.number)
+        }
+        _ => Ok(header),
+    }
+}
+
+/// Requests a single body from the peer.
+async fn get_body(
+    network: &NetworkHandle,
+    enode: &NodeRecord,
+    hash: H256,
+) -> eyre::Result<BlockBody> {
+    let (tx, rx) = oneshot::channel();
+    let request = GetBlockBodies(vec![hash]);
+    info!(target: "reth::cli", ?request, "Requesting body");
+    network.send_request(enode.id, PeerRequest::GetBlockBodies { request, response: tx });
+
+    let bodies = rx.await??.0;
+    bodies.into_iter().next().ok_or_else(|| eyre::eyre!("Body not found: {hash:?}"))
+}
+
+/// Parses a block number or a `0x` prefixed block hash.
+fn block_id_value_parser(value: &str) -> eyre::Result<BlockHashOrNumber, eyre::Error> {
+    if value.starts_with("0x") {
+        Ok(BlockHashOrNumber::Hash(value.parse::<H256>()?))
+    } else {
+        Ok(BlockHashOrNumber::Number(value.parse::<u64>()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_p2p_command() {
+        let enode = "enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303?discport=30301";
+        let command = Command::parse_from(["reth", "header", enode, "1000"]);
+        assert!(matches!(
+            command.command,
+            Subcommands::Header { id: BlockHashOrNumber::Number(1000), .. }
+        ));
+
+        let hash = "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3";
+        let command = Command::parse_from(["reth", "body", enode, hash]);
+        match command.command {
+            Subcommands::Body { enode: node, id } => {
+                assert_eq!(node.tcp_port, 30303);
+                assert_eq!(node.udp_port, 30301);
+                assert_eq!(id, BlockHashOrNumber::Hash(hash.parse().unwrap()));
+            }
+            _ => panic!("expected body command"),
+        }
+
+        assert!(Command::try_parse_from(["reth", "header", enode, "latest"]).is_err());
+
+        let command = Command::parse_from(["reth", "enr", "--port", "30305", "--nat", "none"]);
+        match command.command {
+            Subcommands::Enr { p2p_secret_key: None, port, nat } => {
+                assert_eq!(port, 30305);
+                assert_eq!(nat, NatResolver::None);
+            }
+            _ => panic!("expected enr command"),
+        }
+    }
+}
@@ -1,16 +1,33 @@
 //! Prometheus exporter
 
 use eyre::WrapErr;
+use metrics::{describe_gauge, gauge};
 use metrics_exporter_prometheus::PrometheusBuilder;
 use metrics_util::layers::{PrefixLayer, Stack};
-use std::net::SocketAddr;
+use reth_db::{
+    database::Database,
+    mdbx::{Env, WriteMap},
+    tables,
+};
+use reth_network::NetworkMetrics;
+use reth_stages::stages_metrics_describer;
+use reth_tasks::{RestartPolicy, TaskExecutor, TaskMetrics};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tracing::warn;
 
-pub(crate) fn initialize(listen_addr: SocketAddr) -> eyre::Result<()> {
+/// The interval at which the database metrics are updated.
+const DB_METRICS_INTERVAL: Duration = Duration::from_secs(30);
+
+pub(crate) fn initialize(listen_addr: SocketAddr, executor: &TaskExecutor) -> eyre::Result<()> {
     let (recorder, exporter) = PrometheusBuilder::new()
         .with_http_listener(listen_addr)
         .build()
         .wrap_err("Could not build Prometheus endpoint.")?;
-    tokio::task::spawn(exporter);
+    executor.spawn("metrics exporter", async move {
+        if let Err(err) = exporter.await {
+            warn!(target: "reth::cli", ?err, "Metrics endpoint stopped");
+        }
+    });
     Stack::new(recorder)
         .push(PrefixLayer::new("reth"))
         .install()
@@ -18,3 +35,49 @@ pub(crate) fn initialize(listen_addr: SocketAddr) -> eyre::Result<()> {
 
     Ok(())
 }
+
+/// Describes the metrics of all node components.
+pub(crate) fn describe() {
+    stages_metrics_describer::describe();
+    NetworkMetrics::describe();
+    TaskMetrics::describe();
+    describe_gauge!("db.table_size", "The size of a database table in bytes");
+    describe_gauge!("db.table_entries", "The number of entries of a database table");
+}
+
+/// Spawns a task that periodically reports the size of all database tables.
+///
+/// The task is restarted a few times if it panics, the node keeps running without the database
+/// metrics once it gave up.
+pub(crate) fn spawn_db_metrics(db: Arc<Env<WriteMap>>, executor: &TaskExecutor) {
+    executor.spawn_with_restarts("db metrics", RestartPolicy::Limited(3), move || {
+        let db = db.clone();
+        async move {
+            let mut interval = tokio::time::interval(DB_METRICS_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(err) = report_db_metrics(&db) {
+                    warn!(target: "reth::cli", ?err, "Failed to collect database metrics");
+                }
+            }
+        }
+    });
+}
+
+fn report_db_metrics(db: &Env<WriteMap>) -> eyre::Result<()> {
+    db.view(|tx| {
+        for table in tables::TABLES.iter().map(|(_, name)| name) {
+            let table_db = tx.inner.open_db(Some(table)).wrap_err("Could not open db.")?;
+            let stats =
+                tx.inner.db_stat(&table_db).wrap_err(format!("Could not find table: {table}"))?;
+
+            let page_size = stats.page_size() as usize;
+            let num_pages = stats.leaf_pages() + stats.branch_pages() + stats.overflow_pages();
+            let table_size = page_size * num_pages;
+
+            gauge!("db.table_size", table_size as f64, "table" => *table);
+            gauge!("db.table_entries", stats.entries() as f64, "table" => *table);
+        }
+        Ok::<(), eyre::Report>(())
+    })?
+}
@@ -0,0 +1,597 @@
+//! RPC servers of the node.
+//!
+//! The namespaces selected with `--http.api` and `--ws.api` are served on the HTTP and WebSocket
+//! ports, the HTTP namespaces are also served on the IPC endpoint. The Engine API is never part of
+//! them, it's served on its own port where every request has to be authenticated with a JWT signed
+//! with the secret shared with the consensus client.
+use crate::{
+    config::{RpcConfig, RpcModule},
+    dirs::{data_dir, ipc_path},
+};
+use clap::Args;
+use eyre::WrapErr;
+use jsonrpsee::{
+    server::{ServerBuilder, ServerHandle},
+    Methods,
+};
+use reth_network::NetworkHandle;
+use reth_primitives::IntoRecoveredTransaction;
+use reth_provider::{
+    BlockProvider, ChainNotifications, HeaderProvider, HistoricalRangeProvider, LogIndexProvider,
+    ReceiptProvider, StageCheckpointProvider, StateProofProvider, StateProviderFactory,
+    TransactionsProvider,
+};
+use reth_rpc::{
+    AdminApi, AuthLayer, ConnectionLimiter, DebugApi, EngineApi, EthApi, EthConfig, EthFilter,
+    EthPubSub, JwtSecret, NetApi, ResponseCache, RpcLimits, RpcLimitsLayer, TraceApi, TraceConfig,
+    TransactionForwarder, TxPoolApi, Web3Api, DEFAULT_MAX_BATCH_LEN, DEFAULT_MAX_CONNECTIONS,
+    DEFAULT_MAX_REQUEST_SIZE, DEFAULT_MAX_RESPONSE_SIZE, DEFAULT_METHOD_CONCURRENCY,
+};
+use reth_rpc_api::{
+    AdminApiServer, DebugApiServer, EngineApiServer, EthApiServer, EthFilterApiServer,
+    EthPubSubApiServer, NetApiServer, TraceApiServer, TxPoolApiServer, Web3ApiServer,
+};
+use reth_tasks::TaskExecutor;
+use reth_transaction_pool::TransactionPool;
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
+use tracing::info;
+
+/// The default port of the HTTP server.
+pub const DEFAULT_HTTP_PORT: u16 = 8545;
+
+/// The default port of the WebSocket server.
+pub const DEFAULT_WS_PORT: u16 = 8546;
+
+/// The default port of the authenticated Engine API server.
+pub const DEFAULT_AUTH_PORT: u16 = 8551;
+
+/// The size flags are in megabytes.
+const MB: u32 = 1024 * 1024;
+
+/// The flags of the RPC servers.
+///
+/// The namespaces served by each server are part of the configuration, see [RpcConfig].
+#[derive(Debug, Clone, Args)]
+pub struct RpcServerArgs {
+    /// Enable the HTTP server.
+    #[arg(long)]
+    pub http: bool,
+
+    /// The interface of the HTTP server.
+    #[arg(long = "http.addr", value_name = "IP", default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    pub http_addr: IpAddr,
+
+    /// The port of the HTTP server.
+    #[arg(long = "http.port", value_name = "PORT", default_value_t = DEFAULT_HTTP_PORT)]
+    pub http_port: u16,
+
+    /// Enable the WebSocket server.
+    #[arg(long)]
+    pub ws: bool,
+
+    /// The interface of the WebSocket server.
+    #[arg(long = "ws.addr", value_name = "IP", default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    pub ws_addr: IpAddr,
+
+    /// The port of the WebSocket server.
+    #[arg(long = "ws.port", value_name = "PORT", default_value_t = DEFAULT_WS_PORT)]
+    pub ws_port: u16,
+
+    /// The interface of the authenticated Engine API server.
+    #[arg(
+        long = "authrpc.addr",
+        value_name = "IP",
+        default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST)
+    )]
+    pub auth_addr: IpAddr,
+
+    /// The port of the authenticated Engine API server.
+    #[arg(long = "authrpc.port", value_name = "PORT", default_value_t = DEFAULT_AUTH_PORT)]
+    pub auth_port: u16,
+
+    /// The path to the hex encoded JWT secret shared with the consensus client.
+    ///
+    /// A new secret is written to the path if the file doesn't exist. Defaults to `jwt.hex` in
+    /// the OS-specific data directory.
+    #[arg(long = "authrpc.jwtsecret", value_name = "PATH")]
+    pub auth_jwtsecret: Option<PathBuf>,
+
+    /// Disable the IPC server.
+    #[arg(long)]
+    pub ipcdisable: bool,
+
+    /// The path of the IPC endpoint, which serves the namespaces of the HTTP server.
+    ///
+    /// Defaults to `~/.reth/reth.ipc`, or the `reth.ipc` named pipe on Windows.
+    #[arg(long, value_name = "PATH")]
+    pub ipcpath: Option<PathBuf>,
+
+    /// The maximum size of a request in megabytes.
+    #[arg(long = "rpc.max-request-size", value_name = "MB", default_value_t = DEFAULT_MAX_REQUEST_SIZE / MB)]
+    pub max_request_size: u32,
+
+    /// The maximum size of a response in megabytes.
+    #[arg(long = "rpc.max-response-size", value_name = "MB", default_value_t = DEFAULT_MAX_RESPONSE_SIZE / MB)]
+    pub max_response_size: u32,
+
+    /// The maximum number of connections of each of the HTTP, WebSocket and IPC servers.
+    #[arg(long = "rpc.max-connections", value_name = "COUNT", default_value_t = DEFAULT_MAX_CONNECTIONS)]
+    pub max_connections: u32,
+
+    /// The maximum number of connections of each remote IP to the HTTP and WebSocket servers, 0
+    /// for no limit.
+    ///
+    /// The connections are accepted by a proxy in front of the servers then.
+    #[arg(long = "rpc.max-connections-per-ip", value_name = "COUNT", default_value_t = 0)]
+    pub max_connections_per_ip: usize,
+
+    /// The maximum number of calls in a batch of the HTTP and IPC servers, 0 for no limit.
+    #[arg(long = "rpc.max-batch-size", value_name = "COUNT", default_value_t = DEFAULT_MAX_BATCH_LEN)]
+    pub max_batch_size: usize,
+
+    /// Comma separated maximum numbers of concurrent calls of methods of the HTTP, WebSocket and
+    /// IPC servers, e.g. `trace_block=2`.
+    ///
+    /// Overrides the limits of the listed methods, 0 removes the limit of a method. The methods
+    /// that replay blocks are limited to 4 concurrent calls by default.
+    #[arg(
+        long = "rpc.method-concurrency",
+        value_name = "METHOD=COUNT",
+        value_delimiter = ',',
+        value_parser = parse_method_concurrency
+    )]
+    pub method_concurrency: Vec<(String, usize)>,
+
+    /// Forward the transactions of `eth_sendRawTransaction` to the HTTP JSON-RPC endpoint at the
+    /// given URL, e.g. the sequencer of a rollup or a block builder.
+    ///
+    /// The transactions are still added to the local pool and propagated to the peers.
+    #[arg(long = "rpc.tx-forward-url", value_name = "URL")]
+    pub tx_forward_url: Option<String>,
+}
+
+impl Default for RpcServerArgs {
+    fn default() -> Self {
+        Self {
+            http: false,
+            http_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            http_port: DEFAULT_HTTP_PORT,
+            ws: false,
+            ws_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            ws_port: DEFAULT_WS_PORT,
+            auth_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            auth_port: DEFAULT_AUTH_PORT,
+            auth_jwtsecret: None,
+            ipcdisable: false,
+            ipcpath: None,
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE / MB,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE / MB,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_connections_per_ip: 0,
+            max_batch_size: DEFAULT_MAX_BATCH_LEN,
+            method_concurrency: Vec::new(),
+            tx_forward_url: None,
+        }
+    }
+}
+
+// === impl RpcServerArgs ===
+
+impl RpcServerArgs {
+    /// Returns the path of the JWT secret.
+    pub fn jwt_secret_path(&self) -> eyre::Result<PathBuf> {
+        match &self.auth_jwtsecret {
+            Some(path) => Ok(path.clone()),
+            None => data_dir()
+                .map(|dir| dir.join("jwt.hex"))
+                .ok_or_else(|| eyre::eyre!("Could not determine the JWT secret path. Set one.")),
+        }
+    }
+
+    /// Returns the call limits of the HTTP, WebSocket and IPC servers.
+    pub fn limits(&self) -> RpcLimits {
+        let mut method_concurrency = DEFAULT_METHOD_CONCURRENCY
+            .iter()
+            .map(|(method, permits)| (method.to_string(), *permits))
+            .collect::<BTreeMap<_, _>>();
+        method_concurrency.extend(self.method_concurrency.iter().cloned());
+        RpcLimits {
+            max_request_size: self.max_request_size_bytes(),
+            max_batch_len: (self.max_batch_size > 0).then_some(self.max_batch_size),
+            method_concurrency,
+        }
+    }
+
+    /// Returns the settings of the `eth` namespace.
+    pub fn eth_config(&self) -> eyre::Result<EthConfig> {
+        let forwarder = self
+            .tx_forward_url
+            .as_deref()
+            .map(TransactionForwarder::new)
+            .transpose()
+            .wrap_err("Invalid transaction forward URL")?;
+        Ok(EthConfig { forwarder, ..Default::default() })
+    }
+
+    fn max_request_size_bytes(&self) -> u32 {
+        self.max_request_size.saturating_mul(MB)
+    }
+
+    fn max_response_size_bytes(&self) -> u32 {
+        self.max_response_size.saturating_mul(MB)
+    }
+
+    /// Returns the address a server binds to, a local one behind a [ConnectionLimiter] if the
+    /// connections per IP are limited.
+    fn bind_addr(&self, addr: SocketAddr) -> SocketAddr {
+        if self.max_connections_per_ip > 0 {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)
+        } else {
+            addr
+        }
+    }
+
+    /// Accepts the connections of `addr` and forwards them to the server at `server` if the
+    /// connections per IP are limited.
+    ///
+    /// Returns the address the clients connect to.
+    async fn limit_connections(
+        &self,
+        addr: SocketAddr,
+        server: SocketAddr,
+        executor: &TaskExecutor,
+    ) -> eyre::Result<SocketAddr> {
+        if self.max_connections_per_ip == 0 {
+            return Ok(server)
+        }
+        let limiter = ConnectionLimiter::bind(addr, server, self.max_connections_per_ip)
+            .await
+            .wrap_err_with(|| format!("Could not listen at {addr}"))?;
+        let addr = limiter.local_addr()?;
+        executor.spawn_critical("rpc connection limiter", limiter.run(executor.clone()));
+        Ok(addr)
+    }
+
+    /// Returns the path of the IPC endpoint.
+    pub fn ipc_path(&self) -> eyre::Result<PathBuf> {
+        match &self.ipcpath {
+            Some(path) => Ok(path.clone()),
+            None => ipc_path()
+                .ok_or_else(|| eyre::eyre!("Could not determine the IPC endpoint path. Set one.")),
+        }
+    }
+}
+
+/// Parses a `METHOD=COUNT` concurrency limit.
+fn parse_method_concurrency(value: &str) -> eyre::Result<(String, usize)> {
+    let (method, permits) =
+        value.split_once('=').ok_or_else(|| eyre::eyre!("expected METHOD=COUNT, got {value}"))?;
+    Ok((method.to_string(), permits.parse()?))
+}
+
+/// Creates the handlers of the RPC namespaces.
+///
+/// All handlers share one cache of the responses of finalized blocks.
+pub struct RpcRegistry<Client, Pool> {
+    client: Arc<Client>,
+    pool: Pool,
+    network: NetworkHandle,
+    chain_notifications: ChainNotifications,
+    executor: TaskExecutor,
+    eth_config: EthConfig,
+    cache: ResponseCache,
+    extra_methods: Methods,
+}
+
+// === impl RpcRegistry ===
+
+impl<Client, Pool> RpcRegistry<Client, Pool>
+where
+    Client: BlockProvider
+        + HeaderProvider
+        + ReceiptProvider
+        + LogIndexProvider
+        + TransactionsProvider
+        + StateProviderFactory
+        + StageCheckpointProvider
+        + HistoricalRangeProvider
+        + StateProofProvider
+        + 'static,
+    Pool: TransactionPool + Clone + 'static,
+    Pool::Transaction: IntoRecoveredTransaction,
+{
+    /// Creates a new registry for the namespaces of the given client, pool and network.
+    ///
+    /// Subscriptions follow the canonical chain through the given notifications and are served by
+    /// tasks of the executor.
+    pub fn new(
+        client: Arc<Client>,
+        pool: Pool,
+        network: NetworkHandle,
+        chain_notifications: ChainNotifications,
+        executor: TaskExecutor,
+    ) -> Self {
+        Self {
+            client,
+            pool,
+            network,
+            chain_notifications,
+            executor,
+            eth_config: EthConfig::default(),
+            cache: ResponseCache::default(),
+            extra_methods: Methods::new(),
+        }
+    }
+
+    /// Sets the settings of the `eth` namespace.
+    pub fn with_eth_config(mut self, config: EthConfig) -> Self {
+        self.eth_config = config;
+        self
+    }
+
+    /// Serves the given methods on every server in addition to the selected namespaces.
+    pub fn with_extra_methods(mut self, methods: Methods) -> Self {
+        self.extra_methods = methods;
+        self
+    }
+
+    /// Returns the methods of the given namespaces along with the extra methods.
+    pub fn methods(&self, modules: &[RpcModule]) -> eyre::Result<Methods> {
+        let mut methods = Methods::new();
+        for (idx, module) in modules.iter().enumerate() {
+            if modules[..idx].contains(module) {
+                continue
+            }
+            let module_methods: Methods = match module {
+                RpcModule::Admin => {
+                    AdminApi::new(self.network.clone(), self.pool.clone(), self.executor.clone())
+                        .into_rpc()
+                        .into()
+                }
+                RpcModule::Debug => DebugApi::new(self.client.clone()).into_rpc().into(),
+                RpcModule::Eth => self.eth_methods()?,
+                RpcModule::Net => {
+                    NetApi::new(self.network.clone(), Box::new(self.eth_api())).into_rpc().into()
+                }
+                RpcModule::Trace => TraceApi::with_cache(
+                    self.client.clone(),
+                    TraceConfig::default(),
+                    self.cache.clone(),
+                )
+                .into_rpc()
+                .into(),
+                RpcModule::Txpool => TxPoolApi::new(self.pool.clone()).into_rpc().into(),
+                RpcModule::Web3 => Web3Api::new().into_rpc().into(),
+            };
+            methods.merge(module_methods)?;
+        }
+        methods.merge(self.extra_methods.clone())?;
+        Ok(methods)
+    }
+
+    /// Returns the methods of the `eth` namespace, including filters and subscriptions.
+    fn eth_methods(&self) -> eyre::Result<Methods> {
+        let mut methods: Methods = self.eth_api().into_rpc().into();
+        methods.merge(EthFilter::new(self.client.clone()).into_rpc())?;
+        let pubsub = EthPubSub::new(
+            self.client.clone(),
+            self.pool.clone(),
+            self.chain_notifications.clone(),
+            self.executor.clone(),
+        );
+        methods.merge(pubsub.into_rpc())?;
+        Ok(methods)
+    }
+
+    fn eth_api(&self) -> EthApi<Pool, Client> {
+        EthApi::with_config(
+            self.client.clone(),
+            self.pool.clone(),
+            self.cache.clone(),
+            self.eth_config.clone(),
+        )
+    }
+}
+
+impl<Client, Pool> std::fmt::Debug for RpcRegistry<Client, Pool> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcRegistry").finish_non_exhaustive()
+    }
+}
+
+/// The handles of the running RPC servers.
+///
+/// The servers are stopped once the handles are dropped.
+#[derive(Debug)]
+pub struct RpcServerHandles {
+    /// The HTTP server, if enabled.
+    pub http: Option<ServerHandle>,
+    /// The WebSocket server, if enabled.
+    pub ws: Option<ServerHandle>,
+    /// The IPC server, unless disabled.
+    pub ipc: Option<ServerHandle>,
+    /// The authenticated Engine API server.
+    pub auth: ServerHandle,
+}
+
+/// Starts the enabled HTTP, WebSocket and IPC servers with the namespaces of the configuration,
+/// and the authenticated server with the Engine API.
+pub async fn start_servers<Client, Pool>(
+    args: &RpcServerArgs,
+    config: &RpcConfig,
+    registry: &RpcRegistry<Client, Pool>,
+    engine: EngineApi,
+    executor: &TaskExecutor,
+) -> eyre::Result<RpcServerHandles>
+where
+    Client: BlockProvider
+        + HeaderProvider
+        + ReceiptProvider
+        + LogIndexProvider
+        + TransactionsProvider
+        + StateProviderFactory
+        + StageCheckpointProvider
+        + HistoricalRangeProvider
+        + StateProofProvider
+        + 'static,
+    Pool: TransactionPool + Clone + 'static,
+    Pool::Transaction: IntoRecoveredTransaction,
+{
+    let limits = RpcLimitsLayer::new(args.limits());
+
+    let http = if args.http {
+        let addr = SocketAddr::new(args.http_addr, args.http_port);
+        let server = ServerBuilder::default()
+            .http_only()
+            .max_request_body_size(args.max_request_size_bytes())
+            .max_response_body_size(args.max_response_size_bytes())
+            .max_connections(args.max_connections)
+            .set_middleware(tower::ServiceBuilder::new().layer(limits.clone()))
+            .build(args.bind_addr(addr))
+            .await
+            .wrap_err_with(|| format!("Could not start the HTTP server at {addr}"))?;
+        let addr = args.limit_connections(addr, server.local_addr()?, executor).await?;
+        let handle = server.start(registry.methods(&config.http)?)?;
+        info!(target: "reth::cli", %addr, modules = ?config.http, "Started HTTP server");
+        Some(handle)
+    } else {
+        None
+    };
+
+    let ws = if args.ws {
+        let addr = SocketAddr::new(args.ws_addr, args.ws_port);
+        let server = ServerBuilder::default()
+            .ws_only()
+            .max_request_body_size(args.max_request_size_bytes())
+            .max_response_body_size(args.max_response_size_bytes())
+            .max_connections(args.max_connections)
+            .build(args.bind_addr(addr))
+            .await
+            .wrap_err_with(|| format!("Could not start the WebSocket server at {addr}"))?;
+        let addr = args.limit_connections(addr, server.local_addr()?, executor).await?;
+        // the middleware only sees the upgrade request, so the methods are limited instead
+        let handle = server.start(limits.limit_methods(registry.methods(&config.ws)?)?)?;
+        info!(target: "reth::cli", %addr, modules = ?config.ws, "Started WebSocket server");
+        Some(handle)
+    } else {
+        None
+    };
+
+    let ipc = if args.ipcdisable {
+        None
+    } else {
+        let path = args.ipc_path()?;
+        if let Some(dir) = path.parent().filter(|_| cfg!(unix)) {
+            std::fs::create_dir_all(dir).wrap_err_with(|| {
+                format!("Could not create the IPC directory {}", dir.display())
+            })?;
+        }
+        let endpoint = path.to_string_lossy();
+        let server = reth_ipc::server::Builder::default()
+            .max_request_body_size(args.max_request_size_bytes())
+            .max_response_body_size(args.max_response_size_bytes())
+            .max_connections(args.max_connections)
+            .set_middleware(tower::ServiceBuilder::new().layer(limits))
+            .build(&endpoint)
+            .wrap_err_with(|| format!("Could not create the IPC endpoint at {endpoint}"))?;
+        let handle = server.start(registry.methods(&config.http)?).await?;
+        info!(target: "reth::cli", %endpoint, modules = ?config.http, "Started IPC server");
+        Some(handle)
+    };
+
+    let secret_path = args.jwt_secret_path()?;
+    let secret = JwtSecret::load_or_create(&secret_path)?;
+    let addr = SocketAddr::new(args.auth_addr, args.auth_port);
+    let server = ServerBuilder::default()
+        .set_middleware(tower::ServiceBuilder::new().layer(AuthLayer::new(secret)))
+        .build(addr)
+        .await
+        .wrap_err_with(|| format!("Could not start the Engine API server at {addr}"))?;
+    // the consensus client also needs the `eth` namespace on the authenticated port
+    let mut methods = registry.methods(&[RpcModule::Eth])?;
+    methods.merge(engine.into_rpc())?;
+    let auth = server.start(methods)?;
+    info!(target: "reth::cli", %addr, secret = %secret_path.display(), "Started Engine API server");
+
+    Ok(RpcServerHandles { http, ws, ipc, auth })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct CommandParser {
+        #[clap(flatten)]
+        args: RpcServerArgs,
+    }
+
+    #[test]
+    fn default_servers() {
+        let args = CommandParser::parse_from(["reth"]).args;
+        assert!(!args.http && !args.ws && !args.ipcdisable);
+        assert_eq!(args.auth_port, DEFAULT_AUTH_PORT);
+        if cfg!(unix) {
+            assert!(args.ipc_path().unwrap().ends_with(".reth/reth.ipc"));
+        }
+    }
+
+    #[test]
+    fn default_args_match_flags() {
+        let args = CommandParser::parse_from(["reth"]).args;
+        assert_eq!(format!("{:?}", RpcServerArgs::default()), format!("{args:?}"));
+    }
+
+    #[test]
+    fn server_flags() {
+        let args = CommandParser::parse_from([
+            "reth",
+            "--http",
+            "--http.addr",
+            "0.0.0.0",
+            "--authrpc.port",
+            "9551",
+            "--ipcpath",
+            "/tmp/reth.ipc",
+        ])
+        .args;
+        assert!(args.http);
+        assert_eq!(args.http_addr, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(args.http_port, DEFAULT_HTTP_PORT);
+        assert_eq!(args.auth_port, 9551);
+        assert_eq!(args.ipc_path().unwrap(), PathBuf::from("/tmp/reth.ipc"));
+    }
+
+    #[test]
+    fn limit_flags() {
+        let limits = CommandParser::parse_from(["reth"]).args.limits();
+        assert_eq!(limits, RpcLimits::default());
+
+        let args = CommandParser::parse_from([
+            "reth",
+            "--rpc.max-request-size",
+            "1",
+            "--rpc.max-batch-size",
+            "0",
+            "--rpc.method-concurrency",
+            "trace_block=1,trace_filter=0,eth_call=16",
+        ])
+        .args;
+        let limits = args.limits();
+        assert_eq!(limits.max_request_size, 1024 * 1024);
+        assert_eq!(limits.max_batch_len, None);
+        assert_eq!(limits.method_concurrency["trace_block"], 1);
+        assert_eq!(limits.method_concurrency["trace_filter"], 0);
+        assert_eq!(limits.method_concurrency["eth_call"], 16);
+        assert_eq!(limits.method_concurrency["debug_accountRange"], 4);
+
+        assert!(CommandParser::try_parse_from(["reth", "--rpc.method-concurrency", "trace_block"])
+            .is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Command for replaying recorded Engine API calls against the consensus engine.
+
+use crate::util;
+use clap::Parser;
+use std::path::PathBuf;
+use tracing::{error, info};
+/// Models for parsing JSON Engine API tests
+pub mod models;
+/// Engine API test runner
+pub mod runner;
+
+/// Replay the Engine API calls of a consensus client recorded in json files
+///
+/// Every test starts a consensus engine at the genesis block of its chain and compares the
+/// responses to its calls, like interleaved `engine_newPayload` and `engine_forkchoiceUpdated`
+/// calls or invalid payloads, with the recorded ones. The command fails if any test fails.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// Path to the JSON test files
+    path: Vec<PathBuf>,
+}
+
+impl Command {
+    /// Execute the command
+    pub async fn execute(self) -> eyre::Result<()> {
+        let mut num_of_failed = 0;
+        let mut num_of_passed = 0;
+        for file in
+            self.path.iter().flat_map(|item| util::find_all_files_with_postfix(item, ".json"))
+        {
+            let results = match runner::run_test(&file).await {
+                Ok(results) => results,
+                Err(error) => {
+                    num_of_failed += 1;
+                    error!("Test file {file:?} failed:\n {error}\n");
+                    continue
+                }
+            };
+            for (name, result) in results {
+                match result {
+                    Ok(()) => num_of_passed += 1,
+                    Err(error) => {
+                        num_of_failed += 1;
+                        error!("Test {name} of {file:?} failed:\n {error:#}\n");
+                    }
+                }
+            }
+        }
+
+        info!("\nPASSED {num_of_passed}/{} tests\n", num_of_passed + num_of_failed);
+        if num_of_failed > 0 {
+            eyre::bail!("{num_of_failed} of {} tests failed", num_of_passed + num_of_failed)
+        }
+        Ok(())
+    }
+}
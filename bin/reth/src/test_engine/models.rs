@@ -0,0 +1,89 @@
+use crate::util::chainspec::ChainSpecification;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Engine API test deserializer.
+#[derive(Debug, Deserialize)]
+pub struct EngineTest(pub BTreeMap<String, EngineTestData>);
+
+/// A recorded sequence of Engine API calls of a consensus client.
+#[derive(Debug, Deserialize)]
+pub struct EngineTestData {
+    /// The chain the calls are made on, the node starts at its genesis block.
+    pub chain: ChainSpecification,
+    /// The calls in the order they were made.
+    pub steps: Vec<Step>,
+}
+
+/// A call of the Engine API and its expected response.
+///
+/// A call without an expected result or error only has to succeed.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Step {
+    /// The method, e.g. `engine_newPayloadV1`.
+    pub method: String,
+    /// The positional parameters of the call.
+    #[serde(default)]
+    pub params: Vec<Value>,
+    /// The expected result.
+    ///
+    /// Only the fields of the expected objects are compared, e.g. `{ "status": "INVALID" }`
+    /// matches an invalid payload status with any validation error.
+    pub expect: Option<Value>,
+    /// The expected code of the error response.
+    pub expect_error: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_test_deserialize() {
+        let test = r#"{
+            "unknownPayload" : {
+                "chain" : {
+                    "config" : {
+                        "chainId" : 1337,
+                        "homesteadBlock" : 0,
+                        "daoForkBlock" : 0,
+                        "daoForkSupport" : false,
+                        "eip150Block" : 0,
+                        "eip155Block" : 0,
+                        "eip158Block" : 0,
+                        "byzantiumBlock" : 0,
+                        "constantinopleBlock" : 0,
+                        "petersburgBlock" : 0,
+                        "istanbulBlock" : 0,
+                        "muirGlacierBlock" : 0,
+                        "berlinBlock" : 0,
+                        "londonBlock" : 0,
+                        "parisBlock" : 0,
+                        "terminalTotalDifficulty" : 0
+                    },
+                    "nonce" : "0x0",
+                    "timestamp" : "0x0",
+                    "extraData" : "0x",
+                    "gasLimit" : "0x1c9c380",
+                    "difficulty" : "0x0",
+                    "mixHash" : "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "coinbase" : "0x0000000000000000000000000000000000000000",
+                    "stateRoot" : "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "alloc" : {}
+                },
+                "steps" : [
+                    {
+                        "method" : "engine_getPayloadV1",
+                        "params" : ["0x0000000000000001"],
+                        "expectError" : -38001
+                    }
+                ]
+            }
+        }"#;
+
+        let res = serde_json::from_str::<EngineTest>(test);
+        assert!(res.is_ok(), "Failed to deserialize EngineTest with error: {res:?}");
+    }
+}
@@ -0,0 +1,173 @@
+use super::models::{EngineTest, EngineTestData, Step};
+use crate::node::init_genesis;
+use eyre::{eyre, WrapErr};
+use jsonrpsee::{
+    core::{params::ArrayParams, Error as RpcError},
+    types::error::CallError,
+};
+use reth_consensus::engine::EthConsensusEngine;
+use reth_db::mdbx::{test_utils::create_test_rw_db, WriteMap};
+use reth_provider::ProviderImpl;
+use reth_rpc::EngineApi;
+use reth_rpc_api::EngineApiServer;
+use serde_json::Value;
+use std::{path::Path, sync::Arc};
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::debug;
+
+/// Run all Engine API tests of the JSON file at the specified path.
+///
+/// Returns the result of every test by name.
+pub async fn run_test(path: &Path) -> eyre::Result<Vec<(String, eyre::Result<()>)>> {
+    let tests: EngineTest = serde_json::from_slice(&std::fs::read(path)?)?;
+    let mut results = Vec::with_capacity(tests.0.len());
+    for (name, test) in tests.0 {
+        debug!("Executing test: {name}");
+        let result = run_engine_test(test).await;
+        results.push((name, result));
+    }
+    Ok(results)
+}
+
+/// Replays the calls of the test against a fresh consensus engine that only knows the genesis
+/// block of the chain.
+///
+/// The engine runs without a pipeline, so chains that are not buffered or stored are never
+/// synced.
+pub async fn run_engine_test(test: EngineTestData) -> eyre::Result<()> {
+    let db = create_test_rw_db::<WriteMap>();
+    init_genesis(db.clone(), test.chain.genesis.clone())?;
+    let provider = Arc::new(ProviderImpl::new(db));
+
+    let (engine_tx, engine_rx) = unbounded_channel();
+    tokio::task::spawn(EthConsensusEngine::new(test.chain.consensus.clone(), provider, engine_rx));
+    // the engine stops once the API is dropped
+    let api = EngineApi::new(engine_tx, test.chain.consensus).into_rpc();
+
+    for (index, step) in test.steps.iter().enumerate() {
+        let mut params = ArrayParams::new();
+        for param in &step.params {
+            params.insert(param)?;
+        }
+        let response = api.call::<_, Value>(&step.method, params).await;
+        check_response(step, response)
+            .wrap_err_with(|| format!("Step {index} calling {} failed", step.method))?;
+    }
+    Ok(())
+}
+
+/// Compares the response of a call with the expected one.
+fn check_response(step: &Step, response: Result<Value, RpcError>) -> eyre::Result<()> {
+    match (response, step.expect_error) {
+        (Ok(result), None) => match &step.expect {
+            Some(expected) if !matches_expected(expected, &result) => {
+                Err(eyre!("Expected result {expected}, got {result}"))
+            }
+            _ => Ok(()),
+        },
+        (Ok(result), Some(code)) => Err(eyre!("Expected error {code}, got result {result}")),
+        (Err(RpcError::Call(CallError::Custom(err))), Some(code)) if err.code() == code => Ok(()),
+        (Err(err), _) => Err(eyre!("Unexpected error: {err}")),
+    }
+}
+
+/// Returns true if the actual value has all fields of the expected objects, with equal values.
+fn matches_expected(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected.iter().all(|(key, value)| {
+            actual.get(key).map_or(false, |actual| matches_expected(value, actual))
+        }),
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len() &&
+                expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| matches_expected(expected, actual))
+        }
+        (expected, actual) => expected == actual,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::chainspec::ChainSpecification;
+    use reth_primitives::{Header, H256};
+    use serde_json::json;
+
+    fn step(method: &str, params: Vec<Value>, expect: Option<Value>, error: Option<i32>) -> Step {
+        Step { method: method.to_string(), params, expect, expect_error: error }
+    }
+
+    #[test]
+    fn match_expected_fields() {
+        let actual =
+            json!({ "status": "INVALID", "validationError": "bad", "latestValidHash": null });
+        assert!(matches_expected(&json!({ "status": "INVALID" }), &actual));
+        assert!(matches_expected(&json!({ "latestValidHash": null }), &actual));
+        assert!(!matches_expected(&json!({ "status": "VALID" }), &actual));
+        assert!(!matches_expected(&json!({ "payloadId": null }), &actual));
+        assert!(!matches_expected(&json!([1]), &json!([1, 2])));
+    }
+
+    #[tokio::test]
+    async fn replay_engine_calls() {
+        let chain = ChainSpecification::default();
+        let genesis_hash = Header::from(chain.genesis.clone()).hash_slow();
+        let forkchoice = |head: H256| {
+            json!({
+                "headBlockHash": head,
+                "safeBlockHash": head,
+                "finalizedBlockHash": head,
+            })
+        };
+        let payload = json!({
+            "parentHash": genesis_hash,
+            "feeRecipient": "0x0000000000000000000000000000000000000000",
+            "stateRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "prevRandao": H256::zero(),
+            "blockNumber": "0x1",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x1",
+            "extraData": "0x",
+            "baseFeePerGas": "0x7",
+            "blockHash": H256::zero(),
+            "transactions": [],
+        });
+
+        let steps = vec![
+            step(
+                "engine_newPayloadV1",
+                vec![payload],
+                Some(json!({ "status": "INVALID_BLOCK_HASH" })),
+                None,
+            ),
+            step(
+                "engine_forkchoiceUpdatedV1",
+                vec![forkchoice(H256::repeat_byte(1))],
+                Some(json!({ "payloadStatus": { "status": "SYNCING" } })),
+                None,
+            ),
+            step(
+                "engine_forkchoiceUpdatedV1",
+                vec![forkchoice(genesis_hash)],
+                Some(json!({ "payloadStatus": { "status": "VALID" }, "payloadId": null })),
+                None,
+            ),
+            step("engine_getPayloadV1", vec![json!("0x0000000000000001")], None, Some(-38001)),
+        ];
+        run_engine_test(EngineTestData { chain: chain.clone(), steps }).await.unwrap();
+
+        // a mismatching response fails the test
+        let steps = vec![step(
+            "engine_forkchoiceUpdatedV1",
+            vec![forkchoice(genesis_hash)],
+            Some(json!({ "payloadStatus": { "status": "SYNCING" } })),
+            None,
+        )];
+        assert!(run_engine_test(EngineTestData { chain, steps }).await.is_err());
+    }
+}
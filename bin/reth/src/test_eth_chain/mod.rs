@@ -2,49 +2,90 @@
 
 use crate::util;
 use clap::Parser;
-use std::path::PathBuf;
+use futures::StreamExt;
+use models::ForkSpec;
+use runner::{Outcome, Status, Summary};
+use std::{fs::File, path::PathBuf};
 use tracing::{error, info};
 /// Models for parsing JSON blockchain tests
 pub mod models;
 /// Ethereum blockhain test runner
 pub mod runner;
 
-/// Execute Ethereum blockchain tests by specifying path to json files
+/// Execute Ethereum blockchain and state tests by specifying path to json files
+///
+/// Files of the BlockchainTests and GeneralStateTests suites are told apart by their content. The
+/// command fails if any test fails.
 #[derive(Debug, Parser)]
 pub struct Command {
     /// Path to Ethereum JSON test files
     path: Vec<PathBuf>,
+
+    /// Only run the tests of the given comma separated forks, e.g. `Berlin,London`
+    #[arg(long, value_name = "FORKS", value_delimiter = ',')]
+    fork: Vec<ForkSpec>,
+
+    /// The number of test files that are run in parallel, defaults to the number of CPUs
+    #[arg(long, short)]
+    jobs: Option<usize>,
+
+    /// Write the outcomes of all tests as JSON to the given file
+    #[arg(long, value_name = "FILE")]
+    summary: Option<PathBuf>,
 }
 
 impl Command {
     /// Execute the command
     pub async fn execute(self) -> eyre::Result<()> {
-        // note the use of `into_iter()` to consume `items`
-        let futs: Vec<_> = self
+        let jobs = self
+            .jobs
+            .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+            .unwrap_or(1)
+            .max(1);
+        let files: Vec<_> = self
             .path
             .iter()
             .flat_map(|item| util::find_all_files_with_postfix(item, ".json"))
-            .map(|file| async { (runner::run_test(file.clone()).await, file) })
             .collect();
 
-        let results = futures::future::join_all(futs).await;
-        // await the tasks for resolve's to complete and give back our test results
-        let mut num_of_failed = 0;
-        let mut num_of_passed = 0;
-        for (result, file) in results {
-            match result {
-                Ok(_) => {
-                    num_of_passed += 1;
-                }
-                Err(error) => {
-                    num_of_failed += 1;
-                    error!("Test {file:?} failed:\n {error}\n");
+        let outcomes: Vec<Outcome> = futures::stream::iter(files)
+            .map(|file| {
+                let forks = self.fork.clone();
+                async move {
+                    // a panicking test only fails its file
+                    tokio::spawn(runner::run_test(file.clone(), forks))
+                        .await
+                        .unwrap_or_else(|err| vec![Outcome::file_failed(file, err.into())])
                 }
-            }
+            })
+            .buffer_unordered(jobs)
+            .concat()
+            .await;
+
+        let summary = Summary::new(outcomes);
+        for outcome in summary.tests.iter().filter(|outcome| outcome.status == Status::Failed) {
+            error!(
+                "Test {} {:?} failed:\n {}\n",
+                outcome.name,
+                outcome.file,
+                outcome.error.as_deref().unwrap_or_default()
+            );
         }
+        info!(
+            "\nPASSED {}/{} tests, {} skipped\n",
+            summary.passed,
+            summary.passed + summary.failed,
+            summary.skipped
+        );
 
-        info!("\nPASSED {num_of_passed}/{} tests\n", num_of_passed + num_of_failed);
+        if let Some(path) = &self.summary {
+            serde_json::to_writer_pretty(File::create(path)?, &summary)?;
+            info!("Summary written to {}", path.display());
+        }
 
+        if summary.failed > 0 {
+            eyre::bail!("{} of {} tests failed", summary.failed, summary.passed + summary.failed)
+        }
         Ok(())
     }
 }
@@ -2,8 +2,12 @@ use reth_primitives::{
     Address, BigEndianHash, Bloom, Bytes, Header as RethHeader, JsonU256, SealedHeader, H160, H256,
     H64,
 };
-use serde::{self, Deserialize};
-use std::collections::BTreeMap;
+use serde::{
+    self,
+    de::{value::Error as ValueError, IntoDeserializer},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{collections::BTreeMap, str::FromStr};
 
 /// Blockchain test deserializer.
 #[derive(Debug, PartialEq, Eq, Deserialize)]
@@ -159,7 +163,7 @@ pub struct Account {
 }
 
 /// Fork Spec
-#[derive(Debug, PartialEq, Eq, PartialOrd, Hash, Ord, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash, Ord, Deserialize, Serialize)]
 pub enum ForkSpec {
     /// Frontier
     Frontier,
@@ -198,16 +202,25 @@ pub enum ForkSpec {
     /// Paris aka The Merge
     Merge,
     /// Merge EOF test
-    #[serde(alias = "Merge+3540+3670")]
+    #[serde(rename = "Merge+3540+3670", alias = "MergeEOF")]
     MergeEOF,
     /// After Merge Init Code test
-    #[serde(alias = "Merge+3860")]
+    #[serde(rename = "Merge+3860", alias = "MergeMeterInitCode")]
     MergeMeterInitCode,
     /// After Merge plus new PUSH0 opcode
-    #[serde(alias = "Merge+3855")]
+    #[serde(rename = "Merge+3855", alias = "MergePush0")]
     MergePush0,
 }
 
+/// Parses the name of the fork used in the tests.
+impl FromStr for ForkSpec {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
 impl From<ForkSpec> for reth_executor::SpecUpgrades {
     fn from(fork_spec: ForkSpec) -> Self {
         match fork_spec {
@@ -294,6 +307,130 @@ pub struct AccessListItem {
 /// Access list.
 pub type AccessList = Vec<AccessListItem>;
 
+/// General state test deserializer.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+pub struct StateTest(pub BTreeMap<String, StateTestData>);
+
+/// Ethereum general state test data.
+///
+/// A state test executes a single transaction on top of the pre state, once for every variant of
+/// the transaction and every fork of the post states.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+pub struct StateTestData {
+    /// The block the transaction is executed in.
+    pub env: Env,
+    /// Pre state.
+    pub pre: State,
+    /// The expected results of the variants of the transaction by fork.
+    pub post: BTreeMap<ForkSpec, Vec<PostState>>,
+    /// The transaction.
+    pub transaction: StateTransaction,
+}
+
+/// The block of a state test.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Env {
+    /// Coinbase.
+    pub current_coinbase: Address,
+    /// Difficulty.
+    pub current_difficulty: JsonU256,
+    /// Gas limit.
+    pub current_gas_limit: JsonU256,
+    /// Block number.
+    pub current_number: JsonU256,
+    /// Timestamp.
+    pub current_timestamp: JsonU256,
+    /// Base fee per gas.
+    pub current_base_fee: Option<JsonU256>,
+    /// Prev randao.
+    pub current_random: Option<H256>,
+    /// Parent hash.
+    pub previous_hash: H256,
+}
+
+impl From<&Env> for RethHeader {
+    fn from(env: &Env) -> Self {
+        RethHeader {
+            parent_hash: env.previous_hash,
+            beneficiary: env.current_coinbase,
+            difficulty: env.current_difficulty.0,
+            number: env.current_number.0.as_u64(),
+            gas_limit: env.current_gas_limit.0.as_u64(),
+            timestamp: env.current_timestamp.0.as_u64(),
+            mix_hash: env.current_random.unwrap_or_default(),
+            base_fee_per_gas: env.current_base_fee.as_ref().map(|fee| fee.0.as_u64()),
+            ..Default::default()
+        }
+    }
+}
+
+/// The expected result of a variant of the transaction of a state test.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostState {
+    /// State root after the transaction.
+    pub hash: H256,
+    /// Hash of the RLP encoded logs of the transaction.
+    pub logs: H256,
+    /// The variant of the transaction.
+    pub indexes: Indexes,
+    /// The signed transaction.
+    pub txbytes: Option<Bytes>,
+    /// The reason the transaction is invalid, the state is unchanged then.
+    pub expect_exception: Option<String>,
+}
+
+/// The indexes of the data, the gas limit and the value of a variant of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Indexes {
+    /// Index of the data.
+    pub data: usize,
+    /// Index of the gas limit.
+    pub gas: usize,
+    /// Index of the value.
+    pub value: usize,
+}
+
+/// The transaction of a state test with all its variants.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateTransaction {
+    /// Data of the variants.
+    pub data: Vec<Bytes>,
+    /// Gas limits of the variants.
+    pub gas_limit: Vec<JsonU256>,
+    /// Values of the variants.
+    pub value: Vec<JsonU256>,
+    /// Access lists of the variants, by the index of their data.
+    pub access_lists: Option<Vec<Option<AccessList>>>,
+    /// Gas price.
+    pub gas_price: Option<JsonU256>,
+    /// Max fee per gas.
+    pub max_fee_per_gas: Option<JsonU256>,
+    /// Max priority fee per gas.
+    pub max_priority_fee_per_gas: Option<JsonU256>,
+    /// Nonce.
+    pub nonce: JsonU256,
+    /// The key the transaction is signed with.
+    pub secret_key: H256,
+    /// The recipient, `None` for contract creations.
+    #[serde(deserialize_with = "deserialize_recipient")]
+    pub to: Option<Address>,
+}
+
+/// Deserializes the recipient of a transaction, which is empty for contract creations.
+fn deserialize_recipient<'de, D>(deserializer: D) -> Result<Option<Address>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let to = String::deserialize(deserializer)?;
+    if to.is_empty() {
+        return Ok(None)
+    }
+    to.trim_start_matches("0x").parse().map(Some).map_err(serde::de::Error::custom)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -470,4 +607,89 @@ mod test {
         let res = serde_json::from_str::<Vec<Transaction>>(test);
         assert!(res.is_ok(), "Failed to deserialize transactin with error: {res:?}");
     }
+
+    #[test]
+    fn state_test_deserialize() {
+        let test = r#"{
+            "add11" : {
+                "env" : {
+                    "currentBaseFee" : "0x0a",
+                    "currentCoinbase" : "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+                    "currentDifficulty" : "0x020000",
+                    "currentGasLimit" : "0xff112233445566",
+                    "currentNumber" : "0x01",
+                    "currentTimestamp" : "0x03e8",
+                    "previousHash" : "0x5e20a0453cecd065ea59c37ac63e079ee08998b6045136a8ce6635c7912ec0b6"
+                },
+                "post" : {
+                    "Berlin" : [
+                        {
+                            "hash" : "0xe0f6a4b1112a3bd1f8fbf4e3a1d2bc6f7d4c6a9e2f48497a7d1ef5e2a8b8a3d4",
+                            "indexes" : {
+                                "data" : 0,
+                                "gas" : 0,
+                                "value" : 0
+                            },
+                            "logs" : "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                            "txbytes" : "0xf8"
+                        }
+                    ],
+                    "Merge+3860" : [
+                        {
+                            "expectException" : "TR_InitCodeLimitExceeded",
+                            "hash" : "0xe0f6a4b1112a3bd1f8fbf4e3a1d2bc6f7d4c6a9e2f48497a7d1ef5e2a8b8a3d4",
+                            "indexes" : {
+                                "data" : 0,
+                                "gas" : 0,
+                                "value" : 0
+                            },
+                            "logs" : "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+                        }
+                    ]
+                },
+                "pre" : {
+                    "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b" : {
+                        "balance" : "0x0ba1a9ce0ba1a9ce",
+                        "code" : "0x",
+                        "nonce" : "0x00",
+                        "storage" : {
+                        }
+                    }
+                },
+                "transaction" : {
+                    "data" : [
+                        "0x"
+                    ],
+                    "gasLimit" : [
+                        "0x04c4b400"
+                    ],
+                    "gasPrice" : "0x0a",
+                    "nonce" : "0x00",
+                    "secretKey" : "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
+                    "sender" : "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+                    "to" : "",
+                    "value" : [
+                        "0x01"
+                    ]
+                }
+            }
+        }"#;
+
+        let test = serde_json::from_str::<StateTest>(test).unwrap();
+        let test = &test.0["add11"];
+        assert_eq!(test.transaction.to, None);
+        assert_eq!(
+            test.post.keys().copied().collect::<Vec<_>>(),
+            vec![ForkSpec::Berlin, ForkSpec::MergeMeterInitCode]
+        );
+        assert!(test.post[&ForkSpec::MergeMeterInitCode][0].expect_exception.is_some());
+    }
+
+    #[test]
+    fn parse_fork_spec() {
+        assert_eq!("London".parse::<ForkSpec>().unwrap(), ForkSpec::London);
+        assert_eq!("Merge+3855".parse::<ForkSpec>().unwrap(), ForkSpec::MergePush0);
+        assert_eq!("MergePush0".parse::<ForkSpec>().unwrap(), ForkSpec::MergePush0);
+        assert!("Shanghai".parse::<ForkSpec>().is_err());
+    }
 }
@@ -1,5 +1,7 @@
-use super::models::Test;
-use crate::test_eth_chain::models::{ForkSpec, RootOrState};
+use super::models::{
+    BlockchainTestData, ForkSpec, Indexes, PostState, RootOrState, State, StateTest, StateTestData,
+    StateTransaction, Test,
+};
 use eyre::eyre;
 use reth_db::{
     cursor::DbCursorRO,
@@ -9,13 +11,25 @@ use reth_db::{
     transaction::{DbTx, DbTxMut},
     Error as DbError,
 };
-use reth_executor::SpecUpgrades;
+use reth_executor::{
+    executor::execute_transactions,
+    revm_wrap::{State as RevmState, SubState},
+    SpecUpgrades,
+};
+use reth_interfaces::test_utils::generators::sign_message;
 use reth_primitives::{
-    keccak256, Account as RethAccount, Address, JsonU256, SealedBlock, SealedHeader, StorageEntry,
-    H256, U256,
+    keccak256,
+    proofs::{calculate_log_root, calculate_state_root, calculate_storage_root},
+    AccessList, AccessListItem, Account as RethAccount, Address, BigEndianHash, Header, JsonU256,
+    SealedBlock, SealedHeader, StorageEntry, Transaction, TransactionKind, TransactionSigned,
+    TransactionSignedEcRecovered, TxEip1559, TxEip2930, TxLegacy, H256, U256,
 };
+use reth_provider::StateProviderImplRefLatest;
 use reth_rlp::Decodable;
-use reth_stages::{stages::execution::ExecutionStage, ExecInput, Stage, Transaction};
+use reth_stages::{
+    stages::execution::ExecutionStage, ExecInput, Stage, Transaction as DbTransaction,
+};
+use serde::Serialize;
 use std::{
     collections::HashMap,
     ffi::OsStr,
@@ -70,194 +84,494 @@ pub fn should_skip(path: &Path) -> bool {
     false
 }
 
-/// Run one JSON-encoded Ethereum blockchain test at the specified path.
-pub async fn run_test(path: PathBuf) -> eyre::Result<()> {
-    let path = path.as_path();
-    let json_file = std::fs::read(path)?;
-    let suites: Test = serde_json::from_reader(&*json_file)?;
+/// Whether the tests of the fork can be run.
+///
+/// Constantinople was replaced by Petersburg on mainnet, the other forks are not supported by the
+/// executor yet.
+fn is_supported(fork: ForkSpec) -> bool {
+    !matches!(
+        fork,
+        ForkSpec::ByzantiumToConstantinopleAt5 |
+            ForkSpec::Constantinople |
+            ForkSpec::MergeEOF |
+            ForkSpec::MergeMeterInitCode |
+            ForkSpec::MergePush0,
+    )
+}
 
-    if should_skip(path) {
-        return Ok(())
-    }
+/// The status of a test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The test passed.
+    Passed,
+    /// The test failed.
+    Failed,
+    /// The test was not run.
+    Skipped,
+}
 
-    for (name, suite) in suites.0 {
-        if matches!(
-            suite.network,
-            ForkSpec::ByzantiumToConstantinopleAt5 |
-                ForkSpec::Constantinople |
-                ForkSpec::MergeEOF |
-                ForkSpec::MergeMeterInitCode |
-                ForkSpec::MergePush0,
-        ) {
-            continue
-        }
+/// The outcome of a test for a fork.
+#[derive(Debug, Clone, Serialize)]
+pub struct Outcome {
+    /// The file of the test.
+    pub file: PathBuf,
+    /// The name of the test, state tests are suffixed with the indexes of the transaction.
+    pub name: String,
+    /// The fork the test was run for, `None` if the file could not be run at all.
+    pub fork: Option<ForkSpec>,
+    /// The status of the test.
+    pub status: Status,
+    /// Why the test failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
 
-        // if matches!(suite.pre, State(RootOrState::Root(_))) {}
+// === impl Outcome ===
 
-        let pre_state = suite.pre.0;
+impl Outcome {
+    /// Creates the outcome of a test with the result of its run.
+    pub fn new(
+        file: PathBuf,
+        name: String,
+        fork: Option<ForkSpec>,
+        result: eyre::Result<Status>,
+    ) -> Self {
+        let (status, error) = match result {
+            Ok(status) => (status, None),
+            Err(err) => (Status::Failed, Some(format!("{err:#}"))),
+        };
+        Self { file, name, fork, status, error }
+    }
 
-        debug!("Executing test: {name} for spec: {:?}", suite.network);
+    /// Creates the outcome of a file that could not be run.
+    pub fn file_failed(file: PathBuf, error: eyre::Report) -> Self {
+        let name = file.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        Self::new(file, name, None, Err(error))
+    }
+}
 
-        let spec_upgrades: SpecUpgrades = suite.network.into();
-        // if paris aka merge is not activated we dont have block rewards;
-        let has_block_reward = spec_upgrades.paris != 0;
+/// The outcomes of all tests of a run.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    /// The number of passed tests.
+    pub passed: usize,
+    /// The number of failed tests.
+    pub failed: usize,
+    /// The number of skipped tests.
+    pub skipped: usize,
+    /// The outcomes of all tests, sorted by file and name.
+    pub tests: Vec<Outcome>,
+}
 
-        // Create db and acquire transaction
-        let db = create_test_rw_db::<WriteMap>();
-        let tx = db.tx_mut()?;
+// === impl Summary ===
 
-        // insert genesis
-        let header: SealedHeader = suite.genesis_block_header.into();
-        let genesis_block = SealedBlock { header, body: vec![], ommers: vec![] };
-        reth_provider::insert_canonical_block(&tx, &genesis_block, has_block_reward)?;
+impl Summary {
+    /// Creates the summary of the outcomes.
+    pub fn new(mut tests: Vec<Outcome>) -> Self {
+        tests.sort_by(|a, b| (&a.file, &a.name, a.fork).cmp(&(&b.file, &b.name, b.fork)));
+        let count = |status| tests.iter().filter(|test| test.status == status).count();
+        let (passed, failed, skipped) =
+            (count(Status::Passed), count(Status::Failed), count(Status::Skipped));
+        Self { passed, failed, skipped, tests }
+    }
+}
 
-        suite.blocks.iter().try_for_each(|block| -> eyre::Result<()> {
-            let decoded = SealedBlock::decode(&mut block.rlp.as_ref())?;
-            reth_provider::insert_canonical_block(&tx, &decoded, has_block_reward)?;
-            Ok(())
-        })?;
+/// A JSON test file of one of the suites.
+enum TestFile {
+    /// A file of the BlockchainTests.
+    Blockchain(Test),
+    /// A file of the GeneralStateTests.
+    State(StateTest),
+}
 
-        pre_state.into_iter().try_for_each(|(address, account)| -> eyre::Result<()> {
-            let has_code = !account.code.is_empty();
-            let code_hash = if has_code { Some(keccak256(&account.code)) } else { None };
-            tx.put::<tables::PlainAccountState>(
-                address,
-                RethAccount {
-                    balance: account.balance.0,
-                    nonce: account.nonce.0.as_u64(),
-                    bytecode_hash: code_hash,
-                },
-            )?;
-            if let Some(code_hash) = code_hash {
-                tx.put::<tables::Bytecodes>(code_hash, account.code.to_vec())?;
-            }
-            account.storage.iter().try_for_each(|(k, v)| {
-                tracing::trace!("Update storage: {address} key:{:?} val:{:?}", k.0, v.0);
-                let mut key = H256::zero();
-                k.0.to_big_endian(&mut key.0);
-                tx.put::<tables::PlainStorageState>(address, StorageEntry { key, value: v.0 })
-            })?;
+/// Reads the test file, telling the suites apart by their tests.
+fn read_test_file(path: &Path) -> eyre::Result<TestFile> {
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    // state tests have a single transaction instead of blocks
+    let is_state_test = json
+        .as_object()
+        .and_then(|tests| tests.values().next())
+        .map_or(false, |test| test.get("transaction").is_some());
+    Ok(if is_state_test {
+        TestFile::State(serde_json::from_value(json)?)
+    } else {
+        TestFile::Blockchain(serde_json::from_value(json)?)
+    })
+}
 
-            Ok(())
-        })?;
+/// Run the JSON-encoded Ethereum blockchain or state tests at the specified path.
+///
+/// Only the tests of the given forks are run, all tests if there are none.
+pub async fn run_test(path: PathBuf, forks: Vec<ForkSpec>) -> Vec<Outcome> {
+    let test_file = match read_test_file(&path) {
+        Ok(test_file) => test_file,
+        Err(err) => return vec![Outcome::file_failed(path, err)],
+    };
+    let skip = should_skip(&path);
+    let selected = |fork: ForkSpec| forks.is_empty() || forks.contains(&fork);
 
-        // Commit the pre suite state
-        tx.commit()?;
-
-        let storage = db.view(|tx| -> Result<_, DbError> {
-            let mut cursor = tx.cursor_dup::<tables::PlainStorageState>()?;
-            let walker = cursor.first()?.map(|first| cursor.walk(first.0)).transpose()?;
-            Ok(walker.map(|mut walker| {
-                let mut map: HashMap<Address, HashMap<U256, U256>> = HashMap::new();
-                while let Some(Ok((address, slot))) = walker.next() {
-                    let key = U256::from_big_endian(&slot.key.0);
-                    map.entry(address).or_default().insert(key, slot.value);
+    let mut outcomes = Vec::new();
+    match test_file {
+        TestFile::Blockchain(suites) => {
+            for (name, suite) in suites.0 {
+                let fork = suite.network;
+                if !selected(fork) {
+                    continue
                 }
-                map
-            }))
-        })??;
-        tracing::trace!("Pre state :{:?}", storage);
-
-        // Initialize the execution stage
-        // Hardcode the chain_id to Ethereum 1.
-        let mut stage =
-            ExecutionStage::new(reth_executor::Config { chain_id: 1.into(), spec_upgrades });
-
-        // Call execution stage
-        let input = ExecInput::default();
-        {
-            let mut transaction = Transaction::new(db.as_ref())?;
-
-            // ignore error
-            let _ = stage.execute(&mut transaction, input).await;
-            transaction.commit()?;
-        }
-
-        // Validate post state
-        match suite.post_state {
-            Some(RootOrState::Root(root)) => {
-                info!("Post state is root: #{root:?}")
+                let result = if skip || !is_supported(fork) {
+                    Ok(Status::Skipped)
+                } else {
+                    run_blockchain_test(&name, suite).await.map(|_| Status::Passed)
+                };
+                outcomes.push(Outcome::new(path.clone(), name, Some(fork), result));
             }
-            Some(RootOrState::State(state)) => db.view(|tx| -> eyre::Result<()> {
-                let mut cursor = tx.cursor_dup::<tables::PlainStorageState>()?;
-                let walker = cursor.first()?.map(|first| cursor.walk(first.0)).transpose()?;
-                let storage = walker.map(|mut walker| {
-                    let mut map: HashMap<Address, HashMap<U256, U256>> = HashMap::new();
-                    while let Some(Ok((address, slot))) = walker.next() {
-                        let key = U256::from_big_endian(&slot.key.0);
-                        map.entry(address).or_default().insert(key, slot.value);
-                    }
-                    map
-                });
-                tracing::trace!("Our storage:{:?}", storage);
-                for (address, test_account) in state.iter() {
-                    // check account
-                    let our_account = tx
-                        .get::<tables::PlainAccountState>(*address)?
-                        .ok_or(eyre!("Account is missing:{address} expected:{:?}", test_account))?;
-                    if test_account.balance.0 != our_account.balance {
-                        return Err(eyre!(
-                            "Account {address} balance diff, expected {} got{}",
-                            test_account.balance.0,
-                            our_account.balance
-                        ))
-                    }
-                    if test_account.nonce.0.as_u64() != our_account.nonce {
-                        return Err(eyre!(
-                            "Account {address} nonce diff, expected {} got {}",
-                            test_account.nonce.0,
-                            our_account.nonce
-                        ))
+        }
+        TestFile::State(suites) => {
+            for (name, suite) in suites.0 {
+                for (&fork, posts) in suite.post.iter().filter(|(fork, _)| selected(**fork)) {
+                    for post in posts {
+                        let Indexes { data, gas, value } = post.indexes;
+                        let result = if skip || !is_supported(fork) {
+                            Ok(Status::Skipped)
+                        } else {
+                            debug!("Executing test: {name} for spec: {fork:?}");
+                            run_state_test(&suite, fork, post).map(|_| Status::Passed)
+                        };
+                        let name = format!("{name}_d{data}g{gas}v{value}");
+                        outcomes.push(Outcome::new(path.clone(), name, Some(fork), result));
                     }
-                    if let Some(our_bytecode) = our_account.bytecode_hash {
-                        let test_bytecode = keccak256(test_account.code.as_ref());
-                        if our_bytecode != test_bytecode {
-                            return Err(eyre!(
-                                "Account {address} bytecode diff, expected: {} got: {:?}",
-                                test_account.code,
-                                our_account.bytecode_hash
-                            ))
-                        }
-                    } else if !test_account.code.is_empty() {
+                }
+            }
+        }
+    }
+    outcomes
+}
+
+/// Run one blockchain test: insert its blocks, execute them and compare the post state.
+async fn run_blockchain_test(name: &str, suite: BlockchainTestData) -> eyre::Result<()> {
+    debug!("Executing test: {name} for spec: {:?}", suite.network);
+
+    let spec_upgrades: SpecUpgrades = suite.network.into();
+    // if paris aka merge is not activated we dont have block rewards;
+    let has_block_reward = spec_upgrades.paris != 0;
+
+    // Create db and acquire transaction
+    let db = create_test_rw_db::<WriteMap>();
+    let tx = db.tx_mut()?;
+
+    // insert genesis
+    let header: SealedHeader = suite.genesis_block_header.into();
+    let genesis_block = SealedBlock { header, body: vec![], ommers: vec![] };
+    reth_provider::insert_canonical_block(&tx, &genesis_block, has_block_reward)?;
+
+    suite.blocks.iter().try_for_each(|block| -> eyre::Result<()> {
+        let decoded = SealedBlock::decode(&mut block.rlp.as_ref())?;
+        reth_provider::insert_canonical_block(&tx, &decoded, has_block_reward)?;
+        Ok(())
+    })?;
+
+    insert_pre_state(&tx, suite.pre)?;
+
+    // Commit the pre suite state
+    tx.commit()?;
+
+    let storage = db.view(|tx| read_storage(tx))??;
+    tracing::trace!("Pre state :{:?}", storage);
+
+    // Initialize the execution stage
+    // Hardcode the chain_id to Ethereum 1.
+    let mut stage = ExecutionStage::new(reth_executor::Config {
+        chain_id: 1.into(),
+        spec_upgrades,
+        hooks: Default::default(),
+    });
+
+    // Call execution stage
+    let input = ExecInput::default();
+    {
+        let mut transaction = DbTransaction::new(db.as_ref())?;
+
+        // ignore error
+        let _ = stage.execute(&mut transaction, input).await;
+        transaction.commit()?;
+    }
+
+    // Validate post state
+    match suite.post_state {
+        Some(RootOrState::Root(root)) => {
+            info!("Post state is root: #{root:?}")
+        }
+        Some(RootOrState::State(state)) => db.view(|tx| -> eyre::Result<()> {
+            let storage = read_storage(tx)?;
+            tracing::trace!("Our storage:{:?}", storage);
+            for (address, test_account) in state.iter() {
+                // check account
+                let our_account = tx
+                    .get::<tables::PlainAccountState>(*address)?
+                    .ok_or(eyre!("Account is missing:{address} expected:{:?}", test_account))?;
+                if test_account.balance.0 != our_account.balance {
+                    return Err(eyre!(
+                        "Account {address} balance diff, expected {} got{}",
+                        test_account.balance.0,
+                        our_account.balance
+                    ))
+                }
+                if test_account.nonce.0.as_u64() != our_account.nonce {
+                    return Err(eyre!(
+                        "Account {address} nonce diff, expected {} got {}",
+                        test_account.nonce.0,
+                        our_account.nonce
+                    ))
+                }
+                if let Some(our_bytecode) = our_account.bytecode_hash {
+                    let test_bytecode = keccak256(test_account.code.as_ref());
+                    if our_bytecode != test_bytecode {
                         return Err(eyre!(
-                            "Account {address} bytecode diff, expected {} got empty bytecode",
+                            "Account {address} bytecode diff, expected: {} got: {:?}",
                             test_account.code,
+                            our_account.bytecode_hash
                         ))
                     }
+                } else if !test_account.code.is_empty() {
+                    return Err(eyre!(
+                        "Account {address} bytecode diff, expected {} got empty bytecode",
+                        test_account.code,
+                    ))
+                }
 
-                    // get walker if present
-                    if let Some(storage) = storage.as_ref() {
-                        // iterate over storages
-                        for (JsonU256(key), JsonU256(value)) in test_account.storage.iter() {
-                            let our_value = storage
-                                .get(address)
-                                .ok_or(eyre!(
-                                    "Missing storage from test {storage:?} got {:?}",
-                                    test_account.storage
-                                ))?
-                                .get(key)
-                                .ok_or(eyre!(
-                                    "Slot is missing from table {storage:?} got:{:?}",
-                                    test_account.storage
-                                ))?;
-                            if value != our_value {
-                                return Err(eyre!(
-                                    "Storage diff we got {address}: {storage:?} but expect: {:?}",
-                                    test_account.storage
-                                ))
-                            }
-                        }
-                    } else if !test_account.storage.is_empty() {
+                // iterate over storages
+                for (JsonU256(key), JsonU256(value)) in test_account.storage.iter() {
+                    let our_value = storage
+                        .get(address)
+                        .ok_or(eyre!(
+                            "Missing storage from test {storage:?} got {:?}",
+                            test_account.storage
+                        ))?
+                        .get(key)
+                        .ok_or(eyre!(
+                            "Slot is missing from table {storage:?} got:{:?}",
+                            test_account.storage
+                        ))?;
+                    if value != our_value {
                         return Err(eyre!(
-                            "Walker is not present, but storage is not empty.{:?}",
+                            "Storage diff we got {address}: {storage:?} but expect: {:?}",
                             test_account.storage
                         ))
                     }
                 }
-                Ok(())
-            })??,
-            None => info!("Post state is none"),
+            }
+            Ok(())
+        })??,
+        None => info!("Post state is none"),
+    }
+    Ok(())
+}
+
+/// Run one variant of the transaction of a state test for the fork: execute it on top of the pre
+/// state and compare the state root and the logs with the expected ones.
+///
+/// State tests don't reward the beneficiary of the block.
+fn run_state_test(suite: &StateTestData, fork: ForkSpec, post: &PostState) -> eyre::Result<()> {
+    let transaction = state_transaction(&suite.transaction, post.indexes)?;
+    let header = Header::from(&suite.env);
+    // Hardcode the chain_id to Ethereum 1.
+    let config = reth_executor::Config {
+        chain_id: 1.into(),
+        spec_upgrades: fork.into(),
+        hooks: Default::default(),
+    };
+
+    let db = create_test_rw_db::<WriteMap>();
+    let tx = db.tx_mut()?;
+    insert_pre_state(&tx, suite.pre.clone())?;
+
+    let state = SubState::new(RevmState::new(StateProviderImplRefLatest::new(&tx)));
+    let result =
+        with_large_stack(|| execute_transactions(&header, &[transaction], &config, state))?;
+    let logs = match result {
+        Ok(mut result) => {
+            result.block_reward = None;
+            let logs = calculate_log_root(
+                result.changesets.iter().flat_map(|changeset| changeset.receipt.logs.iter()),
+            );
+            result.apply_to_db(&tx, 0)?;
+            logs
         }
+        // the state is unchanged by invalid transactions
+        Err(_) if post.expect_exception.is_some() => calculate_log_root(std::iter::empty()),
+        Err(err) => return Err(err.into()),
+    };
+
+    if logs != post.logs {
+        return Err(eyre!("Logs hash diff, expected {:?} got {logs:?}", post.logs))
+    }
+    let state_root = state_root(&tx)?;
+    if state_root != post.hash {
+        return Err(eyre!("State root diff, expected {:?} got {state_root:?}", post.hash))
     }
     Ok(())
 }
+
+/// Builds and signs the variant of the transaction of a state test.
+fn state_transaction(
+    transaction: &StateTransaction,
+    indexes: Indexes,
+) -> eyre::Result<TransactionSignedEcRecovered> {
+    let input = transaction
+        .data
+        .get(indexes.data)
+        .ok_or_else(|| eyre!("Transaction data {} is missing", indexes.data))?
+        .clone();
+    let gas_limit = transaction
+        .gas_limit
+        .get(indexes.gas)
+        .ok_or_else(|| eyre!("Transaction gas limit {} is missing", indexes.gas))?;
+    let value = transaction
+        .value
+        .get(indexes.value)
+        .ok_or_else(|| eyre!("Transaction value {} is missing", indexes.value))?;
+    let (nonce, gas_limit, value) =
+        (to_u64(&transaction.nonce)?, to_u64(gas_limit)?, to_u128(value)?);
+    let to = transaction.to.map(TransactionKind::Call).unwrap_or(TransactionKind::Create);
+    let access_list = transaction
+        .access_lists
+        .as_ref()
+        .and_then(|access_lists| access_lists.get(indexes.data).cloned().flatten())
+        .map(|access_list| {
+            AccessList(
+                access_list
+                    .into_iter()
+                    .map(|item| AccessListItem {
+                        address: item.address,
+                        storage_keys: item.storage_keys,
+                    })
+                    .collect(),
+            )
+        });
+    let gas_price =
+        || transaction.gas_price.as_ref().ok_or_else(|| eyre!("Transaction gas price is missing"));
+
+    let unsigned = match (&transaction.max_fee_per_gas, access_list) {
+        (Some(max_fee_per_gas), access_list) => Transaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            nonce,
+            gas_limit,
+            max_fee_per_gas: to_u128(max_fee_per_gas)?,
+            max_priority_fee_per_gas: to_u128(
+                transaction
+                    .max_priority_fee_per_gas
+                    .as_ref()
+                    .ok_or_else(|| eyre!("Transaction max priority fee per gas is missing"))?,
+            )?,
+            to,
+            value,
+            input,
+            access_list: access_list.unwrap_or_default(),
+        }),
+        (None, Some(access_list)) => Transaction::Eip2930(TxEip2930 {
+            chain_id: 1,
+            nonce,
+            gas_price: to_u128(gas_price()?)?,
+            gas_limit,
+            to,
+            value,
+            input,
+            access_list,
+        }),
+        (None, None) => Transaction::Legacy(TxLegacy {
+            chain_id: None,
+            nonce,
+            gas_price: to_u128(gas_price()?)?,
+            gas_limit,
+            to,
+            value,
+            input,
+        }),
+    };
+    let signature = sign_message(transaction.secret_key, unsigned.signature_hash())?;
+    TransactionSigned::from_transaction_and_signature(unsigned, signature)
+        .into_ecrecovered()
+        .ok_or_else(|| eyre!("Transaction signer cannot be recovered"))
+}
+
+fn to_u64(value: &JsonU256) -> eyre::Result<u64> {
+    value.0.try_into().map_err(|_| eyre!("{} does not fit into 64 bits", value.0))
+}
+
+fn to_u128(value: &JsonU256) -> eyre::Result<u128> {
+    value.0.try_into().map_err(|_| eyre!("{} does not fit into 128 bits", value.0))
+}
+
+/// Runs the closure on a thread with a large stack.
+///
+/// For ethereum tests that has MAX gas that calls contract until max depth (1024 calls) revm can
+/// take more then default allocated stack space, see the execution stage.
+fn with_large_stack<T: Send>(f: impl FnOnce() -> T + Send) -> eyre::Result<T> {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(50 * 1024 * 1024)
+            .spawn_scoped(scope, f)
+            .expect("Expects that thread name is not null")
+            .join()
+            .map_err(|_| eyre!("Execution panicked"))
+    })
+}
+
+/// Writes the accounts of the pre state with their code and storage to the plain state.
+fn insert_pre_state<'a>(tx: &impl DbTxMut<'a>, pre_state: State) -> eyre::Result<()> {
+    pre_state.0.into_iter().try_for_each(|(address, account)| -> eyre::Result<()> {
+        let has_code = !account.code.is_empty();
+        let code_hash = if has_code { Some(keccak256(&account.code)) } else { None };
+        tx.put::<tables::PlainAccountState>(
+            address,
+            RethAccount {
+                balance: account.balance.0,
+                nonce: account.nonce.0.as_u64(),
+                bytecode_hash: code_hash,
+            },
+        )?;
+        if let Some(code_hash) = code_hash {
+            tx.put::<tables::Bytecodes>(code_hash, account.code.to_vec())?;
+        }
+        account.storage.iter().try_for_each(|(k, v)| {
+            tracing::trace!("Update storage: {address} key:{:?} val:{:?}", k.0, v.0);
+            let mut key = H256::zero();
+            k.0.to_big_endian(&mut key.0);
+            tx.put::<tables::PlainStorageState>(address, StorageEntry { key, value: v.0 })
+        })?;
+
+        Ok(())
+    })
+}
+
+/// Reads the plain storage of all accounts.
+fn read_storage<'a>(tx: &impl DbTx<'a>) -> Result<HashMap<Address, HashMap<U256, U256>>, DbError> {
+    let mut cursor = tx.cursor_dup::<tables::PlainStorageState>()?;
+    let mut storage: HashMap<Address, HashMap<U256, U256>> = HashMap::new();
+    if let Some((first, _)) = cursor.first()? {
+        for entry in cursor.walk(first)? {
+            let (address, slot) = entry?;
+            let key = U256::from_big_endian(&slot.key.0);
+            storage.entry(address).or_default().insert(key, slot.value);
+        }
+    }
+    Ok(storage)
+}
+
+/// Calculates the state root of the plain state.
+fn state_root<'a>(tx: &impl DbTx<'a>) -> eyre::Result<H256> {
+    let storage = read_storage(tx)?;
+    let mut cursor = tx.cursor::<tables::PlainAccountState>()?;
+    let mut accounts = Vec::new();
+    if let Some((first, _)) = cursor.first()? {
+        for entry in cursor.walk(first)? {
+            let (address, account) = entry?;
+            let slots = storage.get(&address).into_iter().flatten();
+            let storage_root =
+                calculate_storage_root(slots.map(|(key, value)| (H256::from_uint(key), *value)));
+            accounts.push((address, account, storage_root));
+        }
+    }
+    Ok(calculate_state_root(accounts))
+}
@@ -0,0 +1,60 @@
+//! Transaction pool migration tool
+use clap::{Parser, Subcommand};
+use eyre::WrapErr;
+use jsonrpsee::http_client::HttpClientBuilder;
+use reth_rpc_api::AdminApiClient;
+use std::path::PathBuf;
+use tracing::info;
+
+/// `reth txpool` command
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The HTTP RPC endpoint of the node.
+    #[arg(long = "rpc-url", value_name = "URL", default_value = "http://localhost:8545")]
+    rpc_url: String,
+
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Subcommand, Debug)]
+/// `reth txpool` subcommands
+pub enum Subcommands {
+    /// Writes all transactions of the node's transaction pool to a file
+    Export {
+        /// The file to write the transactions to
+        path: PathBuf,
+    },
+    /// Adds the transactions of a file created by `reth txpool export` to the node's transaction
+    /// pool
+    Import {
+        /// The file to read the transactions from
+        path: PathBuf,
+    },
+}
+
+impl Command {
+    /// Execute `txpool` command
+    pub async fn execute(&self) -> eyre::Result<()> {
+        let client = HttpClientBuilder::default()
+            .build(&self.rpc_url)
+            .wrap_err_with(|| format!("Could not connect to {}", self.rpc_url))?;
+
+        match &self.command {
+            Subcommands::Export { path } => {
+                let transactions = client.export_transactions().await?;
+                std::fs::write(path, &transactions)
+                    .wrap_err_with(|| format!("Could not write to {}", path.display()))?;
+                info!("Exported transactions to {}", path.display());
+            }
+            Subcommands::Import { path } => {
+                let transactions = std::fs::read(path)
+                    .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+                let added = client.import_transactions(transactions.into()).await?;
+                info!("Imported {} transactions", added.len());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -77,6 +77,7 @@ pub fn chain_spec_value_parser(s: &str) -> Result<ChainSpecification, eyre::Erro
         "mainnet" => serde_json::from_str(include_str!("../../res/chainspec/mainnet.json"))?,
         "goerli" => serde_json::from_str(include_str!("../../res/chainspec/goerli.json"))?,
         "sepolia" => serde_json::from_str(include_str!("../../res/chainspec/mainnet.json"))?,
+        "dev" => serde_json::from_str(include_str!("../../res/chainspec/dev.json"))?,
         _ => {
             let raw = std::fs::read_to_string(PathBuf::from(shellexpand::full(s)?.into_owned()))?;
             serde_json::from_str(&raw)?
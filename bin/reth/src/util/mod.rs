@@ -26,66 +26,131 @@ pub(crate) fn parse_path(value: &str) -> Result<PathBuf, shellexpand::LookupErro
 
 /// Tracing utility
 pub mod reth_tracing {
+    use ::reth_tracing::{LogFormat, WorkerGuard};
+    use clap::Args;
+    use eyre::WrapErr;
+    use std::path::PathBuf;
     use tracing::Subscriber;
-    use tracing_subscriber::{prelude::*, EnvFilter};
-
-    /// Tracing modes
-    pub enum TracingMode {
-        /// Enable all traces.
-        All,
-        /// Enable debug traces.
-        Debug,
-        /// Enable info traces.
-        Info,
-        /// Enable warn traces.
-        Warn,
-        /// Enable error traces.
-        Error,
-        /// Disable tracing.
-        Silent,
-    }
+    use tracing_subscriber::{prelude::*, reload, EnvFilter, Registry};
 
-    impl TracingMode {
-        fn into_env_filter(self) -> EnvFilter {
-            match self {
-                Self::All => EnvFilter::new("reth=trace"),
-                Self::Debug => EnvFilter::new("reth=debug"),
-                Self::Info => EnvFilter::new("reth=info"),
-                Self::Warn => EnvFilter::new("reth=warn"),
-                Self::Error => EnvFilter::new("reth=error"),
-                Self::Silent => EnvFilter::new(""),
-            }
-        }
+    /// The log filter of stdout if neither `--log.filter`, `RUST_LOG` nor the config file set one.
+    pub const DEFAULT_LOG_FILTER: &str = "reth=info";
+
+    /// A handle to replace the log filter of the subscriber at runtime.
+    pub type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+    /// The log output options.
+    #[derive(Debug, Args)]
+    pub struct LogArgs {
+        /// The log filter of stdout, as comma separated `target=level` directives.
+        ///
+        /// For example, `net=trace,executor=info` raises the verbosity of the networking targets
+        /// only. Takes precedence over the config file and is overridden by the `RUST_LOG`
+        /// environment variable. Defaults to `reth=info`.
+        #[arg(long = "log.filter", value_name = "DIRECTIVES", global = true)]
+        pub filter: Option<String>,
+        /// The format of the logs written to stdout and the log files.
+        #[arg(long = "log.format", value_name = "FORMAT", global = true, default_value_t)]
+        pub format: LogFormat,
+        /// Also write the logs to daily rotated files in the directory.
+        #[arg(long = "log.directory", value_name = "PATH", global = true)]
+        pub directory: Option<PathBuf>,
+        /// The maximum number of log files to keep.
+        #[arg(
+            long = "log.max-files",
+            value_name = "COUNT",
+            global = true,
+            default_value_t = 5,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        pub max_files: u64,
+        /// The log filter of the log files, independent of the verbosity of stdout.
+        #[arg(
+            long = "log.file.filter",
+            value_name = "FILTER",
+            global = true,
+            default_value = "reth=debug"
+        )]
+        pub file_filter: String,
     }
 
-    impl From<u8> for TracingMode {
-        fn from(value: u8) -> Self {
-            match value {
-                0 => Self::Error,
-                1 => Self::Warn,
-                2 => Self::Info,
-                3 => Self::Debug,
-                _ => Self::All,
-            }
+    // === impl LogArgs ===
+
+    impl LogArgs {
+        /// Whether the log filter of stdout was set via `--log.filter` or `RUST_LOG`, in which case
+        /// the filter of the config file is ignored.
+        pub fn is_filter_set(&self) -> bool {
+            self.filter.is_some() ||
+                !std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_default().is_empty()
         }
     }
 
     /// Build subscriber
-    // TODO: JSON/systemd support
-    pub fn build_subscriber(mods: TracingMode) -> impl Subscriber {
+    ///
+    /// Returns the subscriber and a [`FilterHandle`] that can be used to change the log filter of
+    /// stdout. If log files are enabled, the returned guard must be held until the program exits
+    /// to flush all logs.
+    // TODO: systemd support
+    pub fn build_subscriber(
+        args: &LogArgs,
+    ) -> eyre::Result<(impl Subscriber, FilterHandle, Option<WorkerGuard>)> {
         // TODO: Auto-detect
         let no_color = std::env::var("RUST_LOG_STYLE").map(|val| val == "never").unwrap_or(false);
         let with_target = std::env::var("RUST_LOG_TARGET").map(|val| val != "0").unwrap_or(false);
 
         // Take env over config
         let filter = if std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_default().is_empty() {
-            mods.into_env_filter()
+            let directives = args.filter.as_deref().unwrap_or(DEFAULT_LOG_FILTER);
+            EnvFilter::try_new(directives)
+                .wrap_err_with(|| format!("Invalid log filter: {directives}"))?
         } else {
             EnvFilter::from_default_env()
         };
 
-        tracing_subscriber::registry()
-            .with(tracing_subscriber::fmt::layer().with_ansi(!no_color).with_target(with_target))
-            .with(filter)
+        let (filter, handle) = reload::Layer::new(filter);
+        let stdout =
+            ::reth_tracing::stdout(args.format, !no_color, with_target).with_filter(filter);
+
+        let (file, guard) = match &args.directory {
+            Some(dir) => {
+                let file_filter = EnvFilter::try_new(&args.file_filter)
+                    .wrap_err_with(|| format!("Invalid log file filter: {}", args.file_filter))?;
+                let (layer, guard) =
+                    ::reth_tracing::file(args.format, dir, "reth.log", args.max_files as usize)
+                        .wrap_err_with(|| format!("Could not open log directory {dir:?}"))?;
+                (Some(layer.with_filter(file_filter)), Some(guard))
+            }
+            None => (None, None),
+        };
+
+        let subscriber = tracing_subscriber::registry().with(stdout).with(file);
+        Ok((subscriber, handle, guard))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct CommandParser {
+            #[clap(flatten)]
+            logs: LogArgs,
+        }
+
+        #[test]
+        fn parse_log_filter() {
+            let args = CommandParser::parse_from(["reth"]).logs;
+            assert_eq!(args.filter, None);
+            assert!(build_subscriber(&args).is_ok());
+
+            let args =
+                CommandParser::parse_from(["reth", "--log.filter", "net=trace,executor=info"]).logs;
+            assert_eq!(args.filter.as_deref(), Some("net=trace,executor=info"));
+            assert!(build_subscriber(&args).is_ok());
+
+            let args = CommandParser::parse_from(["reth", "--log.filter", "net=loud"]).logs;
+            assert!(build_subscriber(&args).is_err());
+        }
     }
 }
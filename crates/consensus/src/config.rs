@@ -1,5 +1,5 @@
 //! Reth block execution/validation configuration and constants
-use reth_primitives::BlockNumber;
+use reth_primitives::{BlockNumber, SenderPermissions, U256};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -11,6 +11,15 @@ pub const EIP1559_BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
 /// Elasticity multiplier as defined in: https://eips.ethereum.org/EIPS/eip-1559
 pub const EIP1559_ELASTICITY_MULTIPLIER: u64 = 2;
 
+/// Intrinsic gas cost of the cheapest possible transaction.
+pub const MIN_TRANSACTION_GAS: u64 = 21_000;
+/// Minimum gas limit allowed for a block.
+pub const MIN_GAS_LIMIT: u64 = 5_000;
+/// Maximum gas limit allowed for a block (2^63 - 1).
+pub const MAX_GAS_LIMIT: u64 = 0x7fffffffffffffff;
+/// Bound divisor of the gas limit, used to limit the change of gas limit between blocks.
+pub const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+
 /// Common configuration for consensus algorithms.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -52,6 +61,41 @@ pub struct Config {
     /// activated.
     #[cfg_attr(feature = "serde", serde(rename = "terminalTotalDifficulty"))]
     pub merge_terminal_total_difficulty: u128,
+    /// The Shanghai hard-fork timestamp, `None` if it is not scheduled.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub shanghai_time: Option<u64>,
+    /// The Cancun hard-fork timestamp, `None` if it is not scheduled.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub cancun_time: Option<u64>,
+    /// Whether receipts of unknown EIP-2718 transaction types are accepted, see
+    /// [`Receipt::decode_lenient`](reth_primitives::Receipt::decode_lenient).
+    ///
+    /// Only networks that introduced custom transaction types should enable this.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lenient_receipt_decoding: bool,
+    /// Restrictions on transaction senders and contract deployers for permissioned networks.
+    ///
+    /// Public networks leave this unset, which permits every account.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub sender_permissions: Option<SenderPermissions>,
+}
+
+impl Config {
+    /// Returns true if the Paris hard-fork is active for a block whose parent has the given total
+    /// difficulty, i.e. the terminal total difficulty was reached by one of its ancestors.
+    pub fn is_paris_active_at_total_difficulty(&self, parent_total_difficulty: U256) -> bool {
+        parent_total_difficulty >= U256::from(self.merge_terminal_total_difficulty)
+    }
+
+    /// Returns true if the Shanghai hard-fork is active at the timestamp.
+    pub fn is_shanghai_active_at_timestamp(&self, timestamp: u64) -> bool {
+        self.shanghai_time.map_or(false, |time| timestamp >= time)
+    }
+
+    /// Returns true if the Cancun hard-fork is active at the timestamp.
+    pub fn is_cancun_active_at_timestamp(&self, timestamp: u64) -> bool {
+        self.cancun_time.map_or(false, |time| timestamp >= time)
+    }
 }
 
 impl Default for Config {
@@ -72,6 +116,10 @@ impl Default for Config {
             london_block: 12965000,
             paris_block: 15537394,
             merge_terminal_total_difficulty: 58750000000000000000000,
+            shanghai_time: None,
+            cancun_time: None,
+            lenient_receipt_decoding: false,
+            sender_permissions: None,
         }
     }
 }
@@ -1,7 +1,7 @@
 //! Consensus for ethereum network
 use crate::{verification, Config};
 use reth_interfaces::consensus::{Consensus, Error, ForkchoiceState};
-use reth_primitives::{BlockNumber, SealedBlock, SealedHeader, H256};
+use reth_primitives::{BlockNumber, SealedBlock, SealedHeader, H256, U256};
 use tokio::sync::{watch, watch::error::SendError};
 
 /// Ethereum beacon consensus
@@ -54,6 +54,18 @@ impl Consensus for BeaconConsensus {
         Ok(())
     }
 
+    fn validate_header_with_total_difficulty(
+        &self,
+        header: &SealedHeader,
+        parent_total_difficulty: U256,
+    ) -> Result<(), Error> {
+        verification::validate_header_regarding_total_difficulty(
+            header,
+            parent_total_difficulty,
+            &self.config,
+        )
+    }
+
     fn pre_validate_block(&self, block: &SealedBlock) -> Result<(), Error> {
         verification::validate_block_standalone(block)
     }
@@ -0,0 +1,187 @@
+//! Subscriptions to the canonical chain as set by the consensus client.
+use futures::Stream;
+use reth_primitives::SealedHeader;
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
+
+/// Updates the latest canonical, safe and finalized headers observed by the engine.
+#[derive(Debug)]
+pub struct CanonicalHeadersTracker {
+    head: watch::Sender<Option<SealedHeader>>,
+    safe: watch::Sender<Option<SealedHeader>>,
+    finalized: watch::Sender<Option<SealedHeader>>,
+}
+
+// === impl CanonicalHeadersTracker ===
+
+impl CanonicalHeadersTracker {
+    /// Creates a new tracker without any known headers.
+    pub fn new() -> Self {
+        Self {
+            head: watch::channel(None).0,
+            safe: watch::channel(None).0,
+            finalized: watch::channel(None).0,
+        }
+    }
+
+    /// Returns a new handle to the tracked headers.
+    pub fn handle(&self) -> CanonicalHeadersHandle {
+        CanonicalHeadersHandle {
+            head: self.head.subscribe(),
+            safe: self.safe.subscribe(),
+            finalized: self.finalized.subscribe(),
+        }
+    }
+
+    /// Sets the new canonical head.
+    pub fn set_head(&self, header: SealedHeader) {
+        update(&self.head, header)
+    }
+
+    /// Sets the new safe header.
+    pub fn set_safe(&self, header: SealedHeader) {
+        update(&self.safe, header)
+    }
+
+    /// Sets the new finalized header.
+    pub fn set_finalized(&self, header: SealedHeader) {
+        update(&self.finalized, header)
+    }
+}
+
+impl Default for CanonicalHeadersTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replaces the header and notifies the subscribers, unless it is the current header.
+fn update(sender: &watch::Sender<Option<SealedHeader>>, header: SealedHeader) {
+    sender.send_if_modified(|current| {
+        if current.as_ref().map(|current| current.hash()) == Some(header.hash()) {
+            return false
+        }
+        *current = Some(header);
+        true
+    });
+}
+
+/// A cheaply cloneable handle to the latest canonical, safe and finalized headers.
+///
+/// This gives embedders, like the derivation pipeline of a rollup, direct access to the chain
+/// selected by the consensus client without polling the RPC.
+#[derive(Debug, Clone)]
+pub struct CanonicalHeadersHandle {
+    head: watch::Receiver<Option<SealedHeader>>,
+    safe: watch::Receiver<Option<SealedHeader>>,
+    finalized: watch::Receiver<Option<SealedHeader>>,
+}
+
+// === impl CanonicalHeadersHandle ===
+
+impl CanonicalHeadersHandle {
+    /// Returns the current canonical head, if any.
+    pub fn head(&self) -> Option<SealedHeader> {
+        self.head.borrow().clone()
+    }
+
+    /// Returns the current safe header, if any.
+    pub fn safe(&self) -> Option<SealedHeader> {
+        self.safe.borrow().clone()
+    }
+
+    /// Returns the current finalized header, if any.
+    pub fn finalized(&self) -> Option<SealedHeader> {
+        self.finalized.borrow().clone()
+    }
+
+    /// Returns a stream of canonical heads, starting with the current head.
+    pub fn subscribe_head(&self) -> CanonicalHeaderStream {
+        CanonicalHeaderStream::new(self.head.clone())
+    }
+
+    /// Returns a stream of safe headers, starting with the current safe header.
+    pub fn subscribe_safe(&self) -> CanonicalHeaderStream {
+        CanonicalHeaderStream::new(self.safe.clone())
+    }
+
+    /// Returns a stream of finalized headers, starting with the current finalized header.
+    pub fn subscribe_finalized(&self) -> CanonicalHeaderStream {
+        CanonicalHeaderStream::new(self.finalized.clone())
+    }
+}
+
+/// A stream of header updates that yields the current header first, if there is one.
+///
+/// Like any [watch] channel, intermediate updates are skipped if the stream is polled slower than
+/// the headers change. The stream ends once the engine is dropped.
+#[must_use = "streams do nothing unless polled"]
+pub struct CanonicalHeaderStream {
+    inner: WatchStream<Option<SealedHeader>>,
+}
+
+impl CanonicalHeaderStream {
+    fn new(rx: watch::Receiver<Option<SealedHeader>>) -> Self {
+        Self { inner: WatchStream::new(rx) }
+    }
+}
+
+impl Stream for CanonicalHeaderStream {
+    type Item = SealedHeader;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Some(header)) => return Poll::Ready(Some(header)),
+                // nothing to replay yet
+                Some(None) => continue,
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use reth_primitives::Header;
+
+    fn header(number: u64) -> SealedHeader {
+        Header { number, ..Default::default() }.seal()
+    }
+
+    #[tokio::test]
+    async fn replays_current_header() {
+        let tracker = CanonicalHeadersTracker::new();
+        let handle = tracker.handle();
+        assert_eq!(handle.head(), None);
+
+        tracker.set_head(header(1));
+        tracker.set_finalized(header(0));
+        assert_eq!(handle.head(), Some(header(1)));
+        assert_eq!(handle.safe(), None);
+
+        // late subscribers receive the current header first
+        let mut heads = handle.subscribe_head();
+        assert_eq!(heads.next().await, Some(header(1)));
+        tracker.set_head(header(2));
+        assert_eq!(heads.next().await, Some(header(2)));
+
+        let mut finalized = tracker.handle().subscribe_finalized();
+        assert_eq!(finalized.next().await, Some(header(0)));
+
+        // no header is replayed before the first update
+        let mut safe = handle.subscribe_safe();
+        tracker.set_safe(header(1));
+        assert_eq!(safe.next().await, Some(header(1)));
+
+        drop(tracker);
+        assert_eq!(heads.next().await, None);
+    }
+}
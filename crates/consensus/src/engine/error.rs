@@ -21,6 +21,41 @@ pub enum EngineApiError {
         /// The block hash provided with the payload.
         consensus: H256,
     },
+    /// Payload contains more transactions than its gas could possibly pay for.
+    #[error("Invalid payload transaction count: {count}. Max: {max}")]
+    PayloadTransactionCount {
+        /// The number of transactions in the payload.
+        count: usize,
+        /// The maximum number of transactions the payload gas could cover.
+        max: usize,
+    },
+    /// Payload transactions exceed the maximum accepted size.
+    #[error("Invalid payload size: {size}. Max: {max}")]
+    PayloadSize {
+        /// The total size of the payload transactions in bytes.
+        size: usize,
+        /// The maximum accepted size in bytes.
+        max: usize,
+    },
+    /// Payload gas used exceeds the gas limit.
+    #[error("Invalid payload gas used: {gas_used}. Gas limit: {gas_limit}")]
+    PayloadGasUsed {
+        /// The payload gas used.
+        gas_used: u64,
+        /// The payload gas limit.
+        gas_limit: u64,
+    },
+    /// Payload gas limit is out of the accepted bounds.
+    #[error("Invalid payload gas limit: {0}")]
+    PayloadGasLimit(u64),
+    /// Payload gas limit changed too much in regards to the parent.
+    #[error("Invalid payload gas limit: {gas_limit}. Parent gas limit: {parent_gas_limit}")]
+    PayloadGasLimitChange {
+        /// The payload gas limit.
+        gas_limit: u64,
+        /// The parent block gas limit.
+        parent_gas_limit: u64,
+    },
     /// Invalid payload block hash.
     #[error("Invalid payload timestamp: {invalid}. Latest: {latest}")]
     PayloadTimestamp {
@@ -55,6 +90,29 @@ pub enum EngineApiError {
         /// Consensus terminal block hash.
         consensus: H256,
     },
+    /// The method version doesn't support the fork active at the payload timestamp.
+    #[error("Unsupported fork")]
+    UnsupportedFork,
+    /// Withdrawals are missing after the Shanghai hard-fork.
+    #[error("Missing withdrawals after Shanghai")]
+    WithdrawalsMissing,
+    /// Withdrawals are present before the Shanghai hard-fork.
+    #[error("Unexpected withdrawals before Shanghai")]
+    UnexpectedWithdrawals,
+    /// Cancun fields are missing after the Cancun hard-fork.
+    #[error("Missing Cancun fields after Cancun")]
+    CancunFieldsMissing,
+    /// Cancun fields are present before the Cancun hard-fork.
+    #[error("Unexpected Cancun fields before Cancun")]
+    UnexpectedCancunFields,
+    /// Blob versioned hashes don't match the blob transactions of the payload.
+    #[error("Invalid blob versioned hashes: expected {expected}, got {got}")]
+    BlobVersionedHashes {
+        /// The number of blob versioned hashes of the payload transactions.
+        expected: usize,
+        /// The number of blob versioned hashes provided with the payload.
+        got: usize,
+    },
     /// Forkchoice zero hash head received.
     #[error("Received zero hash as forkchoice head")]
     ForkchoiceEmptyHead,
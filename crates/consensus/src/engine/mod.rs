@@ -2,8 +2,7 @@ use futures::StreamExt;
 use reth_interfaces::consensus::ForkchoiceState;
 use reth_primitives::{
     proofs::{self, EMPTY_LIST_HASH},
-    rpc::BlockId,
-    Header, SealedBlock, TransactionSigned, H64,
+    BlockNumber, Header, SealedBlock, SealedHeader, TransactionSigned, H256, H64, U256,
 };
 use reth_provider::{BlockProvider, HeaderProvider};
 use reth_rlp::Decodable;
@@ -18,12 +17,22 @@ use std::{
     sync::Arc,
     task::{ready, Context, Poll},
 };
-use tokio::sync::oneshot;
+use tokio::sync::{
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+mod canon;
 mod error;
-use crate::Config;
+mod tree;
+use crate::{config, BeaconConsensus, Config};
+pub use canon::{CanonicalHeaderStream, CanonicalHeadersHandle, CanonicalHeadersTracker};
 pub use error::{EngineApiError, EngineApiResult};
+pub use tree::{BlockchainTree, CanonicalUpdate, DEFAULT_MAX_BUFFERED_BLOCKS};
+
+/// The maximum accepted size in bytes of all encoded transactions in a single payload.
+pub const MAX_PAYLOAD_SIZE: usize = 128 * 1024 * 1024;
 
 /// The Engine API response sender
 pub type EngineApiSender<Ok> = oneshot::Sender<EngineApiResult<Ok>>;
@@ -38,7 +47,7 @@ pub trait ConsensusEngine {
 
     /// Updates the fork choice state
     fn fork_choice_updated(
-        &self,
+        &mut self,
         fork_choice_state: ForkchoiceState,
         payload_attributes: Option<PayloadAttributes>,
     ) -> EngineApiResult<ForkchoiceUpdated>;
@@ -79,9 +88,54 @@ pub struct EthConsensusEngine<Client> {
     local_store: HashMap<H64, ExecutionPayload>, // TODO: bound
     // remote_store: HashMap<H64, ExecutionPayload>,
     rx: UnboundedReceiverStream<EngineMessage>,
+    /// The headers selected by the latest forkchoice update
+    canonical_headers: CanonicalHeadersTracker,
+    /// The blocks of new payloads that are not canonical yet
+    tree: BlockchainTree,
+    /// The pipeline that syncs the chain selected by forkchoice updates, if any
+    pipeline: Option<PipelineHandle>,
+}
+
+/// The handles to notify the pipeline of a new canonical chain.
+struct PipelineHandle {
+    /// Delivers the new head to the headers stage.
+    consensus: Arc<BeaconConsensus>,
+    /// Requests the pipeline to revert the canonical blocks of a previous chain.
+    unwind_tx: UnboundedSender<BlockNumber>,
 }
 
 impl<Client: HeaderProvider + BlockProvider> EthConsensusEngine<Client> {
+    /// Creates a new engine that handles the messages received over the channel.
+    pub fn new(config: Config, client: Arc<Client>, rx: UnboundedReceiver<EngineMessage>) -> Self {
+        Self {
+            config,
+            client,
+            local_store: Default::default(),
+            rx: UnboundedReceiverStream::new(rx),
+            canonical_headers: CanonicalHeadersTracker::new(),
+            tree: BlockchainTree::default(),
+            pipeline: None,
+        }
+    }
+
+    /// Makes the chain selected by forkchoice updates canonical with the pipeline.
+    ///
+    /// The new head is delivered to the headers stage over the [BeaconConsensus], if the head is
+    /// on a side chain the pipeline is first requested to unwind to the fork block.
+    pub fn with_pipeline(
+        mut self,
+        consensus: Arc<BeaconConsensus>,
+        unwind_tx: UnboundedSender<BlockNumber>,
+    ) -> Self {
+        self.pipeline = Some(PipelineHandle { consensus, unwind_tx });
+        self
+    }
+
+    /// Returns a handle to the latest canonical, safe and finalized headers.
+    pub fn canonical_headers(&self) -> CanonicalHeadersHandle {
+        self.canonical_headers.handle()
+    }
+
     fn on_message(&mut self, msg: EngineMessage) {
         match msg {
             EngineMessage::GetPayload(payload_id, tx) => {
@@ -109,6 +163,8 @@ impl<Client: HeaderProvider + BlockProvider> EthConsensusEngine<Client> {
     /// `payload.block_hash`.
     /// Ref: https://github.com/ethereum/go-ethereum/blob/79a478bb6176425c2400e949890e668a3d9a3d05/core/beacon/types.go#L145
     fn try_construct_block(&self, payload: ExecutionPayload) -> EngineApiResult<SealedBlock> {
+        validate_payload_limits(&payload)?;
+
         if payload.extra_data.len() > 32 {
             return Err(EngineApiError::PayloadExtraData(payload.extra_data))
         }
@@ -153,6 +209,103 @@ impl<Client: HeaderProvider + BlockProvider> EthConsensusEngine<Client> {
 
         Ok(SealedBlock { header, body: transactions, ommers: Default::default() })
     }
+
+    /// Returns the stored or buffered header with the given hash, if the hash is set and the
+    /// header is known.
+    fn sealed_header(&self, hash: H256) -> EngineApiResult<Option<SealedHeader>> {
+        if hash.is_zero() {
+            return Ok(None)
+        }
+        if let Some(header) = self.tree.header(&hash) {
+            return Ok(Some(header.clone()))
+        }
+        Ok(self.client.header(&hash)?.map(|header| SealedHeader::new(header, hash)))
+    }
+
+    /// Returns the total difficulty of the stored or buffered header.
+    ///
+    /// Payloads have no difficulty, so buffered headers have the total difficulty of the stored
+    /// parent of their buffered chain.
+    fn total_difficulty(&self, header: &SealedHeader) -> EngineApiResult<Option<U256>> {
+        let stored = self
+            .tree
+            .chain(header.hash())
+            .first()
+            .map(|block| block.parent_hash)
+            .unwrap_or_else(|| header.hash());
+        Ok(self.client.header_td(&stored)?)
+    }
+
+    /// Returns how the canonical chain changes if the block becomes the head, or `None` if an
+    /// ancestor of the block is unknown.
+    fn canonical_update(&self, head: &SealedHeader) -> EngineApiResult<Option<CanonicalUpdate>> {
+        let best_hash = self.client.chain_info()?.best_hash;
+        let (mut number, mut hash) = (head.number, head.hash());
+        loop {
+            if self.client.canonical_hash(number)? == Some(hash) {
+                let update = if hash == head.hash() {
+                    CanonicalUpdate::Canonical
+                } else if hash == best_hash {
+                    CanonicalUpdate::Extend
+                } else {
+                    CanonicalUpdate::Reorg { fork_number: number, fork_hash: hash }
+                };
+                return Ok(Some(update))
+            }
+            // walk down the side chain until it joins the canonical chain
+            match self.sealed_header(hash)? {
+                Some(header) if header.number > 0 => {
+                    number = header.number - 1;
+                    hash = header.parent_hash;
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// Notifies the pipeline of the new forkchoice state, after requesting it to unwind to the
+    /// given block.
+    fn sync_pipeline(&self, state: ForkchoiceState, unwind_to: Option<BlockNumber>) {
+        let Some(pipeline) = &self.pipeline else { return };
+        if let Some(block) = unwind_to {
+            // the unwind is requested first, so the headers stage never extends the old chain
+            let _ = pipeline.unwind_tx.send(block);
+        }
+        // TODO: commit the buffered blocks instead of downloading them again
+        let _ = pipeline.consensus.notify_fork_choice_state(state);
+    }
+}
+
+/// Performs cheap sanity checks on the payload before any transaction is decoded.
+///
+/// Every transaction costs at least [config::MIN_TRANSACTION_GAS], so a payload can never contain
+/// more transactions than its gas used allows. This rejects absurd payloads from a malicious or
+/// buggy consensus client before the expensive RLP decoding and execution.
+pub fn validate_payload_limits(payload: &ExecutionPayload) -> EngineApiResult<()> {
+    let gas_limit = payload.gas_limit.as_u64();
+    if !(config::MIN_GAS_LIMIT..=config::MAX_GAS_LIMIT).contains(&gas_limit) {
+        return Err(EngineApiError::PayloadGasLimit(gas_limit))
+    }
+
+    let gas_used = payload.gas_used.as_u64();
+    if gas_used > gas_limit {
+        return Err(EngineApiError::PayloadGasUsed { gas_used, gas_limit })
+    }
+
+    let max_transactions = (gas_used / config::MIN_TRANSACTION_GAS) as usize;
+    if payload.transactions.len() > max_transactions {
+        return Err(EngineApiError::PayloadTransactionCount {
+            count: payload.transactions.len(),
+            max: max_transactions,
+        })
+    }
+
+    let size = payload.transactions.iter().map(|tx| tx.len()).sum::<usize>();
+    if size > MAX_PAYLOAD_SIZE {
+        return Err(EngineApiError::PayloadSize { size, max: MAX_PAYLOAD_SIZE })
+    }
+
+    Ok(())
 }
 
 impl<Client: HeaderProvider + BlockProvider> ConsensusEngine for EthConsensusEngine<Client> {
@@ -175,18 +328,30 @@ impl<Client: HeaderProvider + BlockProvider> ConsensusEngine for EthConsensusEng
             return Ok(PayloadStatus::new(PayloadStatusEnum::Valid, block.hash()))
         }
 
-        let Some(parent) = self.client.block(BlockId::Hash(block.parent_hash))? else {
-             // TODO: cache block for storing later
-             return Ok(PayloadStatus::from_status(PayloadStatusEnum::Syncing))
+        let Some(parent) = self.sealed_header(block.parent_hash)? else {
+            // the block may become canonical once its ancestors are synced
+            self.tree.insert(block);
+            return Ok(PayloadStatus::from_status(PayloadStatusEnum::Syncing))
         };
 
-        let parent_td = self.client.header_td(&block.parent_hash)?;
-        if parent_td.unwrap_or_default() <= self.config.merge_terminal_total_difficulty.into() {
+        let parent_td = self.total_difficulty(&parent)?;
+        if !self.config.is_paris_active_at_total_difficulty(parent_td.unwrap_or_default()) {
             return Ok(PayloadStatus::from_status(PayloadStatusEnum::Invalid {
                 validation_error: EngineApiError::PayloadPreMerge.to_string(),
             }))
         }
 
+        let max_gas_limit_change = parent.gas_limit / config::GAS_LIMIT_BOUND_DIVISOR;
+        if block.gas_limit.abs_diff(parent.gas_limit) >= max_gas_limit_change {
+            return Ok(PayloadStatus::from_status(PayloadStatusEnum::Invalid {
+                validation_error: EngineApiError::PayloadGasLimitChange {
+                    gas_limit: block.gas_limit,
+                    parent_gas_limit: parent.gas_limit,
+                }
+                .to_string(),
+            }))
+        }
+
         if block.timestamp <= parent.timestamp {
             return Err(EngineApiError::PayloadTimestamp {
                 invalid: block.timestamp,
@@ -196,15 +361,23 @@ impl<Client: HeaderProvider + BlockProvider> ConsensusEngine for EthConsensusEng
 
         // TODO: execute block
 
-        Ok(PayloadStatus::new(PayloadStatusEnum::Valid, block.hash()))
+        // blocks on top of buffered blocks are not validated until their chain is synced
+        let status = if self.tree.block(&parent.hash()).is_some() {
+            PayloadStatus::from_status(PayloadStatusEnum::Accepted)
+        } else {
+            PayloadStatus::new(PayloadStatusEnum::Valid, block.hash())
+        };
+        self.tree.insert(block);
+        Ok(status)
     }
 
     fn fork_choice_updated(
-        &self,
+        &mut self,
         fork_choice_state: ForkchoiceState,
         _payload_attributes: Option<PayloadAttributes>,
     ) -> EngineApiResult<ForkchoiceUpdated> {
-        let ForkchoiceState { head_block_hash, finalized_block_hash, .. } = fork_choice_state;
+        let ForkchoiceState { head_block_hash, finalized_block_hash, safe_block_hash } =
+            fork_choice_state;
 
         if head_block_hash.is_zero() {
             return Ok(ForkchoiceUpdated::from_status(PayloadStatusEnum::Invalid {
@@ -212,13 +385,40 @@ impl<Client: HeaderProvider + BlockProvider> ConsensusEngine for EthConsensusEng
             }))
         }
 
-        // Block is not known, nothing to do.
-        if !self.client.is_known(&head_block_hash)? {
+        // Block is not known, the pipeline has to download it.
+        let Some(head) = self.sealed_header(head_block_hash)? else {
+            self.sync_pipeline(fork_choice_state, None);
             return Ok(ForkchoiceUpdated::from_status(PayloadStatusEnum::Syncing))
-        }
+        };
 
         // The finalized block hash is not known, we are still syncing
-        if !finalized_block_hash.is_zero() && !self.client.is_known(&finalized_block_hash)? {
+        let finalized = self.sealed_header(finalized_block_hash)?;
+        if !finalized_block_hash.is_zero() && finalized.is_none() {
+            self.sync_pipeline(fork_choice_state, None);
+            return Ok(ForkchoiceUpdated::from_status(PayloadStatusEnum::Syncing))
+        }
+
+        let update = self.canonical_update(&head)?;
+        match update {
+            // An ancestor of the canonical head is not reverted to.
+            Some(CanonicalUpdate::Canonical) => {}
+            Some(CanonicalUpdate::Extend) | None => self.sync_pipeline(fork_choice_state, None),
+            Some(CanonicalUpdate::Reorg { fork_number, .. }) => {
+                self.sync_pipeline(fork_choice_state, Some(fork_number))
+            }
+        }
+
+        self.canonical_headers.set_head(head);
+        if let Some(safe) = self.sealed_header(safe_block_hash)? {
+            self.canonical_headers.set_safe(safe);
+        }
+        if let Some(finalized) = finalized {
+            // blocks at or below the finalized block never become canonical
+            self.tree.remove_until(finalized.number);
+            self.canonical_headers.set_finalized(finalized);
+        }
+
+        if update != Some(CanonicalUpdate::Canonical) {
             return Ok(ForkchoiceUpdated::from_status(PayloadStatusEnum::Syncing))
         }
 
@@ -291,3 +491,59 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Bytes, U256, U64};
+
+    fn payload(gas_limit: u64, gas_used: u64, transactions: Vec<Bytes>) -> ExecutionPayload {
+        ExecutionPayload {
+            parent_hash: Default::default(),
+            fee_recipient: Default::default(),
+            state_root: Default::default(),
+            receipts_root: Default::default(),
+            logs_bloom: Default::default(),
+            prev_randao: Default::default(),
+            block_number: U64::from(1),
+            gas_limit: U64::from(gas_limit),
+            gas_used: U64::from(gas_used),
+            timestamp: U64::from(1),
+            extra_data: Default::default(),
+            base_fee_per_gas: U256::from(7),
+            block_hash: Default::default(),
+            transactions,
+            withdrawals: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+        }
+    }
+
+    #[test]
+    fn payload_limits() {
+        assert!(
+            validate_payload_limits(&payload(30_000_000, 21_000, vec![Bytes::default()])).is_ok()
+        );
+
+        assert!(matches!(
+            validate_payload_limits(&payload(1_000, 0, vec![])),
+            Err(EngineApiError::PayloadGasLimit(1_000))
+        ));
+
+        assert!(matches!(
+            validate_payload_limits(&payload(30_000_000, 30_000_001, vec![])),
+            Err(EngineApiError::PayloadGasUsed { .. })
+        ));
+
+        assert!(matches!(
+            validate_payload_limits(&payload(30_000_000, 42_000, vec![Bytes::default(); 3])),
+            Err(EngineApiError::PayloadTransactionCount { count: 3, max: 2 })
+        ));
+
+        let oversized = Bytes::from(vec![0u8; MAX_PAYLOAD_SIZE + 1]);
+        assert!(matches!(
+            validate_payload_limits(&payload(30_000_000, 21_000, vec![oversized])),
+            Err(EngineApiError::PayloadSize { .. })
+        ));
+    }
+}
@@ -0,0 +1,183 @@
+//! Buffer of the blocks received with new payloads that are not canonical yet.
+use reth_primitives::{BlockNumber, SealedBlock, SealedHeader, H256};
+use std::collections::{HashMap, VecDeque};
+
+/// The default maximum number of buffered blocks.
+pub const DEFAULT_MAX_BUFFERED_BLOCKS: usize = 256;
+
+/// Tracks the side chains built from the payloads of the consensus client.
+///
+/// Blocks are buffered until they are finalized or evicted, so a later forkchoice update can
+/// select any of the chains. The oldest block is evicted once the buffer is full.
+#[derive(Debug)]
+pub struct BlockchainTree {
+    /// All buffered blocks by hash.
+    blocks: HashMap<H256, SealedBlock>,
+    /// The hashes of the buffered blocks in insertion order.
+    order: VecDeque<H256>,
+    /// The maximum number of buffered blocks.
+    max_blocks: usize,
+}
+
+// === impl BlockchainTree ===
+
+impl BlockchainTree {
+    /// Creates an empty tree that buffers up to `max_blocks` blocks.
+    pub fn new(max_blocks: usize) -> Self {
+        Self { blocks: Default::default(), order: Default::default(), max_blocks }
+    }
+
+    /// Returns the number of buffered blocks.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns true if no blocks are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the buffered block with the given hash.
+    pub fn block(&self, hash: &H256) -> Option<&SealedBlock> {
+        self.blocks.get(hash)
+    }
+
+    /// Returns the header of the buffered block with the given hash.
+    pub fn header(&self, hash: &H256) -> Option<&SealedHeader> {
+        self.blocks.get(hash).map(|block| &block.header)
+    }
+
+    /// Buffers the block, evicting the oldest block if the buffer is full.
+    pub fn insert(&mut self, block: SealedBlock) {
+        let hash = block.hash();
+        if self.blocks.contains_key(&hash) {
+            return
+        }
+        while self.blocks.len() >= self.max_blocks {
+            let Some(oldest) = self.order.pop_front() else { break };
+            self.remove(&oldest);
+        }
+        self.order.push_back(hash);
+        self.blocks.insert(hash, block);
+    }
+
+    /// Returns the buffered chain that ends with the block with the given hash, in ascending
+    /// order.
+    ///
+    /// The parent of the first block is not buffered, the chain is empty if the block isn't.
+    pub fn chain(&self, tip: H256) -> Vec<&SealedBlock> {
+        let mut chain = Vec::new();
+        let mut hash = tip;
+        while let Some(block) = self.blocks.get(&hash) {
+            chain.push(block);
+            hash = block.parent_hash;
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Removes all blocks at or below the given number, e.g. once it was finalized.
+    pub fn remove_until(&mut self, number: BlockNumber) {
+        let stale = self
+            .blocks
+            .values()
+            .filter(|block| block.number <= number)
+            .map(|block| block.hash())
+            .collect::<Vec<_>>();
+        for hash in stale {
+            self.remove(&hash);
+        }
+    }
+
+    /// Removes the block with the given hash.
+    ///
+    /// Its children stay buffered, they may still become canonical.
+    fn remove(&mut self, hash: &H256) {
+        if self.blocks.remove(hash).is_some() {
+            self.order.retain(|buffered| buffered != hash);
+        }
+    }
+}
+
+impl Default for BlockchainTree {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BUFFERED_BLOCKS)
+    }
+}
+
+/// How the canonical chain has to change to make a block the new head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanonicalUpdate {
+    /// The block is canonical already, either the head or one of its ancestors.
+    Canonical,
+    /// The block descends from the canonical head.
+    Extend,
+    /// The block is on a side chain, the canonical blocks above the fork block are reverted.
+    Reorg {
+        /// The number of the last canonical block that stays canonical.
+        fork_number: BlockNumber,
+        /// The hash of the last canonical block that stays canonical.
+        fork_hash: H256,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::Header;
+
+    fn block(number: u64, parent_hash: H256, extra: u8) -> SealedBlock {
+        let header =
+            Header { number, parent_hash, extra_data: vec![extra].into(), ..Default::default() };
+        SealedBlock { header: header.seal(), body: Vec::new(), ommers: Vec::new() }
+    }
+
+    /// Returns a chain of `len` blocks on top of the given parent.
+    fn chain(parent: (u64, H256), len: u64, extra: u8) -> Vec<SealedBlock> {
+        let mut blocks: Vec<SealedBlock> = Vec::new();
+        for number in parent.0 + 1..=parent.0 + len {
+            let parent_hash = blocks.last().map(|block| block.hash()).unwrap_or(parent.1);
+            blocks.push(block(number, parent_hash, extra));
+        }
+        blocks
+    }
+
+    #[test]
+    fn buffer_side_chains() {
+        let mut tree = BlockchainTree::default();
+        let fork = (10, H256::from_low_u64_be(10));
+        let main = chain(fork, 3, 0);
+        let side = chain(fork, 2, 1);
+        for block in main.iter().chain(side.iter()) {
+            tree.insert(block.clone());
+        }
+        assert_eq!(tree.len(), 5);
+
+        let tip = main.last().unwrap().hash();
+        assert_eq!(tree.chain(tip), main.iter().collect::<Vec<_>>());
+        assert_eq!(tree.chain(side[1].hash()), side.iter().collect::<Vec<_>>());
+        assert!(tree.chain(H256::zero()).is_empty());
+
+        // the children of finalized blocks stay buffered
+        tree.remove_until(11);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.chain(tip), main[1..].iter().collect::<Vec<_>>());
+        assert_eq!(tree.chain(side[1].hash()), vec![&side[1]]);
+    }
+
+    #[test]
+    fn evict_oldest_blocks() {
+        let mut tree = BlockchainTree::new(2);
+        let blocks = chain((0, H256::zero()), 3, 0);
+        for block in &blocks {
+            tree.insert(block.clone());
+        }
+        assert_eq!(tree.len(), 2);
+        assert!(tree.block(&blocks[0].hash()).is_none());
+        assert_eq!(tree.chain(blocks[2].hash()), vec![&blocks[1], &blocks[2]]);
+
+        // reinserting a buffered block doesn't evict anything
+        tree.insert(blocks[2].clone());
+        assert_eq!(tree.len(), 2);
+    }
+}
@@ -2,8 +2,8 @@
 use crate::{config, Config};
 use reth_interfaces::{consensus::Error, Result as RethResult};
 use reth_primitives::{
-    BlockNumber, Header, SealedBlock, SealedHeader, Transaction, TransactionSignedEcRecovered,
-    TxEip1559, TxEip2930, TxLegacy, EMPTY_OMMER_ROOT, H256, U256,
+    Address, BlockNumber, Header, SealedBlock, SealedHeader, Transaction, TransactionKind,
+    TransactionSignedEcRecovered, TxEip1559, TxEip2930, TxLegacy, EMPTY_OMMER_ROOT, H256, U256,
 };
 use reth_provider::{AccountProvider, HeaderProvider};
 use std::{
@@ -24,6 +24,11 @@ pub fn validate_header_standalone(
         })
     }
 
+    // Yellow paper, eq. 47: the gas limit is at least 5000 and at most 2^63 - 1.
+    if !(config::MIN_GAS_LIMIT..=config::MAX_GAS_LIMIT).contains(&header.gas_limit) {
+        return Err(Error::HeaderGasLimitOutOfBounds { gas_limit: header.gas_limit })
+    }
+
     // Check if timestamp is in future. Clock can drift but this can be consensus issue.
     let present_timestamp =
         SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
@@ -125,6 +130,23 @@ pub fn validate_transaction_regarding_header(
     Ok(())
 }
 
+/// Validate that the sender may send the transaction on permissioned networks, see
+/// [Config::sender_permissions].
+pub fn validate_sender_permissions(
+    sender: Address,
+    kind: &TransactionKind,
+    config: &Config,
+) -> Result<(), Error> {
+    let Some(permissions) = &config.sender_permissions else { return Ok(()) };
+    if !permissions.is_sender_permitted(&sender) {
+        return Err(Error::TransactionSenderNotPermitted { sender })
+    }
+    if matches!(kind, TransactionKind::Create) && !permissions.is_deployer_permitted(&sender) {
+        return Err(Error::TransactionDeployerNotPermitted { deployer: sender })
+    }
+    Ok(())
+}
+
 /// Iterate over all transactions, verify them agains each other and against the block.
 /// There is no gas check done as [REVM](https://github.com/bluealloy/revm/blob/fd0108381799662098b7ab2c429ea719d6dfbf28/crates/revm/src/evm_impl.rs#L113-L131) already checks that.
 pub fn validate_all_transaction_regarding_block_and_nonces<
@@ -145,6 +167,7 @@ pub fn validate_all_transaction_regarding_block_and_nonces<
             header.number,
             header.base_fee_per_gas,
         )?;
+        validate_sender_permissions(transaction.signer(), transaction.kind(), config)?;
 
         // Get nonce, if there is previous transaction from same sender we need
         // to take that nonce.
@@ -204,6 +227,8 @@ pub fn validate_block_standalone(block: &SealedBlock) -> Result<(), Error> {
         })
     }
 
+    // TODO: Compare the withdrawals root once blocks carry the withdrawals of Shanghai
+
     Ok(())
 }
 
@@ -247,8 +272,8 @@ pub fn validate_header_regarding_parent(
         })
     }
 
-    // timestamp in past check
-    if child.timestamp < parent.timestamp {
+    // Timestamp has to increase, yellow paper eq. 52: Hs > P(H)Hs
+    if child.timestamp <= parent.timestamp {
         return Err(Error::TimestampIsInPast {
             parent_timestamp: parent.timestamp,
             timestamp: child.timestamp,
@@ -269,14 +294,15 @@ pub fn validate_header_regarding_parent(
     }
 
     // Check gas limit, max diff between child/parent gas_limit should be  max_diff=parent_gas/1024
+    let max_gas_limit_change = parent_gas_limit / config::GAS_LIMIT_BOUND_DIVISOR;
     if child.gas_limit > parent_gas_limit {
-        if child.gas_limit - parent_gas_limit >= parent_gas_limit / 1024 {
+        if child.gas_limit - parent_gas_limit >= max_gas_limit_change {
             return Err(Error::GasLimitInvalidIncrease {
                 parent_gas_limit,
                 child_gas_limit: child.gas_limit,
             })
         }
-    } else if parent_gas_limit - child.gas_limit >= parent_gas_limit / 1024 {
+    } else if parent_gas_limit - child.gas_limit >= max_gas_limit_change {
         return Err(Error::GasLimitInvalidDecrease {
             parent_gas_limit,
             child_gas_limit: child.gas_limit,
@@ -305,6 +331,34 @@ pub fn validate_header_regarding_parent(
     Ok(())
 }
 
+/// Validate header in regards to the total difficulty of its parent.
+///
+/// The block after the terminal block, the first block whose parent has reached the terminal total
+/// difficulty, is the first proof-of-stake block. Proof-of-work blocks can't extend the chain past
+/// the terminal block and proof-of-stake blocks can't precede it.
+pub fn validate_header_regarding_total_difficulty(
+    header: &SealedHeader,
+    parent_total_difficulty: U256,
+    config: &config::Config,
+) -> Result<(), Error> {
+    let terminal_total_difficulty = U256::from(config.merge_terminal_total_difficulty);
+    if config.is_paris_active_at_total_difficulty(parent_total_difficulty) {
+        if header.difficulty != U256::zero() {
+            return Err(Error::PoWBlockAfterMerge {
+                difficulty: header.difficulty,
+                terminal_total_difficulty,
+            })
+        }
+    } else if header.difficulty == U256::zero() {
+        return Err(Error::PoSBlockBeforeMerge {
+            parent_total_difficulty,
+            terminal_total_difficulty,
+        })
+    }
+
+    Ok(())
+}
+
 /// Validate block in regards to chain (parent)
 ///
 /// Checks:
@@ -363,9 +417,10 @@ pub fn full_validation<Provider: HeaderProvider + AccountProvider>(
 mod tests {
     use reth_interfaces::Result;
     use reth_primitives::{
-        hex_literal::hex, Account, Address, BlockHash, Bytes, Header, Signature, TransactionKind,
+        hex_literal::hex, Account, BlockHash, Bytes, Header, SenderPermissions, Signature,
         TransactionSigned,
     };
+    use std::collections::HashSet;
 
     use super::*;
 
@@ -485,6 +540,7 @@ mod tests {
         parent.gas_limit = 30000000;
         parent.base_fee_per_gas = Some(0x28041f7f5);
         parent.number -= 1;
+        parent.timestamp -= 12;
 
         let ommers = Vec::new();
         let body = Vec::new();
@@ -514,6 +570,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn gas_limit_out_of_bounds() {
+        let (block, _) = mock_block();
+        let config = Config::default();
+        let mut header = block.header.unseal();
+        header.gas_used = 0;
+        header.gas_limit = config::MIN_GAS_LIMIT - 1;
+
+        assert_eq!(
+            validate_header_standalone(&header.seal(), &config),
+            Err(Error::HeaderGasLimitOutOfBounds { gas_limit: config::MIN_GAS_LIMIT - 1 })
+        );
+    }
+
+    #[test]
+    fn timestamp_not_after_parent() {
+        let (block, mut parent) = mock_block();
+        let config = Config::default();
+        parent.timestamp = block.timestamp;
+
+        assert_eq!(
+            validate_header_regarding_parent(&parent.seal(), &block.header, &config),
+            Err(Error::TimestampIsInPast {
+                parent_timestamp: block.timestamp,
+                timestamp: block.timestamp
+            })
+        );
+    }
+
+    #[test]
+    fn gas_limit_change_out_of_bounds() {
+        let (block, parent) = mock_block();
+        let config = Config::default();
+        let parent = parent.seal();
+        let max_change = parent.gas_limit / config::GAS_LIMIT_BOUND_DIVISOR;
+
+        let mut header = block.header.clone().unseal();
+        header.gas_limit = parent.gas_limit + max_change;
+        assert_eq!(
+            validate_header_regarding_parent(&parent, &header.clone().seal(), &config),
+            Err(Error::GasLimitInvalidIncrease {
+                parent_gas_limit: parent.gas_limit,
+                child_gas_limit: header.gas_limit
+            })
+        );
+
+        header.gas_limit = parent.gas_limit - max_change;
+        assert_eq!(
+            validate_header_regarding_parent(&parent, &header.clone().seal(), &config),
+            Err(Error::GasLimitInvalidDecrease {
+                parent_gas_limit: parent.gas_limit,
+                child_gas_limit: header.gas_limit
+            })
+        );
+    }
+
+    #[test]
+    fn base_fee_mismatch() {
+        let (block, parent) = mock_block();
+        let config = Config::default();
+        let expected = block.base_fee_per_gas.unwrap();
+        let mut header = block.header.unseal();
+        header.base_fee_per_gas = Some(expected + 1);
+
+        assert_eq!(
+            validate_header_regarding_parent(&parent.seal(), &header.seal(), &config),
+            Err(Error::BaseFeeDiff { expected, got: expected + 1 })
+        );
+    }
+
+    #[test]
+    fn merge_transition() {
+        let (block, parent) = mock_block();
+        let config = Config::default();
+        let terminal_total_difficulty = U256::from(config.merge_terminal_total_difficulty);
+
+        // the parent is the terminal block
+        let mut pos = block.header.clone().unseal();
+        pos.difficulty = U256::zero();
+        let pos = pos.seal();
+        assert_eq!(
+            validate_header_regarding_total_difficulty(&pos, terminal_total_difficulty, &config),
+            Ok(())
+        );
+        assert_eq!(
+            validate_header_regarding_total_difficulty(
+                &pos,
+                terminal_total_difficulty - 1,
+                &config
+            ),
+            Err(Error::PoSBlockBeforeMerge {
+                parent_total_difficulty: terminal_total_difficulty - 1,
+                terminal_total_difficulty
+            })
+        );
+
+        let mut pow = block.header.unseal();
+        pow.difficulty = parent.difficulty + 1;
+        let pow = pow.seal();
+        assert_eq!(
+            validate_header_regarding_total_difficulty(
+                &pow,
+                terminal_total_difficulty - 1,
+                &config
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate_header_regarding_total_difficulty(&pow, terminal_total_difficulty, &config),
+            Err(Error::PoWBlockAfterMerge {
+                difficulty: pow.difficulty,
+                terminal_total_difficulty
+            })
+        );
+    }
+
     #[test]
     fn sanity_tx_nonce_check() {
         let (block, _) = mock_block();
@@ -570,4 +742,59 @@ mod tests {
             Err(Error::TransactionNonceNotConsistent.into())
         );
     }
+
+    #[test]
+    fn sender_not_permitted() {
+        let (block, _) = mock_block();
+        let txs = vec![mock_tx(0)];
+        let signer = txs[0].signer();
+        let mut config = Config {
+            sender_permissions: Some(SenderPermissions {
+                allowed_senders: Some(HashSet::from([signer])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        validate_all_transaction_regarding_block_and_nonces(
+            txs.iter(),
+            &block.header,
+            Provider::new_known(),
+            &config,
+        )
+        .expect("To Pass");
+
+        config.sender_permissions = Some(SenderPermissions {
+            denied_senders: HashSet::from([signer]),
+            ..Default::default()
+        });
+        assert_eq!(
+            validate_all_transaction_regarding_block_and_nonces(
+                txs.iter(),
+                &block.header,
+                Provider::new_known(),
+                &config,
+            ),
+            Err(Error::TransactionSenderNotPermitted { sender: signer }.into())
+        );
+    }
+
+    #[test]
+    fn deployer_not_permitted() {
+        let config = Config {
+            sender_permissions: Some(SenderPermissions {
+                allowed_deployers: Some(HashSet::new()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let sender = Address::zero();
+        assert_eq!(
+            validate_sender_permissions(sender, &TransactionKind::Create, &config),
+            Err(Error::TransactionDeployerNotPermitted { deployer: sender })
+        );
+        assert_eq!(
+            validate_sender_permissions(sender, &TransactionKind::Call(sender), &config),
+            Ok(())
+        );
+    }
 }
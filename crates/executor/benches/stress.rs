@@ -0,0 +1,113 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use reth_executor::{
+    executor::execute,
+    revm_wrap::{State, SubState},
+    Config, SpecUpgrades,
+};
+use reth_interfaces::{
+    executor::Error,
+    test_utils::generators::stress::{
+        stress_block_range, StressAccount, StressConfig, StressFixture, StressProfile,
+    },
+};
+use reth_primitives::{
+    Account, Address, Bytes, Header, StorageKey, StorageValue, TransactionSignedEcRecovered, H256,
+    U256,
+};
+use reth_provider::{AccountProvider, StateProvider};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// The worst cases that are measured.
+const PROFILES: [(&str, StressProfile); 4] = [
+    ("max_calldata", StressProfile::MaxCalldata),
+    ("deep_calls", StressProfile::DeepCalls { depth: 255, fanout: 1 }),
+    ("call_tree", StressProfile::DeepCalls { depth: 8, fanout: 3 }),
+    ("storage_heavy", StressProfile::StorageHeavy { prefilled_slots: 500, slots_per_tx: 100 }),
+];
+
+/// Provides the pre-state of a [StressFixture].
+#[derive(Clone)]
+struct FixtureState {
+    accounts: Arc<BTreeMap<Address, StressAccount>>,
+}
+
+impl AccountProvider for FixtureState {
+    fn basic_account(&self, address: Address) -> reth_interfaces::Result<Option<Account>> {
+        Ok(self.accounts.get(&address).map(|account| account.account))
+    }
+}
+
+impl StateProvider for FixtureState {
+    fn storage(
+        &self,
+        account: Address,
+        storage_key: StorageKey,
+    ) -> reth_interfaces::Result<Option<StorageValue>> {
+        Ok(self
+            .accounts
+            .get(&account)
+            .and_then(|account| account.storage.get(&storage_key).copied()))
+    }
+
+    fn bytecode_by_hash(&self, code_hash: H256) -> reth_interfaces::Result<Option<Bytes>> {
+        Ok(self
+            .accounts
+            .values()
+            .find(|account| account.account.bytecode_hash == Some(code_hash))
+            .and_then(|account| account.code.clone()))
+    }
+
+    fn block_hash(&self, _number: U256) -> reth_interfaces::Result<Option<H256>> {
+        Ok(None)
+    }
+}
+
+/// Returns the header of the first block of the fixture with the `gas_used` it actually uses.
+fn calibrated_header(
+    fixture: &StressFixture,
+    transactions: &[TransactionSignedEcRecovered],
+    config: &Config,
+    state: &FixtureState,
+) -> Header {
+    let mut header = fixture.blocks[0].header.as_ref().clone();
+    match execute(&header, transactions, config, SubState::new(State::new(state.clone()))) {
+        Ok(_) => {}
+        Err(Error::BlockGasUsed { got, .. }) => header.gas_used = got,
+        Err(err) => panic!("Failed to execute stress block: {err:?}"),
+    }
+    header
+}
+
+pub fn execution(c: &mut Criterion) {
+    let config = Config {
+        chain_id: U256::from(1),
+        spec_upgrades: SpecUpgrades::new_london_activated(),
+        hooks: Default::default(),
+    };
+    let mut group = c.benchmark_group("Execution");
+    group.sample_size(10);
+
+    for (name, profile) in PROFILES {
+        let fixture = stress_block_range(&StressConfig::new(profile));
+        let state = FixtureState { accounts: Arc::new(fixture.prestate.clone()) };
+        let transactions = fixture.blocks[0]
+            .body
+            .iter()
+            .map(|tx| tx.try_ecrecovered().expect("valid signature"))
+            .collect::<Vec<_>>();
+        let header = calibrated_header(&fixture, &transactions, &config, &state);
+
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || SubState::new(State::new(state.clone())),
+                |db| execute(&header, &transactions, &config, db).expect("block executes"),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, execution);
+criterion_main!(benches);
@@ -0,0 +1,607 @@
+//! Batched writes of the state changes of executed blocks.
+//!
+//! [ExecutionResult::apply_to_db] writes every account and storage change of a block as soon as
+//! it is applied. A [StateBatch] instead accumulates the results of many blocks in memory, only
+//! keeps the final value of every account and storage slot, and writes everything sorted by key
+//! at once. The change sets of new transitions are appended to the end of their tables.
+//!
+//! Until the batch is written, the blocks that follow the batch read the state through a
+//! [BatchStateProvider], which serves the changes of the batch on top of the stored state.
+
+use crate::executor::{AccountChangeSet, AccountInfoChangeSet, ExecutionResult};
+use reth_db::{
+    cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW},
+    models::{AccountBeforeTx, TransitionIdAddress},
+    tables,
+    transaction::DbTxMut,
+    Error as DbError,
+};
+use reth_primitives::{
+    Account, Address, Bytes, StorageEntry, StorageKey, StorageValue, TransitionId, H256, U256,
+};
+use reth_provider::{AccountProvider, StateProvider};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::trace;
+
+/// The state changes of a consecutive range of transitions, see the [module docs](self).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StateBatch {
+    /// The first transition of the batch.
+    first_transition: Option<TransitionId>,
+    /// The next transition after the batch.
+    next_transition: TransitionId,
+    /// The final accounts, `None` if the account was destroyed.
+    accounts: BTreeMap<Address, Option<Account>>,
+    /// The final storage of the changed accounts.
+    storage: BTreeMap<Address, StorageChanges>,
+    /// The new bytecodes.
+    bytecodes: BTreeMap<H256, Vec<u8>>,
+    /// The accounts prior to every transition.
+    account_changesets: BTreeMap<TransitionId, Vec<AccountBeforeTx>>,
+    /// The storage slots prior to every transition.
+    storage_changesets: BTreeMap<(TransitionId, Address), Vec<StorageEntry>>,
+}
+
+/// The final storage of an account in a [StateBatch].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct StorageChanges {
+    /// The first transition that wiped the storage of the account. The stored storage is wiped
+    /// before the slots are written, and the stored slots are added to the change set of the
+    /// transition.
+    wiped_at: Option<TransitionId>,
+    /// The slots whose values prior to the first wipe are in the change sets of the batch.
+    recorded: BTreeSet<H256>,
+    /// The final values of the changed slots, zero if the slot is cleared.
+    slots: BTreeMap<H256, U256>,
+}
+
+// === impl StateBatch ===
+
+impl StateBatch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the batch contains no changes.
+    pub fn is_empty(&self) -> bool {
+        self.first_transition.is_none()
+    }
+
+    /// Returns the account after the changes of the batch, `None` if the batch doesn't change it.
+    ///
+    /// The inner value is `None` if the account was destroyed.
+    pub fn account(&self, address: &Address) -> Option<Option<Account>> {
+        self.accounts.get(address).copied()
+    }
+
+    /// Returns the value of the storage slot after the changes of the batch, `None` if the batch
+    /// doesn't change it.
+    ///
+    /// The slots of an account whose storage was wiped are zero unless they were written after the
+    /// wipe.
+    pub fn storage(&self, address: &Address, key: &H256) -> Option<U256> {
+        let changes = self.storage.get(address)?;
+        match changes.slots.get(key) {
+            Some(value) => Some(*value),
+            None => changes.wiped_at.map(|_| U256::zero()),
+        }
+    }
+
+    /// Returns a bytecode that was deployed by the batch.
+    pub fn bytecode(&self, code_hash: &H256) -> Option<&[u8]> {
+        self.bytecodes.get(code_hash).map(Vec::as_slice)
+    }
+
+    /// Adds the changes of a block that start at the given transition.
+    ///
+    /// Returns the transition after the last one of the block, the blocks have to be added in the
+    /// order of their transitions.
+    pub fn extend(
+        &mut self,
+        result: ExecutionResult,
+        mut transition_id: TransitionId,
+    ) -> TransitionId {
+        debug_assert!(
+            self.is_empty() || transition_id >= self.next_transition,
+            "blocks are added in order"
+        );
+        self.first_transition.get_or_insert(transition_id);
+
+        for result in result.changesets.into_iter() {
+            for (address, account_change_set) in result.changeset.into_iter() {
+                let AccountChangeSet { account, wipe_storage, storage } = account_change_set;
+                self.insert_account_change(transition_id, address, account);
+
+                if !wipe_storage && storage.is_empty() {
+                    continue
+                }
+                let storage: BTreeMap<H256, (U256, U256)> = storage
+                    .into_iter()
+                    .map(|(key, values)| {
+                        let mut hkey = H256::zero();
+                        key.to_big_endian(&mut hkey.0);
+                        (hkey, values)
+                    })
+                    .collect();
+                let changes = self.storage.entry(address).or_default();
+                let mut previous = Vec::with_capacity(storage.len());
+
+                if wipe_storage {
+                    if changes.wiped_at.is_none() {
+                        changes.wiped_at = Some(transition_id);
+                        changes.recorded =
+                            changes.slots.keys().chain(storage.keys()).copied().collect();
+                    }
+                    // the slots changed by the batch are wiped as well
+                    for (key, value) in std::mem::take(&mut changes.slots) {
+                        if !value.is_zero() && !storage.contains_key(&key) {
+                            previous.push(StorageEntry { key, value });
+                        }
+                    }
+                }
+                for (key, (old_value, new_value)) in storage {
+                    previous.push(StorageEntry { key, value: old_value });
+                    changes.slots.insert(key, new_value);
+                }
+                if !previous.is_empty() {
+                    previous.sort_by_key(|entry| entry.key);
+                    self.storage_changesets.insert((transition_id, address), previous);
+                }
+            }
+            for (hash, bytecode) in result.new_bytecodes.into_iter() {
+                let bytecode = bytecode.bytes();
+                self.bytecodes.insert(hash, bytecode[..bytecode.len()].to_vec());
+            }
+            transition_id += 1;
+        }
+
+        if let Some(block_reward_changeset) = result.block_reward {
+            for (address, changeset) in block_reward_changeset.into_iter() {
+                self.insert_account_change(transition_id, address, changeset);
+            }
+            transition_id += 1;
+        }
+
+        self.next_transition = transition_id;
+        transition_id
+    }
+
+    fn insert_account_change(
+        &mut self,
+        transition_id: TransitionId,
+        address: Address,
+        change: AccountInfoChangeSet,
+    ) {
+        let (info, new) = match change {
+            AccountInfoChangeSet::Changed { old, new } => (Some(old), Some(new)),
+            AccountInfoChangeSet::Created { new } => (None, Some(new)),
+            AccountInfoChangeSet::Destroyed { old } => (Some(old), None),
+            AccountInfoChangeSet::NoChange => return,
+        };
+        self.account_changesets
+            .entry(transition_id)
+            .or_default()
+            .push(AccountBeforeTx { address, info });
+        self.accounts.insert(address, new);
+    }
+
+    /// Writes the changes to the database, sorted by key.
+    ///
+    /// The change sets are appended if their tables contain no later transitions. The stored slots
+    /// of wiped accounts are added to the change sets of the transitions that wiped them.
+    pub fn write_to_db<'a, TX: DbTxMut<'a>>(mut self, tx: &TX) -> Result<(), DbError> {
+        let Some(first_transition) = self.first_transition else { return Ok(()) };
+        trace!(target: "executor", first_transition, next_transition = self.next_transition, accounts = self.accounts.len(), storage = self.storage.len(), "Writing state batch");
+
+        let mut storage_cursor = tx.cursor_dup_mut::<tables::PlainStorageState>()?;
+        for (address, changes) in &self.storage {
+            let Some(wiped_at) = changes.wiped_at else { continue };
+            let mut wiped = Vec::new();
+            for entry in storage_cursor.walk_dup(*address, None)? {
+                let (_, entry) = entry?;
+                if !changes.recorded.contains(&entry.key) {
+                    wiped.push(entry);
+                }
+            }
+            if !wiped.is_empty() {
+                let previous = self.storage_changesets.entry((wiped_at, *address)).or_default();
+                previous.extend(wiped);
+                previous.sort_by_key(|entry| entry.key);
+            }
+        }
+
+        // Write the change sets
+        let mut account_changeset_cursor = tx.cursor_dup_mut::<tables::AccountChangeSet>()?;
+        let append = account_changeset_cursor
+            .last()?
+            .map_or(true, |(transition, _)| transition < first_transition);
+        for (transition, changes) in self.account_changesets {
+            // the changes of a transition are sorted by address
+            for change in changes {
+                if append {
+                    account_changeset_cursor.append_dup(transition, change)?;
+                } else {
+                    account_changeset_cursor.upsert(transition, change)?;
+                }
+            }
+        }
+
+        let mut storage_changeset_cursor = tx.cursor_dup_mut::<tables::StorageChangeSet>()?;
+        let append = storage_changeset_cursor
+            .last()?
+            .map_or(true, |(TransitionIdAddress((transition, _)), _)| {
+                transition < first_transition
+            });
+        for (key, entries) in self.storage_changesets {
+            let key = TransitionIdAddress(key);
+            // the entries of an account are sorted by key
+            for entry in entries {
+                if append {
+                    storage_changeset_cursor.append_dup(key.clone(), entry)?;
+                } else {
+                    storage_changeset_cursor.upsert(key.clone(), entry)?;
+                }
+            }
+        }
+
+        // Write the final state
+        let mut account_cursor = tx.cursor_mut::<tables::PlainAccountState>()?;
+        for (address, account) in self.accounts {
+            match account {
+                Some(account) => account_cursor.upsert(address, account)?,
+                None => {
+                    if account_cursor.seek_exact(address)?.is_some() {
+                        account_cursor.delete_current()?;
+                    }
+                }
+            }
+        }
+
+        for (address, StorageChanges { wiped_at, slots, .. }) in self.storage {
+            let wiped = wiped_at.is_some();
+            if wiped && storage_cursor.seek_exact(address)?.is_some() {
+                storage_cursor.delete_current_duplicates()?;
+            }
+            for (key, value) in slots {
+                // Always delete the old value as a put to the duplicate table does not override it
+                if !wiped {
+                    if let Some(entry) = storage_cursor.seek_by_key_subkey(address, key)? {
+                        if entry.key == key {
+                            storage_cursor.delete_current()?;
+                        }
+                    }
+                }
+                if !value.is_zero() {
+                    storage_cursor.upsert(address, StorageEntry { key, value })?;
+                }
+            }
+        }
+
+        let mut bytecode_cursor = tx.cursor_mut::<tables::Bytecodes>()?;
+        for (hash, bytecode) in self.bytecodes {
+            bytecode_cursor.upsert(hash, bytecode)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [StateProvider] that serves the state after the changes of a [StateBatch], and reads the state
+/// the batch doesn't change from the inner provider.
+#[derive(Debug)]
+pub struct BatchStateProvider<'a, SP> {
+    provider: SP,
+    batch: &'a StateBatch,
+}
+
+// === impl BatchStateProvider ===
+
+impl<'a, SP: StateProvider> BatchStateProvider<'a, SP> {
+    /// Creates a new provider of the state after the batch, on top of the state of `provider`
+    /// that the batch was executed on.
+    pub fn new(provider: SP, batch: &'a StateBatch) -> Self {
+        Self { provider, batch }
+    }
+}
+
+impl<'a, SP: StateProvider> AccountProvider for BatchStateProvider<'a, SP> {
+    fn basic_account(&self, address: Address) -> reth_interfaces::Result<Option<Account>> {
+        match self.batch.account(&address) {
+            Some(account) => Ok(account),
+            None => self.provider.basic_account(address),
+        }
+    }
+}
+
+impl<'a, SP: StateProvider> StateProvider for BatchStateProvider<'a, SP> {
+    fn storage(
+        &self,
+        account: Address,
+        storage_key: StorageKey,
+    ) -> reth_interfaces::Result<Option<StorageValue>> {
+        match self.batch.storage(&account, &storage_key) {
+            // cleared slots are not stored either
+            Some(value) => Ok((!value.is_zero()).then_some(value)),
+            None => self.provider.storage(account, storage_key),
+        }
+    }
+
+    fn bytecode_by_hash(&self, code_hash: H256) -> reth_interfaces::Result<Option<Bytes>> {
+        match self.batch.bytecode(&code_hash) {
+            Some(bytecode) => Ok(Some(Bytes::from(bytecode.to_vec()))),
+            None => self.provider.bytecode_by_hash(code_hash),
+        }
+    }
+
+    fn block_hash(&self, number: U256) -> reth_interfaces::Result<Option<H256>> {
+        self.provider.block_hash(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::TransactionChangeSet;
+    use reth_db::{
+        database::Database,
+        mdbx::{test_utils, Env, EnvKind, WriteMap},
+        transaction::DbTx,
+    };
+    use reth_primitives::Receipt;
+    use reth_provider::StateProviderImplRefLatest;
+    use std::sync::Arc;
+
+    fn changeset(
+        address: Address,
+        account: AccountInfoChangeSet,
+        storage: impl IntoIterator<Item = (u64, (u64, u64))>,
+        wipe_storage: bool,
+    ) -> TransactionChangeSet {
+        let storage = storage
+            .into_iter()
+            .map(|(key, (old, new))| (U256::from(key), (U256::from(old), U256::from(new))))
+            .collect();
+        TransactionChangeSet {
+            receipt: Receipt::default(),
+            changeset: BTreeMap::from([(
+                address,
+                AccountChangeSet { account, storage, wipe_storage },
+            )]),
+            new_bytecodes: BTreeMap::new(),
+        }
+    }
+
+    fn slot(key: u64) -> H256 {
+        H256::from_low_u64_be(key)
+    }
+
+    #[test]
+    fn write_final_state_and_changesets() {
+        let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
+        let tx = db.tx_mut().unwrap();
+
+        let address = Address::from_low_u64_be(1);
+        let destroyed = Address::from_low_u64_be(2);
+        let account = |nonce| Account { nonce, ..Default::default() };
+        tx.put::<tables::PlainAccountState>(address, account(1)).unwrap();
+        tx.put::<tables::PlainAccountState>(destroyed, account(1)).unwrap();
+        for (address, key) in [(address, 1), (address, 2), (destroyed, 1)] {
+            tx.put::<tables::PlainStorageState>(
+                address,
+                StorageEntry { key: slot(key), value: U256::from(1) },
+            )
+            .unwrap();
+        }
+
+        let mut batch = StateBatch::new();
+        let first = ExecutionResult {
+            changesets: vec![
+                changeset(
+                    address,
+                    AccountInfoChangeSet::Changed { old: account(1), new: account(2) },
+                    [(1, (1, 2)), (3, (0, 1))],
+                    false,
+                ),
+                changeset(destroyed, AccountInfoChangeSet::Destroyed { old: account(1) }, [], true),
+            ],
+            block_reward: None,
+        };
+        let second = ExecutionResult {
+            changesets: vec![changeset(
+                address,
+                AccountInfoChangeSet::Changed { old: account(2), new: account(3) },
+                [(1, (2, 3)), (2, (1, 0))],
+                false,
+            )],
+            block_reward: Some(BTreeMap::from([(
+                destroyed,
+                AccountInfoChangeSet::Created { new: account(0) },
+            )])),
+        };
+        assert_eq!(batch.extend(first, 5), 7);
+        assert_eq!(batch.extend(second, 7), 9);
+        batch.write_to_db(&tx).unwrap();
+
+        // only the final values are stored
+        assert_eq!(tx.get::<tables::PlainAccountState>(address), Ok(Some(account(3))));
+        assert_eq!(tx.get::<tables::PlainAccountState>(destroyed), Ok(Some(account(0))));
+        let storage = tx
+            .cursor_dup::<tables::PlainStorageState>()
+            .unwrap()
+            .walk(Address::zero())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            storage,
+            vec![
+                (address, StorageEntry { key: slot(1), value: U256::from(3) }),
+                (address, StorageEntry { key: slot(3), value: U256::from(1) }),
+            ]
+        );
+
+        // every transition has its change set
+        let account_changesets = tx
+            .cursor_dup::<tables::AccountChangeSet>()
+            .unwrap()
+            .walk(0)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            account_changesets,
+            vec![
+                (5, AccountBeforeTx { address, info: Some(account(1)) }),
+                (6, AccountBeforeTx { address: destroyed, info: Some(account(1)) }),
+                (7, AccountBeforeTx { address, info: Some(account(2)) }),
+                (8, AccountBeforeTx { address: destroyed, info: None }),
+            ]
+        );
+        let storage_changesets = tx
+            .cursor_dup::<tables::StorageChangeSet>()
+            .unwrap()
+            .walk(TransitionIdAddress((0, Address::zero())))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            storage_changesets,
+            vec![
+                (
+                    TransitionIdAddress((5, address)),
+                    StorageEntry { key: slot(1), value: U256::from(1) }
+                ),
+                (
+                    TransitionIdAddress((5, address)),
+                    StorageEntry { key: slot(3), value: U256::zero() }
+                ),
+                (
+                    TransitionIdAddress((6, destroyed)),
+                    StorageEntry { key: slot(1), value: U256::from(1) }
+                ),
+                (
+                    TransitionIdAddress((7, address)),
+                    StorageEntry { key: slot(1), value: U256::from(2) }
+                ),
+                (
+                    TransitionIdAddress((7, address)),
+                    StorageEntry { key: slot(2), value: U256::from(1) }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_through_batch() {
+        let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
+        let tx = db.tx_mut().unwrap();
+
+        let address = Address::from_low_u64_be(1);
+        let destroyed = Address::from_low_u64_be(2);
+        let untouched = Address::from_low_u64_be(3);
+        let account = |nonce| Account { nonce, ..Default::default() };
+        for address in [address, destroyed, untouched] {
+            tx.put::<tables::PlainAccountState>(address, account(1)).unwrap();
+            for key in [1, 2] {
+                tx.put::<tables::PlainStorageState>(
+                    address,
+                    StorageEntry { key: slot(key), value: U256::from(1) },
+                )
+                .unwrap();
+            }
+        }
+
+        let mut batch = StateBatch::new();
+        batch.extend(
+            ExecutionResult {
+                changesets: vec![
+                    changeset(
+                        address,
+                        AccountInfoChangeSet::Changed { old: account(1), new: account(2) },
+                        [(1, (1, 2)), (2, (1, 0))],
+                        false,
+                    ),
+                    changeset(
+                        destroyed,
+                        AccountInfoChangeSet::Destroyed { old: account(1) },
+                        [],
+                        true,
+                    ),
+                ],
+                block_reward: None,
+            },
+            1,
+        );
+
+        // the next block reads the state after the batch
+        let provider = BatchStateProvider::new(StateProviderImplRefLatest::new(&tx), &batch);
+        assert_eq!(provider.basic_account(address).unwrap(), Some(account(2)));
+        assert_eq!(provider.basic_account(destroyed).unwrap(), None);
+        assert_eq!(provider.basic_account(untouched).unwrap(), Some(account(1)));
+        assert_eq!(provider.storage(address, slot(1)).unwrap(), Some(U256::from(2)));
+        assert_eq!(provider.storage(address, slot(2)).unwrap(), None);
+        assert_eq!(provider.storage(destroyed, slot(1)).unwrap(), None);
+        assert_eq!(provider.storage(untouched, slot(1)).unwrap(), Some(U256::from(1)));
+    }
+
+    #[test]
+    fn wiped_slots_in_changesets() {
+        let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
+        let tx = db.tx_mut().unwrap();
+
+        let address = Address::from_low_u64_be(1);
+        for key in [1, 2, 4] {
+            tx.put::<tables::PlainStorageState>(
+                address,
+                StorageEntry { key: slot(key), value: U256::from(1) },
+            )
+            .unwrap();
+        }
+
+        // the slots are changed before the account is wiped and a slot is written again
+        let mut batch = StateBatch::new();
+        let result = ExecutionResult {
+            changesets: vec![
+                changeset(
+                    address,
+                    AccountInfoChangeSet::NoChange,
+                    [(1, (1, 2)), (3, (0, 1))],
+                    false,
+                ),
+                changeset(address, AccountInfoChangeSet::NoChange, [(2, (1, 5))], true),
+            ],
+            block_reward: None,
+        };
+        assert_eq!(batch.extend(result, 5), 7);
+        batch.write_to_db(&tx).unwrap();
+
+        let storage = tx
+            .cursor_dup::<tables::PlainStorageState>()
+            .unwrap()
+            .walk(Address::zero())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(storage, vec![(address, StorageEntry { key: slot(2), value: U256::from(5) })]);
+
+        // the wipe records the values of the batch and the untouched stored slots
+        let storage_changesets = tx
+            .cursor_dup::<tables::StorageChangeSet>()
+            .unwrap()
+            .walk(TransitionIdAddress((6, Address::zero())))
+            .unwrap()
+            .map(|entry| entry.map(|(_, entry)| (entry.key, entry.value)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            storage_changesets,
+            vec![
+                (slot(1), U256::from(2)),
+                (slot(2), U256::from(1)),
+                (slot(3), U256::from(1)),
+                (slot(4), U256::from(1)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Builds blocks from transactions that are already ordered, e.g. by the sequencer of a rollup.
+
+use crate::{
+    executor::{execute_transactions, ExecutionResult},
+    revm_wrap::SubState,
+    Config,
+};
+use reth_interfaces::executor::Error;
+use reth_primitives::{
+    proofs::{self, EMPTY_LIST_HASH},
+    Address, Bloom, Bytes, Header, SealedBlock, SealedHeader, TransactionSignedEcRecovered, H256,
+};
+use reth_provider::StateProvider;
+
+/// The fields of a block that are chosen by its producer instead of resulting from the execution.
+///
+/// This mirrors the payload attributes of the engine API.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockAttributes {
+    /// The timestamp of the block, must be greater than the parent timestamp.
+    pub timestamp: u64,
+    /// The address that receives the priority fees.
+    pub beneficiary: Address,
+    /// The randomness of the block, stored as the mix hash.
+    pub prev_randao: H256,
+    /// The gas limit of the block.
+    pub gas_limit: u64,
+    /// The base fee of the block, if EIP-1559 is active.
+    pub base_fee_per_gas: Option<u64>,
+    /// Arbitrary data of the block.
+    pub extra_data: Bytes,
+}
+
+/// Executes the transactions on top of the parent and assembles the block.
+///
+/// The transactions are included in the given order, any transaction that does not fit into the
+/// gas limit fails the whole block.
+///
+/// NOTE: The state root is not computed yet and is left empty.
+pub fn build_block<DB: StateProvider>(
+    parent: &SealedHeader,
+    attributes: BlockAttributes,
+    transactions: Vec<TransactionSignedEcRecovered>,
+    config: &Config,
+    db: SubState<DB>,
+) -> Result<(SealedBlock, ExecutionResult), Error> {
+    let header = new_header(parent, attributes);
+    let result = execute_transactions(&header, &transactions, config, db)?;
+    let block = seal_block(header, transactions, &result);
+    Ok((block, result))
+}
+
+/// Creates the header of the child of `parent`, without execution results.
+pub(crate) fn new_header(parent: &SealedHeader, attributes: BlockAttributes) -> Header {
+    let BlockAttributes {
+        timestamp,
+        beneficiary,
+        prev_randao,
+        gas_limit,
+        base_fee_per_gas,
+        extra_data,
+    } = attributes;
+
+    Header {
+        parent_hash: parent.hash(),
+        ommers_hash: EMPTY_LIST_HASH,
+        beneficiary,
+        number: parent.number + 1,
+        gas_limit,
+        timestamp,
+        mix_hash: prev_randao,
+        base_fee_per_gas,
+        extra_data: extra_data.0,
+        ..Default::default()
+    }
+}
+
+/// Fills in the execution results of the transactions and assembles the block.
+pub(crate) fn seal_block(
+    mut header: Header,
+    transactions: Vec<TransactionSignedEcRecovered>,
+    result: &ExecutionResult,
+) -> SealedBlock {
+    let receipts = result.changesets.iter().map(|changeset| &changeset.receipt);
+    header.gas_used = result.gas_used();
+    header.receipts_root = proofs::calculate_receipt_root(receipts.clone());
+    header.logs_bloom = receipts.fold(Bloom::zero(), |bloom, receipt| bloom | receipt.bloom);
+
+    let body = transactions.into_iter().map(|tx| tx.into_signed()).collect::<Vec<_>>();
+    header.transactions_root = proofs::calculate_transaction_root(body.iter());
+
+    SealedBlock { header: header.seal(), body, ommers: Vec::new() }
+}
@@ -0,0 +1,588 @@
+//! Bundles of transactions of local order flow, e.g. of searchers connected to the node.
+//!
+//! A [Bundle] is included in a block as a whole or not at all, with its transactions in the given
+//! order. [build_block_with_bundles] simulates every bundle that is valid for the block and only
+//! includes it if the block pays the beneficiary more with the bundle than without it.
+
+use crate::{
+    builder::{new_header, seal_block, BlockAttributes},
+    executor::{execute_transactions, AccountInfoChangeSet, ExecutionResult},
+    revm_wrap::SubState,
+    Config,
+};
+use reth_interfaces::executor::Error;
+use reth_primitives::{
+    keccak256, Address, BlockNumber, Header, SealedBlock, SealedHeader,
+    TransactionSignedEcRecovered, TxHash, H256, U256,
+};
+use reth_provider::StateProvider;
+use std::{cmp::Reverse, collections::HashSet};
+use tracing::trace;
+
+/// The default maximum number of bundles in a [BundlePool].
+pub const DEFAULT_MAX_BUNDLES: usize = 1024;
+
+/// An ordered group of transactions that is included atomically.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bundle {
+    /// The transactions of the bundle, in the order they are executed.
+    pub transactions: Vec<TransactionSignedEcRecovered>,
+    /// The transactions of the bundle that are allowed to fail.
+    ///
+    /// The bundle is dropped if any other of its transactions fails.
+    pub reverting_tx_hashes: Vec<TxHash>,
+    /// Whether the bundle has to be placed before all other transactions of the block.
+    pub top_of_block: bool,
+    /// The first block the bundle can be included in.
+    pub min_block_number: Option<BlockNumber>,
+    /// The last block the bundle can be included in.
+    pub max_block_number: Option<BlockNumber>,
+    /// The minimum timestamp of a block the bundle is included in.
+    pub min_timestamp: Option<u64>,
+    /// The maximum timestamp of a block the bundle is included in.
+    pub max_timestamp: Option<u64>,
+}
+
+// === impl Bundle ===
+
+impl Bundle {
+    /// Returns the hash of the bundle, the hash of the concatenated transaction hashes.
+    pub fn hash(&self) -> H256 {
+        keccak256(self.transactions.iter().flat_map(|tx| tx.hash().0).collect::<Vec<_>>())
+    }
+
+    /// Returns true if the bundle can be included in a block with the given number and timestamp.
+    pub fn is_valid_at(&self, number: BlockNumber, timestamp: u64) -> bool {
+        self.min_block_number.map_or(true, |min| number >= min) &&
+            self.max_block_number.map_or(true, |max| number <= max) &&
+            self.min_timestamp.map_or(true, |min| timestamp >= min) &&
+            self.max_timestamp.map_or(true, |max| timestamp <= max)
+    }
+
+    /// Returns true if the bundle can't be included in any child of the given head.
+    fn is_expired(&self, head: &Header) -> bool {
+        // the timestamp of a child is greater than the timestamp of its parent
+        self.max_block_number.map_or(false, |max| max <= head.number) ||
+            self.max_timestamp.map_or(false, |max| max <= head.timestamp)
+    }
+
+    /// Returns true if the transaction may fail without invalidating the bundle.
+    fn may_revert(&self, hash: TxHash) -> bool {
+        self.reverting_tx_hashes.contains(&hash)
+    }
+}
+
+/// Reasons a bundle is rejected by the [BundlePool].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BundleError {
+    /// The bundle has no transactions.
+    #[error("The bundle has no transactions.")]
+    Empty,
+    /// The bundle can't be included in any block.
+    #[error("The validity window of the bundle is empty.")]
+    EmptyWindow,
+    /// A transaction that may revert is not part of the bundle.
+    #[error("Transaction {0:?} that may revert is not part of the bundle.")]
+    UnknownRevertingTransaction(TxHash),
+    /// The bundle is already in the pool.
+    #[error("Bundle {0:?} is already known.")]
+    AlreadyKnown(H256),
+    /// The pool is full.
+    #[error("The bundle pool is full, it holds at most {max} bundles.")]
+    PoolFull {
+        /// The maximum number of bundles.
+        max: usize,
+    },
+}
+
+/// The bundles that were submitted to the node and are offered to the block builder.
+#[derive(Debug)]
+pub struct BundlePool {
+    /// The bundles, in the order they were submitted.
+    bundles: Vec<Bundle>,
+    /// The maximum number of bundles.
+    max_bundles: usize,
+}
+
+// === impl BundlePool ===
+
+impl BundlePool {
+    /// Creates an empty pool that holds at most `max_bundles` bundles.
+    pub fn new(max_bundles: usize) -> Self {
+        Self { bundles: Vec::new(), max_bundles }
+    }
+
+    /// Adds the bundle to the pool and returns its hash.
+    pub fn insert(&mut self, bundle: Bundle) -> Result<H256, BundleError> {
+        if bundle.transactions.is_empty() {
+            return Err(BundleError::Empty)
+        }
+        let empty_window =
+            |min: Option<u64>, max: Option<u64>| min.zip(max).map_or(false, |(min, max)| min > max);
+        if empty_window(bundle.min_block_number, bundle.max_block_number) ||
+            empty_window(bundle.min_timestamp, bundle.max_timestamp)
+        {
+            return Err(BundleError::EmptyWindow)
+        }
+        if let Some(hash) = bundle
+            .reverting_tx_hashes
+            .iter()
+            .find(|hash| !bundle.transactions.iter().any(|tx| tx.hash() == **hash))
+        {
+            return Err(BundleError::UnknownRevertingTransaction(*hash))
+        }
+
+        let hash = bundle.hash();
+        if self.bundles.iter().any(|known| known.hash() == hash) {
+            return Err(BundleError::AlreadyKnown(hash))
+        }
+        if self.bundles.len() >= self.max_bundles {
+            return Err(BundleError::PoolFull { max: self.max_bundles })
+        }
+        self.bundles.push(bundle);
+        Ok(hash)
+    }
+
+    /// Returns the bundles in the order they were submitted.
+    pub fn bundles(&self) -> &[Bundle] {
+        &self.bundles
+    }
+
+    /// Returns the number of bundles.
+    pub fn len(&self) -> usize {
+        self.bundles.len()
+    }
+
+    /// Returns true if the pool holds no bundles.
+    pub fn is_empty(&self) -> bool {
+        self.bundles.is_empty()
+    }
+
+    /// Removes the bundles that can't be included after the new canonical block, because their
+    /// validity window has passed or one of their transactions is part of the block.
+    pub fn on_new_block(&mut self, block: &SealedBlock) {
+        let included = block.body.iter().map(|tx| tx.hash()).collect::<HashSet<_>>();
+        self.bundles.retain(|bundle| {
+            !bundle.is_expired(&block.header) &&
+                !bundle.transactions.iter().any(|tx| included.contains(&tx.hash()))
+        });
+    }
+}
+
+impl Default for BundlePool {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BUNDLES)
+    }
+}
+
+/// A block built by [build_block_with_bundles].
+#[derive(Debug)]
+pub struct BundleBlock {
+    /// The block, the state root is not computed yet and is left empty.
+    pub block: SealedBlock,
+    /// The result of the execution of the block.
+    pub result: ExecutionResult,
+    /// The hashes of the included bundles, in the order they are placed in the block.
+    pub bundles: Vec<H256>,
+    /// The amount the transactions of the block pay to the beneficiary, without the block reward.
+    pub payment: U256,
+}
+
+/// Executes the bundles and transactions on top of the parent and assembles the most profitable
+/// block.
+///
+/// The bundles are placed before the transactions, which are included in the given order. A
+/// transaction is skipped if it does not fit into the remaining gas, and so are all following
+/// transactions of its sender. Transactions that are replaced by a transaction of an included
+/// bundle with the same sender and nonce are skipped as well.
+///
+/// Every bundle that is valid for the block is simulated on top of the parent first, bundles that
+/// don't pay the beneficiary are dropped. The others are tried in the order of their payment, with
+/// the bundles that have to be at the top of the block first. A bundle is included if none of its
+/// transactions fails unexpectedly and the block pays more with it than without it.
+///
+/// `state` is called for every execution and has to return the state after the parent.
+///
+/// NOTE: The state root is not computed yet and is left empty.
+pub fn build_block_with_bundles<DB: StateProvider>(
+    parent: &SealedHeader,
+    attributes: BlockAttributes,
+    bundles: &[Bundle],
+    transactions: Vec<TransactionSignedEcRecovered>,
+    config: &Config,
+    state: impl Fn() -> SubState<DB>,
+) -> Result<BundleBlock, Error> {
+    let header = new_header(parent, attributes);
+    let builder = Builder { header: &header, config, state, transactions: &transactions };
+
+    let mut candidates = Vec::new();
+    for bundle in
+        bundles.iter().filter(|bundle| bundle.is_valid_at(header.number, header.timestamp))
+    {
+        match builder.simulate(&[], bundle)? {
+            Some(simulation) if !simulation.payment.is_zero() => {
+                candidates.push((bundle, simulation.payment))
+            }
+            _ => trace!(target: "executor::bundle", hash = ?bundle.hash(), "Dropping bundle"),
+        }
+    }
+    // a bundle at the top of the block can't be placed after another bundle
+    candidates.sort_by_key(|(bundle, payment)| (!bundle.top_of_block, Reverse(*payment)));
+
+    let mut best = builder.fill(Vec::new(), 0)?;
+    let mut prefix = Vec::new();
+    let mut included = Vec::new();
+    for (bundle, _) in candidates {
+        if bundle.top_of_block && !prefix.is_empty() {
+            continue
+        }
+        let Some(simulation) = builder.simulate(&prefix, bundle)? else { continue };
+        let candidate =
+            builder.fill(simulation.transactions.clone(), simulation.result.gas_used())?;
+        trace!(target: "executor::bundle", hash = ?bundle.hash(), payment = ?candidate.payment, best = ?best.payment, "Simulated bundle");
+        if candidate.payment > best.payment {
+            prefix = simulation.transactions;
+            best = candidate;
+            included.push(bundle.hash());
+        }
+    }
+
+    let Simulation { transactions, result, payment } = best;
+    let block = seal_block(header, transactions, &result);
+    Ok(BundleBlock { block, result, bundles: included, payment })
+}
+
+/// The transactions of a block and the result of their execution.
+struct Simulation {
+    transactions: Vec<TransactionSignedEcRecovered>,
+    result: ExecutionResult,
+    payment: U256,
+}
+
+/// Executes the candidates of [build_block_with_bundles].
+struct Builder<'a, F> {
+    header: &'a Header,
+    config: &'a Config,
+    state: F,
+    /// The transactions that are placed after the bundles.
+    transactions: &'a [TransactionSignedEcRecovered],
+}
+
+impl<'a, DB: StateProvider, F: Fn() -> SubState<DB>> Builder<'a, F> {
+    /// Executes the bundle after the prefix.
+    ///
+    /// Returns `None` if the bundle does not fit into the block or one of its transactions fails
+    /// unexpectedly.
+    fn simulate(
+        &self,
+        prefix: &[TransactionSignedEcRecovered],
+        bundle: &Bundle,
+    ) -> Result<Option<Simulation>, Error> {
+        let transactions =
+            prefix.iter().chain(bundle.transactions.iter()).cloned().collect::<Vec<_>>();
+        let simulation = match self.execute(transactions) {
+            Ok(simulation) => simulation,
+            Err(Error::TransactionGasLimitMoreThenAvailableBlockGas { .. }) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        // a transaction that used no gas was not executed at all, e.g. because of its nonce
+        let skipped = invalid_transactions(&simulation.result).any(|idx| idx >= prefix.len());
+        let failed = simulation.result.changesets[prefix.len()..]
+            .iter()
+            .zip(bundle.transactions.iter())
+            .any(|(changeset, tx)| !changeset.receipt.success && !bundle.may_revert(tx.hash()));
+        Ok((!skipped && !failed).then_some(simulation))
+    }
+
+    /// Executes the prefix followed by the transactions that still fit into the block.
+    fn fill(
+        &self,
+        mut transactions: Vec<TransactionSignedEcRecovered>,
+        prefix_gas_used: u64,
+    ) -> Result<Simulation, Error> {
+        let prefix_len = transactions.len();
+        let replaced = transactions
+            .iter()
+            .map(|tx| (tx.signer(), tx.nonce()))
+            .collect::<HashSet<(Address, u64)>>();
+        let mut skipped_senders = HashSet::new();
+        // the gas limits are an upper bound of the gas used, so every added transaction fits
+        let mut available_gas = self.header.gas_limit.saturating_sub(prefix_gas_used);
+        for tx in self.transactions {
+            if skipped_senders.contains(&tx.signer()) ||
+                replaced.contains(&(tx.signer(), tx.nonce()))
+            {
+                continue
+            }
+            if tx.gas_limit() > available_gas {
+                skipped_senders.insert(tx.signer());
+                continue
+            }
+            available_gas -= tx.gas_limit();
+            transactions.push(tx.clone());
+        }
+
+        loop {
+            let simulation = self.execute(transactions)?;
+            let invalid = invalid_transactions(&simulation.result).collect::<HashSet<_>>();
+            if invalid.is_empty() {
+                return Ok(simulation)
+            }
+            // The bundles can invalidate transactions, e.g. by spending the balance of their
+            // sender. These transactions and the following ones of their senders are removed.
+            let mut skipped_senders = HashSet::new();
+            transactions = simulation
+                .transactions
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, tx)| {
+                    if invalid.contains(idx) {
+                        skipped_senders.insert(tx.signer());
+                    }
+                    *idx < prefix_len || !skipped_senders.contains(&tx.signer())
+                })
+                .map(|(_, tx)| tx)
+                .collect();
+        }
+    }
+
+    fn execute(
+        &self,
+        transactions: Vec<TransactionSignedEcRecovered>,
+    ) -> Result<Simulation, Error> {
+        let result = execute_transactions(self.header, &transactions, self.config, (self.state)())?;
+        let payment = payment(&result, self.header.beneficiary);
+        Ok(Simulation { transactions, result, payment })
+    }
+}
+
+/// Returns the indices of the transactions that used no gas, which were not executed.
+fn invalid_transactions(result: &ExecutionResult) -> impl Iterator<Item = usize> + '_ {
+    let mut cumulative_gas_used = 0;
+    result.changesets.iter().enumerate().filter_map(move |(idx, changeset)| {
+        let gas_used = changeset.receipt.cumulative_gas_used - cumulative_gas_used;
+        cumulative_gas_used = changeset.receipt.cumulative_gas_used;
+        (gas_used == 0).then_some(idx)
+    })
+}
+
+/// Returns the amount the executed transactions paid to the beneficiary.
+fn payment(result: &ExecutionResult, beneficiary: Address) -> U256 {
+    let (mut before, mut after) = (None, None);
+    for changeset in result.changesets.iter() {
+        let Some(change) = changeset.changeset.get(&beneficiary) else { continue };
+        let (old, new) = match &change.account {
+            AccountInfoChangeSet::Changed { old, new } => (old.balance, new.balance),
+            AccountInfoChangeSet::Created { new } => (U256::zero(), new.balance),
+            AccountInfoChangeSet::Destroyed { old } => (old.balance, U256::zero()),
+            AccountInfoChangeSet::NoChange => continue,
+        };
+        before.get_or_insert(old);
+        after = Some(new);
+    }
+    after.unwrap_or_default().saturating_sub(before.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{revm_wrap::State, SpecUpgrades};
+    use reth_primitives::{
+        hex_literal::hex, Account, Bytes, Signature, StorageKey, StorageValue, Transaction,
+        TransactionKind, TransactionSigned, TxLegacy, H160,
+    };
+    use reth_provider::AccountProvider;
+    use std::collections::HashMap;
+
+    const BENEFICIARY: Address = H160([0xbe; 20]);
+    /// A contract that always reverts.
+    const REVERTING: Address = H160([0xee; 20]);
+    const REVERTING_CODE: [u8; 5] = hex!("60006000fd");
+
+    #[derive(Debug, Default, Clone)]
+    struct TestState(HashMap<Address, Account>);
+
+    impl AccountProvider for TestState {
+        fn basic_account(&self, address: Address) -> reth_interfaces::Result<Option<Account>> {
+            Ok(self.0.get(&address).copied())
+        }
+    }
+
+    impl StateProvider for TestState {
+        fn storage(
+            &self,
+            _account: Address,
+            _storage_key: StorageKey,
+        ) -> reth_interfaces::Result<Option<StorageValue>> {
+            Ok(None)
+        }
+
+        fn bytecode_by_hash(&self, code_hash: H256) -> reth_interfaces::Result<Option<Bytes>> {
+            Ok((code_hash == keccak256(REVERTING_CODE)).then(|| REVERTING_CODE.into()))
+        }
+
+        fn block_hash(&self, _number: U256) -> reth_interfaces::Result<Option<H256>> {
+            Ok(None)
+        }
+    }
+
+    fn sender(n: u8) -> Address {
+        H160([n; 20])
+    }
+
+    /// A transfer of 1 wei to the zero address.
+    fn transfer(from: u8, nonce: u64, gas_price: u128) -> TransactionSignedEcRecovered {
+        call(from, nonce, gas_price, Address::zero(), 21_000)
+    }
+
+    fn call(
+        from: u8,
+        nonce: u64,
+        gas_price: u128,
+        to: Address,
+        gas_limit: u64,
+    ) -> TransactionSignedEcRecovered {
+        let tx = Transaction::Legacy(TxLegacy {
+            chain_id: Some(1),
+            nonce,
+            gas_price,
+            gas_limit,
+            to: TransactionKind::Call(to),
+            value: 1,
+            input: Bytes::default(),
+        });
+        TransactionSignedEcRecovered::from_signed_transaction(
+            TransactionSigned::from_transaction_and_signature(tx, Signature::default()),
+            sender(from),
+        )
+    }
+
+    fn bundle(transactions: Vec<TransactionSignedEcRecovered>) -> Bundle {
+        Bundle { transactions, ..Default::default() }
+    }
+
+    fn build(
+        bundles: &[Bundle],
+        transactions: Vec<TransactionSignedEcRecovered>,
+        gas_limit: u64,
+    ) -> BundleBlock {
+        let mut state = TestState(
+            (1..=4)
+                .map(|n| (sender(n), Account { balance: U256::exp10(18), ..Default::default() }))
+                .collect(),
+        );
+        state.0.insert(
+            REVERTING,
+            Account { bytecode_hash: Some(keccak256(REVERTING_CODE)), ..Default::default() },
+        );
+        let parent = Header { number: 9, timestamp: 100, ..Default::default() }.seal();
+        let attributes = BlockAttributes {
+            timestamp: 112,
+            beneficiary: BENEFICIARY,
+            gas_limit,
+            ..Default::default()
+        };
+        let config = Config {
+            chain_id: 1.into(),
+            spec_upgrades: SpecUpgrades::new_berlin_activated(),
+            hooks: Default::default(),
+        };
+        build_block_with_bundles(&parent, attributes, bundles, transactions, &config, || {
+            SubState::new(State::new(state.clone()))
+        })
+        .unwrap()
+    }
+
+    fn hashes(block: &SealedBlock) -> Vec<TxHash> {
+        block.body.iter().map(|tx| tx.hash()).collect()
+    }
+
+    #[test]
+    fn include_profitable_bundles_first() {
+        let pool = vec![transfer(1, 0, 1), transfer(2, 0, 1)];
+        let top = Bundle { top_of_block: true, ..bundle(vec![transfer(3, 0, 2)]) };
+        // outbid by the other bundle at the top of the block
+        let other_top = Bundle { top_of_block: true, ..bundle(vec![transfer(4, 0, 1)]) };
+        // replaces the transaction of sender 2
+        let replacement = bundle(vec![transfer(2, 0, 5)]);
+
+        let built = build(&[other_top, replacement.clone(), top.clone()], pool.clone(), 1_000_000);
+        assert_eq!(built.bundles, vec![top.hash(), replacement.hash()]);
+        assert_eq!(
+            hashes(&built.block),
+            vec![top.transactions[0].hash(), replacement.transactions[0].hash(), pool[0].hash()]
+        );
+        assert_eq!(built.payment, U256::from(21_000 * (2 + 5 + 1)));
+        assert_eq!(built.block.gas_used, 3 * 21_000);
+    }
+
+    #[test]
+    fn drop_unprofitable_bundles() {
+        let pool = vec![transfer(1, 0, 10), transfer(2, 0, 10)];
+        // pays nothing
+        let free = bundle(vec![transfer(3, 0, 0)]);
+        // pays less than the transaction it displaces from the full block
+        let cheap = bundle(vec![transfer(4, 0, 1)]);
+        // the second transaction reverts
+        let failing = bundle(vec![transfer(3, 0, 20), call(3, 1, 20, REVERTING, 30_000)]);
+        // the transaction can't be executed because of the nonce gap, even though it may revert
+        let gap = transfer(4, 1, 20);
+        let skipped = Bundle { reverting_tx_hashes: vec![gap.hash()], ..bundle(vec![gap]) };
+        // only valid in the next block
+        let future = Bundle { min_block_number: Some(11), ..bundle(vec![transfer(4, 0, 20)]) };
+
+        let built = build(&[free, cheap, failing.clone(), skipped, future], pool.clone(), 42_000);
+        assert!(built.bundles.is_empty());
+        assert_eq!(hashes(&built.block), vec![pool[0].hash(), pool[1].hash()]);
+        assert_eq!(built.payment, U256::from(42_000 * 10));
+
+        let reverting =
+            Bundle { reverting_tx_hashes: vec![failing.transactions[1].hash()], ..failing.clone() };
+        let built = build(&[reverting.clone()], pool, 1_000_000);
+        assert_eq!(built.bundles, vec![reverting.hash()]);
+        assert_eq!(built.block.body.len(), 4);
+        assert!(!built.result.changesets[1].receipt.success);
+    }
+
+    #[test]
+    fn bundle_pool() {
+        let mut pool = BundlePool::new(2);
+        assert_eq!(pool.insert(bundle(Vec::new())), Err(BundleError::Empty));
+        assert_eq!(
+            pool.insert(Bundle {
+                min_timestamp: Some(20),
+                max_timestamp: Some(10),
+                ..bundle(vec![transfer(1, 0, 1)])
+            }),
+            Err(BundleError::EmptyWindow)
+        );
+        let unknown = transfer(2, 0, 1).hash();
+        assert_eq!(
+            pool.insert(Bundle {
+                reverting_tx_hashes: vec![unknown],
+                ..bundle(vec![transfer(1, 0, 1)])
+            }),
+            Err(BundleError::UnknownRevertingTransaction(unknown))
+        );
+
+        let expiring = Bundle { max_block_number: Some(10), ..bundle(vec![transfer(1, 0, 1)]) };
+        let mined = bundle(vec![transfer(2, 0, 1), transfer(3, 0, 1)]);
+        pool.insert(expiring.clone()).unwrap();
+        assert_eq!(pool.insert(expiring.clone()), Err(BundleError::AlreadyKnown(expiring.hash())));
+        pool.insert(mined.clone()).unwrap();
+        assert_eq!(
+            pool.insert(bundle(vec![transfer(4, 0, 1)])),
+            Err(BundleError::PoolFull { max: 2 })
+        );
+
+        let block = |number, body: Vec<TransactionSigned>| SealedBlock {
+            header: Header { number, timestamp: number * 12, ..Default::default() }.seal(),
+            body,
+            ommers: Vec::new(),
+        };
+        pool.on_new_block(&block(9, Vec::new()));
+        assert_eq!(pool.len(), 2);
+        pool.on_new_block(&block(10, vec![mined.transactions[1].clone().into_signed()]));
+        assert!(pool.is_empty());
+    }
+}
@@ -0,0 +1,521 @@
+//! Caches of the state that is read during execution.
+//!
+//! Execution reads accounts, storage slots and bytecodes in the order the transactions touch
+//! them, which results in random reads of the database. The [StateCache] keeps the recently read
+//! state in memory and can be warmed with the state that the transactions of the next blocks are
+//! known to touch, see [PrefetchTargets].
+//!
+//! The [BytecodeCache] keeps analysed bytecodes, which never change for a code hash, across
+//! blocks.
+
+use crate::threaded_revm_wrap::StateReaderPool;
+use lru::LruCache;
+use reth_interfaces::Result;
+use reth_primitives::{
+    Account, Address, Bytes, Header, StorageKey, StorageValue, TransactionKind,
+    TransactionSignedEcRecovered, H256, U256,
+};
+use reth_provider::{AccountProvider, StateProvider};
+use revm::{
+    BerlinSpec, Bytecode, ByzantiumSpec, FrontierSpec, HomesteadSpec, IstanbulSpec, LatestSpec,
+    LondonSpec, MergeSpec, PetersburgSpec, SpecId, SpuriousDragonSpec, TangerineSpec,
+};
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Debug},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread::{Scope, ScopedJoinHandle},
+};
+
+/// The default number of entries of each cache of the [StateCache].
+pub const DEFAULT_STATE_CACHE_SIZE: usize = 100_000;
+
+/// The default number of analysed bytecodes of the [BytecodeCache].
+pub const DEFAULT_BYTECODE_CACHE_SIZE: usize = 10_000;
+
+/// LRU caches of accounts, storage slots and bytecodes.
+///
+/// Entries that do not exist in the database are cached as well. The cache does not observe writes
+/// to the database, it must only be used while the state it was filled from does not change.
+pub struct StateCache {
+    inner: Mutex<Caches>,
+}
+
+struct Caches {
+    accounts: LruCache<Address, Option<Account>>,
+    storage: LruCache<(Address, StorageKey), Option<StorageValue>>,
+    bytecodes: LruCache<H256, Option<Bytes>>,
+}
+
+// === impl StateCache ===
+
+impl StateCache {
+    /// Creates a new cache that holds at most `size` entries of each kind.
+    pub fn new(size: usize) -> Self {
+        Self {
+            inner: Mutex::new(Caches {
+                accounts: LruCache::new(size),
+                storage: LruCache::new(size),
+                bytecodes: LruCache::new(size),
+            }),
+        }
+    }
+
+    /// Loads the targets that are not cached yet from the provider.
+    ///
+    /// The targets are read in key order, the bytecodes of the loaded accounts are read last.
+    pub fn prefetch<SP: StateProvider>(
+        &self,
+        provider: &SP,
+        targets: &PrefetchTargets,
+    ) -> Result<()> {
+        let mut code_hashes = BTreeSet::new();
+        for address in &targets.accounts {
+            if self.inner().accounts.contains(address) {
+                continue
+            }
+            let account = provider.basic_account(*address)?;
+            if let Some(code_hash) = account.and_then(|account| account.bytecode_hash) {
+                code_hashes.insert(code_hash);
+            }
+            self.inner().accounts.put(*address, account);
+        }
+
+        for (address, key) in &targets.storage {
+            if self.inner().storage.contains(&(*address, *key)) {
+                continue
+            }
+            let value = provider.storage(*address, *key)?;
+            self.inner().storage.put((*address, *key), value);
+        }
+
+        for code_hash in code_hashes {
+            if self.inner().bytecodes.contains(&code_hash) {
+                continue
+            }
+            let bytecode = provider.bytecode_by_hash(code_hash)?;
+            self.inner().bytecodes.put(code_hash, bytecode);
+        }
+        Ok(())
+    }
+
+    /// Spawns a thread in the scope that loads the targets like [StateCache::prefetch], with the
+    /// targets split among the workers of the pool, which read them concurrently.
+    ///
+    /// The caller goes on while the state is read, e.g. executes the blocks whose state was
+    /// prefetched before, and joins the returned handle once it needs the targets.
+    pub fn spawn_prefetch<'scope, 'env>(
+        &'env self,
+        scope: &'scope Scope<'scope, 'env>,
+        pool: &StateReaderPool<'_>,
+        targets: PrefetchTargets,
+    ) -> ScopedJoinHandle<'scope, Result<()>> {
+        let providers = (0..pool.workers()).map(|_| pool.provider()).collect::<Vec<_>>();
+        scope.spawn(move || {
+            let parts = targets.split(providers.len());
+            std::thread::scope(|scope| {
+                let prefetches = parts
+                    .iter()
+                    .zip(&providers)
+                    .map(|(part, provider)| scope.spawn(move || self.prefetch(provider, part)))
+                    .collect::<Vec<_>>();
+                prefetches.into_iter().try_for_each(|prefetch| {
+                    prefetch.join().expect("Expects prefetch to not panic")
+                })
+            })
+        })
+    }
+
+    fn inner(&self) -> std::sync::MutexGuard<'_, Caches> {
+        // the caches are only ever updated by single `put` calls, so they are never left in an
+        // inconsistent state
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Default for StateCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATE_CACHE_SIZE)
+    }
+}
+
+impl Debug for StateCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner();
+        f.debug_struct("StateCache")
+            .field("accounts", &inner.accounts.len())
+            .field("storage", &inner.storage.len())
+            .field("bytecodes", &inner.bytecodes.len())
+            .finish()
+    }
+}
+
+/// An LRU cache of analysed bytecodes by code hash.
+///
+/// The jump table of a bytecode depends on the opcode gas costs of the spec, so bytecodes are
+/// cached for every spec they are executed with.
+pub struct BytecodeCache {
+    bytecodes: Mutex<LruCache<(H256, SpecId), Bytecode>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+// === impl BytecodeCache ===
+
+impl BytecodeCache {
+    /// Creates a new cache that holds at most `size` bytecodes.
+    pub fn new(size: usize) -> Self {
+        Self {
+            bytecodes: Mutex::new(LruCache::new(size)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the analysed bytecode of the code hash, analysing the bytecode that `load` returns
+    /// if it is not cached yet.
+    ///
+    /// Bytecodes of specs that can not be analysed ahead of execution are returned as they are.
+    pub fn get_or_analyse(
+        &self,
+        code_hash: H256,
+        spec_id: SpecId,
+        load: impl FnOnce() -> Result<Bytecode>,
+    ) -> Result<Bytecode> {
+        if let Some(bytecode) = self.lock().get(&(code_hash, spec_id)) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(bytecode.clone())
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        match analyse(load()?, spec_id) {
+            Ok(bytecode) => {
+                self.lock().put((code_hash, spec_id), bytecode.clone());
+                Ok(bytecode)
+            }
+            Err(bytecode) => Ok(bytecode),
+        }
+    }
+
+    /// Returns the number of bytecodes that were served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytecodes that had to be loaded.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of cached bytecodes.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if no bytecodes are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<(H256, SpecId), Bytecode>> {
+        self.bytecodes.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Default for BytecodeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_BYTECODE_CACHE_SIZE)
+    }
+}
+
+impl Debug for BytecodeCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BytecodeCache")
+            .field("len", &self.len())
+            .field("hits", &self.hits())
+            .field("misses", &self.misses())
+            .finish()
+    }
+}
+
+/// Analyses the bytecode for the spec like the EVM does before it executes it.
+///
+/// Returns the bytecode as it is if the spec is not known.
+fn analyse(bytecode: Bytecode, spec_id: SpecId) -> std::result::Result<Bytecode, Bytecode> {
+    let bytecode = match spec_id {
+        SpecId::FRONTIER => bytecode.to_analysed::<FrontierSpec>(),
+        SpecId::HOMESTEAD => bytecode.to_analysed::<HomesteadSpec>(),
+        SpecId::TANGERINE => bytecode.to_analysed::<TangerineSpec>(),
+        SpecId::SPURIOUS_DRAGON => bytecode.to_analysed::<SpuriousDragonSpec>(),
+        SpecId::BYZANTIUM => bytecode.to_analysed::<ByzantiumSpec>(),
+        SpecId::PETERSBURG => bytecode.to_analysed::<PetersburgSpec>(),
+        SpecId::ISTANBUL => bytecode.to_analysed::<IstanbulSpec>(),
+        SpecId::BERLIN => bytecode.to_analysed::<BerlinSpec>(),
+        SpecId::LONDON => bytecode.to_analysed::<LondonSpec>(),
+        SpecId::MERGE => bytecode.to_analysed::<MergeSpec>(),
+        SpecId::LATEST => bytecode.to_analysed::<LatestSpec>(),
+        _ => return Err(bytecode),
+    };
+    Ok(bytecode)
+}
+
+/// A [StateProvider] that serves reads from a [StateCache] and caches the reads that miss it.
+#[derive(Debug)]
+pub struct CachedStateProvider<'a, SP> {
+    provider: SP,
+    cache: &'a StateCache,
+}
+
+// === impl CachedStateProvider ===
+
+impl<'a, SP: StateProvider> CachedStateProvider<'a, SP> {
+    /// Creates a new provider that caches the reads of `provider`.
+    pub fn new(provider: SP, cache: &'a StateCache) -> Self {
+        Self { provider, cache }
+    }
+}
+
+impl<'a, SP: StateProvider> AccountProvider for CachedStateProvider<'a, SP> {
+    fn basic_account(&self, address: Address) -> Result<Option<Account>> {
+        if let Some(account) = self.cache.inner().accounts.get(&address) {
+            return Ok(*account)
+        }
+        let account = self.provider.basic_account(address)?;
+        self.cache.inner().accounts.put(address, account);
+        Ok(account)
+    }
+}
+
+impl<'a, SP: StateProvider> StateProvider for CachedStateProvider<'a, SP> {
+    fn storage(&self, account: Address, storage_key: StorageKey) -> Result<Option<StorageValue>> {
+        if let Some(value) = self.cache.inner().storage.get(&(account, storage_key)) {
+            return Ok(*value)
+        }
+        let value = self.provider.storage(account, storage_key)?;
+        self.cache.inner().storage.put((account, storage_key), value);
+        Ok(value)
+    }
+
+    fn bytecode_by_hash(&self, code_hash: H256) -> Result<Option<Bytes>> {
+        if let Some(bytecode) = self.cache.inner().bytecodes.get(&code_hash) {
+            return Ok(bytecode.clone())
+        }
+        let bytecode = self.provider.bytecode_by_hash(code_hash)?;
+        self.cache.inner().bytecodes.put(code_hash, bytecode.clone());
+        Ok(bytecode)
+    }
+
+    fn block_hash(&self, number: U256) -> Result<Option<H256>> {
+        self.provider.block_hash(number)
+    }
+}
+
+/// The accounts and storage slots that transactions are known to touch before they are executed.
+///
+/// These are the beneficiary of the block, the senders and recipients of the transactions and the
+/// entries of their access lists.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PrefetchTargets {
+    /// The touched accounts.
+    pub accounts: BTreeSet<Address>,
+    /// The touched storage slots.
+    pub storage: BTreeSet<(Address, StorageKey)>,
+}
+
+// === impl PrefetchTargets ===
+
+impl PrefetchTargets {
+    /// Adds the state touched by the block.
+    pub fn extend_block(&mut self, header: &Header, transactions: &[TransactionSignedEcRecovered]) {
+        self.accounts.insert(header.beneficiary);
+        for transaction in transactions {
+            self.accounts.insert(transaction.signer());
+            if let TransactionKind::Call(to) = transaction.kind() {
+                self.accounts.insert(*to);
+            }
+            if let Some(access_list) = transaction.access_list() {
+                for item in &access_list.0 {
+                    self.accounts.insert(item.address);
+                    self.storage.extend(item.storage_keys.iter().map(|key| (item.address, *key)));
+                }
+            }
+        }
+    }
+
+    /// Returns true if there is nothing to prefetch.
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storage.is_empty()
+    }
+
+    /// Splits the targets into at most `parts` parts of consecutive keys.
+    pub fn split(&self, parts: usize) -> Vec<PrefetchTargets> {
+        let parts = parts.max(1);
+        let mut split = vec![PrefetchTargets::default(); parts];
+        let accounts = ((self.accounts.len() + parts - 1) / parts).max(1);
+        for (index, address) in self.accounts.iter().enumerate() {
+            split[index / accounts].accounts.insert(*address);
+        }
+        let slots = ((self.storage.len() + parts - 1) / parts).max(1);
+        for (index, slot) in self.storage.iter().enumerate() {
+            split[index / slots].storage.insert(*slot);
+        }
+        split.retain(|part| !part.is_empty());
+        split
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{
+        AccessList, AccessListItem, Signature, Transaction, TransactionSigned, TxEip2930,
+    };
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// A provider that counts the reads of the database.
+    #[derive(Default)]
+    struct CountingProvider {
+        accounts: HashMap<Address, Account>,
+        reads: AtomicUsize,
+    }
+
+    impl AccountProvider for CountingProvider {
+        fn basic_account(&self, address: Address) -> Result<Option<Account>> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            Ok(self.accounts.get(&address).copied())
+        }
+    }
+
+    impl StateProvider for CountingProvider {
+        fn storage(&self, _account: Address, _key: StorageKey) -> Result<Option<StorageValue>> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(U256::from(1)))
+        }
+
+        fn bytecode_by_hash(&self, _code_hash: H256) -> Result<Option<Bytes>> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(Bytes::from(vec![0x00])))
+        }
+
+        fn block_hash(&self, _number: U256) -> Result<Option<H256>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn caches_reads() {
+        let address = Address::from_low_u64_be(1);
+        let provider = CountingProvider {
+            accounts: HashMap::from([(address, Account { nonce: 1, ..Default::default() })]),
+            ..Default::default()
+        };
+        let cache = StateCache::new(2);
+        let cached = CachedStateProvider::new(&provider, &cache);
+
+        assert_eq!(cached.basic_account(address).unwrap().map(|account| account.nonce), Some(1));
+        assert_eq!(cached.basic_account(address).unwrap().map(|account| account.nonce), Some(1));
+        // missing accounts are cached as well
+        assert_eq!(cached.basic_account(Address::zero()).unwrap(), None);
+        assert_eq!(cached.basic_account(Address::zero()).unwrap(), None);
+        assert_eq!(cached.storage(address, H256::zero()).unwrap(), Some(U256::from(1)));
+        assert_eq!(cached.storage(address, H256::zero()).unwrap(), Some(U256::from(1)));
+        assert_eq!(provider.reads.load(Ordering::SeqCst), 3);
+
+        // the least recently used account is evicted
+        cached.basic_account(Address::from_low_u64_be(2)).unwrap();
+        cached.basic_account(address).unwrap();
+        assert_eq!(provider.reads.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn caches_analysed_bytecodes() {
+        let cache = BytecodeCache::new(1);
+        let code_hash = H256::from_low_u64_be(1);
+        let load = || Ok(Bytecode::new_raw(vec![0x60, 0x00, 0x56].into()));
+
+        let bytecode = cache.get_or_analyse(code_hash, SpecId::LONDON, load).unwrap();
+        assert_eq!(bytecode.bytes().as_ref()[..3], [0x60, 0x00, 0x56]);
+        let cached = cache
+            .get_or_analyse(code_hash, SpecId::LONDON, || panic!("bytecode is cached"))
+            .unwrap();
+        assert_eq!(cached.hash(), bytecode.hash());
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // the bytecode is analysed again for another spec
+        cache.get_or_analyse(code_hash, SpecId::BERLIN, load).unwrap();
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (1, 2, 1));
+    }
+
+    #[test]
+    fn prefetch_targets() {
+        let signer = Address::from_low_u64_be(1);
+        let to = Address::from_low_u64_be(2);
+        let listed = Address::from_low_u64_be(3);
+        let code_hash = H256::from_low_u64_be(4);
+        let transaction = TransactionSignedEcRecovered::from_signed_transaction(
+            TransactionSigned::from_transaction_and_signature(
+                Transaction::Eip2930(TxEip2930 {
+                    to: TransactionKind::Call(to),
+                    access_list: AccessList(vec![AccessListItem {
+                        address: listed,
+                        storage_keys: vec![H256::zero()],
+                    }]),
+                    ..Default::default()
+                }),
+                Signature::default(),
+            ),
+            signer,
+        );
+        let header = Header::default();
+
+        let mut targets = PrefetchTargets::default();
+        targets.extend_block(&header, &[transaction]);
+        assert_eq!(targets.accounts, BTreeSet::from([header.beneficiary, signer, to, listed]));
+        assert_eq!(targets.storage, BTreeSet::from([(listed, H256::zero())]));
+
+        let provider = CountingProvider {
+            accounts: HashMap::from([(
+                to,
+                Account { bytecode_hash: Some(code_hash), ..Default::default() },
+            )]),
+            ..Default::default()
+        };
+        let cache = StateCache::default();
+        cache.prefetch(&provider, &targets).unwrap();
+        // four accounts, one slot and the bytecode of the recipient
+        assert_eq!(provider.reads.load(Ordering::SeqCst), 6);
+
+        // all reads of the targets are served by the cache
+        let cached = CachedStateProvider::new(&provider, &cache);
+        for address in &targets.accounts {
+            cached.basic_account(*address).unwrap();
+        }
+        cached.storage(listed, H256::zero()).unwrap();
+        cached.bytecode_by_hash(code_hash).unwrap();
+        cache.prefetch(&provider, &targets).unwrap();
+        assert_eq!(provider.reads.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn splits_targets() {
+        let mut targets = PrefetchTargets::default();
+        targets.accounts.extend((0..5).map(Address::from_low_u64_be));
+        targets.storage.insert((Address::zero(), H256::zero()));
+
+        let parts = targets.split(2);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].accounts, (0..3).map(Address::from_low_u64_be).collect());
+        assert_eq!(parts[1].accounts, (3..5).map(Address::from_low_u64_be).collect());
+        assert_eq!(parts[0].storage, targets.storage);
+        assert!(parts[1].storage.is_empty());
+
+        assert_eq!(targets.split(0), vec![targets.clone()]);
+        assert!(PrefetchTargets::default().split(4).is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+//! Call tracer that records the call tree of a transaction.
+//!
+//! Every call, contract creation and selfdestruct becomes a [CallTrace] that is addressed by its
+//! position in the call tree, which is the shape of the traces of the parity `trace_` namespace.
+
+use bytes::Bytes as RevmBytes;
+use reth_primitives::{Address, Bytes, H160, U256};
+use revm::{
+    opcode, CallInputs, CallScheme, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
+    Return, B160,
+};
+
+/// The kind of a [CallTrace].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    /// A `CALL` or the call of a transaction.
+    Call,
+    /// A `CALLCODE`.
+    CallCode,
+    /// A `DELEGATECALL`.
+    DelegateCall,
+    /// A `STATICCALL`.
+    StaticCall,
+    /// A `CREATE`, `CREATE2` or the contract creation of a transaction.
+    Create,
+    /// A `SELFDESTRUCT`.
+    SelfDestruct,
+}
+
+/// A call, contract creation or selfdestruct of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallTrace {
+    /// The position in the call tree, the index of the trace in the subtraces of each parent.
+    pub trace_address: Vec<usize>,
+    /// The number of direct subtraces.
+    pub subtraces: usize,
+    /// The kind of the trace.
+    pub kind: CallKind,
+    /// The caller, or the destroyed contract of a selfdestruct.
+    pub from: Address,
+    /// The called or created contract, or the beneficiary of a selfdestruct.
+    ///
+    /// Zero if the creation of a contract failed.
+    pub to: Address,
+    /// The transferred value, or the apparent value of a `DELEGATECALL`.
+    pub value: U256,
+    /// The gas limit of the call.
+    pub gas_limit: u64,
+    /// The gas used by the call.
+    pub gas_used: u64,
+    /// The input of a call or the init code of a creation.
+    pub input: Bytes,
+    /// The output of a call or the code of a created contract.
+    pub output: Bytes,
+    /// The error of a failed call, `None` if it succeeded.
+    pub error: Option<String>,
+}
+
+/// An [Inspector] that records a [CallTrace] for every call, contract creation and selfdestruct.
+///
+/// The traces are recorded in the order they are entered, so every trace is followed by its
+/// subtraces.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    /// All recorded traces.
+    traces: Vec<CallTrace>,
+    /// The indices of the traces that are not completed yet, the innermost one last.
+    open: Vec<usize>,
+}
+
+// === impl CallTracer ===
+
+impl CallTracer {
+    /// Returns the recorded traces.
+    pub fn traces(&self) -> &[CallTrace] {
+        &self.traces
+    }
+
+    /// Consumes the tracer and returns the recorded traces.
+    pub fn into_traces(self) -> Vec<CallTrace> {
+        self.traces
+    }
+
+    /// Records a new trace as the next subtrace of the innermost open trace and returns its index.
+    fn push_trace(
+        &mut self,
+        kind: CallKind,
+        from: B160,
+        to: B160,
+        value: revm::U256,
+        gas_limit: u64,
+        input: Bytes,
+    ) -> usize {
+        let trace_address = match self.open.last() {
+            Some(&parent) => {
+                let parent = &mut self.traces[parent];
+                let mut trace_address = parent.trace_address.clone();
+                trace_address.push(parent.subtraces);
+                parent.subtraces += 1;
+                trace_address
+            }
+            None => Vec::new(),
+        };
+        self.traces.push(CallTrace {
+            trace_address,
+            subtraces: 0,
+            kind,
+            from: H160(from.0),
+            to: H160(to.0),
+            value: U256(*value.as_limbs()),
+            gas_limit,
+            gas_used: 0,
+            input,
+            output: Bytes::default(),
+            error: None,
+        });
+        self.traces.len() - 1
+    }
+
+    /// Completes the innermost open trace.
+    fn close_trace(&mut self, ret: Return, remaining_gas: Gas, output: &RevmBytes) {
+        let Some(idx) = self.open.pop() else { return };
+        let trace = &mut self.traces[idx];
+        trace.gas_used = trace.gas_limit.saturating_sub(remaining_gas.remaining());
+        trace.output = output.clone().into();
+        trace.error = trace_error(ret);
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        // a selfdestruct has no frame of its own, it's recorded as a completed subtrace
+        if interp.current_opcode() == opcode::SELFDESTRUCT && !is_static {
+            if let Ok(target) = interp.stack.peek(0) {
+                let address = interp.contract.address;
+                let beneficiary = B160::from_slice(&target.to_be_bytes::<32>()[12..]);
+                let balance = data
+                    .journaled_state
+                    .state
+                    .get(&address)
+                    .map(|account| account.info.balance)
+                    .unwrap_or_default();
+                self.push_trace(
+                    CallKind::SelfDestruct,
+                    address,
+                    beneficiary,
+                    balance,
+                    0,
+                    Bytes::default(),
+                );
+            }
+        }
+        Return::Continue
+    }
+
+    fn call(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+        _is_static: bool,
+    ) -> (Return, Gas, RevmBytes) {
+        let (kind, from, value) = match inputs.context.scheme {
+            CallScheme::Call => (CallKind::Call, inputs.context.caller, inputs.transfer.value),
+            CallScheme::CallCode => {
+                (CallKind::CallCode, inputs.context.caller, inputs.transfer.value)
+            }
+            CallScheme::DelegateCall => {
+                (CallKind::DelegateCall, inputs.context.address, inputs.context.apparent_value)
+            }
+            CallScheme::StaticCall => {
+                (CallKind::StaticCall, inputs.context.caller, inputs.transfer.value)
+            }
+        };
+        let idx = self.push_trace(
+            kind,
+            from,
+            inputs.contract,
+            value,
+            inputs.gas_limit,
+            inputs.input.clone().into(),
+        );
+        self.open.push(idx);
+        (Return::Continue, Gas::new(0), RevmBytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: RevmBytes,
+        _is_static: bool,
+    ) -> (Return, Gas, RevmBytes) {
+        self.close_trace(ret, remaining_gas, &out);
+        (ret, remaining_gas, out)
+    }
+
+    fn create(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CreateInputs,
+    ) -> (Return, Option<B160>, Gas, RevmBytes) {
+        let idx = self.push_trace(
+            CallKind::Create,
+            inputs.caller,
+            B160::zero(),
+            inputs.value,
+            inputs.gas_limit,
+            inputs.init_code.clone().into(),
+        );
+        self.open.push(idx);
+        (Return::Continue, None, Gas::new(0), RevmBytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CreateInputs,
+        ret: Return,
+        address: Option<B160>,
+        remaining_gas: Gas,
+        out: RevmBytes,
+    ) -> (Return, Option<B160>, Gas, RevmBytes) {
+        if let (Some(&idx), Some(address)) = (self.open.last(), address) {
+            self.traces[idx].to = H160(address.0);
+        }
+        self.close_trace(ret, remaining_gas, &out);
+        (ret, address, remaining_gas, out)
+    }
+}
+
+/// Returns the error of a call that completed with `ret`, in the wording of parity traces.
+fn trace_error(ret: Return) -> Option<String> {
+    let error = match ret {
+        revm::return_ok!() => return None,
+        revm::return_revert!() => "Reverted",
+        Return::OutOfGas => "Out of gas",
+        Return::OpcodeNotFound => "Bad instruction",
+        Return::InvalidJump => "Bad jump destination",
+        Return::StackUnderflow => "Stack underflow",
+        Return::StackOverflow => "Out of stack",
+        Return::CallNotAllowedInsideStatic => "Mutable Call In Static Context",
+        ret => return Some(format!("{ret:?}")),
+    };
+    Some(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::hex_literal::hex;
+    use revm::{
+        db::{CacheDB, EmptyDB},
+        AccountInfo, Bytecode, TransactTo, EVM,
+    };
+
+    const CALLER: B160 = B160([0xaa; 20]);
+    const OUTER: B160 = B160([0x01; 20]);
+    const INNER: B160 = B160([0x02; 20]);
+    const REVERTING: B160 = B160([0x03; 20]);
+    const BENEFICIARY: B160 = B160([0xbb; 20]);
+
+    fn contract(db: &mut CacheDB<EmptyDB>, address: B160, code: &[u8], balance: u64) {
+        let bytecode = Bytecode::new_raw(code.to_vec().into());
+        db.insert_account_info(
+            address,
+            AccountInfo {
+                balance: revm::U256::from(balance),
+                code_hash: bytecode.hash(),
+                code: Some(bytecode),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// `call(gas, address, 0, 0, 0, 0, 0)`, pops the success flag
+    fn call_code(address: B160) -> Vec<u8> {
+        let mut code = hex!("6000600060006000600073").to_vec();
+        code.extend_from_slice(&address.0);
+        code.extend_from_slice(&hex!("5af150"));
+        code
+    }
+
+    fn trace(to: B160) -> Vec<CallTrace> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        // calls the inner and the reverting contract
+        let outer = [call_code(INNER), call_code(REVERTING)].concat();
+        contract(&mut db, OUTER, &outer, 0);
+        // `selfdestruct(BENEFICIARY)`
+        let mut inner = vec![opcode::PUSH20];
+        inner.extend_from_slice(&BENEFICIARY.0);
+        inner.push(opcode::SELFDESTRUCT);
+        contract(&mut db, INNER, &inner, 7);
+        // `revert(0, 0)`
+        contract(&mut db, REVERTING, &hex!("60006000fd"), 0);
+
+        let mut evm = EVM::new();
+        evm.database(db);
+        evm.env.tx.caller = CALLER;
+        evm.env.tx.transact_to = TransactTo::Call(to);
+        evm.env.tx.gas_limit = 100_000;
+
+        let mut tracer = CallTracer::default();
+        evm.inspect(&mut tracer);
+        tracer.into_traces()
+    }
+
+    #[test]
+    fn records_call_tree() {
+        let traces = trace(OUTER);
+        let tree = traces
+            .iter()
+            .map(|trace| (trace.trace_address.clone(), trace.subtraces, trace.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tree,
+            vec![
+                (vec![], 2, CallKind::Call),
+                (vec![0], 1, CallKind::Call),
+                (vec![0, 0], 0, CallKind::SelfDestruct),
+                (vec![1], 0, CallKind::Call),
+            ]
+        );
+
+        let outer = &traces[0];
+        assert_eq!((outer.from, outer.to), (H160(CALLER.0), H160(OUTER.0)));
+        assert_eq!(outer.error, None);
+        assert!(outer.gas_used > traces[1].gas_used + traces[3].gas_used);
+
+        let selfdestruct = &traces[2];
+        assert_eq!((selfdestruct.from, selfdestruct.to), (H160(INNER.0), H160(BENEFICIARY.0)));
+        assert_eq!(selfdestruct.value, U256::from(7));
+
+        let reverted = &traces[3];
+        assert_eq!((reverted.from, reverted.to), (H160(OUTER.0), H160(REVERTING.0)));
+        assert_eq!(reverted.error.as_deref(), Some("Reverted"));
+    }
+
+    #[test]
+    fn records_failed_transaction() {
+        let traces = trace(REVERTING);
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].error.as_deref(), Some("Reverted"));
+        assert!(traces[0].output.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 //! Reth block execution/validation configuration and constants
 
+use crate::hooks::ChainHooks;
 use reth_primitives::{BlockNumber, U256};
 
 /// Two ethereum worth of wei
@@ -16,12 +17,37 @@ pub struct Config {
     pub chain_id: U256,
     /// Spec upgrades.
     pub spec_upgrades: SpecUpgrades,
+    /// Precompiles and opcode gas surcharges of the chain that are not part of Ethereum.
+    pub hooks: ChainHooks,
 }
 
 impl Config {
     /// Create new config for ethereum.
     pub fn new_ethereum() -> Self {
-        Self { chain_id: 1.into(), spec_upgrades: SpecUpgrades::new_ethereum() }
+        Self {
+            chain_id: 1.into(),
+            spec_upgrades: SpecUpgrades::new_ethereum(),
+            hooks: ChainHooks::default(),
+        }
+    }
+
+    /// Sets the precompiles and opcode gas surcharges of the chain.
+    pub fn with_hooks(mut self, hooks: ChainHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Returns the reward of the beneficiary of the block, `None` after the merge.
+    ///
+    /// NOTE: Related to Ethereum reward change, for other network this is probably going to be
+    /// moved to config.
+    pub fn block_reward(&self, block_num: BlockNumber) -> Option<u128> {
+        match block_num {
+            n if n >= self.spec_upgrades.paris => None,
+            n if n >= self.spec_upgrades.petersburg => Some(WEI_2ETH),
+            n if n >= self.spec_upgrades.byzantium => Some(WEI_3ETH),
+            _ => Some(WEI_5ETH),
+        }
     }
 }
 
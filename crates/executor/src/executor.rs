@@ -1,21 +1,28 @@
 use crate::{
-    config::{WEI_2ETH, WEI_3ETH, WEI_5ETH},
+    hooks::HookedInspector,
     revm_wrap::{self, to_reth_acc, SubState},
     Config,
 };
 use hashbrown::hash_map::Entry;
-use reth_db::{models::AccountBeforeTx, tables, transaction::DbTxMut, Error as DbError};
+use reth_db::{
+    cursor::DbDupCursorRO,
+    models::{AccountBeforeTx, TransitionIdAddress},
+    tables,
+    transaction::DbTxMut,
+    Error as DbError,
+};
 use reth_interfaces::executor::Error;
 use reth_primitives::{
-    bloom::logs_bloom, Account, Address, Bloom, Header, Log, Receipt, TransactionSignedEcRecovered,
-    H160, H256, U256,
+    bloom::logs_bloom, Account, Address, Bloom, Header, Log, Receipt, StorageEntry,
+    TransactionSignedEcRecovered, TransitionId, H160, H256, U256,
 };
 use reth_provider::StateProvider;
 use revm::{
     db::AccountState, Account as RevmAccount, AccountInfo, AnalysisKind, Bytecode, Database,
-    Return, B160, EVM, U256 as evmU256,
+    Inspector, Return, B160, EVM, U256 as evmU256,
 };
 use std::collections::BTreeMap;
+use tracing::trace;
 
 /// Main block executor
 pub struct Executor {
@@ -105,7 +112,7 @@ pub struct AccountChangeSet {
 
 /// Execution Result containing vector of transaction changesets
 /// and block reward if present
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExecutionResult {
     /// Transaction changeest contraining [Receipt], changed [Accounts][Account] and Storages.
     pub changesets: Vec<TransactionChangeSet>,
@@ -114,6 +121,100 @@ pub struct ExecutionResult {
     pub block_reward: Option<BTreeMap<Address, AccountInfoChangeSet>>,
 }
 
+impl ExecutionResult {
+    /// The gas used by all transactions.
+    pub fn gas_used(&self) -> u64 {
+        self.changesets.last().map(|changeset| changeset.receipt.cumulative_gas_used).unwrap_or(0)
+    }
+
+    /// Apply the state changes, changesets and new bytecodes of the block to a database
+    /// transaction, starting at the given transition.
+    ///
+    /// Returns the transition after the last one of the block.
+    pub fn apply_to_db<'a, TX: DbTxMut<'a>>(
+        self,
+        tx: &TX,
+        mut transition_id: TransitionId,
+    ) -> Result<TransitionId, DbError> {
+        // insert state change set
+        for result in self.changesets.into_iter() {
+            // TODO insert to transitionId to tx_index
+            for (address, account_change_set) in result.changeset.into_iter() {
+                let AccountChangeSet { account, wipe_storage, storage } = account_change_set;
+                // apply account change to db. Updates AccountChangeSet and PlainAccountState
+                // tables.
+                trace!(target: "executor", ?address, transition_id, ?account, wipe_storage, "Applying account changeset");
+                account.apply_to_db(tx, address, transition_id)?;
+
+                let storage_id = TransitionIdAddress((transition_id, address));
+
+                // wipe storage
+                if wipe_storage {
+                    // insert the wiped slots into StorageChangeSet, the changed slots are
+                    // inserted below
+                    let mut storage_cursor = tx.cursor_dup_mut::<tables::PlainStorageState>()?;
+                    for entry in storage_cursor.walk_dup(address, None)? {
+                        let (_, entry) = entry?;
+                        if !storage.contains_key(&U256::from_big_endian(entry.key.as_bytes())) {
+                            tx.put::<tables::StorageChangeSet>(storage_id.clone(), entry)?;
+                        }
+                    }
+                    tx.delete::<tables::PlainStorageState>(address, None)?;
+                }
+                // insert storage changeset
+                for (key, (old_value, new_value)) in storage {
+                    let mut hkey = H256::zero();
+                    key.to_big_endian(&mut hkey.0);
+
+                    trace!(target: "executor", ?address, transition_id, ?hkey, ?old_value, ?new_value, "Applying storage changeset");
+
+                    // insert into StorageChangeSet
+                    tx.put::<tables::StorageChangeSet>(
+                        storage_id.clone(),
+                        StorageEntry { key: hkey, value: old_value },
+                    )?;
+
+                    // Always delete old value as duplicate table put will not override it
+                    tx.delete::<tables::PlainStorageState>(
+                        address,
+                        Some(StorageEntry { key: hkey, value: old_value }),
+                    )?;
+                    if !new_value.is_zero() {
+                        tx.put::<tables::PlainStorageState>(
+                            address,
+                            StorageEntry { key: hkey, value: new_value },
+                        )?;
+                    }
+                }
+            }
+            // insert bytecode
+            for (hash, bytecode) in result.new_bytecodes.into_iter() {
+                // make different types of bytecode. Checked and maybe even analyzed (needs to
+                // be packed). Currently save only raw bytes.
+                let bytecode = bytecode.bytes();
+                trace!(target: "executor", ?hash, ?bytecode, len = bytecode.len(), "Inserting bytecode");
+                tx.put::<tables::Bytecodes>(hash, bytecode[..bytecode.len()].to_vec())?;
+
+                // NOTE: bytecode bytes are not inserted in change set and it stand in saparate
+                // table
+            }
+            transition_id += 1;
+        }
+
+        // If there is block reward we will add account changeset to db
+        if let Some(block_reward_changeset) = self.block_reward {
+            // we are sure that block reward index is present.
+            for (address, changeset) in block_reward_changeset.into_iter() {
+                trace!(target: "executor", ?address, transition_id, "Applying block reward");
+                changeset.apply_to_db(tx, address, transition_id)?;
+            }
+            transition_id += 1;
+        }
+
+        Ok(transition_id)
+    }
+}
+
 /// Commit change to database and return change diff that is used to update state and create
 /// history index
 ///
@@ -307,6 +408,80 @@ pub fn execute<DB: StateProvider>(
     config: &Config,
     db: SubState<DB>,
 ) -> Result<ExecutionResult, Error> {
+    let result = execute_transactions(header, transactions, config, db)?;
+
+    // Check if gas used matches the value set in header.
+    let gas_used = result.gas_used();
+    if header.gas_used != gas_used {
+        return Err(Error::BlockGasUsed { got: gas_used, expected: header.gas_used })
+    }
+
+    Ok(result)
+}
+
+/// Executes the transactions on top of the header without comparing the gas used to the one in
+/// the header, since it is unknown while the block is being built.
+pub fn execute_transactions<DB: StateProvider>(
+    header: &Header,
+    transactions: &[TransactionSignedEcRecovered],
+    config: &Config,
+    db: SubState<DB>,
+) -> Result<ExecutionResult, Error> {
+    let mut hooks = config.hooks.at_block(header.number);
+    if hooks.is_empty() {
+        execute_transactions_with(header, transactions, config, db, |evm, _| evm.transact())
+    } else {
+        execute_transactions_with(header, transactions, config, db, |evm, _| {
+            evm.inspect(&mut hooks)
+        })
+    }
+}
+
+/// Executes the transactions on top of the header like [execute_transactions], but inspects the
+/// execution of every transaction with the inspector that `inspector` returns for it.
+///
+/// Returns the inspectors in the order of the transactions. Like the execution of a block that
+/// is being built, the gas used is not compared to the one in the header, so a prefix of the
+/// transactions of a block can be replayed.
+pub fn execute_transactions_with_inspector<DB, I, F>(
+    header: &Header,
+    transactions: &[TransactionSignedEcRecovered],
+    config: &Config,
+    db: SubState<DB>,
+    mut inspector: F,
+) -> Result<(ExecutionResult, Vec<I>), Error>
+where
+    DB: StateProvider,
+    I: Inspector<SubState<DB>>,
+    F: FnMut(&TransactionSignedEcRecovered) -> I,
+{
+    let hooks = config.hooks.at_block(header.number);
+    let mut inspectors = Vec::with_capacity(transactions.len());
+    let result =
+        execute_transactions_with(header, transactions, config, db, |evm, transaction| {
+            let mut inspector = HookedInspector::new(hooks.clone(), inspector(transaction));
+            let out = evm.inspect(&mut inspector);
+            inspectors.push(inspector.into_inner());
+            out
+        })?;
+    Ok((result, inspectors))
+}
+
+/// Executes the transactions on top of the header, each with `transact`.
+fn execute_transactions_with<DB, F>(
+    header: &Header,
+    transactions: &[TransactionSignedEcRecovered],
+    config: &Config,
+    db: SubState<DB>,
+    mut transact: F,
+) -> Result<ExecutionResult, Error>
+where
+    DB: StateProvider,
+    F: FnMut(
+        &mut EVM<SubState<DB>>,
+        &TransactionSignedEcRecovered,
+    ) -> (revm::ExecutionResult, hashbrown::HashMap<B160, RevmAccount>),
+{
     let mut evm = EVM::new();
     evm.database(db);
 
@@ -335,7 +510,7 @@ pub fn execute<DB: StateProvider>(
         revm_wrap::fill_tx_env(&mut evm.env.tx, transaction);
 
         // Execute transaction.
-        let out = evm.transact();
+        let out = transact(&mut evm, transaction);
 
         // Useful for debugging
         // let out = evm.inspect(revm::inspectors::CustomPrintTracer::default());
@@ -389,11 +564,6 @@ pub fn execute<DB: StateProvider>(
         })
     }
 
-    // Check if gas used matches the value set in header.
-    if header.gas_used != cumulative_gas_used {
-        return Err(Error::BlockGasUsed { got: cumulative_gas_used, expected: header.gas_used })
-    }
-
     // it is okay to unwrap the db.
     let beneficiary = evm
         .db
@@ -401,15 +571,7 @@ pub fn execute<DB: StateProvider>(
         .basic(B160(header.beneficiary.0))
         .map_err(|_| Error::ProviderError)?;
 
-    // NOTE: Related to Ethereum reward change, for other network this is probably going to be moved
-    // to config.
-    let block_reward = match header.number {
-        n if n >= config.spec_upgrades.paris => None,
-        n if n >= config.spec_upgrades.petersburg => Some(WEI_2ETH),
-        n if n >= config.spec_upgrades.byzantium => Some(WEI_3ETH),
-        _ => Some(WEI_5ETH),
-    }
-    .map(|reward| {
+    let block_reward = config.block_reward(header.number).map(|reward| {
         // add block reward to beneficiary/miner
         if let Some(beneficiary) = beneficiary {
             // if account is present append `Changed` changeset for block reward
@@ -436,8 +598,13 @@ mod tests {
 
     use std::{collections::HashMap, sync::Arc};
 
-    use crate::{config::SpecUpgrades, revm_wrap::State};
+    use crate::{
+        call_tracer::{CallKind, CallTracer},
+        config::SpecUpgrades,
+        revm_wrap::State,
+    };
     use reth_db::{
+        cursor::DbCursorRO,
         database::Database,
         mdbx::{test_utils, Env, EnvKind, WriteMap},
         transaction::DbTx,
@@ -504,12 +671,15 @@ mod tests {
         }
     }
 
+    /// Got rlp block from: src/GeneralStateTestsFiller/stChainId/chainIdGasCostFiller.json
+    fn chain_id_gas_cost_block() -> SealedBlock {
+        let mut block_rlp = hex!("f90262f901f9a075c371ba45999d87f4542326910a11af515897aebce5265d3f6acd1f1161f82fa01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa098f2dcd87c8ae4083e7017a05456c14eea4b1db2032126e27b3b1563d57d7cc0a08151d548273f6683169524b66ca9fe338b9ce42bc3540046c828fd939ae23bcba03f4e5c2ec5b2170b711d97ee755c160457bb58d8daa338e835ec02ae6860bbabb901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000083020000018502540be40082a8798203e800a00000000000000000000000000000000000000000000000000000000000000000880000000000000000f863f861800a8405f5e10094100000000000000000000000000000000000000080801ba07e09e26678ed4fac08a249ebe8ed680bf9051a5e14ad223e4b2b9d26e0208f37a05f6e3f188e3e6eab7d7d3b6568f5eac7d687b08d307d3154ccd8c87b4630509bc0").as_slice();
+        SealedBlock::decode(&mut block_rlp).unwrap()
+    }
+
     #[test]
     fn sanity_execution() {
-        // Got rlp block from: src/GeneralStateTestsFiller/stChainId/chainIdGasCostFiller.json
-
-        let mut block_rlp = hex!("f90262f901f9a075c371ba45999d87f4542326910a11af515897aebce5265d3f6acd1f1161f82fa01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa098f2dcd87c8ae4083e7017a05456c14eea4b1db2032126e27b3b1563d57d7cc0a08151d548273f6683169524b66ca9fe338b9ce42bc3540046c828fd939ae23bcba03f4e5c2ec5b2170b711d97ee755c160457bb58d8daa338e835ec02ae6860bbabb901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000083020000018502540be40082a8798203e800a00000000000000000000000000000000000000000000000000000000000000000880000000000000000f863f861800a8405f5e10094100000000000000000000000000000000000000080801ba07e09e26678ed4fac08a249ebe8ed680bf9051a5e14ad223e4b2b9d26e0208f37a05f6e3f188e3e6eab7d7d3b6568f5eac7d687b08d307d3154ccd8c87b4630509bc0").as_slice();
-        let block = SealedBlock::decode(&mut block_rlp).unwrap();
+        let block = chain_id_gas_cost_block();
 
         let mut db = StateProviderTest::default();
 
@@ -600,6 +770,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn execution_with_inspector() {
+        let block = chain_id_gas_cost_block();
+        let contract = H160(hex!("1000000000000000000000000000000000000000"));
+        let sender = H160(hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"));
+
+        let mut db = StateProviderTest::default();
+        db.insert_account(
+            contract,
+            Account { balance: 0x00.into(), nonce: 0x00, bytecode_hash: None },
+            Some(hex!("5a465a905090036002900360015500").into()),
+            HashMap::new(),
+        );
+        db.insert_account(
+            sender,
+            Account { balance: 0x3635c9adc5dea00000u128.into(), nonce: 0x00, bytecode_hash: None },
+            None,
+            HashMap::new(),
+        );
+
+        let config = Config {
+            spec_upgrades: SpecUpgrades::new_berlin_activated(),
+            ..Config::new_ethereum()
+        };
+        let transactions: Vec<TransactionSignedEcRecovered> =
+            block.body.iter().map(|tx| tx.try_ecrecovered().unwrap()).collect();
+
+        let (out, tracers) = execute_transactions_with_inspector(
+            &block.header,
+            &transactions,
+            &config,
+            SubState::new(State::new(db)),
+            |_| CallTracer::default(),
+        )
+        .unwrap();
+        assert_eq!(out.gas_used(), block.header.gas_used);
+
+        let traces = tracers.into_iter().map(CallTracer::into_traces).collect::<Vec<_>>();
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].len(), 1, "the contract doesn't call other contracts");
+        let trace = &traces[0][0];
+        assert_eq!((trace.kind, trace.from, trace.to), (CallKind::Call, sender, contract));
+        assert_eq!(trace.error, None);
+    }
+
     #[test]
     fn apply_account_info_changeset() {
         let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
@@ -637,4 +852,64 @@ mod tests {
             Ok(Some(AccountBeforeTx { address, info: Some(acc2) }))
         );
     }
+
+    #[test]
+    fn apply_wiped_storage() {
+        let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
+        let address = H160::zero();
+        let slot = H256::from_low_u64_be;
+
+        let tx = db.tx_mut().unwrap();
+        for key in [1, 2] {
+            tx.put::<tables::PlainStorageState>(
+                address,
+                StorageEntry { key: slot(key), value: U256::from(1) },
+            )
+            .unwrap();
+        }
+
+        // the account is wiped and slot 2 is written again
+        let result = ExecutionResult {
+            changesets: vec![TransactionChangeSet {
+                receipt: reth_primitives::Receipt::default(),
+                changeset: BTreeMap::from([(
+                    address,
+                    AccountChangeSet {
+                        account: AccountInfoChangeSet::NoChange,
+                        storage: BTreeMap::from([(U256::from(2), (U256::from(1), U256::from(3)))]),
+                        wipe_storage: true,
+                    },
+                )]),
+                new_bytecodes: BTreeMap::new(),
+            }],
+            block_reward: None,
+        };
+        assert_eq!(result.apply_to_db(&tx, 4), Ok(5));
+
+        let storage = tx
+            .cursor_dup::<tables::PlainStorageState>()
+            .unwrap()
+            .walk(address)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(storage, vec![(address, StorageEntry { key: slot(2), value: U256::from(3) })]);
+
+        // every wiped slot is in the change set once
+        let storage_id = TransitionIdAddress((4, address));
+        let changeset = tx
+            .cursor_dup::<tables::StorageChangeSet>()
+            .unwrap()
+            .walk(storage_id.clone())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            changeset,
+            vec![
+                (storage_id.clone(), StorageEntry { key: slot(1), value: U256::from(1) }),
+                (storage_id, StorageEntry { key: slot(2), value: U256::from(1) }),
+            ]
+        );
+    }
 }
@@ -0,0 +1,414 @@
+//! Chain specific extensions of the EVM.
+//!
+//! [ChainHooks] add precompiled contracts and opcode gas surcharges that are not part of
+//! Ethereum, each from an activation block on, so L2s and private chains can reuse the executor.
+//! They are applied by an [Inspector], so they also apply while a tracer inspects the execution.
+
+use bytes::Bytes as RevmBytes;
+use reth_primitives::{Address, BlockNumber, Bytes};
+use revm::{
+    CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter, Return, B160, B256,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+};
+
+/// A precompiled contract that is not part of Ethereum.
+///
+/// Any closure with the signature of [Precompile::run] is a precompile.
+pub trait Precompile: Send + Sync {
+    /// Runs the precompile on the input of a call that was given `gas_limit` gas.
+    fn run(&self, input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError>;
+}
+
+impl<F> Precompile for F
+where
+    F: Fn(&[u8], u64) -> Result<PrecompileOutput, PrecompileError> + Send + Sync,
+{
+    fn run(&self, input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+        self(input, gas_limit)
+    }
+}
+
+/// The output of a successful [Precompile] call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrecompileOutput {
+    /// The gas used by the call.
+    pub gas_used: u64,
+    /// The returned data.
+    pub output: Bytes,
+}
+
+/// The error of a failed [Precompile] call, it consumes all gas of the call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum PrecompileError {
+    /// The gas limit of the call is too low.
+    #[error("Out of gas")]
+    OutOfGas,
+    /// The input is invalid.
+    #[error("Invalid precompile input")]
+    InvalidInput,
+}
+
+/// A [Precompile] at an address, from its activation block on.
+#[derive(Clone)]
+struct CustomPrecompile {
+    address: Address,
+    activation: BlockNumber,
+    precompile: Arc<dyn Precompile>,
+}
+
+impl fmt::Debug for CustomPrecompile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomPrecompile")
+            .field("address", &self.address)
+            .field("activation", &self.activation)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Precompiled contracts and opcode gas surcharges of a chain, in addition to the ones of
+/// Ethereum.
+///
+/// Activation blocks are block numbers, like the ones of [SpecUpgrades](crate::SpecUpgrades), so
+/// hooks can be tied to the hardforks of the chain.
+#[derive(Debug, Clone, Default)]
+pub struct ChainHooks {
+    /// The registered precompiles, ordered by activation block.
+    precompiles: Vec<CustomPrecompile>,
+    /// The gas surcharges of opcodes by activation block.
+    opcode_gas: BTreeMap<BlockNumber, HashMap<u8, u64>>,
+}
+
+// === impl ChainHooks ===
+
+impl ChainHooks {
+    /// Registers a precompile at the address from the activation block on.
+    ///
+    /// A precompile that is registered at the same address with a later activation block replaces
+    /// this one from its own activation block on.
+    ///
+    /// NOTE: The value of a call to a custom precompile is not transferred.
+    pub fn with_precompile(
+        mut self,
+        address: Address,
+        activation: BlockNumber,
+        precompile: impl Precompile + 'static,
+    ) -> Self {
+        let idx = self.precompiles.partition_point(|p| p.activation <= activation);
+        self.precompiles.insert(
+            idx,
+            CustomPrecompile { address, activation, precompile: Arc::new(precompile) },
+        );
+        self
+    }
+
+    /// Charges `gas` on top of the Ethereum gas cost of the opcode from the activation block on.
+    ///
+    /// The surcharge replaces the one of an earlier activation block, a surcharge of zero removes
+    /// it.
+    pub fn with_opcode_gas(mut self, activation: BlockNumber, opcode: u8, gas: u64) -> Self {
+        self.opcode_gas.entry(activation).or_default().insert(opcode, gas);
+        self
+    }
+
+    /// Returns true if no precompiles or surcharges are registered.
+    pub fn is_empty(&self) -> bool {
+        self.precompiles.is_empty() && self.opcode_gas.is_empty()
+    }
+
+    /// Returns the hooks that are active in the block.
+    pub fn at_block(&self, number: BlockNumber) -> ActiveHooks<'_> {
+        let precompiles = self
+            .precompiles
+            .iter()
+            .take_while(|p| p.activation <= number)
+            .map(|p| (B160(p.address.0), &*p.precompile))
+            .collect();
+        let opcode_gas = self
+            .opcode_gas
+            .range(..=number)
+            .flat_map(|(_, surcharges)| surcharges.iter().map(|(&opcode, &gas)| (opcode, gas)))
+            .collect::<HashMap<_, _>>()
+            .into_iter()
+            .filter(|(_, gas)| *gas > 0)
+            .collect();
+        ActiveHooks { precompiles, opcode_gas }
+    }
+}
+
+/// The [ChainHooks] that are active in a block.
+///
+/// An [Inspector] that runs the calls to the precompiles and charges the opcode gas surcharges.
+#[derive(Clone, Default)]
+pub struct ActiveHooks<'a> {
+    precompiles: HashMap<B160, &'a dyn Precompile>,
+    opcode_gas: HashMap<u8, u64>,
+}
+
+// === impl ActiveHooks ===
+
+impl<'a> ActiveHooks<'a> {
+    /// Returns true if no precompiles or surcharges are active.
+    pub fn is_empty(&self) -> bool {
+        self.precompiles.is_empty() && self.opcode_gas.is_empty()
+    }
+}
+
+impl fmt::Debug for ActiveHooks<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActiveHooks")
+            .field("precompiles", &self.precompiles.keys().collect::<Vec<_>>())
+            .field("opcode_gas", &self.opcode_gas)
+            .finish()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for ActiveHooks<'_> {
+    fn initialize_interp(
+        &mut self,
+        _interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        // precompiles are warm, like the ones of Ethereum
+        for &address in self.precompiles.keys() {
+            let _ = data.journaled_state.load_account(address, data.db);
+        }
+        Return::Continue
+    }
+
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        if let Some(&gas) = self.opcode_gas.get(&interp.current_opcode()) {
+            if !interp.gas.record_cost(gas) {
+                return Return::OutOfGas
+            }
+        }
+        Return::Continue
+    }
+
+    fn call(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+        _is_static: bool,
+    ) -> (Return, Gas, RevmBytes) {
+        let Some(precompile) = self.precompiles.get(&inputs.contract) else {
+            return (Return::Continue, Gas::new(0), RevmBytes::new())
+        };
+        let mut gas = Gas::new(inputs.gas_limit);
+        match precompile.run(&inputs.input, inputs.gas_limit) {
+            Ok(PrecompileOutput { gas_used, output }) if gas.record_cost(gas_used) => {
+                (Return::Return, gas, output.0)
+            }
+            Ok(_) | Err(PrecompileError::OutOfGas) => (Return::OutOfGas, gas, RevmBytes::new()),
+            Err(PrecompileError::InvalidInput) => (Return::PrecompileError, gas, RevmBytes::new()),
+        }
+    }
+}
+
+/// An [Inspector] that applies the [ActiveHooks] to the execution that the inner inspector
+/// inspects.
+///
+/// The inner inspector sees the calls to custom precompiles like calls to any other contract.
+#[derive(Debug)]
+pub struct HookedInspector<'a, I> {
+    hooks: ActiveHooks<'a>,
+    inner: I,
+}
+
+// === impl HookedInspector ===
+
+impl<'a, I> HookedInspector<'a, I> {
+    /// Creates a new inspector that applies the hooks next to the inner one.
+    pub fn new(hooks: ActiveHooks<'a>, inner: I) -> Self {
+        Self { hooks, inner }
+    }
+
+    /// Consumes the inspector and returns the inner one.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<DB: Database, I: Inspector<DB>> Inspector<DB> for HookedInspector<'_, I> {
+    fn initialize_interp(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        self.hooks.initialize_interp(interp, data, is_static);
+        self.inner.initialize_interp(interp, data, is_static)
+    }
+
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        match self.inner.step(interp, data, is_static) {
+            Return::Continue => self.hooks.step(interp, data, is_static),
+            ret => ret,
+        }
+    }
+
+    fn log(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        address: &B160,
+        topics: &[B256],
+        log_data: &RevmBytes,
+    ) {
+        self.inner.log(data, address, topics, log_data)
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+        eval: Return,
+    ) -> Return {
+        self.inner.step_end(interp, data, is_static, eval)
+    }
+
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+        is_static: bool,
+    ) -> (Return, Gas, RevmBytes) {
+        match self.inner.call(data, inputs, is_static) {
+            (Return::Continue, ..) => self.hooks.call(data, inputs, is_static),
+            out => out,
+        }
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: RevmBytes,
+        is_static: bool,
+    ) -> (Return, Gas, RevmBytes) {
+        self.inner.call_end(data, inputs, remaining_gas, ret, out, is_static)
+    }
+
+    fn create(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &mut CreateInputs,
+    ) -> (Return, Option<B160>, Gas, RevmBytes) {
+        self.inner.create(data, inputs)
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CreateInputs,
+        ret: Return,
+        address: Option<B160>,
+        remaining_gas: Gas,
+        out: RevmBytes,
+    ) -> (Return, Option<B160>, Gas, RevmBytes) {
+        self.inner.create_end(data, inputs, ret, address, remaining_gas, out)
+    }
+
+    fn selfdestruct(&mut self) {
+        self.inner.selfdestruct()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::H160;
+    use revm::{
+        db::{CacheDB, EmptyDB},
+        opcode, AccountInfo, Bytecode, TransactTo, EVM,
+    };
+
+    const CALLER: B160 = B160([0xaa; 20]);
+    const CONTRACT: B160 = B160([0x01; 20]);
+    const PRECOMPILE: Address = H160([0xff; 20]);
+
+    /// Reverses the input for 100 gas.
+    fn reverse(input: &[u8], gas_limit: u64) -> Result<PrecompileOutput, PrecompileError> {
+        if gas_limit < 100 {
+            return Err(PrecompileError::OutOfGas)
+        }
+        let output = input.iter().rev().copied().collect::<Vec<_>>();
+        Ok(PrecompileOutput { gas_used: 100, output: output.into() })
+    }
+
+    fn transact(to: B160, input: &[u8], hooks: ActiveHooks<'_>) -> revm::ExecutionResult {
+        let mut db = CacheDB::new(EmptyDB::default());
+        // `jumpdest; stop`
+        let bytecode = Bytecode::new_raw(vec![opcode::JUMPDEST, opcode::STOP].into());
+        db.insert_account_info(
+            CONTRACT,
+            AccountInfo { code_hash: bytecode.hash(), code: Some(bytecode), ..Default::default() },
+        );
+
+        let mut evm = EVM::new();
+        evm.database(db);
+        evm.env.tx.caller = CALLER;
+        evm.env.tx.transact_to = TransactTo::Call(to);
+        evm.env.tx.data = input.to_vec().into();
+        evm.env.tx.gas_limit = 100_000;
+        let mut hooks = hooks;
+        evm.inspect(&mut hooks).0
+    }
+
+    #[test]
+    fn activates_hooks_at_block() {
+        let hooks = ChainHooks::default()
+            .with_precompile(PRECOMPILE, 10, reverse)
+            .with_opcode_gas(5, opcode::JUMPDEST, 1_000)
+            .with_opcode_gas(20, opcode::JUMPDEST, 0);
+        assert!(ChainHooks::default().at_block(0).is_empty());
+        assert!(hooks.at_block(4).is_empty());
+
+        let active = hooks.at_block(5);
+        assert!(active.precompiles.is_empty());
+        assert_eq!(active.opcode_gas, HashMap::from([(opcode::JUMPDEST, 1_000)]));
+
+        let active = hooks.at_block(10);
+        assert!(active.precompiles.contains_key(&B160(PRECOMPILE.0)));
+
+        assert!(hooks.at_block(20).opcode_gas.is_empty());
+    }
+
+    #[test]
+    fn runs_custom_precompiles() {
+        let hooks = ChainHooks::default().with_precompile(PRECOMPILE, 0, reverse);
+        let result = transact(B160(PRECOMPILE.0), &[1, 2, 3], hooks.at_block(0));
+        assert_eq!(result.exit_reason, Return::Return);
+        let revm::TransactOut::Call(output) = result.out else { panic!("not a call") };
+        assert_eq!(output.to_vec(), vec![3, 2, 1]);
+
+        // without the hook the address has no code
+        let plain = transact(B160(PRECOMPILE.0), &[1, 2, 3], ActiveHooks::default());
+        assert_eq!(result.gas_used, plain.gas_used + 100);
+    }
+
+    #[test]
+    fn charges_opcode_surcharges() {
+        let hooks = ChainHooks::default().with_opcode_gas(0, opcode::JUMPDEST, 1_000);
+        let result = transact(CONTRACT, &[], hooks.at_block(0));
+        let plain = transact(CONTRACT, &[], ActiveHooks::default());
+        assert!(matches!(result.exit_reason, revm::return_ok!()));
+        assert_eq!(result.gas_used, plain.gas_used + 1_000);
+    }
+}
@@ -7,9 +7,17 @@
 
 //! Reth executor executes transaction in block of data.
 
+pub mod batch;
+pub mod builder;
+pub mod bundle;
+pub mod cache;
+pub mod call_tracer;
 pub mod config;
 /// Executor
 pub mod executor;
+pub mod hooks;
 /// Wrapper around revm database and types
 pub mod revm_wrap;
+pub mod threaded_revm_wrap;
+pub mod tracer;
 pub use config::{Config, SpecUpgrades};
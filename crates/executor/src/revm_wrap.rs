@@ -1,3 +1,4 @@
+use crate::cache::BytecodeCache;
 use reth_interfaces::Error;
 use reth_primitives::{
     Account, Header, Transaction, TransactionKind, TransactionSignedEcRecovered, TxEip1559,
@@ -6,34 +7,50 @@ use reth_primitives::{
 use reth_provider::StateProvider;
 use revm::{
     db::{CacheDB, DatabaseRef},
-    BlockEnv, TransactTo, TxEnv, B160, B256, U256 as evmU256,
+    BlockEnv, SpecId, TransactTo, TxEnv, B160, B256, U256 as evmU256,
 };
+use std::sync::Arc;
 
 /// SubState of database. Uses revm internal cache with binding to reth DbExecutor trait.
 pub type SubState<DB> = CacheDB<State<DB>>;
 
 /// Wrapper around ExeuctorDb that implements revm database trait
-pub struct State<DB: StateProvider>(pub DB);
+pub struct State<DB: StateProvider> {
+    db: DB,
+    /// The cache of analysed bytecodes and the spec of the executed block.
+    bytecodes: Option<(Arc<BytecodeCache>, SpecId)>,
+}
 
 impl<DB: StateProvider> State<DB> {
     /// Create new State with generic ExecutorDb.
     pub fn new(db: DB) -> Self {
-        Self(db)
+        Self { db, bytecodes: None }
+    }
+
+    /// Serve the bytecodes analysed for the spec of the executed block from the cache.
+    pub fn with_bytecode_cache(mut self, cache: Arc<BytecodeCache>, spec_id: SpecId) -> Self {
+        self.bytecodes = Some((cache, spec_id));
+        self
     }
 
     /// Return inner state reference
     pub fn state(&self) -> &DB {
-        &self.0
+        &self.db
     }
 
     /// Return inner state mutable reference
     pub fn state_mut(&mut self) -> &mut DB {
-        &mut self.0
+        &mut self.db
     }
 
     /// Consume State and return inner DbExecutable.
     pub fn into_inner(self) -> DB {
-        self.0
+        self.db
+    }
+
+    fn load_bytecode(&self, code_hash: H256) -> Result<revm::Bytecode, Error> {
+        let bytecode = self.db.bytecode_by_hash(code_hash)?.unwrap_or_default();
+        Ok(revm::Bytecode::new_raw(bytecode.0))
     }
 }
 
@@ -41,7 +58,7 @@ impl<DB: StateProvider> DatabaseRef for State<DB> {
     type Error = Error;
 
     fn basic(&self, address: B160) -> Result<Option<revm::AccountInfo>, Self::Error> {
-        Ok(self.0.basic_account(H160(address.0))?.map(|account| revm::AccountInfo {
+        Ok(self.db.basic_account(H160(address.0))?.map(|account| revm::AccountInfo {
             balance: evmU256::from_limbs(account.balance.0),
             nonce: account.nonce,
             code_hash: B256(account.bytecode_hash.unwrap_or(KECCAK_EMPTY).0),
@@ -50,19 +67,24 @@ impl<DB: StateProvider> DatabaseRef for State<DB> {
     }
 
     fn code_by_hash(&self, code_hash: B256) -> Result<revm::Bytecode, Self::Error> {
-        let bytecode = self.0.bytecode_by_hash(H256(code_hash.0))?.unwrap_or_default();
-        Ok(revm::Bytecode::new_raw(bytecode.0))
+        let code_hash = H256(code_hash.0);
+        match &self.bytecodes {
+            Some((cache, spec_id)) => {
+                cache.get_or_analyse(code_hash, *spec_id, || self.load_bytecode(code_hash))
+            }
+            None => self.load_bytecode(code_hash),
+        }
     }
 
     fn storage(&self, address: B160, index: evmU256) -> Result<evmU256, Self::Error> {
         let index = H256(index.to_be_bytes());
         let ret =
-            evmU256::from_limbs(self.0.storage(H160(address.0), index)?.unwrap_or_default().0);
+            evmU256::from_limbs(self.db.storage(H160(address.0), index)?.unwrap_or_default().0);
         Ok(ret)
     }
 
     fn block_hash(&self, number: evmU256) -> Result<B256, Self::Error> {
-        Ok(B256(self.0.block_hash(U256(*number.as_limbs()))?.unwrap_or_default().0))
+        Ok(B256(self.db.block_hash(U256(*number.as_limbs()))?.unwrap_or_default().0))
     }
 }
 
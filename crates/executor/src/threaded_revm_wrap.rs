@@ -0,0 +1,311 @@
+//! A pool of threads that serves the state reads of concurrent executions.
+//!
+//! Database transactions can't be shared between threads, so every worker of the
+//! [StateReaderPool] opens its own read transaction and serves the reads that executions on other
+//! threads issue through a [ThreadedStateProvider]. The provider implements [StateProvider], so it
+//! can be wrapped in a [State](crate::revm_wrap::State) like any other provider.
+//!
+//! The reads are queued in a bounded queue. Once it is full, the executions block until a worker
+//! took a read off the queue, so a burst of concurrent executions can't pile up reads in memory,
+//! and the number of open transactions is bounded by the number of workers.
+//!
+//! The workers are scoped threads, so the transactions only live as long as the scope the pool is
+//! spawned in. The execution stage spawns a pool for every batch of blocks to prefetch their
+//! state concurrently while it executes the blocks before, see
+//! [StateCache::spawn_prefetch](crate::cache::StateCache::spawn_prefetch).
+
+use reth_db::database::Database;
+use reth_interfaces::{provider::Error as ProviderError, Result};
+use reth_primitives::{Account, Address, Bytes, StorageKey, StorageValue, H256, U256};
+use reth_provider::{AccountProvider, StateProvider, StateProviderImplLatest};
+use std::{
+    fmt::{self, Debug},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{Scope, ScopedJoinHandle},
+};
+use tracing::trace;
+
+/// The default number of workers of the [StateReaderPool].
+pub const DEFAULT_STATE_READERS: usize = 4;
+
+/// The default number of reads that are queued before the executions block.
+pub const DEFAULT_STATE_READ_QUEUE_SIZE: usize = 1024;
+
+/// The settings of a [StateReaderPool].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateReaderConfig {
+    /// The number of worker threads, each of which holds a read transaction.
+    pub workers: usize,
+    /// The number of reads that are queued before the executions block.
+    pub queue_size: usize,
+}
+
+impl Default for StateReaderConfig {
+    fn default() -> Self {
+        Self { workers: DEFAULT_STATE_READERS, queue_size: DEFAULT_STATE_READ_QUEUE_SIZE }
+    }
+}
+
+/// A read of the state and the channel its result is sent back over.
+enum StateRead {
+    Account(Address, SyncSender<Result<Option<Account>>>),
+    Storage(Address, StorageKey, SyncSender<Result<Option<StorageValue>>>),
+    Bytecode(H256, SyncSender<Result<Option<Bytes>>>),
+    BlockHash(U256, SyncSender<Result<Option<H256>>>),
+    /// Stops the worker that receives it.
+    Shutdown,
+}
+
+// === impl StateRead ===
+
+impl StateRead {
+    /// Serves the read from the provider, returns false if the worker has to stop.
+    fn serve<SP: StateProvider>(self, provider: &SP) -> bool {
+        // The execution that issued a read may be gone, in which case the result is dropped.
+        match self {
+            StateRead::Account(address, reply) => {
+                let _ = reply.send(provider.basic_account(address));
+            }
+            StateRead::Storage(address, key, reply) => {
+                let _ = reply.send(provider.storage(address, key));
+            }
+            StateRead::Bytecode(code_hash, reply) => {
+                let _ = reply.send(provider.bytecode_by_hash(code_hash));
+            }
+            StateRead::BlockHash(number, reply) => {
+                let _ = reply.send(provider.block_hash(number));
+            }
+            StateRead::Shutdown => return false,
+        }
+        true
+    }
+}
+
+/// A pool of threads that read the latest state of the database for executions on other threads.
+///
+/// Each worker reads the committed state as of the time the pool was spawned, the pool should be
+/// spawned again once the state changed. Dropping the pool shuts it down.
+pub struct StateReaderPool<'scope> {
+    sender: SyncSender<StateRead>,
+    closed: Arc<AtomicBool>,
+    workers: Vec<ScopedJoinHandle<'scope, ()>>,
+}
+
+// === impl StateReaderPool ===
+
+impl<'scope> StateReaderPool<'scope> {
+    /// Spawns the workers in the scope, each with its own read transaction of the database.
+    ///
+    /// Returns once all workers opened their transaction, or the first error of a worker.
+    pub fn spawn<'env, DB: Database>(
+        scope: &'scope Scope<'scope, 'env>,
+        db: &'env DB,
+        config: StateReaderConfig,
+    ) -> Result<Self> {
+        let (sender, receiver) = sync_channel(config.queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (ready_tx, ready_rx) = sync_channel(config.workers.max(1));
+
+        let mut pool =
+            Self { sender, closed: Arc::new(AtomicBool::new(false)), workers: Vec::new() };
+        for id in 0..config.workers.max(1) {
+            let (receiver, ready) = (receiver.clone(), ready_tx.clone());
+            let worker = std::thread::Builder::new()
+                .name(format!("state-reader-{id}"))
+                .spawn_scoped(scope, move || match db.tx() {
+                    Ok(tx) => {
+                        let _ = ready.send(Ok(()));
+                        drop(ready);
+                        serve_reads(id, StateProviderImplLatest::new(tx), &receiver);
+                    }
+                    Err(err) => {
+                        let _ = ready.send(Err(err));
+                    }
+                })
+                .expect("Expects that thread name is not null");
+            pool.workers.push(worker);
+        }
+        // Only the workers keep the queue open, so the shutdown can't block on a queue no one
+        // reads.
+        drop((receiver, ready_tx));
+
+        for ready in ready_rx {
+            // dropping the pool stops the workers that are already serving
+            ready?;
+        }
+        Ok(pool)
+    }
+
+    /// Returns the number of workers.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns a provider that reads the state through this pool.
+    pub fn provider(&self) -> ThreadedStateProvider {
+        ThreadedStateProvider { sender: self.sender.clone(), closed: self.closed.clone() }
+    }
+
+    /// Stops the pool once the queued reads are served, and waits for the workers to exit.
+    ///
+    /// Reads that are issued afterwards fail with [ProviderError::StateReaderShutdown].
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return
+        }
+        // The markers are queued behind the pending reads, so these are still served.
+        for _ in 0..self.workers.len() {
+            if self.sender.send(StateRead::Shutdown).is_err() {
+                break
+            }
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for StateReaderPool<'_> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl Debug for StateReaderPool<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateReaderPool")
+            .field("workers", &self.workers.len())
+            .field("closed", &self.closed.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Serves the queued reads until the worker receives a shutdown marker.
+fn serve_reads<SP: StateProvider>(id: usize, provider: SP, receiver: &Mutex<Receiver<StateRead>>) {
+    loop {
+        // The lock is only held while waiting for the next read, not while serving it.
+        let next = receiver.lock().ok().and_then(|receiver| receiver.recv().ok());
+        let Some(read) = next else { break };
+        if !read.serve(&provider) {
+            break
+        }
+    }
+    trace!(target: "executor::threaded_revm_wrap", id, "State reader stopped");
+}
+
+/// A [StateProvider] that reads the state through a [StateReaderPool].
+///
+/// Reads block while the queue of the pool is full.
+#[derive(Debug, Clone)]
+pub struct ThreadedStateProvider {
+    sender: SyncSender<StateRead>,
+    closed: Arc<AtomicBool>,
+}
+
+// === impl ThreadedStateProvider ===
+
+impl ThreadedStateProvider {
+    /// Queues the read and waits for its result.
+    fn read<T>(&self, read: impl FnOnce(SyncSender<Result<T>>) -> StateRead) -> Result<T> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(ProviderError::StateReaderShutdown.into())
+        }
+        let (reply, result) = sync_channel(1);
+        // Both fail if the workers exited before the read was served.
+        self.sender.send(read(reply)).map_err(|_| ProviderError::StateReaderShutdown)?;
+        result.recv().map_err(|_| ProviderError::StateReaderShutdown)?
+    }
+}
+
+impl AccountProvider for ThreadedStateProvider {
+    fn basic_account(&self, address: Address) -> Result<Option<Account>> {
+        self.read(|reply| StateRead::Account(address, reply))
+    }
+}
+
+impl StateProvider for ThreadedStateProvider {
+    fn storage(&self, account: Address, storage_key: StorageKey) -> Result<Option<StorageValue>> {
+        self.read(|reply| StateRead::Storage(account, storage_key, reply))
+    }
+
+    fn bytecode_by_hash(&self, code_hash: H256) -> Result<Option<Bytes>> {
+        self.read(|reply| StateRead::Bytecode(code_hash, reply))
+    }
+
+    fn block_hash(&self, number: U256) -> Result<Option<H256>> {
+        self.read(|reply| StateRead::BlockHash(number, reply))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        mdbx::{test_utils::create_test_db, EnvKind, WriteMap},
+        tables,
+        transaction::{DbTx, DbTxMut},
+    };
+    use reth_interfaces::Error;
+    use reth_primitives::StorageEntry;
+
+    #[test]
+    fn serves_concurrent_reads() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let tx = db.tx_mut().unwrap();
+        let accounts = (1..=16).map(Address::from_low_u64_be).collect::<Vec<_>>();
+        for (nonce, address) in accounts.iter().enumerate() {
+            let account = Account { nonce: nonce as u64, ..Default::default() };
+            tx.put::<tables::PlainAccountState>(*address, account).unwrap();
+            let entry = StorageEntry { key: H256::zero(), value: U256::from(nonce) };
+            tx.put::<tables::PlainStorageState>(*address, entry).unwrap();
+        }
+        tx.commit().unwrap();
+
+        // a queue of one read blocks all but one of the executions at a time
+        std::thread::scope(|scope| {
+            let config = StateReaderConfig { workers: 2, queue_size: 1 };
+            let pool = StateReaderPool::spawn(scope, db.as_ref(), config).unwrap();
+            let mut executions = Vec::new();
+            for (nonce, address) in accounts.iter().enumerate() {
+                let provider = pool.provider();
+                executions.push(scope.spawn(move || {
+                    let account = provider.basic_account(*address).unwrap().unwrap();
+                    assert_eq!(account.nonce, nonce as u64);
+                    assert_eq!(
+                        provider.storage(*address, H256::zero()).unwrap(),
+                        Some(U256::from(nonce))
+                    );
+                }));
+            }
+            for execution in executions {
+                execution.join().unwrap();
+            }
+            assert_eq!(pool.provider().basic_account(Address::zero()), Ok(None));
+        });
+    }
+
+    #[test]
+    fn reads_fail_after_shutdown() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        std::thread::scope(|scope| {
+            let pool =
+                StateReaderPool::spawn(scope, db.as_ref(), StateReaderConfig::default()).unwrap();
+            let provider = pool.provider();
+            assert_eq!(provider.block_hash(U256::zero()), Ok(None));
+
+            pool.shutdown();
+            assert_eq!(
+                provider.basic_account(Address::zero()),
+                Err(Error::Provider(ProviderError::StateReaderShutdown))
+            );
+        });
+    }
+}
@@ -0,0 +1,278 @@
+//! Struct log tracer that records the executed opcodes of a transaction.
+//!
+//! Struct logs of large transactions can easily be hundreds of MB, so [StructLogConfig] allows to
+//! only capture a subset of opcodes, to selectively disable stack, memory and storage capture and
+//! to limit the number of recorded steps.
+
+use revm::{opcode, Database, EVMData, Inspector, Interpreter, Return, B160, U256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Configuration of the [StructLogTracer].
+///
+/// By default all opcodes are captured including stack and storage, while memory and return data
+/// need to be enabled explicitly.
+#[derive(Debug, Clone, Default)]
+pub struct StructLogConfig {
+    /// The opcodes to capture. All opcodes are captured if `None`.
+    pub opcodes: Option<HashSet<u8>>,
+    /// Disables capturing of the stack.
+    pub disable_stack: bool,
+    /// Disables capturing of the storage.
+    pub disable_storage: bool,
+    /// Enables capturing of the memory.
+    pub enable_memory: bool,
+    /// Enables capturing of the return data.
+    pub enable_return_data: bool,
+    /// The maximum number of steps to capture. Unlimited if `None`.
+    pub limit: Option<usize>,
+}
+
+// === impl StructLogConfig ===
+
+impl StructLogConfig {
+    /// Only capture the given opcodes.
+    pub fn with_opcodes(mut self, opcodes: impl IntoIterator<Item = u8>) -> Self {
+        self.opcodes = Some(opcodes.into_iter().collect());
+        self
+    }
+
+    /// Sets the maximum number of steps to capture.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns `true` if the opcode should be captured.
+    pub fn captures(&self, op: u8) -> bool {
+        self.opcodes.as_ref().map(|opcodes| opcodes.contains(&op)).unwrap_or(true)
+    }
+}
+
+/// A single captured step of the execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLog {
+    /// Program counter.
+    pub pc: usize,
+    /// The executed opcode.
+    pub op: u8,
+    /// Remaining gas before the step.
+    pub gas: u64,
+    /// Gas consumed by the step.
+    pub gas_cost: u64,
+    /// Call depth.
+    pub depth: u64,
+    /// The stack before the step, if enabled.
+    pub stack: Option<Vec<U256>>,
+    /// The memory before the step, if enabled.
+    pub memory: Option<Vec<u8>>,
+    /// The return data of the last call, if enabled.
+    pub return_data: Option<Vec<u8>>,
+    /// The storage slots of the current contract accessed so far, if enabled.
+    pub storage: Option<BTreeMap<U256, U256>>,
+}
+
+// === impl StructLog ===
+
+impl StructLog {
+    /// Returns the name of the opcode.
+    pub fn op_name(&self) -> &'static str {
+        opcode::OPCODE_JUMPMAP[self.op as usize].unwrap_or("INVALID")
+    }
+}
+
+/// An [Inspector] that records a [StructLog] for every captured step.
+#[derive(Debug, Default)]
+pub struct StructLogTracer {
+    config: StructLogConfig,
+    /// All captured steps.
+    logs: Vec<StructLog>,
+    /// The storage slots accessed so far, by contract.
+    storage: HashMap<B160, BTreeMap<U256, U256>>,
+    /// The index of the log of the current step, if it was captured.
+    pending_log: Option<usize>,
+    /// The contract and key of the current step, if it's an `SLOAD`.
+    pending_sload: Option<(B160, U256)>,
+}
+
+// === impl StructLogTracer ===
+
+impl StructLogTracer {
+    /// Creates a new tracer with the given config.
+    pub fn new(config: StructLogConfig) -> Self {
+        Self { config, ..Default::default() }
+    }
+
+    /// Returns the captured steps.
+    pub fn logs(&self) -> &[StructLog] {
+        &self.logs
+    }
+
+    /// Consumes the tracer and returns the captured steps.
+    pub fn into_logs(self) -> Vec<StructLog> {
+        self.logs
+    }
+
+    /// Returns `true` if the configured limit of steps is reached.
+    pub fn is_limit_reached(&self) -> bool {
+        self.config.limit.map(|limit| self.logs.len() >= limit).unwrap_or(false)
+    }
+}
+
+impl<DB: Database> Inspector<DB> for StructLogTracer {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        self.pending_log = None;
+        self.pending_sload = None;
+
+        if self.is_limit_reached() {
+            return Return::Continue
+        }
+
+        let op = interp.current_opcode();
+        let address = interp.contract.address;
+
+        // keep track of accessed storage even if the opcode is not captured, so the storage of
+        // captured steps is complete
+        if !self.config.disable_storage {
+            match op {
+                opcode::SSTORE => {
+                    if let (Ok(key), Ok(value)) = (interp.stack.peek(0), interp.stack.peek(1)) {
+                        self.storage.entry(address).or_default().insert(key, value);
+                    }
+                }
+                opcode::SLOAD => {
+                    // the loaded value is only known after the step
+                    self.pending_sload = interp.stack.peek(0).ok().map(|key| (address, key));
+                }
+                _ => {}
+            }
+        }
+
+        if !self.config.captures(op) {
+            return Return::Continue
+        }
+
+        let log = StructLog {
+            pc: interp.program_counter(),
+            op,
+            gas: interp.gas().remaining(),
+            gas_cost: 0,
+            depth: data.journaled_state.depth(),
+            stack: (!self.config.disable_stack).then(|| interp.stack.data().clone()),
+            memory: self.config.enable_memory.then(|| interp.memory.data().clone()),
+            return_data: self.config.enable_return_data.then(|| interp.return_data_buffer.to_vec()),
+            storage: (!self.config.disable_storage)
+                .then(|| self.storage.get(&address).cloned().unwrap_or_default()),
+        };
+        self.pending_log = Some(self.logs.len());
+        self.logs.push(log);
+
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+        _eval: Return,
+    ) -> Return {
+        if let Some((address, key)) = self.pending_sload.take() {
+            if let Ok(value) = interp.stack.peek(0) {
+                self.storage.entry(address).or_default().insert(key, value);
+                if let Some(storage) =
+                    self.pending_log.and_then(|idx| self.logs[idx].storage.as_mut())
+                {
+                    storage.insert(key, value);
+                }
+            }
+        }
+
+        if let Some(idx) = self.pending_log.take() {
+            let log = &mut self.logs[idx];
+            log.gas_cost = log.gas.saturating_sub(interp.gas().remaining());
+        }
+
+        Return::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::{
+        db::{CacheDB, EmptyDB},
+        AccountInfo, Bytecode, TransactTo, EVM,
+    };
+
+    /// `sstore(0, 1)`, `sload(0)`
+    const CODE: [u8; 9] = [
+        opcode::PUSH1,
+        0x01,
+        opcode::PUSH1,
+        0x00,
+        opcode::SSTORE,
+        opcode::PUSH1,
+        0x00,
+        opcode::SLOAD,
+        opcode::STOP,
+    ];
+
+    fn trace(config: StructLogConfig) -> Vec<StructLog> {
+        let contract = B160::from_low_u64_be(0x1000);
+        let bytecode = Bytecode::new_raw(CODE.to_vec().into());
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            contract,
+            AccountInfo { code_hash: bytecode.hash(), code: Some(bytecode), ..Default::default() },
+        );
+
+        let mut evm = EVM::new();
+        evm.database(db);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+        evm.env.tx.gas_limit = 100_000;
+
+        let mut tracer = StructLogTracer::new(config);
+        evm.inspect(&mut tracer);
+        tracer.into_logs()
+    }
+
+    #[test]
+    fn captures_all_steps() {
+        let logs = trace(StructLogConfig::default());
+        let ops = logs.iter().map(|log| log.op_name()).collect::<Vec<_>>();
+        assert_eq!(ops, vec!["PUSH1", "PUSH1", "SSTORE", "PUSH1", "SLOAD", "STOP"]);
+        assert!(logs.iter().all(|log| log.stack.is_some() && log.memory.is_none()));
+
+        let sload = &logs[4];
+        assert_eq!(sload.stack, Some(vec![U256::ZERO]));
+        assert_eq!(sload.storage, Some(BTreeMap::from([(U256::ZERO, U256::from(1))])));
+        assert!(sload.gas_cost > 0);
+    }
+
+    #[test]
+    fn filters_opcodes() {
+        let config = StructLogConfig { disable_stack: true, ..Default::default() }
+            .with_opcodes([opcode::SSTORE, opcode::SLOAD]);
+        let logs = trace(config);
+        assert_eq!(
+            logs.iter().map(|log| log.op).collect::<Vec<_>>(),
+            [opcode::SSTORE, opcode::SLOAD]
+        );
+        assert!(logs.iter().all(|log| log.stack.is_none()));
+        // storage is tracked for uncaptured steps as well
+        assert_eq!(logs[1].storage, Some(BTreeMap::from([(U256::ZERO, U256::from(1))])));
+    }
+
+    #[test]
+    fn respects_limit() {
+        let config = StructLogConfig { disable_storage: true, ..Default::default() }.with_limit(2);
+        let logs = trace(config);
+        assert_eq!(logs.len(), 2);
+        assert!(logs.iter().all(|log| log.storage.is_none()));
+    }
+}
@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use reth_primitives::{BlockHash, BlockNumber, SealedBlock, SealedHeader, H256};
+use reth_primitives::{Address, BlockHash, BlockNumber, SealedBlock, SealedHeader, H256, U256};
 use tokio::sync::watch::Receiver;
 
 /// Re-export forkchoice state
@@ -17,6 +17,19 @@ pub trait Consensus: Send + Sync {
     /// **This should not be called for the genesis block**.
     fn validate_header(&self, header: &SealedHeader, parent: &SealedHeader) -> Result<(), Error>;
 
+    /// Validate that the header is on the right side of the merge, given the total difficulty of
+    /// its parent.
+    ///
+    /// Headers whose parent reached the terminal total difficulty must be proof-of-stake, all
+    /// other headers must be proof-of-work.
+    ///
+    /// **This should not be called for the genesis block**.
+    fn validate_header_with_total_difficulty(
+        &self,
+        header: &SealedHeader,
+        parent_total_difficulty: U256,
+    ) -> Result<(), Error>;
+
     /// Validate a block disregarding world state, i.e. things that can be checked before sender
     /// recovery and execution.
     ///
@@ -40,12 +53,16 @@ pub trait Consensus: Send + Sync {
 pub enum Error {
     #[error("Block used gas ({gas_used:?}) is greater then gas limit ({gas_limit:?}).")]
     HeaderGasUsedExceedsGasLimit { gas_used: u64, gas_limit: u64 },
+    #[error("Block gas limit ({gas_limit:?}) is below the minimum or above the maximum.")]
+    HeaderGasLimitOutOfBounds { gas_limit: u64 },
     #[error("Block ommer hash ({got:?}) is different then expected: ({expected:?})")]
     BodyOmmersHashDiff { got: H256, expected: H256 },
     #[error("Block transaction root ({got:?}) is different then expected: ({expected:?})")]
     BodyTransactionRootDiff { got: H256, expected: H256 },
     #[error("Block receipts root ({got:?}) is different then expected: ({expected:?}).")]
     BodyReceiptsRootDiff { got: H256, expected: H256 },
+    #[error("Block state root ({got:?}) is different then expected: ({expected:?}).")]
+    BodyStateRootDiff { got: H256, expected: H256 },
     #[error("Block with [hash:{hash:?},number: {number:}] is already known.")]
     BlockKnown { hash: BlockHash, number: BlockNumber },
     #[error("Block parent [hash:{hash:?}] is not known.")]
@@ -78,6 +95,10 @@ pub enum Error {
     SignerAccountHasBytecode,
     #[error("Transaction nonce is not consistent.")]
     TransactionNonceNotConsistent,
+    #[error("Transaction sender {sender:?} is not permitted.")]
+    TransactionSenderNotPermitted { sender: Address },
+    #[error("Contract deployer {deployer:?} is not permitted.")]
+    TransactionDeployerNotPermitted { deployer: Address },
     #[error("Account does not have enough funds ({available_funds:?}) to cover transaction max fee: {max_fee:?}.")]
     InsufficientFunds { max_fee: u128, available_funds: u128 },
     #[error("Eip2930 transaction is enabled after berlin hardfork.")]
@@ -104,4 +125,8 @@ pub enum Error {
     TheMergeOmmerRootIsNotEmpty,
     #[error("Mix hash after merge is not zero")]
     TheMergeMixHashIsNotZero,
+    #[error("Block difficulty {difficulty} is not zero after the terminal total difficulty {terminal_total_difficulty} was reached.")]
+    PoWBlockAfterMerge { difficulty: U256, terminal_total_difficulty: U256 },
+    #[error("Block difficulty is zero before the terminal total difficulty {terminal_total_difficulty} was reached, parent total difficulty: {parent_total_difficulty}.")]
+    PoSBlockBeforeMerge { parent_total_difficulty: U256, terminal_total_difficulty: U256 },
 }
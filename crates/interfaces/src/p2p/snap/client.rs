@@ -0,0 +1,29 @@
+use crate::p2p::{downloader::DownloadClient, error::PeerRequestResult};
+use async_trait::async_trait;
+pub use reth_eth_wire::types::snap::{
+    AccountRange, ByteCodes, GetAccountRange, GetByteCodes, GetStorageRanges, GetTrieNodes,
+    StorageRanges, TrieNodes,
+};
+
+/// A client of the `snap/1` protocol, which downloads the state of recent blocks as ranges of
+/// trie leaves with proofs.
+///
+/// The request ids of the requests are assigned by the client.
+#[async_trait]
+#[auto_impl::auto_impl(&, Arc, Box)]
+pub trait SnapClient: DownloadClient {
+    /// Requests a range of accounts of the state trie.
+    async fn get_account_range(&self, request: GetAccountRange) -> PeerRequestResult<AccountRange>;
+
+    /// Requests the storage slots of accounts.
+    async fn get_storage_ranges(
+        &self,
+        request: GetStorageRanges,
+    ) -> PeerRequestResult<StorageRanges>;
+
+    /// Requests contract codes by their hashes.
+    async fn get_byte_codes(&self, request: GetByteCodes) -> PeerRequestResult<ByteCodes>;
+
+    /// Requests nodes of the state trie and of storage tries by their paths.
+    async fn get_trie_nodes(&self, request: GetTrieNodes) -> PeerRequestResult<TrieNodes>;
+}
@@ -0,0 +1,2 @@
+/// Traits and types for `snap` protocol clients.
+pub mod client;
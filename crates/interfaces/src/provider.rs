@@ -1,4 +1,4 @@
-use reth_primitives::{BlockHash, BlockNumber};
+use reth_primitives::{BlockHash, BlockNumber, TransitionId};
 
 /// KV error type. They are using u32 to represent error code.
 #[allow(missing_docs)]
@@ -10,6 +10,18 @@ pub enum Error {
     BlockHash { block_hash: BlockHash },
     #[error("Block body not exists #{block_number} ({block_hash:?})")]
     BlockBody { block_number: BlockNumber, block_hash: BlockHash },
+    #[error("Block {block_hash:?} is not part of the canonical chain")]
+    NonCanonicalBlock { block_hash: BlockHash },
     #[error("Block transition does not exist for block #{block_number} ({block_hash:?})")]
     BlockTransition { block_number: BlockNumber, block_hash: BlockHash },
+    #[error("Historical state of block #{block_number} is pruned, the oldest available block is #{oldest}")]
+    HistoryPruned { block_number: BlockNumber, oldest: BlockNumber },
+    #[error("The changeset of the indexed change at transition #{transition} is missing")]
+    ChangesetMissing { transition: TransitionId },
+    #[error("The state of block #{block_number} can't be proven, only the latest hashed state is")]
+    ProofUnavailable { block_number: BlockNumber },
+    #[error("Failed to read block #{block_number} from the freezer: {reason}")]
+    FreezerRead { block_number: BlockNumber, reason: String },
+    #[error("The state reader pool is shut down")]
+    StateReaderShutdown,
 }
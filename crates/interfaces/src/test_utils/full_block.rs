@@ -0,0 +1,303 @@
+//! A mock peer that serves canned headers and bodies.
+use crate::p2p::{
+    bodies::client::BodiesClient,
+    downloader::DownloadClient,
+    error::{PeerRequestResult, RequestError},
+    headers::client::{HeadersClient, HeadersRequest},
+};
+use reth_eth_wire::{BlockBody, BlockHeaders};
+use reth_primitives::{
+    BlockHashOrNumber, BlockNumber, Header, PeerId, SealedBlock, SealedHeader, H256,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// A misbehaviour of the peer for a single response of a [TestFullBlockClient].
+#[derive(Debug, Clone)]
+pub enum ResponseFault {
+    /// The response is delayed by the duration.
+    Delay(Duration),
+    /// Only the first `n` items of the response are returned.
+    Truncate(usize),
+    /// The items of the response don't match the requested ones. Headers don't link to their
+    /// parents and bodies don't match the roots of their headers.
+    Malicious,
+    /// The request fails with the error.
+    Error(RequestError),
+}
+
+/// A mock peer that serves the headers and bodies of a set of blocks.
+///
+/// Every request of a kind consumes the next fault that was queued for that kind, requests without
+/// a queued fault are answered honestly. Peers that are reported for bad messages are recorded, so
+/// retry and penalty logic of downloaders can be tested deterministically.
+#[derive(Debug, Default)]
+pub struct TestFullBlockClient {
+    /// The id of the peer that answers the requests.
+    peer_id: PeerId,
+    /// The maximum number of items of a response.
+    soft_limit: Option<usize>,
+    /// The known headers by hash.
+    headers: Mutex<HashMap<H256, Header>>,
+    /// The hashes of the known headers by number.
+    hashes: Mutex<HashMap<BlockNumber, H256>>,
+    /// The known bodies by block hash.
+    bodies: Mutex<HashMap<H256, BlockBody>>,
+    /// The faults of the next header responses.
+    header_faults: Mutex<VecDeque<ResponseFault>>,
+    /// The faults of the next body responses.
+    body_faults: Mutex<VecDeque<ResponseFault>>,
+    /// The number of header requests.
+    header_requests: AtomicU64,
+    /// The number of body requests.
+    body_requests: AtomicU64,
+    /// The peers that were reported for bad messages.
+    reported: Mutex<Vec<PeerId>>,
+}
+
+// === impl TestFullBlockClient ===
+
+impl TestFullBlockClient {
+    /// Creates a client that serves the blocks.
+    pub fn new(blocks: impl IntoIterator<Item = SealedBlock>) -> Self {
+        let client = Self::default();
+        client.extend(blocks);
+        client
+    }
+
+    /// Sets the id of the peer that answers the requests.
+    pub fn with_peer_id(mut self, peer_id: PeerId) -> Self {
+        self.peer_id = peer_id;
+        self
+    }
+
+    /// Limits the number of items of every response, like peers that don't serve more than a
+    /// fixed number of headers or bodies at once.
+    pub fn with_soft_limit(mut self, limit: usize) -> Self {
+        self.soft_limit = Some(limit);
+        self
+    }
+
+    /// Adds blocks to the served ones.
+    pub fn extend(&self, blocks: impl IntoIterator<Item = SealedBlock>) {
+        for SealedBlock { header, body, ommers } in blocks {
+            let hash = header.hash();
+            let ommers = ommers.into_iter().map(SealedHeader::unseal).collect();
+            self.hashes.lock().unwrap().insert(header.number, hash);
+            self.headers.lock().unwrap().insert(hash, header.unseal());
+            self.bodies.lock().unwrap().insert(hash, BlockBody { transactions: body, ommers });
+        }
+    }
+
+    /// Queues a fault for the next header response without a fault.
+    pub fn push_header_fault(&self, fault: ResponseFault) {
+        self.header_faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Queues a fault for the next body response without a fault.
+    pub fn push_body_fault(&self, fault: ResponseFault) {
+        self.body_faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Returns the number of header requests.
+    pub fn header_requests(&self) -> u64 {
+        self.header_requests.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of body requests.
+    pub fn body_requests(&self) -> u64 {
+        self.body_requests.load(Ordering::SeqCst)
+    }
+
+    /// Returns the peers that were reported for bad messages, in the order of the reports.
+    pub fn reported_peers(&self) -> Vec<PeerId> {
+        self.reported.lock().unwrap().clone()
+    }
+
+    /// Returns the headers of the request, without any fault.
+    fn honest_headers(&self, request: &HeadersRequest) -> Vec<Header> {
+        let headers = self.headers.lock().unwrap();
+        let hashes = self.hashes.lock().unwrap();
+
+        let start = match request.start {
+            BlockHashOrNumber::Hash(hash) => headers.get(&hash).map(|header| header.number),
+            BlockHashOrNumber::Number(number) => Some(number),
+        };
+        let Some(mut number) = start else { return vec![] };
+
+        // the direction maps to the `reverse` flag of the request, like it is sent to peers
+        let reverse = bool::from(request.direction);
+        let mut response = Vec::new();
+        while (response.len() as u64) < request.limit {
+            let Some(header) = hashes.get(&number).and_then(|hash| headers.get(hash)) else {
+                break
+            };
+            response.push(header.clone());
+
+            let next = if reverse { number.checked_sub(1) } else { number.checked_add(1) };
+            let Some(next) = next else { break };
+            number = next;
+        }
+        response
+    }
+
+    /// Returns the known bodies of the requested hashes, without any fault.
+    fn honest_bodies(&self, hashes: &[H256]) -> Vec<BlockBody> {
+        let bodies = self.bodies.lock().unwrap();
+        hashes.iter().filter_map(|hash| bodies.get(hash).cloned()).collect()
+    }
+
+    /// Applies the soft limit and the fault to a response.
+    ///
+    /// `corrupt` turns an item into one that doesn't match the request.
+    async fn respond<T>(
+        &self,
+        mut items: Vec<T>,
+        fault: Option<ResponseFault>,
+        corrupt: impl Fn(&mut T),
+    ) -> PeerRequestResult<Vec<T>> {
+        if let Some(limit) = self.soft_limit {
+            items.truncate(limit);
+        }
+
+        match fault {
+            Some(ResponseFault::Delay(delay)) => tokio::time::sleep(delay).await,
+            Some(ResponseFault::Truncate(len)) => items.truncate(len),
+            Some(ResponseFault::Malicious) => items.iter_mut().for_each(corrupt),
+            Some(ResponseFault::Error(err)) => return Err(err),
+            None => {}
+        }
+
+        Ok((self.peer_id, items).into())
+    }
+}
+
+impl DownloadClient for TestFullBlockClient {
+    fn report_bad_message(&self, peer_id: PeerId) {
+        self.reported.lock().unwrap().push(peer_id);
+    }
+}
+
+#[async_trait::async_trait]
+impl HeadersClient for TestFullBlockClient {
+    async fn get_headers(&self, request: HeadersRequest) -> PeerRequestResult<BlockHeaders> {
+        self.header_requests.fetch_add(1, Ordering::SeqCst);
+        let fault = self.header_faults.lock().unwrap().pop_front();
+        let headers = self.honest_headers(&request);
+
+        // the header links to itself instead of its parent
+        let (peer_id, headers) = self
+            .respond(headers, fault, |header: &mut Header| header.parent_hash = header.hash_slow())
+            .await?
+            .split();
+        Ok((peer_id, BlockHeaders(headers)).into())
+    }
+}
+
+#[async_trait::async_trait]
+impl BodiesClient for TestFullBlockClient {
+    async fn get_block_bodies(&self, hashes: Vec<H256>) -> PeerRequestResult<Vec<BlockBody>> {
+        self.body_requests.fetch_add(1, Ordering::SeqCst);
+        let fault = self.body_faults.lock().unwrap().pop_front();
+        let bodies = self.honest_bodies(&hashes);
+
+        // an additional ommer changes the ommers hash of every body
+        self.respond(bodies, fault, |body: &mut BlockBody| body.ommers.push(Header::default()))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::generators::random_block_range;
+    use reth_primitives::HeadersDirection;
+
+    fn headers_request(start: BlockHashOrNumber, limit: u64, reverse: bool) -> HeadersRequest {
+        HeadersRequest { start, limit, direction: HeadersDirection::new(reverse) }
+    }
+
+    #[tokio::test]
+    async fn serves_headers_and_bodies() {
+        let blocks = random_block_range(0..10, H256::zero(), 0..3);
+        let client = TestFullBlockClient::new(blocks.clone());
+
+        let request = headers_request(blocks[2].hash().into(), 3, false);
+        let headers = client.get_headers(request).await.unwrap().split().1 .0;
+        let expected = blocks[2..5].iter().map(|block| block.header.clone().unseal());
+        assert_eq!(headers, expected.collect::<Vec<_>>());
+
+        let request = headers_request(9u64.into(), 20, true);
+        let headers = client.get_headers(request).await.unwrap().split().1 .0;
+        let numbers = headers.iter().map(|header| header.number).collect::<Vec<_>>();
+        assert_eq!(numbers, (0..10).rev().collect::<Vec<_>>());
+
+        let hashes = blocks.iter().map(SealedBlock::hash).chain([H256::zero()]).collect();
+        let bodies = client.get_block_bodies(hashes).await.unwrap().split().1;
+        assert_eq!(bodies.len(), blocks.len());
+        for (body, block) in bodies.iter().zip(&blocks) {
+            assert_eq!(body.transactions, block.body);
+        }
+        assert_eq!(client.header_requests(), 2);
+        assert_eq!(client.body_requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn applies_faults_once() {
+        let blocks = random_block_range(0..10, H256::zero(), 1..3);
+        let client = TestFullBlockClient::new(blocks.clone()).with_soft_limit(5);
+        client.push_header_fault(ResponseFault::Truncate(2));
+        client.push_header_fault(ResponseFault::Error(RequestError::Timeout));
+        client.push_body_fault(ResponseFault::Malicious);
+
+        let request = headers_request(0u64.into(), 10, false);
+        let headers = client.get_headers(request.clone()).await.unwrap().split().1 .0;
+        assert_eq!(headers.len(), 2);
+        assert!(matches!(client.get_headers(request.clone()).await, Err(RequestError::Timeout)));
+        let headers = client.get_headers(request).await.unwrap().split().1 .0;
+        assert_eq!(headers.len(), 5);
+
+        let hashes = vec![blocks[0].hash()];
+        let body = client.get_block_bodies(hashes.clone()).await.unwrap().split().1;
+        assert_eq!(body[0].ommers.len(), blocks[0].ommers.len() + 1);
+        let body = client.get_block_bodies(hashes).await.unwrap().split().1;
+        assert_eq!(body[0].ommers.len(), blocks[0].ommers.len());
+    }
+
+    #[tokio::test]
+    async fn malicious_headers_do_not_link() {
+        let blocks = random_block_range(0..3, H256::zero(), 0..1);
+        let client = TestFullBlockClient::new(blocks.clone());
+        client.push_header_fault(ResponseFault::Malicious);
+
+        let request = headers_request(0u64.into(), 3, false);
+        let headers = client.get_headers(request).await.unwrap().split().1 .0;
+        for (header, block) in headers.iter().zip(&blocks) {
+            assert_ne!(header.parent_hash, block.parent_hash);
+        }
+    }
+
+    #[tokio::test]
+    async fn delays_responses() {
+        let client = TestFullBlockClient::default();
+        client.push_body_fault(ResponseFault::Delay(Duration::from_secs(10)));
+
+        let delayed = client.get_block_bodies(vec![]);
+        assert!(tokio::time::timeout(Duration::from_millis(10), delayed).await.is_err());
+        assert!(client.get_block_bodies(vec![]).await.unwrap().split().1.is_empty());
+    }
+
+    #[test]
+    fn records_reports() {
+        let peer_id = PeerId::from_low_u64_be(1);
+        let client = TestFullBlockClient::default().with_peer_id(peer_id);
+        client.report_bad_message(peer_id);
+        assert_eq!(client.reported_peers(), vec![peer_id]);
+    }
+}
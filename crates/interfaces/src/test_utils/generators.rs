@@ -8,6 +8,9 @@ use secp256k1::{KeyPair, Message as SecpMessage, Secp256k1, SecretKey};
 // TODO(onbjerg): Maybe we should split this off to its own crate, or move the helpers to the
 // relevant crates?
 
+pub mod chain;
+pub mod stress;
+
 /// Generates a range of random [SealedHeader]s.
 ///
 /// The parent hash of the first header
@@ -0,0 +1,376 @@
+//! Deterministic generators of structurally valid chains.
+//!
+//! Unlike [random_block_range](super::random_block_range), the generated blocks link up like the
+//! blocks of a real chain: every header is a valid child of its parent as far as gas limits, base
+//! fees and timestamps are concerned, the bodies match the roots of their headers and every block
+//! comes with the receipts of its transactions. The same [ChainConfig] always produces the same
+//! [ChainFixture].
+//!
+//! The transactions are plain transfers between random accounts, so the generated chain has no
+//! state that could be checked: the state roots are random.
+
+use super::sign_message;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use reth_primitives::{
+    proofs, Address, Bloom, Header, Receipt, SealedBlock, SealedHeader, Transaction,
+    TransactionKind, TransactionSigned, TxEip1559, TxType, H256,
+};
+use secp256k1::SecretKey;
+use std::ops::Range;
+
+/// The gas used by every transaction, which are plain transfers.
+pub const TRANSFER_GAS: u64 = 21_000;
+
+/// The seconds between two blocks.
+pub const BLOCK_TIME: u64 = 12;
+
+/// The maximum change of the gas limit relative to the gas limit of the parent.
+const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+
+/// The ratio of the gas limit to the gas target of EIP-1559.
+const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// The maximum change of the base fee relative to the base fee of the parent.
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+
+/// Configuration of [random_chain].
+#[derive(Debug, Clone)]
+pub struct ChainConfig {
+    /// The seed of all generated keys and data.
+    pub seed: u64,
+    /// The numbers of the generated blocks.
+    pub blocks: Range<u64>,
+    /// The parent hash of the first block.
+    pub parent_hash: H256,
+    /// The range the number of transactions of every block is sampled from.
+    pub txs_per_block: Range<u64>,
+    /// The gas limit of the first block, the gas limits of the following blocks drift from it.
+    pub gas_limit: u64,
+    /// The base fee of the first block, the base fees of the following blocks follow EIP-1559.
+    pub base_fee: u64,
+    /// The number of distinct senders.
+    pub senders: usize,
+    /// The forks as the number of the canonical block they branch off from and their length.
+    pub forks: Vec<(u64, u64)>,
+}
+
+// === impl ChainConfig ===
+
+impl ChainConfig {
+    /// Creates a config for the blocks with the given numbers.
+    ///
+    /// The first block has a gas limit of 30M and a base fee of 1 gwei.
+    pub fn new(blocks: Range<u64>) -> Self {
+        Self {
+            seed: 0,
+            blocks,
+            parent_hash: H256::zero(),
+            txs_per_block: 0..10,
+            gas_limit: 30_000_000,
+            base_fee: 1_000_000_000,
+            senders: 10,
+            forks: Vec::new(),
+        }
+    }
+
+    /// Sets the seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the parent hash of the first block.
+    pub fn with_parent_hash(mut self, parent_hash: H256) -> Self {
+        self.parent_hash = parent_hash;
+        self
+    }
+
+    /// Sets the range the number of transactions of every block is sampled from.
+    ///
+    /// The number is capped by the transfers that fit into the gas limit of the block.
+    pub fn with_txs_per_block(mut self, txs_per_block: Range<u64>) -> Self {
+        self.txs_per_block = txs_per_block;
+        self
+    }
+
+    /// Sets the gas limit of the first block.
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Sets the base fee of the first block.
+    pub fn with_base_fee(mut self, base_fee: u64) -> Self {
+        self.base_fee = base_fee;
+        self
+    }
+
+    /// Adds a fork of `len` blocks on top of the canonical block with the number `parent`.
+    pub fn with_fork(mut self, parent: u64, len: u64) -> Self {
+        self.forks.push((parent, len));
+        self
+    }
+}
+
+/// A generated block and the receipts of its transactions.
+#[derive(Debug, Clone)]
+pub struct ChainBlock {
+    /// The block.
+    pub block: SealedBlock,
+    /// The receipts of the transactions of the block.
+    pub receipts: Vec<Receipt>,
+}
+
+/// The blocks generated by [random_chain].
+#[derive(Debug, Clone)]
+pub struct ChainFixture {
+    /// The canonical blocks, on top of each other.
+    pub blocks: Vec<ChainBlock>,
+    /// The blocks of the configured forks in the order of [ChainConfig::forks], each on top of
+    /// its canonical parent.
+    pub forks: Vec<Vec<ChainBlock>>,
+}
+
+// === impl ChainFixture ===
+
+impl ChainFixture {
+    /// Returns the canonical headers.
+    pub fn headers(&self) -> Vec<SealedHeader> {
+        self.blocks.iter().map(|block| block.block.header.clone()).collect()
+    }
+
+    /// Returns the canonical blocks.
+    pub fn sealed_blocks(&self) -> Vec<SealedBlock> {
+        self.blocks.iter().map(|block| block.block.clone()).collect()
+    }
+}
+
+/// Generates the blocks of the given [ChainConfig].
+///
+/// The transactions of the forks are sent by other accounts than the ones of the canonical
+/// chain, so the nonces of all transactions are valid wherever the fork branches off.
+///
+/// # Panics
+///
+/// If a fork branches off from a block that is not generated.
+pub fn random_chain(config: &ChainConfig) -> ChainFixture {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let mut senders = random_senders(&mut rng, config.senders);
+    let mut blocks: Vec<ChainBlock> = Vec::with_capacity(config.blocks.clone().count());
+    for number in config.blocks.clone() {
+        let parent = blocks.last().map(|block| &block.block.header);
+        let block = random_chain_block(&mut rng, config, number, parent, &mut senders);
+        blocks.push(block);
+    }
+
+    let mut forks = Vec::with_capacity(config.forks.len());
+    for (parent, len) in config.forks.iter().copied() {
+        let parent = blocks
+            .iter()
+            .find(|block| block.block.number == parent)
+            .unwrap_or_else(|| panic!("fork parent {parent} is not generated"));
+        let mut senders = random_senders(&mut rng, config.senders);
+        let mut fork: Vec<ChainBlock> = Vec::with_capacity(len as usize);
+        for number in parent.block.number + 1..parent.block.number + 1 + len {
+            let parent = fork.last().unwrap_or(parent);
+            let block = random_chain_block(
+                &mut rng,
+                config,
+                number,
+                Some(&parent.block.header),
+                &mut senders,
+            );
+            fork.push(block);
+        }
+        forks.push(fork);
+    }
+
+    ChainFixture { blocks, forks }
+}
+
+/// Calculates the base fee of the child of a block with the given gas used, gas limit and base
+/// fee, as specified by EIP-1559.
+pub fn next_base_fee(gas_used: u64, gas_limit: u64, base_fee: u64) -> u64 {
+    let gas_target = gas_limit / ELASTICITY_MULTIPLIER;
+    if gas_used == gas_target {
+        return base_fee
+    }
+    let delta = |gas_delta: u64| {
+        (base_fee as u128 * gas_delta as u128 /
+            gas_target as u128 /
+            BASE_FEE_MAX_CHANGE_DENOMINATOR as u128) as u64
+    };
+    if gas_used > gas_target {
+        base_fee + delta(gas_used - gas_target).max(1)
+    } else {
+        base_fee.saturating_sub(delta(gas_target - gas_used))
+    }
+}
+
+/// Generates the keys of the senders, with their next nonce.
+fn random_senders(rng: &mut StdRng, count: usize) -> Vec<(H256, u64)> {
+    (0..count.max(1)).map(|_| (H256::from_slice(&SecretKey::new(rng).secret_bytes()), 0)).collect()
+}
+
+/// Generates a block on top of the parent, or the first block of the chain if there is none.
+fn random_chain_block(
+    rng: &mut StdRng,
+    config: &ChainConfig,
+    number: u64,
+    parent: Option<&SealedHeader>,
+    senders: &mut [(H256, u64)],
+) -> ChainBlock {
+    let (parent_hash, gas_limit, base_fee, timestamp) = match parent {
+        Some(parent) => {
+            // stay below the maximum change of the gas limit
+            let max_change = parent.gas_limit / GAS_LIMIT_BOUND_DIVISOR;
+            let change = rng.gen_range(0..max_change.max(1));
+            let gas_limit = if rng.gen::<bool>() {
+                parent.gas_limit + change
+            } else {
+                parent.gas_limit.saturating_sub(change).max(TRANSFER_GAS)
+            };
+            let base_fee = next_base_fee(
+                parent.gas_used,
+                parent.gas_limit,
+                parent.base_fee_per_gas.unwrap_or(config.base_fee),
+            );
+            (parent.hash(), gas_limit, base_fee, parent.timestamp + BLOCK_TIME)
+        }
+        None => (config.parent_hash, config.gas_limit, config.base_fee, number * BLOCK_TIME),
+    };
+
+    let max_txs = gas_limit / TRANSFER_GAS;
+    let tx_count = if config.txs_per_block.is_empty() {
+        0
+    } else {
+        rng.gen_range(config.txs_per_block.clone()).min(max_txs)
+    };
+
+    let mut body = Vec::with_capacity(tx_count as usize);
+    let mut receipts = Vec::with_capacity(tx_count as usize);
+    for index in 0..tx_count {
+        let (secret, nonce) = &mut senders[rng.gen_range(0..senders.len())];
+        let max_priority_fee_per_gas = rng.gen_range(0..1_000_000_000u128);
+        let tx = Transaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            nonce: *nonce,
+            gas_limit: TRANSFER_GAS,
+            max_fee_per_gas: base_fee as u128 * 2 + max_priority_fee_per_gas,
+            max_priority_fee_per_gas,
+            to: TransactionKind::Call(Address::random_using(rng)),
+            value: rng.gen_range(1..1_000_000_000_000_000_000u128),
+            ..Default::default()
+        });
+        let signature = sign_message(*secret, tx.signature_hash()).expect("valid secret");
+        body.push(TransactionSigned::from_transaction_and_signature(tx, signature));
+        receipts.push(Receipt {
+            tx_type: TxType::EIP1559,
+            success: true,
+            cumulative_gas_used: (index + 1) * TRANSFER_GAS,
+            bloom: Bloom::zero(),
+            logs: Vec::new(),
+        });
+        *nonce += 1;
+    }
+
+    let header = Header {
+        parent_hash,
+        ommers_hash: proofs::calculate_ommers_root(std::iter::empty::<&Header>()),
+        beneficiary: Address::random_using(rng),
+        state_root: H256::random_using(rng),
+        transactions_root: proofs::calculate_transaction_root(body.iter()),
+        receipts_root: proofs::calculate_receipt_root(receipts.iter()),
+        logs_bloom: Bloom::zero(),
+        number,
+        gas_limit,
+        gas_used: tx_count * TRANSFER_GAS,
+        timestamp,
+        mix_hash: H256::random_using(rng),
+        base_fee_per_gas: Some(base_fee),
+        ..Default::default()
+    };
+    ChainBlock { block: SealedBlock { header: header.seal(), body, ommers: Vec::new() }, receipts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(blocks: &[ChainBlock]) -> Vec<H256> {
+        blocks.iter().map(|block| block.block.hash()).collect()
+    }
+
+    #[test]
+    fn deterministic() {
+        let config = ChainConfig::new(0..20).with_fork(10, 3);
+        let fixture = random_chain(&config);
+        assert_eq!(fixture.blocks.len(), 20);
+        assert_eq!(hashes(&fixture.blocks), hashes(&random_chain(&config).blocks));
+        assert_ne!(
+            hashes(&fixture.blocks),
+            hashes(&random_chain(&config.clone().with_seed(1)).blocks)
+        );
+    }
+
+    #[test]
+    fn headers_are_linked() {
+        let config = ChainConfig::new(5..50).with_txs_per_block(0..50).with_gas_limit(500_000);
+        let fixture = random_chain(&config);
+        assert_eq!(fixture.blocks[0].block.parent_hash, H256::zero());
+
+        for pair in fixture.blocks.windows(2) {
+            let (parent, child) = (&pair[0].block.header, &pair[1].block.header);
+            assert_eq!(child.parent_hash, parent.hash());
+            assert_eq!(child.number, parent.number + 1);
+            assert!(child.timestamp > parent.timestamp);
+            assert!(child.gas_limit.abs_diff(parent.gas_limit) < parent.gas_limit / 1024);
+            assert_eq!(
+                child.base_fee_per_gas,
+                Some(next_base_fee(
+                    parent.gas_used,
+                    parent.gas_limit,
+                    parent.base_fee_per_gas.unwrap()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn bodies_and_receipts_match_headers() {
+        let fixture = random_chain(&ChainConfig::new(0..10).with_txs_per_block(1..20));
+        for ChainBlock { block, receipts } in &fixture.blocks {
+            assert_eq!(
+                block.transactions_root,
+                proofs::calculate_transaction_root(block.body.iter())
+            );
+            assert_eq!(block.receipts_root, proofs::calculate_receipt_root(receipts.iter()));
+            assert_eq!(block.body.len(), receipts.len());
+            assert_eq!(
+                block.gas_used,
+                receipts.last().map(|receipt| receipt.cumulative_gas_used).unwrap_or_default()
+            );
+            for tx in &block.body {
+                assert!(tx.recover_signer().is_some());
+                assert!(tx.max_fee_per_gas() >= block.base_fee_per_gas.unwrap() as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn forks() {
+        let fixture = random_chain(&ChainConfig::new(0..10).with_fork(4, 8).with_fork(9, 1));
+        assert_eq!(fixture.forks.len(), 2);
+
+        let fork = &fixture.forks[0];
+        assert_eq!(fork.len(), 8);
+        assert_eq!(fork[0].block.parent_hash, fixture.blocks[4].block.hash());
+        assert_eq!(fork[0].block.number, 5);
+        assert_ne!(fork[0].block.hash(), fixture.blocks[5].block.hash());
+        for pair in fork.windows(2) {
+            assert_eq!(pair[1].block.parent_hash, pair[0].block.hash());
+        }
+        assert_eq!(fixture.forks[1][0].block.number, 10);
+    }
+}
@@ -0,0 +1,372 @@
+//! Deterministic generators of adversarial blocks for benchmarks.
+//!
+//! Unlike the random generators, the generated blocks are meant to be executed: every
+//! [StressFixture] contains the pre-state with the funded senders and the crafted contracts that
+//! the transactions call. The same [StressConfig] always produces the same fixture.
+
+use super::sign_message;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use reth_primitives::{
+    keccak256, proofs, Account, Address, Bytes, Header, SealedBlock, StorageKey, Transaction,
+    TransactionKind, TransactionSigned, TxLegacy, H256, U256,
+};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use std::{collections::BTreeMap, ops::Range};
+
+/// The address of the crafted contract of the [StressProfile::DeepCalls] and
+/// [StressProfile::StorageHeavy] profiles.
+pub const STRESS_CONTRACT: Address = Address::repeat_byte(0xc0);
+
+/// The address without code that receives the transactions of the [StressProfile::MaxCalldata]
+/// profile.
+pub const CALLDATA_SINK: Address = Address::repeat_byte(0xda);
+
+/// The intrinsic gas of a transaction.
+const TX_BASE_GAS: u64 = 21_000;
+
+/// The gas of a non-zero calldata byte.
+const NON_ZERO_BYTE_GAS: u64 = 16;
+
+/// The worst case a benchmark should measure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StressProfile {
+    /// Transactions to an account without code that carry as much non-zero calldata as their gas
+    /// limit allows.
+    MaxCalldata,
+    /// Transactions to a contract that recursively calls itself `fanout` times on every level,
+    /// until the given `depth` is reached or the gas runs out.
+    DeepCalls {
+        /// The depth of the call tree.
+        depth: u8,
+        /// The number of calls on every level of the call tree.
+        fanout: u8,
+    },
+    /// Transactions to a contract with `prefilled_slots` non-empty storage slots, each writing
+    /// `slots_per_tx` consecutive slots.
+    ///
+    /// The slots of the first transactions overlap the prefilled slots and are overwritten,
+    /// later transactions create new slots. Transactions run out of gas if they write more slots
+    /// than their gas limit allows.
+    StorageHeavy {
+        /// The number of storage slots of the contract in the pre-state.
+        prefilled_slots: u64,
+        /// The number of slots written by every transaction.
+        slots_per_tx: u64,
+    },
+}
+
+/// Configuration of [stress_block_range].
+#[derive(Debug, Clone)]
+pub struct StressConfig {
+    /// The shape of the transactions.
+    pub profile: StressProfile,
+    /// The seed of all generated keys and data.
+    pub seed: u64,
+    /// The numbers of the generated blocks.
+    pub blocks: Range<u64>,
+    /// The number of transactions of every block.
+    pub txs_per_block: u64,
+    /// The gas limit of every block, evenly split between its transactions.
+    pub block_gas_limit: u64,
+    /// The number of distinct senders.
+    pub senders: usize,
+}
+
+// === impl StressConfig ===
+
+impl StressConfig {
+    /// Creates a config for a single block with mainnet's gas limit.
+    pub fn new(profile: StressProfile) -> Self {
+        Self {
+            profile,
+            seed: 0,
+            blocks: 1..2,
+            txs_per_block: 10,
+            block_gas_limit: 30_000_000,
+            senders: 10,
+        }
+    }
+
+    /// Sets the seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the numbers of the generated blocks.
+    pub fn with_blocks(mut self, blocks: Range<u64>) -> Self {
+        self.blocks = blocks;
+        self
+    }
+
+    /// Sets the number of transactions of every block.
+    pub fn with_txs_per_block(mut self, txs_per_block: u64) -> Self {
+        self.txs_per_block = txs_per_block;
+        self
+    }
+
+    /// Sets the gas limit of every block.
+    pub fn with_block_gas_limit(mut self, block_gas_limit: u64) -> Self {
+        self.block_gas_limit = block_gas_limit;
+        self
+    }
+
+    /// The gas limit of every transaction.
+    pub fn tx_gas_limit(&self) -> u64 {
+        self.block_gas_limit / self.txs_per_block.max(1)
+    }
+}
+
+/// An account of the pre-state of a [StressFixture].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StressAccount {
+    /// The account, its `bytecode_hash` matches `code`.
+    pub account: Account,
+    /// The code of the account.
+    pub code: Option<Bytes>,
+    /// The storage of the account.
+    pub storage: BTreeMap<StorageKey, U256>,
+}
+
+/// Blocks and the state they execute on.
+#[derive(Debug, Clone)]
+pub struct StressFixture {
+    /// The accounts that have to exist before the first block.
+    pub prestate: BTreeMap<Address, StressAccount>,
+    /// The generated blocks, on top of each other.
+    pub blocks: Vec<SealedBlock>,
+}
+
+/// Generates the blocks of the given [StressConfig] and the pre-state they execute on.
+///
+/// The headers are only valid as far as the transactions are concerned: `gas_used` is not known
+/// before execution and left as zero, and the state and receipts roots are not set.
+pub fn stress_block_range(config: &StressConfig) -> StressFixture {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let secp = Secp256k1::new();
+
+    let mut prestate = BTreeMap::new();
+    let mut senders = Vec::with_capacity(config.senders);
+    for _ in 0..config.senders.max(1) {
+        let secret = SecretKey::new(&mut rng);
+        let public = PublicKey::from_secret_key(&secp, &secret);
+        let address = Address::from_slice(&keccak256(&public.serialize_uncompressed()[1..])[12..]);
+        prestate.insert(
+            address,
+            StressAccount {
+                account: Account { nonce: 0, balance: U256::MAX >> 1, bytecode_hash: None },
+                ..Default::default()
+            },
+        );
+        senders.push((H256::from_slice(&secret.secret_bytes()), 0u64));
+    }
+
+    let contract = match config.profile {
+        StressProfile::MaxCalldata => None,
+        StressProfile::DeepCalls { fanout, .. } => Some((deep_calls_code(fanout), BTreeMap::new())),
+        StressProfile::StorageHeavy { prefilled_slots, .. } => {
+            let storage = (1..=prefilled_slots)
+                .map(|slot| (H256::from_low_u64_be(slot), U256::from(slot)))
+                .collect();
+            Some((storage_heavy_code(), storage))
+        }
+    };
+    if let Some((code, storage)) = contract {
+        let account =
+            Account { nonce: 1, balance: U256::zero(), bytecode_hash: Some(keccak256(&code)) };
+        prestate.insert(STRESS_CONTRACT, StressAccount { account, code: Some(code), storage });
+    }
+
+    let tx_gas_limit = config.tx_gas_limit();
+    let mut blocks: Vec<SealedBlock> = Vec::with_capacity(config.blocks.clone().count());
+    let mut tx_index = 0u64;
+    for number in config.blocks.clone() {
+        let mut body = Vec::with_capacity(config.txs_per_block as usize);
+        for _ in 0..config.txs_per_block {
+            let (to, input) = match config.profile {
+                StressProfile::MaxCalldata => {
+                    let len = tx_gas_limit.saturating_sub(TX_BASE_GAS) / NON_ZERO_BYTE_GAS;
+                    let input = (0..len).map(|_| rng.gen_range(1..=u8::MAX)).collect::<Vec<_>>();
+                    (CALLDATA_SINK, input.into())
+                }
+                StressProfile::DeepCalls { depth, .. } => {
+                    (STRESS_CONTRACT, encode_words(&[U256::from(depth)]))
+                }
+                StressProfile::StorageHeavy { slots_per_tx, .. } => {
+                    let start = 1 + tx_index * slots_per_tx;
+                    (STRESS_CONTRACT, encode_words(&[U256::from(start), U256::from(slots_per_tx)]))
+                }
+            };
+
+            let (secret, nonce) = &mut senders[tx_index as usize % senders.len()];
+            let tx = Transaction::Legacy(TxLegacy {
+                chain_id: Some(1),
+                nonce: *nonce,
+                gas_price: 1,
+                gas_limit: tx_gas_limit,
+                to: TransactionKind::Call(to),
+                value: 0,
+                input,
+            });
+            let signature = sign_message(*secret, tx.signature_hash()).expect("valid secret");
+            body.push(TransactionSigned::from_transaction_and_signature(tx, signature));
+
+            *nonce += 1;
+            tx_index += 1;
+        }
+
+        let header = Header {
+            parent_hash: blocks.last().map(|block| block.hash()).unwrap_or_default(),
+            number,
+            timestamp: number * 12,
+            gas_limit: config.block_gas_limit,
+            transactions_root: proofs::calculate_transaction_root(body.iter()),
+            ommers_hash: proofs::calculate_ommers_root(std::iter::empty::<&Header>()),
+            ..Default::default()
+        };
+        blocks.push(SealedBlock { header: header.seal(), body, ommers: Vec::new() });
+    }
+
+    StressFixture { prestate, blocks }
+}
+
+/// Encodes the words as calldata.
+fn encode_words(words: &[U256]) -> Bytes {
+    let mut input = vec![0u8; words.len() * 32];
+    for (word, buf) in words.iter().zip(input.chunks_mut(32)) {
+        word.to_big_endian(buf);
+    }
+    input.into()
+}
+
+/// Code that reads the remaining depth from the first calldata word and, unless it's zero, calls
+/// itself `fanout` times with the decremented depth.
+fn deep_calls_code(fanout: u8) -> Bytes {
+    let mut code = vec![
+        0x60, 0x00, // PUSH1 0
+        0x35, // CALLDATALOAD
+        0x80, // DUP1
+        0x15, // ISZERO
+        0x61, 0x00, 0x00, // PUSH2 <end>
+        0x57, // JUMPI
+        0x60, 0x01, // PUSH1 1
+        0x90, // SWAP1
+        0x03, // SUB
+        0x60, 0x00, // PUSH1 0
+        0x52, // MSTORE
+    ];
+    for _ in 0..fanout {
+        code.extend_from_slice(&[
+            0x60, 0x00, // PUSH1 0 (ret size)
+            0x60, 0x00, // PUSH1 0 (ret offset)
+            0x60, 0x20, // PUSH1 32 (args size)
+            0x60, 0x00, // PUSH1 0 (args offset)
+            0x60, 0x00, // PUSH1 0 (value)
+            0x30, // ADDRESS
+            0x5a, // GAS
+            0xf1, // CALL
+            0x50, // POP
+        ]);
+    }
+    let end = (code.len() as u16).to_be_bytes();
+    code[6..8].copy_from_slice(&end);
+    code.extend_from_slice(&[
+        0x5b, // JUMPDEST
+        0x00, // STOP
+    ]);
+    code.into()
+}
+
+/// Code that writes the remaining gas to the slots `[start, start + count)`, with `start` and
+/// `count` read from the first two calldata words.
+fn storage_heavy_code() -> Bytes {
+    vec![
+        0x60, 0x00, // PUSH1 0
+        0x35, // CALLDATALOAD
+        0x60, 0x20, // PUSH1 32
+        0x35, // CALLDATALOAD
+        0x5b, // JUMPDEST (loop)
+        0x80, // DUP1
+        0x15, // ISZERO
+        0x60, 0x1b, // PUSH1 <end>
+        0x57, // JUMPI
+        0x60, 0x01, // PUSH1 1
+        0x90, // SWAP1
+        0x03, // SUB
+        0x90, // SWAP1
+        0x5a, // GAS
+        0x81, // DUP2
+        0x55, // SSTORE
+        0x60, 0x01, // PUSH1 1
+        0x01, // ADD
+        0x90, // SWAP1
+        0x60, 0x06, // PUSH1 <loop>
+        0x56, // JUMP
+        0x5b, // JUMPDEST (end)
+        0x00, // STOP
+    ]
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUMPDEST: u8 = 0x5b;
+
+    #[test]
+    fn deterministic() {
+        let config = StressConfig::new(StressProfile::StorageHeavy {
+            prefilled_slots: 100,
+            slots_per_tx: 10,
+        })
+        .with_blocks(1..4);
+        let hashes = |fixture: StressFixture| {
+            fixture.blocks.iter().map(|block| block.hash()).collect::<Vec<_>>()
+        };
+
+        let fixture = stress_block_range(&config);
+        assert_eq!(fixture.blocks.len(), 3);
+        assert_eq!(fixture.prestate[&STRESS_CONTRACT].storage.len(), 100);
+        assert_eq!(hashes(fixture), hashes(stress_block_range(&config)));
+        assert_ne!(
+            hashes(stress_block_range(&config)),
+            hashes(stress_block_range(&config.clone().with_seed(1)))
+        );
+    }
+
+    #[test]
+    fn senders_are_funded() {
+        let config = StressConfig::new(StressProfile::DeepCalls { depth: 8, fanout: 2 });
+        let fixture = stress_block_range(&config);
+        for tx in fixture.blocks.iter().flat_map(|block| block.body.iter()) {
+            let sender = tx.recover_signer().unwrap();
+            assert!(fixture.prestate[&sender].account.balance > U256::zero());
+        }
+    }
+
+    #[test]
+    fn max_calldata() {
+        let config = StressConfig::new(StressProfile::MaxCalldata).with_txs_per_block(1);
+        let fixture = stress_block_range(&config);
+        let tx = &fixture.blocks[0].body[0];
+        let input = tx.transaction.input();
+        assert_eq!(input.len() as u64, (config.block_gas_limit - TX_BASE_GAS) / NON_ZERO_BYTE_GAS);
+        assert!(input.iter().all(|byte| *byte != 0));
+        assert!(!fixture.prestate.contains_key(&STRESS_CONTRACT));
+    }
+
+    #[test]
+    fn jump_destinations() {
+        let code = storage_heavy_code();
+        assert_eq!(code[6], JUMPDEST);
+        assert_eq!(code[code[10] as usize], JUMPDEST);
+
+        for fanout in [0, 1, 3, 20] {
+            let code = deep_calls_code(fanout);
+            let end = u16::from_be_bytes([code[6], code[7]]) as usize;
+            assert_eq!(code[end], JUMPDEST);
+            assert_eq!(end, code.len() - 2);
+        }
+    }
+}
@@ -13,7 +13,7 @@ use crate::{
 use futures::{Future, FutureExt, Stream};
 use reth_eth_wire::BlockHeaders;
 use reth_primitives::{
-    BlockNumber, Header, HeadersDirection, PeerId, SealedBlock, SealedHeader, H256,
+    BlockNumber, Header, HeadersDirection, PeerId, SealedBlock, SealedHeader, H256, U256,
 };
 use reth_rpc_types::engine::ForkchoiceState;
 use std::{
@@ -260,6 +260,18 @@ impl Consensus for TestConsensus {
         }
     }
 
+    fn validate_header_with_total_difficulty(
+        &self,
+        _header: &SealedHeader,
+        _parent_total_difficulty: U256,
+    ) -> Result<(), consensus::Error> {
+        if self.fail_validation() {
+            Err(consensus::Error::BaseFeeMissing)
+        } else {
+            Ok(())
+        }
+    }
+
     fn pre_validate_block(&self, _block: &SealedBlock) -> Result<(), consensus::Error> {
         if self.fail_validation() {
             Err(consensus::Error::BaseFeeMissing)
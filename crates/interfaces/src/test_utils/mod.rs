@@ -1,8 +1,12 @@
 mod bodies;
+mod full_block;
 mod headers;
+mod snap;
 
 /// Generators for different data structures like block headers, block bodies and ranges of those.
 pub mod generators;
 
 pub use bodies::*;
+pub use full_block::*;
 pub use headers::*;
+pub use snap::*;
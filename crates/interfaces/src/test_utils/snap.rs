@@ -0,0 +1,436 @@
+//! A mock peer that serves the state of blocks over the `snap` protocol.
+use super::ResponseFault;
+use crate::p2p::{
+    downloader::DownloadClient,
+    error::{PeerRequestResult, RequestError},
+    snap::client::{
+        AccountRange, ByteCodes, GetAccountRange, GetByteCodes, GetStorageRanges, GetTrieNodes,
+        SnapClient, StorageRanges, TrieNodes,
+    },
+};
+use reth_eth_wire::types::snap::{AccountData, StorageData};
+use reth_primitives::{
+    keccak256,
+    proofs::TrieAccount,
+    trie::{decode_compact, Trie, TrieNode},
+    Account, Bytes, PeerId, H256, U256,
+};
+use reth_rlp::Encodable;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// The hashed state of a block: the accounts and their slots by the hashes of the addresses and
+/// keys.
+pub type HashedState = BTreeMap<H256, (Account, BTreeMap<H256, U256>)>;
+
+/// The state of a root that is served by a [TestSnapClient], along with its tries.
+#[derive(Debug)]
+struct ServedState {
+    /// The accounts by the hash of their address.
+    accounts: BTreeMap<H256, TrieAccount>,
+    /// The state trie.
+    account_trie: Trie,
+    /// The non-zero slots of the accounts.
+    storages: HashMap<H256, BTreeMap<H256, U256>>,
+    /// The storage tries of the accounts with slots.
+    storage_tries: HashMap<H256, Trie>,
+}
+
+/// A mock peer of the `snap` protocol that serves the state of a set of roots.
+///
+/// Every response is limited to the soft limit of items instead of the requested number of bytes.
+/// States can be removed again, like peers that only serve the state of recent blocks. Like the
+/// [TestFullBlockClient](super::TestFullBlockClient), every response consumes the next queued
+/// fault and reported peers are recorded.
+#[derive(Debug, Default)]
+pub struct TestSnapClient {
+    /// The id of the peer that answers the requests.
+    peer_id: PeerId,
+    /// The maximum number of items of a response.
+    soft_limit: Option<usize>,
+    /// The served states by their root.
+    states: Mutex<HashMap<H256, ServedState>>,
+    /// The known codes by hash.
+    codes: Mutex<HashMap<H256, Bytes>>,
+    /// The faults of the next responses.
+    faults: Mutex<VecDeque<ResponseFault>>,
+    /// The peers that were reported for bad messages.
+    reported: Mutex<Vec<PeerId>>,
+}
+
+// === impl TestSnapClient ===
+
+impl TestSnapClient {
+    /// Sets the id of the peer that answers the requests.
+    pub fn with_peer_id(mut self, peer_id: PeerId) -> Self {
+        self.peer_id = peer_id;
+        self
+    }
+
+    /// Limits the number of accounts, slots, codes or trie nodes of every response.
+    pub fn with_soft_limit(mut self, limit: usize) -> Self {
+        self.soft_limit = Some(limit);
+        self
+    }
+
+    /// Serves the state and returns its root.
+    ///
+    /// Zero slots are not part of the state.
+    pub fn insert_state(&self, state: &HashedState) -> H256 {
+        let mut accounts = BTreeMap::new();
+        let mut storages = HashMap::new();
+        let mut storage_tries = HashMap::new();
+        for (hash, (account, slots)) in state {
+            let slots = slots
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(key, value)| (*key, *value))
+                .collect::<BTreeMap<_, _>>();
+            let storage_trie =
+                Trie::from_entries(slots.iter().map(|(key, value)| (key, rlp(value))));
+            accounts.insert(*hash, TrieAccount::new(*account, storage_trie.root_hash()));
+            if !slots.is_empty() {
+                storages.insert(*hash, slots);
+                storage_tries.insert(*hash, storage_trie);
+            }
+        }
+
+        let account_trie =
+            Trie::from_entries(accounts.iter().map(|(hash, account)| (hash, rlp(account))));
+        let root = account_trie.root_hash();
+        self.states
+            .lock()
+            .unwrap()
+            .insert(root, ServedState { accounts, account_trie, storages, storage_tries });
+        root
+    }
+
+    /// Stops serving the state of the root.
+    pub fn remove_state(&self, root: H256) {
+        self.states.lock().unwrap().remove(&root);
+    }
+
+    /// Serves the code and returns its hash.
+    pub fn insert_code(&self, code: Bytes) -> H256 {
+        let hash = keccak256(&code);
+        self.codes.lock().unwrap().insert(hash, code);
+        hash
+    }
+
+    /// Queues a fault for the next response without a fault.
+    pub fn push_fault(&self, fault: ResponseFault) {
+        self.faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Returns the peers that were reported for bad messages, in the order of the reports.
+    pub fn reported_peers(&self) -> Vec<PeerId> {
+        self.reported.lock().unwrap().clone()
+    }
+
+    fn limit(&self) -> usize {
+        self.soft_limit.unwrap_or(usize::MAX)
+    }
+
+    /// Returns the accounts of the request with their proof, without any fault.
+    fn honest_account_range(&self, request: &GetAccountRange) -> AccountRange {
+        let states = self.states.lock().unwrap();
+        let Some(state) = states.get(&request.root_hash) else {
+            return AccountRange { request_id: request.request_id, accounts: vec![], proof: vec![] }
+        };
+
+        let accounts = state
+            .accounts
+            .range(request.starting_hash..)
+            .take_while(|(hash, _)| **hash <= request.limit_hash)
+            .take(self.limit())
+            .map(|(hash, account)| AccountData { hash: *hash, account: *account })
+            .collect::<Vec<_>>();
+        let proof = range_proof(
+            &state.account_trie,
+            request.starting_hash,
+            accounts.last().map(|account| account.hash),
+        );
+        AccountRange { request_id: request.request_id, accounts, proof }
+    }
+
+    /// Returns the slots of the request, without any fault.
+    ///
+    /// The last list of slots comes with a proof if it's incomplete or doesn't start at the first
+    /// slot.
+    fn honest_storage_ranges(&self, request: &GetStorageRanges) -> StorageRanges {
+        let mut response =
+            StorageRanges { request_id: request.request_id, slots: vec![], proof: vec![] };
+        let states = self.states.lock().unwrap();
+        let Some(state) = states.get(&request.root_hash) else { return response };
+
+        let mut budget = self.limit();
+        for (index, account) in request.account_hashes.iter().enumerate() {
+            let (origin, limit) = if index == 0 {
+                (
+                    to_hash(&request.starting_hash).unwrap_or_default(),
+                    to_hash(&request.limit_hash).unwrap_or(H256::repeat_byte(0xff)),
+                )
+            } else {
+                (H256::zero(), H256::repeat_byte(0xff))
+            };
+
+            let mut slots = Vec::new();
+            let mut complete = true;
+            let storage = state
+                .storages
+                .get(account)
+                .into_iter()
+                .flat_map(|slots| slots.range(origin..).take_while(|(key, _)| **key <= limit));
+            for (key, value) in storage {
+                if budget == 0 {
+                    complete = false;
+                    break
+                }
+                budget -= 1;
+                slots.push(StorageData { hash: *key, data: rlp(value).into() });
+            }
+
+            if !complete || !origin.is_zero() {
+                if let Some(trie) = state.storage_tries.get(account) {
+                    response.proof = range_proof(trie, origin, slots.last().map(|slot| slot.hash));
+                }
+            }
+            response.slots.push(slots);
+            if !complete || budget == 0 {
+                break
+            }
+        }
+        response
+    }
+
+    /// Returns the known codes of the request, without any fault.
+    fn honest_byte_codes(&self, request: &GetByteCodes) -> ByteCodes {
+        let codes = self.codes.lock().unwrap();
+        let codes = request
+            .hashes
+            .iter()
+            .filter_map(|hash| codes.get(hash).cloned())
+            .take(self.limit())
+            .collect();
+        ByteCodes { request_id: request.request_id, codes }
+    }
+
+    /// Returns the trie nodes of the request up to the first unknown one, without any fault.
+    fn honest_trie_nodes(&self, request: &GetTrieNodes) -> TrieNodes {
+        let mut response = TrieNodes { request_id: request.request_id, nodes: vec![] };
+        let states = self.states.lock().unwrap();
+        let Some(state) = states.get(&request.root_hash) else { return response };
+
+        let node_at = |trie: &Trie, path: &Bytes| {
+            let (path, _) = decode_compact(path)?;
+            trie.node_at(&path)
+                .filter(|node| !matches!(node, TrieNode::Empty))
+                .map(|node| Bytes::from(node.encode()))
+        };
+
+        for paths in &request.paths {
+            let nodes = match paths.as_slice() {
+                [path] => vec![node_at(&state.account_trie, path)],
+                [account, paths @ ..] => {
+                    let trie =
+                        to_hash(account).and_then(|account| state.storage_tries.get(&account));
+                    paths.iter().map(|path| trie.and_then(|trie| node_at(trie, path))).collect()
+                }
+                [] => vec![None],
+            };
+            for node in nodes {
+                let Some(node) = node else { return response };
+                if response.nodes.len() == self.limit() {
+                    return response
+                }
+                response.nodes.push(node);
+            }
+        }
+        response
+    }
+
+    /// Waits for or fails with the next fault, returns the fault if it alters the response.
+    async fn next_fault(&self) -> Result<Option<ResponseFault>, RequestError> {
+        let fault = self.faults.lock().unwrap().pop_front();
+        match fault {
+            Some(ResponseFault::Delay(delay)) => {
+                tokio::time::sleep(delay).await;
+                Ok(None)
+            }
+            Some(ResponseFault::Error(err)) => Err(err),
+            fault => Ok(fault),
+        }
+    }
+}
+
+/// Truncates the items or corrupts the first one.
+fn alter<T>(items: &mut Vec<T>, fault: Option<ResponseFault>, corrupt: impl FnOnce(&mut T)) {
+    match fault {
+        Some(ResponseFault::Truncate(len)) => items.truncate(len),
+        Some(ResponseFault::Malicious) => items.first_mut().into_iter().for_each(corrupt),
+        _ => {}
+    }
+}
+
+/// Returns the proof of the range from `origin` up to the last key.
+fn range_proof(trie: &Trie, origin: H256, last: Option<H256>) -> Vec<Bytes> {
+    let mut proof = trie.proof(origin.as_bytes());
+    if let Some(last) = last {
+        proof.extend(trie.proof(last.as_bytes()));
+    }
+    proof.sort();
+    proof.dedup();
+    proof
+}
+
+fn to_hash(bytes: &[u8]) -> Option<H256> {
+    (bytes.len() == 32).then(|| H256::from_slice(bytes))
+}
+
+fn rlp(value: &impl Encodable) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
+
+impl DownloadClient for TestSnapClient {
+    fn report_bad_message(&self, peer_id: PeerId) {
+        self.reported.lock().unwrap().push(peer_id);
+    }
+}
+
+#[async_trait::async_trait]
+impl SnapClient for TestSnapClient {
+    async fn get_account_range(&self, request: GetAccountRange) -> PeerRequestResult<AccountRange> {
+        let fault = self.next_fault().await?;
+        let mut response = self.honest_account_range(&request);
+        alter(&mut response.accounts, fault, |account| account.account.balance += U256::from(1));
+        Ok((self.peer_id, response).into())
+    }
+
+    async fn get_storage_ranges(
+        &self,
+        request: GetStorageRanges,
+    ) -> PeerRequestResult<StorageRanges> {
+        let fault = self.next_fault().await?;
+        let mut response = self.honest_storage_ranges(&request);
+        alter(&mut response.slots, fault, |slots| {
+            slots.iter_mut().for_each(|slot| slot.data = rlp(&U256::MAX).into())
+        });
+        Ok((self.peer_id, response).into())
+    }
+
+    async fn get_byte_codes(&self, request: GetByteCodes) -> PeerRequestResult<ByteCodes> {
+        let fault = self.next_fault().await?;
+        let mut response = self.honest_byte_codes(&request);
+        alter(&mut response.codes, fault, |code| *code = Bytes::from(vec![0xfe]));
+        Ok((self.peer_id, response).into())
+    }
+
+    async fn get_trie_nodes(&self, request: GetTrieNodes) -> PeerRequestResult<TrieNodes> {
+        let fault = self.next_fault().await?;
+        let mut response = self.honest_trie_nodes(&request);
+        alter(&mut response.nodes, fault, |node| *node = Bytes::from(TrieNode::Empty.encode()));
+        Ok((self.peer_id, response).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::trie::{encode_compact, verify_range_proof};
+
+    fn state(accounts: u64, slots: u64) -> HashedState {
+        (0..accounts)
+            .map(|i| {
+                let account = Account { nonce: i, balance: U256::from(i), bytecode_hash: None };
+                let slots = (1..=slots)
+                    .map(|slot| (keccak256(H256::from_low_u64_be(slot)), U256::from(slot)))
+                    .collect();
+                (keccak256(H256::from_low_u64_be(i)), (account, slots))
+            })
+            .collect()
+    }
+
+    fn leaves(accounts: &[AccountData]) -> Vec<(H256, Vec<u8>)> {
+        accounts.iter().map(|account| (account.hash, rlp(&account.account))).collect()
+    }
+
+    #[tokio::test]
+    async fn serves_proven_ranges() {
+        let client = TestSnapClient::default().with_soft_limit(7);
+        let state = state(20, 10);
+        let root = client.insert_state(&state);
+
+        let mut origin = H256::zero();
+        let mut accounts = Vec::new();
+        loop {
+            let request = GetAccountRange {
+                request_id: 1,
+                root_hash: root,
+                starting_hash: origin,
+                limit_hash: H256::repeat_byte(0xff),
+                response_bytes: 0,
+            };
+            let range = client.get_account_range(request).await.unwrap().split().1;
+            let has_more =
+                verify_range_proof(root, origin, &leaves(&range.accounts), &range.proof).unwrap();
+            accounts.extend(range.accounts);
+            if !has_more {
+                break
+            }
+            origin = H256::from_uint(&(accounts.last().unwrap().hash.into_uint() + 1));
+        }
+        let hashes = accounts.iter().map(|account| account.hash).collect::<Vec<_>>();
+        assert_eq!(hashes, state.keys().copied().collect::<Vec<_>>());
+
+        // the slots of the first account are cut off by the soft limit, with a proof
+        let hashes = state.keys().take(2).copied().collect::<Vec<_>>();
+        let request = GetStorageRanges {
+            request_id: 2,
+            root_hash: root,
+            account_hashes: hashes,
+            starting_hash: Bytes::default(),
+            limit_hash: Bytes::default(),
+            response_bytes: 0,
+        };
+        let ranges = client.get_storage_ranges(request.clone()).await.unwrap().split().1;
+        assert_eq!(ranges.slots.len(), 1);
+        assert_eq!(ranges.slots[0].len(), 7);
+        assert!(!ranges.proof.is_empty());
+
+        // unknown roots are not served
+        client.remove_state(root);
+        let ranges = client.get_storage_ranges(request).await.unwrap().split().1;
+        assert!(ranges.slots.is_empty() && ranges.proof.is_empty());
+    }
+
+    #[tokio::test]
+    async fn serves_trie_nodes_and_codes() {
+        let client = TestSnapClient::default();
+        let root = client.insert_state(&state(50, 3));
+        let code = client.insert_code(Bytes::from(vec![0x60, 0x00]));
+
+        let request = GetTrieNodes {
+            request_id: 1,
+            root_hash: root,
+            paths: vec![vec![encode_compact(&[], false).into()], vec![vec![0x40].into()]],
+            response_bytes: 0,
+        };
+        let nodes = client.get_trie_nodes(request).await.unwrap().split().1.nodes;
+        // the empty path is the root and the second path is not a valid path
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(keccak256(&nodes[0]), root);
+
+        let request =
+            GetByteCodes { request_id: 2, hashes: vec![H256::zero(), code], response_bytes: 0 };
+        let codes = client.get_byte_codes(request).await.unwrap().split().1.codes;
+        assert_eq!(codes, vec![Bytes::from(vec![0x60, 0x00])]);
+
+        client.push_fault(ResponseFault::Error(RequestError::Timeout));
+        let request = GetByteCodes { request_id: 3, hashes: vec![code], response_bytes: 0 };
+        assert!(matches!(client.get_byte_codes(request).await, Err(RequestError::Timeout)));
+    }
+}
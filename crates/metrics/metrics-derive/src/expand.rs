@@ -0,0 +1,479 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Attribute, Data, DeriveInput, Error, Field, Lit, LitStr, Meta, NestedMeta, Result};
+
+/// The separator between the scope and the name of a metric.
+const SEPARATOR: &str = ".";
+
+pub(crate) fn derive(node: &DeriveInput) -> Result<TokenStream> {
+    let ty = &node.ident;
+    let ident_name = ty.to_string();
+
+    let scope = parse_scope(node)?;
+    let fields = parse_metric_fields(node)?;
+    let metrics = fields.iter().filter_map(MetricField::metric).collect::<Vec<_>>();
+
+    // dynamic scopes are passed to every generated function
+    let scope_param = match scope {
+        MetricScope::Static(_) => quote! {},
+        MetricScope::Dynamic => quote! { scope: &str, },
+    };
+
+    let register_fields = fields.iter().map(|field| match field {
+        MetricField::Included(metric) => {
+            let field_name = &metric.field.ident;
+            let name = metric.name(&scope);
+            let register = metric.kind.register_macro();
+            quote! {
+                #field_name: #register(#name),
+            }
+        }
+        MetricField::Skipped(field) => {
+            let field_name = &field.ident;
+            quote! {
+                #field_name: Default::default(),
+            }
+        }
+    });
+
+    let register_fields_with_labels = fields.iter().map(|field| match field {
+        MetricField::Included(metric) => {
+            let field_name = &metric.field.ident;
+            let name = metric.name(&scope);
+            let register = metric.kind.register_macro();
+            quote! {
+                #field_name: #register(#name, labels.clone()),
+            }
+        }
+        MetricField::Skipped(field) => {
+            let field_name = &field.ident;
+            quote! {
+                #field_name: Default::default(),
+            }
+        }
+    });
+
+    let labeled_fields = metrics.iter().filter(|metric| !metric.labels.is_empty()).map(|metric| {
+        let field_name = metric.field.ident.as_ref().expect("named field");
+        let method = format_ident!("{field_name}_with_labels");
+        let name = metric.name(&scope);
+        let register = metric.kind.register_macro();
+        let ty = metric.kind.ty();
+        let keys = metric.labels.iter().map(LitStr::value).collect::<Vec<_>>();
+        let values = keys.iter().map(|key| format_ident!("{key}")).collect::<Vec<_>>();
+        let doc = format!(
+            "Returns the `{}` metric with the given values for the `{}` labels.",
+            metric.display_name(&scope),
+            keys.join("`, `")
+        );
+        quote! {
+            #[doc = #doc]
+            pub fn #method(&self, #scope_param #(#values: impl Into<metrics::SharedString>),*) -> #ty {
+                #register(#name, #(#keys => #values.into()),*)
+            }
+        }
+    });
+
+    let describe_fields = metrics.iter().map(|metric| {
+        let name = metric.name(&scope);
+        let description = &metric.description;
+        let describe = metric.kind.describe_macro();
+        quote! {
+            #describe(#name, #description);
+        }
+    });
+
+    let constructor = match scope {
+        MetricScope::Static(_) => quote! {
+            impl Default for #ty {
+                fn default() -> Self {
+                    Self {
+                        #(#register_fields)*
+                    }
+                }
+            }
+        },
+        MetricScope::Dynamic => quote! {
+            impl #ty {
+                /// Create new instance of metrics with the given scope.
+                pub fn new(scope: &str) -> Self {
+                    Self {
+                        #(#register_fields)*
+                    }
+                }
+            }
+        },
+    };
+
+    Ok(quote! {
+        #constructor
+
+        impl #ty {
+            /// Create new instance of metrics with provided labels.
+            pub fn new_with_labels(#scope_param labels: impl metrics::IntoLabels + Clone) -> Self {
+                Self {
+                    #(#register_fields_with_labels)*
+                }
+            }
+
+            #(#labeled_fields)*
+
+            /// Describe all exposed metrics. Internally calls `describe_*` macros from
+            /// the metrics crate according to the metric type.
+            /// Ref: <https://docs.rs/metrics/0.20.1/metrics/index.html#macros>
+            pub fn describe(#scope_param) {
+                #(#describe_fields)*
+            }
+        }
+
+        impl std::fmt::Debug for #ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(#ident_name).finish()
+            }
+        }
+    })
+}
+
+/// The scope of the metrics of a struct.
+enum MetricScope {
+    /// The scope set with `#[metrics(scope = "..")]`.
+    Static(LitStr),
+    /// The scope is passed to the generated functions, see `#[metrics(dynamic = true)]`.
+    Dynamic,
+}
+
+/// The supported metric types.
+enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricKind {
+    fn from_field(field: &Field) -> Result<Self> {
+        let ty = match &field.ty {
+            syn::Type::Path(ty) => ty,
+            _ => return Err(Error::new_spanned(&field.ty, "Unsupported metric type")),
+        };
+        let kind = match ty.path.segments.last().map(|segment| segment.ident.to_string()) {
+            Some(ident) if ident == "Counter" => MetricKind::Counter,
+            Some(ident) if ident == "Gauge" => MetricKind::Gauge,
+            Some(ident) if ident == "Histogram" => MetricKind::Histogram,
+            _ => return Err(Error::new_spanned(&field.ty, "Unsupported metric type")),
+        };
+        Ok(kind)
+    }
+
+    fn register_macro(&self) -> TokenStream {
+        match self {
+            MetricKind::Counter => quote! { metrics::register_counter! },
+            MetricKind::Gauge => quote! { metrics::register_gauge! },
+            MetricKind::Histogram => quote! { metrics::register_histogram! },
+        }
+    }
+
+    fn ty(&self) -> TokenStream {
+        match self {
+            MetricKind::Counter => quote! { metrics::Counter },
+            MetricKind::Gauge => quote! { metrics::Gauge },
+            MetricKind::Histogram => quote! { metrics::Histogram },
+        }
+    }
+
+    fn describe_macro(&self) -> TokenStream {
+        match self {
+            MetricKind::Counter => quote! { metrics::describe_counter! },
+            MetricKind::Gauge => quote! { metrics::describe_gauge! },
+            MetricKind::Histogram => quote! { metrics::describe_histogram! },
+        }
+    }
+}
+
+/// A parsed field of the struct.
+enum MetricField<'a> {
+    /// A field that is registered as a metric.
+    Included(Metric<'a>),
+    /// A field marked with `#[metric(skip)]`, initialized with its [Default].
+    Skipped(&'a Field),
+}
+
+impl<'a> MetricField<'a> {
+    fn metric(&self) -> Option<&Metric<'a>> {
+        match self {
+            MetricField::Included(metric) => Some(metric),
+            MetricField::Skipped(_) => None,
+        }
+    }
+}
+
+/// A parsed metric field.
+struct Metric<'a> {
+    field: &'a Field,
+    kind: MetricKind,
+    /// The name of the metric without the scope.
+    name: String,
+    description: String,
+    /// The label keys of the metric, see `#[metric(labels(..))]`.
+    labels: Vec<LitStr>,
+}
+
+impl Metric<'_> {
+    /// Returns an expression evaluating to the full name of the metric, including the scope.
+    fn name(&self, scope: &MetricScope) -> TokenStream {
+        match scope {
+            MetricScope::Static(scope) => {
+                let name = format!("{}{SEPARATOR}{}", scope.value(), self.name);
+                quote! { #name }
+            }
+            MetricScope::Dynamic => {
+                let name = format!("{SEPARATOR}{}", self.name);
+                quote! { format!("{}{}", scope, #name) }
+            }
+        }
+    }
+
+    /// Returns the full name of the metric for documentation.
+    fn display_name(&self, scope: &MetricScope) -> String {
+        match scope {
+            MetricScope::Static(scope) => format!("{}{SEPARATOR}{}", scope.value(), self.name),
+            MetricScope::Dynamic => format!("<scope>{SEPARATOR}{}", self.name),
+        }
+    }
+}
+
+/// Parses the mandatory `#[metrics(scope = "..")]` or `#[metrics(dynamic = true)]` attribute of
+/// the struct.
+fn parse_scope(node: &DeriveInput) -> Result<MetricScope> {
+    let attr = parse_single_attr("metrics", &node.attrs)?
+        .ok_or_else(|| Error::new_spanned(node, "`#[metrics(..)]` attribute must be provided."))?;
+
+    let (mut scope, mut dynamic) = (None, None);
+    for meta in parse_nested_metas(attr)? {
+        let value = match meta {
+            Meta::NameValue(value) => value,
+            other => return Err(Error::new_spanned(other, "Unsupported attribute entry.")),
+        };
+        if value.path.is_ident("scope") {
+            if scope.is_some() {
+                return Err(Error::new_spanned(value, "Duplicate `scope` value provided."))
+            }
+            let scope_lit = parse_str_lit(&value.lit)?;
+            validate_metric_name(&scope_lit)?;
+            scope = Some(scope_lit);
+        } else if value.path.is_ident("dynamic") {
+            if dynamic.is_some() {
+                return Err(Error::new_spanned(value, "Duplicate `dynamic` flag provided."))
+            }
+            dynamic = match &value.lit {
+                Lit::Bool(lit) => Some(lit.value),
+                _ => return Err(Error::new_spanned(value, "Value **must** be a bool literal.")),
+            };
+        } else {
+            return Err(Error::new_spanned(value, "Unsupported attribute entry."))
+        }
+    }
+
+    match (scope, dynamic.unwrap_or_default()) {
+        (Some(scope), false) => Ok(MetricScope::Static(scope)),
+        (None, true) => Ok(MetricScope::Dynamic),
+        (Some(_), true) => {
+            Err(Error::new_spanned(node, "`scope = ..` conflicts with `dynamic = true`."))
+        }
+        (None, false) => {
+            Err(Error::new_spanned(node, "Either `scope = ..` or `dynamic = true` must be set."))
+        }
+    }
+}
+
+/// Parses all fields of the struct into [MetricField]s.
+fn parse_metric_fields(node: &DeriveInput) -> Result<Vec<MetricField<'_>>> {
+    let data = match &node.data {
+        Data::Struct(data) => data,
+        _ => return Err(Error::new_spanned(node, "Only structs are supported.")),
+    };
+
+    let mut metrics = Vec::with_capacity(data.fields.len());
+    for field in data.fields.iter() {
+        let ident = field
+            .ident
+            .as_ref()
+            .ok_or_else(|| Error::new_spanned(field, "Only named fields are supported."))?;
+
+        let (mut describe, mut rename, mut labels, mut skip) = (None, None, None, false);
+        if let Some(attr) = parse_single_attr("metric", &field.attrs)? {
+            for meta in parse_nested_metas(attr)? {
+                let value = match meta {
+                    Meta::Path(path) if path.is_ident("skip") => {
+                        skip = true;
+                        continue
+                    }
+                    Meta::List(list) if list.path.is_ident("labels") => {
+                        if labels.is_some() {
+                            return Err(Error::new_spanned(list, "Duplicate `labels` provided."))
+                        }
+                        labels = Some(parse_labels(list)?);
+                        continue
+                    }
+                    Meta::NameValue(value) => value,
+                    other => return Err(Error::new_spanned(other, "Unsupported attribute entry.")),
+                };
+                if value.path.is_ident("describe") {
+                    if describe.is_some() {
+                        return Err(Error::new_spanned(
+                            value,
+                            "Duplicate `describe` value provided.",
+                        ))
+                    }
+                    describe = Some(parse_str_lit(&value.lit)?.value());
+                } else if value.path.is_ident("rename") {
+                    if rename.is_some() {
+                        return Err(Error::new_spanned(value, "Duplicate `rename` value provided."))
+                    }
+                    let rename_lit = parse_str_lit(&value.lit)?;
+                    validate_metric_name(&rename_lit)?;
+                    rename = Some(rename_lit.value());
+                } else {
+                    return Err(Error::new_spanned(value, "Unsupported attribute entry."))
+                }
+            }
+        }
+
+        if skip {
+            if describe.is_some() || rename.is_some() || labels.is_some() {
+                return Err(Error::new_spanned(
+                    field,
+                    "`skip` can't be combined with other metric attributes.",
+                ))
+            }
+            metrics.push(MetricField::Skipped(field));
+            continue
+        }
+
+        let description = describe
+            .or_else(|| parse_docs_to_string(field))
+            .unwrap_or_else(|| describe_from_ident(&ident.to_string()));
+
+        metrics.push(MetricField::Included(Metric {
+            field,
+            kind: MetricKind::from_field(field)?,
+            name: rename.unwrap_or_else(|| ident.to_string()),
+            description,
+            labels: labels.unwrap_or_default(),
+        }));
+    }
+
+    Ok(metrics)
+}
+
+/// Returns the attribute with the given name, if there's exactly one.
+fn parse_single_attr<'a>(ident: &str, attrs: &'a [Attribute]) -> Result<Option<&'a Attribute>> {
+    let mut attr_iter = attrs.iter().filter(|a| a.path.is_ident(ident));
+    if let Some(attr) = attr_iter.next() {
+        if let Some(next_attr) = attr_iter.next() {
+            Err(Error::new_spanned(
+                next_attr,
+                format!("Duplicate `#[{ident}(..)]` attribute provided."),
+            ))
+        } else {
+            Ok(Some(attr))
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+/// Parses the entries of a list attribute.
+fn parse_nested_metas(attr: &Attribute) -> Result<Vec<Meta>> {
+    let list = match attr.parse_meta()? {
+        Meta::List(list) => list,
+        _ => return Err(Error::new_spanned(attr, "Unsupported attribute format.")),
+    };
+
+    list.nested
+        .into_iter()
+        .map(|nested| match nested {
+            NestedMeta::Meta(meta) => Ok(meta),
+            other => Err(Error::new_spanned(other, "Unsupported attribute entry.")),
+        })
+        .collect()
+}
+
+/// Parses the label keys of `labels("a", "b")`.
+fn parse_labels(list: syn::MetaList) -> Result<Vec<LitStr>> {
+    let mut labels: Vec<LitStr> = Vec::with_capacity(list.nested.len());
+    for nested in list.nested.iter() {
+        let label = match nested {
+            NestedMeta::Lit(lit) => parse_str_lit(lit)?,
+            other => return Err(Error::new_spanned(other, "Label **must** be a string literal.")),
+        };
+        validate_label_key(&label)?;
+        if labels.iter().any(|existing| existing.value() == label.value()) {
+            return Err(Error::new_spanned(label, "Duplicate label provided."))
+        }
+        labels.push(label);
+    }
+    if labels.is_empty() {
+        return Err(Error::new_spanned(list, "At least one label must be provided."))
+    }
+    Ok(labels)
+}
+
+fn parse_str_lit(lit: &Lit) -> Result<LitStr> {
+    match lit {
+        Lit::Str(lit_str) => Ok(lit_str.to_owned()),
+        _ => Err(Error::new_spanned(lit, "Value **must** be a string literal.")),
+    }
+}
+
+/// Metric names may only contain alphanumeric characters, underscores and dots.
+fn validate_metric_name(name: &LitStr) -> Result<()> {
+    let value = name.value();
+    let is_valid = !value.is_empty() &&
+        value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(name, format!("Value must only contain [a-zA-Z0-9_.]: {value}")))
+    }
+}
+
+/// Label keys are used as argument names of the generated `with_labels` helpers and must be
+/// lowercase identifiers.
+fn validate_label_key(key: &LitStr) -> Result<()> {
+    let value = key.value();
+    let is_valid = value.starts_with(|c: char| c.is_ascii_lowercase() || c == '_') &&
+        value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(key, format!("Label must only contain [a-z0-9_]: {value}")))
+    }
+}
+
+/// Derives a description from the field name, e.g. `gas_used` becomes `Gas used`.
+fn describe_from_ident(ident: &str) -> String {
+    let words = ident.trim_start_matches("r#").split('_').filter(|word| !word.is_empty());
+    let mut description = words.collect::<Vec<_>>().join(" ");
+    if let Some(first) = description.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    description
+}
+
+/// Joins the doc comment lines of the field, if any.
+fn parse_docs_to_string(field: &Field) -> Option<String> {
+    let docs = field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(doc)) => match doc.lit {
+                Lit::Str(doc) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    (!docs.is_empty()).then(|| docs.join(" "))
+}
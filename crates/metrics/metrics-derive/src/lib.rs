@@ -0,0 +1,145 @@
+#![warn(missing_docs, unreachable_pub)]
+#![deny(unused_must_use, rust_2018_idioms)]
+#![doc(test(
+    no_crate_inject,
+    attr(deny(warnings, rust_2018_idioms), allow(dead_code, unused_variables))
+))]
+
+//! This crate provides [Metrics] derive macro
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod expand;
+
+/// The [Metrics] derive macro instruments all of the struct fields and creates a [Default]
+/// implementation for the struct registering all of the metrics.
+///
+/// Additionally, it creates a `describe()` method on the struct, which internally calls the
+/// describe statements for all metric fields, and a `new_with_labels(labels)` constructor, which
+/// registers all metrics with the same set of labels, e.g. a `stage` label shared by all fields.
+///
+/// Fields can declare the keys of dynamic labels with `#[metric(labels("peer", "direction"))]`.
+/// For every such field a `<field>_with_labels(..)` helper is generated, which takes one value per
+/// label key and returns the metric registered with these label values.
+///
+/// The description of a metric is taken from `#[metric(describe = "..")]`, the doc comment of the
+/// field or, if neither is present, derived from the field name. Fields that aren't metrics can be
+/// marked with `#[metric(skip)]` and are initialized with their [Default] value.
+///
+/// Components with multiple instances can use `#[metrics(dynamic = true)]` instead of a static
+/// scope. Then a `new(scope)` constructor is generated instead of the [Default] implementation, and
+/// all other generated functions take the scope as their first argument, e.g.
+/// `StageMetrics::new("stages.headers")` and `StageMetrics::describe("stages.headers")`.
+///
+/// Sample usage:
+/// ```
+/// use metrics::{Counter, Gauge, Histogram};
+/// use reth_metrics_derive::Metrics;
+///
+/// #[derive(Metrics)]
+/// #[metrics(scope = "metrics.custom")]
+/// pub struct CustomMetrics {
+///     /// A gauge with doc comment description.
+///     gauge: Gauge,
+///     /// A counter with dynamic labels.
+///     #[metric(labels("peer", "direction"))]
+///     messages: Counter,
+///     #[metric(skip)]
+///     last_update: Option<std::time::Instant>,
+///     #[metric(rename = "second_gauge", describe = "A gauge with metric attribute description.")]
+///     gauge2: Gauge,
+///     /// Some doc comment
+///     #[metric(describe = "Metric attribute description will be preferred over doc comment.")]
+///     counter: Counter,
+///     /// A renamed histogram.
+///     #[metric(rename = "histogram")]
+///     histo: Histogram,
+/// }
+/// ```
+///
+/// The example above will be expanded to:
+/// ```
+/// pub struct CustomMetrics {
+///     /// A gauge with doc comment description.
+///     gauge: metrics::Gauge,
+///     /// A counter with dynamic labels.
+///     messages: metrics::Counter,
+///     last_update: Option<std::time::Instant>,
+///     gauge2: metrics::Gauge,
+///     /// Some doc comment
+///     counter: metrics::Counter,
+///     /// A renamed histogram.
+///     histo: metrics::Histogram,
+/// }
+///
+/// impl Default for CustomMetrics {
+///     fn default() -> Self {
+///         Self {
+///             gauge: metrics::register_gauge!("metrics.custom.gauge"),
+///             messages: metrics::register_counter!("metrics.custom.messages"),
+///             last_update: Default::default(),
+///             gauge2: metrics::register_gauge!("metrics.custom.second_gauge"),
+///             counter: metrics::register_counter!("metrics.custom.counter"),
+///             histo: metrics::register_histogram!("metrics.custom.histogram"),
+///         }
+///     }
+/// }
+///
+/// impl CustomMetrics {
+///     /// Create new instance of metrics with provided labels.
+///     pub fn new_with_labels(labels: impl metrics::IntoLabels + Clone) -> Self {
+///         Self {
+///             gauge: metrics::register_gauge!("metrics.custom.gauge", labels.clone()),
+///             messages: metrics::register_counter!("metrics.custom.messages", labels.clone()),
+///             last_update: Default::default(),
+///             gauge2: metrics::register_gauge!("metrics.custom.second_gauge", labels.clone()),
+///             counter: metrics::register_counter!("metrics.custom.counter", labels.clone()),
+///             histo: metrics::register_histogram!("metrics.custom.histogram", labels.clone()),
+///         }
+///     }
+///
+///     /// Returns the `metrics.custom.messages` metric with the given values for the `peer`,
+///     /// `direction` labels.
+///     pub fn messages_with_labels(
+///         &self,
+///         peer: impl Into<metrics::SharedString>,
+///         direction: impl Into<metrics::SharedString>,
+///     ) -> metrics::Counter {
+///         metrics::register_counter!(
+///             "metrics.custom.messages",
+///             "peer" => peer.into(),
+///             "direction" => direction.into()
+///         )
+///     }
+///
+///     /// Describe all exposed metrics
+///     pub fn describe() {
+///         metrics::describe_gauge!(
+///             "metrics.custom.gauge",
+///             "A gauge with doc comment description."
+///         );
+///         metrics::describe_counter!("metrics.custom.messages", "A counter with dynamic labels.");
+///         metrics::describe_gauge!(
+///             "metrics.custom.second_gauge",
+///             "A gauge with metric attribute description."
+///         );
+///         metrics::describe_counter!(
+///             "metrics.custom.counter",
+///             "Metric attribute description will be preferred over doc comment."
+///         );
+///         metrics::describe_histogram!("metrics.custom.histogram", "A renamed histogram.");
+///     }
+/// }
+///
+/// impl std::fmt::Debug for CustomMetrics {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         f.debug_struct("CustomMetrics").finish()
+///     }
+/// }
+/// ```
+#[proc_macro_derive(Metrics, attributes(metrics, metric))]
+pub fn derive_metrics(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand::derive(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
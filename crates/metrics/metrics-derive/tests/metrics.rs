@@ -0,0 +1,131 @@
+use metrics::{Counter, Gauge, Histogram};
+use metrics_util::{
+    debugging::{DebugValue, DebuggingRecorder},
+    MetricKind,
+};
+use reth_metrics_derive::Metrics;
+use std::collections::HashMap;
+
+#[allow(dead_code)]
+#[derive(Metrics)]
+#[metrics(scope = "metrics.custom")]
+struct CustomMetrics {
+    /// A gauge with doc comment description.
+    gauge: Gauge,
+    #[metric(rename = "second_gauge", describe = "A gauge with metric attribute description.")]
+    gauge2: Gauge,
+    /// Some doc comment
+    #[metric(describe = "Metric attribute description will be preferred over doc comment.")]
+    counter: Counter,
+    /// A renamed histogram.
+    #[metric(rename = "histogram")]
+    histo: Histogram,
+    /// A counter with dynamic labels.
+    #[metric(labels("peer", "direction"))]
+    messages: Counter,
+    gas_used: Counter,
+    #[metric(skip)]
+    last_update: Option<std::time::Instant>,
+}
+
+#[allow(dead_code)]
+#[derive(Metrics)]
+#[metrics(dynamic = true)]
+struct DynamicScopeMetrics {
+    /// A gauge with a dynamic scope.
+    gauge: Gauge,
+    /// A counter with a dynamic scope and labels.
+    #[metric(labels("peer"))]
+    counter: Counter,
+}
+
+#[test]
+fn describe_metrics() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    recorder.install().unwrap();
+
+    let metrics = CustomMetrics::default();
+    CustomMetrics::describe();
+    metrics.counter.increment(2);
+    metrics.messages_with_labels("peer1", "inbound").increment(3);
+    metrics.messages_with_labels("peer1", "outbound").increment(1);
+    CustomMetrics::new_with_labels([("stage", "headers")]).gauge.set(5.0);
+
+    let first = DynamicScopeMetrics::new("metrics.dynamic.first");
+    let second = DynamicScopeMetrics::new("metrics.dynamic.second");
+    DynamicScopeMetrics::describe("metrics.dynamic.first");
+    first.gauge.set(1.0);
+    second.gauge.set(2.0);
+    first.counter_with_labels("metrics.dynamic.first", "peer1").increment(1);
+
+    let snapshot = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, description, value)| {
+            let mut labels = key
+                .key()
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect::<Vec<_>>();
+            labels.sort();
+            let name = if labels.is_empty() {
+                key.key().name().to_string()
+            } else {
+                format!("{}{{{}}}", key.key().name(), labels.join(","))
+            };
+            (name, (key.kind(), description, value))
+        })
+        .collect::<HashMap<_, _>>();
+    assert_eq!(snapshot.len(), 19);
+
+    let assert_metric = |name: &str, kind: MetricKind, description: &str| {
+        let (metric_kind, metric_description, _) = &snapshot[name];
+        assert_eq!(*metric_kind, kind, "{name}");
+        assert_eq!(metric_description.as_deref(), Some(description), "{name}");
+    };
+    assert_metric(
+        "metrics.custom.gauge",
+        MetricKind::Gauge,
+        "A gauge with doc comment description.",
+    );
+    assert_metric(
+        "metrics.custom.second_gauge",
+        MetricKind::Gauge,
+        "A gauge with metric attribute description.",
+    );
+    assert_metric(
+        "metrics.custom.counter",
+        MetricKind::Counter,
+        "Metric attribute description will be preferred over doc comment.",
+    );
+    assert_metric("metrics.custom.histogram", MetricKind::Histogram, "A renamed histogram.");
+    assert_metric("metrics.custom.messages", MetricKind::Counter, "A counter with dynamic labels.");
+    assert_metric("metrics.custom.gas_used", MetricKind::Counter, "Gas used");
+    assert!(metrics.last_update.is_none());
+    assert_metric(
+        "metrics.dynamic.first.gauge",
+        MetricKind::Gauge,
+        "A gauge with a dynamic scope.",
+    );
+    assert_metric(
+        "metrics.dynamic.first.counter",
+        MetricKind::Counter,
+        "A counter with a dynamic scope and labels.",
+    );
+
+    assert_eq!(snapshot["metrics.custom.counter"].2, DebugValue::Counter(2));
+    assert_eq!(
+        snapshot["metrics.custom.messages{direction=inbound,peer=peer1}"].2,
+        DebugValue::Counter(3)
+    );
+    assert_eq!(
+        snapshot["metrics.custom.messages{direction=outbound,peer=peer1}"].2,
+        DebugValue::Counter(1)
+    );
+    assert_eq!(snapshot["metrics.custom.gauge{stage=headers}"].2, DebugValue::Gauge(5f64.into()));
+    assert_eq!(snapshot["metrics.dynamic.first.gauge"].2, DebugValue::Gauge(1f64.into()));
+    assert_eq!(snapshot["metrics.dynamic.second.gauge"].2, DebugValue::Gauge(2f64.into()));
+    assert_eq!(snapshot["metrics.dynamic.first.counter{peer=peer1}"].2, DebugValue::Counter(1));
+}
@@ -6,6 +6,7 @@
 use crate::node::NodeRecord;
 use bytes::{Bytes, BytesMut};
 use reth_net_common::ban_list::BanList;
+use reth_net_nat::NatResolver;
 use reth_rlp::Encodable;
 use std::{
     collections::{HashMap, HashSet},
@@ -54,6 +55,9 @@ pub struct Discv4Config {
     pub enable_eip868: bool,
     /// Additional pairs to include in The [`Enr`](enr::Enr) if EIP-868 extension is enabled <https://eips.ethereum.org/EIPS/eip-868>
     pub additional_eip868_rlp_pairs: HashMap<Vec<u8>, Bytes>,
+    /// If configured, try to resolve the external IP that is advertised in the local node record
+    /// and ENR.
+    pub external_ip_resolver: Option<NatResolver>,
 }
 
 impl Discv4Config {
@@ -113,6 +117,7 @@ impl Default for Discv4Config {
             enable_lookup: true,
             enable_eip868: true,
             additional_eip868_rlp_pairs: Default::default(),
+            external_ip_resolver: None,
         }
     }
 }
@@ -247,6 +252,13 @@ impl Discv4ConfigBuilder {
         self
     }
 
+    /// Configures the [`NatResolver`] used to resolve the external IP that is advertised to other
+    /// nodes.
+    pub fn external_ip_resolver(&mut self, external_ip_resolver: Option<NatResolver>) -> &mut Self {
+        self.config.external_ip_resolver = external_ip_resolver;
+        self
+    }
+
     /// Returns the configured [`Discv4Config`]
     pub fn build(&self) -> Discv4Config {
         self.config.clone()
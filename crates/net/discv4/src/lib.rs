@@ -31,6 +31,7 @@ use discv5::{
 use enr::{Enr, EnrBuilder};
 use proto::{EnrRequest, EnrResponse};
 use reth_primitives::{ForkId, PeerId, H256};
+use reth_tasks::TaskExecutor;
 use secp256k1::SecretKey;
 use std::{
     cell::RefCell,
@@ -46,7 +47,7 @@ use std::{
 use tokio::{
     net::UdpSocket,
     sync::{mpsc, mpsc::error::TrySendError, oneshot, oneshot::Sender as OneshotSender},
-    task::{JoinHandle, JoinSet},
+    task::JoinSet,
     time::Interval,
 };
 use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
@@ -67,6 +68,8 @@ pub mod mock;
 /// reexport to get public ip.
 pub use public_ip;
 
+pub use reth_net_nat::{NatResolver, ParseNatResolverError};
+
 /// The default port for discv4 via UDP
 ///
 /// Note: the default TCP port is the same.
@@ -116,17 +119,18 @@ pub struct Discv4 {
 // === impl Discv4 ===
 
 impl Discv4 {
-    /// Same as [`Self::bind`] but also spawns the service onto a new task,
+    /// Same as [`Self::bind`] but also spawns the service onto a new task of the executor,
     /// [`Discv4Service::spawn()`]
     pub async fn spawn(
         local_address: SocketAddr,
         local_enr: NodeRecord,
         secret_key: SecretKey,
         config: Discv4Config,
+        executor: &TaskExecutor,
     ) -> io::Result<Self> {
         let (discv4, service) = Self::bind(local_address, local_enr, secret_key, config).await?;
 
-        service.spawn();
+        service.spawn(executor);
 
         Ok(discv4)
     }
@@ -141,6 +145,7 @@ impl Discv4 {
     /// use secp256k1::SECP256K1;
     /// use reth_primitives::PeerId;
     /// use reth_discv4::{Discv4, Discv4Config, NodeRecord};
+    /// use reth_tasks::TaskExecutor;
     /// # async fn t() -> io::Result<()> {
     /// // generate a (random) keypair
     ///  let mut rng = thread_rng();
@@ -161,7 +166,7 @@ impl Discv4 {
     ///   // get an update strea
     ///   let updates = service.update_stream();
     ///
-    ///   let _handle = service.spawn();
+    ///   service.spawn(&TaskExecutor::current());
     ///
     ///   // lookup the local node in the DHT
     ///   let _discovered = discv4.lookup_self().await.unwrap();
@@ -180,6 +185,15 @@ impl Discv4 {
         local_node_record.udp_port = local_addr.port();
         trace!( target : "discv4",  ?local_addr,"opened UDP socket");
 
+        if let Some(resolver) = config.external_ip_resolver {
+            if let Some(external_ip) = resolver.external_addr().await {
+                debug!(target : "discv4", %resolver, ?external_ip, "resolved external ip");
+                local_node_record.address = external_ip;
+            } else {
+                debug!(target : "discv4", %resolver, "failed to resolve external ip");
+            }
+        }
+
         let (to_service, rx) = mpsc::channel(100);
         let service =
             Discv4Service::new(socket, local_addr, local_node_record, secret_key, config, Some(rx));
@@ -306,6 +320,38 @@ impl Discv4 {
         self.to_service.send(cmd).await?;
         Ok(rx.await?)
     }
+
+    /// Returns the current [`NodeRecord`] and EIP-868 [`Enr`] of the local node, as they are
+    /// advertised to other nodes.
+    pub async fn local_node(&self) -> Result<(NodeRecord, Enr<SecretKey>), Discv4Error> {
+        let (tx, rx) = oneshot::channel();
+        let cmd = Discv4Command::LocalNode(tx);
+        self.to_service.send(cmd).await?;
+        Ok(rx.await?)
+    }
+}
+
+/// Builds the EIP-868 [`Enr`] of the node record, with the additional pairs of the config.
+pub fn local_enr(
+    node_record: &NodeRecord,
+    secret_key: &SecretKey,
+    config: &Discv4Config,
+) -> Enr<SecretKey> {
+    let mut builder = EnrBuilder::new("v4");
+    builder.ip(node_record.address);
+    if node_record.address.is_ipv4() {
+        builder.udp4(node_record.udp_port);
+        builder.tcp4(node_record.tcp_port);
+    } else {
+        builder.udp6(node_record.udp_port);
+        builder.tcp6(node_record.tcp_port);
+    }
+
+    for (key, val) in config.additional_eip868_rlp_pairs.iter() {
+        builder.add_value_rlp(key, val.clone());
+    }
+
+    builder.build(secret_key).expect("v4 is set; qed")
 }
 
 /// Manages discv4 peer discovery over UDP.
@@ -412,23 +458,7 @@ impl Discv4Service {
         };
 
         // for EIP-868 construct an ENR
-        let local_eip_868_enr = {
-            let mut builder = EnrBuilder::new("v4");
-            builder.ip(local_node_record.address);
-            if local_node_record.address.is_ipv4() {
-                builder.udp4(local_node_record.udp_port);
-                builder.tcp4(local_node_record.tcp_port);
-            } else {
-                builder.udp6(local_node_record.udp_port);
-                builder.tcp6(local_node_record.tcp_port);
-            }
-
-            for (key, val) in config.additional_eip868_rlp_pairs.iter() {
-                builder.add_value_rlp(key, val.clone());
-            }
-
-            builder.build(&secret_key).expect("v4 is set; qed")
-        };
+        let local_eip_868_enr = local_enr(&local_node_record, &secret_key, &config);
 
         Discv4Service {
             local_address,
@@ -519,11 +549,9 @@ impl Discv4Service {
         }
     }
 
-    /// Spawns this services onto a new task
-    ///
-    /// Note: requires a running runtime
-    pub fn spawn(mut self) -> JoinHandle<()> {
-        tokio::task::spawn(async move {
+    /// Spawns this services onto a new task of the executor
+    pub fn spawn(mut self, executor: &TaskExecutor) {
+        executor.spawn("discv4", async move {
             self.bootstrap();
             while let Some(event) = self.next().await {
                 trace!(target : "discv4", ?event,  "processed");
@@ -1262,6 +1290,10 @@ impl Discv4Service {
                             let rx = self.update_stream();
                             let _ = tx.send(rx);
                         }
+                        Discv4Command::LocalNode(tx) => {
+                            let _ =
+                                tx.send((self.local_node_record, self.local_eip_868_enr.clone()));
+                        }
                         Discv4Command::BanPeer(node_id) => self.ban_node(node_id),
                         Discv4Command::Remove(node_id) => {
                             self.remove_node(node_id);
@@ -1439,6 +1471,7 @@ enum Discv4Command {
     Remove(PeerId),
     Lookup { node_id: Option<PeerId>, tx: Option<NodeRecordSender> },
     Updates(OneshotSender<ReceiverStream<DiscoveryUpdate>>),
+    LocalNode(OneshotSender<(NodeRecord, Enr<SecretKey>)>),
 }
 
 /// Event type receiver produces
@@ -1793,7 +1826,7 @@ mod tests {
 
         let mut updates = service.update_stream();
 
-        let _handle = service.spawn();
+        service.spawn(&TaskExecutor::current());
 
         let mut table = HashMap::new();
         while let Some(update) = updates.next().await {
@@ -1822,11 +1855,24 @@ mod tests {
 
         service.lookup_self();
 
-        let _handle = service.spawn();
+        service.spawn(&TaskExecutor::current());
         discv4.send_lookup_self();
         let _ = discv4.lookup_self().await;
     }
 
+    #[tokio::test]
+    async fn test_local_node() {
+        let (discv4, service) = create_discv4().await;
+        service.spawn(&TaskExecutor::current());
+
+        discv4.set_tcp_port(30303);
+        let (record, enr) = discv4.local_node().await.unwrap();
+        assert_eq!(record.tcp_port, 30303);
+        assert_eq!(record.udp_port, discv4.local_addr().port());
+        assert_eq!(enr.tcp4().or(enr.tcp6()), Some(30303));
+        assert!(enr.get(b"eth").is_some());
+    }
+
     #[test]
     fn test_insert() {
         let local_node_record = rng_record(&mut rand::thread_rng());
@@ -0,0 +1,32 @@
+use crate::tree::LinkEntry;
+use std::{collections::HashSet, time::Duration};
+
+/// Settings for the [DnsDiscoveryService](crate::DnsDiscoveryService).
+#[derive(Debug, Clone)]
+pub struct DnsDiscoveryConfig {
+    /// Timeout for DNS lookups.
+    ///
+    /// Default: 5s
+    pub lookup_timeout: Duration,
+    /// The DNS request rate limit
+    ///
+    /// Default: 3
+    pub max_requests_per_sec: usize,
+    /// The rate at which trees should be updated.
+    ///
+    /// Default: 30min
+    pub recheck_interval: Duration,
+    /// Links to the DNS networks to bootstrap.
+    pub bootstrap_dns_networks: Option<HashSet<LinkEntry>>,
+}
+
+impl Default for DnsDiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            lookup_timeout: Duration::from_secs(5),
+            max_requests_per_sec: 3,
+            recheck_interval: Duration::from_secs(60 * 30),
+            bootstrap_dns_networks: Some(Default::default()),
+        }
+    }
+}
@@ -0,0 +1,499 @@
+#![warn(missing_docs, unreachable_pub, unused_crate_dependencies)]
+#![deny(unused_must_use, rust_2018_idioms)]
+#![doc(test(
+    no_crate_inject,
+    attr(deny(warnings, rust_2018_idioms), allow(dead_code, unused_variables))
+))]
+
+//! Implementation of [EIP-1459](https://eips.ethereum.org/EIPS/eip-1459) Node Discovery via DNS.
+//!
+//! A node list is a merkle tree of ENRs published as DNS TXT records. The root entry is signed by
+//! the list operator, so a list can be retrieved through any, possibly untrusted, DNS server.
+//!
+//! This crate consists of a [`DnsDiscoveryService`] and [`DnsDiscoveryHandle`] pair. The service
+//! crawls all configured trees, verifies every resolved entry and periodically rechecks the roots
+//! for updates. All discovered nodes are emitted as [`DnsNodeRecordUpdate`]s that listeners
+//! receive via [`DnsDiscoveryHandle::node_record_stream`].
+
+pub use crate::resolver::{DnsResolver, MapResolver, Resolver};
+use crate::{
+    query::{QueryOutcome, QueryPool, ResolveEntryResult, ResolveRootResult},
+    sync::{ResolveKind, SyncTree},
+    tree::{DnsEntry, LinkEntry},
+};
+pub use config::DnsDiscoveryConfig;
+use enr::Enr;
+pub use error::ParseDnsEntryError;
+use reth_discv4::NodeRecord;
+use reth_primitives::{ForkId, PeerId};
+use reth_rlp::Decodable;
+use reth_tasks::TaskExecutor;
+use secp256k1::SecretKey;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{
+        mpsc,
+        mpsc::{error::TrySendError, UnboundedSender},
+        oneshot,
+    },
+    time::Interval,
+};
+use tokio_stream::{
+    wrappers::{ReceiverStream, UnboundedReceiverStream},
+    Stream, StreamExt,
+};
+use tracing::{debug, trace};
+
+mod config;
+mod error;
+mod query;
+pub mod resolver;
+mod sync;
+pub mod tree;
+
+/// [DnsDiscoveryService] front-end.
+#[derive(Clone, Debug)]
+pub struct DnsDiscoveryHandle {
+    /// Channel for sending commands to the service.
+    to_service: UnboundedSender<DnsDiscoveryCommand>,
+}
+
+// === impl DnsDiscoveryHandle ===
+
+impl DnsDiscoveryHandle {
+    /// Starts syncing the given link to a tree.
+    pub fn sync_tree(&mut self, link: &str) -> Result<(), ParseDnsEntryError> {
+        self.sync_tree_with_link(link.parse()?);
+        Ok(())
+    }
+
+    /// Starts syncing the given link to a tree.
+    pub fn sync_tree_with_link(&mut self, link: LinkEntry) {
+        let _ = self.to_service.send(DnsDiscoveryCommand::SyncTree(link));
+    }
+
+    /// Returns the receiver half of new listener channel that streams discovered
+    /// [`NodeRecord`]s.
+    pub async fn node_record_stream(
+        &self,
+    ) -> Result<ReceiverStream<DnsNodeRecordUpdate>, oneshot::error::RecvError> {
+        let (tx, rx) = oneshot::channel();
+        let cmd = DnsDiscoveryCommand::NodeRecordUpdates(tx);
+        let _ = self.to_service.send(cmd);
+        rx.await
+    }
+}
+
+/// A client that discovers nodes via DNS.
+#[must_use = "Service does nothing unless polled"]
+pub struct DnsDiscoveryService<R: Resolver = DnsResolver> {
+    /// Copy of the sender half, so new [`DnsDiscoveryHandle`] can be created on demand.
+    command_tx: UnboundedSender<DnsDiscoveryCommand>,
+    /// Receiver half of the command channel.
+    command_rx: UnboundedReceiverStream<DnsDiscoveryCommand>,
+    /// All subscribers for resolved [NodeRecord]s.
+    node_record_listeners: Vec<mpsc::Sender<DnsNodeRecordUpdate>>,
+    /// All the trees that can be synced.
+    trees: HashMap<LinkEntry, SyncTree>,
+    /// All queries currently in progress
+    queries: QueryPool<R>,
+    /// Buffered events until polled.
+    queued_events: VecDeque<DnsDiscoveryEvent>,
+    /// The rate at which trees should be updated.
+    recheck_interval: Duration,
+    /// Timer that triggers the recheck of stale trees.
+    recheck_timer: Interval,
+}
+
+// === impl DnsDiscoveryService ===
+
+impl<R: Resolver> DnsDiscoveryService<R> {
+    /// Creates a new instance of the [DnsDiscoveryService] using the given settings.
+    ///
+    /// ```
+    /// use reth_dns_discovery::{DnsDiscoveryService, DnsResolver};
+    /// use std::sync::Arc;
+    /// # fn t() {
+    /// let resolver = Arc::new(DnsResolver::from_system_conf().unwrap());
+    /// let service = DnsDiscoveryService::new(resolver, Default::default());
+    /// # }
+    /// ```
+    pub fn new(resolver: Arc<R>, config: DnsDiscoveryConfig) -> Self {
+        let DnsDiscoveryConfig {
+            lookup_timeout,
+            max_requests_per_sec,
+            recheck_interval,
+            bootstrap_dns_networks,
+        } = config;
+        let queries = QueryPool::new(resolver, max_requests_per_sec, lookup_timeout);
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let mut service = Self {
+            command_tx,
+            command_rx: UnboundedReceiverStream::new(command_rx),
+            node_record_listeners: Default::default(),
+            trees: Default::default(),
+            queries,
+            queued_events: Default::default(),
+            recheck_interval,
+            recheck_timer: tokio::time::interval(recheck_interval),
+        };
+
+        for link in bootstrap_dns_networks.unwrap_or_default() {
+            service.sync_tree_with_link(link);
+        }
+
+        service
+    }
+
+    /// Spawns this services onto a new task of the executor
+    pub fn spawn(mut self, executor: &TaskExecutor) {
+        executor.spawn("dns discovery", async move {
+            while let Some(event) = self.next().await {
+                trace!(target : "disc::dns", ?event,  "processed");
+            }
+        })
+    }
+
+    /// Same as [DnsDiscoveryService::new] but also returns a new handle that's connected to the
+    /// service
+    pub fn new_pair(resolver: Arc<R>, config: DnsDiscoveryConfig) -> (Self, DnsDiscoveryHandle) {
+        let service = Self::new(resolver, config);
+        let handle = service.handle();
+        (service, handle)
+    }
+
+    /// Returns a new [`DnsDiscoveryHandle`] that can send commands to this type.
+    pub fn handle(&self) -> DnsDiscoveryHandle {
+        DnsDiscoveryHandle { to_service: self.command_tx.clone() }
+    }
+
+    /// Creates a new channel for [`NodeRecord`]s.
+    pub fn node_record_stream(&mut self) -> ReceiverStream<DnsNodeRecordUpdate> {
+        let (tx, rx) = mpsc::channel(256);
+        self.node_record_listeners.push(tx);
+        ReceiverStream::new(rx)
+    }
+
+    /// Sends  the event to all listeners.
+    ///
+    /// Remove channels that got closed.
+    fn notify(&mut self, record: DnsNodeRecordUpdate) {
+        self.node_record_listeners.retain_mut(|listener| match listener.try_send(record.clone()) {
+            Ok(()) => true,
+            Err(err) => match err {
+                TrySendError::Full(_) => true,
+                TrySendError::Closed(_) => false,
+            },
+        });
+    }
+
+    /// Starts syncing the given link to a tree.
+    pub fn sync_tree(&mut self, link: &str) -> Result<(), ParseDnsEntryError> {
+        self.sync_tree_with_link(link.parse()?);
+        Ok(())
+    }
+
+    /// Starts syncing the given link to a tree.
+    pub fn sync_tree_with_link(&mut self, link: LinkEntry) {
+        self.queries.resolve_root(link);
+    }
+
+    /// Resolves an entry
+    fn resolve_entry(&mut self, link: LinkEntry, hash: String, kind: ResolveKind) {
+        if let Some(tree) = self.trees.get_mut(&link) {
+            tree.unresolved(kind, hash.clone());
+        }
+        self.queries.resolve_entry(link, hash, kind)
+    }
+
+    fn on_resolved_root(&mut self, resp: ResolveRootResult) {
+        let ResolveRootResult { link, res } = resp;
+        let root = match res {
+            Ok(root) => root,
+            Err(err) => {
+                debug!(target : "disc::dns", %err, domain=%link.domain, "Failed to lookup root");
+                return
+            }
+        };
+
+        let pending = match self.trees.get_mut(&link) {
+            Some(tree) => tree.update_root(root),
+            None => {
+                let tree = SyncTree::new(root, link.clone());
+                let pending = vec![
+                    (ResolveKind::Enr, tree.root().enr_root.clone()),
+                    (ResolveKind::Link, tree.root().link_root.clone()),
+                ];
+                self.trees.insert(link.clone(), tree);
+                pending
+            }
+        };
+
+        for (kind, hash) in pending {
+            self.resolve_entry(link.clone(), hash, kind);
+        }
+    }
+
+    fn on_resolved_entry(&mut self, resp: ResolveEntryResult) {
+        let ResolveEntryResult { entry, link, hash, kind } = resp;
+
+        let Some(tree) = self.trees.get_mut(&link) else { return };
+        if !tree.resolved(kind, &hash) {
+            // entry belongs to a previous root of the tree
+            return
+        }
+
+        match entry {
+            Err(err) => {
+                debug!(
+                    target : "disc::dns",
+                    %err, domain=%link.domain, ?hash, "Failed to lookup entry"
+                )
+            }
+            Ok(entry) => match entry {
+                DnsEntry::Root(root) => {
+                    debug!(
+                        target : "disc::dns",
+                        %root, domain=%link.domain, ?hash, "resolved unexpected root"
+                    );
+                }
+                DnsEntry::Link(link_entry) => {
+                    if kind.is_link() {
+                        if !self.trees.contains_key(&link_entry) {
+                            self.sync_tree_with_link(link_entry)
+                        }
+                    } else {
+                        debug!(
+                            target : "disc::dns",
+                            %link_entry, ?hash, "resolved unexpected link"
+                        );
+                    }
+                }
+                DnsEntry::Branch(branch_entry) => {
+                    for child in branch_entry.children {
+                        self.resolve_entry(link.clone(), child, kind);
+                    }
+                }
+                DnsEntry::Node(entry) => {
+                    if kind.is_link() {
+                        debug!(
+                            target : "disc::dns",
+                            domain=%link.domain, ?hash, "resolved unexpected enr"
+                        );
+                    } else {
+                        self.on_resolved_enr(entry.enr)
+                    }
+                }
+            },
+        }
+
+        if self.trees.get(&link).map(SyncTree::is_synced).unwrap_or_default() {
+            self.queued_events.push_back(DnsDiscoveryEvent::TreeSynced(link));
+        }
+    }
+
+    fn on_resolved_enr(&mut self, enr: Enr<SecretKey>) {
+        if let Some(node_record) = convert_enr_node_record(&enr) {
+            self.notify(node_record.clone());
+            self.queued_events.push_back(DnsDiscoveryEvent::NodeRecord(node_record));
+        }
+    }
+
+    /// Queues in root lookups for all trees that haven't been updated within the recheck
+    /// interval.
+    fn recheck_stale_trees(&mut self) {
+        let now = Instant::now();
+        let stale = self
+            .trees
+            .values()
+            .filter(|tree| tree.is_synced() && tree.is_stale(now, self.recheck_interval))
+            .map(|tree| tree.link().clone())
+            .collect::<Vec<_>>();
+        for link in stale {
+            self.sync_tree_with_link(link);
+        }
+    }
+
+    /// Advances the state of the DNS discovery service by polling,triggering lookups
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<DnsDiscoveryEvent> {
+        loop {
+            // drain buffered events first
+            if let Some(event) = self.queued_events.pop_front() {
+                return Poll::Ready(event)
+            }
+
+            // process all incoming commands
+            while let Poll::Ready(Some(cmd)) = Pin::new(&mut self.command_rx).poll_next(cx) {
+                match cmd {
+                    DnsDiscoveryCommand::SyncTree(link) => {
+                        self.sync_tree_with_link(link);
+                    }
+                    DnsDiscoveryCommand::NodeRecordUpdates(tx) => {
+                        let _ = tx.send(self.node_record_stream());
+                    }
+                }
+            }
+
+            while let Poll::Ready(outcome) = self.queries.poll(cx) {
+                // handle query outcome
+                match outcome {
+                    QueryOutcome::Root(resp) => self.on_resolved_root(resp),
+                    QueryOutcome::Entry(resp) => self.on_resolved_entry(resp),
+                }
+            }
+
+            if self.recheck_timer.poll_tick(cx).is_ready() && self.queries.is_idle() {
+                self.recheck_stale_trees();
+            }
+
+            if self.queued_events.is_empty() {
+                return Poll::Pending
+            }
+        }
+    }
+}
+
+/// A Stream events, mainly used for debugging
+impl<R: Resolver> Stream for DnsDiscoveryService<R> {
+    type Item = DnsDiscoveryEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(Some(ready!(self.get_mut().poll(cx))))
+    }
+}
+
+/// The converted discovered [Enr] object
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DnsNodeRecordUpdate {
+    /// Discovered node and it's addresses
+    pub node_record: NodeRecord,
+    /// The forkid of the node, if present in the ENR
+    pub fork_id: Option<ForkId>,
+}
+
+/// Commands sent from [DnsDiscoveryHandle] to [DnsDiscoveryService]
+enum DnsDiscoveryCommand {
+    /// Sync a tree
+    SyncTree(LinkEntry),
+    NodeRecordUpdates(oneshot::Sender<ReceiverStream<DnsNodeRecordUpdate>>),
+}
+
+/// Represents dns discovery related update events.
+#[derive(Debug, Clone)]
+pub enum DnsDiscoveryEvent {
+    /// Resolved an Enr entry via DNS.
+    NodeRecord(DnsNodeRecordUpdate),
+    /// All entries of the tree were resolved.
+    TreeSynced(LinkEntry),
+}
+
+/// Converts an [Enr] into a [NodeRecord]
+///
+/// Returns `None` if the record does not contain an IP address and ports.
+fn convert_enr_node_record(enr: &Enr<SecretKey>) -> Option<DnsNodeRecordUpdate> {
+    let node_record = NodeRecord {
+        address: enr.ip4().map(IpAddr::from).or_else(|| enr.ip6().map(IpAddr::from))?,
+        tcp_port: enr.tcp4().or_else(|| enr.tcp6())?,
+        udp_port: enr.udp4().or_else(|| enr.udp6())?,
+        id: PeerId::from_slice(&enr.public_key().serialize_uncompressed()[1..]),
+    };
+
+    let fork_id =
+        enr.get(b"eth").and_then(|mut maybe_fork_id| ForkId::decode(&mut maybe_fork_id).ok());
+
+    Some(DnsNodeRecordUpdate { node_record, fork_id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{subdomain_hash, TreeRootEntry};
+    use enr::EnrBuilder;
+    use secp256k1::SECP256K1;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn test_convert_enr_node_record() {
+        // rig
+        let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+        let enr = EnrBuilder::new("v4")
+            .ip(Ipv4Addr::LOCALHOST.into())
+            .udp4(30303)
+            .tcp4(30303)
+            .build(&secret_key)
+            .unwrap();
+
+        // test
+        let node_record = convert_enr_node_record(&enr).unwrap().node_record;
+
+        assert_eq!(node_record.address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(node_record.tcp_port, 30303);
+        assert_eq!(node_record.udp_port, 30303);
+        assert_eq!(
+            node_record.id,
+            NodeRecord::from_secret_key("127.0.0.1:30303".parse().unwrap(), &secret_key).id
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_start_root_sync() {
+        reth_tracing::init_test_tracing();
+
+        let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+        let enr = EnrBuilder::new("v4")
+            .ip(Ipv4Addr::LOCALHOST.into())
+            .udp4(30303)
+            .tcp4(30303)
+            .build(&secret_key)
+            .unwrap();
+        let node = format!("{}", tree::NodeEntry { enr });
+        let branch = "enrtree-branch:".to_string();
+
+        let mut root = TreeRootEntry {
+            enr_root: subdomain_hash(&node),
+            link_root: subdomain_hash(&branch),
+            sequence_number: 1,
+            signature: Default::default(),
+        };
+        root.sign(&secret_key);
+
+        let link = LinkEntry {
+            domain: "nodes.example.org".to_string(),
+            pubkey: secret_key.public_key(SECP256K1),
+        };
+
+        let resolver = MapResolver::default();
+        resolver.insert(link.domain.clone(), root.to_string()).await;
+        resolver.insert(format!("{}.{}", root.enr_root, link.domain), node).await;
+        resolver.insert(format!("{}.{}", root.link_root, link.domain), branch).await;
+
+        let mut service = DnsDiscoveryService::new(Arc::new(resolver), Default::default());
+        let mut node_records = service.node_record_stream();
+        service.sync_tree_with_link(link.clone());
+
+        let event = service.next().await.unwrap();
+        match event {
+            DnsDiscoveryEvent::NodeRecord(record) => {
+                assert_eq!(record.node_record.address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+            }
+            DnsDiscoveryEvent::TreeSynced(_) => {
+                unreachable!("enr is resolved before sync completes")
+            }
+        }
+        assert!(node_records.next().await.is_some());
+
+        loop {
+            if let DnsDiscoveryEvent::TreeSynced(synced) = service.next().await.unwrap() {
+                assert_eq!(synced, link);
+                break
+            }
+        }
+    }
+}
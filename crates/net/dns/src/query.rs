@@ -0,0 +1,174 @@
+//! Handles query execution
+
+use crate::{
+    error::{LookupError, LookupResult},
+    resolver::Resolver,
+    sync::ResolveKind,
+    tree::{is_valid_entry_hash, DnsEntry, LinkEntry, TreeRootEntry},
+};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// The `QueryPool` provides an aggregate state machine for driving DNS queries.
+///
+/// Queries are executed in the order they were queued and are rate limited.
+#[must_use = "Query does nothing unless polled"]
+pub(crate) struct QueryPool<R: Resolver> {
+    /// The [Resolver] that's used to lookup queries.
+    resolver: Arc<R>,
+    /// Buffered queries
+    queued_queries: VecDeque<Query>,
+    /// All active queries
+    active_queries: Vec<Query>,
+    /// buffered results
+    queued_outcomes: VecDeque<QueryOutcome>,
+    /// Rate limit for DNS requests
+    rate_limit: Interval,
+    /// Timeout for DNS lookups.
+    lookup_timeout: Duration,
+}
+
+// === impl QueryPool ===
+
+impl<R: Resolver> QueryPool<R> {
+    pub(crate) fn new(
+        resolver: Arc<R>,
+        max_requests_per_sec: usize,
+        lookup_timeout: Duration,
+    ) -> Self {
+        let period = Duration::from_secs(1) / max_requests_per_sec.max(1) as u32;
+        let mut rate_limit = tokio::time::interval(period);
+        rate_limit.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            resolver,
+            queued_queries: Default::default(),
+            active_queries: vec![],
+            queued_outcomes: Default::default(),
+            rate_limit,
+            lookup_timeout,
+        }
+    }
+
+    /// Resolves the root the link's domain references
+    pub(crate) fn resolve_root(&mut self, link: LinkEntry) {
+        let resolver = Arc::clone(&self.resolver);
+        let timeout = self.lookup_timeout;
+        self.queued_queries.push_back(Box::pin(async move {
+            let res = lookup_root(&*resolver, &link, timeout).await;
+            QueryOutcome::Root(ResolveRootResult { link, res })
+        }))
+    }
+
+    /// Resolves the [DnsEntry] for `<hash.domain>`
+    pub(crate) fn resolve_entry(&mut self, link: LinkEntry, hash: String, kind: ResolveKind) {
+        let resolver = Arc::clone(&self.resolver);
+        let timeout = self.lookup_timeout;
+        self.queued_queries.push_back(Box::pin(async move {
+            let entry = lookup_entry(&*resolver, &link.domain, &hash, timeout).await;
+            QueryOutcome::Entry(ResolveEntryResult { entry, link, hash, kind })
+        }))
+    }
+
+    /// Returns `true` if there are no queued or active queries.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.queued_queries.is_empty() && self.active_queries.is_empty()
+    }
+
+    /// Advances the state of the queries
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<QueryOutcome> {
+        loop {
+            // drain buffered events first
+            if let Some(event) = self.queued_outcomes.pop_front() {
+                return Poll::Ready(event)
+            }
+
+            // queue in new queries if the rate limit allows
+            while !self.queued_queries.is_empty() && self.rate_limit.poll_tick(cx).is_ready() {
+                if let Some(query) = self.queued_queries.pop_front() {
+                    self.active_queries.push(query);
+                }
+            }
+
+            // advance all queries
+            for idx in (0..self.active_queries.len()).rev() {
+                let mut query = self.active_queries.swap_remove(idx);
+                if let Poll::Ready(outcome) = query.as_mut().poll(cx) {
+                    self.queued_outcomes.push_back(outcome);
+                } else {
+                    // still pending
+                    self.active_queries.push(query);
+                }
+            }
+
+            if self.queued_outcomes.is_empty() {
+                return Poll::Pending
+            }
+        }
+    }
+}
+
+// === Various future/type alias ===
+
+type Query = Pin<Box<dyn Future<Output = QueryOutcome> + Send>>;
+
+/// The output the queries return
+pub(crate) enum QueryOutcome {
+    Root(ResolveRootResult),
+    Entry(ResolveEntryResult),
+}
+
+/// The result of resolving a tree root
+pub(crate) struct ResolveRootResult {
+    pub(crate) link: LinkEntry,
+    pub(crate) res: LookupResult<TreeRootEntry>,
+}
+
+/// The result of resolving a single entry of a tree
+pub(crate) struct ResolveEntryResult {
+    pub(crate) entry: LookupResult<DnsEntry>,
+    pub(crate) link: LinkEntry,
+    pub(crate) hash: String,
+    pub(crate) kind: ResolveKind,
+}
+
+/// Retrieves the root entry the link points to and verifies its signature.
+async fn lookup_root<R: Resolver>(
+    resolver: &R,
+    link: &LinkEntry,
+    timeout: Duration,
+) -> LookupResult<TreeRootEntry> {
+    let txt = tokio::time::timeout(timeout, resolver.lookup_txt(&link.domain))
+        .await
+        .map_err(|_| LookupError::RequestTimedOut)?
+        .ok_or(LookupError::EntryNotFound)?;
+    let root: TreeRootEntry = txt.parse()?;
+    if !root.verify(&link.pubkey) {
+        return Err(LookupError::InvalidRoot(root))
+    }
+    Ok(root)
+}
+
+/// Retrieves the [DnsEntry] stored at `<hash>.<domain>` and checks that its content matches the
+/// hash.
+async fn lookup_entry<R: Resolver>(
+    resolver: &R,
+    domain: &str,
+    hash: &str,
+    timeout: Duration,
+) -> LookupResult<DnsEntry> {
+    let txt = tokio::time::timeout(timeout, resolver.lookup_txt(&format!("{hash}.{domain}")))
+        .await
+        .map_err(|_| LookupError::RequestTimedOut)?
+        .ok_or(LookupError::EntryNotFound)?;
+    if !is_valid_entry_hash(hash, &txt) {
+        return Err(LookupError::InvalidEntryHash(hash.to_string()))
+    }
+    Ok(txt.parse()?)
+}
@@ -0,0 +1,159 @@
+use crate::tree::{LinkEntry, TreeRootEntry};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+/// The sync state of a single tree.
+///
+/// A tree is synced by first resolving its root and then recursively resolving all entries of the
+/// `enr` and `link` subtrees.
+pub(crate) struct SyncTree {
+    /// Root of the tree
+    root: TreeRootEntry,
+    /// Link to this tree
+    link: LinkEntry,
+    /// Timestamp when the root was updated
+    root_updated: Instant,
+    /// Hashes of entries of the `enr` subtree that still need to be resolved
+    unresolved_nodes: HashSet<String>,
+    /// Hashes of entries of the `link` subtree that still need to be resolved
+    unresolved_links: HashSet<String>,
+}
+
+// === impl SyncTree ===
+
+impl SyncTree {
+    /// Creates a new tree for the given root and marks both subtree roots as unresolved.
+    pub(crate) fn new(root: TreeRootEntry, link: LinkEntry) -> Self {
+        let mut tree = Self {
+            root: root.clone(),
+            link,
+            root_updated: Instant::now(),
+            unresolved_nodes: Default::default(),
+            unresolved_links: Default::default(),
+        };
+        tree.reset(root);
+        tree
+    }
+
+    /// Returns the link to this tree.
+    pub(crate) fn link(&self) -> &LinkEntry {
+        &self.link
+    }
+
+    /// Returns the current root of the tree.
+    pub(crate) fn root(&self) -> &TreeRootEntry {
+        &self.root
+    }
+
+    /// Returns `true` if the root was last updated more than `interval` ago.
+    pub(crate) fn is_stale(&self, now: Instant, interval: Duration) -> bool {
+        now.duration_since(self.root_updated) >= interval
+    }
+
+    /// Returns `true` if all entries of the tree are resolved.
+    pub(crate) fn is_synced(&self) -> bool {
+        self.unresolved_nodes.is_empty() && self.unresolved_links.is_empty()
+    }
+
+    /// Updates the root of the tree.
+    ///
+    /// Returns the kind and hash of all entries that need to be resolved if the root changed.
+    pub(crate) fn update_root(&mut self, root: TreeRootEntry) -> Vec<(ResolveKind, String)> {
+        self.root_updated = Instant::now();
+        // ignore outdated or unchanged roots
+        if root.sequence_number < self.root.sequence_number || root == self.root {
+            return Vec::new()
+        }
+        self.reset(root)
+    }
+
+    /// Marks the entry as resolved.
+    ///
+    /// Returns `false` if the entry was not expected for this tree.
+    pub(crate) fn resolved(&mut self, kind: ResolveKind, hash: &str) -> bool {
+        match kind {
+            ResolveKind::Enr => self.unresolved_nodes.remove(hash),
+            ResolveKind::Link => self.unresolved_links.remove(hash),
+        }
+    }
+
+    /// Marks the entry as unresolved.
+    ///
+    /// Returns `false` if the entry is already tracked.
+    pub(crate) fn unresolved(&mut self, kind: ResolveKind, hash: String) -> bool {
+        match kind {
+            ResolveKind::Enr => self.unresolved_nodes.insert(hash),
+            ResolveKind::Link => self.unresolved_links.insert(hash),
+        }
+    }
+
+    fn reset(&mut self, root: TreeRootEntry) -> Vec<(ResolveKind, String)> {
+        self.unresolved_nodes.clear();
+        self.unresolved_links.clear();
+        self.unresolved_nodes.insert(root.enr_root.clone());
+        self.unresolved_links.insert(root.link_root.clone());
+        let pending = vec![
+            (ResolveKind::Enr, root.enr_root.clone()),
+            (ResolveKind::Link, root.link_root.clone()),
+        ];
+        self.root = root;
+        pending
+    }
+}
+
+/// The subtree an entry belongs to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum ResolveKind {
+    /// Entry of the subtree containing node records
+    Enr,
+    /// Entry of the subtree containing links to other trees
+    Link,
+}
+
+// === impl ResolveKind ===
+
+impl ResolveKind {
+    pub(crate) fn is_link(&self) -> bool {
+        matches!(self, ResolveKind::Link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::{SecretKey, SECP256K1};
+
+    fn root(seq: u64) -> TreeRootEntry {
+        TreeRootEntry {
+            enr_root: format!("ENR{seq}"),
+            link_root: format!("LINK{seq}"),
+            sequence_number: seq,
+            signature: Default::default(),
+        }
+    }
+
+    #[test]
+    fn sync_tree_update_root() {
+        let sk = SecretKey::new(&mut secp256k1::rand::thread_rng());
+        let link =
+            LinkEntry { domain: "nodes.example.org".to_string(), pubkey: sk.public_key(SECP256K1) };
+        let mut tree = SyncTree::new(root(1), link);
+        assert!(!tree.is_synced());
+
+        assert!(tree.resolved(ResolveKind::Enr, "ENR1"));
+        assert!(tree.resolved(ResolveKind::Link, "LINK1"));
+        assert!(tree.is_synced());
+
+        // same root does not require a resync
+        assert!(tree.update_root(root(1)).is_empty());
+
+        let pending = tree.update_root(root(2));
+        assert_eq!(
+            pending,
+            vec![(ResolveKind::Enr, "ENR2".to_string()), (ResolveKind::Link, "LINK2".to_string())]
+        );
+        assert!(!tree.is_synced());
+    }
+}
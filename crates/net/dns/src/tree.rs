@@ -0,0 +1,424 @@
+//! Support for the [EIP-1459 DNS Record Structure](https://eips.ethereum.org/EIPS/eip-1459#dns-record-structure)
+//!
+//! The nodes in a list are encoded as a merkle tree for distribution via the DNS protocol. Entries
+//! of the merkle tree are contained in DNS TXT records. The root of the tree is a TXT record with
+//! the following content:
+//!
+//! ```text
+//! enrtree-root:v1 e=<enr-root> l=<link-root> seq=<sequence-number> sig=<signature>
+//! ```
+//!
+//! where
+//!
+//!    enr-root and link-root refer to the root hashes of subtrees containing nodes and links to
+//!    subtrees.
+//!   `sequence-number` is the tree’s update sequence number, a decimal integer.
+//!   `signature` is a 65-byte secp256k1 EC signature over the keccak256 hash of the record
+//! content, excluding the sig= part, encoded as URL-safe base64 (RFC-4648).
+
+use crate::error::{
+    ParseDnsEntryError,
+    ParseDnsEntryError::{FieldNotFound, UnknownEntry},
+    ParseEntryResult,
+};
+use data_encoding::{BASE32_NOPAD, BASE64URL_NOPAD};
+use enr::Enr;
+use reth_primitives::{keccak256, Bytes};
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    Message, PublicKey, SecretKey, SECP256K1,
+};
+use std::{fmt, str::FromStr};
+
+/// Prefix used for root entries in the ENR tree.
+const ROOT_V1_PREFIX: &str = "enrtree-root:v1";
+/// Prefix used for link entries in the ENR tree.
+const LINK_PREFIX: &str = "enrtree://";
+/// Prefix used for branch entries in the ENR tree.
+const BRANCH_PREFIX: &str = "enrtree-branch:";
+/// Prefix used for ENRs in the ENR tree.
+const ENR_PREFIX: &str = "enr:";
+
+/// The minimum length of a decoded subtree hash.
+///
+/// Mirrors geth's `minHashLength`.
+const MIN_HASH_LENGTH: usize = 12;
+
+/// Represents all variants of DNS entries for Ethereum node lists.
+#[derive(Debug, Clone)]
+pub enum DnsEntry {
+    /// The root of the tree
+    Root(TreeRootEntry),
+    /// A link to another tree
+    Link(LinkEntry),
+    /// A branch of the tree
+    Branch(BranchEntry),
+    /// A leaf containing a node record
+    Node(NodeEntry),
+}
+
+impl fmt::Display for DnsEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsEntry::Root(entry) => fmt::Display::fmt(entry, f),
+            DnsEntry::Link(entry) => fmt::Display::fmt(entry, f),
+            DnsEntry::Branch(entry) => fmt::Display::fmt(entry, f),
+            DnsEntry::Node(entry) => fmt::Display::fmt(entry, f),
+        }
+    }
+}
+
+impl FromStr for DnsEntry {
+    type Err = ParseDnsEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(s) = s.strip_prefix(ROOT_V1_PREFIX) {
+            TreeRootEntry::parse_value(s).map(DnsEntry::Root)
+        } else if let Some(s) = s.strip_prefix(BRANCH_PREFIX) {
+            BranchEntry::parse_value(s).map(DnsEntry::Branch)
+        } else if let Some(s) = s.strip_prefix(LINK_PREFIX) {
+            LinkEntry::parse_value(s).map(DnsEntry::Link)
+        } else if let Some(s) = s.strip_prefix(ENR_PREFIX) {
+            NodeEntry::parse_value(s).map(DnsEntry::Node)
+        } else {
+            Err(UnknownEntry(s.to_string()))
+        }
+    }
+}
+
+/// Represents an `enr-root` hash of subtrees containing nodes and links.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TreeRootEntry {
+    /// The root hash of the subtree containing node records.
+    pub enr_root: String,
+    /// The root hash of the subtree containing links to other trees.
+    pub link_root: String,
+    /// The update sequence number of the tree.
+    pub sequence_number: u64,
+    /// The signature over the root content.
+    pub signature: Bytes,
+}
+
+// === impl TreeRootEntry ===
+
+impl TreeRootEntry {
+    /// Parses the entry from text.
+    ///
+    /// Caution: This assumes the prefix is already removed.
+    fn parse_value(mut input: &str) -> ParseEntryResult<Self> {
+        let input = &mut input;
+        let enr_root = parse_value(input, "e=", "ENR Root", |s| Ok(s.to_string()))?;
+        let link_root = parse_value(input, "l=", "Link Root", |s| Ok(s.to_string()))?;
+        let sequence_number = parse_value(input, "seq=", "Sequence number", |s| {
+            s.parse::<u64>().map_err(|_| {
+                ParseDnsEntryError::Other(format!("Failed to parse sequence number {s}"))
+            })
+        })?;
+        let signature = parse_value(input, "sig=", "Signature", |s| {
+            BASE64URL_NOPAD.decode(s.as_bytes()).map_err(|err| {
+                ParseDnsEntryError::Base64DecodeError(format!("signature error: {err}"))
+            })
+        })?
+        .into();
+
+        Ok(Self { enr_root, link_root, sequence_number, signature })
+    }
+
+    /// Returns the _unsigned_ content pairs of the entry:
+    ///
+    /// ```text
+    /// e=<enr-root> l=<link-root> seq=<sequence-number> sig=<signature>
+    /// ```
+    fn content(&self) -> String {
+        format!(
+            "{} e={} l={} seq={}",
+            ROOT_V1_PREFIX, self.enr_root, self.link_root, self.sequence_number
+        )
+    }
+
+    /// Signs the content with the given key and stores the signature.
+    pub fn sign(&mut self, key: &SecretKey) {
+        let msg = Message::from_slice(keccak256(self.content().as_bytes()).as_ref())
+            .expect("keccak256 is 32 bytes; qed");
+        let (recovery_id, sig) = SECP256K1.sign_ecdsa_recoverable(&msg, key).serialize_compact();
+        let mut signature = sig.to_vec();
+        signature.push(recovery_id.to_i32() as u8);
+        self.signature = signature.into();
+    }
+
+    /// Verify the signature of the record against the given public key.
+    pub fn verify(&self, pubkey: &PublicKey) -> bool {
+        if self.signature.len() != 65 {
+            return false
+        }
+        let Ok(recovery_id) = RecoveryId::from_i32(self.signature[64] as i32) else { return false };
+        let Ok(sig) = RecoverableSignature::from_compact(&self.signature[..64], recovery_id) else {
+            return false
+        };
+        let msg = Message::from_slice(keccak256(self.content().as_bytes()).as_ref())
+            .expect("keccak256 is 32 bytes; qed");
+        SECP256K1.verify_ecdsa(&msg, &sig.to_standard(), pubkey).is_ok()
+    }
+}
+
+impl FromStr for TreeRootEntry {
+    type Err = ParseDnsEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(s) = s.strip_prefix(ROOT_V1_PREFIX) {
+            Self::parse_value(s)
+        } else {
+            Err(UnknownEntry(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for TreeRootEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sig={}", self.content(), BASE64URL_NOPAD.encode(self.signature.as_ref()))
+    }
+}
+
+/// A branch entry with base32 hashes of child entries.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BranchEntry {
+    /// The subdomain hashes of all child entries.
+    pub children: Vec<String>,
+}
+
+// === impl BranchEntry ===
+
+impl BranchEntry {
+    /// Parses the entry from text.
+    ///
+    /// Caution: This assumes the prefix is already removed.
+    fn parse_value(input: &str) -> ParseEntryResult<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(Self { children: Vec::new() })
+        }
+        let children = input
+            .split(',')
+            .map(str::trim)
+            .map(|c| {
+                BASE32_NOPAD
+                    .decode(c.as_bytes())
+                    .ok()
+                    .filter(|decoded| decoded.len() >= MIN_HASH_LENGTH)
+                    .map(|_| c.to_string())
+                    .ok_or_else(|| ParseDnsEntryError::InvalidChildHash(c.to_string()))
+            })
+            .collect::<ParseEntryResult<Vec<_>>>()?;
+        Ok(Self { children })
+    }
+}
+
+impl FromStr for BranchEntry {
+    type Err = ParseDnsEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(s) = s.strip_prefix(BRANCH_PREFIX) {
+            Self::parse_value(s)
+        } else {
+            Err(UnknownEntry(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for BranchEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", BRANCH_PREFIX, self.children.join(","))
+    }
+}
+
+/// A link entry pointing to another tree, `enrtree://<key>@<fqdn>`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LinkEntry {
+    /// The fully qualified domain name of the linked tree.
+    pub domain: String,
+    /// The public key that signs the linked tree.
+    pub pubkey: PublicKey,
+}
+
+// === impl LinkEntry ===
+
+impl LinkEntry {
+    /// Parses the entry from text.
+    ///
+    /// Caution: This assumes the prefix is already removed.
+    fn parse_value(input: &str) -> ParseEntryResult<Self> {
+        let (pubkey, domain) = input.split_once('@').ok_or_else(|| {
+            ParseDnsEntryError::Other(format!("Missing @ delimiter in Link entry: {input}"))
+        })?;
+        let pubkey = BASE32_NOPAD
+            .decode(pubkey.as_bytes())
+            .map_err(|err| ParseDnsEntryError::Base32DecodeError(format!("pubkey error: {err}")))?;
+        let pubkey = PublicKey::from_slice(&pubkey)
+            .map_err(|err| ParseDnsEntryError::Other(format!("Failed to decode pubkey: {err}")))?;
+        Ok(Self { domain: domain.to_string(), pubkey })
+    }
+}
+
+impl FromStr for LinkEntry {
+    type Err = ParseDnsEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(s) = s.strip_prefix(LINK_PREFIX) {
+            Self::parse_value(s)
+        } else {
+            Err(UnknownEntry(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for LinkEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}@{}",
+            LINK_PREFIX,
+            BASE32_NOPAD.encode(&self.pubkey.serialize()),
+            self.domain
+        )
+    }
+}
+
+/// The actual [Enr] entry.
+#[derive(Debug, Clone)]
+pub struct NodeEntry {
+    /// The node record of the leaf.
+    pub enr: Enr<SecretKey>,
+}
+
+// === impl NodeEntry ===
+
+impl NodeEntry {
+    /// Parses the entry from text.
+    ///
+    /// Caution: This assumes the prefix is already removed.
+    fn parse_value(s: &str) -> ParseEntryResult<Self> {
+        let enr: Enr<SecretKey> = s.parse().map_err(ParseDnsEntryError::Other)?;
+        Ok(Self { enr })
+    }
+}
+
+impl FromStr for NodeEntry {
+    type Err = ParseDnsEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(s) = s.strip_prefix(ENR_PREFIX) {
+            Self::parse_value(s)
+        } else {
+            Err(UnknownEntry(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for NodeEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.enr.to_base64())
+    }
+}
+
+/// Returns the subdomain hash of the given entry text: the base32 encoding of the first 16 bytes
+/// of the keccak256 hash of the text.
+pub fn subdomain_hash(entry: &str) -> String {
+    BASE32_NOPAD.encode(&keccak256(entry.as_bytes())[..16])
+}
+
+/// Returns true if the hash of the resolved entry matches the requested subdomain hash.
+pub(crate) fn is_valid_entry_hash(hash: &str, entry: &str) -> bool {
+    let Ok(want) = BASE32_NOPAD.decode(hash.as_bytes()) else { return false };
+    want.len() >= MIN_HASH_LENGTH && keccak256(entry.as_bytes()).starts_with(&want)
+}
+
+/// Parses the value of the key value pair
+fn parse_value<F, V>(input: &mut &str, key: &str, err: &'static str, f: F) -> ParseEntryResult<V>
+where
+    F: Fn(&str) -> ParseEntryResult<V>,
+{
+    ensure_strip_key(input, key, err)?;
+    let val = input.split_whitespace().next().ok_or(FieldNotFound(err))?;
+    *input = &input[val.len()..];
+
+    f(val)
+}
+
+/// Strips the `key` from the `input`
+///
+/// Returns an err if the `input` does not start with the `key`
+fn ensure_strip_key(input: &mut &str, key: &str, err: &'static str) -> ParseEntryResult<()> {
+    *input = input.trim_start().strip_prefix(key).ok_or(FieldNotFound(err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_root_entry() {
+        let s = "enrtree-root:v1 e=QFT4PBCRX4XQCV3VUYJ6BTCEPU l=JGUFMSAGI7KZYB3P7IZW4S5Y3A seq=3 sig=3FmXuVwpa8Y7OstZTx9PIb1mt8FrW7VpDOFv4AaGCsZ2EIHmhraWhe4NxYhQDlw5MjeFXYMbJjsPeKlHzmJREQE";
+        let root: TreeRootEntry = s.parse().unwrap();
+        assert_eq!(root.enr_root, "QFT4PBCRX4XQCV3VUYJ6BTCEPU");
+        assert_eq!(root.link_root, "JGUFMSAGI7KZYB3P7IZW4S5Y3A");
+        assert_eq!(root.sequence_number, 3);
+        assert_eq!(root.signature.len(), 65);
+        assert_eq!(s, root.to_string());
+    }
+
+    #[test]
+    fn sign_and_verify_root_entry() {
+        let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+        let mut root = TreeRootEntry {
+            enr_root: "QFT4PBCRX4XQCV3VUYJ6BTCEPU".to_string(),
+            link_root: "JGUFMSAGI7KZYB3P7IZW4S5Y3A".to_string(),
+            sequence_number: 1,
+            signature: Default::default(),
+        };
+        root.sign(&secret_key);
+        assert!(root.verify(&secret_key.public_key(SECP256K1)));
+
+        let other = SecretKey::new(&mut secp256k1::rand::thread_rng());
+        assert!(!root.verify(&other.public_key(SECP256K1)));
+
+        root.sequence_number += 1;
+        assert!(!root.verify(&secret_key.public_key(SECP256K1)));
+    }
+
+    #[test]
+    fn parse_link_entry() {
+        let s = "enrtree://AM5FCQLWIZX2QFPNJAP7VUERCCRNGRHWZG3YYHIUV7BVDQ5FDPRT2@nodes.example.org";
+        let entry: LinkEntry = s.parse().unwrap();
+        assert_eq!(entry.domain, "nodes.example.org");
+        assert_eq!(s, entry.to_string());
+    }
+
+    #[test]
+    fn parse_branch_entry() {
+        let s = "enrtree-branch:2XS2367YHAXJFGLZHVAWLQD4ZY,H4FHT4B454P6UXFD7JCYQ5PWDY,MHTDO6TMUBRIA2XWG5LUDACK24";
+        let entry: BranchEntry = s.parse().unwrap();
+        assert_eq!(entry.children.len(), 3);
+        assert_eq!(s, entry.to_string());
+
+        let empty: BranchEntry = "enrtree-branch:".parse().unwrap();
+        assert!(empty.children.is_empty());
+
+        assert!("enrtree-branch:1,2".parse::<BranchEntry>().is_err());
+    }
+
+    #[test]
+    fn parse_node_entry() {
+        let s = "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8";
+        let entry: NodeEntry = s.parse().unwrap();
+        assert_eq!(s, entry.to_string());
+    }
+
+    #[test]
+    fn entry_hash() {
+        let entry = "enrtree-branch:";
+        let hash = subdomain_hash(entry);
+        assert!(is_valid_entry_hash(&hash, entry));
+        assert!(!is_valid_entry_hash(&hash, "enrtree-branch:AAAAAAAAAAAAAAAAAAAA"));
+    }
+}
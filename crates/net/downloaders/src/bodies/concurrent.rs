@@ -1,7 +1,8 @@
 use backon::{ExponentialBackoff, Retryable};
 use futures_util::{stream, StreamExt, TryStreamExt};
+use reth_eth_wire::BlockBody;
 use reth_interfaces::{
-    consensus::Consensus as ConsensusTrait,
+    consensus::{self, Consensus as ConsensusTrait},
     p2p::{
         bodies::{
             client::BodiesClient,
@@ -11,7 +12,7 @@ use reth_interfaces::{
         error::{DownloadError, DownloadResult, RequestError},
     },
 };
-use reth_primitives::{SealedBlock, SealedHeader};
+use reth_primitives::{proofs, SealedBlock, SealedHeader};
 use std::{borrow::Borrow, sync::Arc};
 
 /// Downloads bodies in batches.
@@ -112,7 +113,8 @@ where
     /// 2. Return early with the header values, if there were no non-empty headers, else..
     /// 3. Request the bodies for the non-empty headers from a peer chosen by the network client
     /// 4. For any non-empty headers, it proceeds to validate the corresponding body from the peer
-    /// and return it as part of the response via the [`BlockResponse::Full`] variant.
+    /// against the transactions root and ommers hash of the header and return it as part of the
+    /// response via the [`BlockResponse::Full`] variant.
     ///
     /// NB: This assumes that peers respond with bodies in the order that they were requested.
     /// This is a reasonable assumption to make as that's [what Geth
@@ -158,27 +160,52 @@ where
                     }
                 };
 
-                let block = SealedBlock {
-                    header: header.clone(),
-                    body: body.transactions,
-                    ommers: body.ommers.into_iter().map(|header| header.seal()).collect(),
-                };
-
                 // This ensures that the TxRoot and OmmersRoot from the header match the
-                // ones calculated manually from the block body.
-                self.consensus.pre_validate_block(&block).map_err(|error| {
+                // ones calculated manually from the block body, before anything is written.
+                validate_body(&header, &body).map_err(|error| {
                     self.client.report_bad_message(peer_id);
                     DownloadError::BlockValidation { hash: header.hash(), error }
                 })?;
 
-                responses.push(BlockResponse::Full(block));
+                responses.push(BlockResponse::Full(SealedBlock {
+                    header,
+                    body: body.transactions,
+                    ommers: body.ommers.into_iter().map(|header| header.seal()).collect(),
+                }));
             }
         }
 
+        // The peer sent bodies we did not ask for
+        if bodies.next().is_some() {
+            self.client.report_bad_message(peer_id);
+            return Err(DownloadError::RequestError(RequestError::BadResponse))
+        }
+
         Ok(responses)
     }
 }
 
+/// Checks that the body matches the transactions root and ommers hash of its header.
+fn validate_body(header: &SealedHeader, body: &BlockBody) -> Result<(), consensus::Error> {
+    let ommers_hash = proofs::calculate_ommers_root(body.ommers.iter());
+    if header.ommers_hash != ommers_hash {
+        return Err(consensus::Error::BodyOmmersHashDiff {
+            got: ommers_hash,
+            expected: header.ommers_hash,
+        })
+    }
+
+    let transactions_root = proofs::calculate_transaction_root(body.transactions.iter());
+    if header.transactions_root != transactions_root {
+        return Err(consensus::Error::BodyTransactionRootDiff {
+            got: transactions_root,
+            expected: header.transactions_root,
+        })
+    }
+
+    Ok(())
+}
+
 // TODO: Cleanup
 #[cfg(test)]
 mod tests {
@@ -189,7 +216,10 @@ mod tests {
     use reth_eth_wire::BlockBody;
     use reth_interfaces::{
         p2p::{bodies::downloader::BodyDownloader, error::RequestError},
-        test_utils::TestConsensus,
+        test_utils::{
+            generators::{random_block, random_block_range},
+            ResponseFault, TestConsensus, TestFullBlockClient,
+        },
     };
     use reth_primitives::{Header, PeerId, H256};
     use std::{
@@ -200,6 +230,15 @@ mod tests {
         time::Duration,
     };
 
+    /// A header of a block without transactions and a single default ommer.
+    fn header_with_ommer() -> SealedHeader {
+        Header {
+            ommers_hash: proofs::calculate_ommers_root([Header::default()].iter()),
+            ..Default::default()
+        }
+        .seal()
+    }
+
     // Check that the blocks are emitted in order of block number, not in order of
     // first-downloaded
     #[tokio::test]
@@ -293,7 +332,10 @@ mod tests {
                     } else {
                         Ok((
                             PeerId::default(),
-                            vec![BlockBody { transactions: vec![], ommers: vec![] }],
+                            vec![BlockBody {
+                                transactions: vec![],
+                                ommers: vec![Header::default()],
+                            }],
                         )
                             .into())
                     }
@@ -302,7 +344,7 @@ mod tests {
             Arc::new(TestConsensus::default()),
         );
 
-        let header = Header { ommers_hash: H256::default(), ..Default::default() }.seal();
+        let header = header_with_ommer();
         assert_matches!(
             downloader.bodies_stream(&[header.clone()]).next().await,
             Some(Ok(BlockResponse::Full(block))) => {
@@ -327,7 +369,10 @@ mod tests {
                     } else {
                         Ok((
                             PeerId::default(),
-                            vec![BlockBody { transactions: vec![], ommers: vec![] }],
+                            vec![BlockBody {
+                                transactions: vec![],
+                                ommers: vec![Header::default()],
+                            }],
                         )
                             .into())
                     }
@@ -338,14 +383,82 @@ mod tests {
         .with_retries(0);
 
         assert_matches!(
-            downloader
-                .bodies_stream(&[
-                    Header { ommers_hash: H256::default(), ..Default::default() }.seal()
-                ])
-                .next()
-                .await,
+            downloader.bodies_stream(&[header_with_ommer()]).next().await,
             Some(Err(DownloadError::RequestError(RequestError::Timeout)))
         );
         assert_eq!(Arc::try_unwrap(retries_left).unwrap().into_inner(), 2);
     }
+
+    /// Checks that bodies that do not match their headers are rejected and re-requested
+    #[tokio::test]
+    async fn rejects_invalid_bodies() {
+        let block = random_block(1, None, Some(1));
+        let requests = Arc::new(AtomicUsize::new(0));
+        let downloader = ConcurrentDownloader::new(
+            Arc::new(TestBodiesClient::new(|_: Vec<H256>| {
+                let requests = requests.clone();
+                let ommers = block.ommers.iter().map(|ommer| ommer.clone().unseal()).collect();
+                async move {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    // the transactions of the block are missing
+                    Ok((PeerId::default(), vec![BlockBody { transactions: vec![], ommers }]).into())
+                }
+            })),
+            Arc::new(TestConsensus::default()),
+        )
+        .with_retries(1);
+
+        assert_matches!(
+            downloader.bodies_stream(&[block.header.clone()]).next().await,
+            Some(Err(DownloadError::BlockValidation {
+                hash,
+                error: consensus::Error::BodyTransactionRootDiff { .. }
+            })) if hash == block.hash()
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    /// Checks that responses with more bodies than requested are rejected
+    #[tokio::test]
+    async fn rejects_unrequested_bodies() {
+        let downloader = ConcurrentDownloader::new(
+            Arc::new(TestBodiesClient::new(|_: Vec<H256>| async {
+                let body = BlockBody { transactions: vec![], ommers: vec![Header::default()] };
+                Ok((PeerId::default(), vec![body.clone(), body]).into())
+            })),
+            Arc::new(TestConsensus::default()),
+        )
+        .with_retries(0);
+
+        assert_matches!(
+            downloader.bodies_stream(&[header_with_ommer()]).next().await,
+            Some(Err(DownloadError::RequestError(RequestError::BadResponse)))
+        );
+    }
+
+    /// Checks that peers are penalized for bodies that do not match their headers and that the
+    /// request is retried
+    #[tokio::test]
+    async fn penalizes_malicious_peers() {
+        let blocks = random_block_range(0..5, H256::zero(), 1..3);
+        let peer_id = PeerId::from_low_u64_be(1);
+        let client = Arc::new(TestFullBlockClient::new(blocks.clone()).with_peer_id(peer_id));
+        client.push_body_fault(ResponseFault::Malicious);
+        client.push_body_fault(ResponseFault::Truncate(2));
+
+        let downloader =
+            ConcurrentDownloader::new(client.clone(), Arc::new(TestConsensus::default()));
+        let headers = blocks.iter().map(|block| block.header.clone()).collect::<Vec<_>>();
+        assert_matches!(
+            downloader.bodies_stream(headers.iter()).try_collect::<Vec<_>>().await,
+            Ok(responses) => {
+                assert_eq!(
+                    responses,
+                    blocks.into_iter().map(BlockResponse::Full).collect::<Vec<_>>()
+                );
+            }
+        );
+        assert_eq!(client.reported_peers(), vec![peer_id, peer_id]);
+        assert_eq!(client.body_requests(), 3);
+    }
 }
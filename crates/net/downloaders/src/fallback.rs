@@ -0,0 +1,122 @@
+use reth_eth_wire::BlockBody;
+use reth_interfaces::p2p::{
+    bodies::client::BodiesClient,
+    downloader::DownloadClient,
+    error::PeerRequestResult,
+    headers::client::{BlockHeaders, HeadersClient, HeadersRequest},
+};
+use reth_primitives::{PeerId, H256};
+use tracing::debug;
+
+/// A client that retries the requests that the primary client fails to answer with a fallback
+/// client, e.g. the peers of the network with an archive mirror.
+///
+/// A request is retried if it failed or the response is empty, and the response of the primary
+/// client is returned if the fallback client fails as well.
+#[derive(Debug)]
+pub struct FallbackClient<P, F> {
+    primary: P,
+    fallback: Option<F>,
+}
+
+// === impl FallbackClient ===
+
+impl<P, F> FallbackClient<P, F> {
+    /// Creates a new client, requests are only retried if there is a fallback client.
+    pub fn new(primary: P, fallback: Option<F>) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+impl<P: DownloadClient, F: DownloadClient> DownloadClient for FallbackClient<P, F> {
+    fn report_bad_message(&self, peer_id: PeerId) {
+        self.primary.report_bad_message(peer_id);
+        if let Some(fallback) = &self.fallback {
+            fallback.report_bad_message(peer_id);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: HeadersClient, F: HeadersClient> HeadersClient for FallbackClient<P, F> {
+    async fn get_headers(&self, request: HeadersRequest) -> PeerRequestResult<BlockHeaders> {
+        let response = self.primary.get_headers(request.clone()).await;
+        let fallback = match &self.fallback {
+            Some(fallback) if response.as_ref().map_or(true, |res| res.1 .0.is_empty()) => fallback,
+            _ => return response,
+        };
+
+        debug!(target: "downloaders::fallback", ?request, err = ?response.as_ref().err(), "Retrying headers request");
+        match fallback.get_headers(request).await {
+            Ok(res) if !res.1 .0.is_empty() => Ok(res),
+            _ => response,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: BodiesClient, F: BodiesClient> BodiesClient for FallbackClient<P, F> {
+    async fn get_block_bodies(&self, hashes: Vec<H256>) -> PeerRequestResult<Vec<BlockBody>> {
+        let response = self.primary.get_block_bodies(hashes.clone()).await;
+        let fallback = match &self.fallback {
+            Some(fallback) if response.as_ref().map_or(true, |res| res.1.is_empty()) => fallback,
+            _ => return response,
+        };
+
+        debug!(target: "downloaders::fallback", len = hashes.len(), err = ?response.as_ref().err(), "Retrying bodies request");
+        match fallback.get_block_bodies(hashes).await {
+            Ok(res) if !res.1.is_empty() => Ok(res),
+            _ => response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_interfaces::{
+        p2p::error::RequestError,
+        test_utils::{generators::random_header, TestHeadersClient},
+    };
+    use reth_primitives::HeadersDirection;
+
+    fn request() -> HeadersRequest {
+        HeadersRequest { start: 0u64.into(), limit: 1, direction: HeadersDirection::Rising }
+    }
+
+    #[tokio::test]
+    async fn retry_failed_requests() {
+        let primary = TestHeadersClient::default();
+        primary.set_error(RequestError::Timeout).await;
+        let fallback = TestHeadersClient::default();
+        let header = random_header(1, None).unseal();
+        fallback.extend([header.clone()]).await;
+
+        let client = FallbackClient::new(primary, Some(fallback));
+        let response = client.get_headers(request()).await.unwrap();
+        assert_eq!(response.1 .0, vec![header]);
+        assert_eq!(client.primary.request_attempts(), 1);
+        assert_eq!(client.fallback.as_ref().unwrap().request_attempts(), 1);
+
+        // the error of the primary client is returned if the fallback fails too
+        let response = client.get_headers(request()).await;
+        assert!(matches!(response, Err(RequestError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn use_primary_response() {
+        let primary = TestHeadersClient::default();
+        let header = random_header(1, None).unseal();
+        primary.extend([header.clone()]).await;
+        let fallback = TestHeadersClient::default();
+
+        let client = FallbackClient::new(primary, Some(fallback));
+        let response = client.get_headers(request()).await.unwrap();
+        assert_eq!(response.1 .0, vec![header]);
+        assert_eq!(client.fallback.as_ref().unwrap().request_attempts(), 0);
+
+        // without a fallback client the empty response is returned
+        let client = FallbackClient::<_, TestHeadersClient>::new(client.primary, None);
+        assert!(client.get_headers(request()).await.unwrap().1 .0.is_empty());
+    }
+}
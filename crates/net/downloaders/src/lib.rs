@@ -13,5 +13,11 @@ pub mod bodies;
 /// The collection of alhgorithms for downloading block headers.
 pub mod headers;
 
+/// A client that retries failed requests with another client.
+pub mod fallback;
+
+/// A client that downloads headers and bodies from HTTP archive mirrors.
+pub mod mirror;
+
 #[cfg(test)]
 mod test_utils;
@@ -0,0 +1,467 @@
+use reqwest::{header::RANGE, StatusCode};
+use reth_eth_wire::BlockBody;
+use reth_interfaces::p2p::{
+    bodies::client::BodiesClient,
+    downloader::DownloadClient,
+    error::{PeerRequestResult, RequestError},
+    headers::client::{BlockHeaders, HeadersClient, HeadersRequest},
+};
+use reth_primitives::{BlockHashOrNumber, BlockNumber, Header, PeerId, H256};
+use reth_rlp::{Decodable, DecodeError};
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+use tracing::warn;
+
+/// The size of a record of the `index` file in bytes.
+pub const INDEX_RECORD_SIZE: u64 = 56;
+
+/// The default number of blocks, counted from the last block of the mirror, that are searched for
+/// a block hash that was not seen before.
+pub const DEFAULT_SEARCH_DEPTH: u64 = 8192;
+
+/// The number of index records that are requested at once while searching for a block hash.
+const SEARCH_BATCH_SIZE: u64 = 1024;
+
+/// The position of a block in the flat files of a mirror.
+///
+/// The `index` file of a mirror consists of one record per block, starting at genesis. A record
+/// is the block hash followed by the big-endian offset (8 bytes) and length (4 bytes) of the RLP
+/// encoded header in the `headers` file and the same for the body in the `bodies` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IndexRecord {
+    /// The block hash.
+    pub hash: H256,
+    /// The offset of the header in the `headers` file.
+    pub header_offset: u64,
+    /// The length of the header.
+    pub header_len: u32,
+    /// The offset of the body in the `bodies` file.
+    pub body_offset: u64,
+    /// The length of the body.
+    pub body_len: u32,
+}
+
+// === impl IndexRecord ===
+
+impl IndexRecord {
+    /// Appends the encoded record to `out`.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.hash.as_bytes());
+        out.extend_from_slice(&self.header_offset.to_be_bytes());
+        out.extend_from_slice(&self.header_len.to_be_bytes());
+        out.extend_from_slice(&self.body_offset.to_be_bytes());
+        out.extend_from_slice(&self.body_len.to_be_bytes());
+    }
+
+    /// Decodes a record, `buf` must be [INDEX_RECORD_SIZE] bytes long.
+    fn decode(buf: &[u8]) -> Self {
+        let u64_at = |pos: usize| u64::from_be_bytes(buf[pos..pos + 8].try_into().unwrap());
+        let u32_at = |pos: usize| u32::from_be_bytes(buf[pos..pos + 4].try_into().unwrap());
+        Self {
+            hash: H256::from_slice(&buf[..32]),
+            header_offset: u64_at(32),
+            header_len: u32_at(40),
+            body_offset: u64_at(44),
+            body_len: u32_at(52),
+        }
+    }
+}
+
+/// Errors of requests to a mirror.
+#[derive(Debug, thiserror::Error)]
+pub enum MirrorError {
+    /// The request failed.
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    /// The mirror responded with an unexpected status.
+    #[error("Unexpected status {0}.")]
+    Status(StatusCode),
+    /// The index of the mirror is malformed.
+    #[error("Malformed index.")]
+    MalformedIndex,
+    /// A header or body could not be decoded.
+    #[error("Failed to decode the response: {0}.")]
+    Decode(#[from] DecodeError),
+    /// The hash of a header does not match the hash in the index.
+    #[error("Block {number} has the hash {got:?} instead of {expected:?}.")]
+    HashMismatch {
+        /// The number of the block.
+        number: BlockNumber,
+        /// The hash in the index.
+        expected: H256,
+        /// The hash of the header.
+        got: H256,
+    },
+}
+
+impl From<MirrorError> for RequestError {
+    fn from(err: MirrorError) -> Self {
+        match err {
+            MirrorError::Http(err) if err.is_timeout() => RequestError::Timeout,
+            MirrorError::Http(_) => RequestError::ConnectionDropped,
+            _ => RequestError::BadResponse,
+        }
+    }
+}
+
+/// Downloads headers and bodies from HTTP(S) archive mirrors.
+///
+/// Each mirror serves the flat files `index`, `headers` and `bodies` below its base URL, see
+/// [IndexRecord], and only answers range requests for the parts that are needed. All mirrors must
+/// serve identical files, the requests are spread over them and retried at the next mirror if one
+/// fails.
+///
+/// The downloaded headers are checked against the hashes of the index, beyond that the data is
+/// validated by the downloaders like data of peers.
+#[derive(Debug)]
+pub struct MirrorClient {
+    client: reqwest::Client,
+    /// The base URLs of the mirrors.
+    mirrors: Vec<String>,
+    /// The mirror that receives the next request.
+    next_mirror: AtomicUsize,
+    /// The numbers of all block hashes seen in the index so far.
+    numbers: Mutex<HashMap<H256, BlockNumber>>,
+    /// The number of blocks from the end of the index that are searched for an unknown hash.
+    search_depth: u64,
+}
+
+// === impl MirrorClient ===
+
+impl MirrorClient {
+    /// Creates a new client for the mirrors at the given base URLs.
+    pub fn new(mirrors: Vec<String>) -> Self {
+        let mirrors =
+            mirrors.into_iter().map(|url| url.trim_end_matches('/').to_string()).collect();
+        Self {
+            client: reqwest::Client::new(),
+            mirrors,
+            next_mirror: AtomicUsize::new(0),
+            numbers: Default::default(),
+            search_depth: DEFAULT_SEARCH_DEPTH,
+        }
+    }
+
+    /// Sets the number of blocks from the end of the index that are searched for a block hash
+    /// that was not seen before.
+    pub fn with_search_depth(mut self, search_depth: u64) -> Self {
+        self.search_depth = search_depth;
+        self
+    }
+
+    /// Returns the headers of the request.
+    pub async fn headers(&self, request: HeadersRequest) -> Result<Vec<Header>, MirrorError> {
+        let start = match request.start {
+            BlockHashOrNumber::Hash(hash) => match self.number(hash).await? {
+                Some(number) => number,
+                None => return Ok(Vec::new()),
+            },
+            BlockHashOrNumber::Number(number) => number,
+        };
+        // the direction maps to the `reverse` flag of the request, like it is sent to peers
+        let reverse = bool::from(request.direction);
+        let range = headers_range(start, request.limit, reverse);
+
+        let records = self.records(range.clone()).await?;
+        let (first, last) = match (records.first(), records.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        let data = self
+            .get_range("headers", first.header_offset..last.header_offset + last.header_len as u64)
+            .await?;
+        let mut headers = decode_headers(range.start, &records, &data)?;
+
+        if reverse {
+            headers.reverse();
+        }
+        Ok(headers)
+    }
+
+    /// Returns the bodies of the given blocks, in the same order.
+    ///
+    /// Stops at the first block that is not found.
+    pub async fn bodies(&self, hashes: Vec<H256>) -> Result<Vec<BlockBody>, MirrorError> {
+        let mut numbers = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            match self.number(hash).await? {
+                Some(number) => numbers.push(number),
+                None => break,
+            }
+        }
+        let (min, max) = match (numbers.iter().min(), numbers.iter().max()) {
+            (Some(min), Some(max)) => (*min, *max),
+            _ => return Ok(Vec::new()),
+        };
+
+        let records = self.records(min..max + 1).await?;
+        let (first, last) = match (records.first(), records.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        let data = self
+            .get_range("bodies", first.body_offset..last.body_offset + last.body_len as u64)
+            .await?;
+
+        let mut bodies = Vec::with_capacity(numbers.len());
+        for number in numbers {
+            match records.get((number - min) as usize) {
+                Some(record) => bodies.push(decode_body(first.body_offset, record, &data)?),
+                None => break,
+            }
+        }
+        Ok(bodies)
+    }
+
+    /// Returns the number of the block with the given hash.
+    ///
+    /// Hashes that were not seen in the index before are searched in the last blocks of the
+    /// index.
+    async fn number(&self, hash: H256) -> Result<Option<BlockNumber>, MirrorError> {
+        if let Some(number) = self.numbers.lock().unwrap().get(&hash) {
+            return Ok(Some(*number))
+        }
+
+        let end = self.block_count().await?;
+        let mut batch_end = end;
+        while batch_end > end.saturating_sub(self.search_depth) {
+            let batch_start = batch_end.saturating_sub(SEARCH_BATCH_SIZE);
+            let records = self.records(batch_start..batch_end).await?;
+            if let Some(pos) = records.iter().position(|record| record.hash == hash) {
+                return Ok(Some(batch_start + pos as u64))
+            }
+            batch_end = batch_start;
+        }
+        Ok(None)
+    }
+
+    /// Returns the number of blocks in the index.
+    async fn block_count(&self) -> Result<u64, MirrorError> {
+        let len = self.request(|mirror| self.client.head(format!("{mirror}/index"))).await?;
+        let len = len.content_length().ok_or(MirrorError::MalformedIndex)?;
+        Ok(len / INDEX_RECORD_SIZE)
+    }
+
+    /// Returns the index records of the blocks in the range, or less if the index ends before.
+    async fn records(&self, range: Range<BlockNumber>) -> Result<Vec<IndexRecord>, MirrorError> {
+        let start = range.start;
+        let range = range.start.saturating_mul(INDEX_RECORD_SIZE)..
+            range.end.saturating_mul(INDEX_RECORD_SIZE);
+        let data = self.get_range("index", range).await?;
+        if data.len() as u64 % INDEX_RECORD_SIZE != 0 {
+            return Err(MirrorError::MalformedIndex)
+        }
+
+        let records = data
+            .chunks_exact(INDEX_RECORD_SIZE as usize)
+            .map(IndexRecord::decode)
+            .collect::<Vec<_>>();
+        let mut numbers = self.numbers.lock().unwrap();
+        for (number, record) in (start..).zip(&records) {
+            numbers.insert(record.hash, number);
+        }
+        Ok(records)
+    }
+
+    /// Requests the range of bytes of a file.
+    ///
+    /// Returns less bytes if the file ends before.
+    async fn get_range(&self, file: &str, range: Range<u64>) -> Result<Vec<u8>, MirrorError> {
+        if range.is_empty() {
+            return Ok(Vec::new())
+        }
+        let response = self
+            .request(|mirror| {
+                self.client
+                    .get(format!("{mirror}/{file}"))
+                    .header(RANGE, format!("bytes={}-{}", range.start, range.end - 1))
+            })
+            .await?;
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => Ok(response.bytes().await?.to_vec()),
+            StatusCode::RANGE_NOT_SATISFIABLE => Ok(Vec::new()),
+            // the mirror ignored the range
+            StatusCode::OK => {
+                let data = response.bytes().await?;
+                let start = (range.start as usize).min(data.len());
+                let end = (range.end as usize).min(data.len());
+                Ok(data[start..end].to_vec())
+            }
+            status => Err(MirrorError::Status(status)),
+        }
+    }
+
+    /// Sends the request to the mirrors in turn until one responds.
+    ///
+    /// A requested range that starts after the end of a file is an empty but successful response.
+    async fn request<F>(&self, request: F) -> Result<reqwest::Response, MirrorError>
+    where
+        F: Fn(&str) -> reqwest::RequestBuilder,
+    {
+        let first = self.next_mirror.fetch_add(1, Ordering::Relaxed);
+        let mut last_err = None;
+        for idx in 0..self.mirrors.len() {
+            let mirror = &self.mirrors[(first + idx) % self.mirrors.len()];
+            match request(mirror).send().await {
+                Ok(response)
+                    if response.status().is_success() ||
+                        response.status() == StatusCode::RANGE_NOT_SATISFIABLE =>
+                {
+                    return Ok(response)
+                }
+                Ok(response) => last_err = Some(MirrorError::Status(response.status())),
+                Err(err) => last_err = Some(err.into()),
+            }
+            warn!(target: "downloaders::mirror", %mirror, err = ?last_err, "Mirror request failed");
+        }
+        Err(last_err.unwrap_or(MirrorError::Status(StatusCode::SERVICE_UNAVAILABLE)))
+    }
+}
+
+impl DownloadClient for MirrorClient {
+    fn report_bad_message(&self, _peer_id: PeerId) {
+        // mirrors are not peers, the request is retried at the next mirror
+    }
+}
+
+#[async_trait::async_trait]
+impl HeadersClient for MirrorClient {
+    async fn get_headers(&self, request: HeadersRequest) -> PeerRequestResult<BlockHeaders> {
+        let headers = self.headers(request).await?;
+        Ok((PeerId::default(), BlockHeaders(headers)).into())
+    }
+}
+
+#[async_trait::async_trait]
+impl BodiesClient for MirrorClient {
+    async fn get_block_bodies(&self, hashes: Vec<H256>) -> PeerRequestResult<Vec<BlockBody>> {
+        let bodies = self.bodies(hashes).await?;
+        Ok((PeerId::default(), bodies).into())
+    }
+}
+
+/// Returns the block numbers of a headers request.
+fn headers_range(start: BlockNumber, limit: u64, reverse: bool) -> Range<BlockNumber> {
+    if reverse {
+        (start + 1).saturating_sub(limit)..start + 1
+    } else {
+        start..start.saturating_add(limit)
+    }
+}
+
+/// Decodes the headers of the records from the bytes of the `headers` file that start at the
+/// header of the first record, and checks their hashes.
+fn decode_headers(
+    first: BlockNumber,
+    records: &[IndexRecord],
+    data: &[u8],
+) -> Result<Vec<Header>, MirrorError> {
+    let base = records.first().map(|record| record.header_offset).unwrap_or_default();
+    let mut headers = Vec::with_capacity(records.len());
+    for (number, record) in (first..).zip(records) {
+        let mut buf = slice(data, record.header_offset - base, record.header_len)?;
+        let header = Header::decode(&mut buf)?;
+        let hash = header.hash_slow();
+        if hash != record.hash || header.number != number {
+            return Err(MirrorError::HashMismatch { number, expected: record.hash, got: hash })
+        }
+        headers.push(header);
+    }
+    Ok(headers)
+}
+
+/// Decodes the body of the record from the bytes of the `bodies` file that start at `base`.
+fn decode_body(base: u64, record: &IndexRecord, data: &[u8]) -> Result<BlockBody, MirrorError> {
+    let offset = record.body_offset.checked_sub(base).ok_or(MirrorError::MalformedIndex)?;
+    let mut buf = slice(data, offset, record.body_len)?;
+    Ok(BlockBody::decode(&mut buf)?)
+}
+
+fn slice(data: &[u8], offset: u64, len: u32) -> Result<&[u8], MirrorError> {
+    let start = offset as usize;
+    data.get(start..start + len as usize).ok_or(MirrorError::MalformedIndex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_interfaces::test_utils::generators::random_block_range;
+    use reth_rlp::Encodable;
+
+    /// Returns the contents of the `index`, `headers` and `bodies` files of the blocks.
+    fn mirror_files(headers: &[Header], bodies: &[BlockBody]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let (mut index, mut header_file, mut body_file) = (Vec::new(), Vec::new(), Vec::new());
+        for (header, body) in headers.iter().zip(bodies) {
+            let record = IndexRecord {
+                hash: header.hash_slow(),
+                header_offset: header_file.len() as u64,
+                header_len: header.length() as u32,
+                body_offset: body_file.len() as u64,
+                body_len: body.length() as u32,
+            };
+            header.encode(&mut header_file);
+            body.encode(&mut body_file);
+            record.encode(&mut index);
+        }
+        (index, header_file, body_file)
+    }
+
+    #[test]
+    fn ranges_of_requests() {
+        assert_eq!(headers_range(10, 4, true), 7..11);
+        assert_eq!(headers_range(2, 4, true), 0..3);
+        assert_eq!(headers_range(10, 4, false), 10..14);
+        assert_eq!(headers_range(u64::MAX - 1, 4, false), u64::MAX - 1..u64::MAX);
+    }
+
+    #[test]
+    fn decode_files() {
+        let blocks = random_block_range(0..10, H256::zero(), 0..3);
+        let headers = blocks.iter().map(|block| block.header.as_ref().clone()).collect::<Vec<_>>();
+        let bodies = blocks
+            .iter()
+            .map(|block| BlockBody {
+                transactions: block.body.clone(),
+                ommers: block.ommers.iter().map(|header| header.as_ref().clone()).collect(),
+            })
+            .collect::<Vec<_>>();
+        let (index, header_file, body_file) = mirror_files(&headers, &bodies);
+        assert_eq!(index.len() as u64, 10 * INDEX_RECORD_SIZE);
+
+        let records = index
+            .chunks_exact(INDEX_RECORD_SIZE as usize)
+            .map(IndexRecord::decode)
+            .collect::<Vec<_>>();
+        assert_eq!(records[3].hash, blocks[3].hash());
+
+        // a range in the middle of the files
+        let (first, last) = (&records[3], &records[6]);
+        let data = &header_file
+            [first.header_offset as usize..(last.header_offset + last.header_len as u64) as usize];
+        assert_eq!(decode_headers(3, &records[3..7], data).unwrap(), headers[3..7]);
+        let data = &body_file[first.body_offset as usize..];
+        assert_eq!(decode_body(first.body_offset, &records[5], data).unwrap(), bodies[5]);
+
+        // the hashes of the index are checked
+        let mut tampered = records.clone();
+        tampered[4].hash = H256::zero();
+        let data = &header_file[first.header_offset as usize..];
+        assert!(matches!(
+            decode_headers(3, &tampered[3..7], data),
+            Err(MirrorError::HashMismatch { number: 4, .. })
+        ));
+
+        // truncated data
+        assert!(matches!(
+            decode_headers(3, &records[3..7], &data[..10]),
+            Err(MirrorError::MalformedIndex)
+        ));
+    }
+}
@@ -5,6 +5,7 @@ use bytes::{BufMut, Bytes};
 use reth_rlp::{Decodable, DecodeError, Encodable, RlpDecodable, RlpEncodable};
 use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
+use std::fmt;
 
 /// A Capability message consisting of the message-id and the payload
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -53,6 +54,18 @@ impl Capability {
     pub fn is_eth_v67(&self) -> bool {
         self.name == "eth" && self.version == 67
     }
+
+    /// Whether this is snap v1.
+    #[inline]
+    pub fn is_snap_v1(&self) -> bool {
+        self.name == "snap" && self.version == 1
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.name, self.version)
+    }
 }
 
 /// Represents all capabilities of a node.
@@ -62,6 +75,7 @@ pub struct Capabilities {
     inner: Vec<Capability>,
     eth_66: bool,
     eth_67: bool,
+    snap_1: bool,
 }
 
 impl Capabilities {
@@ -94,6 +108,12 @@ impl Capabilities {
     pub fn supports_eth_v67(&self) -> bool {
         self.eth_67
     }
+
+    /// Whether this peer supports the `snap/1` protocol.
+    #[inline]
+    pub fn supports_snap(&self) -> bool {
+        self.snap_1
+    }
 }
 
 impl From<Vec<Capability>> for Capabilities {
@@ -101,6 +121,7 @@ impl From<Vec<Capability>> for Capabilities {
         Self {
             eth_66: value.iter().any(Capability::is_eth_v66),
             eth_67: value.iter().any(Capability::is_eth_v67),
+            snap_1: value.iter().any(Capability::is_snap_v1),
             inner: value,
         }
     }
@@ -119,6 +140,7 @@ impl Decodable for Capabilities {
         Ok(Self {
             eth_66: inner.iter().any(Capability::is_eth_v66),
             eth_67: inner.iter().any(Capability::is_eth_v67),
+            snap_1: inner.iter().any(Capability::is_snap_v1),
             inner,
         })
     }
@@ -172,6 +194,8 @@ impl SharedCapability {
     pub fn num_messages(&self) -> Result<u8, SharedCapabilityError> {
         match self {
             SharedCapability::Eth { version, .. } => Ok(version.total_messages()),
+            // snap/1 reserves message IDs 0x00 - 0x07
+            SharedCapability::UnknownCapability { name, version: 1, .. } if name == "snap" => Ok(8),
             _ => Err(SharedCapabilityError::UnknownCapability),
         }
     }
@@ -209,4 +233,16 @@ mod tests {
         assert_eq!(capability.version(), 66);
         assert_eq!(capability, SharedCapability::Eth { version: EthVersion::Eth66, offset: 0 });
     }
+
+    #[test]
+    fn num_messages() {
+        let eth = SharedCapability::new("eth", 67, 0x10).unwrap();
+        assert_eq!(eth.num_messages().unwrap(), 17);
+
+        let snap = SharedCapability::new("snap", 1, 0x21).unwrap();
+        assert_eq!(snap.num_messages().unwrap(), 8);
+
+        let unknown = SharedCapability::new("les", 4, 0x29).unwrap();
+        assert!(unknown.num_messages().is_err());
+    }
 }
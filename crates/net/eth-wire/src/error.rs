@@ -4,7 +4,8 @@ use std::io;
 use reth_primitives::{Chain, ValidationError, H256};
 
 use crate::{
-    capability::SharedCapabilityError, disconnect::UnknownDisconnectReason, DisconnectReason,
+    capability::SharedCapabilityError, disconnect::UnknownDisconnectReason, message::MessageError,
+    version::ParseVersionError, DisconnectReason,
 };
 
 /// Errors when sending/receiving messages
@@ -21,6 +22,12 @@ pub enum EthStreamError {
     HandshakeError(#[from] HandshakeError),
     #[error("message size ({0}) exceeds max length (10MB)")]
     MessageTooBig(usize),
+    #[error(transparent)]
+    InvalidMessage(#[from] MessageError),
+    #[error(transparent)]
+    UnsupportedVersion(#[from] ParseVersionError),
+    #[error(transparent)]
+    Multiplex(MultiplexError),
 }
 
 // === impl EthStreamError ===
@@ -85,6 +92,8 @@ pub enum P2PStreamError {
     PingBeforeHandshake,
     #[error("too many messages buffered before sending")]
     SendBufferFull,
+    #[error("message id {0} exceeds the message id space of the capability")]
+    MessageIdOutOfRange(u8),
     #[error("disconnected")]
     Disconnected(DisconnectReason),
     #[error("unknown disconnect reason: {0}")]
@@ -106,6 +115,38 @@ impl P2PStreamError {
     }
 }
 
+impl From<MultiplexError> for EthStreamError {
+    fn from(err: MultiplexError) -> Self {
+        match err {
+            // keep the errors of the connection, like disconnects, accessible
+            MultiplexError::P2PStreamError(err) => err.into(),
+            err => EthStreamError::Multiplex(err),
+        }
+    }
+}
+
+/// Errors when multiplexing the capabilities of a [`crate::P2PStream`]
+#[derive(thiserror::Error, Debug)]
+pub enum MultiplexError {
+    /// Error of the underlying [`crate::P2PStream`].
+    #[error(transparent)]
+    P2PStreamError(#[from] P2PStreamError),
+    /// The capability is not shared with the peer.
+    #[error("capability {name}/{version} is not shared with the peer")]
+    CapabilityNotShared {
+        /// Name of the capability
+        name: String,
+        /// Version of the capability
+        version: u8,
+    },
+    /// A protocol was already installed for the capability.
+    #[error("capability {0} is already installed")]
+    AlreadyInstalled(String),
+    /// Received a message with an ID that does not belong to any shared capability.
+    #[error("message id {0} does not belong to any shared capability")]
+    UnknownMessageId(u8),
+}
+
 /// Errors when conducting a p2p handshake
 #[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
 #[allow(missing_docs)]
@@ -1,7 +1,7 @@
 use crate::{
     error::{EthStreamError, HandshakeError},
     message::{EthBroadcastMessage, ProtocolBroadcastMessage},
-    types::{EthMessage, ProtocolMessage, Status},
+    types::{message::MessageError, EthMessage, EthVersion, ProtocolMessage, Status},
 };
 use bytes::{Bytes, BytesMut};
 use futures::{ready, Sink, SinkExt, StreamExt};
@@ -51,6 +51,8 @@ where
         status: Status,
         fork_filter: ForkFilter,
     ) -> Result<(EthStream<S>, Status), EthStreamError> {
+        let version = EthVersion::try_from(status.version)?;
+
         tracing::trace!("sending eth status ...");
 
         // we need to encode and decode here on our own because we don't have an `EthStream` yet
@@ -111,7 +113,7 @@ where
 
                 // now we can create the `EthStream` because the peer has successfully completed
                 // the handshake
-                let stream = EthStream::new(self.inner);
+                let stream = EthStream::new(version, self.inner);
 
                 Ok((stream, resp))
             }
@@ -127,13 +129,20 @@ where
 pub struct EthStream<S> {
     #[pin]
     inner: S,
+    /// The negotiated `eth` version of the stream.
+    version: EthVersion,
 }
 
 impl<S> EthStream<S> {
-    /// Creates a new unauthed [`EthStream`] from a provided stream. You will need
-    /// to manually handshake a peer.
-    pub fn new(inner: S) -> Self {
-        Self { inner }
+    /// Creates a new unauthed [`EthStream`] of the given version from a provided stream. You will
+    /// need to manually handshake a peer.
+    pub fn new(version: EthVersion, inner: S) -> Self {
+        Self { inner, version }
+    }
+
+    /// Returns the negotiated `eth` version of the stream.
+    pub fn version(&self) -> EthVersion {
+        self.version
     }
 
     /// Returns the underlying stream.
@@ -192,7 +201,7 @@ where
             return Poll::Ready(Some(Err(EthStreamError::MessageTooBig(bytes.len()))))
         }
 
-        let msg = match ProtocolMessage::decode(&mut bytes.as_ref()) {
+        let msg = match ProtocolMessage::decode_versioned(*this.version, &mut bytes.as_ref()) {
             Ok(m) => m,
             Err(err) => {
                 tracing::warn!("rlp decode error: msg={bytes:x}");
@@ -226,6 +235,11 @@ where
             return Err(EthStreamError::HandshakeError(HandshakeError::StatusNotInHandshake))
         }
 
+        let message_type = item.message_id();
+        if !message_type.is_supported_by(self.version) {
+            return Err(MessageError::Invalid(self.version, message_type).into())
+        }
+
         let mut bytes = BytesMut::new();
         ProtocolMessage::from(item).encode(&mut bytes);
         let bytes = bytes.freeze();
@@ -326,7 +340,7 @@ mod tests {
             // roughly based off of the design of tokio::net::TcpListener
             let (incoming, _) = listener.accept().await.unwrap();
             let stream = PassthroughCodec::default().framed(incoming);
-            let mut stream = EthStream::new(stream);
+            let mut stream = EthStream::new(EthVersion::Eth67, stream);
 
             // use the stream to get the next message
             let message = stream.next().await.unwrap().unwrap();
@@ -335,7 +349,7 @@ mod tests {
 
         let outgoing = TcpStream::connect(local_addr).await.unwrap();
         let sink = PassthroughCodec::default().framed(outgoing);
-        let mut client_stream = EthStream::new(sink);
+        let mut client_stream = EthStream::new(EthVersion::Eth67, sink);
 
         client_stream.send(test_msg).await.unwrap();
 
@@ -361,7 +375,7 @@ mod tests {
             // roughly based off of the design of tokio::net::TcpListener
             let (incoming, _) = listener.accept().await.unwrap();
             let stream = ECIESStream::incoming(incoming, server_key).await.unwrap();
-            let mut stream = EthStream::new(stream);
+            let mut stream = EthStream::new(EthVersion::Eth67, stream);
 
             // use the stream to get the next message
             let message = stream.next().await.unwrap().unwrap();
@@ -375,7 +389,7 @@ mod tests {
 
         let outgoing = TcpStream::connect(local_addr).await.unwrap();
         let outgoing = ECIESStream::connect(outgoing, client_key, server_id).await.unwrap();
-        let mut client_stream = EthStream::new(outgoing);
+        let mut client_stream = EthStream::new(EthVersion::Eth67, outgoing);
 
         client_stream.send(test_msg).await.unwrap();
 
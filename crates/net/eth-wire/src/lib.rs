@@ -12,6 +12,7 @@ mod disconnect;
 pub mod error;
 mod ethstream;
 mod hello;
+pub mod multiplex;
 mod p2pstream;
 mod pinger;
 pub use builder::*;
@@ -27,5 +28,6 @@ pub use crate::{
     disconnect::DisconnectReason,
     ethstream::{EthStream, UnauthedEthStream, MAX_MESSAGE_SIZE},
     hello::HelloMessage,
+    multiplex::{ProtocolConnection, RlpxMultiplexer},
     p2pstream::{P2PMessage, P2PMessageID, P2PStream, ProtocolVersion, UnauthedP2PStream},
 };
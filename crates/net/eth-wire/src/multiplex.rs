@@ -0,0 +1,470 @@
+//! Multiplexing of all capabilities shared over a single RLPx connection.
+//!
+//! After the `Hello` handshake every shared capability is reserved a range of message IDs, see
+//! [`shared_capability_offsets`](crate::p2pstream::shared_capability_offsets). The
+//! [`RlpxMultiplexer`] drives the [`P2PStream`] and routes incoming messages to the
+//! [`ProtocolConnection`] of the capability the message ID belongs to.
+//!
+//! A [`ProtocolConnection`] is a `Stream` and `Sink` of messages whose IDs are relative to the
+//! capability, so protocol handlers (e.g. [`UnauthedEthStream`](crate::UnauthedEthStream)) can
+//! consume it like a [`P2PStream`] with a single capability.
+//!
+//! The channels between the multiplexer and its protocols are bounded: a protocol that doesn't
+//! read its messages stops the multiplexer from reading the connection, and a connection that
+//! doesn't accept messages makes the [`ProtocolConnection`]s pending.
+
+use crate::{
+    capability::SharedCapability,
+    error::{MultiplexError, P2PStreamError},
+    DisconnectReason, P2PStream,
+};
+use bytes::{Bytes, BytesMut};
+use futures::{channel::mpsc, Sink, SinkExt, StreamExt};
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio_stream::Stream;
+use tracing::trace;
+
+/// The number of messages that are buffered between the [`RlpxMultiplexer`] and a
+/// [`ProtocolConnection`], in each direction.
+pub const PROTOCOL_CHANNEL_CAPACITY: usize = 32;
+
+/// Routes the messages of a [`P2PStream`] to the installed protocols of its shared capabilities.
+///
+/// The multiplexer must be polled in order to make progress. It yields all messages of shared
+/// capabilities for which no protocol was installed.
+#[derive(Debug)]
+#[must_use = "The multiplexer does nothing unless polled"]
+pub struct RlpxMultiplexer<S> {
+    /// The underlying connection.
+    conn: P2PStream<S>,
+    /// All installed protocols.
+    protocols: Vec<ProtocolProxy>,
+}
+
+impl<S> RlpxMultiplexer<S> {
+    /// Creates a new multiplexer for the given [`P2PStream`].
+    pub fn new(conn: P2PStream<S>) -> Self {
+        Self { conn, protocols: Vec::new() }
+    }
+
+    /// Returns a reference to the underlying [`P2PStream`].
+    pub fn inner(&self) -> &P2PStream<S> {
+        &self.conn
+    }
+
+    /// Consumes the multiplexer and returns the underlying [`P2PStream`].
+    ///
+    /// Buffered messages of the installed protocols are dropped.
+    pub fn into_inner(self) -> P2PStream<S> {
+        self.conn
+    }
+
+    /// Returns all capabilities shared with the peer, ordered by their offset.
+    pub fn shared_capabilities(&self) -> &[SharedCapability] {
+        self.conn.shared_capabilities()
+    }
+
+    /// Installs a protocol for the shared capability with the given name and version.
+    ///
+    /// All messages of the capability are routed to the returned [`ProtocolConnection`] from now
+    /// on. Once the [`ProtocolConnection`] is dropped, messages of the capability are yielded by
+    /// the multiplexer again.
+    pub fn install_protocol(
+        &mut self,
+        name: &str,
+        version: u8,
+    ) -> Result<ProtocolConnection, MultiplexError> {
+        if self.protocols.iter().any(|proto| proto.capability.name() == name) {
+            return Err(MultiplexError::AlreadyInstalled(name.to_string()))
+        }
+
+        let capability = self
+            .shared_capabilities()
+            .iter()
+            .find(|cap| cap.name() == name && cap.version() == version)
+            .cloned()
+            .ok_or_else(|| MultiplexError::CapabilityNotShared {
+                name: name.to_string(),
+                version,
+            })?;
+        let num_messages = capability.num_messages().map_err(P2PStreamError::from)?;
+
+        // offsets of the stream's messages are relative to the primary capability
+        let relative_offset = capability.offset() - self.conn.shared_capability().offset();
+
+        let (to_protocol, from_wire) = mpsc::channel(PROTOCOL_CHANNEL_CAPACITY);
+        let (to_wire, from_protocol) = mpsc::channel(PROTOCOL_CHANNEL_CAPACITY);
+
+        self.protocols.push(ProtocolProxy {
+            capability: capability.clone(),
+            to_protocol,
+            pending: None,
+            from_protocol,
+        });
+
+        Ok(ProtocolConnection { capability, num_messages, relative_offset, from_wire, to_wire })
+    }
+
+    /// Starts to gracefully disconnect the connection.
+    ///
+    /// See also [`P2PStream::start_disconnect`].
+    pub fn start_disconnect(&mut self, reason: DisconnectReason) -> Result<(), snap::Error> {
+        self.conn.start_disconnect(reason)
+    }
+}
+
+impl<S> RlpxMultiplexer<S>
+where
+    S: Sink<Bytes, Error = io::Error> + Unpin,
+{
+    /// Flushes and closes the underlying [`P2PStream`].
+    ///
+    /// See also [`P2PStream::start_disconnect`].
+    pub fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), P2PStreamError>> {
+        self.conn.poll_close_unpin(cx)
+    }
+}
+
+impl<S> Stream for RlpxMultiplexer<S>
+where
+    S: Stream<Item = io::Result<BytesMut>> + Sink<Bytes, Error = io::Error> + Unpin,
+{
+    type Item = Result<(SharedCapability, BytesMut), MultiplexError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let primary_offset = this.conn.shared_capability().offset();
+
+        loop {
+            // send the outgoing messages of the protocols as long as the connection accepts them,
+            // messages that don't fit remain in the protocol channels
+            'send: loop {
+                match this.conn.poll_ready_unpin(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                    Poll::Pending => break,
+                }
+
+                let mut idx = 0;
+                while idx < this.protocols.len() {
+                    match this.protocols[idx].from_protocol.poll_next_unpin(cx) {
+                        Poll::Ready(Some(msg)) => {
+                            if let Err(err) = this.conn.start_send_unpin(msg) {
+                                return Poll::Ready(Some(Err(err.into())))
+                            }
+                            continue 'send
+                        }
+                        Poll::Ready(None) => {
+                            // the protocol connection was dropped
+                            this.protocols.swap_remove(idx);
+                        }
+                        Poll::Pending => idx += 1,
+                    }
+                }
+                break
+            }
+
+            if let Poll::Ready(Err(err)) = this.conn.poll_flush_unpin(cx) {
+                return Poll::Ready(Some(Err(err.into())))
+            }
+
+            // deliver the messages that didn't fit into the protocol channels before reading more
+            // from the connection
+            let mut idx = 0;
+            while idx < this.protocols.len() {
+                match this.protocols[idx].poll_deliver(cx) {
+                    Poll::Ready(Ok(())) => idx += 1,
+                    Poll::Ready(Err(_)) => {
+                        trace!(
+                            target : "net::multiplex",
+                            capability=%this.protocols[idx].capability.name(),
+                            "protocol connection dropped"
+                        );
+                        this.protocols.swap_remove(idx);
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            let mut msg = match this.conn.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(msg))) => msg,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            // the stream's message IDs are relative to the primary capability
+            let id = msg[0] + primary_offset;
+
+            if let Some(proto) = this.protocols.iter_mut().find(|proto| proto.contains(id)) {
+                msg[0] = id - proto.capability.offset();
+                proto.pending = Some(msg);
+                continue
+            }
+
+            return match this.conn.shared_capabilities().iter().find(|cap| contains(cap, id)) {
+                Some(cap) => {
+                    msg[0] = id - cap.offset();
+                    Poll::Ready(Some(Ok((cap.clone(), msg))))
+                }
+                None => Poll::Ready(Some(Err(MultiplexError::UnknownMessageId(id)))),
+            }
+        }
+    }
+}
+
+/// The multiplexer's side of a [`ProtocolConnection`].
+#[derive(Debug)]
+struct ProtocolProxy {
+    /// The capability of the protocol.
+    capability: SharedCapability,
+    /// Sends incoming messages to the protocol.
+    to_protocol: mpsc::Sender<BytesMut>,
+    /// The incoming message that is waiting for capacity in the protocol channel.
+    pending: Option<BytesMut>,
+    /// Receives outgoing messages of the protocol.
+    from_protocol: mpsc::Receiver<Bytes>,
+}
+
+// === impl ProtocolProxy ===
+
+impl ProtocolProxy {
+    /// Returns `true` if the message ID belongs to the protocol's capability.
+    fn contains(&self, id: u8) -> bool {
+        contains(&self.capability, id)
+    }
+
+    /// Sends the pending incoming message to the protocol, once the channel has capacity.
+    ///
+    /// Returns an error if the protocol connection was dropped.
+    fn poll_deliver(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), mpsc::SendError>> {
+        if self.pending.is_none() {
+            return Poll::Ready(Ok(()))
+        }
+        ready!(self.to_protocol.poll_ready(cx))?;
+        let msg = self.pending.take().expect("not empty");
+        Poll::Ready(self.to_protocol.start_send(msg))
+    }
+}
+
+/// Returns `true` if the message ID is within the reserved message ID range of the capability.
+fn contains(capability: &SharedCapability, id: u8) -> bool {
+    let offset = capability.offset();
+    id >= offset && capability.num_messages().map(|num| id - offset < num).unwrap_or_default()
+}
+
+/// A connection of a single protocol that is multiplexed over a [`P2PStream`].
+///
+/// All message IDs are relative to the capability of the protocol, the message `0x00` of the
+/// capability is always `0x00`.
+#[derive(Debug)]
+pub struct ProtocolConnection {
+    /// The capability of the protocol.
+    capability: SharedCapability,
+    /// The size of the capability's message ID space.
+    num_messages: u8,
+    /// The offset of the capability relative to the primary capability of the [`P2PStream`].
+    relative_offset: u8,
+    /// Incoming messages routed to this protocol.
+    from_wire: mpsc::Receiver<BytesMut>,
+    /// Outgoing messages of this protocol.
+    to_wire: mpsc::Sender<Bytes>,
+}
+
+// === impl ProtocolConnection ===
+
+impl ProtocolConnection {
+    /// Returns the capability of this protocol.
+    pub fn capability(&self) -> &SharedCapability {
+        &self.capability
+    }
+}
+
+impl Stream for ProtocolConnection {
+    type Item = Result<BytesMut, P2PStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.from_wire.poll_next_unpin(cx).map(|msg| msg.map(Ok))
+    }
+}
+
+impl Sink<Bytes> for ProtocolConnection {
+    type Error = P2PStreamError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.to_wire.poll_ready(cx).map_err(|_| multiplexer_closed())
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        let id = *item.first().ok_or(P2PStreamError::EmptyProtocolMessage)?;
+        if id >= self.num_messages {
+            return Err(P2PStreamError::MessageIdOutOfRange(id))
+        }
+
+        let mut msg = BytesMut::from(&item[..]);
+        msg[0] = id + self.relative_offset;
+        self.to_wire.start_send(msg.freeze()).map_err(|_| multiplexer_closed())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The error returned if the [`RlpxMultiplexer`] of a [`ProtocolConnection`] was dropped.
+fn multiplexer_closed() -> P2PStreamError {
+    P2PStreamError::Io(io::Error::new(io::ErrorKind::BrokenPipe, "multiplexer closed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        capability::Capability, p2pstream::ProtocolVersion, EthVersion, HelloMessage,
+        UnauthedP2PStream,
+    };
+    use reth_ecies::util::pk2id;
+    use secp256k1::{SecretKey, SECP256K1};
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_util::codec::Decoder;
+
+    /// Returns a testing `HelloMessage` that supports `eth/67` and `snap/1`
+    fn hello() -> HelloMessage {
+        let key = SecretKey::new(&mut rand::thread_rng());
+        HelloMessage {
+            protocol_version: ProtocolVersion::V5,
+            client_version: "reth/multiplex".to_string(),
+            capabilities: vec![EthVersion::Eth67.into(), Capability::new("snap".into(), 1)],
+            port: 30303,
+            id: pk2id(&key.public_key(SECP256K1)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiplex_capabilities() {
+        reth_tracing::init_tracing();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let eth_msg = Bytes::from_static(&[0x03, 0xc2, 0x01, 0x02]);
+        let snap_msg = Bytes::from_static(&[0x01, 0xc1, 0x80]);
+
+        let expected_eth = eth_msg.clone();
+        let handle = tokio::spawn(async move {
+            let (incoming, _) = listener.accept().await.unwrap();
+            let stream = crate::PassthroughCodec::default().framed(incoming);
+            let (p2p_stream, _) = UnauthedP2PStream::new(stream).handshake(hello()).await.unwrap();
+
+            let mut multiplexer = RlpxMultiplexer::new(p2p_stream);
+            let mut snap = multiplexer.install_protocol("snap", 1).unwrap();
+
+            // no protocol is installed for `eth`, so the multiplexer yields the message
+            let (capability, msg) = multiplexer.next().await.unwrap().unwrap();
+            assert_eq!(capability.name(), "eth");
+            assert_eq!(&msg[..], &expected_eth[..]);
+
+            tokio::spawn(async move { while multiplexer.next().await.is_some() {} });
+
+            // echo the snap message
+            let msg = snap.next().await.unwrap().unwrap();
+            snap.send(msg.freeze()).await.unwrap();
+        });
+
+        let outgoing = TcpStream::connect(local_addr).await.unwrap();
+        let sink = crate::PassthroughCodec::default().framed(outgoing);
+        let (p2p_stream, _) = UnauthedP2PStream::new(sink).handshake(hello()).await.unwrap();
+
+        let mut multiplexer = RlpxMultiplexer::new(p2p_stream);
+        assert_eq!(multiplexer.shared_capabilities().len(), 2);
+
+        let mut eth = multiplexer.install_protocol("eth", 67).unwrap();
+        let mut snap = multiplexer.install_protocol("snap", 1).unwrap();
+        assert_eq!(snap.capability().offset(), 0x21);
+        assert!(matches!(
+            multiplexer.install_protocol("eth", 67),
+            Err(MultiplexError::AlreadyInstalled(_))
+        ));
+        assert!(matches!(
+            multiplexer.install_protocol("les", 4),
+            Err(MultiplexError::CapabilityNotShared { .. })
+        ));
+
+        tokio::spawn(async move { while multiplexer.next().await.is_some() {} });
+
+        // snap/1 only reserves 8 message IDs
+        assert!(matches!(
+            snap.send(Bytes::from_static(&[0x08, 0xc0])).await,
+            Err(P2PStreamError::MessageIdOutOfRange(0x08))
+        ));
+
+        eth.send(eth_msg).await.unwrap();
+        snap.send(snap_msg.clone()).await.unwrap();
+
+        let echo = snap.next().await.unwrap().unwrap();
+        assert_eq!(&echo[..], &snap_msg[..]);
+
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_multiplex_backpressure() {
+        reth_tracing::init_tracing();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let num_snap_messages = PROTOCOL_CHANNEL_CAPACITY + 10;
+        let eth_msg = Bytes::from_static(&[0x03, 0xc2, 0x01, 0x02]);
+
+        let expected_eth = eth_msg.clone();
+        let handle = tokio::spawn(async move {
+            let (incoming, _) = listener.accept().await.unwrap();
+            let stream = crate::PassthroughCodec::default().framed(incoming);
+            let (p2p_stream, _) = UnauthedP2PStream::new(stream).handshake(hello()).await.unwrap();
+
+            let mut multiplexer = RlpxMultiplexer::new(p2p_stream);
+            let mut snap = multiplexer.install_protocol("snap", 1).unwrap();
+
+            // the snap messages aren't read, so the multiplexer stops reading the connection
+            // before it reaches the eth message
+            assert!(tokio::time::timeout(Duration::from_millis(200), multiplexer.next())
+                .await
+                .is_err());
+
+            let mut received = 0;
+            while let Ok(Some(_)) = snap.from_wire.try_next() {
+                received += 1;
+            }
+            assert!(received < num_snap_messages);
+
+            // once the snap messages are read, the remaining ones are delivered
+            let (capability, msg) = multiplexer.next().await.unwrap().unwrap();
+            assert_eq!(capability.name(), "eth");
+            assert_eq!(&msg[..], &expected_eth[..]);
+            while let Ok(Some(_)) = snap.from_wire.try_next() {
+                received += 1;
+            }
+            assert_eq!(received, num_snap_messages);
+        });
+
+        let outgoing = TcpStream::connect(local_addr).await.unwrap();
+        let sink = crate::PassthroughCodec::default().framed(outgoing);
+        let (mut p2p_stream, _) = UnauthedP2PStream::new(sink).handshake(hello()).await.unwrap();
+
+        // the snap messages right after the ID space of `eth/67`
+        for _ in 0..num_snap_messages {
+            p2p_stream.send(Bytes::from_static(&[0x11, 0xc1, 0x80])).await.unwrap();
+        }
+        p2p_stream.send(eth_msg).await.unwrap();
+
+        handle.await.unwrap();
+    }
+}
@@ -12,7 +12,7 @@ use pin_project::pin_project;
 use reth_rlp::{Decodable, DecodeError, Encodable, EMPTY_LIST_CODE};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{BTreeSet, HashMap, VecDeque},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     io,
     pin::Pin,
     task::{ready, Context, Poll},
@@ -131,11 +131,14 @@ where
             })
         }
 
-        // determine shared capabilities (currently returns only one capability)
-        let capability =
-            set_capability_offsets(hello.capabilities, their_hello.capabilities.clone())?;
+        // determine all shared capabilities and their offsets
+        let mut shared_capabilities =
+            shared_capability_offsets(hello.capabilities, their_hello.capabilities.clone())?;
 
-        let stream = P2PStream::new(self.inner, capability);
+        // the capability with the lowest offset is the primary capability of the stream
+        let capability = shared_capabilities.remove(0);
+        let mut stream = P2PStream::new(self.inner, capability);
+        stream.shared_capabilities.extend(shared_capabilities);
 
         Ok((stream, their_hello))
     }
@@ -161,6 +164,11 @@ pub struct P2PStream<S> {
     /// The supported capability for this stream.
     shared_capability: SharedCapability,
 
+    /// All capabilities shared with the peer, ordered by offset.
+    ///
+    /// The first entry is always the `shared_capability`.
+    shared_capabilities: Vec<SharedCapability>,
+
     /// Outgoing messages buffered for sending to the underlying stream.
     outgoing_messages: VecDeque<Bytes>,
 
@@ -179,6 +187,7 @@ impl<S> P2PStream<S> {
             encoder: snap::raw::Encoder::new(),
             decoder: snap::raw::Decoder::new(),
             pinger: Pinger::new(PING_INTERVAL, PING_TIMEOUT),
+            shared_capabilities: vec![capability.clone()],
             shared_capability: capability,
             outgoing_messages: VecDeque::new(),
             disconnecting: false,
@@ -190,6 +199,15 @@ impl<S> P2PStream<S> {
         &self.shared_capability
     }
 
+    /// Returns all capabilities shared with the peer, ordered by their offset.
+    ///
+    /// Messages of the stream are relative to the offset of the first (primary) capability, see
+    /// [`RlpxMultiplexer`](crate::multiplex::RlpxMultiplexer) for routing messages of multiple
+    /// capabilities.
+    pub fn shared_capabilities(&self) -> &[SharedCapability] {
+        &self.shared_capabilities
+    }
+
     /// Returns `true` if the connection is about to disconnect.
     pub fn is_disconnecting(&self) -> bool {
         self.disconnecting
@@ -490,16 +508,30 @@ where
 }
 
 /// Determines the offsets for each shared capability between the input list of peer
-/// capabilities and the input list of locally supported capabilities.
+/// capabilities and the input list of locally supported capabilities and returns the capability
+/// with the lowest offset.
 ///
-/// Currently only `eth` versions 66 and 67 are supported.
+/// See also [`shared_capability_offsets`].
 pub fn set_capability_offsets(
     local_capabilities: Vec<Capability>,
     peer_capabilities: Vec<Capability>,
 ) -> Result<SharedCapability, P2PStreamError> {
+    Ok(shared_capability_offsets(local_capabilities, peer_capabilities)?.remove(0))
+}
+
+/// Determines the offsets for each shared capability between the input list of peer
+/// capabilities and the input list of locally supported capabilities.
+///
+/// The returned list is ordered by offset and never empty. Capabilities for which the size of the
+/// message ID space is unknown are ignored, currently these are all capabilities except `eth/66`,
+/// `eth/67` and `snap/1`.
+pub fn shared_capability_offsets(
+    local_capabilities: Vec<Capability>,
+    peer_capabilities: Vec<Capability>,
+) -> Result<Vec<SharedCapability>, P2PStreamError> {
     // find intersection of capabilities
-    let our_capabilities_map =
-        local_capabilities.into_iter().map(|c| (c.name, c.version)).collect::<HashMap<_, _>>();
+    let our_capabilities =
+        local_capabilities.into_iter().map(|c| (c.name, c.version)).collect::<HashSet<_>>();
 
     // map of capability name to version
     let mut shared_capabilities = HashMap::new();
@@ -520,14 +552,14 @@ pub fn set_capability_offsets(
 
     // find highest shared version of each shared capability
     for capability in peer_capabilities {
-        // if this is Some, we share this capability
-        if let Some(version) = our_capabilities_map.get(&capability.name) {
+        // a capability is shared if both sides support the same version
+        if our_capabilities.contains(&(capability.name.clone(), capability.version)) {
             // If multiple versions are shared of the same (equal name) capability, the numerically
             // highest wins, others are ignored
-            if capability.version <= *version {
-                shared_capabilities.insert(capability.name.clone(), capability.version);
-                shared_capability_names.insert(capability.name);
-            }
+            let shared =
+                shared_capabilities.entry(capability.name.clone()).or_insert(capability.version);
+            *shared = capability.version.max(*shared);
+            shared_capability_names.insert(capability.name);
         }
     }
 
@@ -551,31 +583,24 @@ pub fn set_capability_offsets(
 
         let shared_capability = SharedCapability::new(&name, *version as u8, offset)?;
 
-        match shared_capability {
-            SharedCapability::UnknownCapability { .. } => {
-                // Capabilities which are not shared are ignored
-                tracing::warn!("unknown capability: name={:?}, version={}", name, version,);
-            }
-            SharedCapability::Eth { .. } => {
-                shared_with_offsets.push(shared_capability.clone());
-
+        match shared_capability.num_messages() {
+            Ok(num_messages) => {
                 // increment the offset if the capability is known
-                offset += shared_capability.num_messages()?;
+                offset += num_messages;
+                shared_with_offsets.push(shared_capability);
+            }
+            Err(_) => {
+                // Capabilities without a known message ID space are ignored
+                tracing::warn!("unknown capability: name={:?}, version={}", name, version,);
             }
         }
     }
 
-    // TODO: support multiple capabilities - we would need a new Stream type to go on top of
-    // `P2PStream` containing its capability. `P2PStream` would still send pings and handle
-    // pongs, but instead contain a map of capabilities to their respective stream / channel.
-    // Each channel would be responsible for containing the offset for that stream and would
-    // only increment / decrement message IDs.
-    // NOTE: since the `P2PStream` currently only supports one capability, we set the
-    // capability with the lowest offset.
-    Ok(shared_with_offsets
-        .first()
-        .ok_or(P2PStreamError::HandshakeError(P2PHandshakeError::NoSharedCapabilities))?
-        .clone())
+    if shared_with_offsets.is_empty() {
+        return Err(P2PStreamError::HandshakeError(P2PHandshakeError::NoSharedCapabilities))
+    }
+
+    Ok(shared_with_offsets)
 }
 
 /// This represents only the reserved `p2p` subprotocol messages.
@@ -851,6 +876,46 @@ mod tests {
         handle.await.unwrap();
     }
 
+    #[test]
+    fn test_shared_capability_offsets() {
+        let local = vec![
+            EthVersion::Eth67.into(),
+            EthVersion::Eth66.into(),
+            Capability::new("snap".into(), 1),
+            Capability::new("les".into(), 4),
+        ];
+        let peer = vec![
+            Capability::new("snap".into(), 1),
+            EthVersion::Eth66.into(),
+            EthVersion::Eth67.into(),
+            Capability::new("les".into(), 4),
+        ];
+
+        let shared = shared_capability_offsets(local.clone(), peer.clone()).unwrap();
+        assert_eq!(
+            shared,
+            vec![
+                SharedCapability::Eth { version: EthVersion::Eth67, offset: 0x10 },
+                SharedCapability::UnknownCapability {
+                    name: "snap".into(),
+                    version: 1,
+                    offset: 0x21
+                },
+            ]
+        );
+        assert_eq!(set_capability_offsets(local, peer).unwrap(), shared[0]);
+
+        let err = shared_capability_offsets(
+            vec![Capability::new("les".into(), 4)],
+            vec![Capability::new("les".into(), 4)],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            P2PStreamError::HandshakeError(P2PHandshakeError::NoSharedCapabilities)
+        ));
+    }
+
     #[test]
     fn snappy_decode_encode_ping() {
         let snappy_ping = b"\x02\x01\0\xc0";
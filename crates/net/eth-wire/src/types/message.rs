@@ -1,8 +1,8 @@
 #![allow(missing_docs)]
 use super::{
-    broadcast::NewBlockHashes, BlockBodies, BlockHeaders, GetBlockBodies, GetBlockHeaders,
-    GetNodeData, GetPooledTransactions, GetReceipts, NewBlock, NewPooledTransactionHashes,
-    NodeData, PooledTransactions, Receipts, Status, Transactions,
+    broadcast::NewBlockHashes, BlockBodies, BlockHeaders, EthVersion, GetBlockBodies,
+    GetBlockHeaders, GetNodeData, GetPooledTransactions, GetReceipts, NewBlock,
+    NewPooledTransactionHashes, NodeData, PooledTransactions, Receipts, Status, Transactions,
 };
 use crate::SharedTransactions;
 use bytes::{Buf, BufMut};
@@ -10,6 +10,17 @@ use reth_rlp::{length_of_length, Decodable, Encodable, Header};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, sync::Arc};
 
+/// An error that occurs when decoding a message of a specific `eth` version.
+#[derive(thiserror::Error, Debug)]
+pub enum MessageError {
+    /// The message is not part of the negotiated `eth` version.
+    #[error("message id {1:?} is invalid for version {0:?}")]
+    Invalid(EthVersion, EthMessageID),
+    /// The message could not be decoded.
+    #[error(transparent)]
+    RlpError(#[from] reth_rlp::DecodeError),
+}
+
 /// An `eth` protocol message, containing a message ID and payload.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProtocolMessage {
@@ -18,6 +29,18 @@ pub struct ProtocolMessage {
 }
 
 impl ProtocolMessage {
+    /// Decodes a message of the given `eth` version, using the first byte to determine the message
+    /// type.
+    ///
+    /// Returns an error if the message type is not part of the version.
+    pub fn decode_versioned(version: EthVersion, buf: &mut &[u8]) -> Result<Self, MessageError> {
+        let message_type = EthMessageID::decode(buf)?;
+        if !message_type.is_supported_by(version) {
+            return Err(MessageError::Invalid(version, message_type))
+        }
+        Ok(Self::decode_message(message_type, buf)?)
+    }
+
     /// Create a new ProtocolMessage from a message type and message rlp bytes.
     pub fn decode_message(
         message_type: EthMessageID,
@@ -289,6 +312,20 @@ pub enum EthMessageID {
     Receipts = 0x10,
 }
 
+// === impl EthMessageID ===
+
+impl EthMessageID {
+    /// Returns `true` if the message is part of the given `eth` version.
+    ///
+    /// `eth/67` removed the `GetNodeData` and `NodeData` messages.
+    pub fn is_supported_by(&self, version: EthVersion) -> bool {
+        match self {
+            EthMessageID::GetNodeData | EthMessageID::NodeData => version == EthVersion::Eth66,
+            _ => true,
+        }
+    }
+}
+
 impl Encodable for EthMessageID {
     fn encode(&self, out: &mut dyn BufMut) {
         out.put_u8(*self as u8);
@@ -397,7 +434,11 @@ where
 
 #[cfg(test)]
 mod test {
-    use crate::types::message::RequestPair;
+    use super::MessageError;
+    use crate::{
+        types::message::RequestPair, EthMessage, EthMessageID, EthVersion, GetNodeData,
+        ProtocolMessage,
+    };
     use hex_literal::hex;
     use reth_rlp::{Decodable, Encodable};
 
@@ -432,4 +473,23 @@ mod test {
         assert_eq!(expected.length(), raw_pair.len());
         assert_eq!(expected, got);
     }
+
+    #[test]
+    fn decode_versioned_node_data() {
+        let msg = ProtocolMessage::from(EthMessage::GetNodeData(RequestPair {
+            request_id: 1337,
+            message: GetNodeData(vec![]),
+        }));
+        let encoded = encode(msg.clone());
+
+        let decoded = ProtocolMessage::decode_versioned(EthVersion::Eth66, &mut &encoded[..]);
+        assert_eq!(decoded.unwrap(), msg);
+
+        // `GetNodeData` was removed in eth/67
+        let err = ProtocolMessage::decode_versioned(EthVersion::Eth67, &mut &encoded[..]);
+        assert!(matches!(
+            err,
+            Err(MessageError::Invalid(EthVersion::Eth67, EthMessageID::GetNodeData))
+        ));
+    }
 }
@@ -1,6 +1,6 @@
 //! Implements the `GetReceipts` and `Receipts` message types.
 use reth_primitives::{Receipt, H256};
-use reth_rlp::{RlpDecodableWrapper, RlpEncodableWrapper};
+use reth_rlp::{DecodeError, Header, RlpDecodableWrapper, RlpEncodableWrapper};
 use serde::{Deserialize, Serialize};
 
 /// A request for transaction receipts from the given block hashes.
@@ -38,6 +38,49 @@ pub struct Receipts(
     pub Vec<Vec<Receipt>>,
 );
 
+impl Receipts {
+    /// Decodes the receipt lists with [`Receipt::decode_lenient`], which accepts receipts of
+    /// unknown transaction types.
+    pub fn decode_lenient(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let mut receipts = Vec::new();
+        for mut block in decode_list_items(buf)? {
+            let mut block_receipts = Vec::new();
+            for mut receipt in decode_list_items(&mut block)? {
+                block_receipts.push(Receipt::decode_lenient(&mut receipt)?);
+            }
+            receipts.push(block_receipts);
+        }
+        Ok(Self(receipts))
+    }
+}
+
+/// Decodes a list header and splits the payload into the raw encodings of its items.
+fn decode_list_items<'a>(buf: &mut &'a [u8]) -> Result<Vec<&'a [u8]>, DecodeError> {
+    let header = Header::decode(buf)?;
+    if !header.list {
+        return Err(DecodeError::UnexpectedString)
+    }
+    if buf.len() < header.payload_length {
+        return Err(DecodeError::InputTooShort)
+    }
+    let (mut payload, rest) = buf.split_at(header.payload_length);
+    *buf = rest;
+
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        let item = &mut &payload[..];
+        let item_header = Header::decode(item)?;
+        let item_len = payload.len() - item.len() + item_header.payload_length;
+        if payload.len() < item_len {
+            return Err(DecodeError::InputTooShort)
+        }
+        let (item, rest) = payload.split_at(item_len);
+        items.push(item);
+        payload = rest;
+    }
+    Ok(items)
+}
+
 #[cfg(test)]
 mod test {
     use crate::types::{message::RequestPair, GetReceipts, Receipts};
@@ -142,4 +185,24 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn decode_receipts_lenient() {
+        let receipts = Receipts(vec![
+            vec![Receipt { tx_type: TxType::Legacy, success: true, ..Default::default() }],
+            vec![
+                Receipt { tx_type: TxType::EIP1559, cumulative_gas_used: 1, ..Default::default() },
+                Receipt {
+                    tx_type: TxType::Other(0x7e),
+                    cumulative_gas_used: 2,
+                    ..Default::default()
+                },
+            ],
+        ]);
+        let mut data = vec![];
+        receipts.encode(&mut data);
+
+        assert!(Receipts::decode(&mut &data[..]).is_err());
+        assert_eq!(Receipts::decode_lenient(&mut &data[..]).unwrap(), receipts);
+    }
 }
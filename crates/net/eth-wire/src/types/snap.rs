@@ -0,0 +1,450 @@
+//! Implements the messages of the `snap/1` protocol, which serves the state of recent blocks as
+//! ranges of trie leaves with proofs, see <https://github.com/ethereum/devp2p/blob/master/caps/snap.md>.
+use bytes::{Buf, BufMut};
+use reth_primitives::{
+    proofs::{TrieAccount, EMPTY_ROOT},
+    Bytes, H256, KECCAK_EMPTY, U256,
+};
+use reth_rlp::{Decodable, DecodeError, Encodable, Header, RlpDecodable, RlpEncodable};
+
+/// A request for the accounts of the state trie of `root_hash` from `starting_hash` on, up to
+/// `limit_hash` or until the response reaches `response_bytes`.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct GetAccountRange {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The state root the accounts are requested of.
+    pub root_hash: H256,
+    /// The hash of the first account of the range.
+    pub starting_hash: H256,
+    /// The hash after which no more accounts are returned.
+    pub limit_hash: H256,
+    /// The soft limit of the size of the response.
+    pub response_bytes: u64,
+}
+
+/// An account of a [AccountRange] response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountData {
+    /// The hash of the address of the account.
+    pub hash: H256,
+    /// The account.
+    pub account: TrieAccount,
+}
+
+impl Encodable for AccountData {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header { list: true, payload_length: self.payload_length() }.encode(out);
+        self.hash.encode(out);
+        encode_slim_account(&self.account, out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + reth_rlp::length_of_length(payload_length)
+    }
+}
+
+impl AccountData {
+    fn payload_length(&self) -> usize {
+        self.hash.length() + slim_account_length(&self.account)
+    }
+}
+
+impl Decodable for AccountData {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let header = Header::decode(buf)?;
+        if !header.list {
+            return Err(DecodeError::UnexpectedString)
+        }
+        let started = buf.len();
+        let hash = H256::decode(buf)?;
+        let account = decode_slim_account(buf)?;
+        if started - buf.len() != header.payload_length {
+            return Err(DecodeError::ListLengthMismatch {
+                expected: header.payload_length,
+                got: started - buf.len(),
+            })
+        }
+        Ok(Self { hash, account })
+    }
+}
+
+/// The slim encoding of an account replaces the empty storage root and the empty code hash with
+/// empty strings.
+fn slim_account_fields(account: &TrieAccount) -> (&[u8], &[u8]) {
+    let storage_root =
+        if account.storage_root == EMPTY_ROOT { &[][..] } else { account.storage_root.as_bytes() };
+    let code_hash =
+        if account.code_hash == KECCAK_EMPTY { &[][..] } else { account.code_hash.as_bytes() };
+    (storage_root, code_hash)
+}
+
+fn slim_account_payload_length(account: &TrieAccount) -> usize {
+    let (storage_root, code_hash) = slim_account_fields(account);
+    account.nonce.length() + account.balance.length() + storage_root.length() + code_hash.length()
+}
+
+fn slim_account_length(account: &TrieAccount) -> usize {
+    let payload_length = slim_account_payload_length(account);
+    payload_length + reth_rlp::length_of_length(payload_length)
+}
+
+fn encode_slim_account(account: &TrieAccount, out: &mut dyn BufMut) {
+    let (storage_root, code_hash) = slim_account_fields(account);
+    Header { list: true, payload_length: slim_account_payload_length(account) }.encode(out);
+    account.nonce.encode(out);
+    account.balance.encode(out);
+    storage_root.encode(out);
+    code_hash.encode(out);
+}
+
+fn decode_slim_account(buf: &mut &[u8]) -> Result<TrieAccount, DecodeError> {
+    let header = Header::decode(buf)?;
+    if !header.list {
+        return Err(DecodeError::UnexpectedString)
+    }
+    let started = buf.len();
+    let nonce = u64::decode(buf)?;
+    let balance = U256::decode(buf)?;
+    let decode_hash = |buf: &mut &[u8], empty: H256| -> Result<H256, DecodeError> {
+        let hash = bytes::Bytes::decode(buf)?;
+        match hash.len() {
+            0 => Ok(empty),
+            32 => Ok(H256::from_slice(&hash)),
+            _ => Err(DecodeError::UnexpectedLength),
+        }
+    };
+    let storage_root = decode_hash(buf, EMPTY_ROOT)?;
+    let code_hash = decode_hash(buf, KECCAK_EMPTY)?;
+    if started - buf.len() != header.payload_length {
+        return Err(DecodeError::ListLengthMismatch {
+            expected: header.payload_length,
+            got: started - buf.len(),
+        })
+    }
+    Ok(TrieAccount { nonce, balance, storage_root, code_hash })
+}
+
+/// The response to [GetAccountRange], the consecutive accounts of the range with the proofs of
+/// the first and the last account.
+///
+/// An empty response without a proof means that the peer doesn't serve the state of the root.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct AccountRange {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The accounts, ordered by their hash.
+    pub accounts: Vec<AccountData>,
+    /// The trie nodes of the proofs of the first and the last account.
+    pub proof: Vec<Bytes>,
+}
+
+/// A request for the storage slots of a list of accounts of the state trie of `root_hash`.
+///
+/// Only the slots of the first account are limited by `starting_hash` and `limit_hash`, which
+/// are empty for the whole storage.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct GetStorageRanges {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The state root the storage is requested of.
+    pub root_hash: H256,
+    /// The hashes of the addresses of the accounts.
+    pub account_hashes: Vec<H256>,
+    /// The hash of the first slot of the range of the first account, if any.
+    pub starting_hash: Bytes,
+    /// The hash after which no more slots of the first account are returned, if any.
+    pub limit_hash: Bytes,
+    /// The soft limit of the size of the response.
+    pub response_bytes: u64,
+}
+
+/// A storage slot of a [StorageRanges] response.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct StorageData {
+    /// The hash of the slot.
+    pub hash: H256,
+    /// The RLP encoding of the value of the slot.
+    pub data: Bytes,
+}
+
+/// The response to [GetStorageRanges], the slots of the accounts in the order of the request.
+///
+/// The slots of all accounts but the last one are complete, the proof is of the slots of the last
+/// one if they are incomplete.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct StorageRanges {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The slots of every account, ordered by their hash.
+    pub slots: Vec<Vec<StorageData>>,
+    /// The trie nodes of the proof of the slots of the last account.
+    pub proof: Vec<Bytes>,
+}
+
+/// A request for the code of the given hashes.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct GetByteCodes {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The hashes of the codes.
+    pub hashes: Vec<H256>,
+    /// The soft limit of the size of the response.
+    pub response_bytes: u64,
+}
+
+/// The response to [GetByteCodes], the known codes in the order of the request.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct ByteCodes {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The codes.
+    pub codes: Vec<Bytes>,
+}
+
+/// A request for the trie nodes of the state of `root_hash` at the given paths.
+///
+/// Every path set is either the hex-prefix encoded path of a node of the account trie, or the
+/// hash of an account followed by the hex-prefix encoded paths of nodes of its storage trie.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct GetTrieNodes {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The state root the nodes are requested of.
+    pub root_hash: H256,
+    /// The paths of the nodes.
+    pub paths: Vec<Vec<Bytes>>,
+    /// The soft limit of the size of the response.
+    pub response_bytes: u64,
+}
+
+/// The response to [GetTrieNodes], the nodes in the order of the request.
+///
+/// The response stops at the first node the peer does not have.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct TrieNodes {
+    /// The id of the request.
+    pub request_id: u64,
+    /// The encodings of the nodes.
+    pub nodes: Vec<Bytes>,
+}
+
+/// The IDs of the `snap/1` messages, relative to the offset of the capability.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum SnapMessageID {
+    GetAccountRange = 0x00,
+    AccountRange = 0x01,
+    GetStorageRanges = 0x02,
+    StorageRanges = 0x03,
+    GetByteCodes = 0x04,
+    ByteCodes = 0x05,
+    GetTrieNodes = 0x06,
+    TrieNodes = 0x07,
+}
+
+impl TryFrom<u8> for SnapMessageID {
+    type Error = DecodeError;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        Ok(match id {
+            0x00 => SnapMessageID::GetAccountRange,
+            0x01 => SnapMessageID::AccountRange,
+            0x02 => SnapMessageID::GetStorageRanges,
+            0x03 => SnapMessageID::StorageRanges,
+            0x04 => SnapMessageID::GetByteCodes,
+            0x05 => SnapMessageID::ByteCodes,
+            0x06 => SnapMessageID::GetTrieNodes,
+            0x07 => SnapMessageID::TrieNodes,
+            _ => return Err(DecodeError::Custom("Invalid snap message ID")),
+        })
+    }
+}
+
+/// A message of the `snap/1` protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum SnapMessage {
+    GetAccountRange(GetAccountRange),
+    AccountRange(AccountRange),
+    GetStorageRanges(GetStorageRanges),
+    StorageRanges(StorageRanges),
+    GetByteCodes(GetByteCodes),
+    ByteCodes(ByteCodes),
+    GetTrieNodes(GetTrieNodes),
+    TrieNodes(TrieNodes),
+}
+
+// === impl SnapMessage ===
+
+impl SnapMessage {
+    /// Returns the message's ID.
+    pub fn message_id(&self) -> SnapMessageID {
+        match self {
+            SnapMessage::GetAccountRange(_) => SnapMessageID::GetAccountRange,
+            SnapMessage::AccountRange(_) => SnapMessageID::AccountRange,
+            SnapMessage::GetStorageRanges(_) => SnapMessageID::GetStorageRanges,
+            SnapMessage::StorageRanges(_) => SnapMessageID::StorageRanges,
+            SnapMessage::GetByteCodes(_) => SnapMessageID::GetByteCodes,
+            SnapMessage::ByteCodes(_) => SnapMessageID::ByteCodes,
+            SnapMessage::GetTrieNodes(_) => SnapMessageID::GetTrieNodes,
+            SnapMessage::TrieNodes(_) => SnapMessageID::TrieNodes,
+        }
+    }
+
+    /// Returns the id of the request or of the request the message responds to.
+    pub fn request_id(&self) -> u64 {
+        match self {
+            SnapMessage::GetAccountRange(msg) => msg.request_id,
+            SnapMessage::AccountRange(msg) => msg.request_id,
+            SnapMessage::GetStorageRanges(msg) => msg.request_id,
+            SnapMessage::StorageRanges(msg) => msg.request_id,
+            SnapMessage::GetByteCodes(msg) => msg.request_id,
+            SnapMessage::ByteCodes(msg) => msg.request_id,
+            SnapMessage::GetTrieNodes(msg) => msg.request_id,
+            SnapMessage::TrieNodes(msg) => msg.request_id,
+        }
+    }
+
+    /// Returns whether the message is a request.
+    pub fn is_request(&self) -> bool {
+        (self.message_id() as u8) % 2 == 0
+    }
+
+    /// Encodes the message, prefixed with its ID plus the given offset.
+    ///
+    /// The offset is the one of the `snap` capability relative to the primary capability of the
+    /// connection.
+    pub fn encode_with_offset(&self, offset: u8, out: &mut dyn BufMut) {
+        out.put_u8(self.message_id() as u8 + offset);
+        match self {
+            SnapMessage::GetAccountRange(msg) => msg.encode(out),
+            SnapMessage::AccountRange(msg) => msg.encode(out),
+            SnapMessage::GetStorageRanges(msg) => msg.encode(out),
+            SnapMessage::StorageRanges(msg) => msg.encode(out),
+            SnapMessage::GetByteCodes(msg) => msg.encode(out),
+            SnapMessage::ByteCodes(msg) => msg.encode(out),
+            SnapMessage::GetTrieNodes(msg) => msg.encode(out),
+            SnapMessage::TrieNodes(msg) => msg.encode(out),
+        }
+    }
+
+    /// Decodes a message whose ID is prefixed with the given offset, see
+    /// [SnapMessage::encode_with_offset].
+    pub fn decode_with_offset(offset: u8, buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let id = buf.first().ok_or(DecodeError::InputTooShort)?;
+        let id = id.checked_sub(offset).ok_or(DecodeError::Custom("Invalid snap message ID"))?;
+        let id = SnapMessageID::try_from(id)?;
+        buf.advance(1);
+        Ok(match id {
+            SnapMessageID::GetAccountRange => {
+                SnapMessage::GetAccountRange(GetAccountRange::decode(buf)?)
+            }
+            SnapMessageID::AccountRange => SnapMessage::AccountRange(AccountRange::decode(buf)?),
+            SnapMessageID::GetStorageRanges => {
+                SnapMessage::GetStorageRanges(GetStorageRanges::decode(buf)?)
+            }
+            SnapMessageID::StorageRanges => {
+                SnapMessage::StorageRanges(StorageRanges::decode(buf)?)
+            }
+            SnapMessageID::GetByteCodes => SnapMessage::GetByteCodes(GetByteCodes::decode(buf)?),
+            SnapMessageID::ByteCodes => SnapMessage::ByteCodes(ByteCodes::decode(buf)?),
+            SnapMessageID::GetTrieNodes => SnapMessage::GetTrieNodes(GetTrieNodes::decode(buf)?),
+            SnapMessageID::TrieNodes => SnapMessage::TrieNodes(TrieNodes::decode(buf)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::keccak256;
+
+    fn roundtrip(message: SnapMessage) {
+        let mut encoded = Vec::new();
+        message.encode_with_offset(0x11, &mut encoded);
+        assert_eq!(encoded[0], message.message_id() as u8 + 0x11);
+        let decoded = SnapMessage::decode_with_offset(0x11, &mut &encoded[..]).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    fn snap_messages_roundtrip() {
+        let account = TrieAccount {
+            nonce: 1,
+            balance: U256::from(100),
+            storage_root: EMPTY_ROOT,
+            code_hash: KECCAK_EMPTY,
+        };
+        let contract = TrieAccount {
+            storage_root: keccak256([1]),
+            code_hash: keccak256([2]),
+            ..account
+        };
+
+        roundtrip(SnapMessage::GetAccountRange(GetAccountRange {
+            request_id: 1,
+            root_hash: keccak256([0]),
+            starting_hash: H256::zero(),
+            limit_hash: H256::repeat_byte(0xff),
+            response_bytes: 512 * 1024,
+        }));
+        roundtrip(SnapMessage::AccountRange(AccountRange {
+            request_id: 2,
+            accounts: vec![
+                AccountData { hash: keccak256([3]), account },
+                AccountData { hash: keccak256([4]), account: contract },
+            ],
+            proof: vec![Bytes::from(vec![0xc0])],
+        }));
+        roundtrip(SnapMessage::GetStorageRanges(GetStorageRanges {
+            request_id: 3,
+            root_hash: keccak256([0]),
+            account_hashes: vec![keccak256([4])],
+            starting_hash: Bytes::default(),
+            limit_hash: Bytes::default(),
+            response_bytes: 1024,
+        }));
+        roundtrip(SnapMessage::StorageRanges(StorageRanges {
+            request_id: 4,
+            slots: vec![vec![StorageData { hash: keccak256([5]), data: Bytes::from(vec![0x01]) }]],
+            proof: vec![],
+        }));
+        roundtrip(SnapMessage::GetByteCodes(GetByteCodes {
+            request_id: 5,
+            hashes: vec![keccak256([2])],
+            response_bytes: 1024,
+        }));
+        roundtrip(SnapMessage::ByteCodes(ByteCodes {
+            request_id: 6,
+            codes: vec![Bytes::from(vec![0x60, 0x00])],
+        }));
+        roundtrip(SnapMessage::GetTrieNodes(GetTrieNodes {
+            request_id: 7,
+            root_hash: keccak256([0]),
+            paths: vec![vec![Bytes::from(vec![0x00])], vec![keccak256([4]).0.into(), Bytes::default()]],
+            response_bytes: 1024,
+        }));
+        roundtrip(SnapMessage::TrieNodes(TrieNodes { request_id: 8, nodes: vec![] }));
+    }
+
+    #[test]
+    fn slim_account_encoding() {
+        let account = AccountData { hash: H256::zero(), account: TrieAccount {
+            nonce: 0,
+            balance: U256::zero(),
+            storage_root: EMPTY_ROOT,
+            code_hash: KECCAK_EMPTY,
+        } };
+        let mut encoded = Vec::new();
+        account.encode(&mut encoded);
+        assert_eq!(encoded.len(), account.length());
+        // the hash, followed by a list of a zero nonce and balance and two empty strings
+        assert_eq!(&encoded[34..], &[0xc4, 0x80, 0x80, 0x80, 0x80]);
+
+        // other messages are no snap messages
+        assert!(SnapMessage::decode_with_offset(0x11, &mut &[0x10, 0xc0][..]).is_err());
+    }
+}
@@ -21,13 +21,16 @@ pub enum EthVersion {
 }
 
 impl EthVersion {
-    /// Returns the total number of messages the protocol version supports.
+    /// Returns the size of the message ID space the protocol version reserves.
+    ///
+    /// This determines the offset of the next shared capability.
     pub fn total_messages(&self) -> u8 {
         match self {
-            EthVersion::Eth66 => 15,
+            EthVersion::Eth66 => 17,
             EthVersion::Eth67 => {
-                // eth/67 is eth/66 minus GetNodeData and NodeData messages
-                13
+                // eth/67 is eth/66 minus GetNodeData and NodeData messages, but their IDs stay
+                // reserved
+                17
             }
         }
     }
@@ -10,9 +10,11 @@
 use igd::aio::search_gateway;
 use pin_project_lite::pin_project;
 use std::{
+    fmt,
     future::Future,
-    net::IpAddr,
+    net::{AddrParseError, IpAddr},
     pin::Pin,
+    str::FromStr,
     task::{ready, Context, Poll},
 };
 use tracing::warn;
@@ -25,8 +27,14 @@ pub enum NatResolver {
     Any,
     /// Resolve via Upnp
     Upnp,
+    /// Resolve via NAT-PMP
+    NatPmp,
     /// Resolve external IP via [public_ip::Resolver]
-    ExternalIp,
+    PublicIp,
+    /// Use the given IP as external IP.
+    ExternalIp(IpAddr),
+    /// Don't resolve an external IP and use the local address.
+    None,
 }
 
 // === impl NatResolver ===
@@ -38,6 +46,51 @@ impl NatResolver {
     }
 }
 
+impl fmt::Display for NatResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NatResolver::Any => f.write_str("any"),
+            NatResolver::Upnp => f.write_str("upnp"),
+            NatResolver::NatPmp => f.write_str("natpmp"),
+            NatResolver::PublicIp => f.write_str("publicip"),
+            NatResolver::ExternalIp(ip) => write!(f, "extip:{ip}"),
+            NatResolver::None => f.write_str("none"),
+        }
+    }
+}
+
+/// Error when parsing a [NatResolver]
+#[derive(Debug, thiserror::Error)]
+pub enum ParseNatResolverError {
+    /// Failed to parse the IP of `extip:<IP>`
+    #[error(transparent)]
+    AddrParseError(#[from] AddrParseError),
+    /// Unknown resolver
+    #[error("Unknown Nat Resolver: {0}")]
+    UnknownVariant(String),
+}
+
+impl FromStr for NatResolver {
+    type Err = ParseNatResolverError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let r = match s {
+            "any" => NatResolver::Any,
+            "upnp" => NatResolver::Upnp,
+            "natpmp" => NatResolver::NatPmp,
+            "publicip" | "public-ip" => NatResolver::PublicIp,
+            "none" => NatResolver::None,
+            s => {
+                let ip = s
+                    .strip_prefix("extip:")
+                    .ok_or_else(|| ParseNatResolverError::UnknownVariant(s.to_string()))?;
+                NatResolver::ExternalIp(ip.parse()?)
+            }
+        };
+        Ok(r)
+    }
+}
+
 /// Attempts to produce an IP address with all builtin resolvers (best effort).
 pub async fn external_ip() -> Option<IpAddr> {
     external_addr_with(NatResolver::Any).await
@@ -54,7 +107,10 @@ pub async fn external_addr_with(resolver: NatResolver) -> Option<IpAddr> {
             .await
         }
         NatResolver::Upnp => resolve_external_ip_upnp().await,
-        NatResolver::ExternalIp => resolve_external_ip().await,
+        NatResolver::NatPmp => resolve_external_ip_natpmp().await,
+        NatResolver::PublicIp => resolve_external_ip().await,
+        NatResolver::ExternalIp(ip) => Some(ip),
+        NatResolver::None => None,
     }
 }
 
@@ -119,6 +175,32 @@ async fn resolve_external_ip_upnp() -> Option<IpAddr> {
         .ok()
 }
 
+async fn resolve_external_ip_natpmp() -> Option<IpAddr> {
+    let client = natpmp::new_tokio_natpmp()
+        .await
+        .map_err(|err| {
+            warn!(target: "net::nat", ?err, "failed to find nat-pmp gateway");
+            err
+        })
+        .ok()?;
+    client
+        .send_public_address_request()
+        .await
+        .map_err(|err| {
+            warn!(target: "net::nat", ?err, "failed to send nat-pmp public address request");
+            err
+        })
+        .ok()?;
+    match client.read_response_or_retry().await {
+        Ok(natpmp::Response::Gateway(response)) => Some(IpAddr::V4(*response.public_address())),
+        Ok(_) => None,
+        Err(err) => {
+            warn!(target: "net::nat", ?err, "failed to resolve external ip via nat-pmp gateway");
+            None
+        }
+    }
+}
+
 async fn resolve_external_ip() -> Option<IpAddr> {
     public_ip::addr().await
 }
@@ -127,6 +209,22 @@ async fn resolve_external_ip() -> Option<IpAddr> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_nat_resolver() {
+        for resolver in [
+            NatResolver::Any,
+            NatResolver::Upnp,
+            NatResolver::NatPmp,
+            NatResolver::PublicIp,
+            NatResolver::ExternalIp("1.2.3.4".parse().unwrap()),
+            NatResolver::None,
+        ] {
+            assert_eq!(resolver.to_string().parse::<NatResolver>().unwrap(), resolver);
+        }
+        assert!("extip:invalid".parse::<NatResolver>().is_err());
+        assert!("stun".parse::<NatResolver>().is_err());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn get_external_ip() {
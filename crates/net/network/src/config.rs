@@ -4,13 +4,19 @@ use crate::{
     import::{BlockImport, ProofOfStakeBlockImport},
     peers::PeersConfig,
     session::SessionsConfig,
+    NodeInfo,
 };
-use reth_discv4::{Discv4Config, Discv4ConfigBuilder, NodeRecord, DEFAULT_DISCOVERY_PORT};
+use reth_discv4::{
+    local_enr, Discv4Config, Discv4ConfigBuilder, NatResolver, NodeRecord, DEFAULT_DISCOVERY_PORT,
+};
+use reth_dns_discovery::DnsDiscoveryConfig;
 use reth_primitives::{Chain, ForkFilter, Hardfork, PeerId, H256, MAINNET_GENESIS};
 use reth_tasks::TaskExecutor;
 use secp256k1::{SecretKey, SECP256K1};
 use std::{
+    io,
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    path::Path,
     sync::Arc,
 };
 
@@ -29,6 +35,26 @@ pub fn rng_secret_key() -> SecretKey {
     SecretKey::new(&mut rand::thread_rng())
 }
 
+/// Reads the hex encoded [`SecretKey`] from the file at the given path, or writes a new random key
+/// to it if the file doesn't exist.
+///
+/// A node that loads its key from a file keeps its identity, and thereby its enode URL and ENR,
+/// across restarts.
+pub fn load_or_create_secret_key(path: &Path) -> io::Result<SecretKey> {
+    let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidData, err);
+    if path.exists() {
+        let hex = std::fs::read_to_string(path)?;
+        let bytes = hex::decode(hex.trim()).map_err(|err| invalid(err.to_string()))?;
+        return SecretKey::from_slice(&bytes).map_err(|err| invalid(err.to_string()))
+    }
+    let secret_key = rng_secret_key();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, hex::encode(secret_key.secret_bytes()))?;
+    Ok(secret_key)
+}
+
 /// All network related initialization settings.
 pub struct NetworkConfig<C> {
     /// The client type that can interact with the chain.
@@ -37,6 +63,8 @@ pub struct NetworkConfig<C> {
     pub secret_key: SecretKey,
     /// All boot nodes to start network discovery with.
     pub boot_nodes: Vec<NodeRecord>,
+    /// How to set up discovery over DNS.
+    pub dns_discovery_config: Option<DnsDiscoveryConfig>,
     /// How to set up discovery.
     pub discovery_v4_config: Discv4Config,
     /// Address to use for discovery
@@ -62,7 +90,7 @@ pub struct NetworkConfig<C> {
     pub block_import: Box<dyn BlockImport>,
     /// The default mode of the network.
     pub network_mode: NetworkMode,
-    /// The executor to use for spawning tasks.
+    /// The executor to use for spawning tasks, the current runtime if not set.
     pub executor: Option<TaskExecutor>,
     /// The `Status` message to send to peers at the beginning.
     pub status: Status,
@@ -94,6 +122,29 @@ impl<C> NetworkConfig<C> {
         self.listener_addr = listener_addr;
         self
     }
+
+    /// Returns the [`NodeInfo`] the network advertises once it's started with this config.
+    ///
+    /// The addresses are the configured ones, which can differ from those of the running network
+    /// if a port is `0` or the external IP is resolved, see [`NetworkHandle::node_info`].
+    ///
+    /// [`NetworkHandle::node_info`]: crate::NetworkHandle::node_info
+    pub fn node_info(&self) -> NodeInfo {
+        let mut record = NodeRecord::from_secret_key(self.discovery_addr, &self.secret_key);
+        record.tcp_port = self.listener_addr.port();
+
+        let mut discovery_v4_config = self.discovery_v4_config.clone();
+        discovery_v4_config.add_eip868_pair("eth", self.status.forkid);
+
+        NodeInfo {
+            record,
+            enr: local_enr(&record, &self.secret_key, &discovery_v4_config),
+            listener_addr: self.listener_addr,
+            discovery_addr: self.discovery_addr,
+            client_version: self.hello_message.client_version.clone(),
+            capabilities: self.hello_message.capabilities.clone(),
+        }
+    }
 }
 
 /// Builder for [`NetworkConfig`](struct.NetworkConfig.html).
@@ -103,6 +154,8 @@ pub struct NetworkConfigBuilder<C> {
     client: Arc<C>,
     /// The node's secret key, from which the node's identity is derived.
     secret_key: SecretKey,
+    /// How to configure discovery over DNS.
+    dns_discovery_config: Option<DnsDiscoveryConfig>,
     /// How to set up discovery.
     discovery_v4_builder: Discv4ConfigBuilder,
     /// All boot nodes to start network discovery with.
@@ -143,6 +196,7 @@ impl<C> NetworkConfigBuilder<C> {
         Self {
             client,
             secret_key,
+            dns_discovery_config: None,
             discovery_v4_builder: Default::default(),
             boot_nodes: vec![],
             discovery_addr: None,
@@ -253,6 +307,18 @@ impl<C> NetworkConfigBuilder<C> {
         self
     }
 
+    /// Sets the [`NatResolver`] used to resolve the external IP that is advertised via discovery.
+    pub fn external_ip_resolver(mut self, resolver: NatResolver) -> Self {
+        self.discovery_v4_builder.external_ip_resolver(Some(resolver));
+        self
+    }
+
+    /// Sets the dns discovery config to use.
+    pub fn dns_discovery(mut self, config: DnsDiscoveryConfig) -> Self {
+        self.dns_discovery_config = Some(config);
+        self
+    }
+
     /// Sets the discv4 config to use.
     pub fn boot_nodes(mut self, nodes: impl IntoIterator<Item = NodeRecord>) -> Self {
         self.boot_nodes = nodes.into_iter().collect();
@@ -265,6 +331,7 @@ impl<C> NetworkConfigBuilder<C> {
         let Self {
             client,
             secret_key,
+            dns_discovery_config,
             discovery_v4_builder,
             boot_nodes,
             discovery_addr,
@@ -301,6 +368,7 @@ impl<C> NetworkConfigBuilder<C> {
             client,
             secret_key,
             boot_nodes,
+            dns_discovery_config,
             discovery_v4_config: discovery_v4_builder.build(),
             discovery_addr: discovery_addr.unwrap_or_else(|| {
                 SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DEFAULT_DISCOVERY_PORT))
@@ -3,14 +3,18 @@
 use crate::error::NetworkError;
 use futures::StreamExt;
 use reth_discv4::{DiscoveryUpdate, Discv4, Discv4Config, NodeRecord};
+use reth_dns_discovery::{
+    DnsDiscoveryConfig, DnsDiscoveryHandle, DnsDiscoveryService, DnsNodeRecordUpdate, DnsResolver,
+};
 use reth_primitives::{ForkId, PeerId};
+use reth_tasks::TaskExecutor;
 use secp256k1::SecretKey;
 use std::{
     collections::{hash_map::Entry, HashMap, VecDeque},
     net::{IpAddr, SocketAddr},
+    sync::Arc,
     task::{Context, Poll},
 };
-use tokio::task::JoinHandle;
 use tokio_stream::wrappers::ReceiverStream;
 
 /// An abstraction over the configured discovery protocol.
@@ -31,19 +35,26 @@ pub struct Discovery {
     _dsicv4_config: Discv4Config,
     /// Events buffered until polled.
     queued_events: VecDeque<DiscoveryEvent>,
-    /// The handle to the spawned discv4 service
-    _discv4_service: JoinHandle<()>,
+    /// Handler to interact with the DNS discovery service
+    _dns_discovery: Option<DnsDiscoveryHandle>,
+    /// Updates from the DNS discovery service.
+    dns_discovery_updates: Option<ReceiverStream<DnsNodeRecordUpdate>>,
 }
 
 impl Discovery {
     /// Spawns the discovery service.
     ///
-    /// This will spawn the [`reth_discv4::Discv4Service`] onto a new task and establish a listener
-    /// channel to receive all discovered nodes.
+    /// This will spawn the [`reth_discv4::Discv4Service`] onto a new task of the executor and
+    /// establish a listener channel to receive all discovered nodes.
+    ///
+    /// If a [`DnsDiscoveryConfig`] is provided, this will also spawn the
+    /// [`DnsDiscoveryService`] and merge all nodes it resolves into the discovered set.
     pub async fn new(
         discovery_addr: SocketAddr,
         sk: SecretKey,
         dsicv4_config: Discv4Config,
+        dns_discovery_config: Option<DnsDiscoveryConfig>,
+        executor: &TaskExecutor,
     ) -> Result<Self, NetworkError> {
         let local_enr = NodeRecord::from_secret_key(discovery_addr, &sk);
         let (discv4, mut discv4_service) =
@@ -53,14 +64,29 @@ impl Discovery {
         let discv4_updates = discv4_service.update_stream();
 
         // spawn the service
-        let _discv4_service = discv4_service.spawn();
+        discv4_service.spawn(executor);
+
+        // setup DNS discovery
+        let (_dns_discovery, dns_discovery_updates) = if let Some(dns_config) = dns_discovery_config
+        {
+            let (mut service, dns_disc) = DnsDiscoveryService::new_pair(
+                Arc::new(DnsResolver::from_system_conf()?),
+                dns_config,
+            );
+            let dns_discovery_updates = service.node_record_stream();
+            service.spawn(executor);
+            (Some(dns_disc), Some(dns_discovery_updates))
+        } else {
+            (None, None)
+        };
 
         Ok(Self {
             local_enr,
             discv4,
             discv4_updates,
             _dsicv4_config: dsicv4_config,
-            _discv4_service,
+            _dns_discovery,
+            dns_discovery_updates,
             discovered_nodes: Default::default(),
             queued_events: Default::default(),
         })
@@ -87,18 +113,31 @@ impl Discovery {
         self.local_enr.id
     }
 
+    /// Returns the frontend of the discv4 service.
+    pub(crate) fn discv4(&self) -> Discv4 {
+        self.discv4.clone()
+    }
+
+    /// Processes an incoming [NodeRecord] update from a discovery service
+    fn on_node_record_update(&mut self, record: NodeRecord, fork_id: Option<ForkId>) {
+        let id = record.id;
+        let addr = record.tcp_addr();
+        match self.discovered_nodes.entry(id) {
+            Entry::Occupied(_entry) => {}
+            Entry::Vacant(entry) => {
+                entry.insert(addr);
+                self.queued_events.push_back(DiscoveryEvent::Discovered(id, addr));
+                if let Some(fork_id) = fork_id {
+                    self.queued_events.push_back(DiscoveryEvent::EnrForkId(id, fork_id));
+                }
+            }
+        }
+    }
+
     fn on_discv4_update(&mut self, update: DiscoveryUpdate) {
         match update {
             DiscoveryUpdate::Added(node) => {
-                let id = node.id;
-                let addr = node.tcp_addr();
-                match self.discovered_nodes.entry(id) {
-                    Entry::Occupied(_entry) => {}
-                    Entry::Vacant(entry) => {
-                        entry.insert(addr);
-                        self.queued_events.push_back(DiscoveryEvent::Discovered(id, addr))
-                    }
-                }
+                self.on_node_record_update(node, None);
             }
             DiscoveryUpdate::EnrForkId(node, fork_id) => {
                 self.queued_events.push_back(DiscoveryEvent::EnrForkId(node.id, fork_id))
@@ -126,6 +165,13 @@ impl Discovery {
                 self.on_discv4_update(update)
             }
 
+            // drain the dns update stream
+            while let Some(Poll::Ready(Some(update))) =
+                self.dns_discovery_updates.as_mut().map(|updates| updates.poll_next_unpin(cx))
+            {
+                self.on_node_record_update(update.node_record, update.fork_id);
+            }
+
             if self.queued_events.is_empty() {
                 return Poll::Pending
             }
@@ -153,7 +199,14 @@ mod tests {
         let mut rng = thread_rng();
         let (secret_key, _) = SECP256K1.generate_keypair(&mut rng);
         let discovery_addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
-        let _discovery =
-            Discovery::new(discovery_addr, secret_key, Default::default()).await.unwrap();
+        let _discovery = Discovery::new(
+            discovery_addr,
+            secret_key,
+            Default::default(),
+            Default::default(),
+            &TaskExecutor::current(),
+        )
+        .await
+        .unwrap();
     }
 }
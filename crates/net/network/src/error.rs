@@ -2,7 +2,7 @@
 
 use crate::session::PendingSessionHandshakeError;
 use reth_eth_wire::{
-    error::{EthStreamError, HandshakeError, P2PHandshakeError, P2PStreamError},
+    error::{EthStreamError, HandshakeError, MultiplexError, P2PHandshakeError, P2PStreamError},
     DisconnectReason,
 };
 use std::fmt;
@@ -16,6 +16,11 @@ pub enum NetworkError {
     /// IO error when creating the discovery service
     #[error("Failed to launch discovery service: {0}")]
     Discovery(std::io::Error),
+    /// Error when setting up the DNS resolver failed
+    ///
+    /// See also [DnsResolver](reth_dns_discovery::DnsResolver::from_system_conf)
+    #[error("Failed to configure DNS resolver: {0}")]
+    DnsResolver(#[from] reth_dns_discovery::resolver::ResolveError),
 }
 
 /// Abstraction over errors that can lead to a failed session
@@ -84,6 +89,7 @@ impl SessionError for EthStreamError {
                 )
             }
             EthStreamError::HandshakeError(err) => !matches!(err, HandshakeError::NoResponse),
+            EthStreamError::Multiplex(MultiplexError::UnknownMessageId(_)) => true,
             _ => false,
         }
     }
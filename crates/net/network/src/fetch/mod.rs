@@ -199,17 +199,14 @@ impl StateFetcher {
         peer_id: PeerId,
         res: RequestResult<Vec<Header>>,
     ) -> Option<BlockResponseOutcome> {
-        let is_error = res.is_err();
+        let reputation_change = res.as_ref().err().and_then(reputation_change_for_error);
         if let Some(resp) = self.inflight_headers_requests.remove(&peer_id) {
             let _ = resp.response.send(res.map(|h| (peer_id, h).into()));
         }
 
-        if is_error {
+        if let Some(reputation_change) = reputation_change {
             // if the response was erroneous we want to report the peer.
-            return Some(BlockResponseOutcome::BadResponse(
-                peer_id,
-                ReputationChangeKind::BadMessage,
-            ))
+            return Some(BlockResponseOutcome::BadResponse(peer_id, reputation_change))
         }
 
         if let Some(peer) = self.peers.get_mut(&peer_id) {
@@ -228,9 +225,14 @@ impl StateFetcher {
         peer_id: PeerId,
         res: RequestResult<Vec<BlockBody>>,
     ) -> Option<BlockResponseOutcome> {
+        let reputation_change = res.as_ref().err().and_then(reputation_change_for_error);
         if let Some(resp) = self.inflight_bodies_requests.remove(&peer_id) {
             let _ = resp.response.send(res.map(|b| (peer_id, b).into()));
         }
+
+        if let Some(reputation_change) = reputation_change {
+            return Some(BlockResponseOutcome::BadResponse(peer_id, reputation_change))
+        }
         if let Some(peer) = self.peers.get_mut(&peer_id) {
             if peer.state.on_request_finished() {
                 return self.followup_request(peer_id)
@@ -248,6 +250,20 @@ impl StateFetcher {
     }
 }
 
+/// Returns the reputation change to apply to a peer whose request failed with the given error.
+///
+/// Errors that are not attributable to the peer, like a closed channel, are not penalized.
+fn reputation_change_for_error(err: &RequestError) -> Option<ReputationChangeKind> {
+    match err {
+        RequestError::Timeout => Some(ReputationChangeKind::Timeout),
+        RequestError::BadResponse => Some(ReputationChangeKind::BadMessage),
+        RequestError::ChannelClosed |
+        RequestError::NotConnected |
+        RequestError::ConnectionDropped |
+        RequestError::UnsupportedCapability => None,
+    }
+}
+
 /// The outcome of [`StateFetcher::poll_action`]
 enum PollAction {
     Ready(FetchAction),
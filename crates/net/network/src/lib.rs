@@ -33,6 +33,11 @@
 //!
 //!        * Responds to incoming ETH related requests: `Headers`, `Bodies`
 //!
+//!    - `SNAP request Task`: is an optional spawned
+//!      [`SnapRequestHandler`](crate::snap_requests::SnapRequestHandler) future that:
+//!
+//!        * Responds to incoming SNAP related requests: `AccountRange`, `StorageRanges`
+//!
 //!    - `Discovery Task`: is a spawned [`Discv4`](reth_discv4::Discv4) future that handles peer
 //!      discovery and emits new peers to the `Network`
 //!
@@ -120,9 +125,11 @@ mod import;
 mod listener;
 mod manager;
 mod message;
+mod metrics;
 mod network;
 pub mod peers;
 mod session;
+pub mod snap_requests;
 mod state;
 mod swarm;
 pub mod transactions;
@@ -131,6 +138,8 @@ pub use builder::NetworkBuilder;
 pub use config::{NetworkConfig, NetworkConfigBuilder};
 pub use fetch::FetchClient;
 pub use manager::{NetworkEvent, NetworkManager};
-pub use message::PeerRequest;
-pub use network::NetworkHandle;
+pub use message::{PeerRequest, SnapRequest};
+pub use metrics::NetworkMetrics;
+pub use network::{NetworkHandle, NodeInfo};
 pub use peers::PeersConfig;
+pub use reth_discv4::{NatResolver, NodeRecord};
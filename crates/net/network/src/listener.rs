@@ -102,6 +102,7 @@ impl Stream for TcpListenerStream {
 mod tests {
     use super::*;
     use futures::pin_mut;
+    use reth_tasks::TaskExecutor;
     use std::net::{Ipv4Addr, SocketAddrV4};
     use tokio::macros::support::poll_fn;
 
@@ -113,7 +114,7 @@ mod tests {
                 .unwrap();
         let local_addr = listener.local_address();
 
-        tokio::task::spawn(async move {
+        TaskExecutor::current().spawn("listener", async move {
             pin_mut!(listener);
             match poll_fn(|cx| listener.as_mut().poll(cx)).await {
                 ListenerEvent::Incoming { .. } => {}
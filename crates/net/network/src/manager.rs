@@ -22,10 +22,14 @@ use crate::{
     eth_requests::IncomingEthRequest,
     import::{BlockImport, BlockImportOutcome, BlockValidation},
     listener::ConnectionListener,
-    message::{NewBlockMessage, PeerMessage, PeerRequest, PeerRequestSender},
+    message::{
+        NewBlockMessage, PeerMessage, PeerRequest, PeerRequestSender, PeerSnapRequest, SnapRequest,
+    },
+    metrics::NetworkMetrics,
     network::{NetworkHandle, NetworkHandleMessage},
     peers::{PeersHandle, PeersManager, ReputationChangeKind},
     session::SessionManager,
+    snap_requests::IncomingSnapRequest,
     state::NetworkState,
     swarm::{Swarm, SwarmEvent},
     transactions::NetworkTransactionEvent,
@@ -35,10 +39,13 @@ use futures::{Future, StreamExt};
 use parking_lot::Mutex;
 use reth_eth_wire::{
     capability::{Capabilities, CapabilityMessage},
+    types::snap::{AccountRange, StorageRanges},
     DisconnectReason, Status,
 };
+use reth_interfaces::p2p::error::RequestError;
 use reth_primitives::{PeerId, H256};
 use reth_provider::BlockProvider;
+use reth_tasks::TaskExecutor;
 use std::{
     net::SocketAddr,
     pin::Pin,
@@ -97,11 +104,16 @@ pub struct NetworkManager<C> {
     /// Sender half to send events to the
     /// [`EthRequestHandler`](crate::eth_requests::EthRequestHandler) task, if configured.
     to_eth_request_handler: Option<mpsc::UnboundedSender<IncomingEthRequest>>,
+    /// Sender half to send events to the
+    /// [`SnapRequestHandler`](crate::snap_requests::SnapRequestHandler) task, if configured.
+    to_snap_request_handler: Option<mpsc::UnboundedSender<IncomingSnapRequest>>,
     /// Tracks the number of active session (connected peers).
     ///
     /// This is updated via internal events and shared via `Arc` with the [`NetworkHandle`]
     /// Updated by the `NetworkWorker` and loaded by the `NetworkService`.
     num_active_peers: Arc<AtomicUsize>,
+    /// Metrics for the Network
+    metrics: NetworkMetrics,
 }
 
 // === impl NetworkManager ===
@@ -118,6 +130,12 @@ impl<C> NetworkManager<C> {
         self.to_eth_request_handler = Some(tx);
     }
 
+    /// Sets the dedicated channel for events indented for the
+    /// [`SnapRequestHandler`](crate::snap_requests::SnapRequestHandler).
+    pub fn set_snap_request_handler(&mut self, tx: mpsc::UnboundedSender<IncomingSnapRequest>) {
+        self.to_snap_request_handler = Some(tx);
+    }
+
     /// Returns the [`NetworkHandle`] that can be cloned and shared.
     ///
     /// The [`NetworkHandle`] can be used to interact with this [`NetworkManager`]
@@ -151,6 +169,7 @@ where
             hello_message,
             status,
             fork_filter,
+            dns_discovery_config,
             ..
         } = config;
 
@@ -164,9 +183,22 @@ where
         discovery_v4_config.bootstrap_nodes.extend(boot_nodes.clone());
         discovery_v4_config.add_eip868_pair("eth", status.forkid);
 
-        let discovery = Discovery::new(discovery_addr, secret_key, discovery_v4_config).await?;
+        // without the executor of the node, the tasks run on the current runtime
+        let executor = executor.unwrap_or_else(TaskExecutor::current);
+        let discovery = Discovery::new(
+            discovery_addr,
+            secret_key,
+            discovery_v4_config,
+            dns_discovery_config,
+            &executor,
+        )
+        .await?;
         // need to retrieve the addr here since provided port could be `0`
         let local_peer_id = discovery.local_id();
+        let discv4 = discovery.discv4();
+        // the advertised records point to the port that accepts incoming connections
+        discv4.set_tcp_port(listener_address.lock().port());
+        let local_hello_message = hello_message.clone();
 
         let sessions = SessionManager::new(
             secret_key,
@@ -190,6 +222,8 @@ where
             local_peer_id,
             peers_handle,
             network_mode,
+            discv4,
+            &local_hello_message,
         );
 
         Ok(Self {
@@ -200,7 +234,9 @@ where
             event_listeners: Default::default(),
             to_transactions_manager: None,
             to_eth_request_handler: None,
+            to_snap_request_handler: None,
             num_active_peers,
+            metrics: Default::default(),
         })
     }
 
@@ -350,6 +386,53 @@ where
         }
     }
 
+    /// Handle an incoming `snap` request from the peer
+    ///
+    /// Without a [`SnapRequestHandler`](crate::snap_requests::SnapRequestHandler), empty responses
+    /// tell the peer that the state of the requested root is not available.
+    fn on_received_snap_request(&mut self, peer_id: PeerId, req: PeerSnapRequest) {
+        let Some(ref reqs) = self.to_snap_request_handler else {
+            match req {
+                PeerSnapRequest::GetAccountRange { request, response } => {
+                    let request_id = request.request_id;
+                    let _ = response.send(Ok(AccountRange {
+                        request_id,
+                        accounts: vec![],
+                        proof: vec![],
+                    }));
+                }
+                PeerSnapRequest::GetStorageRanges { request, response } => {
+                    let request_id = request.request_id;
+                    let _ = response.send(Ok(StorageRanges {
+                        request_id,
+                        slots: vec![],
+                        proof: vec![],
+                    }));
+                }
+            }
+            return
+        };
+        let event = match req {
+            PeerSnapRequest::GetAccountRange { request, response } => {
+                IncomingSnapRequest::GetAccountRange { peer_id, request, response }
+            }
+            PeerSnapRequest::GetStorageRanges { request, response } => {
+                IncomingSnapRequest::GetStorageRanges { peer_id, request, response }
+            }
+        };
+        let _ = reqs.send(event);
+    }
+
+    /// Sends the `snap` request to the next peer that supports the protocol.
+    fn on_snap_request(&mut self, request: SnapRequest) {
+        match self.swarm.state_mut().next_snap_peer() {
+            Some(peer_id) => {
+                self.swarm.sessions_mut().send_message(&peer_id, PeerMessage::SnapRequest(request))
+            }
+            None => request.send_err_response(RequestError::NotConnected),
+        }
+    }
+
     /// Invoked after a `NewBlock` message from the peer was validated
     fn on_block_import_result(&mut self, outcome: BlockImportOutcome) {
         let BlockImportOutcome { peer, result } = outcome;
@@ -417,6 +500,12 @@ where
             PeerMessage::EthRequest(req) => {
                 self.on_eth_request(peer_id, req);
             }
+            PeerMessage::ReceivedSnapRequest(req) => {
+                self.on_received_snap_request(peer_id, req);
+            }
+            PeerMessage::SnapRequest(_) => {
+                unreachable!("Not emitted by session")
+            }
             PeerMessage::ReceivedTransaction(msg) => {
                 self.notify_tx_manager(NetworkTransactionEvent::IncomingTransactions {
                     peer_id,
@@ -449,6 +538,7 @@ where
             NetworkHandleMessage::EthRequest { peer_id, request } => {
                 self.swarm.sessions_mut().send_message(&peer_id, PeerMessage::EthRequest(request))
             }
+            NetworkHandleMessage::SnapRequest(request) => self.on_snap_request(request),
             NetworkHandleMessage::SendTransaction { peer_id, msg } => {
                 self.swarm.sessions_mut().send_message(&peer_id, PeerMessage::SendTransactions(msg))
             }
@@ -538,6 +628,7 @@ where
                     direction,
                 } => {
                     let total_active = this.num_active_peers.fetch_add(1, Ordering::Relaxed) + 1;
+                    this.metrics.connected_peers.set(total_active as f64);
                     info!(
                         target : "net",
                         ?remote_addr,
@@ -562,14 +653,18 @@ where
                 }
                 SwarmEvent::PeerAdded(peer_id) => {
                     info!(target: "net", ?peer_id, "Peer added");
+                    this.metrics.tracked_peers.increment(1f64);
                     this.event_listeners.send(NetworkEvent::PeerAdded(peer_id));
                 }
                 SwarmEvent::PeerRemoved(peer_id) => {
                     info!(target: "net", ?peer_id, "Peer dropped");
+                    this.metrics.tracked_peers.decrement(1f64);
                     this.event_listeners.send(NetworkEvent::PeerRemoved(peer_id));
                 }
                 SwarmEvent::SessionClosed { peer_id, remote_addr, error } => {
                     let total_active = this.num_active_peers.fetch_sub(1, Ordering::Relaxed) - 1;
+                    this.metrics.connected_peers.set(total_active as f64);
+                    this.metrics.closed_sessions.increment(1);
                     trace!(
                         target : "net",
                         ?remote_addr,
@@ -605,6 +700,7 @@ where
                         ?error,
                         "Incoming pending session failed"
                     );
+                    this.metrics.pending_session_failures.increment(1);
 
                     if let Some(ref err) = error {
                         this.swarm
@@ -626,6 +722,7 @@ where
                         ?error,
                         "Outgoing pending session failed"
                     );
+                    this.metrics.pending_session_failures.increment(1);
 
                     if let Some(ref err) = error {
                         this.swarm.state_mut().peers_mut().on_pending_session_dropped(
@@ -654,6 +751,7 @@ where
                         .apply_reputation_change(&peer_id, ReputationChangeKind::FailedToConnect);
                 }
                 SwarmEvent::BadMessage { peer_id } => {
+                    this.metrics.invalid_messages_received.increment(1);
                     this.swarm
                         .state_mut()
                         .peers_mut()
@@ -5,12 +5,17 @@
 
 use futures::FutureExt;
 use reth_eth_wire::{
-    capability::RawCapabilityMessage, message::RequestPair, BlockBodies, BlockBody, BlockHeaders,
-    EthMessage, GetBlockBodies, GetBlockHeaders, GetNodeData, GetPooledTransactions, GetReceipts,
-    NewBlock, NewBlockHashes, NewPooledTransactionHashes, NodeData, PooledTransactions, Receipts,
-    SharedTransactions, Transactions,
+    capability::RawCapabilityMessage,
+    message::RequestPair,
+    types::snap::{
+        AccountRange, ByteCodes, GetAccountRange, GetByteCodes, GetStorageRanges, GetTrieNodes,
+        StorageRanges, TrieNodes,
+    },
+    BlockBodies, BlockBody, BlockHeaders, EthMessage, GetBlockBodies, GetBlockHeaders, GetNodeData,
+    GetPooledTransactions, GetReceipts, NewBlock, NewBlockHashes, NewPooledTransactionHashes,
+    NodeData, PooledTransactions, Receipts, SharedTransactions, SnapMessage, Transactions,
 };
-use reth_interfaces::p2p::error::{RequestError, RequestResult};
+use reth_interfaces::p2p::error::{PeerRequestResult, RequestError, RequestResult};
 use reth_primitives::{Header, PeerId, Receipt, TransactionSigned, H256};
 use std::{
     fmt,
@@ -53,6 +58,10 @@ pub enum PeerMessage {
     PooledTransactions(NewPooledTransactionHashes),
     /// All `eth` request variants.
     EthRequest(PeerRequest),
+    /// All `snap` request variants.
+    SnapRequest(SnapRequest),
+    /// The `snap` requests _from_ the peer that are served from the local state.
+    ReceivedSnapRequest(PeerSnapRequest),
     /// Other than eth namespace message
     #[allow(unused)]
     Other(RawCapabilityMessage),
@@ -146,6 +155,150 @@ impl PeerRequest {
     }
 }
 
+/// Requests of the `snap` protocol that expect a response.
+///
+/// Unlike the responses of a [`PeerRequest`], the responses carry the peer that served them, since
+/// the network picks the peer.
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names, missing_docs)]
+pub enum SnapRequest {
+    /// Request a range of accounts from the peer.
+    ///
+    /// The response should be sent through the channel.
+    GetAccountRange {
+        request: GetAccountRange,
+        response: oneshot::Sender<PeerRequestResult<AccountRange>>,
+    },
+    /// Request the storage slots of accounts from the peer.
+    ///
+    /// The response should be sent through the channel.
+    GetStorageRanges {
+        request: GetStorageRanges,
+        response: oneshot::Sender<PeerRequestResult<StorageRanges>>,
+    },
+    /// Request contract codes from the peer.
+    ///
+    /// The response should be sent through the channel.
+    GetByteCodes { request: GetByteCodes, response: oneshot::Sender<PeerRequestResult<ByteCodes>> },
+    /// Request trie nodes from the peer.
+    ///
+    /// The response should be sent through the channel.
+    GetTrieNodes { request: GetTrieNodes, response: oneshot::Sender<PeerRequestResult<TrieNodes>> },
+}
+
+// === impl SnapRequest ===
+
+impl SnapRequest {
+    /// Send an error back to the receiver.
+    pub(crate) fn send_err_response(self, err: RequestError) {
+        let _ = match self {
+            SnapRequest::GetAccountRange { response, .. } => response.send(Err(err)).ok(),
+            SnapRequest::GetStorageRanges { response, .. } => response.send(Err(err)).ok(),
+            SnapRequest::GetByteCodes { response, .. } => response.send(Err(err)).ok(),
+            SnapRequest::GetTrieNodes { response, .. } => response.send(Err(err)).ok(),
+        };
+    }
+
+    /// Returns the [`SnapMessage`] for this type with the given request id.
+    pub fn create_request_message(&self, request_id: u64) -> SnapMessage {
+        match self {
+            SnapRequest::GetAccountRange { request, .. } => {
+                SnapMessage::GetAccountRange(GetAccountRange { request_id, ..request.clone() })
+            }
+            SnapRequest::GetStorageRanges { request, .. } => {
+                SnapMessage::GetStorageRanges(GetStorageRanges { request_id, ..request.clone() })
+            }
+            SnapRequest::GetByteCodes { request, .. } => {
+                SnapMessage::GetByteCodes(GetByteCodes { request_id, ..request.clone() })
+            }
+            SnapRequest::GetTrieNodes { request, .. } => {
+                SnapMessage::GetTrieNodes(GetTrieNodes { request_id, ..request.clone() })
+            }
+        }
+    }
+
+    /// Sends the response of the peer back to the receiver.
+    ///
+    /// Returns `false` if the message does not respond to this request, in which case a
+    /// [`RequestError::BadResponse`] is sent instead.
+    pub(crate) fn send_response(self, peer_id: PeerId, message: SnapMessage) -> bool {
+        let _ = match (self, message) {
+            (SnapRequest::GetAccountRange { response, .. }, SnapMessage::AccountRange(msg)) => {
+                response.send(Ok((peer_id, msg).into())).ok()
+            }
+            (SnapRequest::GetStorageRanges { response, .. }, SnapMessage::StorageRanges(msg)) => {
+                response.send(Ok((peer_id, msg).into())).ok()
+            }
+            (SnapRequest::GetByteCodes { response, .. }, SnapMessage::ByteCodes(msg)) => {
+                response.send(Ok((peer_id, msg).into())).ok()
+            }
+            (SnapRequest::GetTrieNodes { response, .. }, SnapMessage::TrieNodes(msg)) => {
+                response.send(Ok((peer_id, msg).into())).ok()
+            }
+            (request, _) => {
+                request.send_err_response(RequestError::BadResponse);
+                return false
+            }
+        };
+        true
+    }
+}
+
+/// Requests of the `snap` protocol received from a peer that are served from the local state.
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names, missing_docs)]
+pub enum PeerSnapRequest {
+    /// Requests a range of accounts.
+    ///
+    /// The response should be sent through the channel.
+    GetAccountRange {
+        request: GetAccountRange,
+        response: oneshot::Sender<RequestResult<AccountRange>>,
+    },
+    /// Requests the storage slots of accounts.
+    ///
+    /// The response should be sent through the channel.
+    GetStorageRanges {
+        request: GetStorageRanges,
+        response: oneshot::Sender<RequestResult<StorageRanges>>,
+    },
+}
+
+/// Corresponding variant for [`PeerSnapRequest`].
+#[derive(Debug)]
+#[allow(missing_docs)]
+pub enum PeerSnapResponse {
+    AccountRange { response: oneshot::Receiver<RequestResult<AccountRange>> },
+    StorageRanges { response: oneshot::Receiver<RequestResult<StorageRanges>> },
+}
+
+// === impl PeerSnapResponse ===
+
+impl PeerSnapResponse {
+    /// Polls the type to completion.
+    ///
+    /// The message of the response has the given request id.
+    pub(crate) fn poll(
+        &mut self,
+        request_id: u64,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<RequestResult<SnapMessage>, oneshot::error::RecvError>> {
+        let res = match self {
+            PeerSnapResponse::AccountRange { response } => {
+                ready!(response.poll_unpin(cx)).map(|res| {
+                    res.map(|msg| SnapMessage::AccountRange(AccountRange { request_id, ..msg }))
+                })
+            }
+            PeerSnapResponse::StorageRanges { response } => {
+                ready!(response.poll_unpin(cx)).map(|res| {
+                    res.map(|msg| SnapMessage::StorageRanges(StorageRanges { request_id, ..msg }))
+                })
+            }
+        };
+        Poll::Ready(res)
+    }
+}
+
 /// Corresponding variant for [`PeerRequest`].
 #[derive(Debug)]
 pub enum PeerResponse {
@@ -0,0 +1,18 @@
+use metrics::{Counter, Gauge};
+use reth_metrics_derive::Metrics;
+
+/// Metrics for the entire network, handled by [`NetworkManager`](crate::NetworkManager)
+#[derive(Metrics)]
+#[metrics(scope = "network")]
+pub struct NetworkMetrics {
+    /// Number of currently connected peers
+    pub(crate) connected_peers: Gauge,
+    /// Number of peers known to the node
+    pub(crate) tracked_peers: Gauge,
+    /// Cumulative number of failures of pending sessions
+    pub(crate) pending_session_failures: Counter,
+    /// Total number of sessions closed
+    pub(crate) closed_sessions: Counter,
+    /// Number of invalid/malformed messages received from peers
+    pub(crate) invalid_messages_received: Counter,
+}
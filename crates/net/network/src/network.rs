@@ -1,14 +1,28 @@
 use crate::{
     config::NetworkMode,
     manager::NetworkEvent,
-    message::PeerRequest,
+    message::{PeerRequest, SnapRequest},
     peers::{PeersHandle, ReputationChangeKind},
     FetchClient,
 };
+use enr::Enr;
 use parking_lot::Mutex;
-use reth_eth_wire::{DisconnectReason, NewBlock, NewPooledTransactionHashes, SharedTransactions};
-use reth_interfaces::p2p::headers::client::StatusUpdater;
+use reth_discv4::{error::Discv4Error, Discv4, NodeRecord};
+use reth_eth_wire::{
+    capability::Capability, DisconnectReason, HelloMessage, NewBlock, NewPooledTransactionHashes,
+    SharedTransactions,
+};
+use reth_interfaces::p2p::{
+    downloader::DownloadClient,
+    error::PeerRequestResult,
+    headers::client::StatusUpdater,
+    snap::client::{
+        AccountRange, ByteCodes, GetAccountRange, GetByteCodes, GetStorageRanges, GetTrieNodes,
+        SnapClient, StorageRanges, TrieNodes,
+    },
+};
 use reth_primitives::{PeerId, TransactionSigned, TxHash, H256, U256};
+use secp256k1::SecretKey;
 use std::{
     net::SocketAddr,
     sync::{
@@ -39,6 +53,8 @@ impl NetworkHandle {
         local_peer_id: PeerId,
         peers: PeersHandle,
         network_mode: NetworkMode,
+        discv4: Discv4,
+        hello_message: &HelloMessage,
     ) -> Self {
         let inner = NetworkInner {
             num_active_peers,
@@ -47,6 +63,9 @@ impl NetworkHandle {
             local_peer_id,
             peers,
             network_mode,
+            discv4,
+            client_version: hello_message.client_version.clone(),
+            capabilities: hello_message.capabilities.clone(),
         };
         Self { inner: Arc::new(inner) }
     }
@@ -66,6 +85,19 @@ impl NetworkHandle {
         &self.inner.local_peer_id
     }
 
+    /// Returns the enode URL, ENR, addresses and protocols the node advertises to other nodes.
+    pub async fn node_info(&self) -> Result<NodeInfo, Discv4Error> {
+        let (record, enr) = self.inner.discv4.local_node().await?;
+        Ok(NodeInfo {
+            record,
+            enr,
+            listener_addr: self.local_addr(),
+            discovery_addr: self.inner.discv4.local_addr(),
+            client_version: self.inner.client_version.clone(),
+            capabilities: self.inner.capabilities.clone(),
+        })
+    }
+
     /// Returns the [`PeersHandle`] that can be cloned and shared.
     ///
     /// The [`PeersHandle`] can be used to interact with the network's peer set.
@@ -143,6 +175,13 @@ impl NetworkHandle {
         self.send_message(NetworkHandleMessage::EthRequest { peer_id, request })
     }
 
+    /// Sends a [`SnapRequest`] to the next connected peer that supports the `snap` protocol.
+    ///
+    /// If no such peer is connected, the request fails with `RequestError::NotConnected`.
+    pub fn send_snap_request(&self, request: SnapRequest) {
+        self.send_message(NetworkHandleMessage::SnapRequest(request))
+    }
+
     /// Send transactions hashes to the peer.
     pub fn send_transactions_hashes(&self, peer_id: PeerId, msg: Vec<TxHash>) {
         self.send_message(NetworkHandleMessage::SendPooledTransactionHashes {
@@ -167,6 +206,42 @@ impl StatusUpdater for NetworkHandle {
     }
 }
 
+impl DownloadClient for NetworkHandle {
+    fn report_bad_message(&self, peer_id: PeerId) {
+        self.reputation_change(peer_id, ReputationChangeKind::BadMessage);
+    }
+}
+
+#[async_trait::async_trait]
+impl SnapClient for NetworkHandle {
+    async fn get_account_range(&self, request: GetAccountRange) -> PeerRequestResult<AccountRange> {
+        let (response, rx) = oneshot::channel();
+        self.send_snap_request(SnapRequest::GetAccountRange { request, response });
+        rx.await?
+    }
+
+    async fn get_storage_ranges(
+        &self,
+        request: GetStorageRanges,
+    ) -> PeerRequestResult<StorageRanges> {
+        let (response, rx) = oneshot::channel();
+        self.send_snap_request(SnapRequest::GetStorageRanges { request, response });
+        rx.await?
+    }
+
+    async fn get_byte_codes(&self, request: GetByteCodes) -> PeerRequestResult<ByteCodes> {
+        let (response, rx) = oneshot::channel();
+        self.send_snap_request(SnapRequest::GetByteCodes { request, response });
+        rx.await?
+    }
+
+    async fn get_trie_nodes(&self, request: GetTrieNodes) -> PeerRequestResult<TrieNodes> {
+        let (response, rx) = oneshot::channel();
+        self.send_snap_request(SnapRequest::GetTrieNodes { request, response });
+        rx.await?
+    }
+}
+
 #[derive(Debug)]
 struct NetworkInner {
     /// Number of active peer sessions the node's currently handling.
@@ -181,6 +256,29 @@ struct NetworkInner {
     peers: PeersHandle,
     /// The mode of the network
     network_mode: NetworkMode,
+    /// The frontend of the discovery service, which owns the node's records.
+    discv4: Discv4,
+    /// The client version of the `Hello` message.
+    client_version: String,
+    /// The capabilities of the `Hello` message.
+    capabilities: Vec<Capability>,
+}
+
+/// The addresses and protocols the local node advertises to other nodes.
+#[derive(Debug, Clone)]
+pub struct NodeInfo {
+    /// The record of the node, which displays as its enode URL.
+    pub record: NodeRecord,
+    /// The EIP-868 record of the node.
+    pub enr: Enr<SecretKey>,
+    /// The address that accepts incoming connections.
+    pub listener_addr: SocketAddr,
+    /// The address of the discovery service.
+    pub discovery_addr: SocketAddr,
+    /// The client version the node identifies itself with in the RLPx handshake.
+    pub client_version: String,
+    /// The protocols the node supports, e.g. `eth/67`.
+    pub capabilities: Vec<Capability>,
 }
 
 /// Internal messages that can be passed to the  [`NetworkManager`](crate::NetworkManager).
@@ -205,6 +303,8 @@ pub(crate) enum NetworkHandleMessage {
         /// The request to send to the peer's sessions.
         request: PeerRequest,
     },
+    /// Send a `snap` protocol request to a peer that supports it.
+    SnapRequest(SnapRequest),
     /// Apply a reputation change to the given peer.
     ReputationChange(PeerId, ReputationChangeKind),
     /// Returns the client that can be used to interact with the network.
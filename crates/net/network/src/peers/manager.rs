@@ -1,17 +1,20 @@
 use crate::{
     error::SessionError,
     peers::{
-        reputation::{is_banned_reputation, BACKOFF_REPUTATION_CHANGE, DEFAULT_REPUTATION},
+        reputation::{
+            is_banned_reputation, Reputation, BACKOFF_REPUTATION_CHANGE, DEFAULT_REPUTATION,
+        },
         ReputationChangeKind, ReputationChangeWeights,
     },
     session::{Direction, PendingSessionHandshakeError},
 };
 use futures::StreamExt;
+use reth_discv4::NodeRecord;
 use reth_eth_wire::{error::EthStreamError, DisconnectReason};
 use reth_net_common::ban_list::BanList;
 use reth_primitives::{ForkId, PeerId};
 use std::{
-    collections::{hash_map::Entry, HashMap, VecDeque},
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
     fmt::Display,
     net::{IpAddr, SocketAddr},
     task::{Context, Poll},
@@ -25,6 +28,10 @@ use tokio::{
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::{debug, trace};
 
+/// The maximum exponent of the ban duration multiplier, bans are at most `2^16` times as long as
+/// the configured ban duration before the maximum ban duration applies.
+const MAX_BAN_EXPONENT: u32 = 16;
+
 /// A communication channel to the [`PeersManager`] to apply manual changes to the peer set.
 #[derive(Clone, Debug)]
 pub struct PeersHandle {
@@ -44,6 +51,13 @@ impl PeersHandle {
         self.send(PeerCommand::Add(peer_id, addr));
     }
 
+    /// Adds a trusted peer to the set.
+    ///
+    /// Trusted peers are never banned or disconnected and are exempt from the slot limits.
+    pub fn add_trusted_peer(&self, peer_id: PeerId, addr: SocketAddr) {
+        self.send(PeerCommand::AddTrusted(peer_id, addr));
+    }
+
     /// Removes a peer from the set.
     pub fn remove_peer(&self, peer_id: PeerId) {
         self.send(PeerCommand::Remove(peer_id));
@@ -54,6 +68,13 @@ impl PeersHandle {
         self.send(PeerCommand::ReputationChange(peer_id, kind));
     }
 
+    /// Updates the maximum number of inbound and outbound connections.
+    ///
+    /// Lowering a limit does not disconnect already established sessions.
+    pub fn set_connection_limits(&self, max_inbound: usize, max_outbound: usize) {
+        self.send(PeerCommand::SetConnectionLimits { max_inbound, max_outbound });
+    }
+
     /// Returns a peer by its [`PeerId`], or `None` if the peer is not in the peer set.
     pub async fn peer_by_id(&self, peer_id: PeerId) -> Option<Peer> {
         let (tx, rx) = oneshot::channel();
@@ -61,6 +82,14 @@ impl PeersHandle {
 
         rx.await.unwrap_or(None)
     }
+
+    /// Returns all peers in the peer set.
+    pub async fn all_peers(&self) -> Vec<(PeerId, Peer)> {
+        let (tx, rx) = oneshot::channel();
+        self.send(PeerCommand::GetPeers(tx));
+
+        rx.await.unwrap_or_default()
+    }
 }
 
 /// Maintains the state of _all_ the peers known to the network.
@@ -90,6 +119,8 @@ pub(crate) struct PeersManager {
     unban_interval: Interval,
     /// How long to ban bad peers.
     ban_duration: Duration,
+    /// Upper bound for the ban duration of repeatedly banned peers.
+    max_ban_duration: Duration,
     /// How long peers to which we could not connect for non-fatal reasons, e.g.
     /// [`DisconnectReason::TooManyPeers`], are put in time out.
     backoff_duration: Duration,
@@ -104,7 +135,9 @@ impl PeersManager {
             reputation_weights,
             ban_list,
             ban_duration,
+            max_ban_duration,
             backoff_duration,
+            trusted_nodes,
         } = config;
         let (manager_tx, handle_rx) = mpsc::unbounded_channel();
         let now = Instant::now();
@@ -112,8 +145,13 @@ impl PeersManager {
         // We use half of the interval to decrease the max duration to `150%` in worst case
         let unban_interval = ban_duration.min(backoff_duration) / 2;
 
+        let peers = trusted_nodes
+            .into_iter()
+            .map(|node| (node.id, Peer::trusted(node.tcp_addr())))
+            .collect();
+
         Self {
-            peers: Default::default(),
+            peers,
             manager_tx,
             handle_rx: UnboundedReceiverStream::new(handle_rx),
             queued_actions: Default::default(),
@@ -126,6 +164,7 @@ impl PeersManager {
             connection_info,
             ban_list,
             ban_duration,
+            max_ban_duration,
             backoff_duration,
         }
     }
@@ -146,7 +185,7 @@ impl PeersManager {
         if self.ban_list.is_banned_ip(&addr) {
             return Err(InboundConnectionError::IpBanned)
         }
-        if !self.connection_info.has_in_capacity() {
+        if !self.connection_info.has_in_capacity() && !self.is_trusted_ip(&addr) {
             return Err(InboundConnectionError::ExceedsLimit(self.connection_info.max_inbound))
         }
 
@@ -184,6 +223,11 @@ impl PeersManager {
         self.connection_info.decr_in()
     }
 
+    /// Returns `true` if the ip address belongs to a trusted peer.
+    fn is_trusted_ip(&self, addr: &IpAddr) -> bool {
+        self.peers.values().any(|peer| peer.is_trusted() && peer.addr.ip() == *addr)
+    }
+
     /// Called when a new _incoming_ active session was established to the given peer.
     ///
     /// This will update the state of the peer if not yet tracked.
@@ -214,12 +258,31 @@ impl PeersManager {
         }
     }
 
-    /// Bans the peer temporarily with the configured ban timeout
+    /// Bans the peer temporarily.
+    ///
+    /// The ban duration starts at the configured ban timeout and doubles with every subsequent ban
+    /// of the same peer, up to the configured maximum.
     fn ban_peer(&mut self, peer_id: PeerId) {
-        self.ban_list.ban_peer_until(peer_id, std::time::Instant::now() + self.ban_duration);
+        let num_bans = self
+            .peers
+            .get_mut(&peer_id)
+            .map(|peer| {
+                peer.num_bans = peer.num_bans.saturating_add(1);
+                peer.num_bans
+            })
+            .unwrap_or(1);
+        let ban_duration = self.ban_duration_for(num_bans);
+        trace!(target: "net::peers", ?peer_id, ?ban_duration, num_bans, "banning peer");
+        self.ban_list.ban_peer_until(peer_id, std::time::Instant::now() + ban_duration);
         self.queued_actions.push_back(PeerAction::BanPeer { peer_id });
     }
 
+    /// Returns how long to ban a peer that is banned for the `num_bans`th time.
+    fn ban_duration_for(&self, num_bans: u32) -> Duration {
+        let factor = 1u32 << num_bans.saturating_sub(1).min(MAX_BAN_EXPONENT);
+        self.ban_duration.saturating_mul(factor).min(self.max_ban_duration.max(self.ban_duration))
+    }
+
     /// Bans the IP temporarily with the configured ban timeout
     fn ban_ip(&mut self, ip: IpAddr) {
         self.ban_list.ban_ip_until(ip, std::time::Instant::now() + self.ban_duration);
@@ -241,6 +304,15 @@ impl PeersManager {
     pub(crate) fn apply_reputation_change(&mut self, peer_id: &PeerId, rep: ReputationChangeKind) {
         let reputation_change = self.reputation_weights.change(rep);
         let outcome = if let Some(peer) = self.peers.get_mut(peer_id) {
+            if peer.is_trusted() {
+                trace!(
+                    target: "net::peers",
+                    ?peer_id,
+                    ?rep,
+                    "ignoring reputation change of trusted peer"
+                );
+                return
+            }
             peer.apply_reputation(reputation_change.as_i32())
         } else {
             return
@@ -325,6 +397,14 @@ impl PeersManager {
     ) {
         trace!(target: "net::peers", ?remote_addr, ?peer_id, ?err, "handling failed connection");
 
+        if let Some(peer) = self.peers.get_mut(peer_id).filter(|peer| peer.is_trusted()) {
+            // trusted peers are never banned or removed, a new connection is attempted on the next
+            // refill
+            self.connection_info.decr_state(peer.state);
+            peer.state = PeerConnectionState::Idle;
+            return
+        }
+
         if err.is_fatal_protocol_error() {
             trace!(target: "net::peers", ?remote_addr, ?peer_id, ?err, "fatal connection error");
             // remove the peer to which we can't establish a connection due to protocol related
@@ -414,8 +494,39 @@ impl PeersManager {
         self.fill_outbound_slots();
     }
 
-    /// Removes the tracked node from the set.
+    /// Adds a trusted peer to the set or marks an already tracked peer as trusted.
+    pub(crate) fn add_trusted_peer(&mut self, peer_id: PeerId, addr: SocketAddr) {
+        match self.peers.entry(peer_id) {
+            Entry::Occupied(mut entry) => {
+                let peer = entry.get_mut();
+                peer.kind = PeerKind::Trusted;
+                peer.addr = addr;
+                peer.remove_after_disconnect = false;
+                peer.unban();
+            }
+            Entry::Vacant(entry) => {
+                trace!(target : "net::peers", ?peer_id, ?addr, "added trusted peer");
+                entry.insert(Peer::trusted(addr));
+                self.queued_actions.push_back(PeerAction::PeerAdded(peer_id));
+            }
+        }
+        self.ban_list.unban_peer(&peer_id);
+
+        self.fill_outbound_slots();
+    }
+
+    /// Removes a node that is no longer available according to discovery.
+    ///
+    /// Trusted peers are kept.
     pub(crate) fn remove_discovered_node(&mut self, peer_id: PeerId) {
+        if self.peers.get(&peer_id).map(|peer| peer.is_trusted()).unwrap_or_default() {
+            return
+        }
+        self.remove_peer(peer_id)
+    }
+
+    /// Removes the tracked node from the set.
+    pub(crate) fn remove_peer(&mut self, peer_id: PeerId) {
         if let Some(mut peer) = self.peers.remove(&peer_id) {
             trace!(target : "net::peers",  ?peer_id, "remove discovered node");
             self.queued_actions.push_back(PeerAction::PeerRemoved(peer_id));
@@ -437,6 +548,15 @@ impl PeersManager {
         }
     }
 
+    /// Updates the maximum number of inbound and outbound connections and fills the outbound
+    /// slots if the limit was raised.
+    pub(crate) fn set_connection_limits(&mut self, max_inbound: usize, max_outbound: usize) {
+        trace!(target : "net::peers", max_inbound, max_outbound, "updated connection limits");
+        self.connection_info.max_inbound = max_inbound;
+        self.connection_info.max_outbound = max_outbound;
+        self.fill_outbound_slots();
+    }
+
     /// Returns the idle peer with the highest reputation.
     ///
     /// Peers with a `forkId` are considered better than peers without.
@@ -472,6 +592,22 @@ impl PeersManager {
     /// New connections are only initiated, if slots are available and appropriate peers are
     /// available.
     fn fill_outbound_slots(&mut self) {
+        // trusted peers are exempt from the slot limits
+        for (peer_id, peer) in self.peers.iter_mut() {
+            if peer.is_trusted() && peer.state.is_unconnected() {
+                trace!(
+                    target : "net::peers",
+                    ?peer_id,
+                    addr=?peer.addr,
+                    "schedule outbound connection to trusted peer"
+                );
+                peer.state = PeerConnectionState::Out;
+                self.connection_info.inc_out();
+                self.queued_actions
+                    .push_back(PeerAction::Connect { peer_id: *peer_id, remote_addr: peer.addr });
+            }
+        }
+
         // as long as there a slots available try to fill them with the best peers
         while self.connection_info.has_out_capacity() {
             let action = {
@@ -512,13 +648,24 @@ impl PeersManager {
                     PeerCommand::Add(peer_id, addr) => {
                         self.add_discovered_node(peer_id, addr);
                     }
-                    PeerCommand::Remove(peer) => self.remove_discovered_node(peer),
+                    PeerCommand::AddTrusted(peer_id, addr) => {
+                        self.add_trusted_peer(peer_id, addr);
+                    }
+                    PeerCommand::Remove(peer) => self.remove_peer(peer),
                     PeerCommand::ReputationChange(peer_id, rep) => {
                         self.apply_reputation_change(&peer_id, rep)
                     }
+                    PeerCommand::SetConnectionLimits { max_inbound, max_outbound } => {
+                        self.set_connection_limits(max_inbound, max_outbound)
+                    }
                     PeerCommand::GetPeer(peer, tx) => {
                         let _ = tx.send(self.peers.get(&peer).cloned());
                     }
+                    PeerCommand::GetPeers(tx) => {
+                        let peers =
+                            self.peers.iter().map(|(id, peer)| (*id, peer.clone())).collect();
+                        let _ = tx.send(peers);
+                    }
                 }
             }
 
@@ -622,6 +769,10 @@ pub struct Peer {
     fork_id: Option<ForkId>,
     /// Whether the entry should be removed after an existing session was terminated.
     remove_after_disconnect: bool,
+    /// How often the peer was banned.
+    num_bans: u32,
+    /// The kind of peer.
+    kind: PeerKind,
 }
 
 // === impl Peer ===
@@ -638,9 +789,41 @@ impl Peer {
             reputation: DEFAULT_REPUTATION,
             fork_id: None,
             remove_after_disconnect: false,
+            num_bans: 0,
+            kind: Default::default(),
         }
     }
 
+    fn trusted(addr: SocketAddr) -> Self {
+        Self { kind: PeerKind::Trusted, ..Self::new(addr) }
+    }
+
+    /// Returns `true` if this is a trusted peer.
+    #[inline]
+    pub fn is_trusted(&self) -> bool {
+        matches!(self.kind, PeerKind::Trusted)
+    }
+
+    /// Returns the address of the peer.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the current reputation of the peer.
+    pub fn reputation(&self) -> Reputation {
+        self.reputation
+    }
+
+    /// Returns how often the peer was banned.
+    pub fn num_bans(&self) -> u32 {
+        self.num_bans
+    }
+
+    /// Returns `true` if the peer is currently connected.
+    pub fn is_connected(&self) -> bool {
+        self.state.is_connected()
+    }
+
     /// Applies a reputation change to the peer and returns what action should be taken.
     fn apply_reputation(&mut self, reputation: i32) -> ReputationChangeOutcome {
         let previous = self.reputation;
@@ -667,7 +850,7 @@ impl Peer {
 
     /// Returns true if the peer's reputation is below the banned threshold.
     #[inline]
-    fn is_banned(&self) -> bool {
+    pub fn is_banned(&self) -> bool {
         is_banned_reputation(self.reputation)
     }
 
@@ -678,6 +861,16 @@ impl Peer {
     }
 }
 
+/// Represents the kind of peer
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+enum PeerKind {
+    /// Basic peer kind.
+    #[default]
+    Basic,
+    /// Trusted peer that is never banned or disconnected and exempt from the slot limits.
+    Trusted,
+}
+
 /// Outcomes when a reputation change is applied to a peer
 enum ReputationChangeOutcome {
     /// Nothing to do.
@@ -736,14 +929,20 @@ impl PeerConnectionState {
 pub(crate) enum PeerCommand {
     /// Command for manually add
     Add(PeerId, SocketAddr),
+    /// Command for manually adding a trusted peer
+    AddTrusted(PeerId, SocketAddr),
     /// Remove a peer from the set
     ///
     /// If currently connected this will disconnect the session
     Remove(PeerId),
     /// Apply a reputation change to the given peer.
     ReputationChange(PeerId, ReputationChangeKind),
+    /// Update the maximum number of connections.
+    SetConnectionLimits { max_inbound: usize, max_outbound: usize },
     /// Get information about a peer
     GetPeer(PeerId, oneshot::Sender<Option<Peer>>),
+    /// Get information about all peers
+    GetPeers(oneshot::Sender<Vec<(PeerId, Peer)>>),
 }
 
 /// Actions the peer manager can trigger.
@@ -791,9 +990,13 @@ pub struct PeersConfig {
     pub ban_list: BanList,
     /// How long to ban bad peers.
     pub ban_duration: Duration,
+    /// The maximum duration a repeatedly banned peer is banned for.
+    pub max_ban_duration: Duration,
     /// How long to backoff peers that are we failed to connect to for non-fatal reasons, such as
     /// [`DisconnectReason::TooManyPeers`].
     pub backoff_duration: Duration,
+    /// Trusted nodes to connect to.
+    pub trusted_nodes: HashSet<NodeRecord>,
 }
 
 impl Default for PeersConfig {
@@ -805,8 +1008,11 @@ impl Default for PeersConfig {
             ban_list: Default::default(),
             // Ban peers for 12h
             ban_duration: Duration::from_secs(60 * 60 * 12),
+            // Ban repeat offenders for at most a week
+            max_ban_duration: Duration::from_secs(60 * 60 * 24 * 7),
             // backoff peers for 1h
             backoff_duration: Duration::from_secs(60 * 60),
+            trusted_nodes: Default::default(),
         }
     }
 }
@@ -845,6 +1051,19 @@ impl PeersConfig {
         self.refill_slots_interval = interval;
         self
     }
+
+    /// Nodes that are never banned or disconnected and exempt from the slot limits.
+    pub fn with_trusted_nodes(mut self, nodes: HashSet<NodeRecord>) -> Self {
+        self.trusted_nodes = nodes;
+        self
+    }
+
+    /// How long to ban bad peers and the upper bound for repeatedly banned peers.
+    pub fn with_ban_duration(mut self, ban_duration: Duration, max_ban_duration: Duration) -> Self {
+        self.ban_duration = ban_duration;
+        self.max_ban_duration = max_ban_duration;
+        self
+    }
 }
 
 #[derive(Debug, Error)]
@@ -925,6 +1144,36 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_set_connection_limits() {
+        let peer = PeerId::random();
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 1, 2)), 8008);
+        let mut peers = PeersManager::default();
+        peers.set_connection_limits(30, 0);
+        peers.add_discovered_node(peer, socket_addr);
+
+        match event!(peers) {
+            PeerAction::PeerAdded(peer_id) => {
+                assert_eq!(peer_id, peer);
+            }
+            _ => unreachable!(),
+        }
+        poll_fn(|cx| {
+            assert!(peers.poll(cx).is_pending());
+            Poll::Ready(())
+        })
+        .await;
+
+        peers.set_connection_limits(30, 1);
+        match event!(peers) {
+            PeerAction::Connect { peer_id, remote_addr } => {
+                assert_eq!(peer_id, peer);
+                assert_eq!(remote_addr, socket_addr);
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[tokio::test]
     async fn test_ban() {
         let peer = PeerId::random();
@@ -1222,6 +1471,36 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_ban_duration_backoff() {
+        let peers = PeersManager::new(
+            PeersConfig::default()
+                .with_ban_duration(Duration::from_secs(1), Duration::from_secs(10)),
+        );
+        assert_eq!(peers.ban_duration_for(1), Duration::from_secs(1));
+        assert_eq!(peers.ban_duration_for(2), Duration::from_secs(2));
+        assert_eq!(peers.ban_duration_for(4), Duration::from_secs(8));
+        assert_eq!(peers.ban_duration_for(5), Duration::from_secs(10));
+        assert_eq!(peers.ban_duration_for(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_ban_counted() {
+        let peer = PeerId::random();
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 1, 2)), 8008);
+        let mut peers = PeersManager::default();
+        peers.add_discovered_node(peer, socket_addr);
+
+        for num_bans in 1..=2 {
+            peers.apply_reputation_change(&peer, ReputationChangeKind::BadProtocol);
+            let p = peers.peers.get_mut(&peer).unwrap();
+            assert!(p.is_banned());
+            assert_eq!(p.num_bans(), num_bans);
+            p.unban();
+        }
+        assert!(peers.ban_list.is_banned_peer(&peer));
+    }
+
     #[tokio::test]
     async fn test_remove_discovered_active() {
         let peer = PeerId::random();
@@ -1314,7 +1593,11 @@ mod test {
         let mut peer_manager = PeersManager::new(config);
         peer_manager.on_active_inbound_session(given_peer_id, socket_addr);
 
-        let Some(PeerAction::DisconnectBannedIncoming { peer_id }) = peer_manager.queued_actions.pop_front() else { panic!() };
+        let Some(PeerAction::DisconnectBannedIncoming { peer_id }) =
+            peer_manager.queued_actions.pop_front()
+        else {
+            panic!()
+        };
 
         assert_eq!(peer_id, given_peer_id)
     }
@@ -1356,4 +1639,37 @@ mod test {
         assert_eq!(info.num_inbound, 0);
         assert_eq!(info.num_outbound, 0);
     }
+
+    #[tokio::test]
+    async fn test_trusted_peer() {
+        let trusted = PeerId::random();
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 1, 2)), 8008);
+        let config = PeersConfig::default().with_max_outbound(0);
+        let mut peers = PeersManager::new(config);
+        peers.add_discovered_node(PeerId::random(), socket_addr);
+        peers.add_trusted_peer(trusted, socket_addr);
+
+        // trusted peers are connected even if there are no free slots
+        let mut connected = Vec::new();
+        poll_fn(|cx| {
+            while let Poll::Ready(action) = peers.poll(cx) {
+                if let PeerAction::Connect { peer_id, .. } = action {
+                    connected.push(peer_id);
+                }
+            }
+            Poll::Ready(())
+        })
+        .await;
+        assert_eq!(connected, vec![trusted]);
+
+        // trusted peers are never banned
+        peers.apply_reputation_change(&trusted, ReputationChangeKind::BadProtocol);
+        let peer = peers.peers.get(&trusted).unwrap();
+        assert!(!peer.is_banned());
+        assert_eq!(peer.state, PeerConnectionState::Out);
+
+        // trusted peers are not removed by discovery
+        peers.remove_discovered_node(trusted);
+        assert!(peers.peers.contains_key(&trusted));
+    }
 }
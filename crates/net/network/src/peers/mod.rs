@@ -4,5 +4,5 @@ mod manager;
 mod reputation;
 
 pub(crate) use manager::{InboundConnectionError, PeerAction, PeersManager};
-pub use manager::{PeersConfig, PeersHandle};
-pub use reputation::{ReputationChangeKind, ReputationChangeWeights};
+pub use manager::{Peer, PeersConfig, PeersHandle};
+pub use reputation::{Reputation, ReputationChangeKind, ReputationChangeWeights};
@@ -1,7 +1,7 @@
 //! Peer reputation management
 
 /// The type that tracks the reputation score.
-pub(crate) type Reputation = i32;
+pub type Reputation = i32;
 
 /// The default reputation of a peer
 pub(crate) const DEFAULT_REPUTATION: Reputation = 0;
@@ -1,12 +1,16 @@
 //! Represents an established session.
 
 use crate::{
-    message::{NewBlockMessage, PeerMessage, PeerRequest, PeerResponse, PeerResponseResult},
+    message::{
+        NewBlockMessage, PeerMessage, PeerRequest, PeerResponse, PeerResponseResult,
+        PeerSnapRequest, PeerSnapResponse, SnapRequest,
+    },
     session::{
         handle::{ActiveSessionMessage, SessionCommand},
         SessionId,
     },
 };
+use bytes::{Bytes, BytesMut};
 use fnv::FnvHashMap;
 use futures::{stream::Fuse, SinkExt, StreamExt};
 use reth_ecies::stream::ECIESStream;
@@ -14,7 +18,9 @@ use reth_eth_wire::{
     capability::Capabilities,
     error::{EthStreamError, HandshakeError, P2PStreamError},
     message::{EthBroadcastMessage, RequestPair},
-    DisconnectReason, EthMessage, EthStream, P2PStream,
+    types::snap::{ByteCodes, TrieNodes},
+    DisconnectReason, EthMessage, EthMessageID, EthStream, P2PStream, ProtocolConnection,
+    RlpxMultiplexer, SnapMessage,
 };
 use reth_interfaces::p2p::error::RequestError;
 use reth_primitives::PeerId;
@@ -42,12 +48,18 @@ use tracing::{debug, error, info, trace, warn};
 ///    - incoming commands from the [`SessionsManager`]
 ///    - incoming requests via the request channel
 ///    - responses for handled ETH requests received from the remote peer.
+///    - messages of the `snap` protocol, if the peer shares it.
 #[allow(unused)]
 pub(crate) struct ActiveSession {
     /// Keeps track of request ids.
     pub(crate) next_id: u64,
-    /// The underlying connection.
-    pub(crate) conn: EthStream<P2PStream<ECIESStream<TcpStream>>>,
+    /// The `eth` protocol of the connection.
+    pub(crate) conn: EthStream<ProtocolConnection>,
+    /// The `snap` protocol of the connection, if the peer shares it.
+    pub(crate) snap: Option<ProtocolConnection>,
+    /// The underlying connection, routes the messages of the shared capabilities to `conn` and
+    /// `snap`.
+    pub(crate) multiplexer: RlpxMultiplexer<ECIESStream<TcpStream>>,
     /// Identifier of the node we're connected to.
     pub(crate) remote_peer_id: PeerId,
     /// The address we're connected to.
@@ -64,10 +76,14 @@ pub(crate) struct ActiveSession {
     pub(crate) request_tx: Fuse<ReceiverStream<PeerRequest>>,
     /// All requests sent to the remote peer we're waiting on a response
     pub(crate) inflight_requests: FnvHashMap<u64, InflightRequest>,
+    /// All `snap` requests sent to the remote peer we're waiting on a response
+    pub(crate) inflight_snap_requests: FnvHashMap<u64, InflightRequest<SnapRequest>>,
     /// All requests that were sent by the remote peer.
     pub(crate) received_requests: Vec<ReceivedRequest>,
+    /// All `snap` requests that were sent by the remote peer.
+    pub(crate) received_snap_requests: Vec<ReceivedSnapRequest>,
     /// Buffered messages that should be handled and sent to the peer.
-    pub(crate) queued_outgoing: VecDeque<OutgoingMessage>,
+    pub(crate) queued_outgoing: QueuedOutgoingMessages,
     /// The maximum time we wait for a response from a peer.
     pub(crate) request_timeout: Duration,
     /// Interval when to check for timed out requests.
@@ -77,7 +93,7 @@ pub(crate) struct ActiveSession {
 impl ActiveSession {
     /// Returns `true` if the session is currently in the process of disconnecting
     fn is_disconnecting(&self) -> bool {
-        self.conn.inner().is_disconnecting()
+        self.multiplexer.inner().is_disconnecting()
     }
 
     /// Returns the next request id
@@ -193,31 +209,128 @@ impl ActiveSession {
     fn on_peer_request(&mut self, request: PeerRequest, deadline: Instant) {
         let request_id = self.next_id();
         let msg = request.create_request_message(request_id);
-        self.queued_outgoing.push_back(msg.into());
+        if !msg.message_id().is_supported_by(self.conn.version()) {
+            // e.g. `GetNodeData` is not part of `eth/67`
+            request.send_err_response(RequestError::UnsupportedCapability);
+            return
+        }
+        self.queued_outgoing.push(msg);
         let req = InflightRequest { request, deadline };
         self.inflight_requests.insert(request_id, req);
     }
 
+    /// Handle an outgoing `snap` request.
+    fn on_snap_request(&mut self, request: SnapRequest, deadline: Instant) {
+        if self.snap.is_none() {
+            request.send_err_response(RequestError::UnsupportedCapability);
+            return
+        }
+        let request_id = self.next_id();
+        self.queue_snap_message(request.create_request_message(request_id));
+        let req = InflightRequest { request, deadline };
+        self.inflight_snap_requests.insert(request_id, req);
+    }
+
+    /// Handle a message read from the `snap` protocol of the connection.
+    fn on_snap_bytes(&mut self, msg: BytesMut) {
+        match SnapMessage::decode_with_offset(0, &mut &msg[..]) {
+            Ok(msg) => self.on_snap_message(msg),
+            Err(err) => {
+                debug!(target: "net::session", ?err, remote_peer_id=?self.remote_peer_id, "received invalid snap message");
+                self.on_bad_message();
+            }
+        }
+    }
+
+    /// Handle a `snap` message read from the connection.
+    fn on_snap_message(&mut self, msg: SnapMessage) {
+        let request_id = msg.request_id();
+        let response = match msg {
+            SnapMessage::GetAccountRange(request) => {
+                let (tx, response) = oneshot::channel();
+                self.on_received_snap_request(
+                    request_id,
+                    PeerSnapRequest::GetAccountRange { request, response: tx },
+                    PeerSnapResponse::AccountRange { response },
+                );
+                return
+            }
+            SnapMessage::GetStorageRanges(request) => {
+                let (tx, response) = oneshot::channel();
+                self.on_received_snap_request(
+                    request_id,
+                    PeerSnapRequest::GetStorageRanges { request, response: tx },
+                    PeerSnapResponse::StorageRanges { response },
+                );
+                return
+            }
+            // codes and trie nodes aren't served, empty responses tell the peer that they are not
+            // available
+            SnapMessage::GetByteCodes(_) => {
+                SnapMessage::ByteCodes(ByteCodes { request_id, codes: vec![] })
+            }
+            SnapMessage::GetTrieNodes(_) => {
+                SnapMessage::TrieNodes(TrieNodes { request_id, nodes: vec![] })
+            }
+            response => {
+                match self.inflight_snap_requests.remove(&request_id) {
+                    Some(req) => {
+                        if !req.request.send_response(self.remote_peer_id, response) {
+                            self.on_bad_message();
+                        }
+                    }
+                    None => self.on_bad_message(),
+                }
+                return
+            }
+        };
+        self.queue_snap_message(response);
+    }
+
+    /// Sends the `snap` request of the peer to the manager and tracks the pending response.
+    fn on_received_snap_request(
+        &mut self,
+        request_id: u64,
+        request: PeerSnapRequest,
+        rx: PeerSnapResponse,
+    ) {
+        let received = ReceivedSnapRequest { request_id, rx, received: Instant::now() };
+        if self.try_emit_message(PeerMessage::ReceivedSnapRequest(request)).is_ok() {
+            self.received_snap_requests.push(received);
+        }
+    }
+
+    /// Queues the `snap` message.
+    fn queue_snap_message(&mut self, msg: SnapMessage) {
+        let mut buf = BytesMut::new();
+        msg.encode_with_offset(0, &mut buf);
+        self.queued_outgoing.push(OutgoingMessage::Snap(buf.freeze()));
+    }
+
     /// Handle a message received from the internal network
     fn on_peer_message(&mut self, msg: PeerMessage) {
         match msg {
             PeerMessage::NewBlockHashes(msg) => {
-                self.queued_outgoing.push_back(EthMessage::NewBlockHashes(msg).into());
+                self.queued_outgoing.push(EthMessage::NewBlockHashes(msg));
             }
             PeerMessage::NewBlock(msg) => {
-                self.queued_outgoing.push_back(EthBroadcastMessage::NewBlock(msg.block).into());
+                self.queued_outgoing.push(EthBroadcastMessage::NewBlock(msg.block));
             }
             PeerMessage::PooledTransactions(msg) => {
-                self.queued_outgoing.push_back(EthMessage::NewPooledTransactionHashes(msg).into());
+                self.queued_outgoing.push(EthMessage::NewPooledTransactionHashes(msg));
             }
             PeerMessage::EthRequest(req) => {
                 let deadline = self.request_deadline();
                 self.on_peer_request(req, deadline);
             }
+            PeerMessage::SnapRequest(req) => {
+                let deadline = self.request_deadline();
+                self.on_snap_request(req, deadline);
+            }
             PeerMessage::SendTransactions(msg) => {
-                self.queued_outgoing.push_back(EthBroadcastMessage::Transactions(msg).into());
+                self.queued_outgoing.push(EthBroadcastMessage::Transactions(msg));
             }
-            PeerMessage::ReceivedTransaction(_) => {
+            PeerMessage::ReceivedTransaction(_) | PeerMessage::ReceivedSnapRequest(_) => {
                 unreachable!("Not emitted by network")
             }
             PeerMessage::Other(other) => {
@@ -235,7 +348,7 @@ impl ActiveSession {
     fn handle_outgoing_response(&mut self, id: u64, resp: PeerResponseResult) {
         match resp.try_into_message(id) {
             Ok(msg) => {
-                self.queued_outgoing.push_back(msg.into());
+                self.queued_outgoing.push(msg);
             }
             Err(err) => {
                 error!(target : "net", ?err, "Failed to respond to received request");
@@ -292,11 +405,7 @@ impl ActiveSession {
 
     /// Starts the disconnect process
     fn start_disconnect(&mut self, reason: DisconnectReason) -> Result<(), EthStreamError> {
-        self.conn
-            .inner_mut()
-            .start_disconnect(reason)
-            .map_err(P2PStreamError::from)
-            .map_err(Into::into)
+        self.multiplexer.start_disconnect(reason).map_err(P2PStreamError::from).map_err(Into::into)
     }
 
     /// Flushes the disconnect message and emits the corresponding message
@@ -304,7 +413,7 @@ impl ActiveSession {
         debug_assert!(self.is_disconnecting(), "not disconnecting");
 
         // try to close the flush out the remaining Disconnect message
-        let _ = ready!(self.conn.poll_close_unpin(cx));
+        let _ = ready!(self.multiplexer.poll_close(cx));
         self.emit_disconnect();
         Poll::Ready(())
     }
@@ -323,6 +432,18 @@ impl ActiveSession {
             let req = self.inflight_requests.remove(&id).expect("exists; qed");
             req.request.send_err_response(RequestError::Timeout);
         }
+
+        let timedout = self
+            .inflight_snap_requests
+            .iter()
+            .filter(|(_, req)| now > req.deadline)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in timedout {
+            warn!(target: "net::session", ?id, remote_peer_id=?self.remote_peer_id, "timed out outgoing snap request");
+            let req = self.inflight_snap_requests.remove(&id).expect("exists; qed");
+            req.request.send_err_response(RequestError::Timeout);
+        }
     }
 }
 
@@ -401,13 +522,39 @@ impl Future for ActiveSession {
                 }
             }
 
-            // Send messages by advancing the sink and queuing in buffered messages
-            while this.conn.poll_ready_unpin(cx).is_ready() {
-                if let Some(msg) = this.queued_outgoing.pop_front() {
+            for idx in (0..this.received_snap_requests.len()).rev() {
+                let mut req = this.received_snap_requests.swap_remove(idx);
+                match req.rx.poll(req.request_id, cx) {
+                    Poll::Pending => {
+                        this.received_snap_requests.push(req);
+                    }
+                    Poll::Ready(Ok(Ok(msg))) => {
+                        this.queue_snap_message(msg);
+                    }
+                    Poll::Ready(Ok(Err(err))) => {
+                        error!(target : "net", ?err, "Failed to respond to received snap request");
+                    }
+                    Poll::Ready(Err(_)) => {
+                        // ignore on error
+                    }
+                }
+            }
+
+            // Send messages by advancing the sinks and queuing in buffered messages, both protocols
+            // share the capacity of the connection
+            while this.conn.poll_ready_unpin(cx).is_ready() &&
+                this.snap.as_mut().map_or(true, |snap| snap.poll_ready_unpin(cx).is_ready())
+            {
+                if let Some(msg) = this.queued_outgoing.pop() {
                     progress = true;
                     let res = match msg {
                         OutgoingMessage::Eth(msg) => this.conn.start_send_unpin(msg),
                         OutgoingMessage::Broadcast(msg) => this.conn.start_send_broadcast(msg),
+                        OutgoingMessage::Snap(msg) => match this.snap.as_mut() {
+                            Some(snap) => snap.start_send_unpin(msg).map_err(Into::into),
+                            // snap messages are only queued if the peer shares `snap`
+                            None => Ok(()),
+                        },
                     };
                     if let Err(err) = res {
                         error!(target: "net::session", ?err,  remote_peer_id=?this.remote_peer_id, "failed to send message");
@@ -421,6 +568,33 @@ impl Future for ActiveSession {
                 }
             }
 
+            // drive the connection, this routes the received messages to the protocols
+            loop {
+                match this.multiplexer.poll_next_unpin(cx) {
+                    Poll::Pending => break,
+                    Poll::Ready(None) => {
+                        if this.is_disconnecting() {
+                            break
+                        } else {
+                            debug!(target: "net::session", remote_peer_id=?this.remote_peer_id, "connection completed");
+                            this.emit_disconnect();
+                            return Poll::Ready(())
+                        }
+                    }
+                    Poll::Ready(Some(Ok((capability, _)))) => {
+                        // only `eth` and `snap` are advertised and both are installed
+                        progress = true;
+                        debug!(target: "net::session", %capability, remote_peer_id=?this.remote_peer_id, "received message of unsupported capability");
+                        this.on_bad_message();
+                    }
+                    Poll::Ready(Some(Err(err))) => {
+                        error!(target: "net::session", ?err, remote_peer_id=?this.remote_peer_id, "failed to receive message");
+                        this.close_on_error(err.into());
+                        return Poll::Ready(())
+                    }
+                }
+            }
+
             loop {
                 match this.conn.poll_next_unpin(cx) {
                     Poll::Pending => break,
@@ -455,6 +629,20 @@ impl Future for ActiveSession {
                 }
             }
 
+            while let Some(Poll::Ready(Some(msg))) =
+                this.snap.as_mut().map(|snap| snap.poll_next_unpin(cx))
+            {
+                progress = true;
+                match msg {
+                    Ok(msg) => this.on_snap_bytes(msg),
+                    Err(err) => {
+                        error!(target: "net::session", ?err, remote_peer_id=?this.remote_peer_id, "failed to receive snap message");
+                        this.close_on_error(err.into());
+                        return Poll::Ready(())
+                    }
+                }
+            }
+
             if !progress {
                 if this.timeout_interval.poll_tick(cx).is_ready() {
                     // check for timed out requests
@@ -467,6 +655,26 @@ impl Future for ActiveSession {
     }
 }
 
+/// Splits the authenticated connection into the protocols of an [`ActiveSession`].
+///
+/// Returns the `eth` protocol, the `snap` protocol if the peer shares it, and the multiplexer that
+/// drives the connection.
+pub(crate) fn multiplex(
+    conn: EthStream<P2PStream<ECIESStream<TcpStream>>>,
+) -> (
+    EthStream<ProtocolConnection>,
+    Option<ProtocolConnection>,
+    RlpxMultiplexer<ECIESStream<TcpStream>>,
+) {
+    let version = conn.version();
+    let mut multiplexer = RlpxMultiplexer::new(conn.into_inner());
+    let eth = multiplexer
+        .install_protocol("eth", version as u8)
+        .expect("eth is shared after the handshake; qed");
+    let snap = multiplexer.install_protocol("snap", 1).ok();
+    (EthStream::new(version, eth), snap, multiplexer)
+}
+
 /// Tracks a request received from the peer
 pub(crate) struct ReceivedRequest {
     /// Protocol Identifier
@@ -478,9 +686,20 @@ pub(crate) struct ReceivedRequest {
     received: Instant,
 }
 
+/// Tracks a `snap` request received from the peer
+pub(crate) struct ReceivedSnapRequest {
+    /// Protocol Identifier
+    request_id: u64,
+    /// Receiver half of the channel that's supposed to receive the proper response.
+    rx: PeerSnapResponse,
+    /// Timestamp when we read this msg from the wire.
+    #[allow(unused)]
+    received: Instant,
+}
+
 /// A request that waits for a response from the peer
-pub(crate) struct InflightRequest {
-    request: PeerRequest,
+pub(crate) struct InflightRequest<R = PeerRequest> {
+    request: R,
     deadline: Instant,
 }
 
@@ -490,6 +709,43 @@ pub(crate) enum OutgoingMessage {
     Eth(EthMessage),
     /// A message that may be shared by multiple sessions.
     Broadcast(EthBroadcastMessage),
+    /// An encoded `snap` message.
+    Snap(Bytes),
+}
+
+// === impl OutgoingMessage ===
+
+impl OutgoingMessage {
+    /// Returns the message's ID, if it's an `eth` message.
+    fn message_id(&self) -> Option<EthMessageID> {
+        match self {
+            OutgoingMessage::Eth(msg) => Some(msg.message_id()),
+            OutgoingMessage::Broadcast(msg) => Some(msg.message_id()),
+            OutgoingMessage::Snap(_) => None,
+        }
+    }
+
+    /// Returns the priority class of the message.
+    fn priority(&self) -> MessagePriority {
+        match self.message_id() {
+            Some(
+                EthMessageID::Transactions |
+                EthMessageID::NewPooledTransactionHashes |
+                EthMessageID::GetPooledTransactions |
+                EthMessageID::PooledTransactions,
+            ) => MessagePriority::Transactions,
+            Some(EthMessageID::NewBlockHashes | EthMessageID::NewBlock) => MessagePriority::Blocks,
+            _ => MessagePriority::Sync,
+        }
+    }
+
+    /// Returns `true` if the message is transaction gossip, which the peer doesn't wait for.
+    fn is_transaction_gossip(&self) -> bool {
+        matches!(
+            self.message_id(),
+            Some(EthMessageID::Transactions | EthMessageID::NewPooledTransactionHashes)
+        )
+    }
 }
 
 impl From<EthMessage> for OutgoingMessage {
@@ -504,6 +760,67 @@ impl From<EthBroadcastMessage> for OutgoingMessage {
     }
 }
 
+/// The maximum number of transaction gossip messages that are queued for a peer.
+///
+/// If the peer reads slower than we gossip, new transaction gossip is dropped instead of growing
+/// the queue.
+const MAX_QUEUED_TRANSACTION_GOSSIP: usize = 1024;
+
+/// The priority classes of outgoing messages, from lowest to highest.
+///
+/// If the connection can't keep up, the queued messages of the highest class are sent first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum MessagePriority {
+    /// Transaction gossip and the requests and responses of pooled transactions.
+    Transactions = 0,
+    /// Block announcements.
+    Blocks = 1,
+    /// Requests and responses of headers, bodies, receipts and state, which chain sync waits on.
+    Sync = 2,
+}
+
+/// Outgoing messages of a session, queued by [`MessagePriority`].
+///
+/// Messages of the same class are sent in the order they were queued, so the responses to requests
+/// of the same kind stay in order.
+#[derive(Default)]
+pub(crate) struct QueuedOutgoingMessages {
+    /// A queue per [`MessagePriority`], indexed by the priority.
+    queues: [VecDeque<OutgoingMessage>; 3],
+    /// The number of queued transaction gossip messages.
+    transaction_gossip: usize,
+}
+
+// === impl QueuedOutgoingMessages ===
+
+impl QueuedOutgoingMessages {
+    /// Queues the message in its priority class.
+    ///
+    /// Returns `false` if the message was transaction gossip and dropped because too many are
+    /// queued already.
+    pub(crate) fn push(&mut self, msg: impl Into<OutgoingMessage>) -> bool {
+        let msg = msg.into();
+        if msg.is_transaction_gossip() {
+            if self.transaction_gossip >= MAX_QUEUED_TRANSACTION_GOSSIP {
+                trace!(target: "net::session", msg_id=?msg.message_id(), "dropping transaction gossip");
+                return false
+            }
+            self.transaction_gossip += 1;
+        }
+        self.queues[msg.priority() as usize].push_back(msg);
+        true
+    }
+
+    /// Returns the oldest message of the highest priority class.
+    pub(crate) fn pop(&mut self) -> Option<OutgoingMessage> {
+        let msg = self.queues.iter_mut().rev().find_map(|queue| queue.pop_front())?;
+        if msg.is_transaction_gossip() {
+            self.transaction_gossip -= 1;
+        }
+        Some(msg)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(dead_code)]
@@ -513,10 +830,11 @@ mod tests {
     };
     use reth_ecies::util::pk2id;
     use reth_eth_wire::{
-        EthVersion, HelloMessage, NewPooledTransactionHashes, ProtocolVersion, Status,
-        StatusBuilder, UnauthedEthStream, UnauthedP2PStream,
+        BlockHeaders, EthVersion, HelloMessage, NewBlockHashes, NewPooledTransactionHashes,
+        ProtocolVersion, Status, StatusBuilder, UnauthedEthStream, UnauthedP2PStream,
     };
     use reth_primitives::{ForkFilter, Hardfork};
+    use reth_tasks::TaskExecutor;
     use secp256k1::{SecretKey, SECP256K1};
     use std::time::Duration;
     use tokio::net::TcpListener;
@@ -588,17 +906,20 @@ mod tests {
             let (_disconnect_tx, disconnect_rx) = oneshot::channel();
             let (pending_sessions_tx, pending_sessions_rx) = mpsc::channel(1);
 
-            tokio::task::spawn(start_pending_incoming_session(
-                disconnect_rx,
-                session_id,
-                stream,
-                pending_sessions_tx,
-                remote_addr,
-                self.secret_key,
-                self.hello.clone(),
-                self.status,
-                self.fork_filter.clone(),
-            ));
+            TaskExecutor::current().spawn(
+                "session",
+                start_pending_incoming_session(
+                    disconnect_rx,
+                    session_id,
+                    stream,
+                    pending_sessions_tx,
+                    remote_addr,
+                    self.secret_key,
+                    self.hello.clone(),
+                    self.status,
+                    self.fork_filter.clone(),
+                ),
+            );
 
             let mut stream = ReceiverStream::new(pending_sessions_rx);
 
@@ -616,6 +937,7 @@ mod tests {
 
                     self.to_sessions.push(commands_to_session);
 
+                    let (conn, snap, multiplexer) = multiplex(conn);
                     ActiveSession {
                         next_id: 0,
                         remote_peer_id: peer_id,
@@ -626,9 +948,13 @@ mod tests {
                         to_session: self.active_session_tx.clone(),
                         request_tx: ReceiverStream::new(messages_rx).fuse(),
                         inflight_requests: Default::default(),
+                        inflight_snap_requests: Default::default(),
                         conn,
+                        snap,
+                        multiplexer,
                         queued_outgoing: Default::default(),
                         received_requests: Default::default(),
+                        received_snap_requests: Default::default(),
                         timeout_interval: tokio::time::interval(REQUEST_TIMEOUT),
                         request_timeout: REQUEST_TIMEOUT,
                     }
@@ -662,6 +988,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sync_messages_preempt_transaction_gossip() {
+        let mut queue = QueuedOutgoingMessages::default();
+        queue.push(EthMessage::NewPooledTransactionHashes(NewPooledTransactionHashes(vec![])));
+        queue.push(EthMessage::NewBlockHashes(NewBlockHashes(vec![])));
+        queue.push(EthMessage::BlockHeaders(RequestPair {
+            request_id: 1,
+            message: BlockHeaders(vec![]),
+        }));
+        queue.push(EthMessage::BlockHeaders(RequestPair {
+            request_id: 2,
+            message: BlockHeaders(vec![]),
+        }));
+
+        let mut next = || queue.pop().and_then(|msg| msg.message_id());
+        assert_eq!(next(), Some(EthMessageID::BlockHeaders));
+        assert_eq!(next(), Some(EthMessageID::BlockHeaders));
+        assert_eq!(next(), Some(EthMessageID::NewBlockHashes));
+        assert_eq!(next(), Some(EthMessageID::NewPooledTransactionHashes));
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn snap_messages_are_sync_messages() {
+        let mut queue = QueuedOutgoingMessages::default();
+        queue.push(EthMessage::NewBlockHashes(NewBlockHashes(vec![])));
+        queue.push(OutgoingMessage::Snap(Bytes::from_static(&[0x01, 0xc0])));
+
+        assert!(matches!(queue.pop(), Some(OutgoingMessage::Snap(_))));
+        assert!(matches!(queue.pop(), Some(OutgoingMessage::Eth(_))));
+    }
+
+    #[test]
+    fn transaction_gossip_is_bounded() {
+        let mut queue = QueuedOutgoingMessages::default();
+        let gossip = || EthMessage::NewPooledTransactionHashes(NewPooledTransactionHashes(vec![]));
+        for _ in 0..MAX_QUEUED_TRANSACTION_GOSSIP {
+            assert!(queue.push(gossip()));
+        }
+        assert!(!queue.push(gossip()));
+        // responses are never dropped
+        assert!(queue.push(EthMessage::BlockHeaders(RequestPair {
+            request_id: 1,
+            message: BlockHeaders(vec![]),
+        })));
+
+        queue.pop();
+        queue.pop();
+        assert!(queue.push(gossip()));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_disconnect() {
         let mut builder = SessionBuilder::default();
@@ -676,7 +1053,7 @@ mod tests {
             assert_eq!(msg.as_disconnected().unwrap(), expected_disconnect);
         });
 
-        tokio::task::spawn(async move {
+        TaskExecutor::current().spawn("incoming session", async move {
             let (incoming, _) = listener.accept().await.unwrap();
             let mut session = builder.connect_incoming(incoming).await;
 
@@ -701,7 +1078,7 @@ mod tests {
 
         let (tx, rx) = oneshot::channel();
 
-        tokio::task::spawn(async move {
+        TaskExecutor::current().spawn("incoming session", async move {
             let (incoming, _) = listener.accept().await.unwrap();
             let session = builder.connect_incoming(incoming).await;
             session.await;
@@ -709,7 +1086,7 @@ mod tests {
             tx.send(()).unwrap();
         });
 
-        tokio::task::spawn(fut);
+        TaskExecutor::current().spawn("outgoing session", fut);
 
         rx.await.unwrap();
     }
@@ -736,7 +1113,7 @@ mod tests {
 
         let (tx, rx) = oneshot::channel();
 
-        tokio::task::spawn(async move {
+        TaskExecutor::current().spawn("incoming session", async move {
             let (incoming, _) = listener.accept().await.unwrap();
             let session = builder.connect_incoming(incoming).await;
             session.await;
@@ -744,7 +1121,7 @@ mod tests {
             tx.send(()).unwrap();
         });
 
-        tokio::task::spawn(fut);
+        TaskExecutor::current().spawn("outgoing session", fut);
 
         rx.await.unwrap();
     }
@@ -763,7 +1140,7 @@ mod tests {
 
         let (tx, rx) = oneshot::channel();
 
-        tokio::task::spawn(async move {
+        TaskExecutor::current().spawn("incoming session", async move {
             let (incoming, _) = listener.accept().await.unwrap();
             let session = builder.connect_incoming(incoming).await;
             session.await;
@@ -771,7 +1148,7 @@ mod tests {
             tx.send(()).unwrap();
         });
 
-        tokio::task::spawn(fut);
+        TaskExecutor::current().spawn("outgoing session", fut);
 
         rx.await.unwrap();
     }
@@ -15,7 +15,7 @@ use fnv::FnvHashMap;
 use futures::{future::Either, io, FutureExt, StreamExt};
 use reth_ecies::{stream::ECIESStream, ECIESError};
 use reth_eth_wire::{
-    capability::{Capabilities, CapabilityMessage},
+    capability::{Capabilities, CapabilityMessage, SharedCapability},
     error::EthStreamError,
     DisconnectReason, HelloMessage, Status, UnauthedEthStream, UnauthedP2PStream,
 };
@@ -66,7 +66,7 @@ pub(crate) struct SessionManager {
     /// Size of the command buffer per session.
     session_command_buffer: usize,
     /// The executor for spawned tasks.
-    executor: Option<TaskExecutor>,
+    executor: TaskExecutor,
     /// All pending session that are currently handshaking, exchanging `Hello`s.
     ///
     /// Events produced during the authentication phase are reported to this manager. Once the
@@ -97,7 +97,7 @@ impl SessionManager {
     pub(crate) fn new(
         secret_key: SecretKey,
         config: SessionsConfig,
-        executor: Option<TaskExecutor>,
+        executor: TaskExecutor,
         status: Status,
         hello_message: HelloMessage,
         fork_filter: ForkFilter,
@@ -137,16 +137,12 @@ impl SessionManager {
         SessionId(id)
     }
 
-    /// Spawns the given future onto a new task that is tracked in the `spawned_tasks` [`JoinSet`].
+    /// Spawns the given future onto a new task of the executor.
     fn spawn<F>(&self, f: F)
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        if let Some(ref executor) = self.executor {
-            executor.spawn(async move { f.await })
-        } else {
-            tokio::task::spawn(async move { f.await });
-        }
+        self.executor.spawn("session", f)
     }
 
     /// Invoked on a received status update.
@@ -357,6 +353,7 @@ impl SessionManager {
 
                 let messages = PeerRequestSender { peer_id, to_session_tx };
 
+                let (conn, snap, multiplexer) = active::multiplex(conn);
                 let session = ActiveSession {
                     next_id: 0,
                     remote_peer_id: peer_id,
@@ -367,9 +364,13 @@ impl SessionManager {
                     to_session: self.active_session_tx.clone(),
                     request_tx: ReceiverStream::new(messages_rx).fuse(),
                     inflight_requests: Default::default(),
+                    inflight_snap_requests: Default::default(),
                     conn,
+                    snap,
+                    multiplexer,
                     queued_outgoing: Default::default(),
                     received_requests: Default::default(),
+                    received_snap_requests: Default::default(),
                     timeout_interval: tokio::time::interval(self.request_timeout),
                     request_timeout: self.request_timeout,
                 };
@@ -728,7 +729,7 @@ async fn authenticate_stream(
     remote_addr: SocketAddr,
     direction: Direction,
     hello: HelloMessage,
-    status: Status,
+    mut status: Status,
     fork_filter: ForkFilter,
 ) -> PendingSessionEvent {
     // conduct the p2p handshake and return the authenticated stream
@@ -744,6 +745,11 @@ async fn authenticate_stream(
         }
     };
 
+    // the status must announce the `eth` version negotiated in the hello handshake
+    if let SharedCapability::Eth { version, .. } = p2p_stream.shared_capability() {
+        status.version = *version as u8;
+    }
+
     // if the hello handshake was successful we can try status handshake
     let eth_unauthed = UnauthedEthStream::new(p2p_stream);
     let (eth_stream, their_status) = match eth_unauthed.handshake(status, fork_filter).await {
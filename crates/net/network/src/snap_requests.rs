@@ -0,0 +1,655 @@
+//! Serves the `snap` requests of peers from the hashed state.
+
+use crate::peers::PeersHandle;
+use futures::StreamExt;
+use reth_eth_wire::types::snap::{
+    AccountData, AccountRange, GetAccountRange, GetStorageRanges, StorageData, StorageRanges,
+};
+use reth_interfaces::p2p::error::RequestResult;
+use reth_primitives::{
+    proofs::TrieAccount,
+    trie::{to_nibbles, Trie, TrieError, TrieNode},
+    Bytes, PeerId, H256,
+};
+use reth_provider::{AccountRangeProvider, StorageRangeProvider, TrieNodeProvider};
+use reth_rlp::Encodable;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::{mpsc::UnboundedReceiver, oneshot};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::debug;
+
+// Limits: <https://github.com/ethereum/go-ethereum/blob/v1.11.2/eth/protocols/snap/handler.go#L34-L49>
+
+/// Maximum size of replies to data retrievals.
+const SOFT_RESPONSE_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Number of accounts or slots that are read from the database at once.
+const READ_BATCH_SIZE: usize = 1024;
+
+/// Manages `snap` related requests on top of the p2p network.
+///
+/// Peers are served the state of the hashed state tables, along with the proofs of the ranges.
+/// Only the state of the stored tries is served, see [TrieNodeProvider::state_root]. The proofs
+/// are built from the stored branches along the paths of the first and last key of a range, the
+/// leaves on these paths are recovered from the hashed state.
+///
+/// This can be spawned to another task and is supposed to be run as background service.
+#[must_use = "Manager does nothing unless polled."]
+pub struct SnapRequestHandler<C> {
+    /// The client type that can interact with the state.
+    client: Arc<C>,
+    /// Used for reporting peers.
+    #[allow(unused)]
+    // TODO use to report spammers
+    peers: PeersHandle,
+    /// Incoming request from the [NetworkManager](crate::NetworkManager).
+    incoming_requests: UnboundedReceiverStream<IncomingSnapRequest>,
+}
+
+// === impl SnapRequestHandler ===
+impl<C> SnapRequestHandler<C> {
+    /// Create a new instance
+    pub fn new(
+        client: Arc<C>,
+        peers: PeersHandle,
+        incoming: UnboundedReceiver<IncomingSnapRequest>,
+    ) -> Self {
+        Self { client, peers, incoming_requests: UnboundedReceiverStream::new(incoming) }
+    }
+}
+
+impl<C> SnapRequestHandler<C>
+where
+    C: AccountRangeProvider + StorageRangeProvider + TrieNodeProvider,
+{
+    /// Returns `true` if the root is the root of the served state.
+    fn is_served(&self, root: H256) -> Result<bool, ServeError> {
+        Ok(self.client.state_root()? == Some(root))
+    }
+
+    /// Returns the accounts of the request with the proof of the range.
+    ///
+    /// The response is empty if the root is not the root of the served state.
+    fn get_account_range_response(&self, request: GetAccountRange) -> AccountRange {
+        let request_id = request.request_id;
+        match self.account_range(request) {
+            Ok(Some(response)) => response,
+            Ok(None) => AccountRange { request_id, accounts: vec![], proof: vec![] },
+            Err(err) => {
+                debug!(target: "net::snap", ?err, "failed to serve account range");
+                AccountRange { request_id, accounts: vec![], proof: vec![] }
+            }
+        }
+    }
+
+    fn account_range(&self, request: GetAccountRange) -> Result<Option<AccountRange>, ServeError> {
+        let GetAccountRange { request_id, root_hash, starting_hash, limit_hash, response_bytes } =
+            request;
+        if !self.is_served(root_hash)? {
+            return Ok(None)
+        }
+
+        let limit = (response_bytes as usize).min(SOFT_RESPONSE_LIMIT);
+        let mut total_bytes = 0;
+        let mut accounts = Vec::new();
+        let mut next = Some(starting_hash);
+        'pages: while let Some(start) = next {
+            let page = self.client.account_range(start, READ_BATCH_SIZE)?;
+            for (hash, account) in page.accounts {
+                if hash > limit_hash || total_bytes >= limit {
+                    break 'pages
+                }
+                let account = TrieAccount::new(account, self.client.storage_root(hash)?);
+                total_bytes += hash.length() + account.length();
+                accounts.push(AccountData { hash, account });
+            }
+            next = page.next;
+        }
+
+        let mut trie = Trie::from_root(root_hash);
+        let proof = range_proof(
+            &mut trie,
+            starting_hash,
+            accounts.last().map(|account| account.hash),
+            |path, hash| self.load_account_node(path, hash),
+        )?;
+
+        // the state may have changed while it was read
+        if !self.is_served(root_hash)? {
+            return Ok(None)
+        }
+        Ok(Some(AccountRange { request_id, accounts, proof }))
+    }
+
+    /// Returns the slots of the request.
+    ///
+    /// The last list of slots comes with a proof if it's incomplete or doesn't start at the first
+    /// slot. The response is empty if the root is not the root of the served state.
+    fn get_storage_ranges_response(&self, request: GetStorageRanges) -> StorageRanges {
+        let request_id = request.request_id;
+        match self.storage_ranges(request) {
+            Ok(Some(response)) => response,
+            Ok(None) => StorageRanges { request_id, slots: vec![], proof: vec![] },
+            Err(err) => {
+                debug!(target: "net::snap", ?err, "failed to serve storage ranges");
+                StorageRanges { request_id, slots: vec![], proof: vec![] }
+            }
+        }
+    }
+
+    fn storage_ranges(
+        &self,
+        request: GetStorageRanges,
+    ) -> Result<Option<StorageRanges>, ServeError> {
+        let GetStorageRanges {
+            request_id,
+            root_hash,
+            account_hashes,
+            starting_hash,
+            limit_hash,
+            response_bytes,
+        } = request;
+        if !self.is_served(root_hash)? {
+            return Ok(None)
+        }
+        let mut response = StorageRanges { request_id, slots: vec![], proof: vec![] };
+
+        let limit = (response_bytes as usize).min(SOFT_RESPONSE_LIMIT);
+        let mut total_bytes = 0;
+        for (index, account) in account_hashes.iter().enumerate() {
+            if total_bytes >= limit {
+                break
+            }
+            // the bounds only apply to the first account
+            let (origin, last) = if index == 0 {
+                (
+                    to_hash(&starting_hash).unwrap_or_default(),
+                    to_hash(&limit_hash).unwrap_or(H256::repeat_byte(0xff)),
+                )
+            } else {
+                (H256::zero(), H256::repeat_byte(0xff))
+            };
+
+            let mut slots = Vec::new();
+            let mut complete = true;
+            let mut next = Some(origin);
+            'pages: while let Some(start) = next {
+                let page = self.client.storage_range(*account, start, READ_BATCH_SIZE)?;
+                for (key, value) in page.slots {
+                    if key > last {
+                        break 'pages
+                    }
+                    if value.is_zero() {
+                        continue
+                    }
+                    if total_bytes >= limit {
+                        complete = false;
+                        break 'pages
+                    }
+                    let data = Bytes::from(rlp(&value));
+                    total_bytes += key.length() + data.len();
+                    slots.push(StorageData { hash: key, data });
+                }
+                next = page.next;
+            }
+
+            if !complete || !origin.is_zero() {
+                let mut trie = Trie::from_root(self.client.storage_root(*account)?);
+                response.proof = range_proof(
+                    &mut trie,
+                    origin,
+                    slots.last().map(|slot| slot.hash),
+                    |path, hash| self.load_storage_node(*account, path, hash),
+                )?;
+            }
+            response.slots.push(slots);
+            if !complete {
+                break
+            }
+        }
+
+        // the state may have changed while it was read
+        if !self.is_served(root_hash)? {
+            return Ok(None)
+        }
+        Ok(Some(response))
+    }
+
+    /// Loads the node of the state trie at the position, a stored branch or extension or the leaf
+    /// of the one account whose hashed address starts with the nibbles.
+    fn load_account_node(&self, path: &[u8], hash: H256) -> Result<TrieNode, ServeError> {
+        if let Some(encoded) = self.client.account_trie_node(path)? {
+            return verified(TrieNode::decode(&encoded)?, hash)
+        }
+        let page = self.client.account_range(start_key(path), 1)?;
+        let Some((hashed_address, account)) = page.accounts.into_iter().next() else {
+            return Err(TrieError::MissingNode(hash).into())
+        };
+        let account = TrieAccount::new(account, self.client.storage_root(hashed_address)?);
+        verified(leaf(path, hashed_address, rlp(&account)), hash)
+    }
+
+    /// Loads the node of the storage trie of the account at the position, like
+    /// [Self::load_account_node].
+    fn load_storage_node(
+        &self,
+        hashed_address: H256,
+        path: &[u8],
+        hash: H256,
+    ) -> Result<TrieNode, ServeError> {
+        if let Some(encoded) = self.client.storage_trie_node(hashed_address, path)? {
+            return verified(TrieNode::decode(&encoded)?, hash)
+        }
+        // zero slots are not part of the trie
+        let mut next = Some(start_key(path));
+        while let Some(start) = next {
+            let page = self.client.storage_range(hashed_address, start, READ_BATCH_SIZE)?;
+            if let Some((key, value)) = page.slots.into_iter().find(|(_, value)| !value.is_zero()) {
+                return verified(leaf(path, key, rlp(&value)), hash)
+            }
+            next = page.next;
+        }
+        Err(TrieError::MissingNode(hash).into())
+    }
+
+    fn on_account_range_request(
+        &mut self,
+        _peer_id: PeerId,
+        request: GetAccountRange,
+        response: oneshot::Sender<RequestResult<AccountRange>>,
+    ) {
+        let accounts = self.get_account_range_response(request);
+        let _ = response.send(Ok(accounts));
+    }
+
+    fn on_storage_ranges_request(
+        &mut self,
+        _peer_id: PeerId,
+        request: GetStorageRanges,
+        response: oneshot::Sender<RequestResult<StorageRanges>>,
+    ) {
+        let slots = self.get_storage_ranges_response(request);
+        let _ = response.send(Ok(slots));
+    }
+}
+
+/// An endless future.
+///
+/// This should be spawned or used as part of `tokio::select!`.
+impl<C> Future for SnapRequestHandler<C>
+where
+    C: AccountRangeProvider + StorageRangeProvider + TrieNodeProvider,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            match this.incoming_requests.poll_next_unpin(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(()),
+                Poll::Ready(Some(incoming)) => match incoming {
+                    IncomingSnapRequest::GetAccountRange { peer_id, request, response } => {
+                        this.on_account_range_request(peer_id, request, response)
+                    }
+                    IncomingSnapRequest::GetStorageRanges { peer_id, request, response } => {
+                        this.on_storage_ranges_request(peer_id, request, response)
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// An error reading the served state.
+#[derive(Debug, thiserror::Error)]
+enum ServeError {
+    /// The state could not be read.
+    #[error(transparent)]
+    Provider(#[from] reth_interfaces::Error),
+    /// The stored nodes don't match the hashed state.
+    #[error(transparent)]
+    Trie(#[from] TrieError),
+}
+
+/// Returns the proof of the range from the proofs of its first and last key.
+///
+/// Only the nodes on the paths of the two keys are loaded into the trie.
+fn range_proof(
+    trie: &mut Trie,
+    origin: H256,
+    last: Option<H256>,
+    mut load: impl FnMut(&[u8], H256) -> Result<TrieNode, ServeError>,
+) -> Result<Vec<Bytes>, ServeError> {
+    let mut proof = trie.proof_with(origin.as_bytes(), &mut load)?;
+    if let Some(last) = last {
+        proof.extend(trie.proof_with(last.as_bytes(), &mut load)?);
+    }
+    proof.sort();
+    proof.dedup();
+    Ok(proof)
+}
+
+/// Returns the leaf of the key at the position of the nibbles.
+fn leaf(path: &[u8], key: H256, value: Vec<u8>) -> TrieNode {
+    TrieNode::Leaf { path: to_nibbles(key.as_bytes())[path.len()..].to_vec(), value }
+}
+
+/// Returns the node if it's the node of the hash.
+fn verified(node: TrieNode, hash: H256) -> Result<TrieNode, ServeError> {
+    if node.hash() != hash {
+        return Err(TrieError::MissingNode(hash).into())
+    }
+    Ok(node)
+}
+
+/// Returns the smallest key that starts with the nibbles.
+fn start_key(prefix: &[u8]) -> H256 {
+    let mut key = H256::zero();
+    for (i, nibble) in prefix.iter().enumerate() {
+        key.as_bytes_mut()[i / 2] |= if i % 2 == 0 { nibble << 4 } else { *nibble };
+    }
+    key
+}
+
+fn to_hash(bytes: &[u8]) -> Option<H256> {
+    (bytes.len() == 32).then(|| H256::from_slice(bytes))
+}
+
+fn rlp(value: &impl Encodable) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
+
+/// All `snap` requests served from the state delegated by the network.
+#[derive(Debug)]
+#[allow(missing_docs, clippy::enum_variant_names)]
+pub enum IncomingSnapRequest {
+    /// Request a range of accounts from the peer.
+    ///
+    /// The response should be sent through the channel.
+    GetAccountRange {
+        peer_id: PeerId,
+        request: GetAccountRange,
+        response: oneshot::Sender<RequestResult<AccountRange>>,
+    },
+    /// Request the storage slots of accounts from the peer.
+    ///
+    /// The response should be sent through the channel.
+    GetStorageRanges {
+        peer_id: PeerId,
+        request: GetStorageRanges,
+        response: oneshot::Sender<RequestResult<StorageRanges>>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peers::PeersManager;
+    use reth_primitives::{keccak256, proofs::EMPTY_ROOT, trie::verify_range_proof, Account, U256};
+    use reth_provider::{AccountRange as AccountPage, StorageRange};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+    use tokio::sync::mpsc;
+
+    /// A client that serves a hashed state and the stored nodes of its tries.
+    #[derive(Default)]
+    struct TestState {
+        state: Mutex<BTreeMap<H256, (Account, BTreeMap<H256, U256>)>>,
+        tries: Mutex<StoredTries>,
+        /// The number of stored nodes that were read.
+        node_reads: AtomicUsize,
+    }
+
+    /// The nodes of the tries of a state, like the merkle stage stores them.
+    #[derive(Default)]
+    struct StoredTries {
+        root: Option<H256>,
+        account_nodes: HashMap<Vec<u8>, Vec<u8>>,
+        storage_nodes: HashMap<(H256, Vec<u8>), Vec<u8>>,
+        storage_roots: HashMap<H256, H256>,
+    }
+
+    impl TestState {
+        /// Replaces the hashed state, the stored tries don't match it until they are stored again.
+        fn set_state(&self, state: BTreeMap<H256, (Account, BTreeMap<H256, U256>)>) {
+            *self.state.lock().unwrap() = state;
+            self.tries.lock().unwrap().root = None;
+        }
+
+        /// Stores the nodes of the tries of the hashed state.
+        fn store_tries(&self) {
+            let state = self.state.lock().unwrap();
+            let mut tries = StoredTries::default();
+            let mut accounts = Vec::new();
+            for (hash, (account, storage)) in state.iter() {
+                let slots = storage.iter().filter(|(_, value)| !value.is_zero());
+                let trie = Trie::from_entries(slots.map(|(key, value)| (key, rlp(value))));
+                let (root, branches) = trie.root_hash_with_branches();
+                for (path, node) in branches {
+                    tries.storage_nodes.insert((*hash, path), node);
+                }
+                if root != EMPTY_ROOT {
+                    tries.storage_roots.insert(*hash, root);
+                }
+                accounts.push((*hash, rlp(&TrieAccount::new(*account, root))));
+            }
+            let (root, branches) = Trie::from_entries(accounts).root_hash_with_branches();
+            tries.root = Some(root);
+            tries.account_nodes = branches.into_iter().collect();
+            *self.tries.lock().unwrap() = tries;
+        }
+
+        fn root(&self) -> H256 {
+            self.tries.lock().unwrap().root.unwrap()
+        }
+    }
+
+    impl AccountRangeProvider for TestState {
+        fn account_range(&self, start: H256, limit: usize) -> reth_interfaces::Result<AccountPage> {
+            let state = self.state.lock().unwrap();
+            let mut range = state.range(start..).map(|(hash, (account, _))| (*hash, *account));
+            let accounts = range.by_ref().take(limit).collect();
+            Ok(AccountPage { accounts, next: range.next().map(|(hash, _)| hash) })
+        }
+    }
+
+    impl StorageRangeProvider for TestState {
+        fn storage_range(
+            &self,
+            hashed_address: H256,
+            start: H256,
+            limit: usize,
+        ) -> reth_interfaces::Result<StorageRange> {
+            let state = self.state.lock().unwrap();
+            let Some((_, storage)) = state.get(&hashed_address) else {
+                return Ok(StorageRange::default())
+            };
+            let mut range = storage.range(start..).map(|(key, value)| (*key, *value));
+            let slots = range.by_ref().take(limit).collect();
+            Ok(StorageRange { slots, next: range.next().map(|(key, _)| key) })
+        }
+    }
+
+    impl TrieNodeProvider for TestState {
+        fn state_root(&self) -> reth_interfaces::Result<Option<H256>> {
+            Ok(self.tries.lock().unwrap().root)
+        }
+
+        fn account_trie_node(&self, path: &[u8]) -> reth_interfaces::Result<Option<Vec<u8>>> {
+            self.node_reads.fetch_add(1, Ordering::Relaxed);
+            Ok(self.tries.lock().unwrap().account_nodes.get(path).cloned())
+        }
+
+        fn storage_trie_node(
+            &self,
+            hashed_address: H256,
+            path: &[u8],
+        ) -> reth_interfaces::Result<Option<Vec<u8>>> {
+            self.node_reads.fetch_add(1, Ordering::Relaxed);
+            let tries = self.tries.lock().unwrap();
+            Ok(tries.storage_nodes.get(&(hashed_address, path.to_vec())).cloned())
+        }
+
+        fn storage_root(&self, hashed_address: H256) -> reth_interfaces::Result<H256> {
+            let tries = self.tries.lock().unwrap();
+            Ok(tries.storage_roots.get(&hashed_address).copied().unwrap_or(EMPTY_ROOT))
+        }
+    }
+
+    fn handler(client: Arc<TestState>) -> SnapRequestHandler<TestState> {
+        let (_, rx) = mpsc::unbounded_channel();
+        SnapRequestHandler::new(client, PeersManager::default().handle(), rx)
+    }
+
+    /// Returns a state of accounts of which every third has slots.
+    fn state(len: u64, slots: u64) -> BTreeMap<H256, (Account, BTreeMap<H256, U256>)> {
+        (0..len)
+            .map(|i| {
+                let account = Account { nonce: i, balance: U256::from(i), bytecode_hash: None };
+                let storage = (0..if i % 3 == 0 { slots } else { 0 })
+                    .map(|key| (keccak256(H256::from_low_u64_be(key)), U256::from(key + 1)))
+                    .collect();
+                (keccak256(H256::from_low_u64_be(i)), (account, storage))
+            })
+            .collect()
+    }
+
+    /// Returns a client with the state and its stored tries.
+    fn client(state: BTreeMap<H256, (Account, BTreeMap<H256, U256>)>) -> Arc<TestState> {
+        let client = Arc::new(TestState::default());
+        client.set_state(state);
+        client.store_tries();
+        client
+    }
+
+    fn account_range_request(root_hash: H256, response_bytes: u64) -> GetAccountRange {
+        GetAccountRange {
+            request_id: 1,
+            root_hash,
+            starting_hash: H256::zero(),
+            limit_hash: H256::repeat_byte(0xff),
+            response_bytes,
+        }
+    }
+
+    fn leaves(range: &AccountRange) -> Vec<(H256, Vec<u8>)> {
+        range.accounts.iter().map(|account| (account.hash, rlp(&account.account))).collect()
+    }
+
+    #[test]
+    fn serves_account_ranges_with_proofs() {
+        let client = client(state(100, 10));
+        let handler = handler(Arc::clone(&client));
+        let root = client.root();
+
+        let unknown = H256::repeat_byte(0x11);
+        let range = handler.get_account_range_response(account_range_request(unknown, 1 << 20));
+        assert!(range.accounts.is_empty());
+
+        let range = handler.get_account_range_response(account_range_request(root, 1 << 20));
+        assert_eq!(range.accounts.len(), 100);
+        assert_eq!(
+            verify_range_proof(root, H256::zero(), &leaves(&range), &range.proof),
+            Ok(false)
+        );
+        // only the nodes on the paths of the first and last account are read
+        let stored = client.tries.lock().unwrap().account_nodes.len();
+        assert!(client.node_reads.load(Ordering::Relaxed) < stored);
+
+        // a small response continues where the first one ended
+        let range = handler.get_account_range_response(account_range_request(root, 1000));
+        assert!(!range.accounts.is_empty() && range.accounts.len() < 100);
+        assert_eq!(verify_range_proof(root, H256::zero(), &leaves(&range), &range.proof), Ok(true));
+
+        let origin = H256::from_uint(&(range.accounts.last().unwrap().hash.into_uint() + 1));
+        let request =
+            GetAccountRange { starting_hash: origin, ..account_range_request(root, 1000) };
+        let next = handler.get_account_range_response(request);
+        assert!(next.accounts[0].hash > range.accounts.last().unwrap().hash);
+        assert!(verify_range_proof(root, origin, &leaves(&next), &next.proof).is_ok());
+
+        // the leaves have to match the state
+        let mut modified = leaves(&next);
+        modified[0].1 = rlp(&TrieAccount::new(Account::default(), H256::zero()));
+        assert_eq!(
+            verify_range_proof(root, origin, &modified, &next.proof),
+            Err(TrieError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn serves_storage_ranges_with_proofs() {
+        let client = client(state(10, 50));
+        let handler = handler(Arc::clone(&client));
+        let root = client.root();
+        let (accounts, storage_roots): (Vec<_>, Vec<_>) =
+            client.tries.lock().unwrap().storage_roots.iter().map(|(a, r)| (*a, *r)).unzip();
+
+        let request = GetStorageRanges {
+            request_id: 1,
+            root_hash: root,
+            account_hashes: accounts.clone(),
+            starting_hash: Bytes::default(),
+            limit_hash: Bytes::default(),
+            response_bytes: 1 << 20,
+        };
+        let ranges = handler.get_storage_ranges_response(request.clone());
+        assert_eq!(ranges.slots.len(), accounts.len());
+        assert!(ranges.proof.is_empty());
+        for (slots, storage_root) in ranges.slots.iter().zip(&storage_roots) {
+            let leaves =
+                slots.iter().map(|slot| (slot.hash, slot.data.to_vec())).collect::<Vec<_>>();
+            assert_eq!(verify_range_proof(*storage_root, H256::zero(), &leaves, &[]), Ok(false));
+        }
+
+        // the last list of a small response is incomplete and proven
+        let ranges = handler
+            .get_storage_ranges_response(GetStorageRanges { response_bytes: 3000, ..request });
+        assert!(ranges.slots.len() > 1 && ranges.slots.len() < accounts.len());
+        let last = ranges.slots.last().unwrap();
+        let leaves = last.iter().map(|slot| (slot.hash, slot.data.to_vec())).collect::<Vec<_>>();
+        let storage_root = storage_roots[ranges.slots.len() - 1];
+        assert_eq!(
+            verify_range_proof(storage_root, H256::zero(), &leaves, &ranges.proof),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn serves_state_of_stored_tries() {
+        let client = client(state(10, 5));
+        let handler = handler(Arc::clone(&client));
+        let root = client.root();
+
+        // nothing is served while the tries don't match the hashed state
+        client.set_state(state(20, 5));
+        let range = handler.get_account_range_response(account_range_request(root, 1 << 20));
+        assert!(range.accounts.is_empty());
+
+        client.store_tries();
+        let new_root = client.root();
+        assert_ne!(new_root, root);
+        let range = handler.get_account_range_response(account_range_request(root, 1 << 20));
+        assert!(range.accounts.is_empty());
+        let range = handler.get_account_range_response(account_range_request(new_root, 1 << 20));
+        assert_eq!(range.accounts.len(), 20);
+        assert_eq!(
+            verify_range_proof(new_root, H256::zero(), &leaves(&range), &range.proof),
+            Ok(false)
+        );
+    }
+}
@@ -57,6 +57,8 @@ pub struct NetworkState<C> {
     /// The fetcher streams RLPx related requests on a per-peer basis to this type. This type will
     /// then queue in the request and notify the fetcher once the result has been received.
     state_fetcher: StateFetcher,
+    /// The number of `snap` requests that were assigned to peers, to take turns.
+    snap_requests: usize,
 }
 
 impl<C> NetworkState<C>
@@ -79,6 +81,7 @@ where
             discovery,
             genesis_hash,
             state_fetcher,
+            snap_requests: 0,
         }
     }
 
@@ -107,6 +110,23 @@ where
         self.active_peers.len()
     }
 
+    /// Returns the peer that should serve the next `snap` request.
+    ///
+    /// The requests take turns among all peers that support the `snap` protocol.
+    pub(crate) fn next_snap_peer(&mut self) -> Option<PeerId> {
+        let mut snap_peers = self
+            .active_peers
+            .iter()
+            .filter(|(_, peer)| peer.capabilities.supports_snap())
+            .map(|(peer_id, _)| *peer_id);
+        let num_snap_peers = snap_peers.clone().count();
+        if num_snap_peers == 0 {
+            return None
+        }
+        self.snap_requests = self.snap_requests.wrapping_add(1);
+        snap_peers.nth(self.snap_requests % num_snap_peers)
+    }
+
     /// Event hook for an activated session for the peer.
     ///
     /// Returns `Ok` if the session is valid, returns an `Err` if the session is not accepted and
@@ -431,7 +451,6 @@ pub(crate) struct ActivePeer {
     /// Best block of the peer.
     pub(crate) best_hash: H256,
     /// The capabilities of the remote peer.
-    #[allow(unused)]
     pub(crate) capabilities: Arc<Capabilities>,
     /// A communication channel directly to the session task.
     pub(crate) request_tx: PeerRequestSender,
@@ -16,11 +16,12 @@ use reth_interfaces::p2p::error::RequestResult;
 use reth_primitives::{
     FromRecoveredTransaction, IntoRecoveredTransaction, PeerId, TransactionSigned, TxHash, H256,
 };
+use reth_rlp::Encodable;
 use reth_transaction_pool::{
     error::PoolResult, PropagateKind, PropagatedTransactions, TransactionPool,
 };
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
     future::Future,
     num::NonZeroUsize,
     pin::Pin,
@@ -34,6 +35,12 @@ use tracing::trace;
 /// Cache limit of transactions to keep track of for a single peer.
 const PEER_TRANSACTION_CACHE_LIMIT: usize = 1024 * 10;
 
+/// Soft limit for the number of hashes in a `GetPooledTransactions` request.
+const GET_POOLED_TRANSACTION_SOFT_LIMIT_NUM_HASHES: usize = 256;
+
+/// Soft limit for the byte size of a `PooledTransactions` response.
+const POOLED_TRANSACTIONS_RESPONSE_SOFT_LIMIT_BYTE_SIZE: usize = 2 * 1024 * 1024;
+
 /// The future for inserting a function into the pool
 pub type PoolImportFuture = Pin<Box<dyn Future<Output = PoolResult<TxHash>> + Send + 'static>>;
 
@@ -84,6 +91,10 @@ pub struct TransactionsManager<Pool> {
     network_events: UnboundedReceiverStream<NetworkEvent>,
     /// All currently active requests for pooled transactions.
     inflight_requests: Vec<GetPooledTxRequest>,
+    /// Hashes of all transactions that are currently requested from a peer.
+    ///
+    /// This way we only request a transaction that's announced by multiple peers once.
+    inflight_hashes: HashSet<TxHash>,
     /// All currently pending transactions grouped by peers.
     ///
     /// This way we can track incoming transactions and prevent multiple pool imports for the same
@@ -123,6 +134,7 @@ impl<Pool: TransactionPool> TransactionsManager<Pool> {
             network,
             network_events,
             inflight_requests: Default::default(),
+            inflight_hashes: Default::default(),
             transactions_by_peers: Default::default(),
             pool_imports: Default::default(),
             peers: Default::default(),
@@ -154,12 +166,17 @@ where
         response: oneshot::Sender<RequestResult<PooledTransactions>>,
     ) {
         if let Some(peer) = self.peers.get_mut(&peer_id) {
-            let transactions = self
-                .pool
-                .get_all(request.0)
-                .into_iter()
-                .map(|tx| tx.transaction.to_recovered_transaction().into_signed())
-                .collect::<Vec<_>>();
+            let mut transactions = Vec::new();
+            let mut total_bytes = 0;
+            for tx in self.pool.get_all(request.0) {
+                let tx = tx.transaction.to_recovered_transaction().into_signed();
+                total_bytes += tx.length();
+                transactions.push(tx);
+
+                if total_bytes > POOLED_TRANSACTIONS_RESPONSE_SOFT_LIMIT_BYTE_SIZE {
+                    break
+                }
+            }
 
             // we sent a response at which point we assume that the peer is aware of the transaction
             peer.transactions.extend(transactions.iter().map(|tx| tx.hash()));
@@ -203,17 +220,18 @@ where
     ) -> PropagatedTransactions {
         let mut propagated = PropagatedTransactions::default();
 
-        // send full transactions to a fraction fo the connected peers (square root of the total
+        // send full transactions to a fraction of the connected peers (square root of the total
         // number of connected peers)
-        let max_num_full = (self.peers.len() as f64).sqrt() as usize + 1;
+        let max_num_full = ((self.peers.len() as f64).sqrt() as usize).max(1);
 
         // Note: Assuming ~random~ order due to random state of the peers map hasher
         for (idx, (peer_id, peer)) in self.peers.iter_mut().enumerate() {
+            // only send transactions the peer is not yet aware of
             let (hashes, full): (Vec<_>, Vec<_>) =
                 txs.iter().filter(|(hash, _)| peer.transactions.insert(*hash)).cloned().unzip();
 
             if !full.is_empty() {
-                if idx > max_num_full {
+                if idx >= max_num_full {
                     for hash in &hashes {
                         propagated.0.entry(*hash).or_default().push(PropagateKind::Hash(*peer_id));
                     }
@@ -243,24 +261,50 @@ where
 
             self.pool.retain_unknown(&mut transactions);
 
+            // skip duplicates and transactions that are already requested from or imported for
+            // another peer
+            let mut unique = HashSet::with_capacity(transactions.len());
+            transactions.retain(|hash| {
+                !self.inflight_hashes.contains(hash) &&
+                    !self.transactions_by_peers.contains_key(hash) &&
+                    unique.insert(*hash)
+            });
+
             if transactions.is_empty() {
                 // nothing to request
                 return
             }
 
             // request the missing transactions
-            let (response, rx) = oneshot::channel();
-            let req = PeerRequest::GetPooledTransactions {
-                request: GetPooledTransactions(transactions),
-                response,
-            };
-
-            if peer.request_tx.try_send(req).is_ok() {
-                self.inflight_requests.push(GetPooledTxRequest { peer_id, response: rx })
+            for hashes in transactions.chunks(GET_POOLED_TRANSACTION_SOFT_LIMIT_NUM_HASHES) {
+                let (response, rx) = oneshot::channel();
+                let req = PeerRequest::GetPooledTransactions {
+                    request: GetPooledTransactions(hashes.to_vec()),
+                    response,
+                };
+
+                if peer.request_tx.try_send(req).is_err() {
+                    // the session is busy, the remaining hashes can be requested from other peers
+                    break
+                }
+                trace!(target: "net::tx", ?peer_id, num_hashes = hashes.len(), "Requesting pooled transactions");
+                self.inflight_hashes.extend(hashes.iter().copied());
+                self.inflight_requests.push(GetPooledTxRequest {
+                    peer_id,
+                    hashes: hashes.to_vec(),
+                    response: rx,
+                })
             }
         }
     }
 
+    /// Invoked when a request for pooled transactions finished.
+    fn on_get_pooled_transactions_response(&mut self, req: GetPooledTxRequest) {
+        for hash in req.hashes {
+            self.inflight_hashes.remove(&hash);
+        }
+    }
+
     /// Handles dedicated transaction events related tot the `eth` protocol.
     fn on_network_tx_event(&mut self, event: NetworkTransactionEvent) {
         match event {
@@ -293,20 +337,21 @@ where
                 self.peers.remove(&peer_id);
             }
             NetworkEvent::SessionEstablished { peer_id, messages, .. } => {
-                // insert a new peer
-                self.peers.insert(
-                    peer_id,
-                    Peer {
-                        transactions: LruCache::new(
-                            NonZeroUsize::new(PEER_TRANSACTION_CACHE_LIMIT).unwrap(),
-                        ),
-                        request_tx: messages,
-                    },
-                );
+                let mut transactions =
+                    LruCache::new(NonZeroUsize::new(PEER_TRANSACTION_CACHE_LIMIT).unwrap());
 
                 // Send a `NewPooledTransactionHashes` to the peer with _all_ transactions in the
                 // pool
-                let msg = NewPooledTransactionHashes(self.pool.pooled_transactions());
+                let hashes = self.pool.pooled_transactions();
+                transactions.extend(hashes.iter().copied());
+
+                // insert a new peer
+                self.peers.insert(peer_id, Peer { transactions, request_tx: messages });
+
+                if hashes.is_empty() {
+                    return
+                }
+                let msg = NewPooledTransactionHashes(hashes);
                 self.network.send_message(NetworkHandleMessage::SendPooledTransactionHashes {
                     peer_id,
                     msg,
@@ -322,24 +367,26 @@ where
         let mut has_bad_transactions = false;
         if let Some(peer) = self.peers.get_mut(&peer_id) {
             for tx in transactions {
-                // recover transaction
-                let tx = if let Some(tx) = tx.into_ecrecovered() {
-                    tx
-                } else {
-                    has_bad_transactions = true;
-                    continue
-                };
-
                 // track that the peer knows this transaction
                 peer.transactions.insert(tx.hash);
 
+                // skip transactions that are already in the pool, before recovering the signer
+                if self.pool.contains(&tx.hash) {
+                    continue
+                }
+
                 match self.transactions_by_peers.entry(tx.hash) {
                     Entry::Occupied(mut entry) => {
                         // transaction was already inserted
                         entry.get_mut().push(peer_id);
                     }
                     Entry::Vacant(entry) => {
-                        // this is a new transaction that should be imported into the pool
+                        // this is a new transaction that should be imported into the pool, the
+                        // signer is only recovered once per transaction
+                        let Some(tx) = tx.into_ecrecovered() else {
+                            has_bad_transactions = true;
+                            continue
+                        };
                         let pool_transaction = <Pool::Transaction as FromRecoveredTransaction>::from_recovered_transaction(tx);
 
                         let pool = self.pool.clone();
@@ -408,18 +455,22 @@ where
         // We remove each request one by one and add them back.
         for idx in (0..this.inflight_requests.len()).rev() {
             let mut req = this.inflight_requests.swap_remove(idx);
+            let peer_id = req.peer_id;
             match req.response.poll_unpin(cx) {
                 Poll::Pending => {
                     this.inflight_requests.push(req);
                 }
                 Poll::Ready(Ok(Ok(txs))) => {
-                    this.import_transactions(req.peer_id, txs.0);
+                    this.on_get_pooled_transactions_response(req);
+                    this.import_transactions(peer_id, txs.0);
                 }
                 Poll::Ready(Ok(Err(_))) => {
-                    this.report_bad_message(req.peer_id);
+                    this.on_get_pooled_transactions_response(req);
+                    this.report_bad_message(peer_id);
                 }
                 Poll::Ready(Err(_)) => {
-                    this.report_bad_message(req.peer_id);
+                    this.on_get_pooled_transactions_response(req);
+                    this.report_bad_message(peer_id);
                 }
             }
         }
@@ -455,6 +506,7 @@ where
 #[allow(missing_docs)]
 struct GetPooledTxRequest {
     peer_id: PeerId,
+    hashes: Vec<TxHash>,
     response: oneshot::Receiver<RequestResult<PooledTransactions>>,
 }
 
@@ -394,3 +394,25 @@ async fn test_geth_disconnect() {
     .await
     .unwrap();
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_node_info() {
+    reth_tracing::init_tracing();
+    let secret_key = SecretKey::new(&mut rand::thread_rng());
+    let local = SocketAddr::new([127, 0, 0, 1].into(), 0);
+    let config = NetworkConfig::builder(Arc::new(TestApi::default()), secret_key)
+        .listener_addr(local)
+        .discovery_addr(local)
+        .build();
+    let network = NetworkManager::new(config).await.unwrap();
+    let handle = network.handle().clone();
+    tokio::task::spawn(network);
+
+    let info = handle.node_info().await.unwrap();
+    assert_eq!(info.record.id, *handle.peer_id());
+    assert_eq!(info.record.tcp_port, handle.local_addr().port());
+    assert_eq!(info.record.udp_port, info.discovery_addr.port());
+    assert_eq!(info.enr.tcp4(), Some(info.record.tcp_port));
+    assert_eq!(info.enr.udp4(), Some(info.record.udp_port));
+    assert!(info.capabilities.iter().any(|cap| cap.to_string() == "eth/67"));
+}
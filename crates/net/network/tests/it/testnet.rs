@@ -10,9 +10,11 @@ use reth_network::{
 };
 use reth_primitives::{
     rpc::{BlockId, BlockNumber},
-    Block, BlockHash, Header, PeerId, H256, U256,
+    Block, BlockHash, Header, PeerId, Receipt, H256, U256,
+};
+use reth_provider::{
+    test_utils::TestApi, BlockProvider, ChainInfo, HeaderProvider, ReceiptProvider,
 };
-use reth_provider::{test_utils::TestApi, BlockProvider, ChainInfo, HeaderProvider};
 use secp256k1::SecretKey;
 use std::{
     collections::HashMap,
@@ -40,7 +42,7 @@ pub struct Testnet<C> {
 
 impl<C> Testnet<C>
 where
-    C: BlockProvider + HeaderProvider,
+    C: BlockProvider + HeaderProvider + ReceiptProvider,
 {
     /// Same as [`Self::try_create_with`] but panics on error
     pub async fn create_with(num_peers: usize, provider: Arc<C>) -> Self {
@@ -119,7 +121,7 @@ where
 
 impl<C> Testnet<C>
 where
-    C: BlockProvider + HeaderProvider + 'static,
+    C: BlockProvider + HeaderProvider + ReceiptProvider + 'static,
 {
     /// Spawns the testnet to a separate task
     pub fn spawn(self) -> TestnetHandle<C> {
@@ -173,7 +175,7 @@ impl<C> fmt::Debug for Testnet<C> {
 
 impl<C> Future for Testnet<C>
 where
-    C: BlockProvider + HeaderProvider,
+    C: BlockProvider + HeaderProvider + ReceiptProvider,
 {
     type Output = ();
 
@@ -216,7 +218,7 @@ pub struct Peer<C> {
 
 impl<C> Peer<C>
 where
-    C: BlockProvider + HeaderProvider,
+    C: BlockProvider + HeaderProvider + ReceiptProvider,
 {
     pub fn num_peers(&self) -> usize {
         self.network.num_connected_peers()
@@ -243,7 +245,7 @@ where
 
 impl<C> Future for Peer<C>
 where
-    C: BlockProvider + HeaderProvider,
+    C: BlockProvider + HeaderProvider + ReceiptProvider,
 {
     type Output = ();
 
@@ -268,7 +270,7 @@ pub struct PeerConfig<C = TestApi> {
 
 impl<C> PeerConfig<C>
 where
-    C: BlockProvider + HeaderProvider,
+    C: BlockProvider + HeaderProvider + ReceiptProvider,
 {
     pub fn new(client: Arc<C>) -> Self {
         let secret_key = SecretKey::new(&mut rand::thread_rng());
@@ -330,6 +332,7 @@ impl NetworkEventStream {
 pub struct MockEthProvider {
     pub blocks: Arc<Mutex<HashMap<H256, Block>>>,
     pub headers: Arc<Mutex<HashMap<H256, Header>>>,
+    pub receipts: Arc<Mutex<HashMap<H256, Vec<Receipt>>>>,
 }
 
 impl MockEthProvider {
@@ -352,6 +355,10 @@ impl MockEthProvider {
             self.add_header(hash, header)
         }
     }
+
+    pub fn add_receipts(&self, hash: H256, receipts: Vec<Receipt>) {
+        self.receipts.lock().insert(hash, receipts);
+    }
 }
 
 impl HeaderProvider for MockEthProvider {
@@ -413,3 +420,15 @@ impl BlockProvider for MockEthProvider {
         Ok(hash)
     }
 }
+
+impl ReceiptProvider for MockEthProvider {
+    fn receipts_by_block(&self, id: BlockId) -> reth_interfaces::Result<Option<Vec<Receipt>>> {
+        let lock = self.receipts.lock();
+        match id {
+            BlockId::Hash(hash) => Ok(lock.get(&hash).cloned()),
+            _ => {
+                unreachable!("unused in network tests")
+            }
+        }
+    }
+}
@@ -1,24 +1,63 @@
 use jsonrpsee::{core::RpcResult as Result, proc_macros::rpc};
+use reth_primitives::{Bytes, H256};
+use reth_rpc_types::{NodeInfo, PeerEvent, PeerInfo, PeerReputation, TxPoolFeeStats};
 
 /// Admin namespace rpc interface that gives access to several non-standard RPC methods.
-#[rpc(server)]
+#[cfg_attr(not(feature = "client"), rpc(server))]
+#[cfg_attr(feature = "client", rpc(server, client))]
 #[async_trait::async_trait]
 pub trait AdminApi {
     /// Adds the given node record to the peerset.
     #[method(name = "admin_addPeer")]
     async fn add_peer(&self, record: String) -> Result<bool>;
 
+    /// Adds the given node record to the peerset as a trusted peer.
+    ///
+    /// Trusted peers are never disconnected and are exempt from the peer slot limits.
+    #[method(name = "admin_addTrustedPeer")]
+    async fn add_trusted_peer(&self, record: String) -> Result<bool>;
+
     /// Disconnects from a remote node if the connection exists.
     ///
     /// Returns true if the peer was successfully removed.
     #[method(name = "admin_removePeer")]
     async fn remove_peer(&self, record: String) -> Result<bool>;
 
-    /// Creates an RPC subscription which serves events received from the network.
+    /// Returns all peers the node is currently connected to.
+    #[method(name = "admin_peers")]
+    async fn peers(&self) -> Result<Vec<PeerInfo>>;
+
+    /// Returns the enode URL, ENR, ports and protocols of the local node.
+    #[method(name = "admin_nodeInfo")]
+    async fn node_info(&self) -> Result<NodeInfo>;
+
+    /// Returns the reputation of all peers in the peer set.
+    #[method(name = "admin_peerReputations")]
+    async fn peer_reputations(&self) -> Result<Vec<PeerReputation>>;
+
+    /// Returns all transactions of the transaction pool, encoded as an RLP list of signed
+    /// transactions.
+    #[method(name = "admin_exportTransactions")]
+    async fn export_transactions(&self) -> Result<Bytes>;
+
+    /// Adds transactions that were exported via `admin_exportTransactions` to the transaction
+    /// pool.
+    ///
+    /// Returns the hashes of the transactions that were added.
+    #[method(name = "admin_importTransactions")]
+    async fn import_transactions(&self, transactions: Bytes) -> Result<Vec<H256>>;
+
+    /// Returns the minimum fee the transaction pool enforces under congestion and the fee
+    /// percentiles it is derived from.
+    #[method(name = "admin_poolFeeStats")]
+    async fn pool_fee_stats(&self) -> Result<TxPoolFeeStats>;
+
+    /// Creates an RPC subscription which serves the sessions with peers that are established and
+    /// closed.
     #[subscription(
         name = "admin_peerEvents",
         unsubscribe = "admin_peerEvents_unsubscribe",
-        item = String
+        item = PeerEvent
     )]
     fn subscribe(&self);
 }
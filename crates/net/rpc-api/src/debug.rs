@@ -3,7 +3,7 @@ use reth_primitives::{
     rpc::{BlockId, Bytes},
     H256,
 };
-use reth_rpc_types::RichBlock;
+use reth_rpc_types::{AccountRange, RichBlock};
 
 /// Debug rpc interface.
 #[cfg_attr(not(feature = "client"), rpc(server))]
@@ -28,4 +28,17 @@ pub trait DebugApi {
     /// Returns an array of recent bad blocks that the client has seen on the network.
     #[method(name = "debug_getBadBlocks")]
     async fn bad_blocks(&self) -> Result<Vec<RichBlock>>;
+
+    /// Returns a page of at most `max_results` accounts of the state at the given block, starting
+    /// at the account with the hashed address `start`.
+    #[method(name = "debug_accountRange")]
+    async fn account_range(
+        &self,
+        block_id: BlockId,
+        start: H256,
+        max_results: u64,
+        nocode: bool,
+        nostorage: bool,
+        incompletes: bool,
+    ) -> Result<AccountRange>;
 }
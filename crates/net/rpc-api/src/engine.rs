@@ -1,5 +1,5 @@
 use jsonrpsee::{core::RpcResult as Result, proc_macros::rpc};
-use reth_primitives::H64;
+use reth_primitives::{H256, H64};
 use reth_rpc_types::engine::{
     ExecutionPayload, ForkchoiceState, ForkchoiceUpdated, PayloadAttributes, PayloadStatus,
     TransitionConfiguration,
@@ -17,6 +17,15 @@ pub trait EngineApi {
     #[method(name = "engine_newPayloadV2")]
     async fn new_payload_v2(&self, payload: ExecutionPayload) -> Result<PayloadStatus>;
 
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#engine_newpayloadv3>
+    #[method(name = "engine_newPayloadV3")]
+    async fn new_payload_v3(
+        &self,
+        payload: ExecutionPayload,
+        versioned_hashes: Vec<H256>,
+        parent_beacon_block_root: H256,
+    ) -> Result<PayloadStatus>;
+
     /// See also <https://github.com/ethereum/execution-apis/blob/6709c2a795b707202e93c4f2867fa0bf2640a84f/src/engine/paris.md#engine_forkchoiceupdatedv1>
     ///
     /// Caution: This should not accept the `withdrawals` field
@@ -35,6 +44,14 @@ pub trait EngineApi {
         payload_attributes: Option<PayloadAttributes>,
     ) -> Result<ForkchoiceUpdated>;
 
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#engine_forkchoiceupdatedv3>
+    #[method(name = "engine_forkchoiceUpdatedV3")]
+    async fn fork_choice_updated_v3(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated>;
+
     /// See also <https://github.com/ethereum/execution-apis/blob/6709c2a795b707202e93c4f2867fa0bf2640a84f/src/engine/paris.md#engine_getpayloadv1>
     ///
     /// Caution: This should not return the `withdrawals` field
@@ -45,10 +62,21 @@ pub trait EngineApi {
     #[method(name = "engine_getPayloadV2")]
     async fn get_payload_v2(&self, payload_id: H64) -> Result<ExecutionPayload>;
 
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#engine_getpayloadv3>
+    #[method(name = "engine_getPayloadV3")]
+    async fn get_payload_v3(&self, payload_id: H64) -> Result<ExecutionPayload>;
+
     /// See also <https://github.com/ethereum/execution-apis/blob/6709c2a795b707202e93c4f2867fa0bf2640a84f/src/engine/paris.md#engine_exchangetransitionconfigurationv1>
     #[method(name = "engine_exchangeTransitionConfigurationV1")]
     async fn exchange_transition_configuration(
         &self,
         transition_configuration: TransitionConfiguration,
     ) -> Result<TransitionConfiguration>;
+
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/common.md#engine_exchangecapabilities>
+    ///
+    /// Returns the Engine API methods supported by the node, regardless of the methods supported
+    /// by the consensus client.
+    #[method(name = "engine_exchangeCapabilities")]
+    async fn exchange_capabilities(&self, capabilities: Vec<String>) -> Result<Vec<String>>;
 }
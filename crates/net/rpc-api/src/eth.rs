@@ -4,8 +4,8 @@ use reth_primitives::{
     Address, BlockNumber, Bytes, H256, H64, U256, U64,
 };
 use reth_rpc_types::{
-    CallRequest, EIP1186AccountProofResponse, FeeHistory, Index, RichBlock, SyncStatus,
-    Transaction, TransactionReceipt, TransactionRequest, Work,
+    BlockOverrides, CallRequest, EIP1186AccountProofResponse, FeeHistory, Index, RichBlock,
+    StateOverride, SyncStatus, Transaction, TransactionReceipt, TransactionRequest, Work,
 };
 
 /// Eth rpc interface: <https://ethereum.github.io/execution-apis/api-documentation/>
@@ -127,8 +127,16 @@ pub trait EthApi {
     async fn get_code(&self, address: Address, block_number: Option<BlockId>) -> Result<Bytes>;
 
     /// Executes a new message call immediately without creating a transaction on the block chain.
+    ///
+    /// The state and the block environment of the call can be customized with the overrides.
     #[method(name = "eth_call")]
-    async fn call(&self, request: CallRequest, block_number: Option<BlockId>) -> Result<Bytes>;
+    async fn call(
+        &self,
+        request: CallRequest,
+        block_number: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<Box<BlockOverrides>>,
+    ) -> Result<Bytes>;
 
     /// Generates an access list for a transaction.
     ///
@@ -153,11 +161,14 @@ pub trait EthApi {
 
     /// Generates and returns an estimate of how much gas is necessary to allow the transaction to
     /// complete.
+    ///
+    /// The state of the estimation can be customized with the overrides.
     #[method(name = "eth_estimateGas")]
     async fn estimate_gas(
         &self,
         request: CallRequest,
         block_number: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
     ) -> Result<U256>;
 
     /// Returns the current price per gas in wei.
@@ -0,0 +1,22 @@
+use jsonrpsee::{core::RpcResult as Result, proc_macros::rpc};
+use reth_rpc_types::{TxpoolContent, TxpoolInspect, TxpoolStatus};
+
+/// Txpool rpc interface.
+#[cfg_attr(not(feature = "client"), rpc(server))]
+#[cfg_attr(feature = "client", rpc(server, client))]
+pub trait TxPoolApi {
+    /// Returns the number of transactions currently pending for inclusion in the next block(s), as
+    /// well as the ones that are being scheduled for future execution only.
+    #[method(name = "txpool_status")]
+    async fn status(&self) -> Result<TxpoolStatus>;
+
+    /// Returns a summary of all the transactions currently pending for inclusion in the next
+    /// block(s), as well as the ones that are being scheduled for future execution only.
+    #[method(name = "txpool_inspect")]
+    async fn inspect(&self) -> Result<TxpoolInspect>;
+
+    /// Returns the details of all transactions currently pending for inclusion in the next
+    /// block(s), as well as the ones that are being scheduled for future execution only.
+    #[method(name = "txpool_content")]
+    async fn content(&self) -> Result<TxpoolContent>;
+}
@@ -0,0 +1,94 @@
+use reth_primitives::{PeerId, U256};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+
+/// A change of the sessions with peers, as sent to the subscribers of `admin_peerEvents`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerEvent {
+    /// The kind of the event.
+    #[serde(rename = "type")]
+    pub kind: PeerEventKind,
+    /// The peer's node id.
+    pub peer: PeerId,
+    /// Why the session was closed, if the reason is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The kind of a [PeerEvent].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerEventKind {
+    /// A session with the peer was established.
+    Add,
+    /// The session with the peer was closed.
+    Drop,
+}
+
+/// The reputation of a peer in the peer set, as returned by `admin_peerReputations`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+    /// The peer's node id.
+    pub id: PeerId,
+    /// Where the peer can be reached.
+    pub address: SocketAddr,
+    /// The current reputation score of the peer.
+    pub reputation: i32,
+    /// Whether the peer's reputation is below the ban threshold.
+    pub banned: bool,
+    /// How often the peer was banned.
+    pub num_bans: u32,
+    /// Whether there's currently an active session with the peer.
+    pub connected: bool,
+}
+
+/// The minimum fee of the transaction pool, as returned by `admin_poolFeeStats`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxPoolFeeStats {
+    /// The fee that new transactions received from peers have to pay at least.
+    ///
+    /// This is zero unless the pool is congested.
+    pub min_fee: U256,
+    /// The number of consecutive blocks after which the pool was congested.
+    pub congested_blocks: u64,
+    /// The lowest fee percentile of the pooled transactions that were included in recent blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub included_percentile: Option<U256>,
+    /// The fee percentile of the pending transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_percentile: Option<U256>,
+}
+
+/// The addresses and protocols of the local node, as returned by `admin_nodeInfo`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    /// The node id, hex encoded.
+    pub id: String,
+    /// The client version the node identifies itself with.
+    pub name: String,
+    /// The enode URL of the node.
+    pub enode: String,
+    /// The EIP-868 record of the node, base64 encoded with the `enr:` prefix.
+    pub enr: String,
+    /// The IP address the node advertises.
+    pub ip: IpAddr,
+    /// The address that accepts incoming connections.
+    pub listen_addr: SocketAddr,
+    /// The ports of the node.
+    pub ports: Ports,
+    /// The protocols the node supports, e.g. `eth/67`.
+    pub protocols: Vec<String>,
+}
+
+/// The ports of a node.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Ports {
+    /// The UDP port of the discovery service.
+    pub discovery: u16,
+    /// The TCP port that accepts incoming connections.
+    pub listener: u16,
+}
@@ -1,5 +1,8 @@
 use crate::Transaction;
-use reth_primitives::{rpc::H64, Address, Bloom, Bytes, H256, U256};
+use reth_primitives::{
+    rpc::H64, Address, Block as PrimitiveBlock, Bloom, Bytes, Header as PrimitiveHeader, H256, U256,
+};
+use reth_rlp::Encodable;
 use serde::{ser::Error, Deserialize, Serialize, Serializer};
 use std::{collections::BTreeMap, ops::Deref};
 
@@ -33,6 +36,44 @@ pub struct Block {
     pub base_fee_per_gas: Option<U256>,
 }
 
+impl Block {
+    /// Creates the object of the block with the given hash and total difficulty.
+    ///
+    /// With `full` the transactions are included as objects, which requires recovering their
+    /// senders. Returns `None` if a signature is invalid.
+    pub fn from_block(
+        block: PrimitiveBlock,
+        hash: H256,
+        total_difficulty: U256,
+        full: bool,
+    ) -> Option<Self> {
+        let size = U256::from(block.length());
+        let uncles = block.ommers.iter().map(|ommer| ommer.hash_slow()).collect();
+        let PrimitiveBlock { header, body, .. } = block;
+        let transactions = if full {
+            let transactions = body
+                .into_iter()
+                .enumerate()
+                .map(|(index, tx)| {
+                    let tx = tx.into_ecrecovered()?;
+                    Some(Transaction::from_block_transaction(tx, &header, hash, index))
+                })
+                .collect::<Option<_>>()?;
+            BlockTransactions::Full(transactions)
+        } else {
+            BlockTransactions::Hashes(body.iter().map(|tx| tx.hash()).collect())
+        };
+        Some(Self {
+            header: Header { size: Some(size), ..Header::from_primitive(&header, hash) },
+            total_difficulty,
+            uncles,
+            transactions,
+            size: Some(size),
+            base_fee_per_gas: header.base_fee_per_gas.map(U256::from),
+        })
+    }
+}
+
 /// Block header representation.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -74,6 +115,33 @@ pub struct Header {
     pub size: Option<U256>,
 }
 
+impl Header {
+    /// Creates the object of the header of the block with the given hash.
+    ///
+    /// The size of the block is not known from the header alone.
+    pub fn from_primitive(header: &PrimitiveHeader, hash: H256) -> Self {
+        Self {
+            hash: Some(hash),
+            parent_hash: header.parent_hash,
+            uncles_hash: header.ommers_hash,
+            author: header.beneficiary,
+            miner: header.beneficiary,
+            state_root: header.state_root,
+            transactions_root: header.transactions_root,
+            receipts_root: header.receipts_root,
+            number: Some(U256::from(header.number)),
+            gas_used: U256::from(header.gas_used),
+            gas_limit: U256::from(header.gas_limit),
+            extra_data: header.extra_data.clone().into(),
+            logs_bloom: header.logs_bloom,
+            timestamp: U256::from(header.timestamp),
+            difficulty: header.difficulty,
+            nonce: Some(H64::from_low_u64_be(header.nonce)),
+            size: None,
+        }
+    }
+}
+
 /// A Block representation that allows to include additional fields
 pub type RichBlock = Rich<Block>;
 
@@ -1,5 +1,6 @@
-use reth_primitives::{rpc::transaction::eip2930::AccessListItem, Address, Bytes, U256};
+use reth_primitives::{rpc::transaction::eip2930::AccessListItem, Address, Bytes, H256, U256, U64};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Call request
 #[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -29,3 +30,43 @@ pub struct CallRequest {
     #[serde(rename = "type")]
     pub transaction_type: Option<U256>,
 }
+
+/// A set of account overrides that are applied to the state before a call, keyed by the address of
+/// the account.
+pub type StateOverride = HashMap<Address, AccountOverride>;
+
+/// Custom account state for the duration of a call.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccountOverride {
+    /// Fake balance to set for the account.
+    pub balance: Option<U256>,
+    /// Fake nonce to set for the account.
+    pub nonce: Option<U64>,
+    /// Fake code to set for the account.
+    pub code: Option<Bytes>,
+    /// Fake storage that replaces the entire storage of the account.
+    pub state: Option<HashMap<H256, H256>>,
+    /// Fake storage slots that are set on top of the storage of the account.
+    pub state_diff: Option<HashMap<H256, H256>>,
+}
+
+/// Custom values of the block environment for the duration of a call.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct BlockOverrides {
+    /// Fake block number.
+    pub number: Option<U256>,
+    /// Fake difficulty.
+    pub difficulty: Option<U256>,
+    /// Fake block timestamp.
+    pub time: Option<U64>,
+    /// Fake block gas limit.
+    pub gas_limit: Option<U64>,
+    /// Fake block beneficiary.
+    pub coinbase: Option<Address>,
+    /// Fake `PREVRANDAO` value.
+    pub random: Option<H256>,
+    /// Fake base fee.
+    pub base_fee: Option<U256>,
+}
@@ -0,0 +1,39 @@
+use reth_primitives::{Bytes, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A page of the account state dump returned by `debug_accountRange`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountRange {
+    /// State root of the block the accounts were dumped at.
+    pub root: H256,
+    /// The accounts of this page, keyed by the hash of their address.
+    pub accounts: BTreeMap<H256, DumpAccount>,
+    /// Hashed address to continue the dump from, `None` if this is the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<H256>,
+}
+
+/// A single account of a state dump.
+///
+/// Note: Address preimages are not tracked by the hashed state, so the address of the account is
+/// not included.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpAccount {
+    /// Account balance.
+    pub balance: U256,
+    /// Account nonce.
+    pub nonce: u64,
+    /// Hash of the account's code.
+    pub code_hash: H256,
+    /// The account's code, omitted if requested or the account has no code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// The account's storage by hashed key, omitted if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<H256, U256>>,
+    /// Hash of the account's address.
+    pub key: H256,
+}
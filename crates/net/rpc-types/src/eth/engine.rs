@@ -28,7 +28,15 @@ pub struct ExecutionPayload {
     /// Array of [`Withdrawal`] enabled with V2
     /// See <https://github.com/ethereum/execution-apis/blob/6709c2a795b707202e93c4f2867fa0bf2640a84f/src/engine/shanghai.md#executionpayloadv2>
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub withdrawal: Option<Withdrawal>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
+    /// The total blob gas used by the transactions, enabled with V3
+    /// See <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#executionpayloadv3>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<U64>,
+    /// The excess blob gas of the block, enabled with V3
+    /// See <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#executionpayloadv3>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excess_blob_gas: Option<U64>,
 }
 
 /// This structure maps onto the validator withdrawal object from the beacon chain spec.
@@ -64,9 +72,13 @@ pub struct PayloadAttributes {
     pub prev_randao: H256,
     pub suggested_fee_recipient: Address,
     /// Array of [`Withdrawal`] enabled with V2
-    /// See <https://github.com/ethereum/execution-apis/blob/6709c2a795b707202e93c4f2867fa0bf2640a84f/src/engine/shanghai.md#executionpayloadv2>
+    /// See <https://github.com/ethereum/execution-apis/blob/6709c2a795b707202e93c4f2867fa0bf2640a84f/src/engine/shanghai.md#payloadattributesv2>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+    /// Root of the parent beacon block, enabled with V3
+    /// See <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#payloadattributesv3>
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub withdrawal: Option<Withdrawal>, // TODO: should be a vec
+    pub parent_beacon_block_root: Option<H256>,
 }
 
 /// This structure contains the result of processing a payload
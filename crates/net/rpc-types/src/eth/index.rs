@@ -14,6 +14,12 @@ impl From<Index> for usize {
     }
 }
 
+impl From<usize> for Index {
+    fn from(idx: usize) -> Self {
+        Index(idx)
+    }
+}
+
 impl Serialize for Index {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
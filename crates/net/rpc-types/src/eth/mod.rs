@@ -3,6 +3,7 @@
 mod account;
 mod block;
 mod call;
+mod dump;
 pub mod engine;
 mod fee;
 mod filter;
@@ -16,7 +17,8 @@ mod work;
 
 pub use account::*;
 pub use block::*;
-pub use call::CallRequest;
+pub use call::{AccountOverride, BlockOverrides, CallRequest, StateOverride};
+pub use dump::{AccountRange, DumpAccount};
 pub use fee::FeeHistory;
 pub use filter::*;
 pub use index::Index;
@@ -7,7 +7,9 @@ pub use request::TransactionRequest;
 pub use typed::*;
 
 use reth_primitives::{
-    rpc::transaction::eip2930::AccessListItem, Address, Bytes, H256, H512, U256, U64,
+    rpc::transaction::eip2930::AccessListItem, Address, Bytes, Header,
+    Transaction as PrimitiveTransaction, TransactionKind, TransactionSignedEcRecovered, TxEip1559,
+    TxEip2930, TxLegacy, H256, H512, U256, U64,
 };
 use serde::{Deserialize, Serialize};
 
@@ -67,3 +69,113 @@ pub struct Transaction {
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub transaction_type: Option<U256>,
 }
+
+impl Transaction {
+    /// Creates the object of a transaction that is not included in a block yet.
+    ///
+    /// The gas price of EIP-1559 transactions is their max fee per gas. The created contract, the
+    /// raw encoding and the public key of the signer are not included.
+    pub fn from_recovered(tx: TransactionSignedEcRecovered) -> Self {
+        let from = tx.signer();
+        let signed = tx.into_signed();
+        let signature = signed.signature();
+        let odd_y_parity = signature.odd_y_parity as u64;
+
+        let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match &signed.transaction {
+            PrimitiveTransaction::Legacy(TxLegacy { gas_price, .. }) |
+            PrimitiveTransaction::Eip2930(TxEip2930 { gas_price, .. }) => (*gas_price, None, None),
+            PrimitiveTransaction::Eip1559(TxEip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                ..
+            }) => (
+                *max_fee_per_gas,
+                Some(U256::from(*max_fee_per_gas)),
+                Some(U256::from(*max_priority_fee_per_gas)),
+            ),
+        };
+        let (chain_id, v, access_list) = match &signed.transaction {
+            PrimitiveTransaction::Legacy(TxLegacy { chain_id: Some(chain_id), .. }) => {
+                (Some(*chain_id), odd_y_parity + 35 + 2 * chain_id, None)
+            }
+            PrimitiveTransaction::Legacy(TxLegacy { chain_id: None, .. }) => {
+                (None, odd_y_parity + 27, None)
+            }
+            PrimitiveTransaction::Eip2930(TxEip2930 { chain_id, access_list, .. }) |
+            PrimitiveTransaction::Eip1559(TxEip1559 { chain_id, access_list, .. }) => {
+                let access_list = access_list
+                    .0
+                    .iter()
+                    .map(|item| AccessListItem {
+                        address: item.address,
+                        storage_keys: item.storage_keys.clone(),
+                    })
+                    .collect();
+                (Some(*chain_id), odd_y_parity, Some(access_list))
+            }
+        };
+        let to = match signed.kind() {
+            TransactionKind::Call(to) => Some(*to),
+            TransactionKind::Create => None,
+        };
+
+        Self {
+            hash: signed.hash,
+            nonce: U256::from(signed.nonce()),
+            block_hash: None,
+            block_number: None,
+            transaction_index: None,
+            from,
+            to,
+            value: U256::from(*signed.value()),
+            gas_price: Some(U256::from(gas_price)),
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            gas: U256::from(signed.gas_limit()),
+            input: signed.input().clone(),
+            creates: None,
+            raw: Bytes::default(),
+            public_key: None,
+            chain_id: chain_id.map(U64::from),
+            standard_v: U256::from(odd_y_parity),
+            v: U256::from(v),
+            r: signature.r,
+            s: signature.s,
+            access_list,
+            transaction_type: Some(U256::from(signed.tx_type().ty())),
+        }
+    }
+
+    /// Creates the object of the transaction at the given index of the block with the given
+    /// header and hash.
+    ///
+    /// The gas price of EIP-1559 transactions is the price they paid given the base fee of the
+    /// block.
+    pub fn from_block_transaction(
+        tx: TransactionSignedEcRecovered,
+        header: &Header,
+        block_hash: H256,
+        index: usize,
+    ) -> Self {
+        let gas_price = effective_gas_price(&tx, header.base_fee_per_gas);
+        Self {
+            block_hash: Some(block_hash),
+            block_number: Some(U256::from(header.number)),
+            transaction_index: Some(U256::from(index)),
+            gas_price: Some(U256::from(gas_price)),
+            ..Self::from_recovered(tx)
+        }
+    }
+}
+
+/// Returns the price per gas the transaction pays in a block with the given base fee.
+///
+/// Transactions that can't pay the base fee are never included, their price is the max fee.
+pub fn effective_gas_price(tx: &PrimitiveTransaction, base_fee: Option<u64>) -> u128 {
+    match base_fee {
+        Some(base_fee) => tx
+            .effective_tip_per_gas(base_fee)
+            .map_or(tx.max_fee_per_gas(), |tip| tip + base_fee as u128),
+        None => tx.max_fee_per_gas(),
+    }
+}
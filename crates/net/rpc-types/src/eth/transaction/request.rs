@@ -1,8 +1,11 @@
-use crate::eth::transaction::typed::{
-    EIP1559TransactionRequest, EIP2930TransactionRequest, LegacyTransactionRequest,
-    TransactionKind, TypedTransactionRequest,
+use crate::{
+    eth::transaction::typed::{
+        EIP1559TransactionRequest, EIP2930TransactionRequest, LegacyTransactionRequest,
+        TransactionKind, TypedTransactionRequest,
+    },
+    CallRequest,
 };
-use reth_primitives::{AccessList, Address, Bytes, U256};
+use reth_primitives::{rpc::transaction::eip2930, AccessList, Address, Bytes, U256};
 use serde::{Deserialize, Serialize};
 
 /// Represents _all_ transaction requests received from RPC
@@ -42,6 +45,32 @@ pub struct TransactionRequest {
 // == impl TransactionRequest ==
 
 impl TransactionRequest {
+    /// Converts the request into the [`CallRequest`] of a call with the same fields.
+    pub fn into_call_request(self) -> CallRequest {
+        CallRequest {
+            from: self.from,
+            to: self.to,
+            gas_price: self.gas_price,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            gas: self.gas,
+            value: self.value,
+            data: self.data,
+            nonce: self.nonce,
+            access_list: self.access_list.map(|access_list| {
+                access_list
+                    .0
+                    .into_iter()
+                    .map(|item| eip2930::AccessListItem {
+                        address: item.address,
+                        storage_keys: item.storage_keys,
+                    })
+                    .collect()
+            }),
+            transaction_type: self.transaction_type,
+        }
+    }
+
     /// Converts the request into a [`TypedTransactionRequest`]
     ///
     /// Returns None if mutual exclusive fields `gasPrice` and `max_fee_per_gas` are either missing
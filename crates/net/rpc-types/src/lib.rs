@@ -9,6 +9,10 @@
 //!
 //! Provides all relevant types for the various RPC endpoints, grouped by namespace.
 
+mod admin;
 mod eth;
+mod txpool;
 
+pub use admin::*;
 pub use eth::*;
+pub use txpool::*;
@@ -0,0 +1,91 @@
+use crate::Transaction;
+use reth_primitives::{Address, U256, U64};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// The number of transactions in the pool, as returned by `txpool_status`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxpoolStatus {
+    /// The number of transactions that are ready to be executed.
+    pub pending: U64,
+    /// The number of transactions that are waiting for a nonce gap to be filled, a higher balance
+    /// or a lower base fee.
+    pub queued: U64,
+}
+
+/// All transactions of the pool, grouped by sender and nonce, as returned by `txpool_content`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxpoolContent {
+    /// The transactions that are ready to be executed.
+    pub pending: BTreeMap<Address, BTreeMap<String, Transaction>>,
+    /// The transactions that can't be executed yet.
+    pub queued: BTreeMap<Address, BTreeMap<String, Transaction>>,
+}
+
+/// Summaries of all transactions of the pool, grouped by sender and nonce, as returned by
+/// `txpool_inspect`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxpoolInspect {
+    /// The transactions that are ready to be executed.
+    pub pending: BTreeMap<Address, BTreeMap<String, TxpoolInspectSummary>>,
+    /// The transactions that can't be executed yet.
+    pub queued: BTreeMap<Address, BTreeMap<String, TxpoolInspectSummary>>,
+}
+
+/// The summary of a pooled transaction.
+///
+/// This is serialized as a single string like
+/// `0x0000000000000000000000000000000000000001: 10 wei + 21000 gas × 2 wei`, which names
+/// `contract creation` instead of the recipient if the transaction creates a contract.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TxpoolInspectSummary {
+    /// The recipient, `None` for contract creations.
+    pub to: Option<Address>,
+    /// The transferred value in wei.
+    pub value: U256,
+    /// The gas limit.
+    pub gas: U256,
+    /// The gas price in wei, the max fee per gas for EIP-1559 transactions.
+    pub gas_price: U256,
+}
+
+impl fmt::Display for TxpoolInspectSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to {
+            Some(to) => write!(f, "{to:?}")?,
+            None => f.write_str("contract creation")?,
+        }
+        write!(f, ": {} wei + {} gas × {} wei", self.value, self.gas, self.gas_price)
+    }
+}
+
+impl FromStr for TxpoolInspectSummary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid transaction summary: {s}");
+        let (to, fees) = s.split_once(": ").ok_or_else(invalid)?;
+        let to = match to {
+            "contract creation" => None,
+            to => Some(to.parse().map_err(|_| invalid())?),
+        };
+        let (value, fees) = fees.split_once(" wei + ").ok_or_else(invalid)?;
+        let (gas, gas_price) = fees.split_once(" gas × ").ok_or_else(invalid)?;
+        let gas_price = gas_price.strip_suffix(" wei").ok_or_else(invalid)?;
+        let parse = |value: &str| U256::from_dec_str(value).map_err(|_| invalid());
+
+        Ok(Self { to, value: parse(value)?, gas: parse(gas)?, gas_price: parse(gas_price)? })
+    }
+}
+
+impl Serialize for TxpoolInspectSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TxpoolInspectSummary {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,213 @@
+//! Provides everything related to `admin_` namespace
+
+use crate::result::{internal_rpc_err, rpc_err};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use jsonrpsee::{
+    core::RpcResult as Result,
+    types::{error::INVALID_PARAMS_CODE, SubscriptionResult},
+    SubscriptionSink,
+};
+use reth_network::{NetworkEvent, NetworkHandle, NodeRecord};
+use reth_primitives::{Bytes, IntoRecoveredTransaction, H256};
+use reth_rpc_api::AdminApiServer;
+use reth_rpc_types::{
+    NodeInfo, PeerEvent, PeerEventKind, PeerInfo, PeerNetworkInfo, PeerReputation, Ports,
+    TxPoolFeeStats,
+};
+use reth_tasks::TaskExecutor;
+use reth_transaction_pool::{backup, PoolFeeStats, TransactionPool};
+
+/// `admin` API implementation.
+///
+/// This type provides the functionality for handling `admin_` related requests.
+pub struct AdminApi<Pool> {
+    /// An interface to interact with the network
+    network: NetworkHandle,
+    /// The transaction pool of the node.
+    pool: Pool,
+    /// The executor of the tasks that serve the subscriptions.
+    executor: TaskExecutor,
+}
+
+impl<Pool> AdminApi<Pool> {
+    /// Creates a new instance of `AdminApi`.
+    pub fn new(network: NetworkHandle, pool: Pool, executor: TaskExecutor) -> Self {
+        Self { network, pool, executor }
+    }
+}
+
+impl<Pool> std::fmt::Debug for AdminApi<Pool> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminApi").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<Pool> AdminApiServer for AdminApi<Pool>
+where
+    Pool: TransactionPool + 'static,
+    Pool::Transaction: IntoRecoveredTransaction,
+{
+    async fn add_peer(&self, record: String) -> Result<bool> {
+        let record = parse_node_record(&record)?;
+        self.network.peers_handle().add_peer(record.id, record.tcp_addr());
+        Ok(true)
+    }
+
+    async fn add_trusted_peer(&self, record: String) -> Result<bool> {
+        let record = parse_node_record(&record)?;
+        self.network.peers_handle().add_trusted_peer(record.id, record.tcp_addr());
+        Ok(true)
+    }
+
+    async fn remove_peer(&self, record: String) -> Result<bool> {
+        let record = parse_node_record(&record)?;
+        self.network.peers_handle().remove_peer(record.id);
+        Ok(true)
+    }
+
+    /// Note: The client version and capabilities of the peers are not tracked by the peer set and
+    /// are left empty.
+    async fn peers(&self) -> Result<Vec<PeerInfo>> {
+        let local_address = self.network.local_addr().to_string();
+        let peers = self.network.peers_handle().all_peers().await;
+        Ok(peers
+            .into_iter()
+            .filter(|(_, peer)| peer.is_connected())
+            .map(|(id, peer)| PeerInfo {
+                id: Some(hex::encode(id.as_bytes())),
+                network: PeerNetworkInfo {
+                    remote_address: peer.addr().to_string(),
+                    local_address: local_address.clone(),
+                },
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn node_info(&self) -> Result<NodeInfo> {
+        let info =
+            self.network.node_info().await.map_err(|err| internal_rpc_err(err.to_string()))?;
+        Ok(NodeInfo {
+            id: hex::encode(info.record.id.as_bytes()),
+            name: info.client_version,
+            enode: info.record.to_string(),
+            enr: info.enr.to_base64(),
+            ip: info.record.address,
+            listen_addr: info.listener_addr,
+            ports: Ports { discovery: info.record.udp_port, listener: info.record.tcp_port },
+            protocols: info.capabilities.iter().map(ToString::to_string).collect(),
+        })
+    }
+
+    async fn peer_reputations(&self) -> Result<Vec<PeerReputation>> {
+        let peers = self.network.peers_handle().all_peers().await;
+        Ok(peers
+            .into_iter()
+            .map(|(id, peer)| PeerReputation {
+                id,
+                address: peer.addr(),
+                reputation: peer.reputation(),
+                banned: peer.is_banned(),
+                num_bans: peer.num_bans(),
+                connected: peer.is_connected(),
+            })
+            .collect())
+    }
+
+    async fn export_transactions(&self) -> Result<Bytes> {
+        let transactions = backup::export_transactions(&self.pool);
+        Ok(backup::encode_transactions(&transactions).into())
+    }
+
+    async fn import_transactions(&self, transactions: Bytes) -> Result<Vec<H256>> {
+        let transactions = backup::decode_transactions(&transactions)
+            .map_err(|err| rpc_err(INVALID_PARAMS_CODE, err.to_string(), None))?;
+        let added = backup::import_transactions(&self.pool, transactions)
+            .await
+            .map_err(|err| internal_rpc_err(err.to_string()))?;
+        Ok(added.into_iter().filter_map(|res| res.ok()).collect())
+    }
+
+    async fn pool_fee_stats(&self) -> Result<TxPoolFeeStats> {
+        let PoolFeeStats { min_fee, congested_blocks, included_percentile, pending_percentile } =
+            self.pool.fee_stats();
+        Ok(TxPoolFeeStats {
+            min_fee,
+            congested_blocks: congested_blocks as u64,
+            included_percentile,
+            pending_percentile,
+        })
+    }
+
+    fn subscribe(&self, mut sink: SubscriptionSink) -> SubscriptionResult {
+        // listen before accepting, so no event is missed
+        let events = self.network.event_listener();
+        sink.accept()?;
+        self.executor.spawn("admin subscription", handle_peer_events(sink, events));
+        Ok(())
+    }
+}
+
+/// Sends the session events of the network to an accepted `admin_peerEvents` subscription until
+/// the subscriber unsubscribes or the network stops.
+async fn handle_peer_events(
+    mut accepted_sink: SubscriptionSink,
+    mut events: impl Stream<Item = NetworkEvent> + Unpin,
+) {
+    while let Some(event) = events.next().await {
+        let Some(event) = peer_event(event) else { continue };
+        if !matches!(accepted_sink.send(&event), Ok(true)) {
+            return
+        }
+    }
+}
+
+/// Converts a network event into the event of a subscription, `None` if it's not about a session.
+fn peer_event(event: NetworkEvent) -> Option<PeerEvent> {
+    match event {
+        NetworkEvent::SessionEstablished { peer_id, .. } => {
+            Some(PeerEvent { kind: PeerEventKind::Add, peer: peer_id, error: None })
+        }
+        NetworkEvent::SessionClosed { peer_id, reason } => Some(PeerEvent {
+            kind: PeerEventKind::Drop,
+            peer: peer_id,
+            error: reason.map(|reason| reason.to_string()),
+        }),
+        NetworkEvent::PeerAdded(_) | NetworkEvent::PeerRemoved(_) => None,
+    }
+}
+
+/// Parses the enode URL of a node.
+fn parse_node_record(record: &str) -> Result<NodeRecord> {
+    record.parse::<NodeRecord>().map_err(|err| rpc_err(INVALID_PARAMS_CODE, err.to_string(), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_eth_wire::DisconnectReason;
+    use reth_primitives::PeerId;
+
+    #[test]
+    fn peer_events_of_sessions() {
+        let peer_id = PeerId::random();
+        let closed =
+            NetworkEvent::SessionClosed { peer_id, reason: Some(DisconnectReason::TooManyPeers) };
+        assert_eq!(
+            peer_event(closed),
+            Some(PeerEvent {
+                kind: PeerEventKind::Drop,
+                peer: peer_id,
+                error: Some(DisconnectReason::TooManyPeers.to_string()),
+            })
+        );
+        let closed = NetworkEvent::SessionClosed { peer_id, reason: None };
+        assert_eq!(peer_event(closed).and_then(|event| event.error), None);
+
+        // changes of the peer set are not sessions
+        assert_eq!(peer_event(NetworkEvent::PeerAdded(peer_id)), None);
+        assert_eq!(peer_event(NetworkEvent::PeerRemoved(peer_id)), None);
+    }
+}
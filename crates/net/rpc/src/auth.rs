@@ -0,0 +1,285 @@
+//! JWT authentication of the Engine API.
+//!
+//! The consensus client authenticates every request with a bearer token signed with a shared
+//! secret, see the [spec](https://github.com/ethereum/execution-apis/blob/main/src/engine/authentication.md).
+
+use futures::future::{self, Either, Ready};
+use hyper::{header::AUTHORIZATION, Body, HeaderMap, Request, Response, StatusCode};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tower::{Layer, Service};
+
+/// The length of a JWT secret in bytes.
+pub const JWT_SECRET_LEN: usize = 32;
+
+/// The maximum difference between the issuance time of a token and the local time.
+pub const JWT_MAX_IAT_DIFF: Duration = Duration::from_secs(60);
+
+/// Errors of the JWT authentication.
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    /// The secret has the wrong length.
+    #[error("JWT secret must be {JWT_SECRET_LEN} bytes, got {0} bytes")]
+    InvalidLength(usize),
+    /// The secret is not hex encoded.
+    #[error("JWT secret is not hex encoded: {0}")]
+    InvalidHex(#[from] hex::FromHexError),
+    /// The secret file could not be read or written.
+    #[error("Could not access the JWT secret file {path:?}: {err}")]
+    Io {
+        /// The path of the secret file.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        err: std::io::Error,
+    },
+    /// The request has no bearer token.
+    #[error("Missing bearer token in the Authorization header")]
+    MissingToken,
+    /// The token is malformed, expired or not signed with the secret.
+    #[error("Invalid JWT: {0}")]
+    InvalidToken(#[from] jsonwebtoken::errors::Error),
+    /// The token was not issued within [JWT_MAX_IAT_DIFF] of the local time.
+    #[error("JWT issued at {iat} is more than {}s off the local time", JWT_MAX_IAT_DIFF.as_secs())]
+    StaleToken {
+        /// The issuance time of the token.
+        iat: u64,
+    },
+}
+
+/// The claims of a JWT.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    /// The issuance time in seconds since the unix epoch.
+    pub iat: u64,
+    /// The optional expiry time in seconds since the unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<u64>,
+}
+
+// === impl Claims ===
+
+impl Claims {
+    /// Returns the claims of a token that is issued now and doesn't expire.
+    pub fn now() -> Self {
+        Self { iat: unix_now(), exp: None }
+    }
+}
+
+/// The secret shared with the consensus client that signs the tokens.
+#[derive(Clone)]
+pub struct JwtSecret([u8; JWT_SECRET_LEN]);
+
+// === impl JwtSecret ===
+
+impl JwtSecret {
+    /// Returns a new random secret.
+    pub fn random() -> Self {
+        Self(rand::thread_rng().gen())
+    }
+
+    /// Parses a hex encoded secret, with or without `0x` prefix.
+    pub fn from_hex(hex: impl AsRef<str>) -> Result<Self, JwtError> {
+        let hex = hex.as_ref().trim();
+        let bytes = hex::decode(hex.strip_prefix("0x").unwrap_or(hex))?;
+        let secret =
+            bytes.try_into().map_err(|bytes: Vec<u8>| JwtError::InvalidLength(bytes.len()))?;
+        Ok(Self(secret))
+    }
+
+    /// Reads the hex encoded secret from the file at the given path.
+    pub fn from_file(path: &Path) -> Result<Self, JwtError> {
+        let hex = std::fs::read_to_string(path)
+            .map_err(|err| JwtError::Io { path: path.to_path_buf(), err })?;
+        Self::from_hex(hex)
+    }
+
+    /// Reads the secret from the file at the given path, or writes a new random secret to it if
+    /// the file doesn't exist.
+    ///
+    /// On unix, a new file is only readable and writable by its owner.
+    pub fn load_or_create(path: &Path) -> Result<Self, JwtError> {
+        if path.exists() {
+            return Self::from_file(path)
+        }
+        let secret = Self::random();
+        let io_err = |err| JwtError::Io { path: path.to_path_buf(), err };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        write_secret_file(path, hex::encode(secret.0).as_bytes()).map_err(io_err)?;
+        Ok(secret)
+    }
+
+    /// Returns a token with the given claims signed with the secret.
+    pub fn encode(&self, claims: &Claims) -> Result<String, JwtError> {
+        let key = EncodingKey::from_secret(&self.0);
+        Ok(jsonwebtoken::encode(&Header::new(Algorithm::HS256), claims, &key)?)
+    }
+
+    /// Validates that the token is signed with the secret, hasn't expired and was issued within
+    /// [JWT_MAX_IAT_DIFF] of the local time.
+    pub fn validate(&self, token: &str) -> Result<(), JwtError> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        // the expiry is optional, the issuance time is required by the claims type
+        validation.required_spec_claims.clear();
+        let key = DecodingKey::from_secret(&self.0);
+        let claims = jsonwebtoken::decode::<Claims>(token, &key, &validation)?.claims;
+        if unix_now().abs_diff(claims.iat) > JWT_MAX_IAT_DIFF.as_secs() {
+            return Err(JwtError::StaleToken { iat: claims.iat })
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for JwtSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never print the secret
+        f.write_str("JwtSecret(..)")
+    }
+}
+
+/// A [Layer] that rejects HTTP and WebSocket requests without a valid token with
+/// `401 Unauthorized`.
+#[derive(Debug, Clone)]
+pub struct AuthLayer {
+    secret: JwtSecret,
+}
+
+// === impl AuthLayer ===
+
+impl AuthLayer {
+    /// Creates a new layer that validates the tokens with the given secret.
+    pub fn new(secret: JwtSecret) -> Self {
+        Self { secret }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService { secret: self.secret.clone(), inner }
+    }
+}
+
+/// The [Service] of the [AuthLayer].
+#[derive(Debug, Clone)]
+pub struct AuthService<S> {
+    secret: JwtSecret,
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for AuthService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Response<Body>, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let authorized = bearer_token(req.headers())
+            .ok_or(JwtError::MissingToken)
+            .and_then(|token| self.secret.validate(token));
+        match authorized {
+            Ok(()) => Either::Left(self.inner.call(req)),
+            Err(err) => {
+                let response = Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .body(Body::from(err.to_string()))
+                    .expect("valid response");
+                Either::Right(future::ok(response))
+            }
+        }
+    }
+}
+
+/// Returns the token of the `Authorization: Bearer <token>` header.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ")
+}
+
+/// Creates the file with the given contents, without permissions for other users on unix.
+fn write_secret_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents)
+}
+
+/// Returns the seconds since the unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("time after the unix epoch").as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_secret() {
+        let hex = "f79ae8046bc11c9927afe911db7143c51a806c4a537cc08e0d37140b0192f430";
+        assert!(JwtSecret::from_hex(hex).is_ok());
+        assert!(JwtSecret::from_hex(format!("0x{hex}\n")).is_ok());
+        assert!(matches!(JwtSecret::from_hex(&hex[2..]), Err(JwtError::InvalidLength(31))));
+        assert!(matches!(JwtSecret::from_hex("0xzz"), Err(JwtError::InvalidHex(_))));
+    }
+
+    #[test]
+    fn validate_tokens() {
+        let secret = JwtSecret::random();
+        let token = secret.encode(&Claims::now()).unwrap();
+        assert!(secret.validate(&token).is_ok());
+
+        // signed with another secret
+        let other = JwtSecret::random().encode(&Claims::now()).unwrap();
+        assert!(matches!(secret.validate(&other), Err(JwtError::InvalidToken(_))));
+
+        // issued too long ago
+        let iat = unix_now() - JWT_MAX_IAT_DIFF.as_secs() - 10;
+        let stale = secret.encode(&Claims { iat, exp: None }).unwrap();
+        assert!(matches!(secret.validate(&stale), Err(JwtError::StaleToken { .. })));
+
+        // expired
+        let expired = secret.encode(&Claims { exp: Some(unix_now() - 3600), ..Claims::now() });
+        assert!(matches!(secret.validate(&expired.unwrap()), Err(JwtError::InvalidToken(_))));
+    }
+
+    #[test]
+    fn create_secret_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jwt.hex");
+        let secret = JwtSecret::load_or_create(&path).unwrap();
+        assert_eq!(JwtSecret::load_or_create(&path).unwrap().0, secret.0);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn extract_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert(AUTHORIZATION, "Basic abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert(AUTHORIZATION, "Bearer abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("abc"));
+    }
+}
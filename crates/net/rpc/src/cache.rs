@@ -0,0 +1,296 @@
+//! Response cache for queries whose result can no longer change.
+//!
+//! Blocks, receipts and traces of finalized blocks are immutable, so the response to a request for
+//! them is the same every time it is made. Explorer-style clients request the same historical data
+//! over and over, and serving these repeated requests from memory avoids hitting the database.
+
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use reth_primitives::BlockNumber;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+
+/// The default maximum size of all cached responses in bytes.
+pub const DEFAULT_MAX_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// A cache of serialized responses, keyed by method and params.
+///
+/// Responses are only cached if the block they belong to is finalized, see
+/// [ResponseCache::set_finalized]. If the cached responses exceed the size limit, the least
+/// recently used ones are evicted.
+///
+/// Clones share the cached responses, so one cache serves all namespaces and servers.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    /// The maximum size of all cached responses in bytes.
+    max_bytes: usize,
+    inner: Arc<Mutex<CacheInner>>,
+}
+
+// === impl ResponseCache ===
+
+impl ResponseCache {
+    /// Creates a new cache that holds at most `max_bytes` of serialized responses.
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes, inner: Default::default() }
+    }
+
+    /// Returns the number of cached responses.
+    pub fn len(&self) -> usize {
+        self.inner.lock().entries.len()
+    }
+
+    /// Returns `true` if no response is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the size of all cached responses in bytes.
+    pub fn size(&self) -> usize {
+        self.inner.lock().size
+    }
+
+    /// Returns the last finalized block.
+    pub fn finalized(&self) -> Option<BlockNumber> {
+        self.inner.lock().finalized
+    }
+
+    /// Sets the last finalized block.
+    ///
+    /// Finality only moves backwards if the chain was unwound, e.g. with a database tool. In that
+    /// case all responses of blocks that are no longer finalized are removed.
+    pub fn set_finalized(&self, finalized: Option<BlockNumber>) {
+        let mut inner = self.inner.lock();
+        if finalized < inner.finalized {
+            inner.remove_above(finalized);
+        }
+        inner.finalized = finalized;
+    }
+
+    /// Returns the cached response to the call of `method` with `params`.
+    ///
+    /// A hit marks the response as the most recently used one.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: &impl Serialize,
+    ) -> Option<T> {
+        let key = CacheKey::new(method, params)?;
+        let mut inner = self.inner.lock();
+        let entry = inner.entries.get_refresh(&key)?;
+        serde_json::from_str(&entry.response).ok()
+    }
+
+    /// Returns the cached response to the call of `method` with `params`, or computes it with `f`
+    /// and caches it.
+    ///
+    /// `f` returns the response together with the number of the block it belongs to. The response
+    /// is only cached if that block is finalized, so `params` must refer to blocks by hash or
+    /// number and never by a tag like `latest`.
+    pub fn get_or_insert_with<P, T, E, F>(
+        &self,
+        method: &'static str,
+        params: &P,
+        f: F,
+    ) -> Result<Option<T>, E>
+    where
+        P: Serialize,
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Result<Option<(BlockNumber, T)>, E>,
+    {
+        if let Some(response) = self.get(method, params) {
+            return Ok(Some(response))
+        }
+        let Some((block, response)) = f()? else { return Ok(None) };
+        self.insert(method, params, block, &response);
+        Ok(Some(response))
+    }
+
+    /// Caches the response to the call of `method` with `params`, which belongs to the given
+    /// block.
+    ///
+    /// Returns `false` if the response was not cached, because the block is not finalized or the
+    /// response alone exceeds the size limit.
+    pub fn insert<T: Serialize>(
+        &self,
+        method: &'static str,
+        params: &impl Serialize,
+        block: BlockNumber,
+        response: &T,
+    ) -> bool {
+        let mut inner = self.inner.lock();
+        if inner.finalized.map_or(true, |finalized| block > finalized) {
+            return false
+        }
+        let (key, response) = match (CacheKey::new(method, params), serde_json::to_string(response))
+        {
+            (Some(key), Ok(response)) => (key, response),
+            _ => return false,
+        };
+        let entry = CacheEntry { block, response };
+        if key.size() + entry.size() > self.max_bytes {
+            return false
+        }
+
+        inner.size += key.size() + entry.size();
+        if let Some(old) = inner.entries.insert(key.clone(), entry) {
+            inner.size -= key.size() + old.size();
+        }
+        while inner.size > self.max_bytes {
+            match inner.entries.pop_front() {
+                Some((key, entry)) => inner.size -= key.size() + entry.size(),
+                None => break,
+            }
+        }
+        true
+    }
+
+    /// Removes all cached responses.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.entries.clear();
+        inner.size = 0;
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CACHE_BYTES)
+    }
+}
+
+/// The cached responses, the least recently used one first.
+#[derive(Debug, Default)]
+struct CacheInner {
+    entries: LinkedHashMap<CacheKey, CacheEntry>,
+    /// The size of all keys and responses in bytes.
+    size: usize,
+    /// The last finalized block, responses of later blocks are not cached.
+    finalized: Option<BlockNumber>,
+}
+
+impl CacheInner {
+    /// Removes the responses of all blocks after the given one.
+    fn remove_above(&mut self, block: Option<BlockNumber>) {
+        let stale = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| block.map_or(true, |block| entry.block > block))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in stale {
+            if let Some(entry) = self.entries.remove(&key) {
+                self.size -= key.size() + entry.size();
+            }
+        }
+    }
+}
+
+/// The method and the serialized params of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    method: &'static str,
+    params: String,
+}
+
+impl CacheKey {
+    fn new(method: &'static str, params: &impl Serialize) -> Option<Self> {
+        Some(Self { method, params: serde_json::to_string(params).ok()? })
+    }
+
+    fn size(&self) -> usize {
+        self.method.len() + self.params.len()
+    }
+}
+
+/// A serialized response and the block it belongs to.
+#[derive(Debug)]
+struct CacheEntry {
+    block: BlockNumber,
+    response: String,
+}
+
+impl CacheEntry {
+    fn size(&self) -> usize {
+        self.response.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::H256;
+
+    const METHOD: &str = "eth_getBlockByHash";
+
+    #[test]
+    fn only_caches_finalized_blocks() {
+        let cache = ResponseCache::default();
+        let params = (H256::zero(), false);
+        assert!(!cache.insert(METHOD, &params, 0, &"block"));
+
+        cache.set_finalized(Some(10));
+        assert!(!cache.insert(METHOD, &params, 11, &"block"));
+        assert!(cache.insert(METHOD, &params, 10, &"block"));
+        assert_eq!(cache.get::<String>(METHOD, &params).as_deref(), Some("block"));
+
+        assert_eq!(cache.get::<String>(METHOD, &(H256::zero(), true)), None);
+        assert_eq!(cache.get::<String>("eth_getBlockByNumber", &params), None);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let key_size = CacheKey::new(METHOD, &0u64).unwrap().size();
+        // `"response"` is 10 bytes long
+        let cache = ResponseCache::new(2 * (key_size + 10));
+        cache.set_finalized(Some(10));
+
+        assert!(cache.insert(METHOD, &0u64, 0, &"response"));
+        assert!(cache.insert(METHOD, &1u64, 1, &"response"));
+        assert_eq!(cache.size(), 2 * (key_size + 10));
+
+        // touch the first response, so the second one is evicted
+        assert!(cache.get::<String>(METHOD, &0u64).is_some());
+        assert!(cache.insert(METHOD, &2u64, 2, &"response"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get::<String>(METHOD, &0u64).is_some());
+        assert!(cache.get::<String>(METHOD, &1u64).is_none());
+        assert!(cache.get::<String>(METHOD, &2u64).is_some());
+
+        // replacing a response does not change the size
+        assert!(cache.insert(METHOD, &2u64, 2, &"response"));
+        assert_eq!(cache.size(), 2 * (key_size + 10));
+
+        // a response that does not fit at all is rejected
+        assert!(!cache.insert(METHOD, &3u64, 3, &"response".repeat(10)));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn invalidates_unwound_blocks() {
+        let cache = ResponseCache::default();
+        cache.set_finalized(Some(10));
+        for block in 0..=10u64 {
+            assert!(cache.insert(METHOD, &block, block, &block));
+        }
+
+        // finality moving forward keeps everything
+        cache.set_finalized(Some(20));
+        assert_eq!(cache.len(), 11);
+
+        cache.set_finalized(Some(5));
+        assert_eq!(cache.len(), 6);
+        assert_eq!(cache.get::<u64>(METHOD, &5u64), Some(5));
+        assert_eq!(cache.get::<u64>(METHOD, &6u64), None);
+
+        cache.set_finalized(None);
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+
+        // clones share the responses
+        let clone = cache.clone();
+        clone.set_finalized(Some(1));
+        assert!(clone.insert(METHOD, &1u64, 1, &1u64));
+        assert_eq!(cache.get::<u64>(METHOD, &1u64), Some(1));
+    }
+}
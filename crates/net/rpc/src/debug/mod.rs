@@ -0,0 +1,239 @@
+//! Provides everything related to `debug_` namespace
+
+use crate::result::{rpc_err, state_rpc_err, ToRpcResult};
+use async_trait::async_trait;
+use jsonrpsee::{core::RpcResult as Result, types::error::INVALID_PARAMS_CODE};
+use reth_primitives::{
+    rpc::{BlockId, Bytes},
+    H256, KECCAK_EMPTY, U256,
+};
+use reth_provider::{
+    BlockProvider, HeaderProvider, HistoricalRangeProvider, ReceiptProvider, StateProvider,
+    StateProviderFactory, TransactionsProvider,
+};
+use reth_rlp::Encodable;
+use reth_rpc_api::DebugApiServer;
+use reth_rpc_types::{AccountRange, DumpAccount, RichBlock};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// The maximum number of accounts returned by a single `debug_accountRange` request.
+pub const MAX_ACCOUNT_RANGE_RESULTS: usize = 256;
+
+/// The number of slots read at once when the storage of an account is dumped.
+const STORAGE_PAGE_SIZE: usize = 1024;
+
+/// `debug` API implementation.
+///
+/// This type provides the functionality for handling `debug_` related requests.
+pub struct DebugApi<Client> {
+    /// The client that can interact with the chain.
+    client: Arc<Client>,
+}
+
+impl<Client> DebugApi<Client>
+where
+    Client: BlockProvider
+        + HeaderProvider
+        + ReceiptProvider
+        + TransactionsProvider
+        + StateProviderFactory
+        + HistoricalRangeProvider
+        + 'static,
+{
+    /// Creates a new instance of the `debug` API.
+    pub fn new(client: Arc<Client>) -> Self {
+        Self { client }
+    }
+
+    /// Returns the canonical number of the block with the given id.
+    fn block_number(&self, block_id: BlockId) -> Result<u64> {
+        self.client
+            .block_number_for_id(block_id)
+            .with_message("failed to resolve block")?
+            .ok_or_else(|| unknown_block(block_id))
+    }
+
+    /// Returns a page of the hashed account state at the given block.
+    ///
+    /// The storage of the accounts is included unless `nostorage` is set. The addresses of the
+    /// accounts are not known, so only incomplete dumps keyed by hashed address can be created and
+    /// `incompletes` must be set.
+    fn account_range_at(
+        &self,
+        block_id: BlockId,
+        start: H256,
+        max_results: usize,
+        nocode: bool,
+        nostorage: bool,
+        incompletes: bool,
+    ) -> Result<AccountRange> {
+        if !incompletes {
+            return Err(rpc_err(
+                INVALID_PARAMS_CODE,
+                "address preimages are not stored, only incomplete dumps are available",
+                None,
+            ))
+        }
+
+        let number = self.block_number(block_id)?;
+        let root = self
+            .client
+            .header_by_number(number)
+            .with_message("failed to read header")?
+            .map(|header| header.state_root)
+            .unwrap_or_default();
+
+        let page = self
+            .client
+            .account_range_at(number, start, max_results.min(MAX_ACCOUNT_RANGE_RESULTS))
+            .map_err(state_rpc_err)?;
+
+        // bytecodes are keyed by their hash, so they are the same in every state
+        let state = self.client.latest().with_message("failed to open state")?;
+        let mut accounts = BTreeMap::new();
+        for (key, account) in page.accounts {
+            let code = match account.bytecode_hash {
+                Some(code_hash) if !nocode => {
+                    state.bytecode_by_hash(code_hash).with_message("failed to read bytecode")?
+                }
+                _ => None,
+            };
+            let storage = if nostorage { None } else { Some(self.storage_at(number, key)?) };
+            accounts.insert(
+                key,
+                DumpAccount {
+                    balance: account.balance,
+                    nonce: account.nonce,
+                    code_hash: account.bytecode_hash.unwrap_or(KECCAK_EMPTY),
+                    code,
+                    storage,
+                    key,
+                },
+            );
+        }
+
+        Ok(AccountRange { root, accounts, next: page.next })
+    }
+
+    /// Returns all slots of the account with the hashed address at the given block.
+    fn storage_at(&self, number: u64, hashed_address: H256) -> Result<BTreeMap<H256, U256>> {
+        let mut storage = BTreeMap::new();
+        let mut start = H256::zero();
+        loop {
+            let page = self
+                .client
+                .storage_range_at(number, hashed_address, start, STORAGE_PAGE_SIZE)
+                .map_err(state_rpc_err)?;
+            storage.extend(page.slots);
+            match page.next {
+                Some(next) => start = next,
+                None => return Ok(storage),
+            }
+        }
+    }
+}
+
+impl<Client> std::fmt::Debug for DebugApi<Client> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DebugApi").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<Client> DebugApiServer for DebugApi<Client>
+where
+    Client: BlockProvider
+        + HeaderProvider
+        + ReceiptProvider
+        + TransactionsProvider
+        + StateProviderFactory
+        + HistoricalRangeProvider
+        + 'static,
+{
+    async fn raw_header(&self, block_id: BlockId) -> Result<Bytes> {
+        let number = self.block_number(block_id)?;
+        let header = self
+            .client
+            .header_by_number(number)
+            .with_message("failed to read header")?
+            .ok_or_else(|| unknown_block(block_id))?;
+        Ok(rlp_bytes(&header))
+    }
+
+    async fn raw_block(&self, block_id: BlockId) -> Result<Bytes> {
+        let block = self
+            .client
+            .block(block_id)
+            .with_message("failed to read block")?
+            .ok_or_else(|| unknown_block(block_id))?;
+        Ok(rlp_bytes(&block))
+    }
+
+    async fn raw_transaction(&self, hash: H256) -> Result<Bytes> {
+        let unknown =
+            || rpc_err(INVALID_PARAMS_CODE, format!("unknown transaction {hash:?}"), None);
+        let (number, index) = self
+            .client
+            .transaction_block(hash)
+            .with_message("failed to locate transaction")?
+            .ok_or_else(unknown)?;
+        let block = self
+            .client
+            .block(block_id(number))
+            .with_message("failed to read block")?
+            .ok_or_else(unknown)?;
+        let tx = block.body.get(index).ok_or_else(unknown)?;
+        Ok(tx.envelope_encoded().to_vec().into())
+    }
+
+    async fn raw_receipts(&self, block_id: BlockId) -> Result<Vec<Bytes>> {
+        let receipts = self
+            .client
+            .receipts_by_block(block_id)
+            .with_message("failed to read receipts")?
+            .ok_or_else(|| unknown_block(block_id))?;
+        Ok(receipts
+            .iter()
+            .map(|receipt| {
+                // typed receipts are the type followed by the payload, without a string header
+                let mut out = Vec::new();
+                receipt.encode_inner(&mut out, false);
+                out.into()
+            })
+            .collect())
+    }
+
+    /// Note: Invalid blocks are not kept, so there are never any bad blocks.
+    async fn bad_blocks(&self) -> Result<Vec<RichBlock>> {
+        Ok(Vec::new())
+    }
+
+    async fn account_range(
+        &self,
+        block_id: BlockId,
+        start: H256,
+        max_results: u64,
+        nocode: bool,
+        nostorage: bool,
+        incompletes: bool,
+    ) -> Result<AccountRange> {
+        self.account_range_at(block_id, start, max_results as usize, nocode, nostorage, incompletes)
+    }
+}
+
+/// Returns the id of the block with the given number.
+fn block_id(number: u64) -> BlockId {
+    BlockId::Number(reth_primitives::rpc::BlockNumber::Number(number.into()))
+}
+
+/// Returns the RLP encoding of the value.
+fn rlp_bytes(value: &impl Encodable) -> Bytes {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out.into()
+}
+
+/// Constructs the error of a block that is not in the canonical chain.
+fn unknown_block(block_id: BlockId) -> jsonrpsee::core::Error {
+    rpc_err(INVALID_PARAMS_CODE, format!("unknown block {block_id:?}"), None)
+}
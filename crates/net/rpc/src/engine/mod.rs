@@ -1,9 +1,12 @@
 use crate::result::rpc_err;
 use async_trait::async_trait;
 use jsonrpsee::core::{Error, RpcResult as Result};
-use reth_consensus::engine::{EngineApiError, EngineApiResult, EngineMessage};
+use reth_consensus::{
+    engine::{EngineApiError, EngineApiResult, EngineMessage},
+    Config,
+};
 use reth_interfaces::consensus::ForkchoiceState;
-use reth_primitives::H64;
+use reth_primitives::{H256, H64};
 use reth_rpc_api::EngineApiServer;
 use reth_rpc_types::engine::{
     ExecutionPayload, ForkchoiceUpdated, PayloadAttributes, PayloadStatus, TransitionConfiguration,
@@ -13,10 +16,40 @@ use tokio::sync::{
     oneshot::{self, Receiver},
 };
 
+/// The Engine API methods supported by the node, returned by `engine_exchangeCapabilities`.
+pub const CAPABILITIES: &[&str] = &[
+    "engine_newPayloadV1",
+    "engine_newPayloadV2",
+    "engine_newPayloadV3",
+    "engine_forkchoiceUpdatedV1",
+    "engine_forkchoiceUpdatedV2",
+    "engine_forkchoiceUpdatedV3",
+    "engine_getPayloadV1",
+    "engine_getPayloadV2",
+    "engine_getPayloadV3",
+    "engine_exchangeTransitionConfigurationV1",
+];
+
+/// The error code for a method version that doesn't support the fork of the payload.
+const UNSUPPORTED_FORK_CODE: i32 = -38005;
+
+/// The version of an Engine API method and the payload structures it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EngineApiMessageVersion {
+    /// Paris structures.
+    V1,
+    /// Shanghai structures, adding withdrawals.
+    V2,
+    /// Cancun structures, adding the blob gas fields and the parent beacon block root.
+    V3,
+}
+
 /// The server implementation of Engine API
 pub struct EngineApi {
     /// Handle to the consensus engine
     engine_tx: UnboundedSender<EngineMessage>,
+    /// The chain configuration, used to determine the fork of a payload.
+    chain: Config,
 }
 
 impl std::fmt::Debug for EngineApi {
@@ -25,36 +58,175 @@ impl std::fmt::Debug for EngineApi {
     }
 }
 
+// === impl EngineApi ===
+
 impl EngineApi {
+    /// Creates a new Engine API server that forwards the requests to the consensus engine.
+    pub fn new(engine_tx: UnboundedSender<EngineMessage>, chain: Config) -> Self {
+        Self { engine_tx, chain }
+    }
+
+    /// Validates that the method version supports the fork active at the timestamp and that the
+    /// fork specific fields are present if and only if the fork is active.
+    ///
+    /// Every method version has to be called for its own fork, except for V2 which also accepts
+    /// the structures of V1 before Shanghai.
+    fn validate_version(
+        &self,
+        version: EngineApiMessageVersion,
+        timestamp: u64,
+        has_withdrawals: bool,
+        cancun_fields: &[bool],
+    ) -> EngineApiResult<()> {
+        let shanghai = self.chain.is_shanghai_active_at_timestamp(timestamp);
+        let cancun = self.chain.is_cancun_active_at_timestamp(timestamp);
+        let supported = match version {
+            EngineApiMessageVersion::V1 => !shanghai,
+            EngineApiMessageVersion::V2 => !cancun,
+            EngineApiMessageVersion::V3 => cancun,
+        };
+        if !supported {
+            return Err(EngineApiError::UnsupportedFork)
+        }
+
+        match (shanghai, has_withdrawals) {
+            (true, false) => return Err(EngineApiError::WithdrawalsMissing),
+            (false, true) => return Err(EngineApiError::UnexpectedWithdrawals),
+            _ => {}
+        }
+        if cancun && !cancun_fields.iter().all(|present| *present) {
+            return Err(EngineApiError::CancunFieldsMissing)
+        }
+        if !cancun && cancun_fields.iter().any(|present| *present) {
+            return Err(EngineApiError::UnexpectedCancunFields)
+        }
+        Ok(())
+    }
+
+    /// Validates the version specific fields of a payload.
+    fn validate_payload(
+        &self,
+        version: EngineApiMessageVersion,
+        payload: &ExecutionPayload,
+    ) -> EngineApiResult<()> {
+        self.validate_version(
+            version,
+            payload.timestamp.as_u64(),
+            payload.withdrawals.is_some(),
+            &[payload.blob_gas_used.is_some(), payload.excess_blob_gas.is_some()],
+        )
+    }
+
+    /// Validates the version specific fields of the payload attributes, if any.
+    fn validate_attributes(
+        &self,
+        version: EngineApiMessageVersion,
+        attributes: Option<&PayloadAttributes>,
+    ) -> EngineApiResult<()> {
+        let Some(attributes) = attributes else { return Ok(()) };
+        self.validate_version(
+            version,
+            attributes.timestamp.as_u64(),
+            attributes.withdrawals.is_some(),
+            &[attributes.parent_beacon_block_root.is_some()],
+        )
+    }
+
+    async fn new_payload(
+        &self,
+        version: EngineApiMessageVersion,
+        payload: ExecutionPayload,
+    ) -> Result<PayloadStatus> {
+        self.validate_payload(version, &payload).map_err(to_rpc_err)?;
+        let (tx, rx) = oneshot::channel();
+        self.delegate_request(EngineMessage::NewPayload(payload, tx), rx).await
+    }
+
+    async fn fork_choice_updated(
+        &self,
+        version: EngineApiMessageVersion,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated> {
+        self.validate_attributes(version, payload_attributes.as_ref()).map_err(to_rpc_err)?;
+        let (tx, rx) = oneshot::channel();
+        self.delegate_request(
+            EngineMessage::ForkchoiceUpdated(fork_choice_state, payload_attributes, tx),
+            rx,
+        )
+        .await
+    }
+
+    /// Returns the built payload, if the method version supports its fork.
+    async fn get_payload(
+        &self,
+        version: EngineApiMessageVersion,
+        payload_id: H64,
+    ) -> Result<ExecutionPayload> {
+        let (tx, rx) = oneshot::channel();
+        let payload = self.delegate_request(EngineMessage::GetPayload(payload_id, tx), rx).await?;
+        self.validate_payload(version, &payload).map_err(to_rpc_err)?;
+        Ok(payload)
+    }
+
     async fn delegate_request<T>(
         &self,
         msg: EngineMessage,
         rx: Receiver<EngineApiResult<T>>,
     ) -> Result<T> {
         let _ = self.engine_tx.send(msg);
-        rx.await.map_err(|err| Error::Custom(err.to_string()))?.map_err(|err| {
-            let code = match err {
-                EngineApiError::PayloadUnknown => -38001,
-                // Any other server error
-                _ => jsonrpsee::types::error::INTERNAL_ERROR_CODE,
-            };
-            rpc_err(code, err.to_string(), None)
-        })
+        rx.await.map_err(|err| Error::Custom(err.to_string()))?.map_err(to_rpc_err)
     }
 }
 
+/// Converts the error of the consensus engine into an RPC error with the Engine API error code.
+fn to_rpc_err(err: EngineApiError) -> Error {
+    let code = match err {
+        EngineApiError::PayloadUnknown => -38001,
+        EngineApiError::UnsupportedFork => UNSUPPORTED_FORK_CODE,
+        EngineApiError::WithdrawalsMissing |
+        EngineApiError::UnexpectedWithdrawals |
+        EngineApiError::CancunFieldsMissing |
+        EngineApiError::UnexpectedCancunFields |
+        EngineApiError::BlobVersionedHashes { .. } => jsonrpsee::types::error::INVALID_PARAMS_CODE,
+        // Any other server error
+        _ => jsonrpsee::types::error::INTERNAL_ERROR_CODE,
+    };
+    rpc_err(code, err.to_string(), None)
+}
+
 #[async_trait]
 impl EngineApiServer for EngineApi {
     /// See also <https://github.com/ethereum/execution-apis/blob/8db51dcd2f4bdfbd9ad6e4a7560aac97010ad063/src/engine/specification.md#engine_newpayloadv1>
     /// Caution: This should not accept the `withdrawals` field
     async fn new_payload_v1(&self, payload: ExecutionPayload) -> Result<PayloadStatus> {
-        let (tx, rx) = oneshot::channel();
-        self.delegate_request(EngineMessage::NewPayload(payload, tx), rx).await
+        self.new_payload(EngineApiMessageVersion::V1, payload).await
     }
 
-    /// See also <https://github.com/ethereum/execution-apis/blob/8db51dcd2f4bdfbd9ad6e4a7560aac97010ad063/src/engine/specification.md#engine_newpayloadv1>
-    async fn new_payload_v2(&self, _payload: ExecutionPayload) -> Result<PayloadStatus> {
-        todo!()
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/shanghai.md#engine_newpayloadv2>
+    async fn new_payload_v2(&self, payload: ExecutionPayload) -> Result<PayloadStatus> {
+        self.new_payload(EngineApiMessageVersion::V2, payload).await
+    }
+
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#engine_newpayloadv3>
+    ///
+    /// Blob transactions aren't supported yet, so a payload never carries blob versioned hashes.
+    /// The parent beacon block root isn't part of the header yet and is not validated.
+    async fn new_payload_v3(
+        &self,
+        payload: ExecutionPayload,
+        versioned_hashes: Vec<H256>,
+        _parent_beacon_block_root: H256,
+    ) -> Result<PayloadStatus> {
+        self.validate_payload(EngineApiMessageVersion::V3, &payload).map_err(to_rpc_err)?;
+        if !versioned_hashes.is_empty() {
+            return Err(to_rpc_err(EngineApiError::BlobVersionedHashes {
+                expected: 0,
+                got: versioned_hashes.len(),
+            }))
+        }
+        let (tx, rx) = oneshot::channel();
+        self.delegate_request(EngineMessage::NewPayload(payload, tx), rx).await
     }
 
     /// See also <https://github.com/ethereum/execution-apis/blob/8db51dcd2f4bdfbd9ad6e4a7560aac97010ad063/src/engine/specification.md#engine_forkchoiceUpdatedV1>
@@ -65,34 +237,45 @@ impl EngineApiServer for EngineApi {
         fork_choice_state: ForkchoiceState,
         payload_attributes: Option<PayloadAttributes>,
     ) -> Result<ForkchoiceUpdated> {
-        let (tx, rx) = oneshot::channel();
-        self.delegate_request(
-            EngineMessage::ForkchoiceUpdated(fork_choice_state, payload_attributes, tx),
-            rx,
-        )
-        .await
+        self.fork_choice_updated(EngineApiMessageVersion::V1, fork_choice_state, payload_attributes)
+            .await
     }
 
     /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/specification.md#engine_forkchoiceupdatedv2>
     async fn fork_choice_updated_v2(
         &self,
-        _fork_choice_state: ForkchoiceState,
-        _payload_attributes: Option<PayloadAttributes>,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<PayloadAttributes>,
+    ) -> Result<ForkchoiceUpdated> {
+        self.fork_choice_updated(EngineApiMessageVersion::V2, fork_choice_state, payload_attributes)
+            .await
+    }
+
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#engine_forkchoiceupdatedv3>
+    async fn fork_choice_updated_v3(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<PayloadAttributes>,
     ) -> Result<ForkchoiceUpdated> {
-        todo!()
+        self.fork_choice_updated(EngineApiMessageVersion::V3, fork_choice_state, payload_attributes)
+            .await
     }
 
     /// See also <https://github.com/ethereum/execution-apis/blob/8db51dcd2f4bdfbd9ad6e4a7560aac97010ad063/src/engine/specification.md#engine_getPayloadV1>
     ///
     /// Caution: This should not return the `withdrawals` field
     async fn get_payload_v1(&self, payload_id: H64) -> Result<ExecutionPayload> {
-        let (tx, rx) = oneshot::channel();
-        self.delegate_request(EngineMessage::GetPayload(payload_id, tx), rx).await
+        self.get_payload(EngineApiMessageVersion::V1, payload_id).await
     }
 
     /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/specification.md#engine_getpayloadv2>
-    async fn get_payload_v2(&self, _payload_id: H64) -> Result<ExecutionPayload> {
-        todo!()
+    async fn get_payload_v2(&self, payload_id: H64) -> Result<ExecutionPayload> {
+        self.get_payload(EngineApiMessageVersion::V2, payload_id).await
+    }
+
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/cancun.md#engine_getpayloadv3>
+    async fn get_payload_v3(&self, payload_id: H64) -> Result<ExecutionPayload> {
+        self.get_payload(EngineApiMessageVersion::V3, payload_id).await
     }
 
     /// See also <https://github.com/ethereum/execution-apis/blob/8db51dcd2f4bdfbd9ad6e4a7560aac97010ad063/src/engine/specification.md#engine_exchangeTransitionConfigurationV1>
@@ -103,4 +286,116 @@ impl EngineApiServer for EngineApi {
         let (tx, rx) = oneshot::channel();
         self.delegate_request(EngineMessage::ExchangeTransitionConfiguration(config, tx), rx).await
     }
+
+    /// See also <https://github.com/ethereum/execution-apis/blob/main/src/engine/common.md#engine_exchangecapabilities>
+    async fn exchange_capabilities(&self, _capabilities: Vec<String>) -> Result<Vec<String>> {
+        Ok(CAPABILITIES.iter().map(|method| method.to_string()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_rpc_types::engine::Withdrawal;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    const SHANGHAI: u64 = 100;
+    const CANCUN: u64 = 200;
+
+    fn api() -> EngineApi {
+        let (engine_tx, _) = unbounded_channel();
+        let chain = Config {
+            shanghai_time: Some(SHANGHAI),
+            cancun_time: Some(CANCUN),
+            ..Default::default()
+        };
+        EngineApi::new(engine_tx, chain)
+    }
+
+    fn attributes(timestamp: u64) -> PayloadAttributes {
+        PayloadAttributes {
+            timestamp: timestamp.into(),
+            prev_randao: H256::zero(),
+            suggested_fee_recipient: Default::default(),
+            withdrawals: None,
+            parent_beacon_block_root: None,
+        }
+    }
+
+    #[test]
+    fn enforce_method_version_per_fork() {
+        let api = api();
+        let v1 = EngineApiMessageVersion::V1;
+        let v2 = EngineApiMessageVersion::V2;
+        let v3 = EngineApiMessageVersion::V3;
+
+        // Paris
+        let paris = attributes(SHANGHAI - 1);
+        assert!(api.validate_attributes(v1, Some(&paris)).is_ok());
+        assert!(api.validate_attributes(v2, Some(&paris)).is_ok());
+        assert!(matches!(
+            api.validate_attributes(v3, Some(&paris)),
+            Err(EngineApiError::UnsupportedFork)
+        ));
+
+        // Shanghai
+        let mut shanghai = attributes(SHANGHAI);
+        assert!(matches!(
+            api.validate_attributes(v1, Some(&shanghai)),
+            Err(EngineApiError::UnsupportedFork)
+        ));
+        assert!(matches!(
+            api.validate_attributes(v2, Some(&shanghai)),
+            Err(EngineApiError::WithdrawalsMissing)
+        ));
+        shanghai.withdrawals = Some(vec![Withdrawal::default()]);
+        assert!(api.validate_attributes(v2, Some(&shanghai)).is_ok());
+        shanghai.parent_beacon_block_root = Some(H256::zero());
+        assert!(matches!(
+            api.validate_attributes(v2, Some(&shanghai)),
+            Err(EngineApiError::UnexpectedCancunFields)
+        ));
+
+        // Cancun
+        let mut cancun = attributes(CANCUN);
+        cancun.withdrawals = Some(Vec::new());
+        assert!(matches!(
+            api.validate_attributes(v2, Some(&cancun)),
+            Err(EngineApiError::UnsupportedFork)
+        ));
+        assert!(matches!(
+            api.validate_attributes(v3, Some(&cancun)),
+            Err(EngineApiError::CancunFieldsMissing)
+        ));
+        cancun.parent_beacon_block_root = Some(H256::zero());
+        assert!(api.validate_attributes(v3, Some(&cancun)).is_ok());
+
+        // withdrawals before Shanghai
+        let mut paris = attributes(0);
+        paris.withdrawals = Some(Vec::new());
+        assert!(matches!(
+            api.validate_attributes(v2, Some(&paris)),
+            Err(EngineApiError::UnexpectedWithdrawals)
+        ));
+
+        // without attributes there is nothing to validate
+        assert!(api.validate_attributes(v3, None).is_ok());
+    }
+
+    #[test]
+    fn version_error_codes() {
+        let code = |err| {
+            let Error::Call(jsonrpsee::types::error::CallError::Custom(err)) = to_rpc_err(err)
+            else {
+                panic!("unexpected error")
+            };
+            err.code()
+        };
+        assert_eq!(code(EngineApiError::UnsupportedFork), UNSUPPORTED_FORK_CODE);
+        assert_eq!(
+            code(EngineApiError::WithdrawalsMissing),
+            jsonrpsee::types::error::INVALID_PARAMS_CODE
+        );
+        assert_eq!(code(EngineApiError::PayloadUnknown), -38001);
+    }
 }
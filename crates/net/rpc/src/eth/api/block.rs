@@ -0,0 +1,341 @@
+//! Lookup of the blocks, transactions and receipts of the chain for `eth_getBlockBy*`,
+//! `eth_getTransactionBy*` and `eth_getTransactionReceipt`.
+
+use crate::{
+    eth::api::EthApi,
+    result::{internal_rpc_err, state_rpc_err},
+};
+use reth_primitives::{
+    keccak256,
+    rpc::{self, BlockId},
+    Address, Block, BlockNumber, TransactionKind, H256, U256, U64,
+};
+use reth_provider::{BlockProvider, HeaderProvider, ReceiptProvider, TransactionsProvider};
+use reth_rlp::Encodable;
+use reth_rpc_types::{
+    effective_gas_price, BlockTransactions, Log, Rich, RichBlock, Transaction, TransactionReceipt,
+};
+use reth_transaction_pool::TransactionPool;
+
+/// Errors of reading the blocks, transactions and receipts of the chain.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum BlockError {
+    /// The chain could not be read.
+    #[error(transparent)]
+    State(#[from] reth_interfaces::Error),
+    /// The sender of a transaction of the block could not be recovered.
+    #[error("invalid signature of a transaction of block {0:?}")]
+    InvalidSignature(H256),
+}
+
+/// Converts an error of reading the chain into a JSON-RPC error.
+pub(crate) fn block_rpc_err(err: BlockError) -> jsonrpsee::core::Error {
+    match err {
+        BlockError::State(err) => state_rpc_err(err),
+        err => internal_rpc_err(err.to_string()),
+    }
+}
+
+// === impl EthApi ===
+
+impl<Pool, Client> EthApi<Pool, Client>
+where
+    Pool: TransactionPool + 'static,
+    Client: BlockProvider + HeaderProvider + ReceiptProvider + TransactionsProvider + 'static,
+{
+    /// Returns the hash of the block with the given id along with the block.
+    fn block_with_hash(&self, id: BlockId) -> Result<Option<(H256, Block)>, BlockError> {
+        let client = self.client();
+        let Some(hash) = client.block_hash_for_id(id)? else { return Ok(None) };
+        Ok(client.block(BlockId::Hash(hash))?.map(|block| (hash, block)))
+    }
+
+    /// Returns the block with the given id, with its full transactions or only their hashes.
+    pub(crate) fn block_at(
+        &self,
+        id: BlockId,
+        full: bool,
+    ) -> Result<Option<RichBlock>, BlockError> {
+        let Some((hash, block)) = self.block_with_hash(id)? else { return Ok(None) };
+        let total_difficulty = self.client().header_td(&hash)?.unwrap_or_default();
+        let block = reth_rpc_types::Block::from_block(block, hash, total_difficulty, full)
+            .ok_or(BlockError::InvalidSignature(hash))?;
+        Ok(Some(Rich { inner: block, extra_info: Default::default() }))
+    }
+
+    /// Returns the number of transactions of the block with the given id.
+    pub(crate) fn block_transaction_count(&self, id: BlockId) -> Result<Option<U256>, BlockError> {
+        Ok(self.block_with_hash(id)?.map(|(_, block)| U256::from(block.body.len())))
+    }
+
+    /// Returns the number of ommers of the block with the given id, which is zero for unknown
+    /// blocks.
+    pub(crate) fn block_uncles_count(&self, id: BlockId) -> Result<U256, BlockError> {
+        Ok(self
+            .block_with_hash(id)?
+            .map_or(U256::zero(), |(_, block)| U256::from(block.ommers.len())))
+    }
+
+    /// Returns the ommer at the given index of the block with the given id.
+    ///
+    /// Ommers have no transactions of their own, their size is the size of their header.
+    pub(crate) fn uncle_at(
+        &self,
+        id: BlockId,
+        index: usize,
+    ) -> Result<Option<RichBlock>, BlockError> {
+        let Some((_, block)) = self.block_with_hash(id)? else { return Ok(None) };
+        let Some(ommer) = block.ommers.into_iter().nth(index) else { return Ok(None) };
+        let hash = ommer.hash_slow();
+        let size = Some(U256::from(ommer.length()));
+        let inner = reth_rpc_types::Block {
+            header: reth_rpc_types::Header {
+                size,
+                ..reth_rpc_types::Header::from_primitive(&ommer, hash)
+            },
+            total_difficulty: self.client().header_td(&hash)?.unwrap_or_default(),
+            uncles: Vec::new(),
+            transactions: BlockTransactions::Hashes(Vec::new()),
+            size,
+            base_fee_per_gas: ommer.base_fee_per_gas.map(U256::from),
+        };
+        Ok(Some(Rich { inner, extra_info: Default::default() }))
+    }
+
+    /// Returns the transaction at the given index of the block with the given id.
+    pub(crate) fn transaction_at(
+        &self,
+        id: BlockId,
+        index: usize,
+    ) -> Result<Option<Transaction>, BlockError> {
+        let Some((hash, block)) = self.block_with_hash(id)? else { return Ok(None) };
+        let Block { header, body, .. } = block;
+        let Some(tx) = body.into_iter().nth(index) else { return Ok(None) };
+        let tx = tx.into_ecrecovered().ok_or(BlockError::InvalidSignature(hash))?;
+        Ok(Some(Transaction::from_block_transaction(tx, &header, hash, index)))
+    }
+
+    /// Returns the canonical transaction with the given hash.
+    pub(crate) fn transaction_by_hash_inner(
+        &self,
+        hash: H256,
+    ) -> Result<Option<Transaction>, BlockError> {
+        match self.client().transaction_block(hash)? {
+            Some((number, index)) => self.transaction_at(block_id(number), index),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the receipt of the canonical transaction with the given hash.
+    ///
+    /// The gas used by the transaction and the index of its logs within the block follow from the
+    /// receipts of the transactions before it.
+    pub(crate) fn transaction_receipt_inner(
+        &self,
+        hash: H256,
+    ) -> Result<Option<TransactionReceipt>, BlockError> {
+        let client = self.client();
+        let Some((number, index)) = client.transaction_block(hash)? else { return Ok(None) };
+        let Some((block_hash, block)) = self.block_with_hash(block_id(number))? else {
+            return Ok(None)
+        };
+        let Some(receipts) = client.receipts_by_block(BlockId::Hash(block_hash))? else {
+            return Ok(None)
+        };
+        let (Some(tx), Some(receipt)) = (block.body.get(index), receipts.get(index)) else {
+            return Ok(None)
+        };
+        let from = tx.recover_signer().ok_or(BlockError::InvalidSignature(block_hash))?;
+
+        let previous = &receipts[..index];
+        let gas_used = receipt.cumulative_gas_used -
+            previous.last().map_or(0, |previous| previous.cumulative_gas_used);
+        let first_log_index = previous.iter().map(|previous| previous.logs.len()).sum::<usize>();
+        let (to, contract_address) = match tx.kind() {
+            TransactionKind::Call(to) => (Some(*to), None),
+            TransactionKind::Create => (None, Some(create_address(from, tx.nonce()))),
+        };
+        let logs = receipt
+            .logs
+            .iter()
+            .enumerate()
+            .map(|(tx_log_index, log)| Log {
+                address: log.address,
+                topics: log.topics.clone(),
+                data: log.data.clone(),
+                block_hash: Some(block_hash),
+                block_number: Some(U256::from(number)),
+                transaction_hash: Some(hash),
+                transaction_index: Some(U256::from(index)),
+                log_index: Some(U256::from(first_log_index + tx_log_index)),
+                transaction_log_index: Some(U256::from(tx_log_index)),
+                removed: false,
+            })
+            .collect();
+
+        Ok(Some(TransactionReceipt {
+            transaction_hash: Some(hash),
+            transaction_index: Some(U256::from(index)),
+            block_hash: Some(block_hash),
+            block_number: Some(U256::from(number)),
+            from,
+            to,
+            cumulative_gas_used: U256::from(receipt.cumulative_gas_used),
+            gas_used: Some(U256::from(gas_used)),
+            contract_address,
+            logs,
+            state_root: None,
+            logs_bloom: receipt.bloom,
+            status_code: Some(U64::from(receipt.success as u64)),
+            effective_gas_price: U256::from(effective_gas_price(tx, block.base_fee_per_gas)),
+            transaction_type: U256::from(tx.tx_type().ty()),
+        }))
+    }
+}
+
+/// Returns the number of the block a response with the given block number field belongs to.
+///
+/// Responses without a block number, like those of pending transactions, belong to no block yet
+/// and are attributed to the last possible block, which is never finalized and never cached.
+pub(super) fn response_block(number: Option<U256>) -> BlockNumber {
+    number.map_or(BlockNumber::MAX, |number| number.as_u64())
+}
+
+/// Returns the id of the block with the given number.
+pub(super) fn block_id(number: BlockNumber) -> BlockId {
+    BlockId::Number(rpc::BlockNumber::Number(number.into()))
+}
+
+/// Returns the address of the contract that the sender creates with the given nonce.
+fn create_address(sender: Address, nonce: u64) -> Address {
+    let mut out = Vec::new();
+    reth_rlp::Header { list: true, payload_length: sender.length() + nonce.length() }
+        .encode(&mut out);
+    sender.encode(&mut out);
+    nonce.encode(&mut out);
+    Address::from_slice(&keccak256(out)[12..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        database::Database,
+        mdbx::{test_utils::create_test_rw_db, WriteMap},
+        tables,
+        transaction::DbTxMut,
+    };
+    use reth_interfaces::test_utils::generators::sign_message;
+    use reth_primitives::{
+        hex_literal::hex, Header, Receipt, SealedBlock, TransactionSigned, TxEip1559, TxType,
+    };
+    use reth_provider::{insert_canonical_block, ProviderImpl};
+    use reth_transaction_pool::test_util::testing_pool;
+    use std::sync::Arc;
+
+    #[test]
+    fn create_address_of_sender_and_nonce() {
+        let sender = Address::from(hex!("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0"));
+        assert_eq!(
+            create_address(sender, 0),
+            Address::from(hex!("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"))
+        );
+        assert_eq!(
+            create_address(sender, 1),
+            Address::from(hex!("343c43a37d37dff08ae8c4a11544c718abb4fcf8"))
+        );
+    }
+
+    #[test]
+    fn blocks_transactions_and_receipts() {
+        let db = create_test_rw_db::<WriteMap>();
+        let api = EthApi::new(Arc::new(ProviderImpl::new(db.clone())), testing_pool());
+
+        let transfer = |nonce, to| {
+            let transaction = reth_primitives::Transaction::Eip1559(TxEip1559 {
+                chain_id: 1,
+                nonce,
+                gas_limit: 100_000,
+                max_fee_per_gas: 20,
+                max_priority_fee_per_gas: 2,
+                to,
+                ..Default::default()
+            });
+            let signature =
+                sign_message(H256::repeat_byte(1), transaction.signature_hash()).unwrap();
+            TransactionSigned::from_transaction_and_signature(transaction, signature)
+        };
+        let body = vec![
+            transfer(0, TransactionKind::Call(Address::repeat_byte(2))),
+            transfer(1, TransactionKind::Create),
+        ];
+        let log = reth_primitives::Log {
+            address: Address::repeat_byte(3),
+            topics: vec![H256::repeat_byte(4)],
+            data: Default::default(),
+        };
+        let receipts = [(21_000, vec![log.clone()]), (74_000, vec![log])].map(
+            |(cumulative_gas_used, logs)| Receipt {
+                tx_type: TxType::EIP1559,
+                success: true,
+                cumulative_gas_used,
+                bloom: Default::default(),
+                logs,
+            },
+        );
+        let genesis = Header::default().seal();
+        let header = Header {
+            number: 1,
+            parent_hash: genesis.hash(),
+            base_fee_per_gas: Some(10),
+            ..Default::default()
+        }
+        .seal();
+        let block_hash = header.hash();
+        db.update(|tx| {
+            insert_canonical_block(
+                tx,
+                &SealedBlock { header: genesis, ..Default::default() },
+                false,
+            )
+            .unwrap();
+            let block = SealedBlock { header, body: body.clone(), ommers: vec![] };
+            insert_canonical_block(tx, &block, false).unwrap();
+            for (number, (signed, receipt)) in body.iter().zip(receipts.clone()).enumerate() {
+                tx.put::<tables::TxHashNumber>(signed.hash(), number as u64).unwrap();
+                tx.put::<tables::Receipts>(number as u64, receipt).unwrap();
+            }
+        })
+        .unwrap();
+
+        let block = api.block_at(BlockId::Hash(block_hash), true).unwrap().unwrap();
+        assert_eq!(block.header.hash, Some(block_hash));
+        let BlockTransactions::Full(transactions) = &block.transactions else {
+            panic!("expected full transactions")
+        };
+        assert_eq!(transactions.len(), 2);
+        // the price of EIP-1559 transactions is the base fee plus the tip
+        assert_eq!(transactions[1].gas_price, Some(U256::from(12)));
+        assert_eq!(transactions[1].transaction_index, Some(U256::from(1)));
+        let block = api.block_at(block_id(1), false).unwrap().unwrap();
+        assert_eq!(
+            block.transactions,
+            BlockTransactions::Hashes(body.iter().map(|tx| tx.hash()).collect())
+        );
+        assert_eq!(api.block_transaction_count(block_id(1)).unwrap(), Some(U256::from(2)));
+        assert!(api.block_at(block_id(2), false).unwrap().is_none());
+
+        let tx = api.transaction_by_hash_inner(body[0].hash()).unwrap().unwrap();
+        assert_eq!((tx.block_hash, tx.block_number), (Some(block_hash), Some(U256::from(1))));
+
+        let receipt = api.transaction_receipt_inner(body[1].hash()).unwrap().unwrap();
+        let from = body[1].recover_signer().unwrap();
+        assert_eq!(receipt.from, from);
+        assert_eq!(receipt.gas_used, Some(U256::from(53_000)));
+        assert_eq!(receipt.contract_address, Some(create_address(from, 1)));
+        assert_eq!(receipt.effective_gas_price, U256::from(12));
+        assert_eq!(receipt.logs[0].log_index, Some(U256::from(1)));
+        assert_eq!(receipt.logs[0].transaction_log_index, Some(U256::from(0)));
+        assert!(api.transaction_receipt_inner(H256::repeat_byte(9)).unwrap().is_none());
+    }
+}
@@ -0,0 +1,514 @@
+//! Execution of `eth_call` and `eth_estimateGas` requests on top of the state of a block.
+
+use crate::{
+    eth::api::EthApi,
+    result::{rpc_err, state_rpc_err},
+};
+use reth_executor::revm_wrap::{self, State, SubState};
+use reth_primitives::{
+    rpc::{BlockId, BlockNumber},
+    Address, Bytes, Header, H256, U256,
+};
+use reth_provider::{
+    BlockProvider, HeaderProvider, ReceiptProvider, StateProvider, StateProviderFactory,
+};
+use reth_rpc_types::{BlockOverrides, CallRequest, StateOverride};
+use reth_transaction_pool::TransactionPool;
+use revm::{
+    Bytecode, Database, Env, Inspector, Return, TransactOut, TransactTo, B160, B256, EVM,
+    U256 as evmU256,
+};
+
+/// The default gas limit of calls, see [`CallConfig::gas_cap`].
+pub const DEFAULT_CALL_GAS_CAP: u64 = 50_000_000;
+
+/// The lowest gas limit a transaction can have.
+const MIN_TRANSACTION_GAS: u64 = 21_000;
+
+/// Error code of calls that reverted, the revert data is returned as error data.
+const REVERT_ERROR_CODE: i32 = 3;
+
+/// Error code of calls that could not be executed.
+const EXECUTION_ERROR_CODE: i32 = -32000;
+
+/// Configuration of `eth_call` and `eth_estimateGas`.
+#[derive(Debug, Clone)]
+pub struct CallConfig {
+    /// The configuration of the executor, e.g. the chain id and the fork blocks.
+    pub executor: reth_executor::Config,
+    /// The maximum gas limit of a call and of the gas estimation.
+    ///
+    /// Calls without a gas limit use the gas limit of the block, capped by this value.
+    pub gas_cap: u64,
+}
+
+impl Default for CallConfig {
+    fn default() -> Self {
+        Self { executor: reth_executor::Config::new_ethereum(), gas_cap: DEFAULT_CALL_GAS_CAP }
+    }
+}
+
+/// Errors of executing a call.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CallError {
+    /// The state could not be read.
+    #[error(transparent)]
+    State(#[from] reth_interfaces::Error),
+    /// The requested block is unknown.
+    #[error("unknown block")]
+    UnknownBlock,
+    /// The request sets both, the legacy gas price and the EIP-1559 fees.
+    #[error("both gasPrice and (maxFeePerGas or maxPriorityFeePerGas) specified")]
+    ConflictingFeeFields,
+    /// An account override replaces and patches the storage at the same time.
+    #[error("account {0:?} has both 'state' and 'stateDiff'")]
+    ConflictingStateOverride(Address),
+    /// The sender can't pay for the value of the call.
+    #[error("insufficient funds for transfer")]
+    InsufficientFunds,
+    /// The call runs out of gas with the highest gas limit the sender can pay for.
+    #[error("gas required exceeds allowance ({0})")]
+    GasAllowance(u64),
+    /// The call reverted.
+    #[error("execution reverted")]
+    Revert(Bytes),
+    /// The call halted, e.g. because it ran out of gas.
+    #[error("execution halted: {0:?}")]
+    Halt(Return),
+    /// The state could not be read during execution.
+    #[error("fatal error during execution")]
+    Fatal,
+}
+
+/// Converts an error of executing a call into a JSON-RPC error.
+pub(crate) fn call_rpc_err(err: CallError) -> jsonrpsee::core::Error {
+    match err {
+        CallError::State(err) => state_rpc_err(err),
+        CallError::Revert(ref output) => {
+            rpc_err(REVERT_ERROR_CODE, err.to_string(), Some(output.as_ref()))
+        }
+        CallError::ConflictingFeeFields | CallError::ConflictingStateOverride(_) => {
+            rpc_err(jsonrpsee::types::error::INVALID_PARAMS_CODE, err.to_string(), None)
+        }
+        err => rpc_err(EXECUTION_ERROR_CODE, err.to_string(), None),
+    }
+}
+
+// === impl EthApi ===
+
+impl<Pool, Client> EthApi<Pool, Client>
+where
+    Pool: TransactionPool + 'static,
+    Client: BlockProvider + HeaderProvider + ReceiptProvider + StateProviderFactory + 'static,
+{
+    /// Executes the request on top of the state of the block and returns the output of the call.
+    pub(crate) fn call_at(
+        &self,
+        request: CallRequest,
+        at: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<&BlockOverrides>,
+    ) -> Result<Bytes, CallError> {
+        let header = self.call_header(at)?;
+        let env = call_env(self.call_config(), &request, &header, block_overrides)?;
+        self.with_state_at(at, |state| {
+            let mut db = SubState::new(State::new(state));
+            Ok(apply_state_overrides(&mut db, state_overrides).and_then(|_| call(env, db)))
+        })?
+    }
+
+    /// Returns the lowest gas limit with which the request doesn't fail on top of the state of the
+    /// block.
+    pub(crate) fn estimate_gas_at(
+        &self,
+        request: CallRequest,
+        at: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
+    ) -> Result<u64, CallError> {
+        let header = self.call_header(at)?;
+        let env = call_env(self.call_config(), &request, &header, None)?;
+        self.with_state_at(at, |state| {
+            let mut db = SubState::new(State::new(state));
+            Ok(apply_state_overrides(&mut db, state_overrides).and_then(|_| estimate_gas(env, db)))
+        })?
+    }
+
+    /// Returns the header whose block environment the calls at the block are executed in.
+    ///
+    /// Like [`with_state_at`](Self::with_state_at), `None` and the `pending` tag refer to the
+    /// latest block.
+    fn call_header(&self, at: Option<BlockId>) -> Result<Header, CallError> {
+        let client = self.client();
+        let number = match at {
+            None | Some(BlockId::Number(BlockNumber::Pending)) => client.chain_info()?.best_number,
+            Some(id) => client.block_number_for_id(id)?.ok_or(CallError::UnknownBlock)?,
+        };
+        client.header_by_number(number)?.ok_or(CallError::UnknownBlock)
+    }
+}
+
+/// Prepares the environment of executing the request on top of the header.
+///
+/// Calls without a gas price don't pay for gas, so the base fee is ignored for them.
+fn call_env(
+    config: &CallConfig,
+    request: &CallRequest,
+    header: &Header,
+    block_overrides: Option<&BlockOverrides>,
+) -> Result<Env, CallError> {
+    let mut env = Env::default();
+    env.cfg.chain_id = evmU256::from_limbs(config.executor.chain_id.0);
+    env.cfg.spec_id = config.executor.spec_upgrades.revm_spec(header.number);
+    env.cfg.perf_all_precompiles_have_balance = false;
+
+    revm_wrap::fill_block_env(&mut env.block, header);
+    if let Some(overrides) = block_overrides {
+        apply_block_overrides(&mut env, overrides);
+    }
+
+    let (gas_price, priority_fee) =
+        match (request.gas_price, request.max_fee_per_gas, request.max_priority_fee_per_gas) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                return Err(CallError::ConflictingFeeFields)
+            }
+            (Some(gas_price), None, None) => (gas_price, None),
+            (None, max_fee, priority_fee) => {
+                (max_fee.or(priority_fee).unwrap_or_default(), priority_fee)
+            }
+        };
+    if gas_price.is_zero() {
+        env.block.basefee = evmU256::ZERO;
+    }
+
+    let tx = &mut env.tx;
+    tx.caller = B160(request.from.unwrap_or_default().0);
+    tx.transact_to = match request.to {
+        Some(to) => TransactTo::Call(B160(to.0)),
+        None => TransactTo::create(),
+    };
+    tx.gas_limit = match request.gas {
+        Some(gas) if gas < U256::from(config.gas_cap) => gas.as_u64(),
+        Some(_) => config.gas_cap,
+        None => header.gas_limit.min(config.gas_cap),
+    };
+    tx.gas_price = evmU256::from_limbs(gas_price.0);
+    tx.gas_priority_fee = priority_fee.map(|fee| evmU256::from_limbs(fee.0));
+    tx.value = evmU256::from_limbs(request.value.unwrap_or_default().0);
+    tx.data = request.data.clone().unwrap_or_default().0;
+    tx.nonce = request.nonce.map(|nonce| nonce.low_u64());
+    tx.chain_id = None;
+    tx.access_list = request
+        .access_list
+        .iter()
+        .flatten()
+        .map(|item| {
+            (B160(item.address.0), item.storage_keys.iter().map(|key| to_slot(*key)).collect())
+        })
+        .collect();
+    Ok(env)
+}
+
+/// Replaces the values of the block environment with the overrides.
+fn apply_block_overrides(env: &mut Env, overrides: &BlockOverrides) {
+    let block = &mut env.block;
+    if let Some(number) = overrides.number {
+        block.number = evmU256::from_limbs(number.0);
+    }
+    if let Some(difficulty) = overrides.difficulty {
+        block.difficulty = evmU256::from_limbs(difficulty.0);
+    }
+    if let Some(time) = overrides.time {
+        block.timestamp = evmU256::from(time.as_u64());
+    }
+    if let Some(gas_limit) = overrides.gas_limit {
+        block.gas_limit = evmU256::from(gas_limit.as_u64());
+    }
+    if let Some(coinbase) = overrides.coinbase {
+        block.coinbase = B160(coinbase.0);
+    }
+    if let Some(random) = overrides.random {
+        block.prevrandao = Some(B256(random.0));
+    }
+    if let Some(base_fee) = overrides.base_fee {
+        block.basefee = evmU256::from_limbs(base_fee.0);
+    }
+}
+
+/// Applies the account overrides to the state of the call.
+fn apply_state_overrides<DB: StateProvider>(
+    db: &mut SubState<DB>,
+    overrides: Option<StateOverride>,
+) -> Result<(), CallError> {
+    for (address, account) in overrides.into_iter().flatten() {
+        let address = B160(address.0);
+        let mut info = db.basic(address)?.unwrap_or_default();
+        if let Some(nonce) = account.nonce {
+            info.nonce = nonce.as_u64();
+        }
+        if let Some(balance) = account.balance {
+            info.balance = evmU256::from_limbs(balance.0);
+        }
+        if let Some(code) = account.code {
+            let bytecode = Bytecode::new_raw(code.0);
+            info.code_hash = bytecode.hash();
+            info.code = Some(bytecode);
+        }
+        db.insert_account_info(address, info);
+
+        match (account.state, account.state_diff) {
+            (Some(_), Some(_)) => {
+                return Err(CallError::ConflictingStateOverride(Address::from(address.0)))
+            }
+            (Some(state), None) => db.replace_account_storage(
+                address,
+                state.into_iter().map(|(key, value)| (to_slot(key), to_slot(value))).collect(),
+            )?,
+            (None, Some(diff)) => {
+                for (key, value) in diff {
+                    db.insert_account_storage(address, to_slot(key), to_slot(value))?;
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Executes the request on top of the header with the inspector and returns the output of the
+/// call, even if it failed.
+///
+/// Unlike [`call_at`](EthApi::call_at) the state is passed in, so the caller can trace calls on
+/// top of any state.
+pub(crate) fn inspect_call<DB, I>(
+    config: &CallConfig,
+    request: &CallRequest,
+    header: &Header,
+    db: SubState<DB>,
+    inspector: I,
+) -> Result<Bytes, CallError>
+where
+    DB: StateProvider,
+    I: Inspector<SubState<DB>>,
+{
+    let mut evm = EVM::new();
+    evm.env = call_env(config, request, header, None)?;
+    evm.database(db);
+    let (result, _) = evm.inspect(inspector);
+    if result.exit_reason == Return::FatalExternalError {
+        return Err(CallError::Fatal)
+    }
+    Ok(output(result.out))
+}
+
+/// Executes the call and returns its output.
+fn call<DB: StateProvider>(env: Env, db: SubState<DB>) -> Result<Bytes, CallError> {
+    let mut evm = EVM::new();
+    evm.env = env;
+    evm.database(db);
+    let result = transact(&mut evm)?;
+    match result.exit_reason {
+        revm::return_ok!() => Ok(output(result.out)),
+        revm::return_revert!() => Err(CallError::Revert(output(result.out))),
+        reason => Err(CallError::Halt(reason)),
+    }
+}
+
+/// Estimates the gas limit of the call with a binary search between the gas used by the call and
+/// the highest gas limit the sender can pay for.
+///
+/// The gas used by a call is lower than the gas limit it needs if the call refunds gas or checks
+/// the remaining gas, e.g. to forward it to a sub-call.
+fn estimate_gas<DB: StateProvider>(env: Env, mut db: SubState<DB>) -> Result<u64, CallError> {
+    let mut highest = env.tx.gas_limit.max(MIN_TRANSACTION_GAS);
+    let mut capped = false;
+    if env.tx.gas_price > evmU256::ZERO {
+        let balance = db.basic(env.tx.caller)?.map(|info| info.balance).unwrap_or_default();
+        let available = balance.checked_sub(env.tx.value).ok_or(CallError::InsufficientFunds)?;
+        let allowance = u64::try_from(available / env.tx.gas_price).unwrap_or(u64::MAX);
+        if allowance < highest {
+            highest = allowance;
+            capped = true;
+        }
+    }
+
+    let mut evm = EVM::new();
+    evm.env = env;
+    evm.env.tx.gas_limit = highest;
+    evm.database(db);
+    let result = transact(&mut evm)?;
+    match result.exit_reason {
+        revm::return_ok!() => {}
+        Return::OutOfGas if capped => return Err(CallError::GasAllowance(highest)),
+        revm::return_revert!() => return Err(CallError::Revert(output(result.out))),
+        reason => return Err(CallError::Halt(reason)),
+    }
+
+    // the call needs at least the gas it used
+    let mut lowest = result.gas_used.max(MIN_TRANSACTION_GAS) - 1;
+    while lowest + 1 < highest {
+        let mid = lowest + (highest - lowest) / 2;
+        evm.env.tx.gas_limit = mid;
+        if matches!(transact(&mut evm)?.exit_reason, revm::return_ok!()) {
+            highest = mid;
+        } else {
+            lowest = mid;
+        }
+    }
+    Ok(highest)
+}
+
+/// Executes the transaction of the environment without committing its changes.
+fn transact<DB: StateProvider>(
+    evm: &mut EVM<SubState<DB>>,
+) -> Result<revm::ExecutionResult, CallError> {
+    let (result, _) = evm.transact();
+    if result.exit_reason == Return::FatalExternalError {
+        return Err(CallError::Fatal)
+    }
+    Ok(result)
+}
+
+/// Returns the output of a call or the code of a created contract.
+fn output(out: TransactOut) -> Bytes {
+    match out {
+        TransactOut::None => Bytes::default(),
+        TransactOut::Call(out) | TransactOut::Create(out, _) => out.into(),
+    }
+}
+
+/// Converts a storage key or value to the revm representation.
+fn to_slot(value: H256) -> evmU256 {
+    evmU256::from_be_bytes(value.to_fixed_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_executor::{Config, SpecUpgrades};
+    use reth_primitives::{hex_literal::hex, Account, StorageKey, StorageValue, H160};
+    use reth_provider::AccountProvider;
+    use reth_rpc_types::AccountOverride;
+
+    /// `mstore(0, 42)`, `return(0, 32)`
+    const RETURN_CODE: [u8; 10] = hex!("602a60005260206000f3");
+    /// `mstore(0, 42)`, `revert(0, 32)`
+    const REVERT_CODE: [u8; 10] = hex!("602a60005260206000fd");
+    const CONTRACT: Address = H160([0xcc; 20]);
+
+    #[derive(Debug, Default)]
+    struct EmptyState;
+
+    impl AccountProvider for EmptyState {
+        fn basic_account(&self, _address: Address) -> reth_interfaces::Result<Option<Account>> {
+            Ok(None)
+        }
+    }
+
+    impl StateProvider for EmptyState {
+        fn storage(
+            &self,
+            _account: Address,
+            _storage_key: StorageKey,
+        ) -> reth_interfaces::Result<Option<StorageValue>> {
+            Ok(None)
+        }
+
+        fn bytecode_by_hash(&self, _code_hash: H256) -> reth_interfaces::Result<Option<Bytes>> {
+            Ok(None)
+        }
+
+        fn block_hash(&self, _number: U256) -> reth_interfaces::Result<Option<H256>> {
+            Ok(None)
+        }
+    }
+
+    fn config() -> CallConfig {
+        let executor = Config {
+            chain_id: 1.into(),
+            spec_upgrades: SpecUpgrades::new_paris_activated(),
+            hooks: Default::default(),
+        };
+        CallConfig { executor, gas_cap: 1_000_000 }
+    }
+
+    fn env(request: &CallRequest) -> Env {
+        let header = Header { gas_limit: 30_000_000, ..Default::default() };
+        call_env(&config(), request, &header, None).unwrap()
+    }
+
+    fn state(code: &[u8]) -> SubState<EmptyState> {
+        let mut db = SubState::new(State::new(EmptyState));
+        let overrides = StateOverride::from([(
+            CONTRACT,
+            AccountOverride { code: Some(code.to_vec().into()), ..Default::default() },
+        )]);
+        apply_state_overrides(&mut db, Some(overrides)).unwrap();
+        db
+    }
+
+    fn request(to: Address) -> CallRequest {
+        CallRequest { to: Some(to), ..Default::default() }
+    }
+
+    #[test]
+    fn call_with_code_override() {
+        let out = call(env(&request(CONTRACT)), state(&RETURN_CODE)).unwrap();
+        assert_eq!(out.as_ref(), H256::from_low_u64_be(42).as_bytes());
+
+        let err = call(env(&request(CONTRACT)), state(&REVERT_CODE)).unwrap_err();
+        let CallError::Revert(out) = err else { panic!("unexpected error {err:?}") };
+        assert_eq!(out.as_ref(), H256::from_low_u64_be(42).as_bytes());
+    }
+
+    #[test]
+    fn estimate_lowest_gas_limit() {
+        let transfer = request(Address::zero());
+        assert_eq!(estimate_gas(env(&transfer), state(&[])).unwrap(), MIN_TRANSACTION_GAS);
+
+        let gas = estimate_gas(env(&request(CONTRACT)), state(&RETURN_CODE)).unwrap();
+        assert!(gas > MIN_TRANSACTION_GAS);
+        let with_gas = |gas: u64| CallRequest { gas: Some(gas.into()), ..request(CONTRACT) };
+        assert!(call(env(&with_gas(gas)), state(&RETURN_CODE)).is_ok());
+        assert!(call(env(&with_gas(gas - 1)), state(&RETURN_CODE)).is_err());
+
+        // the sender can't pay for the gas
+        let request = CallRequest { gas_price: Some(1.into()), ..request(CONTRACT) };
+        let err = estimate_gas(env(&request), state(&RETURN_CODE)).unwrap_err();
+        assert!(matches!(err, CallError::GasAllowance(0)), "unexpected error {err:?}");
+    }
+
+    #[test]
+    fn cap_gas_limit() {
+        let request = CallRequest { gas: Some(u64::MAX.into()), ..request(CONTRACT) };
+        assert_eq!(env(&request).tx.gas_limit, 1_000_000);
+        assert_eq!(env(&CallRequest::default()).tx.gas_limit, 1_000_000);
+
+        let request = CallRequest {
+            gas_price: Some(1.into()),
+            max_fee_per_gas: Some(1.into()),
+            ..Default::default()
+        };
+        let header = Header::default();
+        assert!(matches!(
+            call_env(&config(), &request, &header, None),
+            Err(CallError::ConflictingFeeFields)
+        ));
+    }
+
+    #[test]
+    fn reject_conflicting_storage_overrides() {
+        let mut db = SubState::new(State::new(EmptyState));
+        let overrides = StateOverride::from([(
+            CONTRACT,
+            AccountOverride {
+                state: Some(Default::default()),
+                state_diff: Some(Default::default()),
+                ..Default::default()
+            },
+        )]);
+        assert!(matches!(
+            apply_state_overrides(&mut db, Some(overrides)),
+            Err(CallError::ConflictingStateOverride(CONTRACT))
+        ));
+    }
+}
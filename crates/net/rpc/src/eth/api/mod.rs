@@ -1,13 +1,55 @@
 //! Provides everything related to `eth_` namespace
 
+use crate::{
+    cache::ResponseCache,
+    eth::{
+        gas_oracle::{GasPriceOracle, GasPriceOracleConfig},
+        signer::UnlockedAccounts,
+    },
+};
+use parking_lot::Mutex;
 use reth_interfaces::Result;
-use reth_primitives::U64;
-use reth_provider::{BlockProvider, ChainInfo, StateProviderFactory};
-use reth_rpc_types::Transaction;
+use reth_primitives::{
+    proofs::TrieAccount, rpc::BlockId, Address, BigEndianHash, BlockNumber, H256, U64,
+};
+use reth_provider::{
+    BlockProvider, ChainInfo, HeaderProvider, ReceiptProvider, StageCheckpointProvider,
+    StateProofProvider, StateProvider, StateProviderFactory,
+};
+use reth_rpc_types::{EIP1186AccountProofResponse, StorageProof, SyncInfo, SyncStatus};
 use reth_transaction_pool::TransactionPool;
+use serde::{de::DeserializeOwned, Serialize};
 use std::sync::Arc;
 
+mod block;
+mod call;
 mod server;
+mod transactions;
+
+pub(crate) use block::block_rpc_err;
+pub(crate) use call::{call_rpc_err, inspect_call, CallError};
+pub use call::{CallConfig, DEFAULT_CALL_GAS_CAP};
+pub use transactions::TransactionForwarder;
+pub(crate) use transactions::{send_raw_transaction_rpc_err, sign_rpc_err, SignError};
+
+/// The id of the stage that downloads the headers up to the tip, the key of its checkpoint.
+const HEADERS_STAGE: &str = "Headers";
+
+/// The id of the stage that executes the blocks, the key of its checkpoint.
+const EXECUTION_STAGE: &str = "Execution";
+
+/// Settings of the `eth` API.
+#[derive(Debug, Clone, Default)]
+pub struct EthConfig {
+    /// The settings of `eth_call` and `eth_estimateGas`.
+    pub call: CallConfig,
+    /// The settings of `eth_gasPrice`, `eth_maxPriorityFeePerGas` and `eth_feeHistory`.
+    pub gas_oracle: GasPriceOracleConfig,
+    /// Where the transactions of `eth_sendRawTransaction` are forwarded to, if anywhere.
+    pub forwarder: Option<TransactionForwarder>,
+    /// The accounts of `eth_accounts`, `eth_sign` and `eth_signTransaction`.
+    pub accounts: UnlockedAccounts,
+}
 
 /// `Eth` API trait.
 ///
@@ -21,6 +63,9 @@ pub trait EthApiSpec: Send + Sync {
 
     /// Returns client chain info
     fn chain_info(&self) -> Result<ChainInfo>;
+
+    /// Returns the progress of the sync, or [SyncStatus::None] if the node is synced.
+    fn sync_status(&self) -> Result<SyncStatus>;
 }
 
 /// `Eth` API implementation.
@@ -40,11 +85,54 @@ pub struct EthApi<Pool, Client> {
 impl<Pool, Client> EthApi<Pool, Client>
 where
     Pool: TransactionPool + 'static,
-    Client: BlockProvider + StateProviderFactory + 'static,
+    Client: BlockProvider + ReceiptProvider + StateProviderFactory + 'static,
 {
     /// Creates a new, shareable instance.
     pub fn new(client: Arc<Client>, pool: Pool) -> Self {
-        let inner = EthApiInner { client, pool };
+        Self::with_cache(client, pool, ResponseCache::default())
+    }
+
+    /// Creates a new, shareable instance that caches responses of finalized blocks in the given
+    /// cache.
+    pub fn with_cache(client: Arc<Client>, pool: Pool, cache: ResponseCache) -> Self {
+        Self::with_call_config(client, pool, cache, CallConfig::default())
+    }
+
+    /// Creates a new, shareable instance that executes `eth_call` and `eth_estimateGas` with the
+    /// given configuration.
+    pub fn with_call_config(
+        client: Arc<Client>,
+        pool: Pool,
+        cache: ResponseCache,
+        call_config: CallConfig,
+    ) -> Self {
+        Self::with_config(
+            client,
+            pool,
+            cache,
+            EthConfig { call: call_config, ..Default::default() },
+        )
+    }
+
+    /// Creates a new, shareable instance with the given settings.
+    pub fn with_config(
+        client: Arc<Client>,
+        pool: Pool,
+        cache: ResponseCache,
+        config: EthConfig,
+    ) -> Self {
+        let EthConfig { call, gas_oracle, forwarder, accounts } = config;
+        let gas_oracle = GasPriceOracle::new(client.clone(), gas_oracle);
+        let inner = EthApiInner {
+            client,
+            pool,
+            cache,
+            call_config: call,
+            gas_oracle,
+            forwarder,
+            accounts,
+            sync_start: Mutex::new(None),
+        };
         Self { inner: Arc::new(inner) }
     }
 
@@ -52,12 +140,139 @@ where
     fn client(&self) -> &Arc<Client> {
         &self.inner.client
     }
+
+    /// Returns the transaction pool.
+    fn pool(&self) -> &Pool {
+        &self.inner.pool
+    }
+
+    /// Returns the forwarder of raw transactions.
+    fn forwarder(&self) -> Option<&TransactionForwarder> {
+        self.inner.forwarder.as_ref()
+    }
+
+    /// Returns the unlocked accounts.
+    pub(crate) fn accounts(&self) -> &UnlockedAccounts {
+        &self.inner.accounts
+    }
+
+    /// Returns the configuration of calls.
+    fn call_config(&self) -> &CallConfig {
+        &self.inner.call_config
+    }
+
+    /// Returns the gas price oracle.
+    fn gas_oracle(&self) -> &GasPriceOracle<Client> {
+        &self.inner.gas_oracle
+    }
+
+    /// Calls `f` with the state at the given block.
+    ///
+    /// `None` and the `latest` and `pending` tags refer to the latest state. The state of older
+    /// blocks is unavailable if it was pruned, see
+    /// [`HistoryPruned`](reth_provider::Error::HistoryPruned), and blocks of side chains have no
+    /// state, see [`NonCanonicalBlock`](reth_provider::Error::NonCanonicalBlock).
+    pub(crate) fn with_state_at<T>(
+        &self,
+        at: Option<BlockId>,
+        f: impl FnOnce(&dyn StateProvider) -> Result<T>,
+    ) -> Result<T> {
+        let client = self.client();
+        let block = match at {
+            None => None,
+            Some(BlockId::Hash(hash)) => Some(canonical_number(client, hash)?),
+            Some(id) => client.block_number_for_id(id)?,
+        };
+        match block {
+            Some(number) if number != client.chain_info()?.best_number => {
+                f(&client.history_by_block_number(number)?)
+            }
+            _ => f(&client.latest()?),
+        }
+    }
+
+    /// Returns the cached response to the call of `method` with `params`, or computes it with `f`,
+    /// see [`ResponseCache::get_or_insert_with`].
+    pub(crate) fn cached<P, T, E, F>(
+        &self,
+        method: &'static str,
+        params: &P,
+        f: F,
+    ) -> std::result::Result<Option<T>, E>
+    where
+        P: Serialize,
+        T: Serialize + DeserializeOwned,
+        E: From<reth_interfaces::Error>,
+        F: FnOnce() -> std::result::Result<Option<(BlockNumber, T)>, E>,
+    {
+        let cache = &self.inner.cache;
+        cache.set_finalized(self.client().chain_info()?.last_finalized);
+        cache.get_or_insert_with(method, params, f)
+    }
+}
+
+impl<Pool, Client> EthApi<Pool, Client>
+where
+    Pool: TransactionPool + 'static,
+    Client: BlockProvider + HeaderProvider + StateProofProvider + 'static,
+{
+    /// Returns the [EIP-1186](https://eips.ethereum.org/EIPS/eip-1186) proofs of the account and
+    /// of its slots in the state of the given block.
+    ///
+    /// `None` and the `latest` and `pending` tags refer to the latest block. The proofs are
+    /// generated from the hashed state, so only the block whose state root matches it can be
+    /// proven, see [`ProofUnavailable`](reth_provider::Error::ProofUnavailable).
+    pub(crate) fn proof_at(
+        &self,
+        address: Address,
+        keys: &[H256],
+        at: Option<BlockId>,
+    ) -> Result<EIP1186AccountProofResponse> {
+        let client = self.client();
+        let block = match at {
+            Some(BlockId::Hash(hash)) => Some(canonical_number(client, hash)?),
+            Some(id) => client.block_number_for_id(id)?,
+            None => None,
+        };
+        let block_number = match block {
+            Some(number) => number,
+            None => client.chain_info()?.best_number,
+        };
+        let header = client
+            .header_by_number(block_number)?
+            .ok_or(reth_provider::Error::BlockNumber { block_number })?;
+
+        let proof = client.proof(address, keys)?;
+        if proof.state_root != header.state_root {
+            return Err(reth_provider::Error::ProofUnavailable { block_number }.into())
+        }
+
+        let account = TrieAccount::new(proof.account.unwrap_or_default(), proof.storage_root);
+        Ok(EIP1186AccountProofResponse {
+            address,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: account.nonce.into(),
+            storage_hash: account.storage_root,
+            account_proof: proof.proof,
+            storage_proof: proof
+                .storage_proofs
+                .into_iter()
+                .map(|slot| StorageProof {
+                    key: slot.key.into_uint(),
+                    value: slot.value,
+                    proof: slot.proof,
+                })
+                .collect(),
+        })
+    }
 }
 
 impl<Pool, Client> EthApiSpec for EthApi<Pool, Client>
 where
-    Pool: TransactionPool<Transaction = Transaction> + Clone + 'static,
-    Client: BlockProvider + StateProviderFactory + 'static,
+    Pool: TransactionPool + Clone + 'static,
+    Client:
+        BlockProvider + ReceiptProvider + StateProviderFactory + StageCheckpointProvider + 'static,
 {
     /// Returns the current ethereum protocol version.
     ///
@@ -66,15 +281,52 @@ where
         1u64.into()
     }
 
-    /// Returns the chain id
+    /// Returns the chain id of the executor configuration.
     fn chain_id(&self) -> U64 {
-        todo!()
+        self.call_config().executor.chain_id.as_u64().into()
     }
 
     /// Returns the current info for the chain
     fn chain_info(&self) -> Result<ChainInfo> {
         self.client().chain_info()
     }
+
+    /// Returns the progress of the sync from the checkpoints of the pipeline.
+    ///
+    /// The node syncs while the executed state lags behind the downloaded headers. The starting
+    /// block is the executed block when the sync was first observed, it is reset once the node
+    /// caught up.
+    fn sync_status(&self) -> Result<SyncStatus> {
+        let client = self.client();
+        let highest = client.stage_checkpoint(HEADERS_STAGE)?.unwrap_or_default();
+        let current = client.stage_checkpoint(EXECUTION_STAGE)?.unwrap_or_default();
+
+        let mut sync_start = self.inner.sync_start.lock();
+        if current >= highest {
+            *sync_start = None;
+            return Ok(SyncStatus::None)
+        }
+        let starting = *sync_start.get_or_insert(current);
+        Ok(SyncStatus::Info(SyncInfo {
+            starting_block: starting.into(),
+            current_block: current.into(),
+            highest_block: highest.into(),
+            warp_chunks_amount: None,
+            warp_chunks_processed: None,
+        }))
+    }
+}
+
+/// Returns the number of the canonical block with the given hash.
+///
+/// Fails if the block is unknown or part of a side chain.
+fn canonical_number(client: &impl BlockProvider, hash: H256) -> Result<BlockNumber> {
+    let number =
+        client.block_number(hash)?.ok_or(reth_provider::Error::BlockHash { block_hash: hash })?;
+    if !client.is_canonical(hash)? {
+        return Err(reth_provider::Error::NonCanonicalBlock { block_hash: hash }.into())
+    }
+    Ok(number)
 }
 
 /// Container type `EthApi`
@@ -84,5 +336,154 @@ struct EthApiInner<Pool, Client> {
     pool: Pool,
     /// The client that can interact with the chain.
     client: Arc<Client>,
-    // TODO needs network access to handle things like `eth_syncing`
+    /// Responses of finalized blocks.
+    cache: ResponseCache,
+    /// The configuration of `eth_call` and `eth_estimateGas`.
+    call_config: CallConfig,
+    /// Suggests fees based on the recent blocks.
+    gas_oracle: GasPriceOracle<Client>,
+    /// Forwards the transactions of `eth_sendRawTransaction`.
+    forwarder: Option<TransactionForwarder>,
+    /// The accounts that sign on request.
+    accounts: UnlockedAccounts,
+    /// The executed block when the current sync was first observed by `eth_syncing`.
+    sync_start: Mutex<Option<BlockNumber>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        database::Database,
+        mdbx::{test_utils::create_test_rw_db, WriteMap},
+        tables,
+        transaction::DbTxMut,
+    };
+    use reth_primitives::{
+        keccak256,
+        proofs::{calculate_state_root, EMPTY_ROOT},
+        rpc,
+        trie::verify_proof,
+        Account, Header, SealedBlock, KECCAK_EMPTY, U256,
+    };
+    use reth_provider::{insert_canonical_block, AccountProvider, ProviderImpl};
+    use reth_rlp::Encodable;
+    use reth_transaction_pool::test_util::testing_pool;
+
+    #[test]
+    fn sync_status_follows_checkpoints() {
+        let db = create_test_rw_db::<WriteMap>();
+        let api = EthApi::new(Arc::new(ProviderImpl::new(db.clone())), testing_pool());
+        let checkpoints = |headers: BlockNumber, execution: BlockNumber| {
+            db.update(|tx| {
+                tx.put::<tables::SyncStage>(HEADERS_STAGE.as_bytes().to_vec(), headers).unwrap();
+                tx.put::<tables::SyncStage>(EXECUTION_STAGE.as_bytes().to_vec(), execution)
+                    .unwrap();
+            })
+            .unwrap();
+        };
+        let info = |starting: u64, current: u64, highest: u64| {
+            SyncStatus::Info(SyncInfo {
+                starting_block: starting.into(),
+                current_block: current.into(),
+                highest_block: highest.into(),
+                warp_chunks_amount: None,
+                warp_chunks_processed: None,
+            })
+        };
+
+        assert_eq!(api.sync_status().unwrap(), SyncStatus::None);
+
+        checkpoints(100, 10);
+        assert_eq!(api.sync_status().unwrap(), info(10, 10, 100));
+        checkpoints(120, 50);
+        assert_eq!(api.sync_status().unwrap(), info(10, 50, 120));
+
+        // a new sync starts at the block the node caught up to
+        checkpoints(120, 120);
+        assert_eq!(api.sync_status().unwrap(), SyncStatus::None);
+        checkpoints(130, 120);
+        assert_eq!(api.sync_status().unwrap(), info(120, 120, 130));
+    }
+
+    #[test]
+    fn proofs_of_the_hashed_state() {
+        let db = create_test_rw_db::<WriteMap>();
+        let api = EthApi::new(Arc::new(ProviderImpl::new(db.clone())), testing_pool());
+        let address = Address::repeat_byte(1);
+        let account = Account { nonce: 1, balance: U256::from(10), bytecode_hash: None };
+        let state_root = calculate_state_root([(address, account, EMPTY_ROOT)]);
+        db.update(|tx| {
+            tx.put::<tables::HashedAccount>(keccak256(address), account).unwrap();
+            for (number, state_root) in [(0, state_root), (1, H256::repeat_byte(2))] {
+                let header = Header { number, state_root, ..Default::default() }.seal();
+                let block = SealedBlock { header, body: vec![], ommers: vec![] };
+                insert_canonical_block(tx, &block, false).unwrap();
+            }
+        })
+        .unwrap();
+
+        let at = Some(BlockId::Number(rpc::BlockNumber::Number(0.into())));
+        let proof = api.proof_at(address, &[H256::zero()], at).unwrap();
+        assert_eq!((proof.nonce, proof.balance), (U64::from(1), U256::from(10)));
+        assert_eq!((proof.code_hash, proof.storage_hash), (KECCAK_EMPTY, EMPTY_ROOT));
+        assert_eq!(proof.storage_proof.len(), 1);
+        let mut account_rlp = Vec::new();
+        TrieAccount::new(account, EMPTY_ROOT).encode(&mut account_rlp);
+        assert_eq!(
+            verify_proof(state_root, keccak256(address).as_bytes(), &proof.account_proof),
+            Ok(Some(account_rlp))
+        );
+
+        // the hashed state is not the state of the latest block
+        assert_eq!(
+            api.proof_at(address, &[], None).unwrap_err(),
+            reth_provider::Error::ProofUnavailable { block_number: 1 }.into()
+        );
+    }
+
+    #[test]
+    fn state_of_canonical_blocks_by_hash() {
+        let db = create_test_rw_db::<WriteMap>();
+        let api = EthApi::new(Arc::new(ProviderImpl::new(db.clone())), testing_pool());
+        let address = Address::repeat_byte(1);
+        let account = Account { nonce: 1, balance: U256::from(10), bytecode_hash: None };
+        let genesis = Header::default().seal();
+        let canonical = genesis.hash();
+        let side = H256::repeat_byte(2);
+        db.update(|tx| {
+            tx.put::<tables::PlainAccountState>(address, account).unwrap();
+            let block = SealedBlock { header: genesis, body: vec![], ommers: vec![] };
+            insert_canonical_block(tx, &block, false).unwrap();
+            // a block of a side chain at the same height
+            tx.put::<tables::HeaderNumbers>(side, 0).unwrap();
+        })
+        .unwrap();
+
+        let nonce = |state: &dyn StateProvider| -> Result<Option<u64>> {
+            Ok(state.basic_account(address)?.map(|account| account.nonce))
+        };
+        assert_eq!(api.with_state_at(Some(BlockId::Hash(canonical)), nonce).unwrap(), Some(1));
+        assert_eq!(
+            api.with_state_at(Some(BlockId::Hash(side)), nonce).unwrap_err(),
+            reth_provider::Error::NonCanonicalBlock { block_hash: side }.into()
+        );
+        assert_eq!(
+            api.with_state_at(Some(BlockId::Hash(H256::repeat_byte(3))), nonce).unwrap_err(),
+            reth_provider::Error::BlockHash { block_hash: H256::repeat_byte(3) }.into()
+        );
+    }
+
+    #[test]
+    fn chain_id_of_executor() {
+        let mut config = EthConfig::default();
+        config.call.executor.chain_id = 5.into();
+        let api = EthApi::with_config(
+            Arc::new(ProviderImpl::new(create_test_rw_db::<WriteMap>())),
+            testing_pool(),
+            ResponseCache::default(),
+            config,
+        );
+        assert_eq!(api.chain_id(), U64::from(5));
+    }
 }
@@ -1,44 +1,65 @@
 //! Implementation of the [`jsonrpsee`] generated [`reth_rpc_api::EthApiServer`] trait
 //! Handles RPC requests for he `eth_` namespace.
 
-use crate::{eth::api::EthApi, result::ToRpcResult};
+use crate::{
+    eth::{
+        api::{
+            block_rpc_err, call_rpc_err, send_raw_transaction_rpc_err, sign_rpc_err, EthApi,
+            SignError,
+        },
+        gas_oracle::gas_oracle_rpc_err,
+    },
+    result::{state_rpc_err, unsupported_rpc_err, ToRpcResult},
+};
 use jsonrpsee::core::RpcResult as Result;
 use reth_primitives::{
     rpc::{transaction::eip2930::AccessListWithGasUsed, BlockId},
-    Address, BlockNumber, Bytes, H256, H64, U256, U64,
+    Address, BigEndianHash, BlockNumber, Bytes, H256, H64, U256, U64,
+};
+use reth_provider::{
+    AccountProvider, BlockProvider, HeaderProvider, ReceiptProvider, StateProofProvider,
+    StateProvider, StateProviderFactory, TransactionsProvider,
 };
-use reth_provider::{BlockProvider, StateProviderFactory};
 use reth_rpc_api::EthApiServer;
 use reth_rpc_types::{
-    CallRequest, EIP1186AccountProofResponse, FeeHistory, Index, RichBlock, SyncStatus,
-    TransactionReceipt, TransactionRequest, Work,
+    BlockOverrides, CallRequest, EIP1186AccountProofResponse, FeeHistory, Index, RichBlock,
+    StateOverride, SyncStatus, TransactionReceipt, TransactionRequest, Work,
 };
 use reth_transaction_pool::TransactionPool;
 use serde_json::Value;
 
-use super::EthApiSpec;
+use super::{
+    block::{block_id, response_block},
+    EthApiSpec,
+};
 
 #[async_trait::async_trait]
 impl<Pool, Client> EthApiServer for EthApi<Pool, Client>
 where
     Self: EthApiSpec,
     Pool: TransactionPool + 'static,
-    Client: BlockProvider + StateProviderFactory + 'static,
+    Client: BlockProvider
+        + HeaderProvider
+        + ReceiptProvider
+        + StateProviderFactory
+        + StateProofProvider
+        + TransactionsProvider
+        + 'static,
 {
     fn protocol_version(&self) -> Result<U64> {
         Ok(EthApiSpec::protocol_version(self))
     }
 
     fn syncing(&self) -> Result<SyncStatus> {
-        todo!()
+        EthApiSpec::sync_status(self).with_message("failed to read sync status")
     }
 
     async fn author(&self) -> Result<Address> {
-        todo!()
+        Err(unsupported_rpc_err("eth_coinbase"))
     }
 
     async fn accounts(&self) -> Result<Vec<Address>> {
-        todo!()
+        Ok(self.accounts().addresses())
     }
 
     fn block_number(&self) -> Result<U256> {
@@ -52,107 +73,151 @@ where
         Ok(Some(EthApiSpec::chain_id(self)))
     }
 
-    async fn block_by_hash(&self, _hash: H256, _full: bool) -> Result<Option<RichBlock>> {
-        todo!()
+    async fn block_by_hash(&self, hash: H256, full: bool) -> Result<Option<RichBlock>> {
+        self.cached("eth_getBlockByHash", &(hash, full), || {
+            let block = self.block_at(BlockId::Hash(hash), full)?;
+            Ok(block.map(|block| (response_block(block.header.number), block)))
+        })
+        .map_err(block_rpc_err)
     }
 
-    async fn block_by_number(
-        &self,
-        _number: BlockNumber,
-        _full: bool,
-    ) -> Result<Option<RichBlock>> {
-        todo!()
+    async fn block_by_number(&self, number: BlockNumber, full: bool) -> Result<Option<RichBlock>> {
+        self.cached("eth_getBlockByNumber", &(number, full), || {
+            let block = self.block_at(block_id(number), full)?;
+            Ok(block.map(|block| (response_block(block.header.number), block)))
+        })
+        .map_err(block_rpc_err)
     }
 
-    async fn block_transaction_count_by_hash(&self, _hash: H256) -> Result<Option<U256>> {
-        todo!()
+    async fn block_transaction_count_by_hash(&self, hash: H256) -> Result<Option<U256>> {
+        self.block_transaction_count(BlockId::Hash(hash)).map_err(block_rpc_err)
     }
 
-    async fn block_transaction_count_by_number(
-        &self,
-        _number: BlockNumber,
-    ) -> Result<Option<U256>> {
-        todo!()
+    async fn block_transaction_count_by_number(&self, number: BlockNumber) -> Result<Option<U256>> {
+        self.block_transaction_count(block_id(number)).map_err(block_rpc_err)
     }
 
-    async fn block_uncles_count_by_hash(&self, _hash: H256) -> Result<U256> {
-        todo!()
+    async fn block_uncles_count_by_hash(&self, hash: H256) -> Result<U256> {
+        self.block_uncles_count(BlockId::Hash(hash)).map_err(block_rpc_err)
     }
 
-    async fn block_uncles_count_by_number(&self, _number: BlockNumber) -> Result<U256> {
-        todo!()
+    async fn block_uncles_count_by_number(&self, number: BlockNumber) -> Result<U256> {
+        self.block_uncles_count(block_id(number)).map_err(block_rpc_err)
     }
 
     async fn uncle_by_block_hash_and_index(
         &self,
-        _hash: H256,
-        _index: Index,
+        hash: H256,
+        index: Index,
     ) -> Result<Option<RichBlock>> {
-        todo!()
+        self.uncle_at(BlockId::Hash(hash), index.into()).map_err(block_rpc_err)
     }
 
     async fn uncle_by_block_number_and_index(
         &self,
-        _number: BlockNumber,
-        _index: Index,
+        number: BlockNumber,
+        index: Index,
     ) -> Result<Option<RichBlock>> {
-        todo!()
+        self.uncle_at(block_id(number), index.into()).map_err(block_rpc_err)
     }
 
-    async fn transaction_by_hash(
-        &self,
-        _hash: H256,
-    ) -> Result<Option<reth_rpc_types::Transaction>> {
-        todo!()
+    async fn transaction_by_hash(&self, hash: H256) -> Result<Option<reth_rpc_types::Transaction>> {
+        self.cached("eth_getTransactionByHash", &hash, || {
+            let tx = self.transaction_by_hash_inner(hash)?;
+            Ok(tx.map(|tx| (response_block(tx.block_number), tx)))
+        })
+        .map_err(block_rpc_err)
     }
 
     async fn transaction_by_block_hash_and_index(
         &self,
-        _hash: H256,
-        _index: Index,
+        hash: H256,
+        index: Index,
     ) -> Result<Option<reth_rpc_types::Transaction>> {
-        todo!()
+        let index = usize::from(index);
+        self.cached("eth_getTransactionByBlockHashAndIndex", &(hash, index), || {
+            let tx = self.transaction_at(BlockId::Hash(hash), index)?;
+            Ok(tx.map(|tx| (response_block(tx.block_number), tx)))
+        })
+        .map_err(block_rpc_err)
     }
 
     async fn transaction_by_block_number_and_index(
         &self,
-        _number: BlockNumber,
-        _index: Index,
+        number: BlockNumber,
+        index: Index,
     ) -> Result<Option<reth_rpc_types::Transaction>> {
-        todo!()
+        let index = usize::from(index);
+        self.cached("eth_getTransactionByBlockNumberAndIndex", &(number, index), || {
+            let tx = self.transaction_at(block_id(number), index)?;
+            Ok(tx.map(|tx| (response_block(tx.block_number), tx)))
+        })
+        .map_err(block_rpc_err)
     }
 
-    async fn transaction_receipt(&self, _hash: H256) -> Result<Option<TransactionReceipt>> {
-        todo!()
+    async fn transaction_receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>> {
+        self.cached("eth_getTransactionReceipt", &hash, || {
+            let receipt = self.transaction_receipt_inner(hash)?;
+            Ok(receipt.map(|receipt| (response_block(receipt.block_number), receipt)))
+        })
+        .map_err(block_rpc_err)
     }
 
-    async fn balance(&self, _address: Address, _block_number: Option<BlockId>) -> Result<U256> {
-        todo!()
+    async fn balance(&self, address: Address, block_number: Option<BlockId>) -> Result<U256> {
+        self.with_state_at(block_number, |state| {
+            Ok(state.basic_account(address)?.map(|account| account.balance).unwrap_or_default())
+        })
+        .map_err(state_rpc_err)
     }
 
     async fn storage_at(
         &self,
-        _address: Address,
-        _index: U256,
-        _block_number: Option<BlockId>,
+        address: Address,
+        index: U256,
+        block_number: Option<BlockId>,
     ) -> Result<H256> {
-        todo!()
+        self.with_state_at(block_number, |state| {
+            let value = state.storage(address, H256::from_uint(&index))?.unwrap_or_default();
+            Ok(H256::from_uint(&value))
+        })
+        .map_err(state_rpc_err)
     }
 
     async fn transaction_count(
         &self,
-        _address: Address,
-        _block_number: Option<BlockId>,
+        address: Address,
+        block_number: Option<BlockId>,
     ) -> Result<U256> {
-        todo!()
-    }
-
-    async fn get_code(&self, _address: Address, _block_number: Option<BlockId>) -> Result<Bytes> {
-        todo!()
-    }
-
-    async fn call(&self, _request: CallRequest, _block_number: Option<BlockId>) -> Result<Bytes> {
-        todo!()
+        self.with_state_at(block_number, |state| {
+            Ok(state
+                .basic_account(address)?
+                .map(|account| account.nonce)
+                .unwrap_or_default()
+                .into())
+        })
+        .map_err(state_rpc_err)
+    }
+
+    async fn get_code(&self, address: Address, block_number: Option<BlockId>) -> Result<Bytes> {
+        self.with_state_at(block_number, |state| {
+            let code_hash = state.basic_account(address)?.and_then(|account| account.bytecode_hash);
+            match code_hash {
+                Some(code_hash) => Ok(state.bytecode_by_hash(code_hash)?.unwrap_or_default()),
+                None => Ok(Bytes::default()),
+            }
+        })
+        .map_err(state_rpc_err)
+    }
+
+    async fn call(
+        &self,
+        request: CallRequest,
+        block_number: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<Box<BlockOverrides>>,
+    ) -> Result<Bytes> {
+        self.call_at(request, block_number, state_overrides, block_overrides.as_deref())
+            .map_err(call_rpc_err)
     }
 
     async fn create_access_list(
@@ -160,80 +225,97 @@ where
         _request: CallRequest,
         _block_number: Option<BlockId>,
     ) -> Result<AccessListWithGasUsed> {
-        todo!()
+        Err(unsupported_rpc_err("eth_createAccessList"))
     }
 
     async fn estimate_gas(
         &self,
-        _request: CallRequest,
-        _block_number: Option<BlockId>,
+        request: CallRequest,
+        block_number: Option<BlockId>,
+        state_overrides: Option<StateOverride>,
     ) -> Result<U256> {
-        todo!()
+        self.estimate_gas_at(request, block_number, state_overrides)
+            .map(U256::from)
+            .map_err(call_rpc_err)
     }
 
     async fn gas_price(&self) -> Result<U256> {
-        todo!()
+        self.gas_oracle().suggest_gas_price().map_err(gas_oracle_rpc_err)
     }
 
     async fn fee_history(
         &self,
-        _block_count: U256,
-        _newest_block: BlockNumber,
-        _reward_percentiles: Option<Vec<f64>>,
+        block_count: U256,
+        newest_block: BlockNumber,
+        reward_percentiles: Option<Vec<f64>>,
     ) -> Result<FeeHistory> {
-        todo!()
+        let block_count = block_count.min(U256::from(u64::MAX)).as_u64();
+        self.gas_oracle()
+            .fee_history(block_count, newest_block, reward_percentiles.as_deref())
+            .map_err(gas_oracle_rpc_err)
     }
 
     async fn max_priority_fee_per_gas(&self) -> Result<U256> {
-        todo!()
+        self.gas_oracle().suggest_tip().map(U256::from).map_err(gas_oracle_rpc_err)
     }
 
+    /// Blocks are proposed by the consensus layer, so the node never mines.
     async fn is_mining(&self) -> Result<bool> {
-        todo!()
+        Ok(false)
     }
 
     async fn hashrate(&self) -> Result<U256> {
-        todo!()
+        Ok(U256::zero())
     }
 
     async fn get_work(&self) -> Result<Work> {
-        todo!()
+        Err(unsupported_rpc_err("eth_getWork"))
     }
 
     async fn submit_hashrate(&self, _hashrate: U256, _id: H256) -> Result<bool> {
-        todo!()
+        Ok(false)
     }
 
     async fn submit_work(&self, _nonce: H64, _pow_hash: H256, _mix_digest: H256) -> Result<bool> {
-        todo!()
+        Err(unsupported_rpc_err("eth_submitWork"))
     }
 
-    async fn send_transaction(&self, _request: TransactionRequest) -> Result<H256> {
-        todo!()
+    /// Signs the transaction with the unlocked key of its sender like `eth_signTransaction` and
+    /// submits it like `eth_sendRawTransaction`.
+    async fn send_transaction(&self, request: TransactionRequest) -> Result<H256> {
+        let signed =
+            self.sign_transaction_inner(request.into_call_request()).map_err(sign_rpc_err)?;
+        self.send_raw_transaction_inner(signed.envelope_encoded())
+            .await
+            .map_err(send_raw_transaction_rpc_err)
     }
 
-    async fn send_raw_transaction(&self, _bytes: Bytes) -> Result<H256> {
-        todo!()
+    async fn send_raw_transaction(&self, bytes: Bytes) -> Result<H256> {
+        self.send_raw_transaction_inner(bytes).await.map_err(send_raw_transaction_rpc_err)
     }
 
-    async fn sign(&self, _address: Address, _message: Bytes) -> Result<Bytes> {
-        todo!()
+    async fn sign(&self, address: Address, message: Bytes) -> Result<Bytes> {
+        self.accounts()
+            .sign_message(&address, &message)
+            .ok_or_else(|| sign_rpc_err(SignError::UnknownAccount))
     }
 
-    async fn sign_transaction(&self, _transaction: CallRequest) -> Result<Bytes> {
-        todo!()
+    async fn sign_transaction(&self, transaction: CallRequest) -> Result<Bytes> {
+        self.sign_transaction_inner(transaction)
+            .map(|signed| signed.envelope_encoded())
+            .map_err(sign_rpc_err)
     }
 
     async fn sign_typed_data(&self, _address: Address, _data: Value) -> Result<Bytes> {
-        todo!()
+        Err(unsupported_rpc_err("eth_signTypedData"))
     }
 
     async fn get_proof(
         &self,
-        _address: Address,
-        _keys: Vec<H256>,
-        _block_number: Option<BlockId>,
+        address: Address,
+        keys: Vec<H256>,
+        block_number: Option<BlockId>,
     ) -> Result<EIP1186AccountProofResponse> {
-        todo!()
+        self.proof_at(address, &keys, block_number).map_err(state_rpc_err)
     }
 }
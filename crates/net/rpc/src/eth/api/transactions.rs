@@ -0,0 +1,319 @@
+//! Submission of raw transactions with `eth_sendRawTransaction` and signing of transactions with
+//! `eth_signTransaction`.
+
+use crate::{
+    eth::api::EthApi,
+    result::{rpc_err, state_rpc_err},
+};
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::{HttpClient, HttpClientBuilder},
+    rpc_params,
+};
+use reth_primitives::{
+    AccessList, AccessListItem, Address, Bytes, FromRecoveredTransaction, Transaction,
+    TransactionKind, TransactionSigned, TxEip1559, TxEip2930, TxLegacy, H256, U256,
+};
+use reth_provider::{AccountProvider, BlockProvider, ReceiptProvider, StateProviderFactory};
+use reth_rlp::DecodeError;
+use reth_rpc_types::CallRequest;
+use reth_transaction_pool::{error::PoolError, TransactionOrigin, TransactionPool};
+use tracing::{debug, warn};
+
+/// Error code of transactions that are rejected by the pool.
+const TRANSACTION_REJECTED_CODE: i32 = -32000;
+
+/// Forwards the submitted raw transactions to another node, e.g. the sequencer of a rollup or a
+/// block builder.
+#[derive(Debug, Clone)]
+pub struct TransactionForwarder {
+    client: HttpClient,
+}
+
+// === impl TransactionForwarder ===
+
+impl TransactionForwarder {
+    /// Creates a forwarder to the HTTP JSON-RPC endpoint at the given URL.
+    pub fn new(url: &str) -> Result<Self, jsonrpsee::core::Error> {
+        Ok(Self { client: HttpClientBuilder::default().build(url)? })
+    }
+
+    /// Submits the raw transaction with `eth_sendRawTransaction` and returns the hash the
+    /// endpoint responded with.
+    pub async fn forward(&self, tx: &Bytes) -> Result<H256, jsonrpsee::core::Error> {
+        self.client.request("eth_sendRawTransaction", rpc_params![tx]).await
+    }
+}
+
+/// Errors of submitting a raw transaction.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum SendRawTransactionError {
+    /// The bytes are not a signed transaction.
+    #[error("failed to decode signed transaction: {0}")]
+    Decode(DecodeError),
+    /// The signer of the transaction can't be recovered.
+    #[error("invalid transaction signature")]
+    InvalidSignature,
+    /// The pool rejected the transaction.
+    #[error(transparent)]
+    Pool(#[from] PoolError),
+}
+
+/// Converts an error of submitting a raw transaction into a JSON-RPC error.
+pub(crate) fn send_raw_transaction_rpc_err(err: SendRawTransactionError) -> jsonrpsee::core::Error {
+    match err {
+        SendRawTransactionError::Pool(_) => {
+            rpc_err(TRANSACTION_REJECTED_CODE, err.to_string(), None)
+        }
+        err => rpc_err(jsonrpsee::types::error::INVALID_PARAMS_CODE, err.to_string(), None),
+    }
+}
+
+/// Errors of signing a transaction.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum SignError {
+    /// The state of the sender could not be read.
+    #[error(transparent)]
+    State(#[from] reth_interfaces::Error),
+    /// The request has no sender.
+    #[error("missing from address")]
+    MissingFrom,
+    /// The sender is not an unlocked account.
+    #[error("unknown account")]
+    UnknownAccount,
+    /// The request has no gas limit.
+    #[error("missing gas limit")]
+    MissingGas,
+    /// The request has neither a gas price nor EIP-1559 fees.
+    #[error("missing gasPrice or maxFeePerGas")]
+    MissingFee,
+    /// A number of the request does not fit into the transaction.
+    #[error("{0} is too large")]
+    Overflow(&'static str),
+}
+
+/// Converts an error of signing a transaction into a JSON-RPC error.
+pub(crate) fn sign_rpc_err(err: SignError) -> jsonrpsee::core::Error {
+    match err {
+        SignError::State(err) => state_rpc_err(err),
+        SignError::UnknownAccount => rpc_err(TRANSACTION_REJECTED_CODE, err.to_string(), None),
+        err => rpc_err(jsonrpsee::types::error::INVALID_PARAMS_CODE, err.to_string(), None),
+    }
+}
+
+/// Builds the transaction of the request, with the given chain id and nonce.
+///
+/// Requests with EIP-1559 fees are EIP-1559 transactions, requests with a gas price are legacy
+/// transactions, or EIP-2930 transactions if they have an access list.
+fn build_transaction(
+    request: CallRequest,
+    chain_id: u64,
+    nonce: u64,
+) -> Result<Transaction, SignError> {
+    let to_u64 = |value: U256, field| -> Result<u64, SignError> {
+        value.try_into().map_err(|_| SignError::Overflow(field))
+    };
+    let to_u128 = |value: U256, field| -> Result<u128, SignError> {
+        value.try_into().map_err(|_| SignError::Overflow(field))
+    };
+
+    let gas_limit = to_u64(request.gas.ok_or(SignError::MissingGas)?, "gas")?;
+    let to = request.to.map_or(TransactionKind::Create, TransactionKind::Call);
+    let value = to_u128(request.value.unwrap_or_default(), "value")?;
+    let input = request.data.unwrap_or_default();
+    let access_list = request.access_list.map(|items| {
+        AccessList(
+            items
+                .into_iter()
+                .map(|item| AccessListItem {
+                    address: item.address,
+                    storage_keys: item.storage_keys,
+                })
+                .collect(),
+        )
+    });
+
+    if request.max_fee_per_gas.is_some() || request.max_priority_fee_per_gas.is_some() {
+        let max_fee_per_gas = request.max_fee_per_gas.ok_or(SignError::MissingFee)?;
+        return Ok(Transaction::Eip1559(TxEip1559 {
+            chain_id,
+            nonce,
+            gas_limit,
+            max_fee_per_gas: to_u128(max_fee_per_gas, "maxFeePerGas")?,
+            max_priority_fee_per_gas: to_u128(
+                request.max_priority_fee_per_gas.unwrap_or_default(),
+                "maxPriorityFeePerGas",
+            )?,
+            to,
+            value,
+            input,
+            access_list: access_list.unwrap_or_default(),
+        }))
+    }
+
+    let gas_price = to_u128(request.gas_price.ok_or(SignError::MissingFee)?, "gasPrice")?;
+    Ok(match access_list {
+        Some(access_list) => Transaction::Eip2930(TxEip2930 {
+            chain_id,
+            nonce,
+            gas_price,
+            gas_limit,
+            to,
+            value,
+            input,
+            access_list,
+        }),
+        None => Transaction::Legacy(TxLegacy {
+            chain_id: Some(chain_id),
+            nonce,
+            gas_price,
+            gas_limit,
+            to,
+            value,
+            input,
+        }),
+    })
+}
+
+// === impl EthApi ===
+
+impl<Pool, Client> EthApi<Pool, Client>
+where
+    Pool: TransactionPool + 'static,
+    Client: BlockProvider + ReceiptProvider + StateProviderFactory + 'static,
+{
+    /// Decodes the raw transaction, adds it to the pool as a local transaction and forwards it if
+    /// a forwarder is configured.
+    ///
+    /// The pool propagates the transaction to the peers once it's pending. A failed forward is
+    /// only logged, because the transaction is in the pool already.
+    pub(crate) async fn send_raw_transaction_inner(
+        &self,
+        tx: Bytes,
+    ) -> Result<H256, SendRawTransactionError> {
+        let recovered = TransactionSigned::decode_enveloped(&tx)
+            .map_err(SendRawTransactionError::Decode)?
+            .into_ecrecovered()
+            .ok_or(SendRawTransactionError::InvalidSignature)?;
+        let transaction =
+            <Pool::Transaction as FromRecoveredTransaction>::from_recovered_transaction(recovered);
+        let hash = self.pool().add_transaction(TransactionOrigin::Local, transaction).await?;
+        debug!(target: "rpc::eth", ?hash, "Added raw transaction to the pool");
+
+        if let Some(forwarder) = self.forwarder() {
+            if let Err(err) = forwarder.forward(&tx).await {
+                warn!(target: "rpc::eth", ?hash, %err, "Failed to forward raw transaction");
+            }
+        }
+        Ok(hash)
+    }
+
+    /// Signs the transaction of the request with the key of its sender.
+    ///
+    /// The nonce defaults to the nonce of the sender in the latest state.
+    pub(crate) fn sign_transaction_inner(
+        &self,
+        request: CallRequest,
+    ) -> Result<TransactionSigned, SignError> {
+        let from: Address = request.from.ok_or(SignError::MissingFrom)?;
+        let accounts = self.accounts();
+        if !accounts.contains(&from) {
+            return Err(SignError::UnknownAccount)
+        }
+        let nonce: u64 = match request.nonce {
+            Some(nonce) => nonce.try_into().map_err(|_| SignError::Overflow("nonce"))?,
+            None => self
+                .client()
+                .latest()?
+                .basic_account(from)?
+                .map(|account| account.nonce)
+                .unwrap_or_default(),
+        };
+        let transaction = build_transaction(request, accounts.chain_id(), nonce)?;
+        accounts.sign_transaction(&from, transaction).ok_or(SignError::UnknownAccount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::ResponseCache,
+        eth::{key_address, EthConfig, UnlockedAccounts},
+    };
+    use reth_db::mdbx::{test_utils::create_test_rw_db, WriteMap};
+    use reth_interfaces::test_utils::generators::sign_message;
+    use reth_provider::ProviderImpl;
+    use reth_transaction_pool::test_util::testing_pool;
+    use secp256k1::SecretKey;
+    use std::sync::Arc;
+
+    /// Returns the raw encoding of a signed transfer.
+    fn raw_transfer() -> (H256, Bytes) {
+        let transaction = Transaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            gas_limit: 21_000,
+            max_fee_per_gas: 10,
+            to: TransactionKind::Call(Address::repeat_byte(1)),
+            ..Default::default()
+        });
+        let signature = sign_message(H256::repeat_byte(1), transaction.signature_hash()).unwrap();
+        let signed = TransactionSigned::from_transaction_and_signature(transaction, signature);
+        (signed.hash(), signed.envelope_encoded())
+    }
+
+    #[tokio::test]
+    async fn send_raw_transaction() {
+        let pool = testing_pool();
+        let api =
+            EthApi::new(Arc::new(ProviderImpl::new(create_test_rw_db::<WriteMap>())), pool.clone());
+
+        let (hash, raw) = raw_transfer();
+        assert_eq!(api.send_raw_transaction_inner(raw).await.unwrap(), hash);
+        assert!(pool.contains(&hash));
+
+        let err = api.send_raw_transaction_inner(Bytes::from(vec![0x02, 0xc0])).await.unwrap_err();
+        assert!(matches!(err, SendRawTransactionError::Decode(_)));
+    }
+
+    #[test]
+    fn sign_transaction() {
+        let key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let from = key_address(&key);
+        let config = EthConfig { accounts: UnlockedAccounts::new(5, [key]), ..Default::default() };
+        let api = EthApi::with_config(
+            Arc::new(ProviderImpl::new(create_test_rw_db::<WriteMap>())),
+            testing_pool(),
+            ResponseCache::default(),
+            config,
+        );
+
+        let request = CallRequest {
+            from: Some(from),
+            to: Some(Address::repeat_byte(1)),
+            gas: Some(U256::from(21_000)),
+            max_fee_per_gas: Some(U256::from(10)),
+            ..Default::default()
+        };
+        let signed = api.sign_transaction_inner(request.clone()).unwrap();
+        assert_eq!(signed.recover_signer(), Some(from));
+        assert_eq!(signed.chain_id(), Some(5));
+        assert_eq!(signed.nonce(), 0);
+        assert!(matches!(signed.transaction, Transaction::Eip1559(_)));
+
+        // a gas price without an access list makes a legacy transaction
+        let legacy = CallRequest {
+            gas_price: Some(U256::from(10)),
+            max_fee_per_gas: None,
+            ..request.clone()
+        };
+        let signed = api.sign_transaction_inner(legacy).unwrap();
+        assert!(matches!(signed.transaction, Transaction::Legacy(_)));
+
+        let err = api.sign_transaction_inner(CallRequest { gas: None, ..request.clone() });
+        assert!(matches!(err, Err(SignError::MissingGas)));
+        let err = api
+            .sign_transaction_inner(CallRequest { from: Some(Address::repeat_byte(2)), ..request });
+        assert!(matches!(err, Err(SignError::UnknownAccount)));
+    }
+}
@@ -0,0 +1,676 @@
+//! `eth_` filter RPC handler implementation, including `eth_getLogs`.
+//!
+//! Logs are searched block by block. The log index narrows down the blocks if the range is
+//! indexed, and the logs bloom of the header skips the remaining blocks that can't contain a
+//! matching log before their receipts are read.
+
+use crate::result::{rpc_err, state_rpc_err, unsupported_rpc_err};
+use jsonrpsee::core::RpcResult;
+use parking_lot::Mutex;
+use reth_primitives::{
+    bloom::bloom_contains,
+    rpc::{self, BlockId, Filter, FilterBlockOption, ValueOrArray},
+    Address, Block, BlockNumber, Bloom, Receipt, H256, U256,
+};
+use reth_provider::{BlockProvider, HeaderProvider, LogIndexProvider, ReceiptProvider};
+use reth_rpc_api::EthFilterApiServer;
+use reth_rpc_types::{FilterChanges, Index, Log};
+use std::{collections::HashMap, sync::Arc};
+
+/// The default maximum number of blocks a log query may span, see
+/// [`FilterConfig::max_block_range`].
+pub const DEFAULT_MAX_BLOCK_RANGE: u64 = 10_000;
+
+/// The default maximum number of logs a query may return, see [`FilterConfig::max_logs`].
+pub const DEFAULT_MAX_LOGS: usize = 10_000;
+
+/// Error code of queries that exceed the limits of the [`FilterConfig`].
+const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
+/// Error code of requests for unknown blocks or filters.
+const FILTER_ERROR_CODE: i32 = -32000;
+
+/// Limits of log queries.
+#[derive(Debug, Clone)]
+pub struct FilterConfig {
+    /// The maximum number of blocks between the first and the last block of a query.
+    pub max_block_range: u64,
+    /// The maximum number of logs a query returns, queries with more results fail.
+    pub max_logs: usize,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self { max_block_range: DEFAULT_MAX_BLOCK_RANGE, max_logs: DEFAULT_MAX_LOGS }
+    }
+}
+
+/// Errors of log queries and installed filters.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum FilterError {
+    /// The chain could not be read.
+    #[error(transparent)]
+    State(#[from] reth_interfaces::Error),
+    /// A block of the query is unknown.
+    #[error("unknown block")]
+    UnknownBlock,
+    /// The first block of the query is after its last block.
+    #[error("invalid block range: from block {from} is after to block {to}")]
+    InvalidBlockRange {
+        /// The first block of the query.
+        from: BlockNumber,
+        /// The last block of the query.
+        to: BlockNumber,
+    },
+    /// The query spans more blocks than allowed.
+    #[error("query exceeds max block range {0}")]
+    BlockRangeTooLarge(u64),
+    /// The query matches more logs than allowed.
+    #[error("query returned more than {0} results")]
+    TooManyLogs(usize),
+    /// There's no installed filter with the id.
+    #[error("filter not found")]
+    FilterNotFound,
+}
+
+/// Converts an error of a log query into a JSON-RPC error.
+pub(crate) fn filter_rpc_err(err: FilterError) -> jsonrpsee::core::Error {
+    match err {
+        FilterError::State(err) => state_rpc_err(err),
+        FilterError::InvalidBlockRange { .. } => {
+            rpc_err(jsonrpsee::types::error::INVALID_PARAMS_CODE, err.to_string(), None)
+        }
+        FilterError::BlockRangeTooLarge(_) | FilterError::TooManyLogs(_) => {
+            rpc_err(LIMIT_EXCEEDED_CODE, err.to_string(), None)
+        }
+        err => rpc_err(FILTER_ERROR_CODE, err.to_string(), None),
+    }
+}
+
+/// `Eth` filter RPC implementation.
+///
+/// Answers `eth_getLogs` and keeps the filters that are polled with `eth_getFilterChanges`.
+#[derive(Debug, Clone)]
+pub struct EthFilter<Client> {
+    /// All nested fields bundled together.
+    inner: Arc<EthFilterInner<Client>>,
+}
+
+// === impl EthFilter ===
+
+impl<Client> EthFilter<Client>
+where
+    Client: BlockProvider + HeaderProvider + ReceiptProvider + LogIndexProvider + 'static,
+{
+    /// Creates a new, shareable instance with the default limits.
+    pub fn new(client: Arc<Client>) -> Self {
+        Self::with_config(client, FilterConfig::default())
+    }
+
+    /// Creates a new, shareable instance that enforces the given limits.
+    pub fn with_config(client: Arc<Client>, config: FilterConfig) -> Self {
+        let inner = EthFilterInner {
+            client,
+            config,
+            filters: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(1),
+        };
+        Self { inner: Arc::new(inner) }
+    }
+
+    /// Returns the inner `Client`
+    fn client(&self) -> &Arc<Client> {
+        &self.inner.client
+    }
+
+    /// Returns all logs that match the filter.
+    pub(crate) fn logs_for_filter(&self, filter: &Filter) -> Result<Vec<Log>, FilterError> {
+        let (from, to) = match filter.block_option {
+            FilterBlockOption::AtBlockHash(hash) => {
+                let number = self.client().block_number(hash)?.ok_or(FilterError::UnknownBlock)?;
+                (number, number)
+            }
+            FilterBlockOption::Range { from_block, to_block } => {
+                let best = self.client().chain_info()?.best_number;
+                (self.resolve(from_block, best)?, self.resolve(to_block, best)?)
+            }
+        };
+        self.logs_in_range(&LogFilter::new(filter), from, to)
+    }
+
+    /// Returns the logs of the blocks `from..=to` that match the filter.
+    fn logs_in_range(
+        &self,
+        filter: &LogFilter,
+        from: BlockNumber,
+        to: BlockNumber,
+    ) -> Result<Vec<Log>, FilterError> {
+        let FilterConfig { max_block_range, max_logs } = self.inner.config;
+        if from > to {
+            return Err(FilterError::InvalidBlockRange { from, to })
+        }
+        if to - from >= max_block_range {
+            return Err(FilterError::BlockRangeTooLarge(max_block_range))
+        }
+
+        let client = self.client();
+        let blocks = match client.log_filter_blocks(from..=to, &filter.addresses, &filter.topics)? {
+            Some(blocks) => blocks,
+            None => (from..=to).collect(),
+        };
+
+        let mut logs = Vec::new();
+        for number in blocks {
+            let Some(header) = client.header_by_number(number)? else { break };
+            if !filter.matches_bloom(&header.logs_bloom) {
+                continue
+            }
+            let id = BlockId::Number(rpc::BlockNumber::Number(number.into()));
+            let (Some(block), Some(receipts)) = (client.block(id)?, client.receipts_by_block(id)?)
+            else {
+                continue
+            };
+            append_matching_logs(
+                &mut logs,
+                filter,
+                block.header.hash_slow(),
+                &block,
+                &receipts,
+                false,
+            );
+            if logs.len() > max_logs {
+                return Err(FilterError::TooManyLogs(max_logs))
+            }
+        }
+        Ok(logs)
+    }
+
+    /// Resolves the block of a query, no block refers to the latest block.
+    fn resolve(
+        &self,
+        block: Option<rpc::BlockNumber>,
+        best: BlockNumber,
+    ) -> Result<BlockNumber, FilterError> {
+        match block {
+            None | Some(rpc::BlockNumber::Latest) | Some(rpc::BlockNumber::Pending) => Ok(best),
+            Some(block) => {
+                self.client().convert_block_number(block)?.ok_or(FilterError::UnknownBlock)
+            }
+        }
+    }
+
+    /// Installs a filter whose changes start with the next block.
+    fn install(&self, kind: FilterKind) -> Result<U256, FilterError> {
+        let next_block = self.client().chain_info()?.best_number + 1;
+        let id = {
+            let mut next_id = self.inner.next_id.lock();
+            let id = *next_id;
+            *next_id += 1;
+            id
+        };
+        self.inner.filters.lock().insert(id, ActiveFilter { kind, next_block });
+        Ok(U256::from(id))
+    }
+
+    /// Returns the changes of the installed filter since it was polled last.
+    fn changes(&self, id: usize) -> Result<FilterChanges, FilterError> {
+        let best = self.client().chain_info()?.best_number;
+        let (kind, from) = {
+            let filters = self.inner.filters.lock();
+            let filter = filters.get(&id).ok_or(FilterError::FilterNotFound)?;
+            (filter.kind.clone(), filter.next_block)
+        };
+        if from > best {
+            return Ok(FilterChanges::Empty)
+        }
+
+        let changes = match kind {
+            FilterKind::Blocks => {
+                let mut hashes = Vec::new();
+                for number in from..=best {
+                    let hash = self.client().block_hash(number.into())?;
+                    hashes.push(hash.ok_or(FilterError::UnknownBlock)?);
+                }
+                FilterChanges::Hashes(hashes)
+            }
+            FilterKind::Logs(filter) => {
+                let FilterBlockOption::Range { from_block, to_block } = filter.block_option else {
+                    return Ok(FilterChanges::Empty)
+                };
+                let from = from.max(self.resolve(from_block, best)?);
+                let to = best.min(self.resolve(to_block, best)?);
+                let logs = if from <= to {
+                    self.logs_in_range(&LogFilter::new(&filter), from, to)?
+                } else {
+                    Vec::new()
+                };
+                if logs.is_empty() {
+                    FilterChanges::Empty
+                } else {
+                    FilterChanges::Logs(logs)
+                }
+            }
+        };
+
+        // the filter only advances once the changes were read successfully
+        if let Some(filter) = self.inner.filters.lock().get_mut(&id) {
+            filter.next_block = best + 1;
+        }
+        Ok(changes)
+    }
+
+    /// Returns all logs that match the installed log filter.
+    fn installed_logs(&self, id: usize) -> Result<Vec<Log>, FilterError> {
+        let kind = {
+            let filters = self.inner.filters.lock();
+            filters.get(&id).ok_or(FilterError::FilterNotFound)?.kind.clone()
+        };
+        match kind {
+            FilterKind::Logs(filter) => self.logs_for_filter(&filter),
+            FilterKind::Blocks => Err(FilterError::FilterNotFound),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<Client> EthFilterApiServer for EthFilter<Client>
+where
+    Client: BlockProvider + HeaderProvider + ReceiptProvider + LogIndexProvider + 'static,
+{
+    fn new_filter(&self, filter: Filter) -> RpcResult<U256> {
+        self.install(FilterKind::Logs(filter)).map_err(filter_rpc_err)
+    }
+
+    fn new_block_filter(&self) -> RpcResult<U256> {
+        self.install(FilterKind::Blocks).map_err(filter_rpc_err)
+    }
+
+    /// Pending transactions are not tracked by installed filters.
+    fn new_pending_transaction_filter(&self) -> RpcResult<U256> {
+        Err(unsupported_rpc_err("eth_newPendingTransactionFilter"))
+    }
+
+    async fn filter_changes(&self, index: Index) -> RpcResult<FilterChanges> {
+        self.changes(index.into()).map_err(filter_rpc_err)
+    }
+
+    async fn filter_logs(&self, index: Index) -> RpcResult<Vec<Log>> {
+        self.installed_logs(index.into()).map_err(filter_rpc_err)
+    }
+
+    fn uninstall_filter(&self, index: Index) -> RpcResult<bool> {
+        Ok(self.inner.filters.lock().remove(&index.into()).is_some())
+    }
+
+    async fn logs(&self, filter: Filter) -> RpcResult<Vec<Log>> {
+        self.logs_for_filter(&filter).map_err(filter_rpc_err)
+    }
+}
+
+/// Container type `EthFilter`
+#[derive(Debug)]
+struct EthFilterInner<Client> {
+    /// The client that can interact with the chain.
+    client: Arc<Client>,
+    /// The limits of log queries.
+    config: FilterConfig,
+    /// The installed filters by id.
+    filters: Mutex<HashMap<usize, ActiveFilter>>,
+    /// The id of the next installed filter.
+    next_id: Mutex<usize>,
+}
+
+/// A filter installed with `eth_newFilter` or `eth_newBlockFilter`.
+#[derive(Debug)]
+struct ActiveFilter {
+    /// What the filter reports.
+    kind: FilterKind,
+    /// The first block whose changes were not polled yet.
+    next_block: BlockNumber,
+}
+
+/// The changes an installed filter reports.
+#[derive(Debug, Clone)]
+enum FilterKind {
+    /// The logs that match the filter.
+    Logs(Filter),
+    /// The hashes of new blocks.
+    Blocks,
+}
+
+/// The addresses and topics of a [Filter].
+///
+/// An empty list of addresses or topics of a position matches any address or topic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LogFilter {
+    addresses: Vec<Address>,
+    topics: Vec<Vec<H256>>,
+}
+
+// === impl LogFilter ===
+
+impl LogFilter {
+    pub(crate) fn new(filter: &Filter) -> Self {
+        let addresses = match &filter.address {
+            None => Vec::new(),
+            Some(ValueOrArray::Value(address)) => vec![*address],
+            Some(ValueOrArray::Array(addresses)) => addresses.clone(),
+        };
+        let topics = filter
+            .topics
+            .iter()
+            .map(|topic| match topic {
+                Some(ValueOrArray::Value(Some(topic))) => vec![*topic],
+                // a wildcard in the list matches any topic
+                Some(ValueOrArray::Array(topics)) if topics.iter().all(Option::is_some) => {
+                    topics.iter().flatten().copied().collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect();
+        Self { addresses, topics }
+    }
+
+    /// Returns `false` if the bloom rules out any matching log.
+    pub(crate) fn matches_bloom(&self, bloom: &Bloom) -> bool {
+        let contains_any = |inputs: &mut dyn Iterator<Item = &[u8]>| {
+            let mut inputs = inputs.peekable();
+            inputs.peek().is_none() || inputs.any(|input| bloom_contains(bloom, input))
+        };
+        contains_any(&mut self.addresses.iter().map(|address| address.as_bytes())) &&
+            self.topics
+                .iter()
+                .all(|topics| contains_any(&mut topics.iter().map(|topic| topic.as_bytes())))
+    }
+
+    /// Returns `true` if the log matches the addresses and the topics of every position.
+    pub(crate) fn matches(&self, log: &reth_primitives::Log) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+            return false
+        }
+        self.topics.iter().enumerate().all(|(idx, topics)| {
+            topics.is_empty() || log.topics.get(idx).map_or(false, |topic| topics.contains(topic))
+        })
+    }
+}
+
+/// Appends the logs of the block that match the filter.
+///
+/// The logs are marked as `removed` if the block was removed from the canonical chain.
+pub(crate) fn append_matching_logs(
+    logs: &mut Vec<Log>,
+    filter: &LogFilter,
+    block_hash: H256,
+    block: &Block,
+    receipts: &[Receipt],
+    removed: bool,
+) {
+    let mut log_index = 0u64;
+    for (tx_index, (tx, receipt)) in block.body.iter().zip(receipts).enumerate() {
+        for (tx_log_index, log) in receipt.logs.iter().enumerate() {
+            if filter.matches(log) {
+                logs.push(Log {
+                    address: log.address,
+                    topics: log.topics.clone(),
+                    data: log.data.clone(),
+                    block_hash: Some(block_hash),
+                    block_number: Some(U256::from(block.header.number)),
+                    transaction_hash: Some(tx.hash()),
+                    transaction_index: Some(U256::from(tx_index)),
+                    log_index: Some(U256::from(log_index)),
+                    transaction_log_index: Some(U256::from(tx_log_index)),
+                    removed,
+                });
+            }
+            log_index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::{core::Error as RpcError, types::error::CallError};
+    use reth_interfaces::test_utils::generators::random_signed_tx;
+    use reth_primitives::{bloom::logs_bloom, Header, TxType};
+    use reth_provider::ChainInfo;
+    use std::ops::RangeInclusive;
+
+    /// A chain of blocks with the given logs, the genesis has no transactions.
+    #[derive(Default)]
+    struct TestClient {
+        blocks: Mutex<Vec<(Block, Vec<Receipt>)>>,
+    }
+
+    impl TestClient {
+        fn new() -> Self {
+            let client = Self::default();
+            client.push_block(Vec::new());
+            client
+        }
+
+        /// Appends a block with one transaction per entry, which emits the given logs.
+        fn push_block(&self, logs: Vec<Vec<reth_primitives::Log>>) -> BlockNumber {
+            let mut blocks = self.blocks.lock();
+            let number = blocks.len() as u64;
+            let receipts = logs
+                .into_iter()
+                .map(|logs| Receipt {
+                    tx_type: TxType::Legacy,
+                    success: true,
+                    cumulative_gas_used: 0,
+                    bloom: logs_bloom(&logs),
+                    logs,
+                })
+                .collect::<Vec<_>>();
+            let header = Header {
+                number,
+                parent_hash: blocks
+                    .last()
+                    .map(|(block, _)| block.header.hash_slow())
+                    .unwrap_or_default(),
+                logs_bloom: logs_bloom(receipts.iter().flat_map(|receipt| &receipt.logs)),
+                ..Default::default()
+            };
+            let body = receipts.iter().map(|_| random_signed_tx()).collect();
+            blocks.push((Block { header, body, ommers: Vec::new() }, receipts));
+            number
+        }
+
+        /// Clears the logs bloom of the block, as if it was corrupted.
+        fn clear_bloom(&self, number: BlockNumber) {
+            self.blocks.lock()[number as usize].0.header.logs_bloom = Bloom::zero();
+        }
+
+        fn get(&self, number: BlockNumber) -> Option<(Block, Vec<Receipt>)> {
+            self.blocks.lock().get(number as usize).cloned()
+        }
+    }
+
+    impl HeaderProvider for TestClient {
+        fn header(&self, block_hash: &H256) -> reth_interfaces::Result<Option<Header>> {
+            Ok(self
+                .blocks
+                .lock()
+                .iter()
+                .map(|(block, _)| block.header.clone())
+                .find(|header| header.hash_slow() == *block_hash))
+        }
+
+        fn header_by_number(&self, num: u64) -> reth_interfaces::Result<Option<Header>> {
+            Ok(self.get(num).map(|(block, _)| block.header))
+        }
+
+        fn header_td(&self, _hash: &H256) -> reth_interfaces::Result<Option<U256>> {
+            Ok(None)
+        }
+    }
+
+    impl BlockProvider for TestClient {
+        fn chain_info(&self) -> reth_interfaces::Result<ChainInfo> {
+            let blocks = self.blocks.lock();
+            let (best, _) = blocks.last().expect("genesis");
+            Ok(ChainInfo {
+                best_hash: best.header.hash_slow(),
+                best_number: best.header.number,
+                last_finalized: None,
+                safe_finalized: None,
+            })
+        }
+
+        fn block(&self, id: BlockId) -> reth_interfaces::Result<Option<Block>> {
+            Ok(self.block_number_for_id(id)?.and_then(|number| self.get(number)).map(|(b, _)| b))
+        }
+
+        fn block_number(&self, hash: H256) -> reth_interfaces::Result<Option<BlockNumber>> {
+            Ok(self.header(&hash)?.map(|header| header.number))
+        }
+
+        fn block_hash(&self, number: U256) -> reth_interfaces::Result<Option<H256>> {
+            Ok(self.get(number.as_u64()).map(|(block, _)| block.header.hash_slow()))
+        }
+    }
+
+    impl ReceiptProvider for TestClient {
+        fn receipts_by_block(&self, id: BlockId) -> reth_interfaces::Result<Option<Vec<Receipt>>> {
+            Ok(self.block_number_for_id(id)?.and_then(|number| self.get(number)).map(|(_, r)| r))
+        }
+    }
+
+    impl LogIndexProvider for TestClient {
+        fn log_filter_blocks(
+            &self,
+            _range: RangeInclusive<BlockNumber>,
+            _addresses: &[Address],
+            _topics: &[Vec<H256>],
+        ) -> reth_interfaces::Result<Option<Vec<BlockNumber>>> {
+            Ok(None)
+        }
+    }
+
+    fn log(address: u8, topics: &[u8]) -> reth_primitives::Log {
+        reth_primitives::Log {
+            address: Address::repeat_byte(address),
+            topics: topics.iter().map(|topic| H256::repeat_byte(*topic)).collect(),
+            data: Default::default(),
+        }
+    }
+
+    fn filter(from: u64, to: u64) -> Filter {
+        Filter::new().from_block(from).to_block(to)
+    }
+
+    fn error_code(err: RpcError) -> i32 {
+        let RpcError::Call(CallError::Custom(err)) = err else {
+            panic!("unexpected error {err:?}")
+        };
+        err.code()
+    }
+
+    #[tokio::test]
+    async fn get_logs_by_address_and_topics() {
+        let client = Arc::new(TestClient::new());
+        client.push_block(vec![vec![log(1, &[10, 20]), log(2, &[10])], vec![log(1, &[30])]]);
+        client.push_block(vec![vec![]]);
+        let block = client.push_block(vec![vec![log(3, &[])], vec![log(1, &[10, 21])]]);
+        let eth = EthFilter::new(client.clone());
+
+        let logs = eth.logs(filter(0, 3).address(Address::repeat_byte(1))).await.unwrap();
+        assert_eq!(logs.len(), 3);
+
+        let logs = eth
+            .logs(filter(0, 3).address(Address::repeat_byte(1)).topic0(H256::repeat_byte(10)))
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+        let (expected, _) = client.get(block).unwrap();
+        let last = logs.last().unwrap();
+        assert_eq!(last.block_number, Some(U256::from(block)));
+        assert_eq!(last.block_hash, Some(expected.header.hash_slow()));
+        assert_eq!(last.transaction_hash, Some(expected.body[1].hash()));
+        assert_eq!(last.transaction_index, Some(U256::from(1)));
+        assert_eq!(last.log_index, Some(U256::from(1)));
+        assert_eq!(last.transaction_log_index, Some(U256::from(0)));
+
+        // any of the topics at the second position
+        let topics = vec![Some(H256::repeat_byte(20)), Some(H256::repeat_byte(21))];
+        let logs = eth.logs(filter(0, 3).topic1(ValueOrArray::Array(topics))).await.unwrap();
+        assert_eq!(logs.len(), 2);
+
+        // the topic at the wrong position doesn't match
+        let logs = eth.logs(filter(0, 3).topic1(H256::repeat_byte(10))).await.unwrap();
+        assert!(logs.is_empty());
+
+        let hash = Filter::new().at_block_hash(expected.header.hash_slow());
+        assert_eq!(eth.logs(hash).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn skip_blocks_by_bloom() {
+        let client = Arc::new(TestClient::new());
+        let block = client.push_block(vec![vec![log(1, &[10])]]);
+        let eth = EthFilter::new(client.clone());
+        assert_eq!(eth.logs(filter(0, 1).address(Address::repeat_byte(1))).await.unwrap().len(), 1);
+
+        // the receipts are only read if the bloom may contain the log
+        client.clear_bloom(block);
+        assert!(eth.logs(filter(0, 1).address(Address::repeat_byte(1))).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn enforce_query_limits() {
+        let client = Arc::new(TestClient::new());
+        for _ in 0..4 {
+            client.push_block(vec![vec![log(1, &[]), log(1, &[])]]);
+        }
+        let eth = EthFilter::with_config(client, FilterConfig { max_block_range: 3, max_logs: 5 });
+
+        assert_eq!(eth.logs(filter(1, 2)).await.unwrap().len(), 4);
+        // too many logs
+        assert_eq!(error_code(eth.logs(filter(1, 3)).await.unwrap_err()), LIMIT_EXCEEDED_CODE);
+        // too many blocks
+        assert_eq!(error_code(eth.logs(filter(0, 3)).await.unwrap_err()), LIMIT_EXCEEDED_CODE);
+        assert_eq!(
+            error_code(eth.logs(filter(3, 1)).await.unwrap_err()),
+            jsonrpsee::types::error::INVALID_PARAMS_CODE
+        );
+    }
+
+    #[tokio::test]
+    async fn poll_filter_changes() {
+        let client = Arc::new(TestClient::new());
+        client.push_block(vec![vec![log(1, &[])]]);
+        let eth = EthFilter::new(client.clone());
+
+        let logs =
+            eth.new_filter(Filter::new().from_block(0).address(Address::repeat_byte(1))).unwrap();
+        let logs = Index::from(logs.as_usize());
+        let blocks = Index::from(eth.new_block_filter().unwrap().as_usize());
+        assert_eq!(eth.filter_changes(logs).await.unwrap(), FilterChanges::Empty);
+        assert_eq!(eth.filter_changes(blocks).await.unwrap(), FilterChanges::Empty);
+
+        // only the logs of blocks after the installation are changes
+        client.push_block(vec![vec![log(1, &[]), log(2, &[])]]);
+        let block = client.push_block(vec![vec![log(1, &[])]]);
+        let FilterChanges::Logs(changes) = eth.filter_changes(logs).await.unwrap() else {
+            panic!("expected logs")
+        };
+        assert_eq!(changes.len(), 2);
+        assert_eq!(eth.filter_changes(logs).await.unwrap(), FilterChanges::Empty);
+        assert_eq!(eth.filter_logs(logs).await.unwrap().len(), 3);
+
+        let hashes = (block - 1..=block)
+            .map(|number| client.get(number).unwrap().0.header.hash_slow())
+            .collect();
+        assert_eq!(eth.filter_changes(blocks).await.unwrap(), FilterChanges::Hashes(hashes));
+
+        assert!(eth.uninstall_filter(logs).unwrap());
+        assert!(!eth.uninstall_filter(logs).unwrap());
+        assert_eq!(error_code(eth.filter_changes(logs).await.unwrap_err()), FILTER_ERROR_CODE);
+
+        assert_eq!(
+            error_code(eth.new_pending_transaction_filter().unwrap_err()),
+            jsonrpsee::types::error::METHOD_NOT_FOUND_CODE
+        );
+    }
+}
@@ -0,0 +1,497 @@
+//! Gas price oracle that suggests tips based on the fees paid in recent blocks and answers
+//! `eth_feeHistory`.
+//!
+//! The fees of a block are derived from its transactions and receipts, which is too expensive to
+//! repeat for every request, so they are cached by block hash. Keying by hash keeps the cache
+//! valid across reorgs.
+
+use crate::result::{internal_rpc_err, rpc_err, state_rpc_err};
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use reth_consensus::verification::calculate_next_block_base_fee;
+use reth_primitives::{rpc::BlockId, Block, BlockNumber, Receipt, H256, U256};
+use reth_provider::{BlockProvider, ReceiptProvider};
+use reth_rpc_types::FeeHistory;
+use std::sync::Arc;
+
+/// The number of lowest tips of a block that are sampled for the suggested tip.
+const SAMPLES_PER_BLOCK: usize = 3;
+
+/// The maximum number of blocks whose fees are cached.
+const MAX_CACHED_BLOCKS: usize = 2048;
+
+/// One gwei in wei.
+const GWEI: u128 = 1_000_000_000;
+
+/// Settings of the gas price oracle.
+#[derive(Debug, Clone)]
+pub struct GasPriceOracleConfig {
+    /// The number of recent blocks that are sampled for the suggested tip.
+    pub blocks: u64,
+    /// The percentile of the sampled tips that is suggested.
+    pub percentile: u64,
+    /// The maximum suggested tip.
+    pub max_price: u128,
+    /// Tips below this value are not sampled.
+    pub ignore_price: u128,
+    /// The suggested tip if there are no samples and no tip was suggested before.
+    pub default_price: u128,
+    /// The maximum number of blocks of a `eth_feeHistory` response.
+    pub max_fee_history: u64,
+}
+
+impl Default for GasPriceOracleConfig {
+    fn default() -> Self {
+        Self {
+            blocks: 20,
+            percentile: 60,
+            max_price: 500 * GWEI,
+            ignore_price: 2,
+            default_price: GWEI,
+            max_fee_history: 1024,
+        }
+    }
+}
+
+/// Errors of the gas price oracle.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum GasPriceOracleError {
+    /// The chain could not be read.
+    #[error(transparent)]
+    State(#[from] reth_interfaces::Error),
+    /// The reward percentiles are not monotonically increasing or outside of `0..=100`.
+    #[error("invalid reward percentiles")]
+    InvalidRewardPercentiles,
+    /// A block below the best block is missing.
+    #[error("block #{0} not found")]
+    BlockNotFound(BlockNumber),
+}
+
+/// Converts an error of the gas price oracle into a JSON-RPC error.
+pub(crate) fn gas_oracle_rpc_err(err: GasPriceOracleError) -> jsonrpsee::core::Error {
+    match err {
+        GasPriceOracleError::State(err) => state_rpc_err(err),
+        GasPriceOracleError::InvalidRewardPercentiles => {
+            rpc_err(jsonrpsee::types::error::INVALID_PARAMS_CODE, err.to_string(), None)
+        }
+        GasPriceOracleError::BlockNotFound(_) => internal_rpc_err(err.to_string()),
+    }
+}
+
+/// Suggests tips and collects the fee history of the chain.
+#[derive(Debug)]
+pub(crate) struct GasPriceOracle<Client> {
+    /// The client that can interact with the chain.
+    client: Arc<Client>,
+    /// The settings of the oracle.
+    config: GasPriceOracleConfig,
+    /// The fees of recent blocks and the last suggested tip.
+    cache: Mutex<FeeCache>,
+}
+
+// === impl GasPriceOracle ===
+
+impl<Client> GasPriceOracle<Client>
+where
+    Client: BlockProvider + ReceiptProvider,
+{
+    /// Creates a new oracle with the given settings.
+    pub(crate) fn new(client: Arc<Client>, config: GasPriceOracleConfig) -> Self {
+        Self { client, config, cache: Mutex::new(FeeCache::default()) }
+    }
+
+    /// Returns the suggested tip per gas for a transaction to be included in the next block.
+    ///
+    /// This is the configured percentile of the lowest tips of the recent blocks. The tip is
+    /// computed once per best block.
+    pub(crate) fn suggest_tip(&self) -> Result<u128, GasPriceOracleError> {
+        let best = self.client.chain_info()?;
+        let last_price = self.cache.lock().last_price;
+        if let Some((hash, price)) = last_price {
+            if hash == best.best_hash {
+                return Ok(price)
+            }
+        }
+
+        let mut samples = Vec::new();
+        for number in (0..=best.best_number).rev().take(self.config.blocks as usize) {
+            let Some(fees) = self.block_fees(number)? else { break };
+            samples.extend(
+                fees.tips
+                    .iter()
+                    .map(|(tip, _)| *tip)
+                    .filter(|tip| *tip >= self.config.ignore_price)
+                    .take(SAMPLES_PER_BLOCK),
+            );
+        }
+
+        let price = if samples.is_empty() {
+            last_price.map(|(_, price)| price).unwrap_or(self.config.default_price)
+        } else {
+            samples.sort_unstable();
+            samples[(samples.len() - 1) * self.config.percentile as usize / 100]
+        }
+        .min(self.config.max_price);
+
+        self.cache.lock().last_price = Some((best.best_hash, price));
+        Ok(price)
+    }
+
+    /// Returns the suggested gas price for a legacy transaction: the suggested tip on top of the
+    /// base fee of the best block.
+    pub(crate) fn suggest_gas_price(&self) -> Result<U256, GasPriceOracleError> {
+        let tip = self.suggest_tip()?;
+        let best = self.client.chain_info()?.best_number;
+        let base_fee = self
+            .block_fees(best)?
+            .ok_or(GasPriceOracleError::BlockNotFound(best))?
+            .base_fee
+            .unwrap_or_default();
+        Ok(U256::from(tip) + U256::from(base_fee))
+    }
+
+    /// Returns the fee history of up to `block_count` blocks, ending with `newest_block`.
+    ///
+    /// Blocks after the best block are not available, so the range ends at the best block at
+    /// most. If `reward_percentiles` are given, the response contains the tip at each of the
+    /// percentiles of every block, weighted by the gas used by the transactions.
+    pub(crate) fn fee_history(
+        &self,
+        block_count: u64,
+        newest_block: BlockNumber,
+        reward_percentiles: Option<&[f64]>,
+    ) -> Result<FeeHistory, GasPriceOracleError> {
+        if let Some(percentiles) = reward_percentiles {
+            if percentiles.iter().any(|p| !(0.0..=100.0).contains(p)) ||
+                percentiles.windows(2).any(|w| w[0] > w[1])
+            {
+                return Err(GasPriceOracleError::InvalidRewardPercentiles)
+            }
+        }
+
+        let newest_block = newest_block.min(self.client.chain_info()?.best_number);
+        let block_count = block_count.min(self.config.max_fee_history).min(newest_block + 1);
+        let mut history = FeeHistory {
+            base_fee_per_gas: Vec::new(),
+            gas_used_ratio: Vec::new(),
+            oldest_block: U256::zero(),
+            reward: reward_percentiles.map(|_| Vec::new()),
+        };
+        if block_count == 0 {
+            return Ok(history)
+        }
+
+        let oldest_block = newest_block + 1 - block_count;
+        history.oldest_block = U256::from(oldest_block);
+        let mut next_base_fee = 0;
+        for number in oldest_block..=newest_block {
+            let fees =
+                self.block_fees(number)?.ok_or(GasPriceOracleError::BlockNotFound(number))?;
+            history.base_fee_per_gas.push(U256::from(fees.base_fee.unwrap_or_default()));
+            history.gas_used_ratio.push(fees.gas_used_ratio());
+            if let (Some(reward), Some(percentiles)) = (&mut history.reward, reward_percentiles) {
+                reward.push(fees.rewards(percentiles));
+            }
+            next_base_fee = fees.next_base_fee();
+        }
+        // the base fee of the next block is derived from the newest block
+        history.base_fee_per_gas.push(U256::from(next_base_fee));
+
+        Ok(history)
+    }
+
+    /// Returns the fees of the canonical block, or `None` if there's no such block.
+    fn block_fees(
+        &self,
+        number: BlockNumber,
+    ) -> Result<Option<Arc<BlockFees>>, GasPriceOracleError> {
+        let Some(hash) = self.client.block_hash(U256::from(number))? else { return Ok(None) };
+        if let Some(fees) = self.cache.lock().blocks.get_refresh(&hash) {
+            return Ok(Some(fees.clone()))
+        }
+
+        let Some(block) = self.client.block(BlockId::Hash(hash))? else { return Ok(None) };
+        let receipts = self.client.receipts_by_block(BlockId::Hash(hash))?.unwrap_or_default();
+        let fees = Arc::new(BlockFees::new(&block, &receipts));
+
+        let mut cache = self.cache.lock();
+        cache.blocks.insert(hash, fees.clone());
+        while cache.blocks.len() > MAX_CACHED_BLOCKS {
+            cache.blocks.pop_front();
+        }
+        Ok(Some(fees))
+    }
+}
+
+/// The cached state of the [GasPriceOracle].
+#[derive(Debug, Default)]
+struct FeeCache {
+    /// The fees of recent blocks by hash, the least recently used block first.
+    blocks: LinkedHashMap<H256, Arc<BlockFees>>,
+    /// The hash of the best block and the tip that was suggested for it.
+    last_price: Option<(H256, u128)>,
+}
+
+/// The fees paid in a block.
+#[derive(Debug, Clone, PartialEq)]
+struct BlockFees {
+    /// The base fee of the block, `None` before London.
+    base_fee: Option<u64>,
+    /// The gas used by all transactions of the block.
+    gas_used: u64,
+    /// The gas limit of the block.
+    gas_limit: u64,
+    /// The effective tip and the gas used of every transaction, sorted by tip.
+    tips: Vec<(u128, u64)>,
+}
+
+// === impl BlockFees ===
+
+impl BlockFees {
+    /// Collects the fees of the block, the gas used by each transaction is derived from the
+    /// cumulative gas used of the receipts.
+    fn new(block: &Block, receipts: &[Receipt]) -> Self {
+        let base_fee = block.header.base_fee_per_gas;
+        let mut cumulative_gas_used = 0;
+        let mut tips = block
+            .body
+            .iter()
+            .zip(receipts)
+            .map(|(tx, receipt)| {
+                let gas_used = receipt.cumulative_gas_used.saturating_sub(cumulative_gas_used);
+                cumulative_gas_used = receipt.cumulative_gas_used;
+                let tip =
+                    tx.effective_tip_per_gas(base_fee.unwrap_or_default()).unwrap_or_default();
+                (tip, gas_used)
+            })
+            .collect::<Vec<_>>();
+        tips.sort_unstable_by_key(|(tip, _)| *tip);
+
+        Self { base_fee, gas_used: block.header.gas_used, gas_limit: block.header.gas_limit, tips }
+    }
+
+    /// Returns the ratio of the gas used to the gas limit.
+    fn gas_used_ratio(&self) -> f64 {
+        if self.gas_limit == 0 {
+            return 0.0
+        }
+        self.gas_used as f64 / self.gas_limit as f64
+    }
+
+    /// Returns the base fee of the next block, zero before London.
+    fn next_base_fee(&self) -> u64 {
+        self.base_fee
+            .map(|base_fee| calculate_next_block_base_fee(self.gas_used, self.gas_limit, base_fee))
+            .unwrap_or_default()
+    }
+
+    /// Returns the tip at each of the percentiles, weighted by the gas used of the transactions.
+    ///
+    /// The percentiles must be sorted. All rewards of empty blocks are zero.
+    fn rewards(&self, percentiles: &[f64]) -> Vec<U256> {
+        if self.tips.is_empty() {
+            return vec![U256::zero(); percentiles.len()]
+        }
+
+        let mut index = 0;
+        let mut gas_used = self.tips[0].1;
+        percentiles
+            .iter()
+            .map(|percentile| {
+                let threshold = (self.gas_used as f64 * percentile / 100.0) as u64;
+                while gas_used < threshold && index < self.tips.len() - 1 {
+                    index += 1;
+                    gas_used += self.tips[index].1;
+                }
+                U256::from(self.tips[index].0)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::{core::Error as RpcError, types::error::CallError};
+    use reth_interfaces::Result;
+    use reth_primitives::{
+        Header, Signature, Transaction, TransactionSigned, TxEip1559, TxLegacy, TxType,
+    };
+    use reth_provider::ChainInfo;
+
+    /// The gas used by every transaction of the test blocks.
+    const TX_GAS: u64 = 21_000;
+
+    /// A chain of blocks with the given transactions, each using [TX_GAS].
+    struct TestClient {
+        blocks: Mutex<Vec<(Block, Vec<Receipt>)>>,
+    }
+
+    impl TestClient {
+        fn new() -> Self {
+            let client = Self { blocks: Mutex::new(Vec::new()) };
+            client.push_block(None, Vec::new());
+            client
+        }
+
+        fn push_block(&self, base_fee: Option<u64>, txs: Vec<Transaction>) {
+            let mut blocks = self.blocks.lock();
+            let receipts = (1..=txs.len() as u64)
+                .map(|i| Receipt {
+                    tx_type: TxType::Legacy,
+                    success: true,
+                    cumulative_gas_used: i * TX_GAS,
+                    bloom: Default::default(),
+                    logs: Vec::new(),
+                })
+                .collect::<Vec<_>>();
+            let header = Header {
+                number: blocks.len() as u64,
+                parent_hash: blocks
+                    .last()
+                    .map(|(block, _)| block.header.hash_slow())
+                    .unwrap_or_default(),
+                base_fee_per_gas: base_fee,
+                gas_used: txs.len() as u64 * TX_GAS,
+                gas_limit: 4 * TX_GAS,
+                ..Default::default()
+            };
+            let body = txs
+                .into_iter()
+                .map(|tx| {
+                    TransactionSigned::from_transaction_and_signature(tx, Signature::default())
+                })
+                .collect();
+            blocks.push((Block { header, body, ommers: Vec::new() }, receipts));
+        }
+
+        fn get(&self, number: BlockNumber) -> Option<(Block, Vec<Receipt>)> {
+            self.blocks.lock().get(number as usize).cloned()
+        }
+    }
+
+    impl BlockProvider for TestClient {
+        fn chain_info(&self) -> Result<ChainInfo> {
+            let blocks = self.blocks.lock();
+            let (best, _) = blocks.last().expect("genesis");
+            Ok(ChainInfo {
+                best_hash: best.header.hash_slow(),
+                best_number: best.header.number,
+                last_finalized: None,
+                safe_finalized: None,
+            })
+        }
+
+        fn block(&self, id: BlockId) -> Result<Option<Block>> {
+            Ok(self.block_number_for_id(id)?.and_then(|number| self.get(number)).map(|(b, _)| b))
+        }
+
+        fn block_number(&self, hash: H256) -> Result<Option<BlockNumber>> {
+            Ok(self
+                .blocks
+                .lock()
+                .iter()
+                .find(|(block, _)| block.header.hash_slow() == hash)
+                .map(|(block, _)| block.header.number))
+        }
+
+        fn block_hash(&self, number: U256) -> Result<Option<H256>> {
+            Ok(self.get(number.as_u64()).map(|(block, _)| block.header.hash_slow()))
+        }
+    }
+
+    impl ReceiptProvider for TestClient {
+        fn receipts_by_block(&self, id: BlockId) -> Result<Option<Vec<Receipt>>> {
+            Ok(self.block_number_for_id(id)?.and_then(|number| self.get(number)).map(|(_, r)| r))
+        }
+    }
+
+    fn legacy(gas_price: u128) -> Transaction {
+        Transaction::Legacy(TxLegacy { gas_price, ..Default::default() })
+    }
+
+    fn eip1559(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> Transaction {
+        Transaction::Eip1559(TxEip1559 {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn suggest_tip_from_recent_blocks() {
+        let client = Arc::new(TestClient::new());
+        let config = GasPriceOracleConfig {
+            blocks: 2,
+            percentile: 50,
+            max_price: 100,
+            ignore_price: 2,
+            default_price: 7,
+            max_fee_history: 1024,
+        };
+        let oracle = GasPriceOracle::new(client.clone(), config);
+        assert_eq!(oracle.suggest_tip().unwrap(), 7);
+
+        // tips 40, 1, 20 and 5, the tip below the ignored price isn't sampled
+        client.push_block(Some(10), vec![legacy(50), legacy(11), legacy(30), legacy(15)]);
+        client.push_block(Some(10), vec![eip1559(200, 190)]);
+        assert_eq!(oracle.suggest_tip().unwrap(), 20);
+        assert_eq!(oracle.suggest_gas_price().unwrap(), U256::from(30));
+
+        // only the last two blocks are sampled and the tip is capped
+        client.push_block(Some(10), vec![legacy(11)]);
+        assert_eq!(oracle.suggest_tip().unwrap(), 100);
+
+        // without samples, the last tip is suggested again
+        client.push_block(Some(10), Vec::new());
+        client.push_block(Some(10), Vec::new());
+        assert_eq!(oracle.suggest_tip().unwrap(), 100);
+    }
+
+    #[test]
+    fn fee_history_with_rewards() {
+        let client = Arc::new(TestClient::new());
+        client.push_block(Some(100), vec![legacy(110), legacy(130), legacy(120)]);
+        client.push_block(Some(106), vec![eip1559(200, 3)]);
+        let oracle = GasPriceOracle::new(client, GasPriceOracleConfig::default());
+
+        let history = oracle.fee_history(2, 2, Some(&[0.0, 50.0, 100.0])).unwrap();
+        assert_eq!(history.oldest_block, U256::from(1));
+        assert_eq!(
+            history.base_fee_per_gas,
+            vec![U256::from(100), U256::from(106), U256::from(100)]
+        );
+        assert_eq!(history.gas_used_ratio, vec![0.75, 0.25]);
+        assert_eq!(
+            history.reward,
+            Some(vec![
+                vec![U256::from(10), U256::from(20), U256::from(30)],
+                vec![U256::from(3); 3]
+            ])
+        );
+
+        // the range ends at the best block and includes the genesis
+        let history = oracle.fee_history(10, 5, None).unwrap();
+        assert_eq!(history.oldest_block, U256::zero());
+        assert_eq!(history.base_fee_per_gas.len(), 4);
+        assert_eq!(history.base_fee_per_gas[0], U256::zero());
+        assert_eq!(history.gas_used_ratio[0], 0.0);
+        assert!(history.reward.is_none());
+
+        let history = oracle.fee_history(0, 2, Some(&[50.0])).unwrap();
+        assert!(history.base_fee_per_gas.is_empty());
+        assert_eq!(history.reward, Some(Vec::new()));
+    }
+
+    #[test]
+    fn reject_invalid_percentiles() {
+        let oracle = GasPriceOracle::new(Arc::new(TestClient::new()), Default::default());
+        for percentiles in [&[50.0, 10.0][..], &[101.0], &[-1.0], &[f64::NAN]] {
+            let err = oracle.fee_history(1, 0, Some(percentiles)).unwrap_err();
+            let RpcError::Call(CallError::Custom(err)) = gas_oracle_rpc_err(err) else {
+                panic!("unexpected error")
+            };
+            assert_eq!(err.code(), jsonrpsee::types::error::INVALID_PARAMS_CODE);
+        }
+    }
+}
@@ -1,7 +1,16 @@
 //! `eth` namespace handler implementation.
 
 mod api;
+mod filter;
+mod gas_oracle;
 mod pubsub;
+mod signer;
 
-pub use api::{EthApi, EthApiSpec};
+pub(crate) use api::{call_rpc_err, inspect_call, CallError};
+pub use api::{
+    CallConfig, EthApi, EthApiSpec, EthConfig, TransactionForwarder, DEFAULT_CALL_GAS_CAP,
+};
+pub use filter::{EthFilter, FilterConfig, DEFAULT_MAX_BLOCK_RANGE, DEFAULT_MAX_LOGS};
+pub use gas_oracle::GasPriceOracleConfig;
 pub use pubsub::EthPubSub;
+pub use signer::{key_address, UnlockedAccounts};
@@ -1,15 +1,37 @@
 //! `eth_` PubSub RPC handler implementation
+//!
+//! Subscribers follow the canonical chain through the [ChainNotification]s of the node. When
+//! blocks are reverted, e.g. because of a reorg, their logs are sent again with `removed: true`
+//! before the logs of the new canonical blocks.
 
-use jsonrpsee::{types::SubscriptionResult, SubscriptionSink};
-use reth_provider::BlockProvider;
+use crate::eth::filter::{append_matching_logs, LogFilter};
+use jsonrpsee::{
+    types::{
+        error::{ErrorObject, INVALID_PARAMS_CODE},
+        SubscriptionResult,
+    },
+    SubscriptionSink,
+};
+use reth_primitives::{
+    rpc::{self, BlockId},
+    BlockNumber, Header, H256,
+};
+use reth_provider::{
+    BlockProvider, ChainNotification, ChainNotifications, HeaderProvider, ReceiptProvider,
+};
 use reth_rpc_api::EthPubSubApiServer;
-use reth_rpc_types::pubsub::{Kind, Params};
+use reth_rpc_types::{
+    pubsub::{self, Kind, Params},
+    Log, Rich, RichHeader,
+};
+use reth_tasks::TaskExecutor;
 use reth_transaction_pool::TransactionPool;
-use std::sync::Arc;
+use std::{ops::RangeInclusive, sync::Arc};
+use tokio::sync::broadcast::{self, error::RecvError};
 
 /// `Eth` pubsub RPC implementation.
 ///
-/// This handles
+/// This handles `eth_subscribe` for new heads and logs.
 #[derive(Debug, Clone)]
 pub struct EthPubSub<Pool, Client> {
     /// All nested fields bundled together.
@@ -19,9 +41,17 @@ pub struct EthPubSub<Pool, Client> {
 // === impl EthPubSub ===
 
 impl<Pool, Client> EthPubSub<Pool, Client> {
-    /// Creates a new, shareable instance.
-    pub fn new(client: Arc<Client>, pool: Pool) -> Self {
-        let inner = EthPubSubInner { client, pool };
+    /// Creates a new, shareable instance that follows the canonical chain through the given
+    /// notifications.
+    ///
+    /// The subscriptions are served by tasks of the given executor.
+    pub fn new(
+        client: Arc<Client>,
+        pool: Pool,
+        chain_notifications: ChainNotifications,
+        executor: TaskExecutor,
+    ) -> Self {
+        let inner = EthPubSubInner { client, pool, chain_notifications, executor };
         Self { inner: Arc::new(inner) }
     }
 }
@@ -29,27 +59,157 @@ impl<Pool, Client> EthPubSub<Pool, Client> {
 impl<Pool, Client> EthPubSubApiServer for EthPubSub<Pool, Client>
 where
     Pool: TransactionPool + 'static,
-    Client: BlockProvider + 'static,
+    Client: BlockProvider + HeaderProvider + ReceiptProvider + 'static,
 {
     fn subscribe(
         &self,
         mut sink: SubscriptionSink,
-        _kind: Kind,
-        _params: Option<Params>,
+        kind: Kind,
+        params: Option<Params>,
     ) -> SubscriptionResult {
+        let subscription = match kind {
+            Kind::NewHeads => Subscription::NewHeads,
+            Kind::Logs => match params {
+                Some(Params::Logs(filter)) => Subscription::Logs(LogFilter::new(&filter)),
+                _ => Subscription::Logs(LogFilter::default()),
+            },
+            kind => {
+                let message = format!("unsupported subscription: {kind:?}");
+                sink.reject(ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>))?;
+                return Ok(())
+            }
+        };
+        // subscribe before accepting, so no notification is missed
+        let notifications = self.inner.chain_notifications.subscribe();
         sink.accept()?;
-        todo!()
+        self.inner.executor.spawn(
+            "eth subscription",
+            handle_accepted(self.inner.client.clone(), sink, subscription, notifications),
+        );
+        Ok(())
     }
 }
 
 /// The actual handler for and accepted [`EthPubSub::subscribe`] call.
-async fn handle_accepted<Pool, Client>(
-    _pool: Pool,
-    _client: Arc<Client>,
-    _accepted_sink: SubscriptionSink,
-    _kind: Kind,
-    _params: Option<Params>,
-) {
+///
+/// Runs until the subscriber unsubscribes or the node stops sending notifications. Notifications
+/// the subscriber falls behind on are skipped.
+async fn handle_accepted<Client>(
+    client: Arc<Client>,
+    mut accepted_sink: SubscriptionSink,
+    subscription: Subscription,
+    mut notifications: broadcast::Receiver<ChainNotification>,
+) where
+    Client: BlockProvider + HeaderProvider + ReceiptProvider,
+{
+    loop {
+        let notification = match notifications.recv().await {
+            Ok(notification) => notification,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        };
+        // the blocks may be gone already if they were reverted in the meantime
+        let Ok(results) = subscription.results(client.as_ref(), &notification) else { continue };
+        for result in results {
+            if !matches!(accepted_sink.send(&result), Ok(true)) {
+                return
+            }
+        }
+    }
+}
+
+/// An accepted subscription.
+#[derive(Debug)]
+enum Subscription {
+    /// The headers of new canonical blocks.
+    NewHeads,
+    /// The logs of new and reverted canonical blocks that match the filter.
+    Logs(LogFilter),
+}
+
+// === impl Subscription ===
+
+impl Subscription {
+    /// Returns the messages the subscriber is sent for the notification.
+    fn results<Client>(
+        &self,
+        client: &Client,
+        notification: &ChainNotification,
+    ) -> reth_interfaces::Result<Vec<pubsub::SubscriptionResult>>
+    where
+        Client: BlockProvider + HeaderProvider + ReceiptProvider,
+    {
+        match (self, notification) {
+            (Subscription::NewHeads, ChainNotification::Committed { range }) => {
+                let mut headers = Vec::new();
+                for number in range.clone() {
+                    let Some(header) = client.header_by_number(number)? else { break };
+                    let hash = header.hash_slow();
+                    headers.push(pubsub::SubscriptionResult::Header(Box::new(rich_header(
+                        &header, hash,
+                    ))));
+                }
+                Ok(headers)
+            }
+            (Subscription::NewHeads, ChainNotification::Reverted { .. }) => Ok(Vec::new()),
+            (Subscription::Logs(filter), ChainNotification::Committed { range }) => {
+                Ok(committed_logs(client, filter, range.clone())?
+                    .into_iter()
+                    .map(|log| pubsub::SubscriptionResult::Log(Box::new(log)))
+                    .collect())
+            }
+            (Subscription::Logs(filter), ChainNotification::Reverted { blocks }) => {
+                let mut logs = Vec::new();
+                for block in blocks.iter() {
+                    if filter.matches_bloom(&block.block.header.logs_bloom) {
+                        append_matching_logs(
+                            &mut logs,
+                            filter,
+                            block.hash,
+                            &block.block,
+                            &block.receipts,
+                            true,
+                        );
+                    }
+                }
+                Ok(logs
+                    .into_iter()
+                    .map(|log| pubsub::SubscriptionResult::Log(Box::new(log)))
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Returns the logs of the canonical blocks in the range that match the filter.
+fn committed_logs<Client>(
+    client: &Client,
+    filter: &LogFilter,
+    range: RangeInclusive<BlockNumber>,
+) -> reth_interfaces::Result<Vec<Log>>
+where
+    Client: BlockProvider + HeaderProvider + ReceiptProvider,
+{
+    let mut logs = Vec::new();
+    for number in range {
+        let Some(header) = client.header_by_number(number)? else { break };
+        if !filter.matches_bloom(&header.logs_bloom) {
+            continue
+        }
+        let id = BlockId::Number(rpc::BlockNumber::Number(number.into()));
+        let (Some(block), Some(receipts)) = (client.block(id)?, client.receipts_by_block(id)?)
+        else {
+            break
+        };
+        append_matching_logs(&mut logs, filter, header.hash_slow(), &block, &receipts, false);
+    }
+    Ok(logs)
+}
+
+/// Converts the header of a canonical block to its RPC representation.
+fn rich_header(header: &Header, hash: H256) -> RichHeader {
+    let inner = reth_rpc_types::Header::from_primitive(header, hash);
+    Rich { inner, extra_info: Default::default() }
 }
 
 /// Container type `EthApi`
@@ -59,5 +219,60 @@ struct EthPubSubInner<Pool, Client> {
     pool: Pool,
     /// The client that can interact with the chain.
     client: Arc<Client>,
-    // TODO needs spawn access
+    /// The notifications about changes of the canonical chain.
+    chain_notifications: ChainNotifications,
+    /// The executor of the tasks that serve the subscriptions.
+    executor: TaskExecutor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_interfaces::test_utils::generators::random_signed_tx;
+    use reth_primitives::{bloom::logs_bloom, Address, Block, Receipt, TxType, U256};
+    use reth_provider::{test_utils::TestApi, CanonicalBlock};
+
+    #[test]
+    fn send_removed_logs_of_reverted_blocks() {
+        let log = |address| reth_primitives::Log {
+            address,
+            topics: Vec::new(),
+            data: Default::default(),
+        };
+        let logs = vec![log(Address::from_low_u64_be(1)), log(Address::from_low_u64_be(2))];
+        let receipt = Receipt {
+            tx_type: TxType::Legacy,
+            success: true,
+            cumulative_gas_used: 0,
+            bloom: logs_bloom(&logs),
+            logs,
+        };
+        let header = Header { number: 1, logs_bloom: receipt.bloom, ..Default::default() };
+        let block = CanonicalBlock {
+            hash: header.hash_slow(),
+            block: Block { header, body: vec![random_signed_tx()], ommers: Vec::new() },
+            receipts: vec![receipt],
+            state: Default::default(),
+        };
+        let reverted = ChainNotification::Reverted { blocks: Arc::new(vec![block.clone()]) };
+
+        let filter = rpc::Filter::new().address(Address::from_low_u64_be(2));
+        let results = Subscription::Logs(LogFilter::new(&filter)).results(&TestApi, &reverted);
+        let logs = results
+            .unwrap()
+            .into_iter()
+            .map(|result| match result {
+                pubsub::SubscriptionResult::Log(log) => *log,
+                result => panic!("unexpected result {result:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].removed);
+        assert_eq!(logs[0].address, Address::from_low_u64_be(2));
+        assert_eq!(logs[0].block_hash, Some(block.hash));
+        assert_eq!(logs[0].log_index, Some(U256::from(1)));
+
+        // reverted blocks have no new heads
+        assert!(Subscription::NewHeads.results(&TestApi, &reverted).unwrap().is_empty());
+    }
 }
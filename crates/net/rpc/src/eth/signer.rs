@@ -0,0 +1,124 @@
+//! Signing with the keys of unlocked accounts for `eth_sign` and `eth_signTransaction`.
+
+use reth_primitives::{
+    keccak256, Address, Bytes, Signature, Transaction, TransactionSigned, H256, U256,
+};
+use secp256k1::{Message, PublicKey, SecretKey, SECP256K1};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Returns the address of the account of the key.
+pub fn key_address(key: &SecretKey) -> Address {
+    let public = PublicKey::from_secret_key(SECP256K1, key);
+    let hash = keccak256(&public.serialize_uncompressed()[1..]);
+    Address::from_slice(&hash[12..])
+}
+
+/// The accounts that sign messages and transactions on request.
+///
+/// Anyone with access to the RPC servers can sign with these keys, so accounts should only be
+/// unlocked for development.
+#[derive(Clone, Default)]
+pub struct UnlockedAccounts {
+    /// The id of the chain transactions are signed for.
+    chain_id: u64,
+    keys: Arc<BTreeMap<Address, SecretKey>>,
+}
+
+// === impl UnlockedAccounts ===
+
+impl UnlockedAccounts {
+    /// Creates the accounts of the keys, which sign transactions for the chain with the given id.
+    pub fn new(chain_id: u64, keys: impl IntoIterator<Item = SecretKey>) -> Self {
+        let keys = keys.into_iter().map(|key| (key_address(&key), key)).collect();
+        Self { chain_id, keys: Arc::new(keys) }
+    }
+
+    /// Returns the id of the chain transactions are signed for.
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Returns the addresses of the accounts in ascending order.
+    pub fn addresses(&self) -> Vec<Address> {
+        self.keys.keys().copied().collect()
+    }
+
+    /// Returns true if the account is unlocked.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.keys.contains_key(address)
+    }
+
+    /// Signs the hash with the key of the account, `None` if the account is not unlocked.
+    pub fn sign_hash(&self, address: &Address, hash: H256) -> Option<Signature> {
+        let key = self.keys.get(address)?;
+        let message = Message::from_slice(hash.as_bytes()).expect("hash is 32 bytes");
+        let (recovery_id, data) =
+            SECP256K1.sign_ecdsa_recoverable(&message, key).serialize_compact();
+        Some(Signature {
+            r: U256::from_big_endian(&data[..32]),
+            s: U256::from_big_endian(&data[32..]),
+            odd_y_parity: recovery_id.to_i32() != 0,
+        })
+    }
+
+    /// Signs the message as specified by [EIP-191](https://eips.ethereum.org/EIPS/eip-191) and
+    /// returns the 65 bytes of `r`, `s` and `v`, where `v` is 27 or 28.
+    pub fn sign_message(&self, address: &Address, message: &[u8]) -> Option<Bytes> {
+        let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+        prefixed.extend_from_slice(message);
+        let signature = self.sign_hash(address, keccak256(prefixed))?;
+
+        let mut out = vec![0; 65];
+        signature.r.to_big_endian(&mut out[..32]);
+        signature.s.to_big_endian(&mut out[32..64]);
+        out[64] = 27 + signature.odd_y_parity as u8;
+        Some(out.into())
+    }
+
+    /// Signs the transaction with the key of the account.
+    pub fn sign_transaction(
+        &self,
+        address: &Address,
+        transaction: Transaction,
+    ) -> Option<TransactionSigned> {
+        let signature = self.sign_hash(address, transaction.signature_hash())?;
+        Some(TransactionSigned::from_transaction_and_signature(transaction, signature))
+    }
+}
+
+impl std::fmt::Debug for UnlockedAccounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnlockedAccounts")
+            .field("chain_id", &self.chain_id)
+            .field("addresses", &self.addresses())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{hex_literal::hex, TxEip1559};
+
+    #[test]
+    fn sign_message() {
+        // the first account of the `test test ... junk` mnemonic
+        let key = SecretKey::from_slice(&hex!(
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        ))
+        .unwrap();
+        let address = Address::from(hex!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266"));
+        let accounts = UnlockedAccounts::new(1, [key]);
+        assert_eq!(accounts.addresses(), vec![address]);
+
+        let signature = accounts.sign_message(&address, b"hello").unwrap();
+        assert_eq!(signature.len(), 65);
+        assert!(signature[64] == 27 || signature[64] == 28);
+        assert!(accounts.sign_message(&Address::zero(), b"hello").is_none());
+
+        let signed = accounts
+            .sign_transaction(&address, Transaction::Eip1559(TxEip1559::default()))
+            .unwrap();
+        assert_eq!(signed.recover_signer(), Some(address));
+    }
+}
@@ -11,12 +11,38 @@
 //!
 //! Provides the implementation of all RPC interfaces.
 
+mod admin;
+mod auth;
+mod cache;
+mod debug;
 mod engine;
 mod eth;
+mod limits;
 mod net;
+mod trace;
+mod txpool;
+mod web3;
 
+pub use admin::AdminApi;
+pub use auth::{
+    AuthLayer, AuthService, Claims, JwtError, JwtSecret, JWT_MAX_IAT_DIFF, JWT_SECRET_LEN,
+};
+pub use cache::{ResponseCache, DEFAULT_MAX_CACHE_BYTES};
+pub use debug::DebugApi;
 pub use engine::EngineApi;
-pub use eth::{EthApi, EthApiSpec, EthPubSub};
+pub use eth::{
+    key_address, CallConfig, EthApi, EthApiSpec, EthConfig, EthFilter, EthPubSub, FilterConfig,
+    GasPriceOracleConfig, TransactionForwarder, UnlockedAccounts, DEFAULT_CALL_GAS_CAP,
+    DEFAULT_MAX_BLOCK_RANGE, DEFAULT_MAX_LOGS,
+};
+pub use limits::{
+    ConnectionLimiter, RpcLimits, RpcLimitsLayer, RpcLimitsService, DEFAULT_MAX_BATCH_LEN,
+    DEFAULT_MAX_CONNECTIONS, DEFAULT_MAX_REQUEST_SIZE, DEFAULT_MAX_RESPONSE_SIZE,
+    DEFAULT_METHOD_CONCURRENCY,
+};
 pub use net::NetApi;
+pub use trace::{TraceApi, TraceConfig, DEFAULT_MAX_TRACE_BLOCK_RANGE};
+pub use txpool::TxPoolApi;
+pub use web3::{Web3Api, CLIENT_VERSION};
 
 pub(crate) mod result;
@@ -0,0 +1,534 @@
+//! Limits of the calls handled by an RPC server.
+//!
+//! The [RpcLimitsLayer] rejects batches with too many calls and bounds the number of concurrent
+//! calls of expensive methods. It applies to HTTP requests and IPC messages, the methods of the
+//! WebSocket server are wrapped instead, see [RpcLimitsLayer::limit_methods]. The sizes and the
+//! number of connections are limited by the servers themselves, the [ConnectionLimiter] bounds the
+//! connections of each remote IP.
+
+use futures::future::{self, BoxFuture, Either, FutureExt, Ready};
+use hyper::{body::HttpBody, Body, Request, Response, StatusCode};
+use jsonrpsee::{
+    core::{traits::ToRpcParams, Error as RpcError},
+    Methods, RpcModule,
+};
+use parking_lot::Mutex;
+use reth_tasks::TaskExecutor;
+use serde::{de::IgnoredAny, Deserialize};
+use serde_json::value::RawValue;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+use tower::{Layer, Service};
+use tracing::{debug, trace, warn};
+
+/// The default maximum size of a request in bytes.
+pub const DEFAULT_MAX_REQUEST_SIZE: u32 = 15 * 1024 * 1024;
+
+/// The default maximum size of a response in bytes.
+pub const DEFAULT_MAX_RESPONSE_SIZE: u32 = 160 * 1024 * 1024;
+
+/// The default maximum number of connections of a server.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 100;
+
+/// The default maximum number of calls in a batch.
+pub const DEFAULT_MAX_BATCH_LEN: usize = 1000;
+
+/// The default maximum number of concurrent calls of the methods that replay blocks.
+pub const DEFAULT_METHOD_CONCURRENCY: &[(&str, usize)] =
+    &[("debug_accountRange", 4), ("debug_traceBlock", 4), ("trace_block", 4), ("trace_filter", 4)];
+
+/// The error code of a request that exceeds a limit.
+const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
+/// The limits of the calls handled by an RPC server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcLimits {
+    /// The maximum size of an HTTP request in bytes.
+    pub max_request_size: u32,
+    /// The maximum number of calls in a batch, `None` for no limit.
+    pub max_batch_len: Option<usize>,
+    /// The maximum number of concurrent calls of each method.
+    pub method_concurrency: BTreeMap<String, usize>,
+}
+
+impl Default for RpcLimits {
+    fn default() -> Self {
+        Self {
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            max_batch_len: Some(DEFAULT_MAX_BATCH_LEN),
+            method_concurrency: DEFAULT_METHOD_CONCURRENCY
+                .iter()
+                .map(|(method, permits)| (method.to_string(), *permits))
+                .collect(),
+        }
+    }
+}
+
+/// A [Layer] that enforces [RpcLimits].
+///
+/// Calls of a method with a concurrency limit wait until a slot is available, batches calling
+/// the method take a single slot.
+#[derive(Debug, Clone)]
+pub struct RpcLimitsLayer {
+    limiter: Arc<Limiter>,
+}
+
+// === impl RpcLimitsLayer ===
+
+impl RpcLimitsLayer {
+    /// Creates a new layer that enforces the given limits.
+    ///
+    /// A concurrency limit of zero disables the limit of the method.
+    pub fn new(limits: RpcLimits) -> Self {
+        let semaphores = limits
+            .method_concurrency
+            .into_iter()
+            .filter(|(_, permits)| *permits > 0)
+            .map(|(method, permits)| (method, Arc::new(Semaphore::new(permits))))
+            .collect();
+        let limiter = Limiter {
+            max_request_size: limits.max_request_size,
+            max_batch_len: limits.max_batch_len,
+            semaphores,
+        };
+        Self { limiter: Arc::new(limiter) }
+    }
+
+    /// Wraps the methods with a concurrency limit, so their calls wait for a slot of the same
+    /// semaphores as the calls through the layer.
+    ///
+    /// The layer only sees the upgrade request of a WebSocket connection, the methods of the
+    /// WebSocket server are limited this way instead. Batches are not limited.
+    pub fn limit_methods(&self, methods: Methods) -> Result<Methods, RpcError> {
+        let names = methods
+            .method_names()
+            .filter(|name| self.limiter.semaphores.contains_key(*name))
+            .collect::<Vec<_>>();
+        let mut limited = methods.clone();
+        let mut wrappers = RpcModule::new(());
+        for name in names {
+            limited.remove_method(name);
+            let semaphore = self.limiter.semaphores[name].clone();
+            let methods = methods.clone();
+            wrappers.register_async_method(name, move |params, _| {
+                let (semaphore, methods) = (semaphore.clone(), methods.clone());
+                let params = RawParams(params.as_str().map(ToString::to_string));
+                async move {
+                    let _permit =
+                        semaphore.acquire_owned().await.expect("semaphore is never closed");
+                    methods.call::<_, serde_json::Value>(name, params).await
+                }
+            })?;
+        }
+        limited.merge(wrappers)?;
+        Ok(limited)
+    }
+}
+
+/// The unparsed parameters of a call that is passed on to the wrapped method.
+struct RawParams(Option<String>);
+
+impl ToRpcParams for RawParams {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, RpcError> {
+        self.0.map(RawValue::from_string).transpose().map_err(RpcError::ParseError)
+    }
+}
+
+impl<S> Layer<S> for RpcLimitsLayer {
+    type Service = RpcLimitsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcLimitsService { limiter: self.limiter.clone(), inner }
+    }
+}
+
+/// The [Service] of the [RpcLimitsLayer].
+#[derive(Debug, Clone)]
+pub struct RpcLimitsService<S> {
+    limiter: Arc<Limiter>,
+    inner: S,
+}
+
+/// HTTP requests.
+impl<S> Service<Request<Body>> for RpcLimitsService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // the clone is not ready, the ready service handles the request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        async move {
+            let (parts, body) = req.into_parts();
+            let body = match read_body(body, limiter.max_request_size).await {
+                Ok(body) => body,
+                Err(status) => {
+                    let response = Response::builder()
+                        .status(status)
+                        .body(Body::empty())
+                        .expect("valid response");
+                    return Ok(response)
+                }
+            };
+            let semaphores = match limiter.admit(&body) {
+                Ok(semaphores) => semaphores,
+                Err(error) => {
+                    let response = Response::builder()
+                        .header(hyper::header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(error))
+                        .expect("valid response");
+                    return Ok(response)
+                }
+            };
+            let _permits = acquire(semaphores).await;
+            inner.call(Request::from_parts(parts, Body::from(body))).await
+        }
+        .boxed()
+    }
+}
+
+/// IPC messages.
+impl<S> Service<String> for RpcLimitsService<S>
+where
+    S: Service<String, Response = String>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = String;
+    type Error = S::Error;
+    type Future =
+        Either<BoxFuture<'static, Result<String, S::Error>>, Ready<Result<String, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: String) -> Self::Future {
+        match self.limiter.admit(request.as_bytes()) {
+            Ok(semaphores) => {
+                // the call is only handled once the returned future is polled
+                let call = self.inner.call(request);
+                Either::Left(
+                    async move {
+                        let _permits = acquire(semaphores).await;
+                        call.await
+                    }
+                    .boxed(),
+                )
+            }
+            Err(error) => Either::Right(future::ok(error)),
+        }
+    }
+}
+
+/// The shared state of the [RpcLimitsLayer].
+#[derive(Debug)]
+struct Limiter {
+    max_request_size: u32,
+    max_batch_len: Option<usize>,
+    semaphores: BTreeMap<String, Arc<Semaphore>>,
+}
+
+// === impl Limiter ===
+
+impl Limiter {
+    /// Returns the semaphores of the called methods, or the JSON-RPC error response if the request
+    /// exceeds a limit.
+    ///
+    /// Requests that are not valid JSON-RPC are admitted, the server responds with the error.
+    fn admit(&self, request: &[u8]) -> Result<Vec<Arc<Semaphore>>, String> {
+        let methods = called_methods(request);
+        if let Some(max) = self.max_batch_len {
+            if methods.len() > max {
+                let message =
+                    format!("batch of {} calls exceeds the limit of {max}", methods.len());
+                return Err(limit_error(message))
+            }
+        }
+        // the semaphores are always acquired in the same order
+        Ok(self
+            .semaphores
+            .iter()
+            .filter(|(method, _)| {
+                methods.iter().any(|called| called.as_deref() == Some(method.as_str()))
+            })
+            .map(|(_, semaphore)| semaphore.clone())
+            .collect())
+    }
+}
+
+/// Acquires a permit of every semaphore.
+async fn acquire(semaphores: Vec<Arc<Semaphore>>) -> Vec<OwnedSemaphorePermit> {
+    let mut permits = Vec::with_capacity(semaphores.len());
+    for semaphore in semaphores {
+        permits.push(semaphore.acquire_owned().await.expect("semaphore is never closed"));
+    }
+    permits
+}
+
+/// A call of a request.
+#[derive(Deserialize)]
+struct Call<'a> {
+    #[serde(borrow)]
+    method: Cow<'a, str>,
+}
+
+/// An element of a batch, which doesn't have to be a valid call.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BatchEntry<'a> {
+    Call(#[serde(borrow)] Call<'a>),
+    Invalid(IgnoredAny),
+}
+
+/// Returns the methods of all calls of the request, including `None` for invalid batch entries.
+fn called_methods(request: &[u8]) -> Vec<Option<Cow<'_, str>>> {
+    if let Ok(batch) = serde_json::from_slice::<Vec<BatchEntry<'_>>>(request) {
+        return batch
+            .into_iter()
+            .map(|entry| match entry {
+                BatchEntry::Call(call) => Some(call.method),
+                BatchEntry::Invalid(_) => None,
+            })
+            .collect()
+    }
+    serde_json::from_slice::<Call<'_>>(request)
+        .map(|call| vec![Some(call.method)])
+        .unwrap_or_default()
+}
+
+/// Reads the body, failing with `413 Payload Too Large` if it's larger than `max_size`.
+async fn read_body(mut body: Body, max_size: u32) -> Result<Vec<u8>, StatusCode> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if bytes.len() + chunk.len() > max_size as usize {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE)
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Forwards the TCP connections of an address to a server, closing the connections of the remote
+/// IPs that already have the maximum number of connections open.
+///
+/// The servers only bound the total number of connections, so they listen on a local address
+/// behind the limiter instead.
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    listener: TcpListener,
+    server: SocketAddr,
+    max_per_ip: usize,
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+// === impl ConnectionLimiter ===
+
+impl ConnectionLimiter {
+    /// Binds the address whose connections are forwarded to the server at `server`.
+    pub async fn bind(addr: SocketAddr, server: SocketAddr, max_per_ip: usize) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(Self { listener, server, max_per_ip, connections: Default::default() })
+    }
+
+    /// Returns the address the limiter accepts the connections of.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts the connections, which are forwarded to the server by tasks of the executor.
+    pub async fn run(self, executor: TaskExecutor) {
+        loop {
+            let (stream, remote) = match self.listener.accept().await {
+                Ok(conn) => conn,
+                Err(err) => {
+                    warn!(target: "rpc::limits", ?err, "Failed to accept connection");
+                    continue
+                }
+            };
+            // the connection is closed once the stream is dropped
+            let Some(guard) = self.admit(remote.ip()) else {
+                debug!(target: "rpc::limits", %remote, "Connection exceeds the limit of the IP");
+                continue
+            };
+            let server = self.server;
+            executor.spawn("rpc connection", async move {
+                let _guard = guard;
+                if let Err(err) = forward(stream, server).await {
+                    trace!(target: "rpc::limits", %remote, ?err, "Connection closed");
+                }
+            });
+        }
+    }
+
+    /// Counts a new connection of the IP, unless it has the maximum number of connections open.
+    fn admit(&self, ip: IpAddr) -> Option<ConnectionGuard> {
+        let mut connections = self.connections.lock();
+        let open = connections.entry(ip).or_default();
+        if *open >= self.max_per_ip {
+            return None
+        }
+        *open += 1;
+        Some(ConnectionGuard { ip, connections: self.connections.clone() })
+    }
+}
+
+/// An open connection of a [ConnectionLimiter], which isn't counted anymore once dropped.
+struct ConnectionGuard {
+    ip: IpAddr,
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock();
+        if let Some(open) = connections.get_mut(&self.ip) {
+            *open -= 1;
+            if *open == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Copies the bytes between the connection and a new connection to the server until either is
+/// closed.
+async fn forward(mut stream: TcpStream, server: SocketAddr) -> io::Result<()> {
+    let mut server = TcpStream::connect(server).await?;
+    tokio::io::copy_bidirectional(&mut stream, &mut server).await?;
+    Ok(())
+}
+
+/// Returns the JSON-RPC error response for a request that exceeds a limit.
+fn limit_error(message: String) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": { "code": LIMIT_EXCEEDED_CODE, "message": message },
+        "id": null,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::Ipv4Addr, time::Duration};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn limiter(max_batch_len: usize, method_concurrency: &[(&str, usize)]) -> Arc<Limiter> {
+        let limits = RpcLimits {
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            max_batch_len: Some(max_batch_len),
+            method_concurrency: method_concurrency
+                .iter()
+                .map(|(method, permits)| (method.to_string(), *permits))
+                .collect(),
+        };
+        RpcLimitsLayer::new(limits).limiter
+    }
+
+    fn call(method: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","id":1,"method":"{method}","params":[]}}"#)
+    }
+
+    #[test]
+    fn parse_called_methods() {
+        assert_eq!(called_methods(call("eth_call").as_bytes()), vec![Some("eth_call".into())]);
+        let batch = format!("[{},1,{{}}]", call("trace_block"));
+        assert_eq!(called_methods(batch.as_bytes()), vec![Some("trace_block".into()), None, None]);
+        assert!(called_methods(b"not json").is_empty());
+    }
+
+    #[test]
+    fn reject_long_batches() {
+        let limiter = limiter(2, &[]);
+        assert!(limiter.admit(format!("[{0},{0}]", call("eth_call")).as_bytes()).is_ok());
+
+        // invalid entries count towards the limit
+        let error = limiter.admit(format!("[{},1,2]", call("eth_call")).as_bytes()).unwrap_err();
+        let error: serde_json::Value = serde_json::from_str(&error).unwrap();
+        assert_eq!(error["error"]["code"], LIMIT_EXCEEDED_CODE);
+    }
+
+    #[tokio::test]
+    async fn limit_method_concurrency() {
+        let limiter = limiter(10, &[("trace_block", 1), ("trace_filter", 0)]);
+        assert!(limiter.admit(call("eth_call").as_bytes()).unwrap().is_empty());
+        // no limit
+        assert!(limiter.admit(call("trace_filter").as_bytes()).unwrap().is_empty());
+
+        let batch = format!("[{0},{0}]", call("trace_block"));
+        let semaphores = limiter.admit(batch.as_bytes()).unwrap();
+        assert_eq!(semaphores.len(), 1);
+        let permits = acquire(semaphores).await;
+
+        let semaphores = limiter.admit(call("trace_block").as_bytes()).unwrap();
+        let waiting = tokio::time::timeout(Duration::from_millis(50), acquire(semaphores.clone()));
+        assert!(waiting.await.is_err());
+        drop(permits);
+        assert_eq!(acquire(semaphores).await.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn limit_connections_per_ip() {
+        // the server echoes the bytes of every connection
+        let server = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = server.accept().await {
+                tokio::spawn(async move {
+                    let (mut read, mut write) = stream.split();
+                    let _ = tokio::io::copy(&mut read, &mut write).await;
+                });
+            }
+        });
+        let limiter =
+            ConnectionLimiter::bind((Ipv4Addr::LOCALHOST, 0).into(), server_addr, 1).await.unwrap();
+        let addr = limiter.local_addr().unwrap();
+        tokio::spawn(limiter.run(TaskExecutor::current()));
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        first.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        first.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // the second connection of the IP is closed right away
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(second.read(&mut buf).await.unwrap(), 0);
+
+        // the slot is free again once the first connection is closed
+        drop(first);
+        let mut third = loop {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            if stream.write_all(b"pong").await.is_ok() && stream.read_exact(&mut buf).await.is_ok()
+            {
+                break stream
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(&buf, b"pong");
+        third.shutdown().await.unwrap();
+    }
+}
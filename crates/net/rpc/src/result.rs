@@ -85,6 +85,34 @@ impl<Ok> ToRpcResult<Ok, reth_interfaces::Error> for reth_interfaces::Result<Ok>
     }
 }
 
+/// Error code of failures to serve a request with the local state, e.g. because it was pruned.
+pub(crate) const STATE_ERROR_CODE: i32 = -32000;
+
+/// Converts an error of reading the state into a JSON-RPC error.
+///
+/// Requests for pruned historical state are answered with [`STATE_ERROR_CODE`] and a message that
+/// names the oldest available block, as are proofs of state that can't be proven and requests for
+/// the state of side chain blocks. Any other failure is an internal error.
+pub(crate) fn state_rpc_err(err: reth_interfaces::Error) -> RpcError {
+    match err {
+        reth_interfaces::Error::Provider(
+            err @ (reth_provider::Error::HistoryPruned { .. } |
+            reth_provider::Error::ProofUnavailable { .. } |
+            reth_provider::Error::NonCanonicalBlock { .. }),
+        ) => rpc_err(STATE_ERROR_CODE, err.to_string(), None),
+        err => internal_rpc_err(format!("failed to read state: {err}")),
+    }
+}
+
+/// Constructs the error of a method of an enabled namespace that the node does not support.
+pub(crate) fn unsupported_rpc_err(method: &str) -> RpcError {
+    rpc_err(
+        jsonrpsee::types::error::METHOD_NOT_FOUND_CODE,
+        format!("the method {method} is not supported"),
+        None,
+    )
+}
+
 /// Constructs an internal JSON-RPC error.
 pub(crate) fn internal_rpc_err(msg: impl Into<String>) -> jsonrpsee::core::Error {
     rpc_err(jsonrpsee::types::error::INTERNAL_ERROR_CODE, msg, None)
@@ -131,4 +159,19 @@ mod tests {
         let val = rpc_res.unwrap();
         assert_eq!(val, 100);
     }
+
+    #[test]
+    fn pruned_state_err() {
+        let err = state_rpc_err(
+            reth_provider::Error::HistoryPruned { block_number: 1, oldest: 10 }.into(),
+        );
+        let RpcError::Call(jsonrpsee::types::error::CallError::Custom(err)) = err else {
+            panic!("unexpected error {err:?}")
+        };
+        assert_eq!(err.code(), STATE_ERROR_CODE);
+        assert_eq!(
+            err.message(),
+            "Historical state of block #1 is pruned, the oldest available block is #10"
+        );
+    }
 }
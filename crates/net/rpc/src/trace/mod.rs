@@ -0,0 +1,713 @@
+//! Provides everything related to `trace_` namespace
+
+use crate::{
+    cache::ResponseCache,
+    eth::{call_rpc_err, inspect_call, CallConfig, CallError},
+    result::{rpc_err, state_rpc_err, unsupported_rpc_err},
+};
+use async_trait::async_trait;
+use jsonrpsee::{core::RpcResult as Result, types::error::INVALID_PARAMS_CODE};
+use reth_executor::{
+    call_tracer::{CallKind, CallTrace, CallTracer},
+    executor::execute_transactions_with_inspector,
+    revm_wrap::{State, SubState},
+};
+use reth_primitives::{
+    rpc::{self, BlockId},
+    Address, Block, BlockNumber, Bytes, TransactionSigned, TransactionSignedEcRecovered, H256, U64,
+};
+use reth_provider::{BlockProvider, HeaderProvider, StateProviderFactory, TransactionsProvider};
+use reth_rpc_api::TraceApiServer;
+use reth_rpc_types::{
+    trace::{filter::TraceFilter, parity::*},
+    CallRequest, Index,
+};
+use std::{collections::HashSet, sync::Arc};
+
+/// The default maximum number of blocks `trace_filter` replays, see
+/// [`TraceConfig::max_block_range`].
+pub const DEFAULT_MAX_TRACE_BLOCK_RANGE: u64 = 100;
+
+/// Error code of queries that exceed the limits of the [`TraceConfig`].
+const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
+/// Error code of traces that could not be created.
+const TRACE_ERROR_CODE: i32 = -32000;
+
+/// Settings of the `trace` API.
+#[derive(Debug, Clone)]
+pub struct TraceConfig {
+    /// The settings of `trace_call`, whose executor config is also used to replay blocks.
+    pub call: CallConfig,
+    /// The maximum number of blocks between the first and the last block of `trace_filter`.
+    ///
+    /// Every block of the range is replayed, so the range is much smaller than the one of log
+    /// queries.
+    pub max_block_range: u64,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self { call: CallConfig::default(), max_block_range: DEFAULT_MAX_TRACE_BLOCK_RANGE }
+    }
+}
+
+/// Errors of tracing transactions and calls.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum TraceError {
+    /// The chain or the state could not be read.
+    #[error(transparent)]
+    State(#[from] reth_interfaces::Error),
+    /// The traced call could not be executed.
+    #[error(transparent)]
+    Call(#[from] CallError),
+    /// A replayed transaction could not be executed.
+    #[error(transparent)]
+    Execution(#[from] reth_interfaces::executor::Error),
+    /// The requested block is unknown.
+    #[error("unknown block")]
+    UnknownBlock,
+    /// The requested transaction is not in a canonical block.
+    #[error("unknown transaction {0:?}")]
+    UnknownTransaction(H256),
+    /// The raw transaction could not be decoded.
+    #[error("failed to decode raw transaction: {0}")]
+    InvalidTransaction(#[from] reth_rlp::DecodeError),
+    /// The sender of a replayed transaction could not be recovered.
+    #[error("invalid signature of transaction {0:?}")]
+    InvalidSignature(H256),
+    /// The first block of the filter is after its last block.
+    #[error("invalid block range: from block {from} is after to block {to}")]
+    InvalidBlockRange {
+        /// The first block of the filter.
+        from: BlockNumber,
+        /// The last block of the filter.
+        to: BlockNumber,
+    },
+    /// The filter spans more blocks than allowed.
+    #[error("query exceeds max block range {0}")]
+    BlockRangeTooLarge(u64),
+}
+
+/// Converts an error of tracing into a JSON-RPC error.
+pub(crate) fn trace_rpc_err(err: TraceError) -> jsonrpsee::core::Error {
+    match err {
+        TraceError::State(err) => state_rpc_err(err),
+        TraceError::Call(err) => call_rpc_err(err),
+        TraceError::InvalidBlockRange { .. } | TraceError::InvalidTransaction(_) => {
+            rpc_err(INVALID_PARAMS_CODE, err.to_string(), None)
+        }
+        TraceError::BlockRangeTooLarge(_) => rpc_err(LIMIT_EXCEEDED_CODE, err.to_string(), None),
+        err => rpc_err(TRACE_ERROR_CODE, err.to_string(), None),
+    }
+}
+
+/// `trace` API implementation.
+///
+/// This type provides the functionality for handling parity style `trace_` requests. The traces
+/// are created by replaying blocks on top of the state of their parent, which must not be pruned.
+/// The traces of finalized blocks are cached.
+pub struct TraceApi<Client> {
+    /// The client that can interact with the chain.
+    client: Arc<Client>,
+    /// The settings of the API.
+    config: TraceConfig,
+    /// The cache of the traces of finalized blocks.
+    cache: ResponseCache,
+}
+
+impl<Client> TraceApi<Client>
+where
+    Client: BlockProvider + HeaderProvider + StateProviderFactory + TransactionsProvider + 'static,
+{
+    /// Creates a new instance of the `trace` API.
+    pub fn new(client: Arc<Client>, config: TraceConfig) -> Self {
+        Self::with_cache(client, config, ResponseCache::default())
+    }
+
+    /// Creates a new instance of the `trace` API that caches traces in the given cache, which may
+    /// be shared with other APIs.
+    pub fn with_cache(client: Arc<Client>, config: TraceConfig, cache: ResponseCache) -> Self {
+        Self { client, config, cache }
+    }
+
+    /// Returns the cached traces of the call of `method` with `params`, or computes them with `f`,
+    /// see [`ResponseCache::get_or_insert_with`].
+    fn cached<P, F>(
+        &self,
+        method: &'static str,
+        params: &P,
+        f: F,
+    ) -> std::result::Result<Option<Vec<LocalizedTransactionTrace>>, TraceError>
+    where
+        P: serde::Serialize,
+        F: FnOnce() -> std::result::Result<
+            Option<(BlockNumber, Vec<LocalizedTransactionTrace>)>,
+            TraceError,
+        >,
+    {
+        self.cache.set_finalized(self.client.chain_info()?.last_finalized);
+        self.cache.get_or_insert_with(method, params, f)
+    }
+
+    /// Executes the request on top of the state of the block and returns the requested traces.
+    ///
+    /// Only [`TraceType::Trace`] is supported, VM traces and state diffs are never returned.
+    fn trace_call_at(
+        &self,
+        request: CallRequest,
+        trace_types: HashSet<TraceType>,
+        at: Option<BlockId>,
+    ) -> std::result::Result<TraceResults, TraceError> {
+        let client = &self.client;
+        let best = client.chain_info()?.best_number;
+        let number = match at {
+            None | Some(BlockId::Number(rpc::BlockNumber::Pending)) => best,
+            Some(id) => client.block_number_for_id(id)?.ok_or(TraceError::UnknownBlock)?,
+        };
+        let header = client.header_by_number(number)?.ok_or(TraceError::UnknownBlock)?;
+
+        let mut tracer = CallTracer::default();
+        let config = &self.config.call;
+        let output = if number == best {
+            let db = SubState::new(State::new(client.latest()?));
+            inspect_call(config, &request, &header, db, &mut tracer)?
+        } else {
+            let db = SubState::new(State::new(client.history_by_block_number(number)?));
+            inspect_call(config, &request, &header, db, &mut tracer)?
+        };
+
+        let trace = trace_types
+            .contains(&TraceType::Trace)
+            .then(|| tracer.into_traces().into_iter().map(transaction_trace).collect());
+        Ok(TraceResults { output, trace, vm_trace: None, state_diff: None })
+    }
+
+    /// Returns the traces of the transaction, `None` if it's not in a canonical block.
+    fn transaction_traces_by_hash(
+        &self,
+        hash: H256,
+    ) -> std::result::Result<Option<Vec<LocalizedTransactionTrace>>, TraceError> {
+        let Some((number, index)) = self.client.transaction_block(hash)? else { return Ok(None) };
+        let Some(block) = self.client.block(block_id(number))? else { return Ok(None) };
+
+        // the transactions before the traced one are replayed to get the state it's executed in
+        let calls = self.replay(&block, index + 1)?.pop().unwrap_or_default();
+        let block_hash = block.header.hash_slow();
+        Ok(Some(
+            calls
+                .into_iter()
+                .map(|call| LocalizedTransactionTrace {
+                    trace: transaction_trace(call),
+                    transaction_position: Some(index),
+                    transaction_hash: Some(hash),
+                    block_number: block.header.number.into(),
+                    block_hash,
+                })
+                .collect(),
+        ))
+    }
+
+    /// Replays the transaction and returns its output and the requested traces.
+    fn replay_transaction_by_hash(
+        &self,
+        hash: H256,
+        trace_types: &HashSet<TraceType>,
+    ) -> std::result::Result<TraceResults, TraceError> {
+        let Some((number, index)) = self.client.transaction_block(hash)? else {
+            return Err(TraceError::UnknownTransaction(hash))
+        };
+        let block = self.client.block(block_id(number))?.ok_or(TraceError::UnknownBlock)?;
+        let calls = self.replay(&block, index + 1)?.pop().unwrap_or_default();
+        Ok(trace_results(calls, trace_types))
+    }
+
+    /// Replays all transactions of the block and returns the output and the requested traces of
+    /// each of them.
+    fn replay_block(
+        &self,
+        block_id: BlockId,
+        trace_types: &HashSet<TraceType>,
+    ) -> std::result::Result<Option<Vec<TraceResultsWithTransactionHash>>, TraceError> {
+        let Some(block) = self.client.block(block_id)? else { return Ok(None) };
+        let replayed = self.replay(&block, block.body.len())?;
+        Ok(Some(
+            block
+                .body
+                .iter()
+                .zip(replayed)
+                .map(|(transaction, calls)| TraceResultsWithTransactionHash {
+                    full_trace: trace_results(calls, trace_types),
+                    transaction_hash: transaction.hash(),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Returns the traces of all transactions of the block, followed by the reward of the
+    /// beneficiary if the block has one.
+    fn block_traces(
+        &self,
+        block: &Block,
+    ) -> std::result::Result<Vec<LocalizedTransactionTrace>, TraceError> {
+        let block_hash = block.header.hash_slow();
+        let block_number = U64::from(block.header.number);
+
+        let mut traces = Vec::new();
+        let replayed = self.replay(block, block.body.len())?;
+        for (index, (transaction, calls)) in block.body.iter().zip(replayed).enumerate() {
+            traces.extend(calls.into_iter().map(|call| LocalizedTransactionTrace {
+                trace: transaction_trace(call),
+                transaction_position: Some(index),
+                transaction_hash: Some(transaction.hash()),
+                block_number,
+                block_hash,
+            }));
+        }
+
+        if let Some(reward) = self.config.call.executor.block_reward(block.header.number) {
+            traces.push(LocalizedTransactionTrace {
+                trace: TransactionTrace {
+                    trace_address: Vec::new(),
+                    subtraces: 0,
+                    action: Action::Reward(RewardAction {
+                        author: block.header.beneficiary,
+                        value: reward.into(),
+                        reward_type: RewardType::Block,
+                    }),
+                    result: None,
+                },
+                transaction_position: None,
+                transaction_hash: None,
+                block_number,
+                block_hash,
+            });
+        }
+        Ok(traces)
+    }
+
+    /// Returns the traces of the blocks of the filter that match its addresses.
+    ///
+    /// Like log queries, the filter refers to the latest block if it has no first or last block.
+    fn filter_traces(
+        &self,
+        filter: &TraceFilter,
+    ) -> std::result::Result<Vec<LocalizedTransactionTrace>, TraceError> {
+        let best = self.client.chain_info()?.best_number;
+        let from = filter.from_block.unwrap_or(best);
+        let to = filter.to_block.unwrap_or(best).min(best);
+        if from > to {
+            return Err(TraceError::InvalidBlockRange { from, to })
+        }
+        if to - from >= self.config.max_block_range {
+            return Err(TraceError::BlockRangeTooLarge(self.config.max_block_range))
+        }
+
+        let mut traces = Vec::new();
+        for number in from..=to {
+            let Some(block) = self.client.block(block_id(number))? else { break };
+            traces.extend(
+                self.block_traces(&block)?
+                    .into_iter()
+                    .filter(|trace| filter_matches(filter, trace)),
+            );
+        }
+        Ok(traces
+            .into_iter()
+            .skip(filter.after.unwrap_or_default())
+            .take(filter.count.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Replays the first `count` transactions of the block on top of the state of its parent and
+    /// returns the call traces of each of them.
+    fn replay(
+        &self,
+        block: &Block,
+        count: usize,
+    ) -> std::result::Result<Vec<Vec<CallTrace>>, TraceError> {
+        let transactions = block
+            .body
+            .iter()
+            .take(count)
+            .map(|tx| tx.clone().into_ecrecovered().ok_or(TraceError::InvalidSignature(tx.hash())))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if transactions.is_empty() {
+            return Ok(Vec::new())
+        }
+
+        // a block with transactions is never the genesis
+        let parent = block.header.number.checked_sub(1).ok_or(TraceError::UnknownBlock)?;
+        let state = self.client.history_by_block_number(parent)?;
+        let (_, tracers) = execute_transactions_with_inspector(
+            &block.header,
+            &transactions,
+            &self.config.call.executor,
+            SubState::new(State::new(state)),
+            |_| CallTracer::default(),
+        )?;
+        Ok(tracers.into_iter().map(CallTracer::into_traces).collect())
+    }
+}
+
+impl<Client> std::fmt::Debug for TraceApi<Client> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TraceApi").field("config", &self.config).finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<Client> TraceApiServer for TraceApi<Client>
+where
+    Client: BlockProvider + HeaderProvider + StateProviderFactory + TransactionsProvider + 'static,
+{
+    async fn call(
+        &self,
+        call: CallRequest,
+        trace_types: HashSet<TraceType>,
+        block_id: Option<BlockId>,
+    ) -> Result<TraceResults> {
+        self.trace_call_at(call, trace_types, block_id).map_err(trace_rpc_err)
+    }
+
+    /// Calls are not executed on top of each other yet.
+    async fn call_many(
+        &self,
+        _calls: Vec<(CallRequest, HashSet<TraceType>)>,
+        _block_id: Option<BlockId>,
+    ) -> Result<Vec<TraceResults>> {
+        Err(unsupported_rpc_err("trace_callMany"))
+    }
+
+    async fn raw_transaction(
+        &self,
+        data: Bytes,
+        trace_types: HashSet<TraceType>,
+        block_id: Option<BlockId>,
+    ) -> Result<TraceResults> {
+        let tx = TransactionSigned::decode_enveloped(&data)
+            .map_err(TraceError::from)
+            .and_then(|tx| {
+                let hash = tx.hash();
+                tx.into_ecrecovered().ok_or(TraceError::InvalidSignature(hash))
+            })
+            .map_err(trace_rpc_err)?;
+        self.trace_call_at(call_request(tx), trace_types, block_id).map_err(trace_rpc_err)
+    }
+
+    async fn replay_block_transactions(
+        &self,
+        block_id: BlockId,
+        trace_types: HashSet<TraceType>,
+    ) -> Result<Option<Vec<TraceResultsWithTransactionHash>>> {
+        self.replay_block(block_id, &trace_types).map_err(trace_rpc_err)
+    }
+
+    async fn replay_transaction(
+        &self,
+        transaction: H256,
+        trace_types: HashSet<TraceType>,
+    ) -> Result<TraceResults> {
+        self.replay_transaction_by_hash(transaction, &trace_types).map_err(trace_rpc_err)
+    }
+
+    async fn block(&self, block_id: BlockId) -> Result<Option<Vec<LocalizedTransactionTrace>>> {
+        let traces = || -> std::result::Result<_, TraceError> {
+            let Some(block) = self.client.block(block_id)? else { return Ok(None) };
+            Ok(Some((block.header.number, self.block_traces(&block)?)))
+        };
+        match block_id {
+            // tags refer to different blocks over time and are never cached
+            BlockId::Hash(_) | BlockId::Number(rpc::BlockNumber::Number(_)) => {
+                self.cached("trace_block", &block_id, traces)
+            }
+            _ => traces().map(|traces| traces.map(|(_, traces)| traces)),
+        }
+        .map_err(trace_rpc_err)
+    }
+
+    async fn filter(&self, filter: TraceFilter) -> Result<Vec<LocalizedTransactionTrace>> {
+        self.filter_traces(&filter).map_err(trace_rpc_err)
+    }
+
+    /// The indices are the trace address of the returned trace, empty for the outermost call.
+    fn trace(&self, hash: H256, indices: Vec<Index>) -> Result<Option<LocalizedTransactionTrace>> {
+        let trace_address = indices.into_iter().map(usize::from).collect::<Vec<_>>();
+        let traces = self.transaction_traces(hash)?;
+        Ok(traces.and_then(|traces| {
+            traces.into_iter().find(|trace| trace.trace.trace_address == trace_address)
+        }))
+    }
+
+    fn transaction_traces(&self, hash: H256) -> Result<Option<Vec<LocalizedTransactionTrace>>> {
+        self.cached("trace_transaction", &hash, || {
+            let traces = self.transaction_traces_by_hash(hash)?;
+            Ok(traces.map(|traces| {
+                let number =
+                    traces.first().map_or(BlockNumber::MAX, |trace| trace.block_number.as_u64());
+                (number, traces)
+            }))
+        })
+        .map_err(trace_rpc_err)
+    }
+}
+
+/// Returns the id of the block with the given number.
+fn block_id(number: BlockNumber) -> BlockId {
+    BlockId::Number(rpc::BlockNumber::Number(number.into()))
+}
+
+/// Returns the call that executes the transaction on top of a block.
+///
+/// The gas price of legacy and EIP-2930 transactions applies, EIP-1559 transactions pay their
+/// fees.
+fn call_request(tx: TransactionSignedEcRecovered) -> CallRequest {
+    let tx = reth_rpc_types::Transaction::from_recovered(tx);
+    CallRequest {
+        from: Some(tx.from),
+        to: tx.to,
+        gas_price: tx.max_fee_per_gas.is_none().then_some(tx.gas_price).flatten(),
+        max_fee_per_gas: tx.max_fee_per_gas,
+        max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+        gas: Some(tx.gas),
+        value: Some(tx.value),
+        data: Some(tx.input),
+        nonce: Some(tx.nonce),
+        access_list: tx.access_list,
+        transaction_type: tx.transaction_type,
+    }
+}
+
+/// Returns the requested traces of the calls of a replayed transaction, whose output is the one
+/// of its outermost call.
+///
+/// Only [`TraceType::Trace`] is supported, VM traces and state diffs are never returned.
+fn trace_results(calls: Vec<CallTrace>, trace_types: &HashSet<TraceType>) -> TraceResults {
+    let output = calls.first().map(|call| call.output.clone()).unwrap_or_default();
+    let trace = trace_types
+        .contains(&TraceType::Trace)
+        .then(|| calls.into_iter().map(transaction_trace).collect());
+    TraceResults { output, trace, vm_trace: None, state_diff: None }
+}
+
+/// Converts a trace of the executor into a parity style trace.
+fn transaction_trace(call: CallTrace) -> TransactionTrace {
+    let CallTrace {
+        trace_address,
+        subtraces,
+        kind,
+        from,
+        to,
+        value,
+        gas_limit,
+        gas_used,
+        input,
+        output,
+        error,
+    } = call;
+    let gas = U64::from(gas_limit);
+    let gas_used = U64::from(gas_used);
+
+    let action = match kind {
+        CallKind::Create => Action::Create(CreateAction { from, value, gas, init: input }),
+        CallKind::SelfDestruct => Action::Selfdestruct(SelfdestructAction {
+            address: from,
+            refund_address: to,
+            balance: value,
+        }),
+        kind => {
+            let call_type = match kind {
+                CallKind::CallCode => CallType::CallCode,
+                CallKind::DelegateCall => CallType::DelegateCall,
+                CallKind::StaticCall => CallType::StaticCall,
+                _ => CallType::Call,
+            };
+            Action::Call(CallAction { from, to, value, gas, input, call_type })
+        }
+    };
+
+    let result = match (kind, error) {
+        (CallKind::SelfDestruct, _) => None,
+        (_, Some(error)) => Some(TraceResult::Error { error }),
+        (CallKind::Create, None) => Some(TraceResult::Success {
+            result: TraceOutput::Create(CreateOutput { gas_used, code: output, address: to }),
+        }),
+        (_, None) => Some(TraceResult::Success {
+            result: TraceOutput::Call(CallOutput { gas_used, output }),
+        }),
+    };
+
+    TransactionTrace { trace_address, subtraces, action, result }
+}
+
+/// Returns `true` if the sender and the receiver of the trace match the addresses of the filter.
+///
+/// The receiver of a creation is the created contract and the receiver of a reward is its
+/// author. Rewards have no sender.
+fn filter_matches(filter: &TraceFilter, trace: &LocalizedTransactionTrace) -> bool {
+    let (from, to) = match &trace.trace.action {
+        Action::Call(call) => (Some(call.from), Some(call.to)),
+        Action::Create(create) => {
+            let created = match &trace.trace.result {
+                Some(TraceResult::Success { result: TraceOutput::Create(output) }) => {
+                    Some(output.address)
+                }
+                _ => None,
+            };
+            (Some(create.from), created)
+        }
+        Action::Selfdestruct(selfdestruct) => {
+            (Some(selfdestruct.address), Some(selfdestruct.refund_address))
+        }
+        Action::Reward(reward) => (None, Some(reward.author)),
+    };
+    address_matches(filter.from_address.as_deref(), from) &&
+        address_matches(filter.to_address.as_deref(), to)
+}
+
+/// Returns `true` if there are no addresses to match or the address is one of them.
+fn address_matches(addresses: Option<&[Address]>, address: Option<Address>) -> bool {
+    match addresses {
+        None | Some([]) => true,
+        Some(addresses) => address.map_or(false, |address| addresses.contains(&address)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::U256;
+
+    fn call_trace(kind: CallKind, trace_address: Vec<usize>, error: Option<&str>) -> CallTrace {
+        CallTrace {
+            trace_address,
+            subtraces: 0,
+            kind,
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            value: U256::from(3),
+            gas_limit: 100,
+            gas_used: 40,
+            input: Bytes::from(vec![0xaa]),
+            output: Bytes::from(vec![0xbb]),
+            error: error.map(str::to_string),
+        }
+    }
+
+    fn localized(trace: CallTrace) -> LocalizedTransactionTrace {
+        LocalizedTransactionTrace {
+            trace: transaction_trace(trace),
+            transaction_position: Some(0),
+            transaction_hash: Some(H256::zero()),
+            block_number: U64::from(1),
+            block_hash: H256::zero(),
+        }
+    }
+
+    #[test]
+    fn results_of_replayed_transactions() {
+        let mut inner = call_trace(CallKind::StaticCall, vec![0], None);
+        inner.output = Bytes::from(vec![0xcc]);
+        let calls = vec![call_trace(CallKind::Call, Vec::new(), None), inner];
+
+        // the output is the one of the outermost call
+        let results = trace_results(calls.clone(), &HashSet::from([TraceType::Trace]));
+        assert_eq!(results.output, Bytes::from(vec![0xbb]));
+        assert_eq!(results.trace, Some(calls.iter().cloned().map(transaction_trace).collect()));
+
+        let results = trace_results(calls, &HashSet::from([TraceType::VmTrace]));
+        assert_eq!((results.trace, results.vm_trace, results.state_diff), (None, None, None));
+        assert_eq!(trace_results(Vec::new(), &HashSet::new()).output, Bytes::default());
+    }
+
+    #[test]
+    fn convert_call_traces() {
+        let trace = transaction_trace(call_trace(CallKind::DelegateCall, vec![0, 1], None));
+        assert_eq!(trace.trace_address, vec![0, 1]);
+        assert_eq!(
+            trace.action,
+            Action::Call(CallAction {
+                from: Address::repeat_byte(1),
+                to: Address::repeat_byte(2),
+                value: U256::from(3),
+                gas: U64::from(100),
+                input: Bytes::from(vec![0xaa]),
+                call_type: CallType::DelegateCall,
+            })
+        );
+        assert_eq!(
+            trace.result,
+            Some(TraceResult::Success {
+                result: TraceOutput::Call(CallOutput {
+                    gas_used: U64::from(40),
+                    output: Bytes::from(vec![0xbb]),
+                })
+            })
+        );
+
+        let trace = transaction_trace(call_trace(CallKind::Create, vec![], None));
+        assert_eq!(
+            trace.action,
+            Action::Create(CreateAction {
+                from: Address::repeat_byte(1),
+                value: U256::from(3),
+                gas: U64::from(100),
+                init: Bytes::from(vec![0xaa]),
+            })
+        );
+        assert_eq!(
+            trace.result,
+            Some(TraceResult::Success {
+                result: TraceOutput::Create(CreateOutput {
+                    gas_used: U64::from(40),
+                    code: Bytes::from(vec![0xbb]),
+                    address: Address::repeat_byte(2),
+                })
+            })
+        );
+
+        let trace = transaction_trace(call_trace(CallKind::Call, vec![], Some("Reverted")));
+        assert_eq!(trace.result, Some(TraceResult::Error { error: "Reverted".to_string() }));
+
+        let trace = transaction_trace(call_trace(CallKind::SelfDestruct, vec![0], None));
+        assert_eq!(
+            trace.action,
+            Action::Selfdestruct(SelfdestructAction {
+                address: Address::repeat_byte(1),
+                refund_address: Address::repeat_byte(2),
+                balance: U256::from(3),
+            })
+        );
+        assert_eq!(trace.result, None);
+    }
+
+    #[test]
+    fn filter_by_addresses() {
+        let filter = |from: Option<Vec<Address>>, to: Option<Vec<Address>>| TraceFilter {
+            from_block: None,
+            to_block: None,
+            from_address: from,
+            to_address: to,
+            after: None,
+            count: None,
+        };
+        let call = localized(call_trace(CallKind::Call, vec![], None));
+        let failed_create = localized(call_trace(CallKind::Create, vec![], Some("Out of gas")));
+
+        assert!(filter_matches(&filter(None, Some(Vec::new())), &call));
+        assert!(filter_matches(&filter(Some(vec![Address::repeat_byte(1)]), None), &call));
+        assert!(filter_matches(
+            &filter(Some(vec![Address::repeat_byte(1)]), Some(vec![Address::repeat_byte(2)])),
+            &call
+        ));
+        assert!(!filter_matches(
+            &filter(Some(vec![Address::repeat_byte(1)]), Some(vec![Address::repeat_byte(1)])),
+            &call
+        ));
+        // a contract that wasn't created doesn't receive anything
+        assert!(!filter_matches(
+            &filter(None, Some(vec![Address::repeat_byte(2)])),
+            &failed_create
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+//! Provides everything related to `txpool_` namespace
+
+use async_trait::async_trait;
+use jsonrpsee::core::RpcResult as Result;
+use reth_primitives::{
+    Address, IntoRecoveredTransaction, TransactionKind, TransactionSignedEcRecovered, U256,
+};
+use reth_rpc_api::TxPoolApiServer;
+use reth_rpc_types::{
+    Transaction, TxpoolContent, TxpoolInspect, TxpoolInspectSummary, TxpoolStatus,
+};
+use reth_transaction_pool::{PoolSize, PoolTransaction, TransactionPool, ValidPoolTransaction};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// `txpool` API implementation.
+///
+/// This type provides the functionality for handling `txpool_` related requests. Transactions of
+/// the _basefee_ sub-pool can't be executed with the current base fee, so they are reported as
+/// queued.
+pub struct TxPoolApi<Pool> {
+    /// The transaction pool of the node.
+    pool: Pool,
+}
+
+impl<Pool> TxPoolApi<Pool> {
+    /// Creates a new instance of `TxPoolApi`.
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+impl<Pool> std::fmt::Debug for TxPoolApi<Pool> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TxPoolApi").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<Pool> TxPoolApiServer for TxPoolApi<Pool>
+where
+    Pool: TransactionPool + 'static,
+    Pool::Transaction: IntoRecoveredTransaction,
+{
+    async fn status(&self) -> Result<TxpoolStatus> {
+        let PoolSize { pending, basefee, queued, .. } = self.pool.status();
+        Ok(TxpoolStatus {
+            pending: (pending as u64).into(),
+            queued: ((basefee + queued) as u64).into(),
+        })
+    }
+
+    async fn inspect(&self) -> Result<TxpoolInspect> {
+        Ok(TxpoolInspect {
+            pending: group_by_sender(self.pool.pending_transactions(), inspect_summary),
+            queued: group_by_sender(self.pool.queued_transactions(), inspect_summary),
+        })
+    }
+
+    async fn content(&self) -> Result<TxpoolContent> {
+        Ok(TxpoolContent {
+            pending: group_by_sender(self.pool.pending_transactions(), Transaction::from_recovered),
+            queued: group_by_sender(self.pool.queued_transactions(), Transaction::from_recovered),
+        })
+    }
+}
+
+/// Groups the converted transactions by sender and nonce.
+fn group_by_sender<P, T>(
+    transactions: Vec<Arc<ValidPoolTransaction<P>>>,
+    f: impl Fn(TransactionSignedEcRecovered) -> T,
+) -> BTreeMap<Address, BTreeMap<String, T>>
+where
+    P: PoolTransaction + IntoRecoveredTransaction,
+{
+    let mut grouped = BTreeMap::<_, BTreeMap<_, _>>::new();
+    for tx in transactions {
+        let tx = tx.transaction.to_recovered_transaction();
+        grouped.entry(tx.signer()).or_default().insert(tx.nonce().to_string(), f(tx));
+    }
+    grouped
+}
+
+/// Returns the summary of the transaction, the gas price of EIP-1559 transactions is their max
+/// fee per gas.
+fn inspect_summary(tx: TransactionSignedEcRecovered) -> TxpoolInspectSummary {
+    TxpoolInspectSummary {
+        to: match tx.kind() {
+            TransactionKind::Call(to) => Some(*to),
+            TransactionKind::Create => None,
+        },
+        value: U256::from(*tx.value()),
+        gas: U256::from(tx.gas_limit()),
+        gas_price: U256::from(tx.max_fee_per_gas()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Signature, TransactionSigned, TxLegacy};
+
+    #[test]
+    fn summarize_transactions() {
+        let tx = reth_primitives::Transaction::Legacy(TxLegacy {
+            gas_price: 2,
+            gas_limit: 21_000,
+            to: TransactionKind::Call(Address::from_low_u64_be(1)),
+            value: 10,
+            ..Default::default()
+        });
+        let signed = TransactionSigned::from_transaction_and_signature(tx, Signature::default());
+        let tx = TransactionSignedEcRecovered::from_signed_transaction(signed, Address::zero());
+
+        let summary = inspect_summary(tx);
+        assert_eq!(
+            summary.to_string(),
+            "0x0000000000000000000000000000000000000001: 10 wei + 21000 gas × 2 wei"
+        );
+        assert_eq!(summary.to_string().parse::<TxpoolInspectSummary>().unwrap(), summary);
+
+        let creation = TxpoolInspectSummary { to: None, ..summary };
+        assert_eq!(creation.to_string(), "contract creation: 10 wei + 21000 gas × 2 wei");
+        assert_eq!(creation.to_string().parse::<TxpoolInspectSummary>().unwrap(), creation);
+    }
+}
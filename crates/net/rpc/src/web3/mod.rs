@@ -0,0 +1,52 @@
+//! Provides everything related to `web3_` namespace
+
+use jsonrpsee::core::RpcResult as Result;
+use reth_primitives::{keccak256, Bytes, H256};
+use reth_rpc_api::Web3ApiServer;
+
+/// The version of the client, e.g. `reth/v0.1.0/linux-x86_64`.
+pub const CLIENT_VERSION: &str = concat!(
+    "reth/v",
+    env!("CARGO_PKG_VERSION"),
+    "/",
+    std::env::consts::OS,
+    "-",
+    std::env::consts::ARCH
+);
+
+/// `web3` API implementation.
+///
+/// This type provides the functionality for handling `web3_` related requests.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Web3Api;
+
+impl Web3Api {
+    /// Creates a new instance of `Web3Api`.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Web3ApiServer for Web3Api {
+    fn client_version(&self) -> Result<String> {
+        Ok(CLIENT_VERSION.to_string())
+    }
+
+    fn sha3(&self, input: Bytes) -> Result<H256> {
+        Ok(keccak256(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha3_of_input() {
+        let api = Web3Api::new();
+        // the hash of the empty input is the hash of the empty code
+        assert_eq!(api.sha3(Bytes::default()).unwrap(), reth_primitives::KECCAK_EMPTY);
+        assert!(api.client_version().unwrap().starts_with("reth/v"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Generates random chains that can be executed, together with the state and receipts the chain
+//! is expected to result in.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use reth_eth_wire::BlockBody;
+use reth_executor::{
+    builder::{build_block, BlockAttributes},
+    revm_wrap::{State, SubState},
+    Config, SpecUpgrades,
+};
+use reth_interfaces::{
+    p2p::{
+        bodies::client::BodiesClient,
+        downloader::DownloadClient,
+        error::PeerRequestResult,
+        headers::client::{BlockHeaders, HeadersClient, HeadersRequest},
+    },
+    test_utils::generators::sign_message,
+};
+use reth_primitives::{
+    Account, Address, BlockHashOrNumber, BlockNumber, Bytes, Header, PeerId, Receipt, SealedBlock,
+    SealedHeader, StorageKey, StorageValue, Transaction, TransactionKind, TransactionSigned,
+    TxLegacy, TxType, H256, U256,
+};
+use reth_provider::{AccountProvider, StateProvider};
+use std::collections::{BTreeMap, HashMap};
+
+/// The gas of a plain value transfer.
+const TRANSFER_GAS: u64 = 21_000;
+
+/// The gas limit of every generated block.
+const BLOCK_GAS_LIMIT: u64 = 30_000_000;
+
+/// The chain id of the generated transactions.
+const CHAIN_ID: u64 = 1;
+
+/// Returns the executor config of the generated chains.
+///
+/// The merge is active from genesis, so there are no block rewards and the beneficiary only
+/// receives the fees.
+pub fn executor_config() -> Config {
+    Config {
+        chain_id: CHAIN_ID.into(),
+        spec_upgrades: SpecUpgrades::new_paris_activated(),
+        hooks: Default::default(),
+    }
+}
+
+/// Configuration of [random_transfer_chain].
+#[derive(Debug, Clone)]
+pub struct ChainConfig {
+    /// The seed of all generated keys, values and fees.
+    pub seed: u64,
+    /// The number of generated blocks on top of the genesis.
+    pub blocks: u64,
+    /// The maximum number of transactions of a block, blocks can be empty.
+    pub max_txs_per_block: usize,
+    /// The number of funded senders.
+    pub senders: usize,
+}
+
+/// A generated chain and the values it's expected to result in.
+///
+/// The expected values are computed from the generated transfers alone, without executing the
+/// blocks, so they can be compared with what a node derives from the chain.
+#[derive(Debug, Clone)]
+pub struct TransferChain {
+    /// The genesis header.
+    pub genesis: SealedHeader,
+    /// The generated blocks, on top of the genesis.
+    pub blocks: Vec<SealedBlock>,
+    /// The state after every block, starting with the genesis allocation.
+    pub states: Vec<BTreeMap<Address, Account>>,
+    /// The receipts of every generated block.
+    pub receipts: Vec<Vec<Receipt>>,
+}
+
+// === impl TransferChain ===
+
+impl TransferChain {
+    /// Returns the last block.
+    pub fn tip(&self) -> &SealedHeader {
+        self.blocks.last().map(|block| &block.header).unwrap_or(&self.genesis)
+    }
+
+    /// Returns all accounts that exist at any point of the chain.
+    pub fn addresses(&self) -> impl Iterator<Item = Address> + '_ {
+        self.states.last().into_iter().flat_map(|state| state.keys().copied())
+    }
+}
+
+/// Generates a chain of blocks with value transfers between funded senders and random recipients.
+///
+/// Every transfer pays a random gas price, which goes to the beneficiary of its block, and
+/// transfers at least one wei, so no empty account is touched. The same [ChainConfig] always
+/// produces the same chain.
+pub fn random_transfer_chain(config: &ChainConfig) -> TransferChain {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let executor = executor_config();
+
+    let mut alloc = BTreeMap::new();
+    let mut senders = Vec::with_capacity(config.senders);
+    for _ in 0..config.senders.max(1) {
+        let secret = H256::from(rng.gen::<[u8; 32]>());
+        let address = address_of(secret);
+        let balance = U256::from(rng.gen_range(1u64..1_000)) * U256::exp10(18);
+        alloc.insert(address, Account { nonce: 0, balance, bytecode_hash: None });
+        senders.push((secret, address));
+    }
+    let recipients = (0..config.senders.max(1))
+        .map(|_| Address::from(rng.gen::<[u8; 20]>()))
+        .chain(senders.iter().map(|(_, address)| *address))
+        .collect::<Vec<_>>();
+
+    let genesis = Header { gas_limit: BLOCK_GAS_LIMIT, ..Default::default() }.seal();
+    let mut chain = TransferChain {
+        genesis: genesis.clone(),
+        blocks: Vec::new(),
+        states: vec![alloc],
+        receipts: Vec::new(),
+    };
+
+    let mut parent = genesis;
+    for _ in 0..config.blocks {
+        let mut state = ExpectedState(chain.states.last().expect("genesis state").clone());
+        let beneficiary = recipients[rng.gen_range(0..recipients.len())];
+
+        let mut transactions = Vec::new();
+        let mut receipts = Vec::new();
+        for _ in 0..rng.gen_range(0..=config.max_txs_per_block) {
+            let (secret, sender) = senders[rng.gen_range(0..senders.len())];
+            let to = recipients[rng.gen_range(0..recipients.len())];
+            let value = rng.gen_range(1u128..1_000_000_000);
+            let gas_price = rng.gen_range(1u128..100_000_000_000);
+            let nonce = state.account(sender).nonce;
+
+            let tx = Transaction::Legacy(TxLegacy {
+                chain_id: Some(CHAIN_ID),
+                nonce,
+                gas_price,
+                gas_limit: TRANSFER_GAS,
+                to: TransactionKind::Call(to),
+                value,
+                input: Bytes::default(),
+            });
+            let signature = sign_message(secret, tx.signature_hash()).expect("valid secret");
+            let tx = TransactionSigned::from_transaction_and_signature(tx, signature);
+            transactions.push(tx.into_ecrecovered().expect("valid signature"));
+
+            let fee = U256::from(gas_price) * U256::from(TRANSFER_GAS);
+            state.update(sender, |account| {
+                account.nonce += 1;
+                account.balance -= fee + U256::from(value);
+            });
+            state.update(to, |account| account.balance += U256::from(value));
+            state.update(beneficiary, |account| account.balance += fee);
+
+            receipts.push(Receipt {
+                tx_type: TxType::Legacy,
+                success: true,
+                cumulative_gas_used: TRANSFER_GAS * (receipts.len() as u64 + 1),
+                bloom: Default::default(),
+                logs: Vec::new(),
+            });
+        }
+
+        // the header fields that result from the execution are filled by the builder
+        let before = ExpectedState(chain.states.last().expect("genesis state").clone());
+        let attributes = BlockAttributes {
+            timestamp: parent.timestamp + 12,
+            beneficiary,
+            prev_randao: H256::from(rng.gen::<[u8; 32]>()),
+            gas_limit: BLOCK_GAS_LIMIT,
+            base_fee_per_gas: None,
+            extra_data: Bytes::default(),
+        };
+        let (block, _) = build_block(
+            &parent,
+            attributes,
+            transactions,
+            &executor,
+            SubState::new(State::new(&before)),
+        )
+        .expect("transfers execute");
+
+        parent = block.header.clone();
+        chain.blocks.push(block);
+        chain.states.push(state.0);
+        chain.receipts.push(receipts);
+    }
+    chain
+}
+
+/// Returns the address of the secret key.
+fn address_of(secret: H256) -> Address {
+    let tx = TxLegacy { chain_id: Some(CHAIN_ID), ..Default::default() };
+    let signature = sign_message(secret, tx.signature_hash()).expect("valid secret");
+    TransactionSigned::from_transaction_and_signature(Transaction::Legacy(tx), signature)
+        .recover_signer()
+        .expect("valid signature")
+}
+
+/// The accounts of the chain as computed from the transfers.
+#[derive(Debug, Clone)]
+struct ExpectedState(BTreeMap<Address, Account>);
+
+impl ExpectedState {
+    fn account(&self, address: Address) -> Account {
+        self.0.get(&address).copied().unwrap_or_default()
+    }
+
+    fn update(&mut self, address: Address, f: impl FnOnce(&mut Account)) {
+        f(self.0.entry(address).or_default())
+    }
+}
+
+impl AccountProvider for ExpectedState {
+    fn basic_account(&self, address: Address) -> reth_interfaces::Result<Option<Account>> {
+        Ok(self.0.get(&address).copied())
+    }
+}
+
+impl StateProvider for ExpectedState {
+    fn storage(
+        &self,
+        _account: Address,
+        _storage_key: StorageKey,
+    ) -> reth_interfaces::Result<Option<StorageValue>> {
+        Ok(None)
+    }
+
+    fn bytecode_by_hash(&self, _code_hash: H256) -> reth_interfaces::Result<Option<Bytes>> {
+        Ok(None)
+    }
+
+    fn block_hash(&self, _number: U256) -> reth_interfaces::Result<Option<H256>> {
+        Ok(None)
+    }
+}
+
+/// A client that answers the header and body requests of the downloaders with the blocks of a
+/// [TransferChain], as if there was a peer with exactly these blocks.
+#[derive(Debug, Default)]
+pub struct ChainClient {
+    headers: HashMap<BlockNumber, Header>,
+    numbers: HashMap<H256, BlockNumber>,
+    bodies: HashMap<H256, BlockBody>,
+}
+
+// === impl ChainClient ===
+
+impl ChainClient {
+    /// Creates a client that serves the generated blocks of the chain, but not the genesis.
+    pub fn new(chain: &TransferChain) -> Self {
+        let mut client = Self::default();
+        for block in &chain.blocks {
+            client.numbers.insert(block.hash(), block.number);
+            client.headers.insert(block.number, block.header.as_ref().clone());
+            client.bodies.insert(
+                block.hash(),
+                BlockBody {
+                    transactions: block.body.clone(),
+                    ommers: block.ommers.iter().map(|header| header.as_ref().clone()).collect(),
+                },
+            );
+        }
+        client
+    }
+
+    fn headers(&self, request: HeadersRequest) -> Vec<Header> {
+        let HeadersRequest { start, limit, direction } = request;
+        let mut number = match start {
+            BlockHashOrNumber::Hash(hash) => match self.numbers.get(&hash) {
+                Some(number) => *number,
+                None => return Vec::new(),
+            },
+            BlockHashOrNumber::Number(number) => number,
+        };
+
+        let reverse = bool::from(direction);
+        let mut headers = Vec::new();
+        while let Some(header) = self.headers.get(&number) {
+            headers.push(header.clone());
+            if headers.len() as u64 >= limit {
+                break
+            }
+            number = match if reverse { number.checked_sub(1) } else { number.checked_add(1) } {
+                Some(number) => number,
+                None => break,
+            };
+        }
+        headers
+    }
+}
+
+impl DownloadClient for ChainClient {
+    fn report_bad_message(&self, _peer_id: PeerId) {}
+}
+
+#[async_trait::async_trait]
+impl HeadersClient for ChainClient {
+    async fn get_headers(&self, request: HeadersRequest) -> PeerRequestResult<BlockHeaders> {
+        Ok((PeerId::default(), BlockHeaders(self.headers(request))).into())
+    }
+}
+
+#[async_trait::async_trait]
+impl BodiesClient for ChainClient {
+    async fn get_block_bodies(&self, hashes: Vec<H256>) -> PeerRequestResult<Vec<BlockBody>> {
+        let bodies = hashes.iter().map_while(|hash| self.bodies.get(hash).cloned()).collect();
+        Ok((PeerId::default(), bodies).into())
+    }
+}
@@ -0,0 +1,6 @@
+//! reth-rpc integration tests
+
+mod chain;
+mod sync;
+
+fn main() {}
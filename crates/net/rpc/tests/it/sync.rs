@@ -0,0 +1,269 @@
+//! Syncs generated chains through the pipeline into a fresh database and compares the responses
+//! of the RPC layer with the values derived from the generated data.
+
+use crate::chain::{
+    executor_config, random_transfer_chain, ChainClient, ChainConfig, TransferChain,
+};
+use reth_db::{
+    database::Database,
+    mdbx::{test_utils::create_test_db, Env, EnvKind, WriteMap},
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_downloaders::{
+    bodies::concurrent::ConcurrentDownloader, headers::linear::LinearDownloadBuilder,
+};
+use reth_interfaces::test_utils::{TestConsensus, TestStatusUpdater};
+use reth_primitives::{
+    rpc::{BlockId, BlockNumber},
+    Bytes, SealedBlock, TransactionKind, U256, U64,
+};
+use reth_provider::{insert_canonical_block, ProviderImpl};
+use reth_rpc::{CallConfig, EthApi, TraceApi, TraceConfig};
+use reth_rpc_api::{EthApiServer, TraceApiServer};
+use reth_rpc_types::{
+    trace::parity::{
+        Action, CallAction, CallOutput, CallType, LocalizedTransactionTrace, TraceOutput,
+        TraceResult, TransactionTrace,
+    },
+    BlockTransactions, SyncStatus,
+};
+use reth_stages::{
+    stages::{
+        bodies::BodyStage, execution::ExecutionStage, hashing_account::AccountHashingStage,
+        hashing_storage::StorageHashingStage, headers::HeaderStage,
+        history_index::HistoryIndexStage, log_index::LogIndexStage,
+        sender_recovery::SenderRecoveryStage,
+    },
+    stages_metrics::HeaderMetrics,
+    Pipeline,
+};
+use reth_transaction_pool::test_util::testing_pool;
+use std::sync::Arc;
+
+/// The number of generated chains, each with a different seed.
+const SEEDS: u64 = 8;
+
+/// Writes the genesis block and its allocation, like a node does before the first sync.
+fn init_genesis<DB: Database>(db: &DB, chain: &TransferChain) {
+    let tx = db.tx_mut().unwrap();
+    let genesis =
+        SealedBlock { header: chain.genesis.clone(), body: Vec::new(), ommers: Vec::new() };
+    insert_canonical_block(&tx, &genesis, false).unwrap();
+    tx.put::<tables::HeaderTD>((0, genesis.hash()).into(), genesis.header.difficulty.into())
+        .unwrap();
+    for (address, account) in &chain.states[0] {
+        tx.put::<tables::PlainAccountState>(*address, *account).unwrap();
+    }
+    tx.commit().unwrap();
+}
+
+/// Runs the stages of a full node until the tip of the chain, served by a single peer.
+async fn sync(db: Arc<Env<WriteMap>>, chain: &TransferChain) {
+    let client = Arc::new(ChainClient::new(chain));
+    let consensus = Arc::new(TestConsensus::default());
+    consensus.update_tip(chain.tip().hash());
+
+    let mut pipeline = Pipeline::<Env<WriteMap>>::new()
+        .push(HeaderStage {
+            downloader: LinearDownloadBuilder::default().build(consensus.clone(), client.clone()),
+            consensus: consensus.clone(),
+            client: client.clone(),
+            network_handle: TestStatusUpdater,
+            commit_threshold: 100,
+            metrics: HeaderMetrics::default(),
+        })
+        .push(BodyStage {
+            downloader: Arc::new(ConcurrentDownloader::new(client.clone(), consensus.clone())),
+            consensus: consensus.clone(),
+            commit_threshold: 100,
+        })
+        .push(SenderRecoveryStage { batch_size: 100, commit_threshold: 100 })
+        .push(ExecutionStage::new(executor_config()))
+        .push(AccountHashingStage::default())
+        .push(StorageHashingStage::default())
+        .push(LogIndexStage::default())
+        .push(HistoryIndexStage::default())
+        .set_max_block(Some(chain.tip().number));
+    pipeline.run(db).await.expect("pipeline syncs the generated chain");
+}
+
+/// Asserts that blocks, transactions, receipts, traces and the state at every block match the
+/// generated chain.
+async fn assert_rpc_matches(db: Arc<Env<WriteMap>>, chain: &TransferChain) {
+    let provider = Arc::new(ProviderImpl::new(db));
+    let eth = EthApi::new(provider.clone(), testing_pool());
+
+    assert_eq!(eth.block_number().unwrap(), U256::from(chain.tip().number));
+    assert_eq!(eth.syncing().unwrap(), SyncStatus::None);
+
+    for (block, receipts) in chain.blocks.iter().zip(&chain.receipts) {
+        let number = block.number;
+        let by_number = eth.block_by_number(number, false).await.unwrap().expect("synced block");
+        let header = &by_number.header;
+        assert_eq!(header.hash, Some(block.hash()), "hash of block #{number}");
+        assert_eq!(header.parent_hash, block.parent_hash, "parent of block #{number}");
+        assert_eq!(header.number, Some(U256::from(number)));
+        assert_eq!(header.miner, block.beneficiary, "beneficiary of block #{number}");
+        assert_eq!(header.state_root, block.state_root, "state root of block #{number}");
+        assert_eq!(header.receipts_root, block.receipts_root, "receipts root of block #{number}");
+        assert_eq!(
+            header.gas_used,
+            U256::from(receipts.last().map_or(0, |receipt| receipt.cumulative_gas_used)),
+            "gas used by block #{number}"
+        );
+        assert_eq!(
+            by_number.transactions,
+            BlockTransactions::Hashes(block.body.iter().map(|tx| tx.hash()).collect()),
+            "transactions of block #{number}"
+        );
+
+        let by_hash = eth.block_by_hash(block.hash(), true).await.unwrap().expect("synced block");
+        assert_eq!(by_hash.header, by_number.header, "block #{number} by hash");
+        let BlockTransactions::Full(transactions) = &by_hash.transactions else {
+            panic!("full transactions of block #{number}")
+        };
+        assert_eq!(transactions.len(), block.body.len(), "transactions of block #{number}");
+
+        let mut gas_used = 0;
+        for (index, ((tx, rpc_tx), receipt)) in
+            block.body.iter().zip(transactions).zip(receipts).enumerate()
+        {
+            let from = tx.recover_signer().unwrap();
+            let to = match tx.kind() {
+                TransactionKind::Call(to) => *to,
+                TransactionKind::Create => unreachable!("only transfers"),
+            };
+            assert_eq!(rpc_tx.hash, tx.hash());
+            assert_eq!(rpc_tx.from, from, "sender of {:?}", tx.hash());
+            assert_eq!(rpc_tx.to, Some(to), "recipient of {:?}", tx.hash());
+            assert_eq!(rpc_tx.value, U256::from(*tx.value()), "value of {:?}", tx.hash());
+            assert_eq!(rpc_tx.nonce, U256::from(tx.nonce()), "nonce of {:?}", tx.hash());
+            assert_eq!(rpc_tx.block_hash, Some(block.hash()));
+            assert_eq!(rpc_tx.block_number, Some(U256::from(number)));
+            assert_eq!(rpc_tx.transaction_index, Some(U256::from(index)));
+            assert_eq!(
+                eth.transaction_by_hash(tx.hash()).await.unwrap().as_ref(),
+                Some(rpc_tx),
+                "transaction {:?} by hash",
+                tx.hash()
+            );
+
+            let rpc_receipt =
+                eth.transaction_receipt(tx.hash()).await.unwrap().expect("synced receipt");
+            assert_eq!(rpc_receipt.transaction_hash, Some(tx.hash()));
+            assert_eq!(rpc_receipt.transaction_index, Some(U256::from(index)));
+            assert_eq!(rpc_receipt.block_hash, Some(block.hash()));
+            assert_eq!(rpc_receipt.block_number, Some(U256::from(number)));
+            assert_eq!((rpc_receipt.from, rpc_receipt.to), (from, Some(to)));
+            assert_eq!(rpc_receipt.contract_address, None);
+            assert_eq!(rpc_receipt.status_code, Some(U64::from(receipt.success as u64)));
+            assert_eq!(
+                rpc_receipt.cumulative_gas_used,
+                U256::from(receipt.cumulative_gas_used),
+                "cumulative gas of {:?}",
+                tx.hash()
+            );
+            assert_eq!(
+                rpc_receipt.gas_used,
+                Some(U256::from(receipt.cumulative_gas_used - gas_used)),
+                "gas used by {:?}",
+                tx.hash()
+            );
+            // legacy transactions pay their gas price
+            assert_eq!(rpc_receipt.effective_gas_price, U256::from(tx.max_fee_per_gas()));
+            assert!(rpc_receipt.logs.is_empty());
+            gas_used = receipt.cumulative_gas_used;
+        }
+    }
+
+    // every transfer is a single call without execution gas, the merge is active so there are no
+    // rewards
+    let call = CallConfig { executor: executor_config(), ..Default::default() };
+    let trace = TraceApi::new(provider, TraceConfig { call, ..Default::default() });
+    for block in &chain.blocks {
+        let id = BlockId::Number(BlockNumber::Number(block.number.into()));
+        let expected = block
+            .body
+            .iter()
+            .enumerate()
+            .map(|(position, tx)| LocalizedTransactionTrace {
+                trace: TransactionTrace {
+                    trace_address: Vec::new(),
+                    subtraces: 0,
+                    action: Action::Call(CallAction {
+                        from: tx.recover_signer().unwrap(),
+                        to: match tx.kind() {
+                            TransactionKind::Call(to) => *to,
+                            TransactionKind::Create => unreachable!("only transfers"),
+                        },
+                        value: U256::from(*tx.value()),
+                        gas: U64::zero(),
+                        input: Bytes::default(),
+                        call_type: CallType::Call,
+                    }),
+                    result: Some(TraceResult::Success {
+                        result: TraceOutput::Call(CallOutput {
+                            gas_used: U64::zero(),
+                            output: Bytes::default(),
+                        }),
+                    }),
+                },
+                transaction_position: Some(position),
+                transaction_hash: Some(tx.hash()),
+                block_number: block.number.into(),
+                block_hash: block.hash(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            trace.block(id).await.unwrap(),
+            Some(expected.clone()),
+            "block #{}",
+            block.number
+        );
+
+        for (tx, expected) in block.body.iter().zip(expected) {
+            assert_eq!(
+                trace.transaction_traces(tx.hash()).unwrap(),
+                Some(vec![expected]),
+                "transaction {:?}",
+                tx.hash()
+            );
+        }
+    }
+
+    for (number, state) in chain.states.iter().enumerate() {
+        let id = Some(BlockId::Number(BlockNumber::Number((number as u64).into())));
+        for address in chain.addresses() {
+            let account = state.get(&address).copied().unwrap_or_default();
+            assert_eq!(
+                eth.balance(address, id).await.unwrap(),
+                account.balance,
+                "balance of {address:?} at block #{number}"
+            );
+            assert_eq!(
+                eth.transaction_count(address, id).await.unwrap(),
+                U256::from(account.nonce),
+                "nonce of {address:?} at block #{number}"
+            );
+            assert_eq!(eth.get_code(address, id).await.unwrap(), Bytes::default());
+        }
+    }
+}
+
+#[tokio::test]
+async fn rpc_matches_synced_random_chains() {
+    for seed in 0..SEEDS {
+        let chain = random_transfer_chain(&ChainConfig {
+            seed,
+            blocks: 8,
+            max_txs_per_block: 6,
+            senders: 4,
+        });
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        init_genesis(db.as_ref(), &chain);
+
+        sync(db.clone(), &chain).await;
+        assert_rpc_matches(db, &chain).await;
+    }
+}
@@ -30,6 +30,15 @@ where
     bloom
 }
 
+/// Returns `true` if the bloom may contain the input, e.g. the address or a topic of a log.
+///
+/// Like every bloom filter, this can return false positives but never false negatives.
+pub fn bloom_contains(bloom: &Bloom, input: &[u8]) -> bool {
+    let mut bits = Bloom::zero();
+    m3_2048(&mut bits, input);
+    bloom.0.iter().zip(bits.0.iter()).all(|(byte, bits)| byte & bits == *bits)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,4 +78,20 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn contains_log_inputs() {
+        let log = Log {
+            address: hex!("22341ae42d6dd7384bc8584e50419ea3ac75b83f").into(),
+            topics: vec![
+                hex!("04491edcd115127caedbd478e2e7895ed80c7847e903431f94f9cfa579cad47f").into()
+            ],
+            data: vec![].into(),
+        };
+        let bloom = logs_bloom([&log]);
+        assert!(bloom_contains(&bloom, log.address.as_bytes()));
+        assert!(bloom_contains(&bloom, log.topics[0].as_bytes()));
+        assert!(!bloom_contains(&bloom, &[0xab; 20]));
+        assert!(!bloom_contains(&Bloom::zero(), log.address.as_bytes()));
+    }
 }
@@ -23,6 +23,7 @@ mod integer_list;
 mod jsonu256;
 mod log;
 mod peer;
+mod permissions;
 mod receipt;
 mod storage;
 mod transaction;
@@ -30,6 +31,13 @@ mod transaction;
 /// Helper function for calculating Merkle proofs and hashes
 pub mod proofs;
 
+/// Merkle-Patricia tries and their proofs
+pub mod trie;
+
+/// Strategies for property tests of the encodings
+#[cfg(any(test, feature = "arbitrary"))]
+pub mod strategies;
+
 pub use account::Account;
 pub use block::{Block, BlockHashOrNumber, SealedBlock};
 pub use chain::Chain;
@@ -43,12 +51,13 @@ pub use integer_list::IntegerList;
 pub use jsonu256::JsonU256;
 pub use log::Log;
 pub use peer::{PeerId, WithPeerId};
+pub use permissions::SenderPermissions;
 pub use receipt::Receipt;
 pub use storage::StorageEntry;
 pub use transaction::{
     AccessList, AccessListItem, FromRecoveredTransaction, IntoRecoveredTransaction, Signature,
-    Transaction, TransactionKind, TransactionSigned, TransactionSignedEcRecovered, TxEip1559,
-    TxEip2930, TxLegacy, TxType,
+    Transaction, TransactionKind, TransactionSigned, TransactionSignedEcRecovered,
+    TransactionSignedLazy, TxEip1559, TxEip2930, TxLegacy, TxType,
 };
 
 /// A block hash.
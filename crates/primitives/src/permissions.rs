@@ -0,0 +1,84 @@
+use crate::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Restricts which accounts may send transactions and deploy contracts on permissioned networks.
+///
+/// An account is permitted if it is in the allowlist, or if there is no allowlist, and it is not
+/// in the denylist.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SenderPermissions {
+    /// If set, only these accounts may send transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_senders: Option<HashSet<Address>>,
+    /// Accounts that may never send transactions.
+    pub denied_senders: HashSet<Address>,
+    /// If set, only these accounts may deploy contracts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_deployers: Option<HashSet<Address>>,
+    /// Accounts that may never deploy contracts.
+    pub denied_deployers: HashSet<Address>,
+}
+
+impl SenderPermissions {
+    /// Returns true if the account may send transactions.
+    pub fn is_sender_permitted(&self, sender: &Address) -> bool {
+        is_permitted(sender, self.allowed_senders.as_ref(), &self.denied_senders)
+    }
+
+    /// Returns true if the account may send contract creation transactions.
+    ///
+    /// Deployers must also be permitted to send transactions.
+    pub fn is_deployer_permitted(&self, deployer: &Address) -> bool {
+        self.is_sender_permitted(deployer) &&
+            is_permitted(deployer, self.allowed_deployers.as_ref(), &self.denied_deployers)
+    }
+}
+
+fn is_permitted(
+    account: &Address,
+    allowed: Option<&HashSet<Address>>,
+    denied: &HashSet<Address>,
+) -> bool {
+    allowed.map_or(true, |allowed| allowed.contains(account)) && !denied.contains(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permits_accounts() {
+        let (alice, bob, carol) =
+            (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+
+        let open = SenderPermissions::default();
+        assert!(open.is_sender_permitted(&alice));
+        assert!(open.is_deployer_permitted(&alice));
+
+        let permissions = SenderPermissions {
+            allowed_senders: Some(HashSet::from([alice, bob])),
+            denied_senders: HashSet::from([bob]),
+            allowed_deployers: Some(HashSet::from([alice, carol])),
+            ..Default::default()
+        };
+        assert!(permissions.is_sender_permitted(&alice));
+        assert!(!permissions.is_sender_permitted(&bob));
+        assert!(!permissions.is_sender_permitted(&carol));
+        assert!(permissions.is_deployer_permitted(&alice));
+        // carol is an allowed deployer, but not an allowed sender
+        assert!(!permissions.is_deployer_permitted(&carol));
+    }
+
+    #[test]
+    fn deserializes_partial_lists() {
+        let permissions: SenderPermissions = serde_json::from_str(
+            r#"{"deniedDeployers":["0x0101010101010101010101010101010101010101"]}"#,
+        )
+        .unwrap();
+        assert_eq!(permissions.allowed_senders, None);
+        assert!(permissions.is_sender_permitted(&Address::repeat_byte(1)));
+        assert!(!permissions.is_deployer_permitted(&Address::repeat_byte(1)));
+    }
+}
@@ -1,8 +1,11 @@
-use crate::{keccak256, Header, Log, Receipt, TransactionSigned, H256};
+use crate::{
+    keccak256, Account, Address, Header, Log, Receipt, TransactionSigned, H256, KECCAK_EMPTY, U256,
+};
 use hash_db::Hasher;
 use hex_literal::hex;
 use plain_hasher::PlainHasher;
-use triehash::ordered_trie_root;
+use reth_rlp::{Encodable, RlpDecodable, RlpEncodable};
+use triehash::{ordered_trie_root, sec_trie_root};
 
 /// Keccak-256 hash of the RLP of an empty list, KEC("\xc0").
 pub const EMPTY_LIST_HASH: H256 =
@@ -14,7 +17,7 @@ pub const EMPTY_ROOT: H256 =
 
 /// A [Hasher] that calculates a keccak256 hash of the given data.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
-struct KeccakHasher;
+pub(crate) struct KeccakHasher;
 
 impl Hasher for KeccakHasher {
     type Out = H256;
@@ -66,13 +69,79 @@ pub fn calculate_ommers_root<'a>(ommers: impl Iterator<Item = &'a Header> + Clon
     keccak256(ommers_rlp)
 }
 
+/// An account as it is stored in the state trie.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct TrieAccount {
+    /// The nonce of the account.
+    pub nonce: u64,
+    /// The balance of the account.
+    pub balance: U256,
+    /// The root of the storage trie of the account.
+    pub storage_root: H256,
+    /// The hash of the code of the account.
+    pub code_hash: H256,
+}
+
+impl TrieAccount {
+    /// Creates the trie account of an account with the root of its storage trie.
+    pub fn new(account: Account, storage_root: H256) -> Self {
+        Self {
+            nonce: account.nonce,
+            balance: account.balance,
+            storage_root,
+            code_hash: account.bytecode_hash.unwrap_or(KECCAK_EMPTY),
+        }
+    }
+
+    /// Returns the account without its storage root.
+    pub fn account(&self) -> Account {
+        Account {
+            nonce: self.nonce,
+            balance: self.balance,
+            bytecode_hash: (self.code_hash != KECCAK_EMPTY).then_some(self.code_hash),
+        }
+    }
+}
+
+/// Calculates the root of the storage trie of an account.
+///
+/// Zero values are not part of the trie.
+pub fn calculate_storage_root(storage: impl IntoIterator<Item = (H256, U256)>) -> H256 {
+    sec_trie_root::<KeccakHasher, _, _, _>(
+        storage.into_iter().filter(|(_, value)| !value.is_zero()).map(|(key, value)| {
+            let mut value_rlp = Vec::new();
+            value.encode(&mut value_rlp);
+            (key, value_rlp)
+        }),
+    )
+}
+
+/// Calculates the state root of the accounts with the roots of their storage tries.
+///
+/// This builds the whole trie in memory, it's meant for small states like the ones of genesis
+/// files and tests.
+pub fn calculate_state_root(accounts: impl IntoIterator<Item = (Address, Account, H256)>) -> H256 {
+    sec_trie_root::<KeccakHasher, _, _, _>(accounts.into_iter().map(
+        |(address, account, storage_root)| {
+            let account = TrieAccount::new(account, storage_root);
+            let mut account_rlp = Vec::new();
+            account.encode(&mut account_rlp);
+            (address, account_rlp)
+        },
+    ))
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::{
         hex_literal::hex,
-        proofs::{calculate_receipt_root, calculate_transaction_root},
-        Block, Bloom, Log, Receipt, TxType, H160, H256,
+        keccak256,
+        proofs::{
+            calculate_receipt_root, calculate_state_root, calculate_storage_root,
+            calculate_transaction_root, EMPTY_ROOT,
+        },
+        Account, Block, Bloom, Log, Receipt, TxType, H160, H256, U256,
     };
     use bytes::Bytes;
     use reth_rlp::Decodable;
@@ -105,4 +174,38 @@ mod tests {
             H256(hex!("fe70ae4a136d98944951b2123859698d59ad251a381abc9960fa81cae3d0d4a0"))
         );
     }
+
+    #[test]
+    fn check_state_root() {
+        assert_eq!(calculate_state_root([]), EMPTY_ROOT);
+        // zero slots are not part of the trie
+        assert_eq!(calculate_storage_root([(H256::zero(), U256::zero())]), EMPTY_ROOT);
+
+        // the pre state of the `evmBytecode_d0g0v0_Berlin` blockchain test
+        let code = hex!("67ffffffffffffffff600160006000fb");
+        let accounts = [
+            (
+                H160(hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b")),
+                Account {
+                    nonce: 0,
+                    balance: U256::from(0x38beec8feeca2598u64),
+                    bytecode_hash: None,
+                },
+                EMPTY_ROOT,
+            ),
+            (
+                H160(hex!("b94f5374fce5edbc8e2a8697c15331677e6ebf0b")),
+                Account {
+                    nonce: 0x3f,
+                    balance: U256::zero(),
+                    bytecode_hash: Some(keccak256(code)),
+                },
+                EMPTY_ROOT,
+            ),
+        ];
+        assert_eq!(
+            calculate_state_root(accounts),
+            H256(hex!("642a369a4a9dbf57d83ba05413910a5dd2cff93858c68e9e8293a8fffeae8660"))
+        );
+    }
 }
@@ -60,15 +60,7 @@ impl Receipt {
             header.encode(out);
         }
 
-        match self.tx_type {
-            TxType::EIP2930 => {
-                out.put_u8(0x01);
-            }
-            TxType::EIP1559 => {
-                out.put_u8(0x02);
-            }
-            _ => unreachable!("legacy handled; qed."),
-        }
+        out.put_u8(self.tx_type.ty());
         out.put_slice(payload.as_ref());
     }
 
@@ -103,27 +95,17 @@ impl Receipt {
         *buf = *b;
         Ok(this)
     }
-}
 
-impl Encodable for Receipt {
-    fn length(&self) -> usize {
-        let mut payload_len = self.receipt_length();
-        // account for eip-2718 type prefix and set the list
-        if matches!(self.tx_type, TxType::EIP1559 | TxType::EIP2930) {
-            payload_len += 1;
-            // we include a string header for typed receipts, so include the length here
-            payload_len = length_of_length(payload_len);
-        }
-
-        payload_len
-    }
-    fn encode(&self, out: &mut dyn BufMut) {
-        self.encode_inner(out, true)
+    /// Decodes a receipt like [`Decodable::decode`], but accepts any EIP-2718 type byte above
+    /// `0x02` instead of failing with `invalid receipt type`.
+    ///
+    /// The raw type byte of such receipts is preserved as [`TxType::Other`], so they re-encode to
+    /// the same bytes. This is meant for networks that introduced custom transaction types.
+    pub fn decode_lenient(buf: &mut &[u8]) -> Result<Self, reth_rlp::DecodeError> {
+        Self::decode_with(buf, true)
     }
-}
 
-impl Decodable for Receipt {
-    fn decode(buf: &mut &[u8]) -> Result<Self, reth_rlp::DecodeError> {
+    fn decode_with(buf: &mut &[u8], lenient: bool) -> Result<Self, reth_rlp::DecodeError> {
         // a receipt is either encoded as a string (non legacy) or a list (legacy).
         // We should not consume the buffer if we are decoding a legacy receipt, so let's
         // check if the first byte is between 0x80 and 0xbf.
@@ -138,15 +120,15 @@ impl Decodable for Receipt {
                 let receipt_type = *buf.first().ok_or(reth_rlp::DecodeError::Custom(
                     "typed receipt cannot be decoded from an empty slice",
                 ))?;
-                if receipt_type == 0x01 {
-                    buf.advance(1);
-                    Self::decode_receipt(buf, TxType::EIP2930)
-                } else if receipt_type == 0x02 {
-                    buf.advance(1);
-                    Self::decode_receipt(buf, TxType::EIP1559)
-                } else {
-                    Err(reth_rlp::DecodeError::Custom("invalid receipt type"))
-                }
+                let tx_type = match receipt_type {
+                    0x01 => TxType::EIP2930,
+                    0x02 => TxType::EIP1559,
+                    // valid EIP-2718 type bytes are in the range [0x00, 0x7f]
+                    0x03..=0x7f if lenient => TxType::Other(receipt_type),
+                    _ => return Err(reth_rlp::DecodeError::Custom("invalid receipt type")),
+                };
+                buf.advance(1);
+                Self::decode_receipt(buf, tx_type)
             }
             Ordering::Equal => {
                 Err(reth_rlp::DecodeError::Custom("an empty list is not a valid receipt encoding"))
@@ -156,6 +138,29 @@ impl Decodable for Receipt {
     }
 }
 
+impl Encodable for Receipt {
+    fn length(&self) -> usize {
+        let mut payload_len = self.receipt_length();
+        // account for eip-2718 type prefix and set the list
+        if !matches!(self.tx_type, TxType::Legacy) {
+            payload_len += 1;
+            // we include a string header for typed receipts, so include the length here
+            payload_len += length_of_length(payload_len);
+        }
+
+        payload_len
+    }
+    fn encode(&self, out: &mut dyn BufMut) {
+        self.encode_inner(out, true)
+    }
+}
+
+impl Decodable for Receipt {
+    fn decode(buf: &mut &[u8]) -> Result<Self, reth_rlp::DecodeError> {
+        Self::decode_with(buf, false)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -228,4 +233,26 @@ mod tests {
         let receipt = Receipt::decode(&mut &data[..]).unwrap();
         assert_eq!(receipt, expected);
     }
+
+    #[test]
+    fn decode_unknown_receipt_type_lenient() {
+        let receipt = Receipt {
+            tx_type: TxType::Other(0x7e),
+            success: true,
+            cumulative_gas_used: 21000,
+            ..Default::default()
+        };
+        let mut data = vec![];
+        receipt.encode(&mut data);
+        assert_eq!(receipt.length(), data.len());
+
+        assert!(Receipt::decode(&mut &data[..]).is_err());
+
+        let decoded = Receipt::decode_lenient(&mut &data[..]).unwrap();
+        assert_eq!(decoded, receipt);
+
+        let mut encoded = vec![];
+        decoded.encode(&mut encoded);
+        assert_eq!(encoded, data);
+    }
 }
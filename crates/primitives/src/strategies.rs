@@ -0,0 +1,348 @@
+//! [proptest] strategies for the primitive types, to test their encodings with random values.
+//!
+//! The strategies only produce values that have a valid encoding, e.g. the chain id of legacy
+//! transactions fits into their `v` value and [TxType::Other] is only used with the type bytes
+//! that [Receipt::decode_lenient] accepts. The types implement [Arbitrary] with these strategies.
+
+use crate::{
+    AccessList, AccessListItem, Address, Bloom, Bytes, Header, Log, Receipt, Signature,
+    Transaction, TransactionKind, TransactionSigned, TxEip1559, TxEip2930, TxLegacy, TxType, H256,
+    U256,
+};
+use proptest::{
+    arbitrary::Arbitrary,
+    collection::vec,
+    option,
+    prelude::{any, prop_oneof, BoxedStrategy, Just, Strategy},
+};
+
+/// The maximum length of generated byte arrays, like the input of transactions.
+const MAX_BYTES: usize = 128;
+
+/// The maximum number of elements of generated lists, like the logs of receipts.
+const MAX_ITEMS: usize = 4;
+
+/// Returns a strategy for hashes.
+pub fn h256() -> impl Strategy<Value = H256> {
+    any::<[u8; 32]>().prop_map(H256)
+}
+
+/// Returns a strategy for addresses.
+pub fn address() -> impl Strategy<Value = Address> {
+    any::<[u8; 20]>().prop_map(Address::from)
+}
+
+/// Returns a strategy for 256 bit numbers.
+pub fn u256() -> impl Strategy<Value = U256> {
+    any::<[u8; 32]>().prop_map(|bytes| U256::from_big_endian(&bytes))
+}
+
+/// Returns a strategy for bloom filters.
+pub fn bloom() -> impl Strategy<Value = Bloom> {
+    vec(any::<u8>(), 256).prop_map(|bytes| Bloom::from_slice(&bytes))
+}
+
+/// Returns a strategy for byte arrays of up to [MAX_BYTES] bytes.
+pub fn bytes() -> impl Strategy<Value = bytes::Bytes> {
+    vec(any::<u8>(), 0..=MAX_BYTES).prop_map(bytes::Bytes::from)
+}
+
+/// Returns a strategy for logs.
+pub fn log() -> impl Strategy<Value = Log> {
+    (address(), vec(h256(), 0..=MAX_ITEMS), bytes()).prop_map(|(address, topics, data)| Log {
+        address,
+        topics,
+        data,
+    })
+}
+
+/// Returns a strategy for transaction types, including the unknown types of
+/// [Receipt::decode_lenient].
+pub fn tx_type() -> impl Strategy<Value = TxType> {
+    prop_oneof![
+        Just(TxType::Legacy),
+        Just(TxType::EIP2930),
+        Just(TxType::EIP1559),
+        (0x03..=0x7fu8).prop_map(TxType::Other),
+    ]
+}
+
+/// Returns a strategy for receipts.
+pub fn receipt() -> impl Strategy<Value = Receipt> {
+    (tx_type(), any::<bool>(), any::<u64>(), bloom(), vec(log(), 0..=MAX_ITEMS)).prop_map(
+        |(tx_type, success, cumulative_gas_used, bloom, logs)| Receipt {
+            tx_type,
+            success,
+            cumulative_gas_used,
+            bloom,
+            logs,
+        },
+    )
+}
+
+/// Returns a strategy for headers, with and without a base fee.
+pub fn header() -> impl Strategy<Value = Header> {
+    let hashes = (h256(), h256(), address(), h256(), h256(), h256(), h256());
+    let numbers = (u256(), any::<u64>(), any::<u64>(), any::<u64>(), any::<u64>(), any::<u64>());
+    (hashes, bloom(), numbers, option::of(any::<u64>()), bytes()).prop_map(
+        |(
+            (
+                parent_hash,
+                ommers_hash,
+                beneficiary,
+                state_root,
+                transactions_root,
+                receipts_root,
+                mix_hash,
+            ),
+            logs_bloom,
+            (difficulty, number, gas_limit, gas_used, timestamp, nonce),
+            base_fee_per_gas,
+            extra_data,
+        )| Header {
+            parent_hash,
+            ommers_hash,
+            beneficiary,
+            state_root,
+            transactions_root,
+            receipts_root,
+            logs_bloom,
+            difficulty,
+            number,
+            gas_limit,
+            gas_used,
+            timestamp,
+            mix_hash,
+            nonce,
+            base_fee_per_gas,
+            extra_data,
+        },
+    )
+}
+
+/// Returns a strategy for the recipients of transactions.
+pub fn transaction_kind() -> impl Strategy<Value = TransactionKind> {
+    prop_oneof![Just(TransactionKind::Create), address().prop_map(TransactionKind::Call)]
+}
+
+/// Returns a strategy for access lists.
+pub fn access_list() -> impl Strategy<Value = AccessList> {
+    let item = (address(), vec(h256(), 0..=MAX_ITEMS))
+        .prop_map(|(address, storage_keys)| AccessListItem { address, storage_keys });
+    vec(item, 0..=MAX_ITEMS).prop_map(AccessList)
+}
+
+/// Returns a strategy for transactions of all types.
+///
+/// The chain ids of legacy transactions are limited to 32 bits, so their `v` value doesn't
+/// overflow.
+pub fn transaction() -> impl Strategy<Value = Transaction> {
+    let legacy = (
+        option::of(any::<u32>().prop_map(u64::from)),
+        any::<u64>(),
+        any::<u128>(),
+        any::<u64>(),
+        transaction_kind(),
+        any::<u128>(),
+        bytes(),
+    )
+        .prop_map(|(chain_id, nonce, gas_price, gas_limit, to, value, input)| {
+            Transaction::Legacy(TxLegacy {
+                chain_id,
+                nonce,
+                gas_price,
+                gas_limit,
+                to,
+                value,
+                input: Bytes(input),
+            })
+        });
+    let eip2930 = (
+        any::<u64>(),
+        any::<u64>(),
+        any::<u128>(),
+        any::<u64>(),
+        transaction_kind(),
+        any::<u128>(),
+        bytes(),
+        access_list(),
+    )
+        .prop_map(|(chain_id, nonce, gas_price, gas_limit, to, value, input, access_list)| {
+            Transaction::Eip2930(TxEip2930 {
+                chain_id,
+                nonce,
+                gas_price,
+                gas_limit,
+                to,
+                value,
+                input: Bytes(input),
+                access_list,
+            })
+        });
+    let eip1559 = (
+        (any::<u64>(), any::<u64>(), any::<u128>(), any::<u128>(), any::<u64>()),
+        transaction_kind(),
+        any::<u128>(),
+        bytes(),
+        access_list(),
+    )
+        .prop_map(
+            |(
+                (chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit),
+                to,
+                value,
+                input,
+                access_list,
+            )| {
+                Transaction::Eip1559(TxEip1559 {
+                    chain_id,
+                    nonce,
+                    max_priority_fee_per_gas,
+                    max_fee_per_gas,
+                    gas_limit,
+                    to,
+                    value,
+                    input: Bytes(input),
+                    access_list,
+                })
+            },
+        );
+    prop_oneof![legacy, eip2930, eip1559]
+}
+
+/// Returns a strategy for signatures, which are not necessarily valid.
+pub fn signature() -> impl Strategy<Value = Signature> {
+    (u256(), u256(), any::<bool>()).prop_map(|(r, s, odd_y_parity)| Signature {
+        r,
+        s,
+        odd_y_parity,
+    })
+}
+
+/// Returns a strategy for signed transactions, with a hash that matches their encoding.
+pub fn transaction_signed() -> impl Strategy<Value = TransactionSigned> {
+    (transaction(), signature()).prop_map(|(transaction, signature)| {
+        TransactionSigned::from_transaction_and_signature(transaction, signature)
+    })
+}
+
+/// Implements [Arbitrary] for the types with the strategies of this module.
+macro_rules! impl_arbitrary {
+    ($($ty:ty => $strategy:ident),* $(,)?) => {
+        $(
+            impl Arbitrary for $ty {
+                type Parameters = ();
+                type Strategy = BoxedStrategy<$ty>;
+
+                fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                    $strategy().boxed()
+                }
+            }
+        )*
+    };
+}
+
+impl_arbitrary!(
+    Log => log,
+    TxType => tx_type,
+    Receipt => receipt,
+    Header => header,
+    TransactionKind => transaction_kind,
+    AccessList => access_list,
+    Transaction => transaction,
+    Signature => signature,
+    TransactionSigned => transaction_signed,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{
+        prop_assert, prop_assert_eq, proptest,
+        test_runner::{TestCaseError, TestCaseResult},
+    };
+    use reth_codecs::Compact;
+    use reth_rlp::{Decodable, Encodable};
+    use std::fmt::Debug;
+
+    /// Asserts that the value decodes from its RLP encoding, which has the expected length.
+    fn rlp_roundtrip<T>(value: &T) -> TestCaseResult
+    where
+        T: Encodable + Decodable + PartialEq + Debug,
+    {
+        let mut buf = Vec::new();
+        value.encode(&mut buf);
+        prop_assert_eq!(buf.len(), value.length());
+
+        let mut slice = buf.as_slice();
+        let decoded =
+            T::decode(&mut slice).map_err(|err| TestCaseError::fail(format!("{err:?}")))?;
+        prop_assert_eq!(&decoded, value);
+        prop_assert!(slice.is_empty(), "the encoding is consumed");
+        Ok(())
+    }
+
+    /// Asserts that the value decodes from its compact encoding.
+    fn compact_roundtrip<T>(value: &T) -> TestCaseResult
+    where
+        T: Compact + Clone + PartialEq + Debug,
+    {
+        let mut buf = Vec::new();
+        let len = value.clone().to_compact(&mut buf);
+        let (decoded, rest) = T::from_compact(&buf, len);
+        prop_assert_eq!(&decoded, value);
+        prop_assert!(rest.is_empty(), "the encoding is consumed");
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn header_roundtrip(header: Header) {
+            rlp_roundtrip(&header)?;
+            compact_roundtrip(&header)?;
+        }
+
+        #[test]
+        fn log_roundtrip(log: Log) {
+            rlp_roundtrip(&log)?;
+            compact_roundtrip(&log)?;
+        }
+
+        #[test]
+        fn receipt_roundtrip(receipt: Receipt) {
+            compact_roundtrip(&receipt)?;
+
+            let mut buf = Vec::new();
+            receipt.encode(&mut buf);
+            prop_assert_eq!(buf.len(), receipt.length());
+            let mut slice = buf.as_slice();
+            let decoded = Receipt::decode_lenient(&mut slice)
+                .map_err(|err| TestCaseError::fail(format!("{err:?}")))?;
+            prop_assert_eq!(&decoded, &receipt);
+            prop_assert!(slice.is_empty(), "the encoding is consumed");
+
+            // only the lenient decoding accepts unknown types
+            if matches!(receipt.tx_type, TxType::Other(_)) {
+                prop_assert!(Receipt::decode(&mut buf.as_slice()).is_err());
+            } else {
+                rlp_roundtrip(&receipt)?;
+            }
+        }
+
+        #[test]
+        fn transaction_roundtrip(transaction: Transaction) {
+            compact_roundtrip(&transaction)?;
+        }
+
+        #[test]
+        fn transaction_signed_roundtrip(transaction: TransactionSigned) {
+            rlp_roundtrip(&transaction)?;
+            compact_roundtrip(&transaction)?;
+
+            let decoded = TransactionSigned::decode_enveloped(&transaction.envelope_encoded())
+                .map_err(|err| TestCaseError::fail(format!("{err:?}")))?;
+            prop_assert_eq!(decoded.hash(), transaction.hash());
+            prop_assert_eq!(decoded, transaction);
+        }
+    }
+}
@@ -2,8 +2,11 @@ use crate::{keccak256, Address, Bytes, ChainId, TxHash, H256};
 pub use access_list::{AccessList, AccessListItem};
 use bytes::{Buf, BytesMut};
 use derive_more::{AsRef, Deref};
+use once_cell::sync::OnceCell;
 use reth_codecs::{main_codec, Compact};
-use reth_rlp::{length_of_length, Decodable, DecodeError, Encodable, Header, EMPTY_STRING_CODE};
+use reth_rlp::{
+    length_of_length, Decodable, DecodeError, Encodable, Header, EMPTY_LIST_CODE, EMPTY_STRING_CODE,
+};
 pub use signature::Signature;
 pub use tx_type::TxType;
 
@@ -187,6 +190,15 @@ impl Transaction {
         keccak256(&buf)
     }
 
+    /// Gets the transaction's chain id, `None` for legacy transactions without replay protection.
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            Transaction::Legacy(TxLegacy { chain_id, .. }) => *chain_id,
+            Transaction::Eip2930(TxEip2930 { chain_id, .. }) |
+            Transaction::Eip1559(TxEip1559 { chain_id, .. }) => Some(*chain_id),
+        }
+    }
+
     /// Sets the transaction's chain id to the provided value.
     pub fn set_chain_id(&mut self, chain_id: u64) {
         match self {
@@ -206,6 +218,15 @@ impl Transaction {
         }
     }
 
+    /// Gets the transaction's access list, `None` for legacy transactions.
+    pub fn access_list(&self) -> Option<&AccessList> {
+        match self {
+            Transaction::Legacy(_) => None,
+            Transaction::Eip2930(TxEip2930 { access_list, .. }) |
+            Transaction::Eip1559(TxEip1559 { access_list, .. }) => Some(access_list),
+        }
+    }
+
     /// Get transaction type
     pub fn tx_type(&self) -> TxType {
         match self {
@@ -251,6 +272,20 @@ impl Transaction {
         }
     }
 
+    /// Returns the tip per gas the beneficiary receives if the transaction is included in a block
+    /// with the given base fee.
+    ///
+    /// Returns `None` if the transaction can't pay the base fee.
+    pub fn effective_tip_per_gas(&self, base_fee: u64) -> Option<u128> {
+        let max_tip = self.max_fee_per_gas().checked_sub(base_fee as u128)?;
+        match self {
+            Transaction::Legacy(_) | Transaction::Eip2930(_) => Some(max_tip),
+            Transaction::Eip1559(TxEip1559 { max_priority_fee_per_gas, .. }) => {
+                Some(max_tip.min(*max_priority_fee_per_gas))
+            }
+        }
+    }
+
     /// Get the transaction's input field.
     pub fn input(&self) -> &Bytes {
         match self {
@@ -727,6 +762,31 @@ impl TransactionSigned {
         initial_tx
     }
 
+    /// Returns the encoding of the transaction in the format of `eth_sendRawTransaction`, see
+    /// [`Self::decode_enveloped`].
+    pub fn envelope_encoded(&self) -> Bytes {
+        let mut buf = Vec::new();
+        self.encode_inner(&mut buf, false);
+        buf.into()
+    }
+
+    /// Decodes a transaction in the format of `eth_sendRawTransaction`.
+    ///
+    /// Unlike the p2p format of the [`Decodable`] implementation, typed transactions are not
+    /// wrapped in an RLP string but are only their type followed by the RLP list of their fields.
+    /// Legacy transactions are an RLP list in both formats.
+    pub fn decode_enveloped(tx: &[u8]) -> Result<Self, DecodeError> {
+        let Some(&first) = tx.first() else { return Err(DecodeError::InputTooShort) };
+        if first >= EMPTY_LIST_CODE {
+            return Self::decode(&mut &tx[..])
+        }
+
+        let mut buf = Vec::with_capacity(tx.len() + length_of_length(tx.len()));
+        Header { list: false, payload_length: tx.len() }.encode(&mut buf);
+        buf.extend_from_slice(tx);
+        Self::decode(&mut &buf[..])
+    }
+
     /// Output the length of the inner transaction and signature fields.
     pub(crate) fn inner_tx_len(&self) -> usize {
         let mut len = self.transaction.fields_len();
@@ -799,6 +859,78 @@ impl TransactionSignedEcRecovered {
     }
 }
 
+/// Signed transaction that recovers its signer on first use.
+///
+/// The signer, or the failure to recover it, is cached, so passing the transaction through
+/// validation, the pool and execution recovers the signature at most once. The hash is always
+/// cached by [`TransactionSigned`].
+#[derive(Debug, Clone, Default, AsRef, Deref)]
+pub struct TransactionSignedLazy {
+    /// Signed transaction
+    #[deref]
+    #[as_ref]
+    signed_transaction: TransactionSigned,
+    /// Signer of the transaction, `None` if the signature is invalid
+    signer: OnceCell<Option<Address>>,
+}
+
+impl TransactionSignedLazy {
+    /// Creates the transaction, the signer is recovered on first use.
+    pub fn new(signed_transaction: TransactionSigned) -> Self {
+        Self { signed_transaction, signer: OnceCell::new() }
+    }
+
+    /// Returns the transaction hash.
+    pub fn hash(&self) -> TxHash {
+        self.signed_transaction.hash
+    }
+
+    /// Returns the signer of the transaction, recovering it on the first call.
+    ///
+    /// Returns `None` if the transaction's signature is invalid.
+    pub fn signer(&self) -> Option<Address> {
+        *self.signer.get_or_init(|| self.signed_transaction.recover_signer())
+    }
+
+    /// Returns true if the signer was recovered already.
+    pub fn is_recovered(&self) -> bool {
+        self.signer.get().is_some()
+    }
+
+    /// Converts into a [`TransactionSignedEcRecovered`], recovering the signer if it's not
+    /// cached.
+    pub fn into_ecrecovered(self) -> Option<TransactionSignedEcRecovered> {
+        let signer = self.signer()?;
+        Some(TransactionSignedEcRecovered::from_signed_transaction(self.signed_transaction, signer))
+    }
+
+    /// Transform back to [`TransactionSigned`]
+    pub fn into_signed(self) -> TransactionSigned {
+        self.signed_transaction
+    }
+}
+
+impl PartialEq for TransactionSignedLazy {
+    fn eq(&self, other: &Self) -> bool {
+        self.signed_transaction == other.signed_transaction
+    }
+}
+
+impl Eq for TransactionSignedLazy {}
+
+impl From<TransactionSigned> for TransactionSignedLazy {
+    fn from(signed_transaction: TransactionSigned) -> Self {
+        Self::new(signed_transaction)
+    }
+}
+
+impl From<TransactionSignedEcRecovered> for TransactionSignedLazy {
+    fn from(recovered: TransactionSignedEcRecovered) -> Self {
+        let TransactionSignedEcRecovered { signer, signed_transaction } = recovered;
+        Self { signed_transaction, signer: OnceCell::with_value(Some(signer)) }
+    }
+}
+
 /// A transaction type that can be created from a [`TransactionSignedEcRecovered`] transaction.
 ///
 /// This is a conversion trait that'll ensure transactions received via P2P can be converted to the
@@ -843,6 +975,23 @@ mod tests {
     use reth_rlp::{Decodable, Encodable};
     use std::str::FromStr;
 
+    #[test]
+    fn effective_tip_per_gas() {
+        let legacy = Transaction::Legacy(TxLegacy { gas_price: 30, ..Default::default() });
+        assert_eq!(legacy.effective_tip_per_gas(0), Some(30));
+        assert_eq!(legacy.effective_tip_per_gas(20), Some(10));
+        assert_eq!(legacy.effective_tip_per_gas(31), None);
+
+        let eip1559 = Transaction::Eip1559(TxEip1559 {
+            max_fee_per_gas: 30,
+            max_priority_fee_per_gas: 5,
+            ..Default::default()
+        });
+        assert_eq!(eip1559.effective_tip_per_gas(20), Some(5));
+        assert_eq!(eip1559.effective_tip_per_gas(27), Some(3));
+        assert_eq!(eip1559.effective_tip_per_gas(31), None);
+    }
+
     #[test]
     fn test_decode_create() {
         // tests that a contract creation tx encodes and decodes properly
@@ -899,6 +1048,32 @@ mod tests {
         assert_eq!(decoded, tx);
     }
 
+    #[test]
+    fn decode_enveloped() {
+        let bytes = hex::decode("b87502f872041a8459682f008459682f0d8252089461815774383099e24810ab832a5b2a5425c154d58829a2241af62c000080c001a059e6b67f48fb32e7e570dfb11e042b5ad2e55e3ce3ce9cd989c7e06e07feeafda0016b83f4f980694ed2eee4d10667242b1f40dc406901b34125b008d334d47469").unwrap();
+        let expected = TransactionSigned::decode(&mut &bytes[..]).unwrap();
+
+        // the raw transaction is the p2p encoding without the string header
+        let decoded = TransactionSigned::decode_enveloped(&bytes[2..]).unwrap();
+        assert_eq!(decoded, expected);
+        assert_eq!(decoded.hash(), expected.recalculate_hash());
+        assert_eq!(decoded.envelope_encoded()[..], bytes[2..]);
+
+        let legacy = TransactionSigned::from_transaction_and_signature(
+            Transaction::Legacy(TxLegacy {
+                chain_id: Some(1),
+                gas_limit: 21000,
+                ..Default::default()
+            }),
+            Signature { r: U256::from(1), s: U256::from(2), odd_y_parity: false },
+        );
+        let mut buf = Vec::new();
+        legacy.encode(&mut buf);
+        assert_eq!(TransactionSigned::decode_enveloped(&buf).unwrap(), legacy);
+
+        assert!(TransactionSigned::decode_enveloped(&[]).is_err());
+    }
+
     #[test]
     fn decode_transaction_consumes_buffer() {
         let bytes = &mut &hex::decode("b87502f872041a8459682f008459682f0d8252089461815774383099e24810ab832a5b2a5425c154d58829a2241af62c000080c001a059e6b67f48fb32e7e570dfb11e042b5ad2e55e3ce3ce9cd989c7e06e07feeafda0016b83f4f980694ed2eee4d10667242b1f40dc406901b34125b008d334d47469").unwrap()[..];
@@ -1057,6 +1232,37 @@ mod tests {
         assert_eq!(tx.recover_signer(), Some(signer), "Recovering signer should pass.");
     }
 
+    #[test]
+    fn lazy_signer_recovery() {
+        use crate::{hex_literal::hex, TransactionSignedLazy};
+
+        let signer: Address = hex!("641c5d790f862a58ec7abcfd644c0442e9c201b3").into();
+        let raw =hex!("f88b8212b085028fa6ae00830f424094aad593da0c8116ef7d2d594dd6a63241bccfc26c80a48318b64b000000000000000000000000641c5d790f862a58ec7abcfd644c0442e9c201b32aa0a6ef9e170bca5ffb7ac05433b13b7043de667fbb0b4a5e45d3b54fb2d6efcc63a0037ec2c05c3d60c5f5f78244ce0a3859e3a18a36c61efb061b383507d3ce19d2");
+        let signed = TransactionSigned::decode(&mut raw.as_ref()).unwrap();
+        let hash = signed.hash();
+
+        let tx = TransactionSignedLazy::new(signed.clone());
+        assert!(!tx.is_recovered());
+        assert_eq!(tx.hash(), hash);
+        assert_eq!(tx.signer(), Some(signer));
+        assert!(tx.is_recovered());
+        let recovered = tx.into_ecrecovered().unwrap();
+        assert_eq!(recovered.signer(), signer);
+
+        // a recovered transaction keeps its signer
+        let tx = TransactionSignedLazy::from(recovered);
+        assert!(tx.is_recovered());
+        assert_eq!(tx.signer(), Some(signer));
+
+        // an invalid signature is cached as well
+        let mut invalid = signed;
+        invalid.signature.r = U256::zero();
+        let tx = TransactionSignedLazy::new(invalid);
+        assert_eq!(tx.signer(), None);
+        assert!(tx.is_recovered());
+        assert!(tx.into_ecrecovered().is_none());
+    }
+
     #[test]
     fn recover_signer_legacy() {
         use crate::hex_literal::hex;
@@ -1,3 +1,4 @@
+use bytes::Buf;
 use reth_codecs::Compact;
 use serde::{Deserialize, Serialize};
 
@@ -6,30 +7,68 @@ use serde::{Deserialize, Serialize};
 pub enum TxType {
     /// Legacy transaction pre EIP-2929
     #[default]
-    Legacy = 0_isize,
+    Legacy,
     /// AccessList transaction
-    EIP2930 = 1_isize,
+    EIP2930,
     /// Transaction with Priority fee
-    EIP1559 = 2_isize,
+    EIP1559,
+    /// Transaction type that is not known to reth, identified by its raw EIP-2718 type byte.
+    ///
+    /// This is only produced by lenient receipt decoding, see
+    /// [`Receipt::decode_lenient`](crate::Receipt::decode_lenient).
+    Other(u8),
+}
+
+impl TxType {
+    /// Returns the EIP-2718 type byte, `0` for legacy transactions.
+    pub fn ty(&self) -> u8 {
+        match self {
+            TxType::Legacy => 0,
+            TxType::EIP2930 => 1,
+            TxType::EIP1559 => 2,
+            TxType::Other(ty) => *ty,
+        }
+    }
 }
 
 impl Compact for TxType {
-    fn to_compact(self, _: &mut impl bytes::BufMut) -> usize {
+    fn to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
         match self {
             TxType::Legacy => 0,
             TxType::EIP2930 => 1,
-            _ => 2,
+            TxType::EIP1559 => 2,
+            TxType::Other(ty) => {
+                buf.put_u8(ty);
+                3
+            }
         }
     }
 
-    fn from_compact(buf: &[u8], identifier: usize) -> (Self, &[u8]) {
+    fn from_compact(mut buf: &[u8], identifier: usize) -> (Self, &[u8]) {
         (
             match identifier {
                 0 => TxType::Legacy,
                 1 => TxType::EIP2930,
-                _ => TxType::EIP1559,
+                2 => TxType::EIP1559,
+                _ => TxType::Other(buf.get_u8()),
             },
             buf,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_roundtrip() {
+        for tx_type in [TxType::Legacy, TxType::EIP2930, TxType::EIP1559, TxType::Other(0x7e)] {
+            let mut buf = vec![];
+            let identifier = tx_type.to_compact(&mut buf);
+            let (decoded, rest) = TxType::from_compact(&buf, identifier);
+            assert_eq!(decoded, tx_type);
+            assert!(rest.is_empty());
+        }
+    }
+}
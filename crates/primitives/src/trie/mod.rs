@@ -0,0 +1,328 @@
+//! An in-memory Merkle-Patricia trie and the verification of its proofs.
+//!
+//! The trie is used where the nodes of the state trie are needed instead of just its root, like
+//! the proofs of the `snap` protocol. Tries can be partial: nodes that are only known by their
+//! hash are kept as [TrieNode::Hash], e.g. the siblings of the nodes of a proof.
+//! They can be loaded when an update needs them, see [Trie::insert_with].
+use crate::{proofs::EMPTY_ROOT, Bytes, H256};
+use reth_rlp::DecodeError;
+use std::collections::HashMap;
+use thiserror::Error;
+
+mod nibbles;
+mod node;
+mod proof;
+
+pub use nibbles::{decode_compact, encode_compact, from_nibbles, to_nibbles};
+pub use node::TrieNode;
+pub use proof::{verify_proof, verify_range_proof};
+
+/// An error of a trie operation.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TrieError {
+    /// The operation needs a node that is only known by its hash.
+    #[error("The trie node {0:?} is missing.")]
+    MissingNode(H256),
+    /// The encoding of a node is not a valid trie node.
+    #[error("Invalid trie node.")]
+    InvalidNode,
+    /// The RLP encoding of a node is invalid.
+    #[error("Invalid trie node encoding: {0}")]
+    Rlp(#[from] DecodeError),
+    /// The proof does not match the root.
+    #[error("The proof does not match the root.")]
+    InvalidProof,
+}
+
+/// A Merkle-Patricia trie whose nodes are all kept in memory.
+///
+/// The keys are bytes, the nodes of the state and storage tries of Ethereum are keyed by the
+/// hashes of the addresses and slots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trie {
+    root: TrieNode,
+}
+
+// === impl Trie ===
+
+impl Trie {
+    /// Creates a trie of the given key value pairs.
+    pub fn from_entries<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<[u8]>,
+        V: Into<Vec<u8>>,
+    {
+        let mut trie = Trie::default();
+        for (key, value) in entries {
+            // a trie without hash nodes can't fail
+            trie.insert(key.as_ref(), value.into()).expect("trie is fully known");
+        }
+        trie
+    }
+
+    /// Creates the partial trie of the given root with the nodes of a proof.
+    ///
+    /// The nodes of the proof that are not reachable from the root are ignored.
+    pub fn from_proof(root: H256, proof: &[Bytes]) -> Result<Self, TrieError> {
+        let nodes: HashMap<_, _> =
+            proof.iter().map(|node| (crate::keccak256(node), node.to_vec())).collect();
+        let mut root = if root == EMPTY_ROOT { TrieNode::Empty } else { TrieNode::Hash(root) };
+        root.resolve(&nodes)?;
+        Ok(Self { root })
+    }
+
+    /// Creates the partial trie of the given root whose nodes are loaded when they are needed, see
+    /// [Trie::insert_with].
+    pub fn from_root(root: H256) -> Self {
+        let root = if root == EMPTY_ROOT { TrieNode::Empty } else { TrieNode::Hash(root) };
+        Self { root }
+    }
+
+    /// Returns the root node.
+    pub fn root(&self) -> &TrieNode {
+        &self.root
+    }
+
+    /// Returns the root hash.
+    pub fn root_hash(&self) -> H256 {
+        self.root.hash()
+    }
+
+    /// Returns the value of the key.
+    ///
+    /// Fails if the path of the key leads through a node that is only known by its hash.
+    pub fn get(&self, key: &[u8]) -> Result<Option<&[u8]>, TrieError> {
+        self.root.get(&to_nibbles(key))
+    }
+
+    /// Returns the node that starts at the given nibbles, if any.
+    pub fn node_at(&self, path: &[u8]) -> Option<&TrieNode> {
+        self.root.node_at(path)
+    }
+
+    /// Inserts the value of the key.
+    ///
+    /// Empty values can't be stored in a trie, use [Trie::remove] instead.
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Result<(), TrieError> {
+        self.root.insert(&[], &to_nibbles(key), value, &mut missing)
+    }
+
+    /// Inserts the value of the key like [Trie::insert].
+    ///
+    /// The nodes on the path of the key that are only known by their hash are replaced with the
+    /// nodes `load` returns, it's called with the nibbles of the position of the node and its hash.
+    pub fn insert_with<E: From<TrieError>>(
+        &mut self,
+        key: &[u8],
+        value: Vec<u8>,
+        mut load: impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<(), E> {
+        self.root.insert(&[], &to_nibbles(key), value, &mut load)
+    }
+
+    /// Removes the key and returns its value.
+    pub fn remove(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, TrieError> {
+        self.root.remove(&[], &to_nibbles(key), &mut missing)
+    }
+
+    /// Removes the key and returns its value like [Trie::remove].
+    ///
+    /// The nodes that are only known by their hash are loaded like in [Trie::insert_with], this
+    /// includes the remaining child of a branch that is merged with it.
+    pub fn remove_with<E: From<TrieError>>(
+        &mut self,
+        key: &[u8],
+        mut load: impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<Option<Vec<u8>>, E> {
+        self.root.remove(&[], &to_nibbles(key), &mut load)
+    }
+
+    /// Returns the root hash along with the nibbles of the positions and the encodings of the
+    /// branches and extensions that are the root or are referenced by their hash.
+    ///
+    /// These are the nodes needed to update the trie without building it from all keys again, the
+    /// leaves can be recovered from the key value pairs. Nodes that are only known by their hash
+    /// are not included.
+    pub fn root_hash_with_branches(&self) -> (H256, Vec<(Vec<u8>, Vec<u8>)>) {
+        let is_branch =
+            |node: &TrieNode| matches!(node, TrieNode::Branch { .. } | TrieNode::Extension { .. });
+        let mut branches = Vec::new();
+        let encoded = self.root.encode_with(&mut Vec::new(), &mut |path, node, encoded| {
+            if is_branch(node) {
+                branches.push((path.to_vec(), encoded.to_vec()));
+            }
+        });
+        let root = match &self.root {
+            TrieNode::Empty => EMPTY_ROOT,
+            TrieNode::Hash(hash) => *hash,
+            _ => crate::keccak256(&encoded),
+        };
+        if is_branch(&self.root) {
+            branches.push((Vec::new(), encoded));
+        }
+        (root, branches)
+    }
+
+    /// Returns the proof of the key, the encodings of the nodes on its path from the root.
+    ///
+    /// The proof of a key that is not in the trie proves its absence.
+    pub fn proof(&self, key: &[u8]) -> Vec<Bytes> {
+        let mut proof = Vec::new();
+        self.root.proof(&to_nibbles(key), &mut proof);
+        proof.into_iter().map(Bytes::from).collect()
+    }
+
+    /// Returns the proof of the key like [Trie::proof].
+    ///
+    /// The nodes on the path of the key that are only known by their hash are loaded like in
+    /// [Trie::insert_with] and kept in the trie.
+    pub fn proof_with<E: From<TrieError>>(
+        &mut self,
+        key: &[u8],
+        mut load: impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<Vec<Bytes>, E> {
+        self.root.load_path(&[], &to_nibbles(key), &mut load)?;
+        Ok(self.proof(key))
+    }
+}
+
+/// The loader of tries that are fully known.
+fn missing(_: &[u8], hash: H256) -> Result<TrieNode, TrieError> {
+    Err(TrieError::MissingNode(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keccak256, U256};
+    use reth_rlp::Encodable;
+    use triehash::sec_trie_root;
+
+    fn entries(len: u64) -> Vec<(H256, Vec<u8>)> {
+        (0..len)
+            .map(|i| {
+                let mut value = Vec::new();
+                U256::from(i + 1).encode(&mut value);
+                (keccak256(H256::from_low_u64_be(i)), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn root_matches_triehash() {
+        assert_eq!(Trie::default().root_hash(), EMPTY_ROOT);
+
+        for len in [1, 2, 3, 17, 100] {
+            let plain = (0..len)
+                .map(|i| {
+                    let mut value = Vec::new();
+                    U256::from(i + 1).encode(&mut value);
+                    (H256::from_low_u64_be(i), value)
+                })
+                .collect::<Vec<_>>();
+            let expected = sec_trie_root::<crate::proofs::KeccakHasher, _, _, _>(plain);
+            assert_eq!(Trie::from_entries(entries(len)).root_hash(), expected, "{len} entries");
+        }
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let entries = entries(50);
+        let mut trie = Trie::from_entries(entries.clone());
+        for (key, value) in &entries {
+            assert_eq!(trie.get(key.as_bytes()), Ok(Some(value.as_slice())));
+        }
+        assert_eq!(trie.get(H256::zero().as_bytes()), Ok(None));
+
+        // removing keys restores the shape of a trie without them
+        for (removed, (key, value)) in entries.iter().enumerate() {
+            assert_eq!(trie.remove(key.as_bytes()), Ok(Some(value.clone())));
+            let rest = Trie::from_entries(entries[removed + 1..].iter().cloned());
+            assert_eq!(trie.root_hash(), rest.root_hash());
+        }
+        assert_eq!(trie.root_hash(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn node_roundtrip() {
+        let trie = Trie::from_entries(entries(20));
+        let decoded = TrieNode::decode(&trie.root().encode()).unwrap();
+        assert_eq!(decoded.hash(), trie.root_hash());
+
+        for node in decoded_children(&decoded) {
+            assert!(matches!(node, TrieNode::Hash(_) | TrieNode::Empty));
+        }
+    }
+
+    #[test]
+    fn partial_trie_with_loaded_nodes() {
+        let entries = entries(100);
+        let trie = Trie::from_entries(entries.clone());
+        let (root, branches) = trie.root_hash_with_branches();
+        assert_eq!(root, trie.root_hash());
+        let branches: HashMap<_, _> = branches.into_iter().collect();
+        assert!(branches.contains_key(&Vec::new()));
+
+        // branches are loaded by position, leaves are recovered from the entries
+        let node_at = |path: &[u8]| -> Result<TrieNode, TrieError> {
+            if let Some(encoded) = branches.get(path) {
+                return TrieNode::decode(encoded)
+            }
+            let (key, value) = entries
+                .iter()
+                .find(|(key, _)| to_nibbles(key.as_bytes()).starts_with(path))
+                .expect("leaf exists");
+            Ok(TrieNode::Leaf {
+                path: to_nibbles(key.as_bytes())[path.len()..].to_vec(),
+                value: value.clone(),
+            })
+        };
+        let mut loaded = Vec::new();
+        let mut load = |path: &[u8], _: H256| {
+            loaded.push(path.to_vec());
+            node_at(path)
+        };
+
+        let mut partial = Trie::from_root(root);
+        let inserted = (keccak256(H256::from_low_u64_be(1000)), vec![0x01]);
+        partial.insert_with(inserted.0.as_bytes(), inserted.1.clone(), &mut load).unwrap();
+        for (key, _) in &entries[..10] {
+            assert!(partial.remove_with(key.as_bytes(), &mut load).unwrap().is_some());
+        }
+
+        let expected = Trie::from_entries(entries[10..].iter().cloned().chain([inserted]));
+        assert_eq!(partial.root_hash(), expected.root_hash());
+        // only the paths of the changed keys are loaded
+        assert!(branches.keys().any(|path| !loaded.contains(path)));
+
+        // the proofs of a partial trie match the ones of the full trie
+        let mut partial = Trie::from_root(root);
+        let absent = keccak256(H256::from_low_u64_be(1000));
+        for key in [entries[3].0, entries[42].0, absent] {
+            let proof = partial.proof_with(key.as_bytes(), |path, _| node_at(path)).unwrap();
+            assert_eq!(proof, trie.proof(key.as_bytes()));
+        }
+    }
+
+    fn decoded_children(node: &TrieNode) -> Vec<&TrieNode> {
+        match node {
+            TrieNode::Branch { children, .. } => children.iter().collect(),
+            TrieNode::Extension { child, .. } => vec![child],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn partial_trie_from_proof() {
+        let entries = entries(30);
+        let trie = Trie::from_entries(entries.clone());
+        let (key, value) = &entries[7];
+
+        let partial = Trie::from_proof(trie.root_hash(), &trie.proof(key.as_bytes())).unwrap();
+        assert_eq!(partial.root_hash(), trie.root_hash());
+        assert_eq!(partial.get(key.as_bytes()), Ok(Some(value.as_slice())));
+        // a key behind another child of the root is not part of the proof
+        let (other, _) = entries.iter().find(|(other, _)| other[0] >> 4 != key[0] >> 4).unwrap();
+        assert!(matches!(partial.get(other.as_bytes()), Err(TrieError::MissingNode(_))));
+    }
+}
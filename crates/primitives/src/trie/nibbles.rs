@@ -0,0 +1,93 @@
+//! Nibble paths and their hex-prefix encoding.
+
+/// Splits the bytes of a key into its nibbles, the high nibble of every byte first.
+pub fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+/// Joins an even number of nibbles back into bytes.
+///
+/// Returns `None` if the number of nibbles is odd.
+pub fn from_nibbles(nibbles: &[u8]) -> Option<Vec<u8>> {
+    if nibbles.len() % 2 != 0 {
+        return None
+    }
+    Some(nibbles.chunks(2).map(|pair| (pair[0] << 4) | pair[1]).collect())
+}
+
+/// Encodes the nibbles with the hex-prefix encoding of the yellow paper, which is used for the
+/// paths of leaf and extension nodes and the trie node paths of `snap` requests.
+pub fn encode_compact(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 0x20 } else { 0x00 };
+    let mut encoded = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        encoded.push(flag | 0x10 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        encoded.push(flag);
+        nibbles
+    };
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+/// Decodes a hex-prefix encoded path into its nibbles and whether it's the path of a leaf.
+///
+/// Returns `None` if the flags of the encoding are invalid.
+pub fn decode_compact(encoded: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let (first, rest) = encoded.split_first()?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return None
+    }
+    let is_leaf = flag & 0x2 != 0;
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 0x1 != 0 {
+        nibbles.push(first & 0x0f);
+    } else if first & 0x0f != 0 {
+        return None
+    }
+    nibbles.extend(to_nibbles(rest));
+    Some((nibbles, is_leaf))
+}
+
+/// Returns the length of the common prefix of the nibbles.
+pub(crate) fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_roundtrip() {
+        // the examples of the yellow paper
+        assert_eq!(encode_compact(&[1, 2, 3, 4, 5], false), vec![0x11, 0x23, 0x45]);
+        assert_eq!(encode_compact(&[0, 1, 2, 3, 4, 5], false), vec![0x00, 0x01, 0x23, 0x45]);
+        assert_eq!(encode_compact(&[0, 15, 1, 12, 11, 8], true), vec![0x20, 0x0f, 0x1c, 0xb8]);
+        assert_eq!(encode_compact(&[15, 1, 12, 11, 8], true), vec![0x3f, 0x1c, 0xb8]);
+        assert_eq!(encode_compact(&[], false), vec![0x00]);
+
+        for (nibbles, is_leaf) in
+            [(vec![], false), (vec![], true), (vec![7], true), (vec![1, 2, 3, 4], false)]
+        {
+            let encoded = encode_compact(&nibbles, is_leaf);
+            assert_eq!(decode_compact(&encoded), Some((nibbles, is_leaf)));
+        }
+
+        assert_eq!(decode_compact(&[]), None);
+        assert_eq!(decode_compact(&[0x40]), None);
+        // the padding nibble of even paths is zero
+        assert_eq!(decode_compact(&[0x01]), None);
+    }
+
+    #[test]
+    fn nibbles_roundtrip() {
+        let key = [0xab, 0x01];
+        assert_eq!(to_nibbles(&key), vec![0xa, 0xb, 0x0, 0x1]);
+        assert_eq!(from_nibbles(&to_nibbles(&key)), Some(key.to_vec()));
+        assert_eq!(from_nibbles(&[1]), None);
+        assert_eq!(common_prefix(&[1, 2, 3], &[1, 2, 4, 5]), 2);
+    }
+}
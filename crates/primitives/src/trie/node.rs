@@ -0,0 +1,496 @@
+//! The nodes of a Merkle-Patricia trie.
+use super::{
+    nibbles::{common_prefix, decode_compact, encode_compact},
+    TrieError,
+};
+use crate::{keccak256, proofs::EMPTY_ROOT, H256};
+use reth_rlp::{Decodable, Encodable, Header, EMPTY_STRING_CODE};
+use std::{collections::HashMap, mem};
+
+/// A node of a Merkle-Patricia trie.
+///
+/// The paths of leaves and extensions are the nibbles that follow the position of the node in the
+/// trie.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TrieNode {
+    /// The node of an empty trie or an empty child of a branch.
+    #[default]
+    Empty,
+    /// A leaf with the remaining nibbles of its key.
+    Leaf {
+        /// The remaining nibbles of the key.
+        path: Vec<u8>,
+        /// The value of the key.
+        value: Vec<u8>,
+    },
+    /// An extension of the nibbles all keys of its child share.
+    Extension {
+        /// The shared nibbles.
+        path: Vec<u8>,
+        /// The child, a branch or the hash of one.
+        child: Box<TrieNode>,
+    },
+    /// A branch with a child for every nibble.
+    Branch {
+        /// The children by the next nibble of their keys.
+        children: Box<[TrieNode; 16]>,
+        /// The value of the key that ends at the branch.
+        value: Option<Vec<u8>>,
+    },
+    /// A node that is only known by its hash, like the siblings of the nodes of a proof.
+    Hash(H256),
+}
+
+// === impl TrieNode ===
+
+impl TrieNode {
+    /// Returns a branch without children.
+    pub(crate) fn empty_branch() -> Self {
+        TrieNode::Branch { children: Default::default(), value: None }
+    }
+
+    /// Returns the RLP encoding of the node.
+    ///
+    /// Nodes that are only known by their hash are encoded as the hash.
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_with(&mut Vec::new(), &mut |_, _, _| {})
+    }
+
+    /// Returns the RLP encoding of the node at the position of the `prefix` nibbles and passes the
+    /// position, the node and the encoding of every node of the subtrie that is referenced by its
+    /// hash to `visit`.
+    pub(crate) fn encode_with(
+        &self,
+        prefix: &mut Vec<u8>,
+        visit: &mut impl FnMut(&[u8], &TrieNode, &[u8]),
+    ) -> Vec<u8> {
+        let mut payload = Vec::new();
+        match self {
+            TrieNode::Empty => return vec![EMPTY_STRING_CODE],
+            TrieNode::Hash(hash) => {
+                hash.encode(&mut payload);
+                return payload
+            }
+            TrieNode::Leaf { path, value } => {
+                encode_compact(path, true).as_slice().encode(&mut payload);
+                value.as_slice().encode(&mut payload);
+            }
+            TrieNode::Extension { path, child } => {
+                encode_compact(path, false).as_slice().encode(&mut payload);
+                let len = prefix.len();
+                prefix.extend_from_slice(path);
+                child.encode_reference(prefix, visit, &mut payload);
+                prefix.truncate(len);
+            }
+            TrieNode::Branch { children, value } => {
+                for (nibble, child) in children.iter().enumerate() {
+                    prefix.push(nibble as u8);
+                    child.encode_reference(prefix, visit, &mut payload);
+                    prefix.pop();
+                }
+                value.as_deref().unwrap_or_default().encode(&mut payload);
+            }
+        }
+
+        let mut encoded = Vec::with_capacity(payload.len() + 3);
+        Header { list: true, payload_length: payload.len() }.encode(&mut encoded);
+        encoded.extend(payload);
+        encoded
+    }
+
+    /// Encodes the reference of a parent to the node: nodes shorter than 32 bytes are embedded
+    /// into their parent, the others are referenced by their hash.
+    fn encode_reference(
+        &self,
+        prefix: &mut Vec<u8>,
+        visit: &mut impl FnMut(&[u8], &TrieNode, &[u8]),
+        out: &mut Vec<u8>,
+    ) {
+        match self {
+            TrieNode::Empty => out.push(EMPTY_STRING_CODE),
+            TrieNode::Hash(hash) => hash.encode(out),
+            node => {
+                let encoded = node.encode_with(prefix, visit);
+                if encoded.len() < 32 {
+                    out.extend(encoded);
+                } else {
+                    visit(prefix, node, &encoded);
+                    keccak256(encoded).encode(out);
+                }
+            }
+        }
+    }
+
+    /// Returns the hash of the node, which is the root of the trie if it's the root node.
+    pub fn hash(&self) -> H256 {
+        match self {
+            TrieNode::Empty => EMPTY_ROOT,
+            TrieNode::Hash(hash) => *hash,
+            node => keccak256(node.encode()),
+        }
+    }
+
+    /// Decodes a node from its RLP encoding.
+    ///
+    /// Embedded children are decoded along with their parent, the other children are
+    /// [TrieNode::Hash]es.
+    pub fn decode(encoded: &[u8]) -> Result<Self, TrieError> {
+        let items = list_items(encoded)?;
+        match items.as_slice() {
+            [path, item] => {
+                let path = decode_string(path)?;
+                let (path, is_leaf) = decode_compact(&path).ok_or(TrieError::InvalidNode)?;
+                if is_leaf {
+                    Ok(TrieNode::Leaf { path, value: decode_string(item)? })
+                } else {
+                    let child = decode_reference(item)?;
+                    if path.is_empty() || matches!(child, TrieNode::Empty) {
+                        return Err(TrieError::InvalidNode)
+                    }
+                    Ok(TrieNode::Extension { path, child: Box::new(child) })
+                }
+            }
+            [children @ .., value] if children.len() == 16 => {
+                let mut branch = Box::<[TrieNode; 16]>::default();
+                for (child, item) in branch.iter_mut().zip(children) {
+                    *child = decode_reference(item)?;
+                }
+                let value = decode_string(value)?;
+                Ok(TrieNode::Branch {
+                    children: branch,
+                    value: (!value.is_empty()).then_some(value),
+                })
+            }
+            _ => Err(TrieError::InvalidNode),
+        }
+    }
+
+    /// Replaces the [TrieNode::Hash]es of the subtrie with the nodes of the given encodings by
+    /// hash, as far as they are known.
+    pub(crate) fn resolve(&mut self, nodes: &HashMap<H256, Vec<u8>>) -> Result<(), TrieError> {
+        match self {
+            TrieNode::Hash(hash) => {
+                if let Some(encoded) = nodes.get(hash) {
+                    *self = TrieNode::decode(encoded)?;
+                    self.resolve(nodes)?;
+                }
+            }
+            TrieNode::Extension { child, .. } => child.resolve(nodes)?,
+            TrieNode::Branch { children, .. } => {
+                for child in children.iter_mut() {
+                    child.resolve(nodes)?;
+                }
+            }
+            TrieNode::Empty | TrieNode::Leaf { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the key with the given nibbles.
+    pub(crate) fn get(&self, path: &[u8]) -> Result<Option<&[u8]>, TrieError> {
+        match self {
+            TrieNode::Empty => Ok(None),
+            TrieNode::Hash(hash) => Err(TrieError::MissingNode(*hash)),
+            TrieNode::Leaf { path: leaf, value } => {
+                Ok((leaf.as_slice() == path).then_some(value.as_slice()))
+            }
+            TrieNode::Extension { path: extension, child } => match path.strip_prefix(&**extension)
+            {
+                Some(rest) => child.get(rest),
+                None => Ok(None),
+            },
+            TrieNode::Branch { children, value } => match path.split_first() {
+                Some((nibble, rest)) => children[*nibble as usize].get(rest),
+                None => Ok(value.as_deref()),
+            },
+        }
+    }
+
+    /// Returns the node that starts at the given nibbles, if any.
+    pub(crate) fn node_at(&self, path: &[u8]) -> Option<&TrieNode> {
+        if path.is_empty() {
+            return Some(self)
+        }
+        match self {
+            TrieNode::Extension { path: extension, child } => {
+                child.node_at(path.strip_prefix(&**extension)?)
+            }
+            TrieNode::Branch { children, .. } => children[path[0] as usize].node_at(&path[1..]),
+            _ => None,
+        }
+    }
+
+    /// Appends the encodings of the nodes on the path of the key that are referenced by their
+    /// hash.
+    pub(crate) fn proof(&self, path: &[u8], proof: &mut Vec<Vec<u8>>) {
+        if matches!(self, TrieNode::Empty | TrieNode::Hash(_)) {
+            return
+        }
+        let encoded = self.encode();
+        if proof.is_empty() || encoded.len() >= 32 {
+            proof.push(encoded);
+        }
+        match self {
+            TrieNode::Extension { path: extension, child } => {
+                if let Some(rest) = path.strip_prefix(&**extension) {
+                    child.proof(rest, proof);
+                }
+            }
+            TrieNode::Branch { children, .. } => {
+                if let Some((nibble, rest)) = path.split_first() {
+                    children[*nibble as usize].proof(rest, proof);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces the [TrieNode::Hash]es on the path of the key with the given nibbles in the subtrie
+    /// at the position of the `prefix` nibbles with the nodes `load` returns for their position and
+    /// hash.
+    pub(crate) fn load_path<E: From<TrieError>>(
+        &mut self,
+        prefix: &[u8],
+        path: &[u8],
+        load: &mut impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<(), E> {
+        if let TrieNode::Hash(hash) = self {
+            *self = load(prefix, *hash)?;
+        }
+        match self {
+            TrieNode::Extension { path: extension, child } => {
+                if let Some(rest) = path.strip_prefix(&**extension) {
+                    child.load_path(&[prefix, extension.as_slice()].concat(), rest, load)?;
+                }
+            }
+            TrieNode::Branch { children, .. } => {
+                if let Some((nibble, rest)) = path.split_first() {
+                    let prefix = [prefix, &[*nibble][..]].concat();
+                    children[*nibble as usize].load_path(&prefix, rest, load)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Inserts the value of the key with the given nibbles into the subtrie at the position of the
+    /// `prefix` nibbles.
+    ///
+    /// The [TrieNode::Hash]es on the path are replaced with the nodes `load` returns for their
+    /// position and hash. Fails without changing the subtrie if a node can't be loaded.
+    pub(crate) fn insert<E: From<TrieError>>(
+        &mut self,
+        prefix: &[u8],
+        path: &[u8],
+        value: Vec<u8>,
+        load: &mut impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<(), E> {
+        match self {
+            TrieNode::Empty => *self = TrieNode::Leaf { path: path.to_vec(), value },
+            TrieNode::Hash(hash) => {
+                *self = TrieNode::load(prefix, *hash, load)?;
+                self.insert(prefix, path, value, load)?;
+            }
+            TrieNode::Leaf { path: leaf, value: leaf_value } => {
+                if leaf.as_slice() == path {
+                    *leaf_value = value;
+                } else {
+                    let common = common_prefix(leaf, path);
+                    let (leaf, leaf_value) = (mem::take(leaf), mem::take(leaf_value));
+                    let branch_prefix = [prefix, &path[..common]].concat();
+                    let mut branch = TrieNode::empty_branch();
+                    branch.insert(&branch_prefix, &leaf[common..], leaf_value, load)?;
+                    branch.insert(&branch_prefix, &path[common..], value, load)?;
+                    *self = TrieNode::extend(&path[..common], branch);
+                }
+            }
+            TrieNode::Extension { path: extension, child } => {
+                let common = common_prefix(extension, path);
+                if common == extension.len() {
+                    child.insert(
+                        &[prefix, &extension[..]].concat(),
+                        &path[common..],
+                        value,
+                        load,
+                    )?;
+                } else {
+                    let (extension, child) = (mem::take(extension), mem::take(&mut **child));
+                    let mut branch = TrieNode::empty_branch();
+                    if let TrieNode::Branch { children, .. } = &mut branch {
+                        children[extension[common] as usize] =
+                            TrieNode::extend(&extension[common + 1..], child);
+                    }
+                    branch.insert(
+                        &[prefix, &path[..common]].concat(),
+                        &path[common..],
+                        value,
+                        load,
+                    )?;
+                    *self = TrieNode::extend(&path[..common], branch);
+                }
+            }
+            TrieNode::Branch { children, value: branch_value } => match path.split_first() {
+                Some((nibble, rest)) => children[*nibble as usize].insert(
+                    &[prefix, &[*nibble][..]].concat(),
+                    rest,
+                    value,
+                    load,
+                )?,
+                None => *branch_value = Some(value),
+            },
+        }
+        Ok(())
+    }
+
+    /// Removes the key with the given nibbles from the subtrie at the position of the `prefix`
+    /// nibbles and returns its value.
+    ///
+    /// The [TrieNode::Hash]es on the path, and the remaining child of a branch that is merged with
+    /// it, are replaced with the nodes `load` returns for their position and hash.
+    pub(crate) fn remove<E: From<TrieError>>(
+        &mut self,
+        prefix: &[u8],
+        path: &[u8],
+        load: &mut impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<Option<Vec<u8>>, E> {
+        let removed = match self {
+            TrieNode::Empty => None,
+            TrieNode::Hash(hash) => {
+                *self = TrieNode::load(prefix, *hash, load)?;
+                return self.remove(prefix, path, load)
+            }
+            TrieNode::Leaf { path: leaf, value } => {
+                if leaf.as_slice() != path {
+                    return Ok(None)
+                }
+                let value = mem::take(value);
+                *self = TrieNode::Empty;
+                return Ok(Some(value))
+            }
+            TrieNode::Extension { path: extension, child } => {
+                match path.strip_prefix(&**extension) {
+                    Some(rest) => child.remove(&[prefix, &extension[..]].concat(), rest, load)?,
+                    None => None,
+                }
+            }
+            TrieNode::Branch { children, value } => match path.split_first() {
+                Some((nibble, rest)) => children[*nibble as usize].remove(
+                    &[prefix, &[*nibble][..]].concat(),
+                    rest,
+                    load,
+                )?,
+                None => value.take(),
+            },
+        };
+        if removed.is_some() {
+            self.normalize(prefix, load)?;
+        }
+        Ok(removed)
+    }
+
+    /// Restores the canonical shape of a branch or an extension whose subtrie shrank.
+    fn normalize<E: From<TrieError>>(
+        &mut self,
+        prefix: &[u8],
+        load: &mut impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<(), E> {
+        match self {
+            TrieNode::Extension { path, child } => match mem::take(&mut **child) {
+                TrieNode::Empty => *self = TrieNode::Empty,
+                child => *self = TrieNode::extend(&mem::take(path), child),
+            },
+            TrieNode::Branch { children, value } => {
+                let mut occupied = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, child)| !matches!(child, TrieNode::Empty))
+                    .map(|(nibble, _)| nibble);
+                match (occupied.next(), occupied.next(), value.is_some()) {
+                    (None, _, false) => *self = TrieNode::Empty,
+                    (None, _, true) => {
+                        *self = TrieNode::Leaf { path: Vec::new(), value: value.take().unwrap() }
+                    }
+                    (Some(nibble), None, false) => {
+                        // the remaining child is merged into the branch, so its kind is needed
+                        if let TrieNode::Hash(hash) = children[nibble] {
+                            children[nibble] = TrieNode::load(
+                                &[prefix, &[nibble as u8][..]].concat(),
+                                hash,
+                                load,
+                            )?;
+                        }
+                        let child = mem::take(&mut children[nibble]);
+                        *self = TrieNode::extend(&[nibble as u8], child);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the node `load` returns for the position and hash of a [TrieNode::Hash].
+    fn load<E: From<TrieError>>(
+        prefix: &[u8],
+        hash: H256,
+        load: &mut impl FnMut(&[u8], H256) -> Result<TrieNode, E>,
+    ) -> Result<TrieNode, E> {
+        match load(prefix, hash)? {
+            TrieNode::Hash(_) => Err(TrieError::MissingNode(hash).into()),
+            node => Ok(node),
+        }
+    }
+
+    /// Prepends the nibbles to the path of the node, with an extension in front of branches.
+    fn extend(prefix: &[u8], node: TrieNode) -> TrieNode {
+        if prefix.is_empty() {
+            return node
+        }
+        match node {
+            TrieNode::Leaf { path, value } => {
+                TrieNode::Leaf { path: [prefix, &path].concat(), value }
+            }
+            TrieNode::Extension { path, child } => {
+                TrieNode::Extension { path: [prefix, &path].concat(), child }
+            }
+            node => TrieNode::Extension { path: prefix.to_vec(), child: Box::new(node) },
+        }
+    }
+}
+
+/// Splits an RLP list into the encodings of its items.
+fn list_items(mut encoded: &[u8]) -> Result<Vec<&[u8]>, TrieError> {
+    let header = Header::decode(&mut encoded)?;
+    if !header.list {
+        return Err(TrieError::InvalidNode)
+    }
+    let mut payload = &encoded[..header.payload_length];
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        let mut rest = payload;
+        let item = Header::decode(&mut rest)?;
+        let len = payload.len() - rest.len() + item.payload_length;
+        items.push(&payload[..len]);
+        payload = &payload[len..];
+    }
+    Ok(items)
+}
+
+/// Decodes an RLP string.
+fn decode_string(mut item: &[u8]) -> Result<Vec<u8>, TrieError> {
+    Ok(bytes::Bytes::decode(&mut item)?.to_vec())
+}
+
+/// Decodes the reference of a parent to a child, see [TrieNode::encode_reference].
+fn decode_reference(item: &[u8]) -> Result<TrieNode, TrieError> {
+    match item.first() {
+        Some(&code) if code >= 0xc0 => TrieNode::decode(item),
+        _ => match decode_string(item)?.as_slice() {
+            [] => Ok(TrieNode::Empty),
+            hash if hash.len() == 32 => Ok(TrieNode::Hash(H256::from_slice(hash))),
+            _ => Err(TrieError::InvalidNode),
+        },
+    }
+}
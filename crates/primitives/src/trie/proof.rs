@@ -0,0 +1,269 @@
+//! The verification of the proofs of keys and of ranges of keys.
+use super::{to_nibbles, Trie, TrieError, TrieNode};
+use crate::{Bytes, H256};
+use std::cmp::Ordering;
+
+/// Verifies the proof of a key against the root.
+///
+/// Returns the value of the key, or `None` if the proof proves that the key is not in the trie.
+pub fn verify_proof(root: H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Vec<u8>>, TrieError> {
+    let trie = Trie::from_proof(root, proof)?;
+    match trie.get(key) {
+        Ok(value) => Ok(value.map(<[u8]>::to_vec)),
+        Err(TrieError::MissingNode(_)) => Err(TrieError::InvalidProof),
+        Err(err) => Err(err),
+    }
+}
+
+/// Verifies that the leaves are all the keys of the trie of the root from `origin` up to the last
+/// leaf, given the proofs of `origin` and of the last leaf.
+///
+/// This is how the responses to the range requests of the `snap` protocol are proven. Without a
+/// proof, the leaves have to be the whole trie. No leaves with a proof prove that the trie has no
+/// keys from `origin` on.
+///
+/// Returns whether the trie has keys after the last leaf.
+pub fn verify_range_proof(
+    root: H256,
+    origin: H256,
+    leaves: &[(H256, Vec<u8>)],
+    proof: &[Bytes],
+) -> Result<bool, TrieError> {
+    let sorted = leaves.windows(2).all(|pair| pair[0].0 < pair[1].0);
+    let in_range = leaves.first().map_or(true, |(first, _)| *first >= origin);
+    if !sorted || !in_range || leaves.iter().any(|(_, value)| value.is_empty()) {
+        return Err(TrieError::InvalidProof)
+    }
+
+    if proof.is_empty() {
+        let trie = Trie::from_entries(leaves.iter().map(|(key, value)| (key, value.clone())));
+        return if trie.root_hash() == root { Ok(false) } else { Err(TrieError::InvalidProof) }
+    }
+
+    let mut trie = Trie::from_proof(root, proof)?;
+    if matches!(trie.root, TrieNode::Hash(_)) {
+        return Err(TrieError::InvalidProof)
+    }
+    let last = leaves.last().map(|(last, _)| to_nibbles(last.as_bytes()));
+    let has_more = match &last {
+        Some(last) => has_keys_after(&trie.root, &mut Vec::new(), last),
+        None => false,
+    };
+
+    // the proof has to prove all keys outside of the range, the keys inside of it are replaced
+    // with the leaves
+    let left = to_nibbles(origin.as_bytes());
+    remove_range(&mut trie.root, &mut Vec::new(), &left, last.as_deref())
+        .map_err(|_| TrieError::InvalidProof)?;
+    for (key, value) in leaves {
+        trie.insert(key.as_bytes(), value.clone()).map_err(|_| TrieError::InvalidProof)?;
+    }
+
+    if trie.root_hash() == root {
+        Ok(has_more)
+    } else {
+        Err(TrieError::InvalidProof)
+    }
+}
+
+/// The position of the keys of a subtrie relative to a range of keys.
+enum Position {
+    /// All keys are in the range.
+    Inside,
+    /// No key is in the range.
+    Outside,
+    /// Some keys may be in the range.
+    Straddling,
+}
+
+/// Returns the position of the keys with the given nibbles as prefix relative to the range from
+/// `left` up to and including `right`, without an upper bound if `right` is `None`.
+fn position(prefix: &[u8], left: &[u8], right: Option<&[u8]>) -> Position {
+    let cmp = |bound: &[u8]| prefix.cmp(&bound[..prefix.len().min(bound.len())]);
+    let rest_is = |bound: &[u8], nibble: u8| {
+        bound.get(prefix.len()..).unwrap_or_default().iter().all(|rest| *rest == nibble)
+    };
+
+    let min_in_range = match cmp(left) {
+        Ordering::Less => return Position::Outside,
+        Ordering::Greater => true,
+        Ordering::Equal => rest_is(left, 0),
+    };
+    let max_in_range = match right.map(cmp) {
+        None | Some(Ordering::Less) => true,
+        Some(Ordering::Greater) => return Position::Outside,
+        Some(Ordering::Equal) => rest_is(right.unwrap(), 0x0f),
+    };
+
+    if min_in_range && max_in_range {
+        Position::Inside
+    } else {
+        Position::Straddling
+    }
+}
+
+/// Removes all keys of the range from the subtrie at the given nibbles.
+///
+/// Fails if a subtrie that straddles a bound of the range is only known by its hash.
+fn remove_range(
+    node: &mut TrieNode,
+    prefix: &mut Vec<u8>,
+    left: &[u8],
+    right: Option<&[u8]>,
+) -> Result<(), TrieError> {
+    let len = prefix.len();
+    if let TrieNode::Leaf { path, .. } | TrieNode::Extension { path, .. } = node {
+        prefix.extend_from_slice(path);
+    }
+
+    let result = match position(prefix, left, right) {
+        Position::Outside => Ok(()),
+        Position::Inside => {
+            *node = TrieNode::Empty;
+            Ok(())
+        }
+        Position::Straddling => match node {
+            TrieNode::Hash(hash) => Err(TrieError::MissingNode(*hash)),
+            TrieNode::Extension { child, .. } => remove_range(child, prefix, left, right),
+            TrieNode::Branch { children, .. } => {
+                children.iter_mut().enumerate().try_for_each(|(nibble, child)| {
+                    prefix.push(nibble as u8);
+                    let result = remove_range(child, prefix, left, right);
+                    prefix.pop();
+                    result
+                })
+            }
+            TrieNode::Empty | TrieNode::Leaf { .. } => Ok(()),
+        },
+    };
+
+    prefix.truncate(len);
+    result
+}
+
+/// Returns whether the subtrie at the given nibbles may have keys after the bound.
+fn has_keys_after(node: &TrieNode, prefix: &mut Vec<u8>, bound: &[u8]) -> bool {
+    let len = prefix.len();
+    if let TrieNode::Leaf { path, .. } | TrieNode::Extension { path, .. } = node {
+        prefix.extend_from_slice(path);
+    }
+
+    let has_keys = match prefix.as_slice().cmp(&bound[..prefix.len().min(bound.len())]) {
+        Ordering::Less => false,
+        Ordering::Greater => !matches!(node, TrieNode::Empty),
+        Ordering::Equal => match node {
+            TrieNode::Extension { child, .. } => has_keys_after(child, prefix, bound),
+            TrieNode::Branch { children, .. } => {
+                children.iter().enumerate().any(|(nibble, child)| {
+                    prefix.push(nibble as u8);
+                    let has_keys = has_keys_after(child, prefix, bound);
+                    prefix.pop();
+                    has_keys
+                })
+            }
+            // the subtrie is unknown
+            TrieNode::Hash(_) => true,
+            TrieNode::Empty | TrieNode::Leaf { .. } => false,
+        },
+    };
+
+    prefix.truncate(len);
+    has_keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keccak256;
+
+    fn leaves(len: u64) -> Vec<(H256, Vec<u8>)> {
+        let mut leaves = (0..len)
+            .map(|i| (keccak256(H256::from_low_u64_be(i)), vec![i as u8 + 1; 40]))
+            .collect::<Vec<_>>();
+        leaves.sort();
+        leaves
+    }
+
+    /// Returns the proof of the range from the proofs of its first and last key.
+    fn range_proof(trie: &Trie, origin: H256, last: Option<H256>) -> Vec<Bytes> {
+        let mut proof = trie.proof(origin.as_bytes());
+        if let Some(last) = last {
+            proof.extend(trie.proof(last.as_bytes()));
+        }
+        proof.sort();
+        proof.dedup();
+        proof
+    }
+
+    #[test]
+    fn verify_key_proofs() {
+        let leaves = leaves(40);
+        let trie = Trie::from_entries(leaves.clone());
+        let root = trie.root_hash();
+
+        let (key, value) = &leaves[3];
+        assert_eq!(verify_proof(root, key.as_bytes(), &trie.proof(key.as_bytes())), Ok(Some(value.clone())));
+
+        let absent = H256::zero();
+        assert_eq!(verify_proof(root, absent.as_bytes(), &trie.proof(absent.as_bytes())), Ok(None));
+
+        // a proof of another key doesn't prove the key
+        let other = leaves.iter().find(|(other, _)| other[0] >> 4 != key[0] >> 4).unwrap();
+        assert_eq!(
+            verify_proof(root, key.as_bytes(), &trie.proof(other.0.as_bytes())),
+            Err(TrieError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn verify_ranges() {
+        let leaves = leaves(100);
+        let trie = Trie::from_entries(leaves.clone());
+        let root = trie.root_hash();
+
+        // the whole trie without a proof
+        assert_eq!(verify_range_proof(root, H256::zero(), &leaves, &[]), Ok(false));
+
+        // ranges in the middle of the trie, starting at an existing and at an absent key
+        for (start, end) in [(0, 10), (10, 50), (37, 38), (90, 100)] {
+            let range = &leaves[start..end];
+            let origin = range[0].0;
+            let last = range.last().unwrap().0;
+            let proof = range_proof(&trie, origin, Some(last));
+            assert_eq!(verify_range_proof(root, origin, range, &proof), Ok(end < 100));
+
+            let origin = if start == 0 { H256::zero() } else { leaves[start - 1].0 };
+            let origin = H256::from_uint(&(origin.into_uint() + 1));
+            let proof = range_proof(&trie, origin, Some(last));
+            assert_eq!(verify_range_proof(root, origin, range, &proof), Ok(end < 100));
+
+            // a missing leaf
+            if range.len() > 2 {
+                let mut missing = range.to_vec();
+                missing.remove(1);
+                assert_eq!(
+                    verify_range_proof(root, origin, &missing, &proof),
+                    Err(TrieError::InvalidProof)
+                );
+            }
+
+            // a modified leaf
+            let mut modified = range.to_vec();
+            modified[0].1 = vec![0xff; 40];
+            assert_eq!(
+                verify_range_proof(root, origin, &modified, &proof),
+                Err(TrieError::InvalidProof)
+            );
+        }
+
+        // no keys after the origin
+        let origin = H256::from_uint(&(leaves[99].0.into_uint() + 1));
+        let proof = range_proof(&trie, origin, None);
+        assert_eq!(verify_range_proof(root, origin, &[], &proof), Ok(false));
+
+        // keys after the origin that were left out
+        let origin = leaves[98].0;
+        let proof = range_proof(&trie, origin, None);
+        assert_eq!(verify_range_proof(root, origin, &[], &proof), Err(TrieError::InvalidProof));
+    }
+}
@@ -0,0 +1,103 @@
+//! Round-trip encoding fuzzing for the `reth-primitives` crate.
+//!
+//! Every target asserts the RLP and the `Compact` round trip of a type. Without `cargo test-fuzz`
+//! the targets only run with the default value of the type, the random values are covered by the
+//! property tests of `reth_primitives::strategies`.
+use reth_codecs::Compact;
+use reth_rlp::{Decodable, Encodable};
+use std::fmt::Debug;
+
+/// Asserts that the value survives an RLP round trip and that its encoded length matches
+/// [`Encodable::length`].
+fn rlp_roundtrip<T>(thing: &T)
+where
+    T: Encodable + Decodable + Debug + PartialEq,
+{
+    let mut encoded = Vec::new();
+    thing.encode(&mut encoded);
+    assert_eq!(encoded.len(), thing.length());
+    let decoded = T::decode(&mut &encoded[..]).unwrap();
+    assert_eq!(thing, &decoded, "expected: {thing:?}, got: {decoded:?}");
+}
+
+/// Asserts that the value survives a `Compact` round trip.
+fn compact_roundtrip<T>(thing: &T)
+where
+    T: Compact + Clone + Debug + PartialEq,
+{
+    let mut buf = Vec::new();
+    let len = thing.clone().to_compact(&mut buf);
+    let (decoded, rest) = T::from_compact(&buf, len);
+    assert_eq!(thing, &decoded, "expected: {thing:?}, got: {decoded:?}");
+    assert!(rest.is_empty());
+}
+
+#[allow(non_snake_case)]
+mod fuzz_roundtrip {
+    use crate::{compact_roundtrip, rlp_roundtrip};
+    use reth_primitives::{
+        Header, Log, Receipt, Signature, Transaction, TransactionSigned, TxType,
+    };
+    use test_fuzz::test_fuzz;
+
+    /// Fuzzes the round trips of headers.
+    #[test_fuzz]
+    fn fuzz_Header(header: Header) {
+        rlp_roundtrip(&header);
+        compact_roundtrip(&header);
+    }
+
+    /// Fuzzes the round trips of logs.
+    #[test_fuzz]
+    fn fuzz_Log(log: Log) {
+        rlp_roundtrip(&log);
+        compact_roundtrip(&log);
+    }
+
+    /// Fuzzes the round trips of receipts.
+    ///
+    /// Unknown transaction types only have an RLP encoding if their type byte is a valid EIP-2718
+    /// type that is not known, and only decode leniently.
+    #[test_fuzz]
+    fn fuzz_Receipt(receipt: Receipt) {
+        compact_roundtrip(&receipt);
+        match receipt.tx_type {
+            TxType::Other(ty) if (0x03..=0x7f).contains(&ty) => {
+                let mut encoded = Vec::new();
+                receipt.encode(&mut encoded);
+                assert_eq!(Receipt::decode_lenient(&mut &encoded[..]).unwrap(), receipt);
+            }
+            TxType::Other(_) => {}
+            _ => rlp_roundtrip(&receipt),
+        }
+    }
+
+    /// Fuzzes the round trips of signed transactions.
+    ///
+    /// The transaction is signed with the fuzzed signature, so its hash matches its encoding.
+    #[test_fuzz]
+    fn fuzz_TransactionSigned(transaction: Transaction, signature: Signature) {
+        // the `v` value of legacy transactions has to fit the chain id
+        if let Transaction::Legacy(tx) = &transaction {
+            if tx.chain_id.map_or(false, |chain_id| chain_id > u32::MAX as u64) {
+                return
+            }
+        }
+        let signed = TransactionSigned::from_transaction_and_signature(transaction, signature);
+        rlp_roundtrip(&signed);
+        compact_roundtrip(&signed);
+        let decoded = TransactionSigned::decode_enveloped(&signed.envelope_encoded()).unwrap();
+        assert_eq!(decoded, signed);
+    }
+
+    #[test]
+    fn roundtrip_defaults() {
+        fuzz_Header(Header::default());
+        fuzz_Log(Log::default());
+        fuzz_Receipt(Receipt::default());
+        fuzz_Receipt(Receipt { tx_type: TxType::EIP1559, ..Default::default() });
+        fuzz_Receipt(Receipt { tx_type: TxType::Other(0x7e), ..Default::default() });
+        fuzz_TransactionSigned(Transaction::default(), Signature::default());
+        fuzz_TransactionSigned(Transaction::Eip1559(Default::default()), Signature::default());
+    }
+}
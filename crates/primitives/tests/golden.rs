@@ -0,0 +1,111 @@
+//! Golden encoding vectors for headers, transactions and receipts.
+//!
+//! Every fixture in `tests/fixtures/golden` stores the canonical RLP encoding of a type for a
+//! specific fork. The harness asserts that decoding and re-encoding is byte-exact, that
+//! [`Encodable::length`] matches the actual encoded length and that the value survives a `Compact`
+//! round trip.
+//!
+//! Fixtures may optionally contain a `compact` field with the expected `Compact` encoding. Running
+//! the tests with `RETH_UPDATE_GOLDEN=1` (re)writes these fields from the current implementation.
+//! The `Compact` encodings of the values of every table are pinned by the golden tests of
+//! `reth-db`.
+
+use reth_codecs::Compact;
+use reth_primitives::{Header, Receipt, TransactionSigned, H256};
+use reth_rlp::{Decodable, Encodable};
+use serde_json::Value;
+use std::{fmt::Debug, fs, path::PathBuf};
+
+/// Environment variable that enables rewriting the `compact` fields of all fixtures.
+const UPDATE_ENV: &str = "RETH_UPDATE_GOLDEN";
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden").join(name)
+}
+
+fn decode_hex(value: &str) -> Vec<u8> {
+    hex::decode(value.trim_start_matches("0x")).expect("valid hex")
+}
+
+/// Runs the round trip checks for all vectors of the fixture file.
+///
+/// The `check` closure receives the vector and the decoded value for type specific assertions.
+fn run_golden<T, F>(name: &str, check: F)
+where
+    T: Encodable + Decodable + Compact + Clone + PartialEq + Debug,
+    F: Fn(&Value, &T),
+{
+    let path = fixture_path(name);
+    let mut vectors: Vec<Value> =
+        serde_json::from_str(&fs::read_to_string(&path).expect("fixture exists"))
+            .expect("valid fixture");
+    let update = std::env::var(UPDATE_ENV).is_ok();
+
+    for vector in vectors.iter_mut() {
+        let label = format!("{name}: {} ({})", vector["name"], vector["fork"]);
+        let rlp = decode_hex(vector["rlp"].as_str().expect("rlp field"));
+
+        // decoding must consume the entire encoding
+        let buf = &mut &rlp[..];
+        let decoded = T::decode(buf).unwrap_or_else(|err| panic!("{label}: {err:?}"));
+        assert!(buf.is_empty(), "{label}: {} bytes remaining after decoding", buf.len());
+
+        // re-encoding must be byte-exact
+        let mut encoded = Vec::new();
+        decoded.encode(&mut encoded);
+        assert_eq!(hex::encode(&encoded), hex::encode(&rlp), "{label}: rlp mismatch");
+        assert_eq!(decoded.length(), rlp.len(), "{label}: length mismatch");
+
+        // compact round trip
+        let mut compact = Vec::new();
+        let len = decoded.clone().to_compact(&mut compact);
+        let (from_compact, _) = T::from_compact(&compact, len);
+        assert_eq!(from_compact, decoded, "{label}: compact round trip mismatch");
+
+        if update {
+            vector["compact"] = Value::String(format!("0x{}", hex::encode(&compact)));
+        } else if let Some(expected) = vector.get("compact").and_then(Value::as_str) {
+            assert_eq!(
+                hex::encode(&compact),
+                hex::encode(decode_hex(expected)),
+                "{label}: compact mismatch"
+            );
+        }
+
+        check(vector, &decoded);
+    }
+
+    if update {
+        let mut out = serde_json::to_string_pretty(&vectors).expect("serializable");
+        out.push('\n');
+        fs::write(&path, out).expect("writable fixture");
+    }
+}
+
+fn expected_hash(vector: &Value) -> Option<H256> {
+    vector.get("hash").and_then(Value::as_str).map(|hash| H256::from_slice(&decode_hex(hash)))
+}
+
+#[test]
+fn golden_headers() {
+    run_golden::<Header, _>("headers.json", |vector, header| {
+        if let Some(hash) = expected_hash(vector) {
+            assert_eq!(header.hash_slow(), hash, "{}: hash mismatch", vector["name"]);
+        }
+    });
+}
+
+#[test]
+fn golden_transactions() {
+    run_golden::<TransactionSigned, _>("transactions.json", |vector, tx| {
+        assert_eq!(tx.hash(), tx.recalculate_hash(), "{}: hash inconsistent", vector["name"]);
+        if let Some(hash) = expected_hash(vector) {
+            assert_eq!(tx.hash(), hash, "{}: hash mismatch", vector["name"]);
+        }
+    });
+}
+
+#[test]
+fn golden_receipts() {
+    run_golden::<Receipt, _>("receipts.json", |_, _| {});
+}
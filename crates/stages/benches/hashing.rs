@@ -0,0 +1,80 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use reth_db::{
+    database::Database,
+    mdbx::{test_utils::create_test_db, Env, EnvKind, WriteMap},
+    tables,
+    transaction::DbTxMut,
+};
+use reth_interfaces::test_utils::generators::stress::{
+    stress_block_range, StressConfig, StressProfile,
+};
+use reth_primitives::StorageEntry;
+use reth_stages::{
+    stages::{hashing_account::AccountHashingStage, hashing_storage::StorageHashingStage},
+    ExecInput, Stage, StageId, Transaction,
+};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// The id of the stage that ran before the hashing stages.
+const PREV_STAGE_ID: StageId = StageId("Execution");
+
+/// Creates a database with the plain state of a storage-heavy stress fixture.
+fn storage_heavy_db(prefilled_slots: u64, senders: usize) -> Arc<Env<WriteMap>> {
+    let mut config =
+        StressConfig::new(StressProfile::StorageHeavy { prefilled_slots, slots_per_tx: 0 });
+    config.senders = senders;
+    let fixture = stress_block_range(&config);
+
+    let db = create_test_db::<WriteMap>(EnvKind::RW);
+    db.update(|tx| {
+        for (address, account) in &fixture.prestate {
+            tx.put::<tables::PlainAccountState>(*address, account.account)?;
+            for (key, value) in &account.storage {
+                tx.put::<tables::PlainStorageState>(
+                    *address,
+                    StorageEntry { key: *key, value: *value },
+                )?;
+            }
+        }
+        Ok::<_, reth_db::Error>(())
+    })
+    .and_then(|res| res)
+    .expect("Failed to write plain state");
+    db
+}
+
+/// Runs the stage from scratch until it is done and discards its changes.
+fn run_stage<S: Stage<Env<WriteMap>>>(runtime: &Runtime, db: &Env<WriteMap>, stage: &mut S) {
+    runtime.block_on(async {
+        let mut tx = Transaction::new(db).expect("Failed to open transaction");
+        let mut stage_progress = None;
+        loop {
+            let input = ExecInput { previous_stage: Some((PREV_STAGE_ID, 1)), stage_progress };
+            let output = stage.execute(&mut tx, input).await.expect("Failed to execute stage");
+            if output.done {
+                break
+            }
+            stage_progress = Some(output.stage_progress);
+        }
+    })
+}
+
+pub fn hashing(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut group = c.benchmark_group("Hashing");
+    group.sample_size(10);
+
+    let db = storage_heavy_db(100_000, 10_000);
+    group.bench_function("storage_heavy/accounts", |b| {
+        b.iter(|| run_stage(&runtime, &db, &mut AccountHashingStage::default()))
+    });
+    group.bench_function("storage_heavy/storage", |b| {
+        b.iter(|| run_stage(&runtime, &db, &mut StorageHashingStage::default()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, hashing);
+criterion_main!(benches);
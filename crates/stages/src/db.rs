@@ -155,6 +155,26 @@ where
         self.get_block_transition(key)
     }
 
+    /// Query [tables::Config] for the last block whose history was pruned.
+    pub(crate) fn get_history_pruned_to(&self) -> Result<Option<BlockNumber>, StageError> {
+        self.get_config_u64(tables::HISTORY_PRUNED_TO_KEY)
+    }
+
+    /// Query [tables::Config] for the number of blocks that were moved to the freezer.
+    pub(crate) fn get_frozen_blocks(&self) -> Result<u64, StageError> {
+        Ok(self.get_config_u64(tables::FROZEN_BLOCKS_KEY)?.unwrap_or_default())
+    }
+
+    /// Query [tables::Config] for a big endian `u64`.
+    fn get_config_u64(&self, key: &[u8]) -> Result<Option<u64>, StageError> {
+        let value = self.get::<tables::Config>(key.to_vec())?;
+        value
+            .map(|value| {
+                value.try_into().map(u64::from_be_bytes).map_err(|_| Error::DecodeError.into())
+            })
+            .transpose()
+    }
+
     /// Get the next start transaction id and transition for the `block` by looking at the previous
     /// block. Returns Zero/Zero for Genesis.
     pub(crate) fn get_next_block_ids(
@@ -39,6 +39,30 @@ pub enum StageError {
     /// Invalid checkpoint passed to the stage
     #[error("Invalid stage progress: {0}")]
     StageProgress(u64),
+    /// The unwind needs the changesets of blocks that were pruned.
+    #[error("Can not unwind to block #{unwind_to}, the history before block #{oldest} is pruned.")]
+    HistoryPruned {
+        /// The block to unwind to.
+        unwind_to: BlockNumber,
+        /// The oldest block that can be unwound to.
+        oldest: BlockNumber,
+    },
+    /// The reorg unwinds more blocks than the execution stage allows.
+    #[error("Can not unwind {depth} blocks for a reorg, at most {max_depth} blocks are unwound.")]
+    ReorgTooDeep {
+        /// The number of blocks the reorg unwinds.
+        depth: u64,
+        /// The maximum number of blocks a reorg may unwind.
+        max_depth: u64,
+    },
+    /// The unwind needs the blocks that were moved to the freezer.
+    #[error("Can not unwind to block #{unwind_to}, the blocks before #{frozen} are frozen.")]
+    BlocksFrozen {
+        /// The block to unwind to.
+        unwind_to: BlockNumber,
+        /// The number of frozen blocks.
+        frozen: u64,
+    },
     /// The stage encountered a recoverable error.
     ///
     /// These types of errors are caught by the [Pipeline] and trigger a restart of the stage.
@@ -59,6 +83,9 @@ impl StageError {
             StageError::Database(_) |
                 StageError::DatabaseIntegrity(_) |
                 StageError::StageProgress(_) |
+                StageError::HistoryPruned { .. } |
+                StageError::ReorgTooDeep { .. } |
+                StageError::BlocksFrozen { .. } |
                 StageError::Fatal(_)
         )
     }
@@ -24,6 +24,8 @@ mod util;
 #[cfg(test)]
 mod test_utils;
 
+pub mod sequencer;
+
 /// Implementations of stages.
 pub mod stages;
 
@@ -1,23 +1,31 @@
 use crate::{
-    db::Transaction, error::*, util::opt::MaybeSender, ExecInput, ExecOutput, Stage, StageError,
-    StageId, UnwindInput,
+    db::Transaction,
+    error::*,
+    util::opt::{self, MaybeSender},
+    ExecInput, ExecOutput, Stage, StageError, StageId, UnwindInput,
 };
 use reth_db::{database::Database, transaction::DbTx};
+use reth_interfaces::db::Error as DbError;
 use reth_primitives::BlockNumber;
+use reth_provider::{CanonicalBlock, ChainNotification, ChainNotifications, ProviderImplRef};
+use reth_tasks::shutdown::Shutdown;
 use std::{
     fmt::{Debug, Formatter},
     ops::Deref,
     sync::Arc,
+    time::Instant,
 };
-use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::{Sender, UnboundedReceiver};
 use tracing::*;
 
 mod ctrl;
 mod event;
+mod progress;
 mod state;
 
 use ctrl::*;
 pub use event::*;
+use progress::StageProgress;
 use state::*;
 
 #[cfg_attr(doc, aquamarine::aquamarine)]
@@ -69,23 +77,44 @@ use state::*;
 ///
 /// In case of a validation error (as determined by the consensus engine) in one of the stages, the
 /// pipeline will unwind the stages in reverse order of execution. It is also possible to
-/// request an unwind manually (see [Pipeline::unwind]).
+/// request an unwind manually (see [Pipeline::unwind]), or from another task while the pipeline is
+/// running (see [Pipeline::set_unwind_requests]).
+///
+/// # Shutdown
+///
+/// Once the shutdown is requested (see [Pipeline::set_shutdown]), the pipeline stops after the
+/// progress of the running stage was committed.
 // ANCHOR: struct-Pipeline
 pub struct Pipeline<DB: Database> {
     stages: Vec<QueuedStage<DB>>,
     max_block: Option<BlockNumber>,
+    halt_block: Option<BlockNumber>,
     events_sender: MaybeSender<PipelineEvent>,
+    unwind_requests: Option<UnboundedReceiver<BlockNumber>>,
+    chain_notifications: Option<ChainNotifications>,
+    shutdown: Shutdown,
 }
 // ANCHOR_END: struct-Pipeline
 
 impl<DB: Database> Default for Pipeline<DB> {
     fn default() -> Self {
-        Self { stages: Vec::new(), max_block: None, events_sender: MaybeSender::new(None) }
+        Self {
+            stages: Vec::new(),
+            max_block: None,
+            halt_block: None,
+            events_sender: MaybeSender::new(None),
+            unwind_requests: None,
+            chain_notifications: None,
+            shutdown: Shutdown::default(),
+        }
     }
 }
 impl<DB: Database> Debug for Pipeline<DB> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Pipeline").field("max_block", &self.max_block).finish()
+        f.debug_struct("Pipeline")
+            .field("max_block", &self.max_block)
+            .field("halt_block", &self.halt_block)
+            .finish()
     }
 }
 
@@ -105,43 +134,101 @@ impl<DB: Database> Pipeline<DB> {
     where
         S: Stage<DB> + 'static,
     {
-        self.stages.push(QueuedStage { stage: Box::new(stage) });
+        let progress = StageProgress::new(stage.id());
+        self.stages.push(QueuedStage { stage: Box::new(stage), progress });
         self
     }
 
     /// Set the target block.
     ///
-    /// Once this block is reached, syncing will stop.
+    /// Once this block is reached, syncing will stop. The progress of each stage passed on to the
+    /// next stage is capped at the target block, so all stages but the first one stop exactly at
+    /// this block and leave the database in that state.
     pub fn set_max_block(mut self, block: Option<BlockNumber>) -> Self {
         self.max_block = block;
         self
     }
 
+    /// Set the block before which a stage halts the sync, see
+    /// [ExecutionStage::with_halt_before](crate::stages::execution::ExecutionStage::with_halt_before).
+    ///
+    /// The stages sync up to this block as if it was the [max block](Pipeline::set_max_block),
+    /// which it takes precedence over. A stage that is done at the parent of the block although
+    /// the previous stage reached the block halted, and the pipeline stops once the pass is
+    /// finished.
+    pub fn set_halt_block(mut self, block: Option<BlockNumber>) -> Self {
+        self.halt_block = block;
+        self
+    }
+
     /// Set a channel the pipeline will transmit events over (see [PipelineEvent]).
     pub fn set_channel(mut self, sender: Sender<PipelineEvent>) -> Self {
         self.events_sender.set(Some(sender));
         self
     }
 
+    /// Set a channel over which other tasks request to unwind the pipeline to a block.
+    ///
+    /// This is used when the canonical chain changes, e.g. the consensus client selects a side
+    /// chain that forks off below the local tip. Pending requests are handled before a stage is
+    /// executed, if several are pending the pipeline unwinds to the lowest block.
+    pub fn set_unwind_requests(mut self, requests: UnboundedReceiver<BlockNumber>) -> Self {
+        self.unwind_requests = Some(requests);
+        self
+    }
+
+    /// Set a channel the pipeline will notify about changes of the canonical chain (see
+    /// [ChainNotification]).
+    ///
+    /// A block is canonical once all stages have processed it. Blocks are reported as reverted
+    /// before the unwind that removes them is committed, along with their receipts.
+    pub fn set_chain_notifications(mut self, notifications: ChainNotifications) -> Self {
+        self.chain_notifications = Some(notifications);
+        self
+    }
+
+    /// Set the listener of the shutdown of the node.
+    ///
+    /// Once the shutdown is requested, [Pipeline::run] returns after the current iteration of the
+    /// running stage finished and its progress was committed. Stages are never interrupted in the
+    /// middle of an iteration, so a stage that waits, e.g. for a new tip, delays the shutdown.
+    pub fn set_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
     /// Run the pipeline in an infinite loop. Will terminate early if the user has specified
     /// a `max_block` in the pipeline.
     pub async fn run(&mut self, db: Arc<DB>) -> Result<(), PipelineError> {
         loop {
             let mut state = PipelineState {
                 events_sender: self.events_sender.clone(),
-                max_block: self.max_block,
+                max_block: self.halt_block.or(self.max_block),
+                halt_block: self.halt_block,
+                halted: false,
                 maximum_progress: None,
                 minimum_progress: None,
             };
+            let tx = db.tx()?;
+            let synced = self.synced_block(&tx)?;
+            tx.commit()?;
             let next_action = self.run_loop(&mut state, db.as_ref()).await?;
+            if matches!(next_action, ControlFlow::Shutdown) {
+                info!(target: "sync::pipeline", "Pipeline stopped by shutdown");
+                return Ok(())
+            }
+            if matches!(next_action, ControlFlow::Continue) {
+                self.notify_committed(db.as_ref(), synced)?;
+            }
 
             // Terminate the loop early if it's reached the maximum user
             // configured block.
             if matches!(next_action, ControlFlow::Continue) &&
-                state
-                    .minimum_progress
-                    .zip(self.max_block)
-                    .map_or(false, |(progress, target)| progress >= target)
+                (state.halted ||
+                    state
+                        .minimum_progress
+                        .zip(state.max_block)
+                        .map_or(false, |(progress, target)| progress >= target))
             {
                 return Ok(())
             }
@@ -168,7 +255,7 @@ impl<DB: Database> Pipeline<DB> {
                 "Executing stage"
             );
             let next = queued_stage
-                .execute(state, previous_stage, db)
+                .execute(state, previous_stage, self.unwind_requests.as_mut(), &self.shutdown, db)
                 .instrument(info_span!("execute", stage = %stage_id))
                 .await?;
 
@@ -184,6 +271,7 @@ impl<DB: Database> Pipeline<DB> {
 
                     return Ok(ControlFlow::Unwind { target, bad_block })
                 }
+                ControlFlow::Shutdown => return Ok(ControlFlow::Shutdown),
             }
         }
 
@@ -199,11 +287,12 @@ impl<DB: Database> Pipeline<DB> {
         to: BlockNumber,
         bad_block: Option<BlockNumber>,
     ) -> Result<(), PipelineError> {
+        let mut tx = Transaction::new(db)?;
+        let reverted = self.reverted_blocks(&tx, to)?;
+
         // Unwind stages in reverse order of execution
         let unwind_pipeline = self.stages.iter_mut().rev();
 
-        let mut tx = Transaction::new(db)?;
-
         for QueuedStage { stage, .. } in unwind_pipeline {
             let stage_id = stage.id();
             let span = info_span!("Unwinding", stage = %stage_id);
@@ -213,7 +302,7 @@ impl<DB: Database> Pipeline<DB> {
             if stage_progress < to {
                 debug!(from = %stage_progress, %to, "Unwind point too far for stage");
                 self.events_sender.send(PipelineEvent::Skipped { stage_id }).await?;
-                return Ok(())
+                continue
             }
 
             debug!(from = %stage_progress, %to, ?bad_block, "Starting unwind");
@@ -240,14 +329,62 @@ impl<DB: Database> Pipeline<DB> {
         }
 
         tx.commit()?;
+        if let (Some(notifications), Some(blocks)) = (&self.chain_notifications, reverted) {
+            // there are no receivers if nobody follows the chain
+            let _ = notifications.send(ChainNotification::Reverted { blocks: Arc::new(blocks) });
+        }
         Ok(())
     }
+
+    /// Returns the highest block processed by all stages.
+    fn synced_block<'db>(&self, tx: &impl DbTx<'db>) -> Result<BlockNumber, DbError> {
+        let mut synced = None;
+        for QueuedStage { stage, .. } in &self.stages {
+            synced = opt::min(synced, stage.id().get_progress(tx)?.unwrap_or_default());
+        }
+        Ok(synced.unwrap_or_default())
+    }
+
+    /// Notifies about the blocks that were processed by all stages since `synced`.
+    fn notify_committed(&self, db: &DB, synced: BlockNumber) -> Result<(), PipelineError> {
+        let Some(notifications) = &self.chain_notifications else { return Ok(()) };
+        let tx = db.tx()?;
+        let new_synced = self.synced_block(&tx)?;
+        tx.commit()?;
+        if new_synced > synced {
+            let _ =
+                notifications.send(ChainNotification::Committed { range: synced + 1..=new_synced });
+        }
+        Ok(())
+    }
+
+    /// Returns the canonical blocks above `to` that are removed by an unwind to `to`, or `None`
+    /// if nobody is notified about it.
+    fn reverted_blocks(
+        &self,
+        tx: &Transaction<'_, DB>,
+        to: BlockNumber,
+    ) -> Result<Option<Vec<CanonicalBlock>>, PipelineError> {
+        if self.chain_notifications.is_none() {
+            return Ok(None)
+        }
+        let synced = self.synced_block(tx.deref())?;
+        if synced <= to {
+            return Ok(None)
+        }
+        let blocks = ProviderImplRef::new(tx.deref())
+            .canonical_blocks(to + 1..=synced)
+            .map_err(|err| PipelineError::Internal(Box::new(err)))?;
+        Ok(Some(blocks).filter(|blocks| !blocks.is_empty()))
+    }
 }
 
 /// A container for a queued stage.
 struct QueuedStage<DB: Database> {
     /// The actual stage to execute.
     stage: Box<dyn Stage<DB>>,
+    /// The progress metrics of the stage.
+    progress: StageProgress,
 }
 
 impl<DB: Database> QueuedStage<DB> {
@@ -256,10 +393,40 @@ impl<DB: Database> QueuedStage<DB> {
         &mut self,
         state: &mut PipelineState,
         previous_stage: Option<(StageId, BlockNumber)>,
+        mut unwind_requests: Option<&mut UnboundedReceiver<BlockNumber>>,
+        shutdown: &Shutdown,
         db: &DB,
     ) -> Result<ControlFlow, PipelineError> {
         let stage_id = self.stage.id();
+
+        // Never execute past the target block
+        let previous_stage = previous_stage.map(|(previous_stage_id, progress)| {
+            let capped = state.max_block.map_or(progress, |target| progress.min(target));
+            if capped < progress {
+                debug!(
+                    target: "sync::pipeline",
+                    stage = %stage_id,
+                    previous_stage = %previous_stage_id,
+                    progress,
+                    target = capped,
+                    "Capping previous stage progress at maximum block"
+                );
+            }
+            (previous_stage_id, capped)
+        });
+        let target = previous_stage.map(|(_, progress)| progress).or(state.max_block);
+
         loop {
+            if shutdown.is_requested() {
+                info!(target: "sync::pipeline", stage = %stage_id, "Shutdown requested");
+                return Ok(ControlFlow::Shutdown)
+            }
+
+            if let Some(target) = unwind_requests.as_deref_mut().and_then(next_unwind_request) {
+                info!(target: "sync::pipeline", stage = %stage_id, %target, "Unwind requested");
+                return Ok(ControlFlow::Unwind { target, bad_block: None })
+            }
+
             let mut tx = Transaction::new(db)?;
 
             let prev_progress = stage_id.get_progress(tx.deref())?;
@@ -275,7 +442,9 @@ impl<DB: Database> QueuedStage<DB> {
                 );
                 state.events_sender.send(PipelineEvent::Skipped { stage_id }).await?;
 
-                // We reached the maximum block, so we skip the stage
+                // We reached the maximum block, so we skip the stage. Its progress still counts,
+                // otherwise a pipeline whose stages all reached the maximum block never stops.
+                state.record_progress_outliers(prev_progress.unwrap_or_default());
                 return Ok(ControlFlow::Continue)
             }
 
@@ -284,11 +453,11 @@ impl<DB: Database> QueuedStage<DB> {
                 .send(PipelineEvent::Running { stage_id, stage_progress: prev_progress })
                 .await?;
 
-            match self
-                .stage
-                .execute(&mut tx, ExecInput { previous_stage, stage_progress: prev_progress })
-                .await
-            {
+            let started = Instant::now();
+            let input = ExecInput { previous_stage, stage_progress: prev_progress };
+            // the iteration runs to completion even if the shutdown is requested meanwhile, so its
+            // progress is committed and the shutdown is handled before the next iteration
+            match self.stage.execute(&mut tx, input).await {
                 Ok(out @ ExecOutput { stage_progress, done }) => {
                     info!(
                         target: "sync::pipeline",
@@ -305,11 +474,32 @@ impl<DB: Database> QueuedStage<DB> {
                         .await?;
 
                     // TODO: Make the commit interval configurable
+                    let commit_started = Instant::now();
                     tx.commit()?;
+                    self.progress.on_iteration(
+                        prev_progress.unwrap_or_default(),
+                        stage_progress,
+                        target,
+                        started.elapsed(),
+                        commit_started.elapsed(),
+                    );
 
                     state.record_progress_outliers(stage_progress);
 
                     if done {
+                        // The stage stopped right before the halt block although it was synced
+                        // by the previous stage.
+                        if let Some(block) = state.halt_block {
+                            if stage_progress + 1 == block && target == Some(block) {
+                                info!(
+                                    target: "sync::pipeline",
+                                    stage = %stage_id,
+                                    %block,
+                                    "Stage halted before block"
+                                );
+                                state.halted = true;
+                            }
+                        }
                         return Ok(ControlFlow::Continue)
                     }
                 }
@@ -354,16 +544,40 @@ impl<DB: Database> QueuedStage<DB> {
     }
 }
 
+/// Returns the lowest of the pending unwind requests, if any.
+fn next_unwind_request(requests: &mut UnboundedReceiver<BlockNumber>) -> Option<BlockNumber> {
+    let mut target = None;
+    while let Ok(block) = requests.try_recv() {
+        target = opt::min(target, block);
+    }
+    target
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{StageId, UnwindOutput};
+    use crate::{
+        stages::{
+            bodies::BODIES, execution::EXECUTION, log_index::LOG_INDEX,
+            sender_recovery::SENDER_RECOVERY,
+        },
+        test_utils::{PipelineSimulation, SimulationStep},
+        StageId, UnwindOutput,
+    };
     use assert_matches::assert_matches;
-    use reth_db::mdbx::{self, test_utils, Env, EnvKind, WriteMap};
-    use reth_interfaces::consensus;
+    use rand::{rngs::StdRng, SeedableRng};
+    use reth_db::{
+        mdbx::{self, test_utils, Env, EnvKind, WriteMap},
+        tables,
+        transaction::DbTxMut,
+    };
+    use reth_interfaces::{consensus, test_utils::generators::random_block_range};
+    use reth_primitives::H256;
+    use reth_provider::insert_canonical_block;
+    use std::sync::Mutex;
     use tokio::sync::mpsc::channel;
     use tokio_stream::{wrappers::ReceiverStream, StreamExt};
-    use utils::TestStage;
+    use utils::{GatedStage, TestStage};
 
     /// Runs a simple pipeline.
     #[tokio::test]
@@ -405,6 +619,87 @@ mod tests {
         );
     }
 
+    /// Checks that the pipeline stops once the shutdown is requested, after the running iteration
+    /// of a stage finished and its progress was committed.
+    #[tokio::test]
+    async fn stop_on_shutdown() {
+        let (tx, mut rx) = channel(4);
+        let db = test_utils::create_test_db(EnvKind::RW);
+        let (signal, shutdown) = reth_tasks::shutdown::signal();
+        let (gate, stage) = GatedStage::new(StageId("B"));
+
+        let pipeline_db = db.clone();
+        let pipeline = tokio::spawn(async move {
+            Pipeline::<Env<WriteMap>>::new_with_channel(tx)
+                .push(
+                    TestStage::new(StageId("A"))
+                        .add_exec(Ok(ExecOutput { stage_progress: 10, done: true })),
+                )
+                .push(stage)
+                .set_shutdown(shutdown)
+                .run(pipeline_db)
+                .await
+        });
+
+        let mut events = Vec::new();
+        while events.len() < 3 {
+            events.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(
+            events,
+            vec![
+                PipelineEvent::Running { stage_id: StageId("A"), stage_progress: None },
+                PipelineEvent::Ran {
+                    stage_id: StageId("A"),
+                    result: ExecOutput { stage_progress: 10, done: true },
+                },
+                PipelineEvent::Running { stage_id: StageId("B"), stage_progress: None },
+            ]
+        );
+
+        // the stage isn't done, but it isn't executed again after the shutdown
+        signal.fire();
+        gate.send(ExecOutput { stage_progress: 5, done: false }).unwrap();
+        assert_eq!(
+            rx.recv().await,
+            Some(PipelineEvent::Ran {
+                stage_id: StageId("B"),
+                result: ExecOutput { stage_progress: 5, done: false },
+            })
+        );
+        assert_matches!(pipeline.await.unwrap(), Ok(()));
+        let tx = db.tx().unwrap();
+        assert_eq!(StageId("A").get_progress(&tx).unwrap(), Some(10));
+        assert_eq!(StageId("B").get_progress(&tx).unwrap(), Some(5));
+    }
+
+    /// Checks that stages are never executed past the maximum block.
+    #[tokio::test]
+    async fn run_pipeline_caps_previous_stage_progress() {
+        let db = test_utils::create_test_db(EnvKind::RW);
+        let inputs = Arc::new(Mutex::new(Vec::new()));
+
+        Pipeline::<Env<WriteMap>>::new()
+            .push(
+                TestStage::new(StageId("A"))
+                    .add_exec(Ok(ExecOutput { stage_progress: 20, done: true })),
+            )
+            .push(
+                TestStage::new(StageId("B"))
+                    .add_exec(Ok(ExecOutput { stage_progress: 10, done: true }))
+                    .record_inputs(inputs.clone()),
+            )
+            .set_max_block(Some(10))
+            .run(db)
+            .await
+            .expect("Could not run pipeline");
+
+        assert_eq!(
+            *inputs.lock().unwrap(),
+            vec![ExecInput { previous_stage: Some((StageId("A"), 10)), stage_progress: None }]
+        );
+    }
+
     /// Unwinds a simple pipeline.
     #[tokio::test]
     async fn unwind_pipeline() {
@@ -545,6 +840,106 @@ mod tests {
         );
     }
 
+    /// Checks that requested unwinds are performed before the next stage is executed and that
+    /// stages behind the unwind target are skipped.
+    #[tokio::test]
+    async fn run_pipeline_with_unwind_request() {
+        let (tx, rx) = channel(2);
+        let db = test_utils::create_test_db(EnvKind::RW);
+        let (unwind_tx, unwind_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut pipeline = Pipeline::<Env<mdbx::WriteMap>>::new()
+                .push(
+                    TestStage::new(StageId("A"))
+                        .add_exec(Ok(ExecOutput { stage_progress: 10, done: true }))
+                        .add_unwind(Ok(UnwindOutput { stage_progress: 5 }))
+                        .add_exec(Ok(ExecOutput { stage_progress: 12, done: true })),
+                )
+                .push(
+                    TestStage::new(StageId("B"))
+                        .add_exec(Ok(ExecOutput { stage_progress: 4, done: true }))
+                        .add_exec(Ok(ExecOutput { stage_progress: 12, done: true })),
+                )
+                .set_max_block(Some(4));
+            pipeline.run(db.clone()).await.expect("Could not run pipeline");
+
+            // the lowest of the pending requests wins
+            unwind_tx.send(8).unwrap();
+            unwind_tx.send(5).unwrap();
+            pipeline
+                .set_max_block(Some(12))
+                .set_unwind_requests(unwind_rx)
+                .set_channel(tx)
+                .run(db)
+                .await
+                .expect("Could not run pipeline");
+        });
+
+        assert_eq!(
+            ReceiverStream::new(rx).collect::<Vec<PipelineEvent>>().await,
+            vec![
+                PipelineEvent::Skipped { stage_id: StageId("B") },
+                PipelineEvent::Unwinding {
+                    stage_id: StageId("A"),
+                    input: UnwindInput { stage_progress: 10, unwind_to: 5, bad_block: None }
+                },
+                PipelineEvent::Unwound {
+                    stage_id: StageId("A"),
+                    result: UnwindOutput { stage_progress: 5 },
+                },
+                PipelineEvent::Running { stage_id: StageId("A"), stage_progress: Some(5) },
+                PipelineEvent::Ran {
+                    stage_id: StageId("A"),
+                    result: ExecOutput { stage_progress: 12, done: true },
+                },
+                PipelineEvent::Running { stage_id: StageId("B"), stage_progress: Some(4) },
+                PipelineEvent::Ran {
+                    stage_id: StageId("B"),
+                    result: ExecOutput { stage_progress: 12, done: true },
+                },
+            ]
+        );
+    }
+
+    /// Checks that blocks processed by all stages are committed and that unwound blocks are
+    /// reverted along with their data.
+    #[tokio::test]
+    async fn notify_chain_changes() {
+        let db = test_utils::create_test_db(EnvKind::RW);
+        let blocks = random_block_range(0..3, H256::zero(), 0..1);
+        let tx = db.tx_mut().unwrap();
+        for block in &blocks {
+            insert_canonical_block(&tx, block, false).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let notifications = reth_provider::chain_notifications(8);
+        let mut rx = notifications.subscribe();
+        let mut pipeline = Pipeline::<Env<WriteMap>>::new()
+            .push(
+                TestStage::new(StageId("A"))
+                    .add_exec(Ok(ExecOutput { stage_progress: 2, done: true }))
+                    .add_unwind(Ok(UnwindOutput { stage_progress: 0 })),
+            )
+            .push(
+                TestStage::new(StageId("B"))
+                    .add_exec(Ok(ExecOutput { stage_progress: 2, done: true }))
+                    .add_unwind(Ok(UnwindOutput { stage_progress: 0 })),
+            )
+            .set_max_block(Some(2))
+            .set_chain_notifications(notifications);
+        pipeline.run(db.clone()).await.expect("Could not run pipeline");
+        assert_eq!(rx.try_recv().unwrap(), ChainNotification::Committed { range: 1..=2 });
+
+        pipeline.unwind(&db, 0, None).await.expect("Could not unwind pipeline");
+        assert_matches!(rx.try_recv().unwrap(), ChainNotification::Reverted { blocks: reverted } => {
+            let hashes = reverted.iter().map(|block| block.hash).collect::<Vec<_>>();
+            assert_eq!(hashes, vec![blocks[1].hash(), blocks[2].hash()]);
+        });
+        assert!(rx.try_recv().is_err());
+    }
+
     /// Checks that the pipeline re-runs stages on non-fatal errors and stops on fatal ones.
     #[tokio::test]
     async fn pipeline_error_handling() {
@@ -577,20 +972,92 @@ mod tests {
         );
     }
 
+    /// Restarts the pipeline of a full sync at different blocks.
+    #[tokio::test]
+    async fn simulation_restarts_at_checkpoints() {
+        let simulation = PipelineSimulation::new(1, 12);
+        simulation
+            .assert_steps(&[
+                SimulationStep::Run(1),
+                SimulationStep::Run(5),
+                SimulationStep::Run(5),
+                SimulationStep::Run(simulation.tip()),
+            ])
+            .await;
+    }
+
+    /// Unwinds a full sync, including to the genesis block, and syncs again.
+    #[tokio::test]
+    async fn simulation_unwinds() {
+        let simulation = PipelineSimulation::new(2, 12);
+        simulation
+            .assert_steps(&[
+                SimulationStep::Run(9),
+                SimulationStep::Unwind(4),
+                SimulationStep::Run(simulation.tip()),
+                SimulationStep::Unwind(0),
+                SimulationStep::Unwind(0),
+            ])
+            .await;
+    }
+
+    /// Halts a full sync before the second transaction of a block. The stages before the
+    /// execution sync the block, the ones after it stop at its parent.
+    #[tokio::test]
+    async fn simulation_halts_before_transaction() {
+        let simulation = PipelineSimulation::new(3, 12);
+        let block = simulation.block_with_txs(2).expect("a block with two transactions").number;
+        let db = simulation.run_halted(block, 1).await;
+
+        let tx = db.tx().unwrap();
+        assert_eq!(BODIES.get_progress(&tx).unwrap(), Some(block));
+        assert_eq!(SENDER_RECOVERY.get_progress(&tx).unwrap(), Some(block));
+        assert_eq!(EXECUTION.get_progress(&tx).unwrap(), Some(block - 1));
+        assert_eq!(LOG_INDEX.get_progress(&tx).unwrap(), Some(block - 1));
+
+        // only the receipt of the first transaction of the block is written
+        let hash = tx.get::<tables::CanonicalHeaders>(block).unwrap().unwrap();
+        let body = tx.get::<tables::BlockBodies>((block, hash).into()).unwrap().unwrap();
+        assert!(tx.get::<tables::Receipts>(body.start_tx_id).unwrap().is_some());
+        assert_eq!(tx.get::<tables::Receipts>(body.start_tx_id + 1), Ok(None));
+    }
+
+    /// Restarts and unwinds a full sync at random blocks.
+    #[tokio::test]
+    async fn simulation_random_steps() {
+        for seed in 0..4 {
+            let simulation = PipelineSimulation::new(seed, 10);
+            let steps = simulation.random_steps(&mut StdRng::seed_from_u64(seed), 6);
+            simulation.assert_steps(&steps).await;
+        }
+    }
+
     mod utils {
         use super::*;
         use async_trait::async_trait;
         use std::{collections::VecDeque, error::Error};
+        use tokio::sync::oneshot;
 
         pub(crate) struct TestStage {
             id: StageId,
             exec_outputs: VecDeque<Result<ExecOutput, StageError>>,
             unwind_outputs: VecDeque<Result<UnwindOutput, Box<dyn Error + Send + Sync>>>,
+            exec_inputs: Option<Arc<Mutex<Vec<ExecInput>>>>,
         }
 
         impl TestStage {
             pub(crate) fn new(id: StageId) -> Self {
-                Self { id, exec_outputs: VecDeque::new(), unwind_outputs: VecDeque::new() }
+                Self {
+                    id,
+                    exec_outputs: VecDeque::new(),
+                    unwind_outputs: VecDeque::new(),
+                    exec_inputs: None,
+                }
+            }
+
+            pub(crate) fn record_inputs(mut self, inputs: Arc<Mutex<Vec<ExecInput>>>) -> Self {
+                self.exec_inputs = Some(inputs);
+                self
             }
 
             pub(crate) fn add_exec(mut self, output: Result<ExecOutput, StageError>) -> Self {
@@ -607,8 +1074,23 @@ mod tests {
             }
         }
 
+        /// A stage whose single iteration waits until its output is sent, e.g. like a stage that
+        /// waits for a new tip.
+        pub(crate) struct GatedStage {
+            id: StageId,
+            gate: Option<oneshot::Receiver<ExecOutput>>,
+        }
+
+        impl GatedStage {
+            /// Returns the stage and the sender of the output of its iteration.
+            pub(crate) fn new(id: StageId) -> (oneshot::Sender<ExecOutput>, Self) {
+                let (gate, rx) = oneshot::channel();
+                (gate, Self { id, gate: Some(rx) })
+            }
+        }
+
         #[async_trait]
-        impl<DB: Database> Stage<DB> for TestStage {
+        impl<DB: Database> Stage<DB> for GatedStage {
             fn id(&self) -> StageId {
                 self.id
             }
@@ -618,6 +1100,33 @@ mod tests {
                 _: &mut Transaction<'_, DB>,
                 _input: ExecInput,
             ) -> Result<ExecOutput, StageError> {
+                let gate = self.gate.take().unwrap_or_else(|| panic!("{} executed again", self.id));
+                Ok(gate.await.expect("gate dropped"))
+            }
+
+            async fn unwind(
+                &mut self,
+                _: &mut Transaction<'_, DB>,
+                _input: UnwindInput,
+            ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+                panic!("Gated stage {} unwound.", self.id)
+            }
+        }
+
+        #[async_trait]
+        impl<DB: Database> Stage<DB> for TestStage {
+            fn id(&self) -> StageId {
+                self.id
+            }
+
+            async fn execute(
+                &mut self,
+                _: &mut Transaction<'_, DB>,
+                input: ExecInput,
+            ) -> Result<ExecOutput, StageError> {
+                if let Some(inputs) = &self.exec_inputs {
+                    inputs.lock().unwrap().push(input);
+                }
                 self.exec_outputs
                     .pop_front()
                     .unwrap_or_else(|| panic!("Test stage {} executed too many times.", self.id))
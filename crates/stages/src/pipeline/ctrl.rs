@@ -12,4 +12,6 @@ pub(crate) enum ControlFlow {
     },
     /// The pipeline is allowed to continue executing stages.
     Continue,
+    /// The shutdown was requested, the pipeline stops after the committed progress.
+    Shutdown,
 }
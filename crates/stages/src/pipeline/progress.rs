@@ -0,0 +1,120 @@
+use crate::{stages_metrics::StageMetrics, StageId};
+use reth_primitives::BlockNumber;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// The minimum interval between two progress log lines of a stage.
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The weight of the latest iteration in the moving average of the processing rate.
+const RATE_SMOOTHING: f64 = 0.3;
+
+/// Tracks the progress of a stage across its iterations and estimates the time until the stage
+/// reaches its target.
+pub(crate) struct StageProgress {
+    stage_id: StageId,
+    metrics: StageMetrics,
+    /// Moving average of the processed blocks per second.
+    rate: Option<f64>,
+    /// When the last progress log line was emitted.
+    last_log: Option<Instant>,
+}
+
+impl StageProgress {
+    pub(crate) fn new(stage_id: StageId) -> Self {
+        Self {
+            stage_id,
+            metrics: StageMetrics::new_with_labels([("stage", stage_id.0)]),
+            rate: None,
+            last_log: None,
+        }
+    }
+
+    /// Records an iteration of the stage that moved its checkpoint from `from` to `to`.
+    ///
+    /// The `target` is the block the stage is syncing to, if known.
+    pub(crate) fn on_iteration(
+        &mut self,
+        from: BlockNumber,
+        to: BlockNumber,
+        target: Option<BlockNumber>,
+        elapsed: Duration,
+        commit: Duration,
+    ) {
+        let processed = to.saturating_sub(from);
+        self.metrics.checkpoint.set(to as f64);
+        self.metrics.processed_blocks.increment(processed);
+        self.metrics.iteration_duration.record(elapsed.as_secs_f64());
+        self.metrics.commit_duration.record(commit.as_secs_f64());
+        if let Some(target) = target {
+            self.metrics.target.set(target as f64);
+        }
+
+        self.record_rate(processed, elapsed);
+        let eta = self.eta(to, target);
+        if let Some(eta) = eta {
+            self.metrics.eta_seconds.set(eta.as_secs_f64());
+        }
+
+        let now = Instant::now();
+        if self.last_log.map_or(true, |last| now.duration_since(last) >= LOG_INTERVAL) {
+            self.last_log = Some(now);
+            info!(
+                target: "sync::pipeline",
+                stage = %self.stage_id,
+                checkpoint = to,
+                ?target,
+                blocks_per_second = self.rate.unwrap_or_default(),
+                eta = ?eta,
+                "Stage progress"
+            );
+        }
+    }
+
+    /// Updates the moving average of the processed blocks per second.
+    fn record_rate(&mut self, processed: u64, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if processed == 0 || secs <= 0.0 {
+            return
+        }
+        let rate = processed as f64 / secs;
+        self.rate = Some(self.rate.map_or(rate, |avg| avg + RATE_SMOOTHING * (rate - avg)));
+    }
+
+    /// Returns the estimated time until the stage reaches the target, if the target is known and
+    /// the stage processed any blocks yet.
+    fn eta(&self, checkpoint: BlockNumber, target: Option<BlockNumber>) -> Option<Duration> {
+        let remaining = target?.saturating_sub(checkpoint);
+        if remaining == 0 {
+            return Some(Duration::ZERO)
+        }
+        let rate = self.rate?;
+        Some(Duration::from_secs_f64(remaining as f64 / rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_remaining_time() {
+        let mut progress = StageProgress::new(StageId("Test"));
+        assert_eq!(progress.eta(0, Some(100)), None);
+        assert_eq!(progress.eta(0, None), None);
+
+        // 10 blocks per second
+        progress.record_rate(100, Duration::from_secs(10));
+        assert_eq!(progress.eta(100, Some(1100)), Some(Duration::from_secs(100)));
+        assert_eq!(progress.eta(1100, Some(1100)), Some(Duration::ZERO));
+        assert_eq!(progress.eta(100, None), None);
+
+        // the latest iteration is weighted in: 10 + 0.3 * (20 - 10) = 13 blocks per second
+        progress.record_rate(200, Duration::from_secs(10));
+        assert_eq!(progress.eta(300, Some(1600)), Some(Duration::from_secs(100)));
+
+        // iterations without progress don't change the estimate
+        progress.record_rate(0, Duration::from_secs(10));
+        assert_eq!(progress.eta(300, Some(1600)), Some(Duration::from_secs(100)));
+    }
+}
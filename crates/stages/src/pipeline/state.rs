@@ -8,6 +8,12 @@ use reth_primitives::BlockNumber;
 pub(crate) struct PipelineState {
     pub(crate) events_sender: MaybeSender<PipelineEvent>,
     pub(crate) max_block: Option<BlockNumber>,
+    /// The block before which a stage halts the sync, see [Pipeline::set_halt_block].
+    ///
+    /// [Pipeline::set_halt_block]: crate::Pipeline::set_halt_block
+    pub(crate) halt_block: Option<BlockNumber>,
+    /// Whether a stage halted before the halt block during this pass.
+    pub(crate) halted: bool,
     /// The maximum progress achieved by any stage during the execution of the pipeline.
     pub(crate) maximum_progress: Option<BlockNumber>,
     /// The minimum progress achieved by any stage during the execution of the pipeline.
@@ -33,6 +39,8 @@ mod tests {
         let mut state = PipelineState {
             events_sender: MaybeSender::new(None),
             max_block: None,
+            halt_block: None,
+            halted: false,
             maximum_progress: None,
             minimum_progress: None,
         };
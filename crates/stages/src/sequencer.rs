@@ -0,0 +1,265 @@
+//! Block production driven by an external sequencer.
+//!
+//! A rollup node does not sync blocks from peers or receive them from a consensus client, its
+//! driver derives the ordered transactions and block attributes itself. The [SequencerDriver]
+//! consumes such a feed, executes every block on top of the canonical head and commits it as the
+//! new canonical head, bypassing the engine API and the download stages.
+
+use crate::{
+    db::Transaction,
+    stages::{
+        bodies::BODIES, execution::EXECUTION, headers::HEADERS, sender_recovery::SENDER_RECOVERY,
+    },
+    DatabaseIntegrityError, StageError,
+};
+use reth_db::{cursor::DbCursorRO, database::Database, tables, transaction::DbTx};
+use reth_executor::{
+    builder::{build_block, BlockAttributes},
+    revm_wrap::{State, SubState},
+    Config,
+};
+use reth_interfaces::consensus;
+use reth_primitives::{SealedHeader, TransactionSignedEcRecovered};
+use reth_provider::{insert_canonical_block, StateProviderImplRefLatest};
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+use tracing::*;
+
+/// A block as ordered by the sequencer.
+#[derive(Debug, Clone)]
+pub struct SequencedBlock {
+    /// The attributes of the block.
+    pub attributes: BlockAttributes,
+    /// The transactions of the block, in the order they are executed.
+    pub transactions: Vec<TransactionSignedEcRecovered>,
+}
+
+/// Produces and canonicalizes the blocks of a sequencer feed.
+///
+/// Every produced block is written like a block synced by the pipeline, and the progress of the
+/// stages that the block production replaces is moved to the new head. The remaining stages, e.g.
+/// the hashing stages, can be run by the pipeline afterwards.
+#[derive(Debug)]
+pub struct SequencerDriver<DB> {
+    db: Arc<DB>,
+    config: Config,
+    feed: mpsc::Receiver<SequencedBlock>,
+    head: watch::Sender<Option<SealedHeader>>,
+}
+
+// === impl SequencerDriver ===
+
+impl<DB: Database> SequencerDriver<DB> {
+    /// Creates a new driver that produces the blocks received over the feed.
+    pub fn new(db: Arc<DB>, config: Config, feed: mpsc::Receiver<SequencedBlock>) -> Self {
+        Self { db, config, feed, head: watch::channel(None).0 }
+    }
+
+    /// Returns a receiver of the header of the latest produced block.
+    pub fn subscribe(&self) -> watch::Receiver<Option<SealedHeader>> {
+        self.head.subscribe()
+    }
+
+    /// Produces the blocks of the feed until it is closed.
+    pub async fn run(mut self) -> Result<(), StageError> {
+        while let Some(block) = self.feed.recv().await {
+            self.produce(block)?;
+        }
+        Ok(())
+    }
+
+    /// Executes the block on top of the canonical head and commits it as the new head.
+    pub fn produce(&self, block: SequencedBlock) -> Result<SealedHeader, StageError> {
+        let SequencedBlock { attributes, transactions } = block;
+        let mut tx = Transaction::new(self.db.as_ref())?;
+
+        let (parent_number, parent_hash) = tx
+            .cursor::<tables::CanonicalHeaders>()?
+            .last()?
+            .ok_or(DatabaseIntegrityError::CanonicalHeader { number: 0 })?;
+        let parent = tx
+            .get::<tables::Headers>((parent_number, parent_hash).into())?
+            .ok_or(DatabaseIntegrityError::Header { number: parent_number, hash: parent_hash })?;
+        let parent = SealedHeader::new(parent, parent_hash);
+        let number = parent_number + 1;
+
+        if attributes.timestamp <= parent.timestamp {
+            return Err(StageError::Validation {
+                block: number,
+                error: consensus::Error::TimestampIsInPast {
+                    parent_timestamp: parent.timestamp,
+                    timestamp: attributes.timestamp,
+                },
+            })
+        }
+
+        trace!(target: "sync::sequencer", number, txs = transactions.len(), "Producing block");
+        let state = SubState::new(State::new(StateProviderImplRefLatest::new(&*tx)));
+        let (block, result) =
+            build_block(&parent, attributes, transactions, &self.config, state)
+                .map_err(|error| StageError::ExecutionError { block: number, error })?;
+
+        let has_block_reward = self.config.spec_upgrades.has_block_reward(number);
+        let transition_id = tx.get_block_transition_by_num(parent_number)? + 1;
+        insert_canonical_block(&*tx, &block, has_block_reward)
+            .map_err(|err| StageError::Fatal(Box::new(err)))?;
+        result.apply_to_db(&*tx, transition_id)?;
+
+        for stage in [HEADERS, BODIES, SENDER_RECOVERY, EXECUTION] {
+            stage.save_progress(&*tx, number)?;
+        }
+        tx.commit()?;
+
+        info!(target: "sync::sequencer", number, hash = ?block.hash(), gas_used = block.gas_used, "Produced block");
+        self.head.send_replace(Some(block.header.clone()));
+        Ok(block.header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use reth_db::{
+        mdbx::{test_utils::create_test_db, Env, EnvKind, WriteMap},
+        transaction::DbTxMut,
+    };
+    use reth_executor::SpecUpgrades;
+    use reth_primitives::{hex_literal::hex, keccak256, Account, SealedBlock, H160, U256};
+    use reth_rlp::Decodable;
+    use reth_tasks::TaskExecutor;
+    use std::ops::DerefMut;
+
+    // The genesis and block 1 of the execution stage test, the block calls a contract that
+    // stores to its storage.
+    const GENESIS: [u8; 509] = hex!("f901faf901f5a00000000000000000000000000000000000000000000000000000000000000000a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa045571b40ae66ca7480791bbb2887286e4e4c4b1b298b191c889d6959023a32eda056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421b901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000083020000808502540be400808000a00000000000000000000000000000000000000000000000000000000000000000880000000000000000c0c0");
+    const BLOCK: [u8; 613] = hex!("f90262f901f9a075c371ba45999d87f4542326910a11af515897aebce5265d3f6acd1f1161f82fa01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa098f2dcd87c8ae4083e7017a05456c14eea4b1db2032126e27b3b1563d57d7cc0a08151d548273f6683169524b66ca9fe338b9ce42bc3540046c828fd939ae23bcba03f4e5c2ec5b2170b711d97ee755c160457bb58d8daa338e835ec02ae6860bbabb901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000083020000018502540be40082a8798203e800a00000000000000000000000000000000000000000000000000000000000000000880000000000000000f863f861800a8405f5e10094100000000000000000000000000000000000000080801ba07e09e26678ed4fac08a249ebe8ed680bf9051a5e14ad223e4b2b9d26e0208f37a05f6e3f188e3e6eab7d7d3b6568f5eac7d687b08d307d3154ccd8c87b4630509bc0");
+
+    /// Creates a database with the genesis block and the pre-state of [BLOCK].
+    fn setup() -> (Arc<Env<WriteMap>>, SealedBlock) {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+        let genesis = SealedBlock::decode(&mut GENESIS.as_slice()).unwrap();
+        insert_canonical_block(tx.deref_mut(), &genesis, true).unwrap();
+
+        let code = hex!("5a465a905090036002900360015500");
+        let code_hash = keccak256(code);
+        tx.put::<tables::PlainAccountState>(
+            H160(hex!("1000000000000000000000000000000000000000")),
+            Account { nonce: 0, balance: 0.into(), bytecode_hash: Some(code_hash) },
+        )
+        .unwrap();
+        tx.put::<tables::PlainAccountState>(
+            H160(hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b")),
+            Account {
+                nonce: 0,
+                balance: U256::from(0x3635c9adc5dea00000u128),
+                bytecode_hash: None,
+            },
+        )
+        .unwrap();
+        tx.put::<tables::Bytecodes>(code_hash, code.to_vec()).unwrap();
+        tx.commit().unwrap();
+
+        (db, SealedBlock::decode(&mut BLOCK.as_slice()).unwrap())
+    }
+
+    fn config() -> Config {
+        Config {
+            chain_id: 1.into(),
+            spec_upgrades: SpecUpgrades::new_berlin_activated(),
+            hooks: Default::default(),
+        }
+    }
+
+    fn sequenced(block: &SealedBlock) -> SequencedBlock {
+        SequencedBlock {
+            attributes: BlockAttributes {
+                timestamp: block.timestamp,
+                beneficiary: block.beneficiary,
+                prev_randao: block.mix_hash,
+                gas_limit: block.gas_limit,
+                base_fee_per_gas: block.base_fee_per_gas,
+                extra_data: Default::default(),
+            },
+            transactions: block
+                .body
+                .iter()
+                .map(|tx| tx.clone().into_ecrecovered().unwrap())
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn produce_blocks_from_feed() {
+        let (db, expected) = setup();
+        let (feed, rx) = mpsc::channel(1);
+        let driver = SequencerDriver::new(db.clone(), config(), rx);
+        let mut head = driver.subscribe();
+        let (done_tx, done) = tokio::sync::oneshot::channel();
+        TaskExecutor::current().spawn("sequencer", async move {
+            let _ = done_tx.send(driver.run().await);
+        });
+
+        feed.send(sequenced(&expected)).await.unwrap();
+        head.changed().await.unwrap();
+        let header = head.borrow().clone().unwrap();
+        assert_eq!(header.number, 1);
+        assert_eq!(header.parent_hash, expected.parent_hash);
+        assert_eq!(header.gas_used, expected.gas_used);
+        assert_eq!(header.receipts_root, expected.receipts_root);
+        assert_eq!(header.logs_bloom, expected.logs_bloom);
+        assert_eq!(header.transactions_root, expected.transactions_root);
+
+        let mut empty = sequenced(&expected);
+        empty.attributes.timestamp += 12;
+        empty.transactions.clear();
+        feed.send(empty).await.unwrap();
+        drop(feed);
+        done.await.unwrap().unwrap();
+
+        let tx = db.tx().unwrap();
+        let (number, hash) =
+            tx.cursor::<tables::CanonicalHeaders>().unwrap().last().unwrap().unwrap();
+        assert_eq!(number, 2);
+        assert_eq!(head.borrow().as_ref().map(|header| header.hash()), Some(hash));
+        for stage in [HEADERS, BODIES, SENDER_RECOVERY, EXECUTION] {
+            assert_eq!(stage.get_progress(&tx).unwrap(), Some(2));
+        }
+
+        // the sender paid for the gas, the beneficiary received the rewards of both blocks
+        assert_eq!(
+            tx.get::<tables::PlainAccountState>(H160(hex!(
+                "a94f5374fce5edbc8e2a8697c15331677e6ebf0b"
+            ))),
+            Ok(Some(Account {
+                balance: 0x3635c9adc5de996b46u128.into(),
+                nonce: 1,
+                bytecode_hash: None
+            }))
+        );
+        let beneficiary =
+            tx.get::<tables::PlainAccountState>(expected.beneficiary).unwrap().unwrap();
+        assert_eq!(beneficiary.balance, U256::from(0x1bc16d674ece94bau128 + 0x1bc16d674ec80000));
+    }
+
+    #[test]
+    fn reject_timestamp_in_past() {
+        let (db, block) = setup();
+        let driver = SequencerDriver::new(db.clone(), config(), mpsc::channel(1).1);
+
+        let mut sequenced = sequenced(&block);
+        sequenced.attributes.timestamp = 0;
+        assert_matches!(
+            driver.produce(sequenced),
+            Err(StageError::Validation {
+                block: 1,
+                error: consensus::Error::TimestampIsInPast { .. }
+            })
+        );
+        assert_eq!(
+            db.tx().unwrap().cursor::<tables::CanonicalHeaders>().unwrap().last(),
+            Ok(Some((0, block.parent_hash)))
+        );
+    }
+}
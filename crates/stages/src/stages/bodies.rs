@@ -18,7 +18,8 @@ use reth_primitives::{BlockNumber, SealedHeader};
 use std::{fmt::Debug, sync::Arc};
 use tracing::*;
 
-const BODIES: StageId = StageId("Bodies");
+/// The [`StageId`] of the bodies stage.
+pub const BODIES: StageId = StageId("Bodies");
 
 // TODO(onbjerg): Metrics and events (gradual status for e.g. CLI)
 /// The body stage downloads block bodies.
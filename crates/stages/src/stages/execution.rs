@@ -1,26 +1,43 @@
+use super::prune::ReceiptsPruneMode;
 use crate::{
-    db::Transaction, DatabaseIntegrityError, ExecInput, ExecOutput, Stage, StageError, StageId,
-    UnwindInput, UnwindOutput,
+    db::Transaction, stages_metrics::ExecutionMetrics, DatabaseIntegrityError, ExecInput,
+    ExecOutput, Stage, StageError, StageId, UnwindInput, UnwindOutput,
 };
 use reth_db::{
-    cursor::{DbCursorRO, DbCursorRW},
+    cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO},
     database::Database,
-    models::{BlockNumHash, StoredBlockBody, TransitionIdAddress},
+    models::{BlockNumHash, StoredBlockBody},
     tables,
     transaction::{DbTx, DbTxMut},
 };
 use reth_executor::{
+    batch::{BatchStateProvider, StateBatch},
+    cache::{
+        BytecodeCache, CachedStateProvider, PrefetchTargets, StateCache,
+        DEFAULT_BYTECODE_CACHE_SIZE, DEFAULT_STATE_CACHE_SIZE,
+    },
     config::SpecUpgrades,
-    executor::AccountChangeSet,
+    executor::ExecutionResult,
     revm_wrap::{State, SubState},
+    threaded_revm_wrap::{StateReaderConfig, StateReaderPool},
     Config,
 };
-use reth_primitives::{Address, Header, StorageEntry, TransactionSignedEcRecovered, H256, U256};
-use reth_provider::StateProviderImplRefLatest;
-use std::fmt::Debug;
+use reth_primitives::{
+    Address, BlockNumber, Header, Receipt, TransactionSigned, TransactionSignedEcRecovered, H256,
+    U256,
+};
+use reth_provider::{StateProvider, StateProviderImplRefLatest};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    sync::Arc,
+    thread::ScopedJoinHandle,
+    time::Instant,
+};
 use tracing::*;
 
-const EXECUTION: StageId = StageId("Execution");
+/// The [`StageId`] of the execution stage.
+pub const EXECUTION: StageId = StageId("Execution");
 
 /// The execution stage executes all transactions and
 /// update history indexes.
@@ -30,6 +47,7 @@ const EXECUTION: StageId = StageId("Execution");
 /// [tables::Headers] get for revm environment variables.
 /// [tables::CumulativeTxCount] to get tx number
 /// [tables::Transactions] to execute
+/// [tables::TxSenders] to skip sender recovery, pruned senders are recovered again
 ///
 /// For state access [StateProvider] provides us latest state and history state
 /// For latest most recent state [StateProvider] would need (Used for execution Stage):
@@ -38,6 +56,7 @@ const EXECUTION: StageId = StageId("Execution");
 /// [tables::PlainStorageState]
 ///
 /// Tables updated after state finishes execution:
+/// [tables::Receipts]
 /// [tables::PlainAccountState]
 /// [tables::PlainStorageState]
 /// [tables::Bytecodes]
@@ -49,21 +68,398 @@ const EXECUTION: StageId = StageId("Execution");
 /// [tables::AccountHistory] to remove change set and apply old values to
 /// [tables::PlainAccountState] [tables::StorageHistory] to remove change set and apply old values
 /// to [tables::PlainStorageState]
+///
+/// Receipts that would be pruned right away according to the
+/// [receipts pruning](ExecutionStage::with_receipts_pruning) are not written.
+///
+/// The outcomes of the most recent blocks are kept in memory, see
+/// [ExecutionStage::with_reorg_buffer]. A reorg still unwinds all stages of the pipeline and the
+/// blocks of the new chain are executed as usual. The buffer only saves executing the blocks of a
+/// chain that was canonical before once a later reorg switches back to it. Reorgs deeper than
+/// [ExecutionStage::with_max_reorg_depth] are refused.
+///
+/// For debugging, the execution can halt before a given transaction of a block, see
+/// [ExecutionStage::with_halt_before].
 #[derive(Debug)]
 pub struct ExecutionStage {
     config: Config,
+    metrics: ExecutionMetrics,
+    receipts_pruning: Option<ReceiptsPruneMode>,
+    /// The number of blocks whose touched state is loaded before they are executed.
+    prefetch_blocks: usize,
+    /// The number of entries of each cache of the [StateCache].
+    state_cache_size: usize,
+    /// The threads that prefetch the state of a batch, `None` prefetches on the stage's thread.
+    state_readers: Option<StateReaderConfig>,
+    /// The analysed bytecodes, which are kept across batches.
+    bytecode_cache: Arc<BytecodeCache>,
+    /// The outcomes of the most recently executed blocks, which are kept across unwinds.
+    executed_blocks: ExecutedBlocks,
+    /// The maximum number of blocks a reorg may unwind, `None` if there is no limit.
+    max_reorg_depth: Option<u64>,
+    /// The block and the index of the transaction in it to halt the execution before.
+    halt_before: Option<(BlockNumber, usize)>,
 }
 
 impl Default for ExecutionStage {
     fn default() -> Self {
-        Self { config: Config { chain_id: 1.into(), spec_upgrades: SpecUpgrades::new_ethereum() } }
+        Self::new(Config {
+            chain_id: 1.into(),
+            spec_upgrades: SpecUpgrades::new_ethereum(),
+            hooks: Default::default(),
+        })
     }
 }
 
 impl ExecutionStage {
     /// Create new execution stage with specified config.
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: ExecutionMetrics::default(),
+            receipts_pruning: None,
+            prefetch_blocks: DEFAULT_PREFETCH_BLOCKS,
+            state_cache_size: DEFAULT_STATE_CACHE_SIZE,
+            state_readers: None,
+            bytecode_cache: Arc::new(BytecodeCache::new(DEFAULT_BYTECODE_CACHE_SIZE)),
+            executed_blocks: ExecutedBlocks::new(DEFAULT_REORG_BUFFER_BLOCKS),
+            max_reorg_depth: None,
+            halt_before: None,
+        }
+    }
+
+    /// Set the number of blocks whose senders, recipients and access lists are loaded into the
+    /// state cache before the blocks are executed.
+    ///
+    /// The blocks of a batch are executed in windows of this size, see
+    /// [ExecutionStage::with_state_readers] for how the state of a window is loaded.
+    ///
+    /// Defaults to 32, zero disables prefetching.
+    pub fn with_prefetch_blocks(mut self, blocks: usize) -> Self {
+        self.prefetch_blocks = blocks;
+        self
+    }
+
+    /// Set the number of accounts, storage slots and bytecodes that are cached while executing a
+    /// batch of blocks.
+    ///
+    /// Defaults to [DEFAULT_STATE_CACHE_SIZE] of each.
+    pub fn with_state_cache_size(mut self, size: usize) -> Self {
+        self.state_cache_size = size;
+        self
+    }
+
+    /// Prefetch the state with a pool of threads, each of which reads a part of the state the
+    /// blocks are known to touch.
+    ///
+    /// The pool reads the state of the next window of blocks while the blocks before it execute,
+    /// see [ExecutionStage::with_prefetch_blocks]. A pool is spawned for every batch of blocks. Its
+    /// transactions read the committed state, so the transaction of the stage must not hold
+    /// uncommitted changes when the stage is executed, which is the case in the
+    /// [Pipeline](crate::Pipeline). By default the state is prefetched synchronously through the
+    /// transaction of the stage, before each window of blocks executes.
+    pub fn with_state_readers(mut self, config: StateReaderConfig) -> Self {
+        self.state_readers = Some(config);
+        self
+    }
+
+    /// Set the number of analysed bytecodes that are cached across blocks.
+    ///
+    /// Defaults to [DEFAULT_BYTECODE_CACHE_SIZE].
+    pub fn with_bytecode_cache_size(mut self, size: usize) -> Self {
+        self.bytecode_cache = Arc::new(BytecodeCache::new(size));
+        self
+    }
+
+    /// Skips writing the receipts that are pruned according to the mode.
+    ///
+    /// The receipts are pruned relative to the progress of the previous stage, which during the
+    /// initial sync is the tip of the chain. Receipts that only fall out of the window as the chain
+    /// advances are removed by the [`PruneStage`](super::prune::PruneStage).
+    pub fn with_receipts_pruning(mut self, mode: ReceiptsPruneMode) -> Self {
+        self.receipts_pruning = Some(mode);
+        self
+    }
+
+    /// Set the number of blocks below the tip whose execution outcomes are kept in memory.
+    ///
+    /// Reorgs up to this depth that switch back to a chain that was executed before don't execute
+    /// its blocks again. Defaults to [DEFAULT_REORG_BUFFER_BLOCKS], zero disables the buffer.
+    pub fn with_reorg_buffer(mut self, blocks: usize) -> Self {
+        self.executed_blocks = ExecutedBlocks::new(blocks);
+        self
+    }
+
+    /// Refuse unwinds of more than the given number of blocks that are requested by a reorg.
+    ///
+    /// The unwind fails with [StageError::ReorgTooDeep], which stops the pipeline before it commits
+    /// the unwind. Unwinds below a bad block are not limited. By default reorgs of any depth are
+    /// unwound.
+    pub fn with_max_reorg_depth(mut self, depth: u64) -> Self {
+        self.max_reorg_depth = Some(depth);
+        self
+    }
+
+    /// Halt the execution before the transaction with the given index of the block.
+    ///
+    /// The blocks before are executed as usual. Of the halting block, only the transactions before
+    /// the index are applied, without verifying the block and without the block reward. The stage
+    /// then reports the parent of the block as its progress and is done, so the later stages never
+    /// see the block. The database is left in this state for inspection and can't be synced
+    /// further, except after unwinding below the block.
+    ///
+    /// The previous stages have to sync the halting block, see
+    /// [Pipeline::set_halt_block](crate::Pipeline::set_halt_block).
+    pub fn with_halt_before(mut self, block: BlockNumber, index: usize) -> Self {
+        self.halt_before = Some((block, index));
+        self
+    }
+
+    /// Executes the block on top of the state of the provider.
+    ///
+    /// The gas used and the receipts are only verified if `verify` is set, since a prefix of the
+    /// transactions of a block doesn't match its header.
+    fn execute_block<SP: StateProvider>(
+        &self,
+        header: &Header,
+        transactions: &[TransactionSignedEcRecovered],
+        provider: SP,
+        verify: bool,
+    ) -> Result<ExecutionResult, StageError> {
+        trace!(target: "sync::stages::execution", number = header.number, txs = transactions.len(), "Executing block");
+
+        // for now use default eth config
+        let spec_id = self.config.spec_upgrades.revm_spec(header.number);
+        let state_provider = SubState::new(
+            State::new(provider).with_bytecode_cache(self.bytecode_cache.clone(), spec_id),
+        );
+
+        // For ethereum tests that has MAX gas that calls contract until max depth (1024
+        // calls) revm can take more then default allocated stack space. For
+        // this case we are using local thread with increased stack size. After this task is done https://github.com/bluealloy/revm/issues/305
+        // we can see to set more accurate stack size or even optimize revm to move more
+        // data to heap.
+        let started_at = Instant::now();
+        let changeset = std::thread::scope(|scope| {
+            let handle = std::thread::Builder::new()
+                .stack_size(50 * 1024 * 1024)
+                .spawn_scoped(scope, || {
+                    // execute and store output to results
+                    if !verify {
+                        return reth_executor::executor::execute_transactions(
+                            header,
+                            transactions,
+                            &self.config,
+                            state_provider,
+                        )
+                    }
+                    // ANCHOR: snippet-block_change_patches
+                    reth_executor::executor::execute_and_verify_receipt(
+                        header,
+                        transactions,
+                        &self.config,
+                        state_provider,
+                    )
+                    // ANCHOR_END: snippet-block_change_patches
+                })
+                .expect("Expects that thread name is not null");
+            handle.join().expect("Expects for thread to not panic")
+        })
+        .map_err(|error| StageError::ExecutionError { block: header.number, error })?;
+        self.metrics.block_execution_time.record(started_at.elapsed());
+        self.metrics.executed_blocks.increment(1);
+        self.metrics.executed_transactions.increment(transactions.len() as u64);
+        self.metrics.gas_used.increment(changeset.gas_used());
+        Ok(changeset)
+    }
+
+    /// Executes the blocks of a batch on top of the stored state and returns their state changes
+    /// and receipts.
+    ///
+    /// The state the blocks are known to touch is loaded a window of blocks ahead. The readers, if
+    /// there are any, load the state of the next window while a window executes, otherwise it is
+    /// loaded synchronously before the window executes.
+    fn execute_batch<DB: Database>(
+        &mut self,
+        tx: &Transaction<'_, DB>,
+        canonical_batch: &[BlockNumHash],
+        block_batch: &[(Header, StoredBlockBody)],
+        tip: BlockNumber,
+        readers: Option<&StateReaderPool<'_>>,
+    ) -> Result<(StateBatch, Vec<Vec<Receipt>>), StageError> {
+        // Get transaction of the block that we are executing.
+        let mut tx_cursor = tx.cursor::<tables::Transactions>()?;
+        // Skip sender recovery and load signer from database.
+        let mut tx_sender = tx.cursor::<tables::TxSenders>()?;
+
+        // The state changes of the executed blocks are collected in the state batch, which the
+        // later blocks of the batch read through. The database is only written once the batch is
+        // done, so the cache of the stored state stays valid for the whole batch.
+        let state_cache = StateCache::new(self.state_cache_size);
+        let mut state_batch = StateBatch::new();
+
+        // Fetch transactions, execute them and generate results
+        let mut block_receipts = Vec::with_capacity(canonical_batch.len());
+        let window_size = self.prefetch_blocks.max(1);
+        let mut windows = canonical_batch.chunks(window_size).zip(block_batch.chunks(window_size));
+        std::thread::scope(|scope| -> Result<(), StageError> {
+            // The window whose state is being loaded, with the prefetch of the readers.
+            let mut loading: Option<(
+                Vec<_>,
+                Option<ScopedJoinHandle<'_, reth_interfaces::Result<()>>>,
+            )> = None;
+            loop {
+                let next = match windows.next() {
+                    Some((keys, window)) => {
+                        let mut blocks = Vec::with_capacity(window.len());
+                        for (key, (header, body)) in keys.iter().zip(window) {
+                            // The block was executed on the same parent before it was unwound.
+                            if let Some(result) = self.executed_blocks.get(&key.hash()) {
+                                blocks.push((key, header, Vec::new(), Some(result.clone())));
+                                continue
+                            }
+
+                            let recovered_transactions =
+                                block_transactions(&mut tx_cursor, &mut tx_sender, header, body)?;
+                            blocks.push((key, header, recovered_transactions, None));
+                        }
+                        Some(blocks)
+                    }
+                    None => None,
+                };
+
+                // The state of the loaded window has to be cached before it is executed.
+                let ready = match loading.take() {
+                    Some((blocks, prefetch)) => {
+                        if let Some(prefetch) = prefetch {
+                            prefetch
+                                .join()
+                                .expect("Expects prefetch to not panic")
+                                .map_err(|err| StageError::Fatal(Box::new(err)))?;
+                        }
+                        Some(blocks)
+                    }
+                    None => None,
+                };
+
+                // Load the state the blocks of the next window are known to touch in key order,
+                // instead of reading it in random order during execution. The readers load it
+                // while the ready window executes, otherwise it is loaded on this thread first.
+                let mut prefetch = None;
+                if let Some(blocks) = next.as_ref().filter(|_| self.prefetch_blocks > 0) {
+                    let mut targets = PrefetchTargets::default();
+                    for (_, header, transactions, _) in
+                        blocks.iter().filter(|(.., reused)| reused.is_none())
+                    {
+                        targets.extend_block(header, transactions);
+                    }
+                    trace!(target: "sync::stages::execution", blocks = blocks.len(), accounts = targets.accounts.len(), slots = targets.storage.len(), "Prefetching state");
+                    match readers {
+                        Some(pool) => {
+                            prefetch = Some(state_cache.spawn_prefetch(scope, pool, targets))
+                        }
+                        None => state_cache
+                            .prefetch(&StateProviderImplRefLatest::new(&**tx), &targets)
+                            .map_err(|err| StageError::Fatal(Box::new(err)))?,
+                    }
+                }
+
+                for (key, header, recovered_transactions, reused) in ready.into_iter().flatten() {
+                    let result = match reused {
+                        Some(result) => {
+                            trace!(target: "sync::stages::execution", number = header.number, "Reusing buffered execution outcome");
+                            self.metrics.reused_blocks.increment(1);
+                            result
+                        }
+                        None => self.execute_block(
+                            header,
+                            &recovered_transactions,
+                            BatchStateProvider::new(
+                                CachedStateProvider::new(
+                                    StateProviderImplRefLatest::new(&**tx),
+                                    &state_cache,
+                                ),
+                                &state_batch,
+                            ),
+                            true,
+                        )?,
+                    };
+
+                    if header.number + self.executed_blocks.max_blocks as u64 > tip {
+                        self.executed_blocks.insert(*key, &result);
+                    }
+                    block_receipts.push(
+                        result
+                            .changesets
+                            .iter()
+                            .map(|changeset| changeset.receipt.clone())
+                            .collect::<Vec<_>>(),
+                    );
+                    // The changes are recorded at the transitions the bodies stage assigned to the
+                    // block.
+                    let (_, transition_id) = tx.get_next_block_ids(header.number)?;
+                    state_batch.extend(result, transition_id);
+                }
+
+                match next {
+                    Some(blocks) => loading = Some((blocks, prefetch)),
+                    None => return Ok(()),
+                }
+            }
+        })?;
+        Ok((state_batch, block_receipts))
+    }
+
+    /// Applies the transactions of the halting block before the index on top of the state of its
+    /// parent, see [ExecutionStage::with_halt_before].
+    fn execute_halting_block<DB: Database>(
+        &self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+        block: BlockNumber,
+        index: usize,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = block.saturating_sub(1);
+        if input.previous_stage_progress() < block {
+            info!(target: "sync::stages::execution", stage_progress, "Halting block not synced yet");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+        let (first_tx, transition_id) = tx.get_next_block_ids(block)?;
+
+        // Every transaction changes at least the nonce of its sender, so the changesets of the
+        // block are only present if the halting block was applied by a previous run.
+        if index == 0 || tx.cursor::<tables::AccountChangeSet>()?.seek(transition_id)?.is_some() {
+            info!(target: "sync::stages::execution", block, index, "Halted before transaction");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        let key = tx.get_block_numhash(block)?;
+        let header = tx
+            .get::<tables::Headers>(key)?
+            .ok_or(DatabaseIntegrityError::Header { number: key.number(), hash: key.hash() })?;
+        let body = tx.get_block_body(key)?;
+        let mut transactions = block_transactions(
+            &mut tx.cursor::<tables::Transactions>()?,
+            &mut tx.cursor::<tables::TxSenders>()?,
+            &header,
+            &body,
+        )?;
+        transactions.truncate(index);
+
+        let mut result = self.execute_block(
+            &header,
+            &transactions,
+            StateProviderImplRefLatest::new(&**tx),
+            false,
+        )?;
+        result.block_reward = None;
+        for (tx_number, changeset) in (first_tx..).zip(&result.changesets) {
+            tx.put::<tables::Receipts>(tx_number, changeset.receipt.clone())?;
+        }
+        result.apply_to_db(&**tx, transition_id)?;
+
+        info!(target: "sync::stages::execution", block, index, "Halted before transaction");
+        Ok(ExecOutput { stage_progress, done: true })
     }
 }
 
@@ -71,6 +467,12 @@ impl ExecutionStage {
 /// TODO make this as config
 const BATCH_SIZE: u64 = 1000;
 
+/// The default number of blocks whose touched state is prefetched at once.
+const DEFAULT_PREFETCH_BLOCKS: usize = 32;
+
+/// The default number of blocks whose execution outcomes are kept for shallow reorgs.
+pub const DEFAULT_REORG_BUFFER_BLOCKS: usize = 64;
+
 #[async_trait::async_trait]
 impl<DB: Database> Stage<DB> for ExecutionStage {
     /// Return the id of the stage
@@ -89,21 +491,31 @@ impl<DB: Database> Stage<DB> for ExecutionStage {
         let last_block = input.stage_progress.unwrap_or_default();
         let start_block = last_block + 1;
 
+        if let Some((block, index)) = self.halt_before {
+            if start_block == block {
+                return self.execute_halting_block(tx, input, block, index)
+            }
+            if start_block > block {
+                warn!(target: "sync::stages::execution", stage_progress = last_block, block, "Halting block already executed");
+                return Ok(ExecOutput { stage_progress: last_block, done: true })
+            }
+        }
+
         // Get next canonical block hashes to execute.
         let mut canonicals = tx.cursor::<tables::CanonicalHeaders>()?;
         // Get header with canonical hashes.
         let mut headers = tx.cursor::<tables::Headers>()?;
         // Get bodies with canonical hashes.
         let mut bodies_cursor = tx.cursor::<tables::BlockBodies>()?;
-        // Get transaction of the block that we are executing.
-        let mut tx_cursor = tx.cursor::<tables::Transactions>()?;
-        // Skip sender recovery and load signer from database.
-        let mut tx_sender = tx.cursor::<tables::TxSenders>()?;
 
         // get canonical blocks (num,hash)
+        let halt_block = self.halt_before.map(|(block, _)| block);
         let canonical_batch = canonicals
             .walk(start_block)?
             .take(BATCH_SIZE as usize) // TODO: commit_threshold
+            .take_while(|res| {
+                res.as_ref().map_or(true, |(number, _)| halt_block.map_or(true, |b| *number < b))
+            })
             .map(|i| i.map(BlockNumHash))
             .collect::<Result<Vec<_>, _>>()?;
 
@@ -130,159 +542,45 @@ impl<DB: Database> Stage<DB> for ExecutionStage {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        // Fetch transactions, execute them and generate results
-        let mut block_change_patches = Vec::with_capacity(canonical_batch.len());
-        for (header, body) in block_batch.iter() {
-            let num = header.number;
-            tracing::trace!(target: "sync::stages::execution", ?num, "Execute block.");
-            // iterate over all transactions
-            let mut tx_walker = tx_cursor.walk(body.start_tx_id)?;
-            let mut transactions = Vec::with_capacity(body.tx_count as usize);
-            // get next N transactions.
-            for index in body.tx_id_range() {
-                let (tx_index, tx) =
-                    tx_walker.next().ok_or(DatabaseIntegrityError::EndOfTransactionTable)??;
-                if tx_index != index {
-                    error!(target: "sync::stages::execution", block = header.number, expected = index, found = tx_index, ?body, "Transaction gap");
-                    return Err(DatabaseIntegrityError::TransactionsGap { missing: tx_index }.into())
-                }
-                transactions.push(tx);
+        let tip = input.previous_stage_progress();
+        let (state_batch, block_receipts) = match self.state_readers {
+            Some(config) => {
+                // The readers are spawned for every batch, so they read the state the previous
+                // batch committed.
+                let db = tx.inner();
+                std::thread::scope(|scope| {
+                    let pool = StateReaderPool::spawn(scope, db, config)
+                        .map_err(|err| StageError::Fatal(Box::new(err)))?;
+                    self.execute_batch(tx, &canonical_batch, &block_batch, tip, Some(&pool))
+                })?
             }
+            None => self.execute_batch(tx, &canonical_batch, &block_batch, tip, None)?,
+        };
 
-            // take signers
-            let mut tx_sender_walker = tx_sender.walk(body.start_tx_id)?;
-            let mut signers = Vec::with_capacity(body.tx_count as usize);
-            for index in body.tx_id_range() {
-                let (tx_index, tx) = tx_sender_walker
-                    .next()
-                    .ok_or(DatabaseIntegrityError::EndOfTransactionSenderTable)??;
-                if tx_index != index {
-                    error!(target: "sync::stages::execution", block = header.number, expected = index, found = tx_index, ?body, "Signer gap");
-                    return Err(
-                        DatabaseIntegrityError::TransactionsSignerGap { missing: tx_index }.into()
-                    )
-                }
-                signers.push(tx);
-            }
-            // create ecRecovered transaction by matching tx and its signer
-            let recovered_transactions: Vec<_> = transactions
-                .into_iter()
-                .zip(signers.into_iter())
-                .map(|(tx, signer)| {
-                    TransactionSignedEcRecovered::from_signed_transaction(tx, signer)
-                })
-                .collect();
-
-            // for now use default eth config
-            let state_provider = SubState::new(State::new(StateProviderImplRefLatest::new(&**tx)));
-
-            trace!(target: "sync::stages::execution", number = header.number, txs = recovered_transactions.len(), "Executing block");
-
-            // For ethereum tests that has MAX gas that calls contract until max depth (1024 calls)
-            // revm can take more then default allocated stack space. For this case we are using
-            // local thread with increased stack size. After this task is done https://github.com/bluealloy/revm/issues/305
-            // we can see to set more accurate stack size or even optimize revm to move more data to
-            // heap.
-            let changeset = std::thread::scope(|scope| {
-                let handle = std::thread::Builder::new()
-                    .stack_size(50 * 1024 * 1024)
-                    .spawn_scoped(scope, || {
-                        // execute and store output to results
-                        // ANCHOR: snippet-block_change_patches
-                        reth_executor::executor::execute_and_verify_receipt(
-                            header,
-                            &recovered_transactions,
-                            &self.config,
-                            state_provider,
-                        )
-                        // ANCHOR_END: snippet-block_change_patches
-                    })
-                    .expect("Expects that thread name is not null");
-                handle.join().expect("Expects for thread to not panic")
-            })
-            .map_err(|error| StageError::ExecutionError { block: header.number, error })?;
-            block_change_patches.push(changeset);
-        }
+        self.metrics.bytecode_cache_hits.absolute(self.bytecode_cache.hits());
+        self.metrics.bytecode_cache_misses.absolute(self.bytecode_cache.misses());
+        self.metrics.bytecode_cache_size.set(self.bytecode_cache.len() as f64);
 
-        // Get last tx count so that we can know amount of transaction in the block.
-        let mut current_transition_id = tx.get_block_transition_by_num(last_block)? + 1;
-        info!(target: "sync::stages::execution", current_transition_id, blocks = block_change_patches.len(), "Inserting execution results");
-
-        // apply changes to plain database.
-        for results in block_change_patches.into_iter() {
-            // insert state change set
-            for result in results.changesets.into_iter() {
-                // TODO insert to transitionId to tx_index
-                for (address, account_change_set) in result.changeset.into_iter() {
-                    let AccountChangeSet { account, wipe_storage, storage } = account_change_set;
-                    // apply account change to db. Updates AccountChangeSet and PlainAccountState
-                    // tables.
-                    trace!(target: "sync::stages::execution", ?address, current_transition_id, ?account, wipe_storage, "Applying account changeset");
-                    account.apply_to_db(&**tx, address, current_transition_id)?;
-
-                    // wipe storage
-                    if wipe_storage {
-                        // TODO insert all changes to StorageChangeSet
-                        tx.delete::<tables::PlainStorageState>(address, None)?;
-                    }
-                    // insert storage changeset
-                    let storage_id = TransitionIdAddress((current_transition_id, address));
-                    for (key, (old_value, new_value)) in storage {
-                        let mut hkey = H256::zero();
-                        key.to_big_endian(&mut hkey.0);
-
-                        trace!(target: "sync::stages::execution", ?address, current_transition_id, ?hkey, ?old_value, ?new_value, "Applying storage changeset");
-
-                        // insert into StorageChangeSet
-                        tx.put::<tables::StorageChangeSet>(
-                            storage_id.clone(),
-                            StorageEntry { key: hkey, value: old_value },
-                        )?;
-                        tracing::debug!(
-                            target = "sync::stages::execution",
-                            "{address} setting storage:{key} ({old_value} -> {new_value})"
-                        );
-
-                        // Always delete old value as duplicate table put will not override it
-                        tx.delete::<tables::PlainStorageState>(
-                            address,
-                            Some(StorageEntry { key: hkey, value: old_value }),
-                        )?;
-                        if !new_value.is_zero() {
-                            tx.put::<tables::PlainStorageState>(
-                                address,
-                                StorageEntry { key: hkey, value: new_value },
-                            )?;
-                        }
-                    }
-                    current_transition_id += 1;
-                }
-                // insert bytecode
-                for (hash, bytecode) in result.new_bytecodes.into_iter() {
-                    // make different types of bytecode. Checked and maybe even analyzed (needs to
-                    // be packed). Currently save only raw bytes.
-                    let bytecode = bytecode.bytes();
-                    trace!(target: "sync::stages::execution", ?hash, ?bytecode, len = bytecode.len(), "Inserting bytecode");
-                    tx.put::<tables::Bytecodes>(hash, bytecode[..bytecode.len()].to_vec())?;
-
-                    // NOTE: bytecode bytes are not inserted in change set and it stand in saparate
-                    // table
-                }
-            }
+        info!(target: "sync::stages::execution", blocks = block_receipts.len(), "Inserting execution results");
 
-            // If there is block reward we will add account changeset to db
-            if let Some(block_reward_changeset) = results.block_reward {
-                // we are sure that block reward index is present.
-                for (address, changeset) in block_reward_changeset.into_iter() {
-                    trace!(target: "sync::stages::execution", ?address, current_transition_id, "Applying block reward");
-                    changeset.apply_to_db(&**tx, address, current_transition_id)?;
+        // store the receipts of the transactions and write the state changes to the plain state
+        // sorted by key at once.
+        for ((header, body), receipts) in block_batch.iter().zip(block_receipts) {
+            for (tx_number, receipt) in body.tx_id_range().zip(receipts) {
+                if let Some(prune) = &self.receipts_pruning {
+                    if prune.is_pruned(header.number, tip, &receipt) {
+                        continue
+                    }
                 }
-                current_transition_id += 1;
+                tx.put::<tables::Receipts>(tx_number, receipt)?;
             }
         }
+        state_batch.write_to_db(&**tx)?;
 
         let stage_progress = last_block + canonical_batch.len() as u64;
-        let done = canonical_batch.len() < BATCH_SIZE as usize;
+        // The halting block is applied by the next iteration.
+        let done = canonical_batch.len() < BATCH_SIZE as usize &&
+            halt_block.map_or(true, |block| stage_progress + 1 < block);
         info!(target: "sync::stages::execution", done, stage_progress, "Sync iteration finished");
         Ok(ExecOutput { done, stage_progress })
     }
@@ -293,17 +591,46 @@ impl<DB: Database> Stage<DB> for ExecutionStage {
         tx: &mut Transaction<'_, DB>,
         input: UnwindInput,
     ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        // The changesets to revert the state with are gone for pruned blocks.
+        if let Some(oldest) = tx.get_history_pruned_to()? {
+            if input.unwind_to < oldest {
+                return Err(StageError::HistoryPruned { unwind_to: input.unwind_to, oldest }.into())
+            }
+        }
+
+        // Only reorgs unwind without a bad block.
+        let depth = input.stage_progress.saturating_sub(input.unwind_to);
+        if let Some(max_depth) = self.max_reorg_depth.filter(|_| input.bad_block.is_none()) {
+            if depth > max_depth {
+                return Err(StageError::ReorgTooDeep { depth, max_depth }.into())
+            }
+        }
+
+        // The outcomes of the blocks of a valid chain can be applied again if it becomes canonical
+        // again, the outcomes above a bad block are not trusted.
+        if input.bad_block.is_some() {
+            self.executed_blocks.remove_above(input.unwind_to);
+        }
+
+        // Discard the receipts of the unwound transactions.
+        let (first_unwound_tx, _) = tx.get_next_block_ids(input.unwind_to + 1)?;
+        tx.unwind_table::<tables::Receipts, _>(first_unwound_tx, |tx_number| tx_number + 1)?;
+
         // Acquire changeset cursors
         let mut account_changeset = tx.cursor_dup_mut::<tables::AccountChangeSet>()?;
         let mut storage_changeset = tx.cursor_dup_mut::<tables::StorageChangeSet>()?;
 
-        let from_transition = tx.get_block_transition_by_num(input.stage_progress)?;
+        let mut from_transition = tx.get_block_transition_by_num(input.stage_progress)?;
+        // The transactions applied of a halting block come after the progress of the stage.
+        if let Some((block, index)) = self.halt_before {
+            if block == input.stage_progress + 1 {
+                from_transition += index as u64;
+            }
+        }
 
-        let to_transition = if input.unwind_to != 0 {
-            tx.get_block_transition_by_num(input.unwind_to - 1)?
-        } else {
-            0
-        };
+        // The transition of a block marks the state at its end, the changes of the first unwound
+        // block come after it.
+        let to_transition = tx.get_block_transition_by_num(input.unwind_to)?;
 
         if to_transition > from_transition {
             panic!("Unwind transition {} (stage progress block #{}) is higher than the transition {} of (unwind block #{})", to_transition, input.stage_progress, from_transition, input.unwind_to);
@@ -348,12 +675,17 @@ impl<DB: Database> Stage<DB> for ExecutionStage {
             .collect::<Result<Vec<_>, _>>()?;
 
         // revert all changes to PlainStorage
+        let mut plain_storage = tx.cursor_dup_mut::<tables::PlainStorageState>()?;
         for (key, storage) in storage_chageset_batch.into_iter().rev() {
             let address = key.address();
-            tx.put::<tables::PlainStorageState>(address, storage.clone())?;
-            if storage.value == U256::zero() {
-                // delete value that is zero
-                tx.delete::<tables::PlainStorageState>(address, Some(storage))?;
+            // Always delete the current value as a put to the duplicate table does not override it
+            if let Some(entry) = plain_storage.seek_by_key_subkey(address, storage.key)? {
+                if entry.key == storage.key {
+                    plain_storage.delete_current()?;
+                }
+            }
+            if storage.value != U256::zero() {
+                plain_storage.upsert(address, storage)?;
             }
         }
 
@@ -380,18 +712,145 @@ impl<DB: Database> Stage<DB> for ExecutionStage {
     }
 }
 
+/// Returns the transactions of the block with their senders.
+fn block_transactions<'a, T, S>(
+    transactions: &mut T,
+    senders: &mut S,
+    header: &Header,
+    body: &StoredBlockBody,
+) -> Result<Vec<TransactionSignedEcRecovered>, StageError>
+where
+    T: DbCursorRO<'a, tables::Transactions>,
+    S: DbCursorRO<'a, tables::TxSenders>,
+{
+    // iterate over all transactions
+    let mut tx_walker = transactions.walk(body.start_tx_id)?;
+    let mut block_transactions = Vec::with_capacity(body.tx_count as usize);
+    // get next N transactions.
+    for index in body.tx_id_range() {
+        let (tx_index, tx) =
+            tx_walker.next().ok_or(DatabaseIntegrityError::EndOfTransactionTable)??;
+        if tx_index != index {
+            error!(target: "sync::stages::execution", block = header.number, expected = index, found = tx_index, ?body, "Transaction gap");
+            return Err(DatabaseIntegrityError::TransactionsGap { missing: tx_index }.into())
+        }
+        block_transactions.push(tx);
+    }
+
+    // take signers
+    let signers = block_senders(senders, body, &block_transactions)?;
+    // create ecRecovered transaction by matching tx and its signer
+    Ok(block_transactions
+        .into_iter()
+        .zip(signers.into_iter())
+        .map(|(tx, signer)| TransactionSignedEcRecovered::from_signed_transaction(tx, signer))
+        .collect())
+}
+
+/// Returns the senders of the transactions of the block.
+///
+/// Senders that are not in [tables::TxSenders] because they were pruned are recovered from the
+/// signatures of the transactions.
+fn block_senders<'a, C: DbCursorRO<'a, tables::TxSenders>>(
+    senders: &mut C,
+    body: &StoredBlockBody,
+    transactions: &[TransactionSigned],
+) -> Result<Vec<Address>, StageError> {
+    let stored = senders
+        .walk(body.start_tx_id)?
+        .take_while(|res| res.as_ref().map(|(k, _)| body.tx_id_range().contains(k)).unwrap_or(true))
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    body.tx_id_range()
+        .zip(transactions)
+        .map(|(index, transaction)| match stored.get(&index) {
+            Some(sender) => Ok(*sender),
+            None => {
+                trace!(target: "sync::stages::execution", tx = index, "Recovering pruned sender");
+                transaction.recover_signer().ok_or_else(|| {
+                    DatabaseIntegrityError::TransactionsSignerGap { missing: index }.into()
+                })
+            }
+        })
+        .collect()
+}
+
+/// The execution outcomes of the most recently executed blocks by hash.
+///
+/// The outcome of a block only depends on the state of its parent, which is committed to by the
+/// hash of the block, so it can be applied again after the block was unwound. The oldest block is
+/// evicted once the buffer is full.
+#[derive(Debug, Default)]
+struct ExecutedBlocks {
+    /// The outcomes and numbers of the buffered blocks.
+    outcomes: HashMap<H256, (BlockNumber, ExecutionResult)>,
+    /// The hashes of the buffered blocks in insertion order.
+    order: VecDeque<H256>,
+    /// The maximum number of buffered blocks.
+    max_blocks: usize,
+}
+
+// === impl ExecutedBlocks ===
+
+impl ExecutedBlocks {
+    fn new(max_blocks: usize) -> Self {
+        Self { max_blocks, ..Default::default() }
+    }
+
+    /// Returns the outcome of the block with the given hash.
+    fn get(&self, hash: &H256) -> Option<&ExecutionResult> {
+        self.outcomes.get(hash).map(|(_, result)| result)
+    }
+
+    /// Buffers the outcome of the block, evicting the oldest block if the buffer is full.
+    fn insert(&mut self, key: BlockNumHash, result: &ExecutionResult) {
+        if self.max_blocks == 0 || self.outcomes.contains_key(&key.hash()) {
+            return
+        }
+        while self.outcomes.len() >= self.max_blocks {
+            let Some(oldest) = self.order.pop_front() else { break };
+            self.outcomes.remove(&oldest);
+        }
+        self.order.push_back(key.hash());
+        self.outcomes.insert(key.hash(), (key.number(), result.clone()));
+    }
+
+    /// Removes the outcomes of all blocks above the given number.
+    fn remove_above(&mut self, number: BlockNumber) {
+        self.outcomes.retain(|_, (block, _)| *block <= number);
+        let outcomes = &self.outcomes;
+        self.order.retain(|hash| outcomes.contains_key(hash));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::ops::{Deref, DerefMut};
 
     use super::*;
+    use crate::stages::sender_recovery::SENDER_RECOVERY;
     use reth_db::mdbx::{test_utils::create_test_db, EnvKind, WriteMap};
-    use reth_primitives::{hex_literal::hex, keccak256, Account, SealedBlock, H160, U256};
+    use reth_executor::builder::{build_block, BlockAttributes};
+    use reth_interfaces::test_utils::generators::{random_block, random_block_range, sign_message};
+    use reth_primitives::{
+        hex_literal::hex, keccak256, Account, SealedBlock, StorageEntry, TransactionKind, TxLegacy,
+        H160, H256, U256,
+    };
     use reth_provider::insert_canonical_block;
     use reth_rlp::Decodable;
 
     #[tokio::test]
     async fn sanity_execution_of_block() {
+        sanity_execution(ExecutionStage::default()).await
+    }
+
+    #[tokio::test]
+    async fn sanity_execution_with_state_readers() {
+        let config = StateReaderConfig { workers: 2, queue_size: 1 };
+        sanity_execution(ExecutionStage::default().with_state_readers(config)).await
+    }
+
+    async fn sanity_execution(mut execution_stage: ExecutionStage) {
         // TODO cleanup the setup after https://github.com/paradigmxyz/reth/issues/332
         // is merged as it has similar framework
         let state_db = create_test_db::<WriteMap>(EnvKind::RW);
@@ -432,7 +891,6 @@ mod tests {
         tx.commit().unwrap();
 
         // execute
-        let mut execution_stage = ExecutionStage::default();
         execution_stage.config.spec_upgrades = SpecUpgrades::new_berlin_activated();
         let output = execution_stage.execute(&mut tx, input).await.unwrap();
         tx.commit().unwrap();
@@ -472,6 +930,10 @@ mod tests {
             Ok(Some(StorageEntry { key: H256::from_low_u64_be(1), value: 2.into() })),
             "Post changed of a account"
         );
+        // assert receipt
+        let receipt = tx.get::<tables::Receipts>(0).unwrap().expect("receipt is stored");
+        assert!(receipt.success);
+        assert_eq!(receipt.cumulative_gas_used, block.header.gas_used);
     }
 
     #[tokio::test]
@@ -543,5 +1005,331 @@ mod tests {
             Ok(None),
             "Third account should be unwinded"
         );
+        assert_eq!(db_tx.get::<tables::Receipts>(0), Ok(None), "Receipt should be unwinded");
+        assert_eq!(
+            db_tx.get::<tables::PlainStorageState>(acc1),
+            Ok(None),
+            "Storage should be unwinded"
+        );
+    }
+
+    #[tokio::test]
+    async fn reuse_outcome_after_unwind() {
+        let state_db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(state_db.as_ref()).unwrap();
+        let input = ExecInput { previous_stage: None, stage_progress: None };
+        let mut genesis_rlp = hex!("f901faf901f5a00000000000000000000000000000000000000000000000000000000000000000a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa045571b40ae66ca7480791bbb2887286e4e4c4b1b298b191c889d6959023a32eda056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421b901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000083020000808502540be400808000a00000000000000000000000000000000000000000000000000000000000000000880000000000000000c0c0").as_slice();
+        let genesis = SealedBlock::decode(&mut genesis_rlp).unwrap();
+        let mut block_rlp = hex!("f90262f901f9a075c371ba45999d87f4542326910a11af515897aebce5265d3f6acd1f1161f82fa01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa098f2dcd87c8ae4083e7017a05456c14eea4b1db2032126e27b3b1563d57d7cc0a08151d548273f6683169524b66ca9fe338b9ce42bc3540046c828fd939ae23bcba03f4e5c2ec5b2170b711d97ee755c160457bb58d8daa338e835ec02ae6860bbabb901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000083020000018502540be40082a8798203e800a00000000000000000000000000000000000000000000000000000000000000000880000000000000000f863f861800a8405f5e10094100000000000000000000000000000000000000080801ba07e09e26678ed4fac08a249ebe8ed680bf9051a5e14ad223e4b2b9d26e0208f37a05f6e3f188e3e6eab7d7d3b6568f5eac7d687b08d307d3154ccd8c87b4630509bc0").as_slice();
+        let block = SealedBlock::decode(&mut block_rlp).unwrap();
+        insert_canonical_block(tx.deref_mut(), &genesis, true).unwrap();
+        insert_canonical_block(tx.deref_mut(), &block, true).unwrap();
+
+        let code = hex!("5a465a905090036002900360015500");
+        let code_hash = keccak256(code);
+        let acc1 = H160(hex!("1000000000000000000000000000000000000000"));
+        let acc2 = H160(hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"));
+        let miner = H160(hex!("2adc25665018aa1fe0e6bc666dac8fc2697ff9ba"));
+        let balance = U256::from(0x3635c9adc5dea00000u128);
+        tx.put::<tables::PlainAccountState>(
+            acc1,
+            Account { nonce: 0, balance: 0.into(), bytecode_hash: Some(code_hash) },
+        )
+        .unwrap();
+        tx.put::<tables::PlainAccountState>(
+            acc2,
+            Account { nonce: 0, balance, bytecode_hash: None },
+        )
+        .unwrap();
+        tx.put::<tables::Bytecodes>(code_hash, code.to_vec()).unwrap();
+        tx.commit().unwrap();
+
+        let mut stage = ExecutionStage::default();
+        stage.config.spec_upgrades = SpecUpgrades::new_berlin_activated();
+        stage.execute(&mut tx, input).await.unwrap();
+        let state = |tx: &Transaction<'_, _>| {
+            [acc1, acc2, miner].map(|address| tx.get::<tables::PlainAccountState>(address).unwrap())
+        };
+        let executed = state(&tx);
+        assert!(stage.executed_blocks.get(&block.hash()).is_some());
+
+        stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 1, unwind_to: 0, bad_block: None })
+            .await
+            .unwrap();
+        assert_eq!(tx.get::<tables::PlainAccountState>(miner), Ok(None));
+
+        // the block is not executed again, its transactions are not even read
+        tx.delete::<tables::Transactions>(0, None).unwrap();
+        stage
+            .execute(&mut tx, ExecInput { previous_stage: None, stage_progress: Some(0) })
+            .await
+            .unwrap();
+        assert_eq!(state(&tx), executed);
+        assert_eq!(
+            tx.get::<tables::PlainStorageState>(acc1),
+            Ok(Some(StorageEntry { key: H256::from_low_u64_be(1), value: 2.into() }))
+        );
+        assert!(tx.get::<tables::Receipts>(0).unwrap().is_some());
+
+        // outcomes above a bad block are discarded
+        stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 1, unwind_to: 0, bad_block: Some(1) })
+            .await
+            .unwrap();
+        assert!(stage.executed_blocks.get(&block.hash()).is_none());
+    }
+
+    #[tokio::test]
+    async fn refuse_deep_reorgs() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+        for block in random_block_range(0..4, H256::zero(), 0..1) {
+            insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+        }
+
+        let mut stage = ExecutionStage::default().with_max_reorg_depth(1);
+        let err = stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 3, unwind_to: 1, bad_block: None })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StageError>(),
+            Some(StageError::ReorgTooDeep { depth: 2, max_depth: 1 })
+        ));
+
+        // unwinds within the limit and below bad blocks are not refused
+        stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 3, unwind_to: 2, bad_block: None })
+            .await
+            .unwrap();
+        stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 2, unwind_to: 0, bad_block: Some(1) })
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn recover_pruned_senders() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+        let block = random_block(0, None, Some(4));
+        insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+
+        let body = tx.get_block_body_by_num(0).unwrap();
+        let expected = block
+            .body
+            .iter()
+            .map(|transaction| transaction.recover_signer().unwrap())
+            .collect::<Vec<_>>();
+        let mut senders = tx.cursor::<tables::TxSenders>().unwrap();
+        assert_eq!(block_senders(&mut senders, &body, &block.body).unwrap(), expected);
+        drop(senders);
+
+        // the senders of the first and third transaction were pruned
+        tx.delete::<tables::TxSenders>(body.start_tx_id, None).unwrap();
+        tx.delete::<tables::TxSenders>(body.start_tx_id + 2, None).unwrap();
+        let mut senders = tx.cursor::<tables::TxSenders>().unwrap();
+        assert_eq!(block_senders(&mut senders, &body, &block.body).unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn execute_blocks_on_top_of_batch() {
+        execute_on_top_of_batch(ExecutionStage::default()).await
+    }
+
+    #[tokio::test]
+    async fn prefetch_next_window_while_executing() {
+        // the state of the second block is read while the first block executes
+        let config = StateReaderConfig { workers: 2, queue_size: 1 };
+        let stage = ExecutionStage::default().with_prefetch_blocks(1).with_state_readers(config);
+        execute_on_top_of_batch(stage).await
+    }
+
+    async fn execute_on_top_of_batch(mut execution_stage: ExecutionStage) {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+        execution_stage.config.spec_upgrades = SpecUpgrades::new_paris_activated();
+
+        let secret = H256::from_low_u64_be(1);
+        let recipient = H160::from_low_u64_be(0x1000);
+        let transfer = |nonce| {
+            let transaction = reth_primitives::Transaction::Legacy(TxLegacy {
+                chain_id: Some(1),
+                nonce,
+                gas_price: 1,
+                gas_limit: 21_000,
+                to: TransactionKind::Call(recipient),
+                value: 1,
+                input: Default::default(),
+            });
+            let signature = sign_message(secret, transaction.signature_hash()).unwrap();
+            TransactionSigned::from_transaction_and_signature(transaction, signature)
+                .into_ecrecovered()
+                .unwrap()
+        };
+        let sender = transfer(0).signer();
+        let balance = U256::from(1_000_000u64);
+        let genesis = Header { gas_limit: 30_000_000, ..Default::default() }.seal();
+
+        // build two blocks with a transfer of the same sender each on a scratch database, so
+        // the second transaction is only valid on top of the state of the first block
+        let scratch = create_test_db::<WriteMap>(EnvKind::RW);
+        let scratch_tx = scratch.tx_mut().unwrap();
+        let account = Account { nonce: 0, balance, bytecode_hash: None };
+        scratch_tx.put::<tables::PlainAccountState>(sender, account).unwrap();
+        let mut parent = genesis.clone();
+        let mut blocks = Vec::new();
+        for nonce in 0..2 {
+            let attributes = BlockAttributes {
+                timestamp: parent.timestamp + 12,
+                beneficiary: Default::default(),
+                prev_randao: Default::default(),
+                gas_limit: 30_000_000,
+                base_fee_per_gas: None,
+                extra_data: Default::default(),
+            };
+            let state = State::new(StateProviderImplRefLatest::new(&scratch_tx));
+            let (block, result) = build_block(
+                &parent,
+                attributes,
+                vec![transfer(nonce)],
+                &execution_stage.config,
+                SubState::new(state),
+            )
+            .unwrap();
+            result.apply_to_db(&scratch_tx, nonce).unwrap();
+            parent = block.header.clone();
+            blocks.push(block);
+        }
+
+        let genesis = SealedBlock { header: genesis, body: vec![], ommers: vec![] };
+        insert_canonical_block(tx.deref_mut(), &genesis, false).unwrap();
+        for block in &blocks {
+            insert_canonical_block(tx.deref_mut(), block, false).unwrap();
+        }
+        tx.put::<tables::PlainAccountState>(sender, account).unwrap();
+        tx.commit().unwrap();
+
+        // both blocks are executed in the same batch
+        let input = ExecInput { previous_stage: None, stage_progress: None };
+        let output = execution_stage.execute(&mut tx, input).await.unwrap();
+        tx.commit().unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 2, done: true });
+
+        let fee = U256::from(21_000u64);
+        assert_eq!(
+            tx.get::<tables::PlainAccountState>(sender),
+            Ok(Some(Account {
+                nonce: 2,
+                balance: balance - (fee + U256::from(1u64)) * U256::from(2u64),
+                bytecode_hash: None
+            }))
+        );
+        assert_eq!(
+            tx.get::<tables::PlainAccountState>(recipient).unwrap().map(|acc| acc.balance),
+            Some(U256::from(2u64))
+        );
+        assert_eq!(tx.get::<tables::Receipts>(1).unwrap().map(|r| r.success), Some(true));
+    }
+
+    #[tokio::test]
+    async fn halt_before_transaction() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+        let mut execution_stage = ExecutionStage::default().with_halt_before(2, 1);
+        execution_stage.config.spec_upgrades = SpecUpgrades::new_paris_activated();
+
+        let secret = H256::from_low_u64_be(1);
+        let recipient = H160::from_low_u64_be(0x1000);
+        let transfer = |nonce| {
+            let transaction = reth_primitives::Transaction::Legacy(TxLegacy {
+                chain_id: Some(1),
+                nonce,
+                gas_price: 1,
+                gas_limit: 21_000,
+                to: TransactionKind::Call(recipient),
+                value: 1,
+                input: Default::default(),
+            });
+            let signature = sign_message(secret, transaction.signature_hash()).unwrap();
+            TransactionSigned::from_transaction_and_signature(transaction, signature)
+                .into_ecrecovered()
+                .unwrap()
+        };
+        let sender = transfer(0).signer();
+        let balance = U256::from(1_000_000u64);
+        let account = Account { nonce: 0, balance, bytecode_hash: None };
+        let genesis = Header { gas_limit: 30_000_000, ..Default::default() }.seal();
+
+        // the first block transfers once, the second one twice
+        let scratch = create_test_db::<WriteMap>(EnvKind::RW);
+        let scratch_tx = scratch.tx_mut().unwrap();
+        scratch_tx.put::<tables::PlainAccountState>(sender, account).unwrap();
+        let mut parent = genesis.clone();
+        let mut blocks = Vec::new();
+        for nonces in [0..1, 1..3] {
+            let attributes = BlockAttributes {
+                timestamp: parent.timestamp + 12,
+                beneficiary: Default::default(),
+                prev_randao: Default::default(),
+                gas_limit: 30_000_000,
+                base_fee_per_gas: None,
+                extra_data: Default::default(),
+            };
+            let transition = nonces.start;
+            let state = State::new(StateProviderImplRefLatest::new(&scratch_tx));
+            let (block, result) = build_block(
+                &parent,
+                attributes,
+                nonces.map(&transfer).collect(),
+                &execution_stage.config,
+                SubState::new(state),
+            )
+            .unwrap();
+            result.apply_to_db(&scratch_tx, transition).unwrap();
+            parent = block.header.clone();
+            blocks.push(block);
+        }
+
+        let genesis = SealedBlock { header: genesis, body: vec![], ommers: vec![] };
+        insert_canonical_block(tx.deref_mut(), &genesis, false).unwrap();
+        for block in &blocks {
+            insert_canonical_block(tx.deref_mut(), block, false).unwrap();
+        }
+        tx.put::<tables::PlainAccountState>(sender, account).unwrap();
+        tx.commit().unwrap();
+
+        // the blocks before the halting block are executed as usual
+        let input = ExecInput { previous_stage: Some((SENDER_RECOVERY, 2)), stage_progress: None };
+        let output = execution_stage.execute(&mut tx, input).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 1, done: false });
+
+        // only the first transaction of the halting block is applied, also by later runs
+        let fee = U256::from(21_000u64);
+        let halted_account = Account {
+            nonce: 2,
+            balance: balance - (fee + U256::from(1u64)) * U256::from(2u64),
+            bytecode_hash: None,
+        };
+        for _ in 0..2 {
+            let input =
+                ExecInput { previous_stage: Some((SENDER_RECOVERY, 2)), stage_progress: Some(1) };
+            let output = execution_stage.execute(&mut tx, input).await.unwrap();
+            tx.commit().unwrap();
+            assert_eq!(output, ExecOutput { stage_progress: 1, done: true });
+            assert_eq!(tx.get::<tables::PlainAccountState>(sender), Ok(Some(halted_account)));
+        }
+        assert!(tx.get::<tables::Receipts>(1).unwrap().is_some());
+        assert_eq!(tx.get::<tables::Receipts>(2), Ok(None));
+
+        // unwinding reverts the applied transactions of the halting block as well
+        execution_stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 1, unwind_to: 0, bad_block: None })
+            .await
+            .unwrap();
+        assert_eq!(tx.get::<tables::PlainAccountState>(sender), Ok(Some(account)));
+        assert_eq!(tx.get::<tables::PlainAccountState>(recipient), Ok(None));
+        assert_eq!(tx.get::<tables::Receipts>(1), Ok(None));
     }
 }
@@ -0,0 +1,249 @@
+use crate::{
+    db::Transaction, DatabaseIntegrityError, ExecInput, ExecOutput, Stage, StageError, StageId,
+    UnwindInput, UnwindOutput,
+};
+use reth_db::{cursor::DbCursorRO, database::Database, tables, transaction::DbTxMut};
+use reth_primitives::BlockNumber;
+use reth_provider::freezer::{Freezer, FrozenBlock, DEFAULT_FREEZER_DISTANCE};
+use std::sync::Arc;
+use tracing::*;
+
+/// The [`StageId`] of the freeze stage.
+pub const FREEZE: StageId = StageId("Freeze");
+
+/// The freeze stage moves the headers, bodies and receipts of old blocks from the database to the
+/// [`Freezer`].
+///
+/// Blocks older than the last `distance` blocks are considered final. The canonical hashes, the
+/// block body indices and the state of frozen blocks stay in the database, so they can still be
+/// looked up by hash and number.
+///
+/// The number of frozen blocks is recorded under [`FROZEN_BLOCKS_KEY`](tables::FROZEN_BLOCKS_KEY)
+/// in the same transaction that removes them from the database. Blocks that the freezer holds
+/// beyond it were appended before an unclean shutdown and are frozen again.
+#[derive(Debug)]
+pub struct FreezeStage {
+    /// The freezer to move the blocks to.
+    pub freezer: Arc<Freezer>,
+    /// The number of most recent blocks that stay in the database.
+    pub distance: u64,
+    /// The number of blocks after which the control flow is returned to the pipeline for commit.
+    pub commit_threshold: u64,
+}
+
+// === impl FreezeStage ===
+
+impl FreezeStage {
+    /// Creates a stage that keeps the last [`DEFAULT_FREEZER_DISTANCE`] blocks in the database.
+    pub fn new(freezer: Arc<Freezer>) -> Self {
+        Self { freezer, distance: DEFAULT_FREEZER_DISTANCE, commit_threshold: 10_000 }
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database> Stage<DB> for FreezeStage {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        FREEZE
+    }
+
+    /// Move the blocks that became final with the progress of the previous stage to the freezer.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let previous_stage_progress = input.previous_stage_progress();
+        let frozen = tx.get_frozen_blocks()?;
+        let appended = self.freezer.blocks();
+        if appended < frozen {
+            return Err(StageError::Fatal(
+                format!("The freezer holds {appended} blocks, but {frozen} were moved to it")
+                    .into(),
+            ))
+        }
+        if appended > frozen {
+            warn!(target: "sync::stages::freeze", frozen, appended, "Removing blocks the database still holds from the freezer");
+            self.freezer.truncate(frozen).map_err(|err| StageError::Fatal(err.into()))?;
+        }
+
+        let last_final = match previous_stage_progress.checked_sub(self.distance) {
+            Some(last_final) if last_final >= frozen => last_final,
+            _ => {
+                info!(target: "sync::stages::freeze", target = previous_stage_progress, frozen, "No blocks to freeze");
+                return Ok(ExecOutput { stage_progress: previous_stage_progress, done: true })
+            }
+        };
+        let last = last_final.min(frozen + self.commit_threshold.max(1) - 1);
+
+        info!(target: "sync::stages::freeze", from = frozen, to = last, "Freezing blocks");
+        for number in frozen..=last {
+            freeze_block(tx, &self.freezer, number)?;
+        }
+        // The blocks have to be durable before they're removed from the database.
+        self.freezer.sync().map_err(|err| StageError::Fatal(err.into()))?;
+        tx.put::<tables::Config>(
+            tables::FROZEN_BLOCKS_KEY.to_vec(),
+            (last + 1).to_be_bytes().to_vec(),
+        )?;
+
+        let done = last >= last_final;
+        let stage_progress =
+            if done { previous_stage_progress } else { input.stage_progress.unwrap_or_default() };
+        info!(target: "sync::stages::freeze", stage_progress, frozen = last + 1, done, "Sync iteration finished");
+        Ok(ExecOutput { stage_progress, done })
+    }
+
+    /// Unwind the stage.
+    async fn unwind(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        let frozen = tx.get_frozen_blocks()?;
+        if input.unwind_to + 1 < frozen {
+            return Err(StageError::BlocksFrozen { unwind_to: input.unwind_to, frozen }.into())
+        }
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+/// Appends the block to the freezer and removes it from the database.
+fn freeze_block<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    freezer: &Freezer,
+    number: BlockNumber,
+) -> Result<(), StageError> {
+    let key = tx.get_block_numhash(number)?;
+    let header = tx
+        .get::<tables::Headers>(key)?
+        .ok_or(DatabaseIntegrityError::Header { number, hash: key.hash() })?;
+    let ommers =
+        tx.get::<tables::BlockOmmers>(key)?.map(|stored| stored.ommers).unwrap_or_default();
+    let body = tx.get_block_body(key)?;
+    let transactions = body
+        .tx_id_range()
+        .map(|id| {
+            tx.get::<tables::Transactions>(id)?
+                .ok_or_else(|| DatabaseIntegrityError::Transaction { id }.into())
+        })
+        .collect::<Result<Vec<_>, StageError>>()?;
+    // pruned receipts are missing
+    let mut cursor = tx.cursor::<tables::Receipts>()?;
+    let receipts = cursor
+        .walk(body.start_tx_id)?
+        .take_while(|res| {
+            res.as_ref().map(|(id, _)| body.tx_id_range().contains(id)).unwrap_or_default()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    freezer
+        .append(number, FrozenBlock { header, ommers, transactions, receipts })
+        .map_err(|err| StageError::Fatal(err.into()))?;
+
+    tx.delete::<tables::Headers>(key, None)?;
+    tx.delete::<tables::BlockOmmers>(key, None)?;
+    for id in body.tx_id_range() {
+        tx.delete::<tables::Transactions>(id, None)?;
+        tx.delete::<tables::Receipts>(id, None)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        mdbx::{test_utils::create_test_db, EnvKind, WriteMap},
+        transaction::DbTx,
+    };
+    use reth_interfaces::test_utils::generators::random_block_range;
+    use reth_primitives::{Receipt, H256};
+    use reth_provider::insert_canonical_block;
+    use std::ops::DerefMut;
+
+    fn input(stage_progress: Option<u64>, target: u64) -> ExecInput {
+        ExecInput { previous_stage: Some((StageId("Prune"), target)), stage_progress }
+    }
+
+    #[tokio::test]
+    async fn freeze_final_blocks() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let blocks = random_block_range(0..10, H256::zero(), 1..3);
+        for block in blocks.iter() {
+            insert_canonical_block(tx.deref_mut(), block, false).unwrap();
+            let body = tx.get_block_body_by_num(block.number).unwrap();
+            for id in body.tx_id_range() {
+                tx.put::<tables::Receipts>(id, Receipt::default()).unwrap();
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let freezer = Arc::new(Freezer::open(dir.path()).unwrap());
+        let mut stage = FreezeStage { freezer: freezer.clone(), distance: 3, commit_threshold: 4 };
+
+        // blocks #0 to #6 are final, at most 4 are frozen at once
+        let output = stage.execute(&mut tx, input(None, 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 0, done: false });
+        assert_eq!(tx.get_frozen_blocks().unwrap(), 4);
+        let output = stage.execute(&mut tx, input(Some(0), 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 9, done: true });
+        assert_eq!(tx.get_frozen_blocks().unwrap(), 7);
+        assert_eq!(freezer.blocks(), 7);
+
+        for block in blocks.iter() {
+            let key = (block.number, block.hash()).into();
+            let body = tx.get_block_body(key).unwrap();
+            let in_db = tx.get::<tables::Headers>(key).unwrap().is_some();
+            let receipts = body
+                .tx_id_range()
+                .filter(|id| tx.get::<tables::Receipts>(*id).unwrap().is_some())
+                .count();
+            if block.number < 7 {
+                assert!(!in_db);
+                assert_eq!(receipts, 0);
+                assert_eq!(freezer.header(block.number).unwrap().as_ref(), Some(&*block.header));
+                let (transactions, _) = freezer.body(block.number).unwrap().unwrap();
+                assert_eq!(transactions, block.body);
+                assert_eq!(
+                    freezer.receipts(block.number).unwrap().unwrap().len(),
+                    block.body.len()
+                );
+            } else {
+                assert!(in_db);
+                assert_eq!(receipts, block.body.len());
+            }
+        }
+
+        // the frozen blocks can't be unwound
+        let err = stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 9, unwind_to: 5, bad_block: None })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Can not unwind to block #5, the blocks before #7 are frozen.");
+    }
+
+    #[tokio::test]
+    async fn refreeze_blocks_after_unclean_shutdown() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+        for block in random_block_range(0..5, H256::zero(), 1..3) {
+            insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let freezer = Arc::new(Freezer::open(dir.path()).unwrap());
+        // block #0 was appended, but the transaction that removes it was never committed
+        freezer.append(0, FrozenBlock::default()).unwrap();
+
+        let mut stage = FreezeStage { freezer: freezer.clone(), distance: 3, commit_threshold: 10 };
+        let output = stage.execute(&mut tx, input(None, 4)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 4, done: true });
+        assert_eq!(freezer.blocks(), 2);
+        let key = tx.get_block_numhash(0).unwrap();
+        let header = freezer.header(0).unwrap().unwrap();
+        assert_eq!(header.hash_slow(), key.hash());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{
+    db::Transaction, ExecInput, ExecOutput, Stage, StageError, StageId, UnwindInput, UnwindOutput,
+};
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    models::AccountBeforeTx,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{keccak256, Account, Address};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::*;
+
+/// The [`StageId`] of the account hashing stage.
+pub const ACCOUNT_HASHING: StageId = StageId("AccountHashing");
+
+/// The account hashing stage hashes the addresses of the
+/// [`PlainAccountState`][tables::PlainAccountState] and stores the accounts in the
+/// [`HashedAccount`][tables::HashedAccount] table.
+///
+/// On the first run, or if the range of blocks to process exceeds the
+/// [`clean_threshold`](AccountHashingStage::clean_threshold), the hashed table is rebuilt from the
+/// entire plain state. Otherwise only the accounts changed within the range, as recorded in the
+/// [`AccountChangeSet`][tables::AccountChangeSet] table, are rehashed.
+///
+/// The entire plain state is rehashed in chunks of
+/// [`commit_threshold`](AccountHashingStage::commit_threshold) accounts. The last hashed account is
+/// checkpointed under [`ACCOUNT_HASHING_CHECKPOINT_KEY`][tables::ACCOUNT_HASHING_CHECKPOINT_KEY],
+/// so the next iteration continues after it.
+#[derive(Debug)]
+pub struct AccountHashingStage {
+    /// The number of blocks after which the entire plain state is rehashed instead of applying
+    /// the changesets of the range.
+    pub clean_threshold: u64,
+    /// The number of accounts rehashed from the entire plain state after which the control flow
+    /// is returned to the pipeline for commit.
+    pub commit_threshold: u64,
+}
+
+impl Default for AccountHashingStage {
+    fn default() -> Self {
+        Self { clean_threshold: 500_000, commit_threshold: 100_000 }
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database> Stage<DB> for AccountHashingStage {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        ACCOUNT_HASHING
+    }
+
+    /// Hash the accounts that changed between the stage progress and the progress of the previous
+    /// stage.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = input.stage_progress.unwrap_or_default();
+        let previous_stage_progress = input.previous_stage_progress();
+
+        if previous_stage_progress <= stage_progress {
+            info!(target: "sync::stages::account_hashing", target = previous_stage_progress, stage_progress, "Target block already reached");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        let checkpoint =
+            tx.get::<tables::Config>(tables::ACCOUNT_HASHING_CHECKPOINT_KEY.to_vec())?;
+        if checkpoint.is_some() ||
+            stage_progress == 0 ||
+            previous_stage_progress - stage_progress > self.clean_threshold
+        {
+            let mut plain_accounts = tx.cursor::<tables::PlainAccountState>()?;
+            let mut next = match checkpoint.filter(|checkpoint| !checkpoint.is_empty()) {
+                Some(checkpoint) => {
+                    if checkpoint.len() != Address::len_bytes() {
+                        return Err(reth_db::Error::DecodeError.into())
+                    }
+                    let last = Address::from_slice(&checkpoint);
+                    info!(target: "sync::stages::account_hashing", ?last, "Continuing to rehash the entire account state");
+                    let entry = plain_accounts.walk(last)?.next().transpose()?;
+                    match entry {
+                        Some((address, _)) if address == last => plain_accounts.next()?,
+                        entry => entry,
+                    }
+                }
+                None => {
+                    info!(target: "sync::stages::account_hashing", stage_progress, target = previous_stage_progress, "Rehashing the entire account state");
+                    tx.clear::<tables::HashedAccount>()?;
+                    plain_accounts.first()?
+                }
+            };
+
+            let mut last = None;
+            for _ in 0..self.commit_threshold.max(1) {
+                let Some((address, account)) = next else { break };
+                tx.put::<tables::HashedAccount>(keccak256(address), account)?;
+                last = Some(address);
+                next = plain_accounts.next()?;
+            }
+
+            if next.is_some() {
+                let last = last.expect("at least one account is hashed per chunk; qed");
+                tx.put::<tables::Config>(
+                    tables::ACCOUNT_HASHING_CHECKPOINT_KEY.to_vec(),
+                    last.as_bytes().to_vec(),
+                )?;
+                info!(target: "sync::stages::account_hashing", ?last, "Rehashed a chunk of the account state");
+                return Ok(ExecOutput { stage_progress, done: false })
+            }
+            tx.delete::<tables::Config>(tables::ACCOUNT_HASHING_CHECKPOINT_KEY.to_vec(), None)?;
+        } else {
+            let from_transition = tx.get_block_transition_by_num(stage_progress)? + 1;
+            let to_transition = tx.get_block_transition_by_num(previous_stage_progress)?;
+
+            let mut changesets = tx.cursor_dup::<tables::AccountChangeSet>()?;
+            let addresses = changesets
+                .walk_range(from_transition..=to_transition)?
+                .map(|res| res.map(|(_, changeset)| changeset.address))
+                .collect::<Result<BTreeSet<_>, _>>()?;
+
+            info!(target: "sync::stages::account_hashing", from_transition, to_transition, changed = addresses.len(), "Rehashing changed accounts");
+            for address in addresses {
+                let account = tx.get::<tables::PlainAccountState>(address)?;
+                write_hashed_account(tx, address, account)?;
+            }
+        }
+
+        info!(target: "sync::stages::account_hashing", stage_progress = previous_stage_progress, "Sync iteration finished");
+        Ok(ExecOutput { stage_progress: previous_stage_progress, done: true })
+    }
+
+    /// Unwind the stage.
+    async fn unwind(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        let key = tables::ACCOUNT_HASHING_CHECKPOINT_KEY.to_vec();
+        if tx.get::<tables::Config>(key.clone())?.is_some() {
+            // The changesets can't be applied to the partially rehashed state, so the unfinished
+            // run starts over from the first account.
+            info!(target: "sync::stages::account_hashing", unwind_to = input.unwind_to, "Restarting the rehash of the entire account state");
+            tx.put::<tables::Config>(key, Vec::new())?;
+            return Ok(UnwindOutput { stage_progress: input.unwind_to })
+        }
+
+        let from_transition = tx.get_block_transition_by_num(input.unwind_to)? + 1;
+        let to_transition = tx.get_block_transition_by_num(input.stage_progress)?;
+
+        // The first changeset of an account within the range holds its state at `unwind_to`.
+        let mut changesets = tx.cursor_dup::<tables::AccountChangeSet>()?;
+        let mut accounts = BTreeMap::new();
+        for entry in changesets.walk_range(from_transition..=to_transition)? {
+            let (_, AccountBeforeTx { address, info }) = entry?;
+            accounts.entry(address).or_insert(info);
+        }
+
+        for (address, account) in accounts {
+            write_hashed_account(tx, address, account)?;
+        }
+
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+/// Writes the account to the [`HashedAccount`][tables::HashedAccount] table or removes it if the
+/// account does not exist.
+fn write_hashed_account<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    address: Address,
+    account: Option<Account>,
+) -> Result<(), reth_db::Error> {
+    let hashed_address = keccak256(address);
+    if let Some(account) = account {
+        tx.put::<tables::HashedAccount>(hashed_address, account)?;
+    } else {
+        tx.delete::<tables::HashedAccount>(hashed_address, None)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::mdbx::{test_utils::create_test_db, EnvKind, WriteMap};
+    use reth_interfaces::test_utils::generators::random_block;
+    use reth_primitives::H160;
+    use reth_provider::insert_canonical_block;
+    use std::ops::DerefMut;
+
+    fn input(stage_progress: Option<u64>, target: u64) -> ExecInput {
+        ExecInput { previous_stage: Some((StageId("Execution"), target)), stage_progress }
+    }
+
+    #[tokio::test]
+    async fn clean_hashing_rehashes_plain_state() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let accounts = (1..=3u64)
+            .map(|i| (H160::from_low_u64_be(i), Account { nonce: i, ..Default::default() }))
+            .collect::<Vec<_>>();
+        for (address, account) in accounts.iter() {
+            tx.put::<tables::PlainAccountState>(*address, *account).unwrap();
+        }
+        // stale entry that is removed by the full rehash
+        tx.put::<tables::HashedAccount>(keccak256(H160::zero()), Account::default()).unwrap();
+
+        let output =
+            AccountHashingStage::default().execute(&mut tx, input(None, 10)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 10, done: true });
+
+        assert_eq!(tx.get::<tables::HashedAccount>(keccak256(H160::zero())), Ok(None));
+        for (address, account) in accounts {
+            assert_eq!(tx.get::<tables::HashedAccount>(keccak256(address)), Ok(Some(account)));
+        }
+    }
+
+    #[tokio::test]
+    async fn clean_hashing_in_chunks() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let accounts = (1..=5u64)
+            .map(|i| (H160::from_low_u64_be(i), Account { nonce: i, ..Default::default() }))
+            .collect::<Vec<_>>();
+        for (address, account) in accounts.iter() {
+            tx.put::<tables::PlainAccountState>(*address, *account).unwrap();
+        }
+        tx.put::<tables::HashedAccount>(keccak256(H160::zero()), Account::default()).unwrap();
+
+        let mut stage = AccountHashingStage { commit_threshold: 2, ..Default::default() };
+        let mut stage_progress = None;
+        for (hashed, done) in [(2, false), (4, false), (5, true)] {
+            let output = stage.execute(&mut tx, input(stage_progress, 10)).await.unwrap();
+            assert_eq!(output, ExecOutput { stage_progress: if done { 10 } else { 0 }, done });
+            stage_progress = Some(output.stage_progress);
+
+            // the stale entry is only removed at the start of the run
+            assert_eq!(tx.get::<tables::HashedAccount>(keccak256(H160::zero())), Ok(None));
+            for (i, (address, account)) in accounts.iter().enumerate() {
+                let expected = (i < hashed).then_some(*account);
+                assert_eq!(tx.get::<tables::HashedAccount>(keccak256(address)), Ok(expected));
+            }
+        }
+        assert_eq!(
+            tx.get::<tables::Config>(tables::ACCOUNT_HASHING_CHECKPOINT_KEY.to_vec()),
+            Ok(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn incremental_hashing_and_unwind() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let genesis = random_block(0, None, Some(0));
+        let block1 = random_block(1, Some(genesis.hash()), Some(0));
+        let block2 = random_block(2, Some(block1.hash()), Some(0));
+        for block in [&genesis, &block1, &block2] {
+            insert_canonical_block(tx.deref_mut(), block, true).unwrap();
+        }
+
+        let changed = H160::from_low_u64_be(1);
+        let created = H160::from_low_u64_be(2);
+        let old = Account { nonce: 1, ..Default::default() };
+        let new = Account { nonce: 2, ..Default::default() };
+
+        // state after block #1
+        tx.put::<tables::HashedAccount>(keccak256(changed), old).unwrap();
+
+        // changes of block #2
+        let transition = tx.get_block_transition_by_num(1).unwrap() + 1;
+        tx.put::<tables::AccountChangeSet>(
+            transition,
+            AccountBeforeTx { address: changed, info: Some(old) },
+        )
+        .unwrap();
+        tx.put::<tables::AccountChangeSet>(
+            transition,
+            AccountBeforeTx { address: created, info: None },
+        )
+        .unwrap();
+        tx.put::<tables::PlainAccountState>(changed, new).unwrap();
+        tx.put::<tables::PlainAccountState>(created, new).unwrap();
+
+        let mut stage = AccountHashingStage::default();
+        let output = stage.execute(&mut tx, input(Some(1), 2)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 2, done: true });
+        assert_eq!(tx.get::<tables::HashedAccount>(keccak256(changed)), Ok(Some(new)));
+        assert_eq!(tx.get::<tables::HashedAccount>(keccak256(created)), Ok(Some(new)));
+
+        let output = stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 2, unwind_to: 1, bad_block: None })
+            .await
+            .unwrap();
+        assert_eq!(output, UnwindOutput { stage_progress: 1 });
+        assert_eq!(tx.get::<tables::HashedAccount>(keccak256(changed)), Ok(Some(old)));
+        assert_eq!(tx.get::<tables::HashedAccount>(keccak256(created)), Ok(None));
+    }
+}
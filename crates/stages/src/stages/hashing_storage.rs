@@ -0,0 +1,350 @@
+use crate::{
+    db::Transaction, ExecInput, ExecOutput, Stage, StageError, StageId, UnwindInput, UnwindOutput,
+};
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{keccak256, Address, StorageEntry, H256, U256};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::*;
+
+/// The [`StageId`] of the storage hashing stage.
+pub const STORAGE_HASHING: StageId = StageId("StorageHashing");
+
+/// The storage hashing stage hashes the addresses and keys of the
+/// [`PlainStorageState`][tables::PlainStorageState] and stores the slots in the
+/// [`HashedStorage`][tables::HashedStorage] table.
+///
+/// On the first run, or if the range of blocks to process exceeds the
+/// [`clean_threshold`](StorageHashingStage::clean_threshold), the hashed table is rebuilt from the
+/// entire plain state. Otherwise only the slots changed within the range, as recorded in the
+/// [`StorageChangeSet`][tables::StorageChangeSet] table, are rehashed.
+///
+/// The entire plain state is rehashed in chunks of
+/// [`commit_threshold`](StorageHashingStage::commit_threshold) slots. The last hashed slot is
+/// checkpointed under [`STORAGE_HASHING_CHECKPOINT_KEY`][tables::STORAGE_HASHING_CHECKPOINT_KEY],
+/// so the next iteration continues after it.
+#[derive(Debug)]
+pub struct StorageHashingStage {
+    /// The number of blocks after which the entire plain state is rehashed instead of applying
+    /// the changesets of the range.
+    pub clean_threshold: u64,
+    /// The number of slots rehashed from the entire plain state after which the control flow is
+    /// returned to the pipeline for commit.
+    pub commit_threshold: u64,
+}
+
+impl Default for StorageHashingStage {
+    fn default() -> Self {
+        Self { clean_threshold: 500_000, commit_threshold: 100_000 }
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database> Stage<DB> for StorageHashingStage {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        STORAGE_HASHING
+    }
+
+    /// Hash the storage slots that changed between the stage progress and the progress of the
+    /// previous stage.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = input.stage_progress.unwrap_or_default();
+        let previous_stage_progress = input.previous_stage_progress();
+
+        if previous_stage_progress <= stage_progress {
+            info!(target: "sync::stages::storage_hashing", target = previous_stage_progress, stage_progress, "Target block already reached");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        let checkpoint =
+            tx.get::<tables::Config>(tables::STORAGE_HASHING_CHECKPOINT_KEY.to_vec())?;
+        if checkpoint.is_some() ||
+            stage_progress == 0 ||
+            previous_stage_progress - stage_progress > self.clean_threshold
+        {
+            let mut plain_storage = tx.cursor_dup::<tables::PlainStorageState>()?;
+            let mut next = match checkpoint.filter(|checkpoint| !checkpoint.is_empty()) {
+                Some(checkpoint) => {
+                    if checkpoint.len() != Address::len_bytes() + H256::len_bytes() {
+                        return Err(reth_db::Error::DecodeError.into())
+                    }
+                    let (address, key) = checkpoint.split_at(Address::len_bytes());
+                    let (address, key) = (Address::from_slice(address), H256::from_slice(key));
+                    info!(target: "sync::stages::storage_hashing", ?address, ?key, "Continuing to rehash the entire storage state");
+                    match plain_storage.seek_by_key_subkey(address, key)? {
+                        Some(entry) if entry.key == key => plain_storage.next()?,
+                        Some(entry) => Some((address, entry)),
+                        // no slots of the address after the checkpoint, continue with the next one
+                        None => match plain_storage.seek(address)? {
+                            Some((found, _)) if found == address => plain_storage.next_no_dup()?,
+                            entry => entry,
+                        },
+                    }
+                }
+                None => {
+                    info!(target: "sync::stages::storage_hashing", stage_progress, target = previous_stage_progress, "Rehashing the entire storage state");
+                    tx.clear::<tables::HashedStorage>()?;
+                    plain_storage.first()?
+                }
+            };
+
+            let mut last = None;
+            for _ in 0..self.commit_threshold.max(1) {
+                let Some((address, entry)) = next else { break };
+                tx.put::<tables::HashedStorage>(
+                    keccak256(address),
+                    StorageEntry { key: keccak256(entry.key), value: entry.value },
+                )?;
+                last = Some((address, entry.key));
+                next = plain_storage.next()?;
+            }
+
+            if next.is_some() {
+                let (address, key) = last.expect("at least one slot is hashed per chunk; qed");
+                tx.put::<tables::Config>(
+                    tables::STORAGE_HASHING_CHECKPOINT_KEY.to_vec(),
+                    [address.as_bytes(), key.as_bytes()].concat(),
+                )?;
+                info!(target: "sync::stages::storage_hashing", ?address, ?key, "Rehashed a chunk of the storage state");
+                return Ok(ExecOutput { stage_progress, done: false })
+            }
+            tx.delete::<tables::Config>(tables::STORAGE_HASHING_CHECKPOINT_KEY.to_vec(), None)?;
+        } else {
+            let from_transition = tx.get_block_transition_by_num(stage_progress)? + 1;
+            let to_transition = tx.get_block_transition_by_num(previous_stage_progress)?;
+
+            let mut changesets = tx.cursor_dup::<tables::StorageChangeSet>()?;
+            let slots = changesets
+                .walk((from_transition, Address::zero()).into())?
+                .take_while(|res| {
+                    res.as_ref()
+                        .map(|(k, _)| k.transition_id() <= to_transition)
+                        .unwrap_or_default()
+                })
+                .map(|res| res.map(|(k, entry)| (k.address(), entry.key)))
+                .collect::<Result<BTreeSet<_>, _>>()?;
+
+            info!(target: "sync::stages::storage_hashing", from_transition, to_transition, changed = slots.len(), "Rehashing changed storage slots");
+            let mut plain_storage = tx.cursor_dup::<tables::PlainStorageState>()?;
+            for (address, key) in slots {
+                let value = plain_storage
+                    .seek_by_key_subkey(address, key)?
+                    .filter(|entry| entry.key == key)
+                    .map(|entry| entry.value)
+                    .unwrap_or_default();
+                write_hashed_slot(tx, address, key, value)?;
+            }
+        }
+
+        info!(target: "sync::stages::storage_hashing", stage_progress = previous_stage_progress, "Sync iteration finished");
+        Ok(ExecOutput { stage_progress: previous_stage_progress, done: true })
+    }
+
+    /// Unwind the stage.
+    async fn unwind(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        let key = tables::STORAGE_HASHING_CHECKPOINT_KEY.to_vec();
+        if tx.get::<tables::Config>(key.clone())?.is_some() {
+            // The changesets can't be applied to the partially rehashed state, so the unfinished
+            // run starts over from the first slot.
+            info!(target: "sync::stages::storage_hashing", unwind_to = input.unwind_to, "Restarting the rehash of the entire storage state");
+            tx.put::<tables::Config>(key, Vec::new())?;
+            return Ok(UnwindOutput { stage_progress: input.unwind_to })
+        }
+
+        let from_transition = tx.get_block_transition_by_num(input.unwind_to)? + 1;
+        let to_transition = tx.get_block_transition_by_num(input.stage_progress)?;
+
+        // The first changeset of a slot within the range holds its value at `unwind_to`.
+        let mut changesets = tx.cursor_dup::<tables::StorageChangeSet>()?;
+        let mut slots = BTreeMap::new();
+        for entry in changesets.walk((from_transition, Address::zero()).into())?.take_while(|res| {
+            res.as_ref().map(|(k, _)| k.transition_id() <= to_transition).unwrap_or_default()
+        }) {
+            let (key, entry) = entry?;
+            slots.entry((key.address(), entry.key)).or_insert(entry.value);
+        }
+
+        for ((address, key), value) in slots {
+            write_hashed_slot(tx, address, key, value)?;
+        }
+
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+/// Replaces the slot in the [`HashedStorage`][tables::HashedStorage] table or removes it if the
+/// value is zero.
+fn write_hashed_slot<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    address: Address,
+    key: H256,
+    value: U256,
+) -> Result<(), reth_db::Error> {
+    let hashed_address = keccak256(address);
+    let hashed_key = keccak256(key);
+
+    // Always delete the old value as duplicate table put will not override it
+    let existing = tx
+        .cursor_dup::<tables::HashedStorage>()?
+        .seek_by_key_subkey(hashed_address, hashed_key)?
+        .filter(|entry| entry.key == hashed_key);
+    if let Some(existing) = existing {
+        tx.delete::<tables::HashedStorage>(hashed_address, Some(existing))?;
+    }
+    if !value.is_zero() {
+        tx.put::<tables::HashedStorage>(hashed_address, StorageEntry { key: hashed_key, value })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        mdbx::{test_utils::create_test_db, EnvKind, WriteMap},
+        models::TransitionIdAddress,
+    };
+    use reth_interfaces::test_utils::generators::random_block;
+    use reth_primitives::H160;
+    use reth_provider::insert_canonical_block;
+    use std::ops::DerefMut;
+
+    fn input(stage_progress: Option<u64>, target: u64) -> ExecInput {
+        ExecInput { previous_stage: Some((StageId("Execution"), target)), stage_progress }
+    }
+
+    fn hashed_slot<DB: Database>(
+        tx: &Transaction<'_, DB>,
+        address: Address,
+        key: H256,
+    ) -> Option<U256> {
+        let hashed_key = keccak256(key);
+        tx.cursor_dup::<tables::HashedStorage>()
+            .unwrap()
+            .seek_by_key_subkey(keccak256(address), hashed_key)
+            .unwrap()
+            .filter(|entry| entry.key == hashed_key)
+            .map(|entry| entry.value)
+    }
+
+    #[tokio::test]
+    async fn clean_hashing_rehashes_plain_state() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let address = H160::from_low_u64_be(1);
+        let slots =
+            (1..=3u64).map(|i| (H256::from_low_u64_be(i), U256::from(i))).collect::<Vec<_>>();
+        for (key, value) in slots.iter() {
+            tx.put::<tables::PlainStorageState>(address, StorageEntry { key: *key, value: *value })
+                .unwrap();
+        }
+
+        let output =
+            StorageHashingStage::default().execute(&mut tx, input(None, 10)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 10, done: true });
+
+        for (key, value) in slots {
+            assert_eq!(hashed_slot(&tx, address, key), Some(value));
+        }
+    }
+
+    #[tokio::test]
+    async fn clean_hashing_in_chunks() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        // the chunks end within and at the end of the slots of an address
+        let slots = [(1, 1), (1, 2), (1, 3), (2, 1), (3, 1)]
+            .map(|(address, key)| (H160::from_low_u64_be(address), H256::from_low_u64_be(key)));
+        for (address, key) in slots {
+            tx.put::<tables::PlainStorageState>(
+                address,
+                StorageEntry { key, value: U256::from(1) },
+            )
+            .unwrap();
+        }
+
+        let mut stage = StorageHashingStage { commit_threshold: 2, ..Default::default() };
+        let mut stage_progress = None;
+        for (hashed, done) in [(2, false), (4, false), (5, true)] {
+            let output = stage.execute(&mut tx, input(stage_progress, 10)).await.unwrap();
+            assert_eq!(output, ExecOutput { stage_progress: if done { 10 } else { 0 }, done });
+            stage_progress = Some(output.stage_progress);
+
+            for (i, (address, key)) in slots.into_iter().enumerate() {
+                let expected = (i < hashed).then_some(U256::from(1));
+                assert_eq!(hashed_slot(&tx, address, key), expected);
+            }
+        }
+        assert_eq!(
+            tx.get::<tables::Config>(tables::STORAGE_HASHING_CHECKPOINT_KEY.to_vec()),
+            Ok(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn incremental_hashing_and_unwind() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let genesis = random_block(0, None, Some(0));
+        let block1 = random_block(1, Some(genesis.hash()), Some(0));
+        let block2 = random_block(2, Some(block1.hash()), Some(0));
+        for block in [&genesis, &block1, &block2] {
+            insert_canonical_block(tx.deref_mut(), block, true).unwrap();
+        }
+
+        let address = H160::from_low_u64_be(1);
+        let changed = H256::from_low_u64_be(1);
+        let created = H256::from_low_u64_be(2);
+        let cleared = H256::from_low_u64_be(3);
+
+        // state after block #1
+        for (key, value) in [(changed, U256::from(1)), (cleared, U256::from(3))] {
+            write_hashed_slot(&tx, address, key, value).unwrap();
+        }
+
+        // changes of block #2
+        let transition = tx.get_block_transition_by_num(1).unwrap() + 1;
+        let changeset_key = TransitionIdAddress((transition, address));
+        for (key, value) in
+            [(changed, U256::from(1)), (created, U256::zero()), (cleared, U256::from(3))]
+        {
+            tx.put::<tables::StorageChangeSet>(changeset_key.clone(), StorageEntry { key, value })
+                .unwrap();
+        }
+        for (key, value) in [(changed, U256::from(10)), (created, U256::from(20))] {
+            tx.put::<tables::PlainStorageState>(address, StorageEntry { key, value }).unwrap();
+        }
+
+        let mut stage = StorageHashingStage::default();
+        let output = stage.execute(&mut tx, input(Some(1), 2)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 2, done: true });
+        assert_eq!(hashed_slot(&tx, address, changed), Some(U256::from(10)));
+        assert_eq!(hashed_slot(&tx, address, created), Some(U256::from(20)));
+        assert_eq!(hashed_slot(&tx, address, cleared), None);
+
+        let output = stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 2, unwind_to: 1, bad_block: None })
+            .await
+            .unwrap();
+        assert_eq!(output, UnwindOutput { stage_progress: 1 });
+        assert_eq!(hashed_slot(&tx, address, changed), Some(U256::from(1)));
+        assert_eq!(hashed_slot(&tx, address, created), None);
+        assert_eq!(hashed_slot(&tx, address, cleared), Some(U256::from(3)));
+    }
+}
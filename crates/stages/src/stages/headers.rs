@@ -25,7 +25,8 @@ use stages_metrics::HeaderMetrics;
 use std::{fmt::Debug, sync::Arc};
 use tracing::*;
 
-const HEADERS: StageId = StageId("Headers");
+/// The [`StageId`] of the headers stage.
+pub const HEADERS: StageId = StageId("Headers");
 
 /// The headers stage.
 ///
@@ -41,7 +42,9 @@ const HEADERS: StageId = StageId("Headers");
 ///
 /// NOTE: This stage commits the header changes to the database (everything except the changes to
 /// [`HeaderTD`][reth_interfaces::db::tables::HeaderTD] table). The stage does not return the
-/// control flow to the pipeline in order to preserve the context of the chain tip.
+/// control flow to the pipeline in order to preserve the context of the chain tip. The batches of
+/// headers are committed as they are downloaded, except for the last one that connects to the
+/// local head, so an interrupted download leaves a gap that is filled when the stage runs again.
 #[derive(Debug)]
 pub struct HeaderStage<D: HeaderDownloader, C: Consensus, H: HeadersClient, S: StatusUpdater> {
     /// Strategy for downloading the headers
@@ -95,9 +98,17 @@ impl<DB: Database, D: HeaderDownloader, C: Consensus, H: HeadersClient, S: Statu
 
                     // Perform basic response validation
                     self.validate_header_response(&res)?;
+                    let connects_to_head =
+                        res.last().map_or(true, |header| header.number <= stage_progress + 1);
                     let write_progress =
                         self.write_headers::<DB>(tx, res).await?.unwrap_or_default();
                     current_progress = current_progress.max(write_progress);
+
+                    // The headers above a gap are kept if the download is interrupted, the gap is
+                    // downloaded once the stage runs again.
+                    if !connects_to_head {
+                        tx.commit()?;
+                    }
                 }
                 Err(e) => {
                     self.metrics.update_headers_error_metrics(&e);
@@ -266,7 +277,8 @@ impl<D: HeaderDownloader, C: Consensus, H: HeadersClient, S: StatusUpdater>
         Ok(latest)
     }
 
-    /// Iterate over inserted headers and write td entries
+    /// Iterate over inserted headers, validate them against the total difficulty of their parent
+    /// and write td entries
     fn write_td<DB: Database>(
         &self,
         tx: &Transaction<'_, DB>,
@@ -287,6 +299,10 @@ impl<D: HeaderDownloader, C: Consensus, H: HeadersClient, S: StatusUpdater>
         // Walk over newly inserted headers, update & insert td
         for entry in tx.cursor::<tables::Headers>()?.walk(start_key)? {
             let (key, header) = entry?;
+            let header = SealedHeader::new(header, key.hash());
+            self.consensus
+                .validate_header_with_total_difficulty(&header, td)
+                .map_err(|error| StageError::Validation { block: header.number, error })?;
             td += header.difficulty;
             cursor_td.append(key, td.into())?;
         }
@@ -0,0 +1,207 @@
+use crate::{
+    db::Transaction,
+    stages::log_index::{append_to_shards, remove_from_shards, BlocksByKey},
+    ExecInput, ExecOutput, Stage, StageError, StageId, UnwindInput, UnwindOutput,
+};
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    models::storage_history_key,
+    tables::{self, AddressStorageKey},
+    transaction::DbTx,
+};
+use reth_primitives::Address;
+use std::ops::RangeInclusive;
+use tracing::*;
+
+/// The [`StageId`] of the history index stage.
+pub const HISTORY_INDEX: StageId = StageId("HistoryIndex");
+
+/// The history index stage indexes the changesets of the executed blocks.
+///
+/// For every account and storage slot, the transitions that changed it are stored in the
+/// [`AccountHistory`][tables::AccountHistory] and [`StorageHistory`][tables::StorageHistory]
+/// tables, sharded by ranges of [`shard_size`](HistoryIndexStage::shard_size) transitions. The
+/// state at an old block is then read from the changeset of the first change after the block,
+/// instead of walking all changesets since the block.
+#[derive(Debug)]
+pub struct HistoryIndexStage {
+    /// The number of transitions of a shard. This must not change for an existing database.
+    pub shard_size: u64,
+    /// The number of blocks after which the control flow is returned to the pipeline for commit.
+    pub commit_threshold: u64,
+}
+
+impl Default for HistoryIndexStage {
+    fn default() -> Self {
+        Self { shard_size: 1_000_000, commit_threshold: 100_000 }
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database> Stage<DB> for HistoryIndexStage {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        HISTORY_INDEX
+    }
+
+    /// Index the changesets of the blocks between the stage progress and the progress of the
+    /// previous stage.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = input.stage_progress.unwrap_or_default();
+        let previous_stage_progress = input.previous_stage_progress();
+        let max_block_num = previous_stage_progress.min(stage_progress + self.commit_threshold);
+
+        if max_block_num <= stage_progress {
+            info!(target: "sync::stages::history_index", target = max_block_num, stage_progress, "Target block already reached");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        let (accounts, storages) = changed_keys(tx, stage_progress + 1..=max_block_num)?;
+        info!(target: "sync::stages::history_index", from = stage_progress + 1, to = max_block_num, accounts = accounts.len(), storages = storages.len(), "Indexing changesets");
+        append_to_shards::<DB, tables::AccountHistory, _>(tx, self.shard_size, accounts)?;
+        append_to_shards::<DB, tables::StorageHistory, _>(tx, self.shard_size, storages)?;
+
+        let done = max_block_num >= previous_stage_progress;
+        info!(target: "sync::stages::history_index", stage_progress = max_block_num, done, "Sync iteration finished");
+        Ok(ExecOutput { stage_progress: max_block_num, done })
+    }
+
+    /// Unwind the stage.
+    async fn unwind(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        // The changesets are unwound by the execution stage, which runs after this one on unwinds.
+        let (accounts, storages) = changed_keys(tx, input.unwind_to + 1..=input.stage_progress)?;
+        let last_kept = tx.get_block_transition_by_num(input.unwind_to)?;
+        remove_from_shards::<DB, tables::AccountHistory, _>(tx, last_kept, accounts.into_keys())?;
+        remove_from_shards::<DB, tables::StorageHistory, _>(tx, last_kept, storages.into_keys())?;
+
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+/// Collects the accounts and storage slots that changed in the blocks of the range, each of them
+/// with the ascending transitions that changed it. The changesets hold at most one entry of an
+/// account or slot per transition.
+fn changed_keys<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    range: RangeInclusive<u64>,
+) -> Result<(BlocksByKey<Address>, BlocksByKey<AddressStorageKey>), StageError> {
+    if range.is_empty() {
+        return Ok(Default::default())
+    }
+    let (_, first) = tx.get_next_block_ids(*range.start())?;
+    let last = tx.get_block_transition_by_num(*range.end())?;
+
+    let mut accounts = BlocksByKey::<Address>::new();
+    let mut account_changesets = tx.cursor::<tables::AccountChangeSet>()?;
+    for entry in account_changesets.walk_range(first..last + 1)? {
+        let (transition, before) = entry?;
+        accounts.entry(before.address).or_default().push(transition);
+    }
+
+    let mut storages = BlocksByKey::<AddressStorageKey>::new();
+    let mut storage_changesets = tx.cursor::<tables::StorageChangeSet>()?;
+    let storage_changes =
+        storage_changesets.walk((first, Address::zero()).into())?.take_while(|res| {
+            res.as_ref().map(|(key, _)| key.transition_id() <= last).unwrap_or_default()
+        });
+    for entry in storage_changes {
+        let (key, before) = entry?;
+        let storage_key = storage_history_key(key.address(), before.key);
+        storages.entry(storage_key).or_default().push(key.transition_id());
+    }
+    Ok((accounts, storages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        mdbx::{test_utils::create_test_db, Env, EnvKind, WriteMap},
+        models::{AccountBeforeTx, ShardedKey},
+        table::Table,
+        tables::TransitionList,
+        transaction::DbTxMut,
+    };
+    use reth_interfaces::test_utils::generators::random_block_range;
+    use reth_primitives::{StorageEntry, H160, H256};
+    use reth_provider::insert_canonical_block;
+    use std::ops::DerefMut;
+
+    fn input(stage_progress: Option<u64>, target: u64) -> ExecInput {
+        ExecInput { previous_stage: Some((StageId("Execution"), target)), stage_progress }
+    }
+
+    fn transitions<T>(tx: &Transaction<'_, Env<WriteMap>>, key: T::Key) -> Option<Vec<u64>>
+    where
+        T: Table<Value = TransitionList>,
+    {
+        let list = tx.get::<T>(key).unwrap()?;
+        Some(list.iter(0).map(|transition| transition as u64).collect())
+    }
+
+    #[tokio::test]
+    async fn index_and_unwind_changesets() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        for block in random_block_range(0..10, H256::zero(), 1..3) {
+            insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+        }
+
+        // `account` changes in every transition, its slot in the last transition of every block
+        let account = H160::from_low_u64_be(1);
+        let slot = H256::from_low_u64_be(2);
+        let mut all = Vec::new();
+        let mut last = Vec::new();
+        for number in 1..10u64 {
+            let (_, first) = tx.get_next_block_ids(number).unwrap();
+            let end = tx.get_block_transition_by_num(number).unwrap();
+            for transition in first..=end {
+                tx.put::<tables::AccountChangeSet>(
+                    transition,
+                    AccountBeforeTx { address: account, info: None },
+                )
+                .unwrap();
+                all.push(transition);
+            }
+            tx.put::<tables::StorageChangeSet>(
+                (end, account).into(),
+                StorageEntry { key: slot, value: Default::default() },
+            )
+            .unwrap();
+            last.push(end);
+        }
+        let storage_key = storage_history_key(account, slot);
+
+        let mut stage = HistoryIndexStage { shard_size: 1_000, commit_threshold: 5 };
+        let output = stage.execute(&mut tx, input(Some(0), 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 5, done: false });
+        let output = stage.execute(&mut tx, input(Some(5), 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 9, done: true });
+
+        let account_key = ShardedKey::new(account, 999);
+        let slot_key = ShardedKey::new(storage_key, 999);
+        assert_eq!(transitions::<tables::AccountHistory>(&tx, account_key.clone()), Some(all));
+        assert_eq!(transitions::<tables::StorageHistory>(&tx, slot_key.clone()), Some(last));
+
+        let output = stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 9, unwind_to: 5, bad_block: None })
+            .await
+            .unwrap();
+        assert_eq!(output, UnwindOutput { stage_progress: 5 });
+        let last_kept = tx.get_block_transition_by_num(5).unwrap();
+        let kept = transitions::<tables::AccountHistory>(&tx, account_key).unwrap();
+        assert_eq!(kept.last(), Some(&last_kept));
+        let kept = transitions::<tables::StorageHistory>(&tx, slot_key).unwrap();
+        assert_eq!(kept.last(), Some(&last_kept));
+    }
+}
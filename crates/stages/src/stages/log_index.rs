@@ -0,0 +1,284 @@
+use crate::{
+    db::Transaction, ExecInput, ExecOutput, Stage, StageError, StageId, UnwindInput, UnwindOutput,
+};
+use itertools::Itertools;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    models::ShardedKey,
+    table::Table,
+    tables::{self, BlockList},
+    transaction::{DbTx, DbTxMut},
+    Error as DbError,
+};
+use reth_primitives::{Address, BlockNumber, H256};
+use std::{collections::BTreeMap, ops::RangeInclusive};
+use tracing::*;
+
+/// The [`StageId`] of the log index stage.
+pub const LOG_INDEX: StageId = StageId("LogIndex");
+
+/// The log index stage indexes the addresses and topics of the logs in the
+/// [`Receipts`][tables::Receipts] of the executed blocks.
+///
+/// For every address and topic, the numbers of the blocks that emitted a matching log are stored
+/// in the [`LogAddressIndex`][tables::LogAddressIndex] and
+/// [`LogTopicIndex`][tables::LogTopicIndex] tables, sharded by ranges of
+/// [`shard_size`](LogIndexStage::shard_size) blocks. A log filter over a wide range of blocks then
+/// only has to look at the receipts of the blocks in these lists.
+#[derive(Debug)]
+pub struct LogIndexStage {
+    /// The number of blocks of a shard. This must not change for an existing database.
+    pub shard_size: u64,
+    /// The number of blocks after which the control flow is returned to the pipeline for commit.
+    pub commit_threshold: u64,
+}
+
+impl Default for LogIndexStage {
+    fn default() -> Self {
+        Self { shard_size: 65_536, commit_threshold: 100_000 }
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database> Stage<DB> for LogIndexStage {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        LOG_INDEX
+    }
+
+    /// Index the logs of the blocks between the stage progress and the progress of the previous
+    /// stage.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = input.stage_progress.unwrap_or_default();
+        let previous_stage_progress = input.previous_stage_progress();
+        let max_block_num = previous_stage_progress.min(stage_progress + self.commit_threshold);
+
+        if max_block_num <= stage_progress {
+            info!(target: "sync::stages::log_index", target = max_block_num, stage_progress, "Target block already reached");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        let (addresses, topics) = block_logs(tx, stage_progress + 1..=max_block_num)?;
+        info!(target: "sync::stages::log_index", from = stage_progress + 1, to = max_block_num, addresses = addresses.len(), topics = topics.len(), "Indexing logs");
+        append_to_shards::<DB, tables::LogAddressIndex, _>(tx, self.shard_size, addresses)?;
+        append_to_shards::<DB, tables::LogTopicIndex, _>(tx, self.shard_size, topics)?;
+
+        let done = max_block_num >= previous_stage_progress;
+        info!(target: "sync::stages::log_index", stage_progress = max_block_num, done, "Sync iteration finished");
+        Ok(ExecOutput { stage_progress: max_block_num, done })
+    }
+
+    /// Unwind the stage.
+    async fn unwind(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        // The receipts are unwound by the execution stage, which runs after this one on unwinds.
+        let (addresses, topics) = block_logs(tx, input.unwind_to + 1..=input.stage_progress)?;
+        remove_from_shards::<DB, tables::LogAddressIndex, _>(
+            tx,
+            input.unwind_to,
+            addresses.into_keys(),
+        )?;
+        remove_from_shards::<DB, tables::LogTopicIndex, _>(
+            tx,
+            input.unwind_to,
+            topics.into_keys(),
+        )?;
+
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+/// The blocks that emitted logs, by address and by topic.
+pub(super) type BlocksByKey<K> = BTreeMap<K, Vec<BlockNumber>>;
+
+/// Collects the addresses and topics of the logs of the blocks in the range, each of them with the
+/// ascending numbers of the blocks it appears in.
+fn block_logs<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    range: RangeInclusive<BlockNumber>,
+) -> Result<(BlocksByKey<Address>, BlocksByKey<H256>), StageError> {
+    let mut addresses = BlocksByKey::<Address>::new();
+    let mut topics = BlocksByKey::<H256>::new();
+    let mut receipts = tx.cursor::<tables::Receipts>()?;
+
+    for number in range {
+        let body = tx.get_block_body_by_num(number)?;
+        if body.tx_count == 0 {
+            continue
+        }
+        for entry in receipts.walk(body.start_tx_id)?.take(body.tx_count as usize) {
+            let (_, receipt) = entry?;
+            for log in receipt.logs {
+                push_block(addresses.entry(log.address).or_default(), number);
+                for topic in log.topics {
+                    push_block(topics.entry(topic).or_default(), number);
+                }
+            }
+        }
+    }
+    Ok((addresses, topics))
+}
+
+/// Adds the block to the ascending list of blocks, unless it's already the last one.
+fn push_block(blocks: &mut Vec<BlockNumber>, number: BlockNumber) {
+    if blocks.last() != Some(&number) {
+        blocks.push(number);
+    }
+}
+
+/// Returns the last number of the shard the number belongs to, which is the key of the shard.
+fn shard_end(number: u64, shard_size: u64) -> u64 {
+    (number / shard_size * shard_size).saturating_add(shard_size - 1)
+}
+
+/// Appends the ascending numbers of each key to its shards. The numbers must come after all
+/// numbers that are already indexed.
+///
+/// The numbers are block numbers for the log index and transitions for the history index.
+pub(super) fn append_to_shards<DB, T, K>(
+    tx: &Transaction<'_, DB>,
+    shard_size: u64,
+    numbers: BlocksByKey<K>,
+) -> Result<(), DbError>
+where
+    DB: Database,
+    T: Table<Key = ShardedKey<K>, Value = BlockList>,
+    K: Clone,
+{
+    for (key, numbers) in numbers {
+        let shards = numbers.into_iter().group_by(|number| shard_end(*number, shard_size));
+        for (shard, numbers) in &shards {
+            let sharded_key = ShardedKey::new(key.clone(), shard);
+            let mut list = tx
+                .get::<T>(sharded_key.clone())?
+                .map(|list| list.iter(0).map(|number| number as u64).collect::<Vec<_>>())
+                .unwrap_or_default();
+            list.extend(numbers);
+            tx.put::<T>(sharded_key, list.into())?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes all numbers after `unwind_to` from the shards of the keys.
+pub(super) fn remove_from_shards<DB, T, K>(
+    tx: &Transaction<'_, DB>,
+    unwind_to: u64,
+    keys: impl IntoIterator<Item = K>,
+) -> Result<(), DbError>
+where
+    DB: Database,
+    T: Table<Key = ShardedKey<K>, Value = BlockList>,
+    K: Clone + PartialEq,
+{
+    let mut cursor = tx.cursor::<T>()?;
+    for key in keys {
+        // The first shard that is returned is the one that contains `unwind_to + 1`.
+        let shards = cursor
+            .walk(ShardedKey::new(key.clone(), unwind_to + 1))?
+            .take_while(|res| res.as_ref().map(|(k, _)| k.key == key).unwrap_or_default())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (sharded_key, list) in shards {
+            let kept = list
+                .iter(0)
+                .map(|number| number as u64)
+                .take_while(|number| *number <= unwind_to)
+                .collect::<Vec<_>>();
+            if kept.is_empty() {
+                tx.delete::<T>(sharded_key, None)?;
+            } else {
+                tx.put::<T>(sharded_key, kept.into())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::mdbx::{test_utils::create_test_db, Env, EnvKind, WriteMap};
+    use reth_interfaces::test_utils::generators::random_block_range;
+    use reth_primitives::{Log, Receipt, H160};
+    use reth_provider::insert_canonical_block;
+    use std::ops::DerefMut;
+
+    fn input(stage_progress: Option<u64>, target: u64) -> ExecInput {
+        ExecInput { previous_stage: Some((StageId("Execution"), target)), stage_progress }
+    }
+
+    fn blocks<T>(tx: &Transaction<'_, Env<WriteMap>>, key: T::Key) -> Option<Vec<BlockNumber>>
+    where
+        T: Table<Value = BlockList>,
+    {
+        let list = tx.get::<T>(key).unwrap()?;
+        Some(list.iter(0).map(|block| block as u64).collect())
+    }
+
+    #[tokio::test]
+    async fn index_and_unwind_logs() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        for block in random_block_range(0..10, H256::zero(), 1..3) {
+            insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+        }
+
+        // even blocks emit logs of `even`, odd blocks of `odd` and every third block has a log
+        // with `topic`
+        let (even, odd) = (H160::from_low_u64_be(2), H160::from_low_u64_be(1));
+        let topic = H256::from_low_u64_be(3);
+        for number in 1..10u64 {
+            let body = tx.get_block_body_by_num(number).unwrap();
+            for tx_number in body.tx_id_range() {
+                let log = Log {
+                    address: if number % 2 == 0 { even } else { odd },
+                    topics: if number % 3 == 0 { vec![topic, topic] } else { vec![] },
+                    data: Default::default(),
+                };
+                let receipt = Receipt { success: true, logs: vec![log], ..Default::default() };
+                tx.put::<tables::Receipts>(tx_number, receipt).unwrap();
+            }
+        }
+
+        let mut stage = LogIndexStage { shard_size: 4, commit_threshold: 5 };
+        let output = stage.execute(&mut tx, input(Some(0), 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 5, done: false });
+        let output = stage.execute(&mut tx, input(Some(5), 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 9, done: true });
+
+        let key = |address, shard| ShardedKey::new(address, shard);
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(even, 3)), Some(vec![2]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(even, 7)), Some(vec![4, 6]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(even, 11)), Some(vec![8]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(odd, 3)), Some(vec![1, 3]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(odd, 7)), Some(vec![5, 7]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(odd, 11)), Some(vec![9]));
+        assert_eq!(blocks::<tables::LogTopicIndex>(&tx, ShardedKey::new(topic, 3)), Some(vec![3]));
+        assert_eq!(blocks::<tables::LogTopicIndex>(&tx, ShardedKey::new(topic, 7)), Some(vec![6]));
+        assert_eq!(blocks::<tables::LogTopicIndex>(&tx, ShardedKey::new(topic, 11)), Some(vec![9]));
+
+        let output = stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 9, unwind_to: 5, bad_block: None })
+            .await
+            .unwrap();
+        assert_eq!(output, UnwindOutput { stage_progress: 5 });
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(even, 3)), Some(vec![2]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(even, 7)), Some(vec![4]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(even, 11)), None);
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(odd, 7)), Some(vec![5]));
+        assert_eq!(blocks::<tables::LogAddressIndex>(&tx, key(odd, 11)), None);
+        assert_eq!(blocks::<tables::LogTopicIndex>(&tx, ShardedKey::new(topic, 3)), Some(vec![3]));
+        assert_eq!(blocks::<tables::LogTopicIndex>(&tx, ShardedKey::new(topic, 7)), None);
+        assert_eq!(blocks::<tables::LogTopicIndex>(&tx, ShardedKey::new(topic, 11)), None);
+    }
+}
@@ -0,0 +1,461 @@
+use crate::{
+    db::Transaction, DatabaseIntegrityError, ExecInput, ExecOutput, Stage, StageError, StageId,
+    UnwindInput, UnwindOutput,
+};
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    models::AccountBeforeTx,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_interfaces::consensus;
+use reth_primitives::{keccak256, Address, BlockNumber, TransitionId, H256, U256};
+use std::collections::btree_map::Entry;
+use tracing::*;
+
+mod trie;
+
+use trie::{StateUpdates, UpdateError};
+
+/// The [`StageId`] of the merkle stage.
+pub const MERKLE: StageId = StageId("Merkle");
+
+/// The merkle stage computes the state root of the hashed state and checks it against the state
+/// root of the header of the last block of the range.
+///
+/// The branches of the state and storage tries are stored in the
+/// [`AccountsTrie`][tables::AccountsTrie] and [`StoragesTrie`][tables::StoragesTrie] tables, the
+/// roots of the storage tries in the [`StorageRoots`][tables::StorageRoots] table. Once they are
+/// stored, only the paths of the accounts and slots changed within the range, as recorded in the
+/// [`AccountChangeSet`][tables::AccountChangeSet] and
+/// [`StorageChangeSet`][tables::StorageChangeSet] tables, are updated.
+///
+/// On the first run, or if the range exceeds the
+/// [`clean_threshold`](MerkleStage::clean_threshold), the tries are built from the entire hashed
+/// state, with the storage tries built in parallel.
+///
+/// This stage must run after the hashing stages.
+#[derive(Debug)]
+pub struct MerkleStage {
+    /// The number of blocks after which the tries are built from the entire hashed state instead
+    /// of updating the paths changed within the range.
+    pub clean_threshold: u64,
+    /// The number of storage tries that are built in parallel at once.
+    pub batch_size: usize,
+}
+
+impl Default for MerkleStage {
+    fn default() -> Self {
+        Self { clean_threshold: 500_000, batch_size: 1_000 }
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database> Stage<DB> for MerkleStage {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        MERKLE
+    }
+
+    /// Compute the state root at the progress of the previous stage and check it.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = input.stage_progress.unwrap_or_default();
+        let previous_stage_progress = input.previous_stage_progress();
+
+        if previous_stage_progress <= stage_progress {
+            info!(target: "sync::stages::merkle", target = previous_stage_progress, stage_progress, "Target block already reached");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        // the root is only missing if the tries were never built, or if the state trie is a
+        // single leaf which is cheap to build
+        let state_root = if stage_progress == 0 ||
+            previous_stage_progress - stage_progress > self.clean_threshold ||
+            tx.get::<tables::AccountsTrie>(Vec::new())?.is_none()
+        {
+            self.rebuild(tx, stage_progress, previous_stage_progress)?
+        } else {
+            let from_transition = tx.get_block_transition_by_num(stage_progress)? + 1;
+            let to_transition = tx.get_block_transition_by_num(previous_stage_progress)?;
+            let updates = changed_state(tx, from_transition, to_transition)?;
+
+            info!(target: "sync::stages::merkle", from_transition, to_transition, accounts = updates.accounts.len(), storages = updates.storages.len(), "Updating the paths of changed accounts");
+            match trie::update(tx, header_state_root(tx, stage_progress)?, &updates) {
+                Ok(state_root) => state_root,
+                Err(UpdateError::Trie(error)) => {
+                    warn!(target: "sync::stages::merkle", %error, "Stored trie nodes are inconsistent");
+                    self.rebuild(tx, stage_progress, previous_stage_progress)?
+                }
+                Err(UpdateError::Stage(err)) => return Err(err),
+            }
+        };
+
+        let expected = header_state_root(tx, previous_stage_progress)?;
+        if state_root != expected {
+            warn!(target: "sync::stages::merkle", block = previous_stage_progress, got = ?state_root, ?expected, "State root mismatch");
+            return Err(StageError::Validation {
+                block: previous_stage_progress,
+                error: consensus::Error::BodyStateRootDiff { got: state_root, expected },
+            })
+        }
+
+        info!(target: "sync::stages::merkle", stage_progress = previous_stage_progress, ?state_root, "Sync iteration finished");
+        Ok(ExecOutput { stage_progress: previous_stage_progress, done: true })
+    }
+
+    /// Unwind the stage.
+    async fn unwind(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        // The hashed state is unwound after this stage, so the tries still match it and the
+        // values before the range come from the changesets.
+        let from_transition = tx.get_block_transition_by_num(input.unwind_to)? + 1;
+        let to_transition = tx.get_block_transition_by_num(input.stage_progress)?;
+        let updates = unwound_state(tx, from_transition, to_transition)?;
+
+        let state_root =
+            match trie::update(tx, header_state_root(tx, input.stage_progress)?, &updates) {
+                Ok(state_root) => Some(state_root),
+                Err(UpdateError::Trie(_)) => None,
+                Err(UpdateError::Stage(err)) => return Err(err.into()),
+            };
+        let expected = header_state_root(tx, input.unwind_to)?;
+        if state_root != Some(expected) {
+            // the tries are built again by the next run
+            warn!(target: "sync::stages::merkle", unwind_to = input.unwind_to, got = ?state_root, ?expected, "State root mismatch after unwinding the tries");
+            tx.clear::<tables::AccountsTrie>()?;
+        }
+
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+impl MerkleStage {
+    /// Builds the tries from the entire hashed state.
+    fn rebuild<DB: Database>(
+        &self,
+        tx: &Transaction<'_, DB>,
+        stage_progress: BlockNumber,
+        target: BlockNumber,
+    ) -> Result<H256, StageError> {
+        info!(target: "sync::stages::merkle", stage_progress, target, "Building the tries from the entire hashed state");
+        trie::rebuild(tx, self.batch_size)
+    }
+}
+
+/// Returns the state root of the header of the canonical block.
+fn header_state_root<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    number: BlockNumber,
+) -> Result<H256, StageError> {
+    let key = tx.get_block_numhash(number)?;
+    let header = tx
+        .get::<tables::Headers>(key)?
+        .ok_or(DatabaseIntegrityError::Header { number, hash: key.hash() })?;
+    Ok(header.state_root)
+}
+
+/// Returns the accounts and slots changed between the transitions, inclusive, with their values
+/// in the hashed state.
+fn changed_state<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    from_transition: TransitionId,
+    to_transition: TransitionId,
+) -> Result<StateUpdates, StageError> {
+    let mut updates = StateUpdates::default();
+    let mut changesets = tx.cursor_dup::<tables::AccountChangeSet>()?;
+    for entry in changesets.walk_range(from_transition..=to_transition)? {
+        let (_, AccountBeforeTx { address, .. }) = entry?;
+        updates.accounts.insert(keccak256(address), None);
+    }
+    let mut changesets = tx.cursor_dup::<tables::StorageChangeSet>()?;
+    for entry in changesets.walk((from_transition, Address::zero()).into())?.take_while(|res| {
+        res.as_ref().map(|(k, _)| k.transition_id() <= to_transition).unwrap_or_default()
+    }) {
+        let (key, entry) = entry?;
+        let hashed_address = keccak256(key.address());
+        updates
+            .storages
+            .entry(hashed_address)
+            .or_default()
+            .insert(keccak256(entry.key), U256::zero());
+        updates.accounts.entry(hashed_address).or_default();
+    }
+
+    for (hashed_address, account) in updates.accounts.iter_mut() {
+        *account = tx.get::<tables::HashedAccount>(*hashed_address)?;
+    }
+    let mut storage = tx.cursor_dup::<tables::HashedStorage>()?;
+    for (hashed_address, slots) in updates.storages.iter_mut() {
+        for (key, value) in slots.iter_mut() {
+            *value = storage
+                .seek_by_key_subkey(*hashed_address, *key)?
+                .filter(|entry| entry.key == *key)
+                .map(|entry| entry.value)
+                .unwrap_or_default();
+        }
+    }
+    Ok(updates)
+}
+
+/// Returns the accounts and slots changed between the transitions, inclusive, with their values
+/// before the first transition.
+fn unwound_state<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    from_transition: TransitionId,
+    to_transition: TransitionId,
+) -> Result<StateUpdates, StageError> {
+    // The first changeset of an account or slot within the range holds its value before it.
+    let mut updates = StateUpdates::default();
+    let mut changesets = tx.cursor_dup::<tables::AccountChangeSet>()?;
+    for entry in changesets.walk_range(from_transition..=to_transition)? {
+        let (_, AccountBeforeTx { address, info }) = entry?;
+        updates.accounts.entry(keccak256(address)).or_insert(info);
+    }
+    let mut changesets = tx.cursor_dup::<tables::StorageChangeSet>()?;
+    for entry in changesets.walk((from_transition, Address::zero()).into())?.take_while(|res| {
+        res.as_ref().map(|(k, _)| k.transition_id() <= to_transition).unwrap_or_default()
+    }) {
+        let (key, entry) = entry?;
+        let slots = updates.storages.entry(keccak256(key.address())).or_default();
+        slots.entry(keccak256(entry.key)).or_insert(entry.value);
+    }
+
+    // accounts whose storage changed without a changeset of their own are unchanged
+    for hashed_address in updates.storages.keys() {
+        if let Entry::Vacant(entry) = updates.accounts.entry(*hashed_address) {
+            entry.insert(tx.get::<tables::HashedAccount>(*hashed_address)?);
+        }
+    }
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use reth_db::{
+        mdbx::{test_utils::create_test_db, EnvKind, WriteMap},
+        models::TransitionIdAddress,
+    };
+    use reth_interfaces::test_utils::generators::random_block;
+    use reth_primitives::{
+        proofs::{calculate_state_root, calculate_storage_root, EMPTY_ROOT},
+        Account, Header, SealedBlock, StorageEntry, H160,
+    };
+    use reth_provider::insert_canonical_block;
+    use std::{collections::BTreeMap, ops::DerefMut};
+
+    fn input(stage_progress: Option<u64>, target: u64) -> ExecInput {
+        ExecInput { previous_stage: Some((StageId("StorageHashing"), target)), stage_progress }
+    }
+
+    fn insert_block<DB: Database>(
+        tx: &mut Transaction<'_, DB>,
+        number: u64,
+        parent: Option<H256>,
+        state_root: H256,
+    ) -> SealedBlock {
+        let mut block = random_block(number, parent, Some(0));
+        block.header = Header { state_root, ..block.header.unseal() }.seal();
+        insert_canonical_block(tx.deref_mut(), &block, true).unwrap();
+        block
+    }
+
+    fn write_slot<DB: Database>(
+        tx: &Transaction<'_, DB>,
+        address: Address,
+        key: H256,
+        value: U256,
+    ) {
+        let hashed_address = keccak256(address);
+        let hashed_key = keccak256(key);
+        let existing = tx
+            .cursor_dup::<tables::HashedStorage>()
+            .unwrap()
+            .seek_by_key_subkey(hashed_address, hashed_key)
+            .unwrap()
+            .filter(|entry| entry.key == hashed_key);
+        if let Some(existing) = existing {
+            tx.delete::<tables::HashedStorage>(hashed_address, Some(existing)).unwrap();
+        }
+        if !value.is_zero() {
+            tx.put::<tables::HashedStorage>(
+                hashed_address,
+                StorageEntry { key: hashed_key, value },
+            )
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_state_root_of_hashed_state() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let contract = H160::from_low_u64_be(1);
+        let eoa = H160::from_low_u64_be(2);
+        let account = Account { nonce: 1, balance: U256::from(10), bytecode_hash: None };
+        let slots =
+            (1..=3u64).map(|i| (H256::from_low_u64_be(i), U256::from(i))).collect::<Vec<_>>();
+        for address in [contract, eoa] {
+            tx.put::<tables::HashedAccount>(keccak256(address), account).unwrap();
+        }
+        for (key, value) in slots.iter() {
+            write_slot(&tx, contract, *key, *value);
+        }
+
+        let storage_root = calculate_storage_root(slots);
+        let state_root =
+            calculate_state_root([(contract, account, storage_root), (eoa, account, EMPTY_ROOT)]);
+        let genesis = insert_block(&mut tx, 0, None, H256::zero());
+        insert_block(&mut tx, 1, Some(genesis.hash()), state_root);
+
+        let output = MerkleStage::default().execute(&mut tx, input(None, 1)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 1, done: true });
+        assert_eq!(
+            tx.get::<tables::StorageRoots>(keccak256(contract)).unwrap(),
+            Some(storage_root)
+        );
+
+        // a changed balance is not part of the state root of the header
+        let changed = Account { balance: U256::from(11), ..account };
+        tx.put::<tables::HashedAccount>(keccak256(eoa), changed).unwrap();
+        let result = MerkleStage::default().execute(&mut tx, input(None, 1)).await;
+        assert_matches!(
+            result,
+            Err(StageError::Validation {
+                block: 1,
+                error: consensus::Error::BodyStateRootDiff { expected, .. }
+            }) if expected == state_root
+        );
+    }
+
+    /// The plain state of the test accounts.
+    type State = BTreeMap<Address, (Account, BTreeMap<H256, U256>)>;
+
+    fn state_root(state: &State) -> H256 {
+        calculate_state_root(state.iter().map(|(address, (account, storage))| {
+            (*address, *account, calculate_storage_root(storage.clone()))
+        }))
+    }
+
+    /// Returns the entries of the trie tables.
+    #[allow(clippy::type_complexity)]
+    fn trie_tables<DB: Database>(
+        tx: &Transaction<'_, DB>,
+    ) -> (Vec<(Vec<u8>, Vec<u8>)>, Vec<(Vec<u8>, Vec<u8>)>, Vec<(H256, H256)>) {
+        let accounts = tx.cursor::<tables::AccountsTrie>().unwrap().walk(Vec::new()).unwrap();
+        let storages = tx.cursor::<tables::StoragesTrie>().unwrap().walk(Vec::new()).unwrap();
+        let roots = tx.cursor::<tables::StorageRoots>().unwrap().walk(H256::zero()).unwrap();
+        (
+            accounts.collect::<Result<_, _>>().unwrap(),
+            storages.collect::<Result<_, _>>().unwrap(),
+            roots.collect::<Result<_, _>>().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn updates_changed_paths_and_unwinds() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        let mut state = (1..=40u64)
+            .map(|i| {
+                let account = Account { nonce: i, balance: U256::from(i), bytecode_hash: None };
+                let storage = (1..=i / 4)
+                    .map(|j| (H256::from_low_u64_be(j), U256::from(i * j)))
+                    .collect::<BTreeMap<_, _>>();
+                (H160::from_low_u64_be(i), (account, storage))
+            })
+            .collect::<State>();
+        for (address, (account, storage)) in &state {
+            tx.put::<tables::HashedAccount>(keccak256(address), *account).unwrap();
+            for (key, value) in storage {
+                write_slot(&tx, *address, *key, *value);
+            }
+        }
+
+        let genesis = insert_block(&mut tx, 0, None, H256::zero());
+        let block1 = insert_block(&mut tx, 1, Some(genesis.hash()), state_root(&state));
+        let mut stage = MerkleStage::default();
+        stage.execute(&mut tx, input(None, 1)).await.unwrap();
+        assert!(tx.get::<tables::AccountsTrie>(Vec::new()).unwrap().is_some());
+        let tables_at_block1 = trie_tables(&tx);
+
+        // the changes of block #2 with the changesets of the previous values
+        let transition = tx.get_block_transition_by_num(1).unwrap() + 1;
+        let changed_storage = H160::from_low_u64_be(12);
+        let destroyed = H160::from_low_u64_be(16);
+        let created = H160::from_low_u64_be(41);
+        let changed_balance = H160::from_low_u64_be(5);
+        let mut changes = vec![
+            (changed_storage, H256::from_low_u64_be(1), U256::from(100)),
+            (changed_storage, H256::from_low_u64_be(100), U256::from(1)),
+            (created, H256::from_low_u64_be(1), U256::from(1)),
+        ];
+        for key in state[&destroyed].1.keys() {
+            changes.push((destroyed, *key, U256::zero()));
+        }
+        for (address, key, value) in changes {
+            let storage = &mut state.entry(address).or_default().1;
+            let previous = storage.get(&key).copied().unwrap_or_default();
+            tx.put::<tables::StorageChangeSet>(
+                TransitionIdAddress((transition, address)),
+                StorageEntry { key, value: previous },
+            )
+            .unwrap();
+            if value.is_zero() {
+                storage.remove(&key);
+            } else {
+                storage.insert(key, value);
+            }
+            write_slot(&tx, address, key, value);
+        }
+
+        let new_account = Account { nonce: 1, balance: U256::from(1), bytecode_hash: None };
+        for (address, account) in
+            [(destroyed, None), (created, Some(new_account)), (changed_balance, Some(new_account))]
+        {
+            let previous = if address == created { None } else { Some(state[&address].0) };
+            tx.put::<tables::AccountChangeSet>(
+                transition,
+                AccountBeforeTx { address, info: previous },
+            )
+            .unwrap();
+            match account {
+                Some(account) => {
+                    state.entry(address).or_default().0 = account;
+                    tx.put::<tables::HashedAccount>(keccak256(address), account).unwrap();
+                }
+                None => {
+                    state.remove(&address);
+                    tx.delete::<tables::HashedAccount>(keccak256(address), None).unwrap();
+                }
+            }
+        }
+
+        insert_block(&mut tx, 2, Some(block1.hash()), state_root(&state));
+        let output = stage.execute(&mut tx, input(Some(1), 2)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 2, done: true });
+
+        // the updated tries match the tries built from the entire state
+        let updated = trie_tables(&tx);
+        assert_eq!(trie::rebuild(&tx, 10).unwrap(), state_root(&state));
+        assert_eq!(trie_tables(&tx), updated);
+
+        let output = stage
+            .unwind(&mut tx, UnwindInput { stage_progress: 2, unwind_to: 1, bad_block: None })
+            .await
+            .unwrap();
+        assert_eq!(output, UnwindOutput { stage_progress: 1 });
+        assert_eq!(trie_tables(&tx), tables_at_block1);
+    }
+}
@@ -0,0 +1,322 @@
+//! Building and updating the state and storage tries with the branches stored in the
+//! [`AccountsTrie`][tables::AccountsTrie] and [`StoragesTrie`][tables::StoragesTrie] tables.
+use crate::{db::Transaction, StageError};
+use rayon::prelude::*;
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{
+    keccak256,
+    proofs::{TrieAccount, EMPTY_ROOT},
+    trie::{to_nibbles, Trie, TrieError, TrieNode},
+    Account, H256, U256,
+};
+use reth_rlp::Encodable;
+use std::collections::{BTreeMap, HashSet};
+use thiserror::Error;
+
+/// The changed accounts and slots, by hashed address and hashed slot.
+#[derive(Debug, Default)]
+pub(crate) struct StateUpdates {
+    /// The accounts, `None` if removed. Includes the accounts of all changed storage tries.
+    pub(crate) accounts: BTreeMap<H256, Option<Account>>,
+    /// The slots of the changed storage tries, zero if removed.
+    pub(crate) storages: BTreeMap<H256, BTreeMap<H256, U256>>,
+}
+
+/// An error updating the tries.
+#[derive(Debug, Error)]
+pub(crate) enum UpdateError {
+    /// The stored nodes don't match the tries, they have to be built again.
+    #[error("Inconsistent trie nodes: {0}")]
+    Trie(#[from] TrieError),
+    /// The tables could not be read or written.
+    #[error(transparent)]
+    Stage(#[from] StageError),
+}
+
+impl From<reth_db::Error> for UpdateError {
+    fn from(err: reth_db::Error) -> Self {
+        UpdateError::Stage(err.into())
+    }
+}
+
+/// Builds the tries from the entire hashed state, stores their branches and the roots of the
+/// storage tries and returns the state root.
+///
+/// The storage tries are built in parallel, `batch_size` at a time.
+pub(crate) fn rebuild<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    batch_size: usize,
+) -> Result<H256, StageError> {
+    tx.clear::<tables::AccountsTrie>()?;
+    tx.clear::<tables::StoragesTrie>()?;
+    tx.clear::<tables::StorageRoots>()?;
+
+    let mut batch: Vec<(H256, Vec<(H256, Vec<u8>)>)> = Vec::with_capacity(batch_size);
+    for entry in tx.cursor_dup::<tables::HashedStorage>()?.walk(H256::zero())? {
+        let (hashed_address, entry) = entry?;
+        if entry.value.is_zero() {
+            continue
+        }
+        match batch.last_mut() {
+            Some((last, slots)) if *last == hashed_address => {
+                slots.push((entry.key, rlp(&entry.value)))
+            }
+            _ => {
+                if batch.len() >= batch_size.max(1) {
+                    store_storage_tries(tx, std::mem::take(&mut batch))?;
+                }
+                batch.push((hashed_address, vec![(entry.key, rlp(&entry.value))]));
+            }
+        }
+    }
+    store_storage_tries(tx, batch)?;
+
+    let mut accounts = Vec::new();
+    for entry in tx.cursor::<tables::HashedAccount>()?.walk(H256::zero())? {
+        let (hashed_address, account) = entry?;
+        accounts.push((hashed_address, account_leaf(tx, hashed_address, account)?));
+    }
+    let (state_root, branches) = Trie::from_entries(accounts).root_hash_with_branches();
+    for (path, node) in branches {
+        tx.put::<tables::AccountsTrie>(path, node)?;
+    }
+    Ok(state_root)
+}
+
+/// Builds the storage tries in parallel and stores their roots and branches.
+fn store_storage_tries<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    tries: Vec<(H256, Vec<(H256, Vec<u8>)>)>,
+) -> Result<(), StageError> {
+    let built = tries
+        .into_par_iter()
+        .map(|(hashed_address, slots)| {
+            (hashed_address, Trie::from_entries(slots).root_hash_with_branches())
+        })
+        .collect::<Vec<_>>();
+    for (hashed_address, (root, branches)) in built {
+        tx.put::<tables::StorageRoots>(hashed_address, root)?;
+        for (path, node) in branches {
+            tx.put::<tables::StoragesTrie>(storage_path(hashed_address, &path), node)?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies the updates to the tries of the given state root along the paths of the changed keys
+/// and returns the new state root.
+///
+/// The storage tries are updated first, the leaves of the accounts include the new roots.
+pub(crate) fn update<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    state_root: H256,
+    updates: &StateUpdates,
+) -> Result<H256, UpdateError> {
+    for (hashed_address, slots) in &updates.storages {
+        let root = tx.get::<tables::StorageRoots>(*hashed_address)?.unwrap_or(EMPTY_ROOT);
+        let slots = slots
+            .iter()
+            .map(|(key, value)| (*key, (!value.is_zero()).then(|| rlp(value))))
+            .collect();
+        let mut nodes = StorageNodes { tx, hashed_address: *hashed_address };
+        let root = update_trie(&mut nodes, root, &slots)?;
+        if root == EMPTY_ROOT {
+            tx.delete::<tables::StorageRoots>(*hashed_address, None)?;
+        } else {
+            tx.put::<tables::StorageRoots>(*hashed_address, root)?;
+        }
+    }
+
+    let mut accounts = BTreeMap::new();
+    for (hashed_address, account) in &updates.accounts {
+        let leaf = match account {
+            Some(account) => Some(account_leaf(tx, *hashed_address, *account)?),
+            None => None,
+        };
+        accounts.insert(*hashed_address, leaf);
+    }
+    update_trie(&mut AccountNodes { tx }, state_root, &accounts)
+}
+
+/// The stored branches of a trie and the hashed state its leaves are recovered from.
+trait TrieNodes {
+    /// Returns the encoding of the stored branch or extension at the position.
+    fn branch(&mut self, path: &[u8]) -> Result<Option<Vec<u8>>, StageError>;
+
+    /// Returns the keys of the hashed state that start with the nibbles, with their leaf values.
+    fn leaves(&mut self, prefix: &[u8]) -> Result<Vec<(H256, Vec<u8>)>, StageError>;
+
+    /// Stores the encoding of the branch or extension at the position.
+    fn put_branch(&mut self, path: &[u8], node: Vec<u8>) -> Result<(), StageError>;
+
+    /// Removes the branch or extension at the position.
+    fn delete_branch(&mut self, path: &[u8]) -> Result<(), StageError>;
+}
+
+/// Applies the updates to the trie of the root along the paths of the changed keys, `None`
+/// removes a key, stores the changed branches and returns the new root.
+///
+/// The branches on the paths are loaded from the stored nodes. Leaves are recovered from the
+/// hashed state, which may already hold the updated values: the leaf at a position is the one key
+/// of that subtrie that is not updated, or any key of the subtrie if all of them are updated since
+/// they are replaced anyway.
+fn update_trie(
+    nodes: &mut impl TrieNodes,
+    root: H256,
+    updates: &BTreeMap<H256, Option<Vec<u8>>>,
+) -> Result<H256, UpdateError> {
+    let mut loaded = Vec::new();
+    let mut load = |path: &[u8], hash: H256| -> Result<TrieNode, UpdateError> {
+        if let Some(encoded) = nodes.branch(path)? {
+            if keccak256(&encoded) != hash {
+                return Err(TrieError::MissingNode(hash).into())
+            }
+            loaded.push(path.to_vec());
+            return Ok(TrieNode::decode(&encoded)?)
+        }
+
+        let leaves = nodes.leaves(path)?;
+        let leaf = leaves
+            .iter()
+            .find(|(key, _)| !updates.contains_key(key))
+            .or_else(|| leaves.first())
+            .cloned()
+            .or_else(|| {
+                // the key of the leaf was removed from the hashed state, it's removed from the
+                // trie as well
+                updates
+                    .range(start_key(path)..)
+                    .map(|(key, _)| *key)
+                    .take_while(|key| to_nibbles(key.as_bytes()).starts_with(path))
+                    .next()
+                    .map(|key| (key, Vec::new()))
+            });
+        let (key, value) = leaf.ok_or(TrieError::MissingNode(hash))?;
+        Ok(TrieNode::Leaf { path: to_nibbles(key.as_bytes())[path.len()..].to_vec(), value })
+    };
+
+    let mut trie = Trie::from_root(root);
+    for (key, value) in updates {
+        match value {
+            Some(value) => trie.insert_with(key.as_bytes(), value.clone(), &mut load)?,
+            None => {
+                trie.remove_with(key.as_bytes(), &mut load)?;
+            }
+        }
+    }
+
+    let (root, branches) = trie.root_hash_with_branches();
+    let stored = branches.iter().map(|(path, _)| path.clone()).collect::<HashSet<_>>();
+    for path in loaded.iter().filter(|path| !stored.contains(*path)) {
+        nodes.delete_branch(path)?;
+    }
+    for (path, node) in branches {
+        nodes.put_branch(&path, node)?;
+    }
+    Ok(root)
+}
+
+/// The nodes of the state trie.
+struct AccountNodes<'a, 'tx, DB: Database> {
+    tx: &'a Transaction<'tx, DB>,
+}
+
+impl<'a, 'tx, DB: Database> TrieNodes for AccountNodes<'a, 'tx, DB> {
+    fn branch(&mut self, path: &[u8]) -> Result<Option<Vec<u8>>, StageError> {
+        Ok(self.tx.get::<tables::AccountsTrie>(path.to_vec())?)
+    }
+
+    fn leaves(&mut self, prefix: &[u8]) -> Result<Vec<(H256, Vec<u8>)>, StageError> {
+        let mut cursor = self.tx.cursor::<tables::HashedAccount>()?;
+        let mut leaves = Vec::new();
+        for entry in cursor.walk(start_key(prefix))? {
+            let (hashed_address, account) = entry?;
+            if !to_nibbles(hashed_address.as_bytes()).starts_with(prefix) {
+                break
+            }
+            leaves.push((hashed_address, account_leaf(self.tx, hashed_address, account)?));
+        }
+        Ok(leaves)
+    }
+
+    fn put_branch(&mut self, path: &[u8], node: Vec<u8>) -> Result<(), StageError> {
+        Ok(self.tx.put::<tables::AccountsTrie>(path.to_vec(), node)?)
+    }
+
+    fn delete_branch(&mut self, path: &[u8]) -> Result<(), StageError> {
+        self.tx.delete::<tables::AccountsTrie>(path.to_vec(), None)?;
+        Ok(())
+    }
+}
+
+/// The nodes of the storage trie of an account.
+struct StorageNodes<'a, 'tx, DB: Database> {
+    tx: &'a Transaction<'tx, DB>,
+    hashed_address: H256,
+}
+
+impl<'a, 'tx, DB: Database> TrieNodes for StorageNodes<'a, 'tx, DB> {
+    fn branch(&mut self, path: &[u8]) -> Result<Option<Vec<u8>>, StageError> {
+        Ok(self.tx.get::<tables::StoragesTrie>(storage_path(self.hashed_address, path))?)
+    }
+
+    fn leaves(&mut self, prefix: &[u8]) -> Result<Vec<(H256, Vec<u8>)>, StageError> {
+        let mut cursor = self.tx.cursor_dup::<tables::HashedStorage>()?;
+        let mut leaves = Vec::new();
+        for entry in cursor.walk_dup(self.hashed_address, Some(start_key(prefix)))? {
+            let (_, entry) = entry?;
+            if !to_nibbles(entry.key.as_bytes()).starts_with(prefix) {
+                break
+            }
+            if !entry.value.is_zero() {
+                leaves.push((entry.key, rlp(&entry.value)));
+            }
+        }
+        Ok(leaves)
+    }
+
+    fn put_branch(&mut self, path: &[u8], node: Vec<u8>) -> Result<(), StageError> {
+        Ok(self.tx.put::<tables::StoragesTrie>(storage_path(self.hashed_address, path), node)?)
+    }
+
+    fn delete_branch(&mut self, path: &[u8]) -> Result<(), StageError> {
+        self.tx.delete::<tables::StoragesTrie>(storage_path(self.hashed_address, path), None)?;
+        Ok(())
+    }
+}
+
+/// Returns the leaf value of the account with the stored root of its storage trie.
+fn account_leaf<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    hashed_address: H256,
+    account: Account,
+) -> Result<Vec<u8>, StageError> {
+    let storage_root = tx.get::<tables::StorageRoots>(hashed_address)?.unwrap_or(EMPTY_ROOT);
+    Ok(rlp(&TrieAccount::new(account, storage_root)))
+}
+
+/// Returns the key of the [`StoragesTrie`][tables::StoragesTrie] table for the position in the
+/// storage trie of the account.
+fn storage_path(hashed_address: H256, path: &[u8]) -> Vec<u8> {
+    [hashed_address.as_bytes(), path].concat()
+}
+
+/// Returns the smallest key that starts with the nibbles.
+fn start_key(prefix: &[u8]) -> H256 {
+    let mut key = H256::zero();
+    for (i, nibble) in prefix.iter().enumerate() {
+        key.as_bytes_mut()[i / 2] |= if i % 2 == 0 { nibble << 4 } else { *nibble };
+    }
+    key
+}
+
+fn rlp(value: &impl Encodable) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
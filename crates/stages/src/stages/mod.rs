@@ -2,7 +2,23 @@
 pub mod bodies;
 /// The execution stage that generates state diff.
 pub mod execution;
+/// The freeze stage that moves old blocks to the freezer.
+pub mod freeze;
+/// The account hashing stage.
+pub mod hashing_account;
+/// The storage hashing stage.
+pub mod hashing_storage;
 /// The headers stage.
 pub mod headers;
+/// The history index stage.
+pub mod history_index;
+/// The log index stage.
+pub mod log_index;
+/// The merkle stage that computes and checks the state root.
+pub mod merkle;
+/// The prune stage that removes old data.
+pub mod prune;
 /// The sender recovery stage.
 pub mod sender_recovery;
+/// The snap sync stage that downloads the state of a recent block.
+pub mod snap;
@@ -0,0 +1,459 @@
+use crate::{
+    db::Transaction, ExecInput, ExecOutput, Stage, StageError, StageId, UnwindInput, UnwindOutput,
+};
+use itertools::Itertools;
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    table::Table,
+    tables::{self, TransitionList},
+    transaction::{DbTx, DbTxMut},
+};
+use reth_primitives::{Address, BlockNumber, Receipt, TransitionId, TxNumber};
+use std::ops::{Range, RangeInclusive};
+use tracing::*;
+
+/// The [`StageId`] of the prune stage.
+pub const PRUNE: StageId = StageId("Prune");
+
+/// Which receipts are pruned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptsPruneMode {
+    /// The number of most recent blocks to keep all receipts for.
+    pub distance: u64,
+    /// The contracts whose logs are never pruned, e.g. the deposit contract of a beacon chain.
+    ///
+    /// Receipts of older blocks are kept if they contain a log of one of these contracts.
+    pub contracts: Vec<Address>,
+}
+
+// === impl ReceiptsPruneMode ===
+
+impl ReceiptsPruneMode {
+    /// Creates a mode that keeps the receipts of the last `distance` blocks.
+    pub fn new(distance: u64) -> Self {
+        Self { distance, contracts: Vec::new() }
+    }
+
+    /// Also keeps the older receipts with logs of the given contracts.
+    pub fn with_contracts(mut self, contracts: Vec<Address>) -> Self {
+        self.contracts = contracts;
+        self
+    }
+
+    /// Returns the last block whose receipts are pruned if the chain is at `tip`.
+    pub fn prune_to(&self, tip: BlockNumber) -> Option<BlockNumber> {
+        tip.checked_sub(self.distance)
+    }
+
+    /// Returns `true` if the receipt has a log of one of the [contracts](Self::contracts), so it's
+    /// never pruned.
+    pub fn retains(&self, receipt: &Receipt) -> bool {
+        receipt.logs.iter().any(|log| self.contracts.contains(&log.address))
+    }
+
+    /// Returns `true` if the receipt of the block is pruned if the chain is at `tip`.
+    pub fn is_pruned(&self, block: BlockNumber, tip: BlockNumber, receipt: &Receipt) -> bool {
+        self.prune_to(tip).map_or(false, |prune_to| block <= prune_to) && !self.retains(receipt)
+    }
+}
+
+/// The prune stage removes the data of old blocks that is not needed for syncing to keep the
+/// database small.
+///
+/// Receipts are removed from the [`Receipts`][tables::Receipts] table according to the
+/// [`ReceiptsPruneMode`]. The [`ExecutionStage`](crate::stages::execution::ExecutionStage) does not
+/// write the receipts that would be pruned right away, so this stage only has to prune the blocks
+/// that fall out of the retention window as the chain advances.
+///
+/// Senders are removed from the [`TxSenders`][tables::TxSenders] table, the execution recovers them
+/// from the signatures of the transactions again if the blocks have to be re-executed.
+///
+/// The history of a full node is removed from the changesets and history indices, which makes the
+/// state of the pruned blocks unavailable and prevents unwinding below them. The last pruned block
+/// is recorded under [`HISTORY_PRUNED_TO_KEY`](tables::HISTORY_PRUNED_TO_KEY).
+///
+/// The windows are applied to the blocks after the stage progress, decreasing the distance of an
+/// existing database does not prune the data of earlier blocks.
+#[derive(Debug)]
+pub struct PruneStage {
+    /// The receipts to prune, `None` keeps all receipts.
+    pub receipts: Option<ReceiptsPruneMode>,
+    /// The number of most recent blocks to keep the transaction senders for, `None` keeps all
+    /// senders.
+    pub senders: Option<u64>,
+    /// The number of most recent blocks to keep the changesets and history indices for, `None`
+    /// keeps the history of all blocks.
+    pub history: Option<u64>,
+    /// The number of blocks after which the control flow is returned to the pipeline for commit.
+    pub commit_threshold: u64,
+}
+
+impl Default for PruneStage {
+    fn default() -> Self {
+        Self { receipts: None, senders: None, history: None, commit_threshold: 100_000 }
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database> Stage<DB> for PruneStage {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        PRUNE
+    }
+
+    /// Prune the data of the blocks that fell out of the retention windows between the stage
+    /// progress and the progress of the previous stage.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = input.stage_progress.unwrap_or_default();
+        let previous_stage_progress = input.previous_stage_progress();
+        let max_block_num = previous_stage_progress.min(stage_progress + self.commit_threshold);
+
+        if max_block_num <= stage_progress {
+            info!(target: "sync::stages::prune", target = max_block_num, stage_progress, "Target block already reached");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        if let Some(receipts) = &self.receipts {
+            prune_receipts(tx, receipts, stage_progress, max_block_num)?;
+        }
+        if let Some(distance) = self.senders {
+            prune_senders(tx, distance, stage_progress, max_block_num)?;
+        }
+        if let Some(distance) = self.history {
+            prune_history(tx, distance, stage_progress, max_block_num)?;
+        }
+
+        let done = max_block_num >= previous_stage_progress;
+        info!(target: "sync::stages::prune", stage_progress = max_block_num, done, "Sync iteration finished");
+        Ok(ExecOutput { stage_progress: max_block_num, done })
+    }
+
+    /// Unwind the stage.
+    async fn unwind(
+        &mut self,
+        _tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        // Pruned data can not be restored, the data of the unwound blocks is removed by the
+        // stages that wrote it.
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+/// Returns the blocks that fall out of the window of the last `distance` blocks when the tip moves
+/// from `from` to `to`.
+fn pruned_blocks(
+    distance: u64,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> Option<RangeInclusive<BlockNumber>> {
+    let prune_to = to.checked_sub(distance)?;
+    let prune_from = from.checked_sub(distance).map_or(0, |pruned| pruned + 1);
+    Some(prune_from..=prune_to)
+}
+
+/// Returns the transactions of the blocks.
+fn block_transactions<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    blocks: &RangeInclusive<BlockNumber>,
+) -> Result<Range<TxNumber>, StageError> {
+    let start_tx = tx.get_block_body_by_num(*blocks.start())?.start_tx_id;
+    let end_tx = {
+        let body = tx.get_block_body_by_num(*blocks.end())?;
+        body.start_tx_id + body.tx_count
+    };
+    Ok(start_tx..end_tx)
+}
+
+/// Prunes the receipts of the blocks that fall out of the window when the tip moves from `from` to
+/// `to`.
+fn prune_receipts<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    mode: &ReceiptsPruneMode,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> Result<(), StageError> {
+    let blocks = match pruned_blocks(mode.distance, from, to) {
+        Some(blocks) => blocks,
+        None => return Ok(()),
+    };
+    let transactions = block_transactions(tx, &blocks)?;
+
+    let mut receipts = tx.cursor::<tables::Receipts>()?;
+    let pruned = receipts
+        .walk_range(transactions)?
+        .filter_map(|res| match res {
+            Ok((tx_number, receipt)) => (!mode.retains(&receipt)).then_some(Ok(tx_number)),
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!(target: "sync::stages::prune", ?blocks, receipts = pruned.len(), "Pruning receipts");
+    for tx_number in pruned {
+        tx.delete::<tables::Receipts>(tx_number, None)?;
+    }
+    Ok(())
+}
+
+/// Prunes the transaction senders of the blocks that fall out of the window of the last `distance`
+/// blocks when the tip moves from `from` to `to`.
+fn prune_senders<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    distance: u64,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> Result<(), StageError> {
+    let blocks = match pruned_blocks(distance, from, to) {
+        Some(blocks) => blocks,
+        None => return Ok(()),
+    };
+    let transactions = block_transactions(tx, &blocks)?;
+
+    let mut senders = tx.cursor::<tables::TxSenders>()?;
+    let pruned = senders
+        .walk_range(transactions)?
+        .map(|res| res.map(|(tx_number, _)| tx_number))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!(target: "sync::stages::prune", ?blocks, senders = pruned.len(), "Pruning senders");
+    for tx_number in pruned {
+        tx.delete::<tables::TxSenders>(tx_number, None)?;
+    }
+    Ok(())
+}
+
+/// Prunes the changesets and history indices of the blocks that fall out of the window of the last
+/// `distance` blocks when the tip moves from `from` to `to`.
+fn prune_history<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    distance: u64,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> Result<(), StageError> {
+    let blocks = match pruned_blocks(distance, from, to) {
+        Some(blocks) => blocks,
+        None => return Ok(()),
+    };
+    let (_, first_pruned) = tx.get_next_block_ids(*blocks.start())?;
+    let first_kept = tx.get_block_transition_by_num(*blocks.end())? + 1;
+
+    let mut account_changesets = tx.cursor::<tables::AccountChangeSet>()?;
+    let accounts = account_changesets
+        .walk_range(first_pruned..first_kept)?
+        .map(|res| res.map(|(transition, _)| transition))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut storage_changesets = tx.cursor::<tables::StorageChangeSet>()?;
+    let storages = storage_changesets
+        .walk((first_pruned, Address::zero()).into())?
+        .take_while(|res| {
+            res.as_ref().map(|(k, _)| k.transition_id() < first_kept).unwrap_or_default()
+        })
+        .map(|res| res.map(|(key, _)| key))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!(target: "sync::stages::prune", ?blocks, accounts = accounts.len(), storages = storages.len(), "Pruning changesets");
+    for transition in accounts.into_iter().dedup() {
+        tx.delete::<tables::AccountChangeSet>(transition, None)?;
+    }
+    for key in storages.into_iter().dedup() {
+        tx.delete::<tables::StorageChangeSet>(key, None)?;
+    }
+
+    prune_history_index::<DB, tables::AccountHistory>(tx, first_kept)?;
+    prune_history_index::<DB, tables::StorageHistory>(tx, first_kept)?;
+
+    tx.put::<tables::Config>(
+        tables::HISTORY_PRUNED_TO_KEY.to_vec(),
+        blocks.end().to_be_bytes().to_vec(),
+    )?;
+    Ok(())
+}
+
+/// Removes the entries of a history index that only point to transitions before `first_kept`.
+fn prune_history_index<DB, T>(
+    tx: &Transaction<'_, DB>,
+    first_kept: TransitionId,
+) -> Result<(), StageError>
+where
+    DB: Database,
+    T: Table<Value = TransitionList>,
+{
+    let mut cursor = tx.cursor::<T>()?;
+    let mut pruned = Vec::new();
+    let mut entry = cursor.first()?;
+    while let Some((key, list)) = entry {
+        if list.iter(0).last().map_or(true, |last| (last as u64) < first_kept) {
+            pruned.push(key);
+        }
+        entry = cursor.next()?;
+    }
+
+    info!(target: "sync::stages::prune", table = T::NAME, entries = pruned.len(), "Pruning history index");
+    for key in pruned {
+        tx.delete::<T>(key, None)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db::{
+        mdbx::{test_utils::create_test_db, EnvKind, WriteMap},
+        models::{AccountBeforeTx, ShardedKey},
+    };
+    use reth_interfaces::test_utils::generators::random_block_range;
+    use reth_primitives::{IntegerList, Log, StorageEntry, H160, H256};
+    use reth_provider::insert_canonical_block;
+    use std::ops::DerefMut;
+
+    fn input(stage_progress: Option<u64>, target: u64) -> ExecInput {
+        ExecInput { previous_stage: Some((StageId("LogIndex"), target)), stage_progress }
+    }
+
+    #[test]
+    fn receipts_outside_of_window_are_pruned() {
+        let contract = H160::from_low_u64_be(1);
+        let mode = ReceiptsPruneMode::new(10).with_contracts(vec![contract]);
+        let receipt = Receipt::default();
+        let deposit = Receipt {
+            logs: vec![Log { address: contract, ..Default::default() }],
+            ..Default::default()
+        };
+
+        assert_eq!(mode.prune_to(5), None);
+        assert!(!mode.is_pruned(0, 5, &receipt));
+        assert_eq!(mode.prune_to(100), Some(90));
+        assert!(mode.is_pruned(90, 100, &receipt));
+        assert!(!mode.is_pruned(91, 100, &receipt));
+        assert!(!mode.is_pruned(90, 100, &deposit));
+    }
+
+    #[tokio::test]
+    async fn prune_receipts_as_chain_advances() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        for block in random_block_range(0..10, H256::zero(), 1..3) {
+            insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+        }
+
+        // the receipts of odd blocks have a log of the contract
+        let contract = H160::from_low_u64_be(1);
+        let mut blocks = Vec::new();
+        for number in 0..10u64 {
+            let body = tx.get_block_body_by_num(number).unwrap();
+            for tx_number in body.tx_id_range() {
+                let logs = (number % 2 == 1)
+                    .then(|| vec![Log { address: contract, ..Default::default() }])
+                    .unwrap_or_default();
+                tx.put::<tables::Receipts>(tx_number, Receipt { logs, ..Default::default() })
+                    .unwrap();
+                blocks.push((tx_number, number));
+            }
+        }
+        let remaining = |tx: &Transaction<'_, _>| {
+            let mut cursor = tx.cursor::<tables::Receipts>().unwrap();
+            let tx_numbers = cursor.walk(0).unwrap().map(|res| res.unwrap().0).collect::<Vec<_>>();
+            blocks
+                .iter()
+                .filter(|(tx_number, _)| tx_numbers.contains(tx_number))
+                .map(|(_, number)| *number)
+                .collect::<Vec<_>>()
+        };
+
+        let mode = ReceiptsPruneMode::new(3).with_contracts(vec![contract]);
+        let mut stage =
+            PruneStage { receipts: Some(mode), commit_threshold: 5, ..Default::default() };
+
+        // the tip moves from #0 to #5, so the receipts up to #2 are pruned
+        let output = stage.execute(&mut tx, input(None, 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 5, done: false });
+        let mut expected = blocks.iter().map(|(_, number)| *number).collect::<Vec<_>>();
+        expected.retain(|number| *number > 2 || number % 2 == 1);
+        assert_eq!(remaining(&tx), expected);
+
+        // the tip moves to #9, so the receipts up to #6 are pruned
+        let output = stage.execute(&mut tx, input(Some(5), 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 9, done: true });
+        expected.retain(|number| *number > 6 || number % 2 == 1);
+        assert_eq!(remaining(&tx), expected);
+    }
+
+    #[tokio::test]
+    async fn prune_senders_outside_of_window() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        for block in random_block_range(0..10, H256::zero(), 1..3) {
+            insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+        }
+
+        let mut stage = PruneStage { senders: Some(3), ..Default::default() };
+        let output = stage.execute(&mut tx, input(None, 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 9, done: true });
+
+        // only the senders of the last 3 blocks are kept
+        let first_kept = tx.get_block_body_by_num(7).unwrap().start_tx_id;
+        let last = tx.get_block_body_by_num(9).unwrap().last_tx_index();
+        let mut cursor = tx.cursor::<tables::TxSenders>().unwrap();
+        let remaining = cursor.walk(0).unwrap().map(|res| res.unwrap().0).collect::<Vec<_>>();
+        assert_eq!(remaining, (first_kept..=last).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn prune_history_outside_of_window() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let mut tx = Transaction::new(db.as_ref()).unwrap();
+
+        for block in random_block_range(0..10, H256::zero(), 1..3) {
+            insert_canonical_block(tx.deref_mut(), &block, false).unwrap();
+        }
+
+        let address = H160::from_low_u64_be(1);
+        let last = tx.get_block_transition_by_num(9).unwrap();
+        for transition in 0..=last {
+            tx.put::<tables::AccountChangeSet>(transition, AccountBeforeTx { address, info: None })
+                .unwrap();
+            tx.put::<tables::StorageChangeSet>(
+                (transition, address).into(),
+                StorageEntry { key: H256::zero(), value: Default::default() },
+            )
+            .unwrap();
+        }
+        let first_kept = tx.get_block_transition_by_num(6).unwrap() + 1;
+        let list = |transitions: &[u64]| {
+            IntegerList::new(transitions.iter().map(|t| *t as usize).collect::<Vec<_>>()).unwrap()
+        };
+        let (old, recent) = (ShardedKey::new(address, 1), ShardedKey::new(address, last));
+        tx.put::<tables::AccountHistory>(old.clone(), list(&[0, 1])).unwrap();
+        tx.put::<tables::AccountHistory>(recent.clone(), list(&[1, first_kept])).unwrap();
+
+        let mut stage = PruneStage { history: Some(3), ..Default::default() };
+        let output = stage.execute(&mut tx, input(None, 9)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 9, done: true });
+
+        // only the changesets of the last 3 blocks are kept
+        let mut accounts = tx.cursor::<tables::AccountChangeSet>().unwrap();
+        let remaining = accounts.walk(0).unwrap().map(|res| res.unwrap().0).collect::<Vec<_>>();
+        assert_eq!(remaining, (first_kept..=last).collect::<Vec<_>>());
+        let mut storages = tx.cursor::<tables::StorageChangeSet>().unwrap();
+        let remaining = storages
+            .walk((0, Address::zero()).into())
+            .unwrap()
+            .map(|res| res.unwrap().0.transition_id())
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, (first_kept..=last).collect::<Vec<_>>());
+
+        // the index entries that only point to pruned transitions are removed
+        assert_eq!(tx.get::<tables::AccountHistory>(old).unwrap(), None);
+        assert!(tx.get::<tables::AccountHistory>(recent).unwrap().is_some());
+        assert_eq!(tx.get_history_pruned_to().unwrap(), Some(6));
+    }
+}
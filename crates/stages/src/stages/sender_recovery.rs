@@ -15,7 +15,8 @@ use std::fmt::Debug;
 use thiserror::Error;
 use tracing::*;
 
-const SENDER_RECOVERY: StageId = StageId("SenderRecovery");
+/// The [`StageId`] of the sender recovery stage.
+pub const SENDER_RECOVERY: StageId = StageId("SenderRecovery");
 
 /// The sender recovery stage iterates over existing transactions,
 /// recovers the transaction signer and stores them
@@ -86,9 +87,7 @@ impl<DB: Database> Stage<DB> for SenderRecoveryStage {
         // Acquire the cursor over the transactions
         let mut tx_cursor = tx.cursor::<tables::Transactions>()?;
         // Walk the transactions from start to end index (inclusive)
-        let entries = tx_cursor
-            .walk(start_tx_index)?
-            .take_while(|res| res.as_ref().map(|(k, _)| *k <= end_tx_index).unwrap_or_default());
+        let entries = tx_cursor.walk_range(start_tx_index..=end_tx_index)?;
 
         // Iterate over transactions in chunks
         info!(target: "sync::stages::sender_recovery", start_tx_index, end_tx_index, "Recovering senders");
@@ -121,9 +120,11 @@ impl<DB: Database> Stage<DB> for SenderRecoveryStage {
         tx: &mut Transaction<'_, DB>,
         input: UnwindInput,
     ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
-        // Lookup latest tx id that we should unwind to
-        let latest_tx_id = tx.get_block_body_by_num(input.unwind_to)?.last_tx_index();
-        tx.unwind_table_by_num::<tables::TxSenders>(latest_tx_id)?;
+        // Discard the senders of the transactions of the unwound blocks, the blocks up to
+        // `unwind_to` may not have any transactions.
+        let body = tx.get_block_body_by_num(input.unwind_to)?;
+        let first_unwound_tx = body.start_tx_id + body.tx_count;
+        tx.unwind_table::<tables::TxSenders, _>(first_unwound_tx, |tx_number| tx_number + 1)?;
         Ok(UnwindOutput { stage_progress: input.unwind_to })
     }
 }
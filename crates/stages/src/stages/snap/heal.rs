@@ -0,0 +1,347 @@
+//! The healing of the local state into the state of a pivot.
+use super::{rlp, write_slot, Response, SnapSync, SnapSyncStage};
+use crate::{db::Transaction, StageError};
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_interfaces::p2p::snap::client::{GetTrieNodes, SnapClient};
+use reth_primitives::{
+    keccak256,
+    proofs::{TrieAccount, EMPTY_ROOT},
+    trie::{encode_compact, from_nibbles, to_nibbles, Trie, TrieNode},
+    Bytes, H256, KECCAK_EMPTY, U256,
+};
+use reth_rlp::Decodable;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use tracing::*;
+
+/// The maximum number of trie nodes of a heal request.
+const MAX_TRIE_NODES: usize = 128;
+
+/// A node of the remote state that differs from the local one.
+#[derive(Debug, Clone)]
+struct HealTask {
+    /// The account of the storage trie of the node, `None` for nodes of the account trie.
+    account: Option<H256>,
+    /// The nibbles of the path of the node.
+    path: Vec<u8>,
+    /// The hash of the node.
+    hash: H256,
+}
+
+// === impl HealTask ===
+
+impl HealTask {
+    /// Returns the path set of the node in a [GetTrieNodes] request.
+    fn path_set(&self) -> Vec<Bytes> {
+        let path = Bytes::from(encode_compact(&self.path, false));
+        match self.account {
+            Some(account) => vec![account.as_bytes().to_vec().into(), path],
+            None => vec![path],
+        }
+    }
+}
+
+/// Heals the local state into the state of a root.
+///
+/// The nodes of the remote tries are downloaded top-down, and only the children that differ from
+/// the nodes at the same path of the local tries are followed. Every downloaded node replaces the
+/// local keys below its path with the ones it covers, so the local tries equal the remote ones
+/// once no node is left.
+///
+/// The local tries are built in memory from the hashed state tables, and every write to the
+/// tables is applied to them as well.
+#[derive(Debug)]
+pub(super) struct Healer {
+    /// The local account trie.
+    accounts: Trie,
+    /// The local storage tries by the hash of the address of the account.
+    storages: HashMap<H256, Trie>,
+    /// The nodes that are yet to be downloaded, breadth-first.
+    queue: VecDeque<HealTask>,
+}
+
+// === impl Healer ===
+
+impl Healer {
+    /// Builds the local tries and queues the root, if it differs from the local one.
+    pub(super) fn new<DB: Database>(
+        tx: &Transaction<'_, DB>,
+        root: H256,
+        codes: &mut BTreeSet<H256>,
+    ) -> Result<Self, StageError> {
+        let mut slots = BTreeMap::<H256, Vec<_>>::new();
+        for entry in tx.cursor_dup::<tables::HashedStorage>()?.walk_range(..)? {
+            let (account, entry) = entry?;
+            slots.entry(account).or_default().push((entry.key, rlp(&entry.value)));
+        }
+        let storages = slots
+            .into_iter()
+            .map(|(account, slots)| (account, Trie::from_entries(slots)))
+            .collect::<HashMap<_, _>>();
+
+        let mut accounts = Vec::new();
+        for entry in tx.cursor::<tables::HashedAccount>()?.walk_range(..)? {
+            let (hash, account) = entry?;
+            let storage_root = storages.get(&hash).map_or(EMPTY_ROOT, Trie::root_hash);
+            accounts.push((hash, rlp(&TrieAccount::new(account, storage_root))));
+        }
+
+        let mut healer =
+            Self { accounts: Trie::from_entries(accounts), storages, queue: VecDeque::new() };
+        healer.apply(tx, None, Vec::new(), reference(root), codes)?;
+        Ok(healer)
+    }
+
+    /// Returns the root of the local account trie.
+    fn root(&self) -> H256 {
+        self.accounts.root_hash()
+    }
+
+    /// Returns the local trie of the account, or the account trie.
+    fn trie(&self, account: Option<H256>) -> Option<&Trie> {
+        match account {
+            Some(account) => self.storages.get(&account),
+            None => Some(&self.accounts),
+        }
+    }
+
+    /// Applies the remote node at the path to the local state.
+    ///
+    /// Returns the number of written entries.
+    fn apply<DB: Database>(
+        &mut self,
+        tx: &Transaction<'_, DB>,
+        account: Option<H256>,
+        path: Vec<u8>,
+        node: TrieNode,
+        codes: &mut BTreeSet<H256>,
+    ) -> Result<usize, StageError> {
+        match node {
+            TrieNode::Empty => self.delete_keys(tx, account, &path, None),
+            TrieNode::Hash(hash) => {
+                let local = self.trie(account).and_then(|trie| trie.node_at(&path));
+                if local.map(TrieNode::hash) != Some(hash) {
+                    self.queue.push_back(HealTask { account, path, hash });
+                }
+                Ok(0)
+            }
+            TrieNode::Leaf { path: rest, value } => {
+                let key = [path.as_slice(), rest.as_slice()].concat();
+                let deleted = self.delete_keys(tx, account, &path, Some(&key))?;
+                let key = from_nibbles(&key)
+                    .filter(|key| key.len() == 32)
+                    .map(|key| H256::from_slice(&key))
+                    .ok_or_else(|| StageError::Download("trie leaf of invalid length".into()))?;
+                Ok(deleted + self.write_leaf(tx, account, key, value, codes)?)
+            }
+            TrieNode::Extension { path: extension, child } => {
+                let child_path = [path.as_slice(), extension.as_slice()].concat();
+                let deleted = self.delete_keys(tx, account, &path, Some(&child_path))?;
+                Ok(deleted + self.apply(tx, account, child_path, *child, codes)?)
+            }
+            TrieNode::Branch { children, .. } => {
+                // the keys of the state and storage tries all have the same length, so branches
+                // have no values
+                let mut written = 0;
+                for (nibble, child) in (*children).into_iter().enumerate() {
+                    let child_path = [path.as_slice(), &[nibble as u8][..]].concat();
+                    written += self.apply(tx, account, child_path, child, codes)?;
+                }
+                Ok(written)
+            }
+        }
+    }
+
+    /// Writes the leaf of a remote trie to the local state.
+    ///
+    /// The storage of accounts whose storage root differs from the local one is healed, and
+    /// unknown codes are queued for download.
+    fn write_leaf<DB: Database>(
+        &mut self,
+        tx: &Transaction<'_, DB>,
+        account: Option<H256>,
+        key: H256,
+        value: Vec<u8>,
+        codes: &mut BTreeSet<H256>,
+    ) -> Result<usize, StageError> {
+        let invalid = |err: reth_rlp::DecodeError| StageError::Download(err.to_string());
+        let Some(account) = account else {
+            let trie_account = TrieAccount::decode(&mut value.as_slice()).map_err(invalid)?;
+            tx.put::<tables::HashedAccount>(key, trie_account.account())?;
+            self.accounts.insert(key.as_bytes(), value).expect("local trie is fully known");
+
+            if trie_account.code_hash != KECCAK_EMPTY &&
+                tx.get::<tables::Bytecodes>(trie_account.code_hash)?.is_none()
+            {
+                codes.insert(trie_account.code_hash);
+            }
+            let storage_root = self.storages.get(&key).map_or(EMPTY_ROOT, Trie::root_hash);
+            let written = if storage_root == trie_account.storage_root {
+                0
+            } else {
+                self.apply(tx, Some(key), Vec::new(), reference(trie_account.storage_root), codes)?
+            };
+            return Ok(written + 1)
+        };
+
+        let slot = U256::decode(&mut value.as_slice()).map_err(invalid)?;
+        write_slot(tx, account, key, slot)?;
+        self.storages
+            .entry(account)
+            .or_default()
+            .insert(key.as_bytes(), value)
+            .expect("local trie is fully known");
+        Ok(1)
+    }
+
+    /// Deletes the local keys with the nibbles as prefix, except for the ones with the prefix to
+    /// keep.
+    ///
+    /// The storage of deleted accounts is deleted along with them.
+    fn delete_keys<DB: Database>(
+        &mut self,
+        tx: &Transaction<'_, DB>,
+        account: Option<H256>,
+        prefix: &[u8],
+        keep: Option<&[u8]>,
+    ) -> Result<usize, StageError> {
+        let bound = |nibble: u8| {
+            let mut nibbles = prefix.to_vec();
+            nibbles.resize(64, nibble);
+            H256::from_slice(&from_nibbles(&nibbles).expect("even number of nibbles"))
+        };
+        let (start, end) = (bound(0), bound(0x0f));
+        let deleted =
+            |key: &H256| keep.map_or(true, |keep| !to_nibbles(key.as_bytes()).starts_with(keep));
+
+        match account {
+            None => {
+                let keys = tx
+                    .cursor::<tables::HashedAccount>()?
+                    .walk(start)?
+                    .take_while(|entry| entry.as_ref().map_or(true, |(key, _)| *key <= end))
+                    .map(|entry| entry.map(|(key, _)| key))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let keys = keys.into_iter().filter(deleted).collect::<Vec<_>>();
+                for key in &keys {
+                    tx.delete::<tables::HashedAccount>(*key, None)?;
+                    tx.delete::<tables::HashedStorage>(*key, None)?;
+                    self.accounts.remove(key.as_bytes()).expect("local trie is fully known");
+                    self.storages.remove(key);
+                }
+                Ok(keys.len())
+            }
+            Some(account) => {
+                let entries = tx
+                    .cursor_dup::<tables::HashedStorage>()?
+                    .walk_dup(account, Some(start))?
+                    .take_while(|entry| entry.as_ref().map_or(true, |(_, entry)| entry.key <= end))
+                    .map(|entry| entry.map(|(_, entry)| entry))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let entries =
+                    entries.into_iter().filter(|entry| deleted(&entry.key)).collect::<Vec<_>>();
+                let len = entries.len();
+                for entry in entries {
+                    if let Some(trie) = self.storages.get_mut(&account) {
+                        trie.remove(entry.key.as_bytes()).expect("local trie is fully known");
+                    }
+                    tx.delete::<tables::HashedStorage>(account, Some(entry))?;
+                }
+                Ok(len)
+            }
+        }
+    }
+}
+
+impl<C: SnapClient> SnapSyncStage<C> {
+    /// Downloads the next nodes of the heal queue and applies them to the local state.
+    ///
+    /// Finishes the healing once the queue is empty.
+    pub(super) async fn heal<DB: Database>(
+        &self,
+        tx: &Transaction<'_, DB>,
+        sync: &mut SnapSync,
+    ) -> Result<Option<usize>, StageError> {
+        let mut healer = match sync.healer.take() {
+            Some(healer) => healer,
+            None => Healer::new(tx, sync.root, &mut sync.codes)?,
+        };
+
+        let len = healer.queue.len().min(MAX_TRIE_NODES);
+        let tasks = healer.queue.drain(..len).collect::<Vec<_>>();
+        if tasks.is_empty() {
+            if healer.root() != sync.root {
+                return Err(StageError::Download(format!(
+                    "healed state root {:?} does not match the pivot state root {:?}",
+                    healer.root(),
+                    sync.root
+                )))
+            }
+            info!(target: "sync::stages::snap", pivot = sync.pivot, "Healed the state");
+            sync.heal = false;
+            return Ok(Some(0))
+        }
+
+        let request = GetTrieNodes {
+            request_id: 0,
+            root_hash: sync.root,
+            paths: tasks.iter().map(HealTask::path_set).collect(),
+            response_bytes: self.response_bytes,
+        };
+        let nodes = self
+            .fetch(
+                || self.client.get_trie_nodes(request.clone()),
+                |response| {
+                    if response.nodes.is_empty() {
+                        return Response::Unavailable
+                    }
+                    if response.nodes.len() > tasks.len() {
+                        return Response::Invalid
+                    }
+                    let mut nodes = Vec::with_capacity(response.nodes.len());
+                    for (task, node) in tasks.iter().zip(&response.nodes) {
+                        if keccak256(node) != task.hash {
+                            return Response::Invalid
+                        }
+                        let Ok(node) = TrieNode::decode(node) else { return Response::Invalid };
+                        nodes.push(node);
+                    }
+                    Response::Valid(nodes)
+                },
+            )
+            .await;
+
+        // the nodes that were not served are requested again
+        let served = nodes.as_ref().map_or(0, Vec::len);
+        for task in tasks[served..].iter().rev() {
+            healer.queue.push_front(task.clone());
+        }
+
+        let Some(nodes) = nodes else {
+            sync.healer = Some(healer);
+            return Ok(None)
+        };
+
+        let mut written = 0;
+        for (task, node) in tasks.into_iter().zip(nodes) {
+            written += healer.apply(tx, task.account, task.path, node, &mut sync.codes)?;
+        }
+        debug!(target: "sync::stages::snap", nodes = served, written, queued = healer.queue.len(), "Healed trie nodes");
+
+        sync.healer = Some(healer);
+        Ok(Some(written))
+    }
+}
+
+/// Returns the reference to the node of a root, which is empty for the empty root.
+fn reference(root: H256) -> TrieNode {
+    if root == EMPTY_ROOT {
+        TrieNode::Empty
+    } else {
+        TrieNode::Hash(root)
+    }
+}
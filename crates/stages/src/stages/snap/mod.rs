@@ -0,0 +1,764 @@
+use crate::{
+    db::Transaction, DatabaseIntegrityError, ExecInput, ExecOutput, Stage, StageError, StageId,
+    UnwindInput, UnwindOutput,
+};
+use reth_db::{
+    cursor::DbDupCursorRO,
+    database::Database,
+    tables,
+    transaction::{DbTx, DbTxMut},
+};
+use reth_interfaces::p2p::{
+    error::PeerRequestResult,
+    snap::client::{GetAccountRange, GetByteCodes, GetStorageRanges, SnapClient},
+};
+use reth_primitives::{
+    keccak256,
+    proofs::{TrieAccount, EMPTY_ROOT},
+    trie::verify_range_proof,
+    BlockNumber, Bytes, StorageEntry, H256, KECCAK_EMPTY, U256,
+};
+use reth_rlp::{Decodable, Encodable};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+use tracing::*;
+
+mod heal;
+
+use heal::Healer;
+
+/// The [`StageId`] of the snap sync stage.
+pub const SNAP_SYNC: StageId = StageId("SnapSync");
+
+/// The maximum number of accounts of a storage request.
+const MAX_STORAGE_ACCOUNTS: usize = 128;
+
+/// The maximum number of hashes of a code request.
+const MAX_CODES: usize = 64;
+
+/// The delay before a failed request is sent again.
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// The snap sync stage downloads the state of a recent block, the pivot, from peers of the
+/// `snap/1` protocol instead of executing all blocks.
+///
+/// The state is downloaded as ranges of accounts and storage slots that are proven against the
+/// state root of the pivot, which lags [`pivot_distance`](SnapSyncStage::with_pivot_distance)
+/// blocks behind the previous stage. Peers only serve the state of recent blocks, so the pivot
+/// moves along with the previous stage once its state is no longer served. The ranges of the
+/// previous pivots are then healed: the trie nodes of the new pivot are downloaded top-down
+/// wherever they differ from the local tries. The state of a synced node is healed the same way
+/// whenever the previous stage moves the pivot, so the stage follows the chain in place of the
+/// execution.
+///
+/// Only the [`HashedAccount`][tables::HashedAccount], [`HashedStorage`][tables::HashedStorage] and
+/// [`Bytecodes`][tables::Bytecodes] tables are written, the plain state can't be recovered from
+/// the hashes. The local tries of the healing are kept in memory, and so is the progress within a
+/// pivot: a restarted node downloads the state of the pivot again, or heals it if a pivot was
+/// reached before.
+#[derive(Debug)]
+pub struct SnapSyncStage<C> {
+    /// The client of the `snap` peers.
+    client: Arc<C>,
+    /// The number of blocks the pivot lags behind the previous stage.
+    pivot_distance: u64,
+    /// The soft limit of the size of the responses.
+    response_bytes: u64,
+    /// The number of written entries after which the control flow is returned to the pipeline
+    /// for commit.
+    commit_threshold: usize,
+    /// The number of attempts of a request before the state of the pivot is considered
+    /// unavailable.
+    max_attempts: usize,
+    /// The progress of the sync of the current pivot.
+    sync: Option<SnapSync>,
+}
+
+// === impl SnapSyncStage ===
+
+impl<C: SnapClient> SnapSyncStage<C> {
+    /// Creates the stage with the client of the `snap` peers.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            pivot_distance: 64,
+            response_bytes: 512 * 1024,
+            commit_threshold: 100_000,
+            max_attempts: 5,
+            sync: None,
+        }
+    }
+
+    /// Sets the number of blocks the pivot lags behind the previous stage.
+    pub fn with_pivot_distance(mut self, pivot_distance: u64) -> Self {
+        self.pivot_distance = pivot_distance;
+        self
+    }
+
+    /// Sets the soft limit of the size of the responses.
+    pub fn with_response_bytes(mut self, response_bytes: u64) -> Self {
+        self.response_bytes = response_bytes;
+        self
+    }
+
+    /// Sets the number of written entries after which the stage commits.
+    pub fn with_commit_threshold(mut self, commit_threshold: usize) -> Self {
+        self.commit_threshold = commit_threshold;
+        self
+    }
+
+    /// Sets the number of attempts of a request before the state of the pivot is considered
+    /// unavailable.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Downloads and heals the state of the pivot until the commit threshold is reached.
+    async fn sync<DB: Database>(
+        &self,
+        tx: &Transaction<'_, DB>,
+        sync: &mut SnapSync,
+    ) -> Result<SyncOutcome, StageError> {
+        let mut written = 0;
+        while written < self.commit_threshold {
+            let progress = if let Some(origin) = sync.next_account {
+                self.download_accounts(tx, sync, origin).await?
+            } else if !sync.storages.is_empty() {
+                self.download_storages(tx, sync).await?
+            } else if !sync.codes.is_empty() {
+                self.download_codes(tx, sync).await?
+            } else if sync.heal {
+                self.heal(tx, sync).await?
+            } else {
+                return Ok(SyncOutcome::Done)
+            };
+
+            let Some(progress) = progress else { return Ok(SyncOutcome::Unavailable) };
+            written += progress;
+        }
+        Ok(SyncOutcome::Commit)
+    }
+
+    /// Downloads the next range of accounts and queues their storage and code.
+    async fn download_accounts<DB: Database>(
+        &self,
+        tx: &Transaction<'_, DB>,
+        sync: &mut SnapSync,
+        origin: H256,
+    ) -> Result<Option<usize>, StageError> {
+        let root = sync.root;
+        let request = GetAccountRange {
+            request_id: 0,
+            root_hash: root,
+            starting_hash: origin,
+            limit_hash: H256::repeat_byte(0xff),
+            response_bytes: self.response_bytes,
+        };
+        let range = self
+            .fetch(
+                || self.client.get_account_range(request.clone()),
+                |range| {
+                    if range.accounts.is_empty() && range.proof.is_empty() && root != EMPTY_ROOT {
+                        return Response::Unavailable
+                    }
+                    let leaves = range
+                        .accounts
+                        .iter()
+                        .map(|account| (account.hash, rlp(&account.account)))
+                        .collect::<Vec<_>>();
+                    match verify_range_proof(root, origin, &leaves, &range.proof) {
+                        Ok(has_more) => Response::Valid((range.accounts, has_more)),
+                        Err(_) => Response::Invalid,
+                    }
+                },
+            )
+            .await;
+        let Some((accounts, has_more)) = range else { return Ok(None) };
+
+        for account in &accounts {
+            tx.put::<tables::HashedAccount>(account.hash, account.account.account())?;
+            sync.queue_account(tx, account.hash, &account.account)?;
+        }
+        sync.next_account =
+            if has_more { accounts.last().and_then(|last| next_hash(last.hash)) } else { None };
+
+        debug!(target: "sync::stages::snap", accounts = accounts.len(), next = ?sync.next_account, "Downloaded account range");
+        Ok(Some(accounts.len()))
+    }
+
+    /// Downloads the slots of the next accounts of the storage queue.
+    ///
+    /// Accounts whose slots were only partially downloaded are requested alone, from the first
+    /// missing slot on.
+    async fn download_storages<DB: Database>(
+        &self,
+        tx: &Transaction<'_, DB>,
+        sync: &mut SnapSync,
+    ) -> Result<Option<usize>, StageError> {
+        let tasks = match sync.storages.front() {
+            Some(first) if !first.origin.is_zero() => vec![*first],
+            _ => sync
+                .storages
+                .iter()
+                .take(MAX_STORAGE_ACCOUNTS)
+                .take_while(|task| task.origin.is_zero())
+                .copied()
+                .collect(),
+        };
+        let origin = tasks[0].origin;
+        let request = GetStorageRanges {
+            request_id: 0,
+            root_hash: sync.root,
+            account_hashes: tasks.iter().map(|task| task.account).collect(),
+            starting_hash: if origin.is_zero() {
+                Bytes::default()
+            } else {
+                origin.as_bytes().to_vec().into()
+            },
+            limit_hash: Bytes::default(),
+            response_bytes: self.response_bytes,
+        };
+        let ranges = self
+            .fetch(
+                || self.client.get_storage_ranges(request.clone()),
+                |ranges| {
+                    // all requested accounts have slots
+                    if ranges.slots.is_empty() {
+                        return Response::Unavailable
+                    }
+                    if ranges.slots.len() > tasks.len() {
+                        return Response::Invalid
+                    }
+
+                    // only the slots of the last account are proven, the others are complete
+                    let last = ranges.slots.len() - 1;
+                    let mut verified = Vec::with_capacity(ranges.slots.len());
+                    for (index, (task, slots)) in tasks.iter().zip(ranges.slots).enumerate() {
+                        let leaves = slots
+                            .into_iter()
+                            .map(|slot| (slot.hash, slot.data.to_vec()))
+                            .collect::<Vec<_>>();
+                        let proof = if index == last { &ranges.proof[..] } else { &[][..] };
+                        let Ok(has_more) =
+                            verify_range_proof(task.root, task.origin, &leaves, proof)
+                        else {
+                            return Response::Invalid
+                        };
+                        let Ok(slots) = leaves
+                            .into_iter()
+                            .map(|(key, value)| Ok((key, U256::decode(&mut value.as_slice())?)))
+                            .collect::<Result<Vec<_>, reth_rlp::DecodeError>>()
+                        else {
+                            return Response::Invalid
+                        };
+                        verified.push((slots, has_more));
+                    }
+                    Response::Valid(verified)
+                },
+            )
+            .await;
+        let Some(ranges) = ranges else { return Ok(None) };
+
+        let mut written = 0;
+        for (slots, has_more) in ranges {
+            let task = sync.storages.pop_front().expect("a task per range");
+            for (key, value) in &slots {
+                write_slot(tx, task.account, *key, *value)?;
+            }
+            written += slots.len();
+
+            if has_more {
+                let origin = slots.last().and_then(|(last, _)| next_hash(*last));
+                if let Some(origin) = origin {
+                    sync.storages.push_front(StorageTask { origin, ..task });
+                }
+            }
+        }
+
+        debug!(target: "sync::stages::snap", slots = written, remaining = sync.storages.len(), "Downloaded storage ranges");
+        Ok(Some(written))
+    }
+
+    /// Downloads the next codes of the code queue.
+    async fn download_codes<DB: Database>(
+        &self,
+        tx: &Transaction<'_, DB>,
+        sync: &mut SnapSync,
+    ) -> Result<Option<usize>, StageError> {
+        let hashes = sync.codes.iter().take(MAX_CODES).copied().collect::<Vec<_>>();
+        let request = GetByteCodes {
+            request_id: 0,
+            hashes: hashes.clone(),
+            response_bytes: self.response_bytes,
+        };
+        let codes = self
+            .fetch(
+                || self.client.get_byte_codes(request.clone()),
+                |response| {
+                    if response.codes.is_empty() {
+                        return Response::Unavailable
+                    }
+                    let codes = response
+                        .codes
+                        .into_iter()
+                        .map(|code| (keccak256(&code), code))
+                        .collect::<Vec<_>>();
+                    if codes.iter().any(|(hash, _)| !hashes.contains(hash)) {
+                        return Response::Invalid
+                    }
+                    Response::Valid(codes)
+                },
+            )
+            .await;
+        let Some(codes) = codes else { return Ok(None) };
+
+        for (hash, code) in &codes {
+            tx.put::<tables::Bytecodes>(*hash, code.to_vec())?;
+            sync.codes.remove(hash);
+        }
+
+        debug!(target: "sync::stages::snap", codes = codes.len(), remaining = sync.codes.len(), "Downloaded codes");
+        Ok(Some(codes.len()))
+    }
+
+    /// Sends the request until a peer answers with a valid response, or returns `None` if the
+    /// attempts are exhausted.
+    ///
+    /// Peers that answer with invalid responses are reported.
+    async fn fetch<R, T, F>(
+        &self,
+        mut send: impl FnMut() -> F,
+        mut verify: impl FnMut(R) -> Response<T>,
+    ) -> Option<T>
+    where
+        F: Future<Output = PeerRequestResult<R>>,
+    {
+        for attempt in 0..self.max_attempts {
+            if attempt > 0 {
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+
+            let (peer_id, response) = match send().await {
+                Ok(response) => response.split(),
+                Err(error) => {
+                    debug!(target: "sync::stages::snap", ?error, attempt, "Snap request failed");
+                    continue
+                }
+            };
+            match verify(response) {
+                Response::Valid(value) => return Some(value),
+                Response::Unavailable => {
+                    debug!(target: "sync::stages::snap", ?peer_id, attempt, "Peer does not serve the state of the pivot")
+                }
+                Response::Invalid => {
+                    warn!(target: "sync::stages::snap", ?peer_id, attempt, "Invalid snap response");
+                    self.client.report_bad_message(peer_id);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[async_trait::async_trait]
+impl<DB: Database, C: SnapClient> Stage<DB> for SnapSyncStage<C> {
+    /// Return the id of the stage
+    fn id(&self) -> StageId {
+        SNAP_SYNC
+    }
+
+    /// Download the state of the pivot, or heal the local state into it.
+    async fn execute(
+        &mut self,
+        tx: &mut Transaction<'_, DB>,
+        input: ExecInput,
+    ) -> Result<ExecOutput, StageError> {
+        let stage_progress = input.stage_progress.unwrap_or_default();
+        let pivot = input.previous_stage_progress().saturating_sub(self.pivot_distance);
+
+        if pivot <= stage_progress {
+            info!(target: "sync::stages::snap", target = pivot, stage_progress, "Target block already reached");
+            return Ok(ExecOutput { stage_progress, done: true })
+        }
+
+        let key = tx.get_block_numhash(pivot)?;
+        let root = tx
+            .get::<tables::Headers>(key)?
+            .ok_or(DatabaseIntegrityError::Header { number: pivot, hash: key.hash() })?
+            .state_root;
+
+        // The progress is only kept if the execution succeeds, the writes of a failed one are
+        // discarded.
+        let mut sync = match self.sync.take() {
+            Some(mut sync) => {
+                if sync.pivot != pivot {
+                    info!(target: "sync::stages::snap", from = sync.pivot, to = pivot, "Moving the pivot");
+                    sync.move_pivot(pivot, root);
+                }
+                sync
+            }
+            None if stage_progress == 0 => {
+                info!(target: "sync::stages::snap", pivot, ?root, "Downloading the state of the pivot");
+                tx.clear::<tables::HashedAccount>()?;
+                tx.clear::<tables::HashedStorage>()?;
+                tx.clear::<tables::Bytecodes>()?;
+                SnapSync::download(pivot, root)
+            }
+            None => {
+                info!(target: "sync::stages::snap", pivot, ?root, stage_progress, "Healing the state into the pivot");
+                SnapSync::heal(pivot, root)
+            }
+        };
+
+        match self.sync(tx, &mut sync).await? {
+            SyncOutcome::Commit => {
+                self.sync = Some(sync);
+                Ok(ExecOutput { stage_progress, done: false })
+            }
+            SyncOutcome::Unavailable => {
+                // the pivot moves once the previous stage progresses
+                warn!(target: "sync::stages::snap", pivot, "The state of the pivot is not served");
+                self.sync = Some(sync);
+                Ok(ExecOutput { stage_progress, done: true })
+            }
+            SyncOutcome::Done => {
+                info!(target: "sync::stages::snap", stage_progress = pivot, "Sync iteration finished");
+                Ok(ExecOutput { stage_progress: pivot, done: true })
+            }
+        }
+    }
+
+    /// Unwind the stage.
+    ///
+    /// The state is kept, it's healed into the next pivot.
+    async fn unwind(
+        &mut self,
+        _tx: &mut Transaction<'_, DB>,
+        input: UnwindInput,
+    ) -> Result<UnwindOutput, Box<dyn std::error::Error + Send + Sync>> {
+        self.sync = None;
+        Ok(UnwindOutput { stage_progress: input.unwind_to })
+    }
+}
+
+/// The progress of the sync of the state of a pivot.
+#[derive(Debug)]
+struct SnapSync {
+    /// The number of the pivot.
+    pivot: BlockNumber,
+    /// The state root of the pivot.
+    root: H256,
+    /// The hash of the next account to download, `None` once all accounts are downloaded.
+    next_account: Option<H256>,
+    /// The accounts whose slots are yet to be downloaded.
+    storages: VecDeque<StorageTask>,
+    /// The hashes of the codes that are yet to be downloaded.
+    codes: BTreeSet<H256>,
+    /// Whether the state is to be healed once the ranges are downloaded.
+    heal: bool,
+    /// The healing of the state, once it started.
+    healer: Option<Healer>,
+}
+
+// === impl SnapSync ===
+
+impl SnapSync {
+    /// Returns the progress of the download of the whole state.
+    fn download(pivot: BlockNumber, root: H256) -> Self {
+        Self {
+            pivot,
+            root,
+            next_account: Some(H256::zero()),
+            storages: VecDeque::new(),
+            codes: BTreeSet::new(),
+            heal: false,
+            healer: None,
+        }
+    }
+
+    /// Returns the progress of the healing of the local state.
+    fn heal(pivot: BlockNumber, root: H256) -> Self {
+        Self { next_account: None, heal: true, ..Self::download(pivot, root) }
+    }
+
+    /// Continues the download with the state of another pivot.
+    ///
+    /// The queued storage can't be verified against the new pivot, the accounts are healed
+    /// instead. The codes are still needed.
+    fn move_pivot(&mut self, pivot: BlockNumber, root: H256) {
+        self.pivot = pivot;
+        self.root = root;
+        self.storages.clear();
+        self.heal = true;
+        self.healer = None;
+    }
+
+    /// Queues the storage and the code of a downloaded account.
+    fn queue_account<DB: Database>(
+        &mut self,
+        tx: &Transaction<'_, DB>,
+        hash: H256,
+        account: &TrieAccount,
+    ) -> Result<(), StageError> {
+        if account.storage_root != EMPTY_ROOT {
+            self.storages.push_back(StorageTask {
+                account: hash,
+                root: account.storage_root,
+                origin: H256::zero(),
+            });
+        }
+        if account.code_hash != KECCAK_EMPTY &&
+            tx.get::<tables::Bytecodes>(account.code_hash)?.is_none()
+        {
+            self.codes.insert(account.code_hash);
+        }
+        Ok(())
+    }
+}
+
+/// The slots of an account that are yet to be downloaded.
+#[derive(Debug, Clone, Copy)]
+struct StorageTask {
+    /// The hash of the address of the account.
+    account: H256,
+    /// The root of the storage trie of the account.
+    root: H256,
+    /// The hash of the first slot that is yet to be downloaded.
+    origin: H256,
+}
+
+/// The outcome of a call to [SnapSyncStage::sync].
+enum SyncOutcome {
+    /// The commit threshold was reached.
+    Commit,
+    /// No peer serves the state of the pivot.
+    Unavailable,
+    /// The state of the pivot is complete.
+    Done,
+}
+
+/// The verdict on a response of a peer.
+enum Response<T> {
+    /// The response is valid.
+    Valid(T),
+    /// The peer does not serve the state of the pivot.
+    Unavailable,
+    /// The response is invalid.
+    Invalid,
+}
+
+/// Replaces the slot in the [`HashedStorage`][tables::HashedStorage] table or removes it if the
+/// value is zero.
+fn write_slot<DB: Database>(
+    tx: &Transaction<'_, DB>,
+    account: H256,
+    key: H256,
+    value: U256,
+) -> Result<(), reth_db::Error> {
+    // Always delete the old value as duplicate table put will not override it
+    let existing = tx
+        .cursor_dup::<tables::HashedStorage>()?
+        .seek_by_key_subkey(account, key)?
+        .filter(|entry| entry.key == key);
+    if let Some(existing) = existing {
+        tx.delete::<tables::HashedStorage>(account, Some(existing))?;
+    }
+    if !value.is_zero() {
+        tx.put::<tables::HashedStorage>(account, StorageEntry { key, value })?;
+    }
+    Ok(())
+}
+
+/// Returns the hash after the given one, if any.
+fn next_hash(hash: H256) -> Option<H256> {
+    hash.into_uint().checked_add(U256::one()).map(|next| H256::from_uint(&next))
+}
+
+/// Returns the RLP encoding of the value.
+fn rlp(value: &impl Encodable) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestTransaction;
+    use reth_db::{
+        cursor::DbCursorRO,
+        mdbx::{Env, WriteMap},
+    };
+    use reth_interfaces::test_utils::{HashedState, ResponseFault, TestSnapClient};
+    use reth_primitives::{Account, Header, PeerId};
+    use std::collections::BTreeMap;
+
+    fn input(stage_progress: u64, previous_stage_progress: u64) -> ExecInput {
+        ExecInput {
+            previous_stage: Some((StageId("SenderRecovery"), previous_stage_progress)),
+            stage_progress: Some(stage_progress),
+        }
+    }
+
+    /// Returns accounts with up to 15 slots, every fifth one with the code.
+    fn state(accounts: u64, code_hash: H256) -> HashedState {
+        (0..accounts)
+            .map(|i| {
+                let account = Account {
+                    nonce: i,
+                    balance: U256::from(i * 1000),
+                    bytecode_hash: (i % 5 == 0).then_some(code_hash),
+                };
+                let slots = (0..i % 4 * 5)
+                    .map(|slot| (keccak256(H256::from_low_u64_be(slot)), U256::from(slot + 1)))
+                    .collect();
+                (keccak256(H256::from_low_u64_be(i)), (account, slots))
+            })
+            .collect()
+    }
+
+    /// Returns the state after a block that changes, adds and removes accounts and slots.
+    fn next_state(state: &HashedState) -> HashedState {
+        let mut state = state.clone();
+        let hashes = state.keys().copied().collect::<Vec<_>>();
+        state.remove(&hashes[3]);
+        state.get_mut(&hashes[7]).unwrap().0.balance += U256::from(1);
+        let (_, slots) = state.get_mut(&hashes[10]).unwrap();
+        if let Some(first) = slots.keys().next().copied() {
+            slots.remove(&first);
+        }
+        slots.insert(H256::repeat_byte(0x01), U256::from(5));
+        state.get_mut(&hashes[11]).unwrap().1.clear();
+        state.insert(
+            H256::repeat_byte(0xaa),
+            (
+                Account { nonce: 1, balance: U256::from(1), bytecode_hash: None },
+                BTreeMap::from([(H256::repeat_byte(0x02), U256::from(3))]),
+            ),
+        );
+        state
+    }
+
+    /// Reads the state of the hashed tables.
+    fn hashed_state(tx: &Transaction<'_, Env<WriteMap>>) -> HashedState {
+        let mut state = HashedState::new();
+        for entry in tx.cursor::<tables::HashedAccount>().unwrap().walk_range(..).unwrap() {
+            let (hash, account) = entry.unwrap();
+            state.insert(hash, (account, BTreeMap::new()));
+        }
+        for entry in tx.cursor_dup::<tables::HashedStorage>().unwrap().walk_range(..).unwrap() {
+            let (hash, slot) = entry.unwrap();
+            state
+                .get_mut(&hash)
+                .expect("storage of a known account")
+                .1
+                .insert(slot.key, slot.value);
+        }
+        state
+    }
+
+    /// Executes the stage until it's done, committing after every execution.
+    async fn run(
+        stage: &mut SnapSyncStage<TestSnapClient>,
+        tx: &mut Transaction<'_, Env<WriteMap>>,
+        input: ExecInput,
+    ) -> ExecOutput {
+        loop {
+            let output = stage.execute(tx, input).await.unwrap();
+            tx.commit().unwrap();
+            if output.done {
+                return output
+            }
+        }
+    }
+
+    /// Serves two consecutive states at the blocks 10 and 20.
+    struct Setup {
+        db: TestTransaction,
+        client: Arc<TestSnapClient>,
+        states: [(H256, HashedState); 2],
+        code: Bytes,
+    }
+
+    fn setup(client: TestSnapClient) -> Setup {
+        let code = Bytes::from(vec![0x60, 0x00, 0x60, 0x00, 0xfd]);
+        let code_hash = client.insert_code(code.clone());
+        let first = state(40, code_hash);
+        let second = next_state(&first);
+        let states = [first, second].map(|state| (client.insert_state(&state), state));
+
+        let db = TestTransaction::default();
+        let headers = states
+            .iter()
+            .zip([10, 20])
+            .map(|((root, _), number)| {
+                Header { number, state_root: *root, ..Default::default() }.seal()
+            })
+            .collect::<Vec<_>>();
+        db.insert_headers(headers.iter()).unwrap();
+        Setup { db, client: Arc::new(client), states, code }
+    }
+
+    #[tokio::test]
+    async fn downloads_state_from_scratch() {
+        let peer_id = PeerId::from_low_u64_be(1);
+        let Setup { db, client, states, code } =
+            setup(TestSnapClient::default().with_peer_id(peer_id).with_soft_limit(6));
+        // the first response is corrupted
+        client.push_fault(ResponseFault::Malicious);
+
+        let mut stage =
+            SnapSyncStage::new(client.clone()).with_pivot_distance(10).with_commit_threshold(20);
+        let mut tx = db.inner();
+        let output = run(&mut stage, &mut tx, input(0, 20)).await;
+
+        assert_eq!(output, ExecOutput { stage_progress: 10, done: true });
+        assert_eq!(hashed_state(&tx), states[0].1);
+        assert_eq!(tx.get::<tables::Bytecodes>(keccak256(&code)).unwrap(), Some(code.to_vec()));
+        assert_eq!(client.reported_peers(), vec![peer_id]);
+    }
+
+    #[tokio::test]
+    async fn heals_state_into_new_pivot() {
+        let Setup { db, client, states, .. } = setup(TestSnapClient::default().with_soft_limit(6));
+        let mut tx = db.inner();
+        let mut stage = SnapSyncStage::new(client.clone()).with_pivot_distance(10);
+        run(&mut stage, &mut tx, input(0, 20)).await;
+
+        // a restarted node heals the state of the previous pivot
+        let mut stage = SnapSyncStage::new(client).with_pivot_distance(10);
+        let output = run(&mut stage, &mut tx, input(10, 30)).await;
+        assert_eq!(output, ExecOutput { stage_progress: 20, done: true });
+        assert_eq!(hashed_state(&tx), states[1].1);
+    }
+
+    #[tokio::test]
+    async fn moves_stale_pivot() {
+        let Setup { db, client, states, .. } = setup(TestSnapClient::default().with_soft_limit(4));
+        let mut stage = SnapSyncStage::new(client.clone())
+            .with_pivot_distance(10)
+            .with_commit_threshold(5)
+            .with_max_attempts(1);
+        let mut tx = db.inner();
+
+        let output = stage.execute(&mut tx, input(0, 20)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 0, done: false });
+        tx.commit().unwrap();
+
+        // the state of the pivot is no longer served
+        client.remove_state(states[0].0);
+        let output = stage.execute(&mut tx, input(0, 20)).await.unwrap();
+        assert_eq!(output, ExecOutput { stage_progress: 0, done: true });
+        tx.commit().unwrap();
+
+        // the ranges of both pivots are healed into the new one
+        let output = run(&mut stage, &mut tx, input(0, 30)).await;
+        assert_eq!(output, ExecOutput { stage_progress: 20, done: true });
+        assert_eq!(hashed_state(&tx), states[1].1);
+    }
+}
@@ -1,16 +1,22 @@
-use metrics::{register_counter, Counter};
+use metrics::{Counter, Gauge, Histogram};
 use reth_interfaces::p2p::error::DownloadError;
-use std::fmt;
+use reth_metrics_derive::Metrics;
 
 /// Stagedsync header metrics
+#[derive(Metrics)]
+#[metrics(scope = "stages.headers")]
 pub struct HeaderMetrics {
     /// Number of headers successfully retrieved
+    #[metric(rename = "counter")]
     pub headers_counter: Counter,
     /// Number of timeout errors while requesting headers
+    #[metric(rename = "timeout_errors")]
     pub headers_timeout_errors: Counter,
     /// Number of validation errors while requesting headers
+    #[metric(rename = "validation_errors")]
     pub headers_validation_errors: Counter,
-    /// Elapsed time of successful header requests
+    /// Number of unexpected errors while requesting headers
+    #[metric(rename = "unexpected_errors")]
     pub headers_unexpected_errors: Counter,
 }
 
@@ -27,20 +33,42 @@ impl HeaderMetrics {
     }
 }
 
-impl Default for HeaderMetrics {
-    /// Initialize header metrics struct and register them
-    fn default() -> Self {
-        Self {
-            headers_counter: register_counter!("stages.headers.counter"),
-            headers_timeout_errors: register_counter!("stages.headers.timeout_errors"),
-            headers_validation_errors: register_counter!("stages.headers.validation_errors"),
-            headers_unexpected_errors: register_counter!("stages.headers.unexpected_errors"),
-        }
-    }
+/// Stagedsync execution metrics
+#[derive(Metrics)]
+#[metrics(scope = "stages.execution")]
+pub struct ExecutionMetrics {
+    /// Number of executed blocks
+    pub executed_blocks: Counter,
+    /// Number of executed transactions
+    pub executed_transactions: Counter,
+    /// Total gas used by the executed blocks
+    pub gas_used: Counter,
+    /// Time in seconds it took to execute a block
+    pub block_execution_time: Histogram,
+    /// Number of blocks whose buffered execution outcome was applied again after a reorg
+    pub reused_blocks: Counter,
+    /// Number of analysed bytecodes that were served from the bytecode cache
+    pub bytecode_cache_hits: Counter,
+    /// Number of bytecodes that were loaded and analysed
+    pub bytecode_cache_misses: Counter,
+    /// Number of analysed bytecodes in the bytecode cache
+    pub bytecode_cache_size: Gauge,
 }
 
-impl fmt::Debug for HeaderMetrics {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("HeaderMetrics").finish()
-    }
+/// Stagedsync progress metrics, registered for every stage with a `stage` label
+#[derive(Metrics)]
+#[metrics(scope = "stages")]
+pub struct StageMetrics {
+    /// The block number of the last checkpoint of the stage
+    pub checkpoint: Gauge,
+    /// The block number the stage is syncing to
+    pub target: Gauge,
+    /// Number of blocks processed by the stage
+    pub processed_blocks: Counter,
+    /// Time in seconds a single iteration of the stage took, including the commit
+    pub iteration_duration: Histogram,
+    /// Time in seconds it took to commit the changes of an iteration of the stage
+    pub commit_duration: Histogram,
+    /// Estimated time in seconds until the stage reaches its target
+    pub eta_seconds: Gauge,
 }
@@ -2,12 +2,14 @@ use crate::StageId;
 
 mod macros;
 mod runner;
+mod simulation;
 mod test_db;
 
 pub(crate) use macros::*;
 pub(crate) use runner::{
     ExecuteStageTestRunner, StageTestRunner, TestRunnerError, UnwindStageTestRunner,
 };
+pub(crate) use simulation::{PipelineSimulation, SimulationStep};
 pub(crate) use test_db::TestTransaction;
 
 /// The previous test stage id mock used for testing
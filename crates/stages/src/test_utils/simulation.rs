@@ -0,0 +1,249 @@
+//! A deterministic simulation of the whole pipeline.
+//!
+//! The simulation syncs a generated chain from a mock peer with all stages that are needed to
+//! build the state. It restarts the pipeline at arbitrary blocks and injects unwinds, and the
+//! database it ends up with has to equal the one of a sync without interruptions. This catches
+//! stages whose checkpoints or unwinds drift from what they executed.
+use crate::{
+    stages::{
+        bodies::BodyStage, execution::ExecutionStage, hashing_account::AccountHashingStage,
+        hashing_storage::StorageHashingStage, headers::HeaderStage,
+        history_index::HistoryIndexStage, log_index::LogIndexStage,
+        sender_recovery::SenderRecoveryStage,
+    },
+    stages_metrics::HeaderMetrics,
+    Pipeline,
+};
+use rand::{rngs::StdRng, Rng};
+use reth_db::{
+    database::Database,
+    mdbx::{test_utils::create_test_db, Env, EnvKind, WriteMap},
+    tables::{self, TABLES},
+    transaction::{DbTx, DbTxMut},
+};
+use reth_downloaders::{
+    bodies::concurrent::ConcurrentDownloader, headers::linear::LinearDownloadBuilder,
+};
+use reth_executor::{Config, SpecUpgrades};
+use reth_interfaces::test_utils::{
+    generators::chain::{random_chain, ChainConfig},
+    TestConsensus, TestFullBlockClient, TestStatusUpdater,
+};
+use reth_primitives::{Account, Address, BlockNumber, Header, SealedBlock, U256};
+use reth_provider::insert_canonical_block;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+/// The number of blocks every stage processes before the pipeline commits.
+const COMMIT_THRESHOLD: u64 = 4;
+
+/// An interruption of the sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SimulationStep {
+    /// Runs a new pipeline until all stages reached the block, like a node that is restarted
+    /// afterwards.
+    Run(BlockNumber),
+    /// Unwinds all stages to the block.
+    Unwind(BlockNumber),
+}
+
+/// The raw entries of all tables, by table name.
+type DbDump = BTreeMap<&'static str, Vec<(Vec<u8>, Vec<u8>)>>;
+
+/// Syncs a generated chain with a pipeline of all stages that build the state.
+#[derive(Debug)]
+pub(crate) struct PipelineSimulation {
+    /// The genesis block, which is in the database before the sync.
+    genesis: SealedBlock,
+    /// The accounts of the genesis state, which fund the senders of the chain.
+    alloc: Vec<(Address, Account)>,
+    /// The generated blocks on top of the genesis block.
+    blocks: Vec<SealedBlock>,
+    /// The number of the last block.
+    tip: BlockNumber,
+    /// The peer that serves the chain.
+    client: Arc<TestFullBlockClient>,
+    /// The consensus, whose fork choice points to the last block.
+    consensus: Arc<TestConsensus>,
+}
+
+// === impl PipelineSimulation ===
+
+impl PipelineSimulation {
+    /// Generates a chain of `tip` blocks on top of a genesis block with the seed.
+    pub(crate) fn new(seed: u64, tip: BlockNumber) -> Self {
+        let genesis = SealedBlock {
+            header: Header {
+                gas_limit: 30_000_000,
+                base_fee_per_gas: Some(1_000_000_000),
+                ..Default::default()
+            }
+            .seal(),
+            body: Vec::new(),
+            ommers: Vec::new(),
+        };
+        let config = ChainConfig::new(1..tip + 1)
+            .with_seed(seed)
+            .with_parent_hash(genesis.hash())
+            .with_txs_per_block(0..5);
+        let blocks = random_chain(&config).sealed_blocks();
+
+        let senders = blocks
+            .iter()
+            .flat_map(|block| &block.body)
+            .map(|transaction| transaction.recover_signer().expect("signed transaction"))
+            .collect::<BTreeSet<_>>();
+        let balance = U256::exp10(30);
+        let alloc = senders
+            .into_iter()
+            .map(|sender| (sender, Account { nonce: 0, balance, bytecode_hash: None }))
+            .collect();
+
+        let consensus = Arc::new(TestConsensus::default());
+        consensus.update_tip(blocks.last().expect("blocks are generated").hash());
+        let client = Arc::new(TestFullBlockClient::new(
+            std::iter::once(genesis.clone()).chain(blocks.iter().cloned()),
+        ));
+
+        Self { genesis, alloc, blocks, tip, client, consensus }
+    }
+
+    /// Returns the number of the last block.
+    pub(crate) fn tip(&self) -> BlockNumber {
+        self.tip
+    }
+
+    /// Returns the first block with at least `txs` transactions.
+    pub(crate) fn block_with_txs(&self, txs: usize) -> Option<&SealedBlock> {
+        self.blocks.iter().find(|block| block.body.len() >= txs)
+    }
+
+    /// Returns `len` random steps that run the pipeline to and unwind it to random blocks.
+    pub(crate) fn random_steps(&self, rng: &mut StdRng, len: usize) -> Vec<SimulationStep> {
+        (0..len)
+            .map(|_| {
+                if rng.gen() {
+                    SimulationStep::Run(rng.gen_range(1..=self.tip))
+                } else {
+                    SimulationStep::Unwind(rng.gen_range(0..self.tip))
+                }
+            })
+            .collect()
+    }
+
+    /// Creates a database with the genesis block and state.
+    fn create_db(&self) -> Arc<Env<WriteMap>> {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let tx = db.tx_mut().unwrap();
+        insert_canonical_block(&tx, &self.genesis, true).unwrap();
+        tx.put::<tables::HeaderTD>(
+            self.genesis.header.num_hash().into(),
+            self.genesis.difficulty.into(),
+        )
+        .unwrap();
+        for (address, account) in &self.alloc {
+            tx.put::<tables::PlainAccountState>(*address, *account).unwrap();
+        }
+        tx.commit().unwrap();
+        db
+    }
+
+    /// Creates a pipeline of new stages, like the one of a node that was just started.
+    fn pipeline(&self) -> Pipeline<Env<WriteMap>> {
+        self.pipeline_with(ExecutionStage::new(Config {
+            chain_id: 1.into(),
+            spec_upgrades: SpecUpgrades::new_london_activated(),
+            hooks: Default::default(),
+        }))
+    }
+
+    /// Creates a pipeline of new stages with the given execution stage.
+    fn pipeline_with(&self, execution: ExecutionStage) -> Pipeline<Env<WriteMap>> {
+        let header_downloader = LinearDownloadBuilder::default()
+            .batch_size(COMMIT_THRESHOLD)
+            .build(self.consensus.clone(), self.client.clone());
+        let body_downloader =
+            ConcurrentDownloader::new(self.client.clone(), self.consensus.clone())
+                .with_batch_size(COMMIT_THRESHOLD as usize);
+
+        Pipeline::new()
+            .push(HeaderStage {
+                downloader: header_downloader,
+                consensus: self.consensus.clone(),
+                client: self.client.clone(),
+                network_handle: TestStatusUpdater::default(),
+                commit_threshold: COMMIT_THRESHOLD,
+                metrics: HeaderMetrics::default(),
+            })
+            .push(BodyStage {
+                downloader: Arc::new(body_downloader),
+                consensus: self.consensus.clone(),
+                commit_threshold: COMMIT_THRESHOLD,
+            })
+            .push(SenderRecoveryStage { batch_size: 2, commit_threshold: COMMIT_THRESHOLD })
+            .push(execution)
+            .push(AccountHashingStage::default())
+            .push(StorageHashingStage::default())
+            .push(LogIndexStage { commit_threshold: COMMIT_THRESHOLD, ..Default::default() })
+            .push(HistoryIndexStage { commit_threshold: COMMIT_THRESHOLD, ..Default::default() })
+    }
+
+    /// Syncs to the tip after the steps and returns the database.
+    pub(crate) async fn run(&self, steps: &[SimulationStep]) -> Arc<Env<WriteMap>> {
+        let db = self.create_db();
+        for step in steps.iter().copied().chain([SimulationStep::Run(self.tip)]) {
+            match step {
+                SimulationStep::Run(target) => {
+                    self.pipeline().set_max_block(Some(target)).run(db.clone()).await.unwrap()
+                }
+                SimulationStep::Unwind(target) => {
+                    self.pipeline().unwind(db.as_ref(), target, None).await.unwrap()
+                }
+            }
+        }
+        db
+    }
+
+    /// Syncs with an execution stage that halts before the transaction of the block and returns
+    /// the database.
+    pub(crate) async fn run_halted(&self, block: BlockNumber, index: usize) -> Arc<Env<WriteMap>> {
+        let db = self.create_db();
+        let execution = ExecutionStage::new(Config {
+            chain_id: 1.into(),
+            spec_upgrades: SpecUpgrades::new_london_activated(),
+            hooks: Default::default(),
+        })
+        .with_halt_before(block, index);
+        self.pipeline_with(execution).set_halt_block(Some(block)).run(db.clone()).await.unwrap();
+        db
+    }
+
+    /// Asserts that syncing after the steps results in the same database as syncing without
+    /// interruptions.
+    pub(crate) async fn assert_steps(&self, steps: &[SimulationStep]) {
+        let expected = dump(&self.run(&[]).await);
+        let got = dump(&self.run(steps).await);
+        for (table, entries) in expected {
+            assert!(got[table] == entries, "table {table} differs after the steps {steps:?}");
+        }
+    }
+}
+
+/// Reads the raw entries of all tables.
+fn dump(db: &Env<WriteMap>) -> DbDump {
+    db.view(|tx| {
+        TABLES
+            .iter()
+            .map(|(_, table)| {
+                let table_db = tx.inner.open_db(Some(table)).unwrap();
+                let mut cursor = tx.inner.cursor(&table_db).unwrap();
+                let entries =
+                    cursor.iter_start::<Vec<u8>, Vec<u8>>().collect::<Result<Vec<_>, _>>().unwrap();
+                (*table, entries)
+            })
+            .collect()
+    })
+    .unwrap()
+}
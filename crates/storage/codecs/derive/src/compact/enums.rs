@@ -65,14 +65,26 @@ impl<'a> EnumHandler<'a> {
                         format_ident!("specialized_from_compact")
                     };
 
-                    // Unamed type
-                    self.enum_lines.push(quote! {
-                        #current_variant_index => {
-                            let mut inner = #field_type::default();
-                            (inner, buf) = #field_type::#from_compact_ident(buf, buf.len());
-                            #ident::#variant_name(inner)
-                        }
-                    });
+                    if is_flag_type(next_ftype) {
+                        // Integer type, which is prefixed by its length
+                        self.enum_lines.push(quote! {
+                            #current_variant_index => {
+                                let len = bytes::Buf::get_u8(&mut buf) as usize;
+                                let mut inner = #field_type::default();
+                                (inner, buf) = #field_type::#from_compact_ident(buf, len);
+                                #ident::#variant_name(inner)
+                            }
+                        });
+                    } else {
+                        // Unamed type
+                        self.enum_lines.push(quote! {
+                            #current_variant_index => {
+                                let mut inner = #field_type::default();
+                                (inner, buf) = #field_type::#from_compact_ident(buf, buf.len());
+                                #ident::#variant_name(inner)
+                            }
+                        });
+                    }
                     self.fields_iterator.next();
                 }
                 FieldTypes::EnumVariant(_) => self.enum_lines.push(quote! {
@@ -99,20 +111,34 @@ impl<'a> EnumHandler<'a> {
 
         if let Some(next_field) = self.fields_iterator.peek() {
             match next_field {
-                FieldTypes::EnumUnnamedField((_, use_alt_impl)) => {
+                FieldTypes::EnumUnnamedField((next_ftype, use_alt_impl)) => {
                     let to_compact_ident = if !use_alt_impl {
                         format_ident!("to_compact")
                     } else {
                         format_ident!("specialized_to_compact")
                     };
 
-                    // Unamed type
-                    self.enum_lines.push(quote! {
-                        #ident::#variant_name(field) => {
-                            field.#to_compact_ident(&mut buffer);
-                            #current_variant_index
-                        },
-                    });
+                    if is_flag_type(next_ftype) {
+                        // Integer type, there's no room for its length in the `StructFlags`, so
+                        // it's prefixed by it.
+                        self.enum_lines.push(quote! {
+                            #ident::#variant_name(field) => {
+                                let mut inner = bytes::BytesMut::new();
+                                let len = field.#to_compact_ident(&mut inner);
+                                bytes::BufMut::put_u8(&mut buffer, len as u8);
+                                bytes::BufMut::put(&mut buffer, inner);
+                                #current_variant_index
+                            },
+                        });
+                    } else {
+                        // Unamed type
+                        self.enum_lines.push(quote! {
+                            #ident::#variant_name(field) => {
+                                field.#to_compact_ident(&mut buffer);
+                                #current_variant_index
+                            },
+                        });
+                    }
                     self.fields_iterator.next();
                 }
                 FieldTypes::EnumVariant(_) => self.enum_lines.push(quote! {
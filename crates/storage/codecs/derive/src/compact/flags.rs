@@ -81,14 +81,15 @@ fn build_struct_field_flags(
     let mut total_bits = 0;
 
     // Find out the adequate bit size for the length of each field, if applicable.
-    for (name, ftype, is_compact, _) in fields {
+    for (name, ftype, is_compact, _, flagged_bits) in fields {
         // This happens when dealing with a wrapper struct eg. Struct(pub U256).
         let name = if name.is_empty() { "placeholder" } else { name };
 
         if *is_compact {
-            if is_flag_type(ftype) {
+            if is_flag_type(ftype) || flagged_bits.is_some() {
                 let name = format_ident!("{name}_len");
-                let bitsize = get_bit_size(ftype);
+                // `Option<u64>` keeps `len + 1` (or 0 if `None`), which fits in the bits of `u64`.
+                let bitsize = flagged_bits.unwrap_or_else(|| get_bit_size(ftype));
                 let bsize = format_ident!("B{bitsize}");
                 total_bits += bitsize;
 
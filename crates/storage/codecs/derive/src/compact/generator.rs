@@ -8,6 +8,7 @@ pub fn generate_from_to(ident: &Ident, fields: &FieldList) -> TokenStream2 {
 
     let to_compact = generate_to_compact(fields, ident);
     let from_compact = generate_from_compact(fields, ident);
+    let flagged = generate_flagged(fields, ident);
 
     let fuzz = format_ident!("fuzz_test_{ident}");
     let test = format_ident!("fuzz_{ident}");
@@ -43,6 +44,40 @@ pub fn generate_from_to(ident: &Ident, fields: &FieldList) -> TokenStream2 {
                 #(#from_compact)*
                 (obj, buf)
             }
+
+            #flagged
+        }
+    }
+}
+
+/// Generates the `flagged_to/from_compact` methods of fieldless enums, which keep the variant in
+/// the `StructFlags` of the parent type instead of writing it to the buffer.
+///
+/// Fields of the enum have to be annotated with the number of bits of the variant, eg.
+/// `#[variant_bits(2)]`.
+fn generate_flagged(fields: &FieldList, ident: &Ident) -> TokenStream2 {
+    let is_fieldless_enum = !fields.is_empty() &&
+        fields.iter().all(|field| matches!(field, FieldTypes::EnumVariant(_)));
+    if !is_fieldless_enum {
+        return quote! {}
+    }
+
+    let to_lines = EnumHandler::new(fields).generate_to(ident);
+    let from_lines = EnumHandler::new(fields).generate_from(ident);
+
+    quote! {
+        fn flagged_to_compact(self, _: &mut impl bytes::BufMut) -> usize {
+            (match self {
+                #(#to_lines)*
+            }) as usize
+        }
+
+        fn flagged_from_compact(buf: &[u8], len: usize) -> (Self, &[u8]) {
+            let obj = match len as u8 {
+                #(#from_lines)*
+                _ => unreachable!()
+            };
+            (obj, buf)
         }
     }
 }
@@ -50,7 +85,8 @@ pub fn generate_from_to(ident: &Ident, fields: &FieldList) -> TokenStream2 {
 /// Generates code to implement the [`Compact`] trait method `to_compact`.
 fn generate_from_compact(fields: &FieldList, ident: &Ident) -> Vec<TokenStream2> {
     let mut lines = vec![];
-    let mut known_types = vec!["H256", "H160", "Address", "Bloom", "Vec"];
+    let mut known_types = FIXED_SIZE_TYPES.to_vec();
+    known_types.push("Vec");
 
     // Only types without `bytes::Bytes` should be added here. It's currently manually added, since
     // it's hard to figure out with derive_macro which types have bytes::Bytes fields.
@@ -82,7 +118,7 @@ fn generate_from_compact(fields: &FieldList, ident: &Ident) -> Vec<TokenStream2>
             });
         } else {
             let fields = fields.iter().filter_map(|field| {
-                if let FieldTypes::StructField((name, _, _, _)) = field {
+                if let FieldTypes::StructField((name, _, _, _, _)) = field {
                     let ident = format_ident!("{name}");
                     return Some(quote! {
                         #ident: #ident,
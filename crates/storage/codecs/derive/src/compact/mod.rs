@@ -16,6 +16,9 @@ use flags::*;
 mod structs;
 use structs::*;
 
+mod schema;
+use schema::*;
+
 // Helper Alias type
 type IsCompact = bool;
 // Helper Alias type
@@ -28,8 +31,12 @@ type FieldType = String;
 /// Example: `Vec<H256>` vs `Vec<U256>`. The first does not
 /// require the len of the element, while the latter one does.
 type UseAlternative = bool;
+/// Number of bits of the `StructFlags` that hold the value returned by `flagged_to_compact`, eg.
+/// the presence and length of an `Option<u64>` or the variant of a fieldless enum, instead of it
+/// being written to the buffer.
+type FlaggedBits = Option<u8>;
 // Helper Alias type
-type StructFieldDescriptor = (FieldName, FieldType, IsCompact, UseAlternative);
+type StructFieldDescriptor = (FieldName, FieldType, IsCompact, UseAlternative, FlaggedBits);
 // Helper Alias type
 type FieldList = Vec<FieldTypes>;
 
@@ -45,9 +52,15 @@ pub fn derive(input: TokenStream) -> TokenStream {
     let mut output = quote! {};
 
     let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    // checked first, the schema is the only part that rejects unions with an error
+    let schema = match generate_schema(&ident, &data) {
+        Ok(schema) => schema,
+        Err(err) => return err.to_compile_error().into(),
+    };
     let fields = get_fields(&data);
     output.extend(generate_flag_struct(&ident, &fields));
     output.extend(generate_from_to(&ident, &fields));
+    output.extend(schema);
     output.into()
 }
 
@@ -104,6 +117,7 @@ fn load_field(field: &syn::Field, fields: &mut FieldList, is_enum: bool) {
             let mut ftype = String::new();
 
             let mut use_alt_impl: UseAlternative = false;
+            let mut flagged_bits: FlaggedBits = None;
 
             for (index, segment) in segments.iter().enumerate() {
                 ftype.push_str(&segment.ident.to_string());
@@ -112,12 +126,19 @@ fn load_field(field: &syn::Field, fields: &mut FieldList, is_enum: bool) {
                 }
 
                 use_alt_impl = should_use_alt_impl(&ftype, segment);
+                flagged_bits = get_flagged_option_bits(&ftype, segment);
+            }
+
+            // Fields of fieldless enums can keep their variant in the `StructFlags`.
+            if let Some(bits) = get_variant_bits(field) {
+                flagged_bits = Some(bits);
             }
 
             if is_enum {
                 fields.push(FieldTypes::EnumUnnamedField((ftype.to_string(), use_alt_impl)));
             } else {
                 let should_compact = is_flag_type(&ftype) ||
+                    flagged_bits.is_some() ||
                     field.attrs.iter().any(|attr| {
                         attr.path.segments.iter().any(|path| path.ident == "maybe_zero")
                     });
@@ -127,32 +148,96 @@ fn load_field(field: &syn::Field, fields: &mut FieldList, is_enum: bool) {
                     ftype,
                     should_compact,
                     use_alt_impl,
+                    flagged_bits,
                 )));
             }
         }
     }
 }
 
-/// Since there's no impl specialization in rust stable atm, once we find we have a
-/// Vec/Option we try to find out if it's a Vec/Option of a fixed size data type.
-/// eg, Vec<H256>. If so, we use another impl to code/decode its data.
-fn should_use_alt_impl(ftype: &String, segment: &syn::PathSegment) -> bool {
-    if *ftype == "Vec" || *ftype == "Option" {
+/// Fixed size data types and their known aliases. They're neither given a length in the
+/// `StructFlags` nor prefixed by one when inside a `Vec`/`Option`.
+pub const FIXED_SIZE_TYPES: [&str; 12] = [
+    "H64",
+    "H128",
+    "H160",
+    "H256",
+    "H512",
+    "Address",
+    "Bloom",
+    "TxHash",
+    "BlockHash",
+    "BlockID",
+    "HeaderHash",
+    "StorageKey",
+];
+
+/// Integer types whose `Option` can have both its presence and length stored in the
+/// `StructFlags`, since `len + 1` still fits into the bits of the non-optional type.
+const FLAGGED_OPTION_TYPES: [&str; 9] =
+    ["u64", "BlockNumber", "TxNumber", "ChainId", "TransitionId", "u128", "U256", "i64", "i128"];
+
+/// Integer types that are written as LEB128 varints inside a `Vec`/`Option`, eg. `Vec<u64>`, so
+/// their elements don't need a length prefix.
+const VARINT_TYPES: [&str; 8] =
+    ["u64", "BlockNumber", "TxNumber", "ChainId", "TransitionId", "u128", "i64", "i128"];
+
+/// Returns the single type argument of a `Vec`/`Option`, if it's a plain type like `H256` or
+/// `Vec<H256>`.
+fn get_generic_segment<'a>(
+    ftype: &str,
+    segment: &'a syn::PathSegment,
+) -> Option<&'a syn::PathSegment> {
+    if ftype == "Vec" || ftype == "Option" {
         if let syn::PathArguments::AngleBracketed(ref args) = segment.arguments {
             if let Some(syn::GenericArgument::Type(syn::Type::Path(arg_path))) = args.args.last() {
                 if let (Some(path), 1) =
                     (arg_path.path.segments.first(), arg_path.path.segments.len())
                 {
-                    if ["H256", "H160", "Address", "Bloom"]
-                        .contains(&path.ident.to_string().as_str())
-                    {
-                        return true
-                    }
+                    return Some(path)
                 }
             }
         }
     }
-    false
+    None
+}
+
+/// Returns true if the type is written without a length by its `specialized_to_compact`, so it
+/// can be decoded from the middle of a `Vec`/`Option` without a length prefix.
+fn is_self_delimiting(segment: &syn::PathSegment) -> bool {
+    let ident = segment.ident.to_string();
+    FIXED_SIZE_TYPES.contains(&ident.as_str()) ||
+        VARINT_TYPES.contains(&ident.as_str()) ||
+        (ident == "Vec" && should_use_alt_impl(&ident, segment))
+}
+
+/// Since there's no impl specialization in rust stable atm, once we find we have a
+/// Vec/Option we try to find out if it's a Vec/Option of a fixed size data type, an integer or
+/// a `Vec` of those. eg, Vec<H256>, Vec<u64> or Option<Vec<H256>>. If so, we use another impl to
+/// code/decode its data.
+fn should_use_alt_impl(ftype: &String, segment: &syn::PathSegment) -> bool {
+    get_generic_segment(ftype, segment).map_or(false, is_self_delimiting)
+}
+
+/// Returns the bits of `Option` fields of an integer type, eg. the bits of `u64` for
+/// `Option<u64>`.
+fn get_flagged_option_bits(ftype: &String, segment: &syn::PathSegment) -> FlaggedBits {
+    if *ftype != "Option" {
+        return None
+    }
+    let inner = get_generic_segment(ftype, segment)?.ident.to_string();
+    FLAGGED_OPTION_TYPES.contains(&inner.as_str()).then(|| get_bit_size(&inner))
+}
+
+/// Returns the bits of the `#[variant_bits(N)]` attribute of a field of a fieldless enum type.
+fn get_variant_bits(field: &syn::Field) -> FlaggedBits {
+    let attr = field.attrs.iter().find(|attr| attr.path.is_ident("variant_bits"))?;
+    let bits = attr
+        .parse_args::<syn::LitInt>()
+        .and_then(|bits| bits.base10_parse::<u8>())
+        .expect("`variant_bits` expects the number of bits, eg. `#[variant_bits(2)]`");
+    assert!((1..=8).contains(&bits), "`variant_bits` has to be between 1 and 8");
+    Some(bits)
 }
 
 /// Given the field type in a string format, return the amount of bits necessary to save its maximum
@@ -161,9 +246,9 @@ pub fn get_bit_size(ftype: &str) -> u8 {
     match ftype {
         "bool" | "Option" => 1,
         "TxType" => 2,
-        "u64" | "BlockNumber" | "TxNumber" | "ChainId" => 4,
-        "u128" => 5,
-        "U256" | "TxHash" => 6,
+        "u64" | "BlockNumber" | "TxNumber" | "ChainId" | "TransitionId" | "i64" => 4,
+        "u128" | "i128" => 5,
+        "U256" => 6,
         _ => 0,
     }
 }
@@ -218,9 +303,9 @@ mod tests {
                     pub f_u256_len: B6,
                     pub f_bool_t_len: B1,
                     pub f_bool_f_len: B1,
-                    pub f_option_none_len: B1,
+                    pub f_option_none_len: B6,
                     pub f_option_some_len: B1,
-                    pub f_option_some_u64_len: B1,
+                    pub f_option_some_u64_len: B4,
                     #[skip]
                     unused: B1,
                 }
@@ -228,7 +313,7 @@ mod tests {
                     #[doc=r" Deserializes this fieldset and returns it, alongside the original slice in an advanced position."]
                     pub fn from(mut buf: &[u8]) -> (Self, &[u8]) {
                         (
-                            TestStructFlags::from_bytes([buf.get_u8(), buf.get_u8(),]),
+                            TestStructFlags::from_bytes([buf.get_u8(), buf.get_u8(), buf.get_u8(),]),
                             buf
                         )
                     }
@@ -260,11 +345,11 @@ mod tests {
                     flags.set_f_bool_t_len(f_bool_t_len as u8);
                     let f_bool_f_len = self.f_bool_f.to_compact(&mut buffer);
                     flags.set_f_bool_f_len(f_bool_f_len as u8);
-                    let f_option_none_len = self.f_option_none.to_compact(&mut buffer);
+                    let f_option_none_len = self.f_option_none.flagged_to_compact(&mut buffer);
                     flags.set_f_option_none_len(f_option_none_len as u8);
                     let f_option_some_len = self.f_option_some.specialized_to_compact(&mut buffer);
                     flags.set_f_option_some_len(f_option_some_len as u8);
-                    let f_option_some_u64_len = self.f_option_some_u64.to_compact(&mut buffer);
+                    let f_option_some_u64_len = self.f_option_some_u64.flagged_to_compact(&mut buffer);
                     flags.set_f_option_some_u64_len(f_option_some_u64_len as u8);
                     let f_vec_empty_len = self.f_vec_empty.to_compact(&mut buffer);
                     let f_vec_some_len = self.f_vec_some.specialized_to_compact(&mut buffer);
@@ -285,12 +370,13 @@ mod tests {
                     let mut f_bool_f = bool::default();
                     (f_bool_f, buf) = bool::from_compact(buf, flags.f_bool_f_len() as usize);
                     let mut f_option_none = Option::default();
-                    (f_option_none, buf) = Option::from_compact(buf, flags.f_option_none_len() as usize);
+                    (f_option_none, buf) =
+                        Option::flagged_from_compact(buf, flags.f_option_none_len() as usize);
                     let mut f_option_some = Option::default();
                     (f_option_some, buf) = Option::specialized_from_compact(buf, flags.f_option_some_len() as usize);
                     let mut f_option_some_u64 = Option::default();
                     (f_option_some_u64, buf) =
-                        Option::from_compact(buf, flags.f_option_some_u64_len() as usize);
+                        Option::flagged_from_compact(buf, flags.f_option_some_u64_len() as usize);
                     let mut f_vec_empty = Vec::default();
                     (f_vec_empty, buf) = Vec::from_compact(buf, buf.len());
                     let mut f_vec_some = Vec::default();
@@ -313,4 +399,101 @@ mod tests {
 
         assert_eq!(output.to_string(), should_output.to_string());
     }
+
+    #[test]
+    fn fixed_size_aliases() {
+        let f_struct = quote! {
+            pub struct TestStruct {
+                f_hash: TxHash,
+                f_option_hash: Option<BlockHash>,
+                f_u64: u64,
+            }
+        };
+
+        let DeriveInput { data, .. } = parse2(f_struct).unwrap();
+        let fields = get_fields(&data);
+        assert_eq!(
+            fields,
+            vec![
+                FieldTypes::StructField((
+                    "f_hash".to_string(),
+                    "TxHash".to_string(),
+                    false,
+                    false,
+                    None
+                )),
+                FieldTypes::StructField((
+                    "f_option_hash".to_string(),
+                    "Option".to_string(),
+                    true,
+                    true,
+                    None
+                )),
+                FieldTypes::StructField((
+                    "f_u64".to_string(),
+                    "u64".to_string(),
+                    true,
+                    false,
+                    None
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn varint_and_flagged_fields() {
+        let f_struct = quote! {
+            pub struct TestStruct {
+                #[variant_bits(2)]
+                f_kind: TestKind,
+                f_option_u64: Option<u64>,
+                f_vec_u64: Vec<u64>,
+                f_option_vec: Option<Vec<H256>>,
+                f_vec_u256: Vec<U256>,
+            }
+        };
+
+        let DeriveInput { data, .. } = parse2(f_struct).unwrap();
+        let fields = get_fields(&data);
+        assert_eq!(
+            fields,
+            vec![
+                FieldTypes::StructField((
+                    "f_kind".to_string(),
+                    "TestKind".to_string(),
+                    true,
+                    false,
+                    Some(2)
+                )),
+                FieldTypes::StructField((
+                    "f_option_u64".to_string(),
+                    "Option".to_string(),
+                    true,
+                    true,
+                    Some(4)
+                )),
+                FieldTypes::StructField((
+                    "f_vec_u64".to_string(),
+                    "Vec".to_string(),
+                    false,
+                    true,
+                    None
+                )),
+                FieldTypes::StructField((
+                    "f_option_vec".to_string(),
+                    "Option".to_string(),
+                    true,
+                    true,
+                    None
+                )),
+                FieldTypes::StructField((
+                    "f_vec_u256".to_string(),
+                    "Vec".to_string(),
+                    false,
+                    false,
+                    None
+                )),
+            ]
+        );
+    }
 }
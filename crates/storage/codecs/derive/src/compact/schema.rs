@@ -0,0 +1,145 @@
+use super::*;
+
+/// Generates the `COMPACT_SCHEMA` const, which describes the fields and variants that make up the
+/// encoding of a data type.
+///
+/// Fails for unions, which have no `Compact` encoding.
+pub fn generate_schema(ident: &Ident, data: &Data) -> syn::Result<TokenStream2> {
+    let schema = get_schema(ident, data)?;
+
+    Ok(quote! {
+        impl #ident {
+            /// Fields and variants that make up the `Compact` encoding of this type, in order.
+            ///
+            /// Any change to it changes the encoding, so the data written by a previous version
+            /// has to be migrated.
+            pub const COMPACT_SCHEMA: &str = #schema;
+        }
+    })
+}
+
+/// Returns the schema of a data type, eg. `Account{nonce:u64,balance:U256}` or
+/// `TransactionKind{Create,Call(Address)}`.
+pub fn get_schema(ident: &Ident, data: &Data) -> syn::Result<String> {
+    let body = match data {
+        Data::Struct(data) => get_fields_schema(&data.fields),
+        Data::Enum(data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| format!("{}{}", variant.ident, get_fields_schema(&variant.fields)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", variants.join(","))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "`Compact` can't be derived for unions",
+            ))
+        }
+    };
+    Ok(format!("{ident}{body}"))
+}
+
+fn get_fields_schema(fields: &syn::Fields) -> String {
+    match fields {
+        syn::Fields::Named(fields) => {
+            let fields = fields
+                .named
+                .iter()
+                .map(|field| {
+                    let name = field.ident.as_ref().map(|i| i.to_string()).unwrap_or_default();
+                    format!("{}{name}:{}", get_attributes_schema(field), get_type_schema(&field.ty))
+                })
+                .collect::<Vec<_>>();
+            format!("{{{}}}", fields.join(","))
+        }
+        syn::Fields::Unnamed(fields) => {
+            let fields = fields
+                .unnamed
+                .iter()
+                .map(|field| {
+                    format!("{}{}", get_attributes_schema(field), get_type_schema(&field.ty))
+                })
+                .collect::<Vec<_>>();
+            format!("({})", fields.join(","))
+        }
+        syn::Fields::Unit => String::new(),
+    }
+}
+
+/// Attributes which change how a field is encoded.
+fn get_attributes_schema(field: &syn::Field) -> String {
+    let mut schema = String::new();
+    for attr in &field.attrs {
+        if attr.path.is_ident("maybe_zero") {
+            schema.push_str("#[maybe_zero]");
+        } else if attr.path.is_ident("variant_bits") {
+            schema
+                .push_str(&format!("#[variant_bits{}]", attr.tokens.to_string().replace(' ', "")));
+        }
+    }
+    schema
+}
+
+fn get_type_schema(ty: &syn::Type) -> String {
+    quote!(#ty).to_string().replace(' ', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse2;
+
+    fn schema(input: TokenStream2) -> String {
+        let DeriveInput { ident, data, .. } = parse2(input).unwrap();
+        get_schema(&ident, &data).unwrap()
+    }
+
+    #[test]
+    fn struct_schema() {
+        assert_eq!(
+            schema(quote! {
+                pub struct TestStruct {
+                    f_u64: u64,
+                    #[maybe_zero]
+                    f_hash: H256,
+                    f_option: Option<u64>,
+                    f_bytes: bytes::Bytes,
+                }
+            }),
+            "TestStruct{f_u64:u64,#[maybe_zero]f_hash:H256,f_option:Option<u64>,f_bytes:bytes::Bytes}"
+        );
+        assert_eq!(schema(quote! { pub struct Wrapper(pub Vec<H256>); }), "Wrapper(Vec<H256>)");
+        assert_eq!(
+            schema(quote! {
+                pub struct TestStruct {
+                    #[variant_bits(2)]
+                    f_kind: TestKind,
+                }
+            }),
+            "TestStruct{#[variant_bits(2)]f_kind:TestKind}"
+        );
+    }
+
+    #[test]
+    fn enum_schema() {
+        assert_eq!(
+            schema(quote! {
+                pub enum TestEnum {
+                    Var0,
+                    Var1(TestStruct),
+                    Var2(i64),
+                }
+            }),
+            "TestEnum{Var0,Var1(TestStruct),Var2(i64)}"
+        );
+    }
+
+    #[test]
+    fn union_schema() {
+        let DeriveInput { ident, data, .. } =
+            parse2(quote! { pub union TestUnion { f_u64: u64, f_u32: u32 } }).unwrap();
+        assert!(get_schema(&ident, &data).is_err());
+    }
+}
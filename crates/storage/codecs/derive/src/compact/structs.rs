@@ -46,9 +46,12 @@ impl<'a> StructHandler<'a> {
 
     /// Generates `to_compact` code for a struct field.
     fn to(&mut self, field_descriptor: &StructFieldDescriptor) {
-        let (name, ftype, is_compact, use_alt_impl) = field_descriptor;
+        let (name, ftype, is_compact, use_alt_impl, flagged_bits) = field_descriptor;
+        let is_flagged = is_flag_type(ftype) || flagged_bits.is_some();
 
-        let to_compact_ident = if !use_alt_impl {
+        let to_compact_ident = if flagged_bits.is_some() {
+            format_ident!("flagged_to_compact")
+        } else if !use_alt_impl {
             format_ident!("to_compact")
         } else {
             format_ident!("specialized_to_compact")
@@ -62,7 +65,7 @@ impl<'a> StructHandler<'a> {
                 let _len = self.0.#to_compact_ident(&mut buffer);
             });
 
-            if is_flag_type(ftype) {
+            if is_flagged {
                 self.lines.push(quote! {
                     flags.set_placeholder_len(_len as u8);
                 })
@@ -76,7 +79,7 @@ impl<'a> StructHandler<'a> {
         let len = format_ident!("{name}_len");
 
         // H256 with #[maybe_zero] attribute for example
-        if *is_compact && !is_flag_type(ftype) {
+        if *is_compact && !is_flagged {
             let itype = format_ident!("{ftype}");
             let set_bool_method = format_ident!("set_{name}");
             self.lines.push(quote! {
@@ -90,7 +93,7 @@ impl<'a> StructHandler<'a> {
                 let #len = self.#name.#to_compact_ident(&mut buffer);
             });
         }
-        if is_flag_type(ftype) {
+        if is_flagged {
             self.lines.push(quote! {
                 flags.#set_len_method(#len as u8);
             })
@@ -99,7 +102,8 @@ impl<'a> StructHandler<'a> {
 
     /// Generates `from_compact` code for a struct field.
     fn from(&mut self, field_descriptor: &StructFieldDescriptor, known_types: &[&str]) {
-        let (name, ftype, is_compact, use_alt_impl) = field_descriptor;
+        let (name, ftype, is_compact, use_alt_impl, flagged_bits) = field_descriptor;
+        let is_flagged = is_flag_type(ftype) || flagged_bits.is_some();
 
         let (name, len) = if name.is_empty() {
             self.is_wrapper = true;
@@ -110,7 +114,9 @@ impl<'a> StructHandler<'a> {
             (format_ident!("{name}"), format_ident!("{name}_len"))
         };
 
-        let from_compact_ident = if !use_alt_impl {
+        let from_compact_ident = if flagged_bits.is_some() {
+            format_ident!("flagged_from_compact")
+        } else if !use_alt_impl {
             format_ident!("from_compact")
         } else {
             format_ident!("specialized_from_compact")
@@ -118,7 +124,7 @@ impl<'a> StructHandler<'a> {
 
         assert!(
             known_types.contains(&ftype.as_str()) ||
-                is_flag_type(ftype) ||
+                is_flagged ||
                 self.fields_iterator.peek().is_none(),
             "`{ftype}` field should be placed as the last one since it's not known. 
             If it's an alias type (which are not supported by proc_macro), be sure to add it to either `known_types` or `get_bit_size` lists in the derive crate."
@@ -134,7 +140,7 @@ impl<'a> StructHandler<'a> {
             self.lines.push(quote! {
                 let mut #name = #ident_type::default();
             });
-            if !is_flag_type(ftype) {
+            if !is_flagged {
                 // It's a type that handles its own length requirements. (h256, Custom, ...)
                 self.lines.push(quote! {
                     (#name, buf) = #ident_type::#from_compact_ident(buf, buf.len());
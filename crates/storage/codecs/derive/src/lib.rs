@@ -4,7 +4,7 @@ use syn::{parse_macro_input, DeriveInput};
 
 mod compact;
 
-#[proc_macro_derive(Compact, attributes(maybe_zero))]
+#[proc_macro_derive(Compact, attributes(maybe_zero, variant_bits))]
 pub fn derive(input: TokenStream) -> TokenStream {
     compact::derive(input)
 }
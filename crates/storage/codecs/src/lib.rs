@@ -1,13 +1,23 @@
 use bytes::{Buf, Bytes};
 pub use codecs_derive::*;
-use ethers_core::types::{Bloom, H160, H256, U256};
+use ethers_core::types::{Bloom, H128, H160, H256, H512, H64, U256};
 
 /// Trait that implements the `Compact` codec.
 ///
 /// When deriving the trait for custom structs, be aware of certain limitations/recommendations:
-/// * Works best with structs that only have native types (eg. u64, H256, U256).
-/// * Fixed array types (H256, Address, Bloom) are not compacted.
-/// * Max size of `T` in `Option<T>` or `Vec<T>` shouldn't exceed `0xffff`.
+/// * Works best with structs that only have native types (eg. u64, i64, H256, U256).
+/// * Enum variants can carry a single unnamed field. Integer fields (eg. `Variant(u64)`) are
+///   prefixed by their length, any other field is decoded like the last field of a struct.
+/// * Fixed array types (H256, Address, Bloom) are not compacted and are written without a length.
+/// * Known aliases of fixed array types (eg. TxHash) should be added to `FIXED_SIZE_TYPES` in the
+///   derive crate, so they're not given a length in the `StructFlags`.
+/// * `Option<T>` of an integer type (eg. `Option<u64>`) keeps both its presence and its length in
+///   the `StructFlags`. Any other `Option<T>` is prefixed by its length as a LEB128 varint.
+/// * Integer elements of a `Vec`/`Option` (eg. `Vec<u64>` or `Option<Vec<u64>>`) are written as
+///   LEB128 varints, so they're not prefixed by their length.
+/// * Fields of a fieldless enum annotated with `#[variant_bits(N)]` keep their variant in `N` bits
+///   of the `StructFlags`. Otherwise the variant takes a byte.
+/// * Max size of any other `T` in `Vec<T>` shouldn't exceed `0xffff`.
 /// * Any `bytes::Bytes` field **should be placed last**.
 /// * Any other type which is not known to the derive module **should be placed last** in they
 ///   contain a `bytes::Bytes` field.
@@ -47,6 +57,55 @@ pub trait Compact {
     {
         Self::from_compact(buf, len)
     }
+
+    /// "Optional": Used by `Option<T>` fields where `T` is an integer type, so the compacted
+    /// length can be kept alongside the presence bit in the `StructFlags`. If there's no good
+    /// reason to use it, don't.
+    fn flagged_to_compact(self, buf: &mut impl bytes::BufMut) -> usize
+    where
+        Self: Sized,
+    {
+        self.to_compact(buf)
+    }
+
+    /// "Optional": If there's no good reason to use it, don't.
+    fn flagged_from_compact(buf: &[u8], len: usize) -> (Self, &[u8])
+    where
+        Self: Sized,
+    {
+        Self::from_compact(buf, len)
+    }
+}
+
+/// Writes the value as an unsigned LEB128 varint and returns the number of bytes written.
+///
+/// Every byte holds 7 bits of the value, starting with the least significant ones, and has the
+/// highest bit set if more bytes follow.
+pub fn encode_varuint(mut value: u128, buf: &mut impl bytes::BufMut) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        buf.put_u8(value as u8 | 0x80);
+        value >>= 7;
+        len += 1;
+    }
+    buf.put_u8(value as u8);
+    len
+}
+
+/// Reads an unsigned LEB128 varint and returns it alongside the buffer advanced past it.
+///
+/// It will panic, if the buffer ends before the varint does or the varint overflows a `u128`.
+pub fn decode_varuint(buf: &[u8]) -> (u128, &[u8]) {
+    let mut value = 0;
+    for (index, byte) in buf.iter().enumerate() {
+        let shift = index * 7;
+        assert!(shift < 128, "varint overflows u128");
+        value |= ((byte & 0x7f) as u128) << shift;
+        if byte & 0x80 == 0 {
+            return (value, &buf[index + 1..])
+        }
+    }
+    panic!("buffer ends inside a varint")
 }
 
 macro_rules! impl_uint_compact {
@@ -70,6 +129,19 @@ macro_rules! impl_uint_compact {
                     }
                     (0, buf)
                 }
+
+                /// To be used inside a `Vec`/`Option`, eg. `Vec<u64>`, where there's no room for
+                /// the length. Writes a LEB128 varint.
+                fn specialized_to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
+                    encode_varuint(u128::from(self), buf)
+                }
+
+                /// To be used inside a `Vec`/`Option`, eg. `Vec<u64>`. Reads a LEB128 varint and
+                /// ignores `len`.
+                fn specialized_from_compact(buf: &[u8], _: usize) -> (Self, &[u8]) {
+                    let (value, buf) = decode_varuint(buf);
+                    ($name::try_from(value).expect("varint overflows"), buf)
+                }
             }
         )+
     };
@@ -77,6 +149,38 @@ macro_rules! impl_uint_compact {
 
 impl_uint_compact!(u64, u128);
 
+/// Signed integers are zigzag encoded into their unsigned counterpart, so values close to zero
+/// (eg. `-1`) are compacted as well as small positive ones.
+macro_rules! impl_int_compact {
+    ($($name:tt => $unsigned:tt),+) => {
+        $(
+            impl Compact for $name {
+                fn to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
+                    let zigzag = ((self << 1) ^ (self >> ($name::BITS - 1))) as $unsigned;
+                    zigzag.to_compact(buf)
+                }
+
+                fn from_compact(buf: &[u8], len: usize) -> (Self, &[u8]) {
+                    let (zigzag, buf) = $unsigned::from_compact(buf, len);
+                    (((zigzag >> 1) as $name) ^ -((zigzag & 1) as $name), buf)
+                }
+
+                fn specialized_to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
+                    let zigzag = ((self << 1) ^ (self >> ($name::BITS - 1))) as $unsigned;
+                    zigzag.specialized_to_compact(buf)
+                }
+
+                fn specialized_from_compact(buf: &[u8], len: usize) -> (Self, &[u8]) {
+                    let (zigzag, buf) = $unsigned::specialized_from_compact(buf, len);
+                    (((zigzag >> 1) as $name) ^ -((zigzag & 1) as $name), buf)
+                }
+            }
+        )+
+    };
+}
+
+impl_int_compact!(i64 => u64, i128 => u128);
+
 impl<T> Compact for Vec<T>
 where
     T: Compact + Default,
@@ -89,7 +193,8 @@ where
         for element in self {
             // TODO: elias fano?
             let mut inner = Vec::with_capacity(32);
-            buf.put_u16(element.to_compact(&mut inner) as u16);
+            element.to_compact(&mut inner);
+            buf.put_u16(inner.len() as u16);
             buf.put_slice(&inner);
         }
         0
@@ -102,8 +207,10 @@ where
             #[allow(unused_assignments)]
             let mut element = T::default();
 
-            let len = buf.get_u16();
-            (element, buf) = T::from_compact(buf, len as usize);
+            let len = buf.get_u16() as usize;
+            // Elements can't read past their own length, eg. a trailing `bytes::Bytes` field.
+            (element, _) = T::from_compact(&buf[..len], len);
+            buf.advance(len);
 
             list.push(element);
         }
@@ -111,17 +218,17 @@ where
         (list, buf)
     }
 
-    /// To be used by fixed sized types like Vec<H256>.
+    /// To be used by types that are written without a length, like Vec<H256> or Vec<u64>.
     fn specialized_to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
         buf.put_u16(self.len() as u16);
 
         for element in self {
-            element.to_compact(buf);
+            element.specialized_to_compact(buf);
         }
         0
     }
 
-    /// To be used by fixed sized types like Vec<H256>.
+    /// To be used by types that are written without a length, like Vec<H256> or Vec<u64>.
     fn specialized_from_compact(mut buf: &[u8], len: usize) -> (Self, &[u8]) {
         let mut list = vec![];
         let length = buf.get_u16();
@@ -129,7 +236,7 @@ where
             #[allow(unused_assignments)]
             let mut element = T::default();
 
-            (element, buf) = T::from_compact(buf, len);
+            (element, buf) = T::specialized_from_compact(buf, len);
 
             list.push(element);
         }
@@ -142,45 +249,68 @@ impl<T> Compact for Option<T>
 where
     T: Compact + Default,
 {
-    /// Returns 0 for `None` and 1 for `Some(_)`.
+    /// Returns 0 for `None` and 1 for `Some(_)`. The element is prefixed by its length as a
+    /// varint.
     fn to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
         if let Some(element) = self {
             let mut inner = vec![];
             let len = element.to_compact(&mut inner);
-            buf.put_u16(len as u16);
+            encode_varuint(len as u128, buf);
             buf.put_slice(&inner);
             return 1
         }
         0
     }
 
-    fn from_compact(mut buf: &[u8], len: usize) -> (Self, &[u8]) {
+    fn from_compact(buf: &[u8], len: usize) -> (Self, &[u8]) {
         if len == 0 {
             return (None, buf)
         }
 
-        let len = buf.get_u16();
+        let (len, buf) = decode_varuint(buf);
         let (element, buf) = T::from_compact(buf, len as usize);
 
         (Some(element), buf)
     }
 
-    /// To be used by fixed sized types like Option<H256>.
+    /// To be used by types that are written without a length, like Option<H256> or
+    /// Option<Vec<u64>>.
     fn specialized_to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
         if let Some(element) = self {
-            element.to_compact(buf);
+            element.specialized_to_compact(buf);
             return 1
         }
         0
     }
 
-    /// To be used by fixed sized types like Option<H256>.
+    /// To be used by types that are written without a length, like Option<H256> or
+    /// Option<Vec<u64>>.
     fn specialized_from_compact(buf: &[u8], len: usize) -> (Self, &[u8]) {
         if len == 0 {
             return (None, buf)
         }
 
-        let (element, buf) = T::from_compact(buf, len);
+        let (element, buf) = T::specialized_from_compact(buf, len);
+
+        (Some(element), buf)
+    }
+
+    /// To be used by integer types like Option<u64>. Returns 0 for `None` and the length of the
+    /// element plus one for `Some(_)`.
+    fn flagged_to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
+        if let Some(element) = self {
+            return element.to_compact(buf) + 1
+        }
+        0
+    }
+
+    /// To be used by integer types like Option<u64>.
+    fn flagged_from_compact(buf: &[u8], len: usize) -> (Self, &[u8]) {
+        if len == 0 {
+            return (None, buf)
+        }
+
+        let (element, buf) = T::from_compact(buf, len - 1);
 
         (Some(element), buf)
     }
@@ -251,7 +381,7 @@ macro_rules! impl_hash_compact {
     };
 }
 
-impl_hash_compact!(H256, H160);
+impl_hash_compact!(H64, H128, H160, H256, H512);
 
 impl Compact for Bloom {
     fn to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
@@ -362,7 +492,8 @@ mod tests {
 
         assert_eq!(None::<H256>.to_compact(&mut buf), 0);
         assert_eq!(opt.to_compact(&mut buf), 1);
-        assert_eq!(buf.len(), 34);
+        // The length prefix is a single byte varint.
+        assert_eq!(buf.len(), 33);
 
         assert_eq!(Option::<H256>::from_compact(&buf, 1), (opt, vec![].as_slice()));
 
@@ -375,6 +506,90 @@ mod tests {
         assert_eq!(Option::<H256>::specialized_from_compact(&buf, 1), (opt, vec![].as_slice()));
     }
 
+    #[test]
+    fn compact_option_flagged() {
+        let mut buf = vec![];
+
+        // Presence and length are both returned, so nothing besides the value is written.
+        assert_eq!(None::<u64>.flagged_to_compact(&mut buf), 0);
+        assert!(buf.is_empty());
+        assert_eq!(Some(0u64).flagged_to_compact(&mut buf), 1);
+        assert!(buf.is_empty());
+        assert_eq!(Some(0xffffu64).flagged_to_compact(&mut buf), 3);
+        assert_eq!(buf, vec![0xff, 0xff]);
+
+        // Add some noise data.
+        buf.push(1);
+
+        assert_eq!(Option::<u64>::flagged_from_compact(&buf, 0), (None, buf.as_slice()));
+        assert_eq!(Option::<u64>::flagged_from_compact(&buf, 1), (Some(0), buf.as_slice()));
+        assert_eq!(
+            Option::<u64>::flagged_from_compact(&buf, 3),
+            (Some(0xffff), vec![1u8].as_slice())
+        );
+    }
+
+    #[test]
+    fn compact_option_flagged_roundtrip() {
+        // Every possible length has to fit in the bits reserved for the non-optional type.
+        for bytes in 0..=8 {
+            let value = Some(u64::MAX.checked_shr(64 - bytes * 8).unwrap_or_default());
+            let mut buf = vec![];
+            let len = value.flagged_to_compact(&mut buf);
+            assert!(len < 1 << 4);
+            assert_eq!(Option::<u64>::flagged_from_compact(&buf, len), (value, vec![].as_slice()));
+        }
+        for bytes in 0..=16 {
+            let value = Some(u128::MAX.checked_shr(128 - bytes * 8).unwrap_or_default());
+            let mut buf = vec![];
+            let len = value.flagged_to_compact(&mut buf);
+            assert!(len < 1 << 5);
+            assert_eq!(Option::<u128>::flagged_from_compact(&buf, len), (value, vec![].as_slice()));
+        }
+        for bytes in 0..=32usize {
+            let value =
+                Some(if bytes == 0 { U256::zero() } else { U256::MAX >> (256 - bytes * 8) });
+            let mut buf = vec![];
+            let len = value.flagged_to_compact(&mut buf);
+            assert!(len < 1 << 6);
+            assert_eq!(Option::<U256>::flagged_from_compact(&buf, len), (value, vec![].as_slice()));
+        }
+    }
+
+    #[test]
+    fn compact_fixed_hashes() {
+        fn roundtrip<T: Compact + Default + PartialEq + std::fmt::Debug + Copy>(size: usize) {
+            let mut buf = vec![];
+            assert_eq!(T::default().to_compact(&mut buf), size);
+            assert_eq!(buf, vec![0; size]);
+
+            // Add some noise data.
+            buf.push(1);
+
+            // Fixed size types shouldn't care about the len passed.
+            assert_eq!(T::from_compact(&buf, 1000), (T::default(), vec![1u8].as_slice()));
+        }
+
+        roundtrip::<H64>(8);
+        roundtrip::<H128>(16);
+        roundtrip::<H160>(20);
+        roundtrip::<H256>(32);
+        roundtrip::<H512>(64);
+    }
+
+    #[test]
+    fn compact_vec_bytes() {
+        let list = vec![Bytes::from_static(&[1, 2, 3]), Bytes::new(), Bytes::from_static(&[4])];
+        let mut buf = vec![];
+        list.clone().to_compact(&mut buf);
+
+        // Add some noise data in the end that should be returned by `from_compact`.
+        buf.extend([1u8, 2]);
+
+        // Elements that read the rest of the buffer must stop at their own length.
+        assert_eq!(Vec::<Bytes>::from_compact(&buf, 0), (list, vec![1u8, 2].as_slice()));
+    }
+
     #[test]
     fn compact_vec() {
         let list = vec![H256::zero(), H256::zero()];
@@ -425,6 +640,84 @@ mod tests {
         assert_eq!(u64::from_compact(&buf, 8), (0xffffffffffffffffu64, vec![].as_slice()));
     }
 
+    #[test]
+    fn compact_signed() {
+        let mut buf = vec![];
+
+        assert_eq!(0i64.to_compact(&mut buf), 0);
+        assert!(buf.is_empty());
+        assert_eq!(i64::from_compact(&buf, 0), (0i64, vec![].as_slice()));
+
+        // Small negative values are as compact as small positive ones.
+        assert_eq!((-1i64).to_compact(&mut buf), 1);
+        assert_eq!(buf, vec![1u8]);
+        assert_eq!(i64::from_compact(&buf, 1), (-1i64, vec![].as_slice()));
+
+        let mut buf = vec![];
+        assert_eq!(1i64.to_compact(&mut buf), 1);
+        assert_eq!(buf, vec![2u8]);
+
+        for value in [i64::MIN, i64::MIN + 1, -0x100, 0x100, i64::MAX] {
+            let mut buf = vec![];
+            let len = value.to_compact(&mut buf);
+            assert!(len <= 8);
+            assert_eq!(i64::from_compact(&buf, len), (value, vec![].as_slice()));
+        }
+        for value in [i128::MIN, -1, 1, i128::MAX] {
+            let mut buf = vec![];
+            let len = value.to_compact(&mut buf);
+            assert!(len <= 16);
+            assert_eq!(i128::from_compact(&buf, len), (value, vec![].as_slice()));
+        }
+        let value = Some(i64::MIN);
+        let mut buf = vec![];
+        let len = value.flagged_to_compact(&mut buf);
+        assert!(len < 1 << 4);
+        assert_eq!(Option::<i64>::flagged_from_compact(&buf, len), (value, vec![].as_slice()));
+    }
+
+    #[test]
+    fn compact_varuint() {
+        for (value, encoded) in [
+            (0u128, vec![0u8]),
+            (0x7f, vec![0x7f]),
+            (0x80, vec![0x80, 0x01]),
+            (300, vec![0xac, 0x02]),
+            (0x3fff, vec![0xff, 0x7f]),
+        ] {
+            let mut buf = vec![];
+            assert_eq!(encode_varuint(value, &mut buf), encoded.len());
+            assert_eq!(buf, encoded);
+
+            // Add some noise data.
+            buf.push(1);
+            assert_eq!(decode_varuint(&buf), (value, vec![1u8].as_slice()));
+        }
+
+        let mut buf = vec![];
+        assert_eq!(encode_varuint(u128::MAX, &mut buf), 19);
+        assert_eq!(decode_varuint(&buf), (u128::MAX, vec![].as_slice()));
+    }
+
+    #[test]
+    fn compact_vec_varint() {
+        let list = vec![0u64, 1, 0x80, u64::MAX];
+        let mut buf = vec![];
+        list.clone().specialized_to_compact(&mut buf);
+
+        // No length prefix for the elements, only the varints.
+        assert_eq!(buf.len(), 2 + 1 + 1 + 2 + 10);
+
+        // Add some noise data.
+        buf.push(1);
+        assert_eq!(Vec::<u64>::specialized_from_compact(&buf, 0), (list, vec![1u8].as_slice()));
+
+        let list = vec![-1i64, i64::MIN];
+        let mut buf = vec![];
+        list.clone().specialized_to_compact(&mut buf);
+        assert_eq!(Vec::<i64>::specialized_from_compact(&buf, 0), (list, vec![].as_slice()));
+    }
+
     #[main_codec]
     #[derive(Debug, PartialEq, Clone)]
     pub struct TestStruct {
@@ -448,7 +741,7 @@ mod tests {
                 f_bool_t: true,                               // 1 bit  | 0 bytes
                 f_option_none: None,                          // 1 bit  | 0 bytes
                 f_option_some: Some(H256::zero()),            // 1 bit  | 32 bytes
-                f_option_some_u64: Some(0xffffu64),           // 1 bit  | 2 + 2 bytes
+                f_option_some_u64: Some(0xffffu64),           // 4 bits | 2 bytes
                 f_vec_empty: vec![],                          // 0 bits | 2 bytes
                 f_vec_some: vec![H160::zero(), H160::zero()], // 0 bits | 2 + 20*2 bytes
             }
@@ -461,12 +754,12 @@ mod tests {
         let mut buf = vec![];
         assert_eq!(
             test.to_compact(&mut buf),
-            2 + // TestStructFlags
+            3 + // TestStructFlags
             1 +
             1 +
             // 0 + 0 + 0 +
             32 +
-            2 + 2 +
+            2 +
             2 +
             2 + 20 * 2
         );
@@ -484,6 +777,7 @@ mod tests {
         Var0,
         Var1(TestStruct),
         Var2(u64),
+        Var3(i64),
     }
 
     #[cfg(test)]
@@ -511,4 +805,163 @@ mod tests {
 
         compact_test_enum_all_variants(var0, var1, var2);
     }
+
+    #[test]
+    fn compact_test_enum_integer_variants() {
+        // Integer variants are prefixed by their length, so trailing data isn't consumed.
+        let mut buf = vec![];
+        TestEnum::Var2(0xffff).to_compact(&mut buf);
+        assert_eq!(buf, vec![2, 2, 0xff, 0xff]);
+        buf.push(1);
+        assert_eq!(
+            TestEnum::from_compact(&buf, buf.len()),
+            (TestEnum::Var2(0xffff), [1].as_slice())
+        );
+
+        let mut buf = vec![];
+        TestEnum::Var3(-1).to_compact(&mut buf);
+        assert_eq!(buf, vec![3, 1, 1]);
+        buf.push(1);
+        assert_eq!(TestEnum::from_compact(&buf, buf.len()), (TestEnum::Var3(-1), [1].as_slice()));
+
+        let mut buf = vec![];
+        TestEnum::Var3(0).to_compact(&mut buf);
+        assert_eq!(buf, vec![3, 0]);
+        assert_eq!(TestEnum::from_compact(&buf, buf.len()), (TestEnum::Var3(0), [].as_slice()));
+    }
+
+    #[main_codec]
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+    pub enum TestKind {
+        #[default]
+        Kind0,
+        Kind1,
+        Kind2,
+    }
+
+    #[main_codec]
+    #[derive(Debug, PartialEq, Clone, Default)]
+    pub struct TestVarStruct {
+        #[variant_bits(2)]
+        f_kind: TestKind,
+        f_vec_u64: Vec<u64>,
+        f_option_vec: Option<Vec<H256>>,
+        f_option_struct: Option<TestStruct>,
+        f_u64: u64,
+    }
+
+    #[test]
+    fn compact_fieldless_enum() {
+        // On its own the variant takes a byte.
+        let mut buf = vec![];
+        assert_eq!(TestKind::Kind2.to_compact(&mut buf), 1);
+        assert_eq!(buf, vec![2]);
+        assert_eq!(TestKind::from_compact(&buf, buf.len()), (TestKind::Kind2, vec![].as_slice()));
+
+        // As a field it's kept in the `StructFlags`.
+        let mut buf = vec![];
+        assert_eq!(TestKind::Kind2.flagged_to_compact(&mut buf), 2);
+        assert!(buf.is_empty());
+        assert_eq!(TestKind::flagged_from_compact(&buf, 2), (TestKind::Kind2, vec![].as_slice()));
+    }
+
+    #[test]
+    fn compact_test_var_struct() {
+        let test = TestVarStruct {
+            f_kind: TestKind::Kind1,
+            f_vec_u64: vec![1, 0x80],
+            f_option_vec: Some(vec![H256::zero()]),
+            f_option_struct: Some(TestStruct::default()),
+            f_u64: 0xff,
+        };
+        let mut test_struct_buf = vec![];
+        let test_struct_len = TestStruct::default().to_compact(&mut test_struct_buf);
+
+        let mut buf = vec![];
+        assert_eq!(
+            test.clone().to_compact(&mut buf),
+            1 + // TestVarStructFlags
+            2 + 1 + 2 +
+            2 + 32 +
+            1 + test_struct_len +
+            1
+        );
+        assert_eq!(TestVarStruct::from_compact(&buf, buf.len()), (test, vec![].as_slice()));
+
+        let mut buf = vec![];
+        TestVarStruct::default().to_compact(&mut buf);
+        assert_eq!(
+            TestVarStruct::from_compact(&buf, buf.len()),
+            (TestVarStruct::default(), vec![].as_slice())
+        );
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn proptest_varuint(value: u128) {
+            let mut buf = vec![];
+            let len = encode_varuint(value, &mut buf);
+            proptest::prop_assert_eq!(len, buf.len());
+            proptest::prop_assert_eq!(decode_varuint(&buf), (value, [].as_slice()));
+        }
+
+        #[test]
+        fn proptest_option_integers(value: Option<u64>, signed: Option<i128>) {
+            let mut buf = vec![];
+            let len = value.flagged_to_compact(&mut buf);
+            let decoded = Option::<u64>::flagged_from_compact(&buf, len);
+            proptest::prop_assert_eq!(decoded, (value, [].as_slice()));
+
+            let mut buf = vec![];
+            let len = signed.flagged_to_compact(&mut buf);
+            let decoded = Option::<i128>::flagged_from_compact(&buf, len);
+            proptest::prop_assert_eq!(decoded, (signed, [].as_slice()));
+        }
+
+        #[test]
+        fn proptest_test_var_struct(
+            kind: u8,
+            f_vec_u64: Vec<u64>,
+            hashes: Option<Vec<[u8; 32]>>,
+            f_option_u64: Option<u64>,
+            f_u64: u64,
+        ) {
+            let f_kind = match kind % 3 {
+                0 => TestKind::Kind0,
+                1 => TestKind::Kind1,
+                _ => TestKind::Kind2,
+            };
+            let f_option_struct = f_option_u64.map(|value| TestStruct {
+                f_option_some_u64: Some(value),
+                ..Default::default()
+            });
+            let test = TestVarStruct {
+                f_kind,
+                f_vec_u64,
+                f_option_vec: hashes.map(|hashes| hashes.into_iter().map(H256).collect()),
+                f_option_struct,
+                f_u64,
+            };
+            let mut buf = vec![];
+            let len = test.clone().to_compact(&mut buf);
+            proptest::prop_assert_eq!(len, buf.len());
+            proptest::prop_assert_eq!(TestVarStruct::from_compact(&buf, len), (test, [].as_slice()));
+        }
+    }
+
+    #[test]
+    fn compact_schema() {
+        assert_eq!(
+            TestStruct::COMPACT_SCHEMA,
+            "TestStruct{f_u64:u64,f_u256:U256,f_bool_t:bool,f_bool_f:bool,\
+             f_option_none:Option<H256>,f_option_some:Option<H256>,f_option_some_u64:Option<u64>,\
+             f_vec_empty:Vec<H160>,f_vec_some:Vec<H160>}"
+        );
+        assert_eq!(TestEnum::COMPACT_SCHEMA, "TestEnum{Var0,Var1(TestStruct),Var2(u64),Var3(i64)}");
+        assert_eq!(
+            TestVarStruct::COMPACT_SCHEMA,
+            "TestVarStruct{#[variant_bits(2)]f_kind:TestKind,f_vec_u64:Vec<u64>,\
+             f_option_vec:Option<Vec<H256>>,f_option_struct:Option<TestStruct>,f_u64:u64}"
+        );
+    }
 }
@@ -1,4 +1,7 @@
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use crate::{
     common::{IterPairResult, PairResult, ValueOnlyResult},
@@ -34,12 +37,33 @@ pub trait DbCursorRO<'tx, T: Table> {
     ) -> Result<Walker<'cursor, 'tx, T, Self>, Error>
     where
         Self: Sized;
+
+    /// Returns an iterator over the `(key, value)` pairs with keys within the range.
+    ///
+    /// The end of the range is compared with the [`Ord`] of the key, which has to match the order
+    /// of its encoding.
+    fn walk_range<'cursor>(
+        &'cursor mut self,
+        range: impl RangeBounds<T::Key>,
+    ) -> Result<RangeWalker<'cursor, 'tx, T, Self>, Error>
+    where
+        Self: Sized,
+        T::Key: Ord + Clone;
+
+    /// Returns an iterator in descending order starting at a key less or equal than `start_key`,
+    /// or at the last key if `start_key` is `None`.
+    fn walk_back<'cursor>(
+        &'cursor mut self,
+        start_key: Option<T::Key>,
+    ) -> Result<ReverseWalker<'cursor, 'tx, T, Self>, Error>
+    where
+        Self: Sized;
 }
 
 /// Read only cursor over DupSort table.
 pub trait DbDupCursorRO<'tx, T: DupSort> {
-    /// Seeks for a `(key, value)` pair greater or equal than `key`.
-    fn seek(&mut self, key: T::SubKey) -> PairResult<T>;
+    /// Seeks for the first duplicate of the first key greater or equal than `key`.
+    fn seek(&mut self, key: T::Key) -> PairResult<T>;
 
     /// Returns the next `(key, value)` pair of a DupSort table.
     fn next_dup(&mut self) -> PairResult<T>;
@@ -53,12 +77,14 @@ pub trait DbDupCursorRO<'tx, T: DupSort> {
     /// Seek by key and subkey
     fn seek_by_key_subkey(&mut self, key: T::Key, value: T::SubKey) -> ValueOnlyResult<T>;
 
-    /// Returns an iterator starting at a key greater or equal than `start_key` of a DupSort
-    /// table.
+    /// Returns an iterator over the duplicates of `key`, starting at the first duplicate with a
+    /// subkey greater or equal than `subkey`, or at the first duplicate if `subkey` is `None`.
+    ///
+    /// The iterator is empty if there is no such duplicate.
     fn walk_dup<'cursor>(
         &'cursor mut self,
         key: T::Key,
-        subkey: T::SubKey,
+        subkey: Option<T::SubKey>,
     ) -> Result<DupWalker<'cursor, 'tx, T, Self>, Error>
     where
         Self: Sized;
@@ -122,6 +148,86 @@ impl<'cursor, 'tx, T: Table, CURSOR: DbCursorRO<'tx, T>> std::iter::Iterator
     }
 }
 
+/// Provides an iterator to `Cursor` over the keys within a range when handling `Table`.
+///
+/// The iteration stops at the first key after the range, or at the first error.
+pub struct RangeWalker<'cursor, 'tx, T: Table, CURSOR: DbCursorRO<'tx, T>> {
+    /// Cursor to be used to walk through the table.
+    pub cursor: &'cursor mut CURSOR,
+    /// `(key, value)` where to start the walk.
+    pub start: IterPairResult<T>,
+    /// The end of the range.
+    pub end_key: Bound<T::Key>,
+    /// Whether the end of the range was reached.
+    pub is_done: bool,
+    /// Phantom data for 'tx. As it is only used for `DbCursorRO`.
+    pub _tx_phantom: PhantomData<&'tx T>,
+}
+
+impl<'cursor, 'tx, T: Table, CURSOR: DbCursorRO<'tx, T>> std::iter::Iterator
+    for RangeWalker<'cursor, 'tx, T, CURSOR>
+where
+    T::Key: Ord,
+{
+    type Item = Result<(T::Key, T::Value), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None
+        }
+
+        let next = match self.start.take() {
+            Some(start) => Some(start),
+            None => self.cursor.next().transpose(),
+        };
+        match next {
+            Some(Ok((key, value))) => {
+                let in_range = match &self.end_key {
+                    Bound::Included(end) => key <= *end,
+                    Bound::Excluded(end) => key < *end,
+                    Bound::Unbounded => true,
+                };
+                if in_range {
+                    return Some(Ok((key, value)))
+                }
+                self.is_done = true;
+                None
+            }
+            Some(Err(err)) => {
+                self.is_done = true;
+                Some(Err(err))
+            }
+            None => {
+                self.is_done = true;
+                None
+            }
+        }
+    }
+}
+
+/// Provides an iterator to `Cursor` in descending key order when handling `Table`.
+pub struct ReverseWalker<'cursor, 'tx, T: Table, CURSOR: DbCursorRO<'tx, T>> {
+    /// Cursor to be used to walk through the table.
+    pub cursor: &'cursor mut CURSOR,
+    /// `(key, value)` where to start the walk.
+    pub start: IterPairResult<T>,
+    /// Phantom data for 'tx. As it is only used for `DbCursorRO`.
+    pub _tx_phantom: PhantomData<&'tx T>,
+}
+
+impl<'cursor, 'tx, T: Table, CURSOR: DbCursorRO<'tx, T>> std::iter::Iterator
+    for ReverseWalker<'cursor, 'tx, T, CURSOR>
+{
+    type Item = Result<(T::Key, T::Value), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.start.take();
+        if start.is_some() {
+            return start
+        }
+
+        self.cursor.prev().transpose()
+    }
+}
+
 /// Provides an iterator to `Cursor` when handling a `DupSort` table.
 ///
 /// Reason why we have two lifetimes is to distinguish between `'cursor` lifetime
@@ -132,6 +238,8 @@ pub struct DupWalker<'cursor, 'tx, T: DupSort, CURSOR: DbDupCursorRO<'tx, T>> {
     pub cursor: &'cursor mut CURSOR,
     /// Value where to start the walk.
     pub start: IterPairResult<T>,
+    /// Whether there are no duplicates to walk, the cursor is not positioned then.
+    pub is_done: bool,
     /// Phantom data for 'tx. As it is only used for `DbDupCursorRO`.
     pub _tx_phantom: PhantomData<&'tx T>,
 }
@@ -141,6 +249,10 @@ impl<'cursor, 'tx, T: DupSort, CURSOR: DbDupCursorRO<'tx, T>> std::iter::Iterato
 {
     type Item = Result<(T::Key, T::Value), Error>;
     fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None
+        }
+
         let start = self.start.take();
         if start.is_some() {
             return start
@@ -1,9 +1,12 @@
 //! Mock database
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, ops::RangeBounds};
 
 use crate::{
     common::{PairResult, ValueOnlyResult},
-    cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW, DupWalker, Walker},
+    cursor::{
+        DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW, DupWalker, RangeWalker,
+        ReverseWalker, Walker,
+    },
     database::{Database, DatabaseGAT},
     table::{DupSort, Table},
     transaction::{DbTx, DbTxGAT, DbTxMut, DbTxMutGAT},
@@ -132,6 +135,27 @@ impl<'tx, T: Table> DbCursorRO<'tx, T> for CursorMock {
     {
         todo!()
     }
+
+    fn walk_range<'cursor>(
+        &'cursor mut self,
+        _range: impl RangeBounds<T::Key>,
+    ) -> Result<RangeWalker<'cursor, 'tx, T, Self>, Error>
+    where
+        Self: Sized,
+        T::Key: Ord + Clone,
+    {
+        todo!()
+    }
+
+    fn walk_back<'cursor>(
+        &'cursor mut self,
+        _start_key: Option<T::Key>,
+    ) -> Result<ReverseWalker<'cursor, 'tx, T, Self>, Error>
+    where
+        Self: Sized,
+    {
+        todo!()
+    }
 }
 
 impl<'tx, T: DupSort> DbDupCursorRO<'tx, T> for CursorMock {
@@ -139,7 +163,7 @@ impl<'tx, T: DupSort> DbDupCursorRO<'tx, T> for CursorMock {
         todo!()
     }
 
-    fn seek(&mut self, _key: T::SubKey) -> PairResult<T> {
+    fn seek(&mut self, _key: T::Key) -> PairResult<T> {
         todo!()
     }
 
@@ -162,7 +186,7 @@ impl<'tx, T: DupSort> DbDupCursorRO<'tx, T> for CursorMock {
     fn walk_dup<'cursor>(
         &'cursor mut self,
         _key: <T>::Key,
-        _subkey: <T as DupSort>::SubKey,
+        _subkey: Option<<T as DupSort>::SubKey>,
     ) -> Result<DupWalker<'cursor, 'tx, T, Self>, Error>
     where
         Self: Sized,
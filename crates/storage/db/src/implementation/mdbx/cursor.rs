@@ -1,9 +1,16 @@
 //! Cursor wrapper for libmdbx-sys.
 
-use std::{borrow::Cow, marker::PhantomData};
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use crate::{
-    cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW, DupWalker, Walker},
+    cursor::{
+        DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW, DupWalker, RangeWalker,
+        ReverseWalker, Walker,
+    },
     table::{Compress, DupSort, Encode, Table},
     tables::utils::*,
     Error,
@@ -81,10 +88,75 @@ impl<'tx, K: TransactionKind, T: Table> DbCursorRO<'tx, T> for Cursor<'tx, K, T>
 
         Ok(Walker::<'cursor, 'tx, T, Self> { cursor: self, start, _tx_phantom: PhantomData {} })
     }
+
+    fn walk_range<'cursor>(
+        &'cursor mut self,
+        range: impl RangeBounds<T::Key>,
+    ) -> Result<RangeWalker<'cursor, 'tx, T, Self>, Error>
+    where
+        Self: Sized,
+        T::Key: Ord + Clone,
+    {
+        let start = match range.start_bound().cloned() {
+            Bound::Included(key) => self.inner.set_range(key.encode().as_ref()),
+            Bound::Excluded(key) => {
+                let key = key.encode();
+                match self.inner.set_range::<Cow<'_, [u8]>, Cow<'_, [u8]>>(key.as_ref()) {
+                    Ok(Some((found, _))) if found.as_ref() == key.as_ref() => self.inner.next(),
+                    res => res,
+                }
+            }
+            Bound::Unbounded => self.inner.first(),
+        }
+        .map_err(|e| Error::Read(e.into()))?
+        .map(decoder::<T>);
+
+        Ok(RangeWalker::<'cursor, 'tx, T, Self> {
+            // The cursor is not positioned if there is no key to start at.
+            is_done: start.is_none(),
+            cursor: self,
+            start,
+            end_key: range.end_bound().cloned(),
+            _tx_phantom: PhantomData {},
+        })
+    }
+
+    fn walk_back<'cursor>(
+        &'cursor mut self,
+        start_key: Option<T::Key>,
+    ) -> Result<ReverseWalker<'cursor, 'tx, T, Self>, Error>
+    where
+        Self: Sized,
+    {
+        let start = match start_key {
+            Some(key) => {
+                let key = key.encode();
+                match self.inner.set_range::<Cow<'_, [u8]>, Cow<'_, [u8]>>(key.as_ref()) {
+                    Ok(Some((found, value))) if found.as_ref() == key.as_ref() => {
+                        Ok(Some((found, value)))
+                    }
+                    // The first key after `start_key`, the walk starts at the key before it.
+                    Ok(Some(_)) => self.inner.prev(),
+                    // Every key is before `start_key`.
+                    Ok(None) => self.inner.last(),
+                    Err(err) => Err(err),
+                }
+            }
+            None => self.inner.last(),
+        }
+        .map_err(|e| Error::Read(e.into()))?
+        .map(decoder::<T>);
+
+        Ok(ReverseWalker::<'cursor, 'tx, T, Self> {
+            cursor: self,
+            start,
+            _tx_phantom: PhantomData {},
+        })
+    }
 }
 
 impl<'tx, K: TransactionKind, T: DupSort> DbDupCursorRO<'tx, T> for Cursor<'tx, K, T> {
-    fn seek(&mut self, key: <T as DupSort>::SubKey) -> PairResult<T> {
+    fn seek(&mut self, key: <T as Table>::Key) -> PairResult<T> {
         decode!(self.inner.set_range(key.encode().as_ref()))
     }
 
@@ -115,22 +187,30 @@ impl<'tx, K: TransactionKind, T: DupSort> DbDupCursorRO<'tx, T> for Cursor<'tx,
             .transpose()
     }
 
-    /// Returns an iterator starting at a key greater or equal than `start_key` of a DUPSORT table.
+    /// Returns an iterator over the duplicates of `key` of a DUPSORT table, starting at `subkey`
+    /// or at the first duplicate.
     fn walk_dup<'cursor>(
         &'cursor mut self,
         key: T::Key,
-        subkey: T::SubKey,
+        subkey: Option<T::SubKey>,
     ) -> Result<DupWalker<'cursor, 'tx, T, Self>, Error> {
         // encode key and decode it after.
         let key = key.encode().as_ref().to_vec();
 
-        let start = self
-            .inner
-            .get_both_range(key.as_ref(), subkey.encode().as_ref())
-            .map_err(|e| Error::Read(e.into()))?
-            .map(|val| decoder::<T>((Cow::Owned(key), val)));
+        let value = match subkey {
+            Some(subkey) => self.inner.get_both_range(key.as_ref(), subkey.encode().as_ref()),
+            None => self.inner.set(key.as_ref()),
+        }
+        .map_err(|e| Error::Read(e.into()))?;
+        let start = value.map(|val| decoder::<T>((Cow::Owned(key), val)));
+        let is_done = start.is_none();
 
-        Ok(DupWalker::<'cursor, 'tx, T, Self> { cursor: self, start, _tx_phantom: PhantomData {} })
+        Ok(DupWalker::<'cursor, 'tx, T, Self> {
+            cursor: self,
+            start,
+            is_done,
+            _tx_phantom: PhantomData {},
+        })
     }
 }
 
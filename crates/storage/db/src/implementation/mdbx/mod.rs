@@ -26,6 +26,42 @@ pub enum EnvKind {
     RW,
 }
 
+/// Settings of the MDBX environment.
+#[derive(Debug, Clone, Copy)]
+pub struct EnvConfig {
+    /// The maximum size of the database file in bytes.
+    pub max_size: usize,
+    /// The number of bytes the database file grows by once it is full.
+    pub growth_step: usize,
+    /// How the commits of read-write environments are flushed to disk.
+    pub sync_mode: SyncMode,
+    /// The maximum number of concurrent read transactions, the MDBX default if `None`.
+    pub max_readers: Option<u32>,
+    /// Whether the OS reads ahead of the accessed pages.
+    ///
+    /// Read-ahead only pays off if the database fits into memory, otherwise it evicts pages that
+    /// are still needed.
+    pub read_ahead: bool,
+    /// Whether no other process may open the environment at the same time.
+    ///
+    /// Opening fails if another process has the environment open, eg. to make sure no node is
+    /// running on the database.
+    pub exclusive: bool,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 1024 * 1024 * 1024 * 1024 * 4, // 4 TB
+            growth_step: 1024 * 1024 * 256,          // 256 MB
+            sync_mode: SyncMode::Durable,
+            max_readers: None,
+            read_ahead: false,
+            exclusive: false,
+        }
+    }
+}
+
 /// Wrapper for the libmdbx environment.
 #[derive(Debug)]
 pub struct Env<E: EnvironmentKind> {
@@ -53,29 +89,43 @@ impl<E: EnvironmentKind> Env<E> {
     ///
     /// It does not create the tables, for that call [`Env::create_tables`].
     pub fn open(path: &Path, kind: EnvKind) -> Result<Env<E>, Error> {
+        Self::open_with_config(path, kind, EnvConfig::default())
+    }
+
+    /// Opens the database at the specified path with the given `EnvKind` and settings.
+    ///
+    /// It does not create the tables, for that call [`Env::create_tables`].
+    pub fn open_with_config(
+        path: &Path,
+        kind: EnvKind,
+        config: EnvConfig,
+    ) -> Result<Env<E>, Error> {
         let mode = match kind {
             EnvKind::RO => Mode::ReadOnly,
-            EnvKind::RW => Mode::ReadWrite { sync_mode: SyncMode::Durable },
+            EnvKind::RW => Mode::ReadWrite { sync_mode: config.sync_mode },
         };
 
-        let env = Env {
-            inner: Environment::new()
-                .set_max_dbs(TABLES.len())
-                .set_geometry(Geometry {
-                    size: Some(0..(1024 * 1024 * 1024 * 1024 * 4)), // TODO: reevaluate (4 tb)
-                    growth_step: Some(1024 * 1024 * 256),           // TODO: reevaluate (256 mb)
-                    shrink_threshold: None,
-                    page_size: Some(PageSize::Set(default_page_size())),
-                })
-                .set_flags(EnvironmentFlags {
-                    mode,
-                    no_rdahead: true, // TODO: reevaluate
-                    coalesce: true,
-                    ..Default::default()
-                })
-                .open(path)
-                .map_err(|e| Error::DatabaseLocation(e.into()))?,
-        };
+        let mut builder = Environment::new();
+        builder
+            .set_max_dbs(TABLES.len())
+            .set_geometry(Geometry {
+                size: Some(0..config.max_size),
+                growth_step: Some(config.growth_step as isize),
+                shrink_threshold: None,
+                page_size: Some(PageSize::Set(default_page_size())),
+            })
+            .set_flags(EnvironmentFlags {
+                mode,
+                no_rdahead: !config.read_ahead,
+                exclusive: config.exclusive,
+                coalesce: true,
+                ..Default::default()
+            });
+        if let Some(max_readers) = config.max_readers {
+            builder.set_max_readers(max_readers);
+        }
+
+        let env = Env { inner: builder.open(path).map_err(|e| Error::DatabaseLocation(e.into()))? };
 
         Ok(env)
     }
@@ -142,7 +192,7 @@ pub mod test_utils {
 
 #[cfg(test)]
 mod tests {
-    use super::{test_utils, Env, EnvKind};
+    use super::{test_utils, Env, EnvConfig, EnvKind, SyncMode};
     use crate::{
         cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO},
         database::Database,
@@ -153,7 +203,7 @@ mod tests {
     };
     use reth_libmdbx::{NoWriteMap, WriteMap};
     use reth_primitives::{Account, Address, Header, IntegerList, StorageEntry, H256, U256};
-    use std::{str::FromStr, sync::Arc};
+    use std::{ops::Bound, str::FromStr, sync::Arc};
     use tempfile::TempDir;
 
     const ERROR_DB_CREATION: &str = "Not able to create the mdbx file.";
@@ -169,6 +219,23 @@ mod tests {
         test_utils::create_test_db::<NoWriteMap>(EnvKind::RW);
     }
 
+    #[test]
+    fn db_open_with_config() {
+        let path = TempDir::new().unwrap();
+        let config = EnvConfig {
+            sync_mode: SyncMode::SafeNoSync,
+            max_readers: Some(500),
+            read_ahead: true,
+            ..Default::default()
+        };
+        let env = Env::<NoWriteMap>::open_with_config(path.path(), EnvKind::RW, config)
+            .expect(ERROR_DB_CREATION);
+        env.create_tables().unwrap();
+        // MDBX rounds the readers up to fill the pages of the lock file
+        let max_readers = env.info().unwrap().max_readers();
+        assert!((500..600).contains(&max_readers), "{max_readers}");
+    }
+
     #[test]
     fn db_manual_put_get() {
         let env = test_utils::create_test_db::<NoWriteMap>(EnvKind::RW);
@@ -213,6 +280,59 @@ mod tests {
         assert_eq!(first.1, value, "First next should be put value");
     }
 
+    #[test]
+    fn db_cursor_walk_range() {
+        let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
+
+        // PUT (0, 0), (1, 0), (3, 0), (4, 0)
+        let tx = db.tx_mut().expect(ERROR_INIT_TX);
+        vec![0, 1, 3, 4]
+            .into_iter()
+            .try_for_each(|key| tx.put::<CanonicalHeaders>(key, H256::zero()))
+            .expect(ERROR_PUT);
+        tx.commit().expect(ERROR_COMMIT);
+
+        let tx = db.tx().expect(ERROR_INIT_TX);
+        let mut cursor = tx.cursor::<CanonicalHeaders>().unwrap();
+        let mut keys = |range: (Bound<u64>, Bound<u64>)| {
+            cursor.walk_range(range).unwrap().map(|res| res.unwrap().0).collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys((Bound::Included(1), Bound::Included(3))), vec![1, 3]);
+        assert_eq!(keys((Bound::Included(1), Bound::Excluded(3))), vec![1]);
+        assert_eq!(keys((Bound::Excluded(1), Bound::Unbounded)), vec![3, 4]);
+        assert_eq!(keys((Bound::Excluded(2), Bound::Included(3))), vec![3]);
+        assert_eq!(keys((Bound::Unbounded, Bound::Excluded(1))), vec![0]);
+        // no key to start at
+        assert_eq!(keys((Bound::Included(5), Bound::Unbounded)), Vec::<u64>::new());
+        assert_eq!(keys((Bound::Included(2), Bound::Included(2))), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn db_cursor_walk_back() {
+        let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
+
+        // PUT (0, 0), (1, 0), (3, 0)
+        let tx = db.tx_mut().expect(ERROR_INIT_TX);
+        vec![0, 1, 3]
+            .into_iter()
+            .try_for_each(|key| tx.put::<CanonicalHeaders>(key, H256::zero()))
+            .expect(ERROR_PUT);
+        tx.commit().expect(ERROR_COMMIT);
+
+        let tx = db.tx().expect(ERROR_INIT_TX);
+        let mut cursor = tx.cursor::<CanonicalHeaders>().unwrap();
+        let mut keys = |start: Option<u64>| {
+            cursor.walk_back(start).unwrap().map(|res| res.unwrap().0).collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(None), vec![3, 1, 0]);
+        assert_eq!(keys(Some(3)), vec![3, 1, 0]);
+        assert_eq!(keys(Some(2)), vec![1, 0]);
+        assert_eq!(keys(Some(10)), vec![3, 1, 0]);
+        assert_eq!(keys(Some(0)), vec![0]);
+    }
+
     #[test]
     fn db_cursor_seek_exact_or_previous_key() {
         let db: Arc<Env<WriteMap>> = test_utils::create_test_db(EnvKind::RW);
@@ -356,7 +476,7 @@ mod tests {
         {
             let tx = env.tx().expect(ERROR_INIT_TX);
             let mut cursor = tx.cursor_dup::<PlainStorageState>().unwrap();
-            let mut walker = cursor.walk_dup(key, H256::from_low_u64_be(1)).unwrap();
+            let mut walker = cursor.walk_dup(key, Some(H256::from_low_u64_be(1))).unwrap();
             assert_eq!(
                 (key, value11),
                 walker
@@ -395,7 +515,7 @@ mod tests {
             let tx = env.tx().expect(ERROR_INIT_TX);
             let mut cursor = tx.cursor_dup::<PlainStorageState>().unwrap();
             let first = cursor.first().unwrap().unwrap();
-            let mut walker = cursor.walk_dup(first.0, first.1.key).unwrap();
+            let mut walker = cursor.walk_dup(first.0, Some(first.1.key)).unwrap();
 
             // Notice that value11 and value22 have been ordered in the DB.
             assert_eq!(Some(Ok((key1, value00.clone()))), walker.next());
@@ -417,6 +537,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn db_walk_dup_of_key() {
+        let env = test_utils::create_test_db::<NoWriteMap>(EnvKind::RW);
+        let key1 = Address::from_low_u64_be(1);
+        let key2 = Address::from_low_u64_be(2);
+        let key3 = Address::from_low_u64_be(3);
+
+        let value1 = StorageEntry { key: H256::from_low_u64_be(1), value: U256::from(1) };
+        let value2 = StorageEntry { key: H256::from_low_u64_be(2), value: U256::from(2) };
+        env.update(|tx| {
+            tx.put::<PlainStorageState>(key1, value2.clone()).expect(ERROR_PUT);
+            tx.put::<PlainStorageState>(key1, value1.clone()).expect(ERROR_PUT);
+            tx.put::<PlainStorageState>(key3, value1.clone()).expect(ERROR_PUT);
+        })
+        .unwrap();
+
+        let tx = env.tx().expect(ERROR_INIT_TX);
+        let mut cursor = tx.cursor_dup::<PlainStorageState>().unwrap();
+
+        // all slots of the key, without the ones of the next key
+        let slots = cursor.walk_dup(key1, None).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(slots, vec![(key1, value1.clone()), (key1, value2.clone())]);
+
+        // the slots from the subkey on
+        let slots = cursor
+            .walk_dup(key1, Some(H256::from_low_u64_be(2)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(slots, vec![(key1, value2)]);
+
+        // no slots of the key or after the subkey
+        assert_eq!(cursor.walk_dup(key2, None).unwrap().next(), None);
+        assert_eq!(cursor.walk_dup(key1, Some(H256::from_low_u64_be(3))).unwrap().next(), None);
+
+        // seek positions at the first slot of the next key
+        assert_eq!(cursor.seek(key2).unwrap(), Some((key3, value1)));
+        assert_eq!(cursor.next_dup().unwrap(), None);
+    }
+
     #[test]
     fn dup_value_with_same_subkey() {
         let env = test_utils::create_test_db::<NoWriteMap>(EnvKind::RW);
@@ -11,6 +11,7 @@
 pub mod abstraction;
 
 mod implementation;
+pub mod migration;
 pub mod tables;
 mod utils;
 
@@ -0,0 +1,173 @@
+//! Decoders of the version `0` table encodings.
+//!
+//! They only exist to upgrade existing databases and mirror the code that `main_codec` generated
+//! before fixed size hash aliases and `Option` integers were kept out of the buffer.
+
+use crate::tables::models::{AccountBeforeTx, StoredBlockOmmers};
+use bytes::Buf;
+use reth_codecs::Compact;
+use reth_primitives::{
+    Account, Address, Bloom, Header, Signature, Transaction, TransactionKind, TransactionSigned,
+    TxHash, TxLegacy, H160, H256, U256,
+};
+
+pub(crate) use flags::*;
+
+mod flags {
+    // The setters are only used to write version `0` entries in tests.
+    #![allow(dead_code)]
+
+    use modular_bitfield::prelude::*;
+
+    /// Version `0` fieldset of [`Header`](reth_primitives::Header).
+    #[bitfield]
+    #[derive(Clone, Copy, Debug, Default)]
+    pub(crate) struct HeaderFlagsV0 {
+        pub(crate) difficulty_len: B6,
+        pub(crate) number_len: B4,
+        pub(crate) gas_limit_len: B4,
+        pub(crate) gas_used_len: B4,
+        pub(crate) timestamp_len: B4,
+        pub(crate) nonce_len: B4,
+        pub(crate) base_fee_per_gas_len: B1,
+        #[skip]
+        unused: B5,
+    }
+
+    /// Version `0` fieldset of [`TxLegacy`](reth_primitives::TxLegacy).
+    #[bitfield]
+    #[derive(Clone, Copy, Debug, Default)]
+    pub(crate) struct TxLegacyFlagsV0 {
+        pub(crate) chain_id_len: B1,
+        pub(crate) nonce_len: B4,
+        pub(crate) gas_price_len: B5,
+        pub(crate) gas_limit_len: B4,
+        pub(crate) value_len: B5,
+        #[skip]
+        unused: B5,
+    }
+
+    /// Version `0` fieldset of [`TransactionSigned`](reth_primitives::TransactionSigned).
+    #[bitfield]
+    #[derive(Clone, Copy, Debug, Default)]
+    pub(crate) struct TransactionSignedFlagsV0 {
+        pub(crate) hash_len: B6,
+        #[skip]
+        unused: B2,
+    }
+}
+
+/// Decodes a version `0` [`Header`].
+pub(crate) fn decode_header(buf: &[u8]) -> Option<Header> {
+    let (flags, mut buf) = take::<4>(buf)?;
+    let flags = HeaderFlagsV0::from_bytes(flags);
+
+    let mut header = Header::default();
+    (header.parent_hash, buf) = H256::from_compact(buf, 32);
+    (header.ommers_hash, buf) = H256::from_compact(buf, 32);
+    (header.beneficiary, buf) = H160::from_compact(buf, 20);
+    (header.state_root, buf) = H256::from_compact(buf, 32);
+    (header.transactions_root, buf) = H256::from_compact(buf, 32);
+    (header.receipts_root, buf) = H256::from_compact(buf, 32);
+    (header.logs_bloom, buf) = Bloom::from_compact(buf, 256);
+    (header.difficulty, buf) = U256::from_compact(buf, flags.difficulty_len() as usize);
+    (header.number, buf) = u64::from_compact(buf, flags.number_len() as usize);
+    (header.gas_limit, buf) = u64::from_compact(buf, flags.gas_limit_len() as usize);
+    (header.gas_used, buf) = u64::from_compact(buf, flags.gas_used_len() as usize);
+    (header.timestamp, buf) = u64::from_compact(buf, flags.timestamp_len() as usize);
+    (header.mix_hash, buf) = H256::from_compact(buf, 32);
+    (header.nonce, buf) = u64::from_compact(buf, flags.nonce_len() as usize);
+    (header.base_fee_per_gas, buf) = decode_prefixed_option(buf, flags.base_fee_per_gas_len())?;
+    (header.extra_data, _) = bytes::Bytes::from_compact(buf, buf.len());
+
+    Some(header)
+}
+
+/// Decodes version `0` [`StoredBlockOmmers`].
+pub(crate) fn decode_ommers(mut buf: &[u8]) -> Option<StoredBlockOmmers> {
+    let count = take_u16(&mut buf)?;
+    let mut ommers = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = take_u16(&mut buf)?;
+        ommers.push(decode_header(buf.get(..len)?)?);
+        buf.advance(len);
+    }
+    Some(StoredBlockOmmers { ommers })
+}
+
+/// Decodes a version `0` [`TransactionSigned`].
+pub(crate) fn decode_transaction_signed(buf: &[u8]) -> Option<TransactionSigned> {
+    let (flags, buf) = take::<1>(buf)?;
+    let flags = TransactionSignedFlagsV0::from_bytes(flags);
+
+    let hash_len = flags.hash_len() as usize;
+    if buf.len() < hash_len {
+        return None
+    }
+    let (hash, buf) = TxHash::from_compact(buf, hash_len);
+    let (signature, buf) = Signature::from_compact(buf, buf.len());
+
+    // Only the legacy variant changed, the others are decoded as they are.
+    let transaction = match buf.first()? {
+        0 => Transaction::Legacy(decode_tx_legacy(&buf[1..])?),
+        _ => Transaction::from_compact(buf, buf.len()).0,
+    };
+
+    Some(TransactionSigned { hash, signature, transaction })
+}
+
+/// Decodes a version `0` [`TxLegacy`].
+fn decode_tx_legacy(buf: &[u8]) -> Option<TxLegacy> {
+    let (flags, mut buf) = take::<3>(buf)?;
+    let flags = TxLegacyFlagsV0::from_bytes(flags);
+
+    let mut tx = TxLegacy::default();
+    (tx.chain_id, buf) = decode_prefixed_option(buf, flags.chain_id_len())?;
+    (tx.nonce, buf) = u64::from_compact(buf, flags.nonce_len() as usize);
+    (tx.gas_price, buf) = u128::from_compact(buf, flags.gas_price_len() as usize);
+    (tx.gas_limit, buf) = u64::from_compact(buf, flags.gas_limit_len() as usize);
+    (tx.to, buf) = TransactionKind::from_compact(buf, buf.len());
+    (tx.value, buf) = u128::from_compact(buf, flags.value_len() as usize);
+    (tx.input, _) = reth_primitives::Bytes::from_compact(buf, buf.len());
+
+    Some(tx)
+}
+
+/// Decodes a version `0` [`AccountBeforeTx`].
+pub(crate) fn decode_account_before_tx(buf: &[u8]) -> Option<AccountBeforeTx> {
+    let address = Address::from_slice(buf.get(..20)?);
+    let mut buf = &buf[20..];
+
+    let info = if buf.is_empty() {
+        None
+    } else {
+        let len = take_u16(&mut buf)?;
+        Some(Account::from_compact(buf.get(..len)?, len).0)
+    };
+
+    Some(AccountBeforeTx { address, info })
+}
+
+/// Decodes an `Option` that was written with a `u16` length prefix.
+fn decode_prefixed_option<T: Compact>(mut buf: &[u8], present: u8) -> Option<(Option<T>, &[u8])> {
+    if present == 0 {
+        return Some((None, buf))
+    }
+    let len = take_u16(&mut buf)?;
+    let (value, _) = T::from_compact(buf.get(..len)?, len);
+    buf.advance(len);
+    Some((Some(value), buf))
+}
+
+/// Splits off the first `N` bytes.
+fn take<const N: usize>(buf: &[u8]) -> Option<([u8; N], &[u8])> {
+    let bytes = buf.get(..N)?.try_into().ok()?;
+    Some((bytes, &buf[N..]))
+}
+
+/// Reads a big endian `u16` and advances the buffer.
+fn take_u16(buf: &mut &[u8]) -> Option<usize> {
+    let (bytes, rest) = take::<2>(buf)?;
+    *buf = rest;
+    Some(u16::from_be_bytes(bytes) as usize)
+}
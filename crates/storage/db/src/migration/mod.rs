@@ -0,0 +1,670 @@
+//! Versioning of the table encodings and migrations between them.
+//!
+//! Every table has its own encoding version, stored in [`tables::Config`] under
+//! [`TABLE_VERSION_KEY_PREFIX`] followed by the name of the table. Tables are at version `0` until
+//! their encoding changes, then they're listed in [`TABLE_VERSIONS`] and [`migrate`] upgrades them
+//! one version at a time. Changing the layout of a single table (eg. `Receipts`) only re-encodes
+//! that table instead of requiring a resync.
+//!
+//! Databases written before the tables were versioned individually have a single version, stored
+//! under [`LEGACY_DB_VERSION_KEY`], which is converted to the versions of the tables it implied.
+
+use crate::{
+    cursor::{DbCursorRO, DbCursorRW},
+    database::Database,
+    table::{Compress, Decompress, Table},
+    tables,
+    transaction::{DbTx, DbTxMut},
+    Error,
+};
+use reth_codecs::Compact;
+use std::collections::BTreeMap;
+
+mod legacy;
+#[cfg(test)]
+mod schema;
+
+/// Encoding version of the tables whose encoding changed since they were added, as written by this
+/// release. Any other table is at version `0`.
+///
+/// The version of a table has to be bumped whenever the encoding of its values changes, which the
+/// tests detect by comparing the `COMPACT_SCHEMA` of the derived types against the ones recorded
+/// for these versions. A migration from the previous version has to be added to
+/// [`migrate_table`].
+///
+/// * `Headers`, `BlockOmmers`, `Transactions` and `NonCanonicalTransactions` `1`: Fixed size hash
+///   aliases (eg. `TxHash`) don't have their length stored and `Option` integers (eg.
+///   `Option<u64>`) keep their length in the `StructFlags` instead of a `u16` prefix.
+/// * `AccountChangeSet` `1`: The account of [`AccountBeforeTx`](tables::models::AccountBeforeTx)
+///   isn't length prefixed.
+pub const TABLE_VERSIONS: [(&str, u64); 5] = [
+    (tables::Headers::const_name(), 1),
+    (tables::BlockOmmers::const_name(), 1),
+    (tables::Transactions::const_name(), 1),
+    (tables::NonCanonicalTransactions::const_name(), 1),
+    (tables::AccountChangeSet::const_name(), 1),
+];
+
+/// Prefix of the keys of the table versions in [`tables::Config`], followed by the table name.
+pub const TABLE_VERSION_KEY_PREFIX: &[u8] = b"table_version/";
+
+/// Key of the single encoding version of all tables in [`tables::Config`], used before the tables
+/// were versioned individually.
+///
+/// Version `1` corresponds to version `1` of every table in [`TABLE_VERSIONS`].
+pub const LEGACY_DB_VERSION_KEY: &[u8] = b"db_version";
+
+/// Migration related errors.
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    /// A table was written by a newer release.
+    #[error("Table {table} is at version {version}, which is newer than the supported version {supported}.")]
+    UnsupportedVersion {
+        /// The name of the table.
+        table: String,
+        /// The version of the table.
+        version: u64,
+        /// The version of the table written by this release.
+        supported: u64,
+    },
+    /// A table has to be migrated before it can be used.
+    #[error("Table {table} is at version {version} and needs to be migrated to {current}.")]
+    Outdated {
+        /// The name of the table.
+        table: &'static str,
+        /// The version of the table.
+        version: u64,
+        /// The version of the table written by this release.
+        current: u64,
+    },
+    /// An entry could not be decoded with the encoding of the previous version.
+    #[error("Entry of table {table} could not be decoded as version {version}.")]
+    Decode {
+        /// The table of the entry.
+        table: &'static str,
+        /// The version the entry was decoded as.
+        version: u64,
+    },
+    /// A database error.
+    #[error(transparent)]
+    Database(#[from] Error),
+}
+
+/// Returns the encoding version of the table written by this release.
+pub fn current_version(table: &str) -> u64 {
+    TABLE_VERSIONS.iter().find(|(name, _)| *name == table).map_or(0, |(_, version)| *version)
+}
+
+/// Returns the encoding versions stored in the database, by table name.
+///
+/// Tables without a stored version are at version `0`.
+pub fn get_table_versions<DB: Database>(db: &DB) -> Result<BTreeMap<String, u64>, Error> {
+    db.view(|tx| {
+        let mut versions = BTreeMap::new();
+        let mut cursor = tx.cursor::<tables::Config>()?;
+        for entry in cursor.walk(TABLE_VERSION_KEY_PREFIX.to_vec())? {
+            let (key, value) = entry?;
+            let Some(table) = key.strip_prefix(TABLE_VERSION_KEY_PREFIX) else { break };
+            let table = String::from_utf8(table.to_vec()).map_err(|_| Error::DecodeError)?;
+            versions.insert(table, decode_version(&value)?);
+        }
+        Ok(versions)
+    })?
+}
+
+/// Checks that every table can be used with its current encoding.
+///
+/// Empty databases are marked with the current versions and databases with a legacy version are
+/// converted to table versions.
+pub fn check_version<DB: Database>(db: &DB) -> Result<(), MigrationError> {
+    let versions = init_table_versions(db)?;
+    match outdated_tables(&versions)?.first() {
+        Some(&(table, version)) => {
+            Err(MigrationError::Outdated { table, version, current: current_version(table) })
+        }
+        None => Ok(()),
+    }
+}
+
+/// Migrates all outdated tables to their current encodings and returns the migrated tables with
+/// the version they were migrated from.
+///
+/// All tables are migrated in a single transaction, so an interrupted migration leaves the
+/// database untouched.
+pub fn migrate<DB: Database>(db: &DB) -> Result<Vec<(&'static str, u64)>, MigrationError> {
+    let versions = init_table_versions(db)?;
+    let outdated = outdated_tables(&versions)?;
+    if outdated.is_empty() {
+        return Ok(outdated)
+    }
+
+    db.update(|tx| {
+        for &(table, version) in &outdated {
+            let current = current_version(table);
+            for from in version..current {
+                migrate_table(tx, table, from)?;
+            }
+            set_table_version(tx, table, current)?;
+        }
+        Ok::<_, MigrationError>(())
+    })??;
+
+    Ok(outdated)
+}
+
+/// Returns the stored table versions, after marking empty databases with the current versions
+/// and converting a legacy version.
+fn init_table_versions<DB: Database>(db: &DB) -> Result<BTreeMap<String, u64>, MigrationError> {
+    let versions = get_table_versions(db)?;
+    if !versions.is_empty() {
+        return Ok(versions)
+    }
+
+    db.update(|tx| {
+        let legacy = tx
+            .get::<tables::Config>(LEGACY_DB_VERSION_KEY.to_vec())?
+            .map(|version| decode_version(&version))
+            .transpose()?;
+        let versions = match legacy {
+            Some(0) => vec![],
+            // The legacy versions ended at `1`.
+            Some(_) => TABLE_VERSIONS.iter().map(|(table, _)| (*table, 1)).collect(),
+            None if is_empty(tx)? => TABLE_VERSIONS.to_vec(),
+            // Written before the encodings were versioned.
+            None => vec![],
+        };
+
+        for (table, version) in &versions {
+            set_table_version(tx, table, *version)?;
+        }
+        tx.delete::<tables::Config>(LEGACY_DB_VERSION_KEY.to_vec(), None)?;
+        Ok(versions)
+    })??;
+
+    Ok(get_table_versions(db)?)
+}
+
+/// Returns the tables that are older than their current version, with their stored version.
+///
+/// Fails if a table is newer than its current version.
+fn outdated_tables(
+    versions: &BTreeMap<String, u64>,
+) -> Result<Vec<(&'static str, u64)>, MigrationError> {
+    if let Some((table, version)) =
+        versions.iter().find(|(table, version)| **version > current_version(table))
+    {
+        return Err(MigrationError::UnsupportedVersion {
+            table: table.clone(),
+            version: *version,
+            supported: current_version(table),
+        })
+    }
+
+    Ok(TABLE_VERSIONS
+        .iter()
+        .filter_map(|(table, current)| {
+            let version = versions.get(*table).copied().unwrap_or_default();
+            (version < *current).then_some((*table, version))
+        })
+        .collect())
+}
+
+/// Returns true if no headers were written yet.
+fn is_empty<'a, TX: DbTx<'a>>(tx: &TX) -> Result<bool, Error> {
+    Ok(tx.cursor::<tables::CanonicalHeaders>()?.first()?.is_none() &&
+        tx.cursor::<tables::Headers>()?.first()?.is_none())
+}
+
+/// Migrates the table from version `from` to `from + 1`.
+fn migrate_table<'a, TX: DbTxMut<'a> + DbTx<'a>>(
+    tx: &TX,
+    table: &str,
+    from: u64,
+) -> Result<(), MigrationError> {
+    match from {
+        0 if table == tables::Headers::NAME => {
+            reencode::<_, RawHeaders, _>(tx, from, false, legacy::decode_header)
+        }
+        0 if table == tables::BlockOmmers::NAME => {
+            reencode::<_, RawBlockOmmers, _>(tx, from, false, legacy::decode_ommers)
+        }
+        0 if table == tables::Transactions::NAME => {
+            reencode::<_, RawTransactions, _>(tx, from, false, legacy::decode_transaction_signed)
+        }
+        0 if table == tables::NonCanonicalTransactions::NAME => {
+            reencode::<_, RawNonCanonicalTransactions, _>(
+                tx,
+                from,
+                false,
+                legacy::decode_transaction_signed,
+            )
+        }
+        0 if table == tables::AccountChangeSet::NAME => {
+            reencode::<_, RawAccountChangeSet, _>(tx, from, true, legacy::decode_account_before_tx)
+        }
+        _ => unreachable!("no migration of table {table} from version {from}"),
+    }
+}
+
+/// Decodes every value of the table with `decode` and writes it back with its current encoding.
+///
+/// Duplicates of `DUPSORT` tables are deleted before rewriting them, since they're identified by
+/// their value.
+fn reencode<'a, TX, T, V>(
+    tx: &TX,
+    version: u64,
+    is_dupsort: bool,
+    decode: fn(&[u8]) -> Option<V>,
+) -> Result<(), MigrationError>
+where
+    TX: DbTxMut<'a> + DbTx<'a>,
+    T: Table<Value = RawValue>,
+    V: Compact,
+{
+    let mut cursor = tx.cursor_mut::<T>()?;
+    let mut entry = cursor.first()?;
+    while let Some((key, RawValue(value))) = entry {
+        let value = decode(&value).ok_or(MigrationError::Decode { table: T::NAME, version })?;
+        let mut buf = vec![];
+        value.to_compact(&mut buf);
+
+        if is_dupsort {
+            cursor.delete_current()?;
+        }
+        // Positions the cursor at the rewritten entry.
+        cursor.upsert(key, RawValue(buf))?;
+
+        entry = cursor.next()?;
+    }
+    Ok(())
+}
+
+/// Writes the encoding version of the table to the database.
+fn set_table_version<'a, TX: DbTxMut<'a>>(tx: &TX, table: &str, version: u64) -> Result<(), Error> {
+    let key = [TABLE_VERSION_KEY_PREFIX, table.as_bytes()].concat();
+    tx.put::<tables::Config>(key, version.to_be_bytes().to_vec())
+}
+
+/// Decodes a big endian `u64` version.
+fn decode_version(value: &[u8]) -> Result<u64, Error> {
+    value.try_into().map(u64::from_be_bytes).map_err(|_| Error::DecodeError)
+}
+
+/// Value of a table read and written without decoding it.
+#[derive(Debug)]
+struct RawValue(Vec<u8>);
+
+impl Compress for RawValue {
+    type Compressed = Vec<u8>;
+
+    fn compress(self) -> Self::Compressed {
+        self.0
+    }
+}
+
+impl Decompress for RawValue {
+    fn decompress<B: Into<bytes::Bytes>>(value: B) -> Result<Self, Error> {
+        Ok(RawValue(value.into().to_vec()))
+    }
+}
+
+/// Declares a view of a table that reads and writes its values as [`RawValue`].
+macro_rules! raw_table {
+    ($(($name:ident, $table:ident)),+) => {
+        $(
+            #[doc = concat!("[`tables::", stringify!($table), "`] with raw values.")]
+            #[derive(Debug)]
+            struct $name;
+
+            impl Table for $name {
+                const NAME: &'static str = <tables::$table as Table>::NAME;
+                type Key = <tables::$table as Table>::Key;
+                type Value = RawValue;
+            }
+        )+
+    };
+}
+
+raw_table!(
+    (RawHeaders, Headers),
+    (RawBlockOmmers, BlockOmmers),
+    (RawTransactions, Transactions),
+    (RawNonCanonicalTransactions, NonCanonicalTransactions),
+    (RawAccountChangeSet, AccountChangeSet)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{legacy::*, *};
+    use crate::{
+        mdbx::{test_utils::create_test_rw_db, WriteMap},
+        tables::models::{AccountBeforeTx, StoredBlockOmmers},
+    };
+    use reth_primitives::{
+        Account, Address, Bytes, Header, Signature, Transaction, TransactionKind,
+        TransactionSigned, TxEip1559, TxLegacy, H256, U256,
+    };
+
+    fn header(base_fee_per_gas: Option<u64>) -> Header {
+        Header {
+            parent_hash: H256::repeat_byte(1),
+            difficulty: U256::from(0x1234u64),
+            number: 100,
+            gas_limit: 30_000_000,
+            gas_used: 21_000,
+            timestamp: 1_700_000_000,
+            nonce: 42,
+            base_fee_per_gas,
+            extra_data: bytes::Bytes::from_static(b"reth"),
+            ..Default::default()
+        }
+    }
+
+    fn transaction(transaction: Transaction) -> TransactionSigned {
+        TransactionSigned {
+            hash: H256::repeat_byte(2),
+            signature: Signature { r: U256::from(3u64), s: U256::from(4u64), odd_y_parity: true },
+            transaction,
+        }
+    }
+
+    fn legacy_tx(chain_id: Option<u64>) -> Transaction {
+        Transaction::Legacy(TxLegacy {
+            chain_id,
+            nonce: 1,
+            gas_price: 2_000_000_000,
+            gas_limit: 21_000,
+            to: TransactionKind::Call(Address::repeat_byte(5)),
+            value: 1_000_000,
+            input: Bytes::from(vec![1, 2, 3]),
+        })
+    }
+
+    /// Encodes the `Option` with the `u16` length prefix of version `0` and returns its presence.
+    fn encode_prefixed_option_v0<T: Compact>(value: Option<T>, buf: &mut Vec<u8>) -> u8 {
+        let Some(value) = value else { return 0 };
+        let mut inner = vec![];
+        let len = value.to_compact(&mut inner);
+        buf.extend((len as u16).to_be_bytes());
+        buf.extend(inner);
+        1
+    }
+
+    /// Encodes the header as version `0`.
+    fn encode_header_v0(header: Header) -> Vec<u8> {
+        let mut flags = HeaderFlagsV0::default();
+        let mut buf = vec![];
+        header.parent_hash.to_compact(&mut buf);
+        header.ommers_hash.to_compact(&mut buf);
+        header.beneficiary.to_compact(&mut buf);
+        header.state_root.to_compact(&mut buf);
+        header.transactions_root.to_compact(&mut buf);
+        header.receipts_root.to_compact(&mut buf);
+        header.logs_bloom.to_compact(&mut buf);
+        flags.set_difficulty_len(header.difficulty.to_compact(&mut buf) as u8);
+        flags.set_number_len(header.number.to_compact(&mut buf) as u8);
+        flags.set_gas_limit_len(header.gas_limit.to_compact(&mut buf) as u8);
+        flags.set_gas_used_len(header.gas_used.to_compact(&mut buf) as u8);
+        flags.set_timestamp_len(header.timestamp.to_compact(&mut buf) as u8);
+        header.mix_hash.to_compact(&mut buf);
+        flags.set_nonce_len(header.nonce.to_compact(&mut buf) as u8);
+        flags
+            .set_base_fee_per_gas_len(encode_prefixed_option_v0(header.base_fee_per_gas, &mut buf));
+        header.extra_data.to_compact(&mut buf);
+        [flags.into_bytes().as_slice(), &buf].concat()
+    }
+
+    /// Encodes the transaction as version `0`.
+    fn encode_transaction_v0(tx: TransactionSigned) -> Vec<u8> {
+        let mut flags = TransactionSignedFlagsV0::default();
+        let mut buf = vec![];
+        flags.set_hash_len(tx.hash.to_compact(&mut buf) as u8);
+        tx.signature.to_compact(&mut buf);
+        match tx.transaction {
+            Transaction::Legacy(tx) => {
+                let mut flags = TxLegacyFlagsV0::default();
+                let mut inner = vec![];
+                flags.set_chain_id_len(encode_prefixed_option_v0(tx.chain_id, &mut inner));
+                flags.set_nonce_len(tx.nonce.to_compact(&mut inner) as u8);
+                flags.set_gas_price_len(tx.gas_price.to_compact(&mut inner) as u8);
+                flags.set_gas_limit_len(tx.gas_limit.to_compact(&mut inner) as u8);
+                tx.to.to_compact(&mut inner);
+                flags.set_value_len(tx.value.to_compact(&mut inner) as u8);
+                tx.input.to_compact(&mut inner);
+                buf.push(0);
+                buf.extend(flags.into_bytes());
+                buf.extend(inner);
+            }
+            transaction => {
+                transaction.to_compact(&mut buf);
+            }
+        }
+        [flags.into_bytes().as_slice(), &buf].concat()
+    }
+
+    /// Encodes the changeset entry as version `0`.
+    fn encode_account_before_tx_v0(entry: AccountBeforeTx) -> Vec<u8> {
+        let mut buf = entry.address.as_bytes().to_vec();
+        entry.info.to_compact(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn decodes_v0_entries() {
+        for base_fee in [None, Some(0), Some(7), Some(u64::MAX)] {
+            let header = header(base_fee);
+            assert_eq!(decode_header(&encode_header_v0(header.clone())), Some(header));
+        }
+
+        for chain_id in [None, Some(1), Some(u64::MAX)] {
+            let tx = transaction(legacy_tx(chain_id));
+            assert_eq!(decode_transaction_signed(&encode_transaction_v0(tx.clone())), Some(tx));
+        }
+        let tx = transaction(Transaction::Eip1559(TxEip1559 {
+            chain_id: 5,
+            max_fee_per_gas: 3,
+            ..Default::default()
+        }));
+        assert_eq!(decode_transaction_signed(&encode_transaction_v0(tx.clone())), Some(tx));
+
+        for info in
+            [None, Some(Account::default()), Some(Account { nonce: 1, ..Default::default() })]
+        {
+            let entry = AccountBeforeTx { address: Address::repeat_byte(6), info };
+            assert_eq!(
+                decode_account_before_tx(&encode_account_before_tx_v0(entry.clone())),
+                Some(entry)
+            );
+        }
+
+        assert_eq!(decode_header(&[0, 1]), None);
+        assert_eq!(decode_transaction_signed(&[]), None);
+    }
+
+    #[test]
+    fn v1_is_smaller() {
+        let header = header(Some(7));
+        let mut buf = vec![];
+        header.clone().to_compact(&mut buf);
+        assert_eq!(buf.len() + 2, encode_header_v0(header).len());
+
+        let tx = transaction(legacy_tx(Some(1)));
+        let mut buf = vec![];
+        tx.clone().to_compact(&mut buf);
+        // The flags of the hash and the `u16` prefix of the chain id are gone.
+        assert_eq!(buf.len() + 1 + 2, encode_transaction_v0(tx).len());
+
+        let entry =
+            AccountBeforeTx { address: Address::repeat_byte(6), info: Some(Account::default()) };
+        let mut buf = vec![];
+        entry.clone().to_compact(&mut buf);
+        assert_eq!(buf.len() + 2, encode_account_before_tx_v0(entry).len());
+    }
+
+    /// Returns the current table versions as stored by the database.
+    fn current_versions() -> BTreeMap<String, u64> {
+        TABLE_VERSIONS.iter().map(|(table, version)| (table.to_string(), *version)).collect()
+    }
+
+    #[test]
+    fn check_version_of_new_db() {
+        let db = create_test_rw_db::<WriteMap>();
+        assert!(get_table_versions(&*db).unwrap().is_empty());
+        check_version(&*db).unwrap();
+        assert_eq!(get_table_versions(&*db).unwrap(), current_versions());
+        assert_eq!(migrate(&*db).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn converts_legacy_version() {
+        let db = create_test_rw_db::<WriteMap>();
+        db.update(|tx| {
+            tx.put::<tables::Config>(LEGACY_DB_VERSION_KEY.to_vec(), 1u64.to_be_bytes().to_vec())?;
+            tx.put::<tables::CanonicalHeaders>(0, H256::zero())
+        })
+        .unwrap()
+        .unwrap();
+
+        check_version(&*db).unwrap();
+        let versions = get_table_versions(&*db).unwrap();
+        assert_eq!(versions.len(), TABLE_VERSIONS.len());
+        assert!(versions.values().all(|version| *version == 1));
+        let legacy = db.view(|tx| tx.get::<tables::Config>(LEGACY_DB_VERSION_KEY.to_vec()));
+        assert_eq!(legacy.unwrap().unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_newer_tables() {
+        let db = create_test_rw_db::<WriteMap>();
+        check_version(&*db).unwrap();
+        db.update(|tx| {
+            set_table_version(tx, tables::Headers::NAME, current_version(tables::Headers::NAME) + 1)
+        })
+        .unwrap()
+        .unwrap();
+
+        let err = check_version(&*db).unwrap_err();
+        assert!(
+            matches!(err, MigrationError::UnsupportedVersion { table, .. } if table == "Headers")
+        );
+        assert!(matches!(migrate(&*db), Err(MigrationError::UnsupportedVersion { .. })));
+    }
+
+    #[test]
+    fn migrates_single_table() {
+        let db = create_test_rw_db::<WriteMap>();
+        check_version(&*db).unwrap();
+
+        let header = header(Some(7));
+        db.update(|tx| {
+            set_table_version(tx, tables::Headers::NAME, 0)?;
+            tx.put::<RawHeaders>(
+                (1, H256::zero()).into(),
+                RawValue(encode_header_v0(header.clone())),
+            )
+        })
+        .unwrap()
+        .unwrap();
+
+        assert!(matches!(
+            check_version(&*db),
+            Err(MigrationError::Outdated { table: "Headers", version: 0, current: 1 })
+        ));
+        assert_eq!(migrate(&*db).unwrap(), vec![(tables::Headers::NAME, 0)]);
+        check_version(&*db).unwrap();
+        assert_eq!(get_table_versions(&*db).unwrap(), current_versions());
+
+        let tx = db.tx().unwrap();
+        assert_eq!(tx.get::<tables::Headers>((1, H256::zero()).into()).unwrap(), Some(header));
+    }
+
+    #[test]
+    fn all_migrations_exist() {
+        let db = create_test_rw_db::<WriteMap>();
+        let tx = db.tx_mut().unwrap();
+        for (table, current) in TABLE_VERSIONS {
+            for from in 0..current {
+                migrate_table(&tx, table, from).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn migrates_v0() {
+        let db = create_test_rw_db::<WriteMap>();
+
+        let headers = [header(None), header(Some(7))];
+        let ommers = StoredBlockOmmers { ommers: headers.to_vec() };
+        let txs = [transaction(legacy_tx(None)), transaction(legacy_tx(Some(1)))];
+        let changes = [
+            AccountBeforeTx { address: Address::repeat_byte(1), info: None },
+            AccountBeforeTx { address: Address::repeat_byte(2), info: Some(Account::default()) },
+            AccountBeforeTx {
+                address: Address::repeat_byte(3),
+                info: Some(Account { nonce: 1, balance: U256::from(2u64), bytecode_hash: None }),
+            },
+        ];
+
+        db.update(|tx| {
+            for (number, header) in headers.iter().enumerate() {
+                let key = (number as u64, H256::zero()).into();
+                tx.put::<RawHeaders>(key, RawValue(encode_header_v0(header.clone())))?;
+            }
+            let ommers_v0 = {
+                let mut buf = (headers.len() as u16).to_be_bytes().to_vec();
+                for header in &headers {
+                    let header = encode_header_v0(header.clone());
+                    buf.extend((header.len() as u16).to_be_bytes());
+                    buf.extend(header);
+                }
+                buf
+            };
+            tx.put::<RawBlockOmmers>((1, H256::zero()).into(), RawValue(ommers_v0))?;
+            for (number, transaction) in txs.iter().enumerate() {
+                let value = RawValue(encode_transaction_v0(transaction.clone()));
+                tx.put::<RawTransactions>(number as u64, value)?;
+                let value = RawValue(encode_transaction_v0(transaction.clone()));
+                tx.put::<RawNonCanonicalTransactions>(vec![number as u8], value)?;
+            }
+            for change in &changes {
+                let value = RawValue(encode_account_before_tx_v0(change.clone()));
+                tx.put::<RawAccountChangeSet>(1, value)?;
+            }
+            // Marks the database as existing.
+            tx.put::<tables::CanonicalHeaders>(0, H256::zero())
+        })
+        .unwrap()
+        .unwrap();
+
+        assert!(matches!(check_version(&*db), Err(MigrationError::Outdated { version: 0, .. })));
+        let migrated = migrate(&*db).unwrap();
+        assert_eq!(migrated, TABLE_VERSIONS.map(|(table, _)| (table, 0)).to_vec());
+        check_version(&*db).unwrap();
+        assert_eq!(get_table_versions(&*db).unwrap(), current_versions());
+
+        let tx = db.tx().unwrap();
+        for (number, header) in headers.iter().enumerate() {
+            let key = (number as u64, H256::zero()).into();
+            assert_eq!(tx.get::<tables::Headers>(key).unwrap().as_ref(), Some(header));
+        }
+        assert_eq!(tx.get::<tables::BlockOmmers>((1, H256::zero()).into()).unwrap(), Some(ommers));
+        for (number, transaction) in txs.iter().enumerate() {
+            assert_eq!(
+                tx.get::<tables::Transactions>(number as u64).unwrap().as_ref(),
+                Some(transaction)
+            );
+            assert_eq!(
+                tx.get::<tables::NonCanonicalTransactions>(vec![number as u8]).unwrap().as_ref(),
+                Some(transaction)
+            );
+        }
+        let mut cursor = tx.cursor::<tables::AccountChangeSet>().unwrap();
+        let migrated = cursor
+            .walk(1)
+            .unwrap()
+            .map(|entry| entry.map(|(_, change)| change))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(migrated, changes);
+    }
+}
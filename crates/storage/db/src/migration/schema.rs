@@ -0,0 +1,96 @@
+//! Guards the table encodings against changes without a migration.
+//!
+//! The `Compact` derive records the fields and variants of each type in its `COMPACT_SCHEMA`. The
+//! schemas of every type that ends up in a table are compared against the ones recorded for the
+//! [`TABLE_VERSIONS`], so changing a field, its type or the order of the variants fails the tests
+//! until the versions of the tables storing the type are bumped and a migration from the previous
+//! encoding is added to [`migrate`].
+//!
+//! Types with a manual `Compact` implementation (eg. `TxType`, `StorageEntry` or
+//! [`AccountBeforeTx`](crate::tables::models::AccountBeforeTx)) are not covered and need a
+//! migration whenever their implementation changes.
+//!
+//! [`migrate`]: super::migrate
+
+use super::TABLE_VERSIONS;
+use crate::tables::{
+    codecs::CompactU256,
+    models::{StoredBlockBody, StoredBlockOmmers},
+};
+use reth_primitives::{
+    AccessList, AccessListItem, Account, Bytes, Header, Log, Receipt, Signature, Transaction,
+    TransactionKind, TransactionSigned, TxEip1559, TxEip2930, TxLegacy,
+};
+
+/// The table versions the [`SCHEMAS`] were recorded at.
+const SCHEMAS_VERSIONS: [(&str, u64); 5] = [
+    ("Headers", 1),
+    ("BlockOmmers", 1),
+    ("Transactions", 1),
+    ("NonCanonicalTransactions", 1),
+    ("AccountChangeSet", 1),
+];
+
+/// The schemas of the types stored in the tables at [`SCHEMAS_VERSIONS`].
+const SCHEMAS: &[&str] = &[
+    "Header{parent_hash:H256,ommers_hash:H256,beneficiary:H160,state_root:H256,\
+     transactions_root:H256,receipts_root:H256,logs_bloom:Bloom,difficulty:U256,\
+     number:BlockNumber,gas_limit:u64,gas_used:u64,timestamp:u64,mix_hash:H256,nonce:u64,\
+     base_fee_per_gas:Option<u64>,extra_data:bytes::Bytes}",
+    "StoredBlockBody{start_tx_id:TxNumber,tx_count:NumTransactions}",
+    "StoredBlockOmmers{ommers:Vec<Header>}",
+    "Account{nonce:u64,balance:U256,bytecode_hash:Option<H256>}",
+    "CompactU256(U256)",
+    "Bytes(bytes::Bytes)",
+    "Log{address:Address,topics:Vec<H256>,data:bytes::Bytes}",
+    "Receipt{tx_type:TxType,success:bool,cumulative_gas_used:u64,bloom:Bloom,logs:Vec<Log>}",
+    "AccessListItem{address:Address,storage_keys:Vec<H256>}",
+    "AccessList(Vec<AccessListItem>)",
+    "Signature{r:U256,s:U256,odd_y_parity:bool}",
+    "TransactionKind{Create,Call(Address)}",
+    "TxLegacy{chain_id:Option<ChainId>,nonce:u64,gas_price:u128,gas_limit:u64,\
+     to:TransactionKind,value:u128,input:Bytes}",
+    "TxEip2930{chain_id:ChainId,nonce:u64,gas_price:u128,gas_limit:u64,to:TransactionKind,\
+     value:u128,access_list:AccessList,input:Bytes}",
+    "TxEip1559{chain_id:u64,nonce:u64,gas_limit:u64,max_fee_per_gas:u128,\
+     max_priority_fee_per_gas:u128,to:TransactionKind,value:u128,access_list:AccessList,\
+     input:Bytes}",
+    "Transaction{Legacy(TxLegacy),Eip2930(TxEip2930),Eip1559(TxEip1559)}",
+    "TransactionSigned{hash:TxHash,signature:Signature,transaction:Transaction}",
+];
+
+#[test]
+fn schemas_match_table_versions() {
+    let current = [
+        Header::COMPACT_SCHEMA,
+        StoredBlockBody::COMPACT_SCHEMA,
+        StoredBlockOmmers::COMPACT_SCHEMA,
+        Account::COMPACT_SCHEMA,
+        CompactU256::COMPACT_SCHEMA,
+        Bytes::COMPACT_SCHEMA,
+        Log::COMPACT_SCHEMA,
+        Receipt::COMPACT_SCHEMA,
+        AccessListItem::COMPACT_SCHEMA,
+        AccessList::COMPACT_SCHEMA,
+        Signature::COMPACT_SCHEMA,
+        TransactionKind::COMPACT_SCHEMA,
+        TxLegacy::COMPACT_SCHEMA,
+        TxEip2930::COMPACT_SCHEMA,
+        TxEip1559::COMPACT_SCHEMA,
+        Transaction::COMPACT_SCHEMA,
+        TransactionSigned::COMPACT_SCHEMA,
+    ];
+
+    for (current, recorded) in current.iter().zip(SCHEMAS) {
+        assert_eq!(
+            current, recorded,
+            "The encoding of a table value changed. Bump the version of the tables that store it \
+             in `TABLE_VERSIONS`, add a migration to `migrate_table` and record the new schemas."
+        );
+    }
+    assert_eq!(current.len(), SCHEMAS.len());
+    assert_eq!(
+        SCHEMAS_VERSIONS, TABLE_VERSIONS,
+        "`TABLE_VERSIONS` changed, record the schemas of the new versions."
+    );
+}
@@ -33,7 +33,7 @@ pub enum TableType {
 }
 
 /// Default tables that should be present inside database.
-pub const TABLES: [(TableType, &str); 23] = [
+pub const TABLES: [(TableType, &str); 30] = [
     (TableType::Table, CanonicalHeaders::const_name()),
     (TableType::Table, HeaderTD::const_name()),
     (TableType::Table, HeaderNumbers::const_name()),
@@ -47,11 +47,18 @@ pub const TABLES: [(TableType, &str); 23] = [
     (TableType::Table, Logs::const_name()),
     (TableType::Table, PlainAccountState::const_name()),
     (TableType::DupSort, PlainStorageState::const_name()),
+    (TableType::Table, HashedAccount::const_name()),
+    (TableType::DupSort, HashedStorage::const_name()),
+    (TableType::Table, StorageRoots::const_name()),
+    (TableType::Table, AccountsTrie::const_name()),
+    (TableType::Table, StoragesTrie::const_name()),
     (TableType::Table, Bytecodes::const_name()),
     (TableType::Table, BlockTransitionIndex::const_name()),
     (TableType::Table, TxTransitionIndex::const_name()),
     (TableType::Table, AccountHistory::const_name()),
     (TableType::Table, StorageHistory::const_name()),
+    (TableType::Table, LogAddressIndex::const_name()),
+    (TableType::Table, LogTopicIndex::const_name()),
     (TableType::DupSort, AccountChangeSet::const_name()),
     (TableType::DupSort, StorageChangeSet::const_name()),
     (TableType::Table, TxSenders::const_name()),
@@ -189,6 +196,47 @@ dupsort!(
     ( PlainStorageState ) Address | [H256] StorageEntry
 );
 
+table!(
+    /// Stores the current state of an [`Account`] indexed by `keccak256(Address)`.
+    ///
+    /// This is the account state in the order of the state trie.
+    ( HashedAccount ) H256 | Account
+);
+
+dupsort!(
+    /// Stores the current value of a storage key indexed by `keccak256(Address)`. The
+    /// [`StorageEntry::key`] is `keccak256(key)` of the plain storage key.
+    ///
+    /// This is the storage state in the order of the storage tries.
+    ( HashedStorage ) H256 | [H256] StorageEntry
+);
+
+table!(
+    /// Stores the root of the storage trie of each account in [`HashedAccount`] with storage,
+    /// indexed by `keccak256(Address)`. Accounts without an entry have an empty storage trie.
+    ///
+    /// The roots are kept between runs of the state root computation, only the roots of the
+    /// accounts whose storage changed are computed again.
+    ( StorageRoots ) H256 | H256
+);
+
+table!(
+    /// Stores the encodings of the branches and extensions of the state trie that are its root or
+    /// are referenced by their hash, indexed by the nibbles of their position in the trie.
+    ///
+    /// Leaves are not stored, they are recovered from [`HashedAccount`] and [`StorageRoots`]. The
+    /// nodes are kept to update the trie along the paths of the changed accounts only.
+    ( AccountsTrie ) TrieNodePath | TrieNodeEncoding
+);
+
+table!(
+    /// Stores the branches and extensions of the storage tries like [`AccountsTrie`], indexed by
+    /// `keccak256(Address)` followed by the nibbles of their position in the storage trie.
+    ///
+    /// Leaves are not stored, they are recovered from [`HashedStorage`].
+    ( StoragesTrie ) StorageTrieNodePath | TrieNodeEncoding
+);
+
 table!(
     /// Stores the transaction numbers that changed each account.
     ///
@@ -236,8 +284,24 @@ table!(
 );
 
 table!(
-    /// Stores pointers to transactions that changed each storage key.
-    ( StorageHistory ) AddressStorageKey | TransitionList
+    /// Stores the transitions that changed each storage slot, sharded like [`AccountHistory`].
+    ( StorageHistory ) ShardedKey<AddressStorageKey> | TransitionList
+);
+
+table!(
+    /// Stores the numbers of the blocks that emitted logs of each contract address.
+    ///
+    /// The blocks are sharded by block ranges of a fixed size, the
+    /// [`ShardedKey::highest_tx_number`] of a key is the last block of its shard.
+    ( LogAddressIndex ) ShardedKey<Address> | BlockList
+);
+
+table!(
+    /// Stores the numbers of the blocks that emitted logs with each topic, regardless of the
+    /// position of the topic within the log.
+    ///
+    /// The blocks are sharded like the blocks of [`LogAddressIndex`].
+    ( LogTopicIndex ) ShardedKey<H256> | BlockList
 );
 
 dupsort!(
@@ -271,13 +335,46 @@ table!(
     ( SyncStage ) StageId | BlockNumber
 );
 
+/// Key of the last block whose changesets and history indices were pruned in [`Config`], as big
+/// endian `u64`. The state of earlier blocks can't be reconstructed anymore.
+pub const HISTORY_PRUNED_TO_KEY: &[u8] = b"history_pruned_to";
+
+/// Key of the last plain account hashed by the unfinished clean run of the account hashing stage
+/// in [`Config`], as the address. Empty if the run starts over from the first account.
+pub const ACCOUNT_HASHING_CHECKPOINT_KEY: &[u8] = b"account_hashing_checkpoint";
+
+/// Key of the last plain storage slot hashed by the unfinished clean run of the storage hashing
+/// stage in [`Config`], as the address followed by the key of the slot. Empty if the run starts
+/// over from the first slot.
+pub const STORAGE_HASHING_CHECKPOINT_KEY: &[u8] = b"storage_hashing_checkpoint";
+
+/// Key of the number of blocks whose headers, bodies and receipts were moved to the freezer in
+/// [`Config`], as big endian `u64`.
+pub const FROZEN_BLOCKS_KEY: &[u8] = b"frozen_blocks";
+
+/// Key of the finalized block of the latest forkchoice update in [`Config`], as big endian `u64`.
+pub const FINALIZED_BLOCK_KEY: &[u8] = b"finalized_block";
+
+/// Key of the safe block of the latest forkchoice update in [`Config`], as big endian `u64`.
+pub const SAFE_BLOCK_KEY: &[u8] = b"safe_block";
+
 ///
 /// Alias Types
 
 /// List with transaction numbers.
 pub type TransitionList = IntegerList;
+/// List with block numbers.
+pub type BlockList = IntegerList;
 /// Encoded stage id.
 pub type StageId = Vec<u8>;
+/// The nibbles of the position of a trie node, one nibble per byte.
+pub type TrieNodePath = Vec<u8>;
+/// The 32 bytes of `keccak256(Address)` followed by a [`TrieNodePath`] in the storage trie.
+pub type StorageTrieNodePath = Vec<u8>;
+/// The RLP encoding of a trie node.
+pub type TrieNodeEncoding = Vec<u8>;
+/// The 20 bytes of an address followed by the 32 bytes of a storage key.
+pub type AddressStorageKey = Vec<u8>;
 
 //
 // TODO: Temporary types, until they're properly defined alongside with the Encode and Decode Trait
@@ -290,6 +387,4 @@ pub type ConfigValue = Vec<u8>;
 /// Temporary placeholder type for DB.
 pub type BlockNumHashTxNumber = Vec<u8>;
 /// Temporary placeholder type for DB.
-pub type AddressStorageKey = Vec<u8>;
-/// Temporary placeholder type for DB.
 pub type Bytecode = Vec<u8>;
@@ -7,7 +7,7 @@ use crate::{
 };
 use bytes::Bytes;
 use reth_codecs::Compact;
-use reth_primitives::{Account, Address, TransitionId};
+use reth_primitives::{Account, Address, TransitionId, H256};
 use serde::{Deserialize, Serialize};
 
 /// Account as it is saved inside [`AccountChangeSet`]. [`Address`] is the subkey.
@@ -26,7 +26,14 @@ impl Compact for AccountBeforeTx {
     fn to_compact(self, buf: &mut impl bytes::BufMut) -> usize {
         // for now put full bytes and later compress it.
         buf.put_slice(&self.address.to_fixed_bytes()[..]);
-        self.info.to_compact(buf) + 32
+
+        // The account is the last element, so its presence is given by the remaining length and
+        // it doesn't need a length prefix.
+        let mut info_len = 0;
+        if let Some(info) = self.info {
+            info_len = info.to_compact(buf);
+        }
+        info_len + 20
     }
 
     fn from_compact(buf: &[u8], len: usize) -> (Self, &[u8])
@@ -34,7 +41,7 @@ impl Compact for AccountBeforeTx {
         Self: Sized,
     {
         let address = Address::from_slice(&buf[..20]);
-        let (info, out) = <Option<Account>>::from_compact(&buf[20..], len - 20);
+        let (info, out) = <Option<Account>>::specialized_from_compact(&buf[20..], len - 20);
         (Self { address, info }, out)
     }
 }
@@ -97,6 +104,12 @@ impl Decode for TransitionIdAddress {
 
 impl_fixed_arbitrary!(TransitionIdAddress, 28);
 
+/// Returns the [`AddressStorageKey`](crate::tables::AddressStorageKey) of a storage slot in
+/// [`StorageHistory`](crate::tables::StorageHistory).
+pub fn storage_history_key(address: Address, key: H256) -> Vec<u8> {
+    [address.as_bytes(), key.as_bytes()].concat()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -120,6 +133,30 @@ mod test {
         assert_eq!(decoded, key);
     }
 
+    #[test]
+    fn test_account_before_tx() {
+        let address = Address::from_str("ba5e000000000000000000000000000000000000").unwrap();
+
+        // A missing account is only the address, without any length or presence byte.
+        let mut buf = vec![];
+        let len = AccountBeforeTx { address, info: None }.to_compact(&mut buf);
+        assert_eq!((len, buf.len()), (20, 20));
+        assert_eq!(
+            AccountBeforeTx::from_compact(&buf, buf.len()).0,
+            AccountBeforeTx { address, info: None }
+        );
+
+        for info in
+            [Account::default(), Account { nonce: 1, balance: 2.into(), bytecode_hash: None }]
+        {
+            let before = AccountBeforeTx { address, info: Some(info) };
+            let mut buf = vec![];
+            let len = before.clone().to_compact(&mut buf);
+            assert_eq!(len, buf.len());
+            assert_eq!(AccountBeforeTx::from_compact(&buf, buf.len()).0, before);
+        }
+    }
+
     #[test]
     fn test_tx_number_address_rand() {
         let mut bytes = [0u8; 28];
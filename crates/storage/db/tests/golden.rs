@@ -0,0 +1,220 @@
+//! Golden encoding vectors for the values of the tables.
+//!
+//! `tests/fixtures/golden/tables.json` pins the compressed encoding of a value of every table
+//! whose values are encoded with `Compact`, which is the on-disk format of the database. The
+//! harness asserts that compressing the value is byte-exact and that decompressing the pinned
+//! encoding returns the value, so a change of the format is noticed before it reaches a database.
+//!
+//! Running the tests with `RETH_UPDATE_GOLDEN=1` rewrites the fixture from the current
+//! implementation, which should only be done alongside a migration of the affected tables.
+
+use bytes::Bytes;
+use reth_db::{
+    table::{Compress, Decompress, Table},
+    tables::{
+        self,
+        codecs::CompactU256,
+        models::{AccountBeforeTx, StoredBlockBody, StoredBlockOmmers},
+    },
+};
+use reth_primitives::{
+    AccessList, AccessListItem, Account, Bloom, Header, Log, Receipt, Signature, StorageEntry,
+    Transaction, TransactionKind, TransactionSigned, TxEip1559, TxLegacy, TxType, H160, H256, U256,
+};
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt::Debug, fs, path::PathBuf};
+
+/// Environment variable that enables rewriting the fixture.
+const UPDATE_ENV: &str = "RETH_UPDATE_GOLDEN";
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden/tables.json")
+}
+
+fn decode_hex(value: &str) -> Vec<u8> {
+    hex::decode(value.trim_start_matches("0x")).expect("valid hex")
+}
+
+/// The pinned encodings of the fixture by table name.
+struct Golden {
+    vectors: BTreeMap<String, String>,
+    update: bool,
+}
+
+impl Golden {
+    fn load() -> Self {
+        let vectors: Vec<Value> =
+            serde_json::from_str(&fs::read_to_string(fixture_path()).expect("fixture exists"))
+                .expect("valid fixture");
+        let vectors = vectors
+            .into_iter()
+            .map(|vector| {
+                let table = vector["table"].as_str().expect("table field").to_string();
+                let compressed = vector["compressed"].as_str().expect("compressed field");
+                (table, compressed.to_string())
+            })
+            .collect();
+        Self { vectors, update: std::env::var(UPDATE_ENV).is_ok() }
+    }
+
+    /// Checks the value against the pinned encoding of the table.
+    fn check<T: Table>(&mut self, value: T::Value)
+    where
+        T::Value: Clone + PartialEq + Debug,
+    {
+        let compressed = value.clone().compress();
+        let compressed = compressed.as_ref();
+
+        if self.update {
+            self.vectors.insert(T::NAME.to_string(), format!("0x{}", hex::encode(compressed)));
+            return
+        }
+
+        let expected =
+            self.vectors.remove(T::NAME).unwrap_or_else(|| panic!("{}: no golden vector", T::NAME));
+        let expected = decode_hex(&expected);
+        assert_eq!(
+            hex::encode(compressed),
+            hex::encode(&expected),
+            "{}: encoding mismatch",
+            T::NAME
+        );
+        assert_eq!(
+            T::Value::decompress(expected).unwrap_or_else(|err| panic!("{}: {err:?}", T::NAME)),
+            value,
+            "{}: decoding mismatch",
+            T::NAME
+        );
+    }
+
+    fn finish(self) {
+        if self.update {
+            let vectors = self
+                .vectors
+                .into_iter()
+                .map(|(table, compressed)| {
+                    serde_json::json!({ "table": table, "compressed": compressed })
+                })
+                .collect::<Vec<_>>();
+            let mut out = serde_json::to_string_pretty(&vectors).expect("serializable");
+            out.push('\n');
+            fs::write(fixture_path(), out).expect("writable fixture");
+        } else {
+            assert!(
+                self.vectors.is_empty(),
+                "golden vectors of unchecked tables: {:?}",
+                self.vectors.keys().collect::<Vec<_>>()
+            );
+        }
+    }
+}
+
+fn header() -> Header {
+    Header {
+        parent_hash: H256::repeat_byte(0x01),
+        ommers_hash: H256::repeat_byte(0x02),
+        beneficiary: H160::repeat_byte(0x03),
+        state_root: H256::repeat_byte(0x04),
+        transactions_root: H256::repeat_byte(0x05),
+        receipts_root: H256::repeat_byte(0x06),
+        logs_bloom: Bloom::repeat_byte(0x07),
+        difficulty: U256::from(0x020000),
+        number: 0x0100,
+        gas_limit: 30_000_000,
+        gas_used: 0,
+        timestamp: 1_672_531_200,
+        mix_hash: H256::repeat_byte(0x08),
+        nonce: 0x42,
+        base_fee_per_gas: Some(7),
+        extra_data: Bytes::from_static(b"reth"),
+    }
+}
+
+#[test]
+fn golden_table_values() {
+    let mut golden = Golden::load();
+
+    golden.check::<tables::Headers>(header());
+    golden.check::<tables::HeaderTD>(CompactU256(
+        U256::from_dec_str("58750003716598352816469").unwrap(),
+    ));
+    golden.check::<tables::BlockBodies>(StoredBlockBody { start_tx_id: 0x0102, tx_count: 3 });
+    golden.check::<tables::BlockOmmers>(StoredBlockOmmers { ommers: vec![header()] });
+
+    golden.check::<tables::Transactions>(TransactionSigned {
+        hash: H256::repeat_byte(0x0a),
+        signature: Signature { r: U256::from(0x0b0b), s: U256::from(0x0c), odd_y_parity: true },
+        transaction: Transaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: 1,
+            to: TransactionKind::Call(H160::repeat_byte(0x0d)),
+            value: 1_000_000_000_000_000_000,
+            access_list: AccessList(vec![AccessListItem {
+                address: H160::repeat_byte(0x0e),
+                storage_keys: vec![H256::repeat_byte(0x0f)],
+            }]),
+            input: Bytes::from_static(&[0xca, 0xfe]).into(),
+        }),
+    });
+    golden.check::<tables::NonCanonicalTransactions>(TransactionSigned {
+        hash: H256::repeat_byte(0x09),
+        signature: Signature { r: U256::from(1), s: U256::from(2), odd_y_parity: false },
+        transaction: Transaction::Legacy(TxLegacy {
+            chain_id: Some(1),
+            nonce: 9,
+            gas_price: 20_000_000_000,
+            gas_limit: 21_000,
+            to: TransactionKind::Create,
+            value: 0,
+            input: Bytes::from_static(&[0x60, 0x80]).into(),
+        }),
+    });
+
+    golden.check::<tables::Receipts>(Receipt {
+        tx_type: TxType::EIP1559,
+        success: true,
+        cumulative_gas_used: 21_000,
+        bloom: Bloom::zero(),
+        logs: vec![Log {
+            address: H160::repeat_byte(0x11),
+            topics: vec![H256::repeat_byte(0x12)],
+            data: Bytes::from_static(&[0x13, 0x14]),
+        }],
+    });
+    golden.check::<tables::Logs>(Receipt {
+        tx_type: TxType::Legacy,
+        success: false,
+        cumulative_gas_used: 0,
+        bloom: Bloom::zero(),
+        logs: vec![],
+    });
+
+    golden.check::<tables::PlainAccountState>(Account {
+        nonce: 1,
+        balance: U256::from(1_000_000_000_000_000_000u64),
+        bytecode_hash: Some(H256::repeat_byte(0x21)),
+    });
+    golden.check::<tables::HashedAccount>(Account::default());
+    golden.check::<tables::AccountChangeSet>(AccountBeforeTx {
+        address: H160::repeat_byte(0x41),
+        info: Some(Account { nonce: 2, ..Default::default() }),
+    });
+
+    golden.check::<tables::PlainStorageState>(StorageEntry {
+        key: H256::repeat_byte(0x31),
+        value: U256::from(0x0100),
+    });
+    golden.check::<tables::StorageChangeSet>(StorageEntry {
+        key: H256::repeat_byte(0x32),
+        value: U256::zero(),
+    });
+    golden.check::<tables::HashedStorage>(StorageEntry {
+        key: H256::repeat_byte(0x33),
+        value: U256::MAX,
+    });
+
+    golden.finish();
+}
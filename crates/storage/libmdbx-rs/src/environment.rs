@@ -2,13 +2,14 @@ use crate::{
     database::Database,
     error::{mdbx_result, Error, Result},
     flags::EnvironmentFlags,
-    transaction::{RO, RW},
-    Mode, Transaction, TransactionKind,
+    transaction::{txn_execute, RO, RW},
+    Mode, ObjectLength, Transaction, TransactionKind,
 };
 use byteorder::{ByteOrder, NativeEndian};
 use libc::c_uint;
 use mem::size_of;
 use std::{
+    collections::HashSet,
     ffi::CString,
     fmt,
     fmt::Debug,
@@ -214,6 +215,166 @@ where
 
         Ok(freelist)
     }
+
+    /// Copies the environment to the file at `dest`, which must not exist yet.
+    ///
+    /// With `compact`, only the pages that are reachable from the databases are copied and they
+    /// are renumbered sequentially, so the copy has no free pages and does not depend on the
+    /// freelist of this environment.
+    pub fn copy(&self, dest: &Path, compact: bool) -> Result<()> {
+        let dest = CString::new(dest.as_os_str().as_bytes()).map_err(|_| Error::Invalid)?;
+        let flags = if compact { ffi::MDBX_CP_COMPACT } else { ffi::MDBX_CP_DEFAULTS };
+        mdbx_result(unsafe { ffi::mdbx_env_copy(self.env(), dest.as_ptr(), flags) })?;
+        Ok(())
+    }
+
+    /// Checks the structure of the environment.
+    ///
+    /// Every named database is read completely and the records of the garbage collector are
+    /// validated. Then every allocated page has to be either a meta page, a page of a database or
+    /// the garbage collector, or listed as free by the garbage collector.
+    ///
+    /// It assumes that the main database only holds the named databases.
+    pub fn check(&self) -> Result<CheckReport> {
+        let txn = self.begin_ro_txn()?;
+        let (info, stat) = txn_execute(&txn.txn_mutex(), |txn| unsafe {
+            let mut info = Info(mem::zeroed());
+            let mut stat = Stat::new();
+            mdbx_result(ffi::mdbx_env_info_ex(self.env(), txn, &mut info.0, size_of::<Info>()))?;
+            mdbx_result(ffi::mdbx_env_stat_ex(
+                self.env(),
+                txn,
+                stat.mdb_stat(),
+                size_of::<Stat>(),
+            ))?;
+            Ok::<_, Error>((info, stat))
+        })?;
+        let gc = Database::freelist_db();
+        let gc_stat = txn.db_stat(&gc)?;
+        let mut report = CheckReport {
+            page_size: stat.page_size(),
+            allocated_pages: info.last_pgno() + 1,
+            tree_pages: stat.branch_pages() + stat.leaf_pages() + stat.overflow_pages(),
+            gc_pages: gc_stat.branch_pages() + gc_stat.leaf_pages() + gc_stat.overflow_pages(),
+            ..Default::default()
+        };
+
+        // The names of the named databases are the keys of the main database.
+        let mut names = Vec::new();
+        for item in txn.cursor(&txn.open_db(None)?)?.iter_start::<Vec<u8>, ()>() {
+            match item {
+                Ok((name, _)) => names.push(String::from_utf8_lossy(&name).into_owned()),
+                Err(err) => {
+                    report.damaged.push((String::new(), err));
+                    return Ok(report)
+                }
+            }
+        }
+        for name in names {
+            if let Err(err) = check_db(&txn, &name) {
+                report.damaged.push((name, err));
+            }
+        }
+
+        // Every record of the garbage collector is a list of page numbers, prefixed by its length.
+        let mut free = HashSet::new();
+        for item in txn.cursor(&gc)?.iter_start::<Vec<u8>, Vec<u8>>() {
+            let (key, value) = match item {
+                Ok(item) => item,
+                Err(err) => {
+                    report.freelist_errors.push(format!("unreadable record: {err}"));
+                    break
+                }
+            };
+            let txnid =
+                if key.len() == size_of::<u64>() { NativeEndian::read_u64(&key) } else { 0 };
+            let pages = value
+                .chunks_exact(size_of::<u32>())
+                .map(NativeEndian::read_u32)
+                .collect::<Vec<_>>();
+            if value.len() % size_of::<u32>() != 0 ||
+                pages.first().map_or(true, |len| *len as usize != pages.len() - 1)
+            {
+                report.freelist_errors.push(format!("record of txn {txnid} has an invalid length"));
+                continue
+            }
+            for page in pages.into_iter().skip(1) {
+                if (page as usize) < NUM_METAS || page as usize >= report.allocated_pages {
+                    report
+                        .freelist_errors
+                        .push(format!("record of txn {txnid} lists page {page} out of range"));
+                } else if !free.insert(page) {
+                    report
+                        .freelist_errors
+                        .push(format!("record of txn {txnid} lists page {page} again"));
+                }
+            }
+        }
+        report.free_pages = free.len();
+        Ok(report)
+    }
+}
+
+/// Reads all entries of the named database and compares them with its statistics.
+fn check_db<E: EnvironmentKind>(txn: &Transaction<'_, RO, E>, name: &str) -> Result<()> {
+    let db = txn.open_db(Some(name))?;
+    let mut entries = 0;
+    for item in txn.cursor(&db)?.iter_start::<ObjectLength, ObjectLength>() {
+        item?;
+        entries += 1;
+    }
+    if entries != txn.db_stat(&db)?.entries() {
+        return Err(Error::Corrupted)
+    }
+    Ok(())
+}
+
+/// The number of meta pages at the beginning of the file.
+const NUM_METAS: usize = 3;
+
+/// The result of [Environment::check].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// Size of a page.
+    pub page_size: u32,
+    /// The number of pages of the file that were allocated, including the meta pages.
+    pub allocated_pages: usize,
+    /// The number of pages of the main database and all named databases.
+    pub tree_pages: usize,
+    /// The number of pages of the garbage collector.
+    pub gc_pages: usize,
+    /// The number of distinct pages the garbage collector lists as free.
+    pub free_pages: usize,
+    /// Invalid records of the garbage collector.
+    pub freelist_errors: Vec<String>,
+    /// The databases that could not be read completely or whose entries do not match their
+    /// statistics. An empty name refers to the main database.
+    pub damaged: Vec<(String, Error)>,
+}
+
+impl CheckReport {
+    /// Returns the number of allocated pages that are neither used nor free, which are lost
+    /// until the file is compacted.
+    ///
+    /// A negative number means that pages are used and free at the same time, which corrupts the
+    /// databases as soon as the pages are reused.
+    pub fn orphan_pages(&self) -> isize {
+        let accounted = NUM_METAS + self.tree_pages + self.gc_pages + self.free_pages;
+        self.allocated_pages as isize - accounted as isize
+    }
+
+    /// Returns true if no problems were found.
+    pub fn is_healthy(&self) -> bool {
+        self.freelist_errors.is_empty() && self.damaged.is_empty() && self.orphan_pages() == 0
+    }
+
+    /// Returns true if a compacting [copy](Environment::copy) rebuilds a healthy environment.
+    ///
+    /// The copy only reads the databases, so only problems of the freelist and the page
+    /// accounting can be repaired.
+    pub fn is_repairable(&self) -> bool {
+        self.damaged.is_empty()
+    }
 }
 
 /// Environment statistics.
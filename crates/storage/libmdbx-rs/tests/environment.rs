@@ -154,3 +154,37 @@ fn test_freelist() {
     freelist = env.freelist().unwrap();
     assert!(freelist > 0);
 }
+
+#[test]
+fn test_check_and_compacting_copy() {
+    let dir = tempdir().unwrap();
+    let env = Environment::new().set_max_dbs(2).open(dir.path()).unwrap();
+
+    let tx = env.begin_rw_txn().expect("begin_rw_txn");
+    for name in ["kept", "cleared"] {
+        let db = tx.create_db(Some(name), DatabaseFlags::empty()).unwrap();
+        for i in 0..1024u64 {
+            tx.put(&db, i.to_be_bytes(), [0u8; 64], WriteFlags::default()).expect("tx.put");
+        }
+    }
+    tx.commit().expect("tx.commit");
+    let tx = env.begin_rw_txn().expect("begin_rw_txn");
+    tx.clear_db(&tx.open_db(Some("cleared")).unwrap()).expect("clear");
+    tx.commit().expect("tx.commit");
+
+    let report = env.check().unwrap();
+    assert!(report.is_healthy(), "{report:?}");
+    assert!(report.free_pages > 0);
+
+    let copy = tempdir().unwrap();
+    env.copy(&copy.path().join("mdbx.dat"), true).unwrap();
+    let copied = Environment::new().set_max_dbs(2).open(copy.path()).unwrap();
+    let copied_report = copied.check().unwrap();
+    assert!(copied_report.is_healthy(), "{copied_report:?}");
+    assert_eq!(copied_report.free_pages, 0);
+    assert!(copied_report.allocated_pages < report.allocated_pages);
+
+    let tx = copied.begin_ro_txn().unwrap();
+    assert_eq!(tx.db_stat(&tx.open_db(Some("kept")).unwrap()).unwrap().entries(), 1024);
+    assert_eq!(tx.db_stat(&tx.open_db(Some("cleared")).unwrap()).unwrap().entries(), 0);
+}
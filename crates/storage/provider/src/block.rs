@@ -7,8 +7,9 @@ use reth_db::{
 use reth_interfaces::{provider::Error as ProviderError, Result};
 use reth_primitives::{
     rpc::{BlockId, BlockNumber},
-    Block, BlockHash, BlockHashOrNumber, Header, SealedBlock, H256, U256,
+    Address, Block, BlockHash, BlockHashOrNumber, Header, Receipt, SealedBlock, TxHash, H256, U256,
 };
+use std::ops::RangeInclusive;
 
 /// Client trait for fetching `Header` related data.
 #[auto_impl(&)]
@@ -76,23 +77,94 @@ pub trait BlockProvider: Send + Sync {
         }
     }
 
-    /// Get the number of the block by matching the given id.
+    /// Get the number of the canonical block by matching the given id.
+    ///
+    /// Blocks of side chains are not indexed by number, so a hash that is not part of the
+    /// canonical chain has no number either.
     fn block_number_for_id(
         &self,
         block_id: BlockId,
     ) -> Result<Option<reth_primitives::BlockNumber>> {
         match block_id {
-            BlockId::Hash(hash) => self.block_number(hash),
+            BlockId::Hash(hash) => match self.block_number(hash)? {
+                Some(number) if self.canonical_hash(number)? == Some(hash) => Ok(Some(number)),
+                _ => Ok(None),
+            },
             BlockId::Number(num) => self.convert_block_number(num),
         }
     }
 
-    /// Gets the `Block` for the given hash. Returns `None` if no block with this hash exists.
+    /// Gets the number of the block with the given hash from the
+    /// [`HeaderNumbers`][tables::HeaderNumbers] index. Returns `None` if no block with this hash
+    /// exists.
     fn block_number(&self, hash: H256) -> Result<Option<reth_primitives::BlockNumber>>;
 
     /// Get the hash of the block with the given number. Returns `None` if no block with this number
     /// exists.
     fn block_hash(&self, number: U256) -> Result<Option<H256>>;
+
+    /// Gets the hash of the canonical block with the given number from the
+    /// [`CanonicalHeaders`][tables::CanonicalHeaders] index. Returns `None` if the canonical chain
+    /// is shorter.
+    fn canonical_hash(&self, number: reth_primitives::BlockNumber) -> Result<Option<H256>> {
+        self.block_hash(number.into())
+    }
+
+    /// Returns whether the block with the given hash is part of the canonical chain.
+    ///
+    /// Both lookups go through the indices, so no headers are read.
+    fn is_canonical(&self, hash: H256) -> Result<bool> {
+        match self.block_number(hash)? {
+            Some(number) => Ok(self.canonical_hash(number)? == Some(hash)),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Api trait for fetching `Receipt` related data.
+pub trait ReceiptProvider: Send + Sync {
+    /// Returns the receipts of all transactions in the block, in the order of the transactions.
+    /// Returns `None` if the block is not found.
+    fn receipts_by_block(&self, id: BlockId) -> Result<Option<Vec<Receipt>>>;
+}
+
+/// Api trait for locating transactions in the canonical chain.
+pub trait TransactionsProvider: Send + Sync {
+    /// Returns the number of the canonical block that contains the transaction with the given
+    /// hash and the index of the transaction in the block.
+    fn transaction_block(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<(reth_primitives::BlockNumber, usize)>>;
+}
+
+/// Api trait for looking up the blocks that may contain the logs of a log filter in the
+/// [`LogAddressIndex`][tables::LogAddressIndex] and [`LogTopicIndex`][tables::LogTopicIndex].
+pub trait LogIndexProvider: Send + Sync {
+    /// Returns the ascending numbers of the blocks in the range that emitted a log of one of the
+    /// addresses and logs with one of the topics of every topic position. Empty lists match any
+    /// address or topic.
+    ///
+    /// The index does not record the position of a topic within a log, so the logs of the
+    /// returned blocks still have to be matched against the filter. Returns `None` if nothing is
+    /// filtered or the range is not indexed yet, in which case all blocks have to be searched.
+    fn log_filter_blocks(
+        &self,
+        range: RangeInclusive<reth_primitives::BlockNumber>,
+        addresses: &[Address],
+        topics: &[Vec<H256>],
+    ) -> Result<Option<Vec<reth_primitives::BlockNumber>>>;
+}
+
+/// Api trait for reading the checkpoints of the pipeline stages in
+/// [`SyncStage`][tables::SyncStage].
+pub trait StageCheckpointProvider: Send + Sync {
+    /// Returns the number of the last block the stage with the given id has committed, or `None`
+    /// if the stage never ran.
+    ///
+    /// The stages commit their progress in batches while the pipeline runs, so the checkpoint
+    /// trails the block the stage is processing.
+    fn stage_checkpoint(&self, stage: &str) -> Result<Option<reth_primitives::BlockNumber>>;
 }
 
 /// Current status of the blockchain's head.
@@ -0,0 +1,60 @@
+//! Notifications about changes of the canonical chain.
+
+use reth_primitives::{Account, Address, Block, BlockHash, BlockNumber, Receipt, StorageEntry};
+use std::{ops::RangeInclusive, sync::Arc};
+use tokio::sync::broadcast;
+
+/// The default capacity of a [ChainNotifications] channel.
+pub const DEFAULT_CHAIN_NOTIFICATIONS_CAPACITY: usize = 256;
+
+/// The sending half of the channel of [ChainNotification]s.
+///
+/// Every component that needs to follow the canonical chain, e.g. RPC subscriptions, subscribes
+/// its own receiver. Receivers that fall behind miss the oldest notifications.
+pub type ChainNotifications = broadcast::Sender<ChainNotification>;
+
+/// A canonical block along with the receipts of its transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalBlock {
+    /// The hash of the block.
+    pub hash: BlockHash,
+    /// The block.
+    pub block: Block,
+    /// The receipts of the transactions of the block, empty if they were not written.
+    pub receipts: Vec<Receipt>,
+    /// The changes of the state by the block, empty if the block was not executed.
+    pub state: StateChanges,
+}
+
+/// The changes of the state by a block, recorded as the values before the block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateChanges {
+    /// The changed accounts and their previous state, `None` if they didn't exist.
+    pub accounts: Vec<(Address, Option<Account>)>,
+    /// The changed storage slots and their previous values, zero if they didn't exist.
+    pub storage: Vec<(Address, StorageEntry)>,
+}
+
+/// A change of the canonical chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainNotification {
+    /// The blocks were appended to the canonical chain.
+    ///
+    /// The blocks can be read from the database, unless they were reverted in the meantime.
+    Committed {
+        /// The numbers of the new canonical blocks.
+        range: RangeInclusive<BlockNumber>,
+    },
+    /// The blocks were removed from the canonical chain, e.g. because of a reorg.
+    ///
+    /// The blocks are no longer in the database, so the notification carries them.
+    Reverted {
+        /// The reverted blocks in ascending order.
+        blocks: Arc<Vec<CanonicalBlock>>,
+    },
+}
+
+/// Creates a new channel of [ChainNotification]s with the given capacity.
+pub fn chain_notifications(capacity: usize) -> ChainNotifications {
+    broadcast::channel(capacity).0
+}
@@ -2,36 +2,88 @@
 //! to provide higher level abstraction over database tables.
 
 mod block;
+mod proof;
 mod storage;
-use std::sync::Arc;
+use std::{marker::PhantomData, sync::Arc};
 
 pub use storage::{
     StateProviderImplHistory, StateProviderImplLatest, StateProviderImplRefHistory,
     StateProviderImplRefLatest,
 };
 
-use reth_db::database::Database;
+use crate::freezer::Freezer;
+use reth_db::{database::Database, transaction::DbTx};
 
 /// A provider that fetches data from a database.
+///
+/// Blocks that were moved out of the database are read from the [`Freezer`], if one is set.
 // TODO: ProviderImpl is a bad name
 pub struct ProviderImpl<DB: Database> {
     /// Database
     db: Arc<DB>,
+    /// Cold storage of old blocks.
+    freezer: Option<Arc<Freezer>>,
 }
 
 impl<DB: Database> ProviderImpl<DB> {
     /// create new database provider
     pub fn new(db: Arc<DB>) -> Self {
-        Self { db }
+        Self { db, freezer: None }
+    }
+
+    /// Reads the blocks that are missing in the database from the given freezer.
+    pub fn with_freezer(mut self, freezer: Arc<Freezer>) -> Self {
+        self.freezer = Some(freezer);
+        self
+    }
+}
+
+/// A provider of headers, blocks and receipts over a reference to a database transaction.
+///
+/// All reads see the same snapshot, including the uncommitted changes of a read-write
+/// transaction, which makes it usable by the stages. Blocks that were moved to the [`Freezer`]
+/// are not found, see [`ProviderImpl`] for that.
+pub struct ProviderImplRef<'a, 'b, TX: DbTx<'a>> {
+    /// Database transaction
+    tx: &'b TX,
+    /// Phantom lifetime `'a`
+    _phantom: PhantomData<&'a TX>,
+}
+
+impl<'a, 'b, TX: DbTx<'a>> ProviderImplRef<'a, 'b, TX> {
+    /// Create new provider over the transaction
+    pub fn new(tx: &'b TX) -> Self {
+        Self { tx, _phantom: PhantomData {} }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::StateProviderFactory;
+    use crate::{
+        freezer::{Freezer, FrozenBlock},
+        AccountProvider, AccountRangeProvider, BlockProvider, HeaderProvider,
+        HistoricalRangeProvider, LogIndexProvider, ReceiptProvider, StateProofProvider,
+        StateProvider, StateProviderFactory, StorageRangeProvider, TransactionsProvider,
+        TrieNodeProvider,
+    };
 
-    use super::ProviderImpl;
-    use reth_db::mdbx::{test_utils::create_test_db, EnvKind, WriteMap};
+    use super::{ProviderImpl, ProviderImplRef};
+    use reth_db::{
+        database::Database,
+        mdbx::{test_utils::create_test_db, EnvKind, WriteMap},
+        models::{storage_history_key, AccountBeforeTx, BlockNumHash, ShardedKey, StoredBlockBody},
+        tables,
+        transaction::{DbTx, DbTxMut},
+    };
+    use reth_primitives::{
+        keccak256,
+        proofs::{calculate_state_root, calculate_storage_root, TrieAccount, EMPTY_ROOT},
+        rpc::{BlockId, BlockNumber},
+        trie::verify_proof,
+        Account, Header, IntegerList, Receipt, StorageEntry, TransactionSigned, H160, H256, U256,
+    };
+    use reth_rlp::Encodable;
+    use std::sync::Arc;
 
     #[test]
     fn common_history_provider() {
@@ -39,4 +91,586 @@ mod tests {
         let provider = ProviderImpl::new(db);
         let _ = provider.latest();
     }
+
+    #[test]
+    fn pruned_history_is_unavailable() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let hashes = (0..3u64).map(H256::from_low_u64_be).collect::<Vec<_>>();
+        db.update(|tx| {
+            for (number, hash) in hashes.iter().enumerate() {
+                let key: BlockNumHash = (number as u64, *hash).into();
+                tx.put::<tables::CanonicalHeaders>(number as u64, *hash).unwrap();
+                tx.put::<tables::HeaderNumbers>(*hash, number as u64).unwrap();
+                tx.put::<tables::BlockTransitionIndex>(key, number as u64).unwrap();
+            }
+            tx.put::<tables::Config>(
+                tables::HISTORY_PRUNED_TO_KEY.to_vec(),
+                1u64.to_be_bytes().to_vec(),
+            )
+            .unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let pruned = reth_interfaces::Error::Provider(crate::Error::HistoryPruned {
+            block_number: 0,
+            oldest: 1,
+        });
+        assert_eq!(provider.history_by_block_number(0).err(), Some(pruned.clone()));
+        assert_eq!(provider.history_by_block_hash(hashes[0]).err(), Some(pruned));
+        assert!(provider.history_by_block_number(1).is_ok());
+        assert!(provider.history_by_block_hash(hashes[2]).is_ok());
+    }
+
+    #[test]
+    fn finalized_and_safe_blocks_of_forkchoice() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        db.update(|tx| {
+            for number in 0..3u64 {
+                tx.put::<tables::CanonicalHeaders>(number, H256::from_low_u64_be(number)).unwrap();
+            }
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db.clone());
+
+        let info = provider.chain_info().unwrap();
+        assert_eq!((info.last_finalized, info.safe_finalized), (None, None));
+
+        // blocks after the best block are not synced yet
+        db.update(|tx| {
+            let put = |key: &[u8], number: u64| {
+                tx.put::<tables::Config>(key.to_vec(), number.to_be_bytes().to_vec()).unwrap()
+            };
+            put(tables::FINALIZED_BLOCK_KEY, 1);
+            put(tables::SAFE_BLOCK_KEY, 5);
+        })
+        .unwrap();
+        let info = provider.chain_info().unwrap();
+        assert_eq!(info.best_number, 2);
+        assert_eq!((info.last_finalized, info.safe_finalized), (Some(1), Some(2)));
+        assert_eq!(provider.block_number_for_id(BlockNumber::Finalized.into()).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn history_state() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let (address, created) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let slot = H256::from_low_u64_be(1);
+        let account = |nonce| Account { nonce, ..Default::default() };
+        let storage = |value: u64| StorageEntry { key: slot, value: value.into() };
+        db.update(|tx| {
+            // blocks #0, #1 and #2 end at transition 1, 3 and 5
+            for number in 0..3u64 {
+                let hash = H256::from_low_u64_be(number);
+                tx.put::<tables::CanonicalHeaders>(number, hash).unwrap();
+                tx.put::<tables::HeaderNumbers>(hash, number).unwrap();
+                tx.put::<tables::BlockTransitionIndex>((number, hash).into(), number * 2 + 1)
+                    .unwrap();
+            }
+            tx.put::<tables::PlainAccountState>(address, account(3)).unwrap();
+            tx.put::<tables::PlainAccountState>(created, account(1)).unwrap();
+            tx.put::<tables::PlainStorageState>(address, storage(30)).unwrap();
+
+            // the account changed in block #1 and #2, the other account was created in block #2
+            let before = |address, info| AccountBeforeTx { address, info };
+            tx.put::<tables::AccountChangeSet>(2, before(address, Some(account(1)))).unwrap();
+            tx.put::<tables::AccountChangeSet>(4, before(address, Some(account(2)))).unwrap();
+            tx.put::<tables::AccountChangeSet>(4, before(created, None)).unwrap();
+            tx.put::<tables::StorageChangeSet>((2, address).into(), storage(10)).unwrap();
+            tx.put::<tables::StorageChangeSet>((4, address).into(), storage(20)).unwrap();
+            tx.put::<tables::StorageChangeSet>((4, created).into(), storage(0)).unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let state = provider.history_by_block_number(0).unwrap();
+        assert_eq!(state.basic_account(address).unwrap(), Some(account(1)));
+        assert_eq!(state.storage(address, slot).unwrap(), Some(10.into()));
+
+        let state = provider.history_by_block_hash(H256::from_low_u64_be(1)).unwrap();
+        assert_eq!(state.basic_account(address).unwrap(), Some(account(2)));
+        assert_eq!(state.basic_account(created).unwrap(), None);
+        assert_eq!(state.storage(address, slot).unwrap(), Some(20.into()));
+
+        // nothing changed after the last block
+        let state = provider.history_by_block_number(2).unwrap();
+        assert_eq!(state.basic_account(address).unwrap(), Some(account(3)));
+        assert_eq!(state.basic_account(created).unwrap(), Some(account(1)));
+        assert_eq!(state.storage(address, slot).unwrap(), Some(30.into()));
+        assert_eq!(state.storage(created, slot).unwrap(), None);
+    }
+
+    #[test]
+    fn indexed_history_state() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let (address, other) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let slot = H256::from_low_u64_be(1);
+        let account = |nonce| Account { nonce, ..Default::default() };
+        let storage = |value: u64| StorageEntry { key: slot, value: value.into() };
+        let index = |transitions: Vec<u64>| IntegerList::from(transitions);
+        db.update(|tx| {
+            // blocks #0, #1 and #2 end at transition 1, 3 and 5
+            for number in 0..3u64 {
+                let hash = H256::from_low_u64_be(number);
+                tx.put::<tables::CanonicalHeaders>(number, hash).unwrap();
+                tx.put::<tables::BlockTransitionIndex>((number, hash).into(), number * 2 + 1)
+                    .unwrap();
+            }
+            tx.put::<tables::PlainAccountState>(address, account(3)).unwrap();
+            tx.put::<tables::PlainStorageState>(address, storage(30)).unwrap();
+
+            // the account changed in block #1 and #2, only block #1 is indexed
+            let before = |address, info| AccountBeforeTx { address, info };
+            tx.put::<tables::AccountChangeSet>(2, before(address, Some(account(1)))).unwrap();
+            tx.put::<tables::AccountChangeSet>(4, before(address, Some(account(2)))).unwrap();
+            tx.put::<tables::StorageChangeSet>((2, address).into(), storage(10)).unwrap();
+            tx.put::<tables::StorageChangeSet>((4, address).into(), storage(20)).unwrap();
+            tx.put::<tables::SyncStage>(b"HistoryIndex".to_vec(), 1).unwrap();
+            tx.put::<tables::AccountHistory>(ShardedKey::new(address, 999), index(vec![2]))
+                .unwrap();
+            let storage_key = storage_history_key(address, slot);
+            tx.put::<tables::StorageHistory>(ShardedKey::new(storage_key, 999), index(vec![2]))
+                .unwrap();
+
+            // an index entry without a changeset
+            tx.put::<tables::AccountHistory>(ShardedKey::new(other, 999), index(vec![2])).unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let state = provider.history_by_block_number(0).unwrap();
+        assert_eq!(state.basic_account(address).unwrap(), Some(account(1)));
+        assert_eq!(state.storage(address, slot).unwrap(), Some(10.into()));
+        assert_eq!(
+            state.basic_account(other).err(),
+            Some(reth_interfaces::Error::Provider(crate::Error::ChangesetMissing {
+                transition: 2
+            }))
+        );
+
+        // the change of block #2 is not indexed yet
+        let state = provider.history_by_block_number(1).unwrap();
+        assert_eq!(state.basic_account(address).unwrap(), Some(account(2)));
+        assert_eq!(state.storage(address, slot).unwrap(), Some(20.into()));
+        assert_eq!(state.basic_account(other).unwrap(), None);
+    }
+
+    #[test]
+    fn account_range_pagination() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let accounts = (1..=5u64)
+            .map(|i| (H256::from_low_u64_be(i), Account { nonce: i, ..Default::default() }))
+            .collect::<Vec<_>>();
+        db.update(|tx| {
+            for (hashed_address, account) in accounts.iter() {
+                tx.put::<tables::HashedAccount>(*hashed_address, *account).unwrap();
+            }
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let page = provider.account_range(H256::zero(), 2).unwrap();
+        assert_eq!(page.accounts, accounts[..2]);
+        assert_eq!(page.next, Some(accounts[2].0));
+
+        let page = provider.account_range(page.next.unwrap(), 2).unwrap();
+        assert_eq!(page.accounts, accounts[2..4]);
+
+        let page = provider.account_range(page.next.unwrap(), 2).unwrap();
+        assert_eq!(page.accounts, accounts[4..]);
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn storage_range_pagination() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let account = H256::from_low_u64_be(1);
+        let slots =
+            (1..=5u64).map(|i| (H256::from_low_u64_be(i), U256::from(i * 10))).collect::<Vec<_>>();
+        db.update(|tx| {
+            for (key, value) in slots.iter() {
+                let entry = StorageEntry { key: *key, value: *value };
+                tx.put::<tables::HashedStorage>(account, entry).unwrap();
+            }
+            // the slots of the next account are not part of the range
+            let entry = StorageEntry { key: H256::zero(), value: U256::from(1) };
+            tx.put::<tables::HashedStorage>(H256::from_low_u64_be(2), entry).unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let page = provider.storage_range(account, H256::zero(), 3).unwrap();
+        assert_eq!(page.slots, slots[..3]);
+        assert_eq!(page.next, Some(slots[3].0));
+
+        let page = provider.storage_range(account, page.next.unwrap(), 3).unwrap();
+        assert_eq!(page.slots, slots[3..]);
+        assert_eq!(page.next, None);
+
+        let page = provider.storage_range(H256::from_low_u64_be(3), H256::zero(), 3).unwrap();
+        assert_eq!(page, Default::default());
+    }
+
+    #[test]
+    fn historical_account_and_storage_ranges() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let account = |nonce| Account { nonce, ..Default::default() };
+        let [changed, created, untouched, destroyed] = [1, 2, 3, 4].map(H160::from_low_u64_be);
+        let [slot, new_slot] = [1, 2].map(H256::from_low_u64_be);
+        db.update(|tx| {
+            for number in 0..2u64 {
+                let hash = H256::from_low_u64_be(number);
+                tx.put::<tables::CanonicalHeaders>(number, hash).unwrap();
+                tx.put::<tables::BlockTransitionIndex>((number, hash).into(), number).unwrap();
+            }
+
+            // block 1 changes, creates and destroys accounts and changes and creates slots
+            tx.put::<tables::HashedAccount>(keccak256(changed), account(2)).unwrap();
+            tx.put::<tables::HashedAccount>(keccak256(created), account(1)).unwrap();
+            tx.put::<tables::HashedAccount>(keccak256(untouched), account(3)).unwrap();
+            for (address, info) in
+                [(changed, Some(account(1))), (created, None), (destroyed, Some(account(4)))]
+            {
+                tx.put::<tables::AccountChangeSet>(1, AccountBeforeTx { address, info }).unwrap();
+            }
+            let hashed = keccak256(changed);
+            for (key, value) in [(slot, 2u64), (new_slot, 5)] {
+                let entry = StorageEntry { key: keccak256(key), value: U256::from(value) };
+                tx.put::<tables::HashedStorage>(hashed, entry).unwrap();
+            }
+            for (key, value) in [(slot, 1u64), (new_slot, 0)] {
+                let entry = StorageEntry { key, value: U256::from(value) };
+                tx.put::<tables::StorageChangeSet>((1, changed).into(), entry).unwrap();
+            }
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let sorted = |accounts: &[(H160, u64)]| {
+            let mut accounts = accounts
+                .iter()
+                .map(|(address, nonce)| (keccak256(address), account(*nonce)))
+                .collect::<Vec<_>>();
+            accounts.sort_by_key(|(hashed, _)| *hashed);
+            accounts
+        };
+        let before = sorted(&[(changed, 1), (untouched, 3), (destroyed, 4)]);
+        let page = provider.account_range_at(0, H256::zero(), 2).unwrap();
+        assert_eq!(page.accounts, before[..2]);
+        assert_eq!(page.next, Some(before[2].0));
+        let page = provider.account_range_at(0, page.next.unwrap(), 2).unwrap();
+        assert_eq!(page.accounts, before[2..]);
+        assert_eq!(page.next, None);
+
+        // the latest block has no changes after it
+        let after = sorted(&[(changed, 2), (created, 1), (untouched, 3)]);
+        assert_eq!(provider.account_range_at(1, H256::zero(), 3).unwrap().accounts, after);
+        assert!(provider.account_range_at(2, H256::zero(), 3).is_err());
+
+        let hashed = keccak256(changed);
+        let page = provider.storage_range_at(0, hashed, H256::zero(), 2).unwrap();
+        assert_eq!(page.slots, vec![(keccak256(slot), U256::from(1))]);
+        assert_eq!(page.next, None);
+        let mut slots =
+            vec![(keccak256(slot), U256::from(2)), (keccak256(new_slot), U256::from(5))];
+        slots.sort();
+        assert_eq!(provider.storage_range_at(1, hashed, H256::zero(), 2).unwrap().slots, slots);
+    }
+
+    #[test]
+    fn state_proofs() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let addresses = (1..=20u64).map(H160::from_low_u64_be).collect::<Vec<_>>();
+        let keys = (1..=10u64).map(H256::from_low_u64_be).collect::<Vec<_>>();
+        let account = |i: u64| Account { nonce: i, balance: U256::from(i), bytecode_hash: None };
+        db.update(|tx| {
+            for (i, address) in addresses.iter().enumerate() {
+                let hashed_address = keccak256(address);
+                tx.put::<tables::HashedAccount>(hashed_address, account(i as u64)).unwrap();
+                // every other account has slots
+                if i % 2 == 0 {
+                    for (j, key) in keys.iter().enumerate() {
+                        let entry = StorageEntry { key: keccak256(key), value: U256::from(j + 1) };
+                        tx.put::<tables::HashedStorage>(hashed_address, entry).unwrap();
+                    }
+                }
+            }
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let storage_root = calculate_storage_root(
+            keys.iter().enumerate().map(|(j, key)| (*key, U256::from(j + 1))),
+        );
+        let state_root = calculate_state_root(addresses.iter().enumerate().map(|(i, address)| {
+            let root = if i % 2 == 0 { storage_root } else { EMPTY_ROOT };
+            (*address, account(i as u64), root)
+        }));
+
+        let absent_key = H256::from_low_u64_be(100);
+        let proof = provider.proof(addresses[2], &[keys[3], absent_key]).unwrap();
+        assert_eq!(proof.state_root, state_root);
+        assert_eq!(proof.account, Some(account(2)));
+        assert_eq!(proof.storage_root, storage_root);
+        let mut account_rlp = Vec::new();
+        TrieAccount::new(account(2), storage_root).encode(&mut account_rlp);
+        assert_eq!(
+            verify_proof(state_root, keccak256(addresses[2]).as_bytes(), &proof.proof),
+            Ok(Some(account_rlp))
+        );
+
+        let [slot, absent] = &proof.storage_proofs[..] else { panic!("two storage proofs") };
+        assert_eq!((slot.key, slot.value), (keys[3], U256::from(4)));
+        assert_eq!(
+            verify_proof(storage_root, keccak256(keys[3]).as_bytes(), &slot.proof),
+            Ok(Some(vec![4]))
+        );
+        assert_eq!((absent.key, absent.value), (absent_key, U256::zero()));
+        assert_eq!(
+            verify_proof(storage_root, keccak256(absent_key).as_bytes(), &absent.proof),
+            Ok(None)
+        );
+
+        // the proof of an absent account proves its absence
+        let address = H160::from_low_u64_be(100);
+        let proof = provider.proof(address, &[]).unwrap();
+        assert_eq!((proof.account, proof.storage_root), (None, EMPTY_ROOT));
+        assert_eq!(verify_proof(state_root, keccak256(address).as_bytes(), &proof.proof), Ok(None));
+    }
+
+    #[test]
+    fn stored_trie_nodes() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let header =
+            Header { number: 1, state_root: H256::repeat_byte(0x11), ..Default::default() };
+        let hash = header.hash_slow();
+        let hashed_address = H256::repeat_byte(0x22);
+        db.update(|tx| {
+            tx.put::<tables::CanonicalHeaders>(1, hash).unwrap();
+            tx.put::<tables::Headers>((1, hash).into(), header.clone()).unwrap();
+            tx.put::<tables::AccountsTrie>(vec![], vec![0xc0]).unwrap();
+            tx.put::<tables::StoragesTrie>(
+                [hashed_address.as_bytes(), &[0x01]].concat(),
+                vec![0xc1],
+            )
+            .unwrap();
+            tx.put::<tables::StorageRoots>(hashed_address, H256::repeat_byte(0x33)).unwrap();
+            tx.put::<tables::SyncStage>(b"AccountHashing".to_vec(), 2).unwrap();
+            tx.put::<tables::SyncStage>(b"Merkle".to_vec(), 1).unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db.clone());
+
+        assert_eq!(provider.account_trie_node(&[]).unwrap(), Some(vec![0xc0]));
+        assert_eq!(provider.account_trie_node(&[0x01]).unwrap(), None);
+        assert_eq!(provider.storage_trie_node(hashed_address, &[0x01]).unwrap(), Some(vec![0xc1]));
+        assert_eq!(provider.storage_root(hashed_address).unwrap(), H256::repeat_byte(0x33));
+        assert_eq!(provider.storage_root(H256::zero()).unwrap(), EMPTY_ROOT);
+
+        // the tries don't match the hashed state before the merkle stage caught up
+        assert_eq!(provider.state_root().unwrap(), None);
+        db.update(|tx| tx.put::<tables::SyncStage>(b"AccountHashing".to_vec(), 1).unwrap())
+            .unwrap();
+        assert_eq!(provider.state_root().unwrap(), Some(header.state_root));
+    }
+
+    #[test]
+    fn block_and_receipts() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let header = Header { number: 1, ..Default::default() };
+        let hash = header.hash_slow();
+        let key: BlockNumHash = (1, hash).into();
+        let transaction = TransactionSigned::default();
+        let receipt = Receipt { cumulative_gas_used: 21_000, ..Default::default() };
+        db.update(|tx| {
+            tx.put::<tables::CanonicalHeaders>(1, hash).unwrap();
+            tx.put::<tables::HeaderNumbers>(hash, 1).unwrap();
+            tx.put::<tables::Headers>(key, header.clone()).unwrap();
+            tx.put::<tables::BlockBodies>(key, StoredBlockBody { start_tx_id: 5, tx_count: 1 })
+                .unwrap();
+            tx.put::<tables::Transactions>(5, transaction.clone()).unwrap();
+            tx.put::<tables::Receipts>(5, receipt.clone()).unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let block = provider.block(hash.into()).unwrap().unwrap();
+        assert_eq!(block.header, header);
+        assert_eq!(block.body, vec![transaction]);
+        assert!(block.ommers.is_empty());
+        assert_eq!(provider.receipts_by_block(hash.into()).unwrap(), Some(vec![receipt]));
+
+        assert_eq!(provider.block(H256::zero().into()).unwrap(), None);
+        assert_eq!(provider.receipts_by_block(H256::zero().into()).unwrap(), None);
+    }
+
+    #[test]
+    fn canonical_index() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let (canonical, side) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        db.update(|tx| {
+            tx.put::<tables::CanonicalHeaders>(1, canonical).unwrap();
+            tx.put::<tables::HeaderNumbers>(canonical, 1).unwrap();
+            // a block of a side chain at the same height
+            tx.put::<tables::HeaderNumbers>(side, 1).unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        assert_eq!(provider.canonical_hash(1).unwrap(), Some(canonical));
+        assert_eq!(provider.canonical_hash(2).unwrap(), None);
+        assert_eq!(provider.block_number(side).unwrap(), Some(1));
+        assert!(provider.is_canonical(canonical).unwrap());
+        assert!(!provider.is_canonical(side).unwrap());
+        assert!(!provider.is_canonical(H256::zero()).unwrap());
+        assert_eq!(provider.block_number_for_id(canonical.into()).unwrap(), Some(1));
+        assert_eq!(provider.block_number_for_id(side.into()).unwrap(), None);
+    }
+
+    #[test]
+    fn transaction_block_lookup() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        // blocks with 2, 0 and 3 transactions
+        let bodies = [(0, 2), (2, 0), (2, 3)];
+        db.update(|tx| {
+            for (number, (start_tx_id, tx_count)) in bodies.into_iter().enumerate() {
+                let hash = H256::from_low_u64_be(number as u64 + 1);
+                let key: BlockNumHash = (number as u64, hash).into();
+                tx.put::<tables::CanonicalHeaders>(number as u64, hash).unwrap();
+                tx.put::<tables::BlockBodies>(key, StoredBlockBody { start_tx_id, tx_count })
+                    .unwrap();
+            }
+            for tx_number in 0..5u64 {
+                tx.put::<tables::TxHashNumber>(H256::from_low_u64_be(0x100 + tx_number), tx_number)
+                    .unwrap();
+            }
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let located = (0..5u64)
+            .map(|n| provider.transaction_block(H256::from_low_u64_be(0x100 + n)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            located,
+            vec![Some((0, 0)), Some((0, 1)), Some((2, 0)), Some((2, 1)), Some((2, 2))]
+        );
+        assert_eq!(provider.transaction_block(H256::zero()).unwrap(), None);
+    }
+
+    #[test]
+    fn provider_over_transaction() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let headers = (0..3u64)
+            .map(|number| Header { number, ..Default::default() }.seal())
+            .collect::<Vec<_>>();
+        let receipt = Receipt { cumulative_gas_used: 21_000, ..Default::default() };
+        let tx = db.tx_mut().unwrap();
+        for header in headers.iter() {
+            let key: BlockNumHash = (header.number, header.hash()).into();
+            tx.put::<tables::CanonicalHeaders>(header.number, header.hash()).unwrap();
+            tx.put::<tables::HeaderNumbers>(header.hash(), header.number).unwrap();
+            tx.put::<tables::Headers>(key, header.clone().unseal()).unwrap();
+            tx.put::<tables::BlockBodies>(
+                key,
+                StoredBlockBody { start_tx_id: header.number, tx_count: 1 },
+            )
+            .unwrap();
+            tx.put::<tables::Receipts>(header.number, receipt.clone()).unwrap();
+        }
+
+        // the uncommitted blocks are visible
+        let provider = ProviderImplRef::new(&tx);
+        let info = provider.chain_info().unwrap();
+        assert_eq!((info.best_number, info.best_hash), (2, headers[2].hash()));
+        assert_eq!(provider.header(&headers[1].hash()).unwrap().as_ref(), Some(&*headers[1]));
+        assert_eq!(provider.header_by_number(2).unwrap().as_ref(), Some(&*headers[2]));
+        assert_eq!(provider.block_hash(1.into()).unwrap(), Some(headers[1].hash()));
+        assert_eq!(provider.block_hash(U256::MAX).unwrap(), None);
+        let block = provider.block(BlockId::Number(BlockNumber::Latest)).unwrap().unwrap();
+        assert_eq!(block.header, *headers[2]);
+        assert_eq!(
+            provider.receipts_by_block(headers[0].hash().into()).unwrap(),
+            Some(vec![receipt])
+        );
+        tx.commit().unwrap();
+
+        let provider = ProviderImpl::new(db);
+        assert_eq!(provider.chain_info().unwrap().best_number, 2);
+        assert_eq!(provider.header(&headers[1].hash()).unwrap().as_ref(), Some(&*headers[1]));
+    }
+
+    #[test]
+    fn frozen_block_and_receipts() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let header = Header::default();
+        let hash = header.hash_slow();
+        let transaction = TransactionSigned::default();
+        let receipt = Receipt { cumulative_gas_used: 21_000, ..Default::default() };
+        // only the indices of the block are left in the database
+        db.update(|tx| {
+            tx.put::<tables::CanonicalHeaders>(0, hash).unwrap();
+            tx.put::<tables::HeaderNumbers>(hash, 0).unwrap();
+            tx.put::<tables::BlockBodies>(
+                (0, hash).into(),
+                StoredBlockBody { start_tx_id: 0, tx_count: 1 },
+            )
+            .unwrap();
+        })
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let freezer = Arc::new(Freezer::open(dir.path()).unwrap());
+        let frozen = FrozenBlock {
+            header: header.clone(),
+            ommers: vec![],
+            transactions: vec![transaction.clone()],
+            receipts: vec![(0, receipt.clone())],
+        };
+        freezer.append(0, frozen).unwrap();
+
+        let provider = ProviderImpl::new(db.clone());
+        assert_eq!(provider.block(hash.into()).unwrap(), None);
+
+        let provider = ProviderImpl::new(db).with_freezer(freezer);
+        let block = provider.block(hash.into()).unwrap().unwrap();
+        assert_eq!(block.header, header);
+        assert_eq!(block.body, vec![transaction]);
+        assert_eq!(provider.header(&hash).unwrap(), Some(header.clone()));
+        assert_eq!(provider.header_by_number(0).unwrap(), Some(header));
+        assert_eq!(provider.receipts_by_block(hash.into()).unwrap(), Some(vec![receipt]));
+    }
+
+    #[test]
+    fn log_index_lookup() {
+        let db = create_test_db::<WriteMap>(EnvKind::RW);
+        let (address, other) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let (topic, other_topic) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        db.update(|tx| {
+            let list = |blocks: Vec<u64>| IntegerList::from(blocks);
+            tx.put::<tables::LogAddressIndex>(ShardedKey::new(address, 9), list(vec![1, 5, 9]))
+                .unwrap();
+            tx.put::<tables::LogAddressIndex>(ShardedKey::new(address, 19), list(vec![12, 15]))
+                .unwrap();
+            tx.put::<tables::LogAddressIndex>(ShardedKey::new(other, 9), list(vec![2, 3])).unwrap();
+            tx.put::<tables::LogTopicIndex>(ShardedKey::new(topic, 9), list(vec![3, 5])).unwrap();
+            tx.put::<tables::LogTopicIndex>(ShardedKey::new(topic, 19), list(vec![15])).unwrap();
+            tx.put::<tables::SyncStage>(b"LogIndex".to_vec(), 15).unwrap();
+        })
+        .unwrap();
+        let provider = ProviderImpl::new(db);
+
+        let blocks = |range, addresses: &[_], topics: &[_]| {
+            provider.log_filter_blocks(range, addresses, topics).unwrap()
+        };
+        assert_eq!(blocks(0..=15, &[address], &[]), Some(vec![1, 5, 9, 12, 15]));
+        assert_eq!(blocks(4..=12, &[address], &[]), Some(vec![5, 9, 12]));
+        assert_eq!(blocks(0..=15, &[address, other], &[]), Some(vec![1, 2, 3, 5, 9, 12, 15]));
+        assert_eq!(blocks(0..=15, &[address], &[vec![], vec![topic]]), Some(vec![5, 15]));
+        assert_eq!(blocks(0..=15, &[], &[vec![topic, other_topic]]), Some(vec![3, 5, 15]));
+        assert_eq!(blocks(0..=15, &[address], &[vec![other_topic]]), Some(vec![]));
+
+        // nothing is filtered or the range is not fully indexed
+        assert_eq!(blocks(0..=15, &[], &[vec![]]), None);
+        assert_eq!(blocks(0..=16, &[address], &[]), None);
+    }
 }
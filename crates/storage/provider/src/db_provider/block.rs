@@ -1,15 +1,69 @@
-use crate::{BlockProvider, ChainInfo, HeaderProvider, ProviderImpl};
-use reth_db::{database::Database, tables, transaction::DbTx};
+use crate::{
+    freezer::{Freezer, FreezerError},
+    BlockProvider, CanonicalBlock, ChainInfo, Error, HeaderProvider, LogIndexProvider,
+    ProviderImpl, ProviderImplRef, ReceiptProvider, StageCheckpointProvider, StateChanges,
+    TransactionsProvider,
+};
+use reth_db::{
+    cursor::DbCursorRO,
+    database::Database,
+    models::{BlockNumHash, ShardedKey, StoredBlockBody},
+    table::Table,
+    tables::{self, BlockList},
+    transaction::DbTx,
+};
 use reth_interfaces::Result;
-use reth_primitives::{rpc::BlockId, Block, BlockHash, BlockNumber, Header, H256, U256};
+use reth_primitives::{
+    rpc::BlockId, Address, Block, BlockHash, BlockNumber, Header, Receipt, TxHash, TxNumber, H256,
+    U256,
+};
+use std::{collections::BTreeSet, ops::RangeInclusive};
+
+/// The id of the stage that writes the log index, which is the key of its progress in
+/// [tables::SyncStage].
+const LOG_INDEX_STAGE: &str = "LogIndex";
+
+impl<DB: Database> ProviderImpl<DB> {
+    /// Returns the canonical blocks in the range along with their receipts and state changes.
+    ///
+    /// Frozen blocks are not included, see [ProviderImplRef::canonical_blocks].
+    pub fn canonical_blocks(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+    ) -> Result<Vec<CanonicalBlock>> {
+        self.db.view(|tx| ProviderImplRef::new(tx).canonical_blocks(range))?
+    }
+
+    /// Returns the key of the block with the given id, if it's known.
+    fn block_key(&self, id: BlockId) -> Result<Option<BlockNumHash>> {
+        self.db.view(|tx| ProviderImplRef::new(tx).block_key(id))?
+    }
+
+    /// Returns the freezer if it holds the block, which is only the case for old canonical blocks.
+    fn frozen(&self, key: BlockNumHash) -> Result<Option<&Freezer>> {
+        let freezer = match self.freezer.as_deref() {
+            Some(freezer) if freezer.contains(key.number()) => freezer,
+            _ => return Ok(None),
+        };
+        let canonical = self.canonical_hash(key.number())?;
+        Ok((canonical == Some(key.hash())).then_some(freezer))
+    }
+}
 
 impl<DB: Database> HeaderProvider for ProviderImpl<DB> {
     fn header(&self, block_hash: &BlockHash) -> Result<Option<Header>> {
-        self.db.view(|tx| tx.get::<tables::Headers>((0, *block_hash).into()))?.map_err(Into::into)
+        if self.freezer.is_some() {
+            if let Some(number) = self.block_number(*block_hash)? {
+                if let Some(freezer) = self.frozen((number, *block_hash).into())? {
+                    return freezer.header(number).map_err(freezer_err(number))
+                }
+            }
+        }
+        self.db.view(|tx| ProviderImplRef::new(tx).header(block_hash))?
     }
 
     fn header_by_number(&self, num: BlockNumber) -> Result<Option<Header>> {
-        if let Some(hash) = self.db.view(|tx| tx.get::<tables::CanonicalHeaders>(num))?? {
+        if let Some(hash) = self.canonical_hash(num)? {
             self.header(&hash)
         } else {
             Ok(None)
@@ -17,38 +71,379 @@ impl<DB: Database> HeaderProvider for ProviderImpl<DB> {
     }
 
     fn header_td(&self, hash: &BlockHash) -> Result<Option<U256>> {
-        if let Some(num) = self.db.view(|tx| tx.get::<tables::HeaderNumbers>(*hash))?? {
-            let td = self.db.view(|tx| tx.get::<tables::HeaderTD>((num, *hash).into()))??;
-            Ok(td.map(|v| v.0))
-        } else {
-            Ok(None)
-        }
+        self.db.view(|tx| ProviderImplRef::new(tx).header_td(hash))?
     }
 }
 
 impl<DB: Database> BlockProvider for ProviderImpl<DB> {
     fn chain_info(&self) -> Result<ChainInfo> {
+        self.db.view(|tx| ProviderImplRef::new(tx).chain_info())?
+    }
+
+    fn block(&self, id: BlockId) -> Result<Option<Block>> {
+        let key = match self.block_key(id)? {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        if let Some(freezer) = self.frozen(key)? {
+            let number = key.number();
+            let header = freezer.header(number).map_err(freezer_err(number))?;
+            let body = freezer.body(number).map_err(freezer_err(number))?;
+            return Ok(header.zip(body).map(|(header, (body, ommers))| Block {
+                header,
+                body,
+                ommers,
+            }))
+        }
+        self.db.view(|tx| ProviderImplRef::new(tx).block_by_key(key))?
+    }
+
+    fn block_number(&self, hash: H256) -> Result<Option<BlockNumber>> {
+        self.db.view(|tx| ProviderImplRef::new(tx).block_number(hash))?
+    }
+
+    fn block_hash(&self, number: U256) -> Result<Option<H256>> {
+        self.db.view(|tx| ProviderImplRef::new(tx).block_hash(number))?
+    }
+
+    fn canonical_hash(&self, number: BlockNumber) -> Result<Option<H256>> {
+        self.db.view(|tx| ProviderImplRef::new(tx).canonical_hash(number))?
+    }
+
+    fn is_canonical(&self, hash: H256) -> Result<bool> {
+        self.db.view(|tx| ProviderImplRef::new(tx).is_canonical(hash))?
+    }
+}
+
+impl<DB: Database> ReceiptProvider for ProviderImpl<DB> {
+    fn receipts_by_block(&self, id: BlockId) -> Result<Option<Vec<Receipt>>> {
+        let key = match self.block_key(id)? {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        if let Some(freezer) = self.frozen(key)? {
+            let number = key.number();
+            let receipts = freezer.receipts(number).map_err(freezer_err(number))?;
+            return Ok(
+                receipts.map(|receipts| receipts.into_iter().map(|(_, receipt)| receipt).collect())
+            )
+        }
+        self.db.view(|tx| ProviderImplRef::new(tx).receipts_by_key(key))?
+    }
+}
+
+impl<DB: Database> TransactionsProvider for ProviderImpl<DB> {
+    fn transaction_block(&self, hash: TxHash) -> Result<Option<(BlockNumber, usize)>> {
+        self.db.view(|tx| ProviderImplRef::new(tx).transaction_block(hash))?
+    }
+}
+
+impl<DB: Database> LogIndexProvider for ProviderImpl<DB> {
+    fn log_filter_blocks(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+        addresses: &[Address],
+        topics: &[Vec<H256>],
+    ) -> Result<Option<Vec<BlockNumber>>> {
+        self.db.view(|tx| {
+            ProviderImplRef::new(tx).log_filter_blocks(range.clone(), addresses, topics)
+        })?
+    }
+}
+
+impl<DB: Database> StageCheckpointProvider for ProviderImpl<DB> {
+    fn stage_checkpoint(&self, stage: &str) -> Result<Option<BlockNumber>> {
+        self.db.view(|tx| ProviderImplRef::new(tx).stage_checkpoint(stage))?
+    }
+}
+
+// === impl ProviderImplRef ===
+
+impl<'a, 'b, TX: DbTx<'a>> ProviderImplRef<'a, 'b, TX> {
+    /// Returns the key of the block with the given id, if it's known.
+    fn block_key(&self, id: BlockId) -> Result<Option<BlockNumHash>> {
+        let hash = match self.block_hash_for_id(id)? {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+        Ok(self.block_number(hash)?.map(|number| (number, hash).into()))
+    }
+
+    /// Returns the canonical blocks in the range along with their receipts and state changes.
+    ///
+    /// Stops at the first block without a body, the result is in ascending order.
+    pub fn canonical_blocks(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+    ) -> Result<Vec<CanonicalBlock>> {
+        let mut blocks = Vec::new();
+        for number in range {
+            let Some(hash) = self.tx.get::<tables::CanonicalHeaders>(number)? else { break };
+            let key = (number, hash).into();
+            let Some(block) = self.block_by_key(key)? else { break };
+            let receipts = self.receipts_by_key(key)?.unwrap_or_default();
+            let state = self.state_changes(key)?;
+            blocks.push(CanonicalBlock { hash, block, receipts, state });
+        }
+        Ok(blocks)
+    }
+
+    /// Returns the block with the given key.
+    fn block_by_key(&self, key: BlockNumHash) -> Result<Option<Block>> {
+        let header = match self.tx.get::<tables::Headers>(key)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let indices = match self.tx.get::<tables::BlockBodies>(key)? {
+            Some(indices) => indices,
+            None => return Ok(None),
+        };
+        let ommers = self
+            .tx
+            .get::<tables::BlockOmmers>(key)?
+            .map(|stored| stored.ommers)
+            .unwrap_or_default();
+        let body = self.walk_block::<tables::Transactions>(indices)?;
+        Ok(Some(Block { header, body, ommers }))
+    }
+
+    /// Returns the changes of the state by the block with the given key, which are recorded at
+    /// the transitions after the transitions of the parent.
+    fn state_changes(&self, key: BlockNumHash) -> Result<StateChanges> {
+        let Some(last) = self.tx.get::<tables::BlockTransitionIndex>(key)? else {
+            return Ok(StateChanges::default())
+        };
+        let first = match key.number().checked_sub(1) {
+            Some(parent) => match self.tx.get::<tables::CanonicalHeaders>(parent)? {
+                Some(hash) => self
+                    .tx
+                    .get::<tables::BlockTransitionIndex>((parent, hash).into())?
+                    .map_or(0, |transition| transition + 1),
+                None => 0,
+            },
+            None => 0,
+        };
+        let accounts = self
+            .tx
+            .cursor_dup::<tables::AccountChangeSet>()?
+            .walk(first)?
+            .take_while(|res| res.as_ref().map_or(true, |(transition, _)| *transition <= last))
+            .map(|res| res.map(|(_, change)| (change.address, change.info)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let storage = self
+            .tx
+            .cursor_dup::<tables::StorageChangeSet>()?
+            .walk((first, Address::zero()).into())?
+            .take_while(|res| res.as_ref().map_or(true, |(key, _)| key.transition_id() <= last))
+            .map(|res| res.map(|(key, entry)| (key.address(), entry)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(StateChanges { accounts, storage })
+    }
+
+    /// Returns the receipts of the block with the given key.
+    ///
+    /// Only the canonical blocks are executed, so blocks of side chains have no receipts.
+    fn receipts_by_key(&self, key: BlockNumHash) -> Result<Option<Vec<Receipt>>> {
+        if self.canonical_hash(key.number())? != Some(key.hash()) {
+            return Ok(None)
+        }
+        match self.tx.get::<tables::BlockBodies>(key)? {
+            Some(indices) => Ok(Some(self.walk_block::<tables::Receipts>(indices)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the transaction indices of the canonical block with the given number.
+    fn canonical_body(&self, number: BlockNumber) -> Result<Option<StoredBlockBody>> {
+        match self.tx.get::<tables::CanonicalHeaders>(number)? {
+            Some(hash) => Ok(self.tx.get::<tables::BlockBodies>((number, hash).into())?),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the values of the table that are keyed by the transactions of the block.
+    fn walk_block<T: Table<Key = TxNumber>>(
+        &self,
+        indices: StoredBlockBody,
+    ) -> Result<Vec<T::Value>> {
+        Ok(self
+            .tx
+            .cursor::<T>()?
+            .walk(indices.start_tx_id)?
+            .take(indices.tx_count as usize)
+            .map(|res| res.map(|(_, value)| value))
+            .collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+}
+
+impl<'a, 'b, TX: DbTx<'a>> HeaderProvider for ProviderImplRef<'a, 'b, TX> {
+    fn header(&self, block_hash: &BlockHash) -> Result<Option<Header>> {
+        match self.block_number(*block_hash)? {
+            Some(number) => Ok(self.tx.get::<tables::Headers>((number, *block_hash).into())?),
+            None => Ok(None),
+        }
+    }
+
+    fn header_by_number(&self, num: BlockNumber) -> Result<Option<Header>> {
+        match self.tx.get::<tables::CanonicalHeaders>(num)? {
+            Some(hash) => Ok(self.tx.get::<tables::Headers>((num, hash).into())?),
+            None => Ok(None),
+        }
+    }
+
+    fn header_td(&self, hash: &BlockHash) -> Result<Option<U256>> {
+        match self.block_number(*hash)? {
+            Some(num) => Ok(self.tx.get::<tables::HeaderTD>((num, *hash).into())?.map(|td| td.0)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a, 'b, TX: DbTx<'a>> BlockProvider for ProviderImplRef<'a, 'b, TX> {
+    /// Returns the last canonical block as the best block.
+    ///
+    /// The finalized and safe blocks are the ones of the latest forkchoice update, see
+    /// [`FINALIZED_BLOCK_KEY`](tables::FINALIZED_BLOCK_KEY). Blocks that are not synced yet are
+    /// not finalized, so they are capped at the best block.
+    fn chain_info(&self) -> Result<ChainInfo> {
+        let (best_number, best_hash) =
+            self.tx.cursor::<tables::CanonicalHeaders>()?.last()?.unwrap_or_default();
+        let synced = |key| -> Result<Option<BlockNumber>> {
+            Ok(config_number(self.tx, key)?.map(|number| number.min(best_number)))
+        };
         Ok(ChainInfo {
-            best_hash: Default::default(),
-            best_number: 0,
-            last_finalized: None,
-            safe_finalized: None,
+            best_hash,
+            best_number,
+            last_finalized: synced(tables::FINALIZED_BLOCK_KEY)?,
+            safe_finalized: synced(tables::SAFE_BLOCK_KEY)?,
         })
     }
 
-    fn block(&self, _id: BlockId) -> Result<Option<Block>> {
-        // TODO
-        Ok(None)
+    fn block(&self, id: BlockId) -> Result<Option<Block>> {
+        match self.block_key(id)? {
+            Some(key) => self.block_by_key(key),
+            None => Ok(None),
+        }
     }
 
     fn block_number(&self, hash: H256) -> Result<Option<BlockNumber>> {
-        self.db.view(|tx| tx.get::<tables::HeaderNumbers>(hash))?.map_err(Into::into)
+        Ok(self.tx.get::<tables::HeaderNumbers>(hash)?)
     }
 
     fn block_hash(&self, number: U256) -> Result<Option<H256>> {
-        // TODO: This unwrap is potentially unsafe
-        self.db
-            .view(|tx| tx.get::<tables::CanonicalHeaders>(number.try_into().unwrap()))?
-            .map_err(Into::into)
+        if number > U256::from(u64::MAX) {
+            return Ok(None)
+        }
+        self.canonical_hash(number.as_u64())
+    }
+
+    fn canonical_hash(&self, number: BlockNumber) -> Result<Option<H256>> {
+        Ok(self.tx.get::<tables::CanonicalHeaders>(number)?)
+    }
+}
+
+impl<'a, 'b, TX: DbTx<'a>> ReceiptProvider for ProviderImplRef<'a, 'b, TX> {
+    fn receipts_by_block(&self, id: BlockId) -> Result<Option<Vec<Receipt>>> {
+        match self.block_key(id)? {
+            Some(key) => self.receipts_by_key(key),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a, 'b, TX: DbTx<'a>> TransactionsProvider for ProviderImplRef<'a, 'b, TX> {
+    /// Finds the block with a binary search over the transaction ranges of the canonical blocks,
+    /// which are ascending.
+    fn transaction_block(&self, hash: TxHash) -> Result<Option<(BlockNumber, usize)>> {
+        let Some(tx_number) = self.tx.get::<tables::TxHashNumber>(hash)? else { return Ok(None) };
+        let (mut low, mut high) = (0, self.chain_info()?.best_number);
+        while low <= high {
+            let mid = low + (high - low) / 2;
+            let Some(body) = self.canonical_body(mid)? else { return Ok(None) };
+            if tx_number < body.start_tx_id {
+                let Some(below) = mid.checked_sub(1) else { return Ok(None) };
+                high = below;
+            } else if tx_number >= body.start_tx_id + body.tx_count {
+                low = mid + 1;
+            } else {
+                return Ok(Some((mid, (tx_number - body.start_tx_id) as usize)))
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a, 'b, TX: DbTx<'a>> LogIndexProvider for ProviderImplRef<'a, 'b, TX> {
+    fn log_filter_blocks(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+        addresses: &[Address],
+        topics: &[Vec<H256>],
+    ) -> Result<Option<Vec<BlockNumber>>> {
+        let indexed = self.tx.get::<tables::SyncStage>(LOG_INDEX_STAGE.as_bytes().to_vec())?;
+        if indexed.map_or(true, |indexed| indexed < *range.end()) {
+            return Ok(None)
+        }
+
+        let mut candidates = None;
+        if !addresses.is_empty() {
+            candidates =
+                Some(indexed_blocks::<_, tables::LogAddressIndex, _>(self.tx, &range, addresses)?);
+        }
+        for topics in topics.iter().filter(|topics| !topics.is_empty()) {
+            let blocks = indexed_blocks::<_, tables::LogTopicIndex, _>(self.tx, &range, topics)?;
+            candidates = Some(match candidates {
+                Some(candidates) => blocks.intersection(&candidates).copied().collect(),
+                None => blocks,
+            });
+        }
+        Ok(candidates.map(|candidates| candidates.into_iter().collect()))
+    }
+}
+
+impl<'a, 'b, TX: DbTx<'a>> StageCheckpointProvider for ProviderImplRef<'a, 'b, TX> {
+    fn stage_checkpoint(&self, stage: &str) -> Result<Option<BlockNumber>> {
+        Ok(self.tx.get::<tables::SyncStage>(stage.as_bytes().to_vec())?)
+    }
+}
+
+/// Returns the block number stored under the key in [tables::Config], as big endian `u64`.
+fn config_number<'a, TX: DbTx<'a>>(tx: &TX, key: &[u8]) -> Result<Option<BlockNumber>> {
+    let Some(value) = tx.get::<tables::Config>(key.to_vec())? else { return Ok(None) };
+    let bytes = value.try_into().map_err(|_| reth_interfaces::db::Error::DecodeError)?;
+    Ok(Some(u64::from_be_bytes(bytes)))
+}
+
+/// Converts an error of reading the block from the freezer.
+fn freezer_err(block_number: BlockNumber) -> impl FnOnce(FreezerError) -> reth_interfaces::Error {
+    move |err| Error::FreezerRead { block_number, reason: err.to_string() }.into()
+}
+
+/// Returns the blocks in the range that are indexed for any of the keys.
+fn indexed_blocks<'a, TX, T, K>(
+    tx: &TX,
+    range: &RangeInclusive<BlockNumber>,
+    keys: &[K],
+) -> Result<BTreeSet<BlockNumber>>
+where
+    TX: DbTx<'a>,
+    T: Table<Key = ShardedKey<K>, Value = BlockList>,
+    K: Clone + PartialEq,
+{
+    let mut blocks = BTreeSet::new();
+    let mut cursor = tx.cursor::<T>()?;
+    for key in keys {
+        // The first shard is the one that contains the start of the range.
+        for entry in cursor.walk(ShardedKey::new(key.clone(), *range.start()))? {
+            let (sharded_key, list) = entry?;
+            if sharded_key.key != *key {
+                break
+            }
+            blocks.extend(list.iter(0).map(|block| block as u64).filter(|b| range.contains(b)));
+            if sharded_key.highest_tx_number >= *range.end() {
+                break
+            }
+        }
     }
+    Ok(blocks)
 }
@@ -0,0 +1,128 @@
+//! Generation of the Merkle proofs of the hashed state.
+use super::ProviderImpl;
+use crate::{AccountProof, StateProofProvider, StorageProof, TrieNodeProvider};
+use reth_db::{
+    cursor::{DbCursorRO, DbDupCursorRO},
+    database::Database,
+    models::BlockNumHash,
+    tables,
+    transaction::DbTx,
+};
+use reth_interfaces::Result;
+use reth_primitives::{
+    keccak256,
+    proofs::{TrieAccount, EMPTY_ROOT},
+    trie::Trie,
+    Address, H256, U256,
+};
+use reth_rlp::{Decodable, Encodable};
+
+/// The stage that stores the nodes of the tries, see [tables::SyncStage].
+const MERKLE_STAGE: &str = "Merkle";
+/// The stages that write the hashed state the tries are built from.
+const HASHING_STAGES: [&str; 2] = ["AccountHashing", "StorageHashing"];
+
+impl<DB: Database> StateProofProvider for ProviderImpl<DB> {
+    fn proof(&self, address: Address, keys: &[H256]) -> Result<AccountProof> {
+        let tx = self.db.tx()?;
+        state_proof(&tx, address, keys)
+    }
+}
+
+impl<DB: Database> TrieNodeProvider for ProviderImpl<DB> {
+    fn state_root(&self) -> Result<Option<H256>> {
+        let tx = self.db.tx()?;
+        let Some(merkle) = tx.get::<tables::SyncStage>(MERKLE_STAGE.as_bytes().to_vec())? else {
+            return Ok(None)
+        };
+        for stage in HASHING_STAGES {
+            if tx.get::<tables::SyncStage>(stage.as_bytes().to_vec())?.unwrap_or_default() > merkle
+            {
+                return Ok(None)
+            }
+        }
+
+        // the merkle stage verified the root of the tries against the header of its checkpoint
+        let Some(hash) = tx.get::<tables::CanonicalHeaders>(merkle)? else { return Ok(None) };
+        let header = tx.get::<tables::Headers>(BlockNumHash((merkle, hash)))?;
+        Ok(header.map(|header| header.state_root))
+    }
+
+    fn account_trie_node(&self, path: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.db.tx()?.get::<tables::AccountsTrie>(path.to_vec())?)
+    }
+
+    fn storage_trie_node(&self, hashed_address: H256, path: &[u8]) -> Result<Option<Vec<u8>>> {
+        let key = [hashed_address.as_bytes(), path].concat();
+        Ok(self.db.tx()?.get::<tables::StoragesTrie>(key)?)
+    }
+
+    fn storage_root(&self, hashed_address: H256) -> Result<H256> {
+        Ok(self.db.tx()?.get::<tables::StorageRoots>(hashed_address)?.unwrap_or(EMPTY_ROOT))
+    }
+}
+
+/// Generates the proof of the account and of its slots from the hashed state tables.
+///
+/// Without stored intermediate nodes, the tries are built from all accounts and slots of the
+/// state.
+pub(crate) fn state_proof<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    address: Address,
+    keys: &[H256],
+) -> Result<AccountProof> {
+    let mut accounts = Vec::new();
+    for entry in tx.cursor::<tables::HashedAccount>()?.walk(H256::zero())? {
+        let (hashed_address, account) = entry?;
+        let storage_root = storage_trie(tx, hashed_address)?.root_hash();
+        accounts.push((hashed_address, rlp(&TrieAccount::new(account, storage_root))));
+    }
+    let account_trie = Trie::from_entries(accounts);
+
+    let hashed_address = keccak256(address);
+    let account = tx.get::<tables::HashedAccount>(hashed_address)?;
+    let storage_trie = storage_trie(tx, hashed_address)?;
+    let storage_proofs = keys
+        .iter()
+        .map(|key| {
+            let hashed_key = keccak256(key);
+            let value = storage_trie
+                .get(hashed_key.as_bytes())
+                .ok()
+                .flatten()
+                .and_then(|mut value| U256::decode(&mut value).ok())
+                .unwrap_or_default();
+            StorageProof { key: *key, value, proof: storage_trie.proof(hashed_key.as_bytes()) }
+        })
+        .collect();
+
+    Ok(AccountProof {
+        state_root: account_trie.root_hash(),
+        address,
+        account,
+        storage_root: storage_trie.root_hash(),
+        proof: account_trie.proof(hashed_address.as_bytes()),
+        storage_proofs,
+    })
+}
+
+/// Builds the storage trie of the account with the hashed address.
+///
+/// Zero values are not part of the trie.
+fn storage_trie<'a, TX: DbTx<'a>>(tx: &TX, hashed_address: H256) -> Result<Trie> {
+    let mut cursor = tx.cursor_dup::<tables::HashedStorage>()?;
+    let mut slots = Vec::new();
+    for entry in cursor.walk_dup(hashed_address, None)? {
+        let (_, entry) = entry?;
+        if !entry.value.is_zero() {
+            slots.push((entry.key, rlp(&entry.value)));
+        }
+    }
+    Ok(Trie::from_entries(slots))
+}
+
+fn rlp(value: &impl Encodable) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
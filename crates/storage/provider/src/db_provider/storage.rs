@@ -1,18 +1,27 @@
 use super::ProviderImpl;
-use crate::{AccountProvider, Error, StateProvider, StateProviderFactory};
+use crate::{
+    AccountProvider, AccountRange, AccountRangeProvider, Error, HistoricalRangeProvider,
+    StateProvider, StateProviderFactory, StorageRange, StorageRangeProvider,
+};
 use reth_db::{
     cursor::{DbCursorRO, DbDupCursorRO},
     database::{Database, DatabaseGAT},
-    tables,
+    models::{storage_history_key, ShardedKey},
+    table::Table,
+    tables::{self, TransitionList},
     transaction::DbTx,
 };
 use reth_interfaces::Result;
 
 use reth_primitives::{
-    Account, Address, BlockHash, BlockNumber, Bytes, StorageKey, StorageValue, TransitionId, H256,
-    U256,
+    keccak256, Account, Address, BlockHash, BlockNumber, Bytes, StorageKey, StorageValue,
+    TransitionId, H256, U256,
 };
-use std::marker::PhantomData;
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// The id of the stage that writes the history indices, which is the key of its progress in
+/// [tables::SyncStage].
+const HISTORY_INDEX_STAGE: &str = "HistoryIndex";
 
 impl<DB: Database> StateProviderFactory for ProviderImpl<DB> {
     type HistorySP<'a> = StateProviderImplHistory<'a,<DB as DatabaseGAT<'a>>::TX> where Self: 'a;
@@ -24,17 +33,7 @@ impl<DB: Database> StateProviderFactory for ProviderImpl<DB> {
 
     fn history_by_block_number(&self, block_number: BlockNumber) -> Result<Self::HistorySP<'_>> {
         let tx = self.db.tx()?;
-        // get block hash
-        let block_hash = tx
-            .get::<tables::CanonicalHeaders>(block_number)?
-            .ok_or(Error::BlockNumber { block_number })?;
-
-        // get transition id
-        let block_num_hash = (block_number, block_hash);
-        let transition = tx
-            .get::<tables::BlockTransitionIndex>(block_num_hash.into())?
-            .ok_or(Error::BlockTransition { block_number, block_hash })?;
-
+        let transition = history_transition(&tx, block_number)?;
         Ok(StateProviderImplHistory::new(tx, transition))
     }
 
@@ -43,6 +42,7 @@ impl<DB: Database> StateProviderFactory for ProviderImpl<DB> {
         // get block number
         let block_number =
             tx.get::<tables::HeaderNumbers>(block_hash)?.ok_or(Error::BlockHash { block_hash })?;
+        ensure_history_available(&tx, block_number)?;
 
         // get transition id
         let block_num_hash = (block_number, block_hash);
@@ -50,7 +50,186 @@ impl<DB: Database> StateProviderFactory for ProviderImpl<DB> {
             .get::<tables::BlockTransitionIndex>(block_num_hash.into())?
             .ok_or(Error::BlockTransition { block_number, block_hash })?;
 
-        Ok(StateProviderImplHistory::new(tx, transition))
+        // the changes of the block are applied up to and including its transition
+        Ok(StateProviderImplHistory::new(tx, transition + 1))
+    }
+}
+
+/// Returns the first transition after the canonical block, whose changesets lead back to the
+/// state of the block.
+fn history_transition<'a, TX: DbTx<'a>>(
+    tx: &TX,
+    block_number: BlockNumber,
+) -> Result<TransitionId> {
+    ensure_history_available(tx, block_number)?;
+    // get block hash
+    let block_hash = tx
+        .get::<tables::CanonicalHeaders>(block_number)?
+        .ok_or(Error::BlockNumber { block_number })?;
+
+    // get transition id
+    let block_num_hash = (block_number, block_hash);
+    let transition = tx
+        .get::<tables::BlockTransitionIndex>(block_num_hash.into())?
+        .ok_or(Error::BlockTransition { block_number, block_hash })?;
+
+    // the changes of the block are applied up to and including its transition
+    Ok(transition + 1)
+}
+
+/// Returns an error if the changesets needed for the state of `block_number` were pruned.
+fn ensure_history_available<'a, TX: DbTx<'a>>(tx: &TX, block_number: BlockNumber) -> Result<()> {
+    let pruned_to = match tx.get::<tables::Config>(tables::HISTORY_PRUNED_TO_KEY.to_vec())? {
+        Some(pruned_to) => u64::from_be_bytes(
+            pruned_to.try_into().map_err(|_| reth_interfaces::db::Error::DecodeError)?,
+        ),
+        None => return Ok(()),
+    };
+    if block_number < pruned_to {
+        return Err(Error::HistoryPruned { block_number, oldest: pruned_to }.into())
+    }
+    Ok(())
+}
+
+impl<DB: Database> AccountRangeProvider for ProviderImpl<DB> {
+    fn account_range(&self, start: H256, limit: usize) -> Result<AccountRange> {
+        let tx = self.db.tx()?;
+        let mut cursor = tx.cursor::<tables::HashedAccount>()?;
+        let mut walker = cursor.walk(start)?;
+
+        let mut accounts = Vec::new();
+        while accounts.len() < limit {
+            match walker.next() {
+                Some(entry) => accounts.push(entry?),
+                None => break,
+            }
+        }
+        let next = walker.next().transpose()?.map(|(hashed_address, _)| hashed_address);
+
+        Ok(AccountRange { accounts, next })
+    }
+}
+
+impl<DB: Database> StorageRangeProvider for ProviderImpl<DB> {
+    fn storage_range(
+        &self,
+        hashed_address: H256,
+        start: H256,
+        limit: usize,
+    ) -> Result<StorageRange> {
+        let tx = self.db.tx()?;
+        let mut cursor = tx.cursor_dup::<tables::HashedStorage>()?;
+        let mut walker = cursor.walk_dup(hashed_address, Some(start))?;
+
+        let mut slots = Vec::new();
+        while slots.len() < limit {
+            match walker.next() {
+                Some(entry) => {
+                    let (_, entry) = entry?;
+                    slots.push((entry.key, entry.value));
+                }
+                None => break,
+            }
+        }
+        let next = walker.next().transpose()?.map(|(_, entry)| entry.key);
+
+        Ok(StorageRange { slots, next })
+    }
+}
+
+impl<DB: Database> HistoricalRangeProvider for ProviderImpl<DB> {
+    fn account_range_at(
+        &self,
+        block_number: BlockNumber,
+        start: H256,
+        limit: usize,
+    ) -> Result<AccountRange> {
+        let tx = self.db.tx()?;
+        let transition = history_transition(&tx, block_number)?;
+
+        // the value at the block of every account that changed after it
+        let mut changed = BTreeMap::new();
+        for entry in tx.cursor::<tables::AccountChangeSet>()?.walk(transition)? {
+            let (_, before) = entry?;
+            changed.entry(keccak256(before.address)).or_insert(before.info);
+        }
+
+        let mut cursor = tx.cursor::<tables::HashedAccount>()?;
+        let latest = cursor.walk(start)?.map(|entry| entry.map_err(Into::into));
+        let (accounts, next) = merge_history(latest, &changed, start, limit)?;
+        Ok(AccountRange { accounts, next })
+    }
+
+    fn storage_range_at(
+        &self,
+        block_number: BlockNumber,
+        hashed_address: H256,
+        start: H256,
+        limit: usize,
+    ) -> Result<StorageRange> {
+        let tx = self.db.tx()?;
+        let transition = history_transition(&tx, block_number)?;
+
+        // the value at the block of every slot of the account that changed after it
+        let mut changed = BTreeMap::new();
+        let mut changesets = tx.cursor::<tables::StorageChangeSet>()?;
+        for entry in changesets.walk((transition, Address::zero()).into())? {
+            let (key, before) = entry?;
+            if keccak256(key.address()) == hashed_address {
+                let value = (before.value != U256::zero()).then_some(before.value);
+                changed.entry(keccak256(before.key)).or_insert(value);
+            }
+        }
+
+        let mut cursor = tx.cursor_dup::<tables::HashedStorage>()?;
+        let latest = cursor
+            .walk_dup(hashed_address, Some(start))?
+            .map(|entry| entry.map(|(_, entry)| (entry.key, entry.value)).map_err(Into::into));
+        let (slots, next) = merge_history(latest, &changed, start, limit)?;
+        Ok(StorageRange { slots, next })
+    }
+}
+
+/// Merges the latest entries from `start` on with the entries that changed after a block, which
+/// map to their value at the block or to `None` if they did not exist yet.
+///
+/// Returns at most `limit` entries of the state at the block, ordered by key, and the key of the
+/// next entry.
+fn merge_history<K, V>(
+    latest: impl Iterator<Item = Result<(K, V)>>,
+    changed: &BTreeMap<K, Option<V>>,
+    start: K,
+    limit: usize,
+) -> Result<(Vec<(K, V)>, Option<K>)>
+where
+    K: Ord + Copy,
+    V: Copy,
+{
+    let mut latest = latest
+        .filter(|entry| !matches!(entry, Ok((key, _)) if changed.contains_key(key)))
+        .peekable();
+    let mut historical = changed
+        .range(start..)
+        .filter_map(|(key, value)| value.map(|value| (*key, value)))
+        .peekable();
+
+    let mut entries = Vec::new();
+    loop {
+        let from_latest = match (latest.peek(), historical.peek()) {
+            (Some(Ok((key, _))), Some((changed, _))) => key < changed,
+            (Some(_), _) => true,
+            (None, Some(_)) => false,
+            (None, None) => return Ok((entries, None)),
+        };
+        let entry = if from_latest {
+            latest.next().expect("peeked entry")?
+        } else {
+            historical.next().expect("peeked entry")
+        };
+        if entries.len() == limit {
+            return Ok((entries, Some(entry.0)))
+        }
+        entries.push(entry);
     }
 }
 
@@ -58,14 +237,14 @@ impl<DB: Database> StateProviderFactory for ProviderImpl<DB> {
 pub struct StateProviderImplHistory<'a, TX: DbTx<'a>> {
     /// Database transaction
     tx: TX,
-    /// Transition is main indexer of account and storage changes
+    /// The first transition that is not applied to the state.
     transition: TransitionId,
     /// Phantom lifetime `'a`
     _phantom: PhantomData<&'a TX>,
 }
 
 impl<'a, TX: DbTx<'a>> StateProviderImplHistory<'a, TX> {
-    /// Create new StateProvider from history transaction number
+    /// Create new StateProvider for the state before the given transition.
     pub fn new(tx: TX, transition: TransitionId) -> Self {
         Self { tx, transition, _phantom: PhantomData {} }
     }
@@ -91,65 +270,128 @@ impl<'a, TX: DbTx<'a>> StateProvider for StateProviderImplHistory<'a, TX> {
         StateProviderImplRefHistory::new(&self.tx, self.transition).block_hash(number)
     }
 }
-/// State provider with given hash
+
+/// State provider for the state before a given transition.
+///
+/// The value of an account or storage slot is the value before its first change at or after the
+/// transition, which is recorded in the changesets. Values that did not change since are read
+/// from the latest state. The first change is looked up in the history indices, only the
+/// changesets of the blocks that are not indexed yet are walked.
 ///
 /// It will access:
 /// [tables::AccountHistory]
-/// [tables::Bytecodes]
 /// [tables::StorageHistory]
 /// [tables::AccountChangeSet]
 /// [tables::StorageChangeSet]
+/// [tables::PlainAccountState]
+/// [tables::PlainStorageState]
+/// [tables::Bytecodes]
 pub struct StateProviderImplRefHistory<'a, 'b, TX: DbTx<'a>> {
     /// Transaction
     tx: &'b TX,
-    /// Transition is main indexer of account and storage changes
+    /// The first transition that is not applied to the state.
     transition: TransitionId,
     /// Phantom lifetime `'a`
     _phantom: PhantomData<&'a TX>,
 }
 
 impl<'a, 'b, TX: DbTx<'a>> StateProviderImplRefHistory<'a, 'b, TX> {
-    /// Create new StateProvider from history transaction number
+    /// Create new StateProvider for the state before the given transition.
     pub fn new(tx: &'b TX, transition: TransitionId) -> Self {
         Self { tx, transition, _phantom: PhantomData {} }
     }
+
+    /// Returns the first transition at or after the transition of the provider that changed the
+    /// key according to the history index `T`, and the first transition that is not indexed.
+    fn first_indexed_change<T, K>(&self, key: K) -> Result<(Option<TransitionId>, TransitionId)>
+    where
+        T: Table<Key = ShardedKey<K>, Value = TransitionList>,
+        K: Clone + PartialEq,
+    {
+        let indexed = first_unindexed_transition(self.tx)?;
+        if self.transition >= indexed {
+            return Ok((None, indexed))
+        }
+
+        // the shards are keyed by the last transition they can contain
+        let mut cursor = self.tx.cursor::<T>()?;
+        for entry in cursor.walk(ShardedKey::new(key.clone(), self.transition))? {
+            let (sharded_key, list) = entry?;
+            if sharded_key.key != key {
+                break
+            }
+            let change = list
+                .iter(0)
+                .map(|transition| transition as u64)
+                .find(|transition| *transition >= self.transition);
+            if change.is_some() {
+                return Ok((change, indexed))
+            }
+        }
+        Ok((None, indexed))
+    }
+}
+
+/// Returns the first transition whose changes are not in the history indices yet.
+fn first_unindexed_transition<'a, TX: DbTx<'a>>(tx: &TX) -> Result<TransitionId> {
+    let progress = tx.get::<tables::SyncStage>(HISTORY_INDEX_STAGE.as_bytes().to_vec())?;
+    let block_number = match progress {
+        Some(block_number) if block_number > 0 => block_number,
+        _ => return Ok(0),
+    };
+    let block_hash = tx
+        .get::<tables::CanonicalHeaders>(block_number)?
+        .ok_or(Error::BlockNumber { block_number })?;
+    let transition = tx
+        .get::<tables::BlockTransitionIndex>((block_number, block_hash).into())?
+        .ok_or(Error::BlockTransition { block_number, block_hash })?;
+    Ok(transition + 1)
 }
 
 impl<'a, 'b, TX: DbTx<'a>> AccountProvider for StateProviderImplRefHistory<'a, 'b, TX> {
     /// Get basic account information.
-    fn basic_account(&self, _address: Address) -> Result<Option<Account>> {
-        // TODO add when AccountHistory is defined
-        Ok(None)
+    fn basic_account(&self, address: Address) -> Result<Option<Account>> {
+        let (change, indexed) = self.first_indexed_change::<tables::AccountHistory, _>(address)?;
+        let mut cursor = self.tx.cursor_dup::<tables::AccountChangeSet>()?;
+        if let Some(change) = change {
+            let before = cursor
+                .seek_by_key_subkey(change, address)?
+                .filter(|before| before.address == address);
+            return Ok(before.ok_or(Error::ChangesetMissing { transition: change })?.info)
+        }
+
+        // the changes after the indexed transitions
+        for entry in cursor.walk(self.transition.max(indexed))? {
+            let (_, before) = entry?;
+            if before.address == address {
+                return Ok(before.info)
+            }
+        }
+        StateProviderImplRefLatest::new(self.tx).basic_account(address)
     }
 }
 
 impl<'a, 'b, TX: DbTx<'a>> StateProvider for StateProviderImplRefHistory<'a, 'b, TX> {
     /// Get storage.
     fn storage(&self, account: Address, storage_key: StorageKey) -> Result<Option<StorageValue>> {
-        // TODO when StorageHistory is defined
-        let transaction_number =
-            self.tx.get::<tables::StorageHistory>(Vec::new())?.map(|_integer_list|
-            // TODO select integer that is one less from transaction_number <- // TODO: (rkrasiuk) not sure this comment is still relevant
-            self.transition);
-
-        if transaction_number.is_none() {
-            return Ok(None)
-        }
-        let num = transaction_number.unwrap();
+        let key = storage_history_key(account, storage_key);
+        let (change, indexed) = self.first_indexed_change::<tables::StorageHistory, _>(key)?;
         let mut cursor = self.tx.cursor_dup::<tables::StorageChangeSet>()?;
+        if let Some(change) = change {
+            let before = cursor
+                .seek_by_key_subkey((change, account).into(), storage_key)?
+                .filter(|before| before.key == storage_key);
+            return Ok(Some(before.ok_or(Error::ChangesetMissing { transition: change })?.value))
+        }
 
-        if let Some((_, entry)) = cursor.seek_exact((num, account).into())? {
-            if entry.key == storage_key {
-                return Ok(Some(entry.value))
-            }
-
-            if let Some((_, entry)) = cursor.seek(storage_key)? {
-                if entry.key == storage_key {
-                    return Ok(Some(entry.value))
-                }
+        // the changes after the indexed transitions
+        for entry in cursor.walk((self.transition.max(indexed), Address::zero()).into())? {
+            let (key, before) = entry?;
+            if key.address() == account && before.key == storage_key {
+                return Ok(Some(before.value))
             }
         }
-        Ok(None)
+        StateProviderImplRefLatest::new(self.tx).storage(account, storage_key)
     }
 
     /// Get account code by its hash
@@ -0,0 +1,131 @@
+//! Reads of flat files outside of the database that don't block the async runtime.
+//!
+//! Cold reads of large files can take milliseconds, so they are performed on a small pool of
+//! dedicated threads instead of tokio's worker threads or shared blocking pool. The reads are
+//! positional (`pread` on unix, `seek_read` on windows), so a single file handle can be shared by
+//! all concurrent reads. Other platforms fall back to seeking a cloned handle.
+
+use std::{
+    fs::File,
+    io,
+    sync::{Arc, Mutex},
+    thread,
+};
+use tokio::sync::{mpsc, oneshot};
+use tracing::trace;
+
+/// The default number of reader threads.
+pub const DEFAULT_READER_THREADS: usize = 4;
+
+/// A single read request.
+struct ReadJob {
+    file: Arc<File>,
+    offset: u64,
+    len: usize,
+    tx: oneshot::Sender<io::Result<Vec<u8>>>,
+}
+
+/// Handle to a pool of threads that read byte ranges of files.
+///
+/// The threads exit once all handles are dropped.
+#[derive(Debug, Clone)]
+pub struct FileReader {
+    jobs: mpsc::UnboundedSender<ReadJob>,
+}
+
+// === impl FileReader ===
+
+impl FileReader {
+    /// Spawns the given number of reader threads.
+    pub fn new(threads: usize) -> io::Result<Self> {
+        let (jobs, rx) = mpsc::unbounded_channel::<ReadJob>();
+        let rx = Arc::new(Mutex::new(rx));
+        for idx in 0..threads.max(1) {
+            let rx = Arc::clone(&rx);
+            thread::Builder::new().name(format!("file-reader-{idx}")).spawn(move || loop {
+                let job = match rx.lock().expect("not poisoned").blocking_recv() {
+                    Some(job) => job,
+                    None => return,
+                };
+                let mut buf = vec![0u8; job.len];
+                let res = read_exact_at(&job.file, &mut buf, job.offset).map(|_| buf);
+                trace!(target: "provider::file_reader", offset = job.offset, len = job.len, ok = res.is_ok(), "Read file range");
+                // the caller may have stopped waiting
+                let _ = job.tx.send(res);
+            })?;
+        }
+        Ok(Self { jobs })
+    }
+
+    /// Reads `len` bytes of the file starting at `offset`.
+    ///
+    /// Fails with [io::ErrorKind::UnexpectedEof] if the file ends before.
+    pub async fn read_at(&self, file: Arc<File>, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let (tx, rx) = oneshot::channel();
+        self.jobs.send(ReadJob { file, offset, len, tx }).map_err(|_| reader_closed())?;
+        rx.await.map_err(|_| reader_closed())?
+    }
+}
+
+fn reader_closed() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "file reader threads exited")
+}
+
+#[cfg(unix)]
+pub(crate) fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+pub(crate) fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill buffer"))
+            }
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    // a cloned handle shares the cursor, so it must not be used concurrently
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().expect("not poisoned");
+    let mut file = file.try_clone()?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn reads_ranges() {
+        let mut tmp = tempfile::tempfile().unwrap();
+        let data = (0..=u8::MAX).cycle().take(10_000).collect::<Vec<_>>();
+        tmp.write_all(&data).unwrap();
+        let file = Arc::new(tmp);
+
+        let reader = FileReader::new(2).unwrap();
+        let reads = (0..10u64).map(|idx| reader.read_at(Arc::clone(&file), idx * 1000, 1000));
+        let chunks = futures::future::join_all(reads).await;
+        for (idx, chunk) in chunks.into_iter().enumerate() {
+            assert_eq!(chunk.unwrap(), data[idx * 1000..(idx + 1) * 1000]);
+        }
+
+        let err = reader.read_at(file, 9_500, 1000).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
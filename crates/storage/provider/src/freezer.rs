@@ -0,0 +1,375 @@
+//! Cold storage of finalized blocks in append-only flat files.
+//!
+//! The freezer keeps the headers, bodies and receipts of old canonical blocks outside of the
+//! database, which keeps the database small and its pages hot. Every [`Segment`] is a pair of
+//! files:
+//!
+//! - `<segment>.dat`: the snappy compressed entries, back to back.
+//! - `<segment>.idx`: the end offset of every entry in the data file, as little endian `u64`.
+//!
+//! Entry `n` of every segment belongs to block `n`, so the location of an entry is found with a
+//! single read of the index file. Neither file has a header, which allows to memory map them as
+//! well as to read them with positional reads.
+//!
+//! An entry is a list of items, each prefixed by its length as little endian `u32`. The items are
+//! table values in their database encoding, so moving data out of the database doesn't re-encode
+//! it:
+//!
+//! - [`Segment::Headers`]: the [`Header`].
+//! - [`Segment::Bodies`]: the [`StoredBlockOmmers`], followed by the [`TransactionSigned`]s.
+//! - [`Segment::Receipts`]: the [`Receipt`]s that weren't pruned, each prefixed by the number of
+//!   its transaction as big endian `u64`.
+
+use bytes::{Buf, BufMut};
+use reth_db::{
+    models::StoredBlockOmmers,
+    table::{Compress, Decompress},
+};
+use reth_primitives::{BlockNumber, Header, Receipt, TransactionSigned, TxNumber};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+use tracing::trace;
+
+mod segment;
+
+use segment::SegmentFile;
+
+/// The default number of most recent blocks that stay in the database.
+///
+/// Older blocks are considered final and can be moved to the freezer.
+pub const DEFAULT_FREEZER_DISTANCE: u64 = 90_000;
+
+/// A freezer error.
+#[derive(Debug, thiserror::Error)]
+pub enum FreezerError {
+    /// Failed to read or write the files.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Failed to compress an entry.
+    #[error(transparent)]
+    Compression(#[from] snap::Error),
+    /// An entry can't be decoded.
+    #[error("The entry of block #{block_number} in the {segment} segment is corrupted")]
+    Corrupted {
+        /// The segment of the entry.
+        segment: Segment,
+        /// The block of the entry.
+        block_number: BlockNumber,
+    },
+    /// The blocks are appended out of order.
+    #[error("Can not freeze block #{got}, the next block to freeze is #{expected}")]
+    OutOfOrder {
+        /// The block that was appended.
+        got: BlockNumber,
+        /// The next block to freeze.
+        expected: BlockNumber,
+    },
+}
+
+/// The kinds of data stored in the freezer, each in its own pair of files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// Block headers.
+    Headers = 0,
+    /// Ommers and transactions.
+    Bodies = 1,
+    /// Transaction receipts.
+    Receipts = 2,
+}
+
+// === impl Segment ===
+
+impl Segment {
+    /// All segments.
+    pub const ALL: [Segment; 3] = [Segment::Headers, Segment::Bodies, Segment::Receipts];
+
+    /// Returns the name of the segment's files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Segment::Headers => "headers",
+            Segment::Bodies => "bodies",
+            Segment::Receipts => "receipts",
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A block as it is stored in the freezer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrozenBlock {
+    /// The header of the block.
+    pub header: Header,
+    /// The ommers of the block.
+    pub ommers: Vec<Header>,
+    /// The transactions of the block.
+    pub transactions: Vec<TransactionSigned>,
+    /// The receipts that weren't pruned and the numbers of their transactions.
+    pub receipts: Vec<(TxNumber, Receipt)>,
+}
+
+/// Append-only storage of the canonical blocks from genesis up to [`Freezer::blocks`].
+///
+/// Reads can happen concurrently with appending blocks.
+#[derive(Debug)]
+pub struct Freezer {
+    /// The directory of the segment files.
+    dir: PathBuf,
+    /// The files of each segment, indexed by [`Segment`].
+    segments: RwLock<Vec<SegmentFile>>,
+}
+
+// === impl Freezer ===
+
+impl Freezer {
+    /// Opens the freezer in the given directory, creating it if it doesn't exist.
+    ///
+    /// Blocks that were only partially appended before an unclean shutdown are removed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, FreezerError> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let mut segments = Segment::ALL
+            .iter()
+            .map(|segment| SegmentFile::open(&dir, segment.name()))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let blocks = segments.iter().map(SegmentFile::len).min().unwrap_or_default();
+        for segment in segments.iter_mut() {
+            segment.truncate(blocks)?;
+        }
+        trace!(target: "provider::freezer", ?dir, blocks, "Opened freezer");
+        Ok(Self { dir, segments: RwLock::new(segments) })
+    }
+
+    /// Returns the directory of the segment files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the number of frozen blocks, which are the blocks `0..blocks`.
+    pub fn blocks(&self) -> u64 {
+        self.segments.read().expect("not poisoned")[Segment::Headers as usize].len()
+    }
+
+    /// Returns `true` if the block is frozen.
+    pub fn contains(&self, block_number: BlockNumber) -> bool {
+        block_number < self.blocks()
+    }
+
+    /// Appends the next block.
+    ///
+    /// The block is only durable after [`Freezer::sync`].
+    pub fn append(
+        &self,
+        block_number: BlockNumber,
+        block: FrozenBlock,
+    ) -> Result<(), FreezerError> {
+        let mut segments = self.segments.write().expect("not poisoned");
+        let expected = segments[Segment::Headers as usize].len();
+        if block_number != expected {
+            return Err(FreezerError::OutOfOrder { got: block_number, expected })
+        }
+
+        let FrozenBlock { header, ommers, transactions, receipts } = block;
+        let headers = encode_entry([header.compress().as_ref().to_vec()])?;
+        let bodies = encode_entry(
+            std::iter::once(StoredBlockOmmers { ommers }.compress().as_ref().to_vec()).chain(
+                transactions
+                    .into_iter()
+                    .map(|transaction| transaction.compress().as_ref().to_vec()),
+            ),
+        )?;
+        let receipts = encode_entry(receipts.into_iter().map(|(tx_number, receipt)| {
+            [tx_number.to_be_bytes().as_slice(), receipt.compress().as_ref()].concat()
+        }))?;
+
+        for (segment, entry) in Segment::ALL.iter().zip([headers, bodies, receipts]) {
+            if let Err(err) = segments[*segment as usize].append(&entry) {
+                // keep the segments aligned
+                for segment in segments.iter_mut() {
+                    let _ = segment.truncate(block_number);
+                }
+                return Err(err.into())
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the appended blocks to disk.
+    pub fn sync(&self) -> Result<(), FreezerError> {
+        for segment in self.segments.read().expect("not poisoned").iter() {
+            segment.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the blocks from `blocks` onwards, e.g. the blocks that were appended but never
+    /// removed from the database.
+    pub fn truncate(&self, blocks: u64) -> Result<(), FreezerError> {
+        for segment in self.segments.write().expect("not poisoned").iter_mut() {
+            segment.truncate(blocks)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the header of the block, or `None` if it isn't frozen.
+    pub fn header(&self, block_number: BlockNumber) -> Result<Option<Header>, FreezerError> {
+        let Some(mut items) = self.items(Segment::Headers, block_number)? else { return Ok(None) };
+        let header = items.pop().ok_or(corrupted(Segment::Headers, block_number))?;
+        Ok(Some(decompress(Segment::Headers, block_number, header)?))
+    }
+
+    /// Returns the transactions and ommers of the block, or `None` if it isn't frozen.
+    pub fn body(
+        &self,
+        block_number: BlockNumber,
+    ) -> Result<Option<(Vec<TransactionSigned>, Vec<Header>)>, FreezerError> {
+        let Some(items) = self.items(Segment::Bodies, block_number)? else { return Ok(None) };
+        let mut items = items.into_iter();
+        let ommers = items.next().ok_or(corrupted(Segment::Bodies, block_number))?;
+        let StoredBlockOmmers { ommers } = decompress(Segment::Bodies, block_number, ommers)?;
+        let transactions = items
+            .map(|transaction| decompress(Segment::Bodies, block_number, transaction))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some((transactions, ommers)))
+    }
+
+    /// Returns the receipts of the block that weren't pruned and the numbers of their
+    /// transactions, or `None` if the block isn't frozen.
+    pub fn receipts(
+        &self,
+        block_number: BlockNumber,
+    ) -> Result<Option<Vec<(TxNumber, Receipt)>>, FreezerError> {
+        let Some(items) = self.items(Segment::Receipts, block_number)? else { return Ok(None) };
+        let receipts = items
+            .into_iter()
+            .map(|mut item| {
+                if item.len() < 8 {
+                    return Err(corrupted(Segment::Receipts, block_number))
+                }
+                let receipt = item.split_off(8);
+                let tx_number = u64::from_be_bytes(item.try_into().expect("8 bytes"));
+                Ok((tx_number, decompress(Segment::Receipts, block_number, receipt)?))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(receipts))
+    }
+
+    /// Reads and decodes the items of an entry.
+    fn items(
+        &self,
+        segment: Segment,
+        block_number: BlockNumber,
+    ) -> Result<Option<Vec<Vec<u8>>>, FreezerError> {
+        let entry =
+            self.segments.read().expect("not poisoned")[segment as usize].get(block_number)?;
+        entry.map(|entry| decode_entry(segment, block_number, &entry)).transpose()
+    }
+}
+
+/// Frames and compresses the items of an entry.
+fn encode_entry(items: impl IntoIterator<Item = Vec<u8>>) -> Result<Vec<u8>, FreezerError> {
+    let mut raw = Vec::new();
+    for item in items {
+        raw.put_u32_le(item.len() as u32);
+        raw.put_slice(&item);
+    }
+    Ok(snap::raw::Encoder::new().compress_vec(&raw)?)
+}
+
+/// Decompresses an entry and splits it into its items.
+fn decode_entry(
+    segment: Segment,
+    block_number: BlockNumber,
+    entry: &[u8],
+) -> Result<Vec<Vec<u8>>, FreezerError> {
+    let raw = snap::raw::Decoder::new()
+        .decompress_vec(entry)
+        .map_err(|_| corrupted(segment, block_number))?;
+    let mut buf = raw.as_slice();
+    let mut items = Vec::new();
+    while buf.has_remaining() {
+        if buf.remaining() < 4 {
+            return Err(corrupted(segment, block_number))
+        }
+        let len = buf.get_u32_le() as usize;
+        if buf.remaining() < len {
+            return Err(corrupted(segment, block_number))
+        }
+        items.push(buf[..len].to_vec());
+        buf.advance(len);
+    }
+    Ok(items)
+}
+
+fn decompress<T: Decompress>(
+    segment: Segment,
+    block_number: BlockNumber,
+    item: Vec<u8>,
+) -> Result<T, FreezerError> {
+    T::decompress(item).map_err(|_| corrupted(segment, block_number))
+}
+
+fn corrupted(segment: Segment, block_number: BlockNumber) -> FreezerError {
+    FreezerError::Corrupted { segment, block_number }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_interfaces::test_utils::generators::random_block;
+    use reth_primitives::Log;
+
+    fn frozen_block(number: BlockNumber) -> FrozenBlock {
+        let block = random_block(number, None, Some(2));
+        let receipts =
+            vec![(number * 2 + 1, Receipt { logs: vec![Log::default()], ..Default::default() })];
+        FrozenBlock {
+            header: block.header.clone().unseal(),
+            ommers: block.ommers.into_iter().map(|ommer| ommer.unseal()).collect(),
+            transactions: block.body,
+            receipts,
+        }
+    }
+
+    #[test]
+    fn freeze_and_read_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let freezer = Freezer::open(dir.path()).unwrap();
+        let blocks = (0..3).map(frozen_block).collect::<Vec<_>>();
+        for (number, block) in blocks.iter().enumerate() {
+            freezer.append(number as u64, block.clone()).unwrap();
+        }
+        assert_eq!(
+            freezer.append(5, frozen_block(5)).unwrap_err().to_string(),
+            "Can not freeze block #5, the next block to freeze is #3"
+        );
+        freezer.sync().unwrap();
+        drop(freezer);
+
+        let freezer = Freezer::open(dir.path()).unwrap();
+        assert_eq!(freezer.blocks(), 3);
+        for (number, block) in blocks.iter().enumerate() {
+            let number = number as u64;
+            assert_eq!(freezer.header(number).unwrap().as_ref(), Some(&block.header));
+            assert_eq!(
+                freezer.body(number).unwrap(),
+                Some((block.transactions.clone(), block.ommers.clone()))
+            );
+            assert_eq!(freezer.receipts(number).unwrap().as_ref(), Some(&block.receipts));
+        }
+        assert_eq!(freezer.header(3).unwrap(), None);
+
+        freezer.truncate(1).unwrap();
+        assert!(freezer.contains(0));
+        assert!(!freezer.contains(1));
+        assert_eq!(freezer.body(1).unwrap(), None);
+    }
+}
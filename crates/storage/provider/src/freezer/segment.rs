@@ -0,0 +1,159 @@
+//! The pair of files that stores the entries of a single [`Segment`](super::Segment).
+
+use crate::file_reader::read_exact_at;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// The size of an entry in the index file.
+const OFFSET_SIZE: u64 = 8;
+
+/// An append-only list of entries.
+///
+/// The entries are stored back to back in the data file, the index file holds the end offset of
+/// every entry in the data file as little endian `u64`.
+#[derive(Debug)]
+pub(crate) struct SegmentFile {
+    /// The entries.
+    data: File,
+    /// The end offsets of the entries.
+    index: File,
+    /// The number of entries.
+    len: u64,
+    /// The end of the last entry in the data file.
+    data_len: u64,
+}
+
+// === impl SegmentFile ===
+
+impl SegmentFile {
+    /// Opens or creates the files `<name>.dat` and `<name>.idx` in `dir`.
+    ///
+    /// The files are truncated to the last complete entry, which drops the leftovers of an append
+    /// that was interrupted before the files were synced.
+    pub(crate) fn open(dir: &Path, name: &str) -> io::Result<Self> {
+        let open = |ext: &str| {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(dir.join(format!("{name}.{ext}")))
+        };
+        let (data, index) = (open("dat")?, open("idx")?);
+
+        let available = data.metadata()?.len();
+        let mut len = index.metadata()?.len() / OFFSET_SIZE;
+        while len > 0 && read_offset(&index, len - 1)? > available {
+            len -= 1;
+        }
+
+        let mut segment = Self { data, index, len, data_len: 0 };
+        segment.truncate(len)?;
+        Ok(segment)
+    }
+
+    /// Returns the number of entries.
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Appends an entry.
+    pub(crate) fn append(&mut self, entry: &[u8]) -> io::Result<()> {
+        let end = self.data_len + entry.len() as u64;
+        self.data.seek(SeekFrom::Start(self.data_len))?;
+        self.data.write_all(entry)?;
+        self.index.seek(SeekFrom::Start(self.len * OFFSET_SIZE))?;
+        self.index.write_all(&end.to_le_bytes())?;
+        self.len += 1;
+        self.data_len = end;
+        Ok(())
+    }
+
+    /// Returns the entry at `idx`, or `None` if there are fewer entries.
+    pub(crate) fn get(&self, idx: u64) -> io::Result<Option<Vec<u8>>> {
+        if idx >= self.len {
+            return Ok(None)
+        }
+        let start = if idx == 0 { 0 } else { read_offset(&self.index, idx - 1)? };
+        let end = read_offset(&self.index, idx)?;
+        let mut entry = vec![0u8; end.saturating_sub(start) as usize];
+        read_exact_at(&self.data, &mut entry, start)?;
+        Ok(Some(entry))
+    }
+
+    /// Removes the entries from `len` onwards.
+    pub(crate) fn truncate(&mut self, len: u64) -> io::Result<()> {
+        let len = len.min(self.len);
+        let data_len = if len == 0 { 0 } else { read_offset(&self.index, len - 1)? };
+        self.index.set_len(len * OFFSET_SIZE)?;
+        self.data.set_len(data_len)?;
+        self.len = len;
+        self.data_len = data_len;
+        Ok(())
+    }
+
+    /// Flushes the appended entries to disk.
+    pub(crate) fn sync(&self) -> io::Result<()> {
+        // The entries are synced first, so a synced offset never points past the data.
+        self.data.sync_data()?;
+        self.index.sync_data()
+    }
+}
+
+/// Reads the end offset of the entry at `idx`.
+fn read_offset(index: &File, idx: u64) -> io::Result<u64> {
+    let mut buf = [0u8; OFFSET_SIZE as usize];
+    read_exact_at(index, &mut buf, idx * OFFSET_SIZE)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_and_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut segment = SegmentFile::open(dir.path(), "test").unwrap();
+        segment.append(b"first").unwrap();
+        segment.append(b"").unwrap();
+        segment.append(b"third").unwrap();
+        segment.sync().unwrap();
+        drop(segment);
+
+        let mut segment = SegmentFile::open(dir.path(), "test").unwrap();
+        assert_eq!(segment.len(), 3);
+        assert_eq!(segment.get(0).unwrap(), Some(b"first".to_vec()));
+        assert_eq!(segment.get(1).unwrap(), Some(Vec::new()));
+        assert_eq!(segment.get(2).unwrap(), Some(b"third".to_vec()));
+        assert_eq!(segment.get(3).unwrap(), None);
+
+        segment.truncate(1).unwrap();
+        segment.append(b"second").unwrap();
+        assert_eq!(segment.get(1).unwrap(), Some(b"second".to_vec()));
+        assert_eq!(segment.get(2).unwrap(), None);
+    }
+
+    #[test]
+    fn drops_incomplete_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut segment = SegmentFile::open(dir.path(), "test").unwrap();
+        segment.append(b"first").unwrap();
+        segment.append(b"second").unwrap();
+        drop(segment);
+
+        // the offset of the second entry was written, but not all of its data
+        let data = OpenOptions::new().write(true).open(dir.path().join("test.dat")).unwrap();
+        data.set_len(8).unwrap();
+        // and half of a third offset
+        let mut index = OpenOptions::new().append(true).open(dir.path().join("test.idx")).unwrap();
+        index.write_all(&[1, 2, 3]).unwrap();
+
+        let segment = SegmentFile::open(dir.path(), "test").unwrap();
+        assert_eq!(segment.len(), 1);
+        assert_eq!(segment.get(0).unwrap(), Some(b"first".to_vec()));
+        assert_eq!(std::fs::metadata(dir.path().join("test.dat")).unwrap().len(), 5);
+    }
+}
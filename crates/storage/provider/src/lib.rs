@@ -9,17 +9,31 @@
 
 mod block;
 
+pub mod chain;
 pub mod db_provider;
+pub mod file_reader;
+pub mod freezer;
 mod state;
 
 #[cfg(any(test, feature = "test-utils"))]
 /// Common test helpers for mocking the Provider.
 pub mod test_utils;
 
-pub use block::{insert_canonical_block, BlockProvider, ChainInfo, HeaderProvider};
+pub use block::{
+    insert_canonical_block, BlockProvider, ChainInfo, HeaderProvider, LogIndexProvider,
+    ReceiptProvider, StageCheckpointProvider, TransactionsProvider,
+};
+pub use chain::{
+    chain_notifications, CanonicalBlock, ChainNotification, ChainNotifications, StateChanges,
+    DEFAULT_CHAIN_NOTIFICATIONS_CAPACITY,
+};
 pub use db_provider::{
-    self as db, ProviderImpl, StateProviderImplHistory, StateProviderImplLatest,
+    self as db, ProviderImpl, ProviderImplRef, StateProviderImplHistory, StateProviderImplLatest,
     StateProviderImplRefHistory, StateProviderImplRefLatest,
 };
 pub use reth_interfaces::provider::Error;
-pub use state::{AccountProvider, StateProvider, StateProviderFactory};
+pub use state::{
+    AccountProof, AccountProvider, AccountRange, AccountRangeProvider, HistoricalRangeProvider,
+    StateProofProvider, StateProvider, StateProviderFactory, StorageProof, StorageRange,
+    StorageRangeProvider, TrieNodeProvider,
+};
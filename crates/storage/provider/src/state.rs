@@ -1,15 +1,18 @@
+use auto_impl::auto_impl;
 use reth_interfaces::Result;
 use reth_primitives::{
     Account, Address, BlockHash, BlockNumber, Bytes, StorageKey, StorageValue, H256, U256,
 };
 
 /// Account provider
+#[auto_impl(&)]
 pub trait AccountProvider: Send + Sync {
     /// Get basic account information.
     fn basic_account(&self, address: Address) -> Result<Option<Account>>;
 }
 
 /// Function needed for executor.
+#[auto_impl(&)]
 pub trait StateProvider: AccountProvider + Send + Sync {
     /// Get storage.
     fn storage(&self, account: Address, storage_key: StorageKey) -> Result<Option<StorageValue>>;
@@ -21,6 +24,131 @@ pub trait StateProvider: AccountProvider + Send + Sync {
     fn block_hash(&self, number: U256) -> Result<Option<H256>>;
 }
 
+/// Provides paginated access to the accounts of the latest hashed state.
+pub trait AccountRangeProvider: Send + Sync {
+    /// Returns at most `limit` accounts whose hashed address is greater than or equal to `start`,
+    /// ordered by hashed address.
+    fn account_range(&self, start: H256, limit: usize) -> Result<AccountRange>;
+}
+
+/// A page of accounts returned by [AccountRangeProvider::account_range].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountRange {
+    /// The accounts of this page and their hashed address.
+    pub accounts: Vec<(H256, Account)>,
+    /// The hashed address of the first account of the next page, `None` if this is the last page.
+    pub next: Option<H256>,
+}
+
+/// Provides paginated access to the storage of the accounts of the latest hashed state.
+pub trait StorageRangeProvider: Send + Sync {
+    /// Returns at most `limit` slots of the account with the hashed address whose hashed key is
+    /// greater than or equal to `start`, ordered by hashed key.
+    fn storage_range(
+        &self,
+        hashed_address: H256,
+        start: H256,
+        limit: usize,
+    ) -> Result<StorageRange>;
+}
+
+/// A page of slots returned by [StorageRangeProvider::storage_range].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageRange {
+    /// The slots of this page by their hashed key.
+    pub slots: Vec<(H256, U256)>,
+    /// The hashed key of the first slot of the next page, `None` if this is the last page.
+    pub next: Option<H256>,
+}
+
+/// Provides paginated access to the hashed accounts and storage of the state after a canonical
+/// block.
+///
+/// The state is the latest hashed state with the changes after the block reverted, so the
+/// changesets after the block must not be pruned.
+pub trait HistoricalRangeProvider: Send + Sync {
+    /// Returns at most `limit` accounts of the state after the block whose hashed address is
+    /// greater than or equal to `start`, ordered by hashed address.
+    fn account_range_at(
+        &self,
+        block_number: BlockNumber,
+        start: H256,
+        limit: usize,
+    ) -> Result<AccountRange>;
+
+    /// Returns at most `limit` slots of the account with the hashed address in the state after the
+    /// block whose hashed key is greater than or equal to `start`, ordered by hashed key.
+    fn storage_range_at(
+        &self,
+        block_number: BlockNumber,
+        hashed_address: H256,
+        start: H256,
+        limit: usize,
+    ) -> Result<StorageRange>;
+}
+
+/// Generates the Merkle proofs of the latest hashed state.
+pub trait StateProofProvider: Send + Sync {
+    /// Returns the proof of the account and the proofs of its slots with the given keys, in the
+    /// order of the keys.
+    ///
+    /// The proofs are against the root of the hashed state, which is the state of the last block
+    /// that was hashed. The proofs of absent accounts and slots prove their absence.
+    fn proof(&self, address: Address, keys: &[H256]) -> Result<AccountProof>;
+}
+
+/// Provides the stored nodes of the tries of the latest hashed state, the branches and extensions
+/// the merkle stage keeps to update the tries.
+///
+/// Leaves are not stored, they are recovered from the hashed state, see [AccountRangeProvider] and
+/// [StorageRangeProvider].
+pub trait TrieNodeProvider: Send + Sync {
+    /// Returns the root of the stored state trie.
+    ///
+    /// Returns `None` if the hashed state is ahead of the stored tries, which don't match the
+    /// hashed state until the merkle stage caught up.
+    fn state_root(&self) -> Result<Option<H256>>;
+
+    /// Returns the encoding of the stored branch or extension of the state trie at the position of
+    /// the nibbles.
+    fn account_trie_node(&self, path: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Returns the encoding of the stored branch or extension of the storage trie of the account
+    /// with the hashed address at the position of the nibbles.
+    fn storage_trie_node(&self, hashed_address: H256, path: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Returns the root of the storage trie of the account with the hashed address.
+    fn storage_root(&self, hashed_address: H256) -> Result<H256>;
+}
+
+/// The proofs of an account and of its slots returned by [StateProofProvider::proof].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountProof {
+    /// The root of the state trie the proofs are against.
+    pub state_root: H256,
+    /// The address of the account.
+    pub address: Address,
+    /// The account, `None` if it doesn't exist.
+    pub account: Option<Account>,
+    /// The root of the storage trie of the account.
+    pub storage_root: H256,
+    /// The encodings of the nodes of the state trie on the path of the account, from the root.
+    pub proof: Vec<Bytes>,
+    /// The proofs of the slots.
+    pub storage_proofs: Vec<StorageProof>,
+}
+
+/// The proof of a slot, part of an [AccountProof].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageProof {
+    /// The plain key of the slot.
+    pub key: H256,
+    /// The value of the slot, zero if it's absent.
+    pub value: U256,
+    /// The encodings of the nodes of the storage trie on the path of the slot, from the root.
+    pub proof: Vec<Bytes>,
+}
+
 /// Light wrapper that creates StateProvider.
 pub trait StateProviderFactory: Send + Sync {
     /// History State provider.
@@ -1,6 +1,12 @@
-use crate::{BlockProvider, ChainInfo, HeaderProvider};
+use crate::{
+    BlockProvider, ChainInfo, HeaderProvider, LogIndexProvider, ReceiptProvider,
+    StageCheckpointProvider, TransactionsProvider,
+};
 use reth_interfaces::Result;
-use reth_primitives::{rpc::BlockId, Block, BlockHash, BlockNumber, Header, H256, U256};
+use reth_primitives::{
+    rpc::BlockId, Address, Block, BlockHash, BlockNumber, Header, Receipt, TxHash, H256, U256,
+};
+use std::ops::RangeInclusive;
 
 /// Supports various api interfaces for testing purposes.
 #[derive(Debug, Clone, Default)]
@@ -44,3 +50,32 @@ impl HeaderProvider for TestApi {
         Ok(None)
     }
 }
+
+impl ReceiptProvider for TestApi {
+    fn receipts_by_block(&self, _id: BlockId) -> Result<Option<Vec<Receipt>>> {
+        Ok(None)
+    }
+}
+
+impl TransactionsProvider for TestApi {
+    fn transaction_block(&self, _hash: TxHash) -> Result<Option<(BlockNumber, usize)>> {
+        Ok(None)
+    }
+}
+
+impl LogIndexProvider for TestApi {
+    fn log_filter_blocks(
+        &self,
+        _range: RangeInclusive<BlockNumber>,
+        _addresses: &[Address],
+        _topics: &[Vec<H256>],
+    ) -> Result<Option<Vec<BlockNumber>>> {
+        Ok(None)
+    }
+}
+
+impl StageCheckpointProvider for TestApi {
+    fn stage_checkpoint(&self, _stage: &str) -> Result<Option<BlockNumber>> {
+        Ok(None)
+    }
+}
@@ -7,16 +7,25 @@
 
 //! reth task management
 
+pub mod shutdown;
+
+mod metrics;
+
+pub use crate::metrics::TaskMetrics;
+
 use futures_util::{Future, FutureExt, Stream};
 use std::{
+    any::Any,
+    panic::AssertUnwindSafe,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 use tokio::{
     runtime::Handle,
     sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
 };
-use tracing::error;
+use tracing::{error, warn};
 use tracing_futures::Instrument;
 
 /// Many reth components require to spawn tasks for long-running jobs. For example `discovery`
@@ -27,19 +36,20 @@ use tracing_futures::Instrument;
 /// runtime. A [`TaskManager`] stores the [`tokio::runtime::Handle`] it is associated with. In this
 /// way it is possible to configure on which runtime a task is executed.
 ///
-/// The main purpose of this type is to be able to monitor if a critical task panicked, for
-/// diagnostic purposes, since tokio task essentially fail silently. Therefore, this type is a
-/// Stream that yields the name of panicked task, See [`TaskExecutor::spawn_critical`]. In order to
-/// execute Tasks use the [`TaskExecutor`] type [`TaskManager::executor`].
+/// The main purpose of this type is to be able to monitor if a critical task panicked, since
+/// tokio task essentially fail silently. Therefore, this type is a Stream that yields a
+/// [`PanickedTaskError`] for every critical task that panicked, which the node treats as fatal,
+/// See [`TaskExecutor::spawn_critical`]. In order to execute Tasks use the [`TaskExecutor`] type
+/// [`TaskManager::executor`].
 pub struct TaskManager {
     /// Handle to the tokio runtime this task manager is associated with.
     ///
     /// See [`Handle`] docs.
     handle: Handle,
     /// Sender half for sending panic signals to this type
-    panicked_tasks_tx: UnboundedSender<String>,
+    panicked_tasks_tx: UnboundedSender<PanickedTaskError>,
     /// Listens for panicked tasks
-    panicked_tasks_rx: UnboundedReceiver<String>,
+    panicked_tasks_rx: UnboundedReceiver<PanickedTaskError>,
 }
 
 // === impl TaskManager ===
@@ -61,17 +71,73 @@ impl TaskManager {
     }
 }
 
-/// A stream that yields the name of panicked tasks.
+/// A stream that yields the panicked critical tasks.
 ///
 /// See [`TaskExecutor::spawn_critical`]
 impl Stream for TaskManager {
-    type Item = String;
+    type Item = PanickedTaskError;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         self.get_mut().panicked_tasks_rx.poll_recv(cx)
     }
 }
 
+/// A critical task panicked.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Critical task `{task_name}` panicked: {}", .error.as_deref().unwrap_or("unknown error"))]
+pub struct PanickedTaskError {
+    /// The name of the task.
+    pub task_name: &'static str,
+    /// The message of the panic, if it was a string.
+    pub error: Option<String>,
+}
+
+/// Returns the message of the panic with the given payload, if it was a string.
+fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    match payload.downcast_ref::<&str>() {
+        Some(msg) => Some(msg.to_string()),
+        None => payload.downcast_ref::<String>().cloned(),
+    }
+}
+
+/// The delay before a task that panicked is restarted for the first time.
+const INITIAL_RESTART_DELAY: Duration = Duration::from_millis(100);
+
+/// The maximum delay before a task that panicked is restarted.
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// Returns the delay before a task is restarted after it was restarted `restarts` times already.
+///
+/// The delay doubles with every restart, up to [`MAX_RESTART_DELAY`].
+fn restart_delay(restarts: usize) -> Duration {
+    let factor = 1u32.checked_shl(restarts.min(u32::BITS as usize) as u32).unwrap_or(u32::MAX);
+    INITIAL_RESTART_DELAY.saturating_mul(factor).min(MAX_RESTART_DELAY)
+}
+
+/// How a task that panicked is handled, see [`TaskExecutor::spawn_with_restarts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// The task is not restarted.
+    Never,
+    /// The task is restarted at most the given number of times.
+    Limited(usize),
+    /// The task is restarted every time it panics.
+    Always,
+}
+
+// === impl RestartPolicy ===
+
+impl RestartPolicy {
+    /// Returns true if the task is restarted after it was restarted `restarts` times already.
+    fn should_restart(&self, restarts: usize) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::Limited(max) => restarts < *max,
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
 /// A type that can spawn new tokio tasks
 #[derive(Debug, Clone)]
 pub struct TaskExecutor {
@@ -80,20 +146,42 @@ pub struct TaskExecutor {
     /// See [`Handle`] docs.
     handle: Handle,
     /// Sender half for sending panic signals to this type
-    panicked_tasks_tx: UnboundedSender<String>,
+    panicked_tasks_tx: UnboundedSender<PanickedTaskError>,
 }
 
 // === impl TaskExecutor ===
 
 impl TaskExecutor {
+    /// Returns an executor of the runtime the caller runs on.
+    ///
+    /// No [`TaskManager`] is notified about the panics of its critical tasks, they are only
+    /// logged, so this suits tests and components that were not given the executor of the node.
+    ///
+    /// # Panics
+    ///
+    /// If called outside of a tokio runtime, see [`Handle::current`].
+    pub fn current() -> Self {
+        TaskManager::new(Handle::current()).executor()
+    }
+
     /// Spawns the task onto the runtime.
     ///
+    /// If this task panics, the panic is logged and counted in the [`TaskMetrics`] of the task,
+    /// the [`TaskManager`] is not notified.
+    ///
     /// See also [`Handle::spawn`].
-    pub fn spawn<F>(&self, fut: F)
+    pub fn spawn<F>(&self, name: &'static str, fut: F)
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        let task = async move { fut.await }.in_current_span();
+        let metrics = TaskMetrics::new_with_labels([("task", name)]);
+        let task = async move {
+            if let Err(err) = run_task(&metrics, fut).await {
+                let error = panic_message(&*err);
+                error!(target: "tasks", task = name, ?error, "Task panicked");
+            }
+        }
+        .in_current_span();
         self.handle.spawn(task);
     }
 
@@ -105,23 +193,81 @@ impl TaskExecutor {
         F: Future<Output = ()> + Send + 'static,
     {
         let panicked_tasks_tx = self.panicked_tasks_tx.clone();
+        let metrics = TaskMetrics::new_with_labels([("task", name)]);
+        let task = async move {
+            if let Err(err) = run_task(&metrics, fut).await {
+                let err = PanickedTaskError { task_name: name, error: panic_message(&*err) };
+                error!(target: "tasks", %err, "Critical task panicked");
+                let _ = panicked_tasks_tx.send(err);
+            }
+        }
+        .in_current_span();
+        self.handle.spawn(task);
+    }
 
-        // wrap the task in catch unwind
-        let task = std::panic::AssertUnwindSafe(fut)
-            .catch_unwind()
-            .map(move |res| {
-                error!("Critical task `{name}` panicked: {res:?}");
-                let _ = panicked_tasks_tx.send(name.to_string());
-            })
-            .in_current_span();
+    /// Spawns a task that is created anew by `task` every time it panics, as long as the
+    /// [`RestartPolicy`] allows it.
+    ///
+    /// The delay before a restart starts at 100ms and doubles with every restart, up to 30s, so a
+    /// task that keeps panicking doesn't occupy the runtime.
+    ///
+    /// Once the task isn't restarted anymore, the last panic is logged and the [`TaskManager`] is
+    /// not notified, like for a task of [`TaskExecutor::spawn`].
+    pub fn spawn_with_restarts<F, Fut>(
+        &self,
+        name: &'static str,
+        policy: RestartPolicy,
+        mut task: F,
+    ) where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let metrics = TaskMetrics::new_with_labels([("task", name)]);
+        let task = async move {
+            let mut restarts = 0;
+            while let Err(err) = run_task(&metrics, task()).await {
+                let error = panic_message(&*err);
+                if !policy.should_restart(restarts) {
+                    error!(target: "tasks", task = name, ?error, restarts, "Task panicked");
+                    return
+                }
+                let delay = restart_delay(restarts);
+                warn!(target: "tasks", task = name, ?error, ?delay, "Restarting task after panic");
+                tokio::time::sleep(delay).await;
+                restarts += 1;
+                metrics.restarted.increment(1);
+            }
+        }
+        .in_current_span();
         self.handle.spawn(task);
     }
 }
 
+/// Runs the task to completion and records it in the metrics.
+///
+/// Returns the payload of the panic if the task panicked.
+async fn run_task<F>(metrics: &TaskMetrics, fut: F) -> Result<(), Box<dyn Any + Send>>
+where
+    F: Future<Output = ()>,
+{
+    metrics.spawned.increment(1);
+    metrics.running.increment(1.0);
+    let res = AssertUnwindSafe(fut).catch_unwind().await;
+    metrics.running.decrement(1.0);
+    if res.is_err() {
+        metrics.panicked.increment(1);
+    }
+    res
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use futures_util::StreamExt;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
 
     #[test]
     fn test_critical() {
@@ -137,7 +283,52 @@ mod tests {
 
         runtime.block_on(async move {
             let panicked_task = manager.next().await.unwrap();
-            assert_eq!(panicked_task, "this is a critical task");
+            assert_eq!(panicked_task.task_name, "this is a critical task");
+            assert_eq!(panicked_task.error.as_deref(), Some("intentionally panic"));
         })
     }
+
+    #[test]
+    fn test_restart() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let manager = TaskManager::new(runtime.handle().clone());
+        let executor = manager.executor();
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        let mut done_tx = Some(done_tx);
+        let task_runs = runs.clone();
+        executor.spawn_with_restarts("restarted task", RestartPolicy::Limited(2), move || {
+            let run = task_runs.fetch_add(1, Ordering::SeqCst);
+            let done_tx = if run == 2 { done_tx.take() } else { None };
+            async move {
+                match done_tx {
+                    Some(done_tx) => {
+                        let _ = done_tx.send(());
+                    }
+                    None => panic!("intentionally panic"),
+                }
+            }
+        });
+
+        runtime.block_on(done_rx).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn restart_policy() {
+        assert!(!RestartPolicy::Never.should_restart(0));
+        assert!(RestartPolicy::Limited(1).should_restart(0));
+        assert!(!RestartPolicy::Limited(1).should_restart(1));
+        assert!(RestartPolicy::Always.should_restart(usize::MAX));
+    }
+
+    #[test]
+    fn restart_backoff() {
+        assert_eq!(restart_delay(0), INITIAL_RESTART_DELAY);
+        assert_eq!(restart_delay(1), INITIAL_RESTART_DELAY * 2);
+        assert_eq!(restart_delay(3), INITIAL_RESTART_DELAY * 8);
+        assert_eq!(restart_delay(20), MAX_RESTART_DELAY);
+        assert_eq!(restart_delay(usize::MAX), MAX_RESTART_DELAY);
+    }
 }
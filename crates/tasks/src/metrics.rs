@@ -0,0 +1,17 @@
+use metrics::{Counter, Gauge};
+use reth_metrics_derive::Metrics;
+
+/// Metrics of the tasks spawned by the [`TaskExecutor`](crate::TaskExecutor), registered for
+/// every task with a `task` label
+#[derive(Metrics)]
+#[metrics(scope = "tasks")]
+pub struct TaskMetrics {
+    /// Number of times the task was spawned
+    pub spawned: Counter,
+    /// Number of instances of the task that are currently running
+    pub running: Gauge,
+    /// Number of times the task panicked
+    pub panicked: Counter,
+    /// Number of times the task was restarted after it panicked
+    pub restarted: Counter,
+}
@@ -0,0 +1,94 @@
+//! Signal to stop the tasks of the node gracefully.
+
+use futures_util::Future;
+use tokio::sync::watch;
+
+/// Creates a new signal and the listener it notifies.
+///
+/// The listener can be cloned, every clone is notified once [`ShutdownSignal::fire`] is called.
+pub fn signal() -> (ShutdownSignal, Shutdown) {
+    let (tx, rx) = watch::channel(false);
+    (ShutdownSignal(tx), Shutdown(rx))
+}
+
+/// The sending half of the shutdown signal, see [`signal`].
+#[derive(Debug)]
+pub struct ShutdownSignal(watch::Sender<bool>);
+
+// === impl ShutdownSignal ===
+
+impl ShutdownSignal {
+    /// Requests all listeners to shut down.
+    pub fn fire(&self) {
+        let _ = self.0.send(true);
+    }
+}
+
+/// A listener for the shutdown signal, see [`signal`].
+///
+/// The default listener is never notified. If the [`ShutdownSignal`] is dropped without being
+/// fired, the shutdown is never requested either.
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+// === impl Shutdown ===
+
+impl Shutdown {
+    /// Returns true if the shutdown was requested.
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once the shutdown is requested.
+    pub async fn requested(&self) {
+        let mut rx = self.0.clone();
+        while !*rx.borrow_and_update() {
+            if rx.changed().await.is_err() {
+                // the signal is gone without having been fired
+                futures_util::future::pending::<()>().await;
+            }
+        }
+    }
+
+    /// Runs the future until it completes or the shutdown is requested.
+    ///
+    /// Returns `None` if the future was dropped because of the shutdown.
+    pub async fn until<F: Future>(self, fut: F) -> Option<F::Output> {
+        tokio::select! {
+            biased;
+            _ = self.requested() => None,
+            output = fut => Some(output),
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        signal().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn notify_clones() {
+        let (signal, shutdown) = signal();
+        let clone = shutdown.clone();
+        assert!(!shutdown.is_requested());
+
+        let task = tokio::spawn(clone.until(futures_util::future::pending::<()>()));
+        signal.fire();
+        assert_eq!(task.await.unwrap(), None);
+        assert!(shutdown.is_requested());
+        shutdown.requested().await;
+    }
+
+    #[tokio::test]
+    async fn run_until_completion() {
+        let (_signal, shutdown) = signal();
+        assert_eq!(shutdown.until(async { 1 }).await, Some(1));
+        assert_eq!(Shutdown::default().until(async { 2 }).await, Some(2));
+    }
+}
@@ -7,10 +7,50 @@
 
 //! reth-tracing
 
+use std::{fmt, path::Path, str::FromStr};
+use tracing::Subscriber;
+use tracing_appender::rolling::{InitError, RollingFileAppender, Rotation};
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
 // re-export tracing crates.
 pub use tracing;
+pub use tracing_appender::non_blocking::WorkerGuard;
 pub use tracing_subscriber;
 
+/// A boxed tracing [Layer].
+pub type BoxedLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+
+/// The format of the emitted logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines.
+    #[default]
+    Terminal,
+    /// One JSON object per event, including the fields of the event and its spans.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terminal" => Ok(LogFormat::Terminal),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format: {s}, expected `terminal` or `json`")),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Terminal => f.write_str("terminal"),
+            LogFormat::Json => f.write_str("json"),
+        }
+    }
+}
+
 /// Initialises a tracing subscriber via `RUST_LOG` environment variable filter.
 ///
 /// Note: This ignores any error and should be used for testing.
@@ -20,3 +60,59 @@ pub fn init_tracing() {
         .with_writer(std::io::stderr)
         .try_init();
 }
+
+/// Returns a layer that writes logs to stdout.
+///
+/// Colors are only used for the terminal format.
+pub fn stdout<S>(format: LogFormat, color: bool, with_target: bool) -> BoxedLayer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let layer = tracing_subscriber::fmt::layer().with_target(with_target);
+    match format {
+        LogFormat::Terminal => layer.with_ansi(color).boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Returns a layer that writes logs to files in the directory that are rotated daily, keeping at
+/// most `max_files` files.
+///
+/// The files are written on a background thread, which flushes all remaining logs once the returned
+/// [WorkerGuard] is dropped.
+pub fn file<S>(
+    format: LogFormat,
+    dir: impl AsRef<Path>,
+    file_name: &str,
+    max_files: usize,
+) -> Result<(BoxedLayer<S>, WorkerGuard), InitError>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(file_name)
+        .max_log_files(max_files)
+        .build(dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let layer = tracing_subscriber::fmt::layer().with_ansi(false).with_writer(writer);
+    let layer = match format {
+        LogFormat::Terminal => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    };
+    Ok((layer, guard))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_log_format() {
+        for format in [LogFormat::Terminal, LogFormat::Json] {
+            assert_eq!(format.to_string().parse::<LogFormat>(), Ok(format));
+        }
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Support for moving the contents of a pool to another node.
+//!
+//! Pooled transactions are exported as an RLP list of [`TransactionSigned`] in their p2p encoding,
+//! which can be written to a file and imported into the pool of another node.
+
+use crate::{error::PoolResult, TransactionOrigin, TransactionPool};
+use reth_primitives::{
+    FromRecoveredTransaction, IntoRecoveredTransaction, TransactionSigned, TxHash,
+};
+use reth_rlp::{Decodable, DecodeError};
+
+/// Returns all transactions that are currently in the pool.
+pub fn export_transactions<P>(pool: &P) -> Vec<TransactionSigned>
+where
+    P: TransactionPool,
+    P::Transaction: IntoRecoveredTransaction,
+{
+    pool.get_all(pool.pooled_transactions())
+        .into_iter()
+        .map(|tx| tx.transaction.to_recovered_transaction().into_signed())
+        .collect()
+}
+
+/// Adds the given transactions to the pool as [`TransactionOrigin::External`] transactions.
+///
+/// Transactions whose signer can't be recovered are skipped.
+pub async fn import_transactions<P>(
+    pool: &P,
+    transactions: Vec<TransactionSigned>,
+) -> PoolResult<Vec<PoolResult<TxHash>>>
+where
+    P: TransactionPool,
+{
+    let transactions = transactions
+        .into_iter()
+        .filter_map(TransactionSigned::into_ecrecovered)
+        .map(<P::Transaction as FromRecoveredTransaction>::from_recovered_transaction)
+        .collect();
+    pool.add_transactions(TransactionOrigin::External, transactions).await
+}
+
+/// Encodes the transactions in the backup format.
+pub fn encode_transactions(transactions: &[TransactionSigned]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    reth_rlp::encode_list::<TransactionSigned, _>(transactions, &mut buf);
+    buf
+}
+
+/// Decodes transactions that were encoded with [`encode_transactions`].
+pub fn decode_transactions(mut buf: &[u8]) -> Result<Vec<TransactionSigned>, DecodeError> {
+    let transactions = Vec::<TransactionSigned>::decode(&mut buf)?;
+    if !buf.is_empty() {
+        return Err(DecodeError::Custom("trailing bytes after transactions"))
+    }
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Signature, Transaction, TxLegacy};
+
+    #[test]
+    fn transactions_roundtrip() {
+        let transactions = (0..3u64)
+            .map(|nonce| {
+                TransactionSigned::from_transaction_and_signature(
+                    Transaction::Legacy(TxLegacy {
+                        nonce,
+                        gas_limit: 21_000,
+                        ..Default::default()
+                    }),
+                    Signature::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let encoded = encode_transactions(&transactions);
+        assert_eq!(decode_transactions(&encoded).unwrap(), transactions);
+
+        let mut trailing = encoded;
+        trailing.push(0x80);
+        assert!(decode_transactions(&trailing).is_err());
+    }
+}
@@ -12,6 +12,8 @@ pub struct PoolConfig {
     pub queued_limit: SubPoolLimit,
     /// Max number of executable transaction slots guaranteed per account
     pub max_account_slots: usize,
+    /// Settings of the minimum fee that is enforced while the pool is congested.
+    pub min_fee: MinFeeConfig,
 }
 
 impl Default for PoolConfig {
@@ -21,6 +23,7 @@ impl Default for PoolConfig {
             basefee_limit: Default::default(),
             queued_limit: Default::default(),
             max_account_slots: MAX_ACCOUNT_SLOTS_PER_SENDER,
+            min_fee: Default::default(),
         }
     }
 }
@@ -48,3 +51,26 @@ impl Default for SubPoolLimit {
         Self { max_txs: 10_000, max_size: 20 * 1024 * 1024 }
     }
 }
+
+/// Settings of the minimum fee of external transactions while the pending sub-pool is congested.
+///
+/// See also [`PoolFeeStats`](crate::PoolFeeStats).
+#[derive(Debug, Clone)]
+pub struct MinFeeConfig {
+    /// The percentile of the fees of included and pending transactions that new transactions have
+    /// to pay while the pool is congested.
+    pub percentile: u8,
+    /// The number of recent blocks whose included transactions are considered.
+    pub history: usize,
+    /// The share of the pending limit in percent at which the pending sub-pool is congested.
+    pub congestion_threshold: u8,
+    /// The number of consecutive blocks after which the pool has to be congested before the
+    /// minimum fee is enforced.
+    pub congested_blocks: usize,
+}
+
+impl Default for MinFeeConfig {
+    fn default() -> Self {
+        Self { percentile: 10, history: 20, congestion_threshold: 90, congested_blocks: 3 }
+    }
+}
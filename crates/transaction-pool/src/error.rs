@@ -21,6 +21,24 @@ pub enum PoolError {
     /// respect the size limits of the pool.
     #[error("[{0:?}] Transaction discarded outright due to pool size constraints.")]
     DiscardedOnInsert(TxHash),
+    /// Thrown if an external transaction pays less than the minimum fee of the congested pool.
+    #[error("[{0:?}] Transaction fee below the minimum fee {1} of the congested pool.")]
+    FeeBelowCongestionMinimum(TxHash, U256),
+    /// Thrown if the sender is not permitted to send the transaction on a permissioned network.
+    #[error("{0:?} not permitted to send transaction {1:?}.")]
+    SenderNotPermitted(Address, TxHash),
+    /// Thrown if the transaction is signed for another chain.
+    #[error("[{0:?}] Transaction chain id {1} does not match the chain.")]
+    InvalidChainId(TxHash, u64),
+    /// Thrown if the gas limit of the transaction exceeds the gas limit of a block.
+    #[error("[{0:?}] Transaction gas limit exceeds the block gas limit {1}.")]
+    GasLimitExceedsBlock(TxHash, u64),
+    /// Thrown if the nonce of the transaction is lower than the nonce of its sender.
+    #[error("[{0:?}] Transaction nonce is lower than the sender nonce {1}.")]
+    NonceTooLow(TxHash, u64),
+    /// Thrown if the state of the sender could not be read.
+    #[error("[{0:?}] Failed to read the sender state: {1}")]
+    StateUnavailable(TxHash, reth_interfaces::Error),
 }
 
 // === impl PoolError ===
@@ -33,6 +51,12 @@ impl PoolError {
             PoolError::ProtocolFeeCapTooLow(hash, _) => hash,
             PoolError::SpammerExceededCapacity(_, hash) => hash,
             PoolError::DiscardedOnInsert(hash) => hash,
+            PoolError::FeeBelowCongestionMinimum(hash, _) => hash,
+            PoolError::SenderNotPermitted(_, hash) => hash,
+            PoolError::InvalidChainId(hash, _) => hash,
+            PoolError::GasLimitExceedsBlock(hash, _) => hash,
+            PoolError::NonceTooLow(hash, _) => hash,
+            PoolError::StateUnavailable(hash, _) => hash,
         }
     }
 }
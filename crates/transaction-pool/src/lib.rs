@@ -80,24 +80,23 @@
 //! that provides the `TransactionPool` interface.
 
 pub use crate::{
-    config::PoolConfig,
-    ordering::TransactionOrdering,
+    config::{MinFeeConfig, PoolConfig},
+    ordering::{GasPriceOrdering, TransactionOrdering},
     traits::{
-        BestTransactions, OnNewBlockEvent, PoolTransaction, PropagateKind, PropagatedTransactions,
-        TransactionOrigin, TransactionPool,
+        BestTransactions, OnNewBlockEvent, PoolFeeStats, PoolSize, PoolTransaction, PropagateKind,
+        PropagatedTransactions, StateDiff, TransactionOrigin, TransactionPool,
+    },
+    validate::{
+        EthTransactionValidator, PermissionedValidator, TransactionValidationOutcome,
+        TransactionValidator, ValidPoolTransaction,
     },
-    validate::{TransactionValidationOutcome, TransactionValidator},
-};
-use crate::{
-    error::PoolResult,
-    pool::PoolInner,
-    traits::{NewTransactionEvent, PoolSize},
-    validate::ValidPoolTransaction,
 };
+use crate::{error::PoolResult, pool::PoolInner, traits::NewTransactionEvent};
 use reth_primitives::{TxHash, U256};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::mpsc::Receiver;
 
+pub mod backup;
 mod config;
 pub mod error;
 mod identifier;
@@ -106,8 +105,8 @@ pub mod pool;
 mod traits;
 mod validate;
 
-#[cfg(test)]
-mod test_util;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_util;
 
 /// A shareable, generic, customizable `TransactionPool` implementation.
 #[derive(Debug)]
@@ -192,6 +191,10 @@ where
         self.pool.size()
     }
 
+    fn fee_stats(&self) -> PoolFeeStats {
+        self.pool.fee_stats()
+    }
+
     fn on_new_block(&self, event: OnNewBlockEvent) {
         self.pool.on_new_block(event);
     }
@@ -233,6 +236,14 @@ where
         Box::new(self.pool.best_transactions())
     }
 
+    fn pending_transactions(&self) -> Vec<Arc<ValidPoolTransaction<Self::Transaction>>> {
+        self.pool.pending_transactions()
+    }
+
+    fn queued_transactions(&self) -> Vec<Arc<ValidPoolTransaction<Self::Transaction>>> {
+        self.pool.queued_transactions()
+    }
+
     fn remove_invalid(
         &self,
         hashes: impl IntoIterator<Item = TxHash>,
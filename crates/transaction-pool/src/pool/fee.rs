@@ -0,0 +1,163 @@
+//! Tracks the minimum fee of external transactions under sustained congestion.
+
+use crate::{config::MinFeeConfig, traits::PoolFeeStats};
+use reth_primitives::U256;
+use std::collections::VecDeque;
+
+/// Derives the minimum fee from the fees of recently included transactions and the fees of the
+/// pending transactions.
+///
+/// Once the pending sub-pool was congested after [`MinFeeConfig::congested_blocks`] consecutive
+/// blocks, new external transactions have to pay at least the configured percentile of both, the
+/// fees of the transactions included in the recent blocks and the fees of the pending
+/// transactions. Cheaper transactions are unlikely to be included soon and would only take the
+/// capacity of includable transactions. The minimum fee is lifted as soon as the pool is no
+/// longer congested.
+#[derive(Debug)]
+pub(crate) struct MinFeeTracker {
+    config: MinFeeConfig,
+    /// The number of pending transactions at which the pool is congested.
+    congestion_limit: usize,
+    /// The fee percentile of the included transactions of the recent blocks, oldest first.
+    ///
+    /// Blocks without known transactions are skipped.
+    included: VecDeque<U256>,
+    /// The fee percentile of the pending transactions after the last block.
+    pending: Option<U256>,
+    /// The number of consecutive blocks after which the pool was congested.
+    congested_blocks: usize,
+    /// The currently enforced minimum fee.
+    min_fee: U256,
+}
+
+// === impl MinFeeTracker ===
+
+impl MinFeeTracker {
+    /// Creates a new tracker for a pending sub-pool that holds at most `pending_limit`
+    /// transactions.
+    pub(crate) fn new(config: MinFeeConfig, pending_limit: usize) -> Self {
+        let congestion_limit = pending_limit * config.congestion_threshold.min(100) as usize / 100;
+        Self {
+            config,
+            congestion_limit,
+            included: VecDeque::new(),
+            pending: None,
+            congested_blocks: 0,
+            min_fee: U256::zero(),
+        }
+    }
+
+    /// Returns the fee external transactions have to pay at least.
+    pub(crate) fn min_fee(&self) -> U256 {
+        self.min_fee
+    }
+
+    /// Updates the minimum fee after a new block.
+    ///
+    /// `included` are the fees of the transactions of the block that were in the pool, `pending`
+    /// the fees of the pending transactions after the block.
+    pub(crate) fn on_new_block(&mut self, included: Vec<U256>, pending: Vec<U256>) {
+        let congested = pending.len() >= self.congestion_limit.max(1);
+        if let Some(fee) = percentile(included, self.config.percentile) {
+            self.included.push_back(fee);
+            while self.included.len() > self.config.history.max(1) {
+                self.included.pop_front();
+            }
+        }
+        self.pending = percentile(pending, self.config.percentile);
+
+        if !congested {
+            self.congested_blocks = 0;
+            self.min_fee = U256::zero();
+            return
+        }
+        self.congested_blocks += 1;
+        if self.congested_blocks >= self.config.congested_blocks {
+            let included = self.included_percentile().unwrap_or_default();
+            self.min_fee = included.max(self.pending.unwrap_or_default());
+        }
+    }
+
+    /// Returns the lowest fee percentile of the included transactions of the recent blocks.
+    fn included_percentile(&self) -> Option<U256> {
+        self.included.iter().min().copied()
+    }
+
+    /// Returns the current state of the tracker.
+    pub(crate) fn stats(&self) -> PoolFeeStats {
+        PoolFeeStats {
+            min_fee: self.min_fee,
+            congested_blocks: self.congested_blocks,
+            included_percentile: self.included_percentile(),
+            pending_percentile: self.pending,
+        }
+    }
+}
+
+/// Returns the fee at the given percentile, `None` if there are no fees.
+fn percentile(mut fees: Vec<U256>, percentile: u8) -> Option<U256> {
+    if fees.is_empty() {
+        return None
+    }
+    fees.sort_unstable();
+    let idx = (fees.len() - 1) * percentile.min(100) as usize / 100;
+    Some(fees[idx])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fees(range: std::ops::Range<u64>) -> Vec<U256> {
+        range.map(U256::from).collect()
+    }
+
+    #[test]
+    fn raise_min_fee_under_sustained_congestion() {
+        let config = MinFeeConfig {
+            percentile: 50,
+            history: 2,
+            congestion_threshold: 50,
+            congested_blocks: 2,
+        };
+        let mut tracker = MinFeeTracker::new(config, 20);
+
+        // not congested
+        tracker.on_new_block(fees(10..13), fees(1..9));
+        assert_eq!(tracker.min_fee(), U256::zero());
+
+        // congested for one block
+        tracker.on_new_block(fees(20..23), fees(1..11));
+        assert_eq!(tracker.min_fee(), U256::zero());
+        assert_eq!(tracker.stats().congested_blocks, 1);
+
+        // congested for two blocks, the pending fees are higher than the included ones
+        tracker.on_new_block(fees(30..33), fees(40..51));
+        let stats = tracker.stats();
+        assert_eq!(stats.included_percentile, Some(21.into()));
+        assert_eq!(stats.pending_percentile, Some(45.into()));
+        assert_eq!(stats.min_fee, 45.into());
+
+        // the oldest block falls out of the history
+        tracker.on_new_block(fees(60..63), fees(1..11));
+        assert_eq!(tracker.stats().included_percentile, Some(31.into()));
+        assert_eq!(tracker.min_fee(), 31.into());
+
+        // blocks without known transactions keep the history
+        tracker.on_new_block(Vec::new(), fees(1..11));
+        assert_eq!(tracker.min_fee(), 31.into());
+
+        // congestion is over
+        tracker.on_new_block(fees(60..63), fees(1..5));
+        assert_eq!(tracker.min_fee(), U256::zero());
+        assert_eq!(tracker.stats().congested_blocks, 0);
+    }
+
+    #[test]
+    fn fee_percentile() {
+        assert_eq!(percentile(Vec::new(), 10), None);
+        assert_eq!(percentile(fees(1..2), 90), Some(1.into()));
+        assert_eq!(percentile(fees(1..101).into_iter().rev().collect(), 10), Some(10.into()));
+        assert_eq!(percentile(fees(1..101), 100), Some(100.into()));
+    }
+}
@@ -72,7 +72,8 @@ use crate::{
     identifier::{SenderId, SenderIdentifiers, TransactionId},
     pool::{listener::PoolEventBroadcast, state::SubPool, txpool::TxPool},
     traits::{
-        NewTransactionEvent, PoolSize, PoolTransaction, PropagatedTransactions, TransactionOrigin,
+        NewTransactionEvent, PoolFeeStats, PoolSize, PoolTransaction, PropagatedTransactions,
+        TransactionOrigin,
     },
     validate::{TransactionValidationOutcome, ValidPoolTransaction},
     OnNewBlockEvent, PoolConfig, TransactionOrdering, TransactionValidator,
@@ -87,6 +88,7 @@ use tracing::warn;
 
 mod best;
 mod events;
+mod fee;
 mod listener;
 mod parked;
 mod pending;
@@ -139,6 +141,11 @@ where
         self.pool.read().size()
     }
 
+    /// Returns the state of the minimum fee of the pool.
+    pub(crate) fn fee_stats(&self) -> PoolFeeStats {
+        self.pool.read().fee_stats()
+    }
+
     /// Returns the internal `SenderId` for this address
     pub(crate) fn get_sender_id(&self, addr: Address) -> SenderId {
         self.identifiers.write().sender_id_or_create(addr)
@@ -334,6 +341,16 @@ where
         self.pool.read().best_transactions()
     }
 
+    /// Returns all transactions of the _pending_ sub-pool.
+    pub(crate) fn pending_transactions(&self) -> Vec<Arc<ValidPoolTransaction<T::Transaction>>> {
+        self.pool.read().pending_transactions()
+    }
+
+    /// Returns all transactions that are not in the _pending_ sub-pool.
+    pub(crate) fn queued_transactions(&self) -> Vec<Arc<ValidPoolTransaction<T::Transaction>>> {
+        self.pool.read().queued_transactions()
+    }
+
     /// Removes and returns all matching transactions from the pool.
     pub(crate) fn remove_invalid(
         &self,
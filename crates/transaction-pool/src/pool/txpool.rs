@@ -5,13 +5,14 @@ use crate::{
     identifier::{SenderId, TransactionId},
     pool::{
         best::BestTransactions,
+        fee::MinFeeTracker,
         parked::{BasefeeOrd, ParkedPool, QueuedOrd},
         pending::PendingPool,
         state::{SubPool, TxState},
         update::{Destination, PoolUpdate},
         AddedPendingTransaction, AddedTransaction, OnNewBlockOutcome,
     },
-    traits::{PoolSize, StateDiff},
+    traits::{PoolFeeStats, PoolSize, StateDiff},
     OnNewBlockEvent, PoolConfig, PoolResult, PoolTransaction, TransactionOrdering,
     ValidPoolTransaction, U256,
 };
@@ -85,6 +86,8 @@ pub struct TxPool<T: TransactionOrdering> {
     basefee_pool: ParkedPool<BasefeeOrd<T::Transaction>>,
     /// All transactions in the pool.
     all_transactions: AllTransactions<T::Transaction>,
+    /// The minimum fee of external transactions under congestion.
+    min_fee: MinFeeTracker,
 }
 
 // === impl TxPool ===
@@ -98,6 +101,7 @@ impl<T: TransactionOrdering> TxPool<T> {
             queued_pool: Default::default(),
             basefee_pool: Default::default(),
             all_transactions: AllTransactions::new(config.max_account_slots),
+            min_fee: MinFeeTracker::new(config.min_fee.clone(), config.pending_limit.max_txs),
             config,
         }
     }
@@ -119,6 +123,11 @@ impl<T: TransactionOrdering> TxPool<T> {
         }
     }
 
+    /// Returns the state of the minimum fee of external transactions.
+    pub(crate) fn fee_stats(&self) -> PoolFeeStats {
+        self.min_fee.stats()
+    }
+
     /// Updates the pool based on the changed base fee.
     ///
     /// This enforces the dynamic fee requirement.
@@ -132,6 +141,17 @@ impl<T: TransactionOrdering> TxPool<T> {
         self.pending_pool.best()
     }
 
+    /// Returns all transactions of the _pending_ sub-pool, sorted by sender and nonce.
+    pub(crate) fn pending_transactions(&self) -> Vec<Arc<ValidPoolTransaction<T::Transaction>>> {
+        self.all_transactions.txs_in(|subpool| subpool.is_pending()).collect()
+    }
+
+    /// Returns all transactions of the _queued_ and _basefee_ sub-pools, sorted by sender and
+    /// nonce.
+    pub(crate) fn queued_transactions(&self) -> Vec<Arc<ValidPoolTransaction<T::Transaction>>> {
+        self.all_transactions.txs_in(|subpool| !subpool.is_pending()).collect()
+    }
+
     /// Returns if the transaction for the given hash is already included in this pool
     pub(crate) fn contains(&self, tx_hash: &TxHash) -> bool {
         self.all_transactions.contains(tx_hash)
@@ -159,8 +179,11 @@ impl<T: TransactionOrdering> TxPool<T> {
     /// sender allowance.
     pub(crate) fn on_new_block(&mut self, event: OnNewBlockEvent) -> OnNewBlockOutcome {
         // Remove all transaction that were included in the block
+        let mut included_fees = Vec::with_capacity(event.mined_transactions.len());
         for tx_hash in &event.mined_transactions {
-            self.remove_transaction_by_hash(tx_hash);
+            if let Some(tx) = self.remove_transaction_by_hash(tx_hash) {
+                included_fees.push(tx.transaction.effective_gas_price());
+            }
         }
 
         // Apply the state changes to the total set of transactions which triggers sub-pool updates.
@@ -170,6 +193,15 @@ impl<T: TransactionOrdering> TxPool<T> {
         // Process the sub-pool updates
         let UpdateOutcome { promoted, discarded, .. } = self.process_updates(updates);
 
+        let pending_fees = self
+            .all_transactions
+            .txs
+            .values()
+            .filter(|tx| tx.subpool.is_pending())
+            .map(|tx| tx.transaction.transaction.effective_gas_price())
+            .collect();
+        self.min_fee.on_new_block(included_fees, pending_fees);
+
         OnNewBlockOutcome {
             block_hash: event.hash,
             mined: event.mined_transactions,
@@ -204,6 +236,11 @@ impl<T: TransactionOrdering> TxPool<T> {
         on_chain_balance: U256,
         on_chain_nonce: u64,
     ) -> PoolResult<AddedTransaction<T::Transaction>> {
+        let min_fee = self.min_fee.min_fee();
+        if !tx.is_local() && tx.transaction.effective_gas_price() < min_fee {
+            return Err(PoolError::FeeBelowCongestionMinimum(*tx.hash(), min_fee))
+        }
+
         // Update sender info with balance and nonce
         self.sender_info
             .entry(tx.sender_id())
@@ -491,6 +528,15 @@ impl<T: PoolTransaction> AllTransactions<T> {
         self.by_hash.contains_key(tx_hash)
     }
 
+    /// Returns an iterator over all transactions in the matching sub-pools, sorted by sender and
+    /// nonce.
+    pub(crate) fn txs_in(
+        &self,
+        mut f: impl FnMut(SubPool) -> bool,
+    ) -> impl Iterator<Item = Arc<ValidPoolTransaction<T>>> + '_ {
+        self.txs.values().filter(move |tx| f(tx.subpool)).map(|tx| tx.transaction.clone())
+    }
+
     /// Returns the internal transaction with additional metadata
     #[cfg(test)]
     pub(crate) fn get(&self, id: &TransactionId) -> Option<&PoolInternalTransaction<T>> {
@@ -1103,7 +1149,8 @@ impl SenderInfo {
 mod tests {
     use super::*;
     use crate::{
-        test_util::{MockTransaction, MockTransactionFactory},
+        config::{MinFeeConfig, SubPoolLimit},
+        test_util::{MockOrdering, MockTransaction, MockTransactionFactory},
         traits::TransactionOrigin,
     };
 
@@ -1321,4 +1368,80 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn rejects_underpriced_under_congestion() {
+        let on_chain_balance = U256::from(1_000_000);
+        let config = PoolConfig {
+            pending_limit: SubPoolLimit { max_txs: 2, ..Default::default() },
+            min_fee: MinFeeConfig {
+                percentile: 50,
+                congestion_threshold: 100,
+                congested_blocks: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut f = MockTransactionFactory::default();
+        let mut pool = TxPool::new(Arc::new(MockOrdering::default()), config);
+        for price in [100u64, 200] {
+            let tx = f.validated(MockTransaction::legacy().with_gas_price(price.into()));
+            pool.add_transaction(tx, on_chain_balance, 0).unwrap();
+        }
+
+        let mined = f.validated(MockTransaction::legacy().with_gas_price(300u64.into()));
+        let mined_hash = *mined.hash();
+        pool.add_transaction(mined, on_chain_balance, 0).unwrap();
+        pool.on_new_block(OnNewBlockEvent {
+            hash: H256::zero(),
+            pending_block_base_fee: U256::zero(),
+            state_changes: StateDiff {},
+            mined_transactions: vec![mined_hash],
+        });
+        assert_eq!(
+            pool.fee_stats(),
+            PoolFeeStats {
+                min_fee: 300u64.into(),
+                congested_blocks: 1,
+                included_percentile: Some(300u64.into()),
+                pending_percentile: Some(100u64.into()),
+            }
+        );
+
+        let underpriced = f.validated(MockTransaction::legacy().with_gas_price(200u64.into()));
+        let err = pool.add_transaction(underpriced, on_chain_balance, 0).unwrap_err();
+        assert!(
+            matches!(err, PoolError::FeeBelowCongestionMinimum(_, fee) if fee == U256::from(300u64))
+        );
+
+        // local transactions are exempt
+        let local = f.validated_with_origin(
+            TransactionOrigin::Local,
+            MockTransaction::legacy().with_gas_price(200u64.into()),
+        );
+        pool.add_transaction(local, on_chain_balance, 0).unwrap();
+        let priced = f.validated(MockTransaction::legacy().with_gas_price(300u64.into()));
+        pool.add_transaction(priced, on_chain_balance, 0).unwrap();
+    }
+
+    #[test]
+    fn split_pending_and_queued() {
+        let on_chain_balance = U256::from(1_000_000);
+        let mut f = MockTransactionFactory::default();
+        let mut pool = TxPool::new(Arc::new(MockOrdering::default()), Default::default());
+
+        let first = MockTransaction::eip1559();
+        let gapped = first.skip(2);
+        let first = f.validated(first);
+        let gapped = f.validated(gapped);
+        pool.add_transaction(first.clone(), on_chain_balance, 0).unwrap();
+        pool.add_transaction(gapped.clone(), on_chain_balance, 0).unwrap();
+
+        let pending = pool.pending_transactions();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].hash(), first.hash());
+        let queued = pool.queued_transactions();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].hash(), gapped.hash());
+    }
 }
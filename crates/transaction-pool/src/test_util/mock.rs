@@ -4,7 +4,8 @@ use crate::{
     identifier::{SenderIdentifiers, TransactionId},
     pool::txpool::{TxPool, MIN_PROTOCOL_BASE_FEE},
     traits::TransactionOrigin,
-    PoolTransaction, TransactionOrdering, ValidPoolTransaction,
+    PoolTransaction, TransactionOrdering, TransactionValidationOutcome, TransactionValidator,
+    ValidPoolTransaction,
 };
 use paste::paste;
 use rand::{
@@ -12,10 +13,10 @@ use rand::{
     prelude::Distribution,
 };
 use reth_primitives::{
-    Address, FromRecoveredTransaction, Transaction, TransactionSignedEcRecovered, TxEip1559,
-    TxHash, TxLegacy, H256, U256,
+    Address, FromRecoveredTransaction, IntoRecoveredTransaction, Transaction, TransactionKind,
+    TransactionSigned, TransactionSignedEcRecovered, TxEip1559, TxHash, TxLegacy, H256, U256,
 };
-use std::{ops::Range, sync::Arc, time::Instant};
+use std::{marker::PhantomData, ops::Range, sync::Arc, time::Instant};
 
 pub type MockTxPool = TxPool<MockOrdering>;
 
@@ -85,6 +86,7 @@ pub enum MockTransaction {
         nonce: u64,
         gas_price: U256,
         gas_limit: u64,
+        to: TransactionKind,
         value: U256,
     },
     Eip1559 {
@@ -94,6 +96,7 @@ pub enum MockTransaction {
         max_fee_per_gas: U256,
         max_priority_fee_per_gas: U256,
         gas_limit: u64,
+        to: TransactionKind,
         value: U256,
     },
 }
@@ -106,6 +109,7 @@ impl MockTransaction {
         hash => H256;
         sender => Address;
         gas_limit => u64;
+        to => TransactionKind;
         value => U256
     }
 
@@ -117,6 +121,7 @@ impl MockTransaction {
             nonce: 0,
             gas_price: U256::zero(),
             gas_limit: 0,
+            to: TransactionKind::Call(Address::random()),
             value: Default::default(),
         }
     }
@@ -130,6 +135,7 @@ impl MockTransaction {
             max_fee_per_gas: MIN_PROTOCOL_BASE_FEE,
             max_priority_fee_per_gas: MIN_PROTOCOL_BASE_FEE,
             gas_limit: 0,
+            to: TransactionKind::Call(Address::random()),
             value: Default::default(),
         }
     }
@@ -296,6 +302,10 @@ impl PoolTransaction for MockTransaction {
         }
     }
 
+    fn kind(&self) -> &TransactionKind {
+        get_value!(self => to)
+    }
+
     fn cost(&self) -> U256 {
         match self {
             MockTransaction::Legacy { gas_price, value, gas_limit, .. } => {
@@ -356,6 +366,7 @@ impl FromRecoveredTransaction for MockTransaction {
                 nonce,
                 gas_price: gas_price.into(),
                 gas_limit,
+                to,
                 value: value.into(),
             },
             Transaction::Eip1559(TxEip1559 {
@@ -375,6 +386,7 @@ impl FromRecoveredTransaction for MockTransaction {
                 max_fee_per_gas: max_fee_per_gas.into(),
                 max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
                 gas_limit,
+                to,
                 value: value.into(),
             },
             Transaction::Eip2930 { .. } => {
@@ -384,6 +396,47 @@ impl FromRecoveredTransaction for MockTransaction {
     }
 }
 
+/// Mock transactions are not signed, the recovered transaction carries a default signature.
+impl IntoRecoveredTransaction for MockTransaction {
+    fn to_recovered_transaction(&self) -> TransactionSignedEcRecovered {
+        let transaction = match self.clone() {
+            MockTransaction::Legacy { nonce, gas_price, gas_limit, to, value, .. } => {
+                Transaction::Legacy(TxLegacy {
+                    chain_id: None,
+                    nonce,
+                    gas_price: gas_price.as_u128(),
+                    gas_limit,
+                    to,
+                    value: value.as_u128(),
+                    input: Default::default(),
+                })
+            }
+            MockTransaction::Eip1559 {
+                nonce,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                gas_limit,
+                to,
+                value,
+                ..
+            } => Transaction::Eip1559(TxEip1559 {
+                chain_id: 1,
+                nonce,
+                gas_limit,
+                max_fee_per_gas: max_fee_per_gas.as_u128(),
+                max_priority_fee_per_gas: max_priority_fee_per_gas.as_u128(),
+                to,
+                value: value.as_u128(),
+                input: Default::default(),
+                access_list: Default::default(),
+            }),
+        };
+        let signed =
+            TransactionSigned { hash: self.get_hash(), signature: Default::default(), transaction };
+        TransactionSignedEcRecovered::from_signed_transaction(signed, self.get_sender())
+    }
+}
+
 #[derive(Default)]
 pub struct MockTransactionFactory {
     pub(crate) ids: SenderIdentifiers,
@@ -440,6 +493,35 @@ impl TransactionOrdering for MockOrdering {
     }
 }
 
+/// A [TransactionValidator] that considers all transactions valid.
+#[derive(Debug)]
+pub struct MockTransactionValidator<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for MockTransactionValidator<T> {
+    fn default() -> Self {
+        Self { _marker: PhantomData }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: PoolTransaction> TransactionValidator for MockTransactionValidator<T> {
+    type Transaction = T;
+
+    async fn validate_transaction(
+        &self,
+        _origin: TransactionOrigin,
+        transaction: Self::Transaction,
+    ) -> TransactionValidationOutcome<Self::Transaction> {
+        TransactionValidationOutcome::Valid {
+            balance: U256::MAX,
+            state_nonce: transaction.nonce(),
+            transaction,
+        }
+    }
+}
+
 /// A configured distribution that can generate transactions
 pub struct MockTransactionDistribution {
     /// legacy to EIP-1559 ration
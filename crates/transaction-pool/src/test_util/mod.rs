@@ -1,7 +1,19 @@
-//! Internal helpers for testing.
+//! Helpers for testing, available to other crates with the `test-utils` feature.
 #![allow(missing_docs, unused, missing_debug_implementations, unreachable_pub)]
 
 mod mock;
+#[cfg(test)]
 mod pool;
 
 pub use mock::*;
+
+use crate::{Pool, PoolConfig};
+use std::sync::Arc;
+
+/// A [Pool] of [MockTransaction]s that accepts all transactions.
+pub type TestPool = Pool<MockTransactionValidator<MockTransaction>, MockOrdering>;
+
+/// Returns a new [TestPool] with the default config.
+pub fn testing_pool() -> TestPool {
+    Pool::new(Arc::new(Default::default()), Arc::new(Default::default()), PoolConfig::default())
+}
@@ -1,5 +1,9 @@
 use crate::{error::PoolResult, pool::state::SubPool, validate::ValidPoolTransaction};
-use reth_primitives::{Address, FromRecoveredTransaction, PeerId, TxHash, H256, U256};
+use reth_primitives::{
+    Address, FromRecoveredTransaction, PeerId, Transaction, TransactionKind,
+    TransactionSignedEcRecovered, TxHash, H256, U256,
+};
+use reth_rlp::Encodable;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt, sync::Arc};
 use tokio::sync::mpsc::Receiver;
@@ -20,6 +24,10 @@ pub trait TransactionPool: Send + Sync + Clone {
     /// Returns stats about the pool.
     fn status(&self) -> PoolSize;
 
+    /// Returns the minimum fee that is enforced under congestion and the fee percentiles it is
+    /// derived from.
+    fn fee_stats(&self) -> PoolFeeStats;
+
     /// Event listener for when a new block was mined.
     ///
     /// Implementers need to update the pool accordingly.
@@ -79,6 +87,18 @@ pub trait TransactionPool: Send + Sync + Clone {
         &self,
     ) -> Box<dyn BestTransactions<Item = Arc<ValidPoolTransaction<Self::Transaction>>>>;
 
+    /// Returns all transactions that are ready to be executed, grouped by sender and sorted by
+    /// nonce.
+    ///
+    /// Consumer: RPC
+    fn pending_transactions(&self) -> Vec<Arc<ValidPoolTransaction<Self::Transaction>>>;
+
+    /// Returns all transactions that can't be executed yet, because of a nonce gap, an
+    /// insufficient balance or a fee cap below the base fee.
+    ///
+    /// Consumer: RPC
+    fn queued_transactions(&self) -> Vec<Arc<ValidPoolTransaction<Self::Transaction>>>;
+
     /// Removes all transactions corresponding to the given hashes.
     ///
     /// Also removes all dependent transactions.
@@ -211,7 +231,7 @@ pub struct OnNewBlockEvent {
 }
 
 /// Contains a list of changed state
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct StateDiff {
     // TODO(mattsse) this could be an `Arc<revm::State>>`
 }
@@ -245,6 +265,9 @@ pub trait PoolTransaction: fmt::Debug + Send + Sync + FromRecoveredTransaction {
     /// Returns the nonce for this transaction.
     fn nonce(&self) -> u64;
 
+    /// Returns whether the transaction creates a contract or calls an account.
+    fn kind(&self) -> &TransactionKind;
+
     /// Calculates the cost that this transaction is allowed to consume:
     ///
     /// For EIP-1559 transactions that is `feeCap x gasLimit + transferred_value`
@@ -272,6 +295,55 @@ pub trait PoolTransaction: fmt::Debug + Send + Sync + FromRecoveredTransaction {
     fn size(&self) -> usize;
 }
 
+impl PoolTransaction for TransactionSignedEcRecovered {
+    fn hash(&self) -> &TxHash {
+        &self.hash
+    }
+
+    fn sender(&self) -> Address {
+        self.signer()
+    }
+
+    fn nonce(&self) -> u64 {
+        self.transaction.nonce()
+    }
+
+    fn kind(&self) -> &TransactionKind {
+        self.transaction.kind()
+    }
+
+    fn cost(&self) -> U256 {
+        U256::from(self.transaction.gas_limit()) * U256::from(self.transaction.max_fee_per_gas()) +
+            U256::from(*self.transaction.value())
+    }
+
+    fn effective_gas_price(&self) -> U256 {
+        U256::from(self.transaction.max_fee_per_gas())
+    }
+
+    fn gas_limit(&self) -> u64 {
+        self.transaction.gas_limit()
+    }
+
+    fn max_fee_per_gas(&self) -> Option<U256> {
+        match &self.transaction {
+            Transaction::Eip1559(tx) => Some(U256::from(tx.max_fee_per_gas)),
+            _ => None,
+        }
+    }
+
+    fn max_priority_fee_per_gas(&self) -> Option<U256> {
+        match &self.transaction {
+            Transaction::Eip1559(tx) => Some(U256::from(tx.max_priority_fee_per_gas)),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.length()
+    }
+}
+
 /// Represents the current status of the pool.
 #[derive(Debug, Clone)]
 pub struct PoolSize {
@@ -288,3 +360,17 @@ pub struct PoolSize {
     /// Reported size of transactions in the _queued_ sub-pool.
     pub queued_size: usize,
 }
+
+/// Represents the state of the minimum fee the pool enforces under congestion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolFeeStats {
+    /// The fee that new external transactions have to pay at least, zero if the pool is not
+    /// congested.
+    pub min_fee: U256,
+    /// The number of consecutive blocks after which the _pending_ sub-pool was congested.
+    pub congested_blocks: usize,
+    /// The lowest fee percentile of the transactions included in the recent blocks.
+    pub included_percentile: Option<U256>,
+    /// The fee percentile of the transactions in the _pending_ sub-pool after the last block.
+    pub pending_percentile: Option<U256>,
+}
@@ -5,8 +5,11 @@ use crate::{
     identifier::{SenderId, TransactionId},
     traits::{PoolTransaction, TransactionOrigin},
 };
-use reth_primitives::{rpc::Address, TxHash, U256};
-use std::{fmt, time::Instant};
+use reth_primitives::{
+    rpc::Address, SenderPermissions, TransactionKind, TransactionSignedEcRecovered, TxHash, U256,
+};
+use reth_provider::{AccountProvider, StateProviderFactory};
+use std::{fmt, sync::Arc, time::Instant};
 
 /// A Result type returned after checking a transaction's validity.
 #[derive(Debug)]
@@ -56,6 +59,130 @@ pub trait TransactionValidator: Send + Sync + 'static {
     ) -> TransactionValidationOutcome<Self::Transaction>;
 }
 
+/// A [TransactionValidator] for permissioned networks that rejects transactions of senders that
+/// are not permitted, before the transaction is passed to the inner validator.
+#[derive(Debug, Clone)]
+pub struct PermissionedValidator<V> {
+    inner: V,
+    permissions: Arc<SenderPermissions>,
+}
+
+// === impl PermissionedValidator ===
+
+impl<V> PermissionedValidator<V> {
+    /// Wraps the validator to enforce the given permissions.
+    pub fn new(inner: V, permissions: SenderPermissions) -> Self {
+        Self { inner, permissions: Arc::new(permissions) }
+    }
+
+    /// Returns true if the sender of the transaction is permitted to send it.
+    fn is_permitted<T: PoolTransaction>(&self, transaction: &T) -> bool {
+        let sender = transaction.sender();
+        match transaction.kind() {
+            TransactionKind::Create => self.permissions.is_deployer_permitted(&sender),
+            TransactionKind::Call(_) => self.permissions.is_sender_permitted(&sender),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<V: TransactionValidator> TransactionValidator for PermissionedValidator<V> {
+    type Transaction = V::Transaction;
+
+    async fn validate_transaction(
+        &self,
+        origin: TransactionOrigin,
+        transaction: Self::Transaction,
+    ) -> TransactionValidationOutcome<Self::Transaction> {
+        if !self.is_permitted(&transaction) {
+            let err = PoolError::SenderNotPermitted(transaction.sender(), *transaction.hash());
+            return TransactionValidationOutcome::Invalid(transaction, err)
+        }
+        self.inner.validate_transaction(origin, transaction).await
+    }
+}
+
+/// A [TransactionValidator] that checks transactions against the latest state of the chain.
+///
+/// Transactions must be signed for the chain, fit into a block if a block gas limit is set, and
+/// must not reuse a nonce of their sender. Transactions with a nonce gap or that the sender can't
+/// afford yet are valid, the pool queues them.
+#[derive(Debug)]
+pub struct EthTransactionValidator<Client> {
+    client: Arc<Client>,
+    chain_id: u64,
+    block_gas_limit: u64,
+}
+
+// === impl EthTransactionValidator ===
+
+impl<Client> EthTransactionValidator<Client> {
+    /// Creates a new validator for transactions of the chain.
+    pub fn new(client: Arc<Client>, chain_id: u64) -> Self {
+        Self { client, chain_id, block_gas_limit: u64::MAX }
+    }
+
+    /// Rejects transactions with a gas limit above the gas limit of a block.
+    pub fn with_block_gas_limit(mut self, block_gas_limit: u64) -> Self {
+        self.block_gas_limit = block_gas_limit;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<Client> TransactionValidator for EthTransactionValidator<Client>
+where
+    Client: StateProviderFactory + 'static,
+{
+    type Transaction = TransactionSignedEcRecovered;
+
+    async fn validate_transaction(
+        &self,
+        _origin: TransactionOrigin,
+        transaction: Self::Transaction,
+    ) -> TransactionValidationOutcome<Self::Transaction> {
+        let hash = *transaction.hash();
+        if let Some(chain_id) = transaction.chain_id().filter(|id| *id != self.chain_id) {
+            return TransactionValidationOutcome::Invalid(
+                transaction,
+                PoolError::InvalidChainId(hash, chain_id),
+            )
+        }
+        if transaction.gas_limit() > self.block_gas_limit {
+            return TransactionValidationOutcome::Invalid(
+                transaction,
+                PoolError::GasLimitExceedsBlock(hash, self.block_gas_limit),
+            )
+        }
+
+        let account = match self
+            .client
+            .latest()
+            .and_then(|state| state.basic_account(transaction.signer()))
+        {
+            Ok(account) => account.unwrap_or_default(),
+            Err(err) => {
+                return TransactionValidationOutcome::Invalid(
+                    transaction,
+                    PoolError::StateUnavailable(hash, err),
+                )
+            }
+        };
+        if transaction.nonce() < account.nonce {
+            return TransactionValidationOutcome::Invalid(
+                transaction,
+                PoolError::NonceTooLow(hash, account.nonce),
+            )
+        }
+
+        TransactionValidationOutcome::Valid {
+            balance: account.balance,
+            state_nonce: account.nonce,
+            transaction,
+        }
+    }
+}
+
 /// A valida transaction in the pool.
 pub struct ValidPoolTransaction<T: PoolTransaction> {
     /// The transaction